@@ -9,7 +9,6 @@ fn main() {
         build.file("cpp/raycast.cpp");
         build.file("cpp/collision.cpp");
         build.file("cpp/base64.cpp");
-        build.file("cpp/python_core.cpp");
         build.flag_if_supported("-O3");
         build.flag_if_supported("-ffast-math");
         build.flag_if_supported("-fno-rtti");
@@ -1,27 +1,49 @@
 import { state } from './state.js';
-import { print, scrollToBottom, getElement } from './dom.js';
-import { setupTerminal } from './terminal.js';
+import { print, clearScreen, scrollToBottom, getElement } from './dom.js';
+import { setupTerminal, startInitramfs } from './terminal.js';
+
+// Set by a key press during boot: print whatever is left at once
+let skipBoot = false;
+
+function handleBootSkip() {
+  skipBoot = true;
+}
 
 export function beginBoot() {
   // Clear screen before booting
-  getElement('output').innerHTML = '';
+  clearScreen();
 
   // Use the new modular boot system
   const bootMessages = state.system.boot_simulate_sequence();
+  skipBoot = state.system.fast_boot();
+  document.addEventListener('keydown', handleBootSkip);
   drainBootLines(bootMessages, 0);
 }
 
 function drainBootLines(messages, index) {
+  if (skipBoot) {
+    messages.slice(index).forEach(line => {
+      if (line !== '') print(line, 'boot');
+    });
+    scrollToBottom();
+    index = messages.length;
+  }
+
   if (index >= messages.length) {
+    document.removeEventListener('keydown', handleBootSkip);
     // Boot complete
     setTimeout(() => {
       // Always clear boot logs before entering login shell.
-      getElement('output').innerHTML = '';
+      clearScreen();
       if (state.system.post_boot_clear_needed()) {
         state.system.acknowledge_post_boot();
       }
+      if (state.system.needs_recovery()) {
+        startInitramfs();
+        return;
+      }
       setupTerminal();
-    }, 2000);
+    }, skipBoot ? 300 : 2000);
     return;
   }
 
@@ -1,8 +1,10 @@
-const COLOR_MAP = {
-  reset: null, black: '#000', red: '#f00', green: '#0f0',
-  yellow: '#ff0', blue: '#00f', magenta: '#f0f', cyan: '#0ff',
-  white: '#fff', gray: '#888', grey: '#888'
-};
+import { state } from './state.js';
+
+let ansi_to_html;
+
+export function initDom(wasm) {
+  ansi_to_html = wasm.ansi_to_html;
+}
 
 export function escapeHtml(text) {
   return text
@@ -11,45 +13,84 @@ export function escapeHtml(text) {
     .replace(/>/g, '&gt;');
 }
 
+// SGR colours, cursor movement and the older `\x1b[COLOR:name]` markers,
+// played out by the crate
 export function renderColorTokens(raw) {
-  const parts = [];
-  let open = false;
-  let lastIndex = 0;
-  const regex = /\x1b\[COLOR:([#0-9A-Fa-f]+|[a-z]+)\]/g;
-  let match;
-
-  while ((match = regex.exec(raw)) !== null) {
-    parts.push(escapeHtml(raw.slice(lastIndex, match.index)));
-    if (open) {
-      parts.push('</span>');
-      open = false;
-    }
-
-    const colorValue = match[1];
-    const cssColor = colorValue.startsWith('#') ? colorValue : COLOR_MAP[colorValue];
-
-    if (cssColor) {
-      parts.push(`<span style="color:${cssColor}">`);
-      open = true;
-    }
-    lastIndex = regex.lastIndex;
+  if (!ansi_to_html) return escapeHtml(raw);
+  try {
+    return ansi_to_html(raw);
+  } catch (e) {
+    return escapeHtml(raw);
   }
+}
 
-  parts.push(escapeHtml(raw.slice(lastIndex)));
-  if (open) parts.push('</span>');
-  return parts.join('');
+function renderLine(text, className) {
+  if (className.includes('boot')) return renderBootLine(text);
+  return text.includes('\x1b') ? renderColorTokens(text) : escapeHtml(text);
 }
 
-export function print(text, className = '') {
+// One element per print; `data-first`/`data-last` are the ids the crate's
+// terminal model gave its lines
+function appendLine(text, className, first) {
   const output = document.getElementById('output');
   const line = document.createElement('div');
   line.className = `line ${className}`;
-  if (className.includes('boot')) {
-    line.innerHTML = renderBootLine(text);
-  } else {
-    line.innerHTML = text.includes('\x1b[COLOR:') ? renderColorTokens(text) : escapeHtml(text);
+  line.innerHTML = renderLine(text, className);
+  if (first !== undefined) {
+    line.dataset.first = first;
+    line.dataset.last = first + text.split('\n').length - 1;
   }
   output.appendChild(line);
+  return line;
+}
+
+function hasTerminal() {
+  return state.system && typeof state.system.term_write === 'function';
+}
+
+// Print and keep the text in the scrollback
+export function print(text, className = '') {
+  if (!hasTerminal()) {
+    appendLine(text, className);
+    return;
+  }
+  appendLine(text, className, state.system.term_write(text, className));
+  // Lines past the scrollback limit are gone from the model; drop them here too
+  const output = document.getElementById('output');
+  const oldest = state.system.term_first_line();
+  while (output.firstChild && output.firstChild.dataset && Number(output.firstChild.dataset.last) < oldest) {
+    output.removeChild(output.firstChild);
+  }
+}
+
+// Show a full-screen frame without adding it to the scrollback
+export function draw(text, className = '') {
+  document.getElementById('output').innerHTML = '';
+  appendLine(text, className);
+}
+
+// Blank the screen and the scrollback behind it
+export function clearScreen() {
+  document.getElementById('output').innerHTML = '';
+  if (hasTerminal()) state.system.term_clear();
+}
+
+// Draw everything the terminal model holds, after `clear`, `reset` or
+// leaving the pager's alternate screen
+export function redrawTerminal() {
+  document.getElementById('output').innerHTML = '';
+  if (!hasTerminal()) return;
+  for (const line of state.system.term_lines() || []) {
+    appendLine(line.text, line.class, line.id);
+  }
+}
+
+// The element holding line `id`, if it is still drawn
+export function findLine(id) {
+  for (const el of document.querySelectorAll('#output > .line[data-first]')) {
+    if (Number(el.dataset.first) <= id && id <= Number(el.dataset.last)) return el;
+  }
+  return null;
 }
 
 export function scrollToBottom() {
@@ -0,0 +1,48 @@
+// Files dragged from the desktop onto the terminal, copied into ~/Downloads
+import { state } from './state.js';
+import { print, scrollToBottom } from './dom.js';
+import { saveUserFiles } from './storage.js';
+
+function carriesFiles(e) {
+  return e.dataTransfer && Array.from(e.dataTransfer.types).includes('Files');
+}
+
+export function installDropHandler(element) {
+  if (!element || element.dataset.dropHandler) return;
+  element.dataset.dropHandler = 'on';
+
+  element.addEventListener('dragover', (e) => {
+    if (!carriesFiles(e)) return;
+    e.preventDefault();
+    e.dataTransfer.dropEffect = 'copy';
+    element.classList.add('dropping');
+  });
+  element.addEventListener('dragleave', () => {
+    element.classList.remove('dropping');
+  });
+  element.addEventListener('drop', async (e) => {
+    if (!carriesFiles(e)) return;
+    e.preventDefault();
+    element.classList.remove('dropping');
+    const system = state.system;
+    if (!system) return;
+
+    let imported = false;
+    for (const file of Array.from(e.dataTransfer.files)) {
+      try {
+        // Refuse big files before reading them into memory
+        if (file.size > system.import_limit()) {
+          print(`${file.name}: ${file.size} bytes is over the import limit`, 'error');
+          continue;
+        }
+        const bytes = new Uint8Array(await file.arrayBuffer());
+        print(system.import_real_file(file.name, bytes), 'info');
+        imported = true;
+      } catch (err) {
+        print(typeof err === 'string' ? err : `${file.name}: ${err.message}`, 'error');
+      }
+    }
+    if (imported) saveUserFiles();
+    scrollToBottom();
+  });
+}
@@ -1,13 +1,12 @@
 import { state } from './state.js';
-import { print, getElement } from './dom.js';
+import { print, clearScreen, getElement, scrollToBottom } from './dom.js';
 import { beginBoot } from './boot.js';
+import { startInitramfs } from './terminal.js';
 import { setMemtest } from './state.js';
-import { Memtest } from '../pkg/terminal_os.js';
 
 export function showGrub() {
   // Clear terminal before showing GRUB
-  const output = document.getElementById('output');
-  if (output) output.innerHTML = '';
+  clearScreen();
 
   const grubDiv = getElement('grub');
   grubDiv.style.display = 'flex';
@@ -19,15 +18,24 @@ export function showGrub() {
         "error: file '/boot/grub/grub.cfg' not found.",
         'Entering rescue mode...',
         '',
-        'grub rescue> ',
-        '',
-        "Available commands: ls, set, unset, insmod, normal, boot"
+        'Booting fallback kernel /boot/vmlinuz-6.1.0-kpawnd ...'
       ].join('\n');
+      // Fall through to the initramfs shell, where reinstall-grub can fix it
+      setTimeout(() => {
+        grubDiv.style.display = 'none';
+        getElement('terminal').style.display = 'flex';
+        startInitramfs();
+      }, 2500);
       return;
     }
   } catch (e) {
     // If backend not ready, proceed with menu
   }
+  try {
+    state.grubMenu.load_config(state.system.grub_config());
+  } catch (e) {
+    // Keep the built-in entries on older wasm bundles
+  }
   updateGrubDisplay();
 
   const handleGrubKey = (e) => {
@@ -115,13 +123,10 @@ export function showGrub() {
         break;
       case 'Enter':
         e.preventDefault();
-        const selected = state.grubMenu.get_selected();
-        const inAdvanced = state.grubMenu.is_advanced_mode();
-        
+        const action = state.grubMenu.selected_action();
+
         // Check if this selection will actually boot (not just navigate menus)
-        const willBoot = (inAdvanced && selected !== 0) || (!inAdvanced && selected !== 1);
-        
-        if (willBoot) {
+        if (action === 'boot' || action === 'memtest') {
           clearInterval(state.grubInterval);
           document.removeEventListener('keydown', handleGrubKey);
         }
@@ -165,8 +170,6 @@ function updateGrubDisplay() {
 }
 
 function bootSelected() {
-  const selected = state.grubMenu.get_selected();
-
   const applyBootProfile = () => {
     try {
       const cmdline = state.grubMenu.get_effective_cmdline();
@@ -178,79 +181,69 @@ function bootSelected() {
     }
   };
 
-  if (state.grubMenu.is_advanced_mode()) {
-    // In advanced mode
-    if (selected === 0) {
+  switch (state.grubMenu.selected_action()) {
+    case 'back':
       // Back to main menu
       state.grubMenu.exit_advanced_mode();
       updateGrubDisplay();
       return; // Don't boot, just return to main menu
-    } else {
-      // Boot selected option
-      getElement('grub').style.display = 'none';
-      getElement('terminal').style.display = 'flex';
-      if (selected === 3) {
-        startMemtest();
-      } else {
-        applyBootProfile();
-        beginBoot();
-      }
-      return;
-    }
-  } else {
-    // In main menu
-    if (selected === 0) {
-      // Normal boot
-      getElement('grub').style.display = 'none';
-      getElement('terminal').style.display = 'flex';
-      applyBootProfile();
-      beginBoot();
-      return;
-    } else if (selected === 1) {
+    case 'advanced':
       // Advanced options - enter submenu
       state.grubMenu.enter_advanced_mode();
       updateGrubDisplay();
       return; // Don't boot, stay in GRUB
-    } else if (selected === 2) {
-      // Memory test
+    case 'memtest':
       getElement('grub').style.display = 'none';
       getElement('terminal').style.display = 'flex';
       startMemtest();
       return;
-    }
+    default:
+      // One of the grub.cfg entries or recovery mode
+      getElement('grub').style.display = 'none';
+      getElement('terminal').style.display = 'flex';
+      applyBootProfile();
+      beginBoot();
   }
 }
 
-function startMemtest() {
-  // Get system memory size (assume 512MB for simulation)
-  const memSize = 512;
-  const memtest = new Memtest(memSize);
+// Runs over the system's memory map; Esc stops it at any point and hands
+// back to `onExit` (the GRUB menu unless the shell started it)
+export function startMemtest(onExit = returnToGrub) {
+  const memtest = state.system.memtest();
   setMemtest(memtest);
 
   // Clear terminal and show header
-  getElement('output').innerHTML = '';
-  print(memtest.get_header(), 'info');
+  clearScreen();
+  memtest.get_header().split('\n').forEach(line => print(line, 'boot'));
+
+  let memtestInterval = null;
+  const handleMemtestKey = (e) => {
+    if (e.key !== 'Escape') return;
+    e.preventDefault();
+    clearInterval(memtestInterval);
+    document.removeEventListener('keydown', handleMemtestKey);
+    setMemtest(null);
+    onExit();
+  };
+  document.addEventListener('keydown', handleMemtestKey);
 
-  // Start memtest loop
-  const memtestInterval = setInterval(() => {
+  memtestInterval = setInterval(() => {
     const continueTesting = memtest.tick();
-    const line = memtest.get_current_line();
-    print(line, 'info');
+    const errors = memtest.take_error_lines();
+    if (errors) {
+      errors.split('\n').forEach(line => print(line, 'error'));
+    }
+    print(memtest.get_current_line(), 'boot');
+    scrollToBottom();
 
     if (!continueTesting) {
       clearInterval(memtestInterval);
-      print('\nPress ESC to return to GRUB menu', 'info');
-
-      // Listen for ESC to return to GRUB
-      const handleMemtestKey = (e) => {
-        if (e.key === 'Escape') {
-          document.removeEventListener('keydown', handleMemtestKey);
-          getElement('terminal').style.display = 'none';
-          getElement('grub').style.display = 'flex';
-          showGrub();
-        }
-      };
-      document.addEventListener('keydown', handleMemtestKey);
     }
   }, 500); // Update every 500ms for visible progress
 }
+
+function returnToGrub() {
+  getElement('terminal').style.display = 'none';
+  getElement('grub').style.display = 'flex';
+  showGrub();
+}
@@ -1,9 +1,10 @@
 import { state } from './state.js';
-import { escapeHtml, scrollToBottom, getElement, renderColorTokens } from './dom.js';
+import { escapeHtml, scrollToBottom, getElement, renderColorTokens, clearScreen } from './dom.js';
 import { saveUserFiles } from './storage.js';
 
-// NanoEditor class from WASM - set by main.js
+// NanoEditor class and the terminal bell from WASM - set by main.js
 let NanoEditor;
+let bell;
 const VISIBLE_LINES = 20;
 let nanoPrompt = null;
 let nanoStatus = '';
@@ -11,6 +12,7 @@ let nanoStatusTimer = null;
 
 export function initNano(wasm) {
   NanoEditor = wasm.NanoEditor;
+  bell = wasm.bell;
 }
 
 export function launchNanoEditor(filename, content) {
@@ -224,6 +226,7 @@ function submitNanoPrompt() {
       setNanoStatus(`Found: ${value}`, 1400);
     } else {
       setNanoStatus(`Not found: ${value}`, 2000);
+      bell();
     }
     renderNano();
     return;
@@ -289,6 +292,7 @@ function submitNanoPrompt() {
     }
     const cmd = `cat ${quotePath(value)}`;
     const raw = state.system.exec(cmd);
+    state.system.poll_events();
     const out = cleanCommandOutput(raw);
     if (!out || /^cat: /.test(out)) {
       setNanoStatus(out || 'Unable to read file', 2200);
@@ -308,6 +312,8 @@ function submitNanoPrompt() {
       return;
     }
     const raw = state.system.exec(value);
+    // Nothing launches from inside the editor
+    state.system.poll_events();
     const out = cleanCommandOutput(raw).trim();
     if (out) {
       state.nanoEditor.insert_string(out);
@@ -343,7 +349,7 @@ function saveNanoFile(onSuccess) {
   } else {
     state.nanoEditor.mark_saved();
 
-    // Persist to localStorage
+    // Persist to IndexedDB
     saveUserFiles();
 
     setNanoStatus(`[ Wrote ${lineCount} lines to ${filename} ]`, 1800);
@@ -386,7 +392,7 @@ function exitNano() {
     nanoStatusTimer = null;
   }
 
-  getElement('output').innerHTML = '';
+  clearScreen();
   getElement('prompt').style.display = '';
   getElement('input').style.display = '';
   const promptText = state.system.prompt();
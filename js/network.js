@@ -1,7 +1,9 @@
 import { print, scrollToBottom } from './dom.js';
+import { state } from './state.js';
+import { saveUserFiles } from './storage.js';
 
 // These will be set by main.js after WASM init
-let fetch_http, curl_request, ping_request, dns_lookup, get_public_ip;
+let fetch_http, net_perform, dns_lookup, get_public_ip;
 
 function sanitizeTarget(raw) {
   // Strip common shell quoting/trailing punctuation that can leak into host args.
@@ -21,83 +23,138 @@ function normalizeUrl(raw, { preferHttps = true } = {}) {
   return `${scheme}${target}`;
 }
 
+// Put a bridged request on the packet bus so tcpdump can show it
+function recordTcp(url, sent, received) {
+  try {
+    const parsed = new URL(url);
+    const port = parsed.port ? Number(parsed.port) : (parsed.protocol === 'https:' ? 443 : 80);
+    const request = `GET ${parsed.pathname}${parsed.search} HTTP/1.1\r\nHost: ${parsed.host}\r\n\r\n`;
+    state.system.network_record_tcp(parsed.hostname, port, sent ?? request.length, received);
+  } catch {
+    // Unparseable URLs never reached the network
+  }
+}
+
 export function initNetwork(wasm) {
   fetch_http = wasm.fetch_http;
-  curl_request = wasm.curl_request;
-  ping_request = wasm.ping_request;
+  net_perform = wasm.net_perform;
   dns_lookup = wasm.dns_lookup;
   get_public_ip = wasm.get_public_ip;
 }
 
-export async function fetchUrl(url) {
-  const normalized = normalizeUrl(url, { preferHttps: true });
-  if (!normalized) {
-    print('Error: missing URL', 'error');
-    scrollToBottom();
-    return;
-  }
-
-  print(`Fetching ${normalized}...`, 'info');
+// Settles the request in flight early when Ctrl+C stops the line
+let cancelRequest = null;
+
+// Run a typed line. Requests its network commands left are answered one
+// at a time, and the system is only called between them, so other calls
+// can reach it while one is in flight. Resolves with everything printed.
+// This is the only caller of exec_start/net_request/net_reply/exec_finish;
+// the order they go in is documented in src/system/netexec.rs
+export async function execAsync(system, line) {
+  system.exec_start(line);
+  const cancelled = new Promise(resolve => { cancelRequest = resolve; });
   try {
-    print(await fetch_http(normalized), 'output');
-  } catch (e) {
-    print(`Error: ${e.message || e}`, 'error');
+    for (let request; (request = system.net_request());) {
+      const pending = net_perform(request);
+      const reply = await Promise.race([pending, cancelled]);
+      if (!reply) {
+        // The answer is no longer wanted once it arrives
+        pending.then(late => late.free(), () => {});
+        break;
+      }
+      system.net_reply(reply);
+    }
+  } finally {
+    cancelRequest = null;
   }
-  scrollToBottom();
+  return system.exec_finish();
 }
 
-export async function doCurl(url, method, showHeaders) {
-  const normalized = normalizeUrl(url, { preferHttps: false });
-  if (!normalized) {
-    print('curl: no URL specified', 'error');
-    scrollToBottom();
-    return;
-  }
-
-  print(`* Connecting to ${normalized}...`, 'info');
-  try {
-    (await curl_request(normalized, method, showHeaders)).split('\n').forEach(line => print(line, 'output'));
-  } catch (e) {
-    print(`curl: (7) Failed to connect: ${e.message || e}`, 'error');
-  }
-  scrollToBottom();
+// Ctrl+C during `execAsync`: stop waiting; `System.interrupt` has dropped
+// the rest of the line's requests
+export function cancelCommand() {
+  if (cancelRequest) cancelRequest(null);
 }
 
-export async function doPing(host) {
-  const target = sanitizeTarget(host);
-  const url = normalizeUrl(target, { preferHttps: true });
+// `traceroute`: each hop line comes from the wasm side, scaled from one
+// timed HTTP probe to the target; CORS failures still complete a round
+// trip, so only timeouts count as lost probes.
+export async function doTraceroute(host, header) {
+  const url = normalizeUrl(host, { preferHttps: true });
+  print(header, 'output');
+  scrollToBottom();
 
-  if (!target || !url) {
-    print('ping: missing host operand', 'error');
+  for (;;) {
+    const start = performance.now();
+    const probe = fetch_http(`${url}${url.includes('?') ? '&' : '?'}traceroute=${Date.now()}`)
+      .then(() => performance.now() - start, () => performance.now() - start);
+    const timeout = new Promise(r => setTimeout(() => r(-1), 5000));
+    const rtt = await Promise.race([probe, timeout]);
+    const line = state.system.traceroute_step(rtt);
+    if (!line) break;
+    print(line, 'output');
     scrollToBottom();
-    return;
   }
+}
 
-  print(`PING ${target}`, 'info');
-
-  const results = [];
-  for (let i = 0; i < 4; i++) {
-    try {
-      const result = await ping_request(url);
-      print(`seq=${i + 1}: ${result}`, 'output');
-      const match = result.match(/time=([0-9.]+)ms/);
-      if (match) results.push(parseFloat(match[1]));
-    } catch {
-      print(`seq=${i + 1}: timeout`, 'error');
-    }
-    await new Promise(r => setTimeout(r, 200));
+// git clone only pulls what fits comfortably in the saved filesystem
+const CLONE_MAX_FILES = 200;
+const CLONE_MAX_FILE_SIZE = 256 * 1024;
+const BINARY_EXTENSIONS = /\.(png|jpe?g|gif|bmp|ico|webp|pdf|zip|gz|tgz|xz|7z|jar|wasm|woff2?|ttf|otf|eot|mp3|mp4|ogg|wav|exe|dll|so|a|o|class|pyc)$/i;
+
+async function fetchJson(url) {
+  const text = await fetch_http(url);
+  recordTcp(url, null, text.length);
+  const data = JSON.parse(text);
+  if (data && data.message && !data.sha && !data.tree && !data.default_branch) {
+    throw new Error(data.message);
   }
+  return data;
+}
 
-  print(`\n--- ${target} ping statistics ---`, 'info');
-  print(`4 packets transmitted, ${results.length} received, ${((4 - results.length) / 4 * 100).toFixed(0)}% packet loss`, 'output');
+// `repo` is `owner/name`, `dir` the absolute target directory
+export async function doGitClone(repo, dir) {
+  const url = `https://github.com/${repo}.git`;
+  const api = `https://api.github.com/repos/${repo}`;
 
-  if (results.length) {
-    const min = Math.min(...results).toFixed(1);
-    const max = Math.max(...results).toFixed(1);
-    const avg = (results.reduce((a, b) => a + b, 0) / results.length).toFixed(1);
-    print(`rtt min/avg/max = ${min}/${avg}/${max} ms`, 'output');
-  } else {
-    print('rtt min/avg/max = 0.0/0.0/0.0 ms', 'output');
+  print(`Cloning into '${dir.split('/').pop()}'...`, 'info');
+  scrollToBottom();
+  try {
+    const meta = await fetchJson(api);
+    const branch = meta.default_branch;
+    const head = await fetchJson(`${api}/commits/${encodeURIComponent(branch)}`);
+    const tree = await fetchJson(`${api}/git/trees/${head.sha}?recursive=1`);
+
+    const blobs = tree.tree.filter(entry => entry.type === 'blob');
+    const wanted = blobs
+      .filter(entry => entry.size <= CLONE_MAX_FILE_SIZE && !BINARY_EXTENSIONS.test(entry.path))
+      .slice(0, CLONE_MAX_FILES);
+    print(`remote: Enumerating objects: ${blobs.length}, done.`, 'output');
+
+    const files = [];
+    for (const entry of wanted) {
+      const path = entry.path.split('/').map(encodeURIComponent).join('/');
+      const raw = `https://raw.githubusercontent.com/${repo}/${head.sha}/${path}`;
+      const text = await fetch_http(raw);
+      recordTcp(raw, null, text.length);
+      files.push([entry.path, text]);
+    }
+
+    const author = head.commit.author || {};
+    const commit = {
+      author: author.name || 'unknown',
+      email: author.email || 'unknown',
+      time: Math.floor(new Date(author.date || Date.now()).getTime() / 1000),
+      message: head.commit.message || ''
+    };
+    const result = state.system.git_clone_finish(dir, url, branch, JSON.stringify(files), JSON.stringify(commit));
+    result.split('\n').forEach(line => print(line, line.startsWith('fatal') ? 'error' : 'output'));
+    if (wanted.length < blobs.length) {
+      print(`warning: skipped ${blobs.length - wanted.length} binary, large or excess files`, 'info');
+    }
+    saveUserFiles();
+  } catch (e) {
+    print(`fatal: unable to access '${url}': ${e.message || e}`, 'error');
   }
   scrollToBottom();
 }
@@ -115,7 +172,9 @@ export async function doDns(host) {
   print('', 'output');
   try {
     print(`;; ANSWER SECTION:`, 'info');
-    (await dns_lookup(target)).split('\n').filter(Boolean).forEach(line => print(line, 'output'));
+    const answer = await dns_lookup(target);
+    recordTcp(`https://cloudflare-dns.com/dns-query?name=${target}&type=A`, null, answer.length);
+    answer.split('\n').filter(Boolean).forEach(line => print(line, 'output'));
   } catch (e) {
     print(`DNS lookup failed: ${e.message || e}`, 'error');
   }
@@ -1,37 +1,118 @@
 // IndexedDB persistence for VFS
+//
+// The filesystem image is a `manifest` record plus `chunk:0`, `chunk:1`, ...
+// Saves from before images kept one JSON tree under `root`. `snapshot`
+// copies of the tree live in a JSON list under `snapshots`. `state` is
+// 'dirty' while a save is under way and 'clean' once the image is written,
+// so a tab closed mid-save leaves the filesystem marked dirty.
 const DB_NAME = 'kpawnd-vfs';
 const STORE_NAME = 'vfs';
 
-export async function idb_save_vfs(data) {
+function openDb() {
   return new Promise((resolve, reject) => {
     const req = indexedDB.open(DB_NAME, 1);
     req.onupgradeneeded = () => {
       req.result.createObjectStore(STORE_NAME);
     };
-    req.onsuccess = () => {
-      const db = req.result;
-      const tx = db.transaction(STORE_NAME, 'readwrite');
-      tx.objectStore(STORE_NAME).put(data, 'root');
-      tx.oncomplete = () => { db.close(); resolve(); };
-      tx.onerror = (e) => { db.close(); reject(e); };
-    };
+    req.onsuccess = () => resolve(req.result);
     req.onerror = reject;
   });
 }
 
-export async function idb_load_vfs() {
+export async function idb_save_image(manifest, chunks) {
+  const db = await openDb();
   return new Promise((resolve, reject) => {
-    const req = indexedDB.open(DB_NAME, 1);
-    req.onupgradeneeded = () => {
-      req.result.createObjectStore(STORE_NAME);
+    const tx = db.transaction(STORE_NAME, 'readwrite');
+    const store = tx.objectStore(STORE_NAME);
+    chunks.forEach((chunk, i) => store.put(chunk, `chunk:${i}`));
+    store.put(manifest, 'manifest');
+    store.put('clean', 'state');
+    // Drop chunks left over from a bigger image, and the pre-image tree
+    const keysReq = store.getAllKeys();
+    keysReq.onsuccess = () => {
+      for (const key of keysReq.result) {
+        const stale = key === 'root'
+          || (typeof key === 'string' && key.startsWith('chunk:') && Number(key.slice(6)) >= chunks.length);
+        if (stale) {
+          store.delete(key);
+        }
+      }
     };
-    req.onsuccess = () => {
-      const db = req.result;
-      const tx = db.transaction(STORE_NAME, 'readonly');
-      const getReq = tx.objectStore(STORE_NAME).get('root');
-      getReq.onsuccess = () => { db.close(); resolve(getReq.result || null); };
-      getReq.onerror = (e) => { db.close(); reject(e); };
+    tx.oncomplete = () => { db.close(); resolve(); };
+    tx.onerror = (e) => { db.close(); reject(e); };
+  });
+}
+
+export async function idb_mark_dirty() {
+  const db = await openDb();
+  return new Promise((resolve, reject) => {
+    const tx = db.transaction(STORE_NAME, 'readwrite');
+    tx.objectStore(STORE_NAME).put('dirty', 'state');
+    tx.oncomplete = () => { db.close(); resolve(); };
+    tx.onerror = (e) => { db.close(); reject(e); };
+  });
+}
+
+export async function idb_load_state() {
+  const db = await openDb();
+  return new Promise((resolve, reject) => {
+    const tx = db.transaction(STORE_NAME, 'readonly');
+    const getReq = tx.objectStore(STORE_NAME).get('state');
+    getReq.onsuccess = () => { db.close(); resolve(getReq.result || null); };
+    getReq.onerror = (e) => { db.close(); reject(e); };
+  });
+}
+
+export async function idb_load_image() {
+  const db = await openDb();
+  return new Promise((resolve, reject) => {
+    const tx = db.transaction(STORE_NAME, 'readonly');
+    const store = tx.objectStore(STORE_NAME);
+    const manifestReq = store.get('manifest');
+    const parts = [];
+    manifestReq.onsuccess = () => {
+      const manifest = manifestReq.result;
+      if (!manifest) {
+        return;
+      }
+      parts.push(manifest);
+      const count = JSON.parse(manifest).chunks || 0;
+      for (let i = 0; i < count; i++) {
+        const chunkReq = store.get(`chunk:${i}`);
+        chunkReq.onsuccess = () => { parts[i + 1] = chunkReq.result; };
+      }
     };
-    req.onerror = reject;
+    tx.oncomplete = () => { db.close(); resolve(parts.length ? parts : null); };
+    tx.onerror = (e) => { db.close(); reject(e); };
+  });
+}
+
+export async function idb_load_vfs() {
+  const db = await openDb();
+  return new Promise((resolve, reject) => {
+    const tx = db.transaction(STORE_NAME, 'readonly');
+    const getReq = tx.objectStore(STORE_NAME).get('root');
+    getReq.onsuccess = () => { db.close(); resolve(getReq.result || null); };
+    getReq.onerror = (e) => { db.close(); reject(e); };
+  });
+}
+
+export async function idb_save_snapshots(snapshots) {
+  const db = await openDb();
+  return new Promise((resolve, reject) => {
+    const tx = db.transaction(STORE_NAME, 'readwrite');
+    tx.objectStore(STORE_NAME).put(snapshots, 'snapshots');
+    tx.oncomplete = () => { db.close(); resolve(); };
+    tx.onerror = (e) => { db.close(); reject(e); };
+  });
+}
+
+export async function idb_load_snapshots() {
+  const db = await openDb();
+  return new Promise((resolve, reject) => {
+    const tx = db.transaction(STORE_NAME, 'readonly');
+    const getReq = tx.objectStore(STORE_NAME).get('snapshots');
+    getReq.onsuccess = () => { db.close(); resolve(getReq.result || null); };
+    getReq.onerror = (e) => { db.close(); reject(e); };
   });
 }
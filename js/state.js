@@ -8,6 +8,10 @@ export const state = {
   memtest: null,
   nanoEditor: null,
   pythonRepl: false,
+  luaRepl: false,
+  sqliteRepl: false,
+  wscat: false,
+  initramfs: false,
   terminalSetup: false,
   user: { username: null, password: null },
   loginStage: null,
@@ -57,6 +61,38 @@ export function getPythonRepl() {
   return state.pythonRepl;
 }
 
+export function setLuaRepl(val) {
+  state.luaRepl = val;
+}
+
+export function getLuaRepl() {
+  return state.luaRepl;
+}
+
+export function setSqliteRepl(val) {
+  state.sqliteRepl = val;
+}
+
+export function getSqliteRepl() {
+  return state.sqliteRepl;
+}
+
+export function setWscat(val) {
+  state.wscat = val;
+}
+
+export function getWscat() {
+  return state.wscat;
+}
+
+export function setInitramfs(val) {
+  state.initramfs = val;
+}
+
+export function getInitramfs() {
+  return state.initramfs;
+}
+
 export function setUser(user) {
   state.user = user;
 }
@@ -1,26 +1,26 @@
-// File Persistence (localStorage)
+// File Persistence (the filesystem image in IndexedDB, user info in localStorage)
 import { state, STORAGE_KEY, USER_INFO_KEY, setUser, setLoginStage } from './state.js';
 
-export function loadUserFiles() {
+// Older versions kept user files as JSON in localStorage. Bring them into
+// the filesystem once, save a full image, and drop the old copy
+export async function migrateUserFiles() {
   try {
     const saved = localStorage.getItem(STORAGE_KEY);
     if (saved) {
       state.system.import_user_files(saved);
-      console.log('Loaded user files from localStorage');
+      await state.system.save();
+      localStorage.removeItem(STORAGE_KEY);
+      console.log('Migrated user files from localStorage');
     }
   } catch (e) {
-    console.warn('Failed to load user files:', e);
+    console.warn('Failed to migrate user files:', e);
   }
 }
 
 export function saveUserFiles() {
-  try {
-    const files = state.system.export_user_files();
-    localStorage.setItem(STORAGE_KEY, files);
-    console.log('Saved user files to localStorage');
-  } catch (e) {
-    console.warn('Failed to save user files:', e);
-  }
+  state.system.save().catch((e) => {
+    console.warn('Failed to save the filesystem:', e);
+  });
 }
 
 export function loadUserInfo() {
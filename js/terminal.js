@@ -1,47 +1,125 @@
-import { getState, setPythonRepl, getNanoEditor, getPythonRepl, getLoginStage, setLoginStage, getUser } from './state.js';
-import { print, scrollToBottom, escapeHtml, renderColorTokens } from './dom.js';
+import { getState, setPythonRepl, getNanoEditor, getPythonRepl, setLuaRepl, getLuaRepl, setSqliteRepl, getSqliteRepl, setWscat, getWscat, setInitramfs, getInitramfs, getLoginStage, setLoginStage, getUser } from './state.js';
+import { print, draw, clearScreen, redrawTerminal, findLine, scrollToBottom, escapeHtml, renderColorTokens } from './dom.js';
 import { saveUserInfo } from './storage.js';
 import { launchNanoEditor } from './nano.js';
-import { showKernelPanic } from './panic.js';
+import { startMemtest } from './grub.js';
 import { saveUserFiles } from './storage.js';
-import { doCurl, doPing, doDns, doMyIp, fetchUrl } from './network.js';
+import { doDns, doMyIp, doGitClone, doTraceroute, execAsync, cancelCommand } from './network.js';
+import { isTmuxActive, refreshTmux, handleTmuxKey } from './tmux.js';
 
 let commandHistory = [];
 let historyIndex = -1;
+// Ctrl+R reverse search: { query, skip, match, original } while active
+let historySearch = null;
+// Ctrl+Shift+F scrollback search: { query, index } while active
+let scrollbackSearch = null;
 let passwordBuffer = '';
 let lastTabInput = '';
 let lastTabAt = 0;
+// Set while the idle lock screen waits for the password
+let screenLocked = false;
+// Set while a typed line waits on its network requests
+let commandRunning = false;
 
 let start_doom;
 let start_doom_with_difficulty;
 let doom_enable_procedural;
 let doom_restore_original_map;
 let start_screensaver;
+let start_screensaver_kind;
+let start_viewer;
+let start_snake;
+let start_pong;
+let blank_now;
+let bell;
+let doom_start_recording;
+let doom_stop_recording;
+let doom_play_demo;
+// VFS path the demo being recorded is saved to when doom exits
+let pendingDemoPath = null;
 
 function setPromptText(text) {
   const promptEl = document.getElementById('prompt');
   if (!promptEl) return;
-  promptEl.innerHTML = text.includes('\x1b[COLOR:') ? renderColorTokens(text) : escapeHtml(text);
+  promptEl.innerHTML = text.includes('\x1b') ? renderColorTokens(text) : escapeHtml(text);
 }
 
 export function initTerminal(wasm) {
   start_doom = wasm.start_doom;
   start_doom_with_difficulty = wasm.start_doom_with_difficulty || wasm.start_doom;
   start_screensaver = wasm.start_screensaver;
+  start_screensaver_kind = wasm.start_screensaver_kind;
+  start_viewer = wasm.start_viewer;
+  start_snake = wasm.start_snake;
+  start_pong = wasm.start_pong;
+  blank_now = wasm.blank_now;
+  bell = wasm.bell;
   doom_enable_procedural = wasm.doom_enable_procedural;
   doom_restore_original_map = wasm.doom_restore_original_map;
+  doom_start_recording = wasm.doom_start_recording;
+  doom_stop_recording = wasm.doom_stop_recording;
+  doom_play_demo = wasm.doom_play_demo;
+
+  // snake hands over each finished game for the high-score table
+  window.addEventListener('KP_SNAKE_SCORE', (event) => {
+    const line = getState().system.record_snake_score(event.detail);
+    if (line) {
+      print(line, 'info');
+    }
+    saveUserFiles();
+  });
+
+  // The idle timer warns before blanking and locks after an idle blank
+  window.addEventListener('KP_IDLE', (event) => {
+    const seq = event.detail || '';
+    if (seq.startsWith('\x1b[IDLE_WARNING:')) {
+      const secs = seq.slice('\x1b[IDLE_WARNING:'.length, -1);
+      print(`Screen blanks in ${secs}s - press a key to stay`, 'info');
+    } else if (seq === '\x1b[LOCK_SCREEN]' && !screenLocked) {
+      screenLocked = true;
+      clearScreen();
+      print('Screen locked', 'info');
+      print('Password:', 'output');
+      setPromptText('');
+    }
+    scrollToBottom();
+  });
+
+  // doom reports how far the player got when it hands the screen back
+  window.addEventListener('KP_DOOM_EXIT', (event) => {
+    if (event.detail) {
+      print(event.detail, 'info');
+    }
+    if (pendingDemoPath) {
+      const path = pendingDemoPath;
+      pendingDemoPath = null;
+      const err = getState().system.save_doom_demo(path, doom_stop_recording());
+      if (err) {
+        print(err, 'error');
+      } else {
+        print(`Demo saved to ${path}`, 'info');
+        saveUserFiles();
+      }
+    }
+    scrollToBottom();
+  });
 }
 
 function showBootSequence(messages) {
   // Clear screen before showing boot sequence
-  document.getElementById('output').innerHTML = '';
+  clearScreen();
 
   let index = 0;
-  
+  const fast = getState().system.fast_boot();
+
   function showNextMessage() {
     if (index >= messages.length) {
       // Boot complete - clear screen immediately and setup terminal
-      document.getElementById('output').innerHTML = '';
+      clearScreen();
+      if (getState().system.needs_recovery()) {
+        startInitramfs();
+        return;
+      }
       // Setup terminal like normal boot
       setPromptText(getState().system.prompt());
       return;
@@ -53,7 +131,11 @@ function showBootSequence(messages) {
       scrollToBottom();
     }
     index++;
-    
+    if (fast) {
+      showNextMessage();
+      return;
+    }
+
     // Variable timing based on message content
     let delay = 80; // default
     if (message.includes('Loading Linux')) {
@@ -85,35 +167,327 @@ function showBootSequence(messages) {
 export function setupTerminal() {
   const state = getState();
   if (state.terminalSetup) return;
-  state.terminalSetup = true;
+  startSession();
+  attachTerminalInput();
+}
 
-  const input = document.getElementById('input');
-  const prompt = document.getElementById('prompt');
+function startSession() {
+  const state = getState();
   const loginStage = getLoginStage();
   if (loginStage !== 'done') {
     startLogin();
   } else {
     const user = getUser();
     if (user && user.username && !state.greeted) {
+      printMotd();
       print(`Hello ${user.username}!`, 'output');
       state.greeted = true;
     }
     setPromptText(state.system.prompt());
+    syncHistory();
+  }
+}
+
+// Arrow keys walk the backend's history, which holds the expanded lines
+// and survives reloads through ~/.bash_history
+function syncHistory() {
+  const system = getState().system;
+  if (system && typeof system.history_entries === 'function') {
+    commandHistory = Array.from(system.history_entries());
+  }
+  historyIndex = commandHistory.length;
+}
+
+function updateHistorySearch(input) {
+  const system = getState().system;
+  const { query } = historySearch;
+  let failed = false;
+  if (query) {
+    let match = system.history_search(query, historySearch.skip);
+    if (match === undefined && historySearch.skip > 0) {
+      // Ran out of older matches: stay on the last one
+      historySearch.skip--;
+      match = system.history_search(query, historySearch.skip);
+    }
+    failed = match === undefined;
+    if (!failed) historySearch.match = match;
+  }
+  input.value = historySearch.match;
+  setPromptText(`(${failed ? 'failed ' : ''}reverse-i-search)\`${query}': `);
+}
+
+// Ctrl+Shift+F: find text in the scrollback the crate keeps. `index` counts
+// matches back from the newest
+function updateScrollbackSearch() {
+  const system = getState().system;
+  const { query } = scrollbackSearch;
+  const hits = query ? (system.term_search(query) || []) : [];
+  scrollbackSearch.index = Math.min(scrollbackSearch.index, Math.max(hits.length - 1, 0));
+  const hit = hits[scrollbackSearch.index];
+  document.querySelectorAll('#output .search-hit').forEach(el => el.classList.remove('search-hit'));
+  const el = hit ? findLine(hit.line) : null;
+  if (el) {
+    el.classList.add('search-hit');
+    el.scrollIntoView({ block: 'center' });
+  }
+  const failed = query && !hit ? 'failed ' : '';
+  const count = hit ? ` [${scrollbackSearch.index + 1}/${hits.length}]` : '';
+  setPromptText(`(${failed}scrollback-search)\`${query}'${count}: `);
+}
+
+function endScrollbackSearch() {
+  scrollbackSearch = null;
+  document.querySelectorAll('#output .search-hit').forEach(el => el.classList.remove('search-hit'));
+  setPromptText(getState().system.prompt());
+  scrollToBottom();
+}
+
+// Ctrl+Shift+F or Enter for the next older match, Shift+Enter for the
+// newer one, Escape to stop. Returns true when the key was consumed
+function handleScrollbackSearchKey(e) {
+  if (['Shift', 'Control', 'Alt', 'Meta'].includes(e.key)) {
+    return true;
+  }
+  e.preventDefault();
+  if ((e.ctrlKey && e.shiftKey && (e.key === 'f' || e.key === 'F')) || (e.key === 'Enter' && !e.shiftKey)) {
+    scrollbackSearch.index++;
+  } else if (e.key === 'Enter') {
+    scrollbackSearch.index = Math.max(scrollbackSearch.index - 1, 0);
+  } else if (e.key === 'Backspace') {
+    scrollbackSearch.query = scrollbackSearch.query.slice(0, -1);
+    scrollbackSearch.index = 0;
+  } else if (e.key.length === 1 && !e.ctrlKey && !e.metaKey && !e.altKey) {
+    scrollbackSearch.query += e.key;
+    scrollbackSearch.index = 0;
+  } else {
+    endScrollbackSearch();
+    return true;
+  }
+  updateScrollbackSearch();
+  return true;
+}
+
+function endHistorySearch() {
+  historySearch = null;
+  setPromptText(getState().system.prompt());
+}
+
+// Returns true when the key was consumed by the search
+function handleHistorySearchKey(e, input) {
+  if (e.ctrlKey && (e.key === 'r' || e.key === 'R')) {
+    e.preventDefault();
+    historySearch.skip++;
+    updateHistorySearch(input);
+    return true;
+  }
+  if (e.ctrlKey && ['c', 'C', 'g', 'G'].includes(e.key)) {
+    e.preventDefault();
+    input.value = historySearch.original;
+    endHistorySearch();
+    return true;
   }
+  if (e.key === 'Backspace') {
+    e.preventDefault();
+    historySearch.query = historySearch.query.slice(0, -1);
+    historySearch.skip = 0;
+    updateHistorySearch(input);
+    return true;
+  }
+  if (e.key.length === 1 && !e.ctrlKey && !e.metaKey && !e.altKey) {
+    e.preventDefault();
+    historySearch.query += e.key;
+    historySearch.skip = 0;
+    updateHistorySearch(input);
+    return true;
+  }
+  if (['Shift', 'Control', 'Alt', 'Meta'].includes(e.key)) {
+    return true;
+  }
+  // Enter runs the match; anything else leaves it on the line for editing
+  endHistorySearch();
+  if (e.key === 'Escape') {
+    e.preventDefault();
+    return true;
+  }
+  return false;
+}
+
+function attachTerminalInput() {
+  const state = getState();
+  if (state.terminalSetup) return;
+  state.terminalSetup = true;
 
+  const input = document.getElementById('input');
   input.addEventListener('keydown', handleTerminalKey);
   input.addEventListener('input', handleTerminalKey);
   document.addEventListener('click', () => input.focus());
   input.focus();
 }
 
+// Boot stops here when grub.cfg or a critical binary is missing
+export function startInitramfs() {
+  const system = getState().system;
+  setInitramfs(true);
+  attachTerminalInput();
+  system.initramfs_banner().split('\n').forEach(line => print(line, 'output'));
+  setPromptText(system.initramfs_prompt());
+  scrollToBottom();
+}
+
+function renderHtopFrame(frame) {
+  draw(frame, 'output');
+  scrollToBottom();
+}
+
+function handleHtopKey(e) {
+  const system = getState().system;
+  if (e.type !== 'keydown') return true;
+  e.preventDefault();
+  const result = system.htop_input(e.key);
+  if (result === '\x1b[HTOP_EXIT]') {
+    redrawTerminal();
+    document.getElementById('input').value = '';
+    setPromptText(system.prompt());
+    return true;
+  }
+  renderHtopFrame(result.slice('\x1b[HTOP]'.length));
+  return true;
+}
+
+// Pattern being typed after `/` in the pager, or null when not searching
+let pagerSearch = null;
+
+function renderPagerFrame(frame) {
+  // The crate keeps the page on its alternate screen; the shell's output
+  // comes back from the terminal model on exit
+  draw(frame, 'output');
+  scrollToBottom();
+}
+
+function showPagerResult(result) {
+  const system = getState().system;
+  if (result === '\x1b[PAGER_EXIT]') {
+    redrawTerminal();
+    pagerSearch = null;
+    document.getElementById('input').value = '';
+    setPromptText(system.prompt());
+    scrollToBottom();
+    return;
+  }
+  renderPagerFrame(result.slice('\x1b[PAGER]'.length));
+}
+
+function handlePagerKey(e) {
+  const system = getState().system;
+  if (e.type !== 'keydown') return true;
+  e.preventDefault();
+  if (pagerSearch !== null) {
+    if (e.key === 'Enter') {
+      const pattern = pagerSearch;
+      pagerSearch = null;
+      setPromptText('');
+      showPagerResult(system.pager_search(pattern));
+    } else if (e.key === 'Escape') {
+      pagerSearch = null;
+      setPromptText('');
+    } else if (e.key === 'Backspace') {
+      pagerSearch = pagerSearch.slice(0, -1);
+      setPromptText('/' + pagerSearch);
+    } else if (e.key.length === 1) {
+      pagerSearch += e.key;
+      setPromptText('/' + pagerSearch);
+    }
+    return true;
+  }
+  if (e.key === '/') {
+    pagerSearch = '';
+    setPromptText('/');
+    return true;
+  }
+  showPagerResult(system.pager_input(e.key));
+  return true;
+}
+
+// Ctrl+C: the crate stops whatever is running, then the REPL flags and the
+// screen are brought back in step with it
+function interrupt(input) {
+  const system = getState().system;
+  input.value = '';
+  passwordBuffer = '';
+  if (screenLocked || getInitramfs()) {
+    print('^C', 'info');
+    scrollToBottom();
+    return;
+  }
+  const fullScreen = system.is_htop_active() || system.is_pager_active();
+  const text = system.interrupt();
+  if (fullScreen) {
+    pagerSearch = null;
+    redrawTerminal();
+  }
+  print(text, 'info');
+  setPythonRepl(system.is_in_python_repl());
+  setLuaRepl(system.is_in_lua_repl());
+  setSqliteRepl(system.is_in_sqlite_repl());
+  setWscat(system.is_in_wscat());
+  setPromptText(system.prompt());
+  scrollToBottom();
+}
+
 export function handleTerminalKey(e) {
   const state = getState();
   const input = document.getElementById('input');
   const loginStage = getLoginStage();
+
+  if (commandRunning) {
+    if (e.type !== 'keydown') return;
+    e.preventDefault();
+    // Ctrl+C is the one key a running command listens to
+    if (e.ctrlKey && (e.key === 'c' || e.key === 'C')) {
+      interrupt(input);
+      cancelCommand();
+    }
+    return;
+  }
+
+  try {
+    const fullScreen = state.system && (state.system.is_htop_active() || state.system.is_pager_active());
+    if (fullScreen && e.type === 'keydown' && e.ctrlKey && (e.key === 'c' || e.key === 'C')) {
+      e.preventDefault();
+      interrupt(input);
+      return;
+    }
+    if (state.system && typeof state.system.is_htop_active === 'function' && state.system.is_htop_active()) {
+      handleHtopKey(e);
+      input.value = '';
+      return;
+    }
+    if (state.system && typeof state.system.is_pager_active === 'function' && state.system.is_pager_active()) {
+      handlePagerKey(e);
+      input.value = '';
+      return;
+    }
+  } catch (_) {}
+
+  if (state.system && handleTmuxKey(e, state.system)) {
+    // The pane switched to may sit in another directory
+    setPromptText(state.system.prompt());
+    return;
+  }
+
+  if (historySearch) {
+    if (e.type !== 'keydown' || handleHistorySearchKey(e, input)) {
+      return;
+    }
+  }
+  if (scrollbackSearch) {
+    if (e.type !== 'keydown' || handleScrollbackSearchKey(e)) {
+      return;
+    }
+  }
   
-  // Check if we're in password mode (login password or sudo password)
-  let isPasswordMode = loginStage === 'password';
+  // Check if we're in password mode (login password, sudo password or lock screen)
+  let isPasswordMode = loginStage === 'password' || screenLocked;
   try {
     if (state.system && typeof state.system.is_waiting_for_sudo === 'function' && state.system.is_waiting_for_sudo()) {
       isPasswordMode = true;
@@ -147,13 +521,32 @@ export function handleTerminalKey(e) {
     case 'C':
       if (e.ctrlKey) {
         e.preventDefault();
-        input.value = '';
-        passwordBuffer = '';
-        print('^C', 'info');
-        if (!getPythonRepl()) {
-          setPromptText(state.system.prompt());
+        interrupt(input);
+      }
+      break;
+
+    case 'r':
+    case 'R':
+      if (e.ctrlKey) {
+        e.preventDefault();
+        if (isPasswordMode || getInitramfs() || (loginStage && loginStage !== 'done') ||
+            getPythonRepl() || getLuaRepl() || getSqliteRepl() || getWscat()) {
+          break;
         }
-        scrollToBottom();
+        historySearch = { query: '', skip: 0, match: '', original: input.value };
+        updateHistorySearch(input);
+      }
+      break;
+
+    case 'f':
+    case 'F':
+      if (e.ctrlKey && e.shiftKey) {
+        e.preventDefault();
+        if (isPasswordMode || getInitramfs() || (loginStage && loginStage !== 'done') || isTmuxActive()) {
+          break;
+        }
+        scrollbackSearch = { query: '', index: 0 };
+        updateScrollbackSearch();
       }
       break;
 
@@ -161,8 +554,10 @@ export function handleTerminalKey(e) {
     case 'L':
       if (e.ctrlKey) {
         e.preventDefault();
-        document.getElementById('output').innerHTML = '';
-        if (!getPythonRepl()) {
+        clearScreen();
+        if (getInitramfs()) {
+          setPromptText(state.system.initramfs_prompt());
+        } else if (!getPythonRepl() && !getLuaRepl() && !getSqliteRepl() && !getWscat()) {
           setPromptText(state.system.prompt());
         }
       }
@@ -185,27 +580,51 @@ export function handleTerminalKey(e) {
         handleCommand(val);
         break;
       }
+
+      if (screenLocked) {
+        handleUnlockInput(val);
+        break;
+      }
+
+      if (getInitramfs()) {
+        handleInitramfsInput(val);
+        break;
+      }
       
       if (loginStage && loginStage !== 'done') {
         handleLoginInput(val);
         break;
       }
-      if (val.trim() && !isPasswordMode) {
-        val = expandHistoryShortcut(val);
-      }
-      if (val.trim()) {
+      const inRepl = getPythonRepl() || getLuaRepl() || getSqliteRepl() || getWscat();
+      // Shell lines are recorded by the backend and picked up by syncHistory
+      if (val.trim() && inRepl) {
         if (!commandHistory.length || commandHistory[commandHistory.length - 1] !== val) {
           commandHistory.push(val);
         }
         historyIndex = commandHistory.length;
       }
-      getPythonRepl() ? handlePythonInput(val) : handleCommand(val);
+      if (getPythonRepl()) {
+        handlePythonInput(val);
+      } else if (getLuaRepl()) {
+        handleLuaInput(val);
+      } else if (getSqliteRepl()) {
+        handleSqliteInput(val);
+      } else if (getWscat()) {
+        handleWscatInput(val);
+      } else {
+        handleCommand(val);
+      }
       input.value = '';
       break;
 
     case 'Tab':
       e.preventDefault();
-      if (!getPythonRepl()) autocomplete(input);
+      if (getPythonRepl() || getLuaRepl()) {
+        // Indent the current block line instead of completing commands
+        input.setRangeText('    ', input.selectionStart, input.selectionEnd, 'end');
+      } else {
+        autocomplete(input);
+      }
       break;
 
     case 'ArrowUp':
@@ -233,6 +652,60 @@ export function handleTerminalKey(e) {
   }
 }
 
+function handleInitramfsInput(line) {
+  const system = getState().system;
+
+  print(`${system.initramfs_prompt()}${line}`, 'command');
+  const result = system.exec_initramfs(line);
+
+  if (result === '\x1b[EXIT_INITRAMFS]') {
+    setInitramfs(false);
+    clearScreen();
+    startSession();
+    return;
+  }
+  if (result === '\x1b[REBOOT]') {
+    setInitramfs(false);
+    print('Rebooting...', 'info');
+    setTimeout(() => {
+      window.dispatchEvent(new CustomEvent('KP_REBOOT'));
+    }, 500);
+    return;
+  }
+  if (result === '\x1b[CLEAR]') {
+    clearScreen();
+  } else if (result) {
+    print(result, 'output');
+  }
+  // Repairs should survive a reload
+  system.save();
+  setPromptText(system.initramfs_prompt());
+  scrollToBottom();
+}
+
+function handleUnlockInput(password) {
+  if (!getState().system.unlock_screen(password)) {
+    print('Authentication failure', 'error');
+    print('Password:', 'output');
+    scrollToBottom();
+    return;
+  }
+  screenLocked = false;
+  clearScreen();
+  setPromptText(getState().system.prompt());
+}
+
+// The backend rewrites /etc/motd for each login and records it in lastlog
+function printMotd() {
+  const system = getState().system;
+  if (!system || typeof system.login_motd !== 'function') return;
+  const motd = system.login_motd();
+  if (motd) {
+    motd.split('\n').forEach(line => print(line, 'output'));
+    print('', 'output');
+  }
+}
+
 function startLogin() {
   setLoginStage('username');
   print('login:', 'output');
@@ -254,20 +727,42 @@ function handleLoginInput(text) {
     // Inform backend of the active user so ownership and prompt reflect it
     try { getState().system.set_user(username); getState().system.set_user_password(password); } catch (e) {}
     setLoginStage('done');
+    printMotd();
     print(`Hello ${username}!`, 'output');
     // Restore normal prompt
     setPromptText(getState().system.prompt());
   }
 }
 
-
+// Tell the backend how many character cells fit across the output, so ls
+// can lay out its columns, and down the terminal, for its screen model
+function syncTerminalWidth(system) {
+  const output = document.getElementById('output');
+  if (!output || typeof system.set_terminal_width !== 'function') return;
+  const probe = document.createElement('span');
+  probe.style.visibility = 'hidden';
+  probe.style.position = 'absolute';
+  probe.textContent = 'M'.repeat(100);
+  output.appendChild(probe);
+  const cell = probe.getBoundingClientRect();
+  output.removeChild(probe);
+  const cellWidth = cell.width / 100;
+  if (cellWidth > 0) {
+    system.set_terminal_width(Math.floor(output.clientWidth / cellWidth));
+  }
+  if (cell.height > 0 && typeof system.set_terminal_height === 'function') {
+    const terminal = document.getElementById('terminal');
+    system.set_terminal_height(Math.floor(terminal.clientHeight / cell.height));
+  }
+}
 
 export async function handleCommand(cmd) {
   const state = getState();
   const system = state.system;
   const promptText = system.prompt();
   
-  if (cmd.trim() !== 'clear') {
+  // Inside tmux the crate records the line in the pane itself
+  if (cmd.trim() !== 'clear' && !isTmuxActive()) {
     // Do not echo password entries when backend is waiting for sudo password
     let waiting = false;
     try {
@@ -275,129 +770,338 @@ export async function handleCommand(cmd) {
     } catch (_) {
       waiting = false;
     }
-    if (!waiting) {
+    let answering = false;
+    try {
+      answering = typeof system.is_waiting_for_answer === 'function' && system.is_waiting_for_answer();
+    } catch (_) {}
+    if (answering) {
+      // The question was printed already; show the answer after it
+      print(cmd, 'command');
+    } else if (!waiting) {
       print(`${promptText}${cmd}`, 'command');
     }
   }
 
   // Delegate to backend for all commands (including sudo and reboot)
 
-  const result = system.exec(cmd);
+  syncTerminalWidth(system);
+  // Network commands finish their requests before this resolves; keys
+  // other than Ctrl+C wait until then
+  commandRunning = true;
+  let result;
+  try {
+    result = await execAsync(system, cmd);
+  } finally {
+    commandRunning = false;
+  }
+  syncHistory();
+  // BEL anywhere in the output rings the bell instead of printing
+  if (result.includes('\x07')) {
+    bell();
+    result = result.replace(/\x07/g, '');
+  }
 
-  // Process escape sequences
-  if (result === '\x1b[CLEAR]') {
-    document.getElementById('output').innerHTML = '';
-  } else if (result === '\x1b[EXIT]') {
-    print('logout', 'info');
-  } else if (result === '\x1b[PYTHON_REPL]') {
-    setPythonRepl(true);
-    print('Python 3.11.0 (sandboxed, Rust-backed)', 'info');
-    print('Type "exit()" to exit', 'info');
-    setPromptText('>>> ');
-  } else if (result.startsWith('\x1b[DOOM_ENABLE_PROC]')) {
-    if (typeof doom_enable_procedural === 'function') {
-      doom_enable_procedural();
-      print('Procedural map enabled.', 'info');
-    } else {
-      print('Procedural map API not available.', 'error');
-    }
-  } else if (result.startsWith('\x1b[DOOM_RESTORE]')) {
-    if (typeof doom_restore_original_map === 'function') {
-      doom_restore_original_map();
-      print('Original map restored.', 'info');
-    } else {
-      print('Restore map API not available.', 'error');
-    }
-  } else if (result === '\x1b[LAUNCH_DOOM]' || result.startsWith('\x1b[LAUNCH_DOOM:')) {
-    // Handle DOOM launch
-    const match = /\x1b\[LAUNCH_DOOM(?::(\d))?\]/.exec(result);
-    if (match && match[1]) {
-      const diff = parseInt(match[1], 10);
-      start_doom_with_difficulty(diff);
-    } else {
-      start_doom();
-    }
-  } else if (result.startsWith('\x1b[LAUNCH_SNAKE]')) {
-    start_doom();
-  } else if (result.startsWith('\x1b[LAUNCH_SCREENSAVER]')) {
-    start_screensaver();
-  } else if (result.startsWith('\x1b[BOOT_SEQUENCE:')) {
-    // Handle boot sequence animation
-    const messagesStr = result.slice(16, -1); // Remove \x1b[BOOT_SEQUENCE: and ]
-    const messages = messagesStr.split('|');
-    showBootSequence(messages);
-  } else if (result === '\x1b[LAUNCH_GRUB]') {
-    // Show GRUB menu
-    import('./grub.js').then(module => module.showGrub());
-  }
-  if (result.startsWith('\x1b[FETCH:')) {
-    await fetchUrl(result.slice(8, -1));
-  } else if (result.startsWith('\x1b[CURL:')) {
-    const parts = result.slice(7, -1).split(':');
-    await doCurl(parts.slice(2).join(':'), parts[0] || 'GET', parts[1] === 'true');
-  } else if (result.startsWith('\x1b[PING:')) {
-    await doPing(result.slice(7, -1));
-  } else if (result.startsWith('\x1b[DNS:')) {
-    await doDns(result.slice(6, -1));
-  } else if (result.startsWith('\x1b[MYIP]')) {
-    await doMyIp();
-  } else if (result.startsWith('\x1b[OPEN:')) {
-    window.open(result.slice(7, -1), '_blank');
-  } else if (result.startsWith('\x1b[NANO:')) {
-    const content = result.slice(7, -1);
-    const colonIdx = content.indexOf(':');
-    if (colonIdx === -1) {
-      launchNanoEditor('', '');
-    } else {
-      launchNanoEditor(content.substring(0, colonIdx), content.substring(colonIdx + 1).replace(/\\n/g, '\n'));
-    }
-  } else if (result.startsWith('\x1b[KERNEL_PANIC]')) {
-    showKernelPanic(result.slice(15));
-  } else if (result === '\x1b[REBOOT]') {
-    print('Rebooting...', 'info');
-    setTimeout(() => {
-      window.dispatchEvent(new CustomEvent('KP_REBOOT'));
-    }, 500);
+  const events = system.poll_events() || [];
+  const power = events.find(ev => ['reboot', 'halt', 'power_off'].includes(ev.type));
+
+  if (result.startsWith('\x1b[HTOP]')) {
+    setPromptText('');
+    renderHtopFrame(result.slice('\x1b[HTOP]'.length));
+    return;
+  } else if (result.startsWith('\x1b[PAGER]')) {
+    setPromptText('');
+    renderPagerFrame(result.slice('\x1b[PAGER]'.length));
+    return;
+  } else if (isTmuxActive() || system.is_tmux_attached()) {
+    refreshTmux(system, result);
+  } else if (power) {
+    // Services and processes are already stopped; show the shutdown log
+    result.split('\n').forEach(line => {
+      if (line) print(line, 'boot');
+    });
   } else if (result && result.trim()) {
-    // Handle command output - clean up any remaining escape sequences
-    const clean = result
-      .replace(/\x1b\[COLOR:[^\]]*\]/g, '')  // \x1b[COLOR:blue], \x1b[COLOR:reset], etc.
-      .replace(/\x1b\[[0-9;]*m/g, '')    // Standard ANSI escapes
-      .replace(/\x1b\[[A-Z_]+[^\]]*\]/g, ''); // Other escape sequences like \x1b[SOMETHING]
+    // Colours and cursor movement are rendered; leftover frontend markers
+    // are not meant to be seen
+    const clean = result.replace(/\x1b\[(?!COLOR:|BG:)[A-Z_]{2,}[^\]]*\]/g, '');
     if (clean.trim()) {
       print(clean, 'output');
     }
   }
 
+  for (const ev of events) {
+    await handleSystemEvent(system, ev);
+  }
+
   const nanoEditor = getNanoEditor();
   // Check if backend is waiting for sudo password after this command
   let waitingSudo = false;
+  let waitingAnswer = false;
   try {
     waitingSudo = typeof system.is_waiting_for_sudo === 'function' && system.is_waiting_for_sudo();
+    waitingAnswer = typeof system.is_waiting_for_answer === 'function' && system.is_waiting_for_answer();
   } catch (_) {}
   
-  if (!getPythonRepl() && !nanoEditor && !waitingSudo) {
+  if (!getPythonRepl() && !getLuaRepl() && !getSqliteRepl() && !getWscat() && !nanoEditor && !waitingSudo && !waitingAnswer) {
     setPromptText(system.prompt());
-  } else if (waitingSudo) {
-    // Clear prompt when waiting for sudo password (Linux-style)
+  } else if (waitingSudo || waitingAnswer) {
+    // Clear prompt when waiting for a sudo password or a y/n answer (Linux-style)
     setPromptText('');
   }
+  // Whatever the command changed survives a reload
+  saveUserFiles();
   scrollToBottom();
 }
 
+// Act on one event the command queued (see `SystemEvent` in the crate)
+async function handleSystemEvent(system, ev) {
+  switch (ev.type) {
+    case 'clear':
+      redrawTerminal();
+      break;
+    case 'logout':
+      print('logout', 'info');
+      break;
+    case 'python_repl':
+      setPythonRepl(true);
+      print('Python 3.11.0 (sandboxed, Rust-backed)', 'info');
+      print('Type "exit()" to exit', 'info');
+      setPromptText('>>> ');
+      break;
+    case 'lua_repl':
+      setLuaRepl(true);
+      print('Lua 5.4.6  Copyright (C) 1994-2023 Lua.org, PUC-Rio', 'info');
+      print('Type "os.exit()" to exit', 'info');
+      setPromptText('> ');
+      break;
+    case 'sqlite_repl':
+      setSqliteRepl(true);
+      setPromptText('sqlite> ');
+      break;
+    case 'wscat':
+      // Output arrives through onSocketEvent once the socket opens
+      setWscat(true);
+      setPromptText('> ');
+      break;
+    case 'doom_map':
+      if (ev.procedural && typeof doom_enable_procedural === 'function') {
+        doom_enable_procedural();
+        print('Procedural map enabled.', 'info');
+      } else if (!ev.procedural && typeof doom_restore_original_map === 'function') {
+        doom_restore_original_map();
+        print('Original map restored.', 'info');
+      } else {
+        print('Doom map API not available.', 'error');
+      }
+      break;
+    case 'memtest': {
+      const input = document.getElementById('input');
+      input.disabled = true;
+      startMemtest(() => {
+        clearScreen();
+        input.disabled = false;
+        input.focus();
+        setPromptText(system.prompt());
+      });
+      break;
+    }
+    case 'launch_doom':
+      if (ev.difficulty != null) {
+        start_doom_with_difficulty(ev.difficulty);
+      } else {
+        start_doom();
+      }
+      break;
+    case 'record_doom_demo':
+      pendingDemoPath = ev.path;
+      doom_start_recording();
+      start_doom_with_difficulty(ev.difficulty);
+      break;
+    case 'play_doom_demo': {
+      const err = doom_play_demo(system.read_doom_demo(ev.path));
+      if (err) {
+        print(`doom: ${ev.path}: ${err}`, 'error');
+      }
+      break;
+    }
+    case 'launch_snake':
+      start_snake(system.snake_best());
+      break;
+    case 'launch_pong':
+      start_pong(ev.cpu);
+      break;
+    case 'launch_screensaver':
+      if (ev.kind) {
+        start_screensaver_kind(ev.kind);
+      } else {
+        start_screensaver();
+      }
+      break;
+    case 'blank_screen':
+      blank_now();
+      break;
+    case 'view_image': {
+      const err = start_viewer(system.read_binary(ev.path), ev.path);
+      if (err) {
+        print(`view: ${ev.path}: ${err}`, 'error');
+      }
+      break;
+    }
+    case 'boot_sequence':
+      showBootSequence(ev.messages);
+      break;
+    case 'launch_grub':
+      import('./grub.js').then(module => module.showGrub());
+      break;
+    case 'git_clone':
+      await doGitClone(ev.repo, ev.dir);
+      break;
+    case 'traceroute':
+      await doTraceroute(ev.host, ev.header);
+      break;
+    case 'dns':
+      await doDns(ev.host);
+      break;
+    case 'my_ip':
+      await doMyIp();
+      break;
+    case 'open_url':
+      window.open(ev.url, '_blank');
+      break;
+    case 'nano':
+      launchNanoEditor(ev.path, ev.content);
+      break;
+    case 'kernel_panic':
+      // The crate draws the panic on the canvas and reboots on a key press
+      try {
+        system.show_kernel_panic();
+      } catch (e) {
+        print(ev.reason, 'error');
+      }
+      break;
+    case 'reboot':
+      print('Rebooting...', 'info');
+      setTimeout(() => {
+        window.dispatchEvent(new CustomEvent('KP_REBOOT'));
+      }, 500);
+      break;
+    case 'halt':
+    case 'power_off':
+      // Boot again on the next key press
+      powerDown(system, ev.type === 'power_off');
+      break;
+  }
+}
+
+// halt/poweroff: save, go dark and power back on with the next key press
+function powerDown(system, off) {
+  system.save().catch((e) => console.warn('Failed to save the filesystem:', e));
+  setTimeout(() => {
+    if (off) {
+      clearScreen();
+    } else {
+      print('System halted.', 'info');
+    }
+    setPromptText('');
+    const powerOn = (e) => {
+      e.preventDefault();
+      e.stopPropagation();
+      document.removeEventListener('keydown', powerOn, true);
+      window.dispatchEvent(new CustomEvent('KP_REBOOT'));
+    };
+    document.addEventListener('keydown', powerOn, true);
+  }, 500);
+}
+
 function handlePythonInput(code) {
   const state = getState();
   const system = state.system;
   
-  print(`>>> ${code}`, 'command');
+  print(`${system.python_prompt()}${code}`, 'command');
   const result = system.exec_python(code);
   
   if (result === '\x1b[EXIT_PYTHON]') {
     setPythonRepl(false);
     setPromptText(system.prompt());
-  } else if (result) {
+    scrollToBottom();
+    return;
+  }
+  if (result) {
+    print(result, 'output');
+  }
+  setPromptText(system.python_prompt());
+  scrollToBottom();
+}
+
+function handleLuaInput(code) {
+  const state = getState();
+  const system = state.system;
+
+  print(`${system.lua_prompt()}${code}`, 'command');
+  const result = system.exec_lua(code);
+
+  if (result === '\x1b[EXIT_LUA]') {
+    setLuaRepl(false);
+    setPromptText(system.prompt());
+    scrollToBottom();
+    return;
+  }
+  if (result) {
+    print(result, 'output');
+  }
+  setPromptText(system.lua_prompt());
+  scrollToBottom();
+}
+
+function handleSqliteInput(line) {
+  const state = getState();
+  const system = state.system;
+
+  print(`${system.sqlite_prompt()}${line}`, 'command');
+  const result = system.exec_sqlite(line);
+  // Databases are written to the VFS after each change; keep the saved image in step
+  saveUserFiles();
+
+  if (result === '\x1b[EXIT_SQLITE]') {
+    setSqliteRepl(false);
+    setPromptText(system.prompt());
+    scrollToBottom();
+    return;
+  }
+  if (result) {
     print(result, 'output');
   }
+  setPromptText(system.sqlite_prompt());
+  scrollToBottom();
+}
+
+function handleWscatInput(line) {
+  const system = getState().system;
+
+  print(`> ${line}`, 'command');
+  const result = system.exec_wscat(line);
+  if (result) {
+    print(result, 'error');
+  }
+  scrollToBottom();
+}
+
+// WebSocket events from the wasm side; wscat sessions print them
+export function onSocketEvent(id, kind, data) {
+  const system = getState().system;
+  let line;
+  try {
+    line = system.socket_event(id, kind, data);
+  } catch (e) {
+    console.warn('Socket event dropped:', e);
+    return;
+  }
+  if (line) {
+    print(line, kind === 'error' ? 'error' : 'output');
+  }
+  if (getWscat() && !system.is_in_wscat()) {
+    setWscat(false);
+    setPromptText(system.prompt());
+  }
   scrollToBottom();
 }
 
@@ -407,7 +1111,7 @@ function autocomplete(partial) {
   const cursor = input.selectionStart ?? value.length;
   const beforeCursor = value.slice(0, cursor);
 
-  if (!beforeCursor || /\s$/.test(beforeCursor)) {
+  if (!beforeCursor.trim()) {
     return;
   }
 
@@ -415,10 +1119,8 @@ function autocomplete(partial) {
   const system = state.system;
   const parts = beforeCursor.split(/\s+/);
   const currentToken = parts[parts.length - 1] || '';
-  const isFirstToken = parts.length === 1;
-  const completions = isFirstToken
-    ? system.complete(currentToken)
-    : system.complete_path(currentToken);
+  // The backend picks commands, flags or paths from the whole line
+  const completions = system.complete(beforeCursor);
 
   if (!completions || completions.length === 0) {
     return;
@@ -432,7 +1134,7 @@ function autocomplete(partial) {
     return;
   }
 
-  const prefix = longestCommonPrefix(completions);
+  const prefix = system.complete_hint(beforeCursor);
   if (prefix.length > currentToken.length) {
     input.value = value.slice(0, cursor - currentToken.length) + prefix + value.slice(cursor);
     const pos = cursor - currentToken.length + prefix.length;
@@ -450,28 +1152,3 @@ function autocomplete(partial) {
   }
 }
 
-function expandHistoryShortcut(value) {
-  const trimmed = value.trim();
-  if (trimmed === '!!') {
-    return commandHistory.length ? commandHistory[commandHistory.length - 1] : value;
-  }
-  if (/^!\d+$/.test(trimmed)) {
-    const idx = parseInt(trimmed.slice(1), 10) - 1;
-    if (idx >= 0 && idx < commandHistory.length) {
-      return commandHistory[idx];
-    }
-  }
-  return value;
-}
-
-function longestCommonPrefix(items) {
-  if (!items || items.length === 0) return '';
-  let prefix = items[0];
-  for (let i = 1; i < items.length; i++) {
-    while (!items[i].startsWith(prefix) && prefix.length > 0) {
-      prefix = prefix.slice(0, -1);
-    }
-    if (!prefix) break;
-  }
-  return prefix;
-}
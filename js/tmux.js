@@ -0,0 +1,81 @@
+// Draws the tmux session the crate keeps (`System.tmux_view`) in place of
+// the normal output, and sends the key after the Ctrl-b prefix to
+// `System.tmux_key`
+import { print, escapeHtml, renderColorTokens } from './dom.js';
+
+// The normal output, put aside while a session is attached
+let saved = null;
+let prefix = false;
+
+export function isTmuxActive() {
+  return saved !== null;
+}
+
+function renderLine(text) {
+  return text.includes('\x1b') ? renderColorTokens(text) : escapeHtml(text);
+}
+
+// One element per layout node: panes inside nested row/column splits
+function renderLayout(node, view, panes) {
+  const el = document.createElement('div');
+  if (node.type === 'pane') {
+    el.className = node.id === view.active_pane ? 'tmux-pane active' : 'tmux-pane';
+    el.innerHTML = (panes.get(node.id) || [])
+      .map(line => `<div class="line">${renderLine(line)}</div>`)
+      .join('');
+  } else {
+    el.className = `tmux-split ${node.direction}`;
+    node.children.forEach(child => el.appendChild(renderLayout(child, view, panes)));
+  }
+  return el;
+}
+
+// Redraw after anything that may have changed the session. Once no session
+// is attached the normal output comes back, with `text` printed under it.
+// Returns whether a session is still attached
+export function refreshTmux(system, text) {
+  const output = document.getElementById('output');
+  const view = system.tmux_view();
+  if (!view) {
+    if (saved !== null) {
+      output.classList.remove('tmux');
+      output.replaceChildren(...saved);
+      saved = null;
+    }
+    if (text && text.trim()) print(text, 'output');
+    return false;
+  }
+  if (saved === null) {
+    saved = Array.from(output.childNodes);
+    output.classList.add('tmux');
+  }
+  const panes = new Map(view.panes.map(p => [p.id, p.lines]));
+  const status = document.createElement('div');
+  status.className = 'tmux-status';
+  const tabs = view.windows.map(w => `${w.index}:${w.name}${w.active ? '*' : '-'}`);
+  status.textContent = `[${view.session}] ${tabs.join(' ')}`;
+  output.replaceChildren(renderLayout(view.layout, view, panes), status);
+  output.querySelectorAll('.tmux-pane').forEach(pane => {
+    pane.scrollTop = pane.scrollHeight;
+  });
+  return true;
+}
+
+// Ctrl-b arms the prefix and the next key goes to the crate. Returns
+// whether the key was used
+export function handleTmuxKey(e, system) {
+  if (e.type !== 'keydown' || saved === null) return false;
+  if (prefix) {
+    if (['Control', 'Shift', 'Alt', 'Meta'].includes(e.key)) return true;
+    e.preventDefault();
+    prefix = false;
+    refreshTmux(system, system.tmux_key(e.key));
+    return true;
+  }
+  if (e.ctrlKey && !e.altKey && e.key.toLowerCase() === 'b') {
+    e.preventDefault();
+    prefix = true;
+    return true;
+  }
+  return false;
+}
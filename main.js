@@ -5,47 +5,73 @@ import init, {
   start_doom,
   start_doom_with_difficulty,
   start_screensaver,
+  start_screensaver_kind,
+  start_viewer,
+  start_snake,
+  start_pong,
   doom_enable_procedural,
   doom_restore_original_map,
+  doom_start_recording,
+  doom_stop_recording,
+  doom_play_demo,
   fetch_http,
-  curl_request,
-  ping_request,
+  net_perform,
   dns_lookup,
   get_public_ip,
-  start_idle_timer
+  start_idle_timer,
+  idle_timeout_ms,
+  blank_now,
+  bell,
+  ansi_to_html
 } from './pkg/terminal_os.js';
 
 import { getState, setSystem, setGrubMenu } from './js/state.js';
-import { print } from './js/dom.js';
-import { loadUserFiles, loadUserInfo } from './js/storage.js';
+import { print, initDom } from './js/dom.js';
+import { migrateUserFiles, loadUserInfo } from './js/storage.js';
 import { showGrub } from './js/grub.js';
 import { showBiosScreen } from './js/bios.js';
 import { initNano } from './js/nano.js';
-import { initTerminal } from './js/terminal.js';
+import { initTerminal, onSocketEvent } from './js/terminal.js';
 import { initNetwork } from './js/network.js';
+import { installDropHandler } from './js/drop.js';
 
 async function main() {
   try {
     await init();
 
-    initNano({ NanoEditor });
+    initDom({ ansi_to_html });
+    initNano({ NanoEditor, bell });
     initTerminal({
       start_doom,
       start_doom_with_difficulty,
       start_screensaver,
+      start_screensaver_kind,
+      start_viewer,
+      start_snake,
+      start_pong,
+      blank_now,
+      bell,
       doom_enable_procedural,
-      doom_restore_original_map
+      doom_restore_original_map,
+      doom_start_recording,
+      doom_stop_recording,
+      doom_play_demo
     });
-    initNetwork({ fetch_http, curl_request, ping_request, dns_lookup, get_public_ip });
+    initNetwork({ fetch_http, net_perform, dns_lookup, get_public_ip });
 
     const system = new System();
     setSystem(system);
+    system.set_socket_listener(onSocketEvent);
     setGrubMenu(new GrubMenu());
+    installDropHandler(document.getElementById('terminal'));
 
-    await system.init();
-
-    loadUserFiles();
+    // false when there is no full filesystem image yet
+    if (!(await system.init())) {
+      await migrateUserFiles();
+    }
     loadUserInfo();
+    // ~/.config/idle has been applied by now
+    start_idle_timer(idle_timeout_ms());
 
     showBiosScreen(() => {
       showGrub();
@@ -60,7 +86,6 @@ async function main() {
 }
 
 main();
-setTimeout(() => start_idle_timer(60000), 1000);
 
 window.addEventListener('beforeunload', async () => {
   const system = getState().system;
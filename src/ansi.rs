@@ -0,0 +1,551 @@
+//! ANSI escape sequences in command output: SGR colours and attributes and
+//! the cursor movement that progress bars and ANSI art rely on. The older
+//! `\x1b[COLOR:name]` and `\x1b[BG:name]` markers are translated to SGR on
+//! the way through, so both render the same. `to_html` plays a block of
+//! output onto a grid of cells and hands the frontend the result as HTML.
+use wasm_bindgen::prelude::*;
+
+pub const RESET: &str = "\x1b[0m";
+pub const BOLD: &str = "\x1b[1m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const MAGENTA: &str = "\x1b[35m";
+pub const CYAN: &str = "\x1b[36m";
+/// `ls` directories and executables, as `dircolors` has them
+pub const DIR: &str = "\x1b[01;34m";
+pub const EXEC: &str = "\x1b[01;32m";
+/// grep's highlight for matches
+pub const MATCH: &str = "\x1b[01;31m";
+
+/// Furthest the cursor may be moved, so a stray `\x1b[9999;9999H` cannot
+/// allocate a huge grid
+const MAX_ROWS: usize = 5000;
+const MAX_COLS: usize = 1000;
+
+const DEFAULT_FG: &str = "#fff";
+const DEFAULT_BG: &str = "#000";
+/// xterm's default 16 colours
+const PALETTE: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// `text` in `sgr`, back to normal after
+pub fn paint(text: &str, sgr: &str) -> String {
+    format!("{}{}{}", sgr, text, RESET)
+}
+
+/// One piece of output: a character to show or something to do
+#[derive(Debug, PartialEq)]
+enum Token {
+    Char(char),
+    /// `\x1b[` parameters and final byte; `private` for `\x1b[?25l` and kin
+    Csi {
+        params: Vec<u16>,
+        private: bool,
+        action: char,
+    },
+    /// A legacy `\x1b[NAME:value]` marker, `value` empty for bare `\x1b[NAME]`
+    Marker {
+        name: String,
+        value: String,
+    },
+}
+
+/// The marker at the start of `rest` (just after `\x1b[`): upper-case name,
+/// optional `:value`, closing `]`. Returns it with its length in bytes
+fn marker(rest: &str) -> Option<(Token, usize)> {
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_uppercase() || c == '_'))
+        .unwrap_or(rest.len());
+    if name_len < 2 {
+        return None;
+    }
+    let end = rest.find(']')?;
+    let body = &rest[..end];
+    if body.contains('\n') || end < name_len {
+        return None;
+    }
+    let (name, value) = body.split_once(':').unwrap_or((body, ""));
+    if name.len() != name_len {
+        return None;
+    }
+    Some((
+        Token::Marker {
+            name: name.to_string(),
+            value: value.to_string(),
+        },
+        end + 1,
+    ))
+}
+
+fn tokens(text: &str) -> Vec<Token> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let Some(after) = rest.strip_prefix('\x1b') else {
+            let c = rest.chars().next().unwrap_or_default();
+            out.push(Token::Char(c));
+            i += c.len_utf8();
+            continue;
+        };
+        if let Some(csi) = after.strip_prefix('[') {
+            if let Some((token, len)) = marker(csi) {
+                out.push(token);
+                i += 2 + len;
+                continue;
+            }
+            // Parameter bytes, then intermediates, then the final byte
+            let end = csi.find(|c: char| ('\x40'..='\x7e').contains(&c));
+            let Some(end) = end else {
+                break;
+            };
+            let params = &csi[..end];
+            let private = params.starts_with(['?', '<', '=', '>']);
+            out.push(Token::Csi {
+                params: params
+                    .trim_start_matches(['?', '<', '=', '>'])
+                    .split(';')
+                    .map(|p| p.parse().unwrap_or(0))
+                    .collect(),
+                private,
+                action: csi[end..].chars().next().unwrap_or_default(),
+            });
+            i += 2 + end + 1;
+        } else if let Some(osc) = after.strip_prefix(']') {
+            // Window titles and the like: skip to BEL or ST
+            let end = osc.find(['\x07', '\x1b']).map_or(osc.len(), |e| {
+                e + if osc[e..].starts_with('\x1b') { 2 } else { 1 }
+            });
+            i += 2 + end.min(osc.len());
+        } else {
+            // Two-byte escapes (`\x1b7`, `\x1b(B`, ...): skip what they select
+            let mut skip = 1 + after.chars().next().map_or(0, char::len_utf8);
+            if after.starts_with(['(', ')']) {
+                skip += 1;
+            }
+            i += skip.min(rest.len());
+        }
+    }
+    out
+}
+
+/// Output with every escape sequence and marker taken out
+pub fn strip(text: &str) -> String {
+    tokens(text)
+        .into_iter()
+        .filter_map(|t| match t {
+            Token::Char(c) if c == '\n' || c == '\t' || !c.is_control() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Columns `text` takes up once its escapes are gone
+pub fn visible_width(text: &str) -> usize {
+    strip(text).chars().count()
+}
+
+/// SGR for a legacy marker colour: a name from the old scheme or `#rgb`
+fn marker_sgr(value: &str, background: bool) -> Option<String> {
+    let base = if background { 40 } else { 30 };
+    let index = match value {
+        "reset" => return Some(format!("\x1b[{}m", base + 9)),
+        "black" => 0,
+        "red" => 1,
+        "green" => 2,
+        "yellow" => 3,
+        "blue" => 4,
+        "magenta" => 5,
+        "cyan" => 6,
+        "white" => 7,
+        "gray" | "grey" => return Some(format!("\x1b[{}m", base + 60)),
+        hex => {
+            let hex = hex
+                .strip_prefix('#')
+                .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))?;
+            let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+            let (r, g, b) = match hex.len() {
+                3 => {
+                    let c = |i: usize| channel(&hex[i..=i]).map(|v| v * 17);
+                    (c(0)?, c(1)?, c(2)?)
+                }
+                6 => (
+                    channel(&hex[0..2])?,
+                    channel(&hex[2..4])?,
+                    channel(&hex[4..6])?,
+                ),
+                _ => return None,
+            };
+            return Some(format!("\x1b[{};2;{};{};{}m", base + 8, r, g, b));
+        }
+    };
+    Some(format!("\x1b[{}m", base + index))
+}
+
+/// Rewrite `\x1b[COLOR:name]` and `\x1b[BG:name]` markers as SGR; other
+/// markers are left for the frontend
+pub fn from_markers(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find("\x1b[") {
+        out.push_str(&rest[..at]);
+        let after = &rest[at + 2..];
+        match marker(after) {
+            Some((Token::Marker { name, value }, len)) if name == "COLOR" || name == "BG" => {
+                if let Some(sgr) = marker_sgr(&value, name == "BG") {
+                    out.push_str(&sgr);
+                }
+                rest = &after[len..];
+            }
+            _ => {
+                out.push_str("\x1b[");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Color {
+    #[default]
+    Default,
+    Index(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn css(self, bright: bool) -> Option<String> {
+        match self {
+            Color::Default => None,
+            Color::Index(i) if i < 16 => {
+                let i = if bright && i < 8 { i + 8 } else { i };
+                Some(PALETTE[i as usize].to_string())
+            }
+            Color::Index(i) if i < 232 => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let i = i - 16;
+                Some(format!(
+                    "#{:02x}{:02x}{:02x}",
+                    level(i / 36),
+                    level(i / 6 % 6),
+                    level(i % 6)
+                ))
+            }
+            Color::Index(i) => {
+                let v = 8 + (i - 232) * 10;
+                Some(format!("#{:02x}{:02x}{:02x}", v, v, v))
+            }
+            Color::Rgb(r, g, b) => Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    reverse: bool,
+}
+
+impl Style {
+    /// Apply the parameters of one `\x1b[...m`
+    fn apply(&mut self, params: &[u16]) {
+        let mut params = params.iter().copied();
+        while let Some(p) = params.next() {
+            match p {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.reverse = true,
+                21 | 22 => (self.bold, self.dim) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.reverse = false,
+                30..=37 => self.fg = Color::Index(p as u8 - 30),
+                39 => self.fg = Color::Default,
+                40..=47 => self.bg = Color::Index(p as u8 - 40),
+                49 => self.bg = Color::Default,
+                90..=97 => self.fg = Color::Index(p as u8 - 90 + 8),
+                100..=107 => self.bg = Color::Index(p as u8 - 100 + 8),
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(5) => params.next().map(|i| Color::Index(i as u8)),
+                        Some(2) => match (params.next(), params.next(), params.next()) {
+                            (Some(r), Some(g), Some(b)) => {
+                                Some(Color::Rgb(r as u8, g as u8, b as u8))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if p == 38 {
+                            self.fg = color;
+                        } else {
+                            self.bg = color;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn css(&self) -> String {
+        // Bold lifts the eight basic colours to their bright versions, as
+        // xterm does
+        let mut fg = self.fg.css(self.bold);
+        let mut bg = self.bg.css(false);
+        if self.reverse {
+            (fg, bg) = (
+                Some(bg.unwrap_or_else(|| DEFAULT_BG.into())),
+                Some(fg.unwrap_or_else(|| DEFAULT_FG.into())),
+            );
+        }
+        let mut css = Vec::new();
+        if let Some(fg) = fg {
+            css.push(format!("color:{}", fg));
+        }
+        if let Some(bg) = bg {
+            css.push(format!("background:{}", bg));
+        }
+        if self.bold {
+            css.push("font-weight:bold".into());
+        }
+        if self.dim {
+            css.push("opacity:0.7".into());
+        }
+        if self.italic {
+            css.push("font-style:italic".into());
+        }
+        if self.underline {
+            css.push("text-decoration:underline".into());
+        }
+        css.join(";")
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+const BLANK: Cell = Cell {
+    ch: ' ',
+    style: Style {
+        fg: Color::Default,
+        bg: Color::Default,
+        bold: false,
+        dim: false,
+        italic: false,
+        underline: false,
+        reverse: false,
+    },
+};
+
+/// The grid one block of output is played onto
+#[derive(Default)]
+struct Grid {
+    rows: Vec<Vec<Cell>>,
+    row: usize,
+    col: usize,
+    saved: (usize, usize),
+    style: Style,
+}
+
+impl Grid {
+    fn line(&mut self) -> &mut Vec<Cell> {
+        if self.rows.len() <= self.row {
+            self.rows.resize(self.row + 1, Vec::new());
+        }
+        &mut self.rows[self.row]
+    }
+
+    fn put(&mut self, ch: char) {
+        let (col, style) = (self.col, self.style);
+        let line = self.line();
+        if line.len() <= col {
+            line.resize(col + 1, BLANK);
+        }
+        line[col] = Cell { ch, style };
+        self.col = (col + 1).min(MAX_COLS);
+    }
+
+    fn goto(&mut self, row: usize, col: usize) {
+        self.row = row.min(MAX_ROWS);
+        self.col = col.min(MAX_COLS);
+    }
+
+    /// `\x1b[K`: 0 to the end of the line, 1 to the start, 2 all of it
+    fn erase_line(&mut self, mode: u16) {
+        let col = self.col;
+        let line = self.line();
+        match mode {
+            0 => line.truncate(col),
+            1 => line.iter_mut().take(col + 1).for_each(|c| *c = BLANK),
+            _ => line.clear(),
+        }
+    }
+
+    /// `\x1b[J`: 0 to the end of the screen, 1 to the start, 2 and 3 all
+    fn erase_screen(&mut self, mode: u16) {
+        match mode {
+            0 => {
+                self.erase_line(0);
+                self.rows.truncate(self.row + 1);
+            }
+            1 => {
+                self.erase_line(1);
+                let row = self.row.min(self.rows.len());
+                self.rows[..row].iter_mut().for_each(Vec::clear);
+            }
+            _ => self.rows.iter_mut().for_each(Vec::clear),
+        }
+    }
+
+    fn csi(&mut self, params: &[u16], action: char) {
+        let n = |i: usize| params.get(i).copied().filter(|&v| v > 0).unwrap_or(1) as usize;
+        let raw = params.first().copied().unwrap_or(0);
+        match action {
+            'm' => self.style.apply(params),
+            'A' => self.goto(self.row.saturating_sub(n(0)), self.col),
+            'B' => self.goto(self.row + n(0), self.col),
+            'C' => self.goto(self.row, self.col + n(0)),
+            'D' => self.goto(self.row, self.col.saturating_sub(n(0))),
+            'E' => self.goto(self.row + n(0), 0),
+            'F' => self.goto(self.row.saturating_sub(n(0)), 0),
+            'G' => self.goto(self.row, n(0) - 1),
+            'H' | 'f' => self.goto(n(0) - 1, n(1) - 1),
+            'K' => self.erase_line(raw),
+            'J' => self.erase_screen(raw),
+            's' => self.saved = (self.row, self.col),
+            'u' => (self.row, self.col) = self.saved,
+            _ => {}
+        }
+    }
+
+    fn html(&self) -> String {
+        let mut lines = Vec::with_capacity(self.rows.len());
+        for row in &self.rows {
+            // Trailing blanks on the default background are not drawn
+            let end = row
+                .iter()
+                .rposition(|c| c.ch != ' ' || c.style.bg != Color::Default || c.style.reverse)
+                .map_or(0, |i| i + 1);
+            let mut html = String::new();
+            let mut open: Option<Style> = None;
+            for cell in &row[..end] {
+                if open != Some(cell.style) {
+                    if open.is_some_and(|s| s != Style::default()) {
+                        html.push_str("</span>");
+                    }
+                    if cell.style != Style::default() {
+                        html.push_str(&format!("<span style=\"{}\">", cell.style.css()));
+                    }
+                    open = Some(cell.style);
+                }
+                match cell.ch {
+                    '&' => html.push_str("&amp;"),
+                    '<' => html.push_str("&lt;"),
+                    '>' => html.push_str("&gt;"),
+                    '"' => html.push_str("&quot;"),
+                    c => html.push(c),
+                }
+            }
+            if open.is_some_and(|s| s != Style::default()) {
+                html.push_str("</span>");
+            }
+            lines.push(html);
+        }
+        lines.join("\n")
+    }
+}
+
+/// Play `text` onto a grid, honouring colours, cursor movement and erases,
+/// and return the grid as HTML lines joined by `\n`. Legacy colour markers
+/// work too; other markers are dropped
+#[wasm_bindgen]
+pub fn ansi_to_html(text: &str) -> String {
+    let mut grid = Grid::default();
+    for token in tokens(&from_markers(text)) {
+        match token {
+            Token::Char('\n') => grid.goto(grid.row + 1, 0),
+            Token::Char('\r') => grid.col = 0,
+            Token::Char('\t') => grid.goto(grid.row, (grid.col / 8 + 1) * 8),
+            Token::Char('\x08') => grid.col = grid.col.saturating_sub(1),
+            Token::Char(c) if c.is_control() => {}
+            Token::Char(c) => grid.put(c),
+            Token::Csi {
+                params,
+                private: false,
+                action,
+            } => grid.csi(&params, action),
+            Token::Csi { .. } | Token::Marker { .. } => {}
+        }
+    }
+    // A newline at the very end leaves an empty row the text never used
+    if text.ends_with('\n') && grid.rows.len() > grid.row {
+        grid.rows.truncate(grid.row);
+    }
+    grid.html()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sgr_cursor_movement_and_markers() {
+        assert_eq!(ansi_to_html("a<b"), "a&lt;b");
+        assert_eq!(
+            ansi_to_html("\x1b[31mred\x1b[0m plain"),
+            "<span style=\"color:#cd0000\">red</span> plain"
+        );
+        assert_eq!(
+            ansi_to_html(&paint("dir", DIR)),
+            "<span style=\"color:#5c5cff;font-weight:bold\">dir</span>"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[38;5;196mx\x1b[48;2;1;2;3my"),
+            "<span style=\"color:#ff0000\">x</span>\
+             <span style=\"color:#ff0000;background:#010203\">y</span>"
+        );
+
+        // A progress bar redrawn in place, then a line rewritten from above
+        assert_eq!(ansi_to_html("10%\r50%\r100%"), "100%");
+        assert_eq!(
+            ansi_to_html("one\ntwo\x1b[1A\x1b[2K\rONE\x1b[1B"),
+            "ONE\ntwo"
+        );
+        assert_eq!(ansi_to_html("\x1b[2;3Hx"), "\n  x");
+        assert_eq!(ansi_to_html("abc\x1b[2D\x1b[K"), "a");
+
+        assert_eq!(
+            from_markers("\x1b[COLOR:green]ok\x1b[COLOR:reset] \x1b[BG:#0f0]\x1b[HTOP]"),
+            "\x1b[32mok\x1b[39m \x1b[48;2;0;255;0m\x1b[HTOP]"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[COLOR:cyan]~\x1b[COLOR:reset]$ \x1b[CLEAR]"),
+            "<span style=\"color:#00cdcd\">~</span>$"
+        );
+        assert_eq!(
+            strip("\x1b]0;title\x07\x1b[1;32mok\x1b[0m\t\x1b[EXIT]"),
+            "ok\t"
+        );
+        assert_eq!(visible_width(&paint("name", EXEC)), 4);
+
+        // Malformed colours are dropped rather than sliced mid-character
+        assert_eq!(from_markers("\x1b[COLOR:#éa]x\x1b[BG:#+f+]y"), "xy");
+    }
+}
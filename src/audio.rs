@@ -0,0 +1,491 @@
+//! Web Audio output shared by everything that makes noise: one `AudioContext`,
+//! a mixer with a gain node per channel under a master gain, ADSR envelopes
+//! on every tone, and a small tracker that loops a procedurally written song
+//! (doom's background music).
+use std::cell::{Cell, RefCell};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, AudioContext, AudioNode, GainNode, OscillatorType};
+
+// How far ahead of the clock the music scheduler queues notes, and how
+// often it wakes up to do so
+const LOOKAHEAD_SECS: f64 = 0.2;
+const SCHEDULE_EVERY_MS: i32 = 50;
+
+/// What `beep` plays without arguments, and what BEL rings
+pub const BEEP_FREQ: f64 = 440.0;
+pub const BEEP_MS: u32 = 200;
+
+/// Mixer channels; each has its own volume under the master volume
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Effects,
+    Music,
+    System,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 3] = [Channel::Effects, Channel::Music, Channel::System];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Effects => "effects",
+            Channel::Music => "music",
+            Channel::System => "system",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Channel> {
+        Channel::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Attack/decay/release in seconds, sustain as a fraction of the peak
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f32,
+    pub release: f64,
+}
+
+impl Envelope {
+    /// Short and clicky, for game effects
+    pub const BLIP: Envelope = Envelope {
+        attack: 0.005,
+        decay: 0.05,
+        sustain: 0.7,
+        release: 0.05,
+    };
+    /// Plucked: falls away quickly, for bass lines and drums
+    pub const PLUCK: Envelope = Envelope {
+        attack: 0.003,
+        decay: 0.08,
+        sustain: 0.3,
+        release: 0.06,
+    };
+    /// Softer onset for melodies
+    pub const PAD: Envelope = Envelope {
+        attack: 0.03,
+        decay: 0.1,
+        sustain: 0.6,
+        release: 0.12,
+    };
+
+    /// Gain breakpoints `(seconds from note on, level)` for a note held for
+    /// `duration`; the note is never released before its decay finishes
+    pub fn points(&self, peak: f32, duration: f64) -> [(f64, f32); 5] {
+        let attack_end = self.attack;
+        let decay_end = attack_end + self.decay;
+        let release_at = duration.max(decay_end);
+        let held = peak * self.sustain;
+        [
+            (0.0, 0.0),
+            (attack_end, peak),
+            (decay_end, held),
+            (release_at, held),
+            (release_at + self.release, 0.0),
+        ]
+    }
+
+    /// How long a note held for `duration` keeps sounding
+    pub fn length(&self, duration: f64) -> f64 {
+        self.points(1.0, duration)[4].0
+    }
+}
+
+/// Master and channel volumes, 0-100, and a mute that silences them all
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Levels {
+    pub master: u8,
+    pub channels: [u8; 3],
+    pub muted: bool,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels {
+            master: 80,
+            channels: [100, 60, 100],
+            muted: false,
+        }
+    }
+}
+
+impl Levels {
+    pub fn channel(&self, channel: Channel) -> u8 {
+        self.channels[channel.index()]
+    }
+
+    pub fn set_channel(&mut self, channel: Channel, percent: u8) {
+        self.channels[channel.index()] = percent.min(100);
+    }
+}
+
+/// MIDI note number to Hz (69 = A4 = 440)
+pub fn note_freq(note: u8) -> f64 {
+    440.0 * 2f64.powf((note as f64 - 69.0) / 12.0)
+}
+
+/// One voice of a song: a note (MIDI number) or rest per row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub wave: OscillatorType,
+    pub envelope: Envelope,
+    pub level: f32,
+    // Fraction of a row each note is held for
+    pub gate: f64,
+    pub notes: Vec<Option<u8>>,
+}
+
+/// Tracker-style song: every track steps through its rows together
+#[derive(Clone, Debug, PartialEq)]
+pub struct Song {
+    pub row_secs: f64,
+    pub tracks: Vec<Track>,
+}
+
+// xorshift32, so the same seed always writes the same song
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        self.next() % n
+    }
+}
+
+const MINOR_SCALE: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+// Chord roots as scale degrees, one per bar
+const PROGRESSIONS: [[usize; 4]; 4] = [[0, 5, 3, 4], [0, 3, 4, 0], [0, 6, 5, 4], [0, 0, 5, 6]];
+const ROWS_PER_BAR: usize = 16;
+
+impl Song {
+    pub fn rows(&self) -> usize {
+        self.tracks.first().map_or(0, |t| t.notes.len())
+    }
+
+    /// Four bars in a minor key: bass on the chord roots, a lead that
+    /// wanders over the chord, and a kick/snare beat
+    pub fn procedural(seed: u32) -> Song {
+        let mut rng = Rng(seed | 1);
+        let bpm = 110.0 + rng.below(31) as f64;
+        let key = 40 + rng.below(8) as u8;
+        let progression = PROGRESSIONS[rng.below(PROGRESSIONS.len() as u32) as usize];
+        let rows = ROWS_PER_BAR * progression.len();
+        let degree_note = |degree: usize| key + MINOR_SCALE[degree % 7] + 12 * (degree / 7) as u8;
+
+        let mut bass = vec![None; rows];
+        let mut lead = vec![None; rows];
+        let mut drums = vec![None; rows];
+        for (row, ((b, l), d)) in bass.iter_mut().zip(&mut lead).zip(&mut drums).enumerate() {
+            let chord = progression[row / ROWS_PER_BAR];
+            let step = row % ROWS_PER_BAR;
+            if step.is_multiple_of(2) {
+                let octave_up = step % 8 == 6;
+                *b = Some(degree_note(chord) + if octave_up { 12 } else { 0 });
+            }
+            if step.is_multiple_of(4) || rng.below(10) < 3 {
+                // Mostly chord tones, now and then a passing note
+                let offset = [0, 2, 4, 7][rng.below(4) as usize] + rng.below(2) as usize;
+                *l = Some(degree_note(chord + offset) + 24);
+            }
+            *d = match step % 8 {
+                0 => Some(33),
+                4 => Some(57),
+                _ => None,
+            };
+        }
+
+        Song {
+            row_secs: 60.0 / bpm / 4.0,
+            tracks: vec![
+                Track {
+                    wave: OscillatorType::Triangle,
+                    envelope: Envelope::PLUCK,
+                    level: 0.5,
+                    gate: 1.6,
+                    notes: bass,
+                },
+                Track {
+                    wave: OscillatorType::Square,
+                    envelope: Envelope::PAD,
+                    level: 0.12,
+                    gate: 0.8,
+                    notes: lead,
+                },
+                Track {
+                    wave: OscillatorType::Sine,
+                    envelope: Envelope::PLUCK,
+                    level: 0.6,
+                    gate: 0.3,
+                    notes: drums,
+                },
+            ],
+        }
+    }
+}
+
+struct Mixer {
+    ctx: AudioContext,
+    master: GainNode,
+    channels: Vec<GainNode>,
+}
+
+struct MusicPlayer {
+    song: Song,
+    next_row: usize,
+    next_time: f64,
+}
+
+type TickClosure = RefCell<Option<wasm_bindgen::closure::Closure<dyn FnMut()>>>;
+
+thread_local! {
+    static MIXER: RefCell<Option<Mixer>> = const { RefCell::new(None) };
+    static LEVELS: Cell<Levels> = Cell::new(Levels::default());
+    static MUSIC: RefCell<Option<MusicPlayer>> = const { RefCell::new(None) };
+    static MUSIC_TICK: TickClosure = const { RefCell::new(None) };
+    static MUSIC_INTERVAL: Cell<i32> = const { Cell::new(-1) };
+}
+
+fn gain_of(percent: u8) -> f32 {
+    percent.min(100) as f32 / 100.0
+}
+
+fn apply_levels(mixer: &Mixer, levels: &Levels) {
+    let master = if levels.muted {
+        0.0
+    } else {
+        gain_of(levels.master)
+    };
+    mixer.master.gain().set_value(master);
+    for channel in Channel::ALL {
+        mixer.channels[channel.index()]
+            .gain()
+            .set_value(gain_of(levels.channel(channel)));
+    }
+}
+
+fn create_mixer() -> Option<Mixer> {
+    let ctx = AudioContext::new().ok()?;
+    let master = ctx.create_gain().ok()?;
+    master.connect_with_audio_node(&ctx.destination()).ok()?;
+    let mut channels = Vec::with_capacity(Channel::ALL.len());
+    for _ in Channel::ALL {
+        let gain = ctx.create_gain().ok()?;
+        gain.connect_with_audio_node(&master).ok()?;
+        channels.push(gain);
+    }
+    let mixer = Mixer {
+        ctx,
+        master,
+        channels,
+    };
+    apply_levels(&mixer, &LEVELS.with(|l| l.get()));
+    Some(mixer)
+}
+
+// The context is made on first use; browsers start it suspended until the
+// page has seen a user gesture, so nudge it awake each time
+fn with_mixer(f: impl FnOnce(&Mixer)) {
+    MIXER.with(|m| {
+        if m.borrow().is_none() {
+            *m.borrow_mut() = create_mixer();
+        }
+        if let Some(ref mixer) = *m.borrow() {
+            let _ = mixer.ctx.resume();
+            f(mixer);
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn schedule_tone(
+    mixer: &Mixer,
+    channel: Channel,
+    wave: OscillatorType,
+    freq: f64,
+    start: f64,
+    duration: f64,
+    peak: f32,
+    envelope: &Envelope,
+) {
+    let (Ok(osc), Ok(gain)) = (mixer.ctx.create_oscillator(), mixer.ctx.create_gain()) else {
+        return;
+    };
+    osc.set_type(wave);
+    osc.frequency().set_value(freq as f32);
+    let out: &AudioNode = &mixer.channels[channel.index()];
+    osc.connect_with_audio_node(&gain).ok();
+    gain.connect_with_audio_node(out).ok();
+
+    let param = gain.gain();
+    for (i, (t, v)) in envelope.points(peak, duration).into_iter().enumerate() {
+        if i == 0 {
+            param.set_value_at_time(v, start + t).ok();
+        } else {
+            param.linear_ramp_to_value_at_time(v, start + t).ok();
+        }
+    }
+    osc.start_with_when(start).ok();
+    osc.stop_with_when(start + envelope.length(duration)).ok();
+}
+
+/// Play one enveloped tone on `channel` right away
+pub fn play_tone(
+    channel: Channel,
+    wave: OscillatorType,
+    freq: f64,
+    duration: f64,
+    peak: f32,
+    envelope: &Envelope,
+) {
+    with_mixer(|m| {
+        let now = m.ctx.current_time();
+        schedule_tone(m, channel, wave, freq, now, duration, peak, envelope);
+    });
+}
+
+/// `beep`: a plain square tone on the system channel
+#[wasm_bindgen]
+pub fn beep(freq: f64, ms: u32) {
+    play_tone(
+        Channel::System,
+        OscillatorType::Square,
+        freq,
+        ms as f64 / 1000.0,
+        0.3,
+        &Envelope::BLIP,
+    );
+}
+
+/// The terminal bell, rung for BEL in output and by the editor
+#[wasm_bindgen]
+pub fn bell() {
+    beep(BEEP_FREQ, BEEP_MS);
+}
+
+pub fn levels() -> Levels {
+    LEVELS.with(|l| l.get())
+}
+
+/// Set every volume at once (from `volume` or ~/.config/audio)
+pub fn set_levels(levels: Levels) {
+    LEVELS.with(|l| l.set(levels));
+    MIXER.with(|m| {
+        if let Some(ref mixer) = *m.borrow() {
+            apply_levels(mixer, &levels);
+        }
+    });
+}
+
+// Queue every row that starts before the lookahead window closes
+fn schedule_music() {
+    with_mixer(|m| {
+        let horizon = m.ctx.current_time() + LOOKAHEAD_SECS;
+        MUSIC.with(|music| {
+            let mut music = music.borrow_mut();
+            let Some(player) = music.as_mut() else {
+                return;
+            };
+            let rows = player.song.rows();
+            if rows == 0 {
+                return;
+            }
+            while player.next_time < horizon {
+                let row = player.next_row;
+                for track in &player.song.tracks {
+                    if let Some(note) = track.notes[row] {
+                        schedule_tone(
+                            m,
+                            Channel::Music,
+                            track.wave,
+                            note_freq(note),
+                            player.next_time,
+                            player.song.row_secs * track.gate,
+                            track.level,
+                            &track.envelope,
+                        );
+                    }
+                }
+                player.next_row = (row + 1) % rows;
+                player.next_time += player.song.row_secs;
+            }
+        });
+    });
+}
+
+/// Loop `song` on the music channel until `stop_music`
+pub fn start_music(song: Song) {
+    stop_music();
+    let mut start = 0.0;
+    with_mixer(|m| start = m.ctx.current_time() + 0.05);
+    MUSIC.with(|music| {
+        *music.borrow_mut() = Some(MusicPlayer {
+            song,
+            next_row: 0,
+            next_time: start,
+        })
+    });
+    schedule_music();
+
+    let tick = wasm_bindgen::closure::Closure::wrap(Box::new(schedule_music) as Box<dyn FnMut()>);
+    if let Ok(id) = window()
+        .unwrap()
+        .set_interval_with_callback_and_timeout_and_arguments_0(
+            tick.as_ref().unchecked_ref(),
+            SCHEDULE_EVERY_MS,
+        )
+    {
+        MUSIC_INTERVAL.with(|h| h.set(id));
+    }
+    MUSIC_TICK.with(|t| *t.borrow_mut() = Some(tick));
+}
+
+/// Stop queueing music; notes already queued play out
+pub fn stop_music() {
+    MUSIC_INTERVAL.with(|h| {
+        let id = h.replace(-1);
+        if id != -1 {
+            window().unwrap().clear_interval_with_handle(id);
+        }
+    });
+    MUSIC_TICK.with(|t| *t.borrow_mut() = None);
+    MUSIC.with(|music| *music.borrow_mut() = None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_and_procedural_song() {
+        let points = Envelope::BLIP.points(0.5, 0.01);
+        // A note shorter than attack+decay still gets its full shape
+        assert_eq!(points[1], (0.005, 0.5));
+        assert!((points[3].0 - 0.055).abs() < 1e-9);
+        assert_eq!(points[4].1, 0.0);
+        assert!((note_freq(69) - 440.0).abs() < 1e-9);
+        assert!((note_freq(57) - 220.0).abs() < 1e-9);
+
+        let song = Song::procedural(1234);
+        assert_eq!(song, Song::procedural(1234));
+        assert_eq!(song.rows(), 64);
+        assert!(song.tracks.iter().all(|t| t.notes.len() == 64));
+        // The beat lands on the first row of every bar
+        assert_eq!(song.tracks[2].notes[0], Some(33));
+        assert_ne!(song, Song::procedural(99));
+    }
+}
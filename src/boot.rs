@@ -35,6 +35,48 @@ impl Default for KernelConfig {
     }
 }
 
+/// Kernel parameters from the command line that change how the system boots
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BootParams {
+    /// `single`, `s`, `1` or `systemd.unit=rescue.target`: no services
+    pub single_user: bool,
+    pub quiet: bool,
+    /// `mem=64M`: cap usable memory at this many bytes
+    pub mem_limit: Option<u32>,
+}
+
+impl BootParams {
+    pub fn parse(cmdline: &str) -> BootParams {
+        let mut params = BootParams::default();
+        for token in cmdline.split_whitespace() {
+            match token {
+                "single" | "s" | "1" | "systemd.unit=rescue.target" => params.single_user = true,
+                "quiet" => params.quiet = true,
+                _ => {
+                    if let Some(size) = token.strip_prefix("mem=") {
+                        params.mem_limit = parse_mem_size(size);
+                    }
+                }
+            }
+        }
+        params
+    }
+}
+
+/// `64M`, `1G`, `512K` or plain bytes, as in the kernel's `memparse`
+pub fn parse_mem_size(text: &str) -> Option<u32> {
+    let (digits, shift) = match text.char_indices().last()? {
+        (i, 'K' | 'k') => (&text[..i], 10),
+        (i, 'M' | 'm') => (&text[..i], 20),
+        (i, 'G' | 'g') => (&text[..i], 30),
+        _ => (text, 0),
+    };
+    let value: u64 = digits.parse().ok()?;
+    u32::try_from(value.checked_shl(shift)?)
+        .ok()
+        .filter(|&v| v > 0)
+}
+
 /// Bootloader trait for different implementations
 pub trait Bootloader {
     fn name(&self) -> &str;
@@ -334,3 +376,22 @@ impl KernelSimulator {
         &self.loaded_modules
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_params() {
+        let params = BootParams::parse("root=/dev/sda1 ro single mem=64M quiet");
+        assert!(params.single_user && params.quiet);
+        assert_eq!(params.mem_limit, Some(64 << 20));
+        assert!(BootParams::parse("ro systemd.unit=rescue.target").single_user);
+        assert_eq!(BootParams::parse("ro splash"), BootParams::default());
+
+        assert_eq!(parse_mem_size("512K"), Some(512 << 10));
+        assert_eq!(parse_mem_size("4096"), Some(4096));
+        assert_eq!(parse_mem_size("8G"), None);
+        assert_eq!(parse_mem_size("lots"), None);
+    }
+}
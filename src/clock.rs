@@ -0,0 +1,241 @@
+//! Wall-clock time for file timestamps, as seconds since the Unix epoch.
+//! Dates are shown in UTC.
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Seconds since 1970-01-01. Native builds (the tests) have no JS clock
+pub fn now_secs() -> i64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Date::now() / 1000.0) as i64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// Milliseconds on a monotonic clock with sub-millisecond resolution, for
+/// timing rather than dates: since page load in the browser, since the
+/// first call natively
+pub fn now_ms() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|w| w.performance())
+            .map_or_else(js_sys::Date::now, |p| p.now())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START
+            .get_or_init(std::time::Instant::now)
+            .elapsed()
+            .as_secs_f64()
+            * 1000.0
+    }
+}
+
+/// A broken-down UTC time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Civil {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// 0 is Sunday
+    pub weekday: u32,
+}
+
+impl Civil {
+    pub fn month_name(&self) -> &'static str {
+        MONTHS[(self.month - 1) as usize]
+    }
+
+    pub fn weekday_name(&self) -> &'static str {
+        WEEKDAYS[self.weekday as usize]
+    }
+}
+
+/// Civil date from seconds since the epoch (Howard Hinnant's algorithm)
+pub fn civil(secs: i64) -> Civil {
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    Civil {
+        year: yoe + era * 400 + i64::from(month <= 2),
+        month: month as u32,
+        day: day as u32,
+        hour: (rem / 3600) as u32,
+        minute: (rem % 3600 / 60) as u32,
+        second: (rem % 60) as u32,
+        weekday: (days + 4).rem_euclid(7) as u32,
+    }
+}
+
+/// Seconds since the epoch for a UTC date; `None` if a field is out of range
+pub fn to_secs(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Option<i64> {
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64)
+}
+
+/// The `ls -l` date column: `Oct 16 10:00` for the last six months,
+/// `Oct 16  2025` otherwise
+pub fn ls_time(secs: i64, now: i64) -> String {
+    let c = civil(secs);
+    if (now - secs).abs() < 183 * 86_400 {
+        format!(
+            "{} {:>2} {:02}:{:02}",
+            c.month_name(),
+            c.day,
+            c.hour,
+            c.minute
+        )
+    } else {
+        format!("{} {:>2}  {}", c.month_name(), c.day, c.year)
+    }
+}
+
+/// `2026-10-16 10:00:00.000000000 +0000` as `stat` prints it
+pub fn stat_time(secs: i64) -> String {
+    let c = civil(secs);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}.000000000 +0000",
+        c.year, c.month, c.day, c.hour, c.minute, c.second
+    )
+}
+
+/// `Thu Oct 16 10:00:00 2026`, as login and `last` print it
+pub fn ctime(secs: i64) -> String {
+    let c = civil(secs);
+    format!(
+        "{} {} {:>2} {:02}:{:02}:{:02} {}",
+        c.weekday_name(),
+        c.month_name(),
+        c.day,
+        c.hour,
+        c.minute,
+        c.second,
+        c.year
+    )
+}
+
+/// `touch -t [[CC]YY]MMDDhhmm[.ss]`
+pub fn parse_touch_stamp(stamp: &str, now: i64) -> Option<i64> {
+    let (digits, seconds) = match stamp.split_once('.') {
+        Some((d, s)) if s.len() == 2 => (d, s.parse().ok()?),
+        Some(_) => return None,
+        None => (stamp, 0),
+    };
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let num = |s: &str| s.parse::<u32>().ok();
+    let (year, rest) = match digits.len() {
+        8 => (civil(now).year, digits),
+        10 => {
+            // Two-digit years 69-99 are 1900s, the rest 2000s
+            let yy = num(&digits[..2])? as i64;
+            (if yy >= 69 { 1900 + yy } else { 2000 + yy }, &digits[2..])
+        }
+        12 => (num(&digits[..4])? as i64, &digits[4..]),
+        _ => return None,
+    };
+    to_secs(
+        year,
+        num(&rest[..2])?,
+        num(&rest[2..4])?,
+        num(&rest[4..6])?,
+        num(&rest[6..8])?,
+        seconds,
+    )
+}
+
+/// `touch -d`: `now`, `@SECONDS`, `YYYY-MM-DD` with an optional
+/// `HH:MM[:SS]` after a space or `T`
+pub fn parse_date(text: &str, now: i64) -> Option<i64> {
+    let text = text.trim();
+    if text == "now" {
+        return Some(now);
+    }
+    if let Some(secs) = text.strip_prefix('@') {
+        return secs.parse().ok();
+    }
+    let (date, time) = match text.split_once([' ', 'T']) {
+        Some((d, t)) => (d, t.trim()),
+        None => (text, "00:00"),
+    };
+    let mut ymd = date.splitn(3, '-');
+    let year = ymd.next()?.parse().ok()?;
+    let month = ymd.next()?.parse().ok()?;
+    let day = ymd.next()?.parse().ok()?;
+    let mut hms = time.splitn(3, ':');
+    let hour = hms.next()?.parse().ok()?;
+    let minute = hms.next()?.parse().ok()?;
+    let second = hms.next().map_or(Some(0), |s| s.parse().ok())?;
+    to_secs(year, month, day, hour, minute, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_round_trip_and_parse() {
+        let secs = to_secs(2026, 10, 16, 10, 5, 9).unwrap();
+        let c = civil(secs);
+        assert_eq!((c.year, c.month, c.day), (2026, 10, 16));
+        assert_eq!((c.hour, c.minute, c.second, c.weekday), (10, 5, 9, 5));
+        assert_eq!(stat_time(secs), "2026-10-16 10:05:09.000000000 +0000");
+        assert_eq!(ctime(secs), "Fri Oct 16 10:05:09 2026");
+        assert_eq!(ls_time(secs, secs + 60), "Oct 16 10:05");
+        assert_eq!(ls_time(secs, secs + 400 * 86_400), "Oct 16  2026");
+
+        assert_eq!(parse_touch_stamp("202610161005.09", 0), Some(secs));
+        assert_eq!(parse_touch_stamp("2610161005.09", 0), Some(secs));
+        assert_eq!(parse_touch_stamp("10161005.09", secs), Some(secs));
+        assert_eq!(parse_touch_stamp("1016", secs), None);
+        assert_eq!(parse_touch_stamp("202613161005", secs), None);
+        assert_eq!(parse_date("2026-10-16 10:05:09", 0), Some(secs));
+        assert_eq!(parse_date("2026-10-16T10:05:09", 0), Some(secs));
+        assert_eq!(parse_date("@42", 0), Some(42));
+        assert_eq!(parse_date("now", 7), Some(7));
+        assert_eq!(parse_date("yesterday", 7), None);
+    }
+}
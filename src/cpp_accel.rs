@@ -70,16 +70,6 @@ unsafe extern "C" {
     fn cpp_b64_encode(input: *const u8, input_len: usize, out: *mut u8) -> usize;
     fn cpp_b64_decoded_max_len(input_len: usize) -> usize;
     fn cpp_b64_decode(input: *const u8, input_len: usize, out: *mut u8) -> usize;
-
-    fn cpp_python_new() -> u32;
-    fn cpp_python_free(id: u32);
-    fn cpp_python_eval(
-        id: u32,
-        code: *const u8,
-        code_len: usize,
-        out: *mut u8,
-        out_cap: usize,
-    ) -> usize;
 }
 
 #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
@@ -390,53 +380,3 @@ pub fn backend_name() -> &'static str {
         "rust"
     }
 }
-
-pub fn python_new() -> u32 {
-    #[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-    {
-        unsafe { cpp_python_new() }
-    }
-
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    {
-        0
-    }
-}
-
-pub fn python_free(id: u32) {
-    #[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-    {
-        unsafe { cpp_python_free(id) };
-    }
-
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    {
-        let _ = id;
-    }
-}
-
-pub fn python_eval(id: u32, code: &str) -> Result<String, String> {
-    #[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-    {
-        let mut out = vec![0u8; 2048];
-        let written =
-            unsafe { cpp_python_eval(id, code.as_ptr(), code.len(), out.as_mut_ptr(), out.len()) };
-        out.truncate(written.min(out.len()));
-        if out.is_empty() {
-            return Ok(String::new());
-        }
-        let status = out[0];
-        let msg = String::from_utf8_lossy(&out[1..]).into_owned();
-        if status == b'0' {
-            Ok(msg)
-        } else {
-            Err(msg)
-        }
-    }
-
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    {
-        let _ = id;
-        Ok(code.to_string())
-    }
-}
@@ -1,42 +1,73 @@
 use std::f64::consts::PI;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{window, AudioContext, Document, HtmlCanvasElement, OscillatorType};
+use web_sys::{window, Document, HtmlCanvasElement, OscillatorType};
 
+use crate::engine::{Camera, MapProvider, Raycaster, Sprite, Texture};
 use crate::physics::{circle_wall_collision, Body, Vec2};
 
-#[cfg(feature = "webgl")]
+use crate::graphics::{Graphics, Renderer};
 use crate::graphics_gl::WebGlGraphics;
 
-#[cfg(not(feature = "webgl"))]
-use crate::graphics::Graphics;
-
-#[cfg(not(feature = "webgl"))]
-type Renderer = Graphics;
-#[cfg(feature = "webgl")]
-type Renderer = WebGlGraphics;
-
 // Game constants
 const MAP_W: usize = 32;
 const MAP_H: usize = 32;
 const TEX_W: usize = 64;
 const TEX_H: usize = 64;
 
-// Number of procedural textures (brick, stone, metal, crate, pillar)
-const NUM_TEXTURES: usize = 5;
+// Number of procedural textures (brick, stone, metal, crate, pillar, exit)
+const NUM_TEXTURES: usize = 6;
 const MONSTER_TEX_W: usize = 32;
 const MONSTER_TEX_H: usize = 32;
 const NUM_MONSTER_TEXTURES: usize = 2; // basic + elite
 
-// Simple CC0-style procedural textures (generated at init) stored RGB
-static mut TEXTURES: [[u8; TEX_W * TEX_H * 3]; NUM_TEXTURES] =
-    [[0u8; TEX_W * TEX_H * 3]; NUM_TEXTURES];
-static mut MONSTER_TEXTURES: [[u8; MONSTER_TEX_W * MONSTER_TEX_H * 3]; NUM_MONSTER_TEXTURES] =
-    [[0u8; MONSTER_TEX_W * MONSTER_TEX_H * 3]; NUM_MONSTER_TEXTURES];
+// Difficulty settings
+#[derive(Clone, Copy, PartialEq)]
+enum Difficulty {
+    Easy,   // Monsters deal 5 damage, player has 150 HP
+    Normal, // Monsters deal 10 damage, player has 100 HP
+    Hard,   // Monsters deal 20 damage, player has 75 HP
+}
 
-fn init_textures() {
-    // Procedural generation ensures no copyright issues (all math-generated)
-    unsafe {
+#[derive(Clone, Copy, PartialEq)]
+enum ControlMode {
+    Human,
+    Bot,
+}
+
+// Wall tile that ends the level when the player walks into it
+const EXIT_TILE: i32 = 5;
+// HUD text is the shared 5x7 font doubled
+const HUD_TEXT_SCALE: u32 = 2;
+
+// Everything a game draws and collides against: the tile map and the
+// procedural textures (all math-generated, so no copyright issues). Owned by
+// the DoomGame, so switching maps never touches another game's world.
+struct GameWorld {
+    map: [i32; MAP_W * MAP_H],
+    // The authored map, kept while `doommap proc` has replaced it
+    original_map: Option<[i32; MAP_W * MAP_H]>,
+    textures: Vec<Texture>,
+    monster_textures: Vec<Texture>,
+}
+
+impl GameWorld {
+    fn new() -> Self {
+        let mut world = GameWorld {
+            map: [0; MAP_W * MAP_H],
+            original_map: None,
+            textures: vec![Texture::new(TEX_W, TEX_H); NUM_TEXTURES],
+            monster_textures: vec![
+                Texture::new(MONSTER_TEX_W, MONSTER_TEX_H);
+                NUM_MONSTER_TEXTURES
+            ],
+        };
+        world.generate_textures();
+        world
+    }
+
+    fn generate_textures(&mut self) {
+        // Procedural generation ensures no copyright issues (all math-generated)
         // 0: Brick wall
         for y in 0..TEX_H {
             for x in 0..TEX_W {
@@ -47,9 +78,9 @@ fn init_textures() {
                 } else {
                     (150 + (x % 16) as u8, 40, 40)
                 };
-                TEXTURES[0][idx] = r;
-                TEXTURES[0][idx + 1] = g;
-                TEXTURES[0][idx + 2] = b;
+                self.textures[0].rgb[idx] = r;
+                self.textures[0].rgb[idx + 1] = g;
+                self.textures[0].rgb[idx + 2] = b;
             }
         }
         // 1: Stone blocks
@@ -57,9 +88,9 @@ fn init_textures() {
             for x in 0..TEX_W {
                 let idx = (y * TEX_W + x) * 3;
                 let shade = 120 + (((x ^ y) & 15) as u8);
-                TEXTURES[1][idx] = shade;
-                TEXTURES[1][idx + 1] = shade - 10;
-                TEXTURES[1][idx + 2] = shade - 20;
+                self.textures[1].rgb[idx] = shade;
+                self.textures[1].rgb[idx + 1] = shade - 10;
+                self.textures[1].rgb[idx + 2] = shade - 20;
             }
         }
         // 2: Metal panel
@@ -68,9 +99,9 @@ fn init_textures() {
                 let idx = (y * TEX_W + x) * 3;
                 let stripe = (x / 8) % 2 == 0;
                 let base = if stripe { 160 } else { 110 };
-                TEXTURES[2][idx] = base;
-                TEXTURES[2][idx + 1] = base;
-                TEXTURES[2][idx + 2] = base + 20;
+                self.textures[2].rgb[idx] = base;
+                self.textures[2].rgb[idx + 1] = base;
+                self.textures[2].rgb[idx + 2] = base + 20;
             }
         }
         // 3: Crate wood
@@ -79,10 +110,10 @@ fn init_textures() {
                 let idx = (y * TEX_W + x) * 3;
                 let grain =
                     ((x as f32 * 0.3).sin() * 10.0) as i32 + ((y as f32 * 0.15).cos() * 8.0) as i32;
-                let base = 100 + (grain.clamp(-20, 30)) as u8;
-                TEXTURES[3][idx] = base + 30;
-                TEXTURES[3][idx + 1] = base + 10;
-                TEXTURES[3][idx + 2] = base;
+                let base = (100 + grain.clamp(-20, 30)) as u8;
+                self.textures[3].rgb[idx] = base + 30;
+                self.textures[3].rgb[idx + 1] = base + 10;
+                self.textures[3].rgb[idx + 2] = base;
             }
         }
         // 4: Pillar marble
@@ -91,9 +122,30 @@ fn init_textures() {
                 let idx = (y * TEX_W + x) * 3;
                 let swirl = ((x as f32 * 0.2).sin() + (y as f32 * 0.3).cos()) * 0.5 + 0.5;
                 let shade = (200.0 * swirl) as u8;
-                TEXTURES[4][idx] = shade;
-                TEXTURES[4][idx + 1] = shade;
-                TEXTURES[4][idx + 2] = shade - 10;
+                self.textures[4].rgb[idx] = shade;
+                self.textures[4].rgb[idx + 1] = shade;
+                self.textures[4].rgb[idx + 2] = shade.saturating_sub(10);
+            }
+        }
+        // 5: Exit switch - hazard-striped frame around a lit green panel
+        for y in 0..TEX_H {
+            for x in 0..TEX_W {
+                let idx = (y * TEX_W + x) * 3;
+                let frame = !(10..TEX_W - 10).contains(&x) || !(10..TEX_H - 10).contains(&y);
+                let (r, g, b) = if frame {
+                    if ((x + y) / 6) % 2 == 0 {
+                        (220, 180, 20)
+                    } else {
+                        (30, 30, 30)
+                    }
+                } else if (28..36).contains(&x) && (18..46).contains(&y) {
+                    (200, 200, 210) // lever
+                } else {
+                    (20, 150 + (y as u8 % 8) * 8, 60)
+                };
+                self.textures[5].rgb[idx] = r;
+                self.textures[5].rgb[idx + 1] = g;
+                self.textures[5].rgb[idx + 2] = b;
             }
         }
         // Monster textures
@@ -106,190 +158,555 @@ fn init_textures() {
                 let r = if edge { 120 } else { 200 - (y as u8 / 2) };
                 let g = if edge { 20 } else { 40 + (x as u8 / 4) };
                 let b = if edge { 20 } else { 30 };
-                MONSTER_TEXTURES[0][idx] = r;
-                MONSTER_TEXTURES[0][idx + 1] = g;
-                MONSTER_TEXTURES[0][idx + 2] = b;
+                self.monster_textures[0].rgb[idx] = r;
+                self.monster_textures[0].rgb[idx + 1] = g;
+                self.monster_textures[0].rgb[idx + 2] = b;
 
                 // Texture 1: elite demon (purple)
                 let idx1 = idx;
                 let r2 = if edge { 80 } else { 150 + ((x ^ y) & 15) as u8 };
                 let g2 = 40 + (y as u8 / 3);
                 let b2 = if edge { 120 } else { 200 - (x as u8 / 2) };
-                MONSTER_TEXTURES[1][idx1] = r2;
-                MONSTER_TEXTURES[1][idx1 + 1] = g2;
-                MONSTER_TEXTURES[1][idx1 + 2] = b2;
+                self.monster_textures[1].rgb[idx1] = r2;
+                self.monster_textures[1].rgb[idx1 + 1] = g2;
+                self.monster_textures[1].rgb[idx1 + 2] = b2;
             }
         }
     }
-}
 
-// Difficulty settings
-#[derive(Clone, Copy, PartialEq)]
-enum Difficulty {
-    Easy,   // Monsters deal 5 damage, player has 150 HP
-    Normal, // Monsters deal 10 damage, player has 100 HP
-    Hard,   // Monsters deal 20 damage, player has 75 HP
-}
-
-#[derive(Clone, Copy, PartialEq)]
-enum ControlMode {
-    Human,
-    Bot,
-}
-
-// Enhanced world map (static mut for runtime initialization)
-static mut WORLD_MAP: [i32; MAP_W * MAP_H] = [0; MAP_W * MAP_H];
+    // Levels 1 and 2 are authored; everything after is procedural
+    fn load_level(&mut self, level: u32) {
+        match level {
+            1 => self.init_world_map(),
+            2 => self.init_warehouse_map(),
+            _ => self.fill_procedural_map(),
+        }
+    }
 
-fn init_world_map() {
-    unsafe {
+    fn init_world_map(&mut self) {
         // Outer walls
         for x in 0..MAP_W {
-            WORLD_MAP[x] = 1;
-            WORLD_MAP[x + (MAP_H - 1) * MAP_W] = 1;
+            self.map[x] = 1;
+            self.map[x + (MAP_H - 1) * MAP_W] = 1;
         }
         for y in 0..MAP_H {
-            WORLD_MAP[y * MAP_W] = 1;
-            WORLD_MAP[MAP_W - 1 + y * MAP_W] = 1;
+            self.map[y * MAP_W] = 1;
+            self.map[MAP_W - 1 + y * MAP_W] = 1;
         }
 
         // Inner structures - rooms and corridors
         // Room 1 (top-left)
         for x in 5..10 {
-            WORLD_MAP[x + 5 * MAP_W] = 2;
-            WORLD_MAP[x + 10 * MAP_W] = 2;
+            self.map[x + 5 * MAP_W] = 2;
+            self.map[x + 10 * MAP_W] = 2;
         }
         for y in 5..10 {
-            WORLD_MAP[5 + y * MAP_W] = 2;
-            WORLD_MAP[10 + y * MAP_W] = 2;
+            self.map[5 + y * MAP_W] = 2;
+            self.map[10 + y * MAP_W] = 2;
         }
-        WORLD_MAP[7 + 10 * MAP_W] = 0; // Door
+        self.map[7 + 10 * MAP_W] = 0; // Door
 
         // Room 2 (top-right)
         for x in 22..28 {
-            WORLD_MAP[x + 5 * MAP_W] = 2;
-            WORLD_MAP[x + 10 * MAP_W] = 2;
+            self.map[x + 5 * MAP_W] = 2;
+            self.map[x + 10 * MAP_W] = 2;
         }
         for y in 5..10 {
-            WORLD_MAP[22 + y * MAP_W] = 2;
-            WORLD_MAP[28 + y * MAP_W] = 2;
+            self.map[22 + y * MAP_W] = 2;
+            self.map[28 + y * MAP_W] = 2;
         }
-        WORLD_MAP[25 + 10 * MAP_W] = 0; // Door
+        self.map[25 + 10 * MAP_W] = 0; // Door
 
         // Room 3 (bottom-left)
         for x in 5..10 {
-            WORLD_MAP[x + 22 * MAP_W] = 2;
-            WORLD_MAP[x + 27 * MAP_W] = 2;
+            self.map[x + 22 * MAP_W] = 2;
+            self.map[x + 27 * MAP_W] = 2;
         }
         for y in 22..27 {
-            WORLD_MAP[5 + y * MAP_W] = 2;
-            WORLD_MAP[10 + y * MAP_W] = 2;
+            self.map[5 + y * MAP_W] = 2;
+            self.map[10 + y * MAP_W] = 2;
         }
-        WORLD_MAP[7 + 22 * MAP_W] = 0; // Door
+        self.map[7 + 22 * MAP_W] = 0; // Door
+        self.map[7 + 27 * MAP_W] = EXIT_TILE; // Exit switch on the back wall
 
         // Room 4 (bottom-right)
         for x in 22..28 {
-            WORLD_MAP[x + 22 * MAP_W] = 2;
-            WORLD_MAP[x + 27 * MAP_W] = 2;
+            self.map[x + 22 * MAP_W] = 2;
+            self.map[x + 27 * MAP_W] = 2;
         }
         for y in 22..27 {
-            WORLD_MAP[22 + y * MAP_W] = 2;
-            WORLD_MAP[28 + y * MAP_W] = 2;
+            self.map[22 + y * MAP_W] = 2;
+            self.map[28 + y * MAP_W] = 2;
         }
-        WORLD_MAP[25 + 22 * MAP_W] = 0; // Door
+        self.map[25 + 22 * MAP_W] = 0; // Door
 
         // Central arena with pillars
         for x in 14..18 {
             for y in 14..18 {
                 if (x == 15 || x == 16) && (y == 15 || y == 16) {
-                    WORLD_MAP[x + y * MAP_W] = 3; // Pillars
+                    self.map[x + y * MAP_W] = 3; // Pillars
                 }
             }
         }
 
         // Scattered crates
-        WORLD_MAP[12 + 8 * MAP_W] = 4;
-        WORLD_MAP[20 + 8 * MAP_W] = 4;
-        WORLD_MAP[12 + 24 * MAP_W] = 4;
-        WORLD_MAP[20 + 24 * MAP_W] = 4;
-        WORLD_MAP[8 + 16 * MAP_W] = 4;
-        WORLD_MAP[24 + 16 * MAP_W] = 4;
+        self.map[12 + 8 * MAP_W] = 4;
+        self.map[20 + 8 * MAP_W] = 4;
+        self.map[12 + 24 * MAP_W] = 4;
+        self.map[20 + 24 * MAP_W] = 4;
+        self.map[8 + 16 * MAP_W] = 4;
+        self.map[24 + 16 * MAP_W] = 4;
     }
-}
 
-use std::sync::Mutex;
-static ORIGINAL_MAP: Mutex<Option<[i32; MAP_W * MAP_H]>> = Mutex::new(None);
+    // Level 2: four bands split by stone walls with offset gaps, so the exit
+    // on the north wall takes a zig-zag through the map to reach
+    #[allow(clippy::needless_range_loop)]
+    fn init_warehouse_map(&mut self) {
+        for i in 0..MAP_W * MAP_H {
+            self.map[i] = 0;
+        }
+        for x in 0..MAP_W {
+            self.map[x] = 1;
+            self.map[x + (MAP_H - 1) * MAP_W] = 1;
+        }
+        for y in 0..MAP_H {
+            self.map[y * MAP_W] = 1;
+            self.map[MAP_W - 1 + y * MAP_W] = 1;
+        }
 
-fn backup_original_map() {
-    let mut guard = ORIGINAL_MAP.lock().unwrap();
-    if guard.is_none() {
-        let current = unsafe { WORLD_MAP };
-        *guard = Some(current);
+        // (row, [gap start, end)) - the spawn row (16) stays open
+        let bands: [(usize, &[(usize, usize)]); 4] = [
+            (6, &[(3, 6)]),
+            (12, &[(14, 18), (26, 30)]),
+            (20, &[(2, 6), (14, 18)]),
+            (26, &[(26, 30)]),
+        ];
+        for (y, gaps) in bands {
+            for x in 1..MAP_W - 1 {
+                if !gaps.iter().any(|&(from, to)| (from..to).contains(&x)) {
+                    self.map[x + y * MAP_W] = 2;
+                }
+            }
+        }
+
+        // Crate stacks and pillars to break up the sight lines
+        for y in [3, 9, 23, 29] {
+            self.map[10 + y * MAP_W] = 4;
+            self.map[22 + y * MAP_W] = 4;
+        }
+        for x in [6, 26] {
+            self.map[x + 14 * MAP_W] = 3;
+            self.map[x + 18 * MAP_W] = 3;
+        }
+
+        self.map[16] = EXIT_TILE;
     }
-}
 
-fn restore_original_map() {
-    if let Some(m) = ORIGINAL_MAP.lock().unwrap().take() {
-        unsafe {
-            WORLD_MAP = m;
+    // Swap in a procedural map, keeping the authored one to restore
+    fn enable_procedural(&mut self) {
+        if self.original_map.is_none() {
+            self.original_map = Some(self.map);
         }
+        self.fill_procedural_map();
     }
-}
 
-#[allow(clippy::needless_range_loop)]
-fn generate_procedural_world() {
-    backup_original_map();
-    unsafe {
+    fn restore_original_map(&mut self) {
+        if let Some(map) = self.original_map.take() {
+            self.map = map;
+        }
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    fn fill_procedural_map(&mut self) {
         for i in 0..MAP_W * MAP_H {
-            WORLD_MAP[i] = 1;
+            self.map[i] = 1;
         }
         for y in 1..MAP_H - 1 {
             for x in 1..MAP_W - 1 {
-                WORLD_MAP[x + y * MAP_W] = 0;
+                self.map[x + y * MAP_W] = 0;
             }
         }
         // pillars
         for _ in 0..40 {
-            let x = 2 + (js_sys::Math::random() * (MAP_W as f64 - 4.0)) as usize;
-            let y = 2 + (js_sys::Math::random() * (MAP_H as f64 - 4.0)) as usize;
-            WORLD_MAP[x + y * MAP_W] = 3;
+            let x = 2 + (random() * (MAP_W as f64 - 4.0)) as usize;
+            let y = 2 + (random() * (MAP_H as f64 - 4.0)) as usize;
+            self.map[x + y * MAP_W] = 3;
         }
         // room borders with crate texture
         for _ in 0..8 {
-            let rw = 4 + (js_sys::Math::random() * 6.0) as usize;
-            let rh = 4 + (js_sys::Math::random() * 6.0) as usize;
-            let rx = 2 + (js_sys::Math::random() * (MAP_W as f64 - rw as f64 - 4.0)) as usize;
-            let ry = 2 + (js_sys::Math::random() * (MAP_H as f64 - rh as f64 - 4.0)) as usize;
+            let rw = 4 + (random() * 6.0) as usize;
+            let rh = 4 + (random() * 6.0) as usize;
+            let rx = 2 + (random() * (MAP_W as f64 - rw as f64 - 4.0)) as usize;
+            let ry = 2 + (random() * (MAP_H as f64 - rh as f64 - 4.0)) as usize;
             for x in rx..rx + rw {
-                WORLD_MAP[x + ry * MAP_W] = 4;
-                WORLD_MAP[x + (ry + rh - 1) * MAP_W] = 4;
+                self.map[x + ry * MAP_W] = 4;
+                self.map[x + (ry + rh - 1) * MAP_W] = 4;
             }
             for y in ry..ry + rh {
-                WORLD_MAP[rx + y * MAP_W] = 4;
-                WORLD_MAP[(rx + rw - 1) + y * MAP_W] = 4;
+                self.map[rx + y * MAP_W] = 4;
+                self.map[(rx + rw - 1) + y * MAP_W] = 4;
             }
         }
         // clear spawn
         for y in 14..18 {
             for x in 14..18 {
-                WORLD_MAP[x + y * MAP_W] = 0;
+                self.map[x + y * MAP_W] = 0;
             }
         }
+        // exit somewhere on the east wall, with the tile in front cleared
+        let y = 2 + (random() * (MAP_H as f64 - 4.0)) as usize;
+        self.map[MAP_W - 1 + y * MAP_W] = EXIT_TILE;
+        self.map[MAP_W - 2 + y * MAP_W] = 0;
+    }
+
+    #[inline(always)]
+    fn tile(&self, x: f64, y: f64) -> i32 {
+        self.cell_at(x, y)
+    }
+
+    // True if no wall stands between the two points
+    fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let delta = to.sub(&from);
+        let distance = delta.length();
+        if distance <= 0.001 {
+            return true;
+        }
+        let dir = delta.scale(1.0 / distance);
+        let result = crate::cpp_accel::raycast_dda_map(
+            from.x,
+            from.y,
+            dir.x,
+            dir.y,
+            distance,
+            &self.map,
+            (MAP_W as i32, MAP_H as i32),
+        );
+        result.hit == 0
     }
 }
 
-#[inline(always)]
-fn tile(x: f64, y: f64) -> i32 {
-    if x >= 0.0 && y >= 0.0 {
-        let xi = x as usize;
-        let yi = y as usize;
-        if xi < MAP_W && yi < MAP_H {
-            unsafe { WORLD_MAP[xi + yi * MAP_W] }
-        } else {
-            1
+impl MapProvider for GameWorld {
+    fn size(&self) -> (usize, usize) {
+        (MAP_W, MAP_H)
+    }
+
+    fn cells(&self) -> &[i32] {
+        &self.map
+    }
+
+    fn wall_texture(&self, cell: i32) -> &Texture {
+        let index = match cell {
+            2 => 1, // stone
+            3 => 4, // pillar marble
+            4 => 3, // crate
+            EXIT_TILE => 5,
+            _ => 0, // brick default
+        };
+        &self.textures[index]
+    }
+}
+
+// How often monsters' route to the player is rebuilt even if the player
+// stays on the same tile (maps can change under `doommap proc`)
+const NAV_REFRESH_MS: f64 = 500.0;
+
+// Breadth-first distance (in steps) from every open tile to the player's
+// tile. Rebuilt when the player changes tile, and shared by all monsters:
+// each one walks to whichever neighbouring tile is closer to the goal.
+struct NavField {
+    goal: (i32, i32),
+    steps: Vec<u16>,
+    computed_at: f64,
+}
+
+impl NavField {
+    const UNREACHABLE: u16 = u16::MAX;
+    const NEIGHBOURS: [(i32, i32); 8] = [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (1, -1),
+        (-1, 1),
+        (-1, -1),
+    ];
+
+    fn empty() -> Self {
+        NavField {
+            goal: (-1, -1),
+            steps: vec![Self::UNREACHABLE; MAP_W * MAP_H],
+            computed_at: f64::NEG_INFINITY,
+        }
+    }
+
+    fn open(world: &GameWorld, x: i32, y: i32) -> bool {
+        world.tile(x as f64, y as f64) == 0
+    }
+
+    // Diagonal moves only where both orthogonal tiles are open, so nothing
+    // tries to squeeze through a wall corner
+    fn can_step(world: &GameWorld, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+        Self::open(world, x + dx, y + dy)
+            && (dx == 0
+                || dy == 0
+                || (Self::open(world, x + dx, y) && Self::open(world, x, y + dy)))
+    }
+
+    fn towards(world: &GameWorld, goal: (i32, i32), now: f64) -> Self {
+        let mut field = NavField {
+            goal,
+            computed_at: now,
+            ..Self::empty()
+        };
+        // The player can stand partly inside a solid tile (the spawn point
+        // is a pillar), so the goal itself isn't required to be open
+        if !(0..MAP_W as i32).contains(&goal.0) || !(0..MAP_H as i32).contains(&goal.1) {
+            return field;
+        }
+        let mut queue = std::collections::VecDeque::new();
+        field.steps[goal.0 as usize + goal.1 as usize * MAP_W] = 0;
+        queue.push_back(goal);
+        while let Some((x, y)) = queue.pop_front() {
+            let next = field.steps[x as usize + y as usize * MAP_W] + 1;
+            for (dx, dy) in Self::NEIGHBOURS {
+                if !Self::can_step(world, x, y, dx, dy) {
+                    continue;
+                }
+                let idx = (x + dx) as usize + (y + dy) as usize * MAP_W;
+                if field.steps[idx] == Self::UNREACHABLE {
+                    field.steps[idx] = next;
+                    queue.push_back((x + dx, y + dy));
+                }
+            }
+        }
+        field
+    }
+
+    fn steps_at(&self, x: i32, y: i32) -> u16 {
+        if x < 0 || y < 0 || x >= MAP_W as i32 || y >= MAP_H as i32 {
+            return Self::UNREACHABLE;
+        }
+        self.steps[x as usize + y as usize * MAP_W]
+    }
+
+    // Centre of the neighbouring tile one step closer to the goal
+    fn next_waypoint(&self, world: &GameWorld, pos: Vec2) -> Option<Vec2> {
+        let (x, y) = (pos.x as i32, pos.y as i32);
+        let here = self.steps_at(x, y);
+        Self::NEIGHBOURS
+            .iter()
+            .filter(|&&(dx, dy)| Self::can_step(world, x, y, dx, dy))
+            .map(|&(dx, dy)| (self.steps_at(x + dx, y + dy), dx, dy))
+            .filter(|&(steps, _, _)| steps < here)
+            .min_by_key(|&(steps, _, _)| steps)
+            .map(|(_, dx, dy)| Vec2::new((x + dx) as f64 + 0.5, (y + dy) as f64 + 0.5))
+    }
+}
+
+// Game RNG (xorshift32). Seeded per game so a demo can replay the same
+// spawns, spread and particles.
+fn random() -> f64 {
+    RNG_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        state.set(x);
+        x as f64 / (u32::MAX as f64 + 1.0)
+    })
+}
+
+fn seed_random(seed: u32) {
+    // xorshift never leaves zero
+    RNG_STATE.with(|state| state.set(seed.max(1)));
+}
+
+// Demo files: "KPDM", version, start_doom_with_difficulty code and RNG seed,
+// then one fixed-size record per frame until the game ended
+const DEMO_MAGIC: &[u8; 4] = b"KPDM";
+const DEMO_VERSION: u8 = 1;
+const DEMO_HEADER_LEN: usize = 10;
+
+// Keys a demo records, in bit order: WSADQE, arrows, space, enter, 1-4
+const DEMO_KEYS: [usize; 16] = [
+    87, 83, 65, 68, 81, 69, 38, 40, 37, 39, 32, 13, 49, 50, 51, 52,
+];
+
+// Everything the game reads from the player in one frame
+#[derive(Clone, Copy, Default)]
+struct FrameInput {
+    dt: f32,
+    keys: u16,
+    // Pointer-lock movement since the last frame
+    mouse_dx: i16,
+    wheel: i8,
+    clicked: bool,
+    held: bool,
+}
+
+impl FrameInput {
+    const SIZE: usize = 10;
+
+    // Sample (and consume) the live keyboard and mouse state
+    fn live(dt: f64) -> Self {
+        let keys = KEYS.with(|k| {
+            let k = k.borrow();
+            DEMO_KEYS
+                .iter()
+                .enumerate()
+                .filter(|&(_, &code)| k[code])
+                .fold(0u16, |bits, (bit, _)| bits | 1 << bit)
+        });
+        let keys = keys | TOUCH.with(|t| t.borrow().stick_keys());
+        FrameInput {
+            dt: dt as f32,
+            keys,
+            mouse_dx: MOUSE_DELTA_X.with(|md| md.replace(0.0)).round() as i16,
+            wheel: WHEEL_STEPS.with(|w| w.replace(0)).clamp(-1, 1) as i8,
+            clicked: MOUSE_CLICKED.with(|mc| mc.replace(false)),
+            held: MOUSE_HELD.with(|mh| mh.get()),
+        }
+    }
+
+    fn key(&self, code: usize) -> bool {
+        DEMO_KEYS
+            .iter()
+            .position(|&k| k == code)
+            .is_some_and(|bit| self.keys & (1 << bit) != 0)
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.dt.to_le_bytes());
+        out.extend_from_slice(&self.keys.to_le_bytes());
+        out.extend_from_slice(&self.mouse_dx.to_le_bytes());
+        out.push(self.wheel as u8);
+        out.push(self.clicked as u8 | (self.held as u8) << 1);
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        FrameInput {
+            dt: f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            keys: u16::from_le_bytes([bytes[4], bytes[5]]),
+            mouse_dx: i16::from_le_bytes([bytes[6], bytes[7]]),
+            wheel: bytes[8] as i8,
+            clicked: bytes[9] & 1 != 0,
+            held: bytes[9] & 2 != 0,
         }
+    }
+}
+
+// A demo being replayed: the frame records and the next one to play
+struct Playback {
+    frames: Vec<u8>,
+    next: usize,
+}
+
+// On-screen ESC button for touch screens, in canvas pixels (x, y, w, h)
+const TOUCH_ESC_BUTTON: (f64, f64, f64, f64) = (20.0, 64.0, 64.0, 32.0);
+// Joystick travel (px) before it counts as a direction
+const TOUCH_DEAD_ZONE: f64 = 14.0;
+// Drag distance is scaled up to match a mouse's turn speed
+const TOUCH_TURN_SCALE: f64 = 2.5;
+// A touch on the turn side shorter and stiller than this fires
+const TOUCH_TAP_MS: f64 = 250.0;
+const TOUCH_TAP_SLOP: f64 = 10.0;
+
+type CanvasPoint = (f64, f64);
+
+// Touch controls: the left half of the canvas is a joystick, the right half
+// turns by dragging and fires on tap. Positions are in canvas pixels.
+#[derive(Default)]
+struct TouchState {
+    installed: bool,
+    // Set once a touch arrives, so desktop players never see the overlay
+    seen: bool,
+    // (touch id, where it went down, where it is now)
+    stick: Option<(i32, CanvasPoint, CanvasPoint)>,
+    // (touch id, last x, down at ms, total travel)
+    look: Option<(i32, f64, f64, f64)>,
+}
+
+impl TouchState {
+    // The joystick as movement keys, in FrameInput bit order
+    fn stick_keys(&self) -> u16 {
+        let Some((_, (ox, oy), (x, y))) = self.stick else {
+            return 0;
+        };
+        let (dx, dy) = (x - ox, y - oy);
+        [
+            (87, dy < -TOUCH_DEAD_ZONE),
+            (83, dy > TOUCH_DEAD_ZONE),
+            (65, dx < -TOUCH_DEAD_ZONE),
+            (68, dx > TOUCH_DEAD_ZONE),
+        ]
+        .iter()
+        .filter(|&&(_, on)| on)
+        .filter_map(|&(code, _)| DEMO_KEYS.iter().position(|&k| k == code))
+        .fold(0, |bits, bit| bits | 1 << bit)
+    }
+}
+
+/// Which renderer doom draws with
+#[derive(Clone, Copy, PartialEq)]
+pub enum RendererKind {
+    /// 2d canvas fed from a software frame buffer
+    Soft,
+    /// Frame buffer uploaded as a WebGL2 texture
+    Gl,
+}
+
+impl RendererKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "soft" | "software" | "2d" => Some(RendererKind::Soft),
+            "gl" | "webgl" => Some(RendererKind::Gl),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RendererKind::Soft => "soft",
+            RendererKind::Gl => "gl",
+        }
+    }
+}
+
+/// Pick the renderer for games started from now on
+pub fn set_renderer(kind: RendererKind) {
+    RENDERER_PREF.with(|r| r.set(kind));
+    GL_FAILED.with(|f| f.set(false));
+}
+
+/// The chosen renderer, noting when GL wasn't available last time
+pub fn renderer_status() -> String {
+    let kind = RENDERER_PREF.with(|r| r.get());
+    if kind == RendererKind::Gl && GL_FAILED.with(|f| f.get()) {
+        "gl (WebGL unavailable last run, fell back to soft)".to_string()
     } else {
-        1
+        kind.name().to_string()
+    }
+}
+
+/// Draw `frames` frames of a fresh level into an offscreen `width` x
+/// `height` buffer, turning a little each frame so the walls change, and
+/// return the milliseconds it took. A game in progress keeps its state and
+/// its RNG
+pub fn benchmark_render(width: u32, height: u32, frames: u32) -> f64 {
+    let saved_rng = RNG_STATE.with(|state| state.get());
+    seed_random(1);
+    let mut game = DoomGame::new(Difficulty::Normal, ControlMode::Bot);
+    let mut gfx = crate::graphics::Offscreen::new(width, height);
+    let start = crate::clock::now_ms();
+    for _ in 0..frames {
+        game.rotate(PI / 90.0);
+        game.render(&mut gfx);
+        let _ = gfx.present();
     }
+    let elapsed = crate::clock::now_ms() - start;
+    RNG_STATE.with(|state| state.set(saved_rng));
+    elapsed
 }
 
 type LoopClosure = std::cell::RefCell<Option<Closure<dyn FnMut(f64)>>>;
@@ -297,16 +714,33 @@ type ResizeClosure = std::cell::RefCell<Option<Closure<dyn FnMut(web_sys::Event)
 
 thread_local! {
     static GAME: std::cell::RefCell<Option<DoomGame>> = const { std::cell::RefCell::new(None) };
-    static GFX: std::cell::RefCell<Option<Renderer>> = const { std::cell::RefCell::new(None) };
+    static GFX: std::cell::RefCell<Option<Box<dyn Renderer>>> = const { std::cell::RefCell::new(None) };
+    // Renderer the next game asks for; `webgl` builds default to GL
+    static RENDERER_PREF: std::cell::Cell<RendererKind> = const {
+        std::cell::Cell::new(if cfg!(feature = "webgl") { RendererKind::Gl } else { RendererKind::Soft })
+    };
+    // Context the canvas element already handed out (it can only ever have one kind)
+    static CANVAS_CONTEXT: std::cell::Cell<Option<RendererKind>> = const { std::cell::Cell::new(None) };
+    // The last GL request fell back to software
+    static GL_FAILED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
     static LOOP: LoopClosure = const { std::cell::RefCell::new(None) };
     static KEYS: std::cell::RefCell<[bool; 256]> = const { std::cell::RefCell::new([false;256]) };
     static MOUSE_DELTA_X: std::cell::Cell<f64> = const { std::cell::Cell::new(0.0) };
     static MOUSE_CLICKED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    static MOUSE_HELD: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    static WHEEL_STEPS: std::cell::Cell<i32> = const { std::cell::Cell::new(0) };
+    static TOUCH: std::cell::RefCell<TouchState> = std::cell::RefCell::new(TouchState::default());
+    static RNG_STATE: std::cell::Cell<u32> = const { std::cell::Cell::new(0x2545_f491) };
+    // Set by doom_start_recording; the next game started records into RECORDING
+    static RECORD_ARMED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    static RECORDING: std::cell::RefCell<Option<Vec<u8>>> = const { std::cell::RefCell::new(None) };
+    static PLAYBACK: std::cell::RefCell<Option<Playback>> = const { std::cell::RefCell::new(None) };
     static RESIZE_CB: ResizeClosure = const { std::cell::RefCell::new(None) };
     static STOPPING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
-    static AUDIO_CTX: std::cell::RefCell<Option<AudioContext>> = const { std::cell::RefCell::new(None) };
 }
 
+type ParticleSpawn = (Vec2, Vec2, (u8, u8, u8), f64);
+
 #[derive(Clone)]
 struct Monster {
     body: Body,
@@ -325,10 +759,117 @@ enum MonsterState {
     Dead,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Weapon {
+    Pistol,
+    Shotgun,
+    Chaingun,
+    RocketLauncher,
+}
+
+// Seconds of holding fire before the chaingun reaches full speed
+const CHAINGUN_SPIN_UP: f64 = 0.6;
+
+impl Weapon {
+    const ALL: [Weapon; 4] = [
+        Weapon::Pistol,
+        Weapon::Shotgun,
+        Weapon::Chaingun,
+        Weapon::RocketLauncher,
+    ];
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Weapon::Pistol => "PISTOL",
+            Weapon::Shotgun => "SHOTGUN",
+            Weapon::Chaingun => "CHAINGUN",
+            Weapon::RocketLauncher => "ROCKET",
+        }
+    }
+
+    // Pistol stays free so the player never hard locks
+    fn ammo_cost(self) -> i32 {
+        match self {
+            Weapon::Pistol => 0,
+            Weapon::Shotgun => 2,
+            Weapon::Chaingun => 1,
+            Weapon::RocketLauncher => 5,
+        }
+    }
+
+    // Damage per projectile (shotgun pellets, rocket direct hit)
+    fn damage(self) -> i32 {
+        match self {
+            Weapon::Pistol => 25,
+            Weapon::Shotgun => 12,
+            Weapon::Chaingun => 15,
+            Weapon::RocketLauncher => 100,
+        }
+    }
+
+    fn pellets(self) -> u32 {
+        if self == Weapon::Shotgun {
+            7
+        } else {
+            1
+        }
+    }
+
+    // Max angle (radians) a projectile strays from the aim
+    fn spread(self) -> f64 {
+        match self {
+            Weapon::Shotgun => 0.12,
+            Weapon::Chaingun => 0.035,
+            _ => 0.0,
+        }
+    }
+
+    fn projectile_speed(self) -> f64 {
+        if self == Weapon::RocketLauncher {
+            12.0
+        } else {
+            20.0
+        }
+    }
+
+    // Explosion radius in map tiles, 0 for none
+    fn splash_radius(self) -> f64 {
+        if self == Weapon::RocketLauncher {
+            2.5
+        } else {
+            0.0
+        }
+    }
+
+    fn sound(self) -> f64 {
+        match self {
+            Weapon::Pistol => 440.0,
+            Weapon::Shotgun => 330.0,
+            Weapon::Chaingun => 520.0,
+            Weapon::RocketLauncher => 110.0,
+        }
+    }
+
+    // Pickup and projectile color
+    fn color(self) -> (u8, u8, u8) {
+        match self {
+            Weapon::Pistol => (255, 255, 0),
+            Weapon::Shotgun => (255, 220, 120),
+            Weapon::Chaingun => (255, 160, 40),
+            Weapon::RocketLauncher => (255, 80, 40),
+        }
+    }
+}
+
 struct Projectile {
     body: Body,
     damage: i32,
     lifetime: f64,
+    weapon: Weapon,
 }
 
 struct Particle {
@@ -339,6 +880,15 @@ struct Particle {
     max_lifetime: f64,
 }
 
+// Stats shown between levels
+struct Intermission {
+    level: u32,
+    kills: u32,
+    monsters: u32,
+    seconds: f64,
+    shown_at: f64,
+}
+
 #[derive(Clone)]
 struct FpsStats {
     average_fps: f64,
@@ -356,13 +906,24 @@ struct DoomGame {
     health: i32,
     max_health: i32,
     ammo: i32,
-    current_weapon: u8,
+    current_weapon: Weapon,
+    weapons_owned: [bool; 4],
+    // 0.0 (idle) to 1.0 (full speed)
+    chaingun_spin: f64,
 
     // Game state
     difficulty: Difficulty,
     score: u32,
     kills: u32,
 
+    // Level progression
+    level: u32,
+    levels_completed: u32,
+    level_start_time: f64,
+    level_start_kills: u32,
+    level_monsters: u32,
+    intermission: Option<Intermission>,
+
     // Entities
     monsters: Vec<Monster>,
     projectiles: Vec<Projectile>,
@@ -381,8 +942,13 @@ struct DoomGame {
     // Day/night cycle (0.0 = midnight, 0.5 = noon, 1.0 = midnight)
     time_of_day: f64,
 
+    // Monster routing towards the player
+    nav: NavField,
+    world: GameWorld,
+
     // Ammo pickups
     ammo_pickups: Vec<Vec2>,
+    weapon_pickups: Vec<(Vec2, Weapon)>,
     last_ammo_spawn_time: f64,
     procedural: bool,
     control_mode: ControlMode,
@@ -390,8 +956,8 @@ struct DoomGame {
 
 impl DoomGame {
     fn new(difficulty: Difficulty, control_mode: ControlMode) -> Self {
-        init_world_map();
-        init_textures();
+        let mut world = GameWorld::new();
+        world.load_level(1);
 
         let (max_health, _damage_mult) = match difficulty {
             Difficulty::Easy => (150, 0.5),
@@ -402,38 +968,26 @@ impl DoomGame {
         let mut player_body = Body::new(16.0, 16.0, 0.3);
         player_body.friction = 0.1; // Reduced friction for better movement
 
-        let mut monsters = Vec::with_capacity(50);
-
-        // Spawn initial monsters based on difficulty
-        let initial_count = match difficulty {
-            Difficulty::Easy => 3,
-            Difficulty::Normal => 5,
-            Difficulty::Hard => 8,
-        };
-
-        for i in 0..initial_count {
-            let angle = (i as f64 / initial_count as f64) * 2.0 * PI;
-            let dist = 8.0;
-            let x = 16.0 + angle.cos() * dist;
-            let y = 16.0 + angle.sin() * dist;
-
-            if tile(x, y) == 0 {
-                monsters.push(Monster::new(x, y, 0, difficulty));
-            }
-        }
-
-        DoomGame {
+        let mut game = DoomGame {
             player_body,
             dir: Vec2::new(-1.0, 0.0),
             plane: Vec2::new(0.0, 0.66),
             health: max_health,
             max_health,
             ammo: 50,
-            current_weapon: 0,
+            current_weapon: Weapon::Pistol,
+            weapons_owned: [true, true, false, false],
+            chaingun_spin: 0.0,
             difficulty,
             score: 0,
             kills: 0,
-            monsters,
+            level: 1,
+            levels_completed: 0,
+            level_start_time: 0.0,
+            level_start_kills: 0,
+            level_monsters: 0,
+            intermission: None,
+            monsters: Vec::with_capacity(50),
             projectiles: Vec::with_capacity(50),
             particles: Vec::with_capacity(100),
             last_shot_time: 0.0,
@@ -451,37 +1005,175 @@ impl DoomGame {
                 frame_time_history: Vec::new(),
             },
             time_of_day: 0.25, // Start at dawn
+            nav: NavField::empty(),
+            world,
             ammo_pickups: Vec::new(),
+            weapon_pickups: Vec::new(),
             last_ammo_spawn_time: 0.0,
             procedural: false,
             control_mode,
+        };
+        game.spawn_initial_monsters();
+        game.place_weapon_pickups();
+        game
+    }
+
+    // A ring of monsters around the spawn point, more on harder settings
+    fn spawn_initial_monsters(&mut self) {
+        let initial_count = match self.difficulty {
+            Difficulty::Easy => 3,
+            Difficulty::Normal => 5,
+            Difficulty::Hard => 8,
+        };
+
+        for i in 0..initial_count {
+            let angle = (i as f64 / initial_count as f64) * 2.0 * PI;
+            let dist = 8.0;
+            let x = 16.0 + angle.cos() * dist;
+            let y = 16.0 + angle.sin() * dist;
+
+            if self.world.tile(x, y) == 0 {
+                self.monsters.push(Monster::new(x, y, 0, self.difficulty));
+                self.level_monsters += 1;
+            }
         }
     }
 
+    // Walking into the exit switch: within reach of an exit tile next to us
+    fn touching_exit(&self) -> bool {
+        let pos = self.player_body.position;
+        let reach = self.player_body.radius + 0.15;
+        [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|(dx, dy)| {
+            let tx = (pos.x as i32 + dx) as f64;
+            let ty = (pos.y as i32 + dy) as f64;
+            if self.world.tile(tx, ty) != EXIT_TILE {
+                return false;
+            }
+            let nearest = Vec2::new(pos.x.clamp(tx, tx + 1.0), pos.y.clamp(ty, ty + 1.0));
+            pos.distance_to(&nearest) < reach
+        })
+    }
+
+    fn finish_level(&mut self) {
+        play_sound(880.0, 0.3);
+        self.levels_completed += 1;
+        self.score += 500 * self.level;
+        self.intermission = Some(Intermission {
+            level: self.level,
+            kills: self.kills - self.level_start_kills,
+            monsters: self.level_monsters,
+            seconds: self.game_time - self.level_start_time,
+            shown_at: self.game_time * 1000.0,
+        });
+    }
+
+    // Load the next map; score, ammo, weapons and health carry over
+    fn advance_level(&mut self) {
+        self.level += 1;
+        self.world.load_level(self.level);
+
+        let mut player_body = Body::new(16.0, 16.0, 0.3);
+        player_body.friction = 0.1;
+        self.player_body = player_body;
+        self.dir = Vec2::new(-1.0, 0.0);
+        self.plane = Vec2::new(0.0, 0.66);
+        self.health = (self.health + 25).min(self.max_health);
+
+        self.monsters.clear();
+        self.projectiles.clear();
+        self.particles.clear();
+        self.ammo_pickups.clear();
+        self.level_start_time = self.game_time;
+        self.level_start_kills = self.kills;
+        self.level_monsters = 0;
+        self.nav = NavField::empty();
+        self.last_spawn_time = self.game_time * 1000.0;
+        self.spawn_initial_monsters();
+        self.place_weapon_pickups();
+        self.intermission = None;
+    }
+
+    // One line for the terminal once the game closes
+    fn summary(&self) -> String {
+        let outcome = if self.health <= 0 { "killed" } else { "quit" };
+        format!(
+            "doom: {} on level {} after clearing {} level{} - score {}, {} kills",
+            outcome,
+            self.level,
+            self.levels_completed,
+            if self.levels_completed == 1 { "" } else { "s" },
+            self.score,
+            self.kills
+        )
+    }
+
     fn enable_procedural(&mut self) {
         self.procedural = true;
-        generate_procedural_world();
+        self.world.enable_procedural();
+        self.place_weapon_pickups();
     }
 
-    fn has_line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
-        let delta = to.sub(&from);
-        let distance = delta.length();
-        if distance <= 0.001 {
-            return true;
+    // Chaingun in the top-left room, rocket launcher in the bottom-right one;
+    // anywhere free if the map has walls there
+    fn place_weapon_pickups(&mut self) {
+        self.weapon_pickups.clear();
+        let spots = [
+            (Vec2::new(7.5, 7.5), Weapon::Chaingun),
+            (Vec2::new(25.5, 24.5), Weapon::RocketLauncher),
+        ];
+        for (spot, weapon) in spots {
+            if self.weapons_owned[weapon.slot()] {
+                continue;
+            }
+            let mut pos = spot;
+            for _ in 0..20 {
+                if self.world.tile(pos.x, pos.y) == 0 {
+                    self.weapon_pickups.push((pos, weapon));
+                    break;
+                }
+                pos = Vec2::new(
+                    2.0 + random() * (MAP_W as f64 - 4.0),
+                    2.0 + random() * (MAP_H as f64 - 4.0),
+                );
+            }
         }
+    }
 
-        let steps = (distance / 0.25).ceil().max(1.0) as i32;
-        for step in 1..steps {
-            let t = step as f64 / steps as f64;
-            let sample = from.lerp(&to, t);
-            if tile(sample.x, sample.y) > 0 {
-                return false;
+    fn select_weapon(&mut self, weapon: Weapon) {
+        if self.weapons_owned[weapon.slot()] && self.ammo >= weapon.ammo_cost() {
+            self.current_weapon = weapon;
+        }
+    }
+
+    // Mouse wheel: next (step > 0) or previous owned weapon with ammo
+    fn cycle_weapon(&mut self, step: i32) {
+        let count = Weapon::ALL.len() as i32;
+        let mut slot = self.current_weapon.slot() as i32;
+        for _ in 1..count {
+            slot = (slot + step).rem_euclid(count);
+            let weapon = Weapon::ALL[slot as usize];
+            if self.weapons_owned[slot as usize] && self.ammo >= weapon.ammo_cost() {
+                self.current_weapon = weapon;
+                return;
             }
         }
-        true
     }
 
-    fn build_ai_command(&self, now: f64) -> (Vec2, f64, bool, u8) {
+    fn fire_interval(&self) -> f64 {
+        match self.current_weapon {
+            Weapon::Pistol => 250.0,
+            Weapon::Shotgun => 700.0,
+            // Spins from pistol speed up to ~14 shots a second
+            Weapon::Chaingun => 250.0 - 180.0 * self.chaingun_spin,
+            Weapon::RocketLauncher => 800.0,
+        }
+    }
+
+    fn has_line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.world.line_of_sight(from, to)
+    }
+
+    fn build_ai_command(&self, now: f64) -> (Vec2, f64, bool, Weapon) {
         let player_pos = self.player_body.position;
         let facing = self.dir.normalize();
         let mut visible_choice: Option<(Vec2, f64)> = None;
@@ -573,30 +1265,38 @@ impl DoomGame {
         }
 
         let ahead = player_pos.add(&facing.scale(0.75));
-        if tile(ahead.x, ahead.y) > 0 {
+        if self.world.tile(ahead.x, ahead.y) > 0 {
             move_force = move_force.add(&strafe_dir.scale(14.0));
             move_force = move_force.sub(&facing.scale(6.0));
             turn += 0.05 * if angle_error >= 0.0 { 1.0 } else { -1.0 };
         }
 
-        let preferred_weapon = if target_visible && target_dist < 8.0 && self.ammo >= 2 {
-            1
+        let owned = |weapon: Weapon| {
+            self.weapons_owned[weapon.slot()] && self.ammo >= weapon.ammo_cost() + 10
+        };
+        let preferred_weapon = if !target_visible {
+            Weapon::Pistol
+        } else if target_dist > 4.0 && owned(Weapon::RocketLauncher) {
+            // Far enough not to be caught in the blast
+            Weapon::RocketLauncher
+        } else if target_dist < 6.0 && owned(Weapon::Shotgun) {
+            Weapon::Shotgun
+        } else if owned(Weapon::Chaingun) {
+            Weapon::Chaingun
         } else {
-            0
+            Weapon::Pistol
         };
 
         let should_shoot = target_visible
             && target_dist < 18.0
             && angle_error.abs() < 0.16
-            && now - self.last_shot_time > 220.0
-            && self.ammo > 0;
+            && now - self.last_shot_time > 220.0;
 
         (move_force, turn, should_shoot, preferred_weapon)
     }
 
-    fn update(&mut self, dt: f64) -> bool {
-        type ParticleSpawn = (Vec2, Vec2, (u8, u8, u8), f64);
-
+    fn update(&mut self, input: &FrameInput) -> bool {
+        let dt = input.dt as f64;
         self.game_time += dt;
 
         // Update FPS counter with detailed statistics
@@ -632,7 +1332,23 @@ impl DoomGame {
             return true;
         }
 
-        let now = js_sys::Date::now();
+        // Game clock in ms; wall-clock time would make demos diverge
+        let now = self.game_time * 1000.0;
+
+        // Between levels: wait for SPACE/ENTER (the bot just waits)
+        if let Some(stats) = &self.intermission {
+            let waited = now - stats.shown_at;
+            let proceed = match self.control_mode {
+                ControlMode::Human => {
+                    waited > 1000.0 && (input.key(32) || input.key(13) || input.clicked)
+                }
+                ControlMode::Bot => waited > 3000.0,
+            };
+            if proceed {
+                self.advance_level();
+            }
+            return false;
+        }
 
         // Player movement with physics
         let move_force = 20.0; // Reduced for better control
@@ -641,44 +1357,47 @@ impl DoomGame {
 
         match self.control_mode {
             ControlMode::Human => {
-                KEYS.with(|k| {
-                    let keys = k.borrow();
-                    let forward = self.dir;
-                    let left = Vec2::new(-self.dir.y, self.dir.x); // left normal
-                    let right = Vec2::new(self.dir.y, -self.dir.x); // right normal
-                    let strafe_force = move_force * 0.7;
-
-                    if keys[38] || keys[87] {
-                        force = force.add(&forward.scale(move_force));
-                    }
-                    if keys[40] || keys[83] {
-                        force = force.sub(&forward.scale(move_force));
-                    }
-                    if keys[65] || keys[81] {
-                        force = force.add(&left.scale(strafe_force));
-                    }
-                    if keys[68] || keys[69] {
-                        force = force.add(&right.scale(strafe_force));
-                    }
-                    if keys[37] {
-                        self.rotate(0.08); // Left arrow - turn left
-                    }
-                    if keys[39] {
-                        self.rotate(-0.08); // Right arrow - turn right
-                    }
-                    if keys[49] {
-                        self.current_weapon = 0;
-                    }
-                    if keys[50] && self.ammo >= 2 {
-                        self.current_weapon = 1;
+                let forward = self.dir;
+                let left = Vec2::new(-self.dir.y, self.dir.x); // left normal
+                let right = Vec2::new(self.dir.y, -self.dir.x); // right normal
+                let strafe_force = move_force * 0.7;
+
+                if input.key(38) || input.key(87) {
+                    force = force.add(&forward.scale(move_force));
+                }
+                if input.key(40) || input.key(83) {
+                    force = force.sub(&forward.scale(move_force));
+                }
+                if input.key(65) || input.key(81) {
+                    force = force.add(&left.scale(strafe_force));
+                }
+                if input.key(68) || input.key(69) {
+                    force = force.add(&right.scale(strafe_force));
+                }
+                if input.key(37) {
+                    self.rotate(0.08); // Left arrow - turn left
+                }
+                if input.key(39) {
+                    self.rotate(-0.08); // Right arrow - turn right
+                }
+                if input.mouse_dx != 0 {
+                    self.rotate(-input.mouse_dx as f64 * 0.002); // Pointer lock sensitivity
+                }
+                // Number keys 1-4
+                for weapon in Weapon::ALL {
+                    if input.key(49 + weapon.slot()) {
+                        self.select_weapon(weapon);
                     }
-                });
+                }
+                if input.wheel != 0 {
+                    self.cycle_weapon(input.wheel as i32);
+                }
             }
             ControlMode::Bot => {
                 let (ai_force, ai_turn, ai_shoot, ai_weapon) = self.build_ai_command(now);
                 force = ai_force;
                 self.rotate(ai_turn);
-                self.current_weapon = ai_weapon;
+                self.select_weapon(ai_weapon);
                 shoot = ai_shoot;
             }
         }
@@ -697,9 +1416,9 @@ impl DoomGame {
         {
             // Find a free tile
             for _ in 0..20 {
-                let x = 2.0 + js_sys::Math::random() * (MAP_W as f64 - 4.0);
-                let y = 2.0 + js_sys::Math::random() * (MAP_H as f64 - 4.0);
-                if tile(x, y) == 0 {
+                let x = 2.0 + random() * (MAP_W as f64 - 4.0);
+                let y = 2.0 + random() * (MAP_H as f64 - 4.0);
+                if self.world.tile(x, y) == 0 {
                     self.ammo_pickups.push(Vec2::new(x, y));
                     self.last_ammo_spawn_time = self.game_time;
                     break;
@@ -717,6 +1436,21 @@ impl DoomGame {
                 true
             }
         });
+        let mut picked_up = None;
+        self.weapon_pickups.retain(|(p, weapon)| {
+            if self.player_body.position.distance_to(p) < 0.6 {
+                picked_up = Some(*weapon);
+                false
+            } else {
+                true
+            }
+        });
+        if let Some(weapon) = picked_up {
+            self.weapons_owned[weapon.slot()] = true;
+            self.ammo = (self.ammo + 20).min(150);
+            self.current_weapon = weapon;
+            play_sound(660.0, 0.15);
+        }
 
         // Passive ammo trickle if completely dry (avoid soft-lock)
         if self.ammo == 0 && (self.game_time as i32 % 1000) < 16 {
@@ -726,15 +1460,20 @@ impl DoomGame {
 
         // Shooting
         if let ControlMode::Human = self.control_mode {
-            shoot = KEYS.with(|k| k.borrow()[32])
-                || MOUSE_CLICKED.with(|mc| {
-                    let clicked = mc.get();
-                    mc.set(false);
-                    clicked
-                });
+            shoot = input.key(32) || input.held || input.clicked;
+        }
+
+        let cost = self.current_weapon.ammo_cost();
+        if self.current_weapon == Weapon::Chaingun && shoot && self.ammo >= cost {
+            self.chaingun_spin = (self.chaingun_spin + dt / CHAINGUN_SPIN_UP).min(1.0);
+        } else {
+            self.chaingun_spin = (self.chaingun_spin - 2.0 * dt / CHAINGUN_SPIN_UP).max(0.0);
         }
+        // The barrels have to get going before the first round leaves
+        let spun_up = self.current_weapon != Weapon::Chaingun || self.chaingun_spin >= 0.3;
 
-        if shoot && now - self.last_shot_time > 250.0 && self.ammo > 0 {
+        if shoot && spun_up && now - self.last_shot_time > self.fire_interval() && self.ammo >= cost
+        {
             self.shoot(now);
         }
 
@@ -751,7 +1490,7 @@ impl DoomGame {
             for dy in -1..=1 {
                 let tx = px + dx;
                 let ty = py + dy;
-                if tile(tx as f64, ty as f64) > 0 {
+                if self.world.tile(tx as f64, ty as f64) > 0 {
                     circle_wall_collision(
                         &mut self.player_body.position,
                         &mut self.player_body.velocity,
@@ -763,18 +1502,30 @@ impl DoomGame {
             }
         }
 
+        if self.touching_exit() {
+            self.finish_level();
+            return false;
+        }
+
         // Update monsters with improved AI and physics
         let mut particles_to_spawn: Vec<ParticleSpawn> = Vec::new();
 
+        let player_pos = self.player_body.position;
+        let player_tile = (player_pos.x as i32, player_pos.y as i32);
+        if self.nav.goal != player_tile || now - self.nav.computed_at > NAV_REFRESH_MS {
+            self.nav = NavField::towards(&self.world, player_tile, now);
+        }
+
         for monster in &mut self.monsters {
             if monster.state != MonsterState::Dead {
-                let to_player = self.player_body.position.sub(&monster.body.position);
+                let to_player = player_pos.sub(&monster.body.position);
                 let dist = to_player.length();
 
                 if dist < 15.0 {
                     monster.state = MonsterState::Chasing;
+                    let in_sight = self.world.line_of_sight(monster.body.position, player_pos);
 
-                    if dist < 1.5 && now - monster.attack_cooldown > 1000.0 {
+                    if dist < 1.5 && in_sight && now - monster.attack_cooldown > 1000.0 {
                         // Melee attack
                         monster.state = MonsterState::Attacking;
                         monster.attack_cooldown = now;
@@ -793,17 +1544,23 @@ impl DoomGame {
                         for _ in 0..5 {
                             particles_to_spawn.push((
                                 self.player_body.position,
-                                Vec2::new(
-                                    (js_sys::Math::random() - 0.5) * 4.0,
-                                    (js_sys::Math::random() - 0.5) * 4.0,
-                                ),
+                                Vec2::new((random() - 0.5) * 4.0, (random() - 0.5) * 4.0),
                                 (255, 0, 0),
                                 0.5,
                             ));
                         }
-                    } else if dist > 1.5 {
-                        // Chase player with pathfinding
-                        let dir = to_player.normalize();
+                    } else if dist > 1.5 || !in_sight {
+                        // Straight at the player when in sight, otherwise
+                        // along the nav field around the walls
+                        let target = if in_sight {
+                            Some(player_pos)
+                        } else {
+                            self.nav.next_waypoint(&self.world, monster.body.position)
+                        };
+                        let dir = target
+                            .unwrap_or(player_pos)
+                            .sub(&monster.body.position)
+                            .normalize();
                         let speed = if monster.sprite_type == 1 { 3.0 } else { 2.0 };
                         monster.body.apply_force(dir.scale(speed));
                     }
@@ -821,7 +1578,7 @@ impl DoomGame {
                     for dy in -1..=1 {
                         let tx = mx + dx;
                         let ty = my + dy;
-                        if tile(tx as f64, ty as f64) > 0 {
+                        if self.world.tile(tx as f64, ty as f64) > 0 {
                             circle_wall_collision(
                                 &mut monster.body.position,
                                 &mut monster.body.velocity,
@@ -842,13 +1599,8 @@ impl DoomGame {
 
         // Update projectiles with physics
         let mut more_particles: Vec<ParticleSpawn> = Vec::new();
-
-        let projectiles_to_check: Vec<_> = self
-            .projectiles
-            .iter()
-            .enumerate()
-            .map(|(i, p)| (i, p.body.position, p.damage))
-            .collect();
+        // Rocket impacts: (centre, damage, radius)
+        let mut explosions: Vec<(Vec2, i32, f64)> = Vec::new();
 
         self.projectiles.retain_mut(|proj| {
             proj.body.integrate(dt);
@@ -861,15 +1613,17 @@ impl DoomGame {
             // Wall collision
             let px = proj.body.position.x as i32;
             let py = proj.body.position.y as i32;
-            if tile(px as f64, py as f64) > 0 {
+            if self.world.tile(px as f64, py as f64) > 0 {
+                let splash = proj.weapon.splash_radius();
+                if splash > 0.0 {
+                    explosions.push((proj.body.position, proj.damage, splash));
+                    return false;
+                }
                 // Collect impact particles
                 for _ in 0..3 {
                     more_particles.push((
                         proj.body.position,
-                        Vec2::new(
-                            (js_sys::Math::random() - 0.5) * 2.0,
-                            (js_sys::Math::random() - 0.5) * 2.0,
-                        ),
+                        Vec2::new((random() - 0.5) * 2.0, (random() - 0.5) * 2.0),
                         (255, 255, 100),
                         0.3,
                     ));
@@ -881,53 +1635,64 @@ impl DoomGame {
         });
 
         // Check monster collisions separately
-        for (idx, proj_pos, damage) in projectiles_to_check {
-            if idx >= self.projectiles.len() {
-                continue;
-            }
-
+        for proj in self.projectiles.iter_mut() {
             for monster in self.monsters.iter_mut() {
                 if monster.state != MonsterState::Dead {
-                    let dist = proj_pos.distance_to(&monster.body.position);
+                    let dist = proj.body.position.distance_to(&monster.body.position);
                     if dist < 0.8 {
                         // Increased collision radius
-                        monster.health -= damage;
-                        if monster.health <= 0 {
-                            monster.state = MonsterState::Dead;
+                        let splash = proj.weapon.splash_radius();
+                        if splash > 0.0 {
+                            explosions.push((proj.body.position, proj.damage, splash));
+                        } else if hit_monster(monster, proj.damage, &mut more_particles) {
                             self.score += 100;
                             self.kills += 1;
-                            play_sound(150.0, 0.2); // Death sound
-
-                            // Collect death particles
-                            for _ in 0..10 {
-                                more_particles.push((
-                                    monster.body.position,
-                                    Vec2::new(
-                                        (js_sys::Math::random() - 0.5) * 5.0,
-                                        (js_sys::Math::random() - 0.5) * 5.0,
-                                    ),
-                                    if monster.sprite_type == 0 {
-                                        (200, 50, 50)
-                                    } else {
-                                        (150, 100, 200)
-                                    },
-                                    1.0,
-                                ));
-                            }
-                        }
-                        // Mark projectile for removal (we'll clean up by index)
-                        if idx < self.projectiles.len() {
-                            self.projectiles[idx].lifetime = 0.0;
                         }
+                        proj.lifetime = 0.0;
                         break;
                     }
                 }
             }
         }
 
-        // Remove dead projectiles
-        self.projectiles.retain(|p| p.lifetime > 0.0);
-
+        // Remove dead projectiles
+        self.projectiles.retain(|p| p.lifetime > 0.0);
+
+        // Splash damage falls off linearly to the edge of the blast and
+        // catches the player too, at half strength
+        for (centre, damage, radius) in explosions {
+            play_sound(80.0, 0.3);
+            for monster in self.monsters.iter_mut() {
+                if monster.state == MonsterState::Dead {
+                    continue;
+                }
+                let dist = centre.distance_to(&monster.body.position);
+                if dist < radius {
+                    let falloff = 1.0 - dist / radius;
+                    let push = monster.body.position.sub(&centre).normalize();
+                    monster.body.apply_impulse(push.scale(4.0 * falloff));
+                    let dealt = (damage as f64 * falloff) as i32;
+                    if hit_monster(monster, dealt, &mut more_particles) {
+                        self.score += 100;
+                        self.kills += 1;
+                    }
+                }
+            }
+            let dist = centre.distance_to(&self.player_body.position);
+            if dist < radius {
+                self.health -= (damage as f64 * (1.0 - dist / radius) * 0.5) as i32;
+            }
+            for _ in 0..16 {
+                let heat = random();
+                more_particles.push((
+                    centre,
+                    Vec2::new((random() - 0.5) * 6.0, (random() - 0.5) * 6.0),
+                    (255, (80.0 + heat * 140.0) as u8, (heat * 40.0) as u8),
+                    0.6,
+                ));
+            }
+        }
+
         // Spawn all collected particles
         for (pos, vel, color, lifetime) in more_particles {
             self.spawn_particle(pos, vel, color, lifetime);
@@ -964,46 +1729,49 @@ impl DoomGame {
     }
 
     fn shoot(&mut self, now: f64) {
-        // Pistol (weapon 0) now infinite ammo (cost 0) so player never hard locks.
-        let cost = if self.current_weapon == 0 { 0 } else { 2 };
-        self.ammo -= cost;
+        let weapon = self.current_weapon;
+        self.ammo -= weapon.ammo_cost();
         self.last_shot_time = now;
 
-        let damage = if self.current_weapon == 0 { 25 } else { 50 };
-
         // Shoot sound
-        play_sound(
-            if self.current_weapon == 0 {
-                440.0
-            } else {
-                330.0
-            },
-            0.05,
-        );
+        let duration = if weapon == Weapon::RocketLauncher {
+            0.15
+        } else {
+            0.05
+        };
+        play_sound(weapon.sound(), duration);
 
-        // Create projectile with physics
-        let mut proj_body = Body::new(
-            self.player_body.position.x,
-            self.player_body.position.y,
-            0.1,
-        );
-        proj_body.velocity = self.dir.scale(20.0);
-        proj_body.friction = 0.0;
+        // Create projectiles with physics, one per pellet
+        for _ in 0..weapon.pellets() {
+            let mut proj_body = Body::new(
+                self.player_body.position.x,
+                self.player_body.position.y,
+                0.1,
+            );
+            let stray = (random() - 0.5) * 2.0 * weapon.spread();
+            proj_body.velocity = self.dir.rotate(stray).scale(weapon.projectile_speed());
+            proj_body.friction = 0.0;
+
+            self.projectiles.push(Projectile {
+                body: proj_body,
+                damage: weapon.damage(),
+                lifetime: 5.0,
+                weapon,
+            });
+        }
 
-        self.projectiles.push(Projectile {
-            body: proj_body,
-            damage,
-            lifetime: 5.0,
-        });
+        // Out of ammo for this one: fall back to the pistol
+        if self.ammo < weapon.ammo_cost() {
+            self.current_weapon = Weapon::Pistol;
+        }
 
         // Muzzle flash particles
         for _ in 0..5 {
             self.spawn_particle(
                 self.player_body.position.add(&self.dir.scale(0.5)),
-                self.dir.scale(2.0).add(&Vec2::new(
-                    (js_sys::Math::random() - 0.5) * 1.0,
-                    (js_sys::Math::random() - 0.5) * 1.0,
-                )),
+                self.dir
+                    .scale(2.0)
+                    .add(&Vec2::new((random() - 0.5) * 1.0, (random() - 0.5) * 1.0)),
                 (255, 200, 0),
                 0.2,
             );
@@ -1012,15 +1780,16 @@ impl DoomGame {
 
     fn spawn_monster(&mut self, now: f64) {
         for _ in 0..10 {
-            let x = 2.0 + js_sys::Math::random() * (MAP_W - 4) as f64;
-            let y = 2.0 + js_sys::Math::random() * (MAP_H - 4) as f64;
+            let x = 2.0 + random() * (MAP_W - 4) as f64;
+            let y = 2.0 + random() * (MAP_H - 4) as f64;
 
             let dist = self.player_body.position.distance_to(&Vec2::new(x, y));
-            if dist > 10.0 && tile(x, y) == 0 {
-                let sprite_type = if js_sys::Math::random() > 0.6 { 1 } else { 0 };
+            if dist > 10.0 && self.world.tile(x, y) == 0 {
+                let sprite_type = if random() > 0.6 { 1 } else { 0 };
 
                 self.monsters
                     .push(Monster::new(x, y, sprite_type, self.difficulty));
+                self.level_monsters += 1;
                 self.last_spawn_time = now;
                 break;
             }
@@ -1074,7 +1843,7 @@ impl DoomGame {
             .collect();
     }
 
-    fn render_fps_display(&self, gfx: &mut Renderer, w: u32, _h: u32) {
+    fn render_fps_display(&self, gfx: &mut dyn Renderer, w: u32, _h: u32) {
         let fps_x = w.saturating_sub(140);
         let fps_y = 20;
 
@@ -1143,7 +1912,7 @@ impl DoomGame {
         }
     }
 
-    fn render(&self, gfx: &mut Renderer) {
+    fn render(&self, gfx: &mut dyn Renderer) {
         let w = gfx.width();
         let h = gfx.height();
 
@@ -1153,6 +1922,12 @@ impl DoomGame {
             return;
         }
 
+        if let Some(stats) = &self.intermission {
+            self.draw_intermission(gfx, stats);
+            let _ = gfx.present();
+            return;
+        }
+
         let half_h = h / 2;
 
         // Sky color based on time of day
@@ -1189,84 +1964,25 @@ impl DoomGame {
         for y in half_h..h {
             let shade = (30 + (h - y) * 20 / half_h).min(255) as u8;
             gfx.draw_hline(0, w - 1, y, shade / 3, shade / 4, shade / 5);
-        } // Z-buffer for sprite rendering
-        let mut z_buffer = vec![f64::MAX; w as usize];
-
-        let map_snapshot = unsafe { WORLD_MAP };
-
-        // Raycast walls (optimized)
-        for x in 0..w {
-            let camera_x = 2.0 * x as f64 / w as f64 - 1.0;
-            let ray_dir = Vec2::new(
-                self.dir.x + self.plane.x * camera_x,
-                self.dir.y + self.plane.y * camera_x,
-            );
-
-            let result = crate::cpp_accel::raycast_dda_map(
-                self.player_body.position.x,
-                self.player_body.position.y,
-                ray_dir.x,
-                ray_dir.y,
-                50.0,
-                &map_snapshot,
-                (MAP_W as i32, MAP_H as i32),
-            );
-
-            if result.hit == 0 || result.distance <= 0.0 {
-                continue;
-            }
-
-            z_buffer[x as usize] = result.distance;
-
-            let line_height = (h as f64 / result.distance).min(h as f64 * 2.0) as u32;
-            let draw_start = (half_h as i32 - line_height as i32 / 2).max(0) as u32;
-            let draw_end = (half_h + line_height / 2).min(h);
-
-            if draw_end <= draw_start {
-                continue;
-            }
-
-            // Wall color based on type
-            let wall_type = tile(result.map_x as f64, result.map_y as f64);
-            let tex_index = match wall_type {
-                2 => 1, // stone
-                3 => 4, // pillar marble
-                4 => 3, // crate
-                _ => 0, // brick default
-            };
-            let tex_x = ((result.wall_x * TEX_W as f64) as i32 & (TEX_W as i32 - 1)) as usize;
-            let side_mult = if result.side == 1 { 0.65 } else { 1.0 };
-            let fog = (1.0 / (1.0 + result.distance * 0.18)).min(1.0) as f32;
-            let day_light = self.get_ambient_light();
-            unsafe {
-                for sy in draw_start..draw_end {
-                    let d_y = sy - draw_start;
-                    let tex_y = ((d_y as f64 / (draw_end - draw_start) as f64) * TEX_H as f64)
-                        as usize
-                        & (TEX_H - 1);
-                    let base = (tex_y * TEX_W + tex_x) * 3;
-                    let mut r = TEXTURES[tex_index][base] as f32;
-                    let mut g = TEXTURES[tex_index][base + 1] as f32;
-                    let mut b = TEXTURES[tex_index][base + 2] as f32;
-                    // Lighting & fog
-                    r = r * side_mult * fog * day_light;
-                    g = g * side_mult * fog * day_light;
-                    b = b * side_mult * fog * day_light;
-                    gfx.set_pixel_rgb(x, sy, r as u8, g as u8, b as u8);
-                }
-            }
         }
 
+        let camera = Camera {
+            pos: self.player_body.position,
+            dir: self.dir,
+            plane: self.plane,
+        };
+        let day_light = self.get_ambient_light();
+        let mut caster = Raycaster::new(w);
+        caster.draw_walls(gfx, &camera, &self.world, day_light);
+
         // Render ammo pickups (simple blue squares)
         for ap in &self.ammo_pickups {
-            let sprite_pos = ap.sub(&self.player_body.position);
-            let inv_det = 1.0 / (self.plane.x * self.dir.y - self.dir.x * self.plane.y);
-            let transform_x = inv_det * (self.dir.y * sprite_pos.x - self.dir.x * sprite_pos.y);
-            let transform_y =
-                inv_det * (-self.plane.y * sprite_pos.x + self.plane.x * sprite_pos.y);
-            if transform_y > 0.1 && transform_y < 20.0 {
-                let screen_x = ((w as f64 / 2.0) * (1.0 + transform_x / transform_y)) as i32;
-                let size = ((10.0 / transform_y).abs() as i32).clamp(2, 12);
+            let Some(proj) = Raycaster::project(&camera, *ap, w) else {
+                continue;
+            };
+            if proj.depth < 20.0 {
+                let screen_x = proj.screen_x;
+                let size = ((10.0 / proj.depth).abs() as i32).clamp(2, 12);
                 if screen_x >= 0 && screen_x < w as i32 {
                     for dy in -size..=size {
                         for dx in -size..=size {
@@ -1284,33 +2000,60 @@ impl DoomGame {
             }
         }
 
+        // Render weapon pickups (squares in the weapon's color)
+        for (wp, weapon) in &self.weapon_pickups {
+            let Some(proj) = Raycaster::project(&camera, *wp, w) else {
+                continue;
+            };
+            if proj.depth < 20.0 {
+                let screen_x = proj.screen_x;
+                let size = ((14.0 / proj.depth).abs() as i32).clamp(2, 16);
+                let (r, g, b) = weapon.color();
+                for dx in -size..=size {
+                    let px = screen_x + dx;
+                    if caster.occluded(px, proj.depth) {
+                        continue;
+                    }
+                    for dy in -size / 2..=size / 2 {
+                        let py = half_h as i32 + size / 2 + dy;
+                        if py >= 0 && py < h as i32 {
+                            let edge = dx.abs() == size || dy.abs() == size / 2;
+                            let shade = if edge { 2 } else { 1 };
+                            gfx.set_pixel_rgb(
+                                px as u32,
+                                py as u32,
+                                r / shade,
+                                g / shade,
+                                b / shade,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
         // Render particles
         for particle in &self.particles {
-            let sprite_pos = particle.position.sub(&self.player_body.position);
-            let inv_det = 1.0 / (self.plane.x * self.dir.y - self.dir.x * self.plane.y);
-            let transform_x = inv_det * (self.dir.y * sprite_pos.x - self.dir.x * sprite_pos.y);
-            let transform_y =
-                inv_det * (-self.plane.y * sprite_pos.x + self.plane.x * sprite_pos.y);
+            let Some(proj) = Raycaster::project(&camera, particle.position, w) else {
+                continue;
+            };
 
-            if transform_y > 0.1 && transform_y < 20.0 {
-                let screen_x = ((w as f64 / 2.0) * (1.0 + transform_x / transform_y)) as i32;
-                let size = ((8.0 / transform_y).abs() as i32).clamp(1, 10);
+            if proj.depth < 20.0 {
+                let screen_x = proj.screen_x;
+                let size = ((8.0 / proj.depth).abs() as i32).clamp(1, 10);
 
-                if screen_x >= 0 && screen_x < w as i32 {
-                    let zbuf_idx = screen_x as usize;
-                    if zbuf_idx < z_buffer.len() && transform_y < z_buffer[zbuf_idx] {
-                        let alpha = (particle.lifetime / particle.max_lifetime) as f32;
-                        let r = (particle.color.0 as f32 * alpha) as u8;
-                        let g = (particle.color.1 as f32 * alpha) as u8;
-                        let b = (particle.color.2 as f32 * alpha) as u8;
-
-                        for dy in -size..=size {
-                            for dx in -size..=size {
-                                let px = screen_x + dx;
-                                let py = half_h as i32 + dy;
-                                if px >= 0 && px < w as i32 && py >= 0 && py < h as i32 {
-                                    gfx.set_pixel_rgb(px as u32, py as u32, r, g, b);
-                                }
+                if !caster.occluded(screen_x, proj.depth) {
+                    let alpha = (particle.lifetime / particle.max_lifetime) as f32;
+                    let r = (particle.color.0 as f32 * alpha) as u8;
+                    let g = (particle.color.1 as f32 * alpha) as u8;
+                    let b = (particle.color.2 as f32 * alpha) as u8;
+
+                    for dy in -size..=size {
+                        for dx in -size..=size {
+                            let px = screen_x + dx;
+                            let py = half_h as i32 + dy;
+                            if px >= 0 && px < w as i32 && py >= 0 && py < h as i32 {
+                                gfx.set_pixel_rgb(px as u32, py as u32, r, g, b);
                             }
                         }
                     }
@@ -1319,28 +2062,24 @@ impl DoomGame {
         }
 
         // Render projectiles
-        for proj in &self.projectiles {
-            let sprite_pos = proj.body.position.sub(&self.player_body.position);
-            let inv_det = 1.0 / (self.plane.x * self.dir.y - self.dir.x * self.plane.y);
-            let transform_x = inv_det * (self.dir.y * sprite_pos.x - self.dir.x * sprite_pos.y);
-            let transform_y =
-                inv_det * (-self.plane.y * sprite_pos.x + self.plane.x * sprite_pos.y);
+        for shot in &self.projectiles {
+            let Some(proj) = Raycaster::project(&camera, shot.body.position, w) else {
+                continue;
+            };
 
-            if transform_y > 0.1 && transform_y < 20.0 {
-                let screen_x = ((w as f64 / 2.0) * (1.0 + transform_x / transform_y)) as i32;
-                let size = ((12.0 / transform_y).abs() as i32).max(2);
+            if proj.depth < 20.0 {
+                let screen_x = proj.screen_x;
+                let size = ((12.0 / proj.depth).abs() as i32).max(2);
+                let (r, g, b) = shot.weapon.color();
 
-                if screen_x >= 0 && screen_x < w as i32 {
-                    let zbuf_idx = screen_x as usize;
-                    if zbuf_idx < z_buffer.len() && transform_y < z_buffer[zbuf_idx] {
-                        for dy in -size..=size {
-                            for dx in -size..=size {
-                                if dx * dx + dy * dy <= size * size {
-                                    let px = screen_x + dx;
-                                    let py = half_h as i32 + dy;
-                                    if px >= 0 && px < w as i32 && py >= 0 && py < h as i32 {
-                                        gfx.set_pixel_rgb(px as u32, py as u32, 255, 255, 0);
-                                    }
+                if !caster.occluded(screen_x, proj.depth) {
+                    for dy in -size..=size {
+                        for dx in -size..=size {
+                            if dx * dx + dy * dy <= size * size {
+                                let px = screen_x + dx;
+                                let py = half_h as i32 + dy;
+                                if px >= 0 && px < w as i32 && py >= 0 && py < h as i32 {
+                                    gfx.set_pixel_rgb(px as u32, py as u32, r, g, b);
                                 }
                             }
                         }
@@ -1349,102 +2088,42 @@ impl DoomGame {
             }
         }
 
-        // Render monsters (sprite rendering with z-buffer)
-        let mut sprites: Vec<(f64, &Monster)> = self
+        // Render monsters (textured billboards, far to near)
+        let alive: Vec<&Monster> = self
             .monsters
             .iter()
             .filter(|m| m.state != MonsterState::Dead)
-            .filter_map(|m| {
-                let sprite_pos = m.body.position.sub(&self.player_body.position);
-                let inv_det = 1.0 / (self.plane.x * self.dir.y - self.dir.x * self.plane.y);
-                let transform_y =
-                    inv_det * (-self.plane.y * sprite_pos.x + self.plane.x * sprite_pos.y);
-                if transform_y > 0.1 {
-                    Some((transform_y, m))
-                } else {
-                    None
-                }
+            .collect();
+        let sprites: Vec<Sprite> = alive
+            .iter()
+            .map(|m| Sprite {
+                pos: m.body.position,
+                texture: &self.world.monster_textures[if m.sprite_type == 0 { 0 } else { 1 }],
+                light: day_light,
             })
             .collect();
 
-        sprites.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-
-        for (transform_y, monster) in sprites {
-            let sprite_pos = monster.body.position.sub(&self.player_body.position);
-            let inv_det = 1.0 / (self.plane.x * self.dir.y - self.dir.x * self.plane.y);
-            let transform_x = inv_det * (self.dir.y * sprite_pos.x - self.dir.x * sprite_pos.y);
-
-            let sprite_screen_x = ((w as f64 / 2.0) * (1.0 + transform_x / transform_y)) as i32;
-            let sprite_height = ((h as f64 / transform_y).abs() as i32).min(h as i32 * 2);
-            let sprite_width = sprite_height;
-
-            if sprite_width <= 0 {
-                continue;
-            }
-
-            let draw_start_y = ((half_h as i32 - sprite_height / 2).max(0)) as u32;
-            let draw_end_y = ((half_h as i32 + sprite_height / 2).min(h as i32 - 1)) as u32;
-            let draw_start_x = ((sprite_screen_x - sprite_width / 2).max(0)) as u32;
-            let draw_end_x = ((sprite_screen_x + sprite_width / 2).min(w as i32 - 1)) as u32;
-
-            if draw_end_y <= draw_start_y || draw_end_x <= draw_start_x {
+        for i in Raycaster::depth_order(&camera, &sprites, w) {
+            let Some(rect) = caster.draw_sprite(gfx, &camera, &sprites[i]) else {
                 continue;
-            }
-
-            // Textured monster billboard
-            let tex_index = if monster.sprite_type == 0 { 0 } else { 1 };
-            let day_light = self.get_ambient_light();
-            for stripe in draw_start_x..=draw_end_x {
-                if stripe >= w {
-                    break;
-                }
-
-                let zbuf_idx = stripe as usize;
-                if zbuf_idx < z_buffer.len() && transform_y < z_buffer[zbuf_idx] {
-                    for y in draw_start_y..=draw_end_y {
-                        let tex_x = (stripe as i32 - (sprite_screen_x - sprite_width / 2)) as f64
-                            / sprite_width as f64;
-                        let tex_y = (y as i32 - (half_h as i32 - sprite_height / 2)) as f64
-                            / sprite_height as f64;
-                        if (0.0..=1.0).contains(&tex_x) && (0.0..=1.0).contains(&tex_y) {
-                            let sx =
-                                (tex_x * (MONSTER_TEX_W as f64)) as usize & (MONSTER_TEX_W - 1);
-                            let sy =
-                                (tex_y * (MONSTER_TEX_H as f64)) as usize & (MONSTER_TEX_H - 1);
-                            let base = (sy * MONSTER_TEX_W + sx) * 3;
-                            unsafe {
-                                let mut r = MONSTER_TEXTURES[tex_index][base] as f32;
-                                let mut g = MONSTER_TEXTURES[tex_index][base + 1] as f32;
-                                let mut b = MONSTER_TEXTURES[tex_index][base + 2] as f32;
-                                // Apply day light
-                                r *= day_light;
-                                g *= day_light;
-                                b *= day_light;
-                                gfx.set_pixel_rgb(stripe, y, r as u8, g as u8, b as u8);
-                            }
-                        }
-                    }
-                }
-            }
+            };
+            let monster = alive[i];
 
             // Health bar above monster
-            if draw_start_y > 10 {
+            if rect.top > 10 {
                 let bar_width = 30u32;
-                let bar_y = draw_start_y - 5;
-                let bar_x = (sprite_screen_x - bar_width as i32 / 2).max(0) as u32;
+                let bar_y = rect.top - 5;
+                let bar_x = (rect.center_x - bar_width as i32 / 2).max(0) as u32;
                 let health_pct = (monster.health as f32 / monster.max_health as f32).max(0.0);
                 let filled_width = (bar_width as f32 * health_pct) as u32;
 
                 for x in 0..bar_width {
                     let bx = bar_x + x;
-                    if bx < w {
-                        let zbuf_idx = bx as usize;
-                        if zbuf_idx < z_buffer.len() && transform_y < z_buffer[zbuf_idx] {
-                            if x < filled_width {
-                                gfx.set_pixel_rgb(bx, bar_y, 0, 255, 0);
-                            } else {
-                                gfx.set_pixel_rgb(bx, bar_y, 50, 50, 50);
-                            }
+                    if bx < w && !caster.occluded(bx as i32, rect.depth) {
+                        if x < filled_width {
+                            gfx.set_pixel_rgb(bx, bar_y, 0, 255, 0);
+                        } else {
+                            gfx.set_pixel_rgb(bx, bar_y, 50, 50, 50);
                         }
                     }
                 }
@@ -1453,6 +2132,7 @@ impl DoomGame {
 
         // Draw HUD
         self.draw_hud(gfx);
+        self.draw_touch_controls(gfx);
         let _ = gfx.present();
     }
 
@@ -1500,7 +2180,7 @@ impl DoomGame {
         }
     }
 
-    fn draw_hud(&self, gfx: &mut Renderer) {
+    fn draw_hud(&self, gfx: &mut dyn Renderer) {
         let w = gfx.width();
         let h = gfx.height();
 
@@ -1526,10 +2206,34 @@ impl DoomGame {
             gfx.fill_rect(bar_x, bar_y, filled, bar_height, r, g, 0);
         }
 
+        // Weapon icon and slots - bottom center
+        let weapon_x = (w / 2).saturating_sub(60);
+        let weapon_y = h - 105;
+        self.draw_weapon_icon(gfx, self.current_weapon, weapon_x, weapon_y);
+        self.draw_text(
+            gfx,
+            self.current_weapon.name(),
+            weapon_x,
+            weapon_y + 26,
+            (220, 220, 220),
+        );
+        for weapon in Weapon::ALL {
+            let color = if weapon == self.current_weapon {
+                (255, 255, 0)
+            } else if self.weapons_owned[weapon.slot()] {
+                (200, 200, 200)
+            } else {
+                (70, 70, 70)
+            };
+            let slot = (weapon.slot() + 1).to_string();
+            let slot_x = weapon_x + weapon.slot() as u32 * 16;
+            self.draw_text(gfx, &slot, slot_x, weapon_y + 44, color);
+        }
+
         // Ammo / weapon indicator - moved higher for better visibility
         let ammo_x = w - 140;
         let ammo_y = h - 105; // Moved up from h-55
-        if self.current_weapon == 0 {
+        if self.current_weapon == Weapon::Pistol {
             // infinite pistol
             self.draw_text(gfx, "AMMO", ammo_x, ammo_y, (200, 200, 200));
             self.draw_text(gfx, "INF", ammo_x, ammo_y + 16, (255, 255, 0));
@@ -1556,6 +2260,8 @@ impl DoomGame {
             Difficulty::Hard => ("HARD", (255, 0, 0)),
         };
         self.draw_text(gfx, diff_str, diff_x, diff_y, diff_color);
+        let level_str = format!("LEVEL {}", self.level);
+        self.draw_text(gfx, &level_str, diff_x, diff_y + 18, (200, 200, 200));
 
         // Enhanced FPS counter with statistics and mini graph
         self.render_fps_display(gfx, w, h);
@@ -1567,173 +2273,118 @@ impl DoomGame {
         gfx.draw_vline(cx, cy - 10, cy + 10, 255, 255, 255);
     }
 
-    fn draw_number(&self, gfx: &mut Renderer, num: i32, x: u32, y: u32) {
-        // 5x7 pixel font for digits 0-9 scaled by scale factor
-        const SCALE: u32 = 2; // Each font pixel becomes SCALE x SCALE block
-        const FONT_W: u32 = 5;
-        const FONT_H: u32 = 7;
-        static DIGITS: [&str; 10] = [
-            // Each string is 5*7=35 chars of '1' (on) or '0' (off), row-major
-            // 0
-            "11111\
-             10001\
-             10011\
-             10101\
-             11001\
-             10001\
-             11111",
-            // 1
-            "00100\
-             01100\
-             00100\
-             00100\
-             00100\
-             00100\
-             01110",
-            // 2
-            "11111\
-             00001\
-             00001\
-             11111\
-             10000\
-             10000\
-             11111",
-            // 3
-            "11111\
-             00001\
-             00001\
-             11111\
-             00001\
-             00001\
-             11111",
-            // 4
-            "10001\
-             10001\
-             10001\
-             11111\
-             00001\
-             00001\
-             00001",
-            // 5
-            "11111\
-             10000\
-             10000\
-             11111\
-             00001\
-             00001\
-             11111",
-            // 6
-            "11111\
-             10000\
-             10000\
-             11111\
-             10001\
-             10001\
-             11111",
-            // 7
-            "11111\
-             00001\
-             00001\
-             00010\
-             00010\
-             00100\
-             00100",
-            // 8
-            "11111\
-             10001\
-             10001\
-             11111\
-             10001\
-             10001\
-             11111",
-            // 9
-            "11111\
-             10001\
-             10001\
-             11111\
-             00001\
-             00001\
-             11111",
+    fn draw_intermission(&self, gfx: &mut dyn Renderer, stats: &Intermission) {
+        let w = gfx.width();
+        let h = gfx.height();
+        gfx.clear(10, 10, 20);
+
+        let seconds = stats.seconds.max(0.0) as u32;
+        let lines = [
+            (format!("LEVEL {} COMPLETE", stats.level), (255, 255, 0)),
+            (String::new(), (0, 0, 0)),
+            (
+                format!("KILLS  {} / {}", stats.kills, stats.monsters),
+                (255, 120, 120),
+            ),
+            (
+                format!("TIME   {}:{:02}", seconds / 60, seconds % 60),
+                (200, 200, 255),
+            ),
+            (format!("SCORE  {}", self.score), (120, 255, 120)),
+            (String::new(), (0, 0, 0)),
+            ("PRESS SPACE".to_string(), (200, 200, 200)),
         ];
+        let top = (h / 2).saturating_sub(lines.len() as u32 * 11);
+        for (i, (text, color)) in lines.iter().enumerate() {
+            // 12px per glyph at scale 2
+            let x = (w / 2).saturating_sub(text.len() as u32 * 6);
+            self.draw_text(gfx, text, x, top + i as u32 * 22, *color);
+        }
+    }
 
-        let digits = num.to_string();
-        let mut offset = 0;
-        for ch in digits.chars() {
-            if let Some(d) = ch.to_digit(10) {
-                let pattern = DIGITS[d as usize].replace('\n', "");
-                for fy in 0..FONT_H {
-                    for fx in 0..FONT_W {
-                        let idx = (fy * FONT_W + fx) as usize;
-                        if pattern.as_bytes()[idx] == b'1' {
-                            // draw scaled block
-                            for sx in 0..SCALE {
-                                for sy in 0..SCALE {
-                                    let px = x + offset + fx * SCALE + sx;
-                                    let py = y + fy * SCALE + sy;
-                                    if px < gfx.width() && py < gfx.height() {
-                                        gfx.set_pixel_rgb(px, py, 255, 255, 0);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+    // ESC button and joystick, only once the player has touched the screen
+    fn draw_touch_controls(&self, gfx: &mut dyn Renderer) {
+        TOUCH.with(|t| {
+            let touch = t.borrow();
+            if !touch.seen {
+                return;
             }
-            offset += (FONT_W + 1) * SCALE; // 1 pixel spacing
-        }
-    }
-
-    fn draw_text(&self, gfx: &mut Renderer, text: &str, x: u32, y: u32, color: (u8, u8, u8)) {
-        // 5x7 uppercase font (subset) scaled by 2
-        const SCALE: u32 = 2;
-        const W: u32 = 5;
-        const H: u32 = 7;
-        fn pattern(ch: char) -> &'static str {
-            match ch {
-                'A' => "01110\n10001\n10001\n11111\n10001\n10001\n10001",
-                'D' => "11110\n10001\n10001\n10001\n10001\n10001\n11110",
-                'E' => "11111\n10000\n10000\n11111\n10000\n10000\n11111",
-                'F' => "11111\n10000\n10000\n11111\n10000\n10000\n10000",
-                'H' => "10001\n10001\n10001\n11111\n10001\n10001\n10001",
-                'I' => "11111\n00100\n00100\n00100\n00100\n00100\n11111",
-                'L' => "10000\n10000\n10000\n10000\n10000\n10000\n11111",
-                'M' => "10001\n11011\n10101\n10101\n10001\n10001\n10001",
-                'N' => "10001\n11001\n10101\n10011\n10001\n10001\n10001",
-                'O' => "01110\n10001\n10001\n10001\n10001\n10001\n01110",
-                'P' => "11110\n10001\n10001\n11110\n10000\n10000\n10000",
-                'R' => "11110\n10001\n10001\n11110\n10100\n10010\n10001",
-                'S' => "01111\n10000\n10000\n01110\n00001\n00001\n11110",
-                'Y' => "10001\n10001\n01010\n00100\n00100\n00100\n00100",
-                '0' => "01110\n10001\n10011\n10101\n11001\n10001\n01110",
-                '1' => "00100\n01100\n00100\n00100\n00100\n00100\n01110",
-                '2' => "11111\n00001\n00001\n11111\n10000\n10000\n11111",
-                '3' => "11111\n00001\n00001\n11111\n00001\n00001\n11111",
-                ':' => "00000\n00100\n00100\n00000\n00100\n00100\n00000",
-                _ => "00000\n00000\n00000\n00000\n00000\n00000\n00000",
-            }
-        }
-        let mut offset = 0;
-        for ch in text.chars() {
-            let pat = pattern(ch).replace('\n', "");
-            for fy in 0..H {
-                for fx in 0..W {
-                    let idx = (fy * W + fx) as usize;
-                    if pat.as_bytes()[idx] == b'1' {
-                        for sx in 0..SCALE {
-                            for sy in 0..SCALE {
-                                let px = x + offset + fx * SCALE + sx;
-                                let py = y + fy * SCALE + sy;
-                                if px < gfx.width() && py < gfx.height() {
-                                    gfx.set_pixel_rgb(px, py, color.0, color.1, color.2);
-                                }
-                            }
-                        }
-                    }
+            let (x, y, w, h) = TOUCH_ESC_BUTTON;
+            let (x, y, w, h) = (x as u32, y as u32, w as u32, h as u32);
+            gfx.fill_rect(x, y, w, h, 60, 20, 20);
+            gfx.draw_rect(x, y, w, h, 255, 80, 80);
+            self.draw_text(gfx, "ESC", x + 14, y + 10, (255, 255, 255));
+
+            if let Some((_, (ox, oy), (cx, cy))) = touch.stick {
+                // Base ring, then the knob clamped to it
+                let base = 48.0;
+                gfx.draw_rect(
+                    (ox - base).max(0.0) as u32,
+                    (oy - base).max(0.0) as u32,
+                    (base * 2.0) as u32,
+                    (base * 2.0) as u32,
+                    200,
+                    200,
+                    200,
+                );
+                let (kx, ky) = (
+                    (cx - ox).clamp(-base, base) + ox,
+                    (cy - oy).clamp(-base, base) + oy,
+                );
+                gfx.fill_rect(
+                    (kx - 12.0).max(0.0) as u32,
+                    (ky - 12.0).max(0.0) as u32,
+                    24,
+                    24,
+                    220,
+                    220,
+                    220,
+                );
+            }
+        });
+    }
+
+    // Side-on silhouettes built from rects, about 40x20 pixels
+    fn draw_weapon_icon(&self, gfx: &mut dyn Renderer, weapon: Weapon, x: u32, y: u32) {
+        let metal = (150, 150, 160);
+        let dark = (70, 70, 80);
+        let wood = (120, 80, 40);
+        let mut rect = |dx: u32, dy: u32, rw: u32, rh: u32, c: (u8, u8, u8)| {
+            gfx.fill_rect(x + dx, y + dy, rw, rh, c.0, c.1, c.2);
+        };
+        match weapon {
+            Weapon::Pistol => {
+                rect(0, 4, 20, 6, metal);
+                rect(12, 10, 7, 10, dark);
+            }
+            Weapon::Shotgun => {
+                rect(0, 4, 30, 3, metal);
+                rect(0, 8, 30, 3, metal);
+                rect(10, 11, 8, 3, dark);
+                rect(28, 6, 14, 7, wood);
+            }
+            Weapon::Chaingun => {
+                for barrel in 0..3 {
+                    rect(0, 2 + barrel * 4, 26, 2, metal);
                 }
+                rect(24, 0, 12, 14, dark);
+                rect(28, 14, 6, 6, dark);
+            }
+            Weapon::RocketLauncher => {
+                rect(0, 4, 40, 9, (90, 110, 80));
+                rect(0, 5, 4, 7, (200, 60, 40));
+                rect(18, 13, 6, 7, dark);
             }
-            offset += (W + 1) * SCALE;
         }
     }
+
+    fn draw_number(&self, gfx: &mut dyn Renderer, num: i32, x: u32, y: u32) {
+        self.draw_text(gfx, &num.to_string(), x, y, (255, 255, 0));
+    }
+
+    fn draw_text(&self, gfx: &mut dyn Renderer, text: &str, x: u32, y: u32, color: (u8, u8, u8)) {
+        gfx.draw_text(text, x, y, HUD_TEXT_SCALE, color.0, color.1, color.2);
+    }
 }
 
 #[wasm_bindgen]
@@ -1747,7 +2398,11 @@ pub fn doom_enable_procedural() {
 
 #[wasm_bindgen]
 pub fn doom_restore_original_map() {
-    restore_original_map();
+    GAME.with(|gm| {
+        if let Some(ref mut game) = *gm.borrow_mut() {
+            game.world.restore_original_map();
+        }
+    });
 }
 
 #[wasm_bindgen]
@@ -1761,6 +2416,31 @@ pub fn doom_get_player_position() -> js_sys::Array {
     });
     arr
 }
+// Apply damage to a live monster; returns whether it died
+fn hit_monster(monster: &mut Monster, damage: i32, particles: &mut Vec<ParticleSpawn>) -> bool {
+    monster.health -= damage;
+    if monster.health > 0 {
+        return false;
+    }
+    monster.state = MonsterState::Dead;
+    play_sound(150.0, 0.2); // Death sound
+
+    // Collect death particles
+    for _ in 0..10 {
+        particles.push((
+            monster.body.position,
+            Vec2::new((random() - 0.5) * 5.0, (random() - 0.5) * 5.0),
+            if monster.sprite_type == 0 {
+                (200, 50, 50)
+            } else {
+                (150, 100, 200)
+            },
+            1.0,
+        ));
+    }
+    true
+}
+
 impl Monster {
     fn new(x: f64, y: f64, sprite_type: u8, difficulty: Difficulty) -> Self {
         let max_health = match (sprite_type, difficulty) {
@@ -1788,31 +2468,16 @@ impl Monster {
     }
 }
 
-// Simple sound synthesis using Web Audio API
+// Square-wave effect on the mixer's effects channel
 fn play_sound(frequency: f64, duration: f64) {
-    AUDIO_CTX.with(|ctx_cell| {
-        if ctx_cell.borrow().is_none() {
-            if let Ok(audio_ctx) = AudioContext::new() {
-                *ctx_cell.borrow_mut() = Some(audio_ctx);
-            }
-        }
-
-        if let Some(ctx) = ctx_cell.borrow().as_ref() {
-            if let Ok(oscillator) = ctx.create_oscillator() {
-                if let Ok(gain) = ctx.create_gain() {
-                    oscillator.set_type(OscillatorType::Square);
-                    oscillator.frequency().set_value(frequency as f32);
-                    oscillator.connect_with_audio_node(&gain).ok();
-                    gain.connect_with_audio_node(&ctx.destination()).ok();
-                    gain.gain().set_value(0.1);
-
-                    let now = ctx.current_time();
-                    oscillator.start_with_when(now).ok();
-                    oscillator.stop_with_when(now + duration).ok();
-                }
-            }
-        }
-    });
+    crate::audio::play_tone(
+        crate::audio::Channel::Effects,
+        OscillatorType::Square,
+        frequency,
+        duration,
+        0.1,
+        &crate::audio::Envelope::BLIP,
+    );
 }
 
 fn document() -> Document {
@@ -1830,6 +2495,62 @@ fn ensure_canvas(width: u32, height: u32) -> Result<HtmlCanvasElement, JsValue>
     Ok(canvas)
 }
 
+// The preferred renderer, falling back to software if WebGL can't start
+fn create_renderer(width: u32, height: u32) -> Box<dyn Renderer> {
+    let want = RENDERER_PREF.with(|r| r.get());
+    if CANVAS_CONTEXT
+        .with(|c| c.get())
+        .is_some_and(|had| had != want)
+    {
+        replace_canvas();
+    }
+    if want == RendererKind::Gl {
+        match WebGlGraphics::new("game-canvas", width, height) {
+            Ok(g) => {
+                CANVAS_CONTEXT.with(|c| c.set(Some(RendererKind::Gl)));
+                GL_FAILED.with(|f| f.set(false));
+                return Box::new(g);
+            }
+            Err(e) => {
+                web_sys::console::warn_1(&JsValue::from_str(&format!(
+                    "doom: WebGL unavailable ({}), using the software renderer",
+                    e.as_string().unwrap_or_default()
+                )));
+                GL_FAILED.with(|f| f.set(true));
+                // A half-initialised GL context would block the 2d one
+                replace_canvas();
+            }
+        }
+    }
+    CANVAS_CONTEXT.with(|c| c.set(Some(RendererKind::Soft)));
+    Box::new(Graphics::new("game-canvas", width, height).unwrap())
+}
+
+/// Make sure the next 2d context on the game canvas will work, for other
+/// programs that draw there after a GL game
+pub(crate) fn claim_2d_canvas() {
+    if CANVAS_CONTEXT.with(|c| c.get()) == Some(RendererKind::Gl) {
+        replace_canvas();
+    }
+    CANVAS_CONTEXT.with(|c| c.set(Some(RendererKind::Soft)));
+}
+
+// Swap the canvas for a fresh copy; a canvas keeps the first context type it
+// hands out, and listeners don't carry over
+fn replace_canvas() {
+    let Some(old) = document().get_element_by_id("game-canvas") else {
+        return;
+    };
+    if let Ok(fresh) = old
+        .clone_node()
+        .and_then(|n| n.dyn_into::<web_sys::Element>().map_err(JsValue::from))
+    {
+        old.replace_with_with_node_1(&fresh).ok();
+    }
+    CANVAS_CONTEXT.with(|c| c.set(None));
+    TOUCH.with(|t| t.borrow_mut().installed = false);
+}
+
 fn update_canvas_size() {
     let w = window().unwrap();
     let width = (w.inner_width().unwrap().as_f64().unwrap() * 0.95) as u32;
@@ -1877,14 +2598,11 @@ fn install_mouse_look(canvas: &HtmlCanvasElement) {
 
     let mouse_move_cb = Closure::<dyn FnMut(web_sys::MouseEvent)>::wrap(Box::new(
         move |evt: web_sys::MouseEvent| {
-            // Use movement_x for pointer lock (relative movement)
+            // Use movement_x for pointer lock (relative movement); the
+            // game applies it on its next frame
             let delta_x = evt.movement_x() as f64;
             if delta_x.abs() > 0.1 {
-                GAME.with(|g| {
-                    if let Some(ref mut game) = *g.borrow_mut() {
-                        game.rotate(-delta_x * 0.002); // Pointer lock sensitivity
-                    }
-                });
+                MOUSE_DELTA_X.with(|md| md.set(md.get() + delta_x));
             }
         },
     ));
@@ -1927,6 +2645,156 @@ fn install_mouse_look(canvas: &HtmlCanvasElement) {
         .add_event_listener_with_callback("click", click_cb.as_ref().unchecked_ref())
         .unwrap();
     click_cb.forget();
+
+    // Track the held button for automatic weapons
+    for (event, held) in [("mousedown", true), ("mouseup", false)] {
+        let cb =
+            Closure::<dyn FnMut(web_sys::Event)>::wrap(Box::new(move |_evt: web_sys::Event| {
+                MOUSE_HELD.with(|mh| mh.set(held));
+            }));
+        canvas
+            .add_event_listener_with_callback(event, cb.as_ref().unchecked_ref())
+            .unwrap();
+        cb.forget();
+    }
+
+    // Scroll to switch weapons
+    let wheel_cb =
+        Closure::<dyn FnMut(web_sys::WheelEvent)>::wrap(Box::new(|evt: web_sys::WheelEvent| {
+            evt.prevent_default();
+            let step = evt.delta_y().signum() as i32;
+            WHEEL_STEPS.with(|w| w.set(w.get() + step));
+        }));
+    canvas
+        .add_event_listener_with_callback("wheel", wheel_cb.as_ref().unchecked_ref())
+        .unwrap();
+    wheel_cb.forget();
+
+    install_touch_controls(canvas);
+}
+
+// Touch position in canvas pixels (the canvas is scaled by CSS)
+fn touch_point(canvas: &HtmlCanvasElement, touch: &web_sys::Touch) -> CanvasPoint {
+    let rect = canvas.get_bounding_client_rect();
+    let sx = canvas.width() as f64 / rect.width().max(1.0);
+    let sy = canvas.height() as f64 / rect.height().max(1.0);
+    (
+        (touch.client_x() as f64 - rect.left()) * sx,
+        (touch.client_y() as f64 - rect.top()) * sy,
+    )
+}
+
+fn install_touch_controls(canvas: &HtmlCanvasElement) {
+    // The canvas element outlives each game, so only listen once
+    if TOUCH.with(|t| std::mem::replace(&mut t.borrow_mut().installed, true)) {
+        return;
+    }
+    // Keep the browser from scrolling or zooming under the player's thumbs
+    canvas.style().set_property("touch-action", "none").ok();
+
+    let start_canvas = canvas.clone();
+    let touch_start = Closure::<dyn FnMut(web_sys::TouchEvent)>::wrap(Box::new(
+        move |evt: web_sys::TouchEvent| {
+            // Also stops the synthetic mouse click, which would fire twice
+            evt.prevent_default();
+            let touches = evt.changed_touches();
+            let half = start_canvas.width() as f64 / 2.0;
+            for i in 0..touches.length() {
+                let Some(touch) = touches.get(i) else {
+                    continue;
+                };
+                let (x, y) = touch_point(&start_canvas, &touch);
+                let (bx, by, bw, bh) = TOUCH_ESC_BUTTON;
+                if (bx..bx + bw).contains(&x) && (by..by + bh).contains(&y) {
+                    KEYS.with(|k| k.borrow_mut()[27] = true);
+                    continue;
+                }
+                TOUCH.with(|t| {
+                    let mut t = t.borrow_mut();
+                    t.seen = true;
+                    if x < half && t.stick.is_none() {
+                        t.stick = Some((touch.identifier(), (x, y), (x, y)));
+                    } else if x >= half && t.look.is_none() {
+                        t.look = Some((touch.identifier(), x, js_sys::Date::now(), 0.0));
+                    }
+                });
+            }
+        },
+    ));
+
+    let move_canvas = canvas.clone();
+    let touch_move = Closure::<dyn FnMut(web_sys::TouchEvent)>::wrap(Box::new(
+        move |evt: web_sys::TouchEvent| {
+            evt.prevent_default();
+            let touches = evt.changed_touches();
+            for i in 0..touches.length() {
+                let Some(touch) = touches.get(i) else {
+                    continue;
+                };
+                let id = touch.identifier();
+                let (x, y) = touch_point(&move_canvas, &touch);
+                TOUCH.with(|t| {
+                    let mut t = t.borrow_mut();
+                    if let Some((stick_id, _, ref mut current)) = t.stick {
+                        if stick_id == id {
+                            *current = (x, y);
+                        }
+                    }
+                    if let Some((look_id, ref mut last_x, _, ref mut travel)) = t.look {
+                        if look_id == id {
+                            let dx = x - *last_x;
+                            *last_x = x;
+                            *travel += dx.abs();
+                            MOUSE_DELTA_X.with(|md| md.set(md.get() + dx * TOUCH_TURN_SCALE));
+                        }
+                    }
+                });
+            }
+        },
+    ));
+
+    let touch_end =
+        Closure::<dyn FnMut(web_sys::TouchEvent)>::wrap(Box::new(|evt: web_sys::TouchEvent| {
+            evt.prevent_default();
+            let touches = evt.changed_touches();
+            for i in 0..touches.length() {
+                let Some(touch) = touches.get(i) else {
+                    continue;
+                };
+                let id = touch.identifier();
+                TOUCH.with(|t| {
+                    let mut t = t.borrow_mut();
+                    if t.stick.is_some_and(|(stick_id, _, _)| stick_id == id) {
+                        t.stick = None;
+                    }
+                    if let Some((look_id, _, down_at, travel)) = t.look {
+                        if look_id == id {
+                            t.look = None;
+                            if js_sys::Date::now() - down_at < TOUCH_TAP_MS
+                                && travel < TOUCH_TAP_SLOP
+                            {
+                                MOUSE_CLICKED.with(|mc| mc.set(true));
+                            }
+                        }
+                    }
+                });
+            }
+        }));
+
+    canvas
+        .add_event_listener_with_callback("touchstart", touch_start.as_ref().unchecked_ref())
+        .unwrap();
+    canvas
+        .add_event_listener_with_callback("touchmove", touch_move.as_ref().unchecked_ref())
+        .unwrap();
+    for event in ["touchend", "touchcancel"] {
+        canvas
+            .add_event_listener_with_callback(event, touch_end.as_ref().unchecked_ref())
+            .unwrap();
+    }
+    touch_start.forget();
+    touch_move.forget();
+    touch_end.forget();
 }
 
 #[allow(dead_code)]
@@ -1942,7 +2810,7 @@ fn start_loop() {
 
         let mut last_time = js_sys::Date::now();
 
-        let closure = Closure::wrap(Box::new(move |_ts: f64| {
+        let closure = Closure::wrap(Box::new(move |ts: f64| {
             let stopping = STOPPING.with(|s| s.get());
             if stopping {
                 return;
@@ -1953,14 +2821,42 @@ fn start_loop() {
                 return;
             }
 
+            let work_start = crate::clock::now_ms();
             // Calculate delta time
             let now = js_sys::Date::now();
             let dt = (now - last_time) / 1000.0;
             last_time = now;
 
+            // A demo supplies every frame's input until it runs out; ESC
+            // still ends it early
+            let escape = KEYS.with(|k| k.borrow()[27]);
+            let input = PLAYBACK.with(|pb| {
+                let mut pb = pb.borrow_mut();
+                let playback = pb.as_mut()?;
+                let record = playback
+                    .frames
+                    .get(playback.next..playback.next + FrameInput::SIZE)?;
+                playback.next += FrameInput::SIZE;
+                Some(Some(FrameInput::decode(record)))
+            });
+            let input = match input {
+                Some(frame) => frame,
+                None if PLAYBACK.with(|pb| pb.borrow().is_some()) => None,
+                None => Some(FrameInput::live(dt)),
+            };
+            let Some(input) = input.filter(|_| !escape) else {
+                stop_doom();
+                return;
+            };
+            RECORDING.with(|rec| {
+                if let Some(buffer) = rec.borrow_mut().as_mut() {
+                    input.encode(buffer);
+                }
+            });
+
             let should_stop = GAME.with(|g| {
                 if let Some(ref mut game) = *g.borrow_mut() {
-                    game.update(dt)
+                    game.update(&input)
                 } else {
                     false
                 }
@@ -1978,12 +2874,13 @@ fn start_loop() {
                     if let Some(ref game) = *g.borrow() {
                         GFX.with(|gfx| {
                             if let Some(ref mut graphics) = *gfx.borrow_mut() {
-                                game.render(graphics);
+                                game.render(graphics.as_mut());
                             }
                         });
                     }
                 });
             }
+            crate::perf::record_frame("doom", ts, crate::clock::now_ms() - work_start);
 
             let should_continue = GAME.with(|g| g.borrow().is_some());
             let loop_present = LOOP.with(|l2| l2.borrow().is_some());
@@ -2033,6 +2930,63 @@ pub fn start_doom() {
 
 #[wasm_bindgen]
 pub fn start_doom_with_difficulty(diff: u8) {
+    let seed = (js_sys::Math::random() * u32::MAX as f64) as u32 ^ js_sys::Date::now() as u32;
+    PLAYBACK.with(|pb| *pb.borrow_mut() = None);
+    if RECORD_ARMED.with(|armed| armed.replace(false)) {
+        let mut demo = Vec::with_capacity(DEMO_HEADER_LEN + 60 * 60 * FrameInput::SIZE);
+        demo.extend_from_slice(DEMO_MAGIC);
+        demo.push(DEMO_VERSION);
+        demo.push(diff);
+        demo.extend_from_slice(&seed.to_le_bytes());
+        RECORDING.with(|rec| *rec.borrow_mut() = Some(demo));
+    } else {
+        RECORDING.with(|rec| *rec.borrow_mut() = None);
+    }
+    launch(diff, seed);
+}
+
+/// Record the next game started into a demo (see `doom_stop_recording`)
+#[wasm_bindgen]
+pub fn doom_start_recording() {
+    RECORD_ARMED.with(|armed| armed.set(true));
+}
+
+/// Finish recording and return the demo, or an empty buffer if nothing was
+/// recorded
+#[wasm_bindgen]
+pub fn doom_stop_recording() -> Vec<u8> {
+    RECORD_ARMED.with(|armed| armed.set(false));
+    RECORDING
+        .with(|rec| rec.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+/// Replay a recorded demo. Returns an error message, or an empty string once
+/// playback has started.
+#[wasm_bindgen]
+pub fn doom_play_demo(demo: Vec<u8>) -> String {
+    if demo.len() < DEMO_HEADER_LEN || &demo[..4] != DEMO_MAGIC {
+        return "not a doom demo".to_string();
+    }
+    if demo[4] != DEMO_VERSION {
+        return format!("unsupported demo version {}", demo[4]);
+    }
+    let diff = demo[5];
+    let seed = u32::from_le_bytes([demo[6], demo[7], demo[8], demo[9]]);
+    RECORD_ARMED.with(|armed| armed.set(false));
+    RECORDING.with(|rec| *rec.borrow_mut() = None);
+    PLAYBACK.with(|pb| {
+        *pb.borrow_mut() = Some(Playback {
+            frames: demo[DEMO_HEADER_LEN..].to_vec(),
+            next: 0,
+        })
+    });
+    launch(diff, seed);
+    String::new()
+}
+
+fn launch(diff: u8, seed: u32) {
+    seed_random(seed);
     let (difficulty, control_mode) = match diff {
         0 => (Difficulty::Easy, ControlMode::Human),
         2 => (Difficulty::Hard, ControlMode::Human),
@@ -2056,20 +3010,11 @@ pub fn start_doom_with_difficulty(diff: u8) {
             let w = window().unwrap();
             let width = (w.inner_width().unwrap().as_f64().unwrap() * 0.80) as u32;
             let height = (w.inner_height().unwrap().as_f64().unwrap() * 0.70) as u32;
+            let renderer = create_renderer(width, height);
             let canvas = ensure_canvas(width, height).unwrap();
             install_mouse_look(&canvas);
             // Removed pointer lock request for better trackpad compatibility
-
-            #[cfg(not(feature = "webgl"))]
-            {
-                let g = Graphics::new("game-canvas", width, height).unwrap();
-                *gfx.borrow_mut() = Some(g);
-            }
-            #[cfg(feature = "webgl")]
-            {
-                let g = WebGlGraphics::new("game-canvas", width, height).unwrap();
-                *gfx.borrow_mut() = Some(g);
-            }
+            *gfx.borrow_mut() = Some(renderer);
         }
     });
 
@@ -2081,11 +3026,14 @@ pub fn start_doom_with_difficulty(diff: u8) {
     install_resize_listener();
     crate::idle::set_game_active(true);
     crate::idle::set_screensaver_active(false);
+    // Same seed, same tune, so demos sound like the game they recorded
+    crate::audio::start_music(crate::audio::Song::procedural(seed));
     start_loop();
 }
 
 #[wasm_bindgen]
 pub fn stop_doom() {
+    let summary = GAME.with(|gm| gm.borrow().as_ref().map(DoomGame::summary));
     STOPPING.with(|s| s.set(true));
     LOOP.with(|l| {
         *l.borrow_mut() = None;
@@ -2107,6 +3055,15 @@ pub fn stop_doom() {
     });
     MOUSE_DELTA_X.with(|md| md.set(0.0));
     MOUSE_CLICKED.with(|mc| mc.set(false));
+    MOUSE_HELD.with(|mh| mh.set(false));
+    WHEEL_STEPS.with(|w| w.set(0));
+    TOUCH.with(|t| {
+        let mut t = t.borrow_mut();
+        t.stick = None;
+        t.look = None;
+    });
+    PLAYBACK.with(|pb| *pb.borrow_mut() = None);
+    crate::audio::stop_music();
 
     // Restore cursor visibility when exiting DOOM
     if let Some(body) = document().body() {
@@ -2123,6 +3080,19 @@ pub fn stop_doom() {
     crate::idle::set_game_active(false);
     crate::idle::set_screensaver_active(false);
     STOPPING.with(|s| s.set(false));
+
+    if let Some(summary) = summary {
+        report_exit(&summary);
+    }
+}
+
+// Let the terminal know how the game went (see KP_DOOM_EXIT in terminal.js)
+fn report_exit(summary: &str) {
+    let init = web_sys::CustomEventInit::new();
+    init.set_detail(&JsValue::from_str(summary));
+    if let Ok(event) = web_sys::CustomEvent::new_with_event_init_dict("KP_DOOM_EXIT", &init) {
+        let _ = window().unwrap().dispatch_event(&event);
+    }
 }
 
 #[wasm_bindgen]
@@ -0,0 +1,378 @@
+//! First-person raycasting engine
+//! Renders a tile map as textured walls plus depth-sorted billboard sprites,
+//! for doom and anything else that wants a Wolfenstein-style view
+
+use crate::cpp_accel::raycast_dda_map;
+use crate::graphics::{FrameBuffer, Renderer};
+use crate::physics::Vec2;
+
+/// Anything the engine can draw into
+pub trait PixelTarget {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8);
+}
+
+impl PixelTarget for FrameBuffer {
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
+        FrameBuffer::set_pixel_rgb(self, x, y, r, g, b);
+    }
+}
+
+impl<R: Renderer + ?Sized> PixelTarget for R {
+    fn width(&self) -> u32 {
+        Renderer::width(self)
+    }
+    fn height(&self) -> u32 {
+        Renderer::height(self)
+    }
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
+        Renderer::set_pixel_rgb(self, x, y, r, g, b);
+    }
+}
+
+/// RGB texture, row-major
+#[derive(Clone)]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub rgb: Vec<u8>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize) -> Self {
+        Texture {
+            width,
+            height,
+            rgb: vec![0; width * height * 3],
+        }
+    }
+
+    /// Colour at texture coordinates `u`, `v` in 0..1 (wrapping)
+    #[inline(always)]
+    pub fn sample(&self, u: f64, v: f64) -> (u8, u8, u8) {
+        let x = (u * self.width as f64) as usize % self.width;
+        let y = (v * self.height as f64) as usize % self.height;
+        let base = (y * self.width + x) * 3;
+        (self.rgb[base], self.rgb[base + 1], self.rgb[base + 2])
+    }
+}
+
+/// A tile map: cell 0 is open, anything else is a wall
+pub trait MapProvider {
+    /// Width and height in cells
+    fn size(&self) -> (usize, usize);
+    /// Row-major cells
+    fn cells(&self) -> &[i32];
+    /// Texture for a wall cell
+    fn wall_texture(&self, cell: i32) -> &Texture;
+
+    /// Cell at a world position; outside the map counts as wall
+    fn cell_at(&self, x: f64, y: f64) -> i32 {
+        let (w, h) = self.size();
+        if x < 0.0 || y < 0.0 || x as usize >= w || y as usize >= h {
+            return 1;
+        }
+        self.cells()[x as usize + y as usize * w]
+    }
+}
+
+/// Viewpoint: position, facing and the camera plane (its length sets the FOV)
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub pos: Vec2,
+    pub dir: Vec2,
+    pub plane: Vec2,
+}
+
+/// Where a world point lands on screen
+#[derive(Clone, Copy)]
+pub struct Projection {
+    pub screen_x: i32,
+    /// Distance along the view direction (what the z-buffer holds)
+    pub depth: f64,
+}
+
+/// A textured billboard standing on the floor, one map cell tall
+pub struct Sprite<'a> {
+    pub pos: Vec2,
+    pub texture: &'a Texture,
+    /// Brightness multiplier
+    pub light: f32,
+}
+
+/// Screen bounds of a drawn sprite, for overlays such as health bars
+#[derive(Clone, Copy)]
+pub struct SpriteRect {
+    pub center_x: i32,
+    pub top: u32,
+    pub bottom: u32,
+    pub depth: f64,
+}
+
+pub struct Raycaster {
+    /// Rays give up after this many cells
+    pub max_distance: f64,
+    /// Fog density; walls fade as 1 / (1 + distance * fog)
+    pub fog: f64,
+    /// Shade for walls hit on their north/south faces
+    pub side_shade: f32,
+    z_buffer: Vec<f64>,
+}
+
+impl Raycaster {
+    /// Projections closer than this are behind (or inside) the camera
+    pub const NEAR: f64 = 0.1;
+
+    pub fn new(width: u32) -> Self {
+        Raycaster {
+            max_distance: 50.0,
+            fog: 0.18,
+            side_shade: 0.65,
+            z_buffer: vec![f64::MAX; width as usize],
+        }
+    }
+
+    /// Where `point` appears for `camera` on a screen `width` wide, or
+    /// None if it is behind the camera
+    pub fn project(camera: &Camera, point: Vec2, width: u32) -> Option<Projection> {
+        let rel = point.sub(&camera.pos);
+        let inv_det = 1.0 / (camera.plane.x * camera.dir.y - camera.dir.x * camera.plane.y);
+        let transform_x = inv_det * (camera.dir.y * rel.x - camera.dir.x * rel.y);
+        let depth = inv_det * (-camera.plane.y * rel.x + camera.plane.x * rel.y);
+        if depth <= Self::NEAR {
+            return None;
+        }
+        let screen_x = ((width as f64 / 2.0) * (1.0 + transform_x / depth)) as i32;
+        Some(Projection { screen_x, depth })
+    }
+
+    /// True if a wall in screen column `x` is in front of `depth`
+    pub fn occluded(&self, x: i32, depth: f64) -> bool {
+        x < 0
+            || self
+                .z_buffer
+                .get(x as usize)
+                .is_none_or(|&wall| depth >= wall)
+    }
+
+    /// Cast one ray per screen column and draw the textured walls, filling
+    /// the z-buffer for the sprites drawn after
+    pub fn draw_walls<T: PixelTarget + ?Sized, M: MapProvider>(
+        &mut self,
+        target: &mut T,
+        camera: &Camera,
+        map: &M,
+        light: f32,
+    ) {
+        let (w, h) = (target.width(), target.height());
+        let half_h = h / 2;
+        let (map_w, map_h) = map.size();
+        self.z_buffer.clear();
+        self.z_buffer.resize(w as usize, f64::MAX);
+
+        for x in 0..w {
+            let camera_x = 2.0 * x as f64 / w as f64 - 1.0;
+            let ray_dir = camera.dir.add(&camera.plane.scale(camera_x));
+            let result = raycast_dda_map(
+                camera.pos.x,
+                camera.pos.y,
+                ray_dir.x,
+                ray_dir.y,
+                self.max_distance,
+                map.cells(),
+                (map_w as i32, map_h as i32),
+            );
+            if result.hit == 0 || result.distance <= 0.0 {
+                continue;
+            }
+            self.z_buffer[x as usize] = result.distance;
+
+            let line_height = (h as f64 / result.distance).min(h as f64 * 2.0) as u32;
+            let draw_start = (half_h as i32 - line_height as i32 / 2).max(0) as u32;
+            let draw_end = (half_h + line_height / 2).min(h);
+            if draw_end <= draw_start {
+                continue;
+            }
+
+            let texture = map.wall_texture(map.cell_at(result.map_x as f64, result.map_y as f64));
+            let side = if result.side == 1 {
+                self.side_shade
+            } else {
+                1.0
+            };
+            let fog = (1.0 / (1.0 + result.distance * self.fog)).min(1.0) as f32;
+            let shade = side * fog * light;
+            for sy in draw_start..draw_end {
+                let v = (sy - draw_start) as f64 / (draw_end - draw_start) as f64;
+                let (r, g, b) = texture.sample(result.wall_x, v);
+                target.set_pixel_rgb(
+                    x,
+                    sy,
+                    (r as f32 * shade) as u8,
+                    (g as f32 * shade) as u8,
+                    (b as f32 * shade) as u8,
+                );
+            }
+        }
+    }
+
+    /// Draw one billboard, clipped against the walls
+    pub fn draw_sprite<T: PixelTarget + ?Sized>(
+        &self,
+        target: &mut T,
+        camera: &Camera,
+        sprite: &Sprite,
+    ) -> Option<SpriteRect> {
+        let (w, h) = (target.width(), target.height());
+        let half_h = h as i32 / 2;
+        let proj = Self::project(camera, sprite.pos, w)?;
+        let size = ((h as f64 / proj.depth).abs() as i32).min(h as i32 * 2);
+        if size <= 0 {
+            return None;
+        }
+
+        let left = proj.screen_x - size / 2;
+        let top = half_h - size / 2;
+        let start_y = top.max(0) as u32;
+        let end_y = (half_h + size / 2).min(h as i32 - 1) as u32;
+        let start_x = left.max(0) as u32;
+        let end_x = (proj.screen_x + size / 2).min(w as i32 - 1) as u32;
+        if end_y <= start_y || end_x <= start_x {
+            return None;
+        }
+
+        for stripe in start_x..=end_x {
+            if self.occluded(stripe as i32, proj.depth) {
+                continue;
+            }
+            let u = (stripe as i32 - left) as f64 / size as f64;
+            for y in start_y..=end_y {
+                let v = (y as i32 - top) as f64 / size as f64;
+                if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) {
+                    let (r, g, b) = sprite.texture.sample(u, v);
+                    target.set_pixel_rgb(
+                        stripe,
+                        y,
+                        (r as f32 * sprite.light) as u8,
+                        (g as f32 * sprite.light) as u8,
+                        (b as f32 * sprite.light) as u8,
+                    );
+                }
+            }
+        }
+        Some(SpriteRect {
+            center_x: proj.screen_x,
+            top: start_y,
+            bottom: end_y,
+            depth: proj.depth,
+        })
+    }
+
+    /// Indices of `sprites` from farthest to nearest, skipping those behind
+    /// the camera; draw in this order so near sprites cover far ones
+    pub fn depth_order(camera: &Camera, sprites: &[Sprite], width: u32) -> Vec<usize> {
+        let mut order: Vec<(usize, f64)> = sprites
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| Self::project(camera, s.pos, width).map(|p| (i, p.depth)))
+            .collect();
+        order.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        order.into_iter().map(|(i, _)| i).collect()
+    }
+
+    /// Draw every sprite, far to near
+    pub fn draw_sprites<T: PixelTarget + ?Sized>(
+        &self,
+        target: &mut T,
+        camera: &Camera,
+        sprites: &[Sprite],
+    ) -> Vec<SpriteRect> {
+        Self::depth_order(camera, sprites, target.width())
+            .into_iter()
+            .filter_map(|i| self.draw_sprite(target, camera, &sprites[i]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Room {
+        cells: Vec<i32>,
+        wall: Texture,
+    }
+
+    impl MapProvider for Room {
+        fn size(&self) -> (usize, usize) {
+            (8, 8)
+        }
+        fn cells(&self) -> &[i32] {
+            &self.cells
+        }
+        fn wall_texture(&self, _cell: i32) -> &Texture {
+            &self.wall
+        }
+    }
+
+    fn camera() -> Camera {
+        Camera {
+            pos: Vec2::new(4.5, 4.5),
+            dir: Vec2::new(1.0, 0.0),
+            plane: Vec2::new(0.0, 0.66),
+        }
+    }
+
+    #[test]
+    fn test_walls_fill_z_buffer() {
+        // 8x8 room with a solid border, camera in the middle facing east
+        let cells = (0..64)
+            .map(|i| (i % 8 == 0 || i % 8 == 7 || i / 8 == 0 || i / 8 == 7) as i32)
+            .collect();
+        let mut wall = Texture::new(4, 4);
+        wall.rgb.fill(200);
+        let room = Room { cells, wall };
+
+        let mut fb = FrameBuffer::new(32, 24);
+        let mut caster = Raycaster::new(32);
+        caster.draw_walls(&mut fb, &camera(), &room, 1.0);
+
+        // The east wall is 2.5 cells ahead of the centre column
+        assert!(caster.occluded(16, 2.6));
+        assert!(!caster.occluded(16, 2.4));
+        let centre = ((12 * 32 + 16) * 4) as usize;
+        assert!(fb.pixels[centre] > 0);
+    }
+
+    #[test]
+    fn test_sprites_draw_far_to_near() {
+        let texture = Texture::new(2, 2);
+        let sprites = [
+            Sprite {
+                pos: Vec2::new(6.0, 4.5),
+                texture: &texture,
+                light: 1.0,
+            },
+            Sprite {
+                pos: Vec2::new(3.0, 4.5), // behind the camera
+                texture: &texture,
+                light: 1.0,
+            },
+            Sprite {
+                pos: Vec2::new(7.0, 4.5),
+                texture: &texture,
+                light: 1.0,
+            },
+        ];
+        assert_eq!(Raycaster::depth_order(&camera(), &sprites, 32), vec![2, 0]);
+        let centre = Raycaster::project(&camera(), Vec2::new(6.0, 4.5), 32).unwrap();
+        assert_eq!(centre.screen_x, 16);
+    }
+}
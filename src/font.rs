@@ -0,0 +1,384 @@
+/// 5x7 bitmap font covering printable ASCII and some half-width katakana,
+/// drawn by `FrameBuffer::draw_text`, `figlet` and the matrix screensaver.
+/// Each glyph is seven rows; bit 4 is the leftmost pixel of a row.
+pub const GLYPH_W: u32 = 5;
+pub const GLYPH_H: u32 = 7;
+
+pub fn glyph(ch: char) -> Option<[u8; 7]> {
+    let rows = match ch {
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11111, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11111, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b11111,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b11111, 0b00001, 0b00001, 0b11111, 0b10000, 0b10000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00001, 0b00001, 0b11111, 0b00001, 0b00001, 0b11111,
+        ],
+        '4' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b00001, 0b00001, 0b00001,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b10000, 0b11111, 0b00001, 0b00001, 0b11111,
+        ],
+        '6' => [
+            0b11111, 0b10000, 0b10000, 0b11111, 0b10001, 0b10001, 0b11111,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00001, 0b00010, 0b00010, 0b00100, 0b00100,
+        ],
+        '8' => [
+            0b11111, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b11111,
+        ],
+        '9' => [
+            0b11111, 0b10001, 0b10001, 0b11111, 0b00001, 0b00001, 0b11111,
+        ],
+        ' ' => [0; 7],
+        ':' => [
+            0b00000, 0b00100, 0b00100, 0b00000, 0b00100, 0b00100, 0b00000,
+        ],
+        '!' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+        ],
+        '?' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+        '.' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+        ',' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+        '\'' => [
+            0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '-' => [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+        '+' => [
+            0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+        ],
+        '=' => [
+            0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+        ],
+        '_' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+        '/' => [
+            0b00001, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b10000,
+        ],
+        '(' => [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+        ')' => [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+        '#' => [
+            0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010,
+        ],
+        'a' => [
+            0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111,
+        ],
+        'b' => [
+            0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110,
+        ],
+        'c' => [
+            0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'd' => [
+            0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111,
+        ],
+        'e' => [
+            0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110,
+        ],
+        'f' => [
+            0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000,
+        ],
+        'g' => [
+            0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+        ],
+        'h' => [
+            0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+        ],
+        'i' => [
+            0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'j' => [
+            0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'k' => [
+            0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010,
+        ],
+        'l' => [
+            0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'm' => [
+            0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001,
+        ],
+        'n' => [
+            0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+        ],
+        'o' => [
+            0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'p' => [
+            0b00000, 0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000,
+        ],
+        'q' => [
+            0b00000, 0b01101, 0b10011, 0b10001, 0b01111, 0b00001, 0b00001,
+        ],
+        'r' => [
+            0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000,
+        ],
+        's' => [
+            0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110,
+        ],
+        't' => [
+            0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110,
+        ],
+        'u' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101,
+        ],
+        'v' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'w' => [
+            0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010,
+        ],
+        'x' => [
+            0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001,
+        ],
+        'y' => [
+            0b00000, 0b10001, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+        ],
+        'z' => [
+            0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '"' => [
+            0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '$' => [
+            0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100,
+        ],
+        '%' => [
+            0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011,
+        ],
+        '&' => [
+            0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+        ],
+        '*' => [
+            0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000,
+        ],
+        ';' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+        '<' => [
+            0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010,
+        ],
+        '>' => [
+            0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000,
+        ],
+        '@' => [
+            0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+        ],
+        '[' => [
+            0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110,
+        ],
+        '\\' => [
+            0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000,
+        ],
+        ']' => [
+            0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110,
+        ],
+        '^' => [
+            0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '`' => [
+            0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '{' => [
+            0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010,
+        ],
+        '|' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        '}' => [
+            0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000,
+        ],
+        '~' => [
+            0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000,
+        ],
+        // Half-width katakana, for the matrix rain
+        'ｱ' => [
+            0b11111, 0b00001, 0b00110, 0b00100, 0b00100, 0b01000, 0b10000,
+        ],
+        'ｲ' => [
+            0b00001, 0b00010, 0b00100, 0b01100, 0b10100, 0b00100, 0b00100,
+        ],
+        'ｳ' => [
+            0b00100, 0b11111, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000,
+        ],
+        'ｴ' => [
+            0b00000, 0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b11111,
+        ],
+        'ｵ' => [
+            0b00010, 0b11111, 0b00110, 0b01010, 0b10010, 0b00010, 0b00110,
+        ],
+        'ｶ' => [
+            0b01000, 0b11111, 0b01001, 0b01001, 0b01001, 0b10001, 0b10011,
+        ],
+        'ｷ' => [
+            0b01000, 0b11111, 0b00100, 0b11111, 0b00100, 0b00100, 0b00100,
+        ],
+        'ｸ' => [
+            0b01000, 0b01111, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000,
+        ],
+        'ｹ' => [
+            0b01000, 0b01111, 0b10100, 0b00100, 0b00100, 0b01000, 0b10000,
+        ],
+        'ｺ' => [
+            0b00000, 0b11111, 0b00001, 0b00001, 0b00001, 0b00001, 0b11111,
+        ],
+        'ｻ' => [
+            0b01010, 0b11111, 0b01010, 0b01010, 0b00010, 0b00100, 0b01000,
+        ],
+        'ｼ' => [
+            0b11000, 0b00001, 0b11001, 0b00001, 0b00010, 0b00100, 0b11000,
+        ],
+        'ﾂ' => [
+            0b00000, 0b10101, 0b10101, 0b00001, 0b00010, 0b00100, 0b11000,
+        ],
+        'ﾃ' => [
+            0b01110, 0b00000, 0b11111, 0b00100, 0b00100, 0b01000, 0b10000,
+        ],
+        'ﾅ' => [
+            0b00100, 0b11111, 0b00100, 0b00100, 0b00100, 0b01000, 0b10000,
+        ],
+        'ﾆ' => [
+            0b00000, 0b01110, 0b00000, 0b00000, 0b00000, 0b11111, 0b00000,
+        ],
+        'ﾊ' => [
+            0b00000, 0b01010, 0b01010, 0b10001, 0b10001, 0b10001, 0b00000,
+        ],
+        'ﾋ' => [
+            0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000, 0b01111,
+        ],
+        'ﾎ' => [
+            0b00100, 0b11111, 0b00100, 0b10101, 0b10101, 0b00100, 0b00100,
+        ],
+        'ﾐ' => [
+            0b11100, 0b00011, 0b11000, 0b00110, 0b00000, 0b11100, 0b00011,
+        ],
+        'ﾑ' => [
+            0b00100, 0b00100, 0b01000, 0b01000, 0b10010, 0b11111, 0b00001,
+        ],
+        'ﾓ' => [
+            0b01110, 0b00100, 0b11111, 0b00100, 0b00100, 0b00100, 0b00011,
+        ],
+        'ﾗ' => [
+            0b01110, 0b00000, 0b11111, 0b00001, 0b00010, 0b00100, 0b01000,
+        ],
+        'ﾘ' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b00001, 0b00010, 0b00100,
+        ],
+        'ﾜ' => [
+            0b11111, 0b10001, 0b00001, 0b00001, 0b00010, 0b00100, 0b01000,
+        ],
+        'ﾝ' => [
+            0b10000, 0b01000, 0b00001, 0b00001, 0b00010, 0b00100, 0b11000,
+        ],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// Whether pixel (`x`, `y`) of `ch` is lit; unknown characters are blank
+pub fn pixel(ch: char, x: u32, y: u32) -> bool {
+    match glyph(ch) {
+        Some(rows) if x < GLYPH_W && y < GLYPH_H => {
+            rows[y as usize] & (1 << (GLYPH_W - 1 - x)) != 0
+        }
+        _ => false,
+    }
+}
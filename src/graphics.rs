@@ -1,3 +1,4 @@
+use crate::font;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
@@ -35,30 +36,215 @@ impl Color {
     pub const MAGENTA: Color = Color::new(255, 0, 255, 255);
 }
 
+/// Inclusive pixel bounds of everything drawn since the last present
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirtyRect {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl DirtyRect {
+    pub fn width(&self) -> u32 {
+        self.x1 - self.x0 + 1
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1 - self.y0 + 1
+    }
+}
+
 /// High-performance frame buffer with batch operations
+/// Horizontal advance of one character at `scale`, including the 1px gap
+pub const fn glyph_advance(scale: u32) -> u32 {
+    (font::GLYPH_W + 1) * scale
+}
+
+/// Pixel size of `text` drawn at `scale`: the widest line by the line count,
+/// without the trailing gap after the last glyph
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let lines = text.split('\n');
+    let (mut cols, mut rows) = (0, 0);
+    for line in lines {
+        cols = cols.max(line.chars().count() as u32);
+        rows += 1;
+    }
+    let width = (cols * glyph_advance(scale)).saturating_sub(scale);
+    let height = rows * (font::GLYPH_H + 1) * scale - scale;
+    (width, height)
+}
+
+/// Visit every lit font pixel of `text` in unscaled font coordinates.
+/// Newlines start a new row; characters the font lacks draw as `?`.
+fn for_each_text_pixel(text: &str, mut plot: impl FnMut(u32, u32)) {
+    let (mut col, mut row) = (0, 0);
+    for ch in text.chars() {
+        if ch == '\n' {
+            col = 0;
+            row += 1;
+            continue;
+        }
+        let rows = font::glyph(ch)
+            .or_else(|| font::glyph('?'))
+            .unwrap_or_default();
+        for (fy, bits) in rows.iter().enumerate() {
+            for fx in 0..font::GLYPH_W {
+                if bits & (1 << (font::GLYPH_W - 1 - fx)) != 0 {
+                    plot(
+                        col * (font::GLYPH_W + 1) + fx,
+                        row * (font::GLYPH_H + 1) + fy as u32,
+                    );
+                }
+            }
+        }
+        col += 1;
+    }
+}
+
+/// How a sprite's pixels combine with what is already in the frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transparency {
+    /// Every pixel overwrites the frame
+    Opaque,
+    /// Pixels of exactly this RGB are skipped, the rest overwrite
+    ColorKey(u8, u8, u8),
+    /// Each pixel is blended by its alpha byte
+    Alpha,
+}
+
+/// An RGBA bitmap that can be blitted into a `FrameBuffer`
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<u8>,
+    pub transparency: Transparency,
+}
+
+impl Sprite {
+    /// Wrap `width * height * 4` bytes of row-major RGBA, blended by alpha
+    pub fn from_rgba(bytes: Vec<u8>, width: u32, height: u32) -> Result<Sprite, String> {
+        let expected = width as usize * height as usize * 4;
+        if bytes.len() != expected {
+            return Err(format!(
+                "sprite is {}x{} and needs {} bytes, got {}",
+                width,
+                height,
+                expected,
+                bytes.len()
+            ));
+        }
+        Ok(Sprite {
+            width,
+            height,
+            pixels: bytes,
+            transparency: Transparency::Alpha,
+        })
+    }
+
+    /// Skip pixels of this color instead of reading alpha
+    pub fn with_color_key(mut self, r: u8, g: u8, b: u8) -> Self {
+        self.transparency = Transparency::ColorKey(r, g, b);
+        self
+    }
+
+    pub fn with_transparency(mut self, transparency: Transparency) -> Self {
+        self.transparency = transparency;
+        self
+    }
+
+    /// RGBA of pixel (`x`, `y`); the caller keeps it in bounds
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
 pub struct FrameBuffer {
     pub width: u32,
     pub height: u32,
     pub pixels: Vec<u8>,
     // Cached values for fast access
     pub stride: usize,
+    // Region drawn since the last take_dirty; writes straight to `pixels`
+    // must call mark_dirty themselves
+    dirty: Option<DirtyRect>,
 }
 
 impl FrameBuffer {
     pub fn new(width: u32, height: u32) -> Self {
         let size = (width * height * 4) as usize;
         let pixels = vec![0; size];
-        FrameBuffer {
+        let mut fb = FrameBuffer {
             width,
             height,
             pixels,
             stride: (width * 4) as usize,
+            dirty: None,
+        };
+        // A new canvas needs one full upload
+        fb.mark_all_dirty();
+        fb
+    }
+
+    /// Grow the dirty region to cover x0..=x1, y0..=y1 (already clipped)
+    #[inline(always)]
+    fn touch(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        self.dirty = Some(match self.dirty {
+            Some(d) => DirtyRect {
+                x0: d.x0.min(x0),
+                y0: d.y0.min(y0),
+                x1: d.x1.max(x1),
+                y1: d.y1.max(y1),
+            },
+            None => DirtyRect { x0, y0, x1, y1 },
+        });
+    }
+
+    /// Record a direct write to `pixels`
+    pub fn mark_dirty(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        let x1 = x.saturating_add(w).min(self.width);
+        let y1 = y.saturating_add(h).min(self.height);
+        if x < x1 && y < y1 {
+            self.touch(x, y, x1 - 1, y1 - 1);
         }
     }
 
+    pub fn mark_all_dirty(&mut self) {
+        self.mark_dirty(0, 0, self.width, self.height);
+    }
+
+    /// Region drawn since the last call, resetting the tracking
+    pub fn take_dirty(&mut self) -> Option<DirtyRect> {
+        self.dirty.take()
+    }
+
+    /// RGBA rows of `rect`, packed for an ImageData of the rect's size
+    pub fn copy_rect(&self, rect: &DirtyRect) -> Vec<u8> {
+        let row_bytes = rect.width() as usize * 4;
+        let mut out = Vec::with_capacity(row_bytes * rect.height() as usize);
+        for y in rect.y0..=rect.y1 {
+            let start = y as usize * self.stride + rect.x0 as usize * 4;
+            out.extend_from_slice(&self.pixels[start..start + row_bytes]);
+        }
+        out
+    }
+
     /// Ultra-fast clear using memset-like pattern
     #[inline]
     pub fn clear(&mut self, color: &Color) {
+        self.mark_all_dirty();
         // Create a 4-byte pattern that can be repeated
         let pattern = [color.r, color.g, color.b, color.a];
 
@@ -95,6 +281,7 @@ impl FrameBuffer {
     /// Fast clear to black (optimized memset to 0)
     #[inline]
     pub fn clear_black(&mut self) {
+        self.mark_all_dirty();
         self.pixels.fill(0);
         // Set alpha channel to 255
         let len = self.pixels.len();
@@ -108,6 +295,7 @@ impl FrameBuffer {
     #[inline(always)]
     pub fn set_pixel(&mut self, x: u32, y: u32, color: &Color) {
         if x < self.width && y < self.height {
+            self.touch(x, y, x, y);
             let idx = (y as usize * self.stride) + (x as usize * 4);
             unsafe {
                 *self.pixels.get_unchecked_mut(idx) = color.r;
@@ -122,6 +310,7 @@ impl FrameBuffer {
     #[inline(always)]
     pub fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
         if x < self.width && y < self.height {
+            self.touch(x, y, x, y);
             let idx = (y as usize * self.stride) + (x as usize * 4);
             unsafe {
                 *self.pixels.get_unchecked_mut(idx) = r;
@@ -140,6 +329,7 @@ impl FrameBuffer {
     /// cause undefined behavior by accessing out-of-bounds memory.
     #[inline(always)]
     pub unsafe fn set_pixel_unchecked(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
+        self.touch(x, y, x, y);
         let idx = (y as usize * self.stride) + (x as usize * 4);
         *self.pixels.get_unchecked_mut(idx) = r;
         *self.pixels.get_unchecked_mut(idx + 1) = g;
@@ -155,6 +345,7 @@ impl FrameBuffer {
         }
         let y0 = y_start.min(self.height - 1);
         let y1 = y_end.min(self.height - 1);
+        self.touch(x, y0, x, y1);
 
         let mut idx = (y0 as usize * self.stride) + (x as usize * 4);
         for _ in y0..=y1 {
@@ -187,6 +378,7 @@ impl FrameBuffer {
         let y0 = y_start.min(self.height - 1);
         let y1 = y_end.min(self.height - 1);
 
+        self.touch(x, y0, x, y1);
         let sr = (r as f32 * shade) as u8;
         let sg = (g as f32 * shade) as u8;
         let sb = (b as f32 * shade) as u8;
@@ -211,6 +403,7 @@ impl FrameBuffer {
         }
         let x0 = x_start.min(self.width - 1);
         let x1 = x_end.min(self.width - 1);
+        self.touch(x0, y, x1, y);
 
         let row_start = (y as usize * self.stride) + (x0 as usize * 4);
         for x in x0..=x1 {
@@ -245,6 +438,7 @@ impl FrameBuffer {
         let x0 = x_start.min(self.width - 1);
         let x1 = x_end.min(self.width - 1);
         let span = (x1 - x0) as f32;
+        self.touch(x0, y, x1, y);
 
         let row_start = (y as usize * self.stride) + (x0 as usize * 4);
         for x in 0..(x1 - x0) {
@@ -265,6 +459,7 @@ impl FrameBuffer {
     pub fn draw_rect(&mut self, x: u32, y: u32, w: u32, h: u32, color: &Color) {
         let x_end = (x + w).min(self.width);
         let y_end = (y + h).min(self.height);
+        self.mark_dirty(x, y, w, h);
 
         for dy in y..y_end {
             let row_start = dy as usize * self.stride;
@@ -292,6 +487,108 @@ impl FrameBuffer {
         }
     }
 
+    /// Draw a 5x7 glyph bitmap (bit 4 leftmost) with each font pixel as a
+    /// `scale_x` x `scale_y` block
+    pub fn draw_glyph(
+        &mut self,
+        rows: &[u8; 7],
+        x: u32,
+        y: u32,
+        scale_x: u32,
+        scale_y: u32,
+        color: &Color,
+    ) {
+        for (fy, bits) in rows.iter().enumerate() {
+            for fx in 0..font::GLYPH_W {
+                if bits & (1 << (font::GLYPH_W - 1 - fx)) == 0 {
+                    continue;
+                }
+                let px = x.saturating_add(fx * scale_x);
+                let py = y.saturating_add(fy as u32 * scale_y);
+                if px < self.width && py < self.height {
+                    self.fill_rect(px, py, scale_x, scale_y, color.r, color.g, color.b);
+                }
+            }
+        }
+    }
+
+    /// Draw one character of the bitmap font at `scale`
+    pub fn draw_char(&mut self, ch: char, x: u32, y: u32, scale: u32, color: &Color) {
+        let rows = font::glyph(ch)
+            .or_else(|| font::glyph('?'))
+            .unwrap_or_default();
+        self.draw_glyph(&rows, x, y, scale, scale, color);
+    }
+
+    /// Draw `text` with its top-left corner at (`x`, `y`); returns the width
+    /// in pixels of the widest line
+    pub fn draw_text(&mut self, text: &str, x: u32, y: u32, scale: u32, color: &Color) -> u32 {
+        for_each_text_pixel(text, |fx, fy| {
+            let px = x.saturating_add(fx * scale);
+            let py = y.saturating_add(fy * scale);
+            if px < self.width && py < self.height {
+                self.fill_rect(px, py, scale, scale, color.r, color.g, color.b);
+            }
+        });
+        text_size(text, scale).0
+    }
+
+    /// Draw `src` at its own size with its top-left at (`x`, `y`), which may
+    /// be partly or wholly off screen
+    pub fn blit(&mut self, src: &Sprite, x: i32, y: i32) {
+        self.blit_scaled(src, x, y, src.width, src.height, false);
+    }
+
+    /// Draw `src` stretched to `w` x `h` (nearest neighbour), mirrored
+    /// left-to-right when `flip_x` is set
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_scaled(&mut self, src: &Sprite, x: i32, y: i32, w: u32, h: u32, flip_x: bool) {
+        if src.width == 0 || src.height == 0 || w == 0 || h == 0 {
+            return;
+        }
+        // Clip the destination rect to the frame
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = (x + w as i32).min(self.width as i32);
+        let y1 = (y + h as i32).min(self.height as i32);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        self.touch(x0 as u32, y0 as u32, x1 as u32 - 1, y1 as u32 - 1);
+
+        for dy in y0..y1 {
+            let sy = ((dy - y) as u64 * src.height as u64 / h as u64) as u32;
+            let row = dy as usize * self.stride;
+            for dx in x0..x1 {
+                let mut sx = ((dx - x) as u64 * src.width as u64 / w as u64) as u32;
+                if flip_x {
+                    sx = src.width - 1 - sx;
+                }
+                let [r, g, b, a] = src.pixel(sx, sy);
+                let idx = row + dx as usize * 4;
+                let dst = &mut self.pixels[idx..idx + 4];
+                match src.transparency {
+                    Transparency::ColorKey(kr, kg, kb) if (r, g, b) == (kr, kg, kb) => continue,
+                    Transparency::Alpha if a == 0 => continue,
+                    Transparency::Alpha if a < 255 => {
+                        let a = a as u16;
+                        let blend =
+                            |s: u8, d: u8| ((s as u16 * a + d as u16 * (255 - a)) / 255) as u8;
+                        dst[0] = blend(r, dst[0]);
+                        dst[1] = blend(g, dst[1]);
+                        dst[2] = blend(b, dst[2]);
+                    }
+                    _ => {
+                        dst[0] = r;
+                        dst[1] = g;
+                        dst[2] = b;
+                    }
+                }
+                dst[3] = 255;
+            }
+        }
+    }
+
     pub fn draw_circle(&mut self, cx: u32, cy: u32, radius: u32, color: &Color) {
         let r2 = (radius * radius) as i32;
         for dy in 0..=radius {
@@ -459,6 +756,38 @@ impl FrameBuffer {
     }
 }
 
+/// A canvas the games draw into, implemented by the software renderer
+/// (`Graphics`, 2d canvas) and the WebGL one (`WebGlGraphics`) so the choice
+/// can be made at runtime
+#[allow(clippy::too_many_arguments)]
+pub trait Renderer {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    fn clear(&mut self, r: u8, g: u8, b: u8);
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8);
+    fn draw_hline(&mut self, x_start: u32, x_end: u32, y: u32, r: u8, g: u8, b: u8);
+    fn draw_vline(&mut self, x: u32, y_start: u32, y_end: u32, r: u8, g: u8, b: u8);
+    fn draw_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8);
+    fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8);
+    /// Draw `text` in the bitmap font at `scale`; returns the width drawn
+    fn draw_text(&mut self, text: &str, x: u32, y: u32, scale: u32, r: u8, g: u8, b: u8) -> u32 {
+        let (width, height) = (self.width(), self.height());
+        for_each_text_pixel(text, |fx, fy| {
+            let px = x.saturating_add(fx * scale);
+            let py = y.saturating_add(fy * scale);
+            if px < width && py < height {
+                self.fill_rect(px, py, scale, scale, r, g, b);
+            }
+        });
+        text_size(text, scale).0
+    }
+    /// Put the finished frame on screen
+    fn present(&mut self) -> Result<(), JsValue>;
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue>;
+    /// "soft" or "gl"
+    fn name(&self) -> &'static str;
+}
+
 #[wasm_bindgen]
 pub struct Graphics {
     #[allow(dead_code)]
@@ -533,7 +862,23 @@ impl Graphics {
         self.buffer.draw_line(x0, y0, x1, y1, &color);
     }
 
-    pub fn present(&self) -> Result<(), JsValue> {
+    /// Upload what changed since the last present. A full frame goes up as
+    /// is; a smaller dirty region is copied out and put at its offset.
+    pub fn present(&mut self) -> Result<(), JsValue> {
+        let Some(dirty) = self.buffer.take_dirty() else {
+            return Ok(());
+        };
+        if dirty.width() < self.buffer.width || dirty.height() < self.buffer.height {
+            let region = self.buffer.copy_rect(&dirty);
+            let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+                wasm_bindgen::Clamped(&region),
+                dirty.width(),
+                dirty.height(),
+            )?;
+            return self
+                .context
+                .put_image_data(&image_data, dirty.x0 as f64, dirty.y0 as f64);
+        }
         let expected_size = (self.buffer.width * self.buffer.height * 4) as usize;
         if self.buffer.pixels.len() != expected_size {
             web_sys::console::error_1(
@@ -581,6 +926,40 @@ impl Graphics {
         self.buffer.fill_rect(x, y, w, h, r, g, b);
     }
 
+    /// Draw `text` in the bitmap font; returns the width in pixels
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        x: u32,
+        y: u32,
+        scale: u32,
+        r: u8,
+        g: u8,
+        b: u8,
+    ) -> u32 {
+        let color = Color::rgb(r, g, b);
+        self.buffer.draw_text(text, x, y, scale, &color)
+    }
+
+    /// Draw an RGBA image (e.g. from `ImageData.data`) scaled to `w` x `h`,
+    /// alpha-blended over the frame
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_image(
+        &mut self,
+        rgba: Vec<u8>,
+        src_w: u32,
+        src_h: u32,
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+    ) -> Result<(), JsValue> {
+        let sprite = Sprite::from_rgba(rgba, src_w, src_h).map_err(|e| JsValue::from_str(&e))?;
+        self.buffer.blit_scaled(&sprite, x, y, w, h, false);
+        Ok(())
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
         if width == self.buffer.width && height == self.buffer.height {
             return Ok(());
@@ -592,6 +971,115 @@ impl Graphics {
     }
 }
 
+impl Graphics {
+    /// The pixels behind this canvas, for drawing with the full FrameBuffer API
+    pub fn frame_buffer(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
+    }
+}
+
+impl Renderer for Graphics {
+    fn width(&self) -> u32 {
+        Graphics::width(self)
+    }
+    fn height(&self) -> u32 {
+        Graphics::height(self)
+    }
+    fn clear(&mut self, r: u8, g: u8, b: u8) {
+        Graphics::clear(self, r, g, b);
+    }
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
+        Graphics::set_pixel_rgb(self, x, y, r, g, b);
+    }
+    fn draw_hline(&mut self, x_start: u32, x_end: u32, y: u32, r: u8, g: u8, b: u8) {
+        Graphics::draw_hline(self, x_start, x_end, y, r, g, b);
+    }
+    fn draw_vline(&mut self, x: u32, y_start: u32, y_end: u32, r: u8, g: u8, b: u8) {
+        Graphics::draw_vline(self, x, y_start, y_end, r, g, b);
+    }
+    fn draw_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8) {
+        Graphics::draw_rect(self, x, y, w, h, r, g, b);
+    }
+    fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8) {
+        Graphics::fill_rect(self, x, y, w, h, r, g, b);
+    }
+    fn draw_text(&mut self, text: &str, x: u32, y: u32, scale: u32, r: u8, g: u8, b: u8) -> u32 {
+        Graphics::draw_text(self, text, x, y, scale, r, g, b)
+    }
+    fn present(&mut self) -> Result<(), JsValue> {
+        Graphics::present(self)
+    }
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        Graphics::resize(self, width, height)
+    }
+    fn name(&self) -> &'static str {
+        "soft"
+    }
+}
+
+/// A renderer with no canvas behind it, for drawing frames nobody sees
+/// (`sysbench` timing the raycaster)
+pub struct Offscreen {
+    buffer: FrameBuffer,
+}
+
+impl Offscreen {
+    pub fn new(width: u32, height: u32) -> Self {
+        Offscreen {
+            buffer: FrameBuffer::new(width, height),
+        }
+    }
+
+    pub fn frame_buffer(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
+    }
+}
+
+impl Renderer for Offscreen {
+    fn width(&self) -> u32 {
+        self.buffer.width
+    }
+    fn height(&self) -> u32 {
+        self.buffer.height
+    }
+    fn clear(&mut self, r: u8, g: u8, b: u8) {
+        self.buffer.clear(&Color::rgb(r, g, b));
+    }
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
+        self.buffer.set_pixel_rgb(x, y, r, g, b);
+    }
+    fn draw_hline(&mut self, x_start: u32, x_end: u32, y: u32, r: u8, g: u8, b: u8) {
+        self.buffer.draw_hline(x_start, x_end, y, r, g, b);
+    }
+    fn draw_vline(&mut self, x: u32, y_start: u32, y_end: u32, r: u8, g: u8, b: u8) {
+        self.buffer.draw_vline(x, y_start, y_end, r, g, b);
+    }
+    fn draw_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8) {
+        self.buffer.draw_rect(x, y, w, h, &Color::rgb(r, g, b));
+    }
+    fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8) {
+        self.buffer.fill_rect(x, y, w, h, r, g, b);
+    }
+    fn draw_text(&mut self, text: &str, x: u32, y: u32, scale: u32, r: u8, g: u8, b: u8) -> u32 {
+        self.buffer
+            .draw_text(text, x, y, scale, &Color::rgb(r, g, b))
+    }
+    /// Nothing to show; just forget what was drawn
+    fn present(&mut self) -> Result<(), JsValue> {
+        self.buffer.take_dirty();
+        Ok(())
+    }
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        if width != self.buffer.width || height != self.buffer.height {
+            self.buffer = FrameBuffer::new(width, height);
+        }
+        Ok(())
+    }
+    fn name(&self) -> &'static str {
+        "offscreen"
+    }
+}
+
 // Snake Game Implementation
 #[wasm_bindgen]
 pub struct SnakeGame {
@@ -705,6 +1193,14 @@ impl SnakeGame {
             0,
             0,
         );
+
+        gfx.draw_text(&format!("SCORE {}", self.score), 4, 4, 2, 255, 255, 255);
+        if self.game_over {
+            let (w, h) = text_size("GAME OVER", 3);
+            let x = (self.width / 2).saturating_sub(w / 2);
+            let y = (self.height / 2).saturating_sub(h / 2);
+            gfx.draw_text("GAME OVER", x, y, 3, 255, 80, 80);
+        }
     }
 
     pub fn set_direction(&mut self, dir: &str) {
@@ -766,166 +1262,464 @@ impl SnakeGame {
 }
 
 // Screensaver - Matrix rain effect
+
+/// What the rain is made of: half-width katakana, as cmatrix draws, with
+/// digits and a few letters mixed in
+const MATRIX_GLYPHS: &[char] = &[
+    'ｱ', 'ｲ', 'ｳ', 'ｴ', 'ｵ', 'ｶ', 'ｷ', 'ｸ', 'ｹ', 'ｺ', 'ｻ', 'ｼ', 'ﾂ', 'ﾃ', 'ﾅ', 'ﾆ', 'ﾊ', 'ﾋ', 'ﾎ',
+    'ﾐ', 'ﾑ', 'ﾓ', 'ﾗ', 'ﾘ', 'ﾜ', 'ﾝ', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'Z', 'T',
+    'E', ':', '*', '+', '<', '>',
+];
+/// Pixels per font pixel; a glyph is 10x14 inside a 12x16 cell
+const MATRIX_SCALE: u32 = 2;
+/// Chance per frame that a lit trail glyph turns into another
+const MATRIX_SHIMMER: f64 = 0.02;
+
+/// The colour the matrix rain falls in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatrixColor {
+    Green,
+    Amber,
+    Blue,
+}
+
+impl MatrixColor {
+    pub const ALL: [MatrixColor; 3] = [MatrixColor::Green, MatrixColor::Amber, MatrixColor::Blue];
+
+    pub fn parse(name: &str) -> Option<MatrixColor> {
+        match name {
+            "green" => Some(MatrixColor::Green),
+            "amber" | "yellow" => Some(MatrixColor::Amber),
+            "blue" | "cyan" => Some(MatrixColor::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MatrixColor::Green => "green",
+            MatrixColor::Amber => "amber",
+            MatrixColor::Blue => "blue",
+        }
+    }
+
+    /// Full-brightness trail colour
+    fn trail(self) -> (u8, u8, u8) {
+        match self {
+            MatrixColor::Green => (0, 255, 70),
+            MatrixColor::Amber => (255, 176, 0),
+            MatrixColor::Blue => (40, 150, 255),
+        }
+    }
+
+    /// The near-white a falling head glows in
+    fn head(self) -> (u8, u8, u8) {
+        match self {
+            MatrixColor::Green => (215, 255, 215),
+            MatrixColor::Amber => (255, 240, 200),
+            MatrixColor::Blue => (210, 235, 255),
+        }
+    }
+}
+
+/// How the matrix rain looks: its colour, the percentage of columns
+/// raining at once, and a multiplier on how fast they fall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatrixStyle {
+    pub color: MatrixColor,
+    pub density: u32,
+    pub speed: f64,
+}
+
+impl MatrixStyle {
+    pub const DEFAULT: MatrixStyle = MatrixStyle {
+        color: MatrixColor::Green,
+        density: 70,
+        speed: 1.0,
+    };
+    pub const DENSITY_RANGE: std::ops::RangeInclusive<u32> = 5..=100;
+    pub const SPEED_RANGE: std::ops::RangeInclusive<f64> = 0.25..=4.0;
+}
+
+impl Default for MatrixStyle {
+    fn default() -> Self {
+        MatrixStyle::DEFAULT
+    }
+}
+
 #[wasm_bindgen]
 pub struct MatrixScreensaver {
-    width: u32,
     height: u32,
     cell_w: u32,
     cell_h: u32,
-    frame: u64,
+    slots: u32,
+    rows: u32,
+    style: MatrixStyle,
+    rng: u32,
     columns: Vec<MatrixColumn>,
 }
 
 struct MatrixColumn {
-    x: u32,
+    slot: u32,
+    /// Top of the head in pixels; the head sits in the cell it falls in
     head_y: f32,
     speed: f32,
     length: u32,
-    glyph_phase: u32,
+    /// One glyph per screen row, shimmering while lit
+    glyphs: Vec<char>,
 }
 
 #[wasm_bindgen]
 impl MatrixScreensaver {
     #[wasm_bindgen(constructor)]
     pub fn new(width: u32, height: u32) -> Self {
-        let cell_w = 10;
-        let cell_h = 16;
-        let num_columns = (width / cell_w).max(1);
-        let mut columns = Vec::with_capacity(num_columns as usize);
+        let seed = (js_sys::Math::random() * u32::MAX as f64) as u32;
+        Self::with_style(width, height, MatrixStyle::default(), seed)
+    }
+
+    pub fn update(&mut self) {
+        for i in 0..self.columns.len() {
+            let speed = self.columns[i].speed;
+            self.columns[i].head_y += speed;
+
+            // Rare drift creates the "digital rain" cadence seen in cmatrix.
+            if self.random() < 0.012 {
+                self.columns[i].speed = self.fall_speed();
+            }
+            if self.random() < 0.008 {
+                self.columns[i].length = 8 + (self.random() * 32.0) as u32;
+            }
+            let head_row = self.columns[i].head_y as i32 / self.cell_h as i32;
+            let tail_row = head_row - self.columns[i].length as i32;
+            for row in tail_row.max(0)..head_row.min(self.rows as i32) {
+                if self.random() < MATRIX_SHIMMER {
+                    self.columns[i].glyphs[row as usize] = self.random_glyph();
+                }
+            }
 
-        for i in 0..num_columns {
-            columns.push(Self::new_column(i, cell_w, width, height));
+            if tail_row * self.cell_h as i32 > self.height as i32 {
+                self.columns[i] = self.new_column(false);
+            }
         }
+    }
 
-        Self {
-            width,
+    pub fn render(&self, gfx: &mut Graphics) {
+        self.draw(&mut gfx.buffer);
+    }
+}
+
+impl MatrixScreensaver {
+    /// A rain of `style` seeded with `seed`, so the same seed falls the same
+    pub fn with_style(width: u32, height: u32, style: MatrixStyle, seed: u32) -> Self {
+        let cell_w = (font::GLYPH_W + 1) * MATRIX_SCALE;
+        let cell_h = (font::GLYPH_H + 1) * MATRIX_SCALE;
+        let slots = (width / cell_w).max(1);
+        let mut saver = Self {
             height,
             cell_w,
             cell_h,
-            frame: 0,
-            columns,
+            slots,
+            rows: height.div_ceil(cell_h).max(1),
+            style,
+            // xorshift never leaves zero
+            rng: seed.max(1),
+            columns: Vec::new(),
+        };
+        let count = (slots * style.density.clamp(1, 100)).div_ceil(100);
+        for _ in 0..count {
+            let column = saver.new_column(true);
+            saver.columns.push(column);
         }
+        saver
     }
 
-    pub fn update(&mut self) {
-        self.frame = self.frame.wrapping_add(1);
-
-        for col in &mut self.columns {
-            col.head_y += col.speed;
+    pub fn style(&self) -> MatrixStyle {
+        self.style
+    }
 
-            // Rare drift creates the "digital rain" cadence seen in cmatrix.
-            if js_sys::Math::random() < 0.012 {
-                col.speed = (2.8 + js_sys::Math::random() as f32 * 9.0).min(15.0);
-            }
-            if js_sys::Math::random() < 0.008 {
-                col.length = 8 + (js_sys::Math::random() * 32.0) as u32;
-            }
+    /// Columns raining at once
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
 
-            let trail_px = (col.length as i32 * self.cell_h as i32) + (self.cell_h as i32 * 2);
-            if col.head_y as i32 - trail_px > self.height as i32 {
-                let x_slot = (col.x / self.cell_w) as usize;
-                *col = Self::new_column(x_slot as u32, self.cell_w, self.width, self.height);
+    /// Glyphs whose cell is on screen and lit, head first per column
+    pub fn lit_glyphs(&self) -> Vec<char> {
+        let mut lit = Vec::new();
+        for col in &self.columns {
+            let head_row = col.head_y as i32 / self.cell_h as i32;
+            for step in 0..col.length as i32 {
+                let row = head_row - step;
+                if (0..self.rows as i32).contains(&row) {
+                    lit.push(col.glyphs[row as usize]);
+                }
             }
         }
+        lit
     }
 
-    pub fn render(&self, gfx: &mut Graphics) {
+    /// Fade what is on screen, then draw every column's trail on the cell
+    /// grid, dimming towards the tail, with a glowing head
+    pub fn draw(&self, fb: &mut FrameBuffer) {
         // Fade effect keeps phosphor-like trails.
-        let gfx_height = gfx.height();
-        crate::cpp_accel::fade_rgba_sub(&mut gfx.buffer.pixels, 10, 16, 10);
+        crate::cpp_accel::fade_rgba_sub(&mut fb.pixels, 14, 14, 14);
+        // The fade touches every pixel behind the dirty tracking's back
+        fb.mark_all_dirty();
 
+        let (tr, tg, tb) = self.style.color.trail();
+        let head = self.style.color.head();
         for col in &self.columns {
-            let max_steps = col.length as i32;
-            for step in 0..max_steps {
-                let y = col.head_y as i32 - (step * self.cell_h as i32);
-                if y < -(self.cell_h as i32) || y >= gfx_height as i32 {
+            let x = col.slot * self.cell_w;
+            let head_row = col.head_y as i32 / self.cell_h as i32;
+            let steps = col.length.max(1) as i32;
+            for step in (0..steps).rev() {
+                let row = head_row - step;
+                if row < 0 || row >= self.rows as i32 {
                     continue;
                 }
-
-                let is_head = step == 0;
-                let intensity = if is_head {
-                    255
-                } else {
-                    let falloff = (step as f32 / max_steps.max(1) as f32).min(1.0);
-                    (210.0 * (1.0 - falloff)).max(20.0) as u8
-                };
-
-                let (r, g, b) = if is_head {
-                    (215, 255, 215)
-                } else {
-                    (0, intensity, 0)
-                };
-
-                let glyph_seed = self
-                    .frame
-                    .wrapping_add((col.glyph_phase as u64).wrapping_mul(7919))
-                    .wrapping_add((step as u64).wrapping_mul(97))
-                    .wrapping_add(col.x as u64 * 17);
-
-                self.draw_glyph(gfx, col.x, y as u32, glyph_seed as u32, (r, g, b));
+                let y = row as u32 * self.cell_h;
+                let glyph = font::glyph(col.glyphs[row as usize]).unwrap_or_default();
+                fb.fill_rect(x, y, self.cell_w, self.cell_h, 0, 0, 0);
+                if step == 0 {
+                    // A dim copy around the head, then the head on top
+                    let glow = Color::rgb(tr / 3, tg / 3, tb / 3);
+                    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                        let gx = (x as i32 + dx).max(0) as u32;
+                        let gy = (y as i32 + dy).max(0) as u32;
+                        fb.draw_glyph(&glyph, gx, gy, MATRIX_SCALE, MATRIX_SCALE, &glow);
+                    }
+                    let color = Color::rgb(head.0, head.1, head.2);
+                    fb.draw_glyph(&glyph, x, y, MATRIX_SCALE, MATRIX_SCALE, &color);
+                    continue;
+                }
+                // The cell just behind the head is brightest
+                let fall = step as f32 / steps as f32;
+                let level = (1.0 - fall).max(0.08);
+                let color = Color::rgb(
+                    (tr as f32 * level) as u8,
+                    (tg as f32 * level) as u8,
+                    (tb as f32 * level) as u8,
+                );
+                fb.draw_glyph(&glyph, x, y, MATRIX_SCALE, MATRIX_SCALE, &color);
             }
         }
     }
 
-    fn new_column(slot: u32, cell_w: u32, width: u32, height: u32) -> MatrixColumn {
-        let mut x = slot.saturating_mul(cell_w);
-        if cell_w > 2 {
-            x = x.saturating_add((js_sys::Math::random() * (cell_w - 2) as f64) as u32);
-        }
-        x = x.min(width.saturating_sub(1));
-
+    /// A column in a random slot with fresh glyphs; `scatter` starts it
+    /// anywhere above the screen so the first frame isn't one flat wave
+    fn new_column(&mut self, scatter: bool) -> MatrixColumn {
+        let slot = (self.random() * self.slots as f64) as u32 % self.slots;
+        let above = if scatter {
+            self.random() * self.height as f64
+        } else {
+            self.random() * self.cell_h as f64 * 8.0
+        };
+        let glyphs = (0..self.rows).map(|_| self.random_glyph()).collect();
         MatrixColumn {
-            x,
-            head_y: -((js_sys::Math::random() * height as f64) as f32),
-            speed: 2.8 + js_sys::Math::random() as f32 * 9.0,
-            length: 8 + (js_sys::Math::random() * 28.0) as u32,
-            glyph_phase: (js_sys::Math::random() * u32::MAX as f64) as u32,
+            slot,
+            head_y: -(above as f32),
+            speed: self.fall_speed(),
+            length: 8 + (self.random() * 28.0) as u32,
+            glyphs,
         }
     }
 
-    fn draw_glyph(&self, gfx: &mut Graphics, x: u32, y: u32, seed: u32, color: (u8, u8, u8)) {
-        // Synthetic 5x7 glyphs with slight variance approximate cmatrix character shimmer.
-        let scale_x = (self.cell_w / 5).max(1);
-        let scale_y = (self.cell_h / 8).max(1);
-        let glyph = Self::glyph_from_seed(seed);
-        let (r, g, b) = color;
+    /// Pixels per frame, scaled by the style's speed
+    fn fall_speed(&mut self) -> f32 {
+        let base = 2.8 + self.random() * 9.0;
+        (base.min(15.0) * self.style.speed) as f32
+    }
 
-        for row in 0..7u32 {
-            let bits = glyph[row as usize];
-            for col in 0..5u32 {
-                if (bits >> (4 - col)) & 1 == 0 {
-                    continue;
-                }
+    fn random_glyph(&mut self) -> char {
+        MATRIX_GLYPHS[(self.random() * MATRIX_GLYPHS.len() as f64) as usize % MATRIX_GLYPHS.len()]
+    }
 
-                let px = x.saturating_add(col * scale_x);
-                let py = y.saturating_add(row * scale_y);
-                for dy in 0..scale_y {
-                    for dx in 0..scale_x {
-                        let tx = px.saturating_add(dx);
-                        let ty = py.saturating_add(dy);
-                        if tx < gfx.width() && ty < gfx.height() {
-                            gfx.set_pixel(tx, ty, r, g, b);
-                        }
-                    }
-                }
-            }
+    // xorshift32, so a seeded rain is reproducible
+    fn random(&mut self) -> f64 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng = x;
+        x as f64 / (u32::MAX as f64 + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_rect_tracks_draws() {
+        let mut fb = FrameBuffer::new(64, 48);
+        // New buffers start fully dirty
+        assert_eq!(
+            fb.take_dirty(),
+            Some(DirtyRect {
+                x0: 0,
+                y0: 0,
+                x1: 63,
+                y1: 47
+            })
+        );
+        assert_eq!(fb.take_dirty(), None);
+
+        fb.set_pixel_rgb(10, 5, 255, 0, 0);
+        fb.fill_rect(20, 30, 4, 2, 0, 255, 0);
+        fb.set_pixel_rgb(100, 100, 0, 0, 255); // off screen, ignored
+        let dirty = fb.take_dirty().unwrap();
+        assert_eq!((dirty.x0, dirty.y0, dirty.x1, dirty.y1), (10, 5, 23, 31));
+
+        let region = fb.copy_rect(&dirty);
+        assert_eq!(region.len(), (dirty.width() * dirty.height() * 4) as usize);
+        assert_eq!(&region[..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_draw_text_uses_shared_font() {
+        let mut fb = FrameBuffer::new(64, 32);
+        let white = Color::rgb(255, 255, 255);
+        let lit = |fb: &FrameBuffer, x: u32, y: u32| fb.pixels[((y * 64 + x) * 4) as usize] == 255;
+
+        // 'I' is a full-width top bar and a centre stem
+        let width = fb.draw_text("I!", 0, 0, 2, &white);
+        assert_eq!(width, 22);
+        assert!(lit(&fb, 0, 0) && lit(&fb, 9, 1));
+        assert!(!lit(&fb, 0, 2) && lit(&fb, 4, 2));
+        // '!' starts one advance later
+        assert!(lit(&fb, 16, 0) && !lit(&fb, 12, 0));
+
+        // Lowercase has its own glyphs, unknown characters fall back to '?'
+        assert_ne!(font::glyph('a'), font::glyph('A'));
+        let mut a = FrameBuffer::new(8, 8);
+        let mut b = FrameBuffer::new(8, 8);
+        a.draw_char('\u{e9}', 0, 0, 1, &white);
+        b.draw_char('?', 0, 0, 1, &white);
+        assert_eq!(a.pixels, b.pixels);
+
+        assert_eq!(text_size("AB\nC", 1), (11, 15));
+        // Drawing past the edge is clipped rather than smeared along it
+        fb.draw_text("WWWW", 60, 30, 3, &white);
+        assert!(lit(&fb, 62, 30) && !lit(&fb, 63, 30));
+    }
+
+    #[test]
+    fn test_blit_transparency_scale_and_flip() {
+        let red = [255, 0, 0, 255];
+        let magenta = [255, 0, 255, 255];
+        let half_blue = [0, 0, 255, 128];
+        let clear = [9, 9, 9, 0];
+        let bytes: Vec<u8> = [red, magenta, half_blue, clear].concat();
+        assert!(Sprite::from_rgba(bytes[..12].to_vec(), 2, 2).is_err());
+        let sprite = Sprite::from_rgba(bytes, 2, 2).unwrap();
+        let px = |fb: &FrameBuffer, x: u32, y: u32| {
+            let i = ((y * fb.width + x) * 4) as usize;
+            [fb.pixels[i], fb.pixels[i + 1], fb.pixels[i + 2]]
+        };
+
+        // Alpha: opaque copies, half blends over white, zero is skipped
+        let mut fb = FrameBuffer::new(4, 4);
+        fb.clear(&Color::WHITE);
+        fb.blit(&sprite, 1, 1);
+        assert_eq!(px(&fb, 1, 1), [255, 0, 0]);
+        assert_eq!(px(&fb, 1, 2), [127, 127, 255]);
+        assert_eq!(px(&fb, 2, 2), [255, 255, 255]);
+
+        // Colour key drops magenta but draws the fully transparent pixel
+        let keyed = sprite.clone().with_color_key(255, 0, 255);
+        fb.clear_black();
+        fb.blit(&keyed, 0, 0);
+        assert_eq!(px(&fb, 1, 0), [0, 0, 0]);
+        assert_eq!(px(&fb, 1, 1), [9, 9, 9]);
+
+        // Doubled and mirrored, clipped at the top-left
+        let opaque = sprite.with_transparency(Transparency::Opaque);
+        fb.clear_black();
+        fb.take_dirty();
+        fb.blit_scaled(&opaque, -1, 0, 4, 4, true);
+        assert_eq!(px(&fb, 0, 0), [255, 0, 255]);
+        assert_eq!(px(&fb, 1, 0), [255, 0, 0]);
+        assert_eq!(px(&fb, 2, 0), [255, 0, 0]);
+        assert_eq!(px(&fb, 3, 0), [0, 0, 0]);
+        let dirty = fb.take_dirty().unwrap();
+        assert_eq!((dirty.x0, dirty.y0, dirty.x1, dirty.y1), (0, 0, 2, 3));
+    }
+
+    // A HUD-sized update should upload a small fraction of a full frame
+    #[test]
+    fn bench_dirty_present_vs_full_frame() {
+        let mut fb = FrameBuffer::new(1280, 720);
+        fb.take_dirty();
+        let frames = 200;
+
+        let start = std::time::Instant::now();
+        let mut full_bytes = 0;
+        for _ in 0..frames {
+            fb.clear_black();
+            let dirty = fb.take_dirty().unwrap();
+            full_bytes += fb.copy_rect(&dirty).len();
+        }
+        let full = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut hud_bytes = 0;
+        for frame in 0..frames {
+            fb.fill_rect(20, 620, 200, 20, (frame % 255) as u8, 200, 0);
+            let dirty = fb.take_dirty().unwrap();
+            hud_bytes += fb.copy_rect(&dirty).len();
         }
+        let hud = start.elapsed();
+
+        println!(
+            "{} frames: full {:?} ({} bytes), hud-only {:?} ({} bytes)",
+            frames, full, full_bytes, hud, hud_bytes
+        );
+        assert_eq!(full_bytes, frames * 1280 * 720 * 4);
+        assert_eq!(hud_bytes, frames * 200 * 20 * 4);
     }
 
-    fn glyph_from_seed(seed: u32) -> [u8; 7] {
-        let mut s = seed ^ 0x9e37_79b9;
-        let mut rows = [0u8; 7];
-        for row in &mut rows {
-            s ^= s << 13;
-            s ^= s >> 17;
-            s ^= s << 5;
-            let mut bits = (s & 0x1f) as u8;
-            // Ensure at least 2 pixels are lit so glyphs stay legible.
-            if bits.count_ones() < 2 {
-                bits |= 0b10001;
-            }
-            *row = bits;
+    #[test]
+    fn test_matrix_rain_draws_font_glyphs() {
+        // Every glyph the rain uses is in the shared font
+        assert!(MATRIX_GLYPHS.iter().all(|&c| font::glyph(c).is_some()));
+
+        let dense = MatrixStyle {
+            density: 100,
+            ..MatrixStyle::DEFAULT
+        };
+        let sparse = MatrixStyle {
+            density: 25,
+            ..MatrixStyle::DEFAULT
+        };
+        assert_eq!(
+            MatrixScreensaver::with_style(240, 160, dense, 7).column_count(),
+            20
+        );
+        assert_eq!(
+            MatrixScreensaver::with_style(240, 160, sparse, 7).column_count(),
+            5
+        );
+
+        let amber = MatrixStyle {
+            color: MatrixColor::Amber,
+            speed: 4.0,
+            ..dense
+        };
+        let mut rain = MatrixScreensaver::with_style(240, 160, amber, 7);
+        for _ in 0..60 {
+            rain.update();
         }
-        rows
+        let lit = rain.lit_glyphs();
+        assert!(!lit.is_empty());
+        assert!(lit.iter().all(|c| MATRIX_GLYPHS.contains(c)));
+
+        let mut fb = FrameBuffer::new(240, 160);
+        rain.draw(&mut fb);
+        let pixels: Vec<&[u8]> = fb.pixels.chunks(4).collect();
+        // Trails are amber, heads glow near white, and nothing is green
+        assert!(pixels
+            .iter()
+            .any(|p| p[0] > p[1] && p[1] > p[2] && p[0] > 100));
+        assert!(pixels.iter().any(|p| p[..3] == [255, 240, 200]));
+        assert!(!pixels.iter().any(|p| p[1] > p[0]));
     }
 }
@@ -1,11 +1,8 @@
-#[cfg(feature = "webgl")]
+use crate::graphics::Renderer;
 use wasm_bindgen::prelude::*;
-#[cfg(feature = "webgl")]
 use wasm_bindgen::JsCast;
-#[cfg(feature = "webgl")]
 use web_sys::{HtmlCanvasElement, WebGl2RenderingContext, WebGlProgram, WebGlShader, WebGlTexture};
 
-#[cfg(feature = "webgl")]
 pub struct WebGlGraphics {
     canvas: HtmlCanvasElement,
     gl: WebGl2RenderingContext, // fall back manually if needed
@@ -16,7 +13,6 @@ pub struct WebGlGraphics {
     pixels: Vec<u8>,
 }
 
-#[cfg(feature = "webgl")]
 fn get_canvas(id: &str, width: u32, height: u32) -> Result<HtmlCanvasElement, JsValue> {
     let window = web_sys::window().ok_or("no window")?;
     let doc = window.document().ok_or("no document")?;
@@ -27,7 +23,6 @@ fn get_canvas(id: &str, width: u32, height: u32) -> Result<HtmlCanvasElement, Js
     Ok(canvas)
 }
 
-#[cfg(feature = "webgl")]
 fn compile_shader(gl: &WebGl2RenderingContext, ty: u32, src: &str) -> Result<WebGlShader, JsValue> {
     let shader = gl.create_shader(ty).ok_or("shader alloc")?;
     gl.shader_source(&shader, src);
@@ -45,7 +40,6 @@ fn compile_shader(gl: &WebGl2RenderingContext, ty: u32, src: &str) -> Result<Web
     }
 }
 
-#[cfg(feature = "webgl")]
 fn link_program(
     gl: &WebGl2RenderingContext,
     vs: &WebGlShader,
@@ -68,7 +62,6 @@ fn link_program(
     }
 }
 
-#[cfg(feature = "webgl")]
 impl WebGlGraphics {
     pub fn new(canvas_id: &str, width: u32, height: u32) -> Result<Self, JsValue> {
         let canvas = get_canvas(canvas_id, width, height)?;
@@ -307,3 +300,39 @@ impl WebGlGraphics {
         Ok(())
     }
 }
+
+impl Renderer for WebGlGraphics {
+    fn width(&self) -> u32 {
+        WebGlGraphics::width(self)
+    }
+    fn height(&self) -> u32 {
+        WebGlGraphics::height(self)
+    }
+    fn clear(&mut self, r: u8, g: u8, b: u8) {
+        WebGlGraphics::clear(self, r, g, b);
+    }
+    fn set_pixel_rgb(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8) {
+        WebGlGraphics::set_pixel_rgb(self, x, y, r, g, b);
+    }
+    fn draw_hline(&mut self, x_start: u32, x_end: u32, y: u32, r: u8, g: u8, b: u8) {
+        WebGlGraphics::draw_hline(self, x_start, x_end, y, r, g, b);
+    }
+    fn draw_vline(&mut self, x: u32, y_start: u32, y_end: u32, r: u8, g: u8, b: u8) {
+        WebGlGraphics::draw_vline(self, x, y_start, y_end, r, g, b);
+    }
+    fn draw_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8) {
+        WebGlGraphics::draw_rect(self, x, y, w, h, r, g, b);
+    }
+    fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, r: u8, g: u8, b: u8) {
+        WebGlGraphics::fill_rect(self, x, y, w, h, r, g, b);
+    }
+    fn present(&mut self) -> Result<(), JsValue> {
+        WebGlGraphics::present(self)
+    }
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        WebGlGraphics::resize(self, width, height)
+    }
+    fn name(&self) -> &'static str {
+        "gl"
+    }
+}
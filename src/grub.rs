@@ -1,7 +1,96 @@
-use std::hash::{DefaultHasher, Hash, Hasher};
 use wasm_bindgen::prelude::*;
 
 const DEFAULT_TIMEOUT_SECS: u32 = 15;
+const ADVANCED_TITLE: &str = "Advanced options for kpawnd GNU/Linux";
+const MEMTEST_TITLE: &str = "Memory test (memtest86+)";
+
+/// One `menuentry` from grub.cfg
+#[derive(Clone, Debug, PartialEq)]
+pub struct BootEntry {
+    pub title: String,
+    pub kernel_version: String,
+    pub cmdline: String,
+}
+
+impl BootEntry {
+    fn new(title: &str, kernel_version: &str, cmdline: &str) -> BootEntry {
+        BootEntry {
+            title: title.to_string(),
+            kernel_version: kernel_version.to_string(),
+            cmdline: cmdline.to_string(),
+        }
+    }
+
+    // `linux /boot/vmlinuz-<version> <cmdline>`; false if the image path
+    // doesn't name a kernel
+    fn set_linux_line(&mut self, tokens: &[&str]) -> bool {
+        let Some(version) = tokens
+            .first()
+            .and_then(|image| image.rsplit('/').next())
+            .and_then(|f| f.strip_prefix("vmlinuz-"))
+        else {
+            return false;
+        };
+        self.kernel_version = version.to_string();
+        let cmdline = tokens[1..].join(" ");
+        if !cmdline.trim().is_empty() {
+            self.cmdline = cmdline;
+        }
+        true
+    }
+}
+
+/// The parts of /boot/grub/grub.cfg the menu understands: `set default=`,
+/// `set timeout=` and `menuentry` blocks with a `linux` line
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrubConfig {
+    pub default: usize,
+    pub timeout: u32,
+    pub entries: Vec<BootEntry>,
+}
+
+pub fn parse_grub_cfg(text: &str) -> GrubConfig {
+    let mut config = GrubConfig {
+        default: 0,
+        timeout: DEFAULT_TIMEOUT_SECS,
+        entries: Vec::new(),
+    };
+    let mut current: Option<BootEntry> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some(setting) = line.strip_prefix("set ") {
+            let Some((key, value)) = setting.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            match key.trim() {
+                "default" => config.default = value.parse().unwrap_or(config.default),
+                "timeout" => config.timeout = value.parse().unwrap_or(config.timeout),
+                _ => {}
+            }
+        } else if let Some(rest) = line.strip_prefix("menuentry ") {
+            let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"');
+            let title = match quote {
+                Some(q) => rest[1..].split(q).next().unwrap_or(""),
+                None => rest.split_whitespace().next().unwrap_or(""),
+            };
+            current = Some(BootEntry::new(title, "", ""));
+        } else if line.starts_with('}') {
+            if let Some(entry) = current.take().filter(|e| !e.kernel_version.is_empty()) {
+                config.entries.push(entry);
+            }
+        } else if let Some(entry) = current.as_mut() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.first() == Some(&"linux") {
+                entry.set_linux_line(&tokens[1..]);
+            }
+        }
+    }
+    config
+}
 
 #[wasm_bindgen]
 pub struct GrubMenu {
@@ -16,10 +105,9 @@ pub struct GrubMenu {
     edit_cursor_row: usize,
     edit_cursor_col: usize,
     advanced_mode: bool,
-    normal_cmdline: String,
-    recovery_cmdline: String,
-    normal_kernel_version: String,
-    recovery_kernel_version: String,
+    timeout: u32,
+    boot_entries: Vec<BootEntry>,
+    recovery: BootEntry,
 }
 
 impl Default for GrubMenu {
@@ -32,14 +120,10 @@ impl Default for GrubMenu {
 impl GrubMenu {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        GrubMenu {
+        let mut menu = GrubMenu {
             selected: 0,
             timer: DEFAULT_TIMEOUT_SECS,
-            entries: vec![
-                "kpawnd GNU/Linux".to_string(),
-                "Advanced options for kpawnd GNU/Linux".to_string(),
-                "Memory test (memtest86+)".to_string(),
-            ],
+            entries: Vec::new(),
             edit_mode: false,
             cmdline_mode: false,
             cmdline_buffer: String::new(),
@@ -48,11 +132,55 @@ impl GrubMenu {
             edit_cursor_row: 0,
             edit_cursor_col: 0,
             advanced_mode: false,
-            normal_cmdline: "root=/dev/sda1 ro quiet splash".to_string(),
-            recovery_cmdline: "root=/dev/sda1 ro single systemd.unit=rescue.target".to_string(),
-            normal_kernel_version: "6.7.0-kpawnd".to_string(),
-            recovery_kernel_version: "6.7.0-kpawnd-recovery".to_string(),
+            timeout: DEFAULT_TIMEOUT_SECS,
+            boot_entries: vec![BootEntry::new(
+                "kpawnd GNU/Linux",
+                "6.7.0-kpawnd",
+                "root=/dev/sda1 ro quiet splash",
+            )],
+            recovery: BootEntry::new(
+                "kpawnd GNU/Linux (recovery mode)",
+                "6.7.0-kpawnd-recovery",
+                "root=/dev/sda1 ro single systemd.unit=rescue.target",
+            ),
+        };
+        menu.show_main_entries();
+        menu
+    }
+
+    /// Take the entries, default and timeout from the text of grub.cfg; a
+    /// file without usable entries leaves the built-in one in place
+    #[wasm_bindgen]
+    pub fn load_config(&mut self, text: &str) {
+        let config = parse_grub_cfg(text);
+        if !config.entries.is_empty() {
+            self.boot_entries = config.entries;
         }
+        self.timeout = config.timeout;
+        self.timer = config.timeout;
+        self.advanced_mode = false;
+        self.show_main_entries();
+        self.selected = config.default.min(self.boot_entries.len() - 1);
+    }
+
+    /// What Enter on the highlighted entry does: "boot", "advanced",
+    /// "back" or "memtest"
+    #[wasm_bindgen]
+    pub fn selected_action(&self) -> String {
+        let action = if self.advanced_mode {
+            match self.selected {
+                0 => "back",
+                3 => "memtest",
+                _ => "boot",
+            }
+        } else if self.selected < self.boot_entries.len() {
+            "boot"
+        } else if self.selected == self.boot_entries.len() {
+            "advanced"
+        } else {
+            "memtest"
+        };
+        action.to_string()
     }
 
     #[wasm_bindgen]
@@ -232,7 +360,7 @@ impl GrubMenu {
     pub fn exit_special_mode(&mut self) {
         self.edit_mode = false;
         self.cmdline_mode = false;
-        self.timer = DEFAULT_TIMEOUT_SECS;
+        self.timer = self.timeout;
     }
 
     #[wasm_bindgen]
@@ -286,12 +414,16 @@ impl GrubMenu {
     pub fn enter_advanced_mode(&mut self) {
         self.advanced_mode = true;
         self.selected = 0;
-        self.timer = DEFAULT_TIMEOUT_SECS;
+        self.timer = self.timeout;
+        let main = &self.boot_entries[0];
         self.entries = vec![
             "Back to main menu".to_string(),
-            "kpawnd GNU/Linux, with Linux 6.7.0-kpawnd".to_string(),
-            "kpawnd GNU/Linux, with Linux 6.7.0-kpawnd-recovery (recovery mode)".to_string(),
-            "Memory test (memtest86+)".to_string(),
+            format!("{}, with Linux {}", main.title, main.kernel_version),
+            format!(
+                "{}, with Linux {} (recovery mode)",
+                main.title, self.recovery.kernel_version
+            ),
+            MEMTEST_TITLE.to_string(),
         ];
     }
 
@@ -299,12 +431,8 @@ impl GrubMenu {
     pub fn exit_advanced_mode(&mut self) {
         self.advanced_mode = false;
         self.selected = 0;
-        self.timer = DEFAULT_TIMEOUT_SECS;
-        self.entries = vec![
-            "kpawnd GNU/Linux".to_string(),
-            "Advanced options for kpawnd GNU/Linux".to_string(),
-            "Memory test (memtest86+)".to_string(),
-        ];
+        self.timer = self.timeout;
+        self.show_main_entries();
     }
 
     #[wasm_bindgen]
@@ -336,12 +464,7 @@ impl GrubMenu {
             return;
         }
 
-        let is_recovery = self.is_recovery_selection();
-        if is_recovery {
-            self.recovery_cmdline = clean.to_string();
-        } else {
-            self.normal_cmdline = clean.to_string();
-        }
+        self.selected_entry_mut().cmdline = clean.to_string();
 
         if self.edit_mode {
             self.enter_edit_mode();
@@ -480,30 +603,11 @@ impl GrubMenu {
             .edit_buffer
             .iter()
             .find(|line| line.trim_start().starts_with("linux"))
+            .cloned()
         {
             let tokens: Vec<&str> = linux_line.split_whitespace().collect();
             if tokens.len() >= 3 {
-                let image = tokens[1];
-                if let Some(version) = image
-                    .rsplit('/')
-                    .next()
-                    .and_then(|f| f.strip_prefix("vmlinuz-"))
-                {
-                    if self.is_recovery_selection() {
-                        self.recovery_kernel_version = version.to_string();
-                    } else {
-                        self.normal_kernel_version = version.to_string();
-                    }
-                }
-
-                let cmdline = tokens[2..].join(" ");
-                if !cmdline.trim().is_empty() {
-                    if self.is_recovery_selection() {
-                        self.recovery_cmdline = cmdline;
-                    } else {
-                        self.normal_cmdline = cmdline;
-                    }
-                }
+                self.selected_entry_mut().set_linux_line(&tokens[1..]);
             }
         }
     }
@@ -564,19 +668,7 @@ impl GrubMenu {
                 if cmd.starts_with("linux ") {
                     let parts: Vec<&str> = cmd.split_whitespace().collect();
                     if parts.len() >= 3 {
-                        let image = parts[1];
-                        if let Some(version) = image
-                            .rsplit('/')
-                            .next()
-                            .and_then(|f| f.strip_prefix("vmlinuz-"))
-                        {
-                            if self.is_recovery_selection() {
-                                self.recovery_kernel_version = version.to_string();
-                                self.recovery_cmdline = parts[2..].join(" ");
-                            } else {
-                                self.normal_kernel_version = version.to_string();
-                                self.normal_cmdline = parts[2..].join(" ");
-                            }
+                        if self.selected_entry_mut().set_linux_line(&parts[1..]) {
                             self.cmdline_output
                                 .push("linux parameters updated".to_string());
                         } else {
@@ -608,103 +700,227 @@ impl GrubMenu {
         self.advanced_mode && self.selected == 2
     }
 
-    fn effective_boot_profile(&self) -> (String, String, String) {
-        if self.is_recovery_selection() {
-            (
-                "kpawnd GNU/Linux (recovery mode)".to_string(),
-                self.recovery_kernel_version.clone(),
-                self.recovery_cmdline.clone(),
-            )
+    fn show_main_entries(&mut self) {
+        self.entries = self.boot_entries.iter().map(|e| e.title.clone()).collect();
+        self.entries.push(ADVANCED_TITLE.to_string());
+        self.entries.push(MEMTEST_TITLE.to_string());
+    }
+
+    // The grub.cfg entry (or the recovery entry) Enter would boot; anything
+    // that isn't a boot entry falls back to the first one
+    fn selected_entry_index(&self) -> usize {
+        if self.advanced_mode || self.selected >= self.boot_entries.len() {
+            0
         } else {
-            (
-                "kpawnd GNU/Linux".to_string(),
-                self.normal_kernel_version.clone(),
-                self.normal_cmdline.clone(),
-            )
+            self.selected
         }
     }
+
+    fn selected_entry_mut(&mut self) -> &mut BootEntry {
+        if self.is_recovery_selection() {
+            return &mut self.recovery;
+        }
+        let idx = self.selected_entry_index();
+        &mut self.boot_entries[idx]
+    }
+
+    fn effective_boot_profile(&self) -> (String, String, String) {
+        let entry = if self.is_recovery_selection() {
+            &self.recovery
+        } else {
+            &self.boot_entries[self.selected_entry_index()]
+        };
+        (
+            entry.title.clone(),
+            entry.kernel_version.clone(),
+            entry.cmdline.clone(),
+        )
+    }
 }
 
+const MEMTEST_TESTS: [&str; 9] = [
+    "Address test, own address",
+    "Moving inversions, ones & zeros",
+    "Moving inversions, 8 bit pattern",
+    "Moving inversions, random pattern",
+    "Block move, 64 moves",
+    "Moving inversions, 32 bit pattern",
+    "Random number sequence",
+    "Modulo 20, ones & zeros",
+    "Bit fade test, 90 min, 2 patterns",
+];
+// Real bytes standing in for the simulated memory (scaled down past this)
+const MEMTEST_MAX_BYTES: u32 = 16 * 1024 * 1024;
+// Each tick covers this much of a test
+const MEMTEST_STEP_PERCENT: u32 = 10;
+
 #[wasm_bindgen]
 pub struct Memtest {
-    tests: Vec<String>,
     current_test: usize,
     progress: u32,
     total_mem: u32,
+    // (offset, size, allocated) from the memory map
+    regions: Vec<(u32, u32, bool)>,
+    faults: Vec<u32>,
     test_memory: Vec<u8>,
     errors: u32,
+    error_lines: Vec<String>,
 }
 
-#[wasm_bindgen]
 impl Memtest {
-    #[wasm_bindgen(constructor)]
-    pub fn new(mem_size: u32) -> Self {
-        // Allocate a reasonable test memory size (limit to 16MB for browser)
-        let test_size = (mem_size * 1024 * 1024).min(16 * 1024 * 1024) as usize;
-        let test_memory = vec![0u8; test_size];
-
+    /// A run over the simulated memory map; cells damaged by OOM panics or
+    /// doom come back as errors
+    pub fn for_memory(mem: &crate::memory::Memory) -> Memtest {
+        let size = mem.total.min(MEMTEST_MAX_BYTES) as usize;
         Memtest {
-            tests: vec![
-                "Address test, own address".to_string(),
-                "Moving inversions, ones & zeros".to_string(),
-                "Moving inversions, 8 bit pattern".to_string(),
-                "Moving inversions, random pattern".to_string(),
-                "Block move, 64 moves".to_string(),
-                "Moving inversions, 32 bit pattern".to_string(),
-                "Random number sequence".to_string(),
-                "Modulo 20, ones & zeros".to_string(),
-                "Bit fade test, 90 min, 2 patterns".to_string(),
-            ],
             current_test: 0,
             progress: 0,
-            total_mem: mem_size,
-            test_memory,
+            total_mem: mem.total,
+            regions: mem
+                .blocks()
+                .map(|b| {
+                    (
+                        b.offset,
+                        b.size,
+                        b.state == crate::memory::BlockState::Allocated,
+                    )
+                })
+                .collect(),
+            faults: mem.faults().to_vec(),
+            test_memory: vec![0u8; size],
             errors: 0,
+            error_lines: Vec::new(),
+        }
+    }
+
+    fn address_of(&self, index: usize) -> u32 {
+        (index as u64 * self.total_mem as u64 / self.test_memory.len() as u64) as u32
+    }
+
+    fn index_of(&self, addr: u32) -> usize {
+        (addr as u64 * self.test_memory.len() as u64 / self.total_mem as u64) as usize
+    }
+
+    fn passes(test: usize) -> usize {
+        match test {
+            1 | 2 | 5 | 7 | 8 => 2,
+            _ => 1,
+        }
+    }
+
+    // What test `test` writes to byte `i` on pass `pass`; None for bytes the
+    // test leaves alone
+    fn expected(test: usize, pass: usize, i: usize) -> Option<u8> {
+        let mix = |seed: u32| ((i as u32 ^ seed).wrapping_mul(2_654_435_761) >> 24) as u8;
+        let ones_zeros = if pass == 0 { 0xFF } else { 0x00 };
+        let alternating = if pass == 0 { 0xAA } else { 0x55 };
+        match test {
+            0 => Some((i % 256) as u8),
+            1 | 5 => Some(ones_zeros),
+            2 | 8 => Some(alternating),
+            3 | 4 => Some(mix(0)),
+            6 => Some(mix(0x5bd1_e995)),
+            7 => i.is_multiple_of(20).then_some(ones_zeros),
+            _ => None,
         }
     }
 
+    // Run the current test over one slice of memory: write each pattern, let
+    // the damaged cells flip a bit, then read it back
+    fn run_step(&mut self, step: u32) {
+        let len = self.test_memory.len();
+        let steps = (100 / MEMTEST_STEP_PERCENT) as usize;
+        let start = step as usize * len / steps;
+        let end = (step as usize + 1) * len / steps;
+        let test = self.current_test;
+        let bad: Vec<(usize, u32)> = self
+            .faults
+            .iter()
+            .map(|&addr| (self.index_of(addr), addr))
+            .filter(|(i, _)| (start..end).contains(i))
+            .collect();
+
+        for pass in 0..Self::passes(test) {
+            for i in start..end {
+                if let Some(byte) = Self::expected(test, pass, i) {
+                    self.test_memory[i] = byte;
+                }
+            }
+            for &(i, addr) in &bad {
+                self.test_memory[i] ^= 1 << (addr % 8);
+            }
+            for i in start..end {
+                let Some(want) = Self::expected(test, pass, i) else {
+                    continue;
+                };
+                let got = self.test_memory[i];
+                if got != want {
+                    self.errors += 1;
+                    self.error_lines.push(format!(
+                        "  ** Error at 0x{:08x}: test #{} expected 0x{:02x}, read 0x{:02x}",
+                        self.address_of(i),
+                        test + 1,
+                        want,
+                        got
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Memtest {
+    /// A clean machine with `mem_size` MB
+    #[wasm_bindgen(constructor)]
+    pub fn new(mem_size: u32) -> Self {
+        Self::for_memory(&crate::memory::Memory::new(
+            mem_size.saturating_mul(1024 * 1024),
+        ))
+    }
+
     #[wasm_bindgen]
     pub fn get_header(&self) -> String {
-        format!(
-            "Memtest86+ v5.01\n\nTesting {}MB of memory\n",
-            self.total_mem
-        )
+        let mut out = format!(
+            "Memtest86+ v5.01\n\nTesting {}MB of memory\nMemory map:\n",
+            self.total_mem / (1024 * 1024)
+        );
+        for &(offset, size, allocated) in &self.regions {
+            out.push_str(&format!(
+                "  0x{:08x} - 0x{:08x}  {:>6}K  {}\n",
+                offset,
+                offset + size - 1,
+                size / 1024,
+                if allocated { "in use" } else { "free" }
+            ));
+        }
+        out
     }
 
     #[wasm_bindgen]
     pub fn tick(&mut self) -> bool {
-        // Perform actual memory testing based on current test
-        match self.current_test {
-            0 => self.test_address_own_address(),
-            1 => self.test_moving_inversions_ones_zeros(),
-            2 => self.test_moving_inversions_8bit(),
-            3 => self.test_moving_inversions_random(),
-            4 => self.test_block_move(),
-            5 => self.test_moving_inversions_32bit(),
-            6 => self.test_random_sequence(),
-            7 => self.test_modulo_20(),
-            8 => self.test_bit_fade(),
-            _ => {}
-        }
-
-        self.progress += 10;
+        if self.is_complete() {
+            return false;
+        }
+        self.run_step(self.progress / MEMTEST_STEP_PERCENT);
+        self.progress += MEMTEST_STEP_PERCENT;
         if self.progress >= 100 {
             self.progress = 0;
             self.current_test += 1;
         }
-        self.current_test < self.tests.len()
+        !self.is_complete()
     }
 
     #[wasm_bindgen]
     pub fn get_current_line(&self) -> String {
-        if self.current_test >= self.tests.len() {
+        if self.is_complete() {
             return format!(
                 "\n** Pass complete, {} errors, press Esc to exit **",
                 self.errors
             );
         }
 
-        let test_name = &self.tests[self.current_test];
+        let test_name = MEMTEST_TESTS[self.current_test];
         let bar_length = 20;
         let filled = (self.progress / 5) as usize;
         let empty = bar_length - filled;
@@ -720,9 +936,15 @@ impl Memtest {
         )
     }
 
+    /// Error reports since the last call, one per line
+    #[wasm_bindgen]
+    pub fn take_error_lines(&mut self) -> String {
+        std::mem::take(&mut self.error_lines).join("\n")
+    }
+
     #[wasm_bindgen]
     pub fn is_complete(&self) -> bool {
-        self.current_test >= self.tests.len()
+        self.current_test >= MEMTEST_TESTS.len()
     }
 
     #[wasm_bindgen]
@@ -731,221 +953,57 @@ impl Memtest {
     }
 }
 
-// Memory testing implementations
-impl Memtest {
-    fn test_address_own_address(&mut self) {
-        let chunk_size = 4096; // Test in 4KB chunks
-        let chunks = self.test_memory.len() / chunk_size;
-
-        for chunk in 0..chunks {
-            let start = chunk * chunk_size;
-            let end = start + chunk_size;
-
-            // Write address pattern
-            for i in start..end {
-                let addr = (i % 256) as u8;
-                self.test_memory[i] = addr;
-            }
-
-            // Read back and verify
-            for i in start..end {
-                let expected = (i % 256) as u8;
-                if self.test_memory[i] != expected {
-                    self.errors += 1;
-                }
-            }
-        }
-    }
-
-    fn test_moving_inversions_ones_zeros(&mut self) {
-        let pattern1 = 0xFFu8; // All ones
-        let pattern2 = 0x00u8; // All zeros
-
-        // First pass: write pattern1, then pattern2
-        for i in 0..self.test_memory.len() {
-            self.test_memory[i] = pattern1;
-        }
-        for i in 0..self.test_memory.len() {
-            if self.test_memory[i] != pattern1 {
-                self.errors += 1;
-            }
-            self.test_memory[i] = pattern2;
-        }
-
-        // Second pass: verify pattern2, then pattern1
-        for i in 0..self.test_memory.len() {
-            if self.test_memory[i] != pattern2 {
-                self.errors += 1;
-            }
-            self.test_memory[i] = pattern1;
-        }
-        for i in 0..self.test_memory.len() {
-            if self.test_memory[i] != pattern1 {
-                self.errors += 1;
-            }
-        }
-    }
-
-    fn test_moving_inversions_8bit(&mut self) {
-        let patterns = [0xAAu8, 0x55u8]; // Alternating bit patterns
-
-        for &pattern in &patterns {
-            // Write pattern
-            for i in 0..self.test_memory.len() {
-                self.test_memory[i] = pattern;
-            }
-
-            // Verify pattern
-            for i in 0..self.test_memory.len() {
-                if self.test_memory[i] != pattern {
-                    self.errors += 1;
-                }
-            }
-        }
-    }
-
-    fn test_moving_inversions_random(&mut self) {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        // Generate pseudo-random pattern based on address
-        for i in 0..self.test_memory.len() {
-            let mut hasher = DefaultHasher::new();
-            i.hash(&mut hasher);
-            let pattern = (hasher.finish() % 256) as u8;
-            self.test_memory[i] = pattern;
-        }
-
-        // Verify pattern
-        for i in 0..self.test_memory.len() {
-            let mut hasher = DefaultHasher::new();
-            i.hash(&mut hasher);
-            let expected = (hasher.finish() % 256) as u8;
-            if self.test_memory[i] != expected {
-                self.errors += 1;
-            }
-        }
-    }
-
-    fn test_block_move(&mut self) {
-        let block_size = 1024;
-        let mut temp_buffer = vec![0u8; block_size];
-
-        for i in (0..self.test_memory.len()).step_by(block_size) {
-            let end = (i + block_size).min(self.test_memory.len());
-
-            // Copy block to temp
-            temp_buffer[..(end - i)].copy_from_slice(&self.test_memory[i..end]);
-
-            // Write different pattern
-            for j in i..end {
-                self.test_memory[j] = 0xFF;
-            }
-
-            // Copy back
-            self.test_memory[i..end].copy_from_slice(&temp_buffer[..(end - i)]);
-
-            // Verify
-            for j in i..end {
-                let mut hasher = DefaultHasher::new();
-                j.hash(&mut hasher);
-                let expected = (hasher.finish() % 256) as u8;
-                if self.test_memory[j] != expected {
-                    self.errors += 1;
-                }
-            }
-        }
-    }
-
-    fn test_moving_inversions_32bit(&mut self) {
-        let patterns = [0xFFFFFFFFu32, 0x00000000u32];
-
-        for &pattern_u32 in &patterns {
-            let pattern_bytes = pattern_u32.to_le_bytes();
-
-            for i in (0..self.test_memory.len()).step_by(4) {
-                if i + 4 <= self.test_memory.len() {
-                    self.test_memory[i..i + 4].copy_from_slice(&pattern_bytes);
-                }
-            }
-
-            // Verify
-            for i in (0..self.test_memory.len()).step_by(4) {
-                if i + 4 <= self.test_memory.len() && self.test_memory[i..i + 4] != pattern_bytes {
-                    self.errors += 1;
-                }
-            }
-        }
-    }
-
-    fn test_random_sequence(&mut self) {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        // Fill with pseudo-random sequence
-        for i in 0..self.test_memory.len() {
-            let mut hasher = DefaultHasher::new();
-            i.hash(&mut hasher);
-            self.test_memory[i] = (hasher.finish() % 256) as u8;
-        }
-
-        // Verify sequence
-        for i in 0..self.test_memory.len() {
-            let mut hasher = DefaultHasher::new();
-            i.hash(&mut hasher);
-            let expected = (hasher.finish() % 256) as u8;
-            if self.test_memory[i] != expected {
-                self.errors += 1;
-            }
-        }
-    }
-
-    fn test_modulo_20(&mut self) {
-        let patterns = [0xFFu8, 0x00u8];
-
-        for &pattern in &patterns {
-            for i in 0..self.test_memory.len() {
-                if i % 20 == 0 {
-                    self.test_memory[i] = pattern;
-                }
-            }
-
-            // Verify
-            for i in 0..self.test_memory.len() {
-                if i % 20 == 0 && self.test_memory[i] != pattern {
-                    self.errors += 1;
-                }
-            }
-        }
-    }
-
-    fn test_bit_fade(&mut self) {
-        // Simplified bit fade test - just write and read back
-        let pattern1 = 0xAAu8;
-        let pattern2 = 0x55u8;
-
-        // Write pattern1
-        for i in 0..self.test_memory.len() {
-            self.test_memory[i] = pattern1;
-        }
-
-        // "Wait" simulation - just verify immediately
-        for i in 0..self.test_memory.len() {
-            if self.test_memory[i] != pattern1 {
-                self.errors += 1;
-            }
-        }
-
-        // Write pattern2
-        for i in 0..self.test_memory.len() {
-            self.test_memory[i] = pattern2;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grub_cfg_entries() {
+        let cfg = "# GRUB configuration file\nset default=\"1\"\nset timeout=3\n\n\
+                   menuentry 'kpawnd GNU/Linux' {\n    linux /boot/vmlinuz-6.1.0-kpawnd root=/dev/sda1 ro quiet\n}\n\
+                   menuentry \"Small\" --class gnu {\n    linux /boot/vmlinuz-6.1.0-kpawnd root=/dev/sda1 mem=64M\n}\n\
+                   menuentry 'No kernel' {\n    echo nothing\n}\n";
+        let config = parse_grub_cfg(cfg);
+        assert_eq!(config.default, 1);
+        assert_eq!(config.timeout, 3);
+        assert_eq!(
+            config.entries,
+            vec![
+                BootEntry::new(
+                    "kpawnd GNU/Linux",
+                    "6.1.0-kpawnd",
+                    "root=/dev/sda1 ro quiet"
+                ),
+                BootEntry::new("Small", "6.1.0-kpawnd", "root=/dev/sda1 mem=64M"),
+            ]
+        );
 
-        // Verify pattern2
-        for i in 0..self.test_memory.len() {
-            if self.test_memory[i] != pattern2 {
-                self.errors += 1;
-            }
-        }
+        let mut menu = GrubMenu::new();
+        menu.load_config(cfg);
+        assert_eq!(menu.get_selected(), 1);
+        assert_eq!(menu.get_effective_cmdline(), "root=/dev/sda1 mem=64M");
+        menu.move_down();
+        assert_eq!(menu.selected_action(), "advanced");
+        menu.move_down();
+        assert_eq!(menu.selected_action(), "memtest");
+    }
+
+    #[test]
+    fn test_memtest_finds_damaged_cells() {
+        let mut mem = crate::memory::Memory::new(1024 * 1024);
+        let mut clean = Memtest::for_memory(&mem);
+        while clean.tick() {}
+        assert!(clean.is_complete());
+        assert_eq!(clean.get_errors(), 0);
+
+        mem.scribble(3);
+        let mut test = Memtest::for_memory(&mem);
+        while test.tick() {}
+        assert!(test.get_errors() >= 3);
+        let lines = test.take_error_lines();
+        for addr in mem.faults() {
+            assert!(lines.contains(&format!("0x{:08x}", addr)));
+        }
+        assert!(test.take_error_lines().is_empty());
     }
 }
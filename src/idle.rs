@@ -1,20 +1,64 @@
+//! Idle timer: starts the screensaver after a stretch without input, warns
+//! the terminal shortly before, and can lock the screen behind the user's
+//! password once the screensaver is dismissed. Both notices reach the
+//! terminal as escape sequences in the detail of a KP_IDLE window event.
 use std::cell::Cell;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{window, Event};
 
+pub const DEFAULT_TIMEOUT_MS: u32 = 60000;
+pub const DEFAULT_WARN_MS: u32 = 10000;
+
 thread_local! {
     static LAST_ACTIVITY: Cell<f64> = const { Cell::new(0.0) };
-    static TIMEOUT_MS: Cell<u32> = const { Cell::new(60000) };
+    // 0 turns blanking off
+    static TIMEOUT_MS: Cell<u32> = const { Cell::new(DEFAULT_TIMEOUT_MS) };
+    static WARN_MS: Cell<u32> = const { Cell::new(DEFAULT_WARN_MS) };
+    static WARNED: Cell<bool> = const { Cell::new(false) };
+    static LOCK_ENABLED: Cell<bool> = const { Cell::new(false) };
+    static LOCKED: Cell<bool> = const { Cell::new(false) };
+    // The running screensaver was started by the timer, not a command
+    static BLANKED_BY_IDLE: Cell<bool> = const { Cell::new(false) };
     static INTERVAL_HANDLE: Cell<i32> = const { Cell::new(-1) };
     static ACTIVE_GAME: Cell<bool> = const { Cell::new(false) };
     static ACTIVE_SCREENSAVER: Cell<bool> = const { Cell::new(false) };
     static CALLBACK_INSTALLED: Cell<bool> = const { Cell::new(false) };
 }
 
+/// Feed a user activity event; the window's input events already call this,
+/// other frontends (iframes, touch overlays) can too
+#[wasm_bindgen]
 pub fn mark_activity() {
     let now = js_sys::Date::now();
     LAST_ACTIVITY.with(|t| t.set(now));
+    WARNED.with(|w| w.set(false));
+}
+
+/// Set the timeout, warning lead time and lock-on-blank from `idle`/`xset`
+pub fn configure(timeout_ms: u32, warn_ms: u32, lock: bool) {
+    TIMEOUT_MS.with(|t| t.set(timeout_ms));
+    WARN_MS.with(|w| w.set(warn_ms));
+    LOCK_ENABLED.with(|l| l.set(lock));
+    mark_activity();
+}
+
+#[wasm_bindgen]
+pub fn idle_timeout_ms() -> u32 {
+    TIMEOUT_MS.with(|t| t.get())
+}
+
+pub fn unlock() {
+    LOCKED.with(|l| l.set(false));
+    mark_activity();
+}
+
+fn notify(sequence: &str) {
+    let init = web_sys::CustomEventInit::new();
+    init.set_detail(&JsValue::from_str(sequence));
+    if let Ok(event) = web_sys::CustomEvent::new_with_event_init_dict("KP_IDLE", &init) {
+        let _ = window().unwrap().dispatch_event(&event);
+    }
 }
 
 fn attach_listeners() {
@@ -51,6 +95,7 @@ fn launch_screensaver_if_idle() {
                 return;
             }
             crate::screensaver::start_screensaver();
+            BLANKED_BY_IDLE.with(|b| b.set(true));
         });
     });
 }
@@ -66,6 +111,34 @@ pub fn set_game_active(active: bool) {
 #[wasm_bindgen]
 pub fn set_screensaver_active(active: bool) {
     ACTIVE_SCREENSAVER.with(|s| s.set(active));
+    if active || !BLANKED_BY_IDLE.with(|b| b.replace(false)) {
+        return;
+    }
+    if LOCK_ENABLED.with(|l| l.get()) && !LOCKED.with(|l| l.replace(true)) {
+        notify("\x1b[LOCK_SCREEN]");
+    }
+}
+
+/// Blank now, as if the timeout had passed (`idle now`, `xset s activate`)
+#[wasm_bindgen]
+pub fn blank_now() {
+    launch_screensaver_if_idle();
+    mark_activity();
+}
+
+// Warn once per idle stretch, `warn_ms` before blanking
+fn warn_if_due(idle_ms: f64, timeout_ms: u32) {
+    let warn_ms = WARN_MS.with(|w| w.get());
+    if warn_ms == 0 || warn_ms >= timeout_ms || WARNED.with(|w| w.get()) {
+        return;
+    }
+    let busy = ACTIVE_GAME.with(|g| g.get()) || ACTIVE_SCREENSAVER.with(|s| s.get());
+    if busy || idle_ms < (timeout_ms - warn_ms) as f64 {
+        return;
+    }
+    WARNED.with(|w| w.set(true));
+    let secs = (timeout_ms as f64 - idle_ms).max(0.0) / 1000.0;
+    notify(&format!("\x1b[IDLE_WARNING:{}]", secs.ceil() as u32));
 }
 
 #[wasm_bindgen]
@@ -80,10 +153,13 @@ pub fn start_idle_timer(timeout_ms: u32) {
         }
     });
     let tick = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
-        let now = js_sys::Date::now();
-        let idle_ms = TIMEOUT_MS.with(|t| t.get()) as f64;
-        let last = LAST_ACTIVITY.with(|t| t.get());
-        if now - last >= idle_ms {
+        let timeout_ms = TIMEOUT_MS.with(|t| t.get());
+        if timeout_ms == 0 {
+            return;
+        }
+        let idle_ms = js_sys::Date::now() - LAST_ACTIVITY.with(|t| t.get());
+        warn_if_due(idle_ms, timeout_ms);
+        if idle_ms >= timeout_ms as f64 {
             launch_screensaver_if_idle();
             // Reset so we don't relaunch repeatedly
             mark_activity();
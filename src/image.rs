@@ -0,0 +1,405 @@
+//! Decoders that turn image files from the VFS into `Sprite`s: PNG
+//! (non-interlaced, any bit depth and color type), uncompressed 24/32-bit BMP,
+//! and binary or ASCII PPM/PGM.
+use crate::graphics::Sprite;
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+
+const PNG_MAGIC: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+// Larger images would take hundreds of MB as RGBA in wasm memory
+const MAX_DIMENSION: u32 = 8192;
+
+/// Short name of the format `bytes` look like, if it is one we decode
+pub fn format_name(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(PNG_MAGIC) {
+        Some("PNG")
+    } else if bytes.starts_with(b"BM") {
+        Some("BMP")
+    } else if matches!(bytes, [b'P', b'2' | b'3' | b'5' | b'6', ..]) {
+        Some("PNM")
+    } else {
+        None
+    }
+}
+
+/// Decode a PNG, BMP or PPM/PGM file into an RGBA sprite
+pub fn decode(bytes: &[u8]) -> Result<Sprite, String> {
+    let (width, height, rgba) = match format_name(bytes) {
+        Some("PNG") => decode_png(bytes)?,
+        Some("BMP") => decode_bmp(bytes)?,
+        Some(_) => decode_pnm(bytes)?,
+        None => return Err("unsupported image format (PNG, BMP or PPM)".to_string()),
+    };
+    Sprite::from_rgba(rgba, width, height)
+}
+
+fn check_size(width: u32, height: u32) -> Result<(), String> {
+    if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(format!("unsupported image size {}x{}", width, height));
+    }
+    Ok(())
+}
+
+fn be32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn le32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn decode_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let truncated = || "truncated PNG".to_string();
+    let mut pos = PNG_MAGIC.len();
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut trns: &[u8] = &[];
+    let mut idat = Vec::new();
+    while pos + 8 <= bytes.len() {
+        let len = be32(bytes, pos) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes[pos + 8..].get(..len).ok_or_else(truncated)?;
+        match kind {
+            b"IHDR" if len >= 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => trns = data,
+            b"IDAT" => idat.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Skip the data and its CRC
+        pos += 12 + len;
+    }
+    let ihdr = header.ok_or("PNG has no IHDR chunk")?;
+    let (width, height) = (be32(ihdr, 0), be32(ihdr, 4));
+    let (depth, color_type, interlace) = (ihdr[8], ihdr[9], ihdr[12]);
+    check_size(width, height)?;
+    if interlace != 0 {
+        return Err("interlaced PNG is not supported".to_string());
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) => 1,
+        (3, 1 | 2 | 4 | 8) => 1,
+        (2, 8 | 16) => 3,
+        (4, 8 | 16) => 2,
+        (6, 8 | 16) => 4,
+        _ => {
+            return Err(format!(
+                "unsupported PNG color type {} at {} bits",
+                color_type, depth
+            ))
+        }
+    };
+
+    let bits_per_pixel = channels * depth as usize;
+    let stride = (width as usize * bits_per_pixel).div_ceil(8);
+    let mut raw = Vec::new();
+    ZlibDecoder::new(idat.as_slice())
+        .read_to_end(&mut raw)
+        .map_err(|e| format!("corrupt PNG data: {}", e))?;
+    if raw.len() < (stride + 1) * height as usize {
+        return Err(truncated());
+    }
+    let pixels = unfilter(&raw, stride, height as usize, bits_per_pixel.div_ceil(8))?;
+
+    // Sample `index` of a row at the image's bit depth, widened to 8 bits
+    let sample = |row: &[u8], index: usize| -> u8 {
+        match depth {
+            16 => row[index * 2],
+            8 => row[index],
+            _ => {
+                let bit = index * depth as usize;
+                let value = (row[bit / 8] >> (8 - depth as usize - bit % 8)) & ((1 << depth) - 1);
+                if color_type == 3 {
+                    value
+                } else {
+                    value * (255 / ((1 << depth) - 1))
+                }
+            }
+        }
+    };
+    // A gray or RGB tRNS entry names one fully transparent color
+    let key = |at: usize| trns.get(at * 2 + 1).copied();
+
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in pixels.chunks_exact(stride) {
+        for x in 0..width as usize {
+            let px = match color_type {
+                0 => {
+                    let v = sample(row, x);
+                    let alpha = if depth <= 8 && key(0) == Some(v) {
+                        0
+                    } else {
+                        255
+                    };
+                    [v, v, v, alpha]
+                }
+                2 => {
+                    let [r, g, b] = [0, 1, 2].map(|c| sample(row, x * 3 + c));
+                    let keyed =
+                        depth == 8 && [key(0), key(1), key(2)] == [Some(r), Some(g), Some(b)];
+                    [r, g, b, if keyed { 0 } else { 255 }]
+                }
+                3 => {
+                    let i = sample(row, x) as usize;
+                    let rgb = palette
+                        .get(i * 3..i * 3 + 3)
+                        .ok_or("PNG palette index out of range")?;
+                    [rgb[0], rgb[1], rgb[2], trns.get(i).copied().unwrap_or(255)]
+                }
+                4 => {
+                    let v = sample(row, x * 2);
+                    [v, v, v, sample(row, x * 2 + 1)]
+                }
+                _ => [0, 1, 2, 3].map(|c| sample(row, x * 4 + c)),
+            };
+            rgba.extend_from_slice(&px);
+        }
+    }
+    Ok((width, height, rgba))
+}
+
+// Undo the per-row PNG filters; `bpp` is the byte distance to the left pixel
+fn unfilter(raw: &[u8], stride: usize, height: usize, bpp: usize) -> Result<Vec<u8>, String> {
+    let mut out = vec![0u8; stride * height];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (done, rest) = out.split_at_mut(y * stride);
+        let prev = if y > 0 {
+            &done[(y - 1) * stride..]
+        } else {
+            &[][..]
+        };
+        let cur = &mut rest[..stride];
+        for i in 0..stride {
+            let a = if i >= bpp { cur[i - bpp] } else { 0 };
+            let b = prev.get(i).copied().unwrap_or(0);
+            let c = if i >= bpp {
+                prev.get(i - bpp).copied().unwrap_or(0)
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(format!("unknown PNG filter {}", filter)),
+            };
+            cur[i] = line[i].wrapping_add(predictor);
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn decode_bmp(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    if bytes.len() < 54 {
+        return Err("truncated BMP".to_string());
+    }
+    let offset = le32(bytes, 10) as usize;
+    let width = le32(bytes, 18) as i32;
+    let raw_height = le32(bytes, 22) as i32;
+    let bpp = u16::from_le_bytes([bytes[28], bytes[29]]);
+    let compression = le32(bytes, 30);
+    // Positive heights store the bottom row first
+    let bottom_up = raw_height > 0;
+    let (width, height) = (width.unsigned_abs(), raw_height.unsigned_abs());
+    check_size(width, height)?;
+    // BI_BITFIELDS at 32 bits is the usual BGRA layout
+    if !matches!((bpp, compression), (24, 0) | (32, 0) | (32, 3)) {
+        return Err(format!(
+            "unsupported BMP ({} bits, compression {}); only uncompressed 24/32-bit",
+            bpp, compression
+        ));
+    }
+    let step = bpp as usize / 8;
+    let stride = (width as usize * step).div_ceil(4) * 4;
+    if bytes.len() < offset + stride * height as usize {
+        return Err("truncated BMP".to_string());
+    }
+
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as usize {
+        let src_y = if bottom_up {
+            height as usize - 1 - y
+        } else {
+            y
+        };
+        let row = &bytes[offset + src_y * stride..];
+        for px in row.chunks_exact(step).take(width as usize) {
+            let alpha = if step == 4 { px[3] } else { 255 };
+            rgba.extend_from_slice(&[px[2], px[1], px[0], alpha]);
+        }
+    }
+    // Most 32-bit BMPs leave the fourth byte zero rather than meaning alpha
+    if step == 4 && rgba.chunks_exact(4).all(|p| p[3] == 0) {
+        rgba.chunks_exact_mut(4).for_each(|p| p[3] = 255);
+    }
+    Ok((width, height, rgba))
+}
+
+fn decode_pnm(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let kind = bytes[1];
+    let (channels, binary) = match kind {
+        b'2' => (1, false),
+        b'3' => (3, false),
+        b'5' => (1, true),
+        _ => (3, true),
+    };
+    // Header: magic, width, height, maxval, separated by whitespace or comments
+    let mut pos = 2;
+    let mut next_token = |bytes: &[u8]| -> Option<u32> {
+        loop {
+            match bytes.get(pos)? {
+                b'#' => {
+                    while *bytes.get(pos)? != b'\n' {
+                        pos += 1;
+                    }
+                }
+                c if c.is_ascii_whitespace() => pos += 1,
+                _ => break,
+            }
+        }
+        let start = pos;
+        while bytes.get(pos).is_some_and(|c| c.is_ascii_digit()) {
+            pos += 1;
+        }
+        std::str::from_utf8(&bytes[start..pos]).ok()?.parse().ok()
+    };
+    let bad = || "malformed PPM header".to_string();
+    let width = next_token(bytes).ok_or_else(bad)?;
+    let height = next_token(bytes).ok_or_else(bad)?;
+    let maxval = next_token(bytes)
+        .filter(|m| (1..=65535).contains(m))
+        .ok_or_else(bad)?;
+    check_size(width, height)?;
+
+    let count = width as usize * height as usize * channels;
+    let samples: Vec<u32> = if binary {
+        // Exactly one whitespace byte separates maxval from the raster
+        let data = bytes.get(pos + 1..).unwrap_or_default();
+        let size = if maxval > 255 { 2 } else { 1 };
+        if data.len() < count * size {
+            return Err("truncated PPM".to_string());
+        }
+        data.chunks_exact(size)
+            .take(count)
+            .map(|s| s.iter().fold(0, |acc, &b| acc << 8 | b as u32))
+            .collect()
+    } else {
+        let samples: Vec<u32> = std::iter::from_fn(|| next_token(bytes))
+            .take(count)
+            .collect();
+        if samples.len() < count {
+            return Err("truncated PPM".to_string());
+        }
+        samples
+    };
+
+    let scale = |v: u32| (v.min(maxval) * 255 / maxval) as u8;
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for px in samples.chunks_exact(channels) {
+        let [r, g, b] = match px {
+            [v] => [scale(*v); 3],
+            _ => [scale(px[0]), scale(px[1]), scale(px[2])],
+        };
+        rgba.extend_from_slice(&[r, g, b, 255]);
+    }
+    Ok((width, height, rgba))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut out = (data.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        // The decoder doesn't check CRCs
+        out.extend_from_slice(&[0; 4]);
+        out
+    }
+
+    fn png(width: u32, height: u32, depth: u8, color: u8, extra: &[u8], rows: &[u8]) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[depth, color, 0, 0, 0]);
+        let mut z = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        z.write_all(rows).unwrap();
+        let mut out = PNG_MAGIC.to_vec();
+        out.extend(chunk(b"IHDR", &ihdr));
+        out.extend_from_slice(extra);
+        out.extend(chunk(b"IDAT", &z.finish().unwrap()));
+        out.extend(chunk(b"IEND", &[]));
+        out
+    }
+
+    #[test]
+    fn test_decode_png_filters_and_palette() {
+        // 2x2 RGBA: row 0 unfiltered, row 1 "up" so it repeats row 0 plus 1
+        let rows = [
+            0, 10, 20, 30, 255, 40, 50, 60, 128, //
+            2, 1, 1, 1, 0, 1, 1, 1, 0,
+        ];
+        let sprite = decode(&png(2, 2, 8, 6, &[], &rows)).unwrap();
+        assert_eq!((sprite.width, sprite.height), (2, 2));
+        assert_eq!(sprite.pixel(1, 0), [40, 50, 60, 128]);
+        assert_eq!(sprite.pixel(0, 1), [11, 21, 31, 255]);
+
+        // 1-bit palette with a transparent first entry
+        let extra = [chunk(b"PLTE", &[0, 0, 0, 255, 0, 0]), chunk(b"tRNS", &[0])].concat();
+        let sprite = decode(&png(3, 1, 1, 3, &extra, &[0, 0b0100_0000])).unwrap();
+        assert_eq!(sprite.pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(sprite.pixel(1, 0), [255, 0, 0, 255]);
+
+        let mut interlaced = png(1, 1, 8, 0, &[], &[0, 0]);
+        interlaced[PNG_MAGIC.len() + 8 + 12] = 1;
+        assert!(decode(&interlaced).unwrap_err().contains("interlaced"));
+    }
+
+    #[test]
+    fn test_decode_bmp_and_ppm() {
+        // 2x2 bottom-up 24-bit BMP: rows padded to 8 bytes
+        let mut bmp = b"BM".to_vec();
+        bmp.resize(54, 0);
+        bmp[10] = 54;
+        bmp[18] = 2;
+        bmp[22] = 2;
+        bmp[28] = 24;
+        bmp.extend_from_slice(&[255, 0, 0, 0, 255, 0, 0, 0]); // bottom: blue, green
+        bmp.extend_from_slice(&[0, 0, 255, 255, 255, 255, 0, 0]); // top: red, white
+        let sprite = decode(&bmp).unwrap();
+        assert_eq!(sprite.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(sprite.pixel(1, 1), [0, 255, 0, 255]);
+
+        let ascii = decode(b"P3\n# comment\n2 1 15\n15 0 0  0 0 15\n").unwrap();
+        assert_eq!(ascii.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(ascii.pixel(1, 0), [0, 0, 255, 255]);
+        let binary = decode(b"P5 1 1 255\n\x80").unwrap();
+        assert_eq!(binary.pixel(0, 0), [128, 128, 128, 255]);
+
+        assert!(decode(b"P6 2 2 255\n\x00").is_err());
+        assert!(decode(b"GIF89a").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::vfs_persist::{self, Snapshot};
 use crate::{memory::Memory, process::ProcessTable, process::Scheduler, vfs::Vfs};
 
 pub const VERSION: &str = "0.6.7";
@@ -23,8 +24,14 @@ pub struct Kernel {
     log: Vec<String>,
     boot_index: usize,
     pub scheduler: Scheduler,
+    /// The login shell, once boot has started it
+    pub shell_pid: Option<u32>,
     pub memory_panic: bool,
     pub memory_panic_reason: String,
+    /// Saved copies of the filesystem for `snapshot restore`
+    pub snapshots: Vec<Snapshot>,
+    /// The last save never finished, so boot checks the filesystem
+    pub unclean_shutdown: bool,
 }
 
 impl Default for Kernel {
@@ -44,8 +51,11 @@ impl Kernel {
             log: Vec::new(),
             boot_index: 0,
             scheduler: Scheduler::new(),
+            shell_pid: None,
             memory_panic: false,
             memory_panic_reason: String::new(),
+            snapshots: Vec::new(),
+            unclean_shutdown: false,
         }
     }
     fn klog(&mut self, msg: &str) {
@@ -57,6 +67,7 @@ impl Kernel {
     }
     fn memory_panic(&mut self, reason: &str) {
         self.memory_panic = true;
+        self.mem.scribble(4);
         self.memory_panic_reason = format!(
             "KERNEL PANIC - not syncing: Out of memory: {}\n\
              \n\
@@ -310,6 +321,7 @@ impl Kernel {
             }
         };
         self.scheduler.add(sh_pid, crate::process::Priority::Normal);
+        self.shell_pid = Some(sh_pid);
         self.ticks += 10;
         self.raw_log("[  OK  ] Reached target Multi-User System.");
         self.ticks += 3;
@@ -326,6 +338,13 @@ impl Kernel {
         self.ticks += 10;
         self.klog("BOOT_COMPLETE");
     }
+    /// Bring the kernel down; the next boot regenerates the log from scratch
+    pub fn power_off(&mut self) {
+        self.state = KernelState::Off;
+        self.log.clear();
+        self.boot_index = 0;
+        self.ticks = 0;
+    }
     pub fn next_boot_line(&mut self) -> Option<String> {
         if self.log.is_empty() {
             self.generate_boot_log();
@@ -338,20 +357,50 @@ impl Kernel {
             None
         }
     }
+    /// Everything `next_boot_line` has yet to hand out, in one go
+    pub fn drain_boot_log(&mut self) -> Vec<String> {
+        if self.log.is_empty() {
+            self.generate_boot_log();
+        }
+        let rest = self.log[self.boot_index..].to_vec();
+        self.boot_index = self.log.len();
+        rest
+    }
     pub fn tick(&mut self) {
         self.ticks += 1;
     }
     pub fn uptime_ms(&self) -> u64 {
         self.ticks / 1000
     }
+    /// Timestamped kernel ring buffer entries, without firmware and init output
+    pub fn dmesg(&self) -> impl Iterator<Item = &str> {
+        self.log.iter().map(String::as_str).filter(|line| {
+            line.strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .is_some_and(|(ts, _)| ts.trim().parse::<f64>().is_ok())
+        })
+    }
 
-    /// Initialize kernel with persistence loading
-    pub async fn init(&mut self) {
-        self.fs.load_from_persistence().await;
+    /// Initialize kernel with persistence loading. `true` if a full
+    /// filesystem image was restored
+    pub async fn init(&mut self) -> bool {
+        self.snapshots = vfs_persist::load_snapshots().await;
+        self.unclean_shutdown = vfs_persist::was_unclean().await;
+        self.fs.load_from_persistence().await
     }
 
-    /// Save kernel state to persistence
-    pub async fn save(&self) {
-        self.fs.save_to_persistence().await;
+    /// Save the filesystem to IndexedDB. The tree is serialized before this
+    /// returns, so the future holds no borrow of the kernel. The image stays
+    /// marked dirty until the write lands, so closing the tab mid-save is a
+    /// power loss the next boot's fsck has to clean up
+    pub fn save(&self) -> impl std::future::Future<Output = Result<(), String>> + 'static {
+        let image = self.fs.save_image();
+        let snapshots = serde_json::to_string(&self.snapshots).map_err(|e| e.to_string());
+        async move {
+            let (manifest, chunks) = image?;
+            vfs_persist::mark_dirty().await?;
+            vfs_persist::save_image(manifest, chunks).await?;
+            vfs_persist::save_snapshots(&snapshots?).await
+        }
     }
 }
@@ -1,36 +1,59 @@
+pub mod ansi;
+pub mod audio;
 pub mod boot;
+pub mod clock;
 pub mod cpp_accel;
+pub mod digest;
 pub mod doom;
+pub mod engine;
+pub mod font;
 pub mod graphics;
-#[cfg(feature = "webgl")]
 pub mod graphics_gl;
 pub mod grub;
 pub mod idle;
+pub mod image;
 pub mod kernel;
+pub mod lua;
 pub mod memory;
+pub mod mux;
 pub mod nano;
 pub mod network;
+pub mod panic_screen;
+pub mod perf;
 pub mod persist;
 pub mod physics;
+pub mod pkg;
+pub mod pong;
 pub mod process;
 pub mod python;
+pub mod regex;
 pub mod screensaver;
 pub mod services;
 pub mod shell;
+pub mod snake;
+pub mod sqlite;
 pub mod system;
+pub mod terminal;
 pub mod vfs;
 pub mod vfs_persist;
+pub mod viewer;
 
+pub use audio::{beep, bell};
 pub use doom::{memory_usage, start_doom, start_doom_with_difficulty, stop_doom};
 pub use graphics::{Graphics, MatrixScreensaver, SnakeGame};
-#[cfg(feature = "webgl")]
 pub use graphics_gl::WebGlGraphics;
 pub use grub::{GrubMenu, Memtest};
-pub use idle::{set_game_active, set_screensaver_active, start_idle_timer, stop_idle_timer};
+pub use idle::{
+    blank_now, idle_timeout_ms, mark_activity, set_game_active, set_screensaver_active,
+    start_idle_timer, stop_idle_timer,
+};
 pub use nano::NanoEditor;
 pub use network::{fetch_http, post_http};
-pub use screensaver::{start_screensaver, stop_screensaver};
+pub use pong::{start_pong, stop_pong};
+pub use screensaver::{start_screensaver, start_screensaver_kind, stop_screensaver};
+pub use snake::{start_snake, stop_snake};
 pub use system::System;
+pub use viewer::{start_viewer, stop_viewer};
 
 use wasm_bindgen::prelude::*;
 use web_sys::window;
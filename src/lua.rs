@@ -0,0 +1,2840 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Statements and loop iterations one chunk may run before it is stopped
+const MAX_STEPS: u64 = 2_000_000;
+const MAX_DEPTH: usize = 200;
+
+// ---- values ----
+
+#[derive(Clone)]
+pub enum LuaValue {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Table(Rc<RefCell<Table>>),
+    Function(Rc<Closure>),
+    Builtin(&'static str),
+}
+
+pub struct Closure {
+    body: Rc<FuncBody>,
+    env: Rc<Env>,
+}
+
+/// Hashable form of a table key; tables and functions key by identity
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Int(i64),
+    Num(u64),
+    Str(String),
+    Bool(bool),
+    Ptr(usize),
+}
+
+/// A Lua table: a 1-based array part plus insertion-ordered hash entries
+#[derive(Default)]
+pub struct Table {
+    array: Vec<LuaValue>,
+    hash: HashMap<Key, (LuaValue, LuaValue)>,
+    order: Vec<Key>,
+}
+
+impl Table {
+    fn get(&self, key: &LuaValue) -> LuaValue {
+        if let Some(i) = array_index(key) {
+            if i >= 1 && (i as usize) <= self.array.len() {
+                return self.array[i as usize - 1].clone();
+            }
+        }
+        match key_of(key) {
+            Some(k) => self.hash.get(&k).map_or(LuaValue::Nil, |(_, v)| v.clone()),
+            None => LuaValue::Nil,
+        }
+    }
+
+    fn get_str(&self, key: &str) -> LuaValue {
+        self.hash
+            .get(&Key::Str(key.to_string()))
+            .map_or(LuaValue::Nil, |(_, v)| v.clone())
+    }
+
+    fn set(&mut self, key: LuaValue, value: LuaValue) {
+        if let Some(i) = array_index(&key) {
+            let len = self.array.len() as i64;
+            if i >= 1 && i <= len {
+                self.array[i as usize - 1] = value;
+                while matches!(self.array.last(), Some(LuaValue::Nil)) {
+                    self.array.pop();
+                }
+                return;
+            }
+            if i == len + 1 && !matches!(value, LuaValue::Nil) {
+                self.array.push(value);
+                self.remove_hash(&Key::Int(i));
+                // Pull following integer keys over from the hash part
+                let mut next = i + 1;
+                while let Some((_, v)) = self.remove_hash(&Key::Int(next)) {
+                    self.array.push(v);
+                    next += 1;
+                }
+                return;
+            }
+        }
+        let Some(k) = key_of(&key) else { return };
+        if matches!(value, LuaValue::Nil) {
+            self.remove_hash(&k);
+        } else if let Some(entry) = self.hash.get_mut(&k) {
+            entry.1 = value;
+        } else {
+            self.order.push(k.clone());
+            self.hash.insert(k, (normalize_key(key), value));
+        }
+    }
+
+    fn remove_hash(&mut self, k: &Key) -> Option<(LuaValue, LuaValue)> {
+        let removed = self.hash.remove(k)?;
+        self.order.retain(|o| o != k);
+        Some(removed)
+    }
+
+    /// The border `#t`
+    fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    fn entries(&self) -> Vec<(LuaValue, LuaValue)> {
+        let mut out: Vec<(LuaValue, LuaValue)> = self
+            .array
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| !matches!(v, LuaValue::Nil))
+            .map(|(i, v)| (LuaValue::Int(i as i64 + 1), v.clone()))
+            .collect();
+        out.extend(self.order.iter().filter_map(|k| self.hash.get(k).cloned()));
+        out
+    }
+
+    fn next(&self, key: &LuaValue) -> Option<(LuaValue, LuaValue)> {
+        let entries = self.entries();
+        match key {
+            LuaValue::Nil => entries.into_iter().next(),
+            k => {
+                let pos = entries.iter().position(|(e, _)| raw_equal(e, k))?;
+                entries.into_iter().nth(pos + 1)
+            }
+        }
+    }
+}
+
+/// Float keys with an integral value are the same key as the integer
+fn normalize_key(key: LuaValue) -> LuaValue {
+    match key {
+        LuaValue::Num(f) if f.fract() == 0.0 && f.abs() < 9.2e18 => LuaValue::Int(f as i64),
+        other => other,
+    }
+}
+
+fn array_index(key: &LuaValue) -> Option<i64> {
+    match normalize_key(key.clone()) {
+        LuaValue::Int(i) => Some(i),
+        _ => None,
+    }
+}
+
+fn key_of(key: &LuaValue) -> Option<Key> {
+    Some(match normalize_key(key.clone()) {
+        LuaValue::Nil => return None,
+        LuaValue::Num(f) if f.is_nan() => return None,
+        LuaValue::Int(i) => Key::Int(i),
+        LuaValue::Num(f) => Key::Num(f.to_bits()),
+        LuaValue::Str(s) => Key::Str(s),
+        LuaValue::Bool(b) => Key::Bool(b),
+        LuaValue::Table(t) => Key::Ptr(Rc::as_ptr(&t) as *const u8 as usize),
+        LuaValue::Function(f) => Key::Ptr(Rc::as_ptr(&f) as *const u8 as usize),
+        LuaValue::Builtin(name) => Key::Str(format!("builtin:{}", name)),
+    })
+}
+
+fn raw_equal(a: &LuaValue, b: &LuaValue) -> bool {
+    use LuaValue as V;
+    match (a, b) {
+        (V::Nil, V::Nil) => true,
+        (V::Bool(x), V::Bool(y)) => x == y,
+        (V::Int(x), V::Int(y)) => x == y,
+        (V::Int(_) | V::Num(_), V::Int(_) | V::Num(_)) => a.as_f64() == b.as_f64(),
+        (V::Str(x), V::Str(y)) => x == y,
+        (V::Table(x), V::Table(y)) => Rc::ptr_eq(x, y),
+        (V::Function(x), V::Function(y)) => Rc::ptr_eq(x, y),
+        (V::Builtin(x), V::Builtin(y)) => x == y,
+        _ => false,
+    }
+}
+
+impl LuaValue {
+    fn str(s: impl Into<String>) -> Self {
+        LuaValue::Str(s.into())
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LuaValue::Nil => "nil",
+            LuaValue::Bool(_) => "boolean",
+            LuaValue::Int(_) | LuaValue::Num(_) => "number",
+            LuaValue::Str(_) => "string",
+            LuaValue::Table(_) => "table",
+            LuaValue::Function(_) | LuaValue::Builtin(_) => "function",
+        }
+    }
+
+    fn truthy(&self) -> bool {
+        !matches!(self, LuaValue::Nil | LuaValue::Bool(false))
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            LuaValue::Int(i) => Some(*i as f64),
+            LuaValue::Num(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Numbers, and strings that read as numbers, as arithmetic sees them
+    fn to_number(&self) -> Option<LuaValue> {
+        match self {
+            LuaValue::Int(_) | LuaValue::Num(_) => Some(self.clone()),
+            LuaValue::Str(s) => parse_number(s.trim()),
+            _ => None,
+        }
+    }
+
+    fn to_integer(&self) -> Option<i64> {
+        match self.to_number()? {
+            LuaValue::Int(i) => Some(i),
+            LuaValue::Num(f) if f.fract() == 0.0 && f.abs() < 9.2e18 => Some(f as i64),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LuaValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LuaValue::Nil => write!(f, "nil"),
+            LuaValue::Bool(b) => write!(f, "{}", b),
+            LuaValue::Int(i) => write!(f, "{}", i),
+            LuaValue::Num(n) => write!(f, "{}", fmt_float(*n)),
+            LuaValue::Str(s) => write!(f, "{}", s),
+            LuaValue::Table(t) => write!(f, "table: {:p}", Rc::as_ptr(t)),
+            LuaValue::Function(c) => write!(f, "function: {:p}", Rc::as_ptr(c)),
+            LuaValue::Builtin(name) => write!(f, "function: builtin: {}", name),
+        }
+    }
+}
+
+fn parse_number(s: &str) -> Option<LuaValue> {
+    let (neg, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let value = if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        LuaValue::Int(u64::from_str_radix(hex, 16).ok()? as i64)
+    } else if body.chars().all(|c| c.is_ascii_digit()) && !body.is_empty() {
+        match body.parse::<i64>() {
+            Ok(i) => LuaValue::Int(i),
+            Err(_) => LuaValue::Num(body.parse().ok()?),
+        }
+    } else {
+        let lower = body.to_ascii_lowercase();
+        if lower.contains("inf") || lower.contains("nan") || body.is_empty() {
+            return None;
+        }
+        LuaValue::Num(body.parse().ok()?)
+    };
+    Some(match (neg, value) {
+        (true, LuaValue::Int(i)) => LuaValue::Int(i.wrapping_neg()),
+        (true, LuaValue::Num(f)) => LuaValue::Num(-f),
+        (_, v) => v,
+    })
+}
+
+/// `%.14g`, with `.0` kept on integral floats the way Lua 5.4 prints them
+fn fmt_float(f: f64) -> String {
+    if f.is_nan() {
+        return if f.is_sign_negative() { "-nan" } else { "nan" }.into();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "inf" } else { "-inf" }.into();
+    }
+    let s = fmt_g(f, 14, false);
+    if s.contains(['.', 'e', 'n', 'i']) {
+        s
+    } else {
+        format!("{}.0", s)
+    }
+}
+
+/// C's `%.<precision>g`
+fn fmt_g(f: f64, precision: usize, upper: bool) -> String {
+    if !f.is_finite() {
+        return fmt_float(f);
+    }
+    let precision = precision.max(1);
+    let sci = format!("{:.*e}", precision - 1, f);
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let exp: i32 = exp.parse().unwrap_or(0);
+    let trim = |s: &str| -> String {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    let out = if exp < -4 || exp >= precision as i32 {
+        format!(
+            "{}e{}{:02}",
+            trim(mantissa),
+            if exp < 0 { '-' } else { '+' },
+            exp.abs()
+        )
+    } else {
+        trim(&format!(
+            "{:.*}",
+            (precision as i32 - 1 - exp).max(0) as usize,
+            f
+        ))
+    };
+    if upper {
+        out.to_uppercase()
+    } else {
+        out
+    }
+}
+
+// ---- errors ----
+
+#[derive(Clone)]
+pub struct LuaError {
+    value: LuaValue,
+    /// The source ended before the construct did, so the REPL should read on
+    incomplete: bool,
+    /// Raised by `os.exit()` to stop the chunk quietly
+    exit: bool,
+}
+
+impl LuaError {
+    fn value(value: LuaValue) -> Self {
+        LuaError {
+            value,
+            incomplete: false,
+            exit: false,
+        }
+    }
+
+    fn message(&self) -> String {
+        match &self.value {
+            LuaValue::Str(s) => s.clone(),
+            LuaValue::Int(_) | LuaValue::Num(_) => self.value.to_string(),
+            LuaValue::Nil => "nil".into(),
+            other => format!("({} object)", other.type_name()),
+        }
+    }
+}
+
+// ---- lexer ----
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Name(String),
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Op(&'static str),
+    Eof,
+}
+
+const OPERATORS: &[&str] = &[
+    "...", "..", "==", "~=", "<=", ">=", "//", "::", "<<", ">>", "+", "-", "*", "/", "%", "^", "#",
+    "&", "~", "|", "<", ">", "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".",
+];
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+fn syntax_error(chunk: &str, line: usize, msg: &str, near: &str, at_eof: bool) -> LuaError {
+    LuaError {
+        value: LuaValue::Str(format!("{}:{}: {} near {}", chunk, line, msg, near)),
+        incomplete: at_eof,
+        exit: false,
+    }
+}
+
+/// Length of a `[[`/`[==[` opener at `i`, returning its level
+fn long_bracket(chars: &[char], i: usize) -> Option<usize> {
+    if chars.get(i) != Some(&'[') {
+        return None;
+    }
+    let mut level = 0;
+    while chars.get(i + 1 + level) == Some(&'=') {
+        level += 1;
+    }
+    (chars.get(i + 1 + level) == Some(&'[')).then_some(level)
+}
+
+fn tokenize(src: &str, chunk: &str) -> Result<Vec<(Tok, usize)>, LuaError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    // Skip a `#!` line so scripts can be made executable
+    if src.starts_with("#!") {
+        while i < chars.len() && chars[i] != '\n' {
+            i += 1;
+        }
+    }
+    let read_long = |i: &mut usize, line: &mut usize, level: usize| -> Option<String> {
+        *i += level + 2;
+        if chars.get(*i) == Some(&'\n') {
+            *i += 1;
+            *line += 1;
+        }
+        let start = *i;
+        while *i < chars.len() {
+            if chars[*i] == ']'
+                && (1..=level).all(|k| chars.get(*i + k) == Some(&'='))
+                && chars.get(*i + level + 1) == Some(&']')
+            {
+                let text: String = chars[start..*i].iter().collect();
+                *i += level + 2;
+                return Some(text);
+            }
+            if chars[*i] == '\n' {
+                *line += 1;
+            }
+            *i += 1;
+        }
+        None
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            i += 2;
+            if let Some(level) = long_bracket(&chars, i) {
+                if read_long(&mut i, &mut line, level).is_none() {
+                    return Err(syntax_error(
+                        chunk,
+                        line,
+                        "unfinished long comment",
+                        "<eof>",
+                        true,
+                    ));
+                }
+            } else {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            continue;
+        }
+        if let Some(level) = long_bracket(&chars, i) {
+            let start_line = line;
+            let Some(text) = read_long(&mut i, &mut line, level) else {
+                return Err(syntax_error(
+                    chunk,
+                    line,
+                    "unfinished long string",
+                    "<eof>",
+                    true,
+                ));
+            };
+            tokens.push((Tok::Str(text), start_line));
+            continue;
+        }
+        if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            let start = i;
+            let hex = c == '0' && matches!(chars.get(i + 1), Some('x' | 'X'));
+            if hex {
+                i += 2;
+            }
+            while i < chars.len() {
+                let ch = chars[i];
+                let exp = if hex { "pP" } else { "eE" };
+                if ch.is_ascii_alphanumeric() || ch == '.' {
+                    if exp.contains(ch) && matches!(chars.get(i + 1), Some('+' | '-')) {
+                        i += 1;
+                    }
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let tok = match parse_number(&text) {
+                Some(LuaValue::Int(n)) => Tok::Int(n),
+                Some(LuaValue::Num(f)) => Tok::Num(f),
+                _ => {
+                    return Err(syntax_error(
+                        chunk,
+                        line,
+                        "malformed number",
+                        &format!("'{}'", text),
+                        false,
+                    ))
+                }
+            };
+            tokens.push((tok, line));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Tok::Name(chars[start..i].iter().collect()), line));
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            i += 1;
+            let mut s = String::new();
+            loop {
+                let Some(&ch) = chars.get(i) else {
+                    return Err(syntax_error(
+                        chunk,
+                        line,
+                        "unfinished string",
+                        "<eof>",
+                        true,
+                    ));
+                };
+                i += 1;
+                match ch {
+                    _ if ch == c => break,
+                    '\n' => {
+                        return Err(syntax_error(
+                            chunk,
+                            line,
+                            "unfinished string",
+                            &format!("'{}{}'", c, s),
+                            false,
+                        ))
+                    }
+                    '\\' => {
+                        let Some(&esc) = chars.get(i) else { continue };
+                        i += 1;
+                        match esc {
+                            'n' => s.push('\n'),
+                            't' => s.push('\t'),
+                            'r' => s.push('\r'),
+                            'a' => s.push('\x07'),
+                            'b' => s.push('\x08'),
+                            'f' => s.push('\x0c'),
+                            'v' => s.push('\x0b'),
+                            '\n' => {
+                                s.push('\n');
+                                line += 1;
+                            }
+                            'x' => {
+                                let hex: String =
+                                    chars[i..(i + 2).min(chars.len())].iter().collect();
+                                i += hex.len();
+                                s.extend(u8::from_str_radix(&hex, 16).ok().map(char::from));
+                            }
+                            'z' => {
+                                while i < chars.len() && chars[i].is_whitespace() {
+                                    if chars[i] == '\n' {
+                                        line += 1;
+                                    }
+                                    i += 1;
+                                }
+                            }
+                            d if d.is_ascii_digit() => {
+                                let mut code = d.to_digit(10).unwrap_or(0);
+                                for _ in 0..2 {
+                                    match chars.get(i).and_then(|c| c.to_digit(10)) {
+                                        Some(n) => {
+                                            code = code * 10 + n;
+                                            i += 1;
+                                        }
+                                        None => break,
+                                    }
+                                }
+                                s.extend(char::from_u32(code));
+                            }
+                            other => s.push(other),
+                        }
+                    }
+                    _ => s.push(ch),
+                }
+            }
+            tokens.push((Tok::Str(s), line));
+            continue;
+        }
+        let rest: String = chars[i..(i + 3).min(chars.len())].iter().collect();
+        let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+            return Err(syntax_error(
+                chunk,
+                line,
+                "unexpected symbol",
+                &format!("'{}'", c),
+                false,
+            ));
+        };
+        tokens.push((Tok::Op(op), line));
+        i += op.len();
+    }
+    tokens.push((Tok::Eof, line));
+    Ok(tokens)
+}
+
+// ---- syntax tree ----
+
+enum Field {
+    Positional(Expr),
+    Keyed(Expr, Expr),
+}
+
+enum Expr {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Vararg,
+    Function(Rc<FuncBody>),
+    Table(Vec<Field>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Unary(&'static str, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Name(String),
+    Index(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    Method(Box<Expr>, String, Vec<Expr>),
+    /// Parentheses cut a multi-value expression down to one value
+    Paren(Box<Expr>),
+}
+
+struct FuncBody {
+    params: Vec<String>,
+    vararg: bool,
+    body: Vec<Stmt>,
+}
+
+enum StmtKind {
+    Local(Vec<String>, Vec<Expr>),
+    Assign(Vec<Expr>, Vec<Expr>),
+    Call(Expr),
+    Do(Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Repeat(Vec<Stmt>, Expr),
+    If(Vec<(Expr, Vec<Stmt>)>, Vec<Stmt>),
+    NumFor(String, Expr, Expr, Option<Expr>, Vec<Stmt>),
+    GenFor(Vec<String>, Vec<Expr>, Vec<Stmt>),
+    LocalFunction(String, Rc<FuncBody>),
+    Return(Vec<Expr>),
+    Break,
+}
+
+struct Stmt {
+    kind: StmtKind,
+    line: usize,
+}
+
+// ---- parser ----
+
+struct Parser<'a> {
+    tokens: Vec<(Tok, usize)>,
+    pos: usize,
+    chunk: &'a str,
+}
+
+/// Binary operator precedence as (left, right) binding power
+fn binary_precedence(op: &str) -> Option<(u8, u8)> {
+    Some(match op {
+        "or" => (1, 1),
+        "and" => (2, 2),
+        "<" | ">" | "<=" | ">=" | "~=" | "==" => (3, 3),
+        "|" => (4, 4),
+        "~" => (5, 5),
+        "&" => (6, 6),
+        "<<" | ">>" => (7, 7),
+        ".." => (9, 8),
+        "+" | "-" => (10, 10),
+        "*" | "/" | "//" | "%" => (11, 11),
+        "^" => (14, 13),
+        _ => return None,
+    })
+}
+
+const UNARY_PRIORITY: u8 = 12;
+
+impl Parser<'_> {
+    fn peek(&self) -> &Tok {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> Tok {
+        let tok = self.tokens[self.pos].0.clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn near(&self) -> String {
+        match self.peek() {
+            Tok::Eof => "<eof>".into(),
+            Tok::Name(n) => format!("'{}'", n),
+            Tok::Int(i) => format!("'{}'", i),
+            Tok::Num(f) => format!("'{}'", fmt_float(*f)),
+            Tok::Str(s) => format!("'{}'", s),
+            Tok::Op(op) => format!("'{}'", op),
+        }
+    }
+
+    fn error(&self, msg: &str) -> LuaError {
+        syntax_error(
+            self.chunk,
+            self.line(),
+            msg,
+            &self.near(),
+            matches!(self.peek(), Tok::Eof),
+        )
+    }
+
+    fn at_kw(&self, kw: &str) -> bool {
+        matches!(self.peek(), Tok::Name(n) if n == kw)
+    }
+
+    fn at_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Tok::Op(o) if *o == op)
+    }
+
+    fn eat_kw(&mut self, kw: &str) -> bool {
+        let hit = self.at_kw(kw);
+        if hit {
+            self.advance();
+        }
+        hit
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        let hit = self.at_op(op);
+        if hit {
+            self.advance();
+        }
+        hit
+    }
+
+    fn expect_kw(&mut self, kw: &str) -> Result<(), LuaError> {
+        if self.eat_kw(kw) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}' expected", kw)))
+        }
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), LuaError> {
+        if self.eat_op(op) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}' expected", op)))
+        }
+    }
+
+    /// Closes a block opened on `opened`, naming it in the error the way Lua does
+    fn expect_end(&mut self, what: &str, opened: usize) -> Result<(), LuaError> {
+        if self.eat_kw(what) || self.eat_op(what) {
+            return Ok(());
+        }
+        let line = self.line();
+        let msg = if line == opened {
+            format!("'{}' expected", what)
+        } else {
+            format!("'{}' expected (to close at line {})", what, opened)
+        };
+        Err(self.error(&msg))
+    }
+
+    fn name(&mut self) -> Result<String, LuaError> {
+        match self.peek() {
+            Tok::Name(n) if !KEYWORDS.contains(&n.as_str()) => {
+                let n = n.clone();
+                self.advance();
+                Ok(n)
+            }
+            _ => Err(self.error("<name> expected")),
+        }
+    }
+
+    fn block_ends(&self) -> bool {
+        matches!(self.peek(), Tok::Eof)
+            || ["end", "else", "elseif", "until"]
+                .iter()
+                .any(|kw| self.at_kw(kw))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, LuaError> {
+        let mut stmts = Vec::new();
+        while !self.block_ends() {
+            if self.eat_op(";") {
+                continue;
+            }
+            let line = self.line();
+            if self.eat_kw("return") {
+                let values = if self.block_ends() || self.at_op(";") {
+                    Vec::new()
+                } else {
+                    self.expr_list()?
+                };
+                self.eat_op(";");
+                stmts.push(Stmt {
+                    kind: StmtKind::Return(values),
+                    line,
+                });
+                if !self.block_ends() {
+                    return Err(self.error("'<eof>' expected"));
+                }
+                break;
+            }
+            let kind = self.statement()?;
+            stmts.push(Stmt { kind, line });
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<StmtKind, LuaError> {
+        let line = self.line();
+        if self.eat_kw("do") {
+            let body = self.block()?;
+            self.expect_end("end", line)?;
+            return Ok(StmtKind::Do(body));
+        }
+        if self.eat_kw("while") {
+            let cond = self.expr()?;
+            self.expect_kw("do")?;
+            let body = self.block()?;
+            self.expect_end("end", line)?;
+            return Ok(StmtKind::While(cond, body));
+        }
+        if self.eat_kw("repeat") {
+            let body = self.block()?;
+            self.expect_end("until", line)?;
+            return Ok(StmtKind::Repeat(body, self.expr()?));
+        }
+        if self.eat_kw("if") {
+            let mut branches = Vec::new();
+            let cond = self.expr()?;
+            self.expect_kw("then")?;
+            branches.push((cond, self.block()?));
+            let mut orelse = Vec::new();
+            loop {
+                if self.eat_kw("elseif") {
+                    let cond = self.expr()?;
+                    self.expect_kw("then")?;
+                    branches.push((cond, self.block()?));
+                } else if self.eat_kw("else") {
+                    orelse = self.block()?;
+                    self.expect_end("end", line)?;
+                    break;
+                } else {
+                    self.expect_end("end", line)?;
+                    break;
+                }
+            }
+            return Ok(StmtKind::If(branches, orelse));
+        }
+        if self.eat_kw("for") {
+            let first = self.name()?;
+            if self.eat_op("=") {
+                let start = self.expr()?;
+                self.expect_op(",")?;
+                let stop = self.expr()?;
+                let step = if self.eat_op(",") {
+                    Some(self.expr()?)
+                } else {
+                    None
+                };
+                self.expect_kw("do")?;
+                let body = self.block()?;
+                self.expect_end("end", line)?;
+                return Ok(StmtKind::NumFor(first, start, stop, step, body));
+            }
+            let mut names = vec![first];
+            while self.eat_op(",") {
+                names.push(self.name()?);
+            }
+            self.expect_kw("in")?;
+            let exprs = self.expr_list()?;
+            self.expect_kw("do")?;
+            let body = self.block()?;
+            self.expect_end("end", line)?;
+            return Ok(StmtKind::GenFor(names, exprs, body));
+        }
+        if self.eat_kw("function") {
+            // `function a.b.c:m()` assigns to the field, with `self` for methods
+            let mut target = Expr::Name(self.name()?);
+            let mut method = false;
+            while self.at_op(".") || self.at_op(":") {
+                method = self.advance() == Tok::Op(":");
+                let field = self.name()?;
+                target = Expr::Index(Box::new(target), Box::new(Expr::Str(field)));
+                if method {
+                    break;
+                }
+            }
+            let body = self.func_body(method, line)?;
+            return Ok(StmtKind::Assign(vec![target], vec![Expr::Function(body)]));
+        }
+        if self.eat_kw("local") {
+            if self.eat_kw("function") {
+                let name = self.name()?;
+                let body = self.func_body(false, line)?;
+                return Ok(StmtKind::LocalFunction(name, body));
+            }
+            let mut names = vec![self.name()?];
+            self.skip_attrib()?;
+            while self.eat_op(",") {
+                names.push(self.name()?);
+                self.skip_attrib()?;
+            }
+            let values = if self.eat_op("=") {
+                self.expr_list()?
+            } else {
+                Vec::new()
+            };
+            return Ok(StmtKind::Local(names, values));
+        }
+        if self.eat_kw("break") {
+            return Ok(StmtKind::Break);
+        }
+        if self.at_kw("goto") || self.at_op("::") {
+            return Err(self.error("goto and labels are not supported"));
+        }
+
+        let expr = self.suffixed_expr()?;
+        if self.at_op("=") || self.at_op(",") {
+            let mut targets = vec![expr];
+            while self.eat_op(",") {
+                targets.push(self.suffixed_expr()?);
+            }
+            self.expect_op("=")?;
+            for t in &targets {
+                if !matches!(t, Expr::Name(_) | Expr::Index(..)) {
+                    return Err(self.error("syntax error"));
+                }
+            }
+            return Ok(StmtKind::Assign(targets, self.expr_list()?));
+        }
+        match expr {
+            Expr::Call(..) | Expr::Method(..) => Ok(StmtKind::Call(expr)),
+            _ => Err(self.error("syntax error")),
+        }
+    }
+
+    /// `<const>`/`<close>` attributes are accepted and ignored
+    fn skip_attrib(&mut self) -> Result<(), LuaError> {
+        if self.eat_op("<") {
+            self.name()?;
+            self.expect_op(">")?;
+        }
+        Ok(())
+    }
+
+    fn func_body(&mut self, method: bool, line: usize) -> Result<Rc<FuncBody>, LuaError> {
+        self.expect_op("(")?;
+        let mut params = Vec::new();
+        if method {
+            params.push("self".to_string());
+        }
+        let mut vararg = false;
+        if !self.at_op(")") {
+            loop {
+                if self.eat_op("...") {
+                    vararg = true;
+                    break;
+                }
+                params.push(self.name()?);
+                if !self.eat_op(",") {
+                    break;
+                }
+            }
+        }
+        self.expect_op(")")?;
+        let body = self.block()?;
+        self.expect_end("end", line)?;
+        Ok(Rc::new(FuncBody {
+            params,
+            vararg,
+            body,
+        }))
+    }
+
+    fn expr_list(&mut self) -> Result<Vec<Expr>, LuaError> {
+        let mut exprs = vec![self.expr()?];
+        while self.eat_op(",") {
+            exprs.push(self.expr()?);
+        }
+        Ok(exprs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, LuaError> {
+        self.sub_expr(0)
+    }
+
+    fn binary_op(&self) -> Option<&'static str> {
+        match self.peek() {
+            Tok::Op(op) => binary_precedence(op).map(|_| *op),
+            Tok::Name(n) if n == "and" => Some("and"),
+            Tok::Name(n) if n == "or" => Some("or"),
+            _ => None,
+        }
+    }
+
+    fn sub_expr(&mut self, limit: u8) -> Result<Expr, LuaError> {
+        let unary = match self.peek() {
+            Tok::Name(n) if n == "not" => Some("not"),
+            Tok::Op(op @ ("-" | "#" | "~")) => Some(*op),
+            _ => None,
+        };
+        let mut left = match unary {
+            Some(op) => {
+                self.advance();
+                let operand = self.sub_expr(UNARY_PRIORITY)?;
+                match (op, operand) {
+                    ("-", Expr::Int(i)) => Expr::Int(i.wrapping_neg()),
+                    ("-", Expr::Num(f)) => Expr::Num(-f),
+                    (op, operand) => Expr::Unary(op, Box::new(operand)),
+                }
+            }
+            None => self.simple_expr()?,
+        };
+        while let Some(op) = self.binary_op() {
+            let (lp, rp) = binary_precedence(op).unwrap_or((0, 0));
+            if lp <= limit {
+                break;
+            }
+            self.advance();
+            let right = self.sub_expr(rp)?;
+            left = match op {
+                "and" => Expr::And(Box::new(left), Box::new(right)),
+                "or" => Expr::Or(Box::new(left), Box::new(right)),
+                _ => Expr::Binary(op, Box::new(left), Box::new(right)),
+            };
+        }
+        Ok(left)
+    }
+
+    fn simple_expr(&mut self) -> Result<Expr, LuaError> {
+        let line = self.line();
+        let expr = match self.peek().clone() {
+            Tok::Int(i) => Expr::Int(i),
+            Tok::Num(f) => Expr::Num(f),
+            Tok::Str(s) => Expr::Str(s),
+            Tok::Op("...") => Expr::Vararg,
+            Tok::Op("{") => return self.table(),
+            Tok::Name(n) => match n.as_str() {
+                "nil" => Expr::Nil,
+                "true" => Expr::Bool(true),
+                "false" => Expr::Bool(false),
+                "function" => {
+                    self.advance();
+                    return Ok(Expr::Function(self.func_body(false, line)?));
+                }
+                _ => return self.suffixed_expr(),
+            },
+            _ => return self.suffixed_expr(),
+        };
+        self.advance();
+        Ok(expr)
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr, LuaError> {
+        if self.eat_op("(") {
+            let inner = self.expr()?;
+            self.expect_op(")")?;
+            return Ok(Expr::Paren(Box::new(inner)));
+        }
+        match self.peek() {
+            Tok::Name(n) if !KEYWORDS.contains(&n.as_str()) => Ok(Expr::Name(self.name()?)),
+            _ => Err(self.error("unexpected symbol")),
+        }
+    }
+
+    fn suffixed_expr(&mut self) -> Result<Expr, LuaError> {
+        let mut expr = self.primary_expr()?;
+        loop {
+            if self.eat_op(".") {
+                let field = self.name()?;
+                expr = Expr::Index(Box::new(expr), Box::new(Expr::Str(field)));
+            } else if self.eat_op("[") {
+                let key = self.expr()?;
+                self.expect_op("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(key));
+            } else if self.eat_op(":") {
+                let method = self.name()?;
+                let args = self.call_args()?;
+                expr = Expr::Method(Box::new(expr), method, args);
+            } else if self.at_op("(") || self.at_op("{") || matches!(self.peek(), Tok::Str(_)) {
+                let args = self.call_args()?;
+                expr = Expr::Call(Box::new(expr), args);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn call_args(&mut self) -> Result<Vec<Expr>, LuaError> {
+        match self.peek().clone() {
+            Tok::Str(s) => {
+                self.advance();
+                Ok(vec![Expr::Str(s)])
+            }
+            Tok::Op("{") => Ok(vec![self.table()?]),
+            _ => {
+                self.expect_op("(")?;
+                if self.eat_op(")") {
+                    return Ok(Vec::new());
+                }
+                let args = self.expr_list()?;
+                self.expect_op(")")?;
+                Ok(args)
+            }
+        }
+    }
+
+    fn table(&mut self) -> Result<Expr, LuaError> {
+        let line = self.line();
+        self.expect_op("{")?;
+        let mut fields = Vec::new();
+        while !self.at_op("}") {
+            if self.eat_op("[") {
+                let key = self.expr()?;
+                self.expect_op("]")?;
+                self.expect_op("=")?;
+                fields.push(Field::Keyed(key, self.expr()?));
+            } else if matches!(self.peek(), Tok::Name(n) if !KEYWORDS.contains(&n.as_str()))
+                && self.tokens.get(self.pos + 1).map(|t| &t.0) == Some(&Tok::Op("="))
+            {
+                let name = self.name()?;
+                self.advance();
+                fields.push(Field::Keyed(Expr::Str(name), self.expr()?));
+            } else {
+                fields.push(Field::Positional(self.expr()?));
+            }
+            if !self.eat_op(",") && !self.eat_op(";") {
+                break;
+            }
+        }
+        self.expect_end("}", line)?;
+        Ok(Expr::Table(fields))
+    }
+}
+
+fn parse(src: &str, chunk: &str) -> Result<Vec<Stmt>, LuaError> {
+    let mut parser = Parser {
+        tokens: tokenize(src, chunk)?,
+        pos: 0,
+        chunk,
+    };
+    let block = parser.block()?;
+    if !matches!(parser.peek(), Tok::Eof) {
+        return Err(parser.error("'<eof>' expected"));
+    }
+    Ok(block)
+}
+
+// ---- interpreter ----
+
+/// Locals of one block; function scopes also carry their `...` values
+struct Env {
+    vars: RefCell<HashMap<String, LuaValue>>,
+    parent: Option<Rc<Env>>,
+    varargs: Option<Vec<LuaValue>>,
+}
+
+impl Env {
+    fn child(parent: &Rc<Env>) -> Rc<Env> {
+        Rc::new(Env {
+            vars: RefCell::new(HashMap::new()),
+            parent: Some(parent.clone()),
+            varargs: None,
+        })
+    }
+
+    fn find(self: &Rc<Env>, name: &str) -> Option<Rc<Env>> {
+        let mut env = Some(self.clone());
+        while let Some(e) = env {
+            if e.vars.borrow().contains_key(name) {
+                return Some(e);
+            }
+            env = e.parent.clone();
+        }
+        None
+    }
+
+    fn declare(&self, name: &str, value: LuaValue) {
+        self.vars.borrow_mut().insert(name.to_string(), value);
+    }
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Return(Vec<LuaValue>),
+}
+
+const LIBRARIES: &[(&str, &[&str])] = &[
+    (
+        "string",
+        &[
+            "len", "sub", "upper", "lower", "rep", "reverse", "byte", "char", "format", "find",
+            "gsub",
+        ],
+    ),
+    ("table", &["insert", "remove", "concat", "sort", "unpack"]),
+    (
+        "math",
+        &[
+            "floor",
+            "ceil",
+            "sqrt",
+            "abs",
+            "max",
+            "min",
+            "fmod",
+            "tointeger",
+            "type",
+            "random",
+            "randomseed",
+            "sin",
+            "cos",
+            "tan",
+            "exp",
+            "log",
+        ],
+    ),
+    ("os", &["time", "clock", "exit"]),
+];
+
+const GLOBAL_FUNCTIONS: &[&str] = &[
+    "print", "type", "tostring", "tonumber", "pairs", "ipairs", "next", "select", "error",
+    "assert", "pcall", "unpack", "rawequal", "rawget", "rawset", "rawlen",
+];
+
+pub struct LuaInterpreter {
+    globals: Rc<RefCell<Table>>,
+    output: String,
+    /// REPL lines collected until a statement is complete
+    pending: String,
+    chunk: String,
+    line: usize,
+    depth: usize,
+    steps: u64,
+    rng: Option<u64>,
+    exited: bool,
+}
+
+impl Default for LuaInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LuaInterpreter {
+    pub fn new() -> Self {
+        let mut globals = Table::default();
+        for name in GLOBAL_FUNCTIONS {
+            globals.set(LuaValue::str(*name), LuaValue::Builtin(name));
+        }
+        for (lib, funcs) in LIBRARIES {
+            let mut table = Table::default();
+            for f in *funcs {
+                // Library entries are named "lib.func" so errors can cite them
+                let full = BUILTIN_NAMES
+                    .iter()
+                    .find(|n| n.split_once('.') == Some((lib, f)))
+                    .copied()
+                    .unwrap_or("print");
+                table.set(LuaValue::str(*f), LuaValue::Builtin(full));
+            }
+            if *lib == "math" {
+                table.set(LuaValue::str("pi"), LuaValue::Num(std::f64::consts::PI));
+                table.set(LuaValue::str("huge"), LuaValue::Num(f64::INFINITY));
+                table.set(LuaValue::str("maxinteger"), LuaValue::Int(i64::MAX));
+                table.set(LuaValue::str("mininteger"), LuaValue::Int(i64::MIN));
+            }
+            globals.set(
+                LuaValue::str(*lib),
+                LuaValue::Table(Rc::new(RefCell::new(table))),
+            );
+        }
+        globals.set(LuaValue::str("_VERSION"), LuaValue::str("Lua 5.4"));
+        let globals = Rc::new(RefCell::new(globals));
+        globals
+            .borrow_mut()
+            .set(LuaValue::str("_G"), LuaValue::Table(globals.clone()));
+        LuaInterpreter {
+            globals,
+            output: String::new(),
+            pending: String::new(),
+            chunk: "stdin".into(),
+            line: 0,
+            depth: 0,
+            steps: 0,
+            rng: None,
+            exited: false,
+        }
+    }
+
+    /// Prompt for the next REPL line: `>> ` while a statement is unfinished
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() {
+            "> "
+        } else {
+            ">> "
+        }
+    }
+
+    /// Feed one REPL line. Returns `None` while the statement needs more
+    /// lines; expressions print their values like the stand-alone `lua`.
+    pub fn push_line(&mut self, line: &str) -> Option<Result<String, String>> {
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let source = self.pending.clone();
+        // Try the input as an expression first, then as statements
+        let as_expr = format!("return {}", source);
+        let program = match parse(&as_expr, "stdin") {
+            Ok(program) => Ok(program),
+            Err(_) => parse(&source, "stdin"),
+        };
+        match program {
+            Err(e) if e.incomplete => None,
+            program => {
+                self.pending.clear();
+                Some(self.finish(program, true))
+            }
+        }
+    }
+
+    /// Whether the last chunk called `os.exit()`
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Run a whole chunk, as `lua file.lua` does
+    pub fn run_script(&mut self, source: &str, chunk: &str) -> Result<String, String> {
+        let chunk = chunk.to_string();
+        self.chunk = chunk.clone();
+        let result = self.finish(parse(source, &chunk), false);
+        self.chunk = "stdin".into();
+        result
+    }
+
+    fn finish(
+        &mut self,
+        program: Result<Vec<Stmt>, LuaError>,
+        echo: bool,
+    ) -> Result<String, String> {
+        self.output.clear();
+        self.steps = 0;
+        self.depth = 0;
+        let result = program.and_then(|program| {
+            let env = Rc::new(Env {
+                vars: RefCell::new(HashMap::new()),
+                parent: None,
+                varargs: Some(Vec::new()),
+            });
+            self.exec_block(&program, &env)
+        });
+        let mut out = std::mem::take(&mut self.output);
+        self.exited = matches!(&result, Err(e) if e.exit);
+        match result {
+            Ok(Flow::Return(values)) if echo && !values.is_empty() => {
+                let parts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                out.push_str(&parts.join("\t"));
+            }
+            Err(e) if !e.exit => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                // The stand-alone interpreter names itself before script errors
+                if !echo {
+                    out.push_str("lua: ");
+                }
+                out.push_str(&e.message());
+                return Err(out);
+            }
+            _ => {}
+        }
+        if out.ends_with('\n') {
+            out.pop();
+        }
+        Ok(out)
+    }
+
+    fn error(&self, msg: &str) -> LuaError {
+        LuaError::value(LuaValue::Str(format!(
+            "{}:{}: {}",
+            self.chunk, self.line, msg
+        )))
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    // ---- statements ----
+
+    fn exec_block(&mut self, block: &[Stmt], env: &Rc<Env>) -> Result<Flow, LuaError> {
+        for stmt in block {
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return Err(self.error("execution step limit exceeded (infinite loop?)"));
+            }
+            self.line = stmt.line;
+            match self.exec_stmt(stmt, env)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt, env: &Rc<Env>) -> Result<Flow, LuaError> {
+        match &stmt.kind {
+            StmtKind::Local(names, exprs) => {
+                let values = self.eval_list(exprs, env)?;
+                for (i, name) in names.iter().enumerate() {
+                    env.declare(name, values.get(i).cloned().unwrap_or(LuaValue::Nil));
+                }
+            }
+            StmtKind::LocalFunction(name, body) => {
+                // Declared first so the function can call itself
+                env.declare(name, LuaValue::Nil);
+                let func = self.closure(body, env);
+                env.declare(name, func);
+            }
+            StmtKind::Assign(targets, exprs) => {
+                let values = self.eval_list(exprs, env)?;
+                for (i, target) in targets.iter().enumerate() {
+                    let value = values.get(i).cloned().unwrap_or(LuaValue::Nil);
+                    self.assign(target, value, env)?;
+                }
+            }
+            StmtKind::Call(expr) => {
+                self.eval_multi(expr, env)?;
+            }
+            StmtKind::Do(body) => return self.exec_block(body, &Env::child(env)),
+            StmtKind::While(cond, body) => {
+                while self.eval(cond, env)?.truthy() {
+                    match self.exec_block(body, &Env::child(env))? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        Flow::Normal => {}
+                    }
+                }
+            }
+            StmtKind::Repeat(body, cond) => loop {
+                // The condition can see the body's locals
+                let scope = Env::child(env);
+                match self.exec_block(body, &scope)? {
+                    Flow::Break => break,
+                    Flow::Return(v) => return Ok(Flow::Return(v)),
+                    Flow::Normal => {}
+                }
+                if self.eval(cond, &scope)?.truthy() {
+                    break;
+                }
+            },
+            StmtKind::If(branches, orelse) => {
+                for (cond, body) in branches {
+                    if self.eval(cond, env)?.truthy() {
+                        return self.exec_block(body, &Env::child(env));
+                    }
+                }
+                return self.exec_block(orelse, &Env::child(env));
+            }
+            StmtKind::NumFor(var, start, stop, step, body) => {
+                let start = self.eval(start, env)?;
+                let stop = self.eval(stop, env)?;
+                let step = match step {
+                    Some(s) => self.eval(s, env)?,
+                    None => LuaValue::Int(1),
+                };
+                let (Some(start), Some(stop), Some(step)) =
+                    (start.to_number(), stop.to_number(), step.to_number())
+                else {
+                    return Err(self.error("'for' initial value must be a number"));
+                };
+                match (start, stop, step) {
+                    (LuaValue::Int(start), LuaValue::Int(stop), LuaValue::Int(step)) => {
+                        if step == 0 {
+                            return Err(self.error("'for' step is zero"));
+                        }
+                        let mut i = start;
+                        while (step > 0 && i <= stop) || (step < 0 && i >= stop) {
+                            let scope = Env::child(env);
+                            scope.declare(var, LuaValue::Int(i));
+                            match self.exec_block(body, &scope)? {
+                                Flow::Break => break,
+                                Flow::Return(v) => return Ok(Flow::Return(v)),
+                                Flow::Normal => {}
+                            }
+                            match i.checked_add(step) {
+                                Some(next) => i = next,
+                                None => break,
+                            }
+                        }
+                    }
+                    (start, stop, step) => {
+                        let (start, stop, step) = (
+                            start.as_f64().unwrap_or(0.0),
+                            stop.as_f64().unwrap_or(0.0),
+                            step.as_f64().unwrap_or(0.0),
+                        );
+                        if step == 0.0 {
+                            return Err(self.error("'for' step is zero"));
+                        }
+                        let mut i = start;
+                        while (step > 0.0 && i <= stop) || (step < 0.0 && i >= stop) {
+                            let scope = Env::child(env);
+                            scope.declare(var, LuaValue::Num(i));
+                            match self.exec_block(body, &scope)? {
+                                Flow::Break => break,
+                                Flow::Return(v) => return Ok(Flow::Return(v)),
+                                Flow::Normal => {}
+                            }
+                            i += step;
+                        }
+                    }
+                }
+            }
+            StmtKind::GenFor(names, exprs, body) => {
+                let values = self.eval_list(exprs, env)?;
+                let mut it = values.into_iter();
+                let func = it.next().unwrap_or(LuaValue::Nil);
+                let state = it.next().unwrap_or(LuaValue::Nil);
+                let mut control = it.next().unwrap_or(LuaValue::Nil);
+                loop {
+                    let results = self.call(&func, vec![state.clone(), control.clone()])?;
+                    let first = results.first().cloned().unwrap_or(LuaValue::Nil);
+                    if matches!(first, LuaValue::Nil) {
+                        break;
+                    }
+                    control = first;
+                    let scope = Env::child(env);
+                    for (i, name) in names.iter().enumerate() {
+                        scope.declare(name, results.get(i).cloned().unwrap_or(LuaValue::Nil));
+                    }
+                    match self.exec_block(body, &scope)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        Flow::Normal => {}
+                    }
+                }
+            }
+            StmtKind::Return(exprs) => {
+                // `return f(x)` keeps every value f returns
+                return Ok(Flow::Return(self.eval_list(exprs, env)?));
+            }
+            StmtKind::Break => return Ok(Flow::Break),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn assign(&mut self, target: &Expr, value: LuaValue, env: &Rc<Env>) -> Result<(), LuaError> {
+        match target {
+            Expr::Name(name) => {
+                match env.find(name) {
+                    Some(scope) => scope.declare(name, value),
+                    None => self.globals.borrow_mut().set(LuaValue::str(name), value),
+                }
+                Ok(())
+            }
+            Expr::Index(obj, key) => {
+                let table = self.eval(obj, env)?;
+                let key = self.eval(key, env)?;
+                let LuaValue::Table(t) = table else {
+                    return Err(self.error(&format!(
+                        "attempt to index a {} value{}",
+                        table.type_name(),
+                        describe(obj, env)
+                    )));
+                };
+                match &key {
+                    LuaValue::Nil => return Err(self.error("table index is nil")),
+                    LuaValue::Num(f) if f.is_nan() => return Err(self.error("table index is NaN")),
+                    _ => {}
+                }
+                t.borrow_mut().set(key, value);
+                Ok(())
+            }
+            _ => Err(self.error("cannot assign")),
+        }
+    }
+
+    // ---- expressions ----
+
+    /// Evaluate an expression list, expanding the last one's extra values
+    fn eval_list(&mut self, exprs: &[Expr], env: &Rc<Env>) -> Result<Vec<LuaValue>, LuaError> {
+        let mut out = Vec::with_capacity(exprs.len());
+        for (i, e) in exprs.iter().enumerate() {
+            if i + 1 == exprs.len() {
+                out.extend(self.eval_multi(e, env)?);
+            } else {
+                out.push(self.eval(e, env)?);
+            }
+        }
+        Ok(out)
+    }
+
+    fn eval_multi(&mut self, expr: &Expr, env: &Rc<Env>) -> Result<Vec<LuaValue>, LuaError> {
+        match expr {
+            Expr::Call(func, args) => {
+                let f = self.eval(func, env)?;
+                let args = self.eval_list(args, env)?;
+                if !matches!(f, LuaValue::Function(_) | LuaValue::Builtin(_)) {
+                    return Err(self.error(&format!(
+                        "attempt to call a {} value{}",
+                        f.type_name(),
+                        describe(func, env)
+                    )));
+                }
+                self.call(&f, args)
+            }
+            Expr::Method(obj, name, args) => {
+                let target = self.eval(obj, env)?;
+                let method = self.index(&target, &LuaValue::str(name.as_str()), obj, env)?;
+                if !matches!(method, LuaValue::Function(_) | LuaValue::Builtin(_)) {
+                    return Err(self.error(&format!(
+                        "attempt to call a {} value (method '{}')",
+                        method.type_name(),
+                        name
+                    )));
+                }
+                let mut call_args = vec![target];
+                call_args.extend(self.eval_list(args, env)?);
+                self.call(&method, call_args)
+            }
+            Expr::Vararg => {
+                let mut scope = Some(env.clone());
+                while let Some(s) = scope {
+                    if let Some(values) = &s.varargs {
+                        return Ok(values.clone());
+                    }
+                    scope = s.parent.clone();
+                }
+                Ok(Vec::new())
+            }
+            other => Ok(vec![self.eval(other, env)?]),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, env: &Rc<Env>) -> Result<LuaValue, LuaError> {
+        Ok(match expr {
+            Expr::Nil => LuaValue::Nil,
+            Expr::Bool(b) => LuaValue::Bool(*b),
+            Expr::Int(i) => LuaValue::Int(*i),
+            Expr::Num(f) => LuaValue::Num(*f),
+            Expr::Str(s) => LuaValue::str(s.as_str()),
+            Expr::Function(body) => self.closure(body, env),
+            Expr::Name(name) => match env.find(name) {
+                Some(scope) => scope
+                    .vars
+                    .borrow()
+                    .get(name)
+                    .cloned()
+                    .unwrap_or(LuaValue::Nil),
+                None => self.globals.borrow().get_str(name),
+            },
+            Expr::Index(obj, key) => {
+                let target = self.eval(obj, env)?;
+                let key = self.eval(key, env)?;
+                self.index(&target, &key, obj, env)?
+            }
+            Expr::Paren(inner) => self.eval(inner, env)?,
+            Expr::And(a, b) => {
+                let left = self.eval(a, env)?;
+                if !left.truthy() {
+                    return Ok(left);
+                }
+                self.eval(b, env)?
+            }
+            Expr::Or(a, b) => {
+                let left = self.eval(a, env)?;
+                if left.truthy() {
+                    return Ok(left);
+                }
+                self.eval(b, env)?
+            }
+            Expr::Unary(op, operand) => {
+                let v = self.eval(operand, env)?;
+                self.unary(op, v, operand, env)?
+            }
+            Expr::Binary(op, a, b) => {
+                let left = self.eval(a, env)?;
+                let right = self.eval(b, env)?;
+                self.binary(op, left, right, a, b, env)?
+            }
+            Expr::Table(fields) => {
+                let mut table = Table::default();
+                let mut next = 1;
+                for (i, field) in fields.iter().enumerate() {
+                    match field {
+                        Field::Keyed(k, v) => {
+                            let key = self.eval(k, env)?;
+                            if matches!(key, LuaValue::Nil) {
+                                return Err(self.error("table index is nil"));
+                            }
+                            let value = self.eval(v, env)?;
+                            table.set(key, value);
+                        }
+                        // The last positional field expands every value it produces
+                        Field::Positional(e) if i + 1 == fields.len() => {
+                            for value in self.eval_multi(e, env)? {
+                                table.set(LuaValue::Int(next), value);
+                                next += 1;
+                            }
+                        }
+                        Field::Positional(e) => {
+                            let value = self.eval(e, env)?;
+                            table.set(LuaValue::Int(next), value);
+                            next += 1;
+                        }
+                    }
+                }
+                LuaValue::Table(Rc::new(RefCell::new(table)))
+            }
+            other => self
+                .eval_multi(other, env)?
+                .into_iter()
+                .next()
+                .unwrap_or(LuaValue::Nil),
+        })
+    }
+
+    fn closure(&self, body: &Rc<FuncBody>, env: &Rc<Env>) -> LuaValue {
+        LuaValue::Function(Rc::new(Closure {
+            body: body.clone(),
+            env: env.clone(),
+        }))
+    }
+
+    fn index(
+        &mut self,
+        target: &LuaValue,
+        key: &LuaValue,
+        expr: &Expr,
+        env: &Rc<Env>,
+    ) -> Result<LuaValue, LuaError> {
+        match target {
+            LuaValue::Table(t) => Ok(t.borrow().get(key)),
+            // Strings index the string library, so `s:upper()` works
+            LuaValue::Str(_) => Ok(match self.globals.borrow().get_str("string") {
+                LuaValue::Table(lib) => lib.borrow().get(key),
+                _ => LuaValue::Nil,
+            }),
+            other => Err(self.error(&format!(
+                "attempt to index a {} value{}",
+                other.type_name(),
+                describe(expr, env)
+            ))),
+        }
+    }
+
+    fn arith_error(
+        &self,
+        op: &str,
+        a: &LuaValue,
+        b: &LuaValue,
+        ea: &Expr,
+        eb: &Expr,
+        env: &Rc<Env>,
+    ) -> LuaError {
+        let (bad, expr) = if a.to_number().is_none() {
+            (a, ea)
+        } else {
+            (b, eb)
+        };
+        let verb = match op {
+            ".." => "concatenate",
+            "&" | "|" | "~" | "<<" | ">>" => "perform bitwise operation on",
+            _ => "perform arithmetic on",
+        };
+        self.error(&format!(
+            "attempt to {} a {} value{}",
+            verb,
+            bad.type_name(),
+            describe(expr, env)
+        ))
+    }
+
+    fn unary(
+        &mut self,
+        op: &str,
+        v: LuaValue,
+        expr: &Expr,
+        env: &Rc<Env>,
+    ) -> Result<LuaValue, LuaError> {
+        match op {
+            "not" => Ok(LuaValue::Bool(!v.truthy())),
+            "#" => match &v {
+                LuaValue::Str(s) => Ok(LuaValue::Int(s.len() as i64)),
+                LuaValue::Table(t) => Ok(LuaValue::Int(t.borrow().len() as i64)),
+                other => Err(self.error(&format!(
+                    "attempt to get length of a {} value{}",
+                    other.type_name(),
+                    describe(expr, env)
+                ))),
+            },
+            "-" => match v.to_number() {
+                Some(LuaValue::Int(i)) => Ok(LuaValue::Int(i.wrapping_neg())),
+                Some(LuaValue::Num(f)) => Ok(LuaValue::Num(-f)),
+                _ => Err(self.arith_error(op, &v, &v, expr, expr, env)),
+            },
+            _ => match v.to_integer() {
+                Some(i) => Ok(LuaValue::Int(!i)),
+                None => Err(self.arith_error("~", &v, &v, expr, expr, env)),
+            },
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: &str,
+        a: LuaValue,
+        b: LuaValue,
+        ea: &Expr,
+        eb: &Expr,
+        env: &Rc<Env>,
+    ) -> Result<LuaValue, LuaError> {
+        use LuaValue as V;
+        match op {
+            "==" => return Ok(V::Bool(raw_equal(&a, &b))),
+            "~=" => return Ok(V::Bool(!raw_equal(&a, &b))),
+            "<" | ">" | "<=" | ">=" => {
+                let ord = match (&a, &b) {
+                    (V::Str(x), V::Str(y)) => x.partial_cmp(y),
+                    (V::Int(x), V::Int(y)) => x.partial_cmp(y),
+                    (V::Int(_) | V::Num(_), V::Int(_) | V::Num(_)) => {
+                        a.as_f64().partial_cmp(&b.as_f64())
+                    }
+                    _ => {
+                        let (x, y) = (a.type_name(), b.type_name());
+                        return Err(self.error(&if x == y {
+                            format!("attempt to compare two {} values", x)
+                        } else {
+                            format!("attempt to compare {} with {}", x, y)
+                        }));
+                    }
+                };
+                use std::cmp::Ordering::*;
+                return Ok(V::Bool(match (op, ord) {
+                    (_, None) => false,
+                    ("<", Some(o)) => o == Less,
+                    (">", Some(o)) => o == Greater,
+                    ("<=", Some(o)) => o != Greater,
+                    (_, Some(o)) => o != Less,
+                }));
+            }
+            ".." => {
+                let piece = |v: &LuaValue| match v {
+                    V::Str(s) => Some(s.clone()),
+                    V::Int(_) | V::Num(_) => Some(v.to_string()),
+                    _ => None,
+                };
+                return match (piece(&a), piece(&b)) {
+                    (Some(x), Some(y)) => Ok(V::Str(x + &y)),
+                    (None, _) => Err(self.concat_error(&a, ea, env)),
+                    (_, None) => Err(self.concat_error(&b, eb, env)),
+                };
+            }
+            _ => {}
+        }
+        let (Some(x), Some(y)) = (a.to_number(), b.to_number()) else {
+            return Err(self.arith_error(op, &a, &b, ea, eb, env));
+        };
+        if matches!(op, "&" | "|" | "~" | "<<" | ">>") {
+            let (Some(x), Some(y)) = (x.to_integer(), y.to_integer()) else {
+                return Err(self.error("number has no integer representation"));
+            };
+            return Ok(V::Int(match op {
+                "&" => x & y,
+                "|" => x | y,
+                "~" => x ^ y,
+                "<<" if y >= 64 || y <= -64 => 0,
+                "<<" if y >= 0 => ((x as u64) << y) as i64,
+                "<<" => ((x as u64) >> -y) as i64,
+                _ if y >= 64 || y <= -64 => 0,
+                _ if y >= 0 => ((x as u64) >> y) as i64,
+                _ => ((x as u64) << -y) as i64,
+            }));
+        }
+        if let (V::Int(x), V::Int(y)) = (&x, &y) {
+            let (x, y) = (*x, *y);
+            match op {
+                "+" => return Ok(V::Int(x.wrapping_add(y))),
+                "-" => return Ok(V::Int(x.wrapping_sub(y))),
+                "*" => return Ok(V::Int(x.wrapping_mul(y))),
+                "//" if y == 0 => return Err(self.error("attempt to perform 'n//0'")),
+                "%" if y == 0 => return Err(self.error("attempt to perform 'n%%0'")),
+                "//" => {
+                    let q = x.wrapping_div(y);
+                    return Ok(V::Int(
+                        if (x.wrapping_rem(y) != 0) && ((x < 0) != (y < 0)) {
+                            q - 1
+                        } else {
+                            q
+                        },
+                    ));
+                }
+                "%" => {
+                    let r = x.wrapping_rem(y);
+                    return Ok(V::Int(if r != 0 && (r < 0) != (y < 0) {
+                        r + y
+                    } else {
+                        r
+                    }));
+                }
+                _ => {}
+            }
+        }
+        let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+        Ok(V::Num(match op {
+            "+" => x + y,
+            "-" => x - y,
+            "*" => x * y,
+            "/" => x / y,
+            "^" => x.powf(y),
+            "//" => (x / y).floor(),
+            _ => {
+                let r = x % y;
+                if r != 0.0 && (r < 0.0) != (y < 0.0) {
+                    r + y
+                } else {
+                    r
+                }
+            }
+        }))
+    }
+
+    fn concat_error(&self, v: &LuaValue, expr: &Expr, env: &Rc<Env>) -> LuaError {
+        self.error(&format!(
+            "attempt to concatenate a {} value{}",
+            v.type_name(),
+            describe(expr, env)
+        ))
+    }
+
+    // ---- calls ----
+
+    fn call(&mut self, func: &LuaValue, args: Vec<LuaValue>) -> Result<Vec<LuaValue>, LuaError> {
+        match func {
+            LuaValue::Builtin(name) => self.call_builtin(name, args),
+            LuaValue::Function(closure) => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(self.error("stack overflow"));
+                }
+                let body = &closure.body;
+                let mut args = args.into_iter();
+                let mut vars = HashMap::new();
+                for param in &body.params {
+                    vars.insert(param.clone(), args.next().unwrap_or(LuaValue::Nil));
+                }
+                let env = Rc::new(Env {
+                    vars: RefCell::new(vars),
+                    parent: Some(closure.env.clone()),
+                    varargs: Some(if body.vararg {
+                        args.collect()
+                    } else {
+                        Vec::new()
+                    }),
+                });
+                let line = self.line;
+                self.depth += 1;
+                let result = self.exec_block(&body.body, &env);
+                self.depth -= 1;
+                self.line = line;
+                match result? {
+                    Flow::Return(values) => Ok(values),
+                    _ => Ok(Vec::new()),
+                }
+            }
+            other => Err(self.error(&format!("attempt to call a {} value", other.type_name()))),
+        }
+    }
+}
+
+/// ` (global 'x')`-style hint naming the variable behind a bad value
+fn describe(expr: &Expr, env: &Rc<Env>) -> String {
+    match expr {
+        Expr::Name(name) if env.find(name).is_some() => format!(" (local '{}')", name),
+        Expr::Name(name) => format!(" (global '{}')", name),
+        Expr::Index(_, key) => match key.as_ref() {
+            Expr::Str(field) => format!(" (field '{}')", field),
+            _ => String::new(),
+        },
+        Expr::Method(_, name, _) => format!(" (method '{}')", name),
+        _ => String::new(),
+    }
+}
+
+// ---- standard library ----
+
+const BUILTIN_NAMES: &[&str] = &[
+    "string.len",
+    "string.sub",
+    "string.upper",
+    "string.lower",
+    "string.rep",
+    "string.reverse",
+    "string.byte",
+    "string.char",
+    "string.format",
+    "string.find",
+    "string.gsub",
+    "table.insert",
+    "table.remove",
+    "table.concat",
+    "table.sort",
+    "table.unpack",
+    "math.floor",
+    "math.ceil",
+    "math.sqrt",
+    "math.abs",
+    "math.max",
+    "math.min",
+    "math.fmod",
+    "math.tointeger",
+    "math.type",
+    "math.random",
+    "math.randomseed",
+    "math.sin",
+    "math.cos",
+    "math.tan",
+    "math.exp",
+    "math.log",
+    "os.time",
+    "os.clock",
+    "os.exit",
+    "ipairs_next",
+];
+
+impl LuaInterpreter {
+    fn arg_error(&self, n: usize, func: &str, msg: &str) -> LuaError {
+        let func = func.rsplit('.').next().unwrap_or(func);
+        self.error(&format!("bad argument #{} to '{}' ({})", n, func, msg))
+    }
+
+    fn check_table(
+        &self,
+        args: &[LuaValue],
+        n: usize,
+        func: &str,
+    ) -> Result<Rc<RefCell<Table>>, LuaError> {
+        match args.get(n - 1) {
+            Some(LuaValue::Table(t)) => Ok(t.clone()),
+            other => Err(self.arg_error(
+                n,
+                func,
+                &format!(
+                    "table expected, got {}",
+                    other.map_or("no value", |v| v.type_name())
+                ),
+            )),
+        }
+    }
+
+    fn check_int(&self, args: &[LuaValue], n: usize, func: &str) -> Result<i64, LuaError> {
+        match args.get(n - 1) {
+            Some(v) => v.to_integer().ok_or_else(|| {
+                self.arg_error(
+                    n,
+                    func,
+                    &match v.to_number() {
+                        Some(_) => "number has no integer representation".to_string(),
+                        None => format!("number expected, got {}", v.type_name()),
+                    },
+                )
+            }),
+            None => Err(self.arg_error(n, func, "number expected, got no value")),
+        }
+    }
+
+    fn opt_int(
+        &self,
+        args: &[LuaValue],
+        n: usize,
+        func: &str,
+        default: i64,
+    ) -> Result<i64, LuaError> {
+        match args.get(n - 1) {
+            None | Some(LuaValue::Nil) => Ok(default),
+            Some(_) => self.check_int(args, n, func),
+        }
+    }
+
+    fn check_num(&self, args: &[LuaValue], n: usize, func: &str) -> Result<f64, LuaError> {
+        match args.get(n - 1).and_then(|v| v.to_number()) {
+            Some(v) => Ok(v.as_f64().unwrap_or(0.0)),
+            None => Err(self.arg_error(
+                n,
+                func,
+                &format!(
+                    "number expected, got {}",
+                    args.get(n - 1).map_or("no value", |v| v.type_name())
+                ),
+            )),
+        }
+    }
+
+    fn check_str(&self, args: &[LuaValue], n: usize, func: &str) -> Result<String, LuaError> {
+        match args.get(n - 1) {
+            Some(LuaValue::Str(s)) => Ok(s.clone()),
+            Some(v @ (LuaValue::Int(_) | LuaValue::Num(_))) => Ok(v.to_string()),
+            other => Err(self.arg_error(
+                n,
+                func,
+                &format!(
+                    "string expected, got {}",
+                    other.map_or("no value", |v| v.type_name())
+                ),
+            )),
+        }
+    }
+
+    /// splitmix64, seeded from the browser's `Math.random` until `math.randomseed`
+    fn next_random(&mut self) -> u64 {
+        let state = self
+            .rng
+            .get_or_insert_with(|| (js_sys::Math::random() * (1u64 << 53) as f64) as u64);
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn call_builtin(&mut self, name: &str, args: Vec<LuaValue>) -> Result<Vec<LuaValue>, LuaError> {
+        use LuaValue as V;
+        let one = |v: LuaValue| Ok(vec![v]);
+        let arg = |n: usize| args.get(n - 1).cloned().unwrap_or(V::Nil);
+        match name {
+            "print" => {
+                let parts: Vec<String> = args.iter().map(|v| v.to_string()).collect();
+                self.write(&parts.join("\t"));
+                self.write("\n");
+                Ok(Vec::new())
+            }
+            "type" => match args.first() {
+                Some(v) => one(V::str(v.type_name())),
+                None => Err(self.arg_error(1, name, "value expected")),
+            },
+            "tostring" => one(V::Str(arg(1).to_string())),
+            "tonumber" => match args.get(1) {
+                None | Some(V::Nil) => one(arg(1).to_number().unwrap_or(V::Nil)),
+                Some(_) => {
+                    let base = self.check_int(&args, 2, name)?;
+                    if !(2..=36).contains(&base) {
+                        return Err(self.arg_error(2, name, "base out of range"));
+                    }
+                    let text = self.check_str(&args, 1, name)?.trim().to_lowercase();
+                    let (neg, digits) = match text.strip_prefix('-') {
+                        Some(rest) => (true, rest.to_string()),
+                        None => (false, text),
+                    };
+                    one(match i64::from_str_radix(&digits, base as u32) {
+                        Ok(i) if neg => V::Int(-i),
+                        Ok(i) => V::Int(i),
+                        Err(_) => V::Nil,
+                    })
+                }
+            },
+            "ipairs" => {
+                self.check_table(&args, 1, name)?;
+                Ok(vec![V::Builtin("ipairs_next"), arg(1), V::Int(0)])
+            }
+            "ipairs_next" => {
+                let t = self.check_table(&args, 1, name)?;
+                let i = arg(2).to_integer().unwrap_or(0) + 1;
+                let value = t.borrow().get(&V::Int(i));
+                Ok(match value {
+                    V::Nil => vec![V::Nil],
+                    v => vec![V::Int(i), v],
+                })
+            }
+            "pairs" => {
+                self.check_table(&args, 1, name)?;
+                Ok(vec![V::Builtin("next"), arg(1), V::Nil])
+            }
+            "next" => {
+                let t = self.check_table(&args, 1, name)?;
+                let entry = t.borrow().next(&arg(2));
+                Ok(match entry {
+                    Some((k, v)) => vec![k, v],
+                    None => vec![V::Nil],
+                })
+            }
+            "select" => match args.first() {
+                Some(V::Str(s)) if s == "#" => one(V::Int(args.len() as i64 - 1)),
+                _ => {
+                    let n = self.check_int(&args, 1, name)?;
+                    let rest = args.len() as i64 - 1;
+                    let start = if n < 0 { rest + n } else { n - 1 };
+                    if n == 0 || start < 0 {
+                        return Err(self.arg_error(1, name, "index out of range"));
+                    }
+                    Ok(args.into_iter().skip(1 + start as usize).collect())
+                }
+            },
+            "error" => {
+                let value = arg(1);
+                let level = self.opt_int(&args, 2, name, 1)?;
+                Err(LuaError::value(match value {
+                    V::Str(s) if level > 0 => {
+                        V::Str(format!("{}:{}: {}", self.chunk, self.line, s))
+                    }
+                    v => v,
+                }))
+            }
+            "assert" => {
+                if arg(1).truthy() {
+                    return Ok(args);
+                }
+                match args.get(1) {
+                    Some(msg) => Err(LuaError::value(msg.clone())),
+                    None => Err(self.error("assertion failed!")),
+                }
+            }
+            "pcall" => {
+                let Some(func) = args.first().cloned() else {
+                    return Err(self.arg_error(1, name, "value expected"));
+                };
+                let depth = self.depth;
+                match self.call(&func, args[1..].to_vec()) {
+                    Ok(mut values) => {
+                        values.insert(0, V::Bool(true));
+                        Ok(values)
+                    }
+                    Err(e) if e.exit => Err(e),
+                    Err(e) => {
+                        self.depth = depth;
+                        Ok(vec![V::Bool(false), e.value])
+                    }
+                }
+            }
+            "rawequal" => one(V::Bool(raw_equal(&arg(1), &arg(2)))),
+            "rawget" => {
+                let t = self.check_table(&args, 1, name)?;
+                let value = t.borrow().get(&arg(2));
+                one(value)
+            }
+            "rawset" => {
+                let t = self.check_table(&args, 1, name)?;
+                t.borrow_mut().set(arg(2), arg(3));
+                one(arg(1))
+            }
+            "rawlen" => match arg(1) {
+                V::Table(t) => one(V::Int(t.borrow().len() as i64)),
+                V::Str(s) => one(V::Int(s.len() as i64)),
+                _ => Err(self.arg_error(1, name, "table or string expected")),
+            },
+            "unpack" | "table.unpack" => {
+                let t = self.check_table(&args, 1, name)?;
+                let len = t.borrow().len() as i64;
+                let from = self.opt_int(&args, 2, name, 1)?;
+                let to = self.opt_int(&args, 3, name, len)?;
+                if to - from >= 1_000_000 {
+                    return Err(self.error("too many results to unpack"));
+                }
+                let t = t.borrow();
+                Ok((from..=to).map(|i| t.get(&V::Int(i))).collect())
+            }
+            _ if name.starts_with("string.") => self.string_lib(name, &args),
+            _ if name.starts_with("table.") => self.table_lib(name, args),
+            _ if name.starts_with("math.") => self.math_lib(name, &args),
+            "os.time" => one(V::Int((js_sys::Date::now() / 1000.0) as i64)),
+            "os.clock" => one(V::Num(self.steps as f64 / 1_000_000.0)),
+            "os.exit" => Err(LuaError {
+                value: V::Nil,
+                incomplete: false,
+                exit: true,
+            }),
+            _ => Err(self.error(&format!("attempt to call a nil value (global '{}')", name))),
+        }
+    }
+
+    fn string_lib(&mut self, name: &str, args: &[LuaValue]) -> Result<Vec<LuaValue>, LuaError> {
+        use LuaValue as V;
+        let s = if name == "string.char" {
+            String::new()
+        } else {
+            self.check_str(args, 1, name)?
+        };
+        let len = s.len() as i64;
+        // Lua string positions are 1-based bytes; negatives count from the end
+        let position = |i: i64| -> i64 {
+            if i < 0 {
+                (len + i + 1).max(1)
+            } else {
+                i
+            }
+        };
+        Ok(vec![match name {
+            "string.len" => V::Int(len),
+            "string.upper" => V::Str(s.to_uppercase()),
+            "string.lower" => V::Str(s.to_lowercase()),
+            "string.reverse" => V::Str(s.chars().rev().collect()),
+            "string.sub" => {
+                let from = position(self.opt_int(args, 2, name, 1)?).max(1);
+                let to = position(self.opt_int(args, 3, name, -1)?).min(len);
+                if from > to {
+                    V::str("")
+                } else {
+                    V::Str(
+                        String::from_utf8_lossy(&s.as_bytes()[from as usize - 1..to as usize])
+                            .into_owned(),
+                    )
+                }
+            }
+            "string.rep" => {
+                let n = self.check_int(args, 2, name)?.max(0) as usize;
+                let sep = match args.get(2) {
+                    Some(V::Str(sep)) => sep.clone(),
+                    _ => String::new(),
+                };
+                if n.saturating_mul(s.len() + sep.len()) > 10_000_000 {
+                    return Err(self.error("resulting string too large"));
+                }
+                V::Str(vec![s; n].join(&sep))
+            }
+            "string.byte" => {
+                let from = position(self.opt_int(args, 2, name, 1)?);
+                let to = position(self.opt_int(args, 3, name, from)?).min(len);
+                return Ok((from.max(1)..=to)
+                    .map(|i| V::Int(s.as_bytes()[i as usize - 1] as i64))
+                    .collect());
+            }
+            "string.char" => {
+                let mut out = String::new();
+                for n in 1..=args.len() {
+                    let code = self.check_int(args, n, name)?;
+                    match u8::try_from(code) {
+                        Ok(b) => out.push(b as char),
+                        Err(_) => return Err(self.arg_error(n, name, "value out of range")),
+                    }
+                }
+                V::Str(out)
+            }
+            "string.find" => {
+                let pattern = self.check_str(args, 2, name)?;
+                let init = position(self.opt_int(args, 3, name, 1)?).max(1) as usize;
+                let needle = literal_pattern(&pattern);
+                match s.get(init - 1..).and_then(|rest| rest.find(&needle)) {
+                    Some(at) => {
+                        let start = (init + at) as i64;
+                        return Ok(vec![V::Int(start), V::Int(start + needle.len() as i64 - 1)]);
+                    }
+                    None => V::Nil,
+                }
+            }
+            "string.gsub" => {
+                let pattern = literal_pattern(&self.check_str(args, 2, name)?);
+                let replacement = self.check_str(args, 3, name)?;
+                if pattern.is_empty() {
+                    return Ok(vec![V::Str(s), V::Int(0)]);
+                }
+                let count = s.matches(&pattern).count() as i64;
+                return Ok(vec![
+                    V::Str(s.replace(&pattern, &replacement)),
+                    V::Int(count),
+                ]);
+            }
+            _ => V::Str(self.format(args)?),
+        }])
+    }
+
+    /// `string.format` for the common conversions: d i u c x X o e E f g G q s %
+    fn format(&self, args: &[LuaValue]) -> Result<String, LuaError> {
+        let name = "format";
+        let fmt = self.check_str(args, 1, name)?;
+        let mut out = String::new();
+        let mut chars = fmt.chars().peekable();
+        let mut n = 1;
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            if chars.peek() == Some(&'%') {
+                chars.next();
+                out.push('%');
+                continue;
+            }
+            let mut flags = String::new();
+            while let Some(&f) = chars.peek().filter(|f| "-+ #0".contains(**f)) {
+                flags.push(f);
+                chars.next();
+            }
+            let mut width = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                width.push(d);
+                chars.next();
+            }
+            let precision = if chars.peek() == Some(&'.') {
+                chars.next();
+                let mut p = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    p.push(d);
+                    chars.next();
+                }
+                Some(p.parse::<usize>().unwrap_or(0))
+            } else {
+                None
+            };
+            let Some(conv) = chars.next() else {
+                return Err(self.error("invalid conversion '%' to 'format'"));
+            };
+            n += 1;
+            let plus = flags.contains('+');
+            let body = match conv {
+                'd' | 'i' => {
+                    let v = self.check_int(args, n, name)?;
+                    let mut s = v.abs().to_string();
+                    if let Some(p) = precision {
+                        while s.len() < p {
+                            s.insert(0, '0');
+                        }
+                    }
+                    sign(v < 0, plus, s)
+                }
+                'u' => self.check_int(args, n, name)?.to_string(),
+                'c' => (self.check_int(args, n, name)? as u8 as char).to_string(),
+                'x' => format!("{:x}", self.check_int(args, n, name)?),
+                'X' => format!("{:X}", self.check_int(args, n, name)?),
+                'o' => format!("{:o}", self.check_int(args, n, name)?),
+                'f' | 'F' => {
+                    let v = self.check_num(args, n, name)?;
+                    sign(
+                        v.is_sign_negative() && v != 0.0,
+                        plus,
+                        format!("{:.*}", precision.unwrap_or(6), v.abs()),
+                    )
+                }
+                'e' | 'E' => {
+                    let v = self.check_num(args, n, name)?;
+                    let s = format!("{:.*e}", precision.unwrap_or(6), v.abs());
+                    let (m, e) = s.split_once('e').unwrap_or((&s, "0"));
+                    let e: i32 = e.parse().unwrap_or(0);
+                    let s = format!("{}e{}{:02}", m, if e < 0 { '-' } else { '+' }, e.abs());
+                    sign(
+                        v < 0.0,
+                        plus,
+                        if conv == 'E' { s.to_uppercase() } else { s },
+                    )
+                }
+                'g' | 'G' => {
+                    let v = self.check_num(args, n, name)?;
+                    sign(
+                        v < 0.0,
+                        plus,
+                        fmt_g(v.abs(), precision.unwrap_or(6), conv == 'G'),
+                    )
+                }
+                'q' => match args.get(n - 1) {
+                    Some(LuaValue::Str(s)) => format!("{:?}", s),
+                    Some(v) => v.to_string(),
+                    None => return Err(self.arg_error(n, name, "no value")),
+                },
+                's' => {
+                    let Some(v) = args.get(n - 1) else {
+                        return Err(self.arg_error(n, name, "no value"));
+                    };
+                    let s = v.to_string();
+                    match precision {
+                        Some(p) => s.chars().take(p).collect(),
+                        None => s,
+                    }
+                }
+                other => {
+                    return Err(self.error(&format!("invalid conversion '%{}' to 'format'", other)))
+                }
+            };
+            let width: usize = width.parse().unwrap_or(0);
+            let pad = width.saturating_sub(body.chars().count());
+            if flags.contains('-') {
+                out.push_str(&body);
+                out.push_str(&" ".repeat(pad));
+            } else if flags.contains('0') && "dioxXfFeEgG".contains(conv) {
+                let (sign, digits) = match body.strip_prefix(['-', '+']) {
+                    Some(rest) => (&body[..1], rest),
+                    None => ("", body.as_str()),
+                };
+                out.push_str(sign);
+                out.push_str(&"0".repeat(pad));
+                out.push_str(digits);
+            } else {
+                out.push_str(&" ".repeat(pad));
+                out.push_str(&body);
+            }
+        }
+        Ok(out)
+    }
+
+    fn table_lib(&mut self, name: &str, args: Vec<LuaValue>) -> Result<Vec<LuaValue>, LuaError> {
+        use LuaValue as V;
+        let t = self.check_table(&args, 1, name)?;
+        let len = t.borrow().len() as i64;
+        match name {
+            "table.insert" => {
+                let (pos, value) = match args.len() {
+                    2 => (len + 1, args[1].clone()),
+                    3 => {
+                        let pos = self.check_int(&args, 2, name)?;
+                        if pos < 1 || pos > len + 1 {
+                            return Err(self.arg_error(2, name, "position out of bounds"));
+                        }
+                        (pos, args[2].clone())
+                    }
+                    _ => return Err(self.error("wrong number of arguments to 'insert'")),
+                };
+                let mut t = t.borrow_mut();
+                for i in (pos..=len).rev() {
+                    let moved = t.get(&V::Int(i));
+                    t.set(V::Int(i + 1), moved);
+                }
+                t.set(V::Int(pos), value);
+                Ok(Vec::new())
+            }
+            "table.remove" => {
+                let pos = self.opt_int(&args, 2, name, len)?;
+                if len == 0 && args.len() < 2 {
+                    return Ok(vec![V::Nil]);
+                }
+                if pos < 1 || pos > len + 1 {
+                    return Err(self.arg_error(2, name, "position out of bounds"));
+                }
+                let mut t = t.borrow_mut();
+                let removed = t.get(&V::Int(pos));
+                for i in pos..len {
+                    let moved = t.get(&V::Int(i + 1));
+                    t.set(V::Int(i), moved);
+                }
+                if pos <= len {
+                    t.set(V::Int(len), V::Nil);
+                }
+                Ok(vec![removed])
+            }
+            "table.concat" => {
+                let sep = match args.get(1) {
+                    None | Some(V::Nil) => String::new(),
+                    Some(_) => self.check_str(&args, 2, name)?,
+                };
+                let from = self.opt_int(&args, 3, name, 1)?;
+                let to = self.opt_int(&args, 4, name, len)?;
+                let mut parts = Vec::new();
+                for i in from..=to {
+                    match t.borrow().get(&V::Int(i)) {
+                        v @ (V::Str(_) | V::Int(_) | V::Num(_)) => parts.push(v.to_string()),
+                        _ => {
+                            return Err(self.error(&format!(
+                                "invalid value (at index {}) in table for 'concat'",
+                                i
+                            )))
+                        }
+                    }
+                }
+                Ok(vec![V::Str(parts.join(&sep))])
+            }
+            _ => {
+                let comparator = args.get(1).cloned().filter(|c| !matches!(c, V::Nil));
+                let mut items: Vec<LuaValue> =
+                    (1..=len).map(|i| t.borrow().get(&V::Int(i))).collect();
+                // Insertion sort keeps errors from the comparator easy to surface
+                for i in 1..items.len() {
+                    let mut j = i;
+                    while j > 0 {
+                        let less = match &comparator {
+                            Some(f) => self
+                                .call(f, vec![items[j].clone(), items[j - 1].clone()])?
+                                .first()
+                                .is_some_and(|v| v.truthy()),
+                            None => {
+                                let empty = Expr::Nil;
+                                let env = Rc::new(Env {
+                                    vars: RefCell::new(HashMap::new()),
+                                    parent: None,
+                                    varargs: None,
+                                });
+                                self.binary(
+                                    "<",
+                                    items[j].clone(),
+                                    items[j - 1].clone(),
+                                    &empty,
+                                    &empty,
+                                    &env,
+                                )?
+                                .truthy()
+                            }
+                        };
+                        if !less {
+                            break;
+                        }
+                        items.swap(j, j - 1);
+                        j -= 1;
+                    }
+                }
+                let mut t = t.borrow_mut();
+                for (i, v) in items.into_iter().enumerate() {
+                    t.set(V::Int(i as i64 + 1), v);
+                }
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn math_lib(&mut self, name: &str, args: &[LuaValue]) -> Result<Vec<LuaValue>, LuaError> {
+        use LuaValue as V;
+        let num = |this: &Self| this.check_num(args, 1, name);
+        let to_int = |f: f64| {
+            if f.is_finite() && f.abs() < 9.2e18 {
+                V::Int(f as i64)
+            } else {
+                V::Num(f)
+            }
+        };
+        let value = match name {
+            "math.floor" | "math.ceil" => match args.first().and_then(|v| v.to_number()) {
+                Some(V::Int(i)) => V::Int(i),
+                _ => {
+                    let f = num(self)?;
+                    to_int(if name == "math.floor" {
+                        f.floor()
+                    } else {
+                        f.ceil()
+                    })
+                }
+            },
+            "math.abs" => match args.first().and_then(|v| v.to_number()) {
+                Some(V::Int(i)) => V::Int(i.wrapping_abs()),
+                _ => V::Num(num(self)?.abs()),
+            },
+            "math.sqrt" => V::Num(num(self)?.sqrt()),
+            "math.sin" => V::Num(num(self)?.sin()),
+            "math.cos" => V::Num(num(self)?.cos()),
+            "math.tan" => V::Num(num(self)?.tan()),
+            "math.exp" => V::Num(num(self)?.exp()),
+            "math.log" => {
+                let x = num(self)?;
+                match args.get(1) {
+                    Some(_) => V::Num(x.ln() / self.check_num(args, 2, name)?.ln()),
+                    None => V::Num(x.ln()),
+                }
+            }
+            "math.fmod" => {
+                let (a, b) = (num(self)?, self.check_num(args, 2, name)?);
+                match (args[0].to_number(), args[1].to_number()) {
+                    (Some(V::Int(x)), Some(V::Int(y))) => {
+                        if y == 0 {
+                            return Err(self.arg_error(2, name, "zero"));
+                        }
+                        V::Int(x.wrapping_rem(y))
+                    }
+                    _ => V::Num(a % b),
+                }
+            }
+            "math.max" | "math.min" => {
+                num(self)?;
+                let mut best = args[0].to_number().unwrap_or(V::Nil);
+                for n in 2..=args.len() {
+                    self.check_num(args, n, name)?;
+                    let v = args[n - 1].to_number().unwrap_or(V::Nil);
+                    let better = if name == "math.max" {
+                        v.as_f64() > best.as_f64()
+                    } else {
+                        v.as_f64() < best.as_f64()
+                    };
+                    if better {
+                        best = v;
+                    }
+                }
+                best
+            }
+            "math.tointeger" => args
+                .first()
+                .and_then(|v| match v {
+                    V::Str(_) => None,
+                    v => v.to_integer(),
+                })
+                .map_or(V::Nil, V::Int),
+            "math.type" => match args.first() {
+                Some(V::Int(_)) => V::str("integer"),
+                Some(V::Num(_)) => V::str("float"),
+                Some(_) => V::Nil,
+                None => return Err(self.arg_error(1, name, "value expected")),
+            },
+            "math.randomseed" => {
+                self.rng = args.first().map(|v| {
+                    v.to_integer()
+                        .unwrap_or_else(|| v.as_f64().map_or(0, f64::to_bits) as i64)
+                        as u64
+                });
+                return Ok(Vec::new());
+            }
+            _ => {
+                let (low, high) = match args.len() {
+                    0 => {
+                        let r = (self.next_random() >> 11) as f64 / (1u64 << 53) as f64;
+                        return Ok(vec![V::Num(r)]);
+                    }
+                    1 => (1, self.check_int(args, 1, name)?),
+                    _ => (
+                        self.check_int(args, 1, name)?,
+                        self.check_int(args, 2, name)?,
+                    ),
+                };
+                if low > high {
+                    return Err(self.arg_error(args.len(), name, "interval is empty"));
+                }
+                let span = (high as i128 - low as i128 + 1) as u128;
+                V::Int((low as i128 + (self.next_random() as u128 % span) as i128) as i64)
+            }
+        };
+        Ok(vec![value])
+    }
+}
+
+fn sign(negative: bool, plus: bool, digits: String) -> String {
+    if negative {
+        format!("-{}", digits)
+    } else if plus {
+        format!("+{}", digits)
+    } else {
+        digits
+    }
+}
+
+/// Lua patterns are matched as plain text; `%x` escapes stand for `x`
+fn literal_pattern(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            out.extend(chars.next());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LuaInterpreter;
+
+    fn run(src: &str) -> String {
+        LuaInterpreter::new()
+            .run_script(src, "test.lua")
+            .unwrap_or_else(|e| e)
+    }
+
+    #[test]
+    fn functions_closures_and_loops() {
+        let src = "\
+local function fib(n)
+  if n < 2 then return n end
+  return fib(n - 1) + fib(n - 2)
+end
+
+local function counter()
+  local n = 0
+  return function() n = n + 1; return n end
+end
+
+local c, total = counter(), 0
+for i = 1, 10 do
+  if i % 2 == 0 then total = total + i end
+end
+c(); c()
+print(fib(15), total, c(), 7 // 2, 7 / 2, 2 ^ 10, 10 // 0.0)
+";
+        assert_eq!(run(src), "610\t30\t3\t3\t3.5\t1024.0\tinf");
+    }
+
+    #[test]
+    fn integer_division_wraps_at_mininteger() {
+        let src = "\
+local m = math.mininteger
+print(m // -1 == m, m % -1, -7 // 2, 7 % -3, m // 1 == m)
+";
+        assert_eq!(run(src), "true\t0\t-4\t-2\ttrue");
+    }
+
+    #[test]
+    fn tables_and_standard_library() {
+        let src = "\
+local t = {3, 1, 2, name = 'pts'}
+table.insert(t, 5)
+table.sort(t, function(a, b) return a > b end)
+local keys = {}
+for k, v in pairs({x = 1, y = 2}) do keys[#keys + 1] = k .. '=' .. v end
+print(#t, table.concat(t, ','), t.name:upper(), table.concat(keys, ' '))
+print(string.format('%5.2f|%-3d|%x|%s', math.pi, 7, 255, nil), ('abc'):rep(2, '-'))
+print(select('#', 1, nil, 3), select(2, 'a', 'b', 'c'), string.find('hello', 'll'))
+";
+        assert_eq!(
+            run(src),
+            "4\t5,3,2,1\tPTS\tx=1 y=2\n 3.14|7  |ff|nil\tabc-abc\n3\tb\t3\t4"
+        );
+    }
+
+    #[test]
+    fn errors_carry_location_and_pcall_catches_them() {
+        let src = "\
+print(pcall(error, 'boom', 0))
+print(pcall(function() local t = nil; return t.x end))
+print(undefined_fn())
+";
+        assert_eq!(
+            run(src),
+            "false\tboom\n\
+             false\ttest.lua:2: attempt to index a nil value (local 't')\n\
+             lua: test.lua:3: attempt to call a nil value (global 'undefined_fn')"
+        );
+    }
+
+    #[test]
+    fn repl_echoes_values_and_waits_for_blocks() {
+        let mut lua = LuaInterpreter::new();
+        assert_eq!(lua.push_line("x = 40"), Some(Ok(String::new())));
+        assert_eq!(lua.push_line("x + 2, 'hi'"), Some(Ok("42\thi".into())));
+        assert_eq!(lua.push_line("function sq(n)"), None);
+        assert_eq!(lua.prompt(), ">> ");
+        assert_eq!(lua.push_line("  return n * n"), None);
+        assert_eq!(lua.push_line("end"), Some(Ok(String::new())));
+        assert_eq!(lua.push_line("sq(9)"), Some(Ok("81".into())));
+        assert_eq!(lua.prompt(), "> ");
+    }
+}
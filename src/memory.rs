@@ -16,6 +16,8 @@ pub struct Memory {
     pub total: u32,
     pub free: u32,
     blocks: BTreeMap<u32, MemoryBlock>,
+    // Addresses left damaged by OOM panics and doom runs; memtest finds them
+    faults: Vec<u32>,
 }
 
 impl Default for Memory {
@@ -41,6 +43,7 @@ impl Memory {
             total,
             free: total,
             blocks,
+            faults: Vec::new(),
         }
     }
 
@@ -110,46 +113,91 @@ impl Memory {
         false
     }
 
-    // Merge adjacent free blocks
+    // Merge adjacent free blocks, collapsing whole runs in a single pass
     fn coalesce(&mut self) {
-        let mut to_remove = Vec::new();
-        let mut to_add = Vec::new();
-
-        let offsets: Vec<u32> = self.blocks.keys().copied().collect();
-        for i in 0..offsets.len() {
-            if i + 1 >= offsets.len() {
-                break;
-            }
-
-            let curr_offset = offsets[i];
-            let next_offset = offsets[i + 1];
-
-            if let (Some(curr), Some(next)) =
-                (self.blocks.get(&curr_offset), self.blocks.get(&next_offset))
-            {
-                if curr.state == BlockState::Free
-                    && next.state == BlockState::Free
-                    && curr.offset + curr.size == next.offset
+        let old = std::mem::take(&mut self.blocks);
+        let mut merged: Option<MemoryBlock> = None;
+        for (_, block) in old {
+            match merged.as_mut() {
+                Some(prev)
+                    if prev.state == BlockState::Free
+                        && block.state == BlockState::Free
+                        && prev.offset + prev.size == block.offset =>
                 {
-                    to_remove.push(curr_offset);
-                    to_remove.push(next_offset);
-                    to_add.push(MemoryBlock {
-                        offset: curr.offset,
-                        size: curr.size + next.size,
-                        state: BlockState::Free,
-                    });
+                    prev.size += block.size;
+                }
+                _ => {
+                    if let Some(prev) = merged.take() {
+                        self.blocks.insert(prev.offset, prev);
+                    }
+                    merged = Some(block);
                 }
             }
         }
+        if let Some(prev) = merged {
+            self.blocks.insert(prev.offset, prev);
+        }
+    }
 
-        for offset in to_remove {
+    /// Change the amount of memory, as `mem=` does at boot. Shrinking only
+    /// works while the memory being cut off is free; returns whether it took.
+    pub fn resize(&mut self, new_total: u32) -> bool {
+        if new_total >= self.total {
+            let extra = new_total - self.total;
+            if extra > 0 {
+                self.blocks.insert(
+                    self.total,
+                    MemoryBlock {
+                        offset: self.total,
+                        size: extra,
+                        state: BlockState::Free,
+                    },
+                );
+                self.total = new_total;
+                self.free += extra;
+                self.coalesce();
+            }
+            return true;
+        }
+        let cut = self.total - new_total;
+        let Some(last) = self.blocks.values_mut().next_back() else {
+            return false;
+        };
+        if last.state != BlockState::Free || last.size < cut {
+            return false;
+        }
+        last.size -= cut;
+        if last.size == 0 {
+            let offset = last.offset;
             self.blocks.remove(&offset);
         }
-        for block in to_add {
-            self.blocks.insert(block.offset, block);
+        self.total = new_total;
+        self.free -= cut;
+        self.faults.retain(|&addr| addr < new_total);
+        true
+    }
+
+    /// Damage `count` more addresses, spread over memory by a fixed sequence
+    /// so the same history always gives the same faults
+    pub fn scribble(&mut self, count: usize) {
+        if self.total == 0 {
+            return;
+        }
+        for _ in 0..count {
+            let n = self.faults.len() as u32 + 1;
+            let addr = n.wrapping_mul(2_654_435_761).rotate_left(7) % self.total;
+            self.faults.push(addr);
         }
     }
 
+    pub fn faults(&self) -> &[u32] {
+        &self.faults
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &MemoryBlock> {
+        self.blocks.values()
+    }
+
     pub fn usage(&self) -> (u32, u32) {
         (self.total - self.free, self.total)
     }
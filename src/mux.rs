@@ -0,0 +1,532 @@
+//! The terminal multiplexer behind `tmux`: sessions hold windows, windows
+//! hold panes laid out by nested splits, and every pane keeps its own
+//! shell state and scrollback. The crate owns the layout; the frontend
+//! only draws what `view` describes.
+use crate::clock;
+use crate::process::ResourceLimits;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Lines of scrollback each pane keeps
+pub const SCROLLBACK_LIMIT: usize = 2000;
+/// Lines of each pane sent to the frontend per redraw
+const VIEW_LINES: usize = 200;
+
+/// The part of the shell each pane has its own copy of
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShellState {
+    pub cwd: String,
+    pub env: HashMap<String, String>,
+    pub limits: ResourceLimits,
+}
+
+pub struct Pane {
+    pub shell: ShellState,
+    scrollback: VecDeque<String>,
+}
+
+impl Pane {
+    fn new(shell: ShellState) -> Self {
+        Pane {
+            shell,
+            scrollback: VecDeque::new(),
+        }
+    }
+
+    /// Append output, the oldest lines falling off past the limit
+    pub fn push(&mut self, text: &str) {
+        self.scrollback.extend(text.split('\n').map(str::to_string));
+        while self.scrollback.len() > SCROLLBACK_LIMIT {
+            self.scrollback.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.scrollback.clear();
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.scrollback.iter().map(String::as_str)
+    }
+}
+
+/// `Row` panes sit side by side (`split-window -h`, `Ctrl-b %`), `Column`
+/// panes stack (`split-window -v`, `Ctrl-b "`)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Row,
+    Column,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Layout {
+    Pane {
+        id: u32,
+    },
+    Split {
+        direction: Direction,
+        children: Vec<Layout>,
+    },
+}
+
+impl Layout {
+    /// Pane ids in reading order
+    fn pane_ids(&self, out: &mut Vec<u32>) {
+        match self {
+            Layout::Pane { id } => out.push(*id),
+            Layout::Split { children, .. } => children.iter().for_each(|c| c.pane_ids(out)),
+        }
+    }
+
+    /// Put pane `new` after `target`, joining a split that already runs
+    /// the same way rather than nesting another
+    fn split(&mut self, target: u32, new: u32, direction: Direction) -> bool {
+        match self {
+            Layout::Pane { id } if *id == target => {
+                *self = Layout::Split {
+                    direction,
+                    children: vec![Layout::Pane { id: target }, Layout::Pane { id: new }],
+                };
+                true
+            }
+            Layout::Pane { .. } => false,
+            Layout::Split {
+                direction: d,
+                children,
+            } => {
+                let at = children
+                    .iter()
+                    .position(|c| *c == Layout::Pane { id: target });
+                match at {
+                    Some(i) if *d == direction => {
+                        children.insert(i + 1, Layout::Pane { id: new });
+                        true
+                    }
+                    _ => children.iter_mut().any(|c| c.split(target, new, direction)),
+                }
+            }
+        }
+    }
+
+    /// Take `target` out, collapsing splits left with one child; `None`
+    /// once nothing is left
+    fn remove(self, target: u32) -> Option<Layout> {
+        match self {
+            Layout::Pane { id } => (id != target).then_some(Layout::Pane { id }),
+            Layout::Split {
+                direction,
+                children,
+            } => {
+                let mut children: Vec<Layout> = children
+                    .into_iter()
+                    .filter_map(|c| c.remove(target))
+                    .collect();
+                match children.len() {
+                    0 => None,
+                    1 => children.pop(),
+                    _ => Some(Layout::Split {
+                        direction,
+                        children,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+struct Window {
+    name: String,
+    layout: Layout,
+    active: u32,
+}
+
+struct Session {
+    name: String,
+    windows: Vec<Window>,
+    active: usize,
+    created: i64,
+}
+
+impl Session {
+    fn window(&self) -> &Window {
+        &self.windows[self.active]
+    }
+
+    fn window_mut(&mut self) -> &mut Window {
+        &mut self.windows[self.active]
+    }
+}
+
+/// What the frontend draws while a session is attached
+#[derive(Serialize)]
+pub struct MuxView {
+    session: String,
+    windows: Vec<WindowTab>,
+    layout: Layout,
+    active_pane: u32,
+    panes: Vec<PaneView>,
+}
+
+#[derive(Serialize)]
+struct WindowTab {
+    index: usize,
+    name: String,
+    active: bool,
+}
+
+#[derive(Serialize)]
+struct PaneView {
+    id: u32,
+    lines: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Mux {
+    sessions: Vec<Session>,
+    panes: BTreeMap<u32, Pane>,
+    attached: Option<usize>,
+    next_pane: u32,
+    next_session: u32,
+}
+
+impl Mux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.attached.is_some()
+    }
+
+    pub fn has_sessions(&self) -> bool {
+        !self.sessions.is_empty()
+    }
+
+    fn current(&self) -> Option<&Session> {
+        self.sessions.get(self.attached?)
+    }
+
+    fn current_mut(&mut self) -> Result<&mut Session, String> {
+        let index = self.attached.ok_or("no current session")?;
+        Ok(&mut self.sessions[index])
+    }
+
+    pub fn session_name(&self) -> Option<&str> {
+        self.current().map(|s| s.name.as_str())
+    }
+
+    pub fn active_pane(&self) -> Option<u32> {
+        self.current().map(|s| s.window().active)
+    }
+
+    pub fn pane(&self, id: u32) -> Option<&Pane> {
+        self.panes.get(&id)
+    }
+
+    /// Every pane of every session
+    pub fn pane_ids(&self) -> Vec<u32> {
+        self.panes.keys().copied().collect()
+    }
+
+    pub fn pane_mut(&mut self, id: u32) -> Option<&mut Pane> {
+        self.panes.get_mut(&id)
+    }
+
+    fn add_pane(&mut self, shell: ShellState) -> u32 {
+        let id = self.next_pane;
+        self.next_pane += 1;
+        self.panes.insert(id, Pane::new(shell));
+        id
+    }
+
+    fn new_window_for(&mut self, shell: ShellState) -> Window {
+        let id = self.add_pane(shell);
+        Window {
+            name: "bash".into(),
+            layout: Layout::Pane { id },
+            active: id,
+        }
+    }
+
+    /// Start a detached session; returns its name
+    pub fn new_session(&mut self, name: Option<&str>, shell: ShellState) -> Result<String, String> {
+        let name = match name {
+            Some(name) if name.is_empty() || name.contains([':', '.']) => {
+                return Err(format!("invalid session: {}", name))
+            }
+            Some(name) => name.to_string(),
+            None => loop {
+                let candidate = self.next_session.to_string();
+                self.next_session += 1;
+                if !self.sessions.iter().any(|s| s.name == candidate) {
+                    break candidate;
+                }
+            },
+        };
+        if self.sessions.iter().any(|s| s.name == name) {
+            return Err(format!("duplicate session: {}", name));
+        }
+        let window = self.new_window_for(shell);
+        self.sessions.push(Session {
+            name: name.clone(),
+            windows: vec![window],
+            active: 0,
+            created: clock::now_secs(),
+        });
+        Ok(name)
+    }
+
+    fn find(&self, target: Option<&str>) -> Result<usize, String> {
+        match target {
+            None if self.sessions.is_empty() => Err("no sessions".into()),
+            None => Ok(self.sessions.len() - 1),
+            Some(name) => self
+                .sessions
+                .iter()
+                .position(|s| s.name == name)
+                .ok_or_else(|| format!("can't find session: {}", name)),
+        }
+    }
+
+    /// Attach to `target`, or the newest session
+    pub fn attach(&mut self, target: Option<&str>) -> Result<(), String> {
+        self.attached = Some(self.find(target)?);
+        Ok(())
+    }
+
+    /// Returns the name of the session left
+    pub fn detach(&mut self) -> Option<String> {
+        let name = self.session_name()?.to_string();
+        self.attached = None;
+        Some(name)
+    }
+
+    /// Open a window after the others and switch to it; returns its pane
+    pub fn new_window(&mut self, shell: ShellState) -> Result<u32, String> {
+        self.current_mut()?;
+        let window = self.new_window_for(shell);
+        let pane = window.active;
+        let session = self.current_mut()?;
+        session.windows.push(window);
+        session.active = session.windows.len() - 1;
+        Ok(pane)
+    }
+
+    /// Split the active pane and switch to the new one
+    pub fn split(&mut self, direction: Direction, shell: ShellState) -> Result<u32, String> {
+        self.current_mut()?;
+        let id = self.add_pane(shell);
+        let window = self.current_mut()?.window_mut();
+        window.layout.split(window.active, id, direction);
+        window.active = id;
+        Ok(id)
+    }
+
+    /// Move to the next pane of the window, wrapping around
+    pub fn next_pane(&mut self) -> Result<(), String> {
+        let window = self.current_mut()?.window_mut();
+        let mut ids = Vec::new();
+        window.layout.pane_ids(&mut ids);
+        let at = ids.iter().position(|&id| id == window.active).unwrap_or(0);
+        window.active = ids[(at + 1) % ids.len()];
+        Ok(())
+    }
+
+    pub fn select_window(&mut self, index: usize) -> Result<(), String> {
+        let session = self.current_mut()?;
+        if index >= session.windows.len() {
+            return Err(format!("can't find window: {}", index));
+        }
+        session.active = index;
+        Ok(())
+    }
+
+    /// Step `by` windows forward or back, wrapping around
+    pub fn cycle_window(&mut self, by: isize) -> Result<(), String> {
+        let session = self.current_mut()?;
+        let len = session.windows.len() as isize;
+        session.active = (session.active as isize + by).rem_euclid(len) as usize;
+        Ok(())
+    }
+
+    /// Close the active pane, then its window once empty, then the session
+    /// once it has no windows. Returns the session's name if it ended
+    pub fn kill_pane(&mut self) -> Result<Option<String>, String> {
+        let session = self.current_mut()?;
+        let window = session.window_mut();
+        let target = window.active;
+        let mut ids = Vec::new();
+        window.layout.pane_ids(&mut ids);
+        let at = ids.iter().position(|&id| id == target).unwrap_or(0);
+        match window.layout.clone().remove(target) {
+            Some(layout) => {
+                window.layout = layout;
+                ids.remove(at);
+                window.active = ids[at.saturating_sub(1).min(ids.len() - 1)];
+            }
+            None => {
+                let index = session.active;
+                session.windows.remove(index);
+                session.active = index.min(session.windows.len().saturating_sub(1));
+            }
+        }
+        self.panes.remove(&target);
+        if self.current_mut()?.windows.is_empty() {
+            let index = self.attached.unwrap_or(0);
+            let name = self.sessions.remove(index).name;
+            self.attached = None;
+            return Ok(Some(name));
+        }
+        Ok(None)
+    }
+
+    /// End `target`, or the current session; returns its name
+    pub fn kill_session(&mut self, target: Option<&str>) -> Result<String, String> {
+        let index = match (target, self.attached) {
+            (None, Some(index)) => index,
+            _ => self.find(target)?,
+        };
+        let session = self.sessions.remove(index);
+        for window in &session.windows {
+            let mut ids = Vec::new();
+            window.layout.pane_ids(&mut ids);
+            for id in ids {
+                self.panes.remove(&id);
+            }
+        }
+        self.attached = match self.attached {
+            Some(a) if a == index => None,
+            Some(a) if a > index => Some(a - 1),
+            other => other,
+        };
+        Ok(session.name)
+    }
+
+    /// End every session; returns whether one was attached
+    pub fn kill_server(&mut self) -> bool {
+        let attached = self.is_attached();
+        *self = Mux::default();
+        attached
+    }
+
+    /// One `tmux ls` line per session
+    pub fn list(&self) -> Vec<String> {
+        self.sessions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                format!(
+                    "{}: {} windows (created {}){}",
+                    s.name,
+                    s.windows.len(),
+                    clock::ctime(s.created),
+                    if self.attached == Some(i) {
+                        " (attached)"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect()
+    }
+
+    /// The attached session's window tabs, layout and pane contents
+    pub fn view(&self) -> Option<MuxView> {
+        let session = self.current()?;
+        let window = session.window();
+        let mut ids = Vec::new();
+        window.layout.pane_ids(&mut ids);
+        let panes = ids
+            .iter()
+            .filter_map(|id| {
+                let pane = self.panes.get(id)?;
+                let skip = pane.scrollback.len().saturating_sub(VIEW_LINES);
+                Some(PaneView {
+                    id: *id,
+                    lines: pane.lines().skip(skip).map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        Some(MuxView {
+            session: session.name.clone(),
+            windows: session
+                .windows
+                .iter()
+                .enumerate()
+                .map(|(index, w)| WindowTab {
+                    index,
+                    name: w.name.clone(),
+                    active: index == session.active,
+                })
+                .collect(),
+            layout: window.layout.clone(),
+            active_pane: window.active,
+            panes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_nest_and_collapse() {
+        let mut mux = Mux::new();
+        let shell = ShellState::default();
+        assert_eq!(mux.attach(None), Err("no sessions".into()));
+        assert_eq!(mux.new_session(None, shell.clone()).unwrap(), "0");
+        assert_eq!(
+            mux.new_session(Some("0"), shell.clone()),
+            Err("duplicate session: 0".into())
+        );
+        mux.attach(Some("0")).unwrap();
+        assert_eq!(mux.active_pane(), Some(0));
+
+        mux.split(Direction::Row, shell.clone()).unwrap();
+        mux.split(Direction::Row, shell.clone()).unwrap();
+        mux.split(Direction::Column, shell.clone()).unwrap();
+        let view = mux.view().unwrap();
+        assert_eq!(
+            view.layout,
+            Layout::Split {
+                direction: Direction::Row,
+                children: vec![
+                    Layout::Pane { id: 0 },
+                    Layout::Pane { id: 1 },
+                    Layout::Split {
+                        direction: Direction::Column,
+                        children: vec![Layout::Pane { id: 2 }, Layout::Pane { id: 3 }],
+                    },
+                ],
+            }
+        );
+        assert_eq!(view.active_pane, 3);
+
+        mux.kill_pane().unwrap();
+        assert_eq!(mux.active_pane(), Some(2));
+        mux.next_pane().unwrap();
+        assert_eq!(mux.active_pane(), Some(0));
+        let pane = mux.pane_mut(0).unwrap();
+        for i in 0..SCROLLBACK_LIMIT + 5 {
+            pane.push(&i.to_string());
+        }
+        assert_eq!(pane.lines().next(), Some("5"));
+        assert_eq!(mux.view().unwrap().panes[0].lines.len(), VIEW_LINES);
+
+        mux.new_window(shell.clone()).unwrap();
+        assert!(mux.list()[0].starts_with("0: 2 windows (created "));
+        assert!(mux.list()[0].ends_with(" (attached)"));
+        mux.cycle_window(1).unwrap();
+        assert_eq!(mux.active_pane(), Some(0));
+        for _ in 0..3 {
+            assert_eq!(mux.kill_pane(), Ok(None));
+        }
+        assert_eq!(mux.kill_pane(), Ok(Some("0".into())));
+        assert!(!mux.is_attached());
+        assert!(!mux.has_sessions());
+        assert!(mux.panes.is_empty());
+    }
+}
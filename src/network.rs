@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
@@ -26,8 +26,9 @@ pub enum SocketState {
 
 #[derive(Debug, Clone)]
 pub struct NetworkInterface {
-    pub name: String,
-    pub ipv4: String,
+    pub name: &'static str,
+    /// IPv4 addresses with their prefix length; the first one is primary
+    pub ipv4: Vec<(String, u8)>,
     pub ipv6: String,
     pub mac: String,
     pub mtu: u32,
@@ -66,6 +67,10 @@ pub struct Socket {
     pub remote_port: u16,
     pub url: Option<String>,
     pub ws: Option<WebSocket>,
+    /// Process holding the socket
+    pub pid: Option<u32>,
+    /// Event handlers installed on `ws`, kept alive as long as the socket
+    handlers: Vec<Closure<dyn FnMut(JsValue)>>,
 }
 
 impl Socket {
@@ -80,6 +85,8 @@ impl Socket {
             remote_port: 0,
             url: None,
             ws: None,
+            pid: None,
+            handlers: Vec::new(),
         }
     }
 
@@ -104,6 +111,53 @@ impl Socket {
         }
     }
 
+    /// Forward WebSocket events to `listener` as `(socket_id, kind, data)`
+    /// where `kind` is `open`, `message`, `error` or `close`; a close
+    /// carries `code<TAB>reason` as its data
+    fn forward_events(&mut self, listener: js_sys::Function) {
+        let Some(ws) = &self.ws else {
+            return;
+        };
+        let id = JsValue::from(self.id);
+        let handler = |kind: &'static str, data: fn(JsValue) -> String| {
+            let listener = listener.clone();
+            let id = id.clone();
+            Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+                let _ = listener.call3(
+                    &JsValue::NULL,
+                    &id,
+                    &JsValue::from_str(kind),
+                    &JsValue::from_str(&data(event)),
+                );
+            })
+        };
+        let open = handler("open", |_| String::new());
+        let message = handler("message", |event| {
+            let data = event
+                .dyn_into::<web_sys::MessageEvent>()
+                .map(|e| e.data())
+                .unwrap_or(JsValue::UNDEFINED);
+            match data.as_string() {
+                Some(text) => text,
+                None => {
+                    String::from_utf8_lossy(&js_sys::Uint8Array::new(&data).to_vec()).into_owned()
+                }
+            }
+        });
+        let error = handler("error", |_| String::new());
+        let close = handler("close", |event| {
+            match event.dyn_into::<web_sys::CloseEvent>() {
+                Ok(e) => format!("{}\t{}", e.code(), e.reason()),
+                Err(_) => "1006\t".to_string(),
+            }
+        });
+        ws.set_onopen(Some(open.as_ref().unchecked_ref()));
+        ws.set_onmessage(Some(message.as_ref().unchecked_ref()));
+        ws.set_onerror(Some(error.as_ref().unchecked_ref()));
+        ws.set_onclose(Some(close.as_ref().unchecked_ref()));
+        self.handlers = vec![open, message, error, close];
+    }
+
     pub fn send(&self, data: &str) -> Result<(), String> {
         if let Some(ws) = &self.ws {
             ws.send_with_str(data)
@@ -114,8 +168,13 @@ impl Socket {
     }
 
     pub fn close(&mut self) -> Result<(), String> {
+        self.close_with(1000, "")
+    }
+
+    /// Close with a status `code` (1000 or 3000-4999) and `reason`
+    pub fn close_with(&mut self, code: u16, reason: &str) -> Result<(), String> {
         if let Some(ws) = &self.ws {
-            ws.close()
+            ws.close_with_code_and_reason(code, reason)
                 .map_err(|e| format!("Failed to close: {:?}", e))?;
             self.state = SocketState::Closing;
         }
@@ -123,7 +182,8 @@ impl Socket {
     }
 }
 
-fn parse_remote_endpoint(url: &str) -> (String, u16) {
+/// Host and port a URL points at, defaulting the port from the scheme
+pub fn parse_remote_endpoint(url: &str) -> (String, u16) {
     let lower = url.to_lowercase();
     let default_port = if lower.starts_with("wss://") { 443 } else { 80 };
     let without_scheme = if let Some(pos) = url.find("://") {
@@ -142,9 +202,167 @@ fn parse_remote_endpoint(url: &str) -> (String, u16) {
     (host_port.to_string(), default_port)
 }
 
+/// Address this machine uses on `eth0`
+pub const HOST_IPV4: &str = "10.0.2.15";
+
+/// The user-mode NAT gateway on `eth0`'s default network
+pub const GATEWAY_IPV4: &str = "10.0.2.2";
+
+pub fn parse_ipv4(text: &str) -> Option<[u8; 4]> {
+    let parts: Vec<u8> = text
+        .split('.')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    parts.try_into().ok()
+}
+
+/// The IPv4 address a recorded host stands for. Bridged traffic only
+/// knows host names, so those get a stable made-up public address.
+pub fn ipv4_for(host: &str) -> [u8; 4] {
+    match host {
+        "localhost" => return [127, 0, 0, 1],
+        "kpawnd" => return parse_ipv4(HOST_IPV4).unwrap_or([10, 0, 2, 15]),
+        _ => {}
+    }
+    if let Some(ip) = parse_ipv4(host) {
+        return ip;
+    }
+    let [a, b, c, d] = name_hash(host).to_be_bytes();
+    [104 + a % 64, b, c, d.max(1)]
+}
+
+/// FNV-1a, for stable made-up details derived from a name
+pub fn name_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Whether `addr` falls inside `net/prefix`
+fn in_subnet(addr: &str, net: &str, prefix: u8) -> bool {
+    match (parse_ipv4(addr), parse_ipv4(net)) {
+        (Some(a), Some(n)) => {
+            let mask = prefix_mask(prefix);
+            u32::from_be_bytes(a) & mask == u32::from_be_bytes(n) & mask
+        }
+        _ => false,
+    }
+}
+
+pub fn prefix_mask(prefix: u8) -> u32 {
+    match prefix {
+        0 => 0,
+        p => u32::MAX << (32 - u32::from(p.min(32))),
+    }
+}
+
+pub fn format_ipv4(addr: u32) -> String {
+    let [a, b, c, d] = addr.to_be_bytes();
+    format!("{}.{}.{}.{}", a, b, c, d)
+}
+
+/// `addr` with its host bits cleared
+pub fn network_address(addr: &str, prefix: u8) -> String {
+    let ip = parse_ipv4(addr).map_or(0, u32::from_be_bytes);
+    format_ipv4(ip & prefix_mask(prefix))
+}
+
+/// `addr` with its host bits set
+pub fn broadcast_address(addr: &str, prefix: u8) -> String {
+    let ip = parse_ipv4(addr).map_or(0, u32::from_be_bytes);
+    format_ipv4(ip | !prefix_mask(prefix))
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    matches!(host, "localhost" | "0.0.0.0" | "::1") || host.starts_with("127.")
+}
+
+/// Packets kept for `tcpdump`; older ones are dropped first
+const PACKET_BUFFER: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PacketKind {
+    /// TCP segment with tcpdump-style flags (`S`, `S.`, `.`, `P.`, `F.`)
+    /// and sequence numbers relative to the start of the connection
+    Tcp {
+        flags: &'static str,
+        seq: u32,
+        ack: u32,
+    },
+    Udp,
+    IcmpEcho {
+        reply: bool,
+        id: u16,
+        seq: u16,
+    },
+}
+
+/// One simulated or bridged packet as seen on an interface
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub time_ms: f64,
+    pub iface: &'static str,
+    pub src: String,
+    pub src_port: u16,
+    pub dst: String,
+    pub dst_port: u16,
+    pub kind: PacketKind,
+    /// Payload bytes after the transport header
+    pub length: usize,
+    /// Sent by this machine rather than received
+    pub outbound: bool,
+}
+
+impl Packet {
+    pub fn protocol(&self) -> Protocol {
+        match self.kind {
+            PacketKind::Tcp { .. } => Protocol::Tcp,
+            PacketKind::Udp => Protocol::Udp,
+            PacketKind::IcmpEcho { .. } => Protocol::Icmp,
+        }
+    }
+
+    /// Bytes on the wire including Ethernet, IPv4 and transport headers
+    pub fn wire_len(&self) -> u64 {
+        let transport = match self.kind {
+            PacketKind::Tcp { .. } => 20,
+            PacketKind::Udp | PacketKind::IcmpEcho { .. } => 8,
+        };
+        (14 + 20 + transport + self.length) as u64
+    }
+}
+
+fn interface(
+    name: &'static str,
+    ipv4: &str,
+    prefix: u8,
+    ipv6: &str,
+    mac: &str,
+) -> NetworkInterface {
+    let loopback = name == "lo";
+    NetworkInterface {
+        name,
+        ipv4: vec![(ipv4.to_string(), prefix)],
+        ipv6: ipv6.to_string(),
+        mac: mac.to_string(),
+        mtu: if loopback { 65536 } else { 1500 },
+        rx_bytes: 0,
+        tx_bytes: 0,
+        rx_packets: 0,
+        tx_packets: 0,
+        is_up: true,
+        is_loopback: loopback,
+    }
+}
+
 pub struct NetworkStack {
     sockets: HashMap<u32, Socket>,
     next_socket_id: u32,
+    packets: VecDeque<Packet>,
+    next_ephemeral_port: u16,
+    interfaces: Vec<NetworkInterface>,
+    /// Frontend callback that receives WebSocket events
+    listener: Option<js_sys::Function>,
 }
 
 impl Default for NetworkStack {
@@ -158,9 +376,270 @@ impl NetworkStack {
         NetworkStack {
             sockets: HashMap::new(),
             next_socket_id: 1,
+            packets: VecDeque::new(),
+            next_ephemeral_port: 40000,
+            interfaces: vec![
+                interface("lo", "127.0.0.1", 8, "::1", "00:00:00:00:00:00"),
+                interface(
+                    "eth0",
+                    HOST_IPV4,
+                    24,
+                    "fe80::5054:ff:fe12:3456",
+                    "52:54:00:12:34:56",
+                ),
+            ],
+            listener: None,
+        }
+    }
+
+    /// Put a packet on the capture bus read by `tcpdump`. Packets on a
+    /// downed interface never make it onto the wire.
+    pub fn record(&mut self, packet: Packet) {
+        let Some(iface) = self.interfaces.iter_mut().find(|i| i.name == packet.iface) else {
+            return;
+        };
+        if !iface.is_up {
+            return;
+        }
+        // Loopback traffic is both sent and received by this machine
+        if packet.outbound || iface.is_loopback {
+            iface.tx_packets += 1;
+            iface.tx_bytes += packet.wire_len();
+        }
+        if !packet.outbound || iface.is_loopback {
+            iface.rx_packets += 1;
+            iface.rx_bytes += packet.wire_len();
+        }
+        if self.packets.len() == PACKET_BUFFER {
+            self.packets.pop_front();
+        }
+        self.packets.push_back(packet);
+    }
+
+    pub fn packets(&self) -> impl Iterator<Item = &Packet> {
+        self.packets.iter()
+    }
+
+    /// The interface and source address used to reach `remote`, or why
+    /// it can't be reached
+    pub fn route_for(&self, remote: &str) -> Result<(&'static str, String), &'static str> {
+        let name = if is_loopback_host(remote) {
+            "lo"
+        } else {
+            "eth0"
+        };
+        let iface = self
+            .interface(name)
+            .filter(|i| i.is_up)
+            .ok_or("Network is unreachable")?;
+        let (local, _) = iface.ipv4.first().ok_or("Network is unreachable")?;
+        if iface.is_loopback {
+            return Ok((name, local.clone()));
+        }
+        if self.on_link(remote) || self.default_gateway().is_some() {
+            Ok((name, local.clone()))
+        } else {
+            Err("Network is unreachable")
+        }
+    }
+
+    /// `eth0`'s default gateway, present while the link is up with an
+    /// address on the gateway's network
+    pub fn default_gateway(&self) -> Option<&'static str> {
+        let eth0 = self.interface("eth0").filter(|i| i.is_up)?;
+        eth0.ipv4
+            .iter()
+            .any(|(net, prefix)| in_subnet(GATEWAY_IPV4, net, *prefix))
+            .then_some(GATEWAY_IPV4)
+    }
+
+    /// Whether `addr` is on one of `eth0`'s networks, reachable without
+    /// going through the gateway
+    pub fn on_link(&self, addr: &str) -> bool {
+        self.interface("eth0").is_some_and(|eth0| {
+            eth0.is_up
+                && eth0
+                    .ipv4
+                    .iter()
+                    .any(|(net, prefix)| in_subnet(addr, net, *prefix))
+        })
+    }
+
+    pub fn interface(&self, name: &str) -> Option<&NetworkInterface> {
+        self.interfaces.iter().find(|i| i.name == name)
+    }
+
+    fn interface_mut(&mut self, name: &str) -> Result<&mut NetworkInterface, String> {
+        self.interfaces
+            .iter_mut()
+            .find(|i| i.name == name)
+            .ok_or_else(|| format!("Cannot find device \"{}\"", name))
+    }
+
+    /// Bring a link up or down. Taking it down resets its counters, like
+    /// reloading the driver would.
+    pub fn set_link(&mut self, name: &str, up: bool) -> Result<(), String> {
+        let iface = self.interface_mut(name)?;
+        if iface.is_up && !up {
+            iface.rx_bytes = 0;
+            iface.tx_bytes = 0;
+            iface.rx_packets = 0;
+            iface.tx_packets = 0;
+        }
+        iface.is_up = up;
+        Ok(())
+    }
+
+    pub fn set_mtu(&mut self, name: &str, mtu: u32) -> Result<(), String> {
+        if !(68..=65536).contains(&mtu) {
+            return Err("Invalid argument".to_string());
+        }
+        self.interface_mut(name)?.mtu = mtu;
+        Ok(())
+    }
+
+    /// Add `addr/prefix` to an interface, as a secondary address if it
+    /// already has one
+    pub fn add_address(&mut self, name: &str, addr: &str, prefix: u8) -> Result<(), String> {
+        if parse_ipv4(addr).is_none() || prefix > 32 {
+            return Err(format!(
+                "Error: any valid prefix is expected rather than \"{}/{}\".",
+                addr, prefix
+            ));
+        }
+        let iface = self.interface_mut(name)?;
+        if iface.ipv4.iter().any(|(a, _)| a == addr) {
+            return Err("RTNETLINK answers: File exists".to_string());
+        }
+        iface.ipv4.push((addr.to_string(), prefix));
+        Ok(())
+    }
+
+    pub fn del_address(&mut self, name: &str, addr: &str) -> Result<(), String> {
+        let iface = self.interface_mut(name)?;
+        let before = iface.ipv4.len();
+        iface.ipv4.retain(|(a, _)| a != addr);
+        if iface.ipv4.len() == before {
+            return Err("RTNETLINK answers: Cannot assign requested address".to_string());
+        }
+        Ok(())
+    }
+
+    /// Replace every address on an interface with `addr/prefix`, or just
+    /// remove them all
+    pub fn set_address(&mut self, name: &str, addr: Option<(&str, u8)>) -> Result<(), String> {
+        let iface = self.interface_mut(name)?;
+        iface.ipv4 = addr.map(|(a, p)| (a.to_string(), p)).into_iter().collect();
+        Ok(())
+    }
+
+    fn ephemeral_port(&mut self) -> u16 {
+        let port = self.next_ephemeral_port;
+        self.next_ephemeral_port = if port >= 60999 { 40000 } else { port + 1 };
+        port
+    }
+
+    /// Record a whole TCP conversation with `remote:port`: handshake, one
+    /// request and response, and teardown
+    pub fn record_tcp_exchange(
+        &mut self,
+        time_ms: f64,
+        remote: &str,
+        port: u16,
+        sent: usize,
+        received: usize,
+    ) {
+        let Ok((iface, local)) = self.route_for(remote) else {
+            return;
+        };
+        let local_port = self.ephemeral_port();
+        let (sent, received) = (sent as u32, received as u32);
+        // (outbound, flags, seq, ack, payload)
+        let segments = [
+            (true, "S", 0, 0, 0),
+            (false, "S.", 0, 1, 0),
+            (true, ".", 1, 1, 0),
+            (true, "P.", 1, 1, sent),
+            (false, ".", 1, sent + 1, 0),
+            (false, "P.", 1, sent + 1, received),
+            (true, ".", sent + 1, received + 1, 0),
+            (true, "F.", sent + 1, received + 1, 0),
+            (false, "F.", received + 1, sent + 2, 0),
+            (true, ".", sent + 2, received + 2, 0),
+        ];
+        for (i, (outbound, flags, seq, ack, payload)) in segments.into_iter().enumerate() {
+            let (src, src_port, dst, dst_port) = if outbound {
+                (local.as_str(), local_port, remote, port)
+            } else {
+                (remote, port, local.as_str(), local_port)
+            };
+            self.record(Packet {
+                time_ms: time_ms + i as f64 * 0.05,
+                iface,
+                src: src.to_string(),
+                src_port,
+                dst: dst.to_string(),
+                dst_port,
+                kind: PacketKind::Tcp { flags, seq, ack },
+                length: payload as usize,
+                outbound,
+            });
         }
     }
 
+    /// Record an ICMP echo request and, if `rtt_ms` is set, its reply
+    pub fn record_ping(&mut self, time_ms: f64, remote: &str, seq: u16, rtt_ms: Option<f64>) {
+        let Ok((iface, local)) = self.route_for(remote) else {
+            return;
+        };
+        let mut packet = Packet {
+            time_ms,
+            iface,
+            src: local,
+            src_port: 0,
+            dst: remote.to_string(),
+            dst_port: 0,
+            kind: PacketKind::IcmpEcho {
+                reply: false,
+                id: 1,
+                seq,
+            },
+            length: 64,
+            outbound: true,
+        };
+        self.record(packet.clone());
+        if let Some(rtt) = rtt_ms {
+            packet.time_ms += rtt;
+            std::mem::swap(&mut packet.src, &mut packet.dst);
+            packet.outbound = false;
+            packet.kind = PacketKind::IcmpEcho {
+                reply: true,
+                id: 1,
+                seq,
+            };
+            self.record(packet);
+        }
+    }
+
+    /// Record a UDP datagram from this machine to `remote:port`
+    pub fn record_udp(&mut self, time_ms: f64, remote: &str, port: u16, length: usize) {
+        let Ok((iface, local)) = self.route_for(remote) else {
+            return;
+        };
+        let src_port = self.ephemeral_port();
+        self.record(Packet {
+            time_ms,
+            iface,
+            src: local,
+            src_port,
+            dst: remote.to_string(),
+            dst_port: port,
+            kind: PacketKind::Udp,
+            length,
+            outbound: true,
+        });
+    }
+
     pub fn socket(&mut self, protocol: Protocol) -> u32 {
         let id = self.next_socket_id;
         self.next_socket_id += 1;
@@ -169,33 +648,181 @@ impl NetworkStack {
     }
 
     pub fn connect_ws(&mut self, socket_id: u32, url: &str) -> Result<(), String> {
-        if let Some(socket) = self.sockets.get_mut(&socket_id) {
-            socket.connect_ws(url)
-        } else {
-            Err("Invalid socket ID".to_string())
+        let (remote, _) = parse_remote_endpoint(url);
+        self.route_for(&remote)?;
+        let port = self.ephemeral_port();
+        let socket = self
+            .sockets
+            .get_mut(&socket_id)
+            .ok_or_else(|| "Invalid socket ID".to_string())?;
+        socket.connect_ws(url)?;
+        socket.local_port = port;
+        if let Some(listener) = &self.listener {
+            socket.forward_events(listener.clone());
         }
+        let now = js_sys::Date::now();
+        for (i, (outbound, flags, ack)) in [(true, "S", 0), (false, "S.", 1), (true, ".", 1)]
+            .into_iter()
+            .enumerate()
+        {
+            self.record_socket_segment(socket_id, now + i as f64 * 0.05, outbound, flags, ack, 0);
+        }
+        Ok(())
     }
 
-    pub fn send(&self, socket_id: u32, data: &str) -> Result<(), String> {
+    pub fn send(&mut self, socket_id: u32, data: &str) -> Result<(), String> {
         if let Some(socket) = self.sockets.get(&socket_id) {
-            socket.send(data)
+            socket.send(data)?;
+            self.record_socket_segment(socket_id, js_sys::Date::now(), true, "P.", 1, data.len());
+            Ok(())
         } else {
             Err("Invalid socket ID".to_string())
         }
     }
 
+    fn record_socket_segment(
+        &mut self,
+        socket_id: u32,
+        time_ms: f64,
+        outbound: bool,
+        flags: &'static str,
+        ack: u32,
+        length: usize,
+    ) {
+        let Some(socket) = self.sockets.get(&socket_id) else {
+            return;
+        };
+        let Ok((iface, local)) = self.route_for(&socket.remote_addr) else {
+            return;
+        };
+        let local = (local, socket.local_port);
+        let remote = (socket.remote_addr.clone(), socket.remote_port);
+        let ((src, src_port), (dst, dst_port)) = if outbound {
+            (local, remote)
+        } else {
+            (remote, local)
+        };
+        self.record(Packet {
+            time_ms,
+            iface,
+            src,
+            src_port,
+            dst,
+            dst_port,
+            kind: PacketKind::Tcp {
+                flags,
+                seq: u32::from(flags != "S" && flags != "S."),
+                ack,
+            },
+            length,
+            outbound,
+        });
+    }
+
     pub fn close(&mut self, socket_id: u32) -> Result<(), String> {
+        self.close_with(socket_id, 1000, "")
+    }
+
+    pub fn close_with(&mut self, socket_id: u32, code: u16, reason: &str) -> Result<(), String> {
         if let Some(socket) = self.sockets.get_mut(&socket_id) {
-            socket.close()?;
+            socket.close_with(code, reason)?;
             Ok(())
         } else {
             Err("Invalid socket ID".to_string())
         }
     }
 
+    /// Set the callback WebSocket events are forwarded to; sockets
+    /// connected from then on report to it
+    pub fn set_listener(&mut self, listener: js_sys::Function) {
+        self.listener = Some(listener);
+    }
+
+    pub fn socket_state(&self, socket_id: u32) -> Option<SocketState> {
+        self.sockets.get(&socket_id).map(|s| s.state)
+    }
+
+    /// Apply a WebSocket event reported by the frontend to the socket's
+    /// state and the capture bus
+    pub fn socket_event(&mut self, socket_id: u32, time_ms: f64, kind: &str, length: usize) {
+        let Some(socket) = self.sockets.get_mut(&socket_id) else {
+            return;
+        };
+        match kind {
+            "open" => socket.state = SocketState::Established,
+            "message" => self.record_socket_segment(socket_id, time_ms, false, "P.", 1, length),
+            "close" | "error" => {
+                let was_open = socket.state == SocketState::Established;
+                socket.state = SocketState::Closed;
+                socket.ws = None;
+                if was_open {
+                    self.record_socket_segment(socket_id, time_ms, false, "F.", 1, 0);
+                    self.record_socket_segment(socket_id, time_ms + 0.05, true, "F.", 1, 0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Open a TCP socket listening on `port` on all addresses
+    pub fn listen(&mut self, port: u16) -> Result<u32, String> {
+        if self.is_listening(port) {
+            return Err("Address already in use".to_string());
+        }
+        let id = self.socket(Protocol::Tcp);
+        if let Some(socket) = self.sockets.get_mut(&id) {
+            socket.state = SocketState::Listen;
+            socket.local_port = port;
+        }
+        Ok(id)
+    }
+
+    pub fn is_listening(&self, port: u16) -> bool {
+        self.sockets
+            .values()
+            .any(|s| s.state == SocketState::Listen && s.local_port == port)
+    }
+
+    /// Drop a socket entirely, as when its owning process exits
+    pub fn release(&mut self, socket_id: u32) {
+        if let Some(ws) = self.sockets.remove(&socket_id).and_then(|s| s.ws) {
+            // Its handlers are dropped with the socket, so nothing may call them
+            ws.set_onopen(None);
+            ws.set_onmessage(None);
+            ws.set_onerror(None);
+            ws.set_onclose(None);
+        }
+    }
+
+    pub fn set_owner(&mut self, socket_id: u32, pid: u32) {
+        if let Some(socket) = self.sockets.get_mut(&socket_id) {
+            socket.pid = Some(pid);
+        }
+    }
+
+    /// Release every socket `pid` holds, as when it exits
+    pub fn release_owned(&mut self, pid: u32) {
+        let owned: Vec<u32> = self
+            .sockets
+            .values()
+            .filter(|s| s.pid == Some(pid))
+            .map(|s| s.id)
+            .collect();
+        for id in owned {
+            self.release(id);
+        }
+    }
+
+    /// Every open socket, in the order they were created
+    pub fn sockets(&self) -> Vec<&Socket> {
+        let mut sockets: Vec<&Socket> = self.sockets.values().collect();
+        sockets.sort_by_key(|s| s.id);
+        sockets
+    }
+
     pub fn list_sockets(&self) -> Vec<String> {
         let mut result = Vec::new();
-        for socket in self.sockets.values() {
+        for socket in self.sockets() {
             let proto = match socket.protocol {
                 Protocol::Tcp => "tcp",
                 Protocol::Udp => "udp",
@@ -223,11 +850,34 @@ impl NetworkStack {
     }
 
     pub fn get_interfaces(&self) -> Vec<NetworkInterface> {
-        Vec::new()
+        self.interfaces.clone()
     }
 
+    /// The main routing table: a subnet route per `eth0` address plus the
+    /// default route when the gateway is reachable
     pub fn get_routes(&self) -> Vec<RouteEntry> {
-        Vec::new()
+        let mut routes = Vec::new();
+        if let Some(gateway) = self.default_gateway() {
+            routes.push(RouteEntry {
+                destination: "0.0.0.0".to_string(),
+                gateway: gateway.to_string(),
+                genmask: "0.0.0.0".to_string(),
+                flags: "UG".to_string(),
+                iface: "eth0".to_string(),
+            });
+        }
+        for iface in self.interfaces.iter().filter(|i| i.is_up && !i.is_loopback) {
+            for (addr, prefix) in &iface.ipv4 {
+                routes.push(RouteEntry {
+                    destination: network_address(addr, *prefix),
+                    gateway: "0.0.0.0".to_string(),
+                    genmask: format_ipv4(prefix_mask(*prefix)),
+                    flags: "U".to_string(),
+                    iface: iface.name.to_string(),
+                });
+            }
+        }
+        routes
     }
 
     pub fn dns_lookup(&self, hostname: &str) -> Vec<DnsRecord> {
@@ -246,7 +896,16 @@ impl NetworkStack {
     }
 
     pub fn arp_table(&self) -> Vec<(String, String, String)> {
-        Vec::new()
+        self.default_gateway()
+            .map(|gateway| {
+                (
+                    gateway.to_string(),
+                    "52:55:0a:00:02:02".to_string(),
+                    "eth0".to_string(),
+                )
+            })
+            .into_iter()
+            .collect()
     }
 
     pub async fn http_get(url: &str) -> Result<String, String> {
@@ -329,23 +988,31 @@ pub async fn post_http(url: &str, body: &str) -> Result<String, JsValue> {
 
 #[wasm_bindgen]
 pub async fn curl_request(url: &str, method: &str, show_headers: bool) -> Result<String, JsValue> {
+    curl_text(url, method, show_headers)
+        .await
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// What `curl` prints for `url`: the body, or with `show_headers` the
+/// status line and a few headers
+pub async fn curl_text(url: &str, method: &str, show_headers: bool) -> Result<String, String> {
     let start = js_sys::Date::now();
 
     let opts = RequestInit::new();
     opts.set_method(method);
     opts.set_mode(RequestMode::Cors);
 
-    let request = Request::new_with_str_and_init(url, &opts)
-        .map_err(|e| JsValue::from_str(&format!("curl: {:?}", e)))?;
+    let request =
+        Request::new_with_str_and_init(url, &opts).map_err(|e| format!("curl: {:?}", e))?;
 
-    let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let window = web_sys::window().ok_or("No window")?;
     let resp_value = JsFuture::from(window.fetch_with_request(&request))
         .await
-        .map_err(|e| JsValue::from_str(&format!("curl: (7) Failed to connect: {:?}", e)))?;
+        .map_err(|e| format!("curl: (7) Failed to connect: {:?}", e))?;
 
     let resp: Response = resp_value
         .dyn_into()
-        .map_err(|_| JsValue::from_str("curl: Invalid response"))?;
+        .map_err(|_| "curl: Invalid response")?;
 
     let elapsed = js_sys::Date::now() - start;
     let status = resp.status();
@@ -369,12 +1036,9 @@ pub async fn curl_request(url: &str, method: &str, show_headers: bool) -> Result
         }
         output.push_str(&format!("\n* Request completed in {:.0}ms\n", elapsed));
     } else {
-        let text = JsFuture::from(
-            resp.text()
-                .map_err(|e| JsValue::from_str(&format!("curl: {:?}", e)))?,
-        )
-        .await
-        .map_err(|e| JsValue::from_str(&format!("curl: {:?}", e)))?;
+        let text = JsFuture::from(resp.text().map_err(|e| format!("curl: {:?}", e))?)
+            .await
+            .map_err(|e| format!("curl: {:?}", e))?;
 
         if let Some(body) = text.as_string() {
             output.push_str(&body);
@@ -384,30 +1048,131 @@ pub async fn curl_request(url: &str, method: &str, show_headers: bool) -> Result
     Ok(output)
 }
 
+/// GET bytes `start..=end` of `url` for a download (the whole body when
+/// `start` is negative); resolves to
+/// `[status, statusText, contentType, total, bytes]`, where `total` is the
+/// full size of the resource or -1 if the server didn't say
+#[wasm_bindgen]
+pub async fn download_request(url: &str, start: f64, end: f64) -> Result<js_sys::Array, JsValue> {
+    let start = (start >= 0.0).then_some(start as u64);
+    let resp = fetch_range(url, start, end as u64)
+        .await
+        .map_err(|e| JsValue::from_str(&e))?;
+    let result = js_sys::Array::new();
+    result.push(&JsValue::from(resp.status));
+    result.push(&JsValue::from_str(&resp.status_text));
+    result.push(&JsValue::from_str(&resp.content_type));
+    result.push(&JsValue::from(resp.total.map_or(-1.0, |t| t as f64)));
+    result.push(&js_sys::Uint8Array::from(&resp.bytes[..]));
+    Ok(result)
+}
+
+/// One response to a ranged download request
+pub struct RangeResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub content_type: String,
+    /// Full size of the resource, if the server said
+    pub total: Option<u64>,
+    pub bytes: Vec<u8>,
+}
+
+/// GET bytes `start..=end` of `url`, or the whole body without `start`
+pub async fn fetch_range(url: &str, start: Option<u64>, end: u64) -> Result<RangeResponse, String> {
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+    opts.set_mode(RequestMode::Cors);
+
+    let request = Request::new_with_str_and_init(url, &opts).map_err(|e| format!("{:?}", e))?;
+    if let Some(start) = start {
+        request
+            .headers()
+            .set("Range", &format!("bytes={}-{}", start, end))
+            .map_err(|e| format!("{:?}", e))?;
+    }
+
+    let window = web_sys::window().ok_or("No window")?;
+    let resp: Response = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(|e| format!("{:?}", e))?
+        .dyn_into()
+        .map_err(|_| "Invalid response")?;
+
+    let buffer = JsFuture::from(resp.array_buffer().map_err(|e| format!("{:?}", e))?)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+    // `Content-Range: bytes 0-1048575/73400320` on a 206
+    let total = match resp.status() {
+        206 => resp
+            .headers()
+            .get("content-range")
+            .ok()
+            .flatten()
+            .and_then(|range| range.rsplit('/').next()?.trim().parse::<u64>().ok()),
+        200 => Some(bytes.len() as u64),
+        _ => None,
+    };
+
+    Ok(RangeResponse {
+        status: resp.status(),
+        status_text: resp.status_text(),
+        content_type,
+        total,
+        bytes,
+    })
+}
+
 /// no-cors mode for compatibility
 #[wasm_bindgen]
 pub async fn ping_request(url: &str) -> Result<String, JsValue> {
+    ping_rtt(url)
+        .await
+        .map(|elapsed| format!("time={:.1}ms", elapsed))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Milliseconds for one HTTP round trip to `url`
+pub async fn ping_rtt(url: &str) -> Result<f64, String> {
     let start = js_sys::Date::now();
 
     let opts = RequestInit::new();
     opts.set_method("GET");
     opts.set_mode(RequestMode::NoCors); // Use no-cors for ping
 
-    let request = Request::new_with_str_and_init(url, &opts)
-        .map_err(|e| JsValue::from_str(&format!("ping: {:?}", e)))?;
+    let request =
+        Request::new_with_str_and_init(url, &opts).map_err(|e| format!("ping: {:?}", e))?;
 
-    let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let window = web_sys::window().ok_or("No window")?;
 
     match JsFuture::from(window.fetch_with_request(&request)).await {
-        Ok(_) => {
-            // With no-cors we get an opaque response but timing is still valid.
-            let elapsed = js_sys::Date::now() - start;
-            Ok(format!("time={:.1}ms", elapsed))
-        }
-        Err(_) => Err(JsValue::from_str("timeout")),
+        // With no-cors we get an opaque response but timing is still valid.
+        Ok(_) => Ok(js_sys::Date::now() - start),
+        Err(_) => Err("timeout".into()),
     }
 }
 
+/// Resolve after `ms` milliseconds on the browser's timer
+pub async fn sleep_ms(ms: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window().is_some_and(|w| {
+            w.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+                .is_ok()
+        });
+        if !scheduled {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
 /// DNS lookup via DNS-over-HTTPS (Cloudflare)
 #[wasm_bindgen]
 pub async fn dns_lookup(hostname: &str) -> Result<String, JsValue> {
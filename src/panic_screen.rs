@@ -0,0 +1,252 @@
+//! The kernel panic screen: the panic trace, a register dump and the uptime
+//! drawn with the bitmap font on a black canvas, a blinking cursor under
+//! them, and a reboot on the next key press.
+use crate::font;
+use crate::graphics::{glyph_advance, Color, Graphics};
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document};
+
+/// Reboot without a key press after this long, like `panic=10`
+const AUTO_REBOOT_MS: i32 = 10_000;
+const BLINK_MS: i32 = 500;
+const MARGIN: u32 = 8;
+const TEXT: Color = Color::rgb(170, 170, 170);
+
+type SaveFuture = Pin<Box<dyn Future<Output = Result<(), String>>>>;
+
+thread_local! {
+    static GFX: RefCell<Option<Graphics>> = const { RefCell::new(None) };
+    static SAVE: RefCell<Option<SaveFuture>> = const { RefCell::new(None) };
+    static TIMERS: RefCell<Vec<i32>> = const { RefCell::new(Vec::new()) };
+    static CURSOR: Cell<(u32, u32, u32)> = const { Cell::new((0, 0, 1)) };
+    static CURSOR_ON: Cell<bool> = const { Cell::new(false) };
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+    static LISTENING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn document() -> Document {
+    window().unwrap().document().unwrap()
+}
+
+/// A register dump to go with `reason`. The values are made up, but the
+/// same panic always shows the same ones
+fn register_dump(reason: &str) -> Vec<String> {
+    // FNV-1a of the reason seeds an xorshift
+    let mut state = reason.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let text = 0xffff_ffff_8100_0000 | (next() & 0x00ff_fff0);
+    let stack = 0xffff_c900_0000_0000 | (next() & 0x0000_0fff_fff8);
+    let mut regs: Vec<u64> = (0..15).map(|_| next()).collect();
+    regs[3] = 0; // RDX is usually zero in a panic path
+    let mut out = vec![format!(
+        "RIP: 0010:{:016x} RSP: 0018:{:016x} EFLAGS: 00010246",
+        text, stack
+    )];
+    let names = [
+        "RAX", "RBX", "RCX", "RDX", "RSI", "RDI", "RBP", "R08", "R09", "R10", "R11", "R12", "R13",
+        "R14", "R15",
+    ];
+    for (names, values) in names.chunks(3).zip(regs.chunks(3)) {
+        out.push(
+            names
+                .iter()
+                .zip(values)
+                .map(|(n, v)| format!("{}: {:016x}", n, v))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+    }
+    out.push(format!(
+        "CR2: {:016x} CR3: {:016x} CR4: 00000000003706f0",
+        next() & 0x0000_7fff_ffff_f000,
+        next() & 0x0000_000f_ffff_f000
+    ));
+    out
+}
+
+/// Every line of the panic screen: the trace from `reason` with registers
+/// and uptime before its closing `---[ end` line
+pub fn panic_lines(reason: &str, uptime_ms: u64) -> Vec<String> {
+    let mut lines: Vec<String> = reason.lines().map(str::to_string).collect();
+    let end = match lines.last() {
+        Some(last) if last.starts_with("---[ end") => lines.pop(),
+        _ => None,
+    };
+    lines.extend(register_dump(reason));
+    lines.push(format!(
+        "Uptime: {}.{:03}s",
+        uptime_ms / 1000,
+        uptime_ms % 1000
+    ));
+    lines.extend(end);
+    lines.push(String::new());
+    lines.push("Press any key to reboot".into());
+    lines
+}
+
+fn draw(gfx: &mut Graphics, lines: &[String]) {
+    let (w, h) = (gfx.width(), gfx.height());
+    let widest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+    let scale = if widest * glyph_advance(2) + 2 * MARGIN <= w {
+        2
+    } else {
+        1
+    };
+    let line_h = (font::GLYPH_H + 2) * scale;
+    // Like a scrolled console, keep the end of the trace when it is too long
+    let rows = (h.saturating_sub(2 * MARGIN) / line_h).max(2) as usize - 1;
+    let shown = &lines[lines.len().saturating_sub(rows)..];
+
+    let fb = gfx.frame_buffer();
+    fb.clear(&Color::BLACK);
+    let mut y = MARGIN;
+    for line in shown {
+        fb.draw_text(line, MARGIN, y, scale, &TEXT);
+        y += line_h;
+    }
+    CURSOR.with(|c| c.set((MARGIN, y, scale)));
+    CURSOR_ON.with(|c| c.set(false));
+}
+
+fn blink() {
+    let on = !CURSOR_ON.with(|c| c.get());
+    CURSOR_ON.with(|c| c.set(on));
+    let (x, y, scale) = CURSOR.with(|c| c.get());
+    GFX.with(|g| {
+        if let Some(ref mut gfx) = *g.borrow_mut() {
+            let shade = if on { 170 } else { 0 };
+            gfx.frame_buffer().fill_rect(
+                x,
+                y,
+                font::GLYPH_W * scale,
+                font::GLYPH_H * scale,
+                shade,
+                shade,
+                shade,
+            );
+            let _ = gfx.present();
+        }
+    });
+}
+
+fn every(ms: i32, f: impl FnMut() + 'static, repeat: bool) {
+    let w = window().unwrap();
+    let cb = Closure::<dyn FnMut()>::wrap(Box::new(f));
+    let id = if repeat {
+        w.set_interval_with_callback_and_timeout_and_arguments_0(cb.as_ref().unchecked_ref(), ms)
+    } else {
+        w.set_timeout_with_callback_and_timeout_and_arguments_0(cb.as_ref().unchecked_ref(), ms)
+    };
+    cb.forget();
+    if let Ok(id) = id {
+        TIMERS.with(|t| t.borrow_mut().push(id));
+    }
+}
+
+/// Save the state the panic left behind, so the next boot finds it, then
+/// start over
+fn reboot() {
+    if !ACTIVE.with(|a| a.replace(false)) {
+        return;
+    }
+    let w = window().unwrap();
+    for id in TIMERS.with(|t| std::mem::take(&mut *t.borrow_mut())) {
+        w.clear_interval_with_handle(id);
+        w.clear_timeout_with_handle(id);
+    }
+    let save = SAVE.with(|s| s.borrow_mut().take());
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Some(save) = save {
+            if let Err(e) = save.await {
+                web_sys::console::warn_1(&JsValue::from_str(&format!(
+                    "Failed to save system state before reboot: {}",
+                    e
+                )));
+            }
+        }
+        GFX.with(|g| *g.borrow_mut() = None);
+        crate::restart_os();
+        let _ = window().unwrap().location().reload();
+    });
+}
+
+fn install_listener() {
+    if LISTENING.with(|l| l.replace(true)) {
+        return;
+    }
+    let keydown = Closure::<dyn FnMut(_)>::wrap(Box::new(|e: web_sys::KeyboardEvent| {
+        if ACTIVE.with(|a| a.get()) && !e.repeat() {
+            e.prevent_default();
+            reboot();
+        }
+    }));
+    window()
+        .unwrap()
+        .add_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())
+        .unwrap();
+    keydown.forget();
+}
+
+/// Take over the screen with `lines`. `save` runs before the reboot
+pub fn show(
+    lines: Vec<String>,
+    save: impl Future<Output = Result<(), String>> + 'static,
+) -> Result<(), JsValue> {
+    let w = window().ok_or("No window")?;
+    let width = w.inner_width()?.as_f64().unwrap_or(800.0) as u32;
+    let height = w.inner_height()?.as_f64().unwrap_or(600.0) as u32;
+
+    crate::doom::claim_2d_canvas();
+    let mut gfx = Graphics::new("game-canvas", width, height)?;
+    draw(&mut gfx, &lines);
+    gfx.present()?;
+    GFX.with(|g| *g.borrow_mut() = Some(gfx));
+    SAVE.with(|s| *s.borrow_mut() = Some(Box::pin(save)));
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:block;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:none;").ok();
+    }
+    // Nothing should blank or lock the screen over the trace
+    crate::idle::set_game_active(true);
+
+    ACTIVE.with(|a| a.set(true));
+    install_listener();
+    blink();
+    every(BLINK_MS, blink, true);
+    every(AUTO_REBOOT_MS, reboot, false);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_gains_registers_and_uptime() {
+        let reason = "KERNEL PANIC - not syncing: test\n\nCall Trace:\n---[ end Kernel panic - not syncing: test ]---";
+        let lines = panic_lines(reason, 12_345);
+        assert_eq!(lines[0], "KERNEL PANIC - not syncing: test");
+        assert!(lines[3].starts_with("RIP: 0010:ffffffff81"));
+        assert!(lines[4].starts_with("RAX: "));
+        assert!(lines.contains(&"Uptime: 12.345s".to_string()));
+        let end = lines.len() - 3;
+        assert!(lines[end].starts_with("---[ end Kernel panic"));
+        assert_eq!(lines.last().unwrap(), "Press any key to reboot");
+        assert_eq!(panic_lines(reason, 12_345), lines);
+        assert_ne!(register_dump("other"), register_dump(reason));
+    }
+}
@@ -0,0 +1,236 @@
+//! Measured performance for `perf` and `/proc/stat`: frame times of
+//! whichever renderer is drawing (doom or a screensaver), how long each
+//! `exec` takes, how late the event loop runs timers, and how much of each
+//! second the main thread spent busy
+use crate::clock;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+/// Frames kept, about five seconds at 60 fps
+const FRAME_WINDOW: usize = 300;
+const EXEC_WINDOW: usize = 200;
+const JANK_WINDOW: usize = 120;
+/// Seconds of busy time kept for the CPU sparkline
+pub const CPU_WINDOW: usize = 60;
+/// How often the jank monitor asks to be woken
+#[cfg(target_arch = "wasm32")]
+const JANK_INTERVAL_MS: f64 = 100.0;
+/// Gaps longer than this are a renderer starting over, not a slow frame
+const FRAME_GAP_MS: f64 = 1000.0;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+thread_local! {
+    static RENDERER: Cell<Option<&'static str>> = const { Cell::new(None) };
+    static LAST_FRAME: Cell<Option<f64>> = const { Cell::new(None) };
+    static FRAMES: RefCell<VecDeque<f64>> = const { RefCell::new(VecDeque::new()) };
+    static EXECS: RefCell<VecDeque<f64>> = const { RefCell::new(VecDeque::new()) };
+    static EXEC_COUNT: Cell<u64> = const { Cell::new(0) };
+    static JANK: RefCell<VecDeque<f64>> = const { RefCell::new(VecDeque::new()) };
+    #[cfg(target_arch = "wasm32")]
+    static MONITOR_STARTED: Cell<bool> = const { Cell::new(false) };
+    /// Busy milliseconds per whole second of `clock::now_ms`
+    static BUSY: RefCell<VecDeque<(u64, f64)>> = const { RefCell::new(VecDeque::new()) };
+    static BUSY_TOTAL: Cell<f64> = const { Cell::new(0.0) };
+}
+
+fn push(samples: &mut VecDeque<f64>, value: f64, window: usize) {
+    samples.push_back(value);
+    while samples.len() > window {
+        samples.pop_front();
+    }
+}
+
+/// Count `ms` of main-thread work against the current second
+pub fn add_busy(ms: f64) {
+    let second = (clock::now_ms() / 1000.0) as u64;
+    BUSY.with(|b| {
+        let mut busy = b.borrow_mut();
+        match busy.back_mut() {
+            Some((s, total)) if *s == second => *total += ms,
+            _ => busy.push_back((second, ms)),
+        }
+        while busy
+            .front()
+            .is_some_and(|(s, _)| s + CPU_WINDOW as u64 <= second)
+        {
+            busy.pop_front();
+        }
+    });
+    BUSY_TOTAL.with(|t| t.set(t.get() + ms));
+}
+
+/// A frame of `renderer` drawn at `ts` (the animation frame timestamp) that
+/// took `work_ms` to draw. A different renderer starts the window afresh
+pub fn record_frame(renderer: &'static str, ts: f64, work_ms: f64) {
+    if RENDERER.with(|r| r.replace(Some(renderer))) != Some(renderer) {
+        FRAMES.with(|f| f.borrow_mut().clear());
+        LAST_FRAME.with(|l| l.set(None));
+    }
+    if let Some(last) = LAST_FRAME.with(|l| l.replace(Some(ts))) {
+        let interval = ts - last;
+        if interval > 0.0 && interval < FRAME_GAP_MS {
+            FRAMES.with(|f| push(&mut f.borrow_mut(), interval, FRAME_WINDOW));
+        }
+    }
+    add_busy(work_ms);
+}
+
+pub fn record_exec(ms: f64) {
+    EXECS.with(|e| push(&mut e.borrow_mut(), ms, EXEC_WINDOW));
+    EXEC_COUNT.with(|c| c.set(c.get() + 1));
+    add_busy(ms);
+}
+
+/// How much later than asked a timer ran
+pub fn record_jank(late_ms: f64) {
+    JANK.with(|j| push(&mut j.borrow_mut(), late_ms.max(0.0), JANK_WINDOW));
+}
+
+/// The renderer that drew last and its frame intervals in milliseconds,
+/// oldest first
+pub fn frames() -> (Option<&'static str>, Vec<f64>) {
+    let renderer = RENDERER.with(Cell::get);
+    (
+        renderer,
+        FRAMES.with(|f| f.borrow().iter().copied().collect()),
+    )
+}
+
+/// Recent `exec` times in milliseconds, and how many there have been
+pub fn exec_times() -> (Vec<f64>, u64) {
+    let times = EXECS.with(|e| e.borrow().iter().copied().collect());
+    (times, EXEC_COUNT.with(Cell::get))
+}
+
+pub fn jank() -> Vec<f64> {
+    JANK.with(|j| j.borrow().iter().copied().collect())
+}
+
+/// Percent busy for each of the last `CPU_WINDOW` seconds, oldest first
+pub fn cpu_history() -> Vec<f64> {
+    let now = (clock::now_ms() / 1000.0) as u64;
+    BUSY.with(|b| {
+        let busy = b.borrow();
+        (0..CPU_WINDOW as u64)
+            .rev()
+            .map(|ago| {
+                let second = now.saturating_sub(ago);
+                busy.iter()
+                    .find(|(s, _)| *s == second)
+                    .map_or(0.0, |(_, ms)| (ms / 10.0).min(100.0))
+            })
+            .collect()
+    })
+}
+
+/// Busy milliseconds since start
+pub fn busy_total_ms() -> f64 {
+    BUSY_TOTAL.with(Cell::get)
+}
+
+/// Forget every sample
+pub fn reset() {
+    RENDERER.with(|r| r.set(None));
+    LAST_FRAME.with(|l| l.set(None));
+    FRAMES.with(|f| f.borrow_mut().clear());
+    EXECS.with(|e| e.borrow_mut().clear());
+    EXEC_COUNT.with(|c| c.set(0));
+    JANK.with(|j| j.borrow_mut().clear());
+    BUSY.with(|b| b.borrow_mut().clear());
+}
+
+/// The `p`th percentile (0 to 100) of `sorted`, nearest rank
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// One block character per value, scaled so `max` is a full block
+pub fn sparkline(values: &[f64], max: f64) -> String {
+    values
+        .iter()
+        .map(|v| {
+            let level = match max > 0.0 {
+                true => (v / max * (SPARKS.len() - 1) as f64).round() as usize,
+                false => 0,
+            };
+            SPARKS[level.min(SPARKS.len() - 1)]
+        })
+        .collect()
+}
+
+/// Bytes of linear memory the module has grown to
+pub fn heap_bytes() -> Option<u64> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+        let memory = wasm_bindgen::memory()
+            .dyn_into::<js_sys::WebAssembly::Memory>()
+            .ok()?;
+        let buffer = memory.buffer().dyn_into::<js_sys::ArrayBuffer>().ok()?;
+        Some(buffer.byte_length() as u64)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Wake every `JANK_INTERVAL_MS` and record how late each wakeup is; the
+/// browser runs timers late while the main thread is stuck
+pub fn start_jank_monitor() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::prelude::*;
+        use wasm_bindgen::JsCast;
+        if MONITOR_STARTED.with(|m| m.replace(true)) {
+            return;
+        }
+        let mut last = clock::now_ms();
+        let tick = Closure::<dyn FnMut()>::wrap(Box::new(move || {
+            let now = clock::now_ms();
+            record_jank(now - last - JANK_INTERVAL_MS);
+            last = now;
+        }));
+        if let Some(win) = web_sys::window() {
+            let _ = win.set_interval_with_callback_and_timeout_and_arguments_0(
+                tick.as_ref().unchecked_ref(),
+                JANK_INTERVAL_MS as i32,
+            );
+        }
+        tick.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_sparklines_and_frames() {
+        let sorted: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 50.0), 50.0);
+        assert_eq!(percentile(&sorted, 99.0), 99.0);
+        assert_eq!(percentile(&sorted, 100.0), 100.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0], 100.0), "▁▅█");
+        assert_eq!(sparkline(&[3.0], 0.0), "▁");
+
+        reset();
+        record_frame("screensaver", 1000.0, 2.0);
+        record_frame("screensaver", 1016.0, 2.0);
+        record_frame("screensaver", 1050.0, 2.0);
+        // A pause is a restart, not a 5 second frame
+        record_frame("screensaver", 6050.0, 2.0);
+        assert_eq!(frames(), (Some("screensaver"), vec![16.0, 34.0]));
+        record_frame("doom", 7000.0, 4.0);
+        record_frame("doom", 7020.0, 4.0);
+        assert_eq!(frames(), (Some("doom"), vec![20.0]));
+        assert!(busy_total_ms() >= 16.0);
+        assert_eq!(cpu_history().len(), CPU_WINDOW);
+    }
+}
@@ -3,8 +3,23 @@ use wasm_bindgen::JsValue;
 
 #[wasm_bindgen(module = "/js/persist.js")]
 extern "C" {
-    #[wasm_bindgen(catch)]
-    pub async fn idb_save_vfs(data: &str) -> Result<(), JsValue>;
+    /// The tree saved before filesystem images, as one JSON string
     #[wasm_bindgen(catch)]
     pub async fn idb_load_vfs() -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(catch)]
+    pub async fn idb_save_image(manifest: &str, chunks: js_sys::Array) -> Result<(), JsValue>;
+    /// Mark the image dirty until the next `idb_save_image` finishes
+    #[wasm_bindgen(catch)]
+    pub async fn idb_mark_dirty() -> Result<(), JsValue>;
+    /// "clean", "dirty", or null from before the marker was kept
+    #[wasm_bindgen(catch)]
+    pub async fn idb_load_state() -> Result<JsValue, JsValue>;
+    /// `[manifest, chunk...]`, or null when no image has been saved
+    #[wasm_bindgen(catch)]
+    pub async fn idb_load_image() -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(catch)]
+    pub async fn idb_save_snapshots(snapshots: &str) -> Result<(), JsValue>;
+    /// The snapshot list as JSON, or null when there are none
+    #[wasm_bindgen(catch)]
+    pub async fn idb_load_snapshots() -> Result<JsValue, JsValue>;
 }
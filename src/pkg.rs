@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+pub const STATUS_PATH: &str = "/var/lib/dpkg/status";
+pub const INFO_DIR: &str = "/var/lib/dpkg/info";
+
+/// A package available from the built-in archive
+pub struct CatalogEntry {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub size_kb: u32,
+    pub section: &'static str,
+    pub description: &'static str,
+    pub depends: &'static [&'static str],
+    /// Executables unpacked by the package; each one becomes a shell command
+    pub files: &'static [&'static str],
+    /// Data files unpacked alongside the executables, as (path, contents)
+    pub data: &'static [(&'static str, &'static str)],
+}
+
+// Cookie file shipped by fortunes-min, one fortune per `%`-separated block
+const FORTUNES: &str = "\
+There is no place like 127.0.0.1
+%
+It works on my machine.
+%
+Real programmers count from 0.
+%
+A bug in the hand is better than one as yet undetected.
+%
+The best way to predict the future is to implement it.
+%
+rm -rf / is not a backup strategy.
+%
+Any sufficiently advanced bug is indistinguishable from a feature.
+%
+You will be awarded some great honor.
+%
+Today is a good day to read the man pages.
+%
+To iterate is human, to recurse divine.
+\t\t-- L. Peter Deutsch
+%
+Premature optimization is the root of all evil.
+\t\t-- Donald Knuth
+%
+Talk is cheap. Show me the code.
+\t\t-- Linus Torvalds
+";
+
+pub const CATALOG: &[CatalogEntry] = &[
+    CatalogEntry {
+        name: "nano",
+        version: "7.2-1",
+        size_kb: 1632,
+        section: "editors",
+        description: "small, friendly text editor inspired by Pico",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "vim",
+        version: "9.0-3",
+        size_kb: 8420,
+        section: "editors",
+        description: "Vi IMproved - enhanced vi editor",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "htop",
+        version: "3.3.0-1",
+        size_kb: 512,
+        section: "utils",
+        description: "interactive process viewer",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "cmatrix",
+        version: "2.0-4",
+        size_kb: 296,
+        section: "misc",
+        description: "simulates the Matrix digital rain",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "curl",
+        version: "8.7.1-1",
+        size_kb: 2304,
+        section: "web",
+        description: "command line tool for transferring data with URL syntax",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "wget",
+        version: "1.21.4-2",
+        size_kb: 1480,
+        section: "web",
+        description: "retrieves files from the web",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "git",
+        version: "2.46.0-1",
+        size_kb: 12640,
+        section: "vcs",
+        description: "fast, scalable, distributed revision control system",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "openssh-server",
+        version: "9.8p1-1",
+        size_kb: 2630,
+        section: "net",
+        description: "secure shell (SSH) server",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "net-tools",
+        version: "2.10-1",
+        size_kb: 720,
+        section: "net",
+        description: "NET-3 networking toolkit",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "python3",
+        version: "3.12.2-1",
+        size_kb: 11240,
+        section: "python",
+        description: "interactive high-level object-oriented language",
+        depends: &[],
+        files: &[],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "sl",
+        version: "5.02-1",
+        size_kb: 56,
+        section: "games",
+        description: "Correct you if you type `sl' by mistake",
+        depends: &[],
+        files: &["/usr/games/sl"],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "cowsay",
+        version: "3.03+dfsg2-8",
+        size_kb: 93,
+        section: "games",
+        description: "configurable talking cow",
+        depends: &[],
+        files: &["/usr/games/cowsay", "/usr/games/cowthink"],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "figlet",
+        version: "2.2.5-3",
+        size_kb: 174,
+        section: "text",
+        description: "Make large character ASCII banners out of ordinary text",
+        depends: &[],
+        files: &["/usr/bin/figlet"],
+        data: &[],
+    },
+    CatalogEntry {
+        name: "fortunes-min",
+        version: "1:1.99.1-7",
+        size_kb: 412,
+        section: "games",
+        description: "Data files containing selected fortune cookies",
+        depends: &[],
+        files: &[],
+        data: &[("/usr/share/games/fortunes/fortunes", FORTUNES)],
+    },
+    CatalogEntry {
+        name: "fortune-mod",
+        version: "1:1.99.1-7",
+        size_kb: 92,
+        section: "games",
+        description: "provides fortune cookies on demand",
+        depends: &["fortunes-min"],
+        files: &["/usr/games/fortune"],
+        data: &[],
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static CatalogEntry> {
+    CATALOG.iter().find(|p| p.name == name)
+}
+
+/// Catalog package that ships an executable named `cmd`
+pub fn provider_of(cmd: &str) -> Option<&'static CatalogEntry> {
+    CATALOG
+        .iter()
+        .find(|p| p.files.iter().any(|f| command_name(f) == Some(cmd)))
+}
+
+/// Command name for an unpacked file, if it lands in a binary directory
+pub fn command_name(path: &str) -> Option<&str> {
+    let (dir, name) = path.rsplit_once('/')?;
+    matches!(
+        dir,
+        "/bin" | "/sbin" | "/usr/bin" | "/usr/sbin" | "/usr/games"
+    )
+    .then_some(name)
+    .filter(|n| !n.is_empty())
+}
+
+/// One stanza of `/var/lib/dpkg/status`
+#[derive(Clone)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub size_kb: u32,
+    pub section: String,
+    pub description: String,
+    pub depends: Vec<String>,
+}
+
+impl InstalledPackage {
+    pub fn from_catalog(entry: &CatalogEntry) -> Self {
+        InstalledPackage {
+            name: entry.name.into(),
+            version: entry.version.into(),
+            size_kb: entry.size_kb,
+            section: entry.section.into(),
+            description: entry.description.into(),
+            depends: entry.depends.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+}
+
+/// Installed package database in dpkg status format
+#[derive(Default)]
+pub struct PackageDb {
+    packages: BTreeMap<String, InstalledPackage>,
+}
+
+impl PackageDb {
+    pub fn parse(text: &str) -> Self {
+        let mut db = PackageDb::default();
+        for stanza in text.split("\n\n") {
+            let mut fields: BTreeMap<&str, &str> = BTreeMap::new();
+            for line in stanza.lines() {
+                if let Some((key, value)) = line.split_once(':') {
+                    fields.insert(key.trim(), value.trim());
+                }
+            }
+            let Some(name) = fields.get("Package").filter(|n| !n.is_empty()) else {
+                continue;
+            };
+            if !fields
+                .get("Status")
+                .is_some_and(|s| s.ends_with(" installed"))
+            {
+                continue;
+            }
+            let depends = fields
+                .get("Depends")
+                .map(|d| {
+                    d.split(',')
+                        .map(|p| p.trim().to_string())
+                        .filter(|p| !p.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            db.packages.insert(
+                name.to_string(),
+                InstalledPackage {
+                    name: name.to_string(),
+                    version: fields.get("Version").unwrap_or(&"0").to_string(),
+                    size_kb: fields
+                        .get("Installed-Size")
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(0),
+                    section: fields.get("Section").unwrap_or(&"misc").to_string(),
+                    description: fields.get("Description").unwrap_or(&"").to_string(),
+                    depends,
+                },
+            );
+        }
+        db
+    }
+
+    pub fn to_status(&self) -> String {
+        let mut out = String::new();
+        for p in self.packages.values() {
+            out.push_str(&format!(
+                "Package: {}\nStatus: install ok installed\nPriority: optional\nSection: {}\nInstalled-Size: {}\nArchitecture: amd64\nVersion: {}\n",
+                p.name, p.section, p.size_kb, p.version
+            ));
+            if !p.depends.is_empty() {
+                out.push_str(&format!("Depends: {}\n", p.depends.join(", ")));
+            }
+            out.push_str(&format!("Description: {}\n\n", p.description));
+        }
+        out
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InstalledPackage> {
+        self.packages.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.packages.contains_key(name)
+    }
+
+    pub fn insert(&mut self, pkg: InstalledPackage) {
+        self.packages.insert(pkg.name.clone(), pkg);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<InstalledPackage> {
+        self.packages.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &InstalledPackage> {
+        self.packages.values()
+    }
+
+    /// Installed packages that depend on `name`
+    pub fn reverse_depends(&self, name: &str) -> Vec<String> {
+        self.packages
+            .values()
+            .filter(|p| p.depends.iter().any(|d| d == name))
+            .map(|p| p.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_round_trip() {
+        let mut db = PackageDb::default();
+        db.insert(InstalledPackage::from_catalog(find("sl").unwrap()));
+        db.insert(InstalledPackage {
+            name: "hello".into(),
+            version: "1:2.10-3".into(),
+            size_kb: 12,
+            section: "devel".into(),
+            description: "example package".into(),
+            depends: vec!["sl".into()],
+        });
+        let parsed = PackageDb::parse(&db.to_status());
+        assert_eq!(parsed.iter().count(), 2);
+        let hello = parsed.get("hello").unwrap();
+        assert_eq!(hello.version, "1:2.10-3");
+        assert_eq!(hello.depends, vec!["sl".to_string()]);
+        assert_eq!(parsed.reverse_depends("sl"), vec!["hello"]);
+    }
+
+    #[test]
+    fn commands_come_from_binary_dirs() {
+        assert_eq!(command_name("/usr/games/sl"), Some("sl"));
+        assert_eq!(command_name("/usr/share/doc/sl/README"), None);
+        assert_eq!(provider_of("sl").map(|p| p.name), Some("sl"));
+        assert!(provider_of("ls").is_none());
+    }
+}
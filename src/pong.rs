@@ -0,0 +1,420 @@
+//! `pong` on the game canvas: W/S moves the left paddle, the arrow keys the
+//! right one (or the computer with `pong cpu`). First to WIN_SCORE wins.
+use crate::graphics::{text_size, Color, FrameBuffer, Graphics};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 500;
+const PADDLE_W: f64 = 12.0;
+const PADDLE_H: f64 = 80.0;
+// Gap between each paddle and its wall
+const PADDLE_INSET: f64 = 24.0;
+const PADDLE_SPEED: f64 = 420.0;
+// The computer tracks the ball a little slower than a player can move
+const CPU_SPEED: f64 = 300.0;
+const BALL_SIZE: f64 = 10.0;
+const SERVE_SPEED: f64 = 320.0;
+// Each paddle hit speeds the ball up, to a cap
+const SPEEDUP: f64 = 1.06;
+const MAX_SPEED: f64 = 900.0;
+const WIN_SCORE: u32 = 11;
+
+type LoopClosure = std::cell::RefCell<Option<wasm_bindgen::closure::Closure<dyn FnMut(f64)>>>;
+
+thread_local! {
+    static GFX: std::cell::RefCell<Option<Graphics>> = const { std::cell::RefCell::new(None) };
+    static GAME: std::cell::RefCell<Option<Pong>> = const { std::cell::RefCell::new(None) };
+    static LOOP: LoopClosure = const { std::cell::RefCell::new(None) };
+    static KEYS: std::cell::RefCell<[bool; 256]> = const { std::cell::RefCell::new([false;256]) };
+    static LAST_TS: std::cell::Cell<Option<f64>> = const { std::cell::Cell::new(None) };
+    static LISTENING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+fn document() -> Document {
+    window().unwrap().document().unwrap()
+}
+
+/// Paddle movement for one frame: -1 up, 0 still, 1 down
+#[derive(Clone, Copy, Default)]
+pub struct PongInput {
+    pub left: i8,
+    pub right: i8,
+}
+
+/// Game state, independent of the canvas so it can be stepped in tests
+pub struct Pong {
+    width: f64,
+    height: f64,
+    // Paddle tops
+    left_y: f64,
+    right_y: f64,
+    ball: (f64, f64),
+    vel: (f64, f64),
+    pub scores: (u32, u32),
+    /// The right paddle is played by the computer
+    pub cpu: bool,
+    /// Waiting for SPACE before the next serve
+    pub serving: bool,
+    // Which side receives the next serve
+    serve_right: bool,
+}
+
+impl Pong {
+    pub fn new(width: u32, height: u32, cpu: bool) -> Pong {
+        let (width, height) = (width as f64, height as f64);
+        let mut pong = Pong {
+            width,
+            height,
+            left_y: (height - PADDLE_H) / 2.0,
+            right_y: (height - PADDLE_H) / 2.0,
+            ball: (0.0, 0.0),
+            vel: (0.0, 0.0),
+            scores: (0, 0),
+            cpu,
+            serving: true,
+            serve_right: true,
+        };
+        pong.center_ball();
+        pong
+    }
+
+    fn center_ball(&mut self) {
+        self.ball = (
+            (self.width - BALL_SIZE) / 2.0,
+            (self.height - BALL_SIZE) / 2.0,
+        );
+        self.vel = (0.0, 0.0);
+    }
+
+    pub fn winner(&self) -> Option<&'static str> {
+        if self.scores.0 >= WIN_SCORE {
+            Some("LEFT")
+        } else if self.scores.1 >= WIN_SCORE {
+            Some(if self.cpu { "CPU" } else { "RIGHT" })
+        } else {
+            None
+        }
+    }
+
+    /// Launch the ball, or start a new match once someone has won
+    pub fn serve(&mut self) {
+        if self.winner().is_some() {
+            self.scores = (0, 0);
+        }
+        if !self.serving {
+            return;
+        }
+        self.serving = false;
+        let dir = if self.serve_right { 1.0 } else { -1.0 };
+        // Alternate between a shallow up and down angle
+        let dy = if (self.scores.0 + self.scores.1).is_multiple_of(2) {
+            0.5
+        } else {
+            -0.5
+        };
+        self.vel = (dir * SERVE_SPEED, dy * SERVE_SPEED);
+    }
+
+    pub fn step(&mut self, dt: f64, input: PongInput) {
+        let max_y = self.height - PADDLE_H;
+        self.left_y = (self.left_y + input.left as f64 * PADDLE_SPEED * dt).clamp(0.0, max_y);
+        if self.cpu {
+            let target = self.ball.1 + BALL_SIZE / 2.0 - PADDLE_H / 2.0;
+            let step = (target - self.right_y).clamp(-CPU_SPEED * dt, CPU_SPEED * dt);
+            self.right_y = (self.right_y + step).clamp(0.0, max_y);
+        } else {
+            self.right_y =
+                (self.right_y + input.right as f64 * PADDLE_SPEED * dt).clamp(0.0, max_y);
+        }
+        if self.serving {
+            return;
+        }
+
+        self.ball.0 += self.vel.0 * dt;
+        self.ball.1 += self.vel.1 * dt;
+        // Top and bottom walls
+        if self.ball.1 < 0.0 {
+            self.ball.1 = -self.ball.1;
+            self.vel.1 = self.vel.1.abs();
+        } else if self.ball.1 > self.height - BALL_SIZE {
+            self.ball.1 = 2.0 * (self.height - BALL_SIZE) - self.ball.1;
+            self.vel.1 = -self.vel.1.abs();
+        }
+
+        let left_face = PADDLE_INSET + PADDLE_W;
+        let right_face = self.width - PADDLE_INSET - PADDLE_W;
+        if self.vel.0 < 0.0 && self.ball.0 <= left_face && self.ball.0 >= PADDLE_INSET {
+            if let Some(vel) = self.bounce(self.left_y, 1.0) {
+                self.vel = vel;
+                self.ball.0 = left_face;
+            }
+        } else if self.vel.0 > 0.0
+            && self.ball.0 + BALL_SIZE >= right_face
+            && self.ball.0 + BALL_SIZE <= self.width - PADDLE_INSET
+        {
+            if let Some(vel) = self.bounce(self.right_y, -1.0) {
+                self.vel = vel;
+                self.ball.0 = right_face - BALL_SIZE;
+            }
+        }
+
+        // Past a paddle: the other side scores and serves to the loser
+        if self.ball.0 + BALL_SIZE < 0.0 {
+            self.scores.1 += 1;
+            self.serve_right = false;
+            self.end_rally();
+        } else if self.ball.0 > self.width {
+            self.scores.0 += 1;
+            self.serve_right = true;
+            self.end_rally();
+        }
+    }
+
+    // New velocity if the ball meets the paddle whose top is `paddle_y`;
+    // where it hits sets the angle, like the arcade game
+    fn bounce(&self, paddle_y: f64, dir: f64) -> Option<(f64, f64)> {
+        let center = self.ball.1 + BALL_SIZE / 2.0;
+        if center < paddle_y || center > paddle_y + PADDLE_H {
+            return None;
+        }
+        let offset = (center - paddle_y) / PADDLE_H * 2.0 - 1.0;
+        let speed = (self.vel.0.hypot(self.vel.1) * SPEEDUP).min(MAX_SPEED);
+        let angle = offset * std::f64::consts::FRAC_PI_3;
+        Some((dir * speed * angle.cos(), speed * angle.sin()))
+    }
+
+    fn end_rally(&mut self) {
+        self.serving = true;
+        self.center_ball();
+    }
+
+    pub fn render(&self, fb: &mut FrameBuffer) {
+        fb.clear_black();
+        let white = Color::WHITE;
+        // Dashed centre line
+        let mid = (self.width / 2.0) as u32 - 2;
+        for y in (0..self.height as u32).step_by(24) {
+            fb.fill_rect(mid, y, 4, 12, 90, 90, 90);
+        }
+        let paddle = |fb: &mut FrameBuffer, x: f64, y: f64| {
+            fb.fill_rect(
+                x as u32,
+                y as u32,
+                PADDLE_W as u32,
+                PADDLE_H as u32,
+                255,
+                255,
+                255,
+            );
+        };
+        paddle(fb, PADDLE_INSET, self.left_y);
+        paddle(fb, self.width - PADDLE_INSET - PADDLE_W, self.right_y);
+        if self.ball.0 >= 0.0 && self.ball.0 < self.width {
+            let size = BALL_SIZE as u32;
+            fb.fill_rect(
+                self.ball.0 as u32,
+                self.ball.1 as u32,
+                size,
+                size,
+                255,
+                255,
+                255,
+            );
+        }
+
+        let quarter = (self.width / 4.0) as u32;
+        for (score, x) in [(self.scores.0, quarter), (self.scores.1, quarter * 3)] {
+            let text = score.to_string();
+            let w = text_size(&text, 6).0;
+            fb.draw_text(&text, x.saturating_sub(w / 2), 24, 6, &white);
+        }
+
+        let message = match self.winner() {
+            Some(side) => format!("{} WINS - SPACE FOR A NEW MATCH", side),
+            None if self.serving => "SPACE TO SERVE - ESC TO QUIT".to_string(),
+            None => return,
+        };
+        let w = text_size(&message, 2).0;
+        let x = (self.width as u32 / 2).saturating_sub(w / 2);
+        fb.draw_text(
+            &message,
+            x,
+            self.height as u32 - 40,
+            2,
+            &Color::rgb(200, 200, 200),
+        );
+    }
+}
+
+fn held(code: usize) -> bool {
+    KEYS.with(|k| k.borrow()[code])
+}
+
+fn axis(up: usize, down: usize) -> i8 {
+    held(down) as i8 - held(up) as i8
+}
+
+// Window listeners for the page's lifetime; they do nothing between games
+fn install_key_listeners() {
+    if LISTENING.with(|l| l.replace(true)) {
+        return;
+    }
+    let keydown = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(
+        |e: web_sys::KeyboardEvent| {
+            if GAME.with(|g| g.borrow().is_none()) {
+                return;
+            }
+            let code = (e.key_code() & 0xff) as usize;
+            KEYS.with(|k| k.borrow_mut()[code] = true);
+            if code == 32 {
+                GAME.with(|g| {
+                    if let Some(ref mut pong) = *g.borrow_mut() {
+                        pong.serve();
+                    }
+                });
+            }
+            // Keep arrows and space from scrolling the page
+            if matches!(code, 32 | 38 | 40) {
+                e.prevent_default();
+            }
+        },
+    ));
+    let keyup = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(
+        |e: web_sys::KeyboardEvent| {
+            KEYS.with(|k| k.borrow_mut()[(e.key_code() & 0xff) as usize] = false);
+        },
+    ));
+    let w = window().unwrap();
+    w.add_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())
+        .unwrap();
+    w.add_event_listener_with_callback("keyup", keyup.as_ref().unchecked_ref())
+        .unwrap();
+    keydown.forget();
+    keyup.forget();
+}
+
+fn start_loop() {
+    LOOP.with(|l| {
+        if l.borrow().is_some() {
+            return;
+        }
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ts: f64| {
+            if held(27) {
+                stop_pong();
+                return;
+            }
+            // Long pauses (a hidden tab) shouldn't teleport the ball
+            let dt = LAST_TS
+                .with(|last| last.replace(Some(ts)))
+                .map_or(0.0, |prev| ((ts - prev) / 1000.0).clamp(0.0, 0.05));
+            let input = PongInput {
+                left: axis(87, 83),
+                right: axis(38, 40),
+            };
+
+            GAME.with(|g| {
+                if let Some(ref mut pong) = *g.borrow_mut() {
+                    if pong.winner().is_none() {
+                        pong.step(dt, input);
+                    }
+                    GFX.with(|gfx| {
+                        if let Some(ref mut gfx) = *gfx.borrow_mut() {
+                            pong.render(gfx.frame_buffer());
+                            let _ = gfx.present();
+                        }
+                    });
+                }
+            });
+
+            LOOP.with(|l2| {
+                if let Some(ref cb) = *l2.borrow() {
+                    let _ = window()
+                        .unwrap()
+                        .request_animation_frame(cb.as_ref().unchecked_ref());
+                }
+            });
+        }) as Box<dyn FnMut(f64)>);
+        let _ = window()
+            .unwrap()
+            .request_animation_frame(closure.as_ref().unchecked_ref());
+        *l.borrow_mut() = Some(closure);
+    });
+}
+
+/// Start a match; with `cpu` the computer plays the right paddle
+#[wasm_bindgen]
+pub fn start_pong(cpu: bool) {
+    crate::doom::claim_2d_canvas();
+    let Ok(gfx) = Graphics::new("game-canvas", WIDTH, HEIGHT) else {
+        return;
+    };
+    GFX.with(|g| *g.borrow_mut() = Some(gfx));
+    GAME.with(|g| *g.borrow_mut() = Some(Pong::new(WIDTH, HEIGHT, cpu)));
+    KEYS.with(|k| *k.borrow_mut() = [false; 256]);
+    LAST_TS.with(|t| t.set(None));
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:block;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:none;").ok();
+    }
+    install_key_listeners();
+    crate::idle::set_game_active(true);
+    start_loop();
+}
+
+#[wasm_bindgen]
+pub fn stop_pong() {
+    LOOP.with(|l| *l.borrow_mut() = None);
+    GAME.with(|g| *g.borrow_mut() = None);
+    GFX.with(|g| *g.borrow_mut() = None);
+    KEYS.with(|k| *k.borrow_mut() = [false; 256]);
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:none;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:flex;").ok();
+    }
+    crate::idle::set_game_active(false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rally_bounce_and_score() {
+        let mut pong = Pong::new(WIDTH, HEIGHT, false);
+        let still = PongInput::default();
+        // Nothing moves until the serve
+        pong.step(0.1, still);
+        assert_eq!(pong.vel, (0.0, 0.0));
+
+        // A ball flying straight at the centred left paddle comes back faster
+        pong.serve();
+        pong.ball = (
+            PADDLE_INSET + PADDLE_W + 2.0,
+            (HEIGHT as f64 - BALL_SIZE) / 2.0,
+        );
+        pong.vel = (-SERVE_SPEED, 0.0);
+        pong.step(0.016, still);
+        assert!(pong.vel.0 > SERVE_SPEED);
+        assert!(pong.vel.1.abs() < 1.0);
+
+        // Moving the right paddle away lets the ball through for the left
+        for _ in 0..200 {
+            pong.step(0.016, PongInput { left: 0, right: -1 });
+        }
+        assert_eq!(pong.scores, (1, 0));
+        assert!(pong.serving);
+
+        pong.scores = (WIN_SCORE, 3);
+        assert_eq!(pong.winner(), Some("LEFT"));
+        pong.serve();
+        assert_eq!(pong.scores, (0, 0));
+    }
+}
@@ -15,6 +15,95 @@ pub enum Priority {
     Low = 1,
 }
 
+pub const NICE_MIN: i8 = -20;
+pub const NICE_MAX: i8 = 19;
+// CPU time one scheduler tick hands out, in microseconds
+pub const TICK_US: f64 = 10_000.0;
+
+impl Priority {
+    /// The run queue a niceness lands in
+    pub fn from_nice(nice: i8) -> Priority {
+        match nice {
+            ..=-1 => Priority::High,
+            0..=9 => Priority::Normal,
+            _ => Priority::Low,
+        }
+    }
+
+    fn default_nice(self) -> i8 {
+        match self {
+            Priority::High => -10,
+            Priority::Normal => 0,
+            Priority::Low => 10,
+        }
+    }
+}
+
+/// A soft and a hard cap; `None` is unlimited
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rlimit {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+impl Rlimit {
+    const fn new(soft: Option<u64>, hard: Option<u64>) -> Self {
+        Rlimit { soft, hard }
+    }
+
+    /// Whether `used` more would go over the soft cap
+    pub fn exceeded(&self, used: u64) -> bool {
+        self.soft.is_some_and(|soft| used >= soft)
+    }
+}
+
+/// What `ulimit` sets for a shell: open files, processes per user and
+/// address space in KiB
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceLimits {
+    pub nofile: Rlimit,
+    pub nproc: Rlimit,
+    pub vmem: Rlimit,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        ResourceLimits {
+            nofile: Rlimit::new(Some(1024), Some(4096)),
+            nproc: Rlimit::new(Some(4096), Some(4096)),
+            vmem: Rlimit::new(None, None),
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// `/proc/self/limits` in the kernel's layout
+    pub fn proc_text(&self) -> String {
+        let show = |v: Option<u64>, scale: u64| match v {
+            Some(v) => (v * scale).to_string(),
+            None => "unlimited".to_string(),
+        };
+        let mut out = format!(
+            "{:<26}{:<21}{:<21}{:<10}\n",
+            "Limit", "Soft Limit", "Hard Limit", "Units"
+        );
+        for (name, limit, scale, units) in [
+            ("Max processes", self.nproc, 1, "processes"),
+            ("Max open files", self.nofile, 1, "files"),
+            ("Max address space", self.vmem, 1024, "bytes"),
+        ] {
+            out.push_str(&format!(
+                "{:<26}{:<21}{:<21}{:<10}\n",
+                name,
+                show(limit.soft, scale),
+                show(limit.hard, scale),
+                units
+            ));
+        }
+        out
+    }
+}
+
 pub struct Process {
     pub pid: u32,
     pub ppid: u32,
@@ -25,11 +114,36 @@ pub struct Process {
     pub remaining_slice: u32,
     pub memory_offset: u32, // Memory block offset allocated for this process
     pub memory_size: u32,   // Size of memory allocated for this process
+    pub nice: i8,
+    pub cpu_time_us: u64, // CPU time handed out by the scheduler so far
+    pub cpu_percent: f64, // Share of the last tick
+    pub user: String,
+    pub tty: Option<String>, // None for daemons
+    pub cmdline: String,
+    pub start_tick: u64, // Scheduler tick the process was spawned on
+}
+
+impl Process {
+    /// CFS-style load weight: each nice step is worth about 25% CPU
+    pub fn weight(&self) -> f64 {
+        1024.0 / 1.25f64.powi(self.nice as i32)
+    }
+
+    /// Virtual size in KiB: the allocation plus mapped libraries
+    pub fn vsz_kb(&self) -> u32 {
+        self.memory_size / 1024 * 4
+    }
+
+    /// Resident size in KiB
+    pub fn rss_kb(&self) -> u32 {
+        self.memory_size / 1024
+    }
 }
 
 pub struct ProcessTable {
     next_pid: u32,
     procs: HashMap<u32, Process>,
+    ticks: u64,
 }
 impl Default for ProcessTable {
     fn default() -> Self {
@@ -41,6 +155,7 @@ impl ProcessTable {
         ProcessTable {
             next_pid: 1,
             procs: HashMap::new(),
+            ticks: 0,
         }
     }
     pub fn spawn(
@@ -89,6 +204,13 @@ impl ProcessTable {
                 remaining_slice: time_slice,
                 memory_offset,
                 memory_size: process_memory_size,
+                nice: priority.default_nice(),
+                cpu_time_us: 0,
+                cpu_percent: 0.0,
+                user: "root".into(),
+                tty: None,
+                cmdline: name.into(),
+                start_tick: self.ticks,
             },
         );
         Some(pid)
@@ -116,6 +238,59 @@ impl ProcessTable {
     pub fn get_mut(&mut self, pid: u32) -> Option<&mut Process> {
         self.procs.get_mut(&pid)
     }
+
+    pub fn get(&self, pid: u32) -> Option<&Process> {
+        self.procs.get(&pid)
+    }
+
+    /// Who started the process, from which terminal, with what arguments
+    pub fn set_session(&mut self, pid: u32, user: &str, tty: Option<&str>, cmdline: &str) {
+        if let Some(process) = self.procs.get_mut(&pid) {
+            process.user = user.to_string();
+            process.tty = tty.map(str::to_string);
+            process.cmdline = cmdline.to_string();
+        }
+    }
+
+    /// Scheduler ticks so far; each is `TICK_US` of CPU time
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Processes started since boot
+    pub fn forks(&self) -> u32 {
+        self.next_pid - 1
+    }
+
+    /// Change a process's niceness (clamped to -20..19) and the priority
+    /// that goes with it; returns the old niceness
+    pub fn set_nice(&mut self, pid: u32, nice: i8) -> Option<i8> {
+        let process = self.procs.get_mut(&pid)?;
+        let old = process.nice;
+        process.nice = nice.clamp(NICE_MIN, NICE_MAX);
+        process.priority = Priority::from_nice(process.nice);
+        Some(old)
+    }
+
+    // Share one tick of CPU between the runnable processes by weight
+    fn account_tick(&mut self) {
+        self.ticks += 1;
+        let total: f64 = self
+            .procs
+            .values()
+            .filter(|p| p.state == ProcState::Run)
+            .map(Process::weight)
+            .sum();
+        for process in self.procs.values_mut() {
+            if process.state != ProcState::Run || total == 0.0 {
+                process.cpu_percent = 0.0;
+                continue;
+            }
+            let share = process.weight() / total;
+            process.cpu_percent = share * 100.0;
+            process.cpu_time_us += (share * TICK_US) as u64;
+        }
+    }
 }
 
 pub struct Scheduler {
@@ -159,7 +334,22 @@ impl Scheduler {
         }
     }
 
+    /// Move a queued process to the queue for its new priority
+    pub fn reprioritize(&mut self, pid: u32, priority: Priority) {
+        let queued = self.high_queue.contains(&pid)
+            || self.normal_queue.contains(&pid)
+            || self.low_queue.contains(&pid);
+        if queued {
+            self.high_queue.retain(|&p| p != pid);
+            self.normal_queue.retain(|&p| p != pid);
+            self.low_queue.retain(|&p| p != pid);
+            self.add(pid, priority);
+        }
+    }
+
     pub fn tick(&mut self, process_table: &mut ProcessTable) {
+        process_table.account_tick();
+
         // Check if current process exhausted time slice
         if let Some(pid) = self.current {
             if let Some(process) = process_table.get_mut(pid) {
@@ -197,3 +387,33 @@ impl Scheduler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+
+    #[test]
+    fn nice_weights_cpu_share() {
+        let mut mem = Memory::new(1024 * 1024);
+        let mut table = ProcessTable::new();
+        let mut sched = Scheduler::new();
+        let a = table.spawn("a", 1, &mut mem).unwrap();
+        let b = table.spawn("b", 1, &mut mem).unwrap();
+        sched.tick(&mut table);
+        assert_eq!(table.get(a).unwrap().cpu_percent, 50.0);
+
+        assert_eq!(table.set_nice(b, 5), Some(0));
+        assert_eq!(table.get(b).unwrap().priority, Priority::Normal);
+        for _ in 0..10 {
+            sched.tick(&mut table);
+        }
+        let (pa, pb) = (table.get(a).unwrap(), table.get(b).unwrap());
+        assert!((pa.cpu_percent / pb.cpu_percent - 1.25f64.powi(5)).abs() < 1e-9);
+        assert!(pa.cpu_time_us > pb.cpu_time_us);
+
+        assert_eq!(table.set_nice(b, -40), Some(5));
+        assert_eq!(table.get(b).unwrap().nice, NICE_MIN);
+        assert_eq!(table.get(b).unwrap().priority, Priority::High);
+    }
+}
@@ -1,286 +1,1562 @@
-#[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
+use crate::vfs::Vfs;
 use std::collections::HashMap;
 use std::fmt;
+use std::rc::Rc;
 
-pub struct PythonInterpreter {
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    globals: HashMap<String, PythonValue>,
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    output: Vec<String>,
-    #[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-    cpp_handle: u32,
+mod builtins;
+mod lexer;
+mod modules;
+mod parser;
+mod value;
+
+use parser::{CompFor, Expr, FPart, FuncDef, Stmt, StmtKind};
+use value::{check_hashable, dict_get, dict_set, format_spec, py_cmp, py_eq, py_eq_checked, Scope};
+pub use value::{Function, PythonValue};
+
+/// Statements and loop iterations one evaluation may run before it is stopped,
+/// so `while True:` cannot hang the page
+const MAX_STEPS: u64 = 2_000_000;
+const MAX_DEPTH: usize = 200;
+/// Largest sequence a range or repetition may materialize
+const MAX_ITEMS: usize = 1_000_000;
+
+/// Evaluated `start:stop:step` of a slice
+type SliceBounds = (Option<i64>, Option<i64>, Option<i64>);
+
+/// A Python exception, with the (line, function) frames it unwound through
+#[derive(Debug, Clone)]
+pub struct PyErr {
+    kind: String,
+    msg: String,
+    line: Option<usize>,
+    trace: Vec<(usize, String)>,
+    /// Set once the innermost statement has recorded its line
+    located: bool,
 }
 
-impl Default for PythonInterpreter {
-    fn default() -> Self {
-        Self::new()
+impl PyErr {
+    fn new(kind: &str, msg: &str) -> Self {
+        PyErr {
+            kind: kind.into(),
+            msg: msg.into(),
+            line: None,
+            trace: Vec::new(),
+            located: false,
+        }
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum PythonValue {
-    Int(i64),
-    Float(f64),
-    String(String),
-    Bool(bool),
-    None,
-    List(Vec<PythonValue>),
+    fn syntax(msg: &str, line: usize) -> Self {
+        PyErr::new("SyntaxError", msg).at(line)
+    }
+
+    fn at(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    fn type_error(msg: &str) -> Self {
+        PyErr::new("TypeError", msg)
+    }
+
+    /// Render as the interpreter would print it for `source` read from `file`
+    fn traceback(&self, file: &str, source: &str) -> String {
+        if matches!(self.kind.as_str(), "SyntaxError" | "IndentationError") {
+            let line = self.line.unwrap_or(1);
+            let text = source.lines().nth(line - 1).unwrap_or("").trim();
+            return format!("  File \"{}\", line {}\n    {}\n{}", file, line, text, self);
+        }
+        let mut out = String::from("Traceback (most recent call last):\n");
+        for (line, func) in self.trace.iter().rev() {
+            out.push_str(&format!(
+                "  File \"{}\", line {}, in {}\n",
+                file, line, func
+            ));
+        }
+        out.push_str(&self.to_string());
+        out
+    }
 }
 
-impl fmt::Display for PythonValue {
+impl fmt::Display for PyErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            PythonValue::Int(i) => write!(f, "{}", i),
-            PythonValue::Float(fl) => write!(f, "{}", fl),
-            PythonValue::String(s) => write!(f, "{}", s),
-            PythonValue::Bool(b) => write!(f, "{}", if *b { "True" } else { "False" }),
-            PythonValue::None => write!(f, "None"),
-            PythonValue::List(items) => {
-                let strs: Vec<String> = items.iter().map(|v| v.to_string()).collect();
-                write!(f, "[{}]", strs.join(", "))
-            }
+        if self.msg.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.kind, self.msg)
         }
     }
 }
 
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(PythonValue),
+}
+
+struct Frame {
+    name: String,
+    scope: Rc<Scope>,
+    globals_decl: Vec<String>,
+}
+
+pub struct PythonInterpreter {
+    globals: HashMap<String, PythonValue>,
+    output: String,
+    /// REPL lines collected until a compound statement is complete
+    pending: String,
+    frames: Vec<Frame>,
+    steps: u64,
+    /// Filesystem for `open` and `os`, present only while `with_fs` runs
+    fs: Option<modules::FsAccess>,
+    rng: Option<u64>,
+}
+
+impl Default for PythonInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PythonInterpreter {
     pub fn new() -> Self {
         PythonInterpreter {
-            #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
             globals: HashMap::new(),
-            #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-            output: Vec::new(),
-            #[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-            cpp_handle: crate::cpp_accel::python_new(),
+            output: String::new(),
+            pending: String::new(),
+            frames: Vec::new(),
+            steps: 0,
+            fs: None,
+            rng: None,
         }
     }
 
-    pub fn eval(&mut self, code: &str) -> Result<String, String> {
-        #[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-        {
-            if self.cpp_handle == 0 {
-                return Err("Python engine unavailable".to_string());
-            }
-            crate::cpp_accel::python_eval(self.cpp_handle, code)
+    /// Lend `fs` to the interpreter while `run` executes, so `open()` and
+    /// the `os` module work on it with the permissions of `user`
+    pub fn with_fs<T>(
+        &mut self,
+        fs: &mut Vfs,
+        user: &str,
+        groups: Vec<String>,
+        run: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        self.fs = Some(modules::FsAccess {
+            vfs: std::mem::take(fs),
+            user: user.to_string(),
+            groups,
+        });
+        let result = run(self);
+        if let Some(access) = self.fs.take() {
+            *fs = access.vfs;
+        }
+        result
+    }
+
+    /// Prompt for the next REPL line: `... ` while a block is still open
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() {
+            ">>> "
+        } else {
+            "... "
         }
+    }
 
-        #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
+    /// Feed one REPL line. Returns `None` while more lines are needed to
+    /// finish a block or bracket, otherwise the result of running the input.
+    pub fn push_line(&mut self, line: &str) -> Option<Result<String, String>> {
+        if !self.pending.is_empty() && line.trim().is_empty() {
+            let source = std::mem::take(&mut self.pending);
+            return Some(self.eval(&source));
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        // Compound statements end with a blank line; anything else runs as
+        // soon as it parses
+        if self
+            .pending
+            .lines()
+            .any(|l| l.split('#').next().unwrap_or("").trim_end().ends_with(':'))
         {
-            // Security: Block dangerous operations
-            if code.contains("import")
-                || code.contains("__")
-                || code.contains("eval")
-                || code.contains("exec")
-                || code.contains("open")
-                || code.contains("file")
-                || code.contains("compile")
-            {
-                return Err("Forbidden operation".to_string());
+            return None;
+        }
+        match parser::parse_program(&self.pending) {
+            Err(e) if lexer::is_incomplete(&e) => None,
+            _ => {
+                let source = std::mem::take(&mut self.pending);
+                Some(self.eval(&source))
             }
+        }
+    }
 
-            let trimmed = code.trim();
+    /// Run interactive input, echoing the value of bare expressions. On error
+    /// the output printed before the exception is returned with the traceback.
+    pub fn eval(&mut self, code: &str) -> Result<String, String> {
+        self.run(code, "<stdin>", true)
+    }
 
-            // Handle print() function
-            if trimmed.starts_with("print(") && trimmed.ends_with(")") {
-                let content = &trimmed[6..trimmed.len() - 1];
-                let result = self.eval_expression(content)?;
-                self.output.push(result.to_string());
-                return Ok(result.to_string());
+    /// Run a whole program, as `python file.py` does
+    pub fn run_script(&mut self, source: &str, filename: &str) -> Result<String, String> {
+        self.run(source, filename, false)
+    }
+
+    fn run(&mut self, source: &str, file: &str, interactive: bool) -> Result<String, String> {
+        self.output.clear();
+        self.frames.clear();
+        self.steps = 0;
+        let result = parser::parse_program(source).and_then(|program| {
+            for stmt in &program {
+                let echo = match &stmt.kind {
+                    StmtKind::Expr(e) if interactive => Some(e),
+                    _ => None,
+                };
+                let res = match echo {
+                    Some(e) => self.eval_expr(e).map(|v| {
+                        if !matches!(v, PythonValue::None) {
+                            self.output.push_str(&v.repr());
+                            self.output.push('\n');
+                        }
+                        Flow::Normal
+                    }),
+                    None => self.exec_stmt(stmt),
+                };
+                match res.map_err(|e| self.locate(e, stmt.line))? {
+                    Flow::Normal => {}
+                    Flow::Return(_) => {
+                        return Err(PyErr::syntax("'return' outside function", stmt.line))
+                    }
+                    _ => return Err(PyErr::syntax("'break' outside loop", stmt.line)),
+                }
             }
+            Ok(())
+        });
+        let mut out = std::mem::take(&mut self.output);
+        if out.ends_with('\n') {
+            out.pop();
+        }
+        match result {
+            Ok(()) => Ok(out),
+            Err(e) => {
+                if !out.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str(&e.traceback(file, source));
+                Err(out)
+            }
+        }
+    }
+
+    fn frame_name(&self) -> String {
+        self.frames
+            .last()
+            .map(|f| f.name.clone())
+            .unwrap_or_else(|| "<module>".into())
+    }
+
+    fn locate(&self, mut err: PyErr, line: usize) -> PyErr {
+        if !err.located && !matches!(err.kind.as_str(), "SyntaxError" | "IndentationError") {
+            err.trace.push((line, self.frame_name()));
+            err.located = true;
+        }
+        err
+    }
 
-            // Handle variable assignment
-            if let Some(eq_pos) = trimmed.find('=') {
-                if !trimmed[..eq_pos].contains('>')
-                    && !trimmed[..eq_pos].contains('<')
-                    && !trimmed[..eq_pos].contains('!')
-                    && !trimmed[..eq_pos].contains('=')
-                {
-                    let var_name = trimmed[..eq_pos].trim().to_string();
-                    let expr = trimmed[eq_pos + 1..].trim();
-                    let value = self.eval_expression(expr)?;
-                    self.globals.insert(var_name, value);
-                    return Ok(String::new());
+    fn tick(&mut self) -> Result<(), PyErr> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(PyErr::new(
+                "RuntimeError",
+                "execution step limit exceeded (infinite loop?)",
+            ));
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    // ---- names ----
+
+    fn lookup(&self, name: &str) -> Result<PythonValue, PyErr> {
+        if let Some(frame) = self.frames.last() {
+            if !frame.globals_decl.iter().any(|g| g == name) {
+                let mut scope = Some(&frame.scope);
+                while let Some(s) = scope {
+                    if let Some(v) = s.vars.borrow().get(name) {
+                        return Ok(v.clone());
+                    }
+                    scope = s.parent.as_ref();
                 }
             }
+        }
+        if let Some(v) = self.globals.get(name) {
+            return Ok(v.clone());
+        }
+        if let Some(b) = builtins::BUILTINS.iter().find(|b| **b == name) {
+            return Ok(PythonValue::Builtin(b));
+        }
+        Err(PyErr::new(
+            "NameError",
+            &format!("name '{}' is not defined", name),
+        ))
+    }
+
+    fn set_name(&mut self, name: &str, value: PythonValue) {
+        match self.frames.last() {
+            Some(f) if !f.globals_decl.iter().any(|g| g == name) => {
+                f.scope.vars.borrow_mut().insert(name.to_string(), value);
+            }
+            _ => {
+                self.globals.insert(name.to_string(), value);
+            }
+        }
+    }
 
-            // Handle expressions
-            let result = self.eval_expression(trimmed)?;
-            Ok(result.to_string())
+    fn delete_name(&mut self, name: &str) -> Result<(), PyErr> {
+        let removed = match self.frames.last() {
+            Some(f) if !f.globals_decl.iter().any(|g| g == name) => {
+                f.scope.vars.borrow_mut().remove(name)
+            }
+            _ => self.globals.remove(name),
+        };
+        match removed {
+            Some(_) => Ok(()),
+            None => Err(PyErr::new(
+                "NameError",
+                &format!("name '{}' is not defined", name),
+            )),
         }
     }
 
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    fn eval_expression(&self, expr: &str) -> Result<PythonValue, String> {
-        let expr = expr.trim();
+    fn current_scope(&self) -> Option<Rc<Scope>> {
+        self.frames.last().map(|f| f.scope.clone())
+    }
 
-        // String literals
-        if (expr.starts_with('"') && expr.ends_with('"'))
-            || (expr.starts_with('\'') && expr.ends_with('\''))
-        {
-            return Ok(PythonValue::String(expr[1..expr.len() - 1].to_string()));
-        }
-
-        // Boolean literals
-        if expr == "True" {
-            return Ok(PythonValue::Bool(true));
-        }
-        if expr == "False" {
-            return Ok(PythonValue::Bool(false));
-        }
-        if expr == "None" {
-            return Ok(PythonValue::None);
-        }
-
-        // Number literals
-        if let Ok(i) = expr.parse::<i64>() {
-            return Ok(PythonValue::Int(i));
-        }
-        if let Ok(f) = expr.parse::<f64>() {
-            return Ok(PythonValue::Float(f));
-        }
-
-        // Variable lookup
-        if let Some(value) = self.globals.get(expr) {
-            return Ok(value.clone());
-        }
-
-        // Built-in functions
-        if expr.starts_with("len(") && expr.ends_with(")") {
-            let arg = &expr[4..expr.len() - 1];
-            let val = self.eval_expression(arg)?;
-            match val {
-                PythonValue::String(s) => Ok(PythonValue::Int(s.len() as i64)),
-                PythonValue::List(l) => Ok(PythonValue::Int(l.len() as i64)),
-                _ => Err("len() requires string or list".to_string()),
-            }
-        } else if expr.starts_with("str(") && expr.ends_with(")") {
-            let arg = &expr[4..expr.len() - 1];
-            let val = self.eval_expression(arg)?;
-            Ok(PythonValue::String(val.to_string()))
-        } else if expr.starts_with("int(") && expr.ends_with(")") {
-            let arg = &expr[4..expr.len() - 1];
-            let val = self.eval_expression(arg)?;
-            match val {
-                PythonValue::Int(i) => Ok(PythonValue::Int(i)),
-                PythonValue::Float(f) => Ok(PythonValue::Int(f as i64)),
-                PythonValue::String(s) => s
-                    .parse::<i64>()
-                    .map(PythonValue::Int)
-                    .map_err(|_| "invalid literal for int()".to_string()),
-                _ => Err("cannot convert to int".to_string()),
-            }
-        } else if expr.starts_with("float(") && expr.ends_with(")") {
-            let arg = &expr[6..expr.len() - 1];
-            let val = self.eval_expression(arg)?;
-            match val {
-                PythonValue::Float(f) => Ok(PythonValue::Float(f)),
-                PythonValue::Int(i) => Ok(PythonValue::Float(i as f64)),
-                PythonValue::String(s) => s
-                    .parse::<f64>()
-                    .map(PythonValue::Float)
-                    .map_err(|_| "invalid literal for float()".to_string()),
-                _ => Err("cannot convert to float".to_string()),
-            }
-        } else if expr.contains('+')
-            || expr.contains('-')
-            || expr.contains('*')
-            || expr.contains('/')
-        {
-            self.eval_arithmetic(expr)
-        } else {
-            Err(format!("name '{}' is not defined", expr))
-        }
-    }
-
-    #[cfg(any(not(feature = "cpp-accel"), cpp_accel_disabled))]
-    fn eval_arithmetic(&self, expr: &str) -> Result<PythonValue, String> {
-        // Simple arithmetic evaluation (left to right, no precedence)
-        let ops = ['+', '-', '*', '/'];
-
-        for op in ops.iter() {
-            if let Some(pos) = expr.rfind(*op) {
-                if pos > 0 && pos < expr.len() - 1 {
-                    let left = self.eval_expression(&expr[..pos])?;
-                    let right = self.eval_expression(&expr[pos + 1..])?;
-
-                    return match (left, right) {
-                        (PythonValue::Int(a), PythonValue::Int(b)) => match op {
-                            '+' => Ok(PythonValue::Int(a + b)),
-                            '-' => Ok(PythonValue::Int(a - b)),
-                            '*' => Ok(PythonValue::Int(a * b)),
-                            '/' => {
-                                if b == 0 {
-                                    Err("division by zero".to_string())
-                                } else {
-                                    Ok(PythonValue::Float(a as f64 / b as f64))
-                                }
-                            }
-                            _ => Err("unsupported operation".to_string()),
-                        },
-                        (PythonValue::Float(a), PythonValue::Float(b)) => match op {
-                            '+' => Ok(PythonValue::Float(a + b)),
-                            '-' => Ok(PythonValue::Float(a - b)),
-                            '*' => Ok(PythonValue::Float(a * b)),
-                            '/' => {
-                                if b == 0.0 {
-                                    Err("division by zero".to_string())
-                                } else {
-                                    Ok(PythonValue::Float(a / b))
-                                }
-                            }
-                            _ => Err("unsupported operation".to_string()),
+    // ---- statements ----
+
+    fn exec_block(&mut self, body: &[Stmt]) -> Result<Flow, PyErr> {
+        for stmt in body {
+            let flow = self
+                .exec_stmt(stmt)
+                .map_err(|e| self.locate(e, stmt.line))?;
+            if !matches!(flow, Flow::Normal) {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<Flow, PyErr> {
+        self.tick()?;
+        match &stmt.kind {
+            StmtKind::Expr(e) => {
+                self.eval_expr(e)?;
+            }
+            StmtKind::Assign(targets, value) => {
+                let value = self.eval_expr(value)?;
+                for target in targets {
+                    self.assign(target, value.clone())?;
+                }
+            }
+            StmtKind::AugAssign(target, op, value) => self.aug_assign(target, op, value)?,
+            StmtKind::If(branches, orelse) => {
+                for (cond, body) in branches {
+                    if self.eval_expr(cond)?.truthy() {
+                        return self.exec_block(body);
+                    }
+                }
+                return self.exec_block(orelse);
+            }
+            StmtKind::While(cond, body) => {
+                while self.eval_expr(cond)?.truthy() {
+                    self.tick()?;
+                    match self.exec_block(body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        _ => {}
+                    }
+                }
+            }
+            StmtKind::For(target, iter, body) => {
+                let iterable = self.eval_expr(iter)?;
+                for item in self.iterate(&iterable)? {
+                    self.tick()?;
+                    self.assign(target, item)?;
+                    match self.exec_block(body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        _ => {}
+                    }
+                }
+            }
+            StmtKind::Def(def) => {
+                let func = self.make_function(def)?;
+                self.set_name(&def.name, func);
+            }
+            StmtKind::Return(value) => {
+                if self.frames.is_empty() {
+                    return Err(PyErr::syntax("'return' outside function", stmt.line));
+                }
+                let value = match value {
+                    Some(e) => self.eval_expr(e)?,
+                    None => PythonValue::None,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StmtKind::Break => return Ok(Flow::Break),
+            StmtKind::Continue => return Ok(Flow::Continue),
+            StmtKind::Pass => {}
+            StmtKind::Global(names) => {
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.globals_decl.extend(names.iter().cloned());
+                }
+            }
+            StmtKind::Del(targets) => {
+                for target in targets {
+                    self.delete(target)?;
+                }
+            }
+            StmtKind::Assert(cond, msg) => {
+                if !self.eval_expr(cond)?.truthy() {
+                    let msg = match msg {
+                        Some(m) => self.eval_expr(m)?.to_string(),
+                        None => String::new(),
+                    };
+                    return Err(PyErr::new("AssertionError", &msg));
+                }
+            }
+            StmtKind::Import(names) => {
+                for (module, binding) in names {
+                    let value = self.import_module(module)?;
+                    // `import os.path` binds `os`
+                    let value = match module.split_once('.') {
+                        Some((top, _)) if top == binding => self.import_module(top)?,
+                        _ => value,
+                    };
+                    self.set_name(binding, value);
+                }
+            }
+            StmtKind::FromImport(module, names) => {
+                self.import_module(module)?;
+                for (name, binding) in names {
+                    if name == "*" {
+                        for (name, value) in self.module_exports(module) {
+                            self.set_name(&name, value);
+                        }
+                        continue;
+                    }
+                    let Some(value) = self.module_attr(module, name) else {
+                        return Err(PyErr::new(
+                            "ImportError",
+                            &format!(
+                                "cannot import name '{}' from '{}' (unknown location)",
+                                name, module
+                            ),
+                        ));
+                    };
+                    self.set_name(binding, value);
+                }
+            }
+            StmtKind::With(items, body) => {
+                let mut files = Vec::new();
+                for (context, target) in items {
+                    let value = self.eval_expr(context)?;
+                    let PythonValue::File(file) = &value else {
+                        return Err(PyErr::type_error(&format!(
+                            "'{}' object does not support the context manager protocol",
+                            value.type_name()
+                        )));
+                    };
+                    files.push(file.clone());
+                    if let Some(target) = target {
+                        self.assign(target, value)?;
+                    }
+                }
+                let result = self.exec_block(body);
+                for file in files {
+                    file.borrow_mut().closed = true;
+                }
+                return result;
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn make_function(&mut self, def: &Rc<FuncDef>) -> Result<PythonValue, PyErr> {
+        let mut defaults = Vec::with_capacity(def.params.len());
+        for p in &def.params {
+            defaults.push(match &p.default {
+                Some(e) => Some(self.eval_expr(e)?),
+                None => None,
+            });
+        }
+        Ok(PythonValue::Function(Rc::new(Function {
+            def: def.clone(),
+            defaults,
+            closure: self.current_scope(),
+        })))
+    }
+
+    fn assign(&mut self, target: &Expr, value: PythonValue) -> Result<(), PyErr> {
+        match target {
+            Expr::Name(name) => {
+                self.set_name(name, value);
+                Ok(())
+            }
+            Expr::Index(obj, index) => {
+                let obj = self.eval_expr(obj)?;
+                if let Expr::Slice(start, stop, step) = index.as_ref() {
+                    let bounds = self.slice_bounds(start, stop, step)?;
+                    return self.set_slice(&obj, bounds, value);
+                }
+                let index = self.eval_expr(index)?;
+                self.set_item(&obj, index, value)
+            }
+            Expr::Tuple(targets) | Expr::List(targets) => {
+                let items = self.iterate(&value)?;
+                let star = targets.iter().position(|t| matches!(t, Expr::Starred(_)));
+                match star {
+                    None if items.len() != targets.len() => Err(PyErr::new(
+                        "ValueError",
+                        &if items.len() > targets.len() {
+                            format!("too many values to unpack (expected {})", targets.len())
+                        } else {
+                            format!(
+                                "not enough values to unpack (expected {}, got {})",
+                                targets.len(),
+                                items.len()
+                            )
                         },
-                        (PythonValue::Float(a), PythonValue::Int(b)) => {
-                            let b = b as f64;
-                            match op {
-                                '+' => Ok(PythonValue::Float(a + b)),
-                                '-' => Ok(PythonValue::Float(a - b)),
-                                '*' => Ok(PythonValue::Float(a * b)),
-                                '/' => {
-                                    if b == 0.0 {
-                                        Err("division by zero".to_string())
-                                    } else {
-                                        Ok(PythonValue::Float(a / b))
-                                    }
-                                }
-                                _ => Err("unsupported operation".to_string()),
-                            }
+                    )),
+                    None => {
+                        for (t, v) in targets.iter().zip(items) {
+                            self.assign(t, v)?;
+                        }
+                        Ok(())
+                    }
+                    Some(pos) => {
+                        let after = targets.len() - pos - 1;
+                        if items.len() < targets.len() - 1 {
+                            return Err(PyErr::new(
+                                "ValueError",
+                                &format!(
+                                    "not enough values to unpack (expected at least {}, got {})",
+                                    targets.len() - 1,
+                                    items.len()
+                                ),
+                            ));
+                        }
+                        let mut items = items;
+                        let tail = items.split_off(items.len() - after);
+                        let middle = items.split_off(pos);
+                        for (t, v) in targets[..pos].iter().zip(items) {
+                            self.assign(t, v)?;
+                        }
+                        if let Expr::Starred(inner) = &targets[pos] {
+                            self.assign(inner, PythonValue::list(middle))?;
+                        }
+                        for (t, v) in targets[pos + 1..].iter().zip(tail) {
+                            self.assign(t, v)?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+            Expr::Attr(obj, attr) => {
+                let obj = self.eval_expr(obj)?;
+                Err(PyErr::new(
+                    "AttributeError",
+                    &format!(
+                        "'{}' object attribute '{}' is read-only",
+                        obj.type_name(),
+                        attr
+                    ),
+                ))
+            }
+            _ => Err(PyErr::new("SyntaxError", "cannot assign to expression")),
+        }
+    }
+
+    fn aug_assign(&mut self, target: &Expr, op: &str, value: &Expr) -> Result<(), PyErr> {
+        match target {
+            Expr::Index(obj, index) if !matches!(index.as_ref(), Expr::Slice(..)) => {
+                let obj = self.eval_expr(obj)?;
+                let index = self.eval_expr(index)?;
+                let current = self.get_item(&obj, &index)?;
+                let rhs = self.eval_expr(value)?;
+                let updated = self.binary_op(op, current, rhs)?;
+                self.set_item(&obj, index, updated)
+            }
+            _ => {
+                let current = self.eval_expr(target)?;
+                let rhs = self.eval_expr(value)?;
+                // `xs += ys` extends the list in place, so aliases see the change
+                if let (PythonValue::List(items), "+") = (&current, op) {
+                    let extra = self.iterate(&rhs)?;
+                    items.borrow_mut().extend(extra);
+                    return self.assign(target, current.clone());
+                }
+                let updated = self.binary_op(op, current, rhs)?;
+                self.assign(target, updated)
+            }
+        }
+    }
+
+    fn delete(&mut self, target: &Expr) -> Result<(), PyErr> {
+        match target {
+            Expr::Name(name) => self.delete_name(name),
+            Expr::Index(obj, index) => {
+                let obj = self.eval_expr(obj)?;
+                if let Expr::Slice(start, stop, step) = index.as_ref() {
+                    let bounds = self.slice_bounds(start, stop, step)?;
+                    if let PythonValue::List(items) = &obj {
+                        let len = items.borrow().len();
+                        let mut picked = value::slice_indices(len, bounds.0, bounds.1, bounds.2)?;
+                        picked.sort_unstable();
+                        let mut items = items.borrow_mut();
+                        for i in picked.into_iter().rev() {
+                            items.remove(i);
                         }
-                        (PythonValue::Int(a), PythonValue::Float(b)) => {
-                            let a = a as f64;
-                            match op {
-                                '+' => Ok(PythonValue::Float(a + b)),
-                                '-' => Ok(PythonValue::Float(a - b)),
-                                '*' => Ok(PythonValue::Float(a * b)),
-                                '/' => {
-                                    if b == 0.0 {
-                                        Err("division by zero".to_string())
-                                    } else {
-                                        Ok(PythonValue::Float(a / b))
-                                    }
-                                }
-                                _ => Err("unsupported operation".to_string()),
+                        return Ok(());
+                    }
+                }
+                let index = self.eval_expr(index)?;
+                match &obj {
+                    PythonValue::List(items) => {
+                        let len = items.borrow().len();
+                        let i = self.seq_index(&index, len, "list")?;
+                        items.borrow_mut().remove(i);
+                        Ok(())
+                    }
+                    PythonValue::Dict(items) => {
+                        let mut items = items.borrow_mut();
+                        match items.iter().position(|(k, _)| py_eq(k, &index)) {
+                            Some(pos) => {
+                                items.remove(pos);
+                                Ok(())
                             }
+                            None => Err(PyErr::new("KeyError", &index.repr())),
                         }
-                        (PythonValue::String(a), PythonValue::String(b)) if *op == '+' => {
-                            Ok(PythonValue::String(format!("{}{}", a, b)))
+                    }
+                    other => Err(PyErr::type_error(&format!(
+                        "'{}' object doesn't support item deletion",
+                        other.type_name()
+                    ))),
+                }
+            }
+            _ => Err(PyErr::new("SyntaxError", "cannot delete expression")),
+        }
+    }
+
+    // ---- expressions ----
+
+    fn eval_expr(&mut self, expr: &Expr) -> Result<PythonValue, PyErr> {
+        match expr {
+            Expr::Int(i) => Ok(PythonValue::Int(*i)),
+            Expr::Float(f) => Ok(PythonValue::Float(*f)),
+            Expr::Str(s) => Ok(PythonValue::str(s.as_str())),
+            Expr::Bool(b) => Ok(PythonValue::Bool(*b)),
+            Expr::NoneLit => Ok(PythonValue::None),
+            Expr::FString(parts) => {
+                let mut out = String::new();
+                for part in parts {
+                    match part {
+                        FPart::Lit(s) => out.push_str(s),
+                        FPart::Expr(e, conv, spec) => {
+                            let v = self.eval_expr(e)?;
+                            let v = match conv {
+                                Some('r' | 'a') => PythonValue::str(v.repr()),
+                                Some('s') => PythonValue::str(v.to_string()),
+                                _ => v,
+                            };
+                            out.push_str(&format_spec(&v, spec)?);
                         }
-                        _ => Err("unsupported operand types".to_string()),
+                    }
+                }
+                Ok(PythonValue::String(out))
+            }
+            Expr::Name(name) => self.lookup(name),
+            Expr::List(items) => Ok(PythonValue::list(self.eval_items(items)?)),
+            Expr::Tuple(items) => Ok(PythonValue::tuple(self.eval_items(items)?)),
+            Expr::Dict(pairs) => {
+                let mut items = Vec::with_capacity(pairs.len());
+                for (k, v) in pairs {
+                    let key = self.eval_expr(k)?;
+                    check_hashable(&key)?;
+                    let value = self.eval_expr(v)?;
+                    dict_set(&mut items, key, value);
+                }
+                Ok(PythonValue::dict(items))
+            }
+            Expr::ListComp(elt, clauses) => {
+                let mut out = Vec::new();
+                self.in_comprehension("<listcomp>", |interp| {
+                    interp.comprehension(clauses, &mut |interp| {
+                        out.push(interp.eval_expr(elt)?);
+                        Ok(())
+                    })
+                })?;
+                Ok(PythonValue::list(out))
+            }
+            Expr::DictComp(key, value, clauses) => {
+                let mut out = Vec::new();
+                self.in_comprehension("<dictcomp>", |interp| {
+                    interp.comprehension(clauses, &mut |interp| {
+                        let k = interp.eval_expr(key)?;
+                        check_hashable(&k)?;
+                        let v = interp.eval_expr(value)?;
+                        dict_set(&mut out, k, v);
+                        Ok(())
+                    })
+                })?;
+                Ok(PythonValue::dict(out))
+            }
+            Expr::Unary(op, operand) => {
+                let v = self.eval_expr(operand)?;
+                match (*op, &v) {
+                    ("not", _) => Ok(PythonValue::Bool(!v.truthy())),
+                    ("-", PythonValue::Float(f)) => Ok(PythonValue::Float(-f)),
+                    ("-", _) if v.as_int().is_some() => v
+                        .as_int()
+                        .and_then(|i| i.checked_neg())
+                        .map(PythonValue::Int)
+                        .ok_or_else(|| PyErr::new("OverflowError", "integer overflow")),
+                    ("+", PythonValue::Float(_)) => Ok(v),
+                    ("+", _) if v.as_int().is_some() => {
+                        Ok(PythonValue::Int(v.as_int().unwrap_or(0)))
+                    }
+                    ("~", _) if v.as_int().is_some() => {
+                        Ok(PythonValue::Int(!v.as_int().unwrap_or(0)))
+                    }
+                    _ => Err(PyErr::type_error(&format!(
+                        "bad operand type for unary {}: '{}'",
+                        op,
+                        v.type_name()
+                    ))),
+                }
+            }
+            Expr::Binary(left, op, right) => {
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                self.binary_op(op, l, r)
+            }
+            Expr::And(left, right) => {
+                let l = self.eval_expr(left)?;
+                if !l.truthy() {
+                    return Ok(l);
+                }
+                self.eval_expr(right)
+            }
+            Expr::Or(left, right) => {
+                let l = self.eval_expr(left)?;
+                if l.truthy() {
+                    return Ok(l);
+                }
+                self.eval_expr(right)
+            }
+            Expr::Compare(first, rest) => {
+                let mut left = self.eval_expr(first)?;
+                for (op, right) in rest {
+                    let right = self.eval_expr(right)?;
+                    if !self.compare(op, &left, &right)? {
+                        return Ok(PythonValue::Bool(false));
+                    }
+                    left = right;
+                }
+                Ok(PythonValue::Bool(true))
+            }
+            Expr::IfExp(cond, body, orelse) => {
+                if self.eval_expr(cond)?.truthy() {
+                    self.eval_expr(body)
+                } else {
+                    self.eval_expr(orelse)
+                }
+            }
+            Expr::Call(func, args, kwargs) => {
+                let func = self.eval_expr(func)?;
+                let args = self.eval_items(args)?;
+                let mut kw = Vec::with_capacity(kwargs.len());
+                for (name, e) in kwargs {
+                    kw.push((name.clone(), self.eval_expr(e)?));
+                }
+                self.call_value(func, args, kw)
+            }
+            Expr::Attr(obj, attr) => {
+                let obj = self.eval_expr(obj)?;
+                self.get_attr(obj, attr)
+            }
+            Expr::Index(obj, index) => {
+                let obj = self.eval_expr(obj)?;
+                if let Expr::Slice(start, stop, step) = index.as_ref() {
+                    let bounds = self.slice_bounds(start, stop, step)?;
+                    return self.get_slice(&obj, bounds);
+                }
+                let index = self.eval_expr(index)?;
+                self.get_item(&obj, &index)
+            }
+            Expr::Slice(..) => Err(PyErr::new("SyntaxError", "invalid syntax")),
+            Expr::Lambda(def) => self.make_function(def),
+            Expr::Starred(_) => Err(PyErr::new(
+                "SyntaxError",
+                "can't use starred expression here",
+            )),
+        }
+    }
+
+    /// Evaluate a list of expressions, expanding `*iterable` entries
+    fn eval_items(&mut self, items: &[Expr]) -> Result<Vec<PythonValue>, PyErr> {
+        let mut out = Vec::with_capacity(items.len());
+        for item in items {
+            match item {
+                Expr::Starred(inner) => {
+                    let v = self.eval_expr(inner)?;
+                    out.extend(self.iterate(&v)?);
+                }
+                other => out.push(self.eval_expr(other)?),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Comprehension variables live in their own scope, as in Python 3
+    fn in_comprehension<T>(
+        &mut self,
+        name: &str,
+        body: impl FnOnce(&mut Self) -> Result<T, PyErr>,
+    ) -> Result<T, PyErr> {
+        let parent = self.current_scope();
+        self.frames.push(Frame {
+            name: name.into(),
+            scope: Rc::new(Scope {
+                vars: Default::default(),
+                parent,
+            }),
+            globals_decl: Vec::new(),
+        });
+        let result = body(self);
+        self.frames.pop();
+        result
+    }
+
+    fn comprehension(
+        &mut self,
+        clauses: &[CompFor],
+        emit: &mut dyn FnMut(&mut Self) -> Result<(), PyErr>,
+    ) -> Result<(), PyErr> {
+        let Some((clause, rest)) = clauses.split_first() else {
+            return emit(self);
+        };
+        let iterable = self.eval_expr(&clause.iter)?;
+        'items: for item in self.iterate(&iterable)? {
+            self.tick()?;
+            self.assign(&clause.target, item)?;
+            for cond in &clause.conds {
+                if !self.eval_expr(cond)?.truthy() {
+                    continue 'items;
+                }
+            }
+            self.comprehension(rest, emit)?;
+        }
+        Ok(())
+    }
+
+    fn compare(&mut self, op: &str, a: &PythonValue, b: &PythonValue) -> Result<bool, PyErr> {
+        use std::cmp::Ordering::*;
+        Ok(match op {
+            "==" => py_eq_checked(a, b)?,
+            "!=" => !py_eq_checked(a, b)?,
+            "<" => py_cmp(a, b)? == Less,
+            ">" => py_cmp(a, b)? == Greater,
+            "<=" => py_cmp(a, b)? != Greater,
+            ">=" => py_cmp(a, b)? != Less,
+            "in" => self.contains(b, a)?,
+            "not in" => !self.contains(b, a)?,
+            "is" | "is not" => {
+                let same = match (a, b) {
+                    (PythonValue::List(x), PythonValue::List(y)) => Rc::ptr_eq(x, y),
+                    (PythonValue::Dict(x), PythonValue::Dict(y)) => Rc::ptr_eq(x, y),
+                    (PythonValue::Tuple(x), PythonValue::Tuple(y)) => Rc::ptr_eq(x, y),
+                    _ => a.type_name() == b.type_name() && py_eq(a, b),
+                };
+                same == (op == "is")
+            }
+            _ => return Err(PyErr::type_error("unsupported comparison")),
+        })
+    }
+
+    fn contains(&self, container: &PythonValue, item: &PythonValue) -> Result<bool, PyErr> {
+        match container {
+            PythonValue::String(s) => match item {
+                PythonValue::String(sub) => Ok(s.contains(sub.as_str())),
+                other => Err(PyErr::type_error(&format!(
+                    "'in <string>' requires string as left operand, not {}",
+                    other.type_name()
+                ))),
+            },
+            PythonValue::List(items) => Ok(items.borrow().iter().any(|v| py_eq(v, item))),
+            PythonValue::Tuple(items) => Ok(items.iter().any(|v| py_eq(v, item))),
+            PythonValue::Dict(items) => Ok(items.borrow().iter().any(|(k, _)| py_eq(k, item))),
+            PythonValue::Range(start, stop, step) => Ok(match item.as_int() {
+                Some(n) => {
+                    let in_bounds = if *step > 0 {
+                        n >= *start && n < *stop
+                    } else {
+                        n <= *start && n > *stop
                     };
+                    in_bounds && (n - start) % step == 0
+                }
+                None => false,
+            }),
+            other => Err(PyErr::type_error(&format!(
+                "argument of type '{}' is not iterable",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn binary_op(
+        &mut self,
+        op: &str,
+        a: PythonValue,
+        b: PythonValue,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        if a.is_number() && b.is_number() {
+            return numeric_op(op, &a, &b);
+        }
+        let repeat = |n: i64, len: usize| -> Result<usize, PyErr> {
+            let n = n.max(0) as usize;
+            if n.saturating_mul(len) > MAX_ITEMS * 10 {
+                return Err(PyErr::new("MemoryError", ""));
+            }
+            Ok(n)
+        };
+        match (op, &a, &b) {
+            ("+", V::String(x), V::String(y)) => Ok(V::String(format!("{}{}", x, y))),
+            ("+", V::List(x), V::List(y)) => {
+                let mut items = x.borrow().clone();
+                items.extend(y.borrow().iter().cloned());
+                Ok(V::list(items))
+            }
+            ("+", V::Tuple(x), V::Tuple(y)) => {
+                Ok(V::tuple(x.iter().chain(y.iter()).cloned().collect()))
+            }
+            ("+", V::String(_), other) => Err(PyErr::type_error(&format!(
+                "can only concatenate str (not \"{}\") to str",
+                other.type_name()
+            ))),
+            ("*", V::String(s), n) | ("*", n, V::String(s)) if n.as_int().is_some() => {
+                let n = repeat(n.as_int().unwrap_or(0), s.len())?;
+                Ok(V::String(s.repeat(n)))
+            }
+            ("*", V::List(items), n) | ("*", n, V::List(items)) if n.as_int().is_some() => {
+                let items = items.borrow();
+                let n = repeat(n.as_int().unwrap_or(0), items.len())?;
+                Ok(V::list(
+                    items
+                        .iter()
+                        .cloned()
+                        .cycle()
+                        .take(items.len() * n)
+                        .collect(),
+                ))
+            }
+            ("*", V::Tuple(items), n) | ("*", n, V::Tuple(items)) if n.as_int().is_some() => {
+                let n = repeat(n.as_int().unwrap_or(0), items.len())?;
+                Ok(V::tuple(
+                    items
+                        .iter()
+                        .cloned()
+                        .cycle()
+                        .take(items.len() * n)
+                        .collect(),
+                ))
+            }
+            ("%", V::String(fmt), args) => Ok(V::String(value::percent_format(fmt, args)?)),
+            ("|", V::Dict(x), V::Dict(y)) => {
+                let mut items = x.borrow().clone();
+                for (k, v) in y.borrow().iter() {
+                    dict_set(&mut items, k.clone(), v.clone());
+                }
+                Ok(V::dict(items))
+            }
+            _ => Err(PyErr::type_error(&format!(
+                "unsupported operand type(s) for {}: '{}' and '{}'",
+                op,
+                a.type_name(),
+                b.type_name()
+            ))),
+        }
+    }
+
+    // ---- containers ----
+
+    fn iterate(&self, v: &PythonValue) -> Result<Vec<PythonValue>, PyErr> {
+        match v {
+            PythonValue::List(items) => Ok(items.borrow().clone()),
+            PythonValue::Tuple(items) => Ok(items.as_ref().clone()),
+            PythonValue::String(s) => {
+                Ok(s.chars().map(|c| PythonValue::String(c.into())).collect())
+            }
+            PythonValue::Dict(items) => Ok(items.borrow().iter().map(|(k, _)| k.clone()).collect()),
+            PythonValue::File(file) => {
+                if file.borrow().closed {
+                    return Err(PyErr::new("ValueError", "I/O operation on closed file."));
+                }
+                let mut lines = Vec::new();
+                loop {
+                    let line = Self::file_readline(file);
+                    if line.is_empty() {
+                        break;
+                    }
+                    lines.push(PythonValue::String(line));
+                }
+                Ok(lines)
+            }
+            PythonValue::Range(start, stop, step) => {
+                let len = value::range_len(*start, *stop, *step);
+                if len > MAX_ITEMS {
+                    return Err(PyErr::new(
+                        "MemoryError",
+                        &format!("range of {} items is too large", len),
+                    ));
+                }
+                Ok((0..len as i64)
+                    .map(|i| PythonValue::Int(start + i * step))
+                    .collect())
+            }
+            other => Err(PyErr::type_error(&format!(
+                "'{}' object is not iterable",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn seq_index(&self, index: &PythonValue, len: usize, kind: &str) -> Result<usize, PyErr> {
+        let Some(i) = index.as_int() else {
+            return Err(PyErr::type_error(&format!(
+                "{} indices must be integers or slices, not {}",
+                kind,
+                index.type_name()
+            )));
+        };
+        let adjusted = if i < 0 { i + len as i64 } else { i };
+        if adjusted < 0 || adjusted >= len as i64 {
+            return Err(PyErr::new(
+                "IndexError",
+                &format!("{} index out of range", kind),
+            ));
+        }
+        Ok(adjusted as usize)
+    }
+
+    fn get_item(&self, obj: &PythonValue, index: &PythonValue) -> Result<PythonValue, PyErr> {
+        match obj {
+            PythonValue::List(items) => {
+                let items = items.borrow();
+                let i = self.seq_index(index, items.len(), "list")?;
+                Ok(items[i].clone())
+            }
+            PythonValue::Tuple(items) => {
+                let i = self.seq_index(index, items.len(), "tuple")?;
+                Ok(items[i].clone())
+            }
+            PythonValue::String(s) => {
+                let i = self.seq_index(index, s.chars().count(), "string")?;
+                Ok(PythonValue::String(
+                    s.chars().nth(i).map(String::from).unwrap_or_default(),
+                ))
+            }
+            PythonValue::Range(start, stop, step) => {
+                let len = value::range_len(*start, *stop, *step);
+                let i = self.seq_index(index, len, "range object")?;
+                Ok(PythonValue::Int(start + i as i64 * step))
+            }
+            PythonValue::Dict(items) => {
+                check_hashable(index)?;
+                dict_get(&items.borrow(), index)
+                    .ok_or_else(|| PyErr::new("KeyError", &index.repr()))
+            }
+            other => Err(PyErr::type_error(&format!(
+                "'{}' object is not subscriptable",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn set_item(
+        &self,
+        obj: &PythonValue,
+        index: PythonValue,
+        value: PythonValue,
+    ) -> Result<(), PyErr> {
+        match obj {
+            PythonValue::List(items) => {
+                let len = items.borrow().len();
+                let i = self.seq_index(&index, len, "list assignment")?;
+                items.borrow_mut()[i] = value;
+                Ok(())
+            }
+            PythonValue::Dict(items) => {
+                check_hashable(&index)?;
+                dict_set(&mut items.borrow_mut(), index, value);
+                Ok(())
+            }
+            other => Err(PyErr::type_error(&format!(
+                "'{}' object does not support item assignment",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn slice_bounds(
+        &mut self,
+        start: &Option<Box<Expr>>,
+        stop: &Option<Box<Expr>>,
+        step: &Option<Box<Expr>>,
+    ) -> Result<SliceBounds, PyErr> {
+        let mut bound = |e: &Option<Box<Expr>>| -> Result<Option<i64>, PyErr> {
+            match e {
+                None => Ok(None),
+                Some(e) => match self.eval_expr(e)? {
+                    PythonValue::None => Ok(None),
+                    v => v.as_int().map(Some).ok_or_else(|| {
+                        PyErr::type_error(
+                            "slice indices must be integers or None or have an __index__ method",
+                        )
+                    }),
+                },
+            }
+        };
+        Ok((bound(start)?, bound(stop)?, bound(step)?))
+    }
+
+    fn get_slice(
+        &self,
+        obj: &PythonValue,
+        (start, stop, step): SliceBounds,
+    ) -> Result<PythonValue, PyErr> {
+        match obj {
+            PythonValue::List(items) => {
+                let items = items.borrow();
+                let picked = value::slice_indices(items.len(), start, stop, step)?;
+                Ok(PythonValue::list(
+                    picked.into_iter().map(|i| items[i].clone()).collect(),
+                ))
+            }
+            PythonValue::Tuple(items) => {
+                let picked = value::slice_indices(items.len(), start, stop, step)?;
+                Ok(PythonValue::tuple(
+                    picked.into_iter().map(|i| items[i].clone()).collect(),
+                ))
+            }
+            PythonValue::String(s) => {
+                let chars: Vec<char> = s.chars().collect();
+                let picked = value::slice_indices(chars.len(), start, stop, step)?;
+                Ok(PythonValue::String(
+                    picked.into_iter().map(|i| chars[i]).collect(),
+                ))
+            }
+            PythonValue::Range(..) => {
+                let items = self.iterate(obj)?;
+                let picked = value::slice_indices(items.len(), start, stop, step)?;
+                Ok(PythonValue::list(
+                    picked.into_iter().map(|i| items[i].clone()).collect(),
+                ))
+            }
+            other => Err(PyErr::type_error(&format!(
+                "'{}' object is not subscriptable",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn set_slice(
+        &self,
+        obj: &PythonValue,
+        (start, stop, step): SliceBounds,
+        value: PythonValue,
+    ) -> Result<(), PyErr> {
+        let PythonValue::List(items) = obj else {
+            return Err(PyErr::type_error(&format!(
+                "'{}' object does not support item assignment",
+                obj.type_name()
+            )));
+        };
+        if step.is_some_and(|s| s != 1) {
+            return Err(PyErr::new(
+                "ValueError",
+                "extended slice assignment is not supported",
+            ));
+        }
+        let replacement = self.iterate(&value)?;
+        let len = items.borrow().len();
+        let picked = value::slice_indices(len, start, stop, None)?;
+        let from = picked.first().copied().unwrap_or_else(|| {
+            let s = start.unwrap_or(0);
+            (if s < 0 { s + len as i64 } else { s }).clamp(0, len as i64) as usize
+        });
+        let to = from + picked.len();
+        items.borrow_mut().splice(from..to, replacement);
+        Ok(())
+    }
+
+    // ---- calls ----
+
+    fn call_value(
+        &mut self,
+        func: PythonValue,
+        args: Vec<PythonValue>,
+        kwargs: Vec<(String, PythonValue)>,
+    ) -> Result<PythonValue, PyErr> {
+        match func {
+            PythonValue::Function(f) => self.call_function(&f, args, kwargs),
+            PythonValue::Builtin(name) => self.call_builtin(name, args, kwargs),
+            PythonValue::Method(obj, name) => self.call_method(*obj, name, args, kwargs),
+            other => Err(PyErr::type_error(&format!(
+                "'{}' object is not callable",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        func: &Rc<Function>,
+        mut args: Vec<PythonValue>,
+        kwargs: Vec<(String, PythonValue)>,
+    ) -> Result<PythonValue, PyErr> {
+        let def = &func.def;
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(PyErr::new(
+                "RecursionError",
+                "maximum recursion depth exceeded",
+            ));
+        }
+        let mut vars = HashMap::new();
+        let positional = def.params.len();
+        if args.len() > positional {
+            match &def.vararg {
+                Some(name) => {
+                    let extra = args.split_off(positional);
+                    vars.insert(name.clone(), PythonValue::tuple(extra));
+                }
+                None => {
+                    return Err(PyErr::type_error(&format!(
+                        "{}() takes {} positional argument{} but {} {} given",
+                        def.name,
+                        positional,
+                        if positional == 1 { "" } else { "s" },
+                        args.len(),
+                        if args.len() == 1 { "was" } else { "were" }
+                    )))
                 }
             }
+        } else if let Some(name) = &def.vararg {
+            vars.insert(name.clone(), PythonValue::tuple(Vec::new()));
+        }
+        let mut slots: Vec<Option<PythonValue>> = args.into_iter().map(Some).collect();
+        slots.resize(positional, None);
+        for (name, value) in kwargs {
+            let Some(pos) = def.params.iter().position(|p| p.name == name) else {
+                return Err(PyErr::type_error(&format!(
+                    "{}() got an unexpected keyword argument '{}'",
+                    def.name, name
+                )));
+            };
+            if slots[pos].is_some() {
+                return Err(PyErr::type_error(&format!(
+                    "{}() got multiple values for argument '{}'",
+                    def.name, name
+                )));
+            }
+            slots[pos] = Some(value);
+        }
+        let mut missing = Vec::new();
+        for ((param, slot), default) in def.params.iter().zip(slots).zip(&func.defaults) {
+            match slot.or_else(|| default.clone()) {
+                Some(v) => {
+                    vars.insert(param.name.clone(), v);
+                }
+                None => missing.push(format!("'{}'", param.name)),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(PyErr::type_error(&format!(
+                "{}() missing {} required positional argument{}: {}",
+                def.name,
+                missing.len(),
+                if missing.len() == 1 { "" } else { "s" },
+                missing.join(" and ")
+            )));
         }
 
-        Err("invalid expression".to_string())
+        self.frames.push(Frame {
+            name: def.name.clone(),
+            scope: Rc::new(Scope {
+                vars: vars.into(),
+                parent: func.closure.clone(),
+            }),
+            globals_decl: Vec::new(),
+        });
+        let result = self.exec_block(&def.body);
+        self.frames.pop();
+        match result {
+            Ok(Flow::Return(v)) => Ok(v),
+            Ok(_) => Ok(PythonValue::None),
+            Err(mut e) => {
+                // Let the calling statement add its own traceback line
+                e.located = false;
+                Err(e)
+            }
+        }
     }
 }
 
-#[cfg(all(feature = "cpp-accel", not(cpp_accel_disabled)))]
-impl Drop for PythonInterpreter {
-    fn drop(&mut self) {
-        if self.cpp_handle != 0 {
-            crate::cpp_accel::python_free(self.cpp_handle);
-            self.cpp_handle = 0;
+fn numeric_op(op: &str, a: &PythonValue, b: &PythonValue) -> Result<PythonValue, PyErr> {
+    use PythonValue as V;
+    let overflow = || PyErr::new("OverflowError", "integer overflow");
+    if let (Some(x), Some(y)) = (a.as_int(), b.as_int()) {
+        let both_bool = matches!((a, b), (V::Bool(_), V::Bool(_)));
+        let int = |v: Option<i64>| v.map(V::Int).ok_or_else(overflow);
+        return match op {
+            "+" => int(x.checked_add(y)),
+            "-" => int(x.checked_sub(y)),
+            "*" => int(x.checked_mul(y)),
+            "/" if y == 0 => Err(PyErr::new("ZeroDivisionError", "division by zero")),
+            "/" => Ok(V::Float(x as f64 / y as f64)),
+            "//" | "%" if y == 0 => Err(PyErr::new(
+                "ZeroDivisionError",
+                "integer division or modulo by zero",
+            )),
+            "//" => int(x
+                .checked_div_euclid(y)
+                .map(|q| q - i64::from(y < 0 && x.rem_euclid(y) != 0))),
+            // Only `i64::MIN % -1` overflows, and Python's answer there is 0
+            "%" => {
+                let r = x.checked_rem_euclid(y).unwrap_or(0);
+                Ok(V::Int(if y < 0 && r != 0 { r + y } else { r }))
+            }
+            "**" if y < 0 => Ok(V::Float((x as f64).powf(y as f64))),
+            "**" => int(u32::try_from(y).ok().and_then(|e| x.checked_pow(e))),
+            "&" if both_bool => Ok(V::Bool(x & y != 0)),
+            "|" if both_bool => Ok(V::Bool(x | y != 0)),
+            "^" if both_bool => Ok(V::Bool(x ^ y != 0)),
+            "&" => Ok(V::Int(x & y)),
+            "|" => Ok(V::Int(x | y)),
+            "^" => Ok(V::Int(x ^ y)),
+            "<<" if y < 0 => Err(PyErr::new("ValueError", "negative shift count")),
+            "<<" => int(u32::try_from(y)
+                .ok()
+                .and_then(|s| x.checked_shl(s))
+                .filter(|r| r >> y.min(63) == x)),
+            ">>" if y < 0 => Err(PyErr::new("ValueError", "negative shift count")),
+            ">>" => Ok(V::Int(x >> y.min(63))),
+            _ => Err(unsupported(op, a, b)),
+        };
+    }
+    let (x, y) = (a.as_float().unwrap_or(0.0), b.as_float().unwrap_or(0.0));
+    match op {
+        "+" => Ok(V::Float(x + y)),
+        "-" => Ok(V::Float(x - y)),
+        "*" => Ok(V::Float(x * y)),
+        "/" | "//" | "%" if y == 0.0 => Err(PyErr::new(
+            "ZeroDivisionError",
+            match op {
+                "/" => "float division by zero",
+                "//" => "float floor division by zero",
+                _ => "float modulo",
+            },
+        )),
+        "/" => Ok(V::Float(x / y)),
+        "//" => Ok(V::Float((x / y).floor())),
+        "%" => {
+            let r = x % y;
+            Ok(V::Float(if r != 0.0 && (r < 0.0) != (y < 0.0) {
+                r + y
+            } else {
+                r
+            }))
         }
+        "**" => Ok(V::Float(x.powf(y))),
+        _ => Err(unsupported(op, a, b)),
+    }
+}
+
+fn unsupported(op: &str, a: &PythonValue, b: &PythonValue) -> PyErr {
+    PyErr::type_error(&format!(
+        "unsupported operand type(s) for {}: '{}' and '{}'",
+        op,
+        a.type_name(),
+        b.type_name()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PythonInterpreter;
+
+    fn run(src: &str) -> String {
+        PythonInterpreter::new()
+            .run_script(src, "test.py")
+            .unwrap_or_else(|e| e)
+    }
+
+    #[test]
+    fn functions_loops_and_branches() {
+        let src = "\
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+
+total = 0
+for i in range(10):
+    if i % 2 == 0:
+        continue
+    elif i > 7:
+        break
+    total += i
+print(fib(15), total)
+";
+        assert_eq!(run(src), "610 16");
+    }
+
+    #[test]
+    fn lists_dicts_and_comprehensions() {
+        let src = "\
+squares = [x * x for x in range(6) if x % 2 == 0]
+ages = {'ann': 31, 'bob': 27}
+ages['cy'] = 40
+names = sorted(ages, key=lambda n: ages[n])
+print(squares, squares[-1], squares[::-1])
+print(names, {k: v // 10 for k, v in ages.items()})
+";
+        assert_eq!(
+            run(src),
+            "[0, 4, 16] 16 [16, 4, 0]\n['bob', 'ann', 'cy'] {'ann': 3, 'bob': 2, 'cy': 4}"
+        );
+    }
+
+    #[test]
+    fn containers_that_hold_themselves() {
+        let src = "\
+x = []
+x.append(x)
+print(x)
+d = {}
+d['a'] = d
+d['b'] = [d, x]
+print(d, d == d, x == x)
+a = [1]
+a.append(a)
+b = [1]
+b.append(b)
+print(a == b)
+";
+        let out = run(src);
+        assert!(out.starts_with("[[...]]\n{'a': {...}, 'b': [{...}, [[...]]]} True True\n"));
+        assert!(out.ends_with("RecursionError: maximum recursion depth exceeded in comparison"));
+    }
+
+    #[test]
+    fn f_strings_and_formatting() {
+        let src = "\
+name = 'pi'
+value = 3.14159
+print(f'{name!r:>6} = {value:.2f} {len(name)=} {{ok}}')
+print('%s has %d items' % ('cart', 3), f'{1234567:,}', f'{0.25:.0%}')
+";
+        assert_eq!(
+            run(src),
+            "  'pi' = 3.14 len(name)=2 {ok}\ncart has 3 items 1,234,567 25%"
+        );
+    }
+
+    #[test]
+    fn errors_report_a_traceback() {
+        let src = "def f(x):\n    return 1 / x\n\nprint('before')\nf(0)\n";
+        assert_eq!(
+            run(src),
+            "before\nTraceback (most recent call last):\n  File \"test.py\", line 5, in <module>\n  File \"test.py\", line 2, in f\nZeroDivisionError: division by zero"
+        );
+    }
+
+    #[test]
+    fn integer_floor_division_overflow_raises() {
+        let src = "m = -9223372036854775807 - 1\nprint(m % -1, m // 1, -7 // 2, 7 % -3)\nm // -1\n";
+        assert_eq!(
+            run(src),
+            "0 -9223372036854775808 -4 -2\nTraceback (most recent call last):\n  File \"test.py\", line 3, in <module>\nOverflowError: integer overflow"
+        );
+    }
+
+    #[test]
+    fn repl_waits_for_blocks_to_finish() {
+        let mut py = PythonInterpreter::new();
+        assert!(py.push_line("def double(x):").is_none());
+        assert_eq!(py.prompt(), "... ");
+        assert!(py.push_line("    return x * 2").is_none());
+        assert_eq!(py.push_line(""), Some(Ok(String::new())));
+        assert_eq!(py.push_line("double(21)"), Some(Ok("42".into())));
+        assert_eq!(py.push_line("'a' + 'b'"), Some(Ok("'ab'".into())));
+    }
+
+    #[test]
+    fn math_json_and_seeded_random() {
+        let src = "\
+import math, json, random
+from math import sqrt as root
+data = json.loads('{\"b\": [1, 2.5, null], \"a\": true}')
+print(root(16), math.floor(-2.5), round(math.pi, 3), data)
+print(json.dumps({'k': (1, 'x'), 'n': None}), json.dumps([1], indent=2))
+random.seed(7)
+first = [random.randint(1, 6) for _ in range(5)]
+random.seed(7)
+print(first == [random.randint(1, 6) for _ in range(5)], all(1 <= n <= 6 for n in first))
+";
+        assert_eq!(
+            run(src),
+            "4.0 -3 3.142 {'b': [1, 2.5, None], 'a': True}\n{\"k\": [1, \"x\"], \"n\": null} [\n  1\n]\nTrue True"
+        );
+    }
+
+    #[test]
+    fn gcd_of_the_smallest_int() {
+        let src = "\
+import math
+low = -9223372036854775807 - 1
+print(math.gcd(12, -18), math.gcd(low, 6))
+print(math.gcd(low, 0))
+";
+        let out = run(src);
+        assert!(out.starts_with("6 2\n"));
+        assert!(out.ends_with("OverflowError: integer overflow"));
+    }
+
+    #[test]
+    fn open_and_os_use_the_filesystem() {
+        let mut fs = crate::vfs::Vfs::new();
+        let mut py = PythonInterpreter::new();
+        let src = "\
+import os
+with open('notes.txt', 'w') as f:
+    f.write('one\\n')
+    f.writelines(['two\\n', 'three\\n'])
+with open('notes.txt', 'a') as f:
+    f.write('four\\n')
+os.mkdir('docs')
+print(os.getcwd(), os.listdir('.'), os.path.isdir('docs'))
+print([line.strip() for line in open('notes.txt')])
+";
+        let out = py.with_fs(&mut fs, "root", Vec::new(), |py| py.run_script(src, "t.py"));
+        assert_eq!(
+            out,
+            Ok("/ ['docs', 'notes.txt'] True\n['one', 'two', 'three', 'four']".into())
+        );
+        assert_eq!(
+            fs.resolve("/notes.txt").map(|n| n.data.as_str()),
+            Some("one\ntwo\nthree\nfour\n")
+        );
     }
 }
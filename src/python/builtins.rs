@@ -0,0 +1,1376 @@
+use super::value::{check_hashable, dict_get, dict_set, format_spec, py_cmp, py_eq, TYPE_NAMES};
+use super::{PyErr, PythonInterpreter, PythonValue, MAX_ITEMS};
+use std::cmp::Ordering;
+
+pub(super) const BUILTINS: &[&str] = &[
+    "print",
+    "len",
+    "range",
+    "str",
+    "int",
+    "float",
+    "bool",
+    "list",
+    "tuple",
+    "dict",
+    "sum",
+    "min",
+    "max",
+    "abs",
+    "round",
+    "sorted",
+    "reversed",
+    "enumerate",
+    "zip",
+    "map",
+    "filter",
+    "any",
+    "all",
+    "type",
+    "isinstance",
+    "repr",
+    "chr",
+    "ord",
+    "hex",
+    "bin",
+    "oct",
+    "divmod",
+    "pow",
+    "input",
+    "format",
+    "callable",
+    "open",
+];
+
+const STR_METHODS: &[&str] = &[
+    "upper",
+    "lower",
+    "strip",
+    "lstrip",
+    "rstrip",
+    "split",
+    "join",
+    "replace",
+    "startswith",
+    "endswith",
+    "find",
+    "index",
+    "count",
+    "format",
+    "isdigit",
+    "isalpha",
+    "isalnum",
+    "isspace",
+    "isupper",
+    "islower",
+    "title",
+    "capitalize",
+    "splitlines",
+    "zfill",
+    "center",
+    "ljust",
+    "rjust",
+];
+
+const LIST_METHODS: &[&str] = &[
+    "append", "extend", "insert", "pop", "remove", "index", "count", "sort", "reverse", "copy",
+    "clear",
+];
+
+const DICT_METHODS: &[&str] = &[
+    "keys",
+    "values",
+    "items",
+    "get",
+    "pop",
+    "update",
+    "setdefault",
+    "copy",
+    "clear",
+];
+
+const TUPLE_METHODS: &[&str] = &["index", "count"];
+
+type Kwargs = Vec<(String, PythonValue)>;
+
+fn arity(name: &str, args: &[PythonValue], min: usize, max: usize) -> Result<(), PyErr> {
+    if args.len() >= min && args.len() <= max {
+        return Ok(());
+    }
+    let msg = if min == max {
+        format!(
+            "{}() takes exactly {} argument{} ({} given)",
+            name,
+            min,
+            if min == 1 { "" } else { "s" },
+            args.len()
+        )
+    } else if args.len() < min {
+        format!(
+            "{} expected at least {} argument{}, got {}",
+            name,
+            min,
+            if min == 1 { "" } else { "s" },
+            args.len()
+        )
+    } else {
+        format!(
+            "{} expected at most {} argument{}, got {}",
+            name,
+            max,
+            if max == 1 { "" } else { "s" },
+            args.len()
+        )
+    };
+    Err(PyErr::type_error(&msg))
+}
+
+fn no_kwargs(name: &str, kwargs: &Kwargs) -> Result<(), PyErr> {
+    match kwargs.first() {
+        Some((key, _)) => Err(PyErr::type_error(&format!(
+            "{}() got an unexpected keyword argument '{}'",
+            name, key
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Pull the named keyword arguments out of `kwargs`, rejecting any others
+fn take_kwargs<const N: usize>(
+    name: &str,
+    kwargs: Kwargs,
+    allowed: [&str; N],
+) -> Result<[Option<PythonValue>; N], PyErr> {
+    let mut out: [Option<PythonValue>; N] = std::array::from_fn(|_| None);
+    for (key, value) in kwargs {
+        match allowed.iter().position(|a| *a == key) {
+            Some(i) => out[i] = Some(value),
+            None => {
+                return Err(PyErr::type_error(&format!(
+                    "{}() got an unexpected keyword argument '{}'",
+                    name, key
+                )))
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn int_arg(name: &str, v: &PythonValue) -> Result<i64, PyErr> {
+    v.as_int().ok_or_else(|| {
+        PyErr::type_error(&format!(
+            "{}() argument must be int, not '{}'",
+            name,
+            v.type_name()
+        ))
+    })
+}
+
+fn str_arg<'a>(name: &str, v: &'a PythonValue) -> Result<&'a str, PyErr> {
+    match v {
+        PythonValue::String(s) => Ok(s),
+        other => Err(PyErr::type_error(&format!(
+            "{}() argument must be str, not {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+fn parse_int(text: &str, base: u32) -> Result<i64, PyErr> {
+    let invalid = || {
+        PyErr::new(
+            "ValueError",
+            &format!(
+                "invalid literal for int() with base {}: {}",
+                base,
+                super::value::str_repr(text)
+            ),
+        )
+    };
+    let trimmed = text.trim().replace('_', "");
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest.to_string()),
+        None => (false, trimmed.trim_start_matches('+').to_string()),
+    };
+    let lower = digits.to_ascii_lowercase();
+    let digits = match base {
+        16 => lower.strip_prefix("0x").unwrap_or(&lower),
+        8 => lower.strip_prefix("0o").unwrap_or(&lower),
+        2 => lower.strip_prefix("0b").unwrap_or(&lower),
+        _ => &lower,
+    };
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+    let value = i64::from_str_radix(digits, base).map_err(|_| invalid())?;
+    Ok(if negative { -value } else { value })
+}
+
+fn round_half_even(x: f64) -> f64 {
+    let r = x.round();
+    if (x - x.trunc()).abs() == 0.5 && r % 2.0 != 0.0 {
+        r - x.signum()
+    } else {
+        r
+    }
+}
+
+fn radix_string(i: i64, prefix: &str, radix: u32) -> String {
+    let digits = match radix {
+        16 => format!("{:x}", i.unsigned_abs()),
+        8 => format!("{:o}", i.unsigned_abs()),
+        _ => format!("{:b}", i.unsigned_abs()),
+    };
+    format!("{}{}{}", if i < 0 { "-" } else { "" }, prefix, digits)
+}
+
+impl PythonInterpreter {
+    pub(super) fn call_builtin(
+        &mut self,
+        name: &'static str,
+        args: Vec<PythonValue>,
+        kwargs: Kwargs,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        if name.contains('.') {
+            return self.call_module_fn(name, args, kwargs);
+        }
+        if name == "open" {
+            return self.open_file(args, kwargs);
+        }
+        if !matches!(name, "print" | "sorted" | "min" | "max" | "int" | "dict") {
+            no_kwargs(name, &kwargs)?;
+        }
+        match name {
+            "print" => {
+                let [sep, end] = take_kwargs(name, kwargs, ["sep", "end"])?;
+                let text_or = |v: Option<PythonValue>, default: &str| match v {
+                    None | Some(V::None) => Ok(default.to_string()),
+                    Some(V::String(s)) => Ok(s),
+                    Some(other) => Err(PyErr::type_error(&format!(
+                        "sep must be None or a string, not {}",
+                        other.type_name()
+                    ))),
+                };
+                let sep = text_or(sep, " ")?;
+                let end = text_or(end, "\n")?;
+                let parts: Vec<String> = args.iter().map(|v| v.to_string()).collect();
+                self.write(&parts.join(&sep));
+                self.write(&end);
+                Ok(V::None)
+            }
+            "len" => {
+                arity(name, &args, 1, 1)?;
+                let len = match &args[0] {
+                    V::String(s) => s.chars().count(),
+                    V::List(items) => items.borrow().len(),
+                    V::Tuple(items) => items.len(),
+                    V::Dict(items) => items.borrow().len(),
+                    V::Range(a, b, c) => super::value::range_len(*a, *b, *c),
+                    other => {
+                        return Err(PyErr::type_error(&format!(
+                            "object of type '{}' has no len()",
+                            other.type_name()
+                        )))
+                    }
+                };
+                Ok(V::Int(len as i64))
+            }
+            "range" => {
+                arity(name, &args, 1, 3)?;
+                let nums = args
+                    .iter()
+                    .map(|a| {
+                        a.as_int().ok_or_else(|| {
+                            PyErr::type_error(&format!(
+                                "'{}' object cannot be interpreted as an integer",
+                                a.type_name()
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (start, stop, step) = match nums.as_slice() {
+                    [stop] => (0, *stop, 1),
+                    [start, stop] => (*start, *stop, 1),
+                    [start, stop, step] => (*start, *stop, *step),
+                    _ => unreachable!(),
+                };
+                if step == 0 {
+                    return Err(PyErr::new("ValueError", "range() arg 3 must not be zero"));
+                }
+                Ok(V::Range(start, stop, step))
+            }
+            "str" => {
+                arity(name, &args, 0, 1)?;
+                Ok(V::String(
+                    args.first().map(|v| v.to_string()).unwrap_or_default(),
+                ))
+            }
+            "repr" => {
+                arity(name, &args, 1, 1)?;
+                Ok(V::String(args[0].repr()))
+            }
+            "int" => {
+                let [base] = take_kwargs(name, kwargs, ["base"])?;
+                arity(name, &args, 0, 2)?;
+                let base = match args.get(1).or(base.as_ref()) {
+                    Some(b) => Some(int_arg(name, b)?),
+                    None => None,
+                };
+                match (args.first(), base) {
+                    (None, _) => Ok(V::Int(0)),
+                    (Some(V::String(s)), base) => {
+                        let base = base.unwrap_or(10);
+                        if !(2..=36).contains(&base) {
+                            return Err(PyErr::new(
+                                "ValueError",
+                                "int() base must be >= 2 and <= 36",
+                            ));
+                        }
+                        Ok(V::Int(parse_int(s, base as u32)?))
+                    }
+                    (Some(_), Some(_)) => Err(PyErr::type_error(
+                        "int() can't convert non-string with explicit base",
+                    )),
+                    (Some(V::Float(f)), None) => {
+                        if !f.is_finite() {
+                            return Err(PyErr::new(
+                                if f.is_nan() {
+                                    "ValueError"
+                                } else {
+                                    "OverflowError"
+                                },
+                                "cannot convert float to integer",
+                            ));
+                        }
+                        Ok(V::Int(f.trunc() as i64))
+                    }
+                    (Some(v), None) => v.as_int().map(V::Int).ok_or_else(|| {
+                        PyErr::type_error(&format!(
+                            "int() argument must be a string or a real number, not '{}'",
+                            v.type_name()
+                        ))
+                    }),
+                }
+            }
+            "float" => {
+                arity(name, &args, 0, 1)?;
+                match args.first() {
+                    None => Ok(V::Float(0.0)),
+                    Some(V::String(s)) => {
+                        let t = s.trim().to_ascii_lowercase();
+                        let parsed = match t.trim_start_matches(['+', '-']) {
+                            "inf" | "infinity" => Some(f64::INFINITY),
+                            "nan" => Some(f64::NAN),
+                            _ => t.replace('_', "").parse::<f64>().ok(),
+                        };
+                        match parsed {
+                            Some(f) if t.starts_with('-') && !f.is_finite() => Ok(V::Float(-f)),
+                            Some(f) => Ok(V::Float(f)),
+                            None => Err(PyErr::new(
+                                "ValueError",
+                                &format!("could not convert string to float: {}", args[0].repr()),
+                            )),
+                        }
+                    }
+                    Some(v) => v.as_float().map(V::Float).ok_or_else(|| {
+                        PyErr::type_error(&format!(
+                            "float() argument must be a string or a real number, not '{}'",
+                            v.type_name()
+                        ))
+                    }),
+                }
+            }
+            "bool" => {
+                arity(name, &args, 0, 1)?;
+                Ok(V::Bool(args.first().is_some_and(|v| v.truthy())))
+            }
+            "list" => {
+                arity(name, &args, 0, 1)?;
+                match args.first() {
+                    Some(v) => Ok(V::list(self.iterate(v)?)),
+                    None => Ok(V::list(Vec::new())),
+                }
+            }
+            "tuple" => {
+                arity(name, &args, 0, 1)?;
+                match args.first() {
+                    Some(v) => Ok(V::tuple(self.iterate(v)?)),
+                    None => Ok(V::tuple(Vec::new())),
+                }
+            }
+            "dict" => {
+                arity(name, &args, 0, 1)?;
+                let mut items = Vec::new();
+                if let Some(source) = args.first() {
+                    self.dict_update(&mut items, source)?;
+                }
+                for (k, v) in kwargs {
+                    dict_set(&mut items, V::String(k), v);
+                }
+                Ok(V::dict(items))
+            }
+            "sum" => {
+                arity(name, &args, 1, 2)?;
+                let mut total = args.get(1).cloned().unwrap_or(V::Int(0));
+                if matches!(total, V::String(_)) {
+                    return Err(PyErr::type_error(
+                        "sum() can't sum strings [use ''.join(seq) instead]",
+                    ));
+                }
+                for item in self.iterate(&args[0])? {
+                    total = self.binary_op("+", total, item)?;
+                }
+                Ok(total)
+            }
+            "min" | "max" => {
+                let [key, default] = take_kwargs(name, kwargs, ["key", "default"])?;
+                if args.is_empty() {
+                    return Err(PyErr::type_error(&format!(
+                        "{} expected at least 1 argument, got 0",
+                        name
+                    )));
+                }
+                let items = if args.len() == 1 {
+                    self.iterate(&args[0])?
+                } else {
+                    args
+                };
+                let want = if name == "min" {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                };
+                let mut best: Option<(PythonValue, PythonValue)> = None;
+                for item in items {
+                    let k = match &key {
+                        Some(f) if !matches!(f, V::None) => {
+                            self.call_value(f.clone(), vec![item.clone()], Vec::new())?
+                        }
+                        _ => item.clone(),
+                    };
+                    let better = match &best {
+                        None => true,
+                        Some((best_key, _)) => py_cmp(&k, best_key)? == want,
+                    };
+                    if better {
+                        best = Some((k, item));
+                    }
+                }
+                match (best, default) {
+                    (Some((_, v)), _) => Ok(v),
+                    (None, Some(d)) => Ok(d),
+                    (None, None) => Err(PyErr::new(
+                        "ValueError",
+                        &format!("{}() arg is an empty sequence", name),
+                    )),
+                }
+            }
+            "abs" => {
+                arity(name, &args, 1, 1)?;
+                match &args[0] {
+                    V::Float(f) => Ok(V::Float(f.abs())),
+                    v => v
+                        .as_int()
+                        .and_then(|i| i.checked_abs())
+                        .map(V::Int)
+                        .ok_or_else(|| {
+                            PyErr::type_error(&format!(
+                                "bad operand type for abs(): '{}'",
+                                v.type_name()
+                            ))
+                        }),
+                }
+            }
+            "round" => {
+                arity(name, &args, 1, 2)?;
+                let digits = match args.get(1) {
+                    None | Some(V::None) => None,
+                    Some(d) => Some(int_arg(name, d)?),
+                };
+                match (&args[0], digits) {
+                    (V::Float(f), None) => {
+                        if !f.is_finite() {
+                            return Err(PyErr::new(
+                                "OverflowError",
+                                "cannot convert float infinity to integer",
+                            ));
+                        }
+                        Ok(V::Int(round_half_even(*f) as i64))
+                    }
+                    (V::Float(f), Some(d)) => {
+                        let scale = 10f64.powi(d.clamp(-308, 308) as i32);
+                        Ok(V::Float(round_half_even(f * scale) / scale))
+                    }
+                    (v, d) if v.as_int().is_some() => {
+                        let i = v.as_int().unwrap_or(0);
+                        match d {
+                            Some(d) if d < 0 => {
+                                let scale = 10i64.checked_pow((-d) as u32).unwrap_or(i64::MAX);
+                                Ok(V::Int(
+                                    round_half_even(i as f64 / scale as f64) as i64 * scale,
+                                ))
+                            }
+                            _ => Ok(V::Int(i)),
+                        }
+                    }
+                    (v, _) => Err(PyErr::type_error(&format!(
+                        "type {} doesn't define __round__ method",
+                        v.type_name()
+                    ))),
+                }
+            }
+            "sorted" => {
+                let [key, reverse] = take_kwargs(name, kwargs, ["key", "reverse"])?;
+                arity(name, &args, 1, 1)?;
+                let mut items = self.iterate(&args[0])?;
+                self.sort_values(&mut items, key, reverse.is_some_and(|r| r.truthy()))?;
+                Ok(V::list(items))
+            }
+            "reversed" => {
+                arity(name, &args, 1, 1)?;
+                if matches!(args[0], V::Dict(_)) {
+                    return Err(PyErr::type_error("'dict' object is not reversible"));
+                }
+                let mut items = self.iterate(&args[0])?;
+                items.reverse();
+                Ok(V::list(items))
+            }
+            "enumerate" => {
+                arity(name, &args, 1, 2)?;
+                let start = match args.get(1) {
+                    Some(s) => int_arg(name, s)?,
+                    None => 0,
+                };
+                let items = self.iterate(&args[0])?;
+                Ok(V::list(
+                    items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, v)| V::tuple(vec![V::Int(start + i as i64), v]))
+                        .collect(),
+                ))
+            }
+            "zip" => {
+                let lists = args
+                    .iter()
+                    .map(|a| self.iterate(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let len = lists.iter().map(|l| l.len()).min().unwrap_or(0);
+                Ok(V::list(
+                    (0..len)
+                        .map(|i| V::tuple(lists.iter().map(|l| l[i].clone()).collect()))
+                        .collect(),
+                ))
+            }
+            "map" => {
+                if args.len() < 2 {
+                    return Err(PyErr::type_error("map() must have at least two arguments."));
+                }
+                let func = args[0].clone();
+                let lists = args[1..]
+                    .iter()
+                    .map(|a| self.iterate(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let len = lists.iter().map(|l| l.len()).min().unwrap_or(0);
+                let mut out = Vec::with_capacity(len);
+                for i in 0..len {
+                    let call_args = lists.iter().map(|l| l[i].clone()).collect();
+                    out.push(self.call_value(func.clone(), call_args, Vec::new())?);
+                }
+                Ok(V::list(out))
+            }
+            "filter" => {
+                arity(name, &args, 2, 2)?;
+                let mut out = Vec::new();
+                for item in self.iterate(&args[1])? {
+                    let keep = match &args[0] {
+                        V::None => item.truthy(),
+                        f => self
+                            .call_value(f.clone(), vec![item.clone()], Vec::new())?
+                            .truthy(),
+                    };
+                    if keep {
+                        out.push(item);
+                    }
+                }
+                Ok(V::list(out))
+            }
+            "any" | "all" => {
+                arity(name, &args, 1, 1)?;
+                let items = self.iterate(&args[0])?;
+                Ok(V::Bool(if name == "any" {
+                    items.iter().any(|v| v.truthy())
+                } else {
+                    items.iter().all(|v| v.truthy())
+                }))
+            }
+            "type" => {
+                arity(name, &args, 1, 1)?;
+                let type_name = args[0].type_name();
+                Ok(match TYPE_NAMES.iter().find(|t| **t == type_name) {
+                    Some(t) => V::Builtin(t),
+                    None => V::Builtin("type"),
+                })
+            }
+            "isinstance" => {
+                arity(name, &args, 2, 2)?;
+                let classes = match &args[1] {
+                    V::Tuple(items) => items.as_ref().clone(),
+                    other => vec![other.clone()],
+                };
+                let actual = args[0].type_name();
+                let mut result = false;
+                for class in classes {
+                    let V::Builtin(class) = class else {
+                        return Err(PyErr::type_error(
+                            "isinstance() arg 2 must be a type, a tuple of types, or a union",
+                        ));
+                    };
+                    // bool is a subclass of int
+                    result |= class == actual || (class == "int" && actual == "bool");
+                }
+                Ok(V::Bool(result))
+            }
+            "chr" => {
+                arity(name, &args, 1, 1)?;
+                let code = int_arg(name, &args[0])?;
+                u32::try_from(code)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .map(|c| V::String(c.into()))
+                    .ok_or_else(|| PyErr::new("ValueError", "chr() arg not in range(0x110000)"))
+            }
+            "ord" => {
+                arity(name, &args, 1, 1)?;
+                let s = str_arg(name, &args[0])?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(V::Int(c as i64)),
+                    _ => Err(PyErr::type_error(&format!(
+                        "ord() expected a character, but string of length {} found",
+                        s.chars().count()
+                    ))),
+                }
+            }
+            "hex" | "bin" | "oct" => {
+                arity(name, &args, 1, 1)?;
+                let i = args[0].as_int().ok_or_else(|| {
+                    PyErr::type_error(&format!(
+                        "'{}' object cannot be interpreted as an integer",
+                        args[0].type_name()
+                    ))
+                })?;
+                Ok(V::String(match name {
+                    "hex" => radix_string(i, "0x", 16),
+                    "oct" => radix_string(i, "0o", 8),
+                    _ => radix_string(i, "0b", 2),
+                }))
+            }
+            "divmod" => {
+                arity(name, &args, 2, 2)?;
+                let q = self.binary_op("//", args[0].clone(), args[1].clone())?;
+                let r = self.binary_op("%", args[0].clone(), args[1].clone())?;
+                Ok(V::tuple(vec![q, r]))
+            }
+            "pow" => {
+                arity(name, &args, 2, 3)?;
+                match args.get(2) {
+                    None => self.binary_op("**", args[0].clone(), args[1].clone()),
+                    Some(m) => {
+                        let (Some(mut base), Some(mut exp), Some(m)) =
+                            (args[0].as_int(), args[1].as_int(), m.as_int())
+                        else {
+                            return Err(PyErr::type_error(
+                                "pow() 3rd argument not allowed unless all arguments are integers",
+                            ));
+                        };
+                        if m == 0 {
+                            return Err(PyErr::new("ValueError", "pow() 3rd argument cannot be 0"));
+                        }
+                        if exp < 0 {
+                            return Err(PyErr::new(
+                                "ValueError",
+                                "base is not invertible for the given modulus",
+                            ));
+                        }
+                        let m = m as i128;
+                        let mut result: i128 = 1;
+                        let mut b = (base as i128).rem_euclid(m);
+                        while exp > 0 {
+                            if exp & 1 == 1 {
+                                result = result * b % m;
+                            }
+                            b = b * b % m;
+                            exp >>= 1;
+                        }
+                        base = result.rem_euclid(m) as i64;
+                        Ok(V::Int(base))
+                    }
+                }
+            }
+            "input" => {
+                arity(name, &args, 0, 1)?;
+                if let Some(prompt) = args.first() {
+                    self.write(&prompt.to_string());
+                }
+                Err(PyErr::new("EOFError", "EOF when reading a line"))
+            }
+            "format" => {
+                arity(name, &args, 1, 2)?;
+                let spec = match args.get(1) {
+                    Some(s) => str_arg(name, s)?.to_string(),
+                    None => String::new(),
+                };
+                Ok(V::String(format_spec(&args[0], &spec)?))
+            }
+            "callable" => {
+                arity(name, &args, 1, 1)?;
+                Ok(V::Bool(matches!(
+                    args[0],
+                    V::Function(_) | V::Builtin(_) | V::Method(..)
+                )))
+            }
+            _ => Err(PyErr::new(
+                "NameError",
+                &format!("name '{}' is not defined", name),
+            )),
+        }
+    }
+
+    /// Sort in place with an optional key function, stably, as `list.sort` does
+    fn sort_values(
+        &mut self,
+        items: &mut Vec<PythonValue>,
+        key: Option<PythonValue>,
+        reverse: bool,
+    ) -> Result<(), PyErr> {
+        let keys = match key {
+            Some(f) if !matches!(f, PythonValue::None) => {
+                let mut keys = Vec::with_capacity(items.len());
+                for item in items.iter() {
+                    keys.push(self.call_value(f.clone(), vec![item.clone()], Vec::new())?);
+                }
+                keys
+            }
+            _ => items.clone(),
+        };
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        let mut failure = None;
+        order.sort_by(|&a, &b| {
+            let ord = match py_cmp(&keys[a], &keys[b]) {
+                Ok(o) => o,
+                Err(e) => {
+                    failure.get_or_insert(e);
+                    Ordering::Equal
+                }
+            };
+            if reverse {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        let sorted: Vec<PythonValue> = order.into_iter().map(|i| items[i].clone()).collect();
+        *items = sorted;
+        Ok(())
+    }
+
+    fn dict_update(
+        &self,
+        items: &mut Vec<(PythonValue, PythonValue)>,
+        source: &PythonValue,
+    ) -> Result<(), PyErr> {
+        if let PythonValue::Dict(other) = source {
+            for (k, v) in other.borrow().iter() {
+                dict_set(items, k.clone(), v.clone());
+            }
+            return Ok(());
+        }
+        for (n, pair) in self.iterate(source)?.into_iter().enumerate() {
+            let pair = self.iterate(&pair).map_err(|_| {
+                PyErr::type_error(&format!(
+                    "cannot convert dictionary update sequence element #{} to a sequence",
+                    n
+                ))
+            })?;
+            let [k, v] = <[PythonValue; 2]>::try_from(pair).map_err(|p| {
+                PyErr::new(
+                    "ValueError",
+                    &format!(
+                        "dictionary update sequence element #{} has length {}; 2 is required",
+                        n,
+                        p.len()
+                    ),
+                )
+            })?;
+            check_hashable(&k)?;
+            dict_set(items, k, v);
+        }
+        Ok(())
+    }
+
+    pub(super) fn get_attr(&mut self, obj: PythonValue, attr: &str) -> Result<PythonValue, PyErr> {
+        let found = match &obj {
+            PythonValue::Module(module) => {
+                return self.module_attr(module, attr).ok_or_else(|| {
+                    PyErr::new(
+                        "AttributeError",
+                        &format!("module '{}' has no attribute '{}'", module, attr),
+                    )
+                })
+            }
+            PythonValue::File(file) => self.file_attr(file, attr),
+            _ => {
+                let methods = match &obj {
+                    PythonValue::String(_) => STR_METHODS,
+                    PythonValue::List(_) => LIST_METHODS,
+                    PythonValue::Dict(_) => DICT_METHODS,
+                    PythonValue::Tuple(_) => TUPLE_METHODS,
+                    _ => &[],
+                };
+                methods
+                    .iter()
+                    .find(|m| **m == attr)
+                    .map(|m| PythonValue::Method(Box::new(obj.clone()), m))
+            }
+        };
+        found.ok_or_else(|| {
+            PyErr::new(
+                "AttributeError",
+                &format!("'{}' object has no attribute '{}'", obj.type_name(), attr),
+            )
+        })
+    }
+
+    pub(super) fn call_method(
+        &mut self,
+        obj: PythonValue,
+        name: &'static str,
+        args: Vec<PythonValue>,
+        kwargs: Kwargs,
+    ) -> Result<PythonValue, PyErr> {
+        match obj {
+            PythonValue::String(s) => self.str_method(&s, name, args, kwargs),
+            PythonValue::List(_) => self.list_method(&obj, name, args, kwargs),
+            PythonValue::File(file) => {
+                no_kwargs(name, &kwargs)?;
+                self.file_method(&file, name, args)
+            }
+            PythonValue::Dict(_) => {
+                no_kwargs(name, &kwargs)?;
+                self.dict_method(&obj, name, args)
+            }
+            PythonValue::Tuple(items) => {
+                no_kwargs(name, &kwargs)?;
+                arity(name, &args, 1, 1)?;
+                match name {
+                    "count" => Ok(PythonValue::Int(
+                        items.iter().filter(|v| py_eq(v, &args[0])).count() as i64,
+                    )),
+                    _ => items
+                        .iter()
+                        .position(|v| py_eq(v, &args[0]))
+                        .map(|i| PythonValue::Int(i as i64))
+                        .ok_or_else(|| PyErr::new("ValueError", "tuple.index(x): x not in tuple")),
+                }
+            }
+            other => Err(PyErr::new(
+                "AttributeError",
+                &format!("'{}' object has no attribute '{}'", other.type_name(), name),
+            )),
+        }
+    }
+
+    fn str_method(
+        &mut self,
+        s: &str,
+        name: &'static str,
+        args: Vec<PythonValue>,
+        kwargs: Kwargs,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        if name == "format" {
+            return self.str_format(s, &args, &kwargs).map(V::String);
+        }
+        if name != "split" {
+            no_kwargs(name, &kwargs)?;
+        }
+        let strs = |args: &[PythonValue]| -> Result<Vec<String>, PyErr> {
+            args.iter()
+                .map(|a| match a {
+                    V::String(s) => Ok(s.clone()),
+                    other => Err(PyErr::type_error(&format!(
+                        "must be str, not {}",
+                        other.type_name()
+                    ))),
+                })
+                .collect()
+        };
+        let bool_of = |f: &dyn Fn(char) -> bool| V::Bool(!s.is_empty() && s.chars().all(f));
+        match name {
+            "upper" => Ok(V::String(s.to_uppercase())),
+            "lower" => Ok(V::String(s.to_lowercase())),
+            "strip" | "lstrip" | "rstrip" => {
+                arity(name, &args, 0, 1)?;
+                let chars: Option<Vec<char>> = match args.first() {
+                    None | Some(V::None) => None,
+                    Some(a) => Some(str_arg(name, a)?.chars().collect()),
+                };
+                let pred = |c: char| match &chars {
+                    Some(set) => set.contains(&c),
+                    None => c.is_whitespace(),
+                };
+                Ok(V::str(match name {
+                    "strip" => s.trim_matches(pred),
+                    "lstrip" => s.trim_start_matches(pred),
+                    _ => s.trim_end_matches(pred),
+                }))
+            }
+            "split" => {
+                let [sep_kw, max_kw] = take_kwargs(name, kwargs, ["sep", "maxsplit"])?;
+                arity(name, &args, 0, 2)?;
+                let sep = args.first().cloned().or(sep_kw);
+                let max = match args.get(1).or(max_kw.as_ref()) {
+                    Some(m) => int_arg(name, m)?,
+                    None => -1,
+                };
+                let limit = if max < 0 {
+                    usize::MAX
+                } else {
+                    max as usize + 1
+                };
+                let parts: Vec<PythonValue> = match sep {
+                    None | Some(V::None) => {
+                        let mut parts = Vec::new();
+                        let mut rest = s.trim_start();
+                        while !rest.is_empty() {
+                            if parts.len() + 1 == limit {
+                                parts.push(V::str(rest.trim_end()));
+                                break;
+                            }
+                            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                            parts.push(V::str(&rest[..end]));
+                            rest = rest[end..].trim_start();
+                        }
+                        parts
+                    }
+                    Some(sep) => {
+                        let sep = str_arg(name, &sep)?.to_string();
+                        if sep.is_empty() {
+                            return Err(PyErr::new("ValueError", "empty separator"));
+                        }
+                        s.splitn(limit, sep.as_str()).map(V::str).collect()
+                    }
+                };
+                Ok(V::list(parts))
+            }
+            "splitlines" => Ok(V::list(s.lines().map(V::str).collect())),
+            "join" => {
+                arity(name, &args, 1, 1)?;
+                let items = self.iterate(&args[0])?;
+                let mut parts = Vec::with_capacity(items.len());
+                for (i, item) in items.iter().enumerate() {
+                    match item {
+                        V::String(p) => parts.push(p.as_str()),
+                        other => {
+                            return Err(PyErr::type_error(&format!(
+                                "sequence item {}: expected str instance, {} found",
+                                i,
+                                other.type_name()
+                            )))
+                        }
+                    }
+                }
+                Ok(V::String(parts.join(s)))
+            }
+            "replace" => {
+                arity(name, &args, 2, 3)?;
+                let parts = strs(&args[..2])?;
+                Ok(V::String(match args.get(2) {
+                    Some(n) if int_arg(name, n)? >= 0 => {
+                        s.replacen(&parts[0], &parts[1], int_arg(name, n)? as usize)
+                    }
+                    _ => s.replace(&parts[0], &parts[1]),
+                }))
+            }
+            "startswith" | "endswith" => {
+                arity(name, &args, 1, 1)?;
+                let options = match &args[0] {
+                    V::Tuple(items) => strs(items)?,
+                    other => strs(std::slice::from_ref(other))?,
+                };
+                Ok(V::Bool(options.iter().any(|o| {
+                    if name == "startswith" {
+                        s.starts_with(o.as_str())
+                    } else {
+                        s.ends_with(o.as_str())
+                    }
+                })))
+            }
+            "find" | "index" => {
+                arity(name, &args, 1, 1)?;
+                let needle = str_arg(name, &args[0])?;
+                match s.find(needle) {
+                    Some(byte) => Ok(V::Int(s[..byte].chars().count() as i64)),
+                    None if name == "find" => Ok(V::Int(-1)),
+                    None => Err(PyErr::new("ValueError", "substring not found")),
+                }
+            }
+            "count" => {
+                arity(name, &args, 1, 1)?;
+                let needle = str_arg(name, &args[0])?;
+                let count = if needle.is_empty() {
+                    s.chars().count() + 1
+                } else {
+                    s.matches(needle).count()
+                };
+                Ok(V::Int(count as i64))
+            }
+            "isdigit" => Ok(bool_of(&|c| c.is_ascii_digit())),
+            "isalpha" => Ok(bool_of(&|c| c.is_alphabetic())),
+            "isalnum" => Ok(bool_of(&|c| c.is_alphanumeric())),
+            "isspace" => Ok(bool_of(&|c| c.is_whitespace())),
+            "isupper" | "islower" => {
+                let cased: Vec<char> = s.chars().filter(|c| c.is_alphabetic()).collect();
+                let upper = name == "isupper";
+                Ok(V::Bool(
+                    !cased.is_empty()
+                        && cased.iter().all(|c| {
+                            if upper {
+                                c.is_uppercase()
+                            } else {
+                                c.is_lowercase()
+                            }
+                        }),
+                ))
+            }
+            "title" => {
+                let mut out = String::new();
+                let mut prev_alpha = false;
+                for c in s.chars() {
+                    if prev_alpha {
+                        out.extend(c.to_lowercase());
+                    } else {
+                        out.extend(c.to_uppercase());
+                    }
+                    prev_alpha = c.is_alphabetic();
+                }
+                Ok(V::String(out))
+            }
+            "capitalize" => {
+                let mut chars = s.chars();
+                Ok(V::String(match chars.next() {
+                    Some(first) => first
+                        .to_uppercase()
+                        .chain(chars.as_str().to_lowercase().chars())
+                        .collect(),
+                    None => String::new(),
+                }))
+            }
+            "zfill" => {
+                arity(name, &args, 1, 1)?;
+                let width = int_arg(name, &args[0])?.max(0) as usize;
+                let len = s.chars().count();
+                if len >= width {
+                    return Ok(V::str(s));
+                }
+                let (sign, digits) = match s.chars().next() {
+                    Some(c @ ('+' | '-')) => (c.to_string(), &s[1..]),
+                    _ => (String::new(), s),
+                };
+                Ok(V::String(format!(
+                    "{}{}{}",
+                    sign,
+                    "0".repeat(width - len),
+                    digits
+                )))
+            }
+            "center" | "ljust" | "rjust" => {
+                arity(name, &args, 1, 2)?;
+                let width = int_arg(name, &args[0])?.max(0) as usize;
+                if width > MAX_ITEMS {
+                    return Err(PyErr::new("MemoryError", ""));
+                }
+                let fill = match args.get(1) {
+                    Some(f) => {
+                        let f = str_arg(name, f)?;
+                        let mut chars = f.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => c,
+                            _ => {
+                                return Err(PyErr::type_error(
+                                    "The fill character must be exactly one character long",
+                                ))
+                            }
+                        }
+                    }
+                    None => ' ',
+                };
+                let pad = width.saturating_sub(s.chars().count());
+                let (left, right) = match name {
+                    "ljust" => (0, pad),
+                    "rjust" => (pad, 0),
+                    _ => {
+                        let left = pad / 2 + (pad & width & 1);
+                        (left, pad - left)
+                    }
+                };
+                let fill = |n: usize| std::iter::repeat_n(fill, n).collect::<String>();
+                Ok(V::String(format!("{}{}{}", fill(left), s, fill(right))))
+            }
+            _ => Err(PyErr::new(
+                "AttributeError",
+                &format!("'str' object has no attribute '{}'", name),
+            )),
+        }
+    }
+
+    /// `str.format`: `{}`, `{0}`, `{name}` fields with `!r` and `:spec`
+    fn str_format(
+        &mut self,
+        fmt: &str,
+        args: &[PythonValue],
+        kwargs: &Kwargs,
+    ) -> Result<String, PyErr> {
+        let mut out = String::new();
+        let mut chars = fmt.chars().peekable();
+        let mut auto = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '}' => {
+                    return Err(PyErr::new(
+                        "ValueError",
+                        "Single '}' encountered in format string",
+                    ))
+                }
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(ch) => field.push(ch),
+                            None => {
+                                return Err(PyErr::new(
+                                    "ValueError",
+                                    "expected '}' before end of string",
+                                ))
+                            }
+                        }
+                    }
+                    let (field, spec) = field.split_once(':').unwrap_or((&field, ""));
+                    let (field, conv) = match field.split_once('!') {
+                        Some((f, c)) => (f, c.chars().next()),
+                        None => (field, None),
+                    };
+                    let value = if field.is_empty() {
+                        auto += 1;
+                        args.get(auto - 1).cloned().ok_or_else(|| {
+                            PyErr::new(
+                                "IndexError",
+                                &format!(
+                                    "Replacement index {} out of range for positional args tuple",
+                                    auto - 1
+                                ),
+                            )
+                        })?
+                    } else if let Ok(i) = field.parse::<usize>() {
+                        args.get(i).cloned().ok_or_else(|| {
+                            PyErr::new(
+                                "IndexError",
+                                &format!(
+                                    "Replacement index {} out of range for positional args tuple",
+                                    i
+                                ),
+                            )
+                        })?
+                    } else {
+                        kwargs
+                            .iter()
+                            .find(|(k, _)| k == field)
+                            .map(|(_, v)| v.clone())
+                            .ok_or_else(|| PyErr::new("KeyError", &super::value::str_repr(field)))?
+                    };
+                    let value = match conv {
+                        Some('r') => PythonValue::String(value.repr()),
+                        Some('s') => PythonValue::String(value.to_string()),
+                        _ => value,
+                    };
+                    out.push_str(&format_spec(&value, spec)?);
+                }
+                other => out.push(other),
+            }
+        }
+        Ok(out)
+    }
+
+    fn list_method(
+        &mut self,
+        obj: &PythonValue,
+        name: &'static str,
+        args: Vec<PythonValue>,
+        kwargs: Kwargs,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        let V::List(items) = obj else { unreachable!() };
+        if name != "sort" {
+            no_kwargs(name, &kwargs)?;
+        }
+        match name {
+            "append" => {
+                arity(name, &args, 1, 1)?;
+                items.borrow_mut().push(args[0].clone());
+                Ok(V::None)
+            }
+            "extend" => {
+                arity(name, &args, 1, 1)?;
+                let extra = self.iterate(&args[0])?;
+                items.borrow_mut().extend(extra);
+                Ok(V::None)
+            }
+            "insert" => {
+                arity(name, &args, 2, 2)?;
+                let len = items.borrow().len() as i64;
+                let i = int_arg(name, &args[0])?;
+                let i = if i < 0 { (i + len).max(0) } else { i.min(len) };
+                items.borrow_mut().insert(i as usize, args[1].clone());
+                Ok(V::None)
+            }
+            "pop" => {
+                arity(name, &args, 0, 1)?;
+                let len = items.borrow().len();
+                if len == 0 {
+                    return Err(PyErr::new("IndexError", "pop from empty list"));
+                }
+                let index = args.first().cloned().unwrap_or(V::Int(-1));
+                let i = self
+                    .seq_index(&index, len, "pop")
+                    .map_err(|_| PyErr::new("IndexError", "pop index out of range"))?;
+                Ok(items.borrow_mut().remove(i))
+            }
+            "remove" => {
+                arity(name, &args, 1, 1)?;
+                let pos = items.borrow().iter().position(|v| py_eq(v, &args[0]));
+                match pos {
+                    Some(i) => {
+                        items.borrow_mut().remove(i);
+                        Ok(V::None)
+                    }
+                    None => Err(PyErr::new("ValueError", "list.remove(x): x not in list")),
+                }
+            }
+            "index" => {
+                arity(name, &args, 1, 1)?;
+                let pos = items.borrow().iter().position(|v| py_eq(v, &args[0]));
+                pos.map(|i| V::Int(i as i64)).ok_or_else(|| {
+                    PyErr::new("ValueError", &format!("{} is not in list", args[0].repr()))
+                })
+            }
+            "count" => {
+                arity(name, &args, 1, 1)?;
+                let n = items.borrow().iter().filter(|v| py_eq(v, &args[0])).count();
+                Ok(V::Int(n as i64))
+            }
+            "sort" => {
+                arity(name, &args, 0, 0)?;
+                let [key, reverse] = take_kwargs(name, kwargs, ["key", "reverse"])?;
+                let mut sorted = items.borrow().clone();
+                self.sort_values(&mut sorted, key, reverse.is_some_and(|r| r.truthy()))?;
+                *items.borrow_mut() = sorted;
+                Ok(V::None)
+            }
+            "reverse" => {
+                items.borrow_mut().reverse();
+                Ok(V::None)
+            }
+            "copy" => Ok(V::list(items.borrow().clone())),
+            "clear" => {
+                items.borrow_mut().clear();
+                Ok(V::None)
+            }
+            _ => Err(PyErr::new(
+                "AttributeError",
+                &format!("'list' object has no attribute '{}'", name),
+            )),
+        }
+    }
+
+    fn dict_method(
+        &mut self,
+        obj: &PythonValue,
+        name: &'static str,
+        args: Vec<PythonValue>,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        let V::Dict(items) = obj else { unreachable!() };
+        match name {
+            "keys" => Ok(V::list(
+                items.borrow().iter().map(|(k, _)| k.clone()).collect(),
+            )),
+            "values" => Ok(V::list(
+                items.borrow().iter().map(|(_, v)| v.clone()).collect(),
+            )),
+            "items" => Ok(V::list(
+                items
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| V::tuple(vec![k.clone(), v.clone()]))
+                    .collect(),
+            )),
+            "get" => {
+                arity(name, &args, 1, 2)?;
+                check_hashable(&args[0])?;
+                Ok(dict_get(&items.borrow(), &args[0])
+                    .unwrap_or_else(|| args.get(1).cloned().unwrap_or(V::None)))
+            }
+            "pop" => {
+                arity(name, &args, 1, 2)?;
+                check_hashable(&args[0])?;
+                let pos = items.borrow().iter().position(|(k, _)| py_eq(k, &args[0]));
+                match (pos, args.get(1)) {
+                    (Some(i), _) => Ok(items.borrow_mut().remove(i).1),
+                    (None, Some(default)) => Ok(default.clone()),
+                    (None, None) => Err(PyErr::new("KeyError", &args[0].repr())),
+                }
+            }
+            "setdefault" => {
+                arity(name, &args, 1, 2)?;
+                check_hashable(&args[0])?;
+                if let Some(v) = dict_get(&items.borrow(), &args[0]) {
+                    return Ok(v);
+                }
+                let value = args.get(1).cloned().unwrap_or(V::None);
+                items.borrow_mut().push((args[0].clone(), value.clone()));
+                Ok(value)
+            }
+            "update" => {
+                arity(name, &args, 1, 1)?;
+                let mut updated = items.borrow().clone();
+                self.dict_update(&mut updated, &args[0])?;
+                *items.borrow_mut() = updated;
+                Ok(V::None)
+            }
+            "copy" => Ok(V::dict(items.borrow().clone())),
+            "clear" => {
+                items.borrow_mut().clear();
+                Ok(V::None)
+            }
+            _ => Err(PyErr::new(
+                "AttributeError",
+                &format!("'dict' object has no attribute '{}'", name),
+            )),
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use super::PyErr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Tok {
+    Name(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// Body of an f-string with escapes already processed
+    FStr(String),
+    Op(&'static str),
+    Newline,
+    Indent,
+    Dedent,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Token {
+    pub tok: Tok,
+    pub line: usize,
+}
+
+// Longest operators first so `**=` is not read as `**` then `=`
+const OPERATORS: &[&str] = &[
+    "**=", "//=", ">>=", "<<=", "**", "//", "==", "!=", "<=", ">=", "+=", "-=", "*=", "/=", "%=",
+    "&=", "|=", "^=", "->", "<<", ">>", "+", "-", "*", "/", "%", "<", ">", "=", "(", ")", "[", "]",
+    "{", "}", ",", ":", ".", ";", "@", "&", "|", "^", "~",
+];
+
+/// Whether a lexing error only means the source stops early, so the REPL
+/// should keep reading lines
+pub(super) fn is_incomplete(err: &PyErr) -> bool {
+    err.kind == "SyntaxError" && err.msg.starts_with("unexpected EOF")
+}
+
+fn unescape(raw: &str, line: usize) -> Result<String, PyErr> {
+    let mut out = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some('\\') => out.push('\\'),
+            Some('\'') => out.push('\''),
+            Some('"') => out.push('"'),
+            Some('\n') => {}
+            Some('x') => {
+                let hex: String = (0..2).filter_map(|_| chars.next()).collect();
+                let code = u32::from_str_radix(&hex, 16)
+                    .map_err(|_| PyErr::syntax("truncated \\xXX escape", line))?;
+                out.extend(char::from_u32(code));
+            }
+            Some('u') => {
+                let hex: String = (0..4).filter_map(|_| chars.next()).collect();
+                let code = u32::from_str_radix(&hex, 16)
+                    .map_err(|_| PyErr::syntax("truncated \\uXXXX escape", line))?;
+                out.extend(char::from_u32(code));
+            }
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    Ok(out)
+}
+
+pub(super) fn tokenize(src: &str) -> Result<Vec<Token>, PyErr> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut indents = vec![0usize];
+    let mut depth = 0usize;
+    let mut line = 1;
+    let mut i = 0;
+    let mut at_line_start = true;
+
+    while i < chars.len() {
+        if at_line_start && depth == 0 {
+            let mut width = 0;
+            while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+                width += if chars[i] == '\t' { 8 - width % 8 } else { 1 };
+                i += 1;
+            }
+            // Blank and comment-only lines do not affect indentation
+            if i >= chars.len() || chars[i] == '\n' || chars[i] == '#' || chars[i] == '\r' {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                i += 1;
+                line += 1;
+                continue;
+            }
+            at_line_start = false;
+            let current = *indents.last().unwrap_or(&0);
+            if width > current {
+                indents.push(width);
+                tokens.push(Token {
+                    tok: Tok::Indent,
+                    line,
+                });
+            } else {
+                while width < *indents.last().unwrap_or(&0) {
+                    indents.pop();
+                    tokens.push(Token {
+                        tok: Tok::Dedent,
+                        line,
+                    });
+                }
+                if width != *indents.last().unwrap_or(&0) {
+                    return Err(PyErr::new(
+                        "IndentationError",
+                        "unindent does not match any outer indentation level",
+                    )
+                    .at(line));
+                }
+            }
+        }
+
+        let c = chars[i];
+        match c {
+            '\n' => {
+                // Newlines inside brackets only continue the logical line
+                if depth == 0 {
+                    if !at_line_start {
+                        tokens.push(Token {
+                            tok: Tok::Newline,
+                            line,
+                        });
+                    }
+                    at_line_start = true;
+                }
+                line += 1;
+                i += 1;
+            }
+            ' ' | '\t' | '\r' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                i += 2;
+                line += 1;
+            }
+            _ if c.is_ascii_digit()
+                || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) =>
+            {
+                let start = i;
+                if c == '0' && matches!(chars.get(i + 1), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B')) {
+                    let radix = match chars[i + 1].to_ascii_lowercase() {
+                        'x' => 16,
+                        'o' => 8,
+                        _ => 2,
+                    };
+                    i += 2;
+                    while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    let digits: String =
+                        chars[start + 2..i].iter().filter(|c| **c != '_').collect();
+                    let value = i64::from_str_radix(&digits, radix)
+                        .map_err(|_| PyErr::syntax("invalid number literal", line))?;
+                    tokens.push(Token {
+                        tok: Tok::Int(value),
+                        line,
+                    });
+                    continue;
+                }
+                let mut is_float = false;
+                while i < chars.len() {
+                    let ch = chars[i];
+                    if ch.is_ascii_digit() || ch == '_' {
+                        i += 1;
+                    } else if ch == '.' && !is_float {
+                        is_float = true;
+                        i += 1;
+                    } else if (ch == 'e' || ch == 'E')
+                        && chars
+                            .get(i + 1)
+                            .is_some_and(|n| n.is_ascii_digit() || *n == '-' || *n == '+')
+                    {
+                        is_float = true;
+                        i += 2;
+                    } else {
+                        break;
+                    }
+                }
+                let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+                let tok = if is_float {
+                    Tok::Float(
+                        text.parse()
+                            .map_err(|_| PyErr::syntax("invalid decimal literal", line))?,
+                    )
+                } else {
+                    Tok::Int(text.parse().map_err(|_| {
+                        PyErr::new("OverflowError", "integer literal is too large").at(line)
+                    })?)
+                };
+                tokens.push(Token { tok, line });
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let lower = word.to_ascii_lowercase();
+                let is_prefix =
+                    matches!(lower.as_str(), "f" | "r" | "b" | "rf" | "fr" | "rb" | "br");
+                if is_prefix && matches!(chars.get(i), Some('"' | '\'')) {
+                    let (body, next, lines) = read_string(&chars, i, line)?;
+                    let raw = lower.contains('r');
+                    let text = if raw { body } else { unescape(&body, line)? };
+                    let tok = if lower.contains('f') {
+                        Tok::FStr(text)
+                    } else {
+                        Tok::Str(text)
+                    };
+                    tokens.push(Token { tok, line });
+                    line += lines;
+                    i = next;
+                } else {
+                    tokens.push(Token {
+                        tok: Tok::Name(word),
+                        line,
+                    });
+                }
+            }
+            '"' | '\'' => {
+                let (body, next, lines) = read_string(&chars, i, line)?;
+                tokens.push(Token {
+                    tok: Tok::Str(unescape(&body, line)?),
+                    line,
+                });
+                line += lines;
+                i = next;
+            }
+            _ => {
+                let rest: String = chars[i..chars.len().min(i + 3)].iter().collect();
+                let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                    return Err(PyErr::syntax(&format!("invalid character '{}'", c), line));
+                };
+                match *op {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                tokens.push(Token {
+                    tok: Tok::Op(op),
+                    line,
+                });
+                i += op.len();
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err(PyErr::syntax("unexpected EOF while parsing", line));
+    }
+    if !at_line_start {
+        tokens.push(Token {
+            tok: Tok::Newline,
+            line,
+        });
+    }
+    while indents.len() > 1 {
+        indents.pop();
+        tokens.push(Token {
+            tok: Tok::Dedent,
+            line,
+        });
+    }
+    tokens.push(Token {
+        tok: Tok::Eof,
+        line,
+    });
+    Ok(tokens)
+}
+
+/// Read a quoted string starting at `start`; returns the raw body, the index
+/// after the closing quote and the number of newlines consumed
+fn read_string(chars: &[char], start: usize, line: usize) -> Result<(String, usize, usize), PyErr> {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = start + if triple { 3 } else { 1 };
+    let mut body = String::new();
+    let mut lines = 0;
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err(PyErr::syntax(
+                if triple {
+                    "unexpected EOF in triple-quoted string"
+                } else {
+                    "unterminated string literal"
+                },
+                line,
+            ));
+        };
+        if c == '\\' {
+            body.push(c);
+            if let Some(&next) = chars.get(i + 1) {
+                body.push(next);
+                if next == '\n' {
+                    lines += 1;
+                }
+            }
+            i += 2;
+            continue;
+        }
+        if c == quote {
+            if !triple {
+                return Ok((body, i + 1, lines));
+            }
+            if chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote) {
+                return Ok((body, i + 3, lines));
+            }
+        }
+        if c == '\n' {
+            if !triple {
+                return Err(PyErr::syntax("unterminated string literal", line));
+            }
+            lines += 1;
+        }
+        body.push(c);
+        i += 1;
+    }
+}
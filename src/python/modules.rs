@@ -0,0 +1,1025 @@
+use super::value::{float_repr, str_repr, PyFile};
+use super::{PyErr, PythonInterpreter, PythonValue};
+use crate::vfs::Vfs;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub(super) const MODULES: &[&str] = &["math", "random", "json", "os", "os.path"];
+
+const FUNCTIONS: &[&str] = &[
+    "math.sqrt",
+    "math.sin",
+    "math.cos",
+    "math.tan",
+    "math.asin",
+    "math.acos",
+    "math.atan",
+    "math.atan2",
+    "math.hypot",
+    "math.floor",
+    "math.ceil",
+    "math.trunc",
+    "math.fabs",
+    "math.exp",
+    "math.log",
+    "math.log2",
+    "math.log10",
+    "math.pow",
+    "math.radians",
+    "math.degrees",
+    "math.factorial",
+    "math.gcd",
+    "math.isclose",
+    "math.isnan",
+    "math.isinf",
+    "random.random",
+    "random.randint",
+    "random.randrange",
+    "random.uniform",
+    "random.choice",
+    "random.shuffle",
+    "random.sample",
+    "random.seed",
+    "json.dump",
+    "json.dumps",
+    "json.load",
+    "json.loads",
+    "os.getcwd",
+    "os.listdir",
+    "os.mkdir",
+    "os.remove",
+    "os.path.exists",
+    "os.path.isfile",
+    "os.path.isdir",
+    "os.path.join",
+    "os.path.basename",
+    "os.path.dirname",
+    "os.path.abspath",
+];
+
+const FILE_METHODS: &[&str] = &[
+    "read",
+    "readline",
+    "readlines",
+    "write",
+    "writelines",
+    "close",
+];
+
+/// The filesystem lent to the interpreter for one run, and who is using it
+pub(super) struct FsAccess {
+    pub vfs: Vfs,
+    pub user: String,
+    pub groups: Vec<String>,
+}
+
+fn os_error(kind: &str, errno: u8, text: &str, path: &str) -> PyErr {
+    PyErr::new(
+        kind,
+        &format!("[Errno {}] {}: {}", errno, text, str_repr(path)),
+    )
+}
+
+fn float_args(name: &str, args: &[PythonValue], count: usize) -> Result<Vec<f64>, PyErr> {
+    if args.len() != count {
+        return Err(PyErr::type_error(&format!(
+            "{}() takes exactly {} argument{} ({} given)",
+            name,
+            count,
+            if count == 1 { "" } else { "s" },
+            args.len()
+        )));
+    }
+    args.iter()
+        .map(|a| {
+            a.as_float().ok_or_else(|| {
+                PyErr::type_error(&format!("must be real number, not {}", a.type_name()))
+            })
+        })
+        .collect()
+}
+
+fn int_of(name: &str, v: Option<&PythonValue>) -> Result<i64, PyErr> {
+    match v {
+        Some(v) => v.as_int().ok_or_else(|| {
+            PyErr::type_error(&format!(
+                "'{}' object cannot be interpreted as an integer",
+                v.type_name()
+            ))
+        }),
+        None => Err(PyErr::type_error(&format!(
+            "{}() missing required argument",
+            name
+        ))),
+    }
+}
+
+fn domain_checked(x: f64) -> Result<PythonValue, PyErr> {
+    if x.is_nan() {
+        return Err(PyErr::new("ValueError", "math domain error"));
+    }
+    Ok(PythonValue::Float(x))
+}
+
+fn float_to_int(x: f64) -> Result<PythonValue, PyErr> {
+    if !x.is_finite() {
+        return Err(PyErr::new(
+            if x.is_nan() {
+                "ValueError"
+            } else {
+                "OverflowError"
+            },
+            "cannot convert float to integer",
+        ));
+    }
+    Ok(PythonValue::Int(x as i64))
+}
+
+/// JSON string literal, escaping non-ASCII like Python's default `ensure_ascii`
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_ascii() && !c.is_ascii_control() => out.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_dump(
+    v: &PythonValue,
+    indent: Option<&str>,
+    sort_keys: bool,
+    level: usize,
+    out: &mut String,
+) -> Result<(), PyErr> {
+    use PythonValue as V;
+    let newline = |out: &mut String, level: usize| {
+        if let Some(ind) = indent {
+            out.push('\n');
+            out.push_str(&ind.repeat(level));
+        }
+    };
+    let item_sep = if indent.is_some() { "," } else { ", " };
+    match v {
+        V::None => out.push_str("null"),
+        V::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        V::Int(i) => out.push_str(&i.to_string()),
+        V::Float(f) if f.is_nan() => out.push_str("NaN"),
+        V::Float(f) if f.is_infinite() => {
+            out.push_str(if *f > 0.0 { "Infinity" } else { "-Infinity" })
+        }
+        V::Float(f) => out.push_str(&float_repr(*f)),
+        V::String(s) => out.push_str(&json_string(s)),
+        V::List(_) | V::Tuple(_) => {
+            let items = match v {
+                V::List(items) => items.borrow().clone(),
+                V::Tuple(items) => items.as_ref().clone(),
+                _ => unreachable!(),
+            };
+            if items.is_empty() {
+                out.push_str("[]");
+                return Ok(());
+            }
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(item_sep);
+                }
+                newline(out, level + 1);
+                json_dump(item, indent, sort_keys, level + 1, out)?;
+            }
+            newline(out, level);
+            out.push(']');
+        }
+        V::Dict(items) => {
+            let mut pairs = Vec::new();
+            for (k, value) in items.borrow().iter() {
+                let key = match k {
+                    V::String(s) => s.clone(),
+                    V::None => "null".into(),
+                    V::Bool(b) => if *b { "true" } else { "false" }.into(),
+                    V::Int(_) | V::Float(_) => {
+                        let mut key = String::new();
+                        json_dump(k, None, false, 0, &mut key)?;
+                        key
+                    }
+                    other => {
+                        return Err(PyErr::type_error(&format!(
+                            "keys must be str, int, float, bool or None, not {}",
+                            other.type_name()
+                        )))
+                    }
+                };
+                pairs.push((key, value.clone()));
+            }
+            if sort_keys {
+                pairs.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            if pairs.is_empty() {
+                out.push_str("{}");
+                return Ok(());
+            }
+            out.push('{');
+            for (i, (key, value)) in pairs.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(item_sep);
+                }
+                newline(out, level + 1);
+                out.push_str(&json_string(key));
+                out.push_str(": ");
+                json_dump(value, indent, sort_keys, level + 1, out)?;
+            }
+            newline(out, level);
+            out.push('}');
+        }
+        other => {
+            return Err(PyErr::type_error(&format!(
+                "Object of type {} is not JSON serializable",
+                other.type_name()
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn from_json(v: serde_json::Value) -> PythonValue {
+    use serde_json::Value as J;
+    match v {
+        J::Null => PythonValue::None,
+        J::Bool(b) => PythonValue::Bool(b),
+        J::Number(n) => match n.as_i64() {
+            Some(i) => PythonValue::Int(i),
+            None => PythonValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        J::String(s) => PythonValue::String(s),
+        J::Array(items) => PythonValue::list(items.into_iter().map(from_json).collect()),
+        J::Object(map) => PythonValue::dict(
+            map.into_iter()
+                .map(|(k, v)| (PythonValue::String(k), from_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// serde_json reports "expected value at line 1 column 1"; Python says
+/// "Expecting value: line 1 column 1"
+fn json_decode_error(err: serde_json::Error) -> PyErr {
+    let text = err.to_string();
+    let reason = text.split(" at line ").next().unwrap_or(&text);
+    let reason = reason.strip_prefix("expected ").map_or_else(
+        || {
+            let mut chars = reason.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        },
+        |rest| format!("Expecting {}", rest),
+    );
+    PyErr::new(
+        "json.decoder.JSONDecodeError",
+        &format!("{}: line {} column {}", reason, err.line(), err.column()),
+    )
+}
+
+impl PythonInterpreter {
+    /// Resolve an `import`; `import os.path` also makes `os` available
+    pub(super) fn import_module(&self, name: &str) -> Result<PythonValue, PyErr> {
+        match MODULES.iter().find(|m| **m == name) {
+            Some(m) => Ok(PythonValue::Module(m)),
+            None => Err(PyErr::new(
+                "ModuleNotFoundError",
+                &format!("No module named {}", str_repr(name)),
+            )),
+        }
+    }
+
+    pub(super) fn module_attr(&self, module: &str, attr: &str) -> Option<PythonValue> {
+        use std::f64::consts;
+        let constant = match (module, attr) {
+            ("math", "pi") => Some(PythonValue::Float(consts::PI)),
+            ("math", "e") => Some(PythonValue::Float(consts::E)),
+            ("math", "tau") => Some(PythonValue::Float(consts::TAU)),
+            ("math", "inf") => Some(PythonValue::Float(f64::INFINITY)),
+            ("math", "nan") => Some(PythonValue::Float(f64::NAN)),
+            ("os", "sep") | ("os.path", "sep") => Some(PythonValue::str("/")),
+            ("os", "linesep") => Some(PythonValue::str("\n")),
+            ("os", "name") => Some(PythonValue::str("posix")),
+            ("os", "path") => Some(PythonValue::Module("os.path")),
+            _ => None,
+        };
+        constant.or_else(|| {
+            let full = format!("{}.{}", module, attr);
+            FUNCTIONS
+                .iter()
+                .find(|f| **f == full)
+                .map(|f| PythonValue::Builtin(f))
+        })
+    }
+
+    /// Names bound by `from module import *`
+    pub(super) fn module_exports(&self, module: &str) -> Vec<(String, PythonValue)> {
+        let prefix = format!("{}.", module);
+        let mut names: Vec<&str> = FUNCTIONS
+            .iter()
+            .filter_map(|f| f.strip_prefix(&prefix))
+            .filter(|f| !f.contains('.'))
+            .collect();
+        names.extend(match module {
+            "math" => &["pi", "e", "tau", "inf", "nan"][..],
+            "os" => &["sep", "linesep", "name", "path"][..],
+            "os.path" => &["sep"][..],
+            _ => &[][..],
+        });
+        names
+            .into_iter()
+            .filter_map(|n| self.module_attr(module, n).map(|v| (n.to_string(), v)))
+            .collect()
+    }
+
+    pub(super) fn call_module_fn(
+        &mut self,
+        name: &'static str,
+        args: Vec<PythonValue>,
+        kwargs: Vec<(String, PythonValue)>,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        let (module, func) = name.rsplit_once('.').unwrap_or(("", name));
+        if !matches!(name, "json.dump" | "json.dumps" | "math.log") {
+            if let Some((key, _)) = kwargs.first() {
+                return Err(PyErr::type_error(&format!(
+                    "{}() got an unexpected keyword argument '{}'",
+                    func, key
+                )));
+            }
+        }
+        match module {
+            "math" => self.call_math(func, &args),
+            "random" => self.call_random(func, args),
+            "json" => match func {
+                // The file forms go through the file object's own read/write
+                "dump" | "load" => {
+                    let file = match args.get(if func == "dump" { 1 } else { 0 }) {
+                        Some(V::File(file)) => file.clone(),
+                        _ => {
+                            return Err(PyErr::type_error(&format!(
+                                "{}() needs a file object opened with open()",
+                                func
+                            )))
+                        }
+                    };
+                    if func == "load" {
+                        let text = self.file_method(&file, "read", Vec::new())?;
+                        return self.call_module_fn("json.loads", vec![text], Vec::new());
+                    }
+                    let text = self.call_module_fn("json.dumps", args[..1].to_vec(), kwargs)?;
+                    self.file_method(&file, "write", vec![text])?;
+                    Ok(V::None)
+                }
+                "dumps" => {
+                    let mut indent = None;
+                    let mut sort_keys = false;
+                    for (key, value) in kwargs {
+                        match (key.as_str(), value) {
+                            ("indent", V::None) => {}
+                            ("indent", V::String(s)) => indent = Some(s),
+                            ("indent", v) if v.as_int().is_some() => {
+                                indent = Some(" ".repeat(v.as_int().unwrap_or(0).max(0) as usize))
+                            }
+                            ("sort_keys", v) => sort_keys = v.truthy(),
+                            (key, _) => {
+                                return Err(PyErr::type_error(&format!(
+                                    "dumps() got an unexpected keyword argument '{}'",
+                                    key
+                                )))
+                            }
+                        }
+                    }
+                    let [obj] = args.as_slice() else {
+                        return Err(PyErr::type_error("dumps() takes 1 positional argument"));
+                    };
+                    let mut out = String::new();
+                    json_dump(obj, indent.as_deref(), sort_keys, 0, &mut out)?;
+                    Ok(V::String(out))
+                }
+                _ => match args.as_slice() {
+                    [V::String(s)] => serde_json::from_str(s)
+                        .map(from_json)
+                        .map_err(json_decode_error),
+                    [other] => Err(PyErr::type_error(&format!(
+                        "the JSON object must be str, not {}",
+                        other.type_name()
+                    ))),
+                    _ => Err(PyErr::type_error("loads() takes 1 positional argument")),
+                },
+            },
+            _ => self.call_os(name, &args),
+        }
+    }
+
+    fn call_math(&mut self, func: &str, args: &[PythonValue]) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        let unary = |f: fn(f64) -> f64| -> Result<PythonValue, PyErr> {
+            domain_checked(f(float_args(func, args, 1)?[0]))
+        };
+        match func {
+            "sqrt" => unary(f64::sqrt),
+            "sin" => unary(f64::sin),
+            "cos" => unary(f64::cos),
+            "tan" => unary(f64::tan),
+            "asin" => unary(f64::asin),
+            "acos" => unary(f64::acos),
+            "atan" => unary(f64::atan),
+            "exp" => unary(f64::exp),
+            "fabs" => unary(f64::abs),
+            "radians" => unary(f64::to_radians),
+            "degrees" => unary(f64::to_degrees),
+            "log2" | "log10" => {
+                let x = float_args(func, args, 1)?[0];
+                if x <= 0.0 {
+                    return Err(PyErr::new("ValueError", "math domain error"));
+                }
+                Ok(V::Float(if func == "log2" { x.log2() } else { x.log10() }))
+            }
+            "log" => {
+                let xs = float_args(func, args, args.len().clamp(1, 2))?;
+                if xs.iter().any(|x| *x <= 0.0) {
+                    return Err(PyErr::new("ValueError", "math domain error"));
+                }
+                let base = xs.get(1).copied().unwrap_or(std::f64::consts::E);
+                Ok(V::Float(xs[0].ln() / base.ln()))
+            }
+            "atan2" => {
+                let xs = float_args(func, args, 2)?;
+                Ok(V::Float(xs[0].atan2(xs[1])))
+            }
+            "pow" => {
+                let xs = float_args(func, args, 2)?;
+                domain_checked(xs[0].powf(xs[1]))
+            }
+            "hypot" => {
+                let xs = float_args(func, args, args.len())?;
+                Ok(V::Float(xs.iter().map(|x| x * x).sum::<f64>().sqrt()))
+            }
+            "floor" | "ceil" | "trunc" => {
+                if let [v] = args {
+                    if let Some(i) = v.as_int() {
+                        return Ok(V::Int(i));
+                    }
+                }
+                let x = float_args(func, args, 1)?[0];
+                float_to_int(match func {
+                    "floor" => x.floor(),
+                    "ceil" => x.ceil(),
+                    _ => x.trunc(),
+                })
+            }
+            "isnan" => Ok(V::Bool(float_args(func, args, 1)?[0].is_nan())),
+            "isinf" => Ok(V::Bool(float_args(func, args, 1)?[0].is_infinite())),
+            "isclose" => {
+                let xs = float_args(func, args, 2)?;
+                let tolerance = 1e-9 * xs[0].abs().max(xs[1].abs());
+                Ok(V::Bool(
+                    xs[0] == xs[1] || (xs[0] - xs[1]).abs() <= tolerance,
+                ))
+            }
+            "factorial" => {
+                let n = int_of(func, args.first())?;
+                if n < 0 {
+                    return Err(PyErr::new(
+                        "ValueError",
+                        "factorial() not defined for negative values",
+                    ));
+                }
+                (1..=n)
+                    .try_fold(1i64, |acc, k| acc.checked_mul(k))
+                    .map(V::Int)
+                    .ok_or_else(|| PyErr::new("OverflowError", "integer overflow"))
+            }
+            _ => {
+                // Unsigned, so gcd(-2**63, 6) works; only a result of
+                // 2**63 itself does not fit back in an int
+                let mut g = 0u64;
+                for v in args {
+                    let mut b = int_of(func, Some(v))?.unsigned_abs();
+                    while b != 0 {
+                        (g, b) = (b, g % b);
+                    }
+                }
+                i64::try_from(g)
+                    .map(V::Int)
+                    .map_err(|_| PyErr::new("OverflowError", "integer overflow"))
+            }
+        }
+    }
+
+    /// splitmix64, seeded from the browser's `Math.random` until `random.seed`
+    fn next_random(&mut self) -> u64 {
+        let state = self
+            .rng
+            .get_or_insert_with(|| (js_sys::Math::random() * (1u64 << 53) as f64) as u64);
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn random_float(&mut self) -> f64 {
+        (self.next_random() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn random_below(&mut self, n: u64) -> u64 {
+        self.next_random() % n
+    }
+
+    fn call_random(&mut self, func: &str, args: Vec<PythonValue>) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        match func {
+            "random" => Ok(V::Float(self.random_float())),
+            "uniform" => {
+                let xs = float_args(func, &args, 2)?;
+                Ok(V::Float(xs[0] + (xs[1] - xs[0]) * self.random_float()))
+            }
+            "seed" => {
+                self.rng = match args.first() {
+                    None | Some(V::None) => None,
+                    Some(V::String(s)) => Some(s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+                        (h ^ b as u64).wrapping_mul(0x100_0000_01b3)
+                    })),
+                    Some(v) => match v.as_int() {
+                        Some(i) => Some(i as u64),
+                        None => Some(v.as_float().map_or(0, f64::to_bits)),
+                    },
+                };
+                Ok(V::None)
+            }
+            "randint" | "randrange" => {
+                let nums = args
+                    .iter()
+                    .map(|a| int_of(func, Some(a)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (start, stop, step) = match (func, nums.as_slice()) {
+                    ("randint", [a, b]) => (*a, b.saturating_add(1), 1),
+                    ("randrange", [stop]) => (0, *stop, 1),
+                    ("randrange", [start, stop]) => (*start, *stop, 1),
+                    ("randrange", [start, stop, step]) if *step != 0 => (*start, *stop, *step),
+                    ("randrange", [_, _, _]) => {
+                        return Err(PyErr::new("ValueError", "zero step for randrange()"))
+                    }
+                    _ => {
+                        return Err(PyErr::type_error(&format!(
+                            "{}() takes {} positional arguments but {} were given",
+                            func,
+                            if func == "randint" { "2" } else { "1 to 3" },
+                            args.len()
+                        )))
+                    }
+                };
+                let count = super::value::range_len(start, stop, step);
+                if count == 0 {
+                    return Err(PyErr::new(
+                        "ValueError",
+                        &format!(
+                            "empty range for randrange() ({}, {}, {})",
+                            start, stop, step
+                        ),
+                    ));
+                }
+                let pick = self.random_below(count as u64) as i64;
+                Ok(V::Int(start + pick * step))
+            }
+            "choice" => {
+                let [seq] = args.as_slice() else {
+                    return Err(PyErr::type_error("choice() takes exactly one argument"));
+                };
+                let items = self.iterate(seq)?;
+                if items.is_empty() {
+                    return Err(PyErr::new(
+                        "IndexError",
+                        "Cannot choose from an empty sequence",
+                    ));
+                }
+                let i = self.random_below(items.len() as u64) as usize;
+                Ok(items[i].clone())
+            }
+            "shuffle" => {
+                let [V::List(items)] = args.as_slice() else {
+                    return Err(PyErr::type_error("shuffle() argument must be a list"));
+                };
+                let len = items.borrow().len();
+                for i in (1..len).rev() {
+                    let j = self.random_below(i as u64 + 1) as usize;
+                    items.borrow_mut().swap(i, j);
+                }
+                Ok(V::None)
+            }
+            _ => {
+                let (Some(population), Some(k)) = (args.first(), args.get(1)) else {
+                    return Err(PyErr::type_error("sample() missing required argument: 'k'"));
+                };
+                let mut pool = self.iterate(population)?;
+                let k = int_of(func, Some(k))?;
+                if k < 0 || k as usize > pool.len() {
+                    return Err(PyErr::new(
+                        "ValueError",
+                        "Sample larger than population or is negative",
+                    ));
+                }
+                let mut picked = Vec::with_capacity(k as usize);
+                for _ in 0..k {
+                    let i = self.random_below(pool.len() as u64) as usize;
+                    picked.push(pool.swap_remove(i));
+                }
+                Ok(V::list(picked))
+            }
+        }
+    }
+
+    fn fs(&mut self) -> Result<&mut FsAccess, PyErr> {
+        self.fs
+            .as_mut()
+            .ok_or_else(|| PyErr::new("OSError", "[Errno 38] Filesystem not available"))
+    }
+
+    /// Permission check mirroring the shell's: only the node itself is tested
+    fn fs_allows(&mut self, path: &str, want: u8) -> Result<bool, PyErr> {
+        let fs = self.fs()?;
+        Ok(match fs.vfs.resolve(path) {
+            Some(node) => node.allows(&fs.user, &fs.groups, want),
+            None => true,
+        })
+    }
+
+    /// Write permission on the directory a new entry would be created in
+    fn fs_can_create(&mut self, path: &str) -> Result<bool, PyErr> {
+        let norm = self.fs()?.vfs.normalize(path);
+        let parent = match norm.rfind('/') {
+            Some(0) | None => "/".to_string(),
+            Some(idx) => norm[..idx].to_string(),
+        };
+        self.fs_allows(&parent, 3)
+    }
+
+    fn call_os(&mut self, name: &str, args: &[PythonValue]) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        let func = name.rsplit('.').next().unwrap_or(name);
+        let mut paths = Vec::with_capacity(args.len());
+        for a in args {
+            match a {
+                V::String(s) => paths.push(s.clone()),
+                other => {
+                    return Err(PyErr::type_error(&format!(
+                        "{}: path should be string, not {}",
+                        func,
+                        other.type_name()
+                    )))
+                }
+            }
+        }
+        let path = paths.first().cloned().unwrap_or_else(|| ".".into());
+        match name {
+            "os.path.join" => {
+                let mut joined = String::new();
+                for p in &paths {
+                    if p.starts_with('/') {
+                        joined.clear();
+                    } else if !joined.is_empty() && !joined.ends_with('/') {
+                        joined.push('/');
+                    }
+                    joined.push_str(p);
+                }
+                return Ok(V::String(joined));
+            }
+            "os.path.basename" => {
+                return Ok(V::str(path.rsplit('/').next().unwrap_or("")));
+            }
+            "os.path.dirname" => {
+                return Ok(V::str(match path.rfind('/') {
+                    Some(0) => "/",
+                    Some(i) => &path[..i],
+                    None => "",
+                }));
+            }
+            _ => {}
+        }
+
+        let fs = self.fs()?;
+        match name {
+            "os.getcwd" => Ok(V::String(fs.vfs.cwd.clone())),
+            "os.path.abspath" => Ok(V::String(fs.vfs.normalize(&path))),
+            "os.path.exists" => Ok(V::Bool(fs.vfs.resolve(&path).is_some())),
+            "os.path.isfile" => Ok(V::Bool(fs.vfs.resolve(&path).is_some_and(|n| !n.is_dir))),
+            "os.path.isdir" => Ok(V::Bool(fs.vfs.resolve(&path).is_some_and(|n| n.is_dir))),
+            "os.listdir" => {
+                let mut names = match fs.vfs.resolve(&path) {
+                    Some(node) if node.is_dir => node.children.keys().cloned().collect::<Vec<_>>(),
+                    Some(_) => {
+                        return Err(os_error("NotADirectoryError", 20, "Not a directory", &path))
+                    }
+                    None => {
+                        return Err(os_error(
+                            "FileNotFoundError",
+                            2,
+                            "No such file or directory",
+                            &path,
+                        ))
+                    }
+                };
+                if !self.fs_allows(&path, 4)? {
+                    return Err(os_error("PermissionError", 13, "Permission denied", &path));
+                }
+                names.sort();
+                Ok(V::list(names.into_iter().map(V::String).collect()))
+            }
+            "os.mkdir" => {
+                if fs.vfs.resolve(&path).is_some() {
+                    return Err(os_error("FileExistsError", 17, "File exists", &path));
+                }
+                if !self.fs_can_create(&path)? {
+                    return Err(os_error("PermissionError", 13, "Permission denied", &path));
+                }
+                let fs = self.fs()?;
+                fs.vfs
+                    .create_dir(&path)
+                    .map(|_| V::None)
+                    .map_err(|e| Self::vfs_error(e, &path))
+            }
+            _ => {
+                match fs.vfs.resolve(&path) {
+                    Some(node) if node.is_dir => {
+                        return Err(os_error("IsADirectoryError", 21, "Is a directory", &path))
+                    }
+                    Some(_) => {}
+                    None => {
+                        return Err(os_error(
+                            "FileNotFoundError",
+                            2,
+                            "No such file or directory",
+                            &path,
+                        ))
+                    }
+                }
+                if !self.fs_can_create(&path)? {
+                    return Err(os_error("PermissionError", 13, "Permission denied", &path));
+                }
+                self.fs()?
+                    .vfs
+                    .remove(&path)
+                    .map(|_| V::None)
+                    .map_err(|e| PyErr::new("OSError", &format!("{}: {}", e, str_repr(&path))))
+            }
+        }
+    }
+
+    fn vfs_error(err: &str, path: &str) -> PyErr {
+        match err {
+            "read-only file system" => os_error("OSError", 30, "Read-only file system", path),
+            "is a directory" => os_error("IsADirectoryError", 21, "Is a directory", path),
+            "parent is not a directory" => {
+                os_error("NotADirectoryError", 20, "Not a directory", path)
+            }
+            _ => os_error("FileNotFoundError", 2, "No such file or directory", path),
+        }
+    }
+
+    /// The `open()` builtin: modes r, w, a and x (a `b`/`t` suffix is ignored)
+    pub(super) fn open_file(
+        &mut self,
+        args: Vec<PythonValue>,
+        kwargs: Vec<(String, PythonValue)>,
+    ) -> Result<PythonValue, PyErr> {
+        let mut args = args.into_iter();
+        let path = args.next();
+        let mut mode = args.next();
+        for (key, value) in kwargs {
+            match key.as_str() {
+                "mode" => mode = Some(value),
+                "encoding" | "newline" | "errors" => {}
+                _ => {
+                    return Err(PyErr::type_error(&format!(
+                        "open() got an unexpected keyword argument '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+        let Some(PythonValue::String(path)) = path else {
+            return Err(PyErr::type_error("open() argument 'file' must be str"));
+        };
+        let mode = match mode {
+            None => "r".to_string(),
+            Some(PythonValue::String(m)) => m,
+            Some(other) => {
+                return Err(PyErr::type_error(&format!(
+                    "open() argument 'mode' must be str, not {}",
+                    other.type_name()
+                )))
+            }
+        };
+        let base: String = mode.chars().filter(|c| !matches!(c, 'b' | 't')).collect();
+        let kind = match base.as_str() {
+            "r" | "w" | "a" | "x" => base.chars().next().unwrap_or('r'),
+            _ => {
+                return Err(PyErr::new(
+                    "ValueError",
+                    &format!("invalid mode: {}", str_repr(&mode)),
+                ))
+            }
+        };
+
+        let existing = self
+            .fs()?
+            .vfs
+            .resolve(&path)
+            .map(|node| (node.is_dir, node.data.clone()));
+        let data = match (kind, existing) {
+            (_, Some((true, _))) => {
+                return Err(os_error("IsADirectoryError", 21, "Is a directory", &path))
+            }
+            ('r', None) => {
+                return Err(os_error(
+                    "FileNotFoundError",
+                    2,
+                    "No such file or directory",
+                    &path,
+                ))
+            }
+            ('x', Some(_)) => return Err(os_error("FileExistsError", 17, "File exists", &path)),
+            ('r', Some((_, data))) => {
+                if !self.fs_allows(&path, 4)? {
+                    return Err(os_error("PermissionError", 13, "Permission denied", &path));
+                }
+                data
+            }
+            (_, Some((_, data))) => {
+                if !self.fs_allows(&path, 2)? {
+                    return Err(os_error("PermissionError", 13, "Permission denied", &path));
+                }
+                if kind == 'a' {
+                    data
+                } else {
+                    self.fs()?
+                        .vfs
+                        .write_file(&path, "")
+                        .map_err(|e| Self::vfs_error(e, &path))?;
+                    String::new()
+                }
+            }
+            (_, None) => {
+                if !self.fs_can_create(&path)? {
+                    return Err(os_error("PermissionError", 13, "Permission denied", &path));
+                }
+                self.fs()?
+                    .vfs
+                    .create_file(&path, "")
+                    .map_err(|e| Self::vfs_error(e, &path))?;
+                String::new()
+            }
+        };
+        Ok(PythonValue::File(Rc::new(RefCell::new(PyFile {
+            path,
+            mode: kind,
+            data,
+            pos: 0,
+            closed: false,
+        }))))
+    }
+
+    pub(super) fn file_attr(&self, file: &Rc<RefCell<PyFile>>, attr: &str) -> Option<PythonValue> {
+        let f = file.borrow();
+        match attr {
+            "name" => Some(PythonValue::str(f.path.as_str())),
+            "mode" => Some(PythonValue::String(f.mode.to_string())),
+            "closed" => Some(PythonValue::Bool(f.closed)),
+            _ => FILE_METHODS
+                .iter()
+                .find(|m| **m == attr)
+                .map(|m| PythonValue::Method(Box::new(PythonValue::File(file.clone())), m)),
+        }
+    }
+
+    pub(super) fn file_method(
+        &mut self,
+        file: &Rc<RefCell<PyFile>>,
+        name: &str,
+        args: Vec<PythonValue>,
+    ) -> Result<PythonValue, PyErr> {
+        use PythonValue as V;
+        if name == "close" {
+            file.borrow_mut().closed = true;
+            return Ok(V::None);
+        }
+        if file.borrow().closed {
+            return Err(PyErr::new("ValueError", "I/O operation on closed file."));
+        }
+        let reading = matches!(name, "read" | "readline" | "readlines");
+        if reading != (file.borrow().mode == 'r') {
+            return Err(PyErr::new(
+                "io.UnsupportedOperation",
+                if reading {
+                    "not readable"
+                } else {
+                    "not writable"
+                },
+            ));
+        }
+        match name {
+            "read" => {
+                let limit = match args.first() {
+                    None | Some(V::None) => -1,
+                    Some(v) => int_of(name, Some(v))?,
+                };
+                let mut f = file.borrow_mut();
+                let rest = &f.data[f.pos..];
+                let end = match usize::try_from(limit) {
+                    Ok(n) => rest.char_indices().nth(n).map_or(rest.len(), |(i, _)| i),
+                    Err(_) => rest.len(),
+                };
+                let text = rest[..end].to_string();
+                f.pos += end;
+                Ok(V::String(text))
+            }
+            "readline" => Ok(V::String(Self::file_readline(file))),
+            "readlines" => {
+                let mut lines = Vec::new();
+                loop {
+                    let line = Self::file_readline(file);
+                    if line.is_empty() {
+                        break;
+                    }
+                    lines.push(V::String(line));
+                }
+                Ok(V::list(lines))
+            }
+            _ => {
+                let texts = match (name, args.as_slice()) {
+                    ("write", [V::String(s)]) => vec![s.clone()],
+                    ("writelines", [seq]) => self
+                        .iterate(seq)?
+                        .into_iter()
+                        .map(|v| match v {
+                            V::String(s) => Ok(s),
+                            other => Err(PyErr::type_error(&format!(
+                                "write() argument must be str, not {}",
+                                other.type_name()
+                            ))),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    (_, [other]) => {
+                        return Err(PyErr::type_error(&format!(
+                            "write() argument must be str, not {}",
+                            other.type_name()
+                        )))
+                    }
+                    _ => {
+                        return Err(PyErr::type_error(&format!(
+                            "{}() takes exactly one argument ({} given)",
+                            name,
+                            args.len()
+                        )))
+                    }
+                };
+                let written: usize = texts.iter().map(|t| t.chars().count()).sum();
+                let (path, data) = {
+                    let mut f = file.borrow_mut();
+                    for t in &texts {
+                        f.data.push_str(t);
+                    }
+                    (f.path.clone(), f.data.clone())
+                };
+                self.fs()?
+                    .vfs
+                    .write_file(&path, &data)
+                    .map_err(|e| Self::vfs_error(e, &path))?;
+                Ok(if name == "write" {
+                    V::Int(written as i64)
+                } else {
+                    V::None
+                })
+            }
+        }
+    }
+
+    pub(super) fn file_readline(file: &Rc<RefCell<PyFile>>) -> String {
+        let mut f = file.borrow_mut();
+        let rest = &f.data[f.pos..];
+        let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let line = rest[..end].to_string();
+        f.pos += end;
+        line
+    }
+}
@@ -0,0 +1,1112 @@
+use super::lexer::{tokenize, Tok, Token};
+use super::PyErr;
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub(super) enum FPart {
+    Lit(String),
+    Expr(Box<Expr>, Option<char>, String),
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct CompFor {
+    pub target: Expr,
+    pub iter: Expr,
+    pub conds: Vec<Expr>,
+}
+
+/// `name=value` arguments of a call
+pub(super) type Keywords = Vec<(String, Expr)>;
+
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    NoneLit,
+    FString(Vec<FPart>),
+    Name(String),
+    List(Vec<Expr>),
+    Tuple(Vec<Expr>),
+    Dict(Vec<(Expr, Expr)>),
+    ListComp(Box<Expr>, Vec<CompFor>),
+    DictComp(Box<Expr>, Box<Expr>, Vec<CompFor>),
+    Unary(&'static str, Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Vec<(&'static str, Expr)>),
+    IfExp(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>, Keywords),
+    Attr(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Option<Box<Expr>>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Lambda(Rc<FuncDef>),
+    Starred(Box<Expr>),
+}
+
+#[derive(Debug)]
+pub(super) struct Param {
+    pub name: String,
+    pub default: Option<Expr>,
+}
+
+#[derive(Debug)]
+pub(super) struct FuncDef {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub vararg: Option<String>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum StmtKind {
+    Expr(Expr),
+    Assign(Vec<Expr>, Expr),
+    AugAssign(Expr, &'static str, Expr),
+    If(Vec<(Expr, Vec<Stmt>)>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    For(Expr, Expr, Vec<Stmt>),
+    /// `with a as x, b:` context managers and their optional targets
+    With(Vec<(Expr, Option<Expr>)>, Vec<Stmt>),
+    Def(Rc<FuncDef>),
+    Return(Option<Expr>),
+    Break,
+    Continue,
+    Pass,
+    Global(Vec<String>),
+    Del(Vec<Expr>),
+    Assert(Expr, Option<Expr>),
+    /// `import a, b as c`: (module, binding)
+    Import(Vec<(String, String)>),
+    /// `from m import a, b as c`
+    FromImport(String, Vec<(String, String)>),
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Stmt {
+    pub kind: StmtKind,
+    pub line: usize,
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "break", "class", "continue", "def", "del", "elif", "else", "for",
+    "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass",
+    "raise", "return", "while", "with", "yield", "True", "False", "None", "try", "except",
+    "finally",
+];
+
+const AUG_OPS: &[(&str, &str)] = &[
+    ("+=", "+"),
+    ("-=", "-"),
+    ("*=", "*"),
+    ("/=", "/"),
+    ("//=", "//"),
+    ("%=", "%"),
+    ("**=", "**"),
+    ("&=", "&"),
+    ("|=", "|"),
+    ("^=", "^"),
+    ("<<=", "<<"),
+    (">>=", ">>"),
+];
+
+pub(super) fn parse_program(src: &str) -> Result<Vec<Stmt>, PyErr> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+    };
+    let mut body = Vec::new();
+    while !parser.at(&Tok::Eof) {
+        if parser.eat(&Tok::Newline) {
+            continue;
+        }
+        body.extend(parser.statement()?);
+    }
+    Ok(body)
+}
+
+/// Parse a single expression, as found inside f-string braces
+fn parse_expression(src: &str, line: usize) -> Result<Expr, PyErr> {
+    let mut tokens = tokenize(src.trim()).map_err(|e| e.at(line))?;
+    for t in &mut tokens {
+        t.line = line;
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.expr_list()?;
+    parser.eat(&Tok::Newline);
+    if !parser.at(&Tok::Eof) {
+        return Err(PyErr::syntax("f-string: invalid syntax", line));
+    }
+    Ok(expr)
+}
+
+fn parse_fstring(body: &str, line: usize) -> Result<Vec<FPart>, PyErr> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut parts = Vec::new();
+    let mut lit = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if (c == '{' || c == '}') && chars.get(i + 1) == Some(&c) {
+            lit.push(c);
+            i += 2;
+            continue;
+        }
+        if c == '}' {
+            return Err(PyErr::syntax("f-string: single '}' is not allowed", line));
+        }
+        if c != '{' {
+            lit.push(c);
+            i += 1;
+            continue;
+        }
+        // Find the end of the replacement field, skipping nested brackets and strings
+        let start = i + 1;
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        let mut expr_end = None;
+        let mut j = start;
+        while j < chars.len() {
+            let ch = chars[j];
+            match quote {
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None => match ch {
+                    '\'' | '"' => quote = Some(ch),
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' => depth -= 1,
+                    '}' if depth > 0 => depth -= 1,
+                    '}' => break,
+                    '!' if depth == 0 && chars.get(j + 1) != Some(&'=') && expr_end.is_none() => {
+                        expr_end = Some(j)
+                    }
+                    ':' if depth == 0 && expr_end.is_none() => expr_end = Some(j),
+                    _ => {}
+                },
+            }
+            j += 1;
+        }
+        if j >= chars.len() {
+            return Err(PyErr::syntax("f-string: expecting '}'", line));
+        }
+        let field_end = expr_end.unwrap_or(j);
+        let expr_src: String = chars[start..field_end].iter().collect();
+        if expr_src.trim().is_empty() {
+            return Err(PyErr::syntax(
+                "f-string: empty expression not allowed",
+                line,
+            ));
+        }
+        let mut conv = None;
+        let mut spec = String::new();
+        if field_end < j {
+            let tail: String = chars[field_end..j].iter().collect();
+            let tail = if let Some(rest) = tail.strip_prefix('!') {
+                conv = rest.chars().next();
+                rest.get(1..).unwrap_or("").to_string()
+            } else {
+                tail
+            };
+            spec = tail.strip_prefix(':').unwrap_or(&tail).to_string();
+        }
+        if !lit.is_empty() {
+            parts.push(FPart::Lit(std::mem::take(&mut lit)));
+        }
+        // `{x=}` prints the expression text before its value
+        let (expr_src, debug) = match expr_src.trim_end().strip_suffix('=') {
+            Some(e) if !e.ends_with(['=', '!', '<', '>']) => (e.to_string(), true),
+            _ => (expr_src, false),
+        };
+        if debug {
+            parts.push(FPart::Lit(format!("{}=", expr_src)));
+            if conv.is_none() && spec.is_empty() {
+                conv = Some('r');
+            }
+        }
+        parts.push(FPart::Expr(
+            Box::new(parse_expression(&expr_src, line)?),
+            conv,
+            spec,
+        ));
+        i = j + 1;
+    }
+    if !lit.is_empty() {
+        parts.push(FPart::Lit(lit));
+    }
+    Ok(parts)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Tok {
+        &self.tokens[self.pos.min(self.tokens.len() - 1)].tok
+    }
+
+    fn peek_at(&self, offset: usize) -> &Tok {
+        &self.tokens[(self.pos + offset).min(self.tokens.len() - 1)].tok
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos.min(self.tokens.len() - 1)].line
+    }
+
+    fn advance(&mut self) -> Tok {
+        let tok = self.peek().clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn at(&self, tok: &Tok) -> bool {
+        self.peek() == tok
+    }
+
+    fn at_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Tok::Op(o) if *o == op)
+    }
+
+    fn at_kw(&self, kw: &str) -> bool {
+        matches!(self.peek(), Tok::Name(n) if n == kw)
+    }
+
+    fn eat(&mut self, tok: &Tok) -> bool {
+        if self.at(tok) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if self.at_op(op) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_kw(&mut self, kw: &str) -> bool {
+        if self.at_kw(kw) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self) -> PyErr {
+        match self.peek() {
+            Tok::Eof => PyErr::syntax("unexpected EOF while parsing", self.line()),
+            Tok::Indent => PyErr::new("IndentationError", "unexpected indent").at(self.line()),
+            _ => PyErr::syntax("invalid syntax", self.line()),
+        }
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), PyErr> {
+        if self.eat_op(op) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn expect_kw(&mut self, kw: &str) -> Result<(), PyErr> {
+        if self.eat_kw(kw) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn name(&mut self) -> Result<String, PyErr> {
+        match self.peek() {
+            Tok::Name(n) if !KEYWORDS.contains(&n.as_str()) => {
+                let n = n.clone();
+                self.advance();
+                Ok(n)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn dotted_name(&mut self) -> Result<String, PyErr> {
+        let mut name = self.name()?;
+        while self.eat_op(".") {
+            name.push('.');
+            name.push_str(&self.name()?);
+        }
+        Ok(name)
+    }
+
+    // ---- statements ----
+
+    fn statement(&mut self) -> Result<Vec<Stmt>, PyErr> {
+        let line = self.line();
+        let kind = match self.peek() {
+            Tok::Name(n) => match n.as_str() {
+                "if" => Some(self.if_stmt()?),
+                "while" => {
+                    self.advance();
+                    let cond = self.expr()?;
+                    Some(StmtKind::While(cond, self.block()?))
+                }
+                "for" => {
+                    self.advance();
+                    let target = self.target_list()?;
+                    self.expect_kw("in")?;
+                    let iter = self.expr_list()?;
+                    Some(StmtKind::For(target, iter, self.block()?))
+                }
+                "def" => {
+                    self.advance();
+                    let name = self.name()?;
+                    self.expect_op("(")?;
+                    let (params, vararg) = self.params(")")?;
+                    self.expect_op(")")?;
+                    if self.eat_op("->") {
+                        self.expr()?;
+                    }
+                    let body = self.block()?;
+                    Some(StmtKind::Def(Rc::new(FuncDef {
+                        name,
+                        params,
+                        vararg,
+                        body,
+                    })))
+                }
+                "with" => {
+                    self.advance();
+                    let mut items = Vec::new();
+                    loop {
+                        let context = self.expr()?;
+                        let target = if self.eat_kw("as") {
+                            let target = self.bitor()?;
+                            check_target(&target, self.line())?;
+                            Some(target)
+                        } else {
+                            None
+                        };
+                        items.push((context, target));
+                        if !self.eat_op(",") {
+                            break;
+                        }
+                    }
+                    Some(StmtKind::With(items, self.block()?))
+                }
+                "class" | "try" => {
+                    return Err(PyErr::syntax(
+                        &format!("'{}' statements are not supported", n),
+                        line,
+                    ))
+                }
+                _ => None,
+            },
+            Tok::Indent => return Err(self.error()),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            return Ok(vec![Stmt { kind, line }]);
+        }
+        self.simple_line()
+    }
+
+    fn if_stmt(&mut self) -> Result<StmtKind, PyErr> {
+        self.advance();
+        let mut branches = vec![(self.expr()?, self.block()?)];
+        let mut orelse = Vec::new();
+        loop {
+            if self.eat_kw("elif") {
+                branches.push((self.expr()?, self.block()?));
+            } else if self.eat_kw("else") {
+                orelse = self.block()?;
+                break;
+            } else {
+                break;
+            }
+        }
+        Ok(StmtKind::If(branches, orelse))
+    }
+
+    /// `:` followed by either an indented suite or simple statements on the same line
+    fn block(&mut self) -> Result<Vec<Stmt>, PyErr> {
+        self.expect_op(":")?;
+        if !self.eat(&Tok::Newline) {
+            return self.simple_line();
+        }
+        if !self.eat(&Tok::Indent) {
+            return Err(match self.peek() {
+                Tok::Eof => PyErr::syntax("unexpected EOF while parsing", self.line()),
+                _ => PyErr::new("IndentationError", "expected an indented block").at(self.line()),
+            });
+        }
+        let mut body = Vec::new();
+        while !self.eat(&Tok::Dedent) {
+            if self.at(&Tok::Eof) {
+                break;
+            }
+            if self.eat(&Tok::Newline) {
+                continue;
+            }
+            body.extend(self.statement()?);
+        }
+        Ok(body)
+    }
+
+    fn simple_line(&mut self) -> Result<Vec<Stmt>, PyErr> {
+        let mut stmts = Vec::new();
+        loop {
+            let line = self.line();
+            stmts.push(Stmt {
+                kind: self.simple_stmt()?,
+                line,
+            });
+            if !self.eat_op(";") || self.at(&Tok::Newline) || self.at(&Tok::Eof) {
+                break;
+            }
+        }
+        if !self.eat(&Tok::Newline) && !self.at(&Tok::Eof) {
+            return Err(self.error());
+        }
+        Ok(stmts)
+    }
+
+    fn simple_stmt(&mut self) -> Result<StmtKind, PyErr> {
+        if let Tok::Name(n) = self.peek() {
+            match n.as_str() {
+                "pass" => {
+                    self.advance();
+                    return Ok(StmtKind::Pass);
+                }
+                "break" => {
+                    self.advance();
+                    return Ok(StmtKind::Break);
+                }
+                "continue" => {
+                    self.advance();
+                    return Ok(StmtKind::Continue);
+                }
+                "return" => {
+                    self.advance();
+                    if self.at(&Tok::Newline) || self.at(&Tok::Eof) || self.at_op(";") {
+                        return Ok(StmtKind::Return(None));
+                    }
+                    return Ok(StmtKind::Return(Some(self.expr_list()?)));
+                }
+                "global" | "nonlocal" => {
+                    self.advance();
+                    let mut names = vec![self.name()?];
+                    while self.eat_op(",") {
+                        names.push(self.name()?);
+                    }
+                    return Ok(StmtKind::Global(names));
+                }
+                "del" => {
+                    self.advance();
+                    let mut targets = vec![self.primary()?];
+                    while self.eat_op(",") {
+                        targets.push(self.primary()?);
+                    }
+                    return Ok(StmtKind::Del(targets));
+                }
+                "assert" => {
+                    self.advance();
+                    let cond = self.expr()?;
+                    let msg = if self.eat_op(",") {
+                        Some(self.expr()?)
+                    } else {
+                        None
+                    };
+                    return Ok(StmtKind::Assert(cond, msg));
+                }
+                "import" => {
+                    self.advance();
+                    let mut names = Vec::new();
+                    loop {
+                        let module = self.dotted_name()?;
+                        let binding = if self.eat_kw("as") {
+                            self.name()?
+                        } else {
+                            module.split('.').next().unwrap_or(&module).to_string()
+                        };
+                        names.push((module, binding));
+                        if !self.eat_op(",") {
+                            break;
+                        }
+                    }
+                    return Ok(StmtKind::Import(names));
+                }
+                "from" => {
+                    self.advance();
+                    let module = self.dotted_name()?;
+                    self.expect_kw("import")?;
+                    let paren = self.eat_op("(");
+                    let mut names = Vec::new();
+                    loop {
+                        let name = if self.eat_op("*") {
+                            "*".to_string()
+                        } else {
+                            self.name()?
+                        };
+                        let binding = if self.eat_kw("as") {
+                            self.name()?
+                        } else {
+                            name.clone()
+                        };
+                        names.push((name, binding));
+                        if !self.eat_op(",") || (paren && self.at_op(")")) {
+                            break;
+                        }
+                    }
+                    if paren {
+                        self.expect_op(")")?;
+                    }
+                    return Ok(StmtKind::FromImport(module, names));
+                }
+                "raise" | "yield" => {
+                    return Err(PyErr::syntax(
+                        &format!("'{}' is not supported", n),
+                        self.line(),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        let first = self.expr_list()?;
+        if let Tok::Op(op) = self.peek() {
+            if let Some((_, bin)) = AUG_OPS.iter().find(|(aug, _)| aug == op) {
+                check_target(&first, self.line())?;
+                self.advance();
+                let value = self.expr_list()?;
+                return Ok(StmtKind::AugAssign(first, bin, value));
+            }
+        }
+        if self.at_op("=") {
+            let mut targets = vec![first];
+            let mut value;
+            loop {
+                self.expect_op("=")?;
+                value = self.expr_list()?;
+                if !self.at_op("=") {
+                    break;
+                }
+                targets.push(value);
+            }
+            for t in &targets {
+                check_target(t, self.line())?;
+            }
+            return Ok(StmtKind::Assign(targets, value));
+        }
+        Ok(StmtKind::Expr(first))
+    }
+
+    fn params(&mut self, close: &str) -> Result<(Vec<Param>, Option<String>), PyErr> {
+        let mut params = Vec::new();
+        let mut vararg = None;
+        while !self.at_op(close) {
+            if self.eat_op("*") {
+                vararg = Some(self.name()?);
+            } else {
+                let name = self.name()?;
+                if close == ")" && self.eat_op(":") {
+                    self.expr()?;
+                }
+                let default = if self.eat_op("=") {
+                    Some(self.expr()?)
+                } else {
+                    if params.iter().any(|p: &Param| p.default.is_some()) {
+                        return Err(PyErr::syntax(
+                            "non-default argument follows default argument",
+                            self.line(),
+                        ));
+                    }
+                    None
+                };
+                params.push(Param { name, default });
+            }
+            if !self.eat_op(",") {
+                break;
+            }
+        }
+        Ok((params, vararg))
+    }
+
+    /// Loop targets: `x`, `i, x`, `(a, b)`
+    fn target_list(&mut self) -> Result<Expr, PyErr> {
+        let mut items = vec![self.bitor()?];
+        let mut tuple = false;
+        while self.eat_op(",") {
+            tuple = true;
+            if self.at_kw("in") || self.at_op("=") {
+                break;
+            }
+            items.push(self.bitor()?);
+        }
+        let target = if tuple {
+            Expr::Tuple(items)
+        } else {
+            items.remove(0)
+        };
+        check_target(&target, self.line())?;
+        Ok(target)
+    }
+
+    // ---- expressions ----
+
+    /// A comma-separated expression list, which becomes a tuple if it has a comma
+    fn expr_list(&mut self) -> Result<Expr, PyErr> {
+        let first = self.star_expr()?;
+        if !self.at_op(",") {
+            return Ok(first);
+        }
+        let mut items = vec![first];
+        while self.eat_op(",") {
+            if self.expr_ends() {
+                break;
+            }
+            items.push(self.star_expr()?);
+        }
+        Ok(Expr::Tuple(items))
+    }
+
+    fn expr_ends(&self) -> bool {
+        matches!(self.peek(), Tok::Newline | Tok::Eof)
+            || matches!(self.peek(), Tok::Op(o) if matches!(*o, ")" | "]" | "}" | "=" | ";" | ":"))
+    }
+
+    fn star_expr(&mut self) -> Result<Expr, PyErr> {
+        if self.eat_op("*") {
+            return Ok(Expr::Starred(Box::new(self.bitor()?)));
+        }
+        self.expr()
+    }
+
+    pub(super) fn expr(&mut self) -> Result<Expr, PyErr> {
+        if self.eat_kw("lambda") {
+            let (params, vararg) = self.params(":")?;
+            self.expect_op(":")?;
+            let body = self.expr()?;
+            let line = self.line();
+            return Ok(Expr::Lambda(Rc::new(FuncDef {
+                name: "<lambda>".into(),
+                params,
+                vararg,
+                body: vec![Stmt {
+                    kind: StmtKind::Return(Some(body)),
+                    line,
+                }],
+            })));
+        }
+        let cond_or_value = self.or_test()?;
+        if self.at_kw("if") && !self.in_comprehension_clause() {
+            self.advance();
+            let cond = self.or_test()?;
+            self.expect_kw("else")?;
+            let orelse = self.expr()?;
+            return Ok(Expr::IfExp(
+                Box::new(cond),
+                Box::new(cond_or_value),
+                Box::new(orelse),
+            ));
+        }
+        Ok(cond_or_value)
+    }
+
+    /// A conditional expression needs an `else`; an `if` without one is a
+    /// comprehension filter
+    fn in_comprehension_clause(&self) -> bool {
+        let mut depth = 0i32;
+        let mut i = self.pos + 1;
+        while i < self.tokens.len() {
+            match &self.tokens[i].tok {
+                Tok::Op("(" | "[" | "{") => depth += 1,
+                Tok::Op(")" | "]" | "}") if depth == 0 => return true,
+                Tok::Op(")" | "]" | "}") => depth -= 1,
+                Tok::Name(n) if depth == 0 && n == "else" => return false,
+                Tok::Name(n) if depth == 0 && (n == "for" || n == "if") => return true,
+                Tok::Newline | Tok::Eof => return true,
+                _ => {}
+            }
+            i += 1;
+        }
+        true
+    }
+
+    fn or_test(&mut self) -> Result<Expr, PyErr> {
+        let mut left = self.and_test()?;
+        while self.eat_kw("or") {
+            let right = self.and_test()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and_test(&mut self) -> Result<Expr, PyErr> {
+        let mut left = self.not_test()?;
+        while self.eat_kw("and") {
+            let right = self.not_test()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn not_test(&mut self) -> Result<Expr, PyErr> {
+        if self.eat_kw("not") {
+            return Ok(Expr::Unary("not", Box::new(self.not_test()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, PyErr> {
+        let left = self.bitor()?;
+        let mut ops = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Tok::Op(o) if matches!(*o, "==" | "!=" | "<" | ">" | "<=" | ">=") => {
+                    let o = *o;
+                    self.advance();
+                    o
+                }
+                Tok::Name(n) if n == "in" => {
+                    self.advance();
+                    "in"
+                }
+                Tok::Name(n)
+                    if n == "not" && matches!(self.peek_at(1), Tok::Name(m) if m == "in") =>
+                {
+                    self.advance();
+                    self.advance();
+                    "not in"
+                }
+                Tok::Name(n) if n == "is" => {
+                    self.advance();
+                    if self.eat_kw("not") {
+                        "is not"
+                    } else {
+                        "is"
+                    }
+                }
+                _ => break,
+            };
+            ops.push((op, self.bitor()?));
+        }
+        if ops.is_empty() {
+            Ok(left)
+        } else {
+            Ok(Expr::Compare(Box::new(left), ops))
+        }
+    }
+
+    fn binary_level(
+        &mut self,
+        ops: &[&'static str],
+        next: fn(&mut Self) -> Result<Expr, PyErr>,
+    ) -> Result<Expr, PyErr> {
+        let mut left = next(self)?;
+        loop {
+            let Some(op) = ops.iter().find(|op| self.at_op(op)) else {
+                return Ok(left);
+            };
+            self.advance();
+            let right = next(self)?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+    }
+
+    fn bitor(&mut self) -> Result<Expr, PyErr> {
+        self.binary_level(&["|"], Self::bitxor)
+    }
+
+    fn bitxor(&mut self) -> Result<Expr, PyErr> {
+        self.binary_level(&["^"], Self::bitand)
+    }
+
+    fn bitand(&mut self) -> Result<Expr, PyErr> {
+        self.binary_level(&["&"], Self::shift)
+    }
+
+    fn shift(&mut self) -> Result<Expr, PyErr> {
+        self.binary_level(&["<<", ">>"], Self::arith)
+    }
+
+    fn arith(&mut self) -> Result<Expr, PyErr> {
+        self.binary_level(&["+", "-"], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, PyErr> {
+        self.binary_level(&["*", "/", "//", "%", "@"], Self::factor)
+    }
+
+    fn factor(&mut self) -> Result<Expr, PyErr> {
+        for op in ["-", "+", "~"] {
+            if self.eat_op(op) {
+                let operand = self.factor()?;
+                return Ok(match (op, operand) {
+                    ("-", Expr::Int(i)) => Expr::Int(-i),
+                    ("-", Expr::Float(f)) => Expr::Float(-f),
+                    (op, operand) => Expr::Unary(
+                        match op {
+                            "-" => "-",
+                            "+" => "+",
+                            _ => "~",
+                        },
+                        Box::new(operand),
+                    ),
+                });
+            }
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, PyErr> {
+        let base = self.primary()?;
+        if self.eat_op("**") {
+            let exp = self.factor()?;
+            return Ok(Expr::Binary(Box::new(base), "**", Box::new(exp)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, PyErr> {
+        let mut expr = self.atom()?;
+        loop {
+            if self.eat_op("(") {
+                let (args, kwargs) = self.call_args()?;
+                expr = Expr::Call(Box::new(expr), args, kwargs);
+            } else if self.eat_op("[") {
+                let index = self.subscript()?;
+                self.expect_op("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else if self.eat_op(".") {
+                let attr = match self.advance() {
+                    Tok::Name(n) => n,
+                    _ => return Err(PyErr::syntax("invalid syntax", self.line())),
+                };
+                expr = Expr::Attr(Box::new(expr), attr);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn call_args(&mut self) -> Result<(Vec<Expr>, Keywords), PyErr> {
+        let mut args = Vec::new();
+        let mut kwargs = Vec::new();
+        while !self.at_op(")") {
+            if let (Tok::Name(n), Tok::Op("=")) = (self.peek(), self.peek_at(1)) {
+                let n = n.clone();
+                self.advance();
+                self.advance();
+                kwargs.push((n, self.expr()?));
+            } else if self.eat_op("*") {
+                args.push(Expr::Starred(Box::new(self.expr()?)));
+            } else {
+                let arg = self.expr()?;
+                // A lone generator expression argument: `sum(x for x in xs)`
+                if self.at_kw("for") {
+                    let clauses = self.comp_clauses()?;
+                    args.push(Expr::ListComp(Box::new(arg), clauses));
+                } else {
+                    args.push(arg);
+                }
+            }
+            if !self.eat_op(",") {
+                break;
+            }
+        }
+        self.expect_op(")")?;
+        Ok((args, kwargs))
+    }
+
+    fn subscript(&mut self) -> Result<Expr, PyErr> {
+        let mut parts: [Option<Box<Expr>>; 3] = [None, None, None];
+        let mut idx = 0;
+        let mut is_slice = false;
+        loop {
+            if !self.at_op(":") && !self.at_op("]") {
+                parts[idx] = Some(Box::new(self.expr()?));
+            }
+            if idx < 2 && self.eat_op(":") {
+                is_slice = true;
+                idx += 1;
+            } else {
+                break;
+            }
+        }
+        if !is_slice {
+            let Some(first) = parts[0].take() else {
+                return Err(PyErr::syntax("invalid syntax", self.line()));
+            };
+            if self.at_op(",") {
+                let mut items = vec![*first];
+                while self.eat_op(",") && !self.at_op("]") {
+                    items.push(self.expr()?);
+                }
+                return Ok(Expr::Tuple(items));
+            }
+            return Ok(*first);
+        }
+        let [a, b, c] = parts;
+        Ok(Expr::Slice(a, b, c))
+    }
+
+    fn comp_clauses(&mut self) -> Result<Vec<CompFor>, PyErr> {
+        let mut clauses = Vec::new();
+        while self.eat_kw("for") {
+            let target = self.target_list()?;
+            self.expect_kw("in")?;
+            let iter = self.or_test()?;
+            let mut conds = Vec::new();
+            while self.eat_kw("if") {
+                conds.push(self.or_test()?);
+            }
+            clauses.push(CompFor {
+                target,
+                iter,
+                conds,
+            });
+        }
+        Ok(clauses)
+    }
+
+    fn atom(&mut self) -> Result<Expr, PyErr> {
+        let line = self.line();
+        match self.advance() {
+            Tok::Int(i) => Ok(Expr::Int(i)),
+            Tok::Float(f) => Ok(Expr::Float(f)),
+            Tok::Str(s) => self.string_concat(vec![FPart::Lit(s)], false),
+            Tok::FStr(s) => {
+                let parts = parse_fstring(&s, line)?;
+                self.string_concat(parts, true)
+            }
+            Tok::Name(n) => match n.as_str() {
+                "True" => Ok(Expr::Bool(true)),
+                "False" => Ok(Expr::Bool(false)),
+                "None" => Ok(Expr::NoneLit),
+                kw if KEYWORDS.contains(&kw) => Err(PyErr::syntax("invalid syntax", line)),
+                _ => Ok(Expr::Name(n)),
+            },
+            Tok::Op("(") => {
+                if self.eat_op(")") {
+                    return Ok(Expr::Tuple(Vec::new()));
+                }
+                let first = self.star_expr()?;
+                if self.at_kw("for") {
+                    let clauses = self.comp_clauses()?;
+                    self.expect_op(")")?;
+                    return Ok(Expr::ListComp(Box::new(first), clauses));
+                }
+                if self.eat_op(")") {
+                    return Ok(first);
+                }
+                let mut items = vec![first];
+                while self.eat_op(",") {
+                    if self.at_op(")") {
+                        break;
+                    }
+                    items.push(self.star_expr()?);
+                }
+                self.expect_op(")")?;
+                Ok(Expr::Tuple(items))
+            }
+            Tok::Op("[") => {
+                if self.eat_op("]") {
+                    return Ok(Expr::List(Vec::new()));
+                }
+                let first = self.star_expr()?;
+                if self.at_kw("for") {
+                    let clauses = self.comp_clauses()?;
+                    self.expect_op("]")?;
+                    return Ok(Expr::ListComp(Box::new(first), clauses));
+                }
+                let mut items = vec![first];
+                while self.eat_op(",") {
+                    if self.at_op("]") {
+                        break;
+                    }
+                    items.push(self.star_expr()?);
+                }
+                self.expect_op("]")?;
+                Ok(Expr::List(items))
+            }
+            Tok::Op("{") => {
+                if self.eat_op("}") {
+                    return Ok(Expr::Dict(Vec::new()));
+                }
+                let key = self.expr()?;
+                if !self.eat_op(":") {
+                    return Err(PyErr::syntax("set literals are not supported", line));
+                }
+                let value = self.expr()?;
+                if self.at_kw("for") {
+                    let clauses = self.comp_clauses()?;
+                    self.expect_op("}")?;
+                    return Ok(Expr::DictComp(Box::new(key), Box::new(value), clauses));
+                }
+                let mut items = vec![(key, value)];
+                while self.eat_op(",") {
+                    if self.at_op("}") {
+                        break;
+                    }
+                    let k = self.expr()?;
+                    self.expect_op(":")?;
+                    items.push((k, self.expr()?));
+                }
+                self.expect_op("}")?;
+                Ok(Expr::Dict(items))
+            }
+            Tok::Eof => Err(PyErr::syntax("unexpected EOF while parsing", line)),
+            _ => Err(PyErr::syntax("invalid syntax", line)),
+        }
+    }
+
+    /// Adjacent string literals are joined, as in `"a" "b"`
+    fn string_concat(&mut self, mut parts: Vec<FPart>, mut is_f: bool) -> Result<Expr, PyErr> {
+        loop {
+            let line = self.line();
+            match self.peek().clone() {
+                Tok::Str(s) => {
+                    self.advance();
+                    parts.push(FPart::Lit(s));
+                }
+                Tok::FStr(s) => {
+                    self.advance();
+                    parts.extend(parse_fstring(&s, line)?);
+                    is_f = true;
+                }
+                _ => break,
+            }
+        }
+        if is_f {
+            return Ok(Expr::FString(parts));
+        }
+        let text = parts
+            .into_iter()
+            .map(|p| match p {
+                FPart::Lit(s) => s,
+                FPart::Expr(..) => String::new(),
+            })
+            .collect();
+        Ok(Expr::Str(text))
+    }
+}
+
+fn check_target(target: &Expr, line: usize) -> Result<(), PyErr> {
+    match target {
+        Expr::Name(_) | Expr::Index(..) | Expr::Attr(..) => Ok(()),
+        Expr::Tuple(items) | Expr::List(items) => {
+            items.iter().try_for_each(|t| check_target(t, line))
+        }
+        Expr::Starred(inner) => check_target(inner, line),
+        _ => Err(PyErr::syntax("cannot assign to expression", line)),
+    }
+}
@@ -0,0 +1,845 @@
+use super::parser::FuncDef;
+use super::PyErr;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Names that evaluate to a class object rather than a plain function
+pub(super) const TYPE_NAMES: &[&str] = &[
+    "int",
+    "float",
+    "str",
+    "bool",
+    "list",
+    "tuple",
+    "dict",
+    "range",
+    "NoneType",
+    "function",
+    "builtin_function_or_method",
+    "method",
+    "module",
+    "type",
+];
+
+/// Variables of one function call; closures keep their defining scope alive
+#[derive(Debug, Default)]
+pub(super) struct Scope {
+    pub vars: RefCell<HashMap<String, PythonValue>>,
+    pub parent: Option<Rc<Scope>>,
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub(super) def: Rc<FuncDef>,
+    pub(super) defaults: Vec<Option<PythonValue>>,
+    pub(super) closure: Option<Rc<Scope>>,
+}
+
+/// A file opened with `open()`. Reads work on a snapshot taken at open time;
+/// writes go straight through to the filesystem.
+#[derive(Debug)]
+pub struct PyFile {
+    pub(super) path: String,
+    pub(super) mode: char,
+    pub(super) data: String,
+    pub(super) pos: usize,
+    pub(super) closed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum PythonValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    None,
+    List(Rc<RefCell<Vec<PythonValue>>>),
+    Tuple(Rc<Vec<PythonValue>>),
+    /// Insertion-ordered key/value pairs
+    Dict(Rc<RefCell<Vec<(PythonValue, PythonValue)>>>),
+    Range(i64, i64, i64),
+    Function(Rc<Function>),
+    Builtin(&'static str),
+    /// A method looked up on a value, waiting to be called
+    Method(Box<PythonValue>, &'static str),
+    Module(&'static str),
+    File(Rc<RefCell<PyFile>>),
+}
+
+impl fmt::Display for PythonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PythonValue::String(s) => write!(f, "{}", s),
+            other => write!(f, "{}", other.repr()),
+        }
+    }
+}
+
+impl PythonValue {
+    pub(super) fn list(items: Vec<PythonValue>) -> Self {
+        PythonValue::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub(super) fn tuple(items: Vec<PythonValue>) -> Self {
+        PythonValue::Tuple(Rc::new(items))
+    }
+
+    pub(super) fn dict(items: Vec<(PythonValue, PythonValue)>) -> Self {
+        PythonValue::Dict(Rc::new(RefCell::new(items)))
+    }
+
+    pub(super) fn str(s: impl Into<String>) -> Self {
+        PythonValue::String(s.into())
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PythonValue::Int(_) => "int",
+            PythonValue::Float(_) => "float",
+            PythonValue::String(_) => "str",
+            PythonValue::Bool(_) => "bool",
+            PythonValue::None => "NoneType",
+            PythonValue::List(_) => "list",
+            PythonValue::Tuple(_) => "tuple",
+            PythonValue::Dict(_) => "dict",
+            PythonValue::Range(..) => "range",
+            PythonValue::Function(_) => "function",
+            PythonValue::Builtin(name) if TYPE_NAMES.contains(name) => "type",
+            PythonValue::Builtin(_) => "builtin_function_or_method",
+            PythonValue::Method(..) => "method",
+            PythonValue::Module(_) => "module",
+            PythonValue::File(_) => "TextIOWrapper",
+        }
+    }
+
+    pub fn repr(&self) -> String {
+        self.repr_within(&mut Vec::new())
+    }
+
+    /// `repr` that prints a list or dict already being printed higher up as
+    /// `[...]` or `{...}`, so containers holding themselves still end
+    fn repr_within(&self, seen: &mut Vec<*const ()>) -> String {
+        let ptr = match self {
+            PythonValue::List(items) => Rc::as_ptr(items) as *const (),
+            PythonValue::Dict(items) => Rc::as_ptr(items) as *const (),
+            _ => std::ptr::null(),
+        };
+        if !ptr.is_null() {
+            if seen.contains(&ptr) {
+                return if matches!(self, PythonValue::List(_)) {
+                    "[...]"
+                } else {
+                    "{...}"
+                }
+                .into();
+            }
+            seen.push(ptr);
+        }
+        let text = self.repr_item(seen);
+        if !ptr.is_null() {
+            seen.pop();
+        }
+        text
+    }
+
+    fn repr_item(&self, seen: &mut Vec<*const ()>) -> String {
+        match self {
+            PythonValue::Int(i) => i.to_string(),
+            PythonValue::Float(f) => float_repr(*f),
+            PythonValue::String(s) => str_repr(s),
+            PythonValue::Bool(b) => if *b { "True" } else { "False" }.into(),
+            PythonValue::None => "None".into(),
+            PythonValue::List(items) => {
+                let items: Vec<String> =
+                    items.borrow().iter().map(|v| v.repr_within(seen)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            PythonValue::Tuple(items) if items.len() == 1 => {
+                format!("({},)", items[0].repr_within(seen))
+            }
+            PythonValue::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(|v| v.repr_within(seen)).collect();
+                format!("({})", items.join(", "))
+            }
+            PythonValue::Dict(items) => {
+                let items: Vec<String> = items
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k.repr_within(seen), v.repr_within(seen)))
+                    .collect();
+                format!("{{{}}}", items.join(", "))
+            }
+            PythonValue::Range(start, stop, 1) => format!("range({}, {})", start, stop),
+            PythonValue::Range(start, stop, step) => {
+                format!("range({}, {}, {})", start, stop, step)
+            }
+            PythonValue::Function(func) => format!(
+                "<function {} at {:#x}>",
+                func.def.name,
+                Rc::as_ptr(func) as usize
+            ),
+            PythonValue::Builtin(name) if TYPE_NAMES.contains(name) => {
+                format!("<class '{}'>", name)
+            }
+            PythonValue::Builtin(name) => format!(
+                "<built-in function {}>",
+                name.rsplit('.').next().unwrap_or(name)
+            ),
+            PythonValue::Method(obj, name) => {
+                format!("<built-in method {} of {} object>", name, obj.type_name())
+            }
+            PythonValue::Module(name) => format!("<module '{}' (built-in)>", name),
+            PythonValue::File(file) => {
+                let file = file.borrow();
+                format!(
+                    "<_io.TextIOWrapper name={} mode='{}' encoding='UTF-8'>",
+                    str_repr(&file.path),
+                    file.mode
+                )
+            }
+        }
+    }
+
+    pub fn truthy(&self) -> bool {
+        match self {
+            PythonValue::Int(i) => *i != 0,
+            PythonValue::Float(f) => *f != 0.0,
+            PythonValue::String(s) => !s.is_empty(),
+            PythonValue::Bool(b) => *b,
+            PythonValue::None => false,
+            PythonValue::List(items) => !items.borrow().is_empty(),
+            PythonValue::Tuple(items) => !items.is_empty(),
+            PythonValue::Dict(items) => !items.borrow().is_empty(),
+            PythonValue::Range(start, stop, step) => range_len(*start, *stop, *step) > 0,
+            _ => true,
+        }
+    }
+
+    /// Integer value of an int or bool
+    pub(super) fn as_int(&self) -> Option<i64> {
+        match self {
+            PythonValue::Int(i) => Some(*i),
+            PythonValue::Bool(b) => Some(i64::from(*b)),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_float(&self) -> Option<f64> {
+        match self {
+            PythonValue::Float(f) => Some(*f),
+            other => other.as_int().map(|i| i as f64),
+        }
+    }
+
+    pub(super) fn is_number(&self) -> bool {
+        matches!(
+            self,
+            PythonValue::Int(_) | PythonValue::Float(_) | PythonValue::Bool(_)
+        )
+    }
+}
+
+pub(super) fn range_len(start: i64, stop: i64, step: i64) -> usize {
+    if step > 0 && start < stop {
+        ((stop - start - 1) / step + 1) as usize
+    } else if step < 0 && start > stop {
+        ((start - stop - 1) / -step + 1) as usize
+    } else {
+        0
+    }
+}
+
+pub(super) fn float_repr(f: f64) -> String {
+    if f.is_nan() {
+        return "nan".into();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "inf" } else { "-inf" }.into();
+    }
+    let abs = f.abs();
+    if abs != 0.0 && !(1e-4..1e16).contains(&abs) {
+        return exp_notation(&format!("{:e}", f));
+    }
+    let s = f.to_string();
+    if s.contains('.') {
+        s
+    } else {
+        format!("{}.0", s)
+    }
+}
+
+/// Rewrite Rust's `1.5e-7` exponent style as Python's `1.5e-07`
+fn exp_notation(s: &str) -> String {
+    let Some((mantissa, exp)) = s.split_once('e') else {
+        return s.to_string();
+    };
+    let exp: i32 = exp.parse().unwrap_or(0);
+    format!(
+        "{}e{}{:02}",
+        mantissa,
+        if exp < 0 { '-' } else { '+' },
+        exp.abs()
+    )
+}
+
+pub(super) fn str_repr(s: &str) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut out = String::from(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                out.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+/// How deep `==` and ordering follow nested containers before giving up,
+/// which is how a list that contains itself ends
+const MAX_NESTING: usize = 200;
+
+fn nesting_error() -> PyErr {
+    PyErr::new(
+        "RecursionError",
+        "maximum recursion depth exceeded in comparison",
+    )
+}
+
+/// `==` for lookups that cannot fail; containers nested too deeply to
+/// compare count as unequal
+pub(super) fn py_eq(a: &PythonValue, b: &PythonValue) -> bool {
+    eq_within(a, b, 0).unwrap_or(false)
+}
+
+/// `==` as the operator evaluates it, raising RecursionError for
+/// containers nested too deeply to compare
+pub(super) fn py_eq_checked(a: &PythonValue, b: &PythonValue) -> Result<bool, PyErr> {
+    eq_within(a, b, 0).ok_or_else(nesting_error)
+}
+
+fn eq_within(a: &PythonValue, b: &PythonValue, depth: usize) -> Option<bool> {
+    use PythonValue as V;
+    if depth > MAX_NESTING {
+        return None;
+    }
+    Some(match (a, b) {
+        (V::Int(_) | V::Bool(_), V::Int(_) | V::Bool(_)) => a.as_int() == b.as_int(),
+        _ if a.is_number() && b.is_number() => a.as_float() == b.as_float(),
+        (V::String(x), V::String(y)) => x == y,
+        (V::None, V::None) => true,
+        (V::List(x), V::List(y)) => Rc::ptr_eq(x, y) || seq_eq(&x.borrow(), &y.borrow(), depth)?,
+        (V::Tuple(x), V::Tuple(y)) => seq_eq(x, y, depth)?,
+        (V::Dict(x), V::Dict(y)) => {
+            if Rc::ptr_eq(x, y) {
+                return Some(true);
+            }
+            let (x, y) = (x.borrow(), y.borrow());
+            if x.len() != y.len() {
+                return Some(false);
+            }
+            for (k, v) in x.iter() {
+                let Some((_, v2)) = y.iter().find(|(k2, _)| py_eq(k, k2)) else {
+                    return Some(false);
+                };
+                if !eq_within(v, v2, depth + 1)? {
+                    return Some(false);
+                }
+            }
+            true
+        }
+        (V::Range(a1, b1, c1), V::Range(a2, b2, c2)) => (a1, b1, c1) == (a2, b2, c2),
+        (V::Function(x), V::Function(y)) => Rc::ptr_eq(x, y),
+        (V::Builtin(x), V::Builtin(y)) => x == y,
+        (V::Module(x), V::Module(y)) => x == y,
+        (V::File(x), V::File(y)) => Rc::ptr_eq(x, y),
+        _ => false,
+    })
+}
+
+fn seq_eq(a: &[PythonValue], b: &[PythonValue], depth: usize) -> Option<bool> {
+    if a.len() != b.len() {
+        return Some(false);
+    }
+    for (x, y) in a.iter().zip(b) {
+        if !eq_within(x, y, depth + 1)? {
+            return Some(false);
+        }
+    }
+    Some(true)
+}
+
+pub(super) fn py_cmp(a: &PythonValue, b: &PythonValue) -> Result<Ordering, PyErr> {
+    cmp_within(a, b, 0)
+}
+
+fn cmp_within(a: &PythonValue, b: &PythonValue, depth: usize) -> Result<Ordering, PyErr> {
+    use PythonValue as V;
+    if depth > MAX_NESTING {
+        return Err(nesting_error());
+    }
+    match (a, b) {
+        (V::Int(_) | V::Bool(_), V::Int(_) | V::Bool(_)) => Ok(a.as_int().cmp(&b.as_int())),
+        _ if a.is_number() && b.is_number() => Ok(a
+            .as_float()
+            .partial_cmp(&b.as_float())
+            .unwrap_or(Ordering::Equal)),
+        (V::String(x), V::String(y)) => Ok(x.cmp(y)),
+        (V::List(x), V::List(y)) => seq_cmp(&x.borrow(), &y.borrow(), depth),
+        (V::Tuple(x), V::Tuple(y)) => seq_cmp(x, y, depth),
+        _ => Err(PyErr::new(
+            "TypeError",
+            &format!(
+                "'<' not supported between instances of '{}' and '{}'",
+                a.type_name(),
+                b.type_name()
+            ),
+        )),
+    }
+}
+
+fn seq_cmp(a: &[PythonValue], b: &[PythonValue], depth: usize) -> Result<Ordering, PyErr> {
+    for (x, y) in a.iter().zip(b) {
+        if !eq_within(x, y, depth + 1).ok_or_else(nesting_error)? {
+            return cmp_within(x, y, depth + 1);
+        }
+    }
+    Ok(a.len().cmp(&b.len()))
+}
+
+pub(super) fn check_hashable(v: &PythonValue) -> Result<(), PyErr> {
+    match v {
+        PythonValue::List(_) | PythonValue::Dict(_) => Err(PyErr::new(
+            "TypeError",
+            &format!("unhashable type: '{}'", v.type_name()),
+        )),
+        PythonValue::Tuple(items) => items.iter().try_for_each(check_hashable),
+        _ => Ok(()),
+    }
+}
+
+pub(super) fn dict_get(
+    items: &[(PythonValue, PythonValue)],
+    key: &PythonValue,
+) -> Option<PythonValue> {
+    items
+        .iter()
+        .find(|(k, _)| py_eq(k, key))
+        .map(|(_, v)| v.clone())
+}
+
+pub(super) fn dict_set(
+    items: &mut Vec<(PythonValue, PythonValue)>,
+    key: PythonValue,
+    value: PythonValue,
+) {
+    match items.iter_mut().find(|(k, _)| py_eq(k, &key)) {
+        Some(slot) => slot.1 = value,
+        None => items.push((key, value)),
+    }
+}
+
+/// Resolve `start:stop:step` against a sequence of `len` items into the
+/// indices it selects, following CPython's clamping rules
+pub(super) fn slice_indices(
+    len: usize,
+    start: Option<i64>,
+    stop: Option<i64>,
+    step: Option<i64>,
+) -> Result<Vec<usize>, PyErr> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err(PyErr::new("ValueError", "slice step cannot be zero"));
+    }
+    let (lower, upper) = if step > 0 { (0, len) } else { (-1, len - 1) };
+    let clamp = |v: Option<i64>, default: i64| match v {
+        None => default,
+        Some(v) if v < 0 => (v + len).max(lower),
+        Some(v) => v.min(upper),
+    };
+    let start = clamp(start, if step > 0 { lower } else { upper });
+    let stop = clamp(stop, if step > 0 { upper } else { lower });
+    let mut out = Vec::new();
+    let mut i = start;
+    while (step > 0 && i < stop) || (step < 0 && i > stop) {
+        out.push(i as usize);
+        i += step;
+    }
+    Ok(out)
+}
+
+/// Apply a format spec (`>10`, `.2f`, `,d`, `08.3f`...) the way `format()` does
+pub(super) fn format_spec(v: &PythonValue, spec: &str) -> Result<String, PyErr> {
+    if spec.is_empty() {
+        return Ok(v.to_string());
+    }
+    let invalid = || PyErr::new("ValueError", "Invalid format specifier");
+    let chars: Vec<char> = spec.chars().collect();
+    let mut i = 0;
+    let mut fill = ' ';
+    let mut align = None;
+    if chars.len() >= 2 && matches!(chars[1], '<' | '>' | '^' | '=') {
+        fill = chars[0];
+        align = Some(chars[1]);
+        i = 2;
+    } else if matches!(chars[0], '<' | '>' | '^' | '=') {
+        align = Some(chars[0]);
+        i = 1;
+    }
+    let mut sign = '-';
+    if let Some(&c @ ('+' | '-' | ' ')) = chars.get(i) {
+        sign = c;
+        i += 1;
+    }
+    let mut alt = false;
+    if chars.get(i) == Some(&'#') {
+        alt = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'0') {
+        if align.is_none() {
+            fill = '0';
+            align = Some('=');
+        }
+        i += 1;
+    }
+    let mut width = 0usize;
+    while let Some(d) = chars.get(i).and_then(|c| c.to_digit(10)) {
+        width = width * 10 + d as usize;
+        i += 1;
+    }
+    let mut grouping = None;
+    if let Some(&c @ (',' | '_')) = chars.get(i) {
+        grouping = Some(c);
+        i += 1;
+    }
+    let mut precision = None;
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        let mut p = 0usize;
+        let start = i;
+        while let Some(d) = chars.get(i).and_then(|c| c.to_digit(10)) {
+            p = p * 10 + d as usize;
+            i += 1;
+        }
+        if i == start {
+            return Err(invalid());
+        }
+        precision = Some(p);
+    }
+    let ty = chars.get(i).copied();
+    if i + usize::from(ty.is_some()) != chars.len() {
+        return Err(invalid());
+    }
+
+    // Bools only format as numbers when a numeric type code is given
+    let numeric = v.is_number() && !(ty.is_none() && matches!(v, PythonValue::Bool(_)));
+    let unknown_code = |t: char| {
+        PyErr::new(
+            "ValueError",
+            &format!(
+                "Unknown format code '{}' for object of type '{}'",
+                t,
+                v.type_name()
+            ),
+        )
+    };
+    let mut body = if !numeric {
+        match ty {
+            None | Some('s') => {
+                let s = v.to_string();
+                match precision {
+                    Some(p) => s.chars().take(p).collect(),
+                    None => s,
+                }
+            }
+            Some(t) => return Err(unknown_code(t)),
+        }
+    } else {
+        let is_int = v.as_int().is_some();
+        match ty {
+            None | Some('d' | 'n') if is_int => {
+                if precision.is_some() {
+                    return Err(PyErr::new(
+                        "ValueError",
+                        "Precision not allowed in integer format specifier",
+                    ));
+                }
+                v.as_int().unwrap_or(0).unsigned_abs().to_string()
+            }
+            Some(radix @ ('x' | 'X' | 'o' | 'b')) if is_int => {
+                let n = v.as_int().unwrap_or(0).unsigned_abs();
+                let (digits, prefix) = match radix {
+                    'x' => (format!("{:x}", n), "0x"),
+                    'X' => (format!("{:X}", n), "0X"),
+                    'o' => (format!("{:o}", n), "0o"),
+                    _ => (format!("{:b}", n), "0b"),
+                };
+                if alt {
+                    format!("{}{}", prefix, digits)
+                } else {
+                    digits
+                }
+            }
+            Some(t @ ('f' | 'F' | 'e' | 'E' | 'g' | 'G' | '%')) => {
+                let f = v.as_float().unwrap_or(0.0).abs();
+                let p = precision.unwrap_or(6);
+                match t {
+                    'f' | 'F' => format!("{:.*}", p, f),
+                    '%' => format!("{:.*}%", p, f * 100.0),
+                    'e' | 'E' => {
+                        let s = exp_notation(&format!("{:.*e}", p, f));
+                        if t == 'E' {
+                            s.to_uppercase()
+                        } else {
+                            s
+                        }
+                    }
+                    _ => general_format(f, p.max(1), alt),
+                }
+            }
+            None => {
+                let f = v.as_float().unwrap_or(0.0).abs();
+                match precision {
+                    Some(p) => general_format(f, p.max(1), alt),
+                    None => float_repr(f),
+                }
+            }
+            Some(t) => return Err(unknown_code(t)),
+        }
+    };
+
+    let negative = numeric && v.as_float().is_some_and(|f| f < 0.0);
+    if let Some(sep) = grouping.filter(|_| numeric) {
+        body = group_digits(&body, sep);
+    }
+    let sign_str = if !numeric {
+        ""
+    } else if negative {
+        "-"
+    } else {
+        match sign {
+            '+' => "+",
+            ' ' => " ",
+            _ => "",
+        }
+    };
+
+    let len = body.chars().count() + sign_str.len();
+    if len >= width {
+        return Ok(format!("{}{}", sign_str, body));
+    }
+    let pad = width - len;
+    let padding = |n: usize| fill.to_string().repeat(n);
+    let align = align.unwrap_or(if numeric { '>' } else { '<' });
+    Ok(match align {
+        '<' => format!("{}{}{}", sign_str, body, padding(pad)),
+        '^' => format!(
+            "{}{}{}{}",
+            padding(pad / 2),
+            sign_str,
+            body,
+            padding(pad - pad / 2)
+        ),
+        '=' => format!("{}{}{}", sign_str, padding(pad), body),
+        _ => format!("{}{}{}", padding(pad), sign_str, body),
+    })
+}
+
+/// `%g`-style formatting: fixed or exponent notation, trailing zeros removed
+fn general_format(f: f64, precision: usize, alt: bool) -> String {
+    if f == 0.0 {
+        return "0".into();
+    }
+    if !f.is_finite() {
+        return float_repr(f);
+    }
+    let exp = f.log10().floor() as i32;
+    let strip = |s: String| {
+        if alt || !s.contains('.') {
+            s
+        } else {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+    if exp < -4 || exp >= precision as i32 {
+        let s = format!("{:.*e}", precision - 1, f);
+        let (mantissa, e) = s.split_once('e').unwrap_or((&s, "0"));
+        exp_notation(&format!("{}e{}", strip(mantissa.to_string()), e))
+    } else {
+        let decimals = (precision as i32 - 1 - exp).max(0) as usize;
+        strip(format!("{:.*}", decimals, f))
+    }
+}
+
+fn group_digits(body: &str, sep: char) -> String {
+    let (int_part, rest) = match body.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => body.split_at(pos),
+        None => (body, ""),
+    };
+    let mut grouped = String::new();
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(sep);
+        }
+        grouped.push(c);
+    }
+    grouped + rest
+}
+
+/// printf-style `"%s: %5.2f" % (name, value)`
+pub(super) fn percent_format(fmt: &str, args: &PythonValue) -> Result<String, PyErr> {
+    let mut values: Vec<PythonValue> = match args {
+        PythonValue::Tuple(items) => items.iter().cloned().collect(),
+        other => vec![other.clone()],
+    };
+    values.reverse();
+    let mapping = match args {
+        PythonValue::Dict(items) => Some(items.borrow().clone()),
+        _ => None,
+    };
+    let mut out = String::new();
+    let chars: Vec<char> = fmt.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '%' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        let mut key = None;
+        if chars.get(i) == Some(&'(') {
+            let end = chars[i..]
+                .iter()
+                .position(|c| *c == ')')
+                .ok_or_else(|| PyErr::new("ValueError", "incomplete format key"))?;
+            key = Some(chars[i + 1..i + end].iter().collect::<String>());
+            i += end + 1;
+        }
+        let (mut left, mut zero, mut sign) = (false, false, "");
+        while let Some(&c @ ('-' | '+' | ' ' | '0' | '#')) = chars.get(i) {
+            match c {
+                '-' => left = true,
+                '0' => zero = true,
+                '+' => sign = "+",
+                ' ' if sign.is_empty() => sign = " ",
+                _ => {}
+            }
+            i += 1;
+        }
+        let mut width = String::new();
+        while let Some(c) = chars.get(i).filter(|c| c.is_ascii_digit()) {
+            width.push(*c);
+            i += 1;
+        }
+        let mut precision = String::new();
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            precision.push('.');
+            while let Some(c) = chars.get(i).filter(|c| c.is_ascii_digit()) {
+                precision.push(*c);
+                i += 1;
+            }
+        }
+        let Some(&conv) = chars.get(i) else {
+            return Err(PyErr::new("ValueError", "incomplete format"));
+        };
+        i += 1;
+        if conv == '%' {
+            out.push('%');
+            continue;
+        }
+        let value = match (&key, &mapping) {
+            (Some(k), Some(map)) => dict_get(map, &PythonValue::str(k.as_str()))
+                .ok_or_else(|| PyErr::new("KeyError", &str_repr(k)))?,
+            (Some(_), None) => return Err(PyErr::new("TypeError", "format requires a mapping")),
+            _ => values
+                .pop()
+                .ok_or_else(|| PyErr::new("TypeError", "not enough arguments for format string"))?,
+        };
+        let align = if left { "<" } else { "" };
+        let zero = if zero && !left { "0" } else { "" };
+        let text = match conv {
+            's' => format_spec(
+                &PythonValue::str(value.to_string()),
+                &format!("{}{}{}", align, width, precision),
+            )?,
+            'r' | 'a' => format_spec(
+                &PythonValue::str(value.repr()),
+                &format!("{}{}{}", align, width, precision),
+            )?,
+            'd' | 'i' | 'u' => {
+                let n = match &value {
+                    PythonValue::Float(f) => PythonValue::Int(f.trunc() as i64),
+                    v if v.as_int().is_some() => PythonValue::Int(v.as_int().unwrap_or(0)),
+                    v => {
+                        return Err(PyErr::new(
+                            "TypeError",
+                            &format!(
+                                "%d format: a real number is required, not {}",
+                                v.type_name()
+                            ),
+                        ))
+                    }
+                };
+                format_spec(&n, &format!("{}{}{}{}d", align, sign, zero, width))?
+            }
+            'c' => match &value {
+                PythonValue::Int(n) => char::from_u32(*n as u32)
+                    .map(String::from)
+                    .unwrap_or_default(),
+                other => other.to_string(),
+            },
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'x' | 'X' | 'o' => {
+                if !value.is_number() {
+                    return Err(PyErr::new(
+                        "TypeError",
+                        &format!("must be real number, not {}", value.type_name()),
+                    ));
+                }
+                let value = if matches!(conv, 'x' | 'X' | 'o') {
+                    PythonValue::Int(value.as_float().unwrap_or(0.0) as i64)
+                } else {
+                    value
+                };
+                format_spec(
+                    &value,
+                    &format!("{}{}{}{}{}{}", align, sign, zero, width, precision, conv),
+                )?
+            }
+            other => {
+                return Err(PyErr::new(
+                    "ValueError",
+                    &format!("unsupported format character '{}'", other),
+                ))
+            }
+        };
+        out.push_str(&text);
+    }
+    if mapping.is_none() && !values.is_empty() {
+        return Err(PyErr::new(
+            "TypeError",
+            "not all arguments converted during string formatting",
+        ));
+    }
+    Ok(out)
+}
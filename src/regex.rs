@@ -0,0 +1,294 @@
+//! A small backtracking regular expression matcher for grep and friends.
+//!
+//! Supports literals, `.`, bracket classes (`[a-z]`, `[^0-9]`), the `\d`,
+//! `\w` and `\s` shorthands, the `*`, `+` and `?` repeats and `^`/`$`
+//! anchors. Groups and alternation are not supported.
+
+#[derive(Clone, Debug)]
+enum Atom {
+    Any,
+    Char(char),
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Repeat {
+    One,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+#[derive(Clone, Debug)]
+struct Piece {
+    atom: Atom,
+    repeat: Repeat,
+}
+
+#[derive(Clone, Debug)]
+pub struct Regex {
+    pieces: Vec<Piece>,
+    anchored_start: bool,
+    anchored_end: bool,
+    ignore_case: bool,
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')];
+
+fn shorthand(c: char) -> Option<Atom> {
+    let (ranges, negated) = match c {
+        'd' => (DIGIT, false),
+        'D' => (DIGIT, true),
+        'w' => (WORD, false),
+        'W' => (WORD, true),
+        's' => (SPACE, false),
+        'S' => (SPACE, true),
+        _ => return None,
+    };
+    Some(Atom::Class {
+        ranges: ranges.to_vec(),
+        negated,
+    })
+}
+
+fn parse_class(chars: &[char], mut i: usize) -> Result<(Atom, usize), String> {
+    let negated = chars.get(i) == Some(&'^');
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err("unterminated [".to_string());
+        };
+        // `]` straight after `[` or `[^` is a literal
+        if c == ']' && !first {
+            return Ok((Atom::Class { ranges, negated }, i + 1));
+        }
+        first = false;
+        let lo = if c == '\\' {
+            i += 1;
+            match chars.get(i) {
+                Some(&e) => match shorthand(e) {
+                    Some(Atom::Class { ranges: r, .. }) => {
+                        ranges.extend(r);
+                        i += 1;
+                        continue;
+                    }
+                    _ => e,
+                },
+                None => return Err("trailing backslash".to_string()),
+            }
+        } else {
+            c
+        };
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&hi| hi != ']') {
+            let hi = chars[i + 2];
+            if hi < lo {
+                return Err(format!("invalid range {}-{}", lo, hi));
+            }
+            ranges.push((lo, hi));
+            i += 3;
+        } else {
+            ranges.push((lo, lo));
+            i += 1;
+        }
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pieces: Vec<Piece> = Vec::new();
+        let mut i = 0;
+        let anchored_start = chars.first() == Some(&'^');
+        if anchored_start {
+            i = 1;
+        }
+        let mut anchored_end = false;
+        while i < chars.len() {
+            let c = chars[i];
+            let atom = match c {
+                '$' if i + 1 == chars.len() => {
+                    anchored_end = true;
+                    i += 1;
+                    continue;
+                }
+                '.' => {
+                    i += 1;
+                    Atom::Any
+                }
+                '[' => {
+                    let (atom, next) = parse_class(&chars, i + 1)?;
+                    i = next;
+                    atom
+                }
+                '\\' => {
+                    let Some(&e) = chars.get(i + 1) else {
+                        return Err("trailing backslash".to_string());
+                    };
+                    i += 2;
+                    shorthand(e).unwrap_or(Atom::Char(e))
+                }
+                '*' | '+' | '?' if !pieces.is_empty() => {
+                    let last = pieces.last_mut().unwrap();
+                    if last.repeat != Repeat::One {
+                        return Err(format!("repeated '{}'", c));
+                    }
+                    last.repeat = match c {
+                        '*' => Repeat::ZeroOrMore,
+                        '+' => Repeat::OneOrMore,
+                        _ => Repeat::ZeroOrOne,
+                    };
+                    i += 1;
+                    continue;
+                }
+                _ => {
+                    i += 1;
+                    Atom::Char(c)
+                }
+            };
+            pieces.push(Piece {
+                atom,
+                repeat: Repeat::One,
+            });
+        }
+        Ok(Regex {
+            pieces,
+            anchored_start,
+            anchored_end,
+            ignore_case: false,
+        })
+    }
+
+    /// A pattern that matches `text` literally
+    pub fn literal(text: &str) -> Regex {
+        Regex {
+            pieces: text
+                .chars()
+                .map(|c| Piece {
+                    atom: Atom::Char(c),
+                    repeat: Repeat::One,
+                })
+                .collect(),
+            anchored_start: false,
+            anchored_end: false,
+            ignore_case: false,
+        }
+    }
+
+    pub fn ignore_case(mut self, ignore: bool) -> Regex {
+        self.ignore_case = ignore;
+        self
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /// Byte range of the leftmost match
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        let chars: Vec<char> = text.chars().collect();
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let last_start = if self.anchored_start { 0 } else { chars.len() };
+        (0..=last_start).find_map(|start| {
+            self.match_here(&chars, start, 0)
+                .map(|end| (offsets[start], offsets[end]))
+        })
+    }
+
+    /// Byte ranges of successive matches that do not overlap
+    pub fn find_all(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut from = 0;
+        while let Some((start, end)) = self.find(&text[from..]) {
+            found.push((from + start, from + end));
+            // `^` only matches once, and an empty match would never advance
+            if self.anchored_start || end == start {
+                break;
+            }
+            from += end;
+        }
+        found
+    }
+
+    fn atom_matches(&self, atom: &Atom, c: char) -> bool {
+        match atom {
+            Atom::Any => true,
+            Atom::Char(want) => {
+                *want == c || (self.ignore_case && want.to_lowercase().eq(c.to_lowercase()))
+            }
+            Atom::Class { ranges, negated } => {
+                let hit = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                let found = hit(c)
+                    || (self.ignore_case
+                        && (c.to_lowercase().any(hit) || c.to_uppercase().any(hit)));
+                found != *negated
+            }
+        }
+    }
+
+    // End of the match for pieces[piece..] starting at chars[pos], greedy
+    // with backtracking
+    fn match_here(&self, chars: &[char], pos: usize, piece: usize) -> Option<usize> {
+        let Some(p) = self.pieces.get(piece) else {
+            return (!self.anchored_end || pos == chars.len()).then_some(pos);
+        };
+        let (min, max) = match p.repeat {
+            Repeat::One => (1, 1),
+            Repeat::ZeroOrOne => (0, 1),
+            Repeat::ZeroOrMore => (0, usize::MAX),
+            Repeat::OneOrMore => (1, usize::MAX),
+        };
+        let mut count = 0;
+        while count < max
+            && pos + count < chars.len()
+            && self.atom_matches(&p.atom, chars[pos + count])
+        {
+            count += 1;
+        }
+        if count < min {
+            return None;
+        }
+        (min..=count)
+            .rev()
+            .find_map(|n| self.match_here(chars, pos + n, piece + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_grep_style_patterns() {
+        let re = |p: &str| Regex::new(p).unwrap();
+        assert!(re("^root").is_match("root:x:0:0"));
+        assert!(!re("^root").is_match("chroot"));
+        assert!(re("[0-9]+").is_match("port 8080"));
+        assert_eq!(re("[0-9]+").find("port 8080"), Some((5, 9)));
+        assert!(re("^a.c$").is_match("abc"));
+        assert!(!re("^a.c$").is_match("abcd"));
+        assert!(re("colou?r").is_match("color"));
+        assert!(re("x*y").is_match("y"));
+        assert!(re("\\d\\d:\\d\\d").is_match("at 12:30"));
+        assert!(re("a\\.b").is_match("a.b") && !re("a\\.b").is_match("axb"));
+        assert!(re("[^a-z]").is_match("abc1"));
+        assert!(re("[]x]").is_match("]"));
+        assert!(re("ERROR").ignore_case(true).is_match("an error"));
+        assert!(re("[A-Z]").ignore_case(true).is_match("q"));
+        assert!(Regex::literal("a.b").is_match("xa.b") && !Regex::literal("a.b").is_match("axb"));
+        assert!(Regex::new("[a-").is_err());
+        assert!(Regex::new("a**").is_err());
+    }
+}
@@ -1,7 +1,568 @@
-use crate::graphics::Graphics;
+use crate::graphics::{Color, FrameBuffer, Graphics, MatrixColor, MatrixScreensaver, MatrixStyle};
 use wasm_bindgen::prelude::*;
 use web_sys::{window, Document};
 
+// An idle-started screensaver moves on to another kind this often
+const ROTATE_MS: f64 = 120_000.0;
+
+/// The screensavers `screensaver <name>` and the idle timer can start
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreensaverKind {
+    Matrix,
+    Starfield,
+    Pipes,
+    Life,
+}
+
+impl ScreensaverKind {
+    pub const ALL: [ScreensaverKind; 4] = [
+        ScreensaverKind::Matrix,
+        ScreensaverKind::Starfield,
+        ScreensaverKind::Pipes,
+        ScreensaverKind::Life,
+    ];
+
+    pub fn parse(name: &str) -> Option<ScreensaverKind> {
+        match name.to_ascii_lowercase().as_str() {
+            "matrix" | "cmatrix" => Some(ScreensaverKind::Matrix),
+            "starfield" | "stars" => Some(ScreensaverKind::Starfield),
+            "pipes" => Some(ScreensaverKind::Pipes),
+            "life" | "gol" => Some(ScreensaverKind::Life),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScreensaverKind::Matrix => "matrix",
+            ScreensaverKind::Starfield => "starfield",
+            ScreensaverKind::Pipes => "pipes",
+            ScreensaverKind::Life => "life",
+        }
+    }
+}
+
+/// Kinds listed on the `savers=` line of `~/.config/screensaver`, in order
+/// and without repeats; every kind when the line is missing or names none
+pub fn parse_config(text: &str) -> Vec<ScreensaverKind> {
+    let mut kinds = Vec::new();
+    for line in text.lines() {
+        let Some(value) = line.trim().strip_prefix("savers=") else {
+            continue;
+        };
+        for kind in value
+            .split([' ', ',', '\t'])
+            .filter_map(ScreensaverKind::parse)
+        {
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+    }
+    if kinds.is_empty() {
+        kinds = ScreensaverKind::ALL.to_vec();
+    }
+    kinds
+}
+
+/// The matrix rain's `matrix_color=`, `matrix_density=` and
+/// `matrix_speed=` lines; anything missing or out of range keeps its default
+pub fn parse_matrix_style(text: &str) -> MatrixStyle {
+    let mut style = MatrixStyle::DEFAULT;
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "matrix_color" => {
+                if let Some(color) = MatrixColor::parse(value.trim()) {
+                    style.color = color;
+                }
+            }
+            "matrix_density" => {
+                if let Ok(density) = value.trim().parse() {
+                    if MatrixStyle::DENSITY_RANGE.contains(&density) {
+                        style.density = density;
+                    }
+                }
+            }
+            "matrix_speed" => {
+                if let Ok(speed) = value.trim().parse() {
+                    if MatrixStyle::SPEED_RANGE.contains(&speed) {
+                        style.speed = speed;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    style
+}
+
+/// `-C COLOR`, `-d PERCENT` and `-s SPEED` applied over `style`
+pub fn parse_matrix_flags(mut style: MatrixStyle, args: &[&str]) -> Result<MatrixStyle, String> {
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let Some(value) = args.next() else {
+            return Err(format!("option requires an argument -- '{}'", flag));
+        };
+        match *flag {
+            "-C" => {
+                style.color = MatrixColor::parse(value)
+                    .ok_or_else(|| format!("unknown color '{}' (green, amber, blue)", value))?;
+            }
+            "-d" => {
+                style.density = value
+                    .parse()
+                    .ok()
+                    .filter(|d| MatrixStyle::DENSITY_RANGE.contains(d))
+                    .ok_or_else(|| format!("density must be 5 to 100, not '{}'", value))?;
+            }
+            "-s" => {
+                style.speed = value
+                    .parse()
+                    .ok()
+                    .filter(|s| MatrixStyle::SPEED_RANGE.contains(s))
+                    .ok_or_else(|| format!("speed must be 0.25 to 4, not '{}'", value))?;
+            }
+            _ => return Err(format!("invalid option '{}'", flag)),
+        }
+    }
+    Ok(style)
+}
+
+pub fn format_config(kinds: &[ScreensaverKind], style: &MatrixStyle) -> String {
+    let names: Vec<&str> = kinds.iter().map(|k| k.name()).collect();
+    format!(
+        "# Screensavers the idle timer rotates between: matrix starfield pipes life\nsavers={}\n\
+         # Matrix rain: green, amber or blue; percent of columns raining; fall speed\n\
+         matrix_color={}\nmatrix_density={}\nmatrix_speed={}\n",
+        names.join(" "),
+        style.color.name(),
+        style.density,
+        style.speed
+    )
+}
+
+fn random() -> f64 {
+    js_sys::Math::random()
+}
+
+struct Star {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+/// Stars streaming towards the viewer
+pub struct Starfield {
+    width: u32,
+    height: u32,
+    stars: Vec<Star>,
+}
+
+impl Starfield {
+    const COUNT: usize = 600;
+    const DEPTH: f64 = 32.0;
+    const SPEED: f64 = 0.35;
+
+    pub fn new(width: u32, height: u32) -> Self {
+        let stars = (0..Self::COUNT)
+            .map(|_| Self::new_star(random() * Self::DEPTH))
+            .collect();
+        Starfield {
+            width,
+            height,
+            stars,
+        }
+    }
+
+    fn new_star(z: f64) -> Star {
+        Star {
+            x: (random() - 0.5) * 40.0,
+            y: (random() - 0.5) * 40.0,
+            z: z.max(0.5),
+        }
+    }
+
+    // Screen position of a star, if it is in front of the viewer and on screen
+    fn project(&self, star: &Star) -> Option<(u32, u32)> {
+        let scale = self.width.max(self.height) as f64 / 2.0;
+        let sx = self.width as f64 / 2.0 + star.x / star.z * scale;
+        let sy = self.height as f64 / 2.0 + star.y / star.z * scale;
+        (sx >= 0.0 && sy >= 0.0 && sx < self.width as f64 && sy < self.height as f64)
+            .then_some((sx as u32, sy as u32))
+    }
+
+    pub fn update(&mut self) {
+        for i in 0..self.stars.len() {
+            self.stars[i].z -= Self::SPEED;
+            if self.stars[i].z < 0.5 || self.project(&self.stars[i]).is_none() {
+                self.stars[i] = Self::new_star(Self::DEPTH);
+            }
+        }
+    }
+
+    pub fn render(&self, fb: &mut FrameBuffer) {
+        fb.clear_black();
+        for star in &self.stars {
+            let Some((x, y)) = self.project(star) else {
+                continue;
+            };
+            let near = 1.0 - star.z / Self::DEPTH;
+            let level = (60.0 + 195.0 * near) as u8;
+            // Close stars are bigger
+            let size = 1 + (near * 3.0) as u32;
+            fb.fill_rect(x, y, size, size, level, level, level);
+        }
+    }
+}
+
+/// The classic 3D pipes: tubes grow through a lattice one segment a frame
+/// and the screen starts over once it is busy enough
+pub struct Pipes {
+    width: u32,
+    height: u32,
+    occupied: Vec<bool>,
+    // Current head of the growing pipe, in lattice cells
+    head: Option<[i32; 3]>,
+    // Index into DIRS of the last segment
+    dir: usize,
+    color: (u8, u8, u8),
+    segments: u32,
+    clear_next: bool,
+}
+
+impl Pipes {
+    const GRID: i32 = 12;
+    const MAX_SEGMENTS: u32 = 600;
+    const DIRS: [[i32; 3]; 6] = [
+        [1, 0, 0],
+        [-1, 0, 0],
+        [0, 1, 0],
+        [0, -1, 0],
+        [0, 0, 1],
+        [0, 0, -1],
+    ];
+
+    pub fn new(width: u32, height: u32) -> Self {
+        Pipes {
+            width,
+            height,
+            occupied: vec![false; (Self::GRID * Self::GRID * Self::GRID) as usize],
+            head: None,
+            dir: 0,
+            color: (0, 0, 0),
+            segments: 0,
+            clear_next: true,
+        }
+    }
+
+    fn cell(p: [i32; 3]) -> Option<usize> {
+        p.iter()
+            .all(|&c| (0..Self::GRID).contains(&c))
+            .then(|| ((p[2] * Self::GRID + p[1]) * Self::GRID + p[0]) as usize)
+    }
+
+    // Lattice point to screen, seen from slightly above and to the side
+    fn project(&self, p: [f64; 3]) -> (f64, f64, f64) {
+        let half = (Self::GRID - 1) as f64 / 2.0;
+        let (x, y, z) = (p[0] - half, p[1] - half, p[2] - half);
+        let (sin_y, cos_y) = 0.5f64.sin_cos();
+        let (sin_x, cos_x) = 0.35f64.sin_cos();
+        let (x, z) = (x * cos_y - z * sin_y, x * sin_y + z * cos_y);
+        let (y, z) = (y * cos_x - z * sin_x, y * sin_x + z * cos_x);
+        let depth = z + Self::GRID as f64 * 1.6;
+        let scale = self.height.min(self.width) as f64 * 1.3 / depth;
+        (
+            self.width as f64 / 2.0 + x * scale,
+            self.height as f64 / 2.0 + y * scale,
+            scale,
+        )
+    }
+
+    fn start_pipe(&mut self) -> bool {
+        for _ in 0..20 {
+            let p = [0, 0, 0].map(|_| (random() * Self::GRID as f64) as i32);
+            if let Some(i) = Self::cell(p).filter(|&i| !self.occupied[i]) {
+                self.occupied[i] = true;
+                self.head = Some(p);
+                let hue = [
+                    (230, 60, 60),
+                    (60, 200, 80),
+                    (70, 110, 240),
+                    (230, 200, 60),
+                    (200, 90, 220),
+                    (70, 210, 220),
+                ];
+                self.color = hue[(random() * hue.len() as f64) as usize % hue.len()];
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn update(&mut self) {
+        if self.segments >= Self::MAX_SEGMENTS {
+            self.occupied.fill(false);
+            self.head = None;
+            self.segments = 0;
+            self.clear_next = true;
+        }
+        if self.head.is_none() && !self.start_pipe() {
+            self.segments = Self::MAX_SEGMENTS;
+        }
+    }
+
+    fn draw_joint(&self, fb: &mut FrameBuffer, p: [f64; 3], radius_scale: f64) {
+        let (sx, sy, scale) = self.project(p);
+        if sx < 0.0 || sy < 0.0 || sx >= self.width as f64 || sy >= self.height as f64 {
+            return;
+        }
+        let r = (scale * 0.22 * radius_scale).max(1.0);
+        let (cr, cg, cb) = self.color;
+        let shade = |c: u8, f: f64| (c as f64 * f).min(255.0) as u8;
+        fb.fill_circle(
+            sx as u32,
+            sy as u32,
+            r as u32,
+            &Color::rgb(shade(cr, 0.55), shade(cg, 0.55), shade(cb, 0.55)),
+        );
+        fb.fill_circle(
+            sx as u32,
+            sy as u32,
+            (r * 0.75) as u32,
+            &Color::rgb(cr, cg, cb),
+        );
+        // A highlight up and to the left sells the tube as round
+        let (hx, hy) = ((sx - r * 0.3).max(0.0), (sy - r * 0.3).max(0.0));
+        fb.fill_circle(
+            hx as u32,
+            hy as u32,
+            (r * 0.3) as u32,
+            &Color::rgb(shade(cr, 1.6), shade(cg, 1.6), shade(cb, 1.6)),
+        );
+    }
+
+    pub fn render(&mut self, fb: &mut FrameBuffer) {
+        if self.clear_next {
+            fb.clear_black();
+            self.clear_next = false;
+        }
+        let Some(head) = self.head else {
+            return;
+        };
+        // Usually carry straight on, otherwise turn somewhere free
+        let mut order: Vec<usize> = (0..Self::DIRS.len()).collect();
+        let turn = (random() * order.len() as f64) as usize % order.len();
+        order.rotate_left(turn);
+        if random() < 0.7 {
+            order.retain(|&d| d != self.dir);
+            order.insert(0, self.dir);
+        }
+        let next = order.into_iter().find_map(|d| {
+            let step = Self::DIRS[d];
+            let p = [head[0] + step[0], head[1] + step[1], head[2] + step[2]];
+            Self::cell(p)
+                .is_some_and(|i| !self.occupied[i])
+                .then_some((d, p))
+        });
+        let Some((dir, next)) = next else {
+            // Boxed in: cap it and start another pipe
+            self.draw_joint(fb, head.map(|c| c as f64), 1.4);
+            self.head = None;
+            return;
+        };
+        if let Some(i) = Self::cell(next) {
+            self.occupied[i] = true;
+        }
+        let from = head.map(|c| c as f64);
+        let to = next.map(|c| c as f64);
+        let steps = 10;
+        for s in 0..=steps {
+            let t = s as f64 / steps as f64;
+            let p = [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t);
+            self.draw_joint(fb, p, 1.0);
+        }
+        self.head = Some(next);
+        self.dir = dir;
+        self.segments += 1;
+    }
+}
+
+/// Conway's Game of Life on a wrapping grid, reseeded when it settles
+pub struct Life {
+    cols: usize,
+    rows: usize,
+    cell: u32,
+    cells: Vec<u8>,
+    generation: u32,
+    frame: u32,
+    last_population: usize,
+    stable_for: u32,
+}
+
+impl Life {
+    const CELL: u32 = 8;
+    // Generations per second is the frame rate divided by this
+    const FRAMES_PER_GEN: u32 = 4;
+    const MAX_GENERATIONS: u32 = 3000;
+
+    pub fn new(width: u32, height: u32) -> Self {
+        let cols = (width / Self::CELL).max(1) as usize;
+        let rows = (height / Self::CELL).max(1) as usize;
+        let mut life = Self::from_cells(cols, rows, vec![0; cols * rows]);
+        life.seed();
+        life
+    }
+
+    /// A grid with the given cells alive (non-zero), for tests and patterns
+    pub fn from_cells(cols: usize, rows: usize, cells: Vec<u8>) -> Self {
+        Life {
+            cols,
+            rows,
+            cell: Self::CELL,
+            cells,
+            generation: 0,
+            frame: 0,
+            last_population: 0,
+            stable_for: 0,
+        }
+    }
+
+    fn seed(&mut self) {
+        for c in &mut self.cells {
+            *c = (random() < 0.25) as u8;
+        }
+        self.generation = 0;
+        self.stable_for = 0;
+    }
+
+    pub fn alive(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.cols + x] != 0
+    }
+
+    /// Advance one generation; a living cell's value counts its age
+    pub fn step(&mut self) {
+        let (cols, rows) = (self.cols, self.rows);
+        let mut next = vec![0u8; self.cells.len()];
+        for y in 0..rows {
+            for x in 0..cols {
+                let mut neighbours = 0;
+                for dy in [rows - 1, 0, 1] {
+                    for dx in [cols - 1, 0, 1] {
+                        if (dx, dy) != (0, 0) && self.alive((x + dx) % cols, (y + dy) % rows) {
+                            neighbours += 1;
+                        }
+                    }
+                }
+                let age = self.cells[y * cols + x];
+                next[y * cols + x] = match (age, neighbours) {
+                    (1.., 2 | 3) => age.saturating_add(1),
+                    (0, 3) => 1,
+                    _ => 0,
+                };
+            }
+        }
+        self.cells = next;
+        self.generation += 1;
+    }
+
+    pub fn update(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+        if !self.frame.is_multiple_of(Self::FRAMES_PER_GEN) {
+            return;
+        }
+        self.step();
+        let population = self.cells.iter().filter(|&&c| c != 0).count();
+        // Oscillators keep the count steady, so a long flat run means it's done
+        if population == self.last_population {
+            self.stable_for += 1;
+        } else {
+            self.stable_for = 0;
+        }
+        self.last_population = population;
+        if population == 0 || self.stable_for > 60 || self.generation > Self::MAX_GENERATIONS {
+            self.seed();
+        }
+    }
+
+    pub fn render(&self, fb: &mut FrameBuffer) {
+        fb.clear_black();
+        let size = self.cell.saturating_sub(1).max(1);
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let age = self.cells[y * self.cols + x];
+                if age == 0 {
+                    continue;
+                }
+                // Newborn cells are bright green, old ones settle to blue
+                let old = (age.min(40) as f32 / 40.0 * 255.0) as u8;
+                fb.fill_rect(
+                    x as u32 * self.cell,
+                    y as u32 * self.cell,
+                    size,
+                    size,
+                    40,
+                    255 - old / 2,
+                    80 + old / 2,
+                );
+            }
+        }
+    }
+}
+
+enum Saver {
+    Matrix(MatrixScreensaver),
+    Starfield(Starfield),
+    Pipes(Pipes),
+    Life(Life),
+}
+
+impl Saver {
+    fn new(kind: ScreensaverKind, width: u32, height: u32, style: MatrixStyle) -> Saver {
+        match kind {
+            ScreensaverKind::Matrix => {
+                let seed = (random() * u32::MAX as f64) as u32;
+                Saver::Matrix(MatrixScreensaver::with_style(width, height, style, seed))
+            }
+            ScreensaverKind::Starfield => Saver::Starfield(Starfield::new(width, height)),
+            ScreensaverKind::Pipes => Saver::Pipes(Pipes::new(width, height)),
+            ScreensaverKind::Life => Saver::Life(Life::new(width, height)),
+        }
+    }
+
+    fn kind(&self) -> ScreensaverKind {
+        match self {
+            Saver::Matrix(_) => ScreensaverKind::Matrix,
+            Saver::Starfield(_) => ScreensaverKind::Starfield,
+            Saver::Pipes(_) => ScreensaverKind::Pipes,
+            Saver::Life(_) => ScreensaverKind::Life,
+        }
+    }
+
+    fn frame(&mut self, gfx: &mut Graphics) {
+        match self {
+            Saver::Matrix(s) => {
+                s.update();
+                s.draw(gfx.frame_buffer());
+            }
+            Saver::Starfield(s) => {
+                s.update();
+                s.render(gfx.frame_buffer());
+            }
+            Saver::Pipes(s) => {
+                s.update();
+                s.render(gfx.frame_buffer());
+            }
+            Saver::Life(s) => {
+                s.update();
+                s.render(gfx.frame_buffer());
+            }
+        }
+    }
+}
+
 fn document() -> Document {
     window().unwrap().document().unwrap()
 }
@@ -10,9 +571,53 @@ type LoopClosure = std::cell::RefCell<Option<wasm_bindgen::closure::Closure<dyn
 
 thread_local! {
     static GFX: std::cell::RefCell<Option<Graphics>> = const { std::cell::RefCell::new(None) };
-    static MATRIX: std::cell::RefCell<Option<crate::graphics::MatrixScreensaver>> = const { std::cell::RefCell::new(None) };
+    static SAVER: std::cell::RefCell<Option<Saver>> = const { std::cell::RefCell::new(None) };
     static LOOP: LoopClosure = const { std::cell::RefCell::new(None) };
     static KEYS: std::cell::RefCell<[bool; 256]> = const { std::cell::RefCell::new([false;256]) };
+    // Kinds the idle timer picks from; set from ~/.config/screensaver
+    static ROTATION: std::cell::RefCell<Vec<ScreensaverKind>> = std::cell::RefCell::new(ScreensaverKind::ALL.to_vec());
+    // Started by the idle timer rather than by name, so it takes turns
+    static ROTATING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    // Frame time the running saver hands over at; set on its first frame
+    static ROTATE_AT: std::cell::Cell<Option<f64>> = const { std::cell::Cell::new(None) };
+    // How the matrix rain looks, from ~/.config/screensaver
+    static MATRIX_STYLE: std::cell::Cell<MatrixStyle> = const { std::cell::Cell::new(MatrixStyle::DEFAULT) };
+    // A style for the next launch only, from `cmatrix -C amber` and the like
+    static MATRIX_ONCE: std::cell::Cell<Option<MatrixStyle>> = const { std::cell::Cell::new(None) };
+}
+
+pub fn set_matrix_style(style: MatrixStyle) {
+    MATRIX_STYLE.with(|s| s.set(style));
+}
+
+pub fn matrix_style() -> MatrixStyle {
+    MATRIX_STYLE.with(|s| s.get())
+}
+
+/// Rain in `style` the next time the matrix starts, then go back to the
+/// configured style
+pub fn set_matrix_style_once(style: MatrixStyle) {
+    MATRIX_ONCE.with(|s| s.set(Some(style)));
+}
+
+/// Replace the kinds the idle timer rotates between
+pub fn set_rotation(kinds: Vec<ScreensaverKind>) {
+    if !kinds.is_empty() {
+        ROTATION.with(|r| *r.borrow_mut() = kinds);
+    }
+}
+
+pub fn rotation() -> Vec<ScreensaverKind> {
+    ROTATION.with(|r| r.borrow().clone())
+}
+
+// A random kind from the rotation, other than `current` when there's a choice
+fn pick_kind(current: Option<ScreensaverKind>) -> ScreensaverKind {
+    let mut kinds = rotation();
+    if kinds.len() > 1 {
+        kinds.retain(|&k| Some(k) != current);
+    }
+    kinds[(random() * kinds.len() as f64) as usize % kinds.len()]
 }
 
 fn ensure_canvas(width: u32, height: u32) -> Result<web_sys::HtmlCanvasElement, JsValue> {
@@ -56,7 +661,7 @@ fn start_loop() {
         if l.borrow().is_some() {
             return;
         }
-        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |_ts: f64| {
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ts: f64| {
             // Check for ESC key to exit - before any borrows
             let should_exit = KEYS.with(|k| k.borrow()[27]);
             if should_exit {
@@ -64,13 +669,28 @@ fn start_loop() {
                 return;
             }
 
-            MATRIX.with(|m| {
-                if let Some(ref mut saver) = *m.borrow_mut() {
-                    GFX.with(|gfx| {
-                        if let Some(ref mut g) = *gfx.borrow_mut() {
-                            saver.update();
-                            saver.render(g);
+            GFX.with(|gfx| {
+                if let Some(ref mut g) = *gfx.borrow_mut() {
+                    // Idle-started savers take turns
+                    if ROTATING.with(|r| r.get()) {
+                        let due = ROTATE_AT.with(|r| *r.get().get_or_insert(ts + ROTATE_MS));
+                        if ts >= due {
+                            let current = SAVER.with(|s| s.borrow().as_ref().map(Saver::kind));
+                            let next = pick_kind(current);
+                            SAVER.with(|s| {
+                                *s.borrow_mut() =
+                                    Some(Saver::new(next, g.width(), g.height(), matrix_style()))
+                            });
+                            ROTATE_AT.with(|r| r.set(Some(ts + ROTATE_MS)));
+                        }
+                    }
+                    SAVER.with(|s| {
+                        if let Some(ref mut saver) = *s.borrow_mut() {
+                            let work_start = crate::clock::now_ms();
+                            saver.frame(g);
                             let _ = g.present();
+                            let work = crate::clock::now_ms() - work_start;
+                            crate::perf::record_frame("screensaver", ts, work);
                         }
                     });
                 }
@@ -92,8 +712,7 @@ fn start_loop() {
     });
 }
 
-#[wasm_bindgen]
-pub fn start_screensaver() {
+fn launch(kind: ScreensaverKind, rotate: bool) {
     if let Some(g) = document().get_element_by_id("graphics") {
         g.set_attribute("style", "display:block;").ok();
     }
@@ -107,24 +726,41 @@ pub fn start_screensaver() {
         let w = window().unwrap();
         let width = w.inner_width().unwrap().as_f64().unwrap() as u32;
         let height = w.inner_height().unwrap().as_f64().unwrap() as u32;
+        crate::doom::claim_2d_canvas();
         let _canvas = ensure_canvas(width, height).unwrap();
         let g = Graphics::new("game-canvas", width, height).unwrap();
 
-        MATRIX.with(|m| {
-            *m.borrow_mut() = Some(crate::graphics::MatrixScreensaver::new(
-                g.width(),
-                g.height(),
-            ));
+        let style = MATRIX_ONCE.with(|s| s.take()).unwrap_or_else(matrix_style);
+        SAVER.with(|s| {
+            *s.borrow_mut() = Some(Saver::new(kind, g.width(), g.height(), style));
         });
 
         *gfx.borrow_mut() = Some(g);
     });
+    ROTATING.with(|r| r.set(rotate));
+    ROTATE_AT.with(|r| r.set(None));
 
     crate::idle::set_game_active(false);
     crate::idle::set_screensaver_active(true);
     start_loop();
 }
 
+/// Start a screensaver picked at random from the rotation in
+/// `~/.config/screensaver`, switching to another every couple of minutes
+#[wasm_bindgen]
+pub fn start_screensaver() {
+    launch(pick_kind(None), true);
+}
+
+/// Start the named screensaver and keep it; unknown names fall back to matrix
+#[wasm_bindgen]
+pub fn start_screensaver_kind(name: &str) {
+    launch(
+        ScreensaverKind::parse(name).unwrap_or(ScreensaverKind::Matrix),
+        false,
+    );
+}
+
 #[wasm_bindgen]
 pub fn stop_screensaver() {
     // Stop the loop first
@@ -133,8 +769,8 @@ pub fn stop_screensaver() {
     });
 
     // Clear state
-    MATRIX.with(|m| {
-        *m.borrow_mut() = None;
+    SAVER.with(|s| {
+        *s.borrow_mut() = None;
     });
     GFX.with(|gfx| {
         *gfx.borrow_mut() = None;
@@ -151,3 +787,62 @@ pub fn stop_screensaver() {
     crate::idle::set_game_active(false);
     crate::idle::set_screensaver_active(false);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_life_blinker_and_config() {
+        // A horizontal blinker turns vertical and back
+        let mut cells = vec![0; 25];
+        cells[2 * 5 + 1..2 * 5 + 4].fill(1);
+        let mut life = Life::from_cells(5, 5, cells);
+        life.step();
+        let alive: Vec<(usize, usize)> = (0..25)
+            .map(|i| (i % 5, i / 5))
+            .filter(|&(x, y)| life.alive(x, y))
+            .collect();
+        assert_eq!(alive, vec![(2, 1), (2, 2), (2, 3)]);
+        life.step();
+        assert!(life.alive(1, 2) && life.alive(3, 2) && !life.alive(2, 1));
+
+        assert_eq!(
+            parse_config("# comment\nsavers=pipes, life pipes bogus\n"),
+            vec![ScreensaverKind::Pipes, ScreensaverKind::Life]
+        );
+        assert_eq!(parse_config(""), ScreensaverKind::ALL.to_vec());
+        let all = ScreensaverKind::ALL.to_vec();
+        assert_eq!(
+            parse_config(&format_config(&all, &MatrixStyle::DEFAULT)),
+            all
+        );
+    }
+
+    #[test]
+    fn test_matrix_style_config_and_flags() {
+        let style = MatrixStyle {
+            color: MatrixColor::Blue,
+            density: 40,
+            speed: 1.5,
+        };
+        let config = format_config(&[ScreensaverKind::Matrix], &style);
+        assert_eq!(parse_matrix_style(&config), style);
+        assert_eq!(parse_config(&config), vec![ScreensaverKind::Matrix]);
+        // Out of range or unknown values keep their defaults
+        assert_eq!(
+            parse_matrix_style("matrix_color=pink\nmatrix_density=500\nmatrix_speed=2\n"),
+            MatrixStyle {
+                speed: 2.0,
+                ..MatrixStyle::DEFAULT
+            }
+        );
+
+        let flagged = parse_matrix_flags(style, &["-C", "amber", "-d", "90"]).unwrap();
+        assert_eq!(flagged.color, MatrixColor::Amber);
+        assert_eq!((flagged.density, flagged.speed), (90, 1.5));
+        assert!(parse_matrix_flags(style, &["-s", "9"]).is_err());
+        assert!(parse_matrix_flags(style, &["-C"]).is_err());
+        assert!(parse_matrix_flags(style, &["-x", "1"]).is_err());
+    }
+}
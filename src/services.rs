@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+// Per-service journal lines kept before the oldest entries are dropped
+const JOURNAL_LIMIT: usize = 200;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ServiceState {
     Stopped,
@@ -15,6 +18,8 @@ pub struct Service {
     pub auto_start: bool,
     pub dependencies: Vec<String>,
     pub pid: Option<u32>,
+    pub description: String,
+    pub journal: Vec<String>,
 }
 
 impl Service {
@@ -25,7 +30,16 @@ impl Service {
             auto_start,
             dependencies,
             pid: None,
+            description: format!("{} service", name),
+            journal: Vec::new(),
+        }
+    }
+
+    fn log(&mut self, msg: String) {
+        if self.journal.len() >= JOURNAL_LIMIT {
+            self.journal.remove(0);
         }
+        self.journal.push(msg);
     }
 
     pub fn start(&mut self, pid: u32) -> bool {
@@ -51,6 +65,8 @@ impl Service {
     pub fn fail(&mut self) {
         self.state = ServiceState::Failed;
         self.pid = None;
+        let msg = format!("{}.service: Failed with result 'exit-code'.", self.name);
+        self.log(msg);
     }
 }
 
@@ -76,6 +92,11 @@ impl ServiceManager {
         manager.register("shell", true, vec!["init".to_string()]);
         manager.register("logger", true, vec!["init".to_string()]);
         manager.register("scheduler", true, vec!["init".to_string()]);
+        manager.set_description("init", "System and Service Manager");
+        manager.set_description("network", "Network Manager");
+        manager.set_description("shell", "Login Shell on tty1");
+        manager.set_description("logger", "System Logging Service");
+        manager.set_description("scheduler", "Process Scheduler");
 
         manager
     }
@@ -101,6 +122,8 @@ impl ServiceManager {
 
         if let Some(service) = self.services.get_mut(name) {
             if service.start(pid) {
+                service.log(format!("Starting {}...", service.description));
+                service.log(format!("Started {}.", service.description));
                 Ok(())
             } else {
                 Err(format!(
@@ -123,6 +146,8 @@ impl ServiceManager {
 
         if let Some(service) = self.services.get_mut(name) {
             if service.stop() {
+                service.log(format!("Stopping {}...", service.description));
+                service.log(format!("Stopped {}.", service.description));
                 Ok(())
             } else {
                 Err(format!(
@@ -188,6 +213,121 @@ impl ServiceManager {
         }
     }
 
+    pub fn contains(&self, name: &str) -> bool {
+        self.services.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Service> {
+        self.services.get(name)
+    }
+
+    /// Service names sorted alphabetically
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.services.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn set_description(&mut self, name: &str, description: &str) {
+        if let Some(service) = self.services.get_mut(name) {
+            service.description = description.to_string();
+        }
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        match self.services.get_mut(name) {
+            Some(service) => {
+                service.auto_start = enabled;
+                Ok(())
+            }
+            None => Err(format!("Service {} not found", name)),
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.services
+            .get(name)
+            .map(|s| s.auto_start)
+            .unwrap_or(false)
+    }
+
+    pub fn journal(&self, name: &str) -> Option<&[String]> {
+        self.services.get(name).map(|s| s.journal.as_slice())
+    }
+
+    pub fn log(&mut self, name: &str, msg: &str) {
+        if let Some(service) = self.services.get_mut(name) {
+            service.log(msg.to_string());
+        }
+    }
+
+    /// Running services that list `name` as a dependency
+    pub fn dependents(&self, name: &str) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .services
+            .values()
+            .filter(|s| s.dependencies.iter().any(|d| d == name))
+            .map(|s| s.name.clone())
+            .collect();
+        out.sort();
+        out
+    }
+
+    /// Topological start order for every registered service
+    pub fn start_order(&self) -> Vec<String> {
+        let mut order = Vec::new();
+        for name in self.names() {
+            self.visit_order(&name, &mut order, &mut Vec::new());
+        }
+        order
+    }
+
+    fn visit_order(&self, name: &str, order: &mut Vec<String>, stack: &mut Vec<String>) {
+        if order.iter().any(|n| n == name) || stack.iter().any(|n| n == name) {
+            return;
+        }
+        stack.push(name.to_string());
+        if let Some(service) = self.services.get(name) {
+            let mut deps = service.dependencies.clone();
+            deps.sort();
+            for dep in deps {
+                self.visit_order(&dep, order, stack);
+            }
+        }
+        stack.pop();
+        if self.services.contains_key(name) {
+            order.push(name.to_string());
+        }
+    }
+
+    /// Start a service after bringing up everything it depends on
+    pub fn start_with_dependencies(
+        &mut self,
+        name: &str,
+        spawn_pid_fn: &mut dyn FnMut(&str) -> Option<u32>,
+    ) -> Result<(), String> {
+        if !self.services.contains_key(name) {
+            return Err(format!("Service {} not found", name));
+        }
+        self.start_service_recursive(name, spawn_pid_fn)
+    }
+
+    /// Stop a service, stopping its running dependents first
+    pub fn stop_with_dependents(&mut self, name: &str) -> Result<Vec<String>, String> {
+        if !self.services.contains_key(name) {
+            return Err(format!("Service {} not found", name));
+        }
+        let mut stopped = Vec::new();
+        for dep in self.dependents(name) {
+            if self.get_state(&dep) == Some(ServiceState::Running) {
+                stopped.extend(self.stop_with_dependents(&dep)?);
+            }
+        }
+        self.stop(name)?;
+        stopped.push(name.to_string());
+        Ok(stopped)
+    }
+
     fn start_service_recursive(
         &mut self,
         name: &str,
@@ -1,34 +1,174 @@
 use crate::kernel::Kernel;
+use crate::process::ResourceLimits;
 use std::collections::{BTreeMap, HashMap};
 
+/// What a command gets to work with: the kernel, the shell and everything
+/// else the running system owns
+pub type SystemCtx = crate::system::System;
+
+/// What a command hands back: what it printed, what it complained about,
+/// and its exit status for `$?`, `&&` and `||`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CmdOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: i32,
+}
+
+impl CmdOutput {
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        CmdOutput {
+            stdout: stdout.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn error(status: i32, stderr: impl Into<String>) -> Self {
+        CmdOutput {
+            stderr: stderr.into(),
+            status,
+            ..Self::default()
+        }
+    }
+
+    /// The output of something that either ran or failed with a message
+    pub fn from_result(result: Result<String, String>) -> Self {
+        match result {
+            Ok(out) => Self::ok(out),
+            Err(e) => Self::error(1, e),
+        }
+    }
+
+    /// A usage message for a command called the wrong way
+    pub fn usage(text: impl Into<String>) -> Self {
+        Self::error(2, text)
+    }
+
+    /// Lines gathered over several operands, one stream each; any error
+    /// makes the status 1
+    pub fn collected(out: Vec<String>, errors: Vec<String>) -> Self {
+        CmdOutput {
+            stdout: out.join("\n"),
+            status: i32::from(!errors.is_empty()),
+            stderr: errors.join("\n"),
+        }
+    }
+
+    /// Both streams as the terminal shows them, errors last
+    pub fn flatten(self) -> String {
+        match (self.stdout.is_empty(), self.stderr.is_empty()) {
+            (_, true) => self.stdout,
+            (true, false) => self.stderr,
+            (false, false) => format!("{}\n{}", self.stdout, self.stderr),
+        }
+    }
+}
+
+/// A command the shell runs itself, found in the [`ProgramRegistry`] under
+/// its name and aliases
+pub trait Command {
+    fn name(&self) -> &'static str;
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Whether running it shows up in the process table, as external
+    /// commands do
+    fn spawns_process(&self) -> bool {
+        false
+    }
+    fn run(&self, ctx: &mut SystemCtx, args: &[&str]) -> CmdOutput;
+}
+
+/// A [`Command`] backed by a plain function, which is how the built-ins are
+/// written
+pub struct Builtin {
+    pub name: &'static str,
+    aliases: &'static [&'static str],
+    spawns: bool,
+    run: fn(&mut SystemCtx, &[&str]) -> CmdOutput,
+}
+impl Builtin {
+    pub const fn new(name: &'static str, run: fn(&mut SystemCtx, &[&str]) -> CmdOutput) -> Self {
+        Builtin {
+            name,
+            aliases: &[],
+            spawns: false,
+            run,
+        }
+    }
+    pub const fn with_aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+    pub const fn spawning(mut self) -> Self {
+        self.spawns = true;
+        self
+    }
+}
+impl Command for Builtin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+    fn spawns_process(&self) -> bool {
+        self.spawns
+    }
+    fn run(&self, ctx: &mut SystemCtx, args: &[&str]) -> CmdOutput {
+        (self.run)(ctx, args)
+    }
+}
+
 pub enum ProgramKind {
-    BuiltIn,
+    BuiltIn(&'static dyn Command),
+    /// Provided by an installed package, named here
+    Package(String),
 }
+#[derive(Default)]
 pub struct ProgramRegistry {
     progs: BTreeMap<String, ProgramKind>,
 }
-impl Default for ProgramRegistry {
-    fn default() -> Self {
-        Self::new()
-    }
-}
 impl ProgramRegistry {
     pub fn new() -> Self {
-        let mut r = ProgramRegistry {
-            progs: BTreeMap::new(),
-        };
-        for name in [
-            "echo", "cat", "ls", "pwd", "uname", "hostname", "id", "whoami", "free",
-        ]
-        .iter()
-        {
-            r.progs.insert((*name).into(), ProgramKind::BuiltIn);
-        }
-        r
+        Self::default()
     }
     pub fn has(&self, name: &str) -> bool {
         self.progs.contains_key(name)
     }
+    pub fn get(&self, name: &str) -> Option<&ProgramKind> {
+        self.progs.get(name)
+    }
+    pub fn is_builtin(&self, name: &str) -> bool {
+        matches!(self.progs.get(name), Some(ProgramKind::BuiltIn(_)))
+    }
+    /// Whether running `name` gets a process of its own
+    pub fn spawns(&self, name: &str) -> bool {
+        match self.progs.get(name) {
+            Some(ProgramKind::BuiltIn(command)) => command.spawns_process(),
+            Some(ProgramKind::Package(_)) => true,
+            None => false,
+        }
+    }
+    /// Every command name, built-in or installed, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.progs.keys().map(String::as_str)
+    }
+    pub fn register(&mut self, name: &str, kind: ProgramKind) {
+        self.progs.entry(name.into()).or_insert(kind);
+    }
+    /// Register a built-in under its name and every alias
+    pub fn register_command(&mut self, command: &'static dyn Command) {
+        self.register(command.name(), ProgramKind::BuiltIn(command));
+        for alias in command.aliases() {
+            self.register(alias, ProgramKind::BuiltIn(command));
+        }
+    }
+    /// Drop every command that came from a package
+    pub fn clear_packages(&mut self) {
+        self.progs
+            .retain(|_, kind| !matches!(kind, ProgramKind::Package(_)));
+    }
 }
 
 pub struct Shell {
@@ -36,6 +176,7 @@ pub struct Shell {
     pub env: HashMap<String, String>,
     pub aliases: HashMap<String, String>,
     pub registry: ProgramRegistry,
+    pub limits: ResourceLimits,
 }
 impl Default for Shell {
     fn default() -> Self {
@@ -49,6 +190,8 @@ impl Shell {
         env.insert("HOME".into(), "/home/user".into());
         env.insert("PATH".into(), "/bin".into());
         env.insert("USER".into(), "user".into());
+        env.insert("SHELL".into(), "/bin/bash".into());
+        env.insert("HISTSIZE".into(), "1000".into());
         env.insert("GITHUB".into(), "https://github.com/kpawnd".into());
         aliases.insert("ll".into(), "ls -la".into());
         aliases.insert("la".into(), "ls -A".into());
@@ -58,6 +201,7 @@ impl Shell {
             env,
             aliases,
             registry: ProgramRegistry::new(),
+            limits: ResourceLimits::default(),
         }
     }
 }
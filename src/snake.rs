@@ -0,0 +1,245 @@
+//! The `snake` game loop: drives `SnakeGame` on the game canvas, speeds it up
+//! as a round goes on, and reports finished scores to the terminal, which
+//! keeps the high-score table (see KP_SNAKE_SCORE in terminal.js).
+use crate::graphics::{Graphics, SnakeGame};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document};
+
+const CELL: u32 = 20;
+const MAX_WIDTH: u32 = 800;
+const MAX_HEIGHT: u32 = 600;
+// Ticks start this far apart and shrink by SPEEDUP_STEP_MS every
+// SPEEDUP_EVERY_MS of play, down to MIN_TICK_MS
+const START_TICK_MS: f64 = 140.0;
+const SPEEDUP_EVERY_MS: f64 = 15_000.0;
+const SPEEDUP_STEP_MS: f64 = 10.0;
+const MIN_TICK_MS: f64 = 50.0;
+
+type LoopClosure = std::cell::RefCell<Option<wasm_bindgen::closure::Closure<dyn FnMut(f64)>>>;
+
+thread_local! {
+    static GFX: std::cell::RefCell<Option<Graphics>> = const { std::cell::RefCell::new(None) };
+    static GAME: std::cell::RefCell<Option<Round>> = const { std::cell::RefCell::new(None) };
+    static LOOP: LoopClosure = const { std::cell::RefCell::new(None) };
+    static LISTENING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+fn document() -> Document {
+    window().unwrap().document().unwrap()
+}
+
+struct Round {
+    game: SnakeGame,
+    // RAF timestamps; `started` is None until the first frame of a round
+    started: Option<f64>,
+    last_tick: f64,
+    best: u32,
+    reported: bool,
+    redraw: bool,
+    // ESC was pressed; the loop shuts down on its next frame
+    quit: bool,
+}
+
+fn tick_interval(elapsed_ms: f64) -> f64 {
+    let steps = (elapsed_ms / SPEEDUP_EVERY_MS).floor();
+    (START_TICK_MS - steps * SPEEDUP_STEP_MS).max(MIN_TICK_MS)
+}
+
+impl Round {
+    fn frame(&mut self, ts: f64) {
+        let started = *self.started.get_or_insert(ts);
+        if self.game.is_game_over() {
+            self.report();
+            return;
+        }
+        if ts - self.last_tick >= tick_interval(ts - started) {
+            self.last_tick = ts;
+            self.game.update();
+            self.redraw = true;
+        }
+    }
+
+    // Hand the score to the terminal once per round
+    fn report(&mut self) {
+        if self.reported || self.game.score() == 0 {
+            return;
+        }
+        self.reported = true;
+        self.best = self.best.max(self.game.score());
+        let init = web_sys::CustomEventInit::new();
+        init.set_detail(&JsValue::from(self.game.score()));
+        if let Ok(event) = web_sys::CustomEvent::new_with_event_init_dict("KP_SNAKE_SCORE", &init) {
+            let _ = window().unwrap().dispatch_event(&event);
+        }
+    }
+
+    fn restart(&mut self) {
+        self.report();
+        self.game.reset();
+        self.started = None;
+        self.reported = false;
+        self.redraw = true;
+    }
+
+    fn render(&mut self, gfx: &mut Graphics) {
+        self.game.render(gfx);
+        let best = format!("BEST {}", self.best.max(self.game.score()));
+        let (w, h) = (gfx.width(), gfx.height());
+        let best_w = crate::graphics::text_size(&best, 2).0;
+        gfx.draw_text(&best, w.saturating_sub(best_w + 4), 4, 2, 255, 255, 0);
+        if self.game.is_game_over() {
+            let hint = "SPACE TO PLAY AGAIN - ESC TO QUIT";
+            let hint_w = crate::graphics::text_size(hint, 2).0;
+            let x = (w / 2).saturating_sub(hint_w / 2);
+            gfx.draw_text(hint, x, h / 2 + 30, 2, 200, 200, 200);
+        }
+        self.redraw = false;
+    }
+}
+
+fn with_round(f: impl FnOnce(&mut Round)) {
+    GAME.with(|g| {
+        if let Some(ref mut round) = *g.borrow_mut() {
+            f(round);
+        }
+    });
+}
+
+// One window listener for the page's lifetime; it ignores keys while no
+// game is running
+fn install_key_listener() {
+    if LISTENING.with(|l| l.replace(true)) {
+        return;
+    }
+    let keydown = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(
+        |e: web_sys::KeyboardEvent| {
+            if GAME.with(|g| g.borrow().is_none()) {
+                return;
+            }
+            let dir = match e.key().as_str() {
+                "ArrowUp" | "w" | "W" => "up",
+                "ArrowDown" | "s" | "S" => "down",
+                "ArrowLeft" | "a" | "A" => "left",
+                "ArrowRight" | "d" | "D" => "right",
+                " " | "Enter" => {
+                    with_round(|r| {
+                        if r.game.is_game_over() {
+                            r.restart();
+                        }
+                    });
+                    ""
+                }
+                "Escape" => {
+                    with_round(|r| r.quit = true);
+                    return;
+                }
+                _ => return,
+            };
+            if !dir.is_empty() {
+                with_round(|r| r.game.set_direction(dir));
+            }
+            // Keep arrows and space from scrolling the page
+            e.prevent_default();
+        },
+    ));
+    window()
+        .unwrap()
+        .add_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())
+        .unwrap();
+    keydown.forget();
+}
+
+fn start_loop() {
+    LOOP.with(|l| {
+        if l.borrow().is_some() {
+            return;
+        }
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ts: f64| {
+            if GAME.with(|g| g.borrow().as_ref().is_some_and(|r| r.quit)) {
+                stop_snake();
+                return;
+            }
+
+            GAME.with(|g| {
+                if let Some(ref mut round) = *g.borrow_mut() {
+                    round.frame(ts);
+                    if round.redraw {
+                        GFX.with(|gfx| {
+                            if let Some(ref mut gfx) = *gfx.borrow_mut() {
+                                round.render(gfx);
+                                let _ = gfx.present();
+                            }
+                        });
+                    }
+                }
+            });
+
+            LOOP.with(|l2| {
+                if let Some(ref cb) = *l2.borrow() {
+                    let _ = window()
+                        .unwrap()
+                        .request_animation_frame(cb.as_ref().unchecked_ref());
+                }
+            });
+        }) as Box<dyn FnMut(f64)>);
+        let _ = window()
+            .unwrap()
+            .request_animation_frame(closure.as_ref().unchecked_ref());
+        *l.borrow_mut() = Some(closure);
+    });
+}
+
+/// Start a game; `best` is the top of the high-score table, shown in the
+/// corner
+#[wasm_bindgen]
+pub fn start_snake(best: u32) {
+    let w = window().unwrap();
+    let fit = |avail: f64, max: u32| ((avail * 0.9) as u32).min(max) / CELL * CELL;
+    let width = fit(w.inner_width().unwrap().as_f64().unwrap(), MAX_WIDTH).max(CELL * 10);
+    let height = fit(w.inner_height().unwrap().as_f64().unwrap(), MAX_HEIGHT).max(CELL * 10);
+
+    crate::doom::claim_2d_canvas();
+    let Ok(gfx) = Graphics::new("game-canvas", width, height) else {
+        return;
+    };
+    GFX.with(|g| *g.borrow_mut() = Some(gfx));
+    GAME.with(|g| {
+        *g.borrow_mut() = Some(Round {
+            game: SnakeGame::new(width, height, CELL),
+            started: None,
+            last_tick: 0.0,
+            best,
+            reported: false,
+            redraw: true,
+            quit: false,
+        })
+    });
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:block;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:none;").ok();
+    }
+    install_key_listener();
+    crate::idle::set_game_active(true);
+    start_loop();
+}
+
+#[wasm_bindgen]
+pub fn stop_snake() {
+    LOOP.with(|l| *l.borrow_mut() = None);
+    // Quitting mid-round still counts the score
+    with_round(|r| r.report());
+    GAME.with(|g| *g.borrow_mut() = None);
+    GFX.with(|g| *g.borrow_mut() = None);
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:none;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:flex;").ok();
+    }
+    crate::idle::set_game_active(false);
+}
@@ -0,0 +1,2142 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// First line of every database file, ahead of the JSON body
+const HEADER: &str = "SQLite format 3\n";
+
+pub const VERSION: &str = "3.45.1 2024-01-30 16:01:20";
+
+// ---- storage ----
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl SqlValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            SqlValue::Null => "null",
+            SqlValue::Integer(_) => "integer",
+            SqlValue::Real(_) => "real",
+            SqlValue::Text(_) => "text",
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            SqlValue::Integer(i) => Some(*i as f64),
+            SqlValue::Real(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Numeric value as arithmetic sees it; text reads its numeric prefix
+    fn numeric(&self) -> SqlValue {
+        match self {
+            SqlValue::Text(s) => {
+                let s = s.trim();
+                let end = s
+                    .char_indices()
+                    .take_while(|(i, c)| {
+                        c.is_ascii_digit() || *c == '.' || (*i == 0 && (*c == '-' || *c == '+'))
+                    })
+                    .map(|(i, c)| i + c.len_utf8())
+                    .last()
+                    .unwrap_or(0);
+                let prefix = &s[..end];
+                match prefix.parse::<i64>() {
+                    Ok(i) => SqlValue::Integer(i),
+                    Err(_) => SqlValue::Real(prefix.parse().unwrap_or(0.0)),
+                }
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// `None` for NULL, which is neither true nor false
+    fn truth(&self) -> Option<bool> {
+        match self.numeric() {
+            SqlValue::Null => None,
+            SqlValue::Integer(i) => Some(i != 0),
+            SqlValue::Real(f) => Some(f != 0.0),
+            SqlValue::Text(_) => Some(false),
+        }
+    }
+}
+
+impl fmt::Display for SqlValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlValue::Null => Ok(()),
+            SqlValue::Integer(i) => write!(f, "{}", i),
+            SqlValue::Real(r) if r.fract() == 0.0 && r.abs() < 1e15 => write!(f, "{:.1}", r),
+            SqlValue::Real(r) => write!(f, "{}", r),
+            SqlValue::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Column {
+    name: String,
+    #[serde(default)]
+    decl: String,
+    #[serde(default)]
+    primary_key: bool,
+    #[serde(default)]
+    not_null: bool,
+    #[serde(default)]
+    default: Option<SqlValue>,
+}
+
+impl Column {
+    /// Type affinity from the declared type, by SQLite's substring rules
+    fn apply_affinity(&self, value: SqlValue) -> SqlValue {
+        let decl = self.decl.to_ascii_uppercase();
+        let numeric = |v: SqlValue| match &v {
+            SqlValue::Text(s) => match s.trim().parse::<i64>() {
+                Ok(i) => SqlValue::Integer(i),
+                Err(_) => s.trim().parse::<f64>().map_or(v, SqlValue::Real),
+            },
+            _ => v,
+        };
+        if decl.contains("INT") {
+            match numeric(value) {
+                SqlValue::Real(f) if f.fract() == 0.0 && f.abs() < 9.2e18 => {
+                    SqlValue::Integer(f as i64)
+                }
+                v => v,
+            }
+        } else if decl.contains("CHAR") || decl.contains("CLOB") || decl.contains("TEXT") {
+            match value {
+                SqlValue::Integer(_) | SqlValue::Real(_) => SqlValue::Text(value.to_string()),
+                v => v,
+            }
+        } else if decl.contains("REAL") || decl.contains("FLOA") || decl.contains("DOUB") {
+            match numeric(value) {
+                SqlValue::Integer(i) => SqlValue::Real(i as f64),
+                v => v,
+            }
+        } else if decl.is_empty() || decl.contains("BLOB") {
+            value
+        } else {
+            numeric(value)
+        }
+    }
+
+    /// `INTEGER PRIMARY KEY` columns alias the rowid and fill themselves in
+    fn is_rowid(&self) -> bool {
+        self.primary_key && self.decl.eq_ignore_ascii_case("INTEGER")
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Table {
+    name: String,
+    sql: String,
+    columns: Vec<Column>,
+    rows: Vec<Vec<SqlValue>>,
+}
+
+impl Table {
+    fn column(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Database {
+    tables: Vec<Table>,
+}
+
+impl Database {
+    /// Read a database file; an empty file is a new, empty database
+    pub fn load(text: &str) -> Result<Self, String> {
+        if text.is_empty() {
+            return Ok(Database::default());
+        }
+        text.strip_prefix(HEADER)
+            .and_then(|body| serde_json::from_str(body).ok())
+            .ok_or_else(|| "file is not a database".to_string())
+    }
+
+    pub fn dump(&self) -> String {
+        format!(
+            "{}{}",
+            HEADER,
+            serde_json::to_string(self).unwrap_or_else(|_| "{}".into())
+        )
+    }
+
+    fn table(&self, name: &str) -> Result<&Table, SqlError> {
+        self.tables
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| SqlError::parse(format!("no such table: {}", name)))
+    }
+
+    fn table_mut(&mut self, name: &str) -> Result<&mut Table, SqlError> {
+        self.tables
+            .iter_mut()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| SqlError::parse(format!("no such table: {}", name)))
+    }
+}
+
+// ---- errors ----
+
+pub struct SqlError {
+    msg: String,
+    /// Raised while stepping a statement rather than preparing it
+    runtime: bool,
+}
+
+impl SqlError {
+    fn parse(msg: impl Into<String>) -> Self {
+        SqlError {
+            msg: msg.into(),
+            runtime: false,
+        }
+    }
+
+    fn runtime(msg: impl Into<String>) -> Self {
+        SqlError {
+            msg: msg.into(),
+            runtime: true,
+        }
+    }
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.runtime { "Runtime" } else { "Parse" };
+        write!(f, "{} error: {}", kind, self.msg)
+    }
+}
+
+// ---- lexer ----
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    /// Bare identifier or keyword, as typed
+    Word(String),
+    /// `"quoted"` or `[bracketed]` identifier
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Real(f64),
+    Sym(&'static str),
+}
+
+struct Token {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "<>", "!=", "==", "||", "(", ")", ",", ";", "*", "=", "<", ">", "+", "-", "/", "%",
+    ".",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, SqlError> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if src[i..].starts_with("--") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            continue;
+        }
+        let tok = if c == '\'' || c == '"' || c == '[' || c == '`' {
+            let close = if c == '[' { ']' } else { c };
+            let mut text = String::new();
+            i += 1;
+            loop {
+                let Some(ch) = src[i..].chars().next() else {
+                    return Err(SqlError::parse(format!(
+                        "unrecognized token: \"{}\"",
+                        &src[start..]
+                    )));
+                };
+                i += ch.len_utf8();
+                if ch == close {
+                    // A doubled quote stands for itself
+                    if close != ']' && src[i..].starts_with(close) {
+                        text.push(close);
+                        i += 1;
+                        continue;
+                    }
+                    break;
+                }
+                text.push(ch);
+            }
+            if c == '\'' {
+                Tok::Str(text)
+            } else {
+                Tok::Ident(text)
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                if matches!(bytes[i], b'e' | b'E') && matches!(bytes.get(i + 1), Some(b'+' | b'-'))
+                {
+                    i += 1;
+                }
+                i += 1;
+            }
+            let text = &src[start..i];
+            match text.parse::<i64>() {
+                Ok(n) => Tok::Int(n),
+                Err(_) => match text.parse::<f64>() {
+                    Ok(f) => Tok::Real(f),
+                    Err(_) => {
+                        return Err(SqlError::parse(format!("unrecognized token: \"{}\"", text)))
+                    }
+                },
+            }
+        } else if c.is_alphabetic() || c == '_' || !c.is_ascii() {
+            let rest = &src[i..];
+            let len = rest
+                .char_indices()
+                .find(|(_, ch)| !(ch.is_alphanumeric() || *ch == '_' || *ch == '$'))
+                .map_or(rest.len(), |(n, _)| n);
+            i += len;
+            Tok::Word(rest[..len].to_string())
+        } else {
+            let Some(sym) = SYMBOLS.iter().find(|s| src[i..].starts_with(**s)) else {
+                return Err(SqlError::parse(format!("unrecognized token: \"{}\"", c)));
+            };
+            i += sym.len();
+            Tok::Sym(sym)
+        };
+        tokens.push(Token { tok, start, end: i });
+    }
+    Ok(tokens)
+}
+
+/// Whether the input ends in a `;` outside any string or comment
+fn is_complete(src: &str) -> bool {
+    let mut quote: Option<char> = None;
+    let mut last = None;
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                '-' if chars.peek() == Some(&'-') => {
+                    for ch in chars.by_ref() {
+                        if ch == '\n' {
+                            break;
+                        }
+                    }
+                }
+                c if c.is_whitespace() => {}
+                c => last = Some(c),
+            },
+        }
+    }
+    quote.is_none() && last == Some(';')
+}
+
+// ---- syntax tree ----
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Lit(SqlValue),
+    Col(String),
+    /// The `*` in `count(*)`
+    Star,
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    IsNull(Box<Expr>, bool),
+    Like(Box<Expr>, Box<Expr>, bool),
+    In(Box<Expr>, Vec<Expr>, bool),
+    Between(Box<Expr>, Box<Expr>, Box<Expr>, bool),
+    Func(String, Vec<Expr>),
+}
+
+enum Item {
+    All,
+    Expr(Expr, String),
+}
+
+struct Select {
+    distinct: bool,
+    items: Vec<Item>,
+    from: Option<String>,
+    filter: Option<Expr>,
+    group: Vec<Expr>,
+    having: Option<Expr>,
+    order: Vec<(Expr, bool)>,
+    limit: Option<Expr>,
+    offset: Option<Expr>,
+}
+
+enum Statement {
+    Create {
+        table: Table,
+        if_not_exists: bool,
+    },
+    Drop {
+        name: String,
+        if_exists: bool,
+    },
+    Insert {
+        table: String,
+        columns: Option<Vec<String>>,
+        rows: Vec<Vec<Expr>>,
+    },
+    Select(Select),
+    Update {
+        table: String,
+        sets: Vec<(String, Expr)>,
+        filter: Option<Expr>,
+    },
+    Delete {
+        table: String,
+        filter: Option<Expr>,
+    },
+}
+
+// ---- parser ----
+
+/// Words that end an expression or clause and so can't be bare aliases
+const RESERVED: &[&str] = &[
+    "ADD", "ALL", "AND", "AS", "ASC", "BETWEEN", "BY", "CREATE", "DEFAULT", "DELETE", "DESC",
+    "DISTINCT", "DROP", "ELSE", "FROM", "GROUP", "HAVING", "IN", "INSERT", "INTO", "IS", "LIKE",
+    "LIMIT", "NOT", "NULL", "OFFSET", "ON", "OR", "ORDER", "SELECT", "SET", "TABLE", "UPDATE",
+    "VALUES", "WHERE",
+];
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn error(&self) -> SqlError {
+        match self.tokens.get(self.pos) {
+            Some(t) => SqlError::parse(format!(
+                "near \"{}\": syntax error",
+                &self.src[t.start..t.end]
+            )),
+            None => SqlError::parse("incomplete input"),
+        }
+    }
+
+    fn at_kw(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Word(w)) if w.eq_ignore_ascii_case(kw))
+    }
+
+    fn eat_kw(&mut self, kw: &str) -> bool {
+        let hit = self.at_kw(kw);
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    fn expect_kw(&mut self, kw: &str) -> Result<(), SqlError> {
+        if self.eat_kw(kw) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        let hit = matches!(self.peek(), Some(Tok::Sym(s)) if *s == sym);
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> Result<(), SqlError> {
+        if self.eat_sym(sym) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, SqlError> {
+        match self.peek() {
+            Some(Tok::Word(w)) if !RESERVED.iter().any(|r| w.eq_ignore_ascii_case(r)) => {
+                let w = w.clone();
+                self.pos += 1;
+                Ok(w)
+            }
+            Some(Tok::Ident(w)) | Some(Tok::Str(w)) => {
+                let w = w.clone();
+                self.pos += 1;
+                Ok(w)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, SqlError> {
+        let start = self.tokens.get(self.pos).map_or(0, |t| t.start);
+        if self.eat_kw("CREATE") {
+            self.expect_kw("TABLE")?;
+            let if_not_exists = self.eat_kw("IF");
+            if if_not_exists {
+                self.expect_kw("NOT")?;
+                self.expect_kw("EXISTS")?;
+            }
+            let name = self.ident()?;
+            self.expect_sym("(")?;
+            let mut columns: Vec<Column> = Vec::new();
+            loop {
+                if ["PRIMARY", "UNIQUE", "CHECK", "FOREIGN", "CONSTRAINT"]
+                    .iter()
+                    .any(|kw| self.at_kw(kw))
+                {
+                    self.skip_constraint();
+                } else {
+                    let column = self.column_def()?;
+                    if columns
+                        .iter()
+                        .any(|c| c.name.eq_ignore_ascii_case(&column.name))
+                    {
+                        return Err(SqlError::parse(format!(
+                            "duplicate column name: {}",
+                            column.name
+                        )));
+                    }
+                    columns.push(column);
+                }
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+            self.expect_sym(")")?;
+            let end = self.tokens[self.pos - 1].end;
+            return Ok(Statement::Create {
+                table: Table {
+                    name,
+                    sql: self.src[start..end].to_string(),
+                    columns,
+                    rows: Vec::new(),
+                },
+                if_not_exists,
+            });
+        }
+        if self.eat_kw("DROP") {
+            self.expect_kw("TABLE")?;
+            let if_exists = self.eat_kw("IF");
+            if if_exists {
+                self.expect_kw("EXISTS")?;
+            }
+            return Ok(Statement::Drop {
+                name: self.ident()?,
+                if_exists,
+            });
+        }
+        if self.eat_kw("INSERT") {
+            self.expect_kw("INTO")?;
+            let table = self.ident()?;
+            let columns = if self.eat_sym("(") {
+                let names = self.ident_list()?;
+                self.expect_sym(")")?;
+                Some(names)
+            } else {
+                None
+            };
+            self.expect_kw("VALUES")?;
+            let mut rows = Vec::new();
+            loop {
+                self.expect_sym("(")?;
+                rows.push(self.expr_list()?);
+                self.expect_sym(")")?;
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+            return Ok(Statement::Insert {
+                table,
+                columns,
+                rows,
+            });
+        }
+        if self.eat_kw("UPDATE") {
+            let table = self.ident()?;
+            self.expect_kw("SET")?;
+            let mut sets = Vec::new();
+            loop {
+                let column = self.ident()?;
+                self.expect_sym("=")?;
+                sets.push((column, self.expr()?));
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+            let filter = self.where_clause()?;
+            return Ok(Statement::Update {
+                table,
+                sets,
+                filter,
+            });
+        }
+        if self.eat_kw("DELETE") {
+            self.expect_kw("FROM")?;
+            let table = self.ident()?;
+            let filter = self.where_clause()?;
+            return Ok(Statement::Delete { table, filter });
+        }
+        if self.eat_kw("SELECT") {
+            return Ok(Statement::Select(self.select()?));
+        }
+        Err(self.error())
+    }
+
+    fn column_def(&mut self) -> Result<Column, SqlError> {
+        let name = self.ident()?;
+        let mut decl = Vec::new();
+        while let Some(Tok::Word(w)) = self.peek() {
+            if [
+                "PRIMARY",
+                "NOT",
+                "UNIQUE",
+                "DEFAULT",
+                "CHECK",
+                "REFERENCES",
+                "COLLATE",
+            ]
+            .iter()
+            .any(|kw| w.eq_ignore_ascii_case(kw))
+            {
+                break;
+            }
+            decl.push(w.clone());
+            self.pos += 1;
+        }
+        // `VARCHAR(20)`, `DECIMAL(10, 2)`
+        if self.eat_sym("(") {
+            while !self.eat_sym(")") {
+                if self.peek().is_none() {
+                    return Err(self.error());
+                }
+                self.pos += 1;
+            }
+        }
+        let mut column = Column {
+            name,
+            decl: decl.join(" "),
+            primary_key: false,
+            not_null: false,
+            default: None,
+        };
+        loop {
+            if self.eat_kw("PRIMARY") {
+                self.expect_kw("KEY")?;
+                column.primary_key = true;
+                let _ = self.eat_kw("ASC") || self.eat_kw("DESC");
+                self.eat_kw("AUTOINCREMENT");
+            } else if self.eat_kw("NOT") {
+                self.expect_kw("NULL")?;
+                column.not_null = true;
+            } else if self.eat_kw("UNIQUE") {
+            } else if self.eat_kw("DEFAULT") {
+                let value = match self.primary()? {
+                    Expr::Lit(v) => v,
+                    Expr::Neg(inner) => match *inner {
+                        Expr::Lit(v) => negate(v),
+                        _ => return Err(self.error()),
+                    },
+                    _ => return Err(self.error()),
+                };
+                column.default = Some(value);
+            } else if self.at_kw("CHECK") || self.at_kw("REFERENCES") || self.at_kw("COLLATE") {
+                self.skip_constraint();
+            } else {
+                return Ok(column);
+            }
+        }
+    }
+
+    /// Step over a constraint clause this engine does not enforce
+    fn skip_constraint(&mut self) {
+        let mut depth = 0;
+        while let Some(tok) = self.peek() {
+            match tok {
+                Tok::Sym("(") => depth += 1,
+                Tok::Sym(")") if depth == 0 => return,
+                Tok::Sym(")") => depth -= 1,
+                Tok::Sym(",") if depth == 0 => return,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn ident_list(&mut self) -> Result<Vec<String>, SqlError> {
+        let mut names = vec![self.ident()?];
+        while self.eat_sym(",") {
+            names.push(self.ident()?);
+        }
+        Ok(names)
+    }
+
+    fn expr_list(&mut self) -> Result<Vec<Expr>, SqlError> {
+        let mut exprs = vec![self.expr()?];
+        while self.eat_sym(",") {
+            exprs.push(self.expr()?);
+        }
+        Ok(exprs)
+    }
+
+    fn where_clause(&mut self) -> Result<Option<Expr>, SqlError> {
+        if self.eat_kw("WHERE") {
+            Ok(Some(self.expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn select(&mut self) -> Result<Select, SqlError> {
+        let distinct = self.eat_kw("DISTINCT");
+        self.eat_kw("ALL");
+        let mut items = Vec::new();
+        loop {
+            if self.eat_sym("*") {
+                items.push(Item::All);
+            } else {
+                let start = self.tokens.get(self.pos).map_or(0, |t| t.start);
+                let expr = self.expr()?;
+                let end = self.tokens[self.pos - 1].end;
+                let label = if self.eat_kw("AS")
+                    || matches!(self.peek(), Some(Tok::Word(_) | Tok::Ident(_)))
+                        && !self.at_kw("FROM")
+                {
+                    self.ident()?
+                } else {
+                    self.src[start..end].to_string()
+                };
+                items.push(Item::Expr(expr, label));
+            }
+            if !self.eat_sym(",") {
+                break;
+            }
+        }
+        let from = if self.eat_kw("FROM") {
+            Some(self.ident()?)
+        } else {
+            None
+        };
+        let filter = self.where_clause()?;
+        let group = if self.eat_kw("GROUP") {
+            self.expect_kw("BY")?;
+            self.expr_list()?
+        } else {
+            Vec::new()
+        };
+        let having = if self.eat_kw("HAVING") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        let mut order = Vec::new();
+        if self.eat_kw("ORDER") {
+            self.expect_kw("BY")?;
+            loop {
+                let expr = self.expr()?;
+                let desc = self.eat_kw("DESC");
+                if !desc {
+                    self.eat_kw("ASC");
+                }
+                order.push((expr, desc));
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+        }
+        let (mut limit, mut offset) = (None, None);
+        if self.eat_kw("LIMIT") {
+            limit = Some(self.expr()?);
+            if self.eat_kw("OFFSET") {
+                offset = Some(self.expr()?);
+            } else if self.eat_sym(",") {
+                // `LIMIT skip, count`
+                offset = limit.take();
+                limit = Some(self.expr()?);
+            }
+        }
+        Ok(Select {
+            distinct,
+            items,
+            from,
+            filter,
+            group,
+            having,
+            order,
+            limit,
+            offset,
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr, SqlError> {
+        let mut left = self.and_expr()?;
+        while self.eat_kw("OR") {
+            left = Expr::Binary("OR", Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, SqlError> {
+        let mut left = self.not_expr()?;
+        while self.eat_kw("AND") {
+            left = Expr::Binary("AND", Box::new(left), Box::new(self.not_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, SqlError> {
+        if self.eat_kw("NOT") {
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, SqlError> {
+        let left = self.additive()?;
+        if self.eat_kw("IS") {
+            let negated = self.eat_kw("NOT");
+            self.expect_kw("NULL")?;
+            return Ok(Expr::IsNull(Box::new(left), negated));
+        }
+        if self.eat_kw("ISNULL") {
+            return Ok(Expr::IsNull(Box::new(left), false));
+        }
+        if self.eat_kw("NOTNULL") {
+            return Ok(Expr::IsNull(Box::new(left), true));
+        }
+        let negated = self.eat_kw("NOT");
+        if self.eat_kw("LIKE") {
+            return Ok(Expr::Like(
+                Box::new(left),
+                Box::new(self.additive()?),
+                negated,
+            ));
+        }
+        if self.eat_kw("IN") {
+            self.expect_sym("(")?;
+            let list = self.expr_list()?;
+            self.expect_sym(")")?;
+            return Ok(Expr::In(Box::new(left), list, negated));
+        }
+        if self.eat_kw("BETWEEN") {
+            let low = self.additive()?;
+            self.expect_kw("AND")?;
+            let high = self.additive()?;
+            return Ok(Expr::Between(
+                Box::new(left),
+                Box::new(low),
+                Box::new(high),
+                negated,
+            ));
+        }
+        if negated {
+            return Err(self.error());
+        }
+        for op in ["=", "==", "!=", "<>", "<=", ">=", "<", ">"] {
+            if self.eat_sym(op) {
+                let op = match op {
+                    "==" => "=",
+                    "<>" => "!=",
+                    other => other,
+                };
+                return Ok(Expr::Binary(op, Box::new(left), Box::new(self.additive()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, SqlError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = if self.eat_sym("+") {
+                "+"
+            } else if self.eat_sym("-") {
+                "-"
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, SqlError> {
+        let mut left = self.concat()?;
+        loop {
+            let op = if self.eat_sym("*") {
+                "*"
+            } else if self.eat_sym("/") {
+                "/"
+            } else if self.eat_sym("%") {
+                "%"
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.concat()?));
+        }
+    }
+
+    fn concat(&mut self) -> Result<Expr, SqlError> {
+        let mut left = self.unary()?;
+        while self.eat_sym("||") {
+            left = Expr::Binary("||", Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, SqlError> {
+        if self.eat_sym("-") {
+            return Ok(match self.unary()? {
+                Expr::Lit(v) => Expr::Lit(negate(v)),
+                e => Expr::Neg(Box::new(e)),
+            });
+        }
+        if self.eat_sym("+") {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, SqlError> {
+        let Some(tok) = self.peek().cloned() else {
+            return Err(self.error());
+        };
+        match tok {
+            Tok::Int(n) => {
+                self.pos += 1;
+                Ok(Expr::Lit(SqlValue::Integer(n)))
+            }
+            Tok::Real(f) => {
+                self.pos += 1;
+                Ok(Expr::Lit(SqlValue::Real(f)))
+            }
+            Tok::Str(s) => {
+                self.pos += 1;
+                Ok(Expr::Lit(SqlValue::Text(s)))
+            }
+            Tok::Sym("(") => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                self.expect_sym(")")?;
+                Ok(inner)
+            }
+            Tok::Word(w) if w.eq_ignore_ascii_case("NULL") => {
+                self.pos += 1;
+                Ok(Expr::Lit(SqlValue::Null))
+            }
+            Tok::Word(w) if w.eq_ignore_ascii_case("TRUE") || w.eq_ignore_ascii_case("FALSE") => {
+                self.pos += 1;
+                Ok(Expr::Lit(SqlValue::Integer(
+                    w.eq_ignore_ascii_case("TRUE") as i64
+                )))
+            }
+            Tok::Word(_) | Tok::Ident(_) => {
+                let name = self.ident()?;
+                if self.eat_sym("(") {
+                    let args = if self.eat_sym("*") {
+                        vec![Expr::Star]
+                    } else if self.eat_sym(")") {
+                        return Ok(Expr::Func(name.to_ascii_lowercase(), Vec::new()));
+                    } else {
+                        self.expr_list()?
+                    };
+                    self.expect_sym(")")?;
+                    return Ok(Expr::Func(name.to_ascii_lowercase(), args));
+                }
+                // `table.column` names the column
+                if self.eat_sym(".") {
+                    return Ok(Expr::Col(self.ident()?));
+                }
+                Ok(Expr::Col(name))
+            }
+            _ => Err(self.error()),
+        }
+    }
+}
+
+fn negate(v: SqlValue) -> SqlValue {
+    match v.numeric() {
+        SqlValue::Integer(i) => SqlValue::Integer(i.wrapping_neg()),
+        SqlValue::Real(f) => SqlValue::Real(-f),
+        other => other,
+    }
+}
+
+// ---- evaluation ----
+
+const AGGREGATES: &[&str] = &["count", "sum", "total", "avg", "min", "max", "group_concat"];
+
+fn is_aggregate(name: &str, args: &[Expr]) -> bool {
+    // min() and max() with several arguments are the scalar versions
+    AGGREGATES.contains(&name) && !(matches!(name, "min" | "max") && args.len() > 1)
+}
+
+fn has_aggregate(expr: &Expr) -> bool {
+    match expr {
+        Expr::Func(name, args) => is_aggregate(name, args) || args.iter().any(has_aggregate),
+        Expr::Neg(e) | Expr::Not(e) | Expr::IsNull(e, _) => has_aggregate(e),
+        Expr::Binary(_, a, b) | Expr::Like(a, b, _) => has_aggregate(a) || has_aggregate(b),
+        Expr::In(e, list, _) => has_aggregate(e) || list.iter().any(has_aggregate),
+        Expr::Between(a, b, c, _) => has_aggregate(a) || has_aggregate(b) || has_aggregate(c),
+        _ => false,
+    }
+}
+
+/// Report the first column reference `expr` makes that the table lacks
+fn check_columns(expr: &Expr, columns: &[Column], aliases: &[&str]) -> Result<(), SqlError> {
+    let check = |e: &Expr| check_columns(e, columns, aliases);
+    match expr {
+        Expr::Col(name) => {
+            if columns.iter().any(|c| c.name.eq_ignore_ascii_case(name))
+                || aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+            {
+                Ok(())
+            } else {
+                Err(SqlError::parse(format!("no such column: {}", name)))
+            }
+        }
+        Expr::Func(_, args) => args.iter().try_for_each(check),
+        Expr::Neg(e) | Expr::Not(e) | Expr::IsNull(e, _) => check(e),
+        Expr::Binary(_, a, b) | Expr::Like(a, b, _) => check(a).and(check(b)),
+        Expr::In(e, list, _) => check(e).and(list.iter().try_for_each(check)),
+        Expr::Between(a, b, c, _) => check(a).and(check(b)).and(check(c)),
+        _ => Ok(()),
+    }
+}
+
+/// What an expression can see: the current row, and its group when aggregating
+struct Ctx<'a> {
+    columns: &'a [Column],
+    row: &'a [SqlValue],
+    group: Option<&'a [&'a [SqlValue]]>,
+}
+
+/// SQLite's cross-type order: NULL < numbers < text
+fn compare(a: &SqlValue, b: &SqlValue) -> Ordering {
+    use SqlValue as V;
+    let rank = |v: &SqlValue| match v {
+        V::Null => 0,
+        V::Integer(_) | V::Real(_) => 1,
+        V::Text(_) => 2,
+    };
+    match (a, b) {
+        (V::Integer(x), V::Integer(y)) => x.cmp(y),
+        (V::Text(x), V::Text(y)) => x.cmp(y),
+        (V::Integer(_) | V::Real(_), V::Integer(_) | V::Real(_)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn bool_value(b: Option<bool>) -> SqlValue {
+    b.map_or(SqlValue::Null, |b| SqlValue::Integer(b as i64))
+}
+
+/// Case-insensitive LIKE with `%` and `_`
+fn like(text: &[char], pattern: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('%', rest)) => (0..=text.len()).any(|i| like(&text[i..], rest)),
+        Some((&p, rest)) => match text.split_first() {
+            Some((&t, text_rest)) => {
+                (p == '_' || t.to_lowercase().eq(p.to_lowercase())) && like(text_rest, rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn arithmetic(op: &str, a: &SqlValue, b: &SqlValue) -> SqlValue {
+    use SqlValue as V;
+    if matches!(a, V::Null) || matches!(b, V::Null) {
+        return V::Null;
+    }
+    let (a, b) = (a.numeric(), b.numeric());
+    if let (V::Integer(x), V::Integer(y)) = (&a, &b) {
+        let (x, y) = (*x, *y);
+        let exact = match op {
+            "+" => x.checked_add(y),
+            "-" => x.checked_sub(y),
+            "*" => x.checked_mul(y),
+            "/" if y == 0 => return V::Null,
+            "/" => x.checked_div(y),
+            _ if y == 0 => return V::Null,
+            _ => x.checked_rem(y),
+        };
+        if let Some(n) = exact {
+            return V::Integer(n);
+        }
+    }
+    let (x, y) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+    match op {
+        "+" => V::Real(x + y),
+        "-" => V::Real(x - y),
+        "*" => V::Real(x * y),
+        _ if y == 0.0 => V::Null,
+        "/" => V::Real(x / y),
+        _ => V::Real(x % y),
+    }
+}
+
+fn eval(expr: &Expr, ctx: &Ctx) -> Result<SqlValue, SqlError> {
+    use SqlValue as V;
+    Ok(match expr {
+        Expr::Lit(v) => v.clone(),
+        Expr::Star => V::Null,
+        Expr::Col(name) => {
+            let idx = ctx
+                .columns
+                .iter()
+                .position(|c| c.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| SqlError::parse(format!("no such column: {}", name)))?;
+            ctx.row.get(idx).cloned().unwrap_or(V::Null)
+        }
+        Expr::Neg(e) => match eval(e, ctx)? {
+            V::Null => V::Null,
+            v => negate(v),
+        },
+        Expr::Not(e) => bool_value(eval(e, ctx)?.truth().map(|b| !b)),
+        Expr::IsNull(e, negated) => {
+            V::Integer((matches!(eval(e, ctx)?, V::Null) != *negated) as i64)
+        }
+        Expr::Like(e, pattern, negated) => {
+            let (text, pattern) = (eval(e, ctx)?, eval(pattern, ctx)?);
+            if matches!(text, V::Null) || matches!(pattern, V::Null) {
+                return Ok(V::Null);
+            }
+            let text: Vec<char> = text.to_string().chars().collect();
+            let pattern: Vec<char> = pattern.to_string().chars().collect();
+            V::Integer((like(&text, &pattern) != *negated) as i64)
+        }
+        Expr::In(e, list, negated) => {
+            let value = eval(e, ctx)?;
+            if matches!(value, V::Null) {
+                return Ok(V::Null);
+            }
+            let mut found = false;
+            for item in list {
+                if compare(&value, &eval(item, ctx)?) == Ordering::Equal {
+                    found = true;
+                    break;
+                }
+            }
+            V::Integer((found != *negated) as i64)
+        }
+        Expr::Between(e, low, high, negated) => {
+            let (v, lo, hi) = (eval(e, ctx)?, eval(low, ctx)?, eval(high, ctx)?);
+            if [&v, &lo, &hi].iter().any(|x| matches!(x, V::Null)) {
+                return Ok(V::Null);
+            }
+            let inside =
+                compare(&v, &lo) != Ordering::Less && compare(&v, &hi) != Ordering::Greater;
+            V::Integer((inside != *negated) as i64)
+        }
+        Expr::Binary(op, a, b) => {
+            let left = eval(a, ctx)?;
+            match *op {
+                // Three-valued logic: a known answer wins over NULL
+                "AND" | "OR" => {
+                    let l = left.truth();
+                    let r = eval(b, ctx)?.truth();
+                    bool_value(match (*op, l, r) {
+                        ("AND", Some(false), _) | ("AND", _, Some(false)) => Some(false),
+                        ("AND", Some(true), Some(true)) => Some(true),
+                        ("OR", Some(true), _) | ("OR", _, Some(true)) => Some(true),
+                        ("OR", Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    })
+                }
+                "||" => match (left, eval(b, ctx)?) {
+                    (V::Null, _) | (_, V::Null) => V::Null,
+                    (l, r) => V::Text(format!("{}{}", l, r)),
+                },
+                "=" | "!=" | "<" | "<=" | ">" | ">=" => {
+                    let right = eval(b, ctx)?;
+                    if matches!(left, V::Null) || matches!(right, V::Null) {
+                        return Ok(V::Null);
+                    }
+                    let ord = compare(&left, &right);
+                    V::Integer(match *op {
+                        "=" => ord == Ordering::Equal,
+                        "!=" => ord != Ordering::Equal,
+                        "<" => ord == Ordering::Less,
+                        "<=" => ord != Ordering::Greater,
+                        ">" => ord == Ordering::Greater,
+                        _ => ord != Ordering::Less,
+                    } as i64)
+                }
+                _ => arithmetic(op, &left, &eval(b, ctx)?),
+            }
+        }
+        Expr::Func(name, args) if is_aggregate(name, args) => aggregate(name, args, ctx)?,
+        Expr::Func(name, args) => {
+            let values = args
+                .iter()
+                .map(|a| eval(a, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            scalar_function(name, values)?
+        }
+    })
+}
+
+fn aggregate(name: &str, args: &[Expr], ctx: &Ctx) -> Result<SqlValue, SqlError> {
+    use SqlValue as V;
+    let Some(group) = ctx.group else {
+        return Err(SqlError::parse(format!(
+            "misuse of aggregate function {}()",
+            name
+        )));
+    };
+    let wrong_args =
+        || SqlError::parse(format!("wrong number of arguments to function {}()", name));
+    if name == "count" && matches!(args, [Expr::Star] | []) {
+        return Ok(V::Integer(group.len() as i64));
+    }
+    let arg = match args {
+        [arg] => arg,
+        [arg, _] if name == "group_concat" => arg,
+        _ => return Err(wrong_args()),
+    };
+    let mut values = Vec::new();
+    for row in group {
+        let row_ctx = Ctx {
+            columns: ctx.columns,
+            row,
+            group: None,
+        };
+        let v = eval(arg, &row_ctx)?;
+        if !matches!(v, V::Null) {
+            values.push(v);
+        }
+    }
+    Ok(match name {
+        "count" => V::Integer(values.len() as i64),
+        "min" => values.into_iter().min_by(compare).unwrap_or(V::Null),
+        "max" => values.into_iter().max_by(compare).unwrap_or(V::Null),
+        "group_concat" => {
+            if values.is_empty() {
+                return Ok(V::Null);
+            }
+            let sep = match args.get(1) {
+                Some(e) => eval(e, ctx)?.to_string(),
+                None => ",".into(),
+            };
+            let parts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            V::Text(parts.join(&sep))
+        }
+        _ => {
+            let count = values.len();
+            let all_int = values.iter().all(|v| matches!(v.numeric(), V::Integer(_)));
+            let sum = values
+                .iter()
+                .fold(V::Integer(0), |acc, v| arithmetic("+", &acc, &v.numeric()));
+            match name {
+                "sum" if count == 0 => V::Null,
+                "sum" if all_int => sum,
+                "sum" | "total" => V::Real(sum.as_f64().unwrap_or(0.0)),
+                _ if count == 0 => V::Null,
+                _ => V::Real(sum.as_f64().unwrap_or(0.0) / count as f64),
+            }
+        }
+    })
+}
+
+fn scalar_function(name: &str, args: Vec<SqlValue>) -> Result<SqlValue, SqlError> {
+    use SqlValue as V;
+    let wrong_args =
+        || SqlError::parse(format!("wrong number of arguments to function {}()", name));
+    let one = || args.first().cloned().ok_or_else(wrong_args);
+    Ok(match name {
+        "upper" | "lower" | "length" | "abs" | "typeof" | "trim" if args.len() != 1 => {
+            return Err(wrong_args())
+        }
+        "upper" => match one()? {
+            V::Null => V::Null,
+            v => V::Text(v.to_string().to_uppercase()),
+        },
+        "lower" => match one()? {
+            V::Null => V::Null,
+            v => V::Text(v.to_string().to_lowercase()),
+        },
+        "trim" => match one()? {
+            V::Null => V::Null,
+            v => V::Text(v.to_string().trim().to_string()),
+        },
+        "length" => match one()? {
+            V::Null => V::Null,
+            v => V::Integer(v.to_string().chars().count() as i64),
+        },
+        "abs" => match one()?.numeric() {
+            V::Integer(i) => V::Integer(
+                i.checked_abs()
+                    .ok_or_else(|| SqlError::runtime("integer overflow"))?,
+            ),
+            V::Real(f) => V::Real(f.abs()),
+            _ => V::Null,
+        },
+        "typeof" => V::Text(one()?.type_name().into()),
+        "round" => {
+            let digits = match args.get(1) {
+                Some(d) => d.numeric().as_f64().unwrap_or(0.0) as i32,
+                None => 0,
+            };
+            match one()?.numeric() {
+                V::Null => V::Null,
+                v => {
+                    let scale = 10f64.powi(digits.max(0));
+                    V::Real((v.as_f64().unwrap_or(0.0) * scale).round() / scale)
+                }
+            }
+        }
+        "coalesce" | "ifnull" => {
+            if args.len() < 2 {
+                return Err(wrong_args());
+            }
+            args.into_iter()
+                .find(|v| !matches!(v, V::Null))
+                .unwrap_or(V::Null)
+        }
+        "min" | "max" => {
+            if args.iter().any(|v| matches!(v, V::Null)) {
+                return Ok(V::Null);
+            }
+            let pick = if name == "min" {
+                args.into_iter().min_by(compare)
+            } else {
+                args.into_iter().max_by(compare)
+            };
+            pick.unwrap_or(V::Null)
+        }
+        "substr" => {
+            let text: Vec<char> = one()?.to_string().chars().collect();
+            let start = args
+                .get(1)
+                .and_then(|v| v.numeric().as_f64())
+                .unwrap_or(1.0) as i64;
+            let len = args
+                .get(2)
+                .and_then(|v| v.numeric().as_f64())
+                .map_or(text.len() as i64, |l| l as i64);
+            let from = if start < 0 {
+                (text.len() as i64 + start).max(0)
+            } else {
+                (start - 1).max(0)
+            } as usize;
+            let to = (from + len.max(0) as usize).min(text.len());
+            V::Text(text[from.min(to)..to].iter().collect())
+        }
+        _ => return Err(SqlError::parse(format!("no such function: {}", name))),
+    })
+}
+
+// ---- execution ----
+
+pub struct QueryResult {
+    columns: Vec<String>,
+    rows: Vec<Vec<SqlValue>>,
+}
+
+impl Database {
+    /// Run one statement; returns rows for SELECT and whether it changed the database
+    fn execute(&mut self, stmt: Statement) -> Result<(Option<QueryResult>, bool), SqlError> {
+        match stmt {
+            Statement::Create {
+                table,
+                if_not_exists,
+            } => {
+                if self.table(&table.name).is_ok() {
+                    if if_not_exists {
+                        return Ok((None, false));
+                    }
+                    return Err(SqlError::parse(format!(
+                        "table {} already exists",
+                        table.name
+                    )));
+                }
+                self.tables.push(table);
+                Ok((None, true))
+            }
+            Statement::Drop { name, if_exists } => {
+                match self
+                    .tables
+                    .iter()
+                    .position(|t| t.name.eq_ignore_ascii_case(&name))
+                {
+                    Some(idx) => {
+                        self.tables.remove(idx);
+                        Ok((None, true))
+                    }
+                    None if if_exists => Ok((None, false)),
+                    None => Err(SqlError::parse(format!("no such table: {}", name))),
+                }
+            }
+            Statement::Insert {
+                table,
+                columns,
+                rows,
+            } => {
+                let table = self.table_mut(&table)?;
+                let targets: Vec<usize> = match &columns {
+                    Some(names) => names
+                        .iter()
+                        .map(|n| {
+                            table.column(n).ok_or_else(|| {
+                                SqlError::parse(format!(
+                                    "table {} has no column named {}",
+                                    table.name, n
+                                ))
+                            })
+                        })
+                        .collect::<Result<_, _>>()?,
+                    None => (0..table.columns.len()).collect(),
+                };
+                for exprs in &rows {
+                    if exprs.len() != targets.len() {
+                        return Err(SqlError::parse(match columns {
+                            Some(_) => {
+                                format!("{} values for {} columns", exprs.len(), targets.len())
+                            }
+                            None => format!(
+                                "table {} has {} columns but {} values were supplied",
+                                table.name,
+                                targets.len(),
+                                exprs.len()
+                            ),
+                        }));
+                    }
+                }
+                let mut changed = false;
+                for exprs in rows {
+                    let mut row: Vec<SqlValue> = table
+                        .columns
+                        .iter()
+                        .map(|c| c.default.clone().unwrap_or(SqlValue::Null))
+                        .collect();
+                    let ctx = Ctx {
+                        columns: &[],
+                        row: &[],
+                        group: None,
+                    };
+                    for (expr, &idx) in exprs.iter().zip(&targets) {
+                        row[idx] = table.columns[idx].apply_affinity(eval(expr, &ctx)?);
+                    }
+                    table.check_row(&mut row, None)?;
+                    table.rows.push(row);
+                    changed = true;
+                }
+                Ok((None, changed))
+            }
+            Statement::Update {
+                table,
+                sets,
+                filter,
+            } => {
+                let table = self.table_mut(&table)?;
+                let mut targets = Vec::new();
+                for (name, expr) in &sets {
+                    let idx = table
+                        .column(name)
+                        .ok_or_else(|| SqlError::parse(format!("no such column: {}", name)))?;
+                    check_columns(expr, &table.columns, &[])?;
+                    targets.push((idx, expr));
+                }
+                if let Some(f) = &filter {
+                    check_columns(f, &table.columns, &[])?;
+                }
+                let mut changed = false;
+                for i in 0..table.rows.len() {
+                    let ctx = Ctx {
+                        columns: &table.columns,
+                        row: &table.rows[i],
+                        group: None,
+                    };
+                    if let Some(f) = &filter {
+                        if eval(f, &ctx)?.truth() != Some(true) {
+                            continue;
+                        }
+                    }
+                    let mut row = table.rows[i].clone();
+                    for (idx, expr) in &targets {
+                        row[*idx] = table.columns[*idx].apply_affinity(eval(expr, &ctx)?);
+                    }
+                    table.check_row(&mut row, Some(i))?;
+                    table.rows[i] = row;
+                    changed = true;
+                }
+                Ok((None, changed))
+            }
+            Statement::Delete { table, filter } => {
+                let table = self.table_mut(&table)?;
+                let before = table.rows.len();
+                if let Some(f) = &filter {
+                    check_columns(f, &table.columns, &[])?;
+                    let mut kept = Vec::new();
+                    for row in std::mem::take(&mut table.rows) {
+                        let ctx = Ctx {
+                            columns: &table.columns,
+                            row: &row,
+                            group: None,
+                        };
+                        if eval(f, &ctx)?.truth() != Some(true) {
+                            kept.push(row);
+                        }
+                    }
+                    table.rows = kept;
+                } else {
+                    table.rows.clear();
+                }
+                Ok((None, table.rows.len() != before))
+            }
+            Statement::Select(select) => Ok((Some(self.select(&select)?), false)),
+        }
+    }
+
+    fn select(&self, s: &Select) -> Result<QueryResult, SqlError> {
+        let (columns, rows): (&[Column], Vec<&[SqlValue]>) = match &s.from {
+            Some(name) => {
+                let t = self.table(name)?;
+                (&t.columns, t.rows.iter().map(Vec::as_slice).collect())
+            }
+            None => (&[], vec![&[]]),
+        };
+
+        let aliases: Vec<&str> = s
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Expr(_, label) => Some(label.as_str()),
+                Item::All => None,
+            })
+            .collect();
+        let mut headers = Vec::new();
+        for item in &s.items {
+            match item {
+                Item::All if s.from.is_none() => {
+                    return Err(SqlError::parse("no tables specified"))
+                }
+                Item::All => headers.extend(columns.iter().map(|c| c.name.clone())),
+                Item::Expr(e, label) => {
+                    check_columns(e, columns, &[])?;
+                    headers.push(label.clone());
+                }
+            }
+        }
+        for e in s.filter.iter().chain(&s.group).chain(&s.having) {
+            check_columns(e, columns, &[])?;
+        }
+        for (e, _) in &s.order {
+            check_columns(e, columns, &aliases)?;
+        }
+
+        let mut matched = Vec::new();
+        for row in rows {
+            if let Some(f) = &s.filter {
+                let ctx = Ctx {
+                    columns,
+                    row,
+                    group: None,
+                };
+                if eval(f, &ctx)?.truth() != Some(true) {
+                    continue;
+                }
+            }
+            matched.push(row);
+        }
+
+        let aggregating = !s.group.is_empty()
+            || s.items.iter().any(|item| match item {
+                Item::Expr(e, _) => has_aggregate(e),
+                Item::All => false,
+            });
+        // Each output row comes from one source row, or from one group
+        let mut groups: Vec<(Vec<SqlValue>, Vec<&[SqlValue]>)> = Vec::new();
+        if aggregating {
+            if s.group.is_empty() {
+                groups.push((Vec::new(), matched.clone()));
+            } else {
+                for &row in &matched {
+                    let ctx = Ctx {
+                        columns,
+                        row,
+                        group: None,
+                    };
+                    let key = s
+                        .group
+                        .iter()
+                        .map(|e| eval(e, &ctx))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match groups.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, members)) => members.push(row),
+                        None => groups.push((key, vec![row])),
+                    }
+                }
+            }
+        }
+
+        let nulls = vec![SqlValue::Null; columns.len()];
+        let contexts: Vec<Ctx> = if aggregating {
+            groups
+                .iter()
+                .map(|(_, members)| Ctx {
+                    columns,
+                    row: members.first().copied().unwrap_or(nulls.as_slice()),
+                    group: Some(members.as_slice()),
+                })
+                .collect()
+        } else {
+            matched
+                .iter()
+                .map(|row| Ctx {
+                    columns,
+                    row,
+                    group: None,
+                })
+                .collect()
+        };
+
+        let mut out: Vec<(Vec<SqlValue>, Vec<SqlValue>)> = Vec::new();
+        for ctx in contexts {
+            let row = ctx.row;
+            if let Some(h) = &s.having {
+                if eval(h, &ctx)?.truth() != Some(true) {
+                    continue;
+                }
+            }
+            let mut values = Vec::new();
+            for item in &s.items {
+                match item {
+                    Item::All => values.extend(row.iter().cloned()),
+                    Item::Expr(e, _) => values.push(eval(e, &ctx)?),
+                }
+            }
+            let mut keys = Vec::new();
+            for (e, _) in &s.order {
+                keys.push(match e {
+                    // ORDER BY 2 sorts on the second result column
+                    Expr::Lit(SqlValue::Integer(n)) => {
+                        values.get(*n as usize - 1).cloned().ok_or_else(|| {
+                            SqlError::parse(format!(
+                                "1st ORDER BY term out of range - should be between 1 and {}",
+                                values.len()
+                            ))
+                        })?
+                    }
+                    Expr::Col(name)
+                        if !columns.iter().any(|c| c.name.eq_ignore_ascii_case(name)) =>
+                    {
+                        let idx = headers
+                            .iter()
+                            .position(|h| h.eq_ignore_ascii_case(name))
+                            .unwrap_or(0);
+                        values.get(idx).cloned().unwrap_or(SqlValue::Null)
+                    }
+                    e => eval(e, &ctx)?,
+                });
+            }
+            if s.distinct && out.iter().any(|(v, _)| *v == values) {
+                continue;
+            }
+            out.push((values, keys));
+        }
+
+        out.sort_by(|(_, a), (_, b)| {
+            a.iter()
+                .zip(b)
+                .zip(&s.order)
+                .map(|((x, y), (_, desc))| {
+                    let ord = compare(x, y);
+                    if *desc {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                })
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let empty = Ctx {
+            columns: &[],
+            row: &[],
+            group: None,
+        };
+        let offset = match &s.offset {
+            Some(e) => eval(e, &empty)?.numeric().as_f64().unwrap_or(0.0).max(0.0) as usize,
+            None => 0,
+        };
+        let limit = match &s.limit {
+            Some(e) => match eval(e, &empty)?.numeric().as_f64() {
+                Some(n) if n >= 0.0 => n as usize,
+                _ => usize::MAX,
+            },
+            None => usize::MAX,
+        };
+        Ok(QueryResult {
+            columns: headers,
+            rows: out
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(values, _)| values)
+                .collect(),
+        })
+    }
+}
+
+impl Table {
+    /// Fill in rowid aliases and enforce NOT NULL and primary keys.
+    /// `skip` is the row being replaced by an UPDATE.
+    fn check_row(&self, row: &mut [SqlValue], skip: Option<usize>) -> Result<(), SqlError> {
+        for (idx, column) in self.columns.iter().enumerate() {
+            if column.is_rowid() {
+                match &row[idx] {
+                    SqlValue::Null => {
+                        let next = self
+                            .rows
+                            .iter()
+                            .filter_map(|r| match r[idx] {
+                                SqlValue::Integer(i) => Some(i),
+                                _ => None,
+                            })
+                            .max()
+                            .unwrap_or(0)
+                            + 1;
+                        row[idx] = SqlValue::Integer(next);
+                    }
+                    SqlValue::Integer(_) => {}
+                    _ => return Err(SqlError::runtime("datatype mismatch")),
+                }
+            }
+            if column.not_null && matches!(row[idx], SqlValue::Null) {
+                return Err(SqlError::runtime(format!(
+                    "NOT NULL constraint failed: {}.{}",
+                    self.name, column.name
+                )));
+            }
+            if column.primary_key && !matches!(row[idx], SqlValue::Null) {
+                let taken = self
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .any(|(i, r)| Some(i) != skip && r[idx] == row[idx]);
+                if taken {
+                    return Err(SqlError::runtime(format!(
+                        "UNIQUE constraint failed: {}.{}",
+                        self.name, column.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// ---- shell ----
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    List,
+    Csv,
+    Column,
+}
+
+const HELP: &str = "\
+.dump ?TABLE?            Render database content as SQL
+.exit                    Exit this program
+.headers on|off          Turn display of headers on or off
+.help                    Show this message
+.mode MODE               Set output mode: column, csv or list
+.quit                    Exit this program
+.schema ?TABLE?          Show the CREATE statements matching TABLE
+.tables                  List names of tables";
+
+/// One open database, as the `sqlite3` shell sees it
+pub struct SqliteShell {
+    pub db: Database,
+    /// Statement text collected until it ends with `;`
+    pending: String,
+    headers: bool,
+    mode: Mode,
+    readonly: bool,
+    dirty: bool,
+    closed: bool,
+}
+
+impl SqliteShell {
+    pub fn new(db: Database, readonly: bool) -> Self {
+        SqliteShell {
+            db,
+            pending: String::new(),
+            headers: false,
+            mode: Mode::List,
+            readonly,
+            dirty: false,
+            closed: false,
+        }
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() {
+            "sqlite> "
+        } else {
+            "   ...> "
+        }
+    }
+
+    /// Whether `.quit` or `.exit` was entered
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Whether the database changed since the last call, so it should be saved
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Feed one line typed at the prompt
+    pub fn push_line(&mut self, line: &str) -> String {
+        if self.pending.is_empty() && line.trim_start().starts_with('.') {
+            return self.dot_command(line.trim());
+        }
+        if self.pending.is_empty() && line.trim().is_empty() {
+            return String::new();
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        if !is_complete(&self.pending) {
+            return String::new();
+        }
+        let sql = std::mem::take(&mut self.pending);
+        self.run(&sql)
+    }
+
+    /// Run every statement in `sql`, stopping at the first error
+    pub fn run(&mut self, sql: &str) -> String {
+        match self.execute(sql) {
+            (out, None) => out,
+            (out, Some(e)) if out.is_empty() => e,
+            (out, Some(e)) => format!("{}\n{}", out, e),
+        }
+    }
+
+    /// [`run`](Self::run) with the error that stopped it kept apart from
+    /// the output of the statements before it
+    pub fn execute(&mut self, sql: &str) -> (String, Option<String>) {
+        let mut out = Vec::new();
+        let tokens = match tokenize(sql) {
+            Ok(tokens) => tokens,
+            Err(e) => return (String::new(), Some(e.to_string())),
+        };
+        for stmt_tokens in tokens.split(|t| t.tok == Tok::Sym(";")) {
+            if stmt_tokens.is_empty() {
+                continue;
+            }
+            match self.run_statement(sql, stmt_tokens) {
+                Ok(Some(text)) if !text.is_empty() => out.push(text),
+                Ok(_) => {}
+                Err(e) => return (out.join("\n"), Some(e.to_string())),
+            }
+        }
+        (out.join("\n"), None)
+    }
+
+    fn run_statement(&mut self, sql: &str, tokens: &[Token]) -> Result<Option<String>, SqlError> {
+        let mut parser = Parser {
+            src: sql,
+            tokens,
+            pos: 0,
+        };
+        let stmt = parser.statement()?;
+        if parser.pos < tokens.len() {
+            return Err(parser.error());
+        }
+        if self.readonly && !matches!(stmt, Statement::Select(_)) {
+            return Err(SqlError::runtime("attempt to write a readonly database"));
+        }
+        let (result, changed) = self.db.execute(stmt)?;
+        self.dirty |= changed;
+        Ok(result.map(|r| self.render(&r)))
+    }
+
+    fn render(&self, result: &QueryResult) -> String {
+        let mut lines = Vec::new();
+        match self.mode {
+            Mode::List => {
+                if self.headers && !result.rows.is_empty() {
+                    lines.push(result.columns.join("|"));
+                }
+                for row in &result.rows {
+                    let cells: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                    lines.push(cells.join("|"));
+                }
+            }
+            Mode::Csv => {
+                let quote = |s: String| {
+                    if s.contains([',', '"', '\n']) {
+                        format!("\"{}\"", s.replace('"', "\"\""))
+                    } else {
+                        s
+                    }
+                };
+                if self.headers && !result.rows.is_empty() {
+                    let cells: Vec<String> = result.columns.iter().cloned().map(quote).collect();
+                    lines.push(cells.join(","));
+                }
+                for row in &result.rows {
+                    let cells: Vec<String> = row.iter().map(|v| quote(v.to_string())).collect();
+                    lines.push(cells.join(","));
+                }
+            }
+            Mode::Column => {
+                if result.rows.is_empty() {
+                    return String::new();
+                }
+                let mut widths: Vec<usize> =
+                    result.columns.iter().map(|c| c.chars().count()).collect();
+                for row in &result.rows {
+                    for (w, v) in widths.iter_mut().zip(row) {
+                        *w = (*w).max(v.to_string().chars().count());
+                    }
+                }
+                let line = |cells: Vec<String>| {
+                    let padded: Vec<String> = cells
+                        .iter()
+                        .zip(&widths)
+                        .map(|(c, w)| format!("{:<w$}", c, w = w))
+                        .collect();
+                    padded.join("  ").trim_end().to_string()
+                };
+                lines.push(line(result.columns.clone()));
+                lines.push(line(widths.iter().map(|w| "-".repeat(*w)).collect()));
+                for row in &result.rows {
+                    lines.push(line(row.iter().map(|v| v.to_string()).collect()));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn dot_command(&mut self, line: &str) -> String {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let arg = parts.get(1).copied();
+        match parts[0] {
+            ".quit" | ".exit" | ".q" => {
+                self.closed = true;
+                String::new()
+            }
+            ".help" => HELP.into(),
+            ".tables" => {
+                let mut names: Vec<&str> = self.db.tables.iter().map(|t| t.name.as_str()).collect();
+                names.sort_unstable();
+                names.join("  ")
+            }
+            ".schema" => self
+                .db
+                .tables
+                .iter()
+                .filter(|t| arg.is_none_or(|a| t.name.eq_ignore_ascii_case(a)))
+                .map(|t| format!("{};", t.sql))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ".headers" | ".header" => match arg {
+                Some("on") => {
+                    self.headers = true;
+                    String::new()
+                }
+                Some("off") => {
+                    self.headers = false;
+                    String::new()
+                }
+                _ => "Usage: .headers on|off".into(),
+            },
+            ".mode" => match arg {
+                Some("list") => {
+                    self.mode = Mode::List;
+                    String::new()
+                }
+                Some("csv") => {
+                    self.mode = Mode::Csv;
+                    String::new()
+                }
+                Some("column") => {
+                    self.mode = Mode::Column;
+                    String::new()
+                }
+                None => format!(
+                    "current output mode: {}",
+                    match self.mode {
+                        Mode::List => "list",
+                        Mode::Csv => "csv",
+                        Mode::Column => "column",
+                    }
+                ),
+                Some(other) => format!(
+                    "Error: mode should be one of: column csv list\n\
+                     unknown mode: {}",
+                    other
+                ),
+            },
+            ".dump" => self.dump_sql(arg),
+            other => format!(
+                "Error: unknown command or invalid arguments:  \"{}\". Enter \".help\" for help",
+                other.trim_start_matches('.')
+            ),
+        }
+    }
+
+    /// `.dump`: the schema and rows as a replayable SQL script
+    fn dump_sql(&self, only: Option<&str>) -> String {
+        let mut lines = vec![
+            "PRAGMA foreign_keys=OFF;".to_string(),
+            "BEGIN TRANSACTION;".into(),
+        ];
+        for table in &self.db.tables {
+            if only.is_some_and(|o| !table.name.eq_ignore_ascii_case(o)) {
+                continue;
+            }
+            lines.push(format!("{};", table.sql));
+            for row in &table.rows {
+                let values: Vec<String> = row
+                    .iter()
+                    .map(|v| match v {
+                        SqlValue::Null => "NULL".into(),
+                        SqlValue::Text(s) => format!("'{}'", s.replace('\'', "''")),
+                        other => other.to_string(),
+                    })
+                    .collect();
+                lines.push(format!(
+                    "INSERT INTO {} VALUES({});",
+                    table.name,
+                    values.join(",")
+                ));
+            }
+        }
+        lines.push("COMMIT;".into());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Database, SqliteShell};
+
+    fn shell() -> SqliteShell {
+        let mut sh = SqliteShell::new(Database::default(), false);
+        sh.run(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INT);
+             INSERT INTO users (name, age) VALUES ('ann', 31), ('bob', '27'), ('cy', NULL);",
+        );
+        sh
+    }
+
+    #[test]
+    fn create_insert_and_select_with_where() {
+        let mut sh = shell();
+        assert!(sh.take_dirty());
+        assert_eq!(
+            sh.run("SELECT * FROM users WHERE age > 28 OR age IS NULL ORDER BY name DESC;"),
+            "3|cy|\n1|ann|31"
+        );
+        assert_eq!(
+            sh.run("SELECT name, age + 1 AS next FROM users WHERE name LIKE 'B%';"),
+            "bob|28"
+        );
+        assert_eq!(
+            sh.run("SELECT count(*), avg(age), max(name) FROM users;"),
+            "3|29.0|cy"
+        );
+        assert!(!sh.take_dirty());
+    }
+
+    #[test]
+    fn errors_match_the_sqlite_shell() {
+        let mut sh = shell();
+        assert_eq!(
+            sh.run("SELECT * FROM nope;"),
+            "Parse error: no such table: nope"
+        );
+        assert_eq!(
+            sh.run("SELECT nope FROM users;"),
+            "Parse error: no such column: nope"
+        );
+        assert_eq!(
+            sh.run("SELEC 1;"),
+            "Parse error: near \"SELEC\": syntax error"
+        );
+        assert_eq!(
+            sh.run("INSERT INTO users (name) VALUES (NULL);"),
+            "Runtime error: NOT NULL constraint failed: users.name"
+        );
+        assert_eq!(
+            sh.run("INSERT INTO users VALUES (1, 'dup', 2);"),
+            "Runtime error: UNIQUE constraint failed: users.id"
+        );
+        assert_eq!(
+            sh.run("SELECT abs(-9223372036854775807 - 1);"),
+            "Runtime error: integer overflow"
+        );
+        assert_eq!(
+            sh.run("SELECT abs(-9223372036854775807);"),
+            "9223372036854775807"
+        );
+    }
+
+    #[test]
+    fn shell_commands_and_round_trip() {
+        let mut sh = shell();
+        assert_eq!(sh.push_line("UPDATE users SET age = 40"), "");
+        assert_eq!(sh.prompt(), "   ...> ");
+        assert_eq!(sh.push_line("WHERE name = 'cy';"), "");
+        assert_eq!(sh.push_line(".tables"), "users");
+        assert_eq!(
+            sh.push_line(".schema"),
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INT);"
+        );
+        sh.push_line(".mode column");
+        assert_eq!(
+            sh.push_line("SELECT name, age FROM users WHERE age >= 31;"),
+            "name  age\n----  ---\nann   31\ncy    40"
+        );
+
+        let reopened = Database::load(&sh.db.dump()).unwrap_or_default();
+        let mut sh = SqliteShell::new(reopened, false);
+        assert_eq!(sh.run("SELECT sum(age) FROM users;"), "98");
+        assert!(Database::load("hello").is_err());
+    }
+}
@@ -1,30 +1,137 @@
 use crate::{
     boot::BootManager,
+    clock,
     kernel::Kernel,
-    network::{NetworkStack, Protocol},
+    lua::LuaInterpreter,
+    mux::{Mux, ShellState},
+    network::{self, NetworkStack, Protocol},
     process::{Priority, ProcState, Process},
     python::PythonInterpreter,
     services::ServiceManager,
-    shell::{prompt, Shell},
+    shell::{prompt, CmdOutput, ProgramKind, Shell},
+    sqlite::{self, Database, SqliteShell},
+    terminal::Terminal,
 };
 use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeSet;
 use std::io::{Cursor, Read, Write};
 use wasm_bindgen::prelude::*;
 use zip::write::SimpleFileOptions;
 use zip::{CompressionMethod, ZipArchive, ZipWriter};
 
+mod apt;
+mod audio;
+mod bootparams;
+mod builtins;
+mod checksum;
+mod cmdlist;
+mod complete;
+mod copy;
+mod diff;
+mod disk;
+mod downloads;
+mod dpkg;
+mod dropped;
+mod events;
+mod export;
+mod find;
+mod fsck;
+mod fun;
+mod generate;
+mod gh;
+mod git;
+mod grep;
+mod hexdump;
+mod history;
+mod htop;
+mod httpd;
+mod idle;
+mod initramfs;
+mod interrupt;
+mod iss;
 mod linux;
+mod loopdev;
+mod ls;
+mod lsof;
+mod lynx;
+mod mail;
+mod motd;
+mod mounts;
+mod neofetch;
+mod netexec;
+mod netif;
+mod nice;
+mod pager;
+mod perf;
+mod power;
+mod ps;
+mod rm;
+mod rss;
+mod screen;
+mod sessions;
+mod snake;
+mod snapshot;
+mod suggest;
+mod sysbench;
+mod systemd;
+mod tcpdump;
+mod tmux;
+mod traceroute;
+mod ulimit;
+mod weather;
+mod wscat;
+mod xargs;
+
+use events::SystemEvent;
+use netif::Unreachable;
 
 const SUDO_TIMEOUT_MS: f64 = 300000.0;
 const BINARY_PREFIX: &str = "__BIN_B64__:";
+/// Where a pipeline leaves a command's output for the next one to read
+const PIPE_STDIN: &str = "/tmp/.pipe.stdin";
+
+// The shell passes quotes through, so `grep "error" log` arrives quoted
+fn unquote(word: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = word.strip_prefix(q).and_then(|rest| rest.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    word
+}
+
+/// Sizes the way `ls -h`, `du -h` and `df -h` print them: bytes up to 1023,
+/// then `4.0K`, `12K`, `1.5M`, rounding up like coreutils
+fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut value = bytes as f64;
+    for unit in ["K", "M", "G", "T"] {
+        value /= 1024.0;
+        let tenths = (value * 10.0).ceil() / 10.0;
+        if tenths < 10.0 {
+            return format!("{:.1}{}", tenths, unit);
+        }
+        if value.ceil() < 1024.0 || unit == "T" {
+            return format!("{}{}", value.ceil() as u64, unit);
+        }
+    }
+    unreachable!()
+}
+
+// The terminal everything typed at the prompt runs on (see `who`)
+const TERMINAL_TTY: &str = "tty1";
 
 struct SudoPendingRequest {
     command: Option<String>,
     target_user: String,
     validate_only: bool,
     list_privileges: bool,
+    /// Set for `su`: switch to `target_user` rather than run `command`,
+    /// with a login shell if true
+    switch_user: Option<bool>,
 }
 
 struct SudoInvocation {
@@ -51,6 +158,54 @@ struct ShellJob {
     state: JobState,
 }
 
+/// `bytes` as file data or output: the text itself, or base64 behind
+/// `BINARY_PREFIX` if it is not UTF-8
+fn bytes_to_data(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => format!("{}{}", BINARY_PREFIX, B64.encode(e.as_bytes())),
+    }
+}
+
+/// Whether every word after the command in `seg` is an option, or the value
+/// of one of the `valued` options; such a command reads the pipe
+fn only_options(seg: &str, valued: &[&str]) -> bool {
+    let mut words = seg.split_whitespace().skip(1);
+    while let Some(word) = words.next() {
+        if !word.starts_with('-') {
+            return false;
+        }
+        if valued.contains(&word) {
+            words.next();
+        }
+    }
+    true
+}
+
+// `echo -e`: the backslash escapes scripts reach for
+fn echo_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('a') => out.push('\x07'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
 #[wasm_bindgen]
 pub struct System {
     boot: BootManager,
@@ -62,12 +217,63 @@ pub struct System {
     cleared_after_boot: bool,
     python_interp: Option<PythonInterpreter>,
     in_python_repl: bool,
+    lua_interp: Option<LuaInterpreter>,
+    in_lua_repl: bool,
+    /// Open `sqlite3` shell and the file it saves to (`None` for `:memory:`)
+    sqlite: Option<(SqliteShell, Option<String>)>,
+    in_sqlite_repl: bool,
     user_password: Option<String>,
     sudo_pending_request: Option<SudoPendingRequest>,
     sudo_waiting_password: bool,
     sudo_authenticated_until: Option<f64>,
     jobs: Vec<ShellJob>,
     next_job_id: u32,
+    /// Process of the job `fg` brought forward, until Ctrl+C stops it
+    foreground: Option<u32>,
+    htop: Option<htop::HtopState>,
+    pager: Option<pager::PagerState>,
+    http_servers: Vec<httpd::HttpServer>,
+    wscat: Option<wscat::WscatSession>,
+    traceroute: Option<traceroute::TracerouteRun>,
+    /// Transfers the frontend is currently fetching
+    active_downloads: Vec<u32>,
+    /// Set while output goes to a pipe or file rather than the terminal
+    output_captured: bool,
+    /// Niceness `nice` wants for the process the next command spawns
+    pending_nice: Option<i8>,
+    /// Set while a typed line runs, so the commands it runs in turn are
+    /// neither expanded nor recorded in history
+    in_exec: bool,
+    /// Exit status of the last command, `$?`
+    last_status: i32,
+    /// `rm -i` waiting for y/n on its next file
+    rm_prompt: Option<rm::RmPrompt>,
+    /// What the last `rm` removed, for `undo-rm`
+    rm_undo: Vec<rm::Removed>,
+    /// SysV runlevel for `runlevel`: 3 after a normal boot, 1 in rescue mode
+    runlevel: u8,
+    previous_runlevel: Option<u8>,
+    /// Events for the frontend since it last polled
+    events: Vec<events::SystemEvent>,
+    event_callback: Option<js_sys::Function>,
+    /// Print the old `\x1b[NAME]` escapes instead of queueing events
+    legacy_markers: bool,
+    /// `tmux` sessions, and the shell from before attaching to one
+    mux: Mux,
+    mux_outer: Option<ShellState>,
+    /// Who is logged in on which terminal
+    sessions: sessions::Sessions,
+    /// What is on screen and in the scrollback
+    term: Terminal,
+    /// Attached loop devices, `losetup`
+    loops: Vec<loopdev::LoopDevice>,
+    /// The page `lynx` has up, which takes the next line typed
+    browser: Option<lynx::Browser>,
+    /// The line `exec_start` ran, while its network requests are out
+    net_run: Option<netexec::NetRun>,
+    /// Number of the request `net_reply` will take next; never reused, so
+    /// a late answer to an older request is turned away
+    net_serial: u64,
 }
 
 impl Default for System {
@@ -90,14 +296,48 @@ impl System {
             cleared_after_boot: false,
             python_interp: None,
             in_python_repl: false,
+            lua_interp: None,
+            in_lua_repl: false,
+            sqlite: None,
+            in_sqlite_repl: false,
             user_password: None,
             sudo_pending_request: None,
             sudo_waiting_password: false,
             sudo_authenticated_until: None,
             jobs: Vec::new(),
             next_job_id: 1,
+            foreground: None,
+            htop: None,
+            pager: None,
+            http_servers: Vec::new(),
+            wscat: None,
+            traceroute: None,
+            active_downloads: Vec::new(),
+            output_captured: false,
+            pending_nice: None,
+            in_exec: false,
+            last_status: 0,
+            rm_prompt: None,
+            rm_undo: Vec::new(),
+            runlevel: 3,
+            previous_runlevel: None,
+            events: Vec::new(),
+            event_callback: None,
+            legacy_markers: false,
+            mux: Mux::new(),
+            mux_outer: None,
+            sessions: sessions::Sessions::default(),
+            term: Terminal::new(),
+            loops: Vec::new(),
+            browser: None,
+            net_run: None,
+            net_serial: 0,
         };
 
+        for builtin in builtins::BUILTINS {
+            system.shell.registry.register_command(builtin);
+        }
+
         // Auto-start system services
         system.services.auto_start_services(&mut |name| {
             system.kernel.proc.spawn(name, 1, &mut system.kernel.mem)
@@ -108,6 +348,10 @@ impl System {
     #[wasm_bindgen]
     pub fn start_boot(&mut self) {
         self.kernel.generate_boot_log();
+        self.record_boot();
+        crate::perf::start_jank_monitor();
+        self.install_unit_files();
+        self.sync_package_commands();
     }
     #[wasm_bindgen]
     pub fn next_boot_line(&mut self) -> Option<String> {
@@ -120,6 +364,24 @@ impl System {
             None
         }
     }
+    /// The rest of the boot log in one call instead of a `next_boot_line`
+    /// round trip per line
+    #[wasm_bindgen]
+    pub fn drain_boot_log(&mut self) -> js_sys::Array {
+        let arr = js_sys::Array::new();
+        for line in self.kernel.drain_boot_log() {
+            if line.contains("BOOT_COMPLETE") {
+                self.booted = true;
+            }
+            arr.push(&JsValue::from_str(&line));
+        }
+        arr
+    }
+    /// Whether `grub fastboot on` asked for the boot log without animation
+    #[wasm_bindgen]
+    pub fn fast_boot(&self) -> bool {
+        self.fast_boot_enabled()
+    }
     #[wasm_bindgen]
     pub fn is_booted(&self) -> bool {
         self.booted
@@ -151,15 +413,102 @@ impl System {
 
     #[wasm_bindgen]
     pub fn exec(&mut self, line: &str) -> String {
+        let started = clock::now_ms();
+        let pane = self.pane_record_input(line);
+        self.session_input(line);
+        let output = self.exec_typed(line);
+        self.pane_record_output(pane, &output);
+        self.deliver_events();
+        if !self.in_exec {
+            crate::perf::record_exec(clock::now_ms() - started);
+        }
+        output
+    }
+
+    /// Run a line as typed at the prompt: history expansion first, then
+    /// the line itself
+    fn exec_typed(&mut self, line: &str) -> String {
+        if self.in_exec || self.sudo_waiting_password {
+            return self.exec_line(line);
+        }
+        if self.rm_prompt.is_some() {
+            return self.answer_rm_prompt(line);
+        }
+        if self.browser.is_some() {
+            return self.lynx_input(line);
+        }
+        let trimmed = line.trim();
+        let expanded = match self.expand_history_line(trimmed) {
+            Ok(expanded) => expanded,
+            Err(e) => return e,
+        };
+        let line = expanded.as_deref().unwrap_or(trimmed);
+        self.record_history(line);
+
+        self.in_exec = true;
+        let output = self.exec_line(line);
+        self.in_exec = false;
+        // Echo the expanded line like bash does, unless the output is a
+        // frontend escape that has to stand alone
+        match expanded {
+            Some(cmd) if !output.starts_with('\x1b') => {
+                if output.is_empty() {
+                    cmd
+                } else {
+                    format!("{}\n{}", cmd, output)
+                }
+            }
+            _ => output,
+        }
+    }
+
+    /// Exit status of the last command, as `$?` would expand
+    #[wasm_bindgen]
+    pub fn last_exit_code(&self) -> i32 {
+        self.last_status
+    }
+
+    /// Most recent history entry containing `query` for Ctrl+R, `skip`
+    /// matches back
+    #[wasm_bindgen]
+    pub fn history_search(&self, query: &str, skip: usize) -> Option<String> {
+        self.find_in_history(query, skip)
+    }
+
+    /// Width of the terminal in character cells, measured by the frontend.
+    /// Exported as `$COLUMNS` for `ls` to lay out its columns
+    #[wasm_bindgen]
+    pub fn set_terminal_width(&mut self, cols: u32) {
+        if cols > 0 {
+            self.shell.env.insert("COLUMNS".into(), cols.to_string());
+            self.term.resize(self.term.rows(), cols as usize);
+        }
+    }
+
+    /// The shell history, oldest first, for arrow-key navigation
+    #[wasm_bindgen]
+    pub fn history_entries(&self) -> js_sys::Array {
+        self.shell
+            .history
+            .iter()
+            .map(|cmd| JsValue::from_str(cmd))
+            .collect()
+    }
+
+    /// Run one command of a list: a pipeline, a redirection, a background
+    /// job or a single command
+    fn exec_command(&mut self, line: &str) -> CmdOutput {
         self.kernel.tick();
         self.kernel.scheduler.tick(&mut self.kernel.proc);
+        self.refresh_proc_pids();
+        self.refresh_proc_stat();
         let trimmed = line.trim();
-        if !trimmed.is_empty() {
-            self.shell.history.push(trimmed.into());
-        }
         if self.sudo_waiting_password {
             self.sudo_waiting_password = false;
             if let Some(request) = self.sudo_pending_request.take() {
+                if let Some(login) = request.switch_user {
+                    return self.su_authenticate(&request.target_user, login, trimmed);
+                }
                 return self.exec_sudo_with_context(
                     request.command.as_deref(),
                     trimmed,
@@ -168,19 +517,26 @@ impl System {
                     request.list_privileges,
                 );
             }
-            return "sudo: authentication state is invalid; try again".into();
+            return CmdOutput::error(1, "sudo: authentication state is invalid; try again");
         }
 
         if let Some(bg_cmd) = trimmed.strip_suffix('&') {
             let cmdline = bg_cmd.trim();
             if cmdline.is_empty() {
-                return "sh: syntax error near unexpected token `&'".into();
+                return CmdOutput::error(2, "sh: syntax error near unexpected token `&'");
             }
             return self.spawn_background_job(cmdline, false);
         }
 
         if let Some((cmd_part, out_path, append)) = Self::split_output_redirection(trimmed) {
-            let output = self.exec(cmd_part);
+            let captured = std::mem::replace(&mut self.output_captured, true);
+            // Only the output goes to the file; errors still reach the terminal
+            let CmdOutput {
+                stdout: output,
+                stderr,
+                status,
+            } = self.exec_command(cmd_part);
+            self.output_captured = captured;
             let existing = if append {
                 self.kernel
                     .fs
@@ -198,6 +554,9 @@ impl System {
                 output
             };
 
+            if !self.can_write_path(out_path) {
+                return CmdOutput::error(1, format!("sh: {}: Permission denied", out_path));
+            }
             let write_res = if self.kernel.fs.resolve(out_path).is_some() {
                 self.kernel.fs.write_file(out_path, &final_data)
             } else {
@@ -205,8 +564,12 @@ impl System {
             };
 
             return match write_res {
-                Ok(()) => String::new(),
-                Err(e) => format!("sh: {}: {}", out_path, e),
+                Ok(()) => CmdOutput {
+                    stdout: String::new(),
+                    stderr,
+                    status,
+                },
+                Err(e) => CmdOutput::error(1, format!("sh: {}: {}", out_path, e)),
             };
         }
 
@@ -216,7 +579,7 @@ impl System {
             } else {
                 format!("{} {}", lhs.trim(), in_path)
             };
-            return self.exec(&merged);
+            return self.exec_command(&merged);
         }
 
         if trimmed.contains('|') {
@@ -226,207 +589,268 @@ impl System {
         let expanded = self.expand_alias_line(trimmed);
         let parts: Vec<&str> = expanded.split_whitespace().collect();
         if parts.is_empty() {
-            return String::new();
+            return CmdOutput::default();
         }
-        let cmd = parts[0];
-        let args = &parts[1..];
-        if cmd == "sudo" {
-            return self.handle_sudo(args);
+        self.run_program(parts[0], &parts[1..], &expanded)
+    }
+
+    /// Run `cmd` from the registry with `args` as they are, with no further
+    /// expansion; `cmdline` is what `ps` shows for a spawned process
+    fn run_program(&mut self, cmd: &str, args: &[&str], cmdline: &str) -> CmdOutput {
+        if let Err(e) = self.spawn_program(cmd, cmdline) {
+            return e;
         }
-        if self.shell.registry.has(cmd) {
-            if let Some(pid) = self.kernel.proc.spawn(cmd, 1, &mut self.kernel.mem) {
-                self.kernel.scheduler.add(pid, Priority::Normal);
-            } else {
-                return "Failed to spawn process: out of memory".to_string();
-            }
+        self.dispatch_program(cmd, args)
+    }
+
+    /// Fork the process `cmd` runs in, if it is one that gets its own; the
+    /// error is what the shell reports when the fork is refused
+    pub(super) fn spawn_program(
+        &mut self,
+        cmd: &str,
+        cmdline: &str,
+    ) -> Result<Option<u32>, CmdOutput> {
+        if !self.shell.registry.spawns(cmd) {
+            return Ok(None);
+        }
+        if let Some(error) = self.fork_denied() {
+            return Err(CmdOutput::error(1, error));
+        }
+        let Some(pid) = self.kernel.proc.spawn(cmd, 1, &mut self.kernel.mem) else {
+            self.kernel.mem.scribble(1);
+            return Err(CmdOutput::error(
+                1,
+                "Failed to spawn process: out of memory",
+            ));
+        };
+        if let Some(error) = self.exec_denied(pid, cmd) {
+            return Err(CmdOutput::error(1, error));
         }
-        match cmd {
-            "reboot" => "\x1b[REBOOT]".into(),
-            "echo" => {
-                let out = args.join(" ");
-                if out == "github" {
-                    format!("\x1b[OPEN:{}]", self.shell.env.get("GITHUB").unwrap())
-                } else {
-                    out
-                }
+        let user = self.current_user();
+        let tty = self.current_tty();
+        self.kernel
+            .proc
+            .set_session(pid, &user, Some(&tty), cmdline);
+        let mut priority = Priority::Normal;
+        if let Some(nice) = self.pending_nice.take() {
+            self.kernel.proc.set_nice(pid, nice);
+            priority = Priority::from_nice(nice);
+        }
+        self.kernel.scheduler.add(pid, priority);
+        Ok(Some(pid))
+    }
+
+    /// Run `cmd` from the registry in the process already set up for it
+    pub(super) fn dispatch_program(&mut self, cmd: &str, args: &[&str]) -> CmdOutput {
+        match self.shell.registry.get(cmd) {
+            Some(ProgramKind::BuiltIn(command)) => {
+                let command = *command;
+                command.run(self, args)
             }
-            "help" => self.cmd_help(),
-            "man" => self.cmd_man(args),
-            "nano" | "vi" | "vim" => self.cmd_nano(args),
-            "python" => self.cmd_python(args),
-            "doom" => {
-                // Parse optional difficulty argument: easy|normal|hard or 0|1|2,
-                // plus AI mode via `doom ai [easy|normal|hard]`.
-                if !args.is_empty() {
-                    let raw = args[0].to_lowercase();
-                    if raw == "ai" || raw == "bot" {
-                        let ai_diff = if args.len() > 1 {
-                            match args[1].to_lowercase().as_str() {
-                                "easy" | "0" => 4u8,
-                                "normal" | "1" => 3u8,
-                                "hard" | "2" => 5u8,
-                                _ => {
-                                    return "usage: doom ai [easy|normal|hard]".to_string();
-                                }
-                            }
-                        } else {
-                            3u8
-                        };
-                        return format!("\x1b[LAUNCH_DOOM:{}]", ai_diff);
-                    }
+            Some(ProgramKind::Package(_)) => self.exec_package_command(cmd, args),
+            None => CmdOutput::error(127, self.command_not_found(cmd)),
+        }
+    }
 
-                    let diff = match raw.as_str() {
-                        "easy" | "0" => Some(0u8),
-                        "normal" | "1" => Some(1u8),
-                        "hard" | "2" => Some(2u8),
-                        _ => None,
-                    };
-                    if let Some(d) = diff {
-                        return format!("\x1b[LAUNCH_DOOM:{}]", d);
-                    }
+    /// `echo [-e] TEXT`; `echo github` opens the project page
+    fn cmd_echo(&mut self, args: &[&str]) -> CmdOutput {
+        let out = match args {
+            ["-e", rest @ ..] => echo_escapes(&rest.join(" ")),
+            _ => args.join(" "),
+        };
+        if out == "github" {
+            let url = self.shell.env.get("GITHUB").unwrap().clone();
+            CmdOutput::ok(self.emit(SystemEvent::OpenUrl { url }))
+        } else {
+            CmdOutput::ok(out)
+        }
+    }
 
-                    return "usage: doom [easy|normal|hard|ai [easy|normal|hard]]".to_string();
-                }
-                "\x1b[LAUNCH_DOOM]".to_string()
+    /// `man NAME` opens the page in the pager; `man -k` and misses print
+    fn cmd_man_paged(&mut self, args: &[&str]) -> CmdOutput {
+        let page = self.cmd_man(args);
+        match args {
+            [name] if !name.starts_with('-') && page.status == 0 => {
+                let title = format!("Manual page {}(1)", name);
+                CmdOutput::ok(self.pager_start(&title, &page.stdout, false, true))
             }
-            "doommap" => {
-                if args.is_empty() {
-                    return "usage: doommap <proc|restore>".into();
-                }
-                match args[0] {
-                    "proc" => "\x1b[DOOM_ENABLE_PROC]".into(),
-                    "restore" => "\x1b[DOOM_RESTORE]".into(),
-                    _ => "usage: doommap <proc|restore>".into(),
+            _ => page,
+        }
+    }
+
+    /// `doom [easy|normal|hard|ai LEVEL|record FILE|play FILE]`; the
+    /// frontend starts the game on the escape this returns
+    fn cmd_doom(&mut self, args: &[&str]) -> CmdOutput {
+        // Doom runs right up against the memory limit and leaves the
+        // odd damaged cell behind for memtest to find
+        self.kernel.mem.scribble(1);
+        // `--renderer=gl|soft` may come anywhere; it sticks for later games
+        let mut args = args.to_vec();
+        if let Some(pos) = args.iter().position(|a| a.starts_with("--renderer=")) {
+            let name = &args[pos]["--renderer=".len()..];
+            match crate::doom::RendererKind::parse(name) {
+                Some(kind) => crate::doom::set_renderer(kind),
+                None => {
+                    return CmdOutput::error(
+                        1,
+                        format!("doom: unknown renderer '{}' (gl or soft)", name),
+                    )
                 }
             }
-            "screensaver" | "cmatrix" => "\x1b[LAUNCH_SCREENSAVER]".to_string(),
-            "wget" => self.cmd_wget(args),
-            "curl" => self.cmd_curl(args),
-            "myip" => self.cmd_myip(),
-            "ls" => self.cmd_ls(args),
-            "cd" => self.cmd_cd(args),
-            "pwd" => self.kernel.fs.cwd.clone(),
-            "cat" => self.cmd_cat(args),
-            "grep" => self.cmd_grep(args),
-            "find" => self.cmd_find(args),
-            "wc" => self.cmd_wc(args),
-            "cksum" => self.cmd_cksum(args),
-            "head" => self.cmd_head(args),
-            "tail" => self.cmd_tail(args),
-            "diff" => self.cmd_diff(args),
-            "sort" => self.cmd_sort(args),
-            "uniq" => self.cmd_uniq(args),
-            "cut" => self.cmd_cut(args),
-            "tr" => self.cmd_tr(args),
-            "tee" => self.cmd_tee(args),
-            "which" => self.cmd_which(args),
-            "whereis" => self.cmd_whereis(args),
-            "file" => self.cmd_file(args),
-            "ln" => self.cmd_ln(args),
-            "cp" => self.cmd_cp(args),
-            "mv" => self.cmd_mv(args),
-            "chmod" => self.cmd_chmod(args),
-            "chown" => self.cmd_chown(args),
-            "df" => self.cmd_df(args),
-            "du" => self.cmd_du(args),
-            "tar" => self.cmd_tar(args),
-            "gzip" | "gunzip" => self.cmd_gzip(args, cmd),
-            "zip" | "unzip" => self.cmd_zip(args, cmd),
-            "apt" | "apt-get" => self.cmd_apt(args),
-            "top" => self.cmd_top(args),
-            "htop" => self.cmd_htop(args),
-            "awk" => self.cmd_awk(args),
-            "sed" => self.cmd_sed(args),
-            "alias" => self.cmd_alias(args),
-            "unalias" => self.cmd_unalias(args),
-            "source" | "." => self.cmd_source(args),
-            "touch" => self.cmd_touch(args),
-            "mkdir" => self.cmd_mkdir(args),
-            "rmdir" => self.cmd_rmdir(args),
-            "rm" => self.cmd_rm(args),
-            "clear" => "\x1b[CLEAR]".into(),
-            "exit" => "\x1b[EXIT]".into(),
-            "ps" => self.cmd_ps(),
-            "kill" => self.cmd_kill(args),
-            "jobs" => self.cmd_jobs(args),
-            "bg" => self.cmd_bg(args),
-            "fg" => self.cmd_fg(args),
-            "disown" => self.cmd_disown(args),
-            "nohup" => self.cmd_nohup(args),
-            "uname" => self.cmd_uname(args),
-            "hostname" => self.cmd_hostname(),
-            "id" => self.cmd_id(args),
-            "groups" => self.cmd_groups(args),
-            "who" => self.cmd_who(args),
-            "whoami" => self
-                .shell
-                .env
-                .get("USER")
-                .cloned()
-                .unwrap_or_else(|| "user".into()),
-            "stat" => self.cmd_stat(args),
-            "mount" => self.cmd_mount(args),
-            "umount" => self.cmd_umount(args),
-            "uptime" => format!("up {}ms", self.kernel.uptime_ms()),
-            "date" => self.cmd_date(),
-            "free" => self.cmd_free(),
-            "history" => self.cmd_history(),
-            "env" => self.cmd_env(),
-            "export" => self.cmd_export(args),
-            "netstat" => self.cmd_netstat(args),
-            "ss" => self.cmd_ss(args),
-            "socket" => self.cmd_socket(args),
-            "service" => self.cmd_service(args),
-            "ping" => self.cmd_ping(args),
-            "traceroute" | "tracert" => self.cmd_traceroute(args),
-            "ifconfig" => self.cmd_ifconfig(args),
-            "ip" => self.cmd_ip(args),
-            "route" => self.cmd_route(args),
-            "arp" => self.cmd_arp(args),
-            "host" | "nslookup" | "dig" => self.cmd_host(args),
-            "nc" | "netcat" => self.cmd_nc(args),
-            "hasgrub" => {
-                if self.has_grub() {
-                    "yes".into()
+            args.remove(pos);
+        }
+        // Parse optional difficulty argument: easy|normal|hard or 0|1|2,
+        // plus AI mode via `doom ai [easy|normal|hard]`.
+        if !args.is_empty() {
+            let raw = args[0].to_lowercase();
+            if raw == "record" || raw == "play" {
+                return self.doom_demo_command(&raw, &args[1..]);
+            }
+            if raw == "ai" || raw == "bot" {
+                let ai_diff = if args.len() > 1 {
+                    match args[1].to_lowercase().as_str() {
+                        "easy" | "0" => 4u8,
+                        "normal" | "1" => 3u8,
+                        "hard" | "2" => 5u8,
+                        _ => {
+                            return CmdOutput::usage(
+                                "usage: doom ai [easy|normal|hard]".to_string(),
+                            );
+                        }
+                    }
                 } else {
-                    "no".into()
-                }
+                    3u8
+                };
+                return CmdOutput::ok(self.emit(SystemEvent::LaunchDoom {
+                    difficulty: Some(ai_diff),
+                }));
             }
-            "grub" => {
-                if args.is_empty() {
-                    return "\x1b[LAUNCH_GRUB]".into();
+
+            let diff = match raw.as_str() {
+                "easy" | "0" => Some(0u8),
+                "normal" | "1" => Some(1u8),
+                "hard" | "2" => Some(2u8),
+                _ => None,
+            };
+            if let Some(d) = diff {
+                return CmdOutput::ok(self.emit(SystemEvent::LaunchDoom {
+                    difficulty: Some(d),
+                }));
+            }
+
+            return CmdOutput::usage("usage: doom [easy|normal|hard|ai [easy|normal|hard]|record <file> [easy|normal|hard]|play <file>]".to_string());
+        }
+        CmdOutput::ok(self.emit(SystemEvent::LaunchDoom { difficulty: None }))
+    }
+
+    fn cmd_doommap(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            return CmdOutput::usage("usage: doommap <proc|restore>");
+        }
+        match args[0] {
+            "proc" => CmdOutput::ok(self.emit(SystemEvent::DoomMap { procedural: true })),
+            "restore" => CmdOutput::ok(self.emit(SystemEvent::DoomMap { procedural: false })),
+            _ => CmdOutput::usage("usage: doommap <proc|restore>"),
+        }
+    }
+
+    fn cmd_pong(&mut self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] => CmdOutput::ok(self.emit(SystemEvent::LaunchPong { cpu: false })),
+            ["cpu"] => CmdOutput::ok(self.emit(SystemEvent::LaunchPong { cpu: true })),
+            _ => CmdOutput::usage("usage: pong [cpu]".to_string()),
+        }
+    }
+
+    fn cmd_memtest(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            CmdOutput::ok(self.emit(SystemEvent::Memtest))
+        } else {
+            CmdOutput::usage("usage: memtest")
+        }
+    }
+
+    fn cmd_hasgrub(&self) -> CmdOutput {
+        if self.has_grub() {
+            CmdOutput::ok("yes")
+        } else {
+            CmdOutput::ok("no")
+        }
+    }
+
+    /// `grub [switch NAME|status|fastboot|boot]`, or the menu with no arguments
+    fn cmd_grub(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            return CmdOutput::ok(self.emit(SystemEvent::LaunchGrub));
+        }
+        match args[0] {
+            "switch" => {
+                if args.len() < 2 {
+                    return CmdOutput::usage("usage: grub switch <bootloader>");
                 }
-                match args[0] {
-                    "switch" => {
-                        if args.len() < 2 {
-                            return "usage: grub switch <bootloader>".into();
-                        }
-                        match self.boot.set_bootloader(args[1]) {
-                            Ok(_) => format!("Switched to {} bootloader", args[1]),
-                            Err(e) => format!("Error: {}", e),
-                        }
-                    }
-                    "status" => {
-                        let current = self.boot.get_current_bootloader();
-                        let available = self.boot.list_bootloaders().join(", ");
-                        format!(
-                            "Current bootloader: {}\nAvailable bootloaders: {}",
-                            current, available
-                        )
-                    }
-                    "boot" => {
-                        let messages = self.boot.simulate_boot_sequence(&mut self.kernel.mem);
-                        self.booted = true; // Mark system as booted for grub boot
-                        format!("\x1b[BOOT_SEQUENCE:{}]", messages.join("|"))
-                    }
-                    _ => "usage: grub <switch|status|boot>".into(),
+                match self.boot.set_bootloader(args[1]) {
+                    Ok(_) => CmdOutput::ok(format!("Switched to {} bootloader", args[1])),
+                    Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                 }
             }
-            "" => String::new(),
-            _ => format!("sh: {}: command not found", cmd),
+            "status" => {
+                let current = self.boot.get_current_bootloader();
+                let available = self.boot.list_bootloaders().join(", ");
+                CmdOutput::ok(format!(
+                    "Current bootloader: {}\nAvailable bootloaders: {}",
+                    current, available
+                ))
+            }
+            "fastboot" => self.cmd_grub_fastboot(&args[1..]),
+            "boot" => {
+                let messages = self.boot_with_params();
+                self.booted = true; // Mark system as booted for grub boot
+                CmdOutput::ok(self.emit(SystemEvent::BootSequence { messages }))
+            }
+            _ => CmdOutput::usage("usage: grub <switch|status|fastboot|boot>"),
         }
     }
 
+    /// Forward a key press to the interactive `htop` view
+    #[wasm_bindgen]
+    pub fn htop_input(&mut self, key: &str) -> String {
+        self.htop_key(key)
+    }
+
+    #[wasm_bindgen]
+    pub fn is_htop_active(&self) -> bool {
+        self.htop.is_some()
+    }
+
+    /// Open a file in the pager, returning the first page
+    #[wasm_bindgen]
+    pub fn pager_open(&mut self, path: &str) -> String {
+        self.pager_open_path(path, false, false).flatten()
+    }
+
+    /// Scroll the open pager by `lines` (negative scrolls back)
+    #[wasm_bindgen]
+    pub fn pager_scroll(&mut self, lines: i32) -> String {
+        self.pager_scroll_by(lines)
+    }
+
+    /// Jump to the next line containing `pattern`; an empty pattern repeats the last search
+    #[wasm_bindgen]
+    pub fn pager_search(&mut self, pattern: &str) -> String {
+        self.pager_find_text(pattern)
+    }
+
+    /// Forward a key press to the open pager
+    #[wasm_bindgen]
+    pub fn pager_input(&mut self, key: &str) -> String {
+        self.pager_key(key)
+    }
+
+    #[wasm_bindgen]
+    pub fn is_pager_active(&self) -> bool {
+        self.pager.is_some()
+    }
+
     #[wasm_bindgen]
     pub fn set_user_password(&mut self, pw: &str) {
         self.user_password = Some(pw.into());
@@ -562,10 +986,10 @@ impl System {
         })
     }
 
-    fn handle_sudo(&mut self, args: &[&str]) -> String {
+    fn handle_sudo(&mut self, args: &[&str]) -> CmdOutput {
         let parsed = match self.parse_sudo_invocation(args) {
             Ok(p) => p,
-            Err(msg) => return msg,
+            Err(msg) => return CmdOutput::error(1, msg),
         };
 
         if parsed.clear_timestamp || parsed.reset_timestamp {
@@ -577,7 +1001,7 @@ impl System {
         }
 
         if !parsed.validate_only && !parsed.list_privileges && parsed.command.is_none() {
-            return Self::sudo_usage();
+            return CmdOutput::error(1, Self::sudo_usage());
         }
 
         let current_user = self.current_user();
@@ -590,18 +1014,18 @@ impl System {
 
         if is_authenticated {
             if parsed.list_privileges {
-                return self.sudo_list_privileges();
+                return CmdOutput::ok(self.sudo_list_privileges());
             }
             if parsed.validate_only {
                 self.sudo_authenticated_until = Some(now + SUDO_TIMEOUT_MS);
-                return String::new();
+                return CmdOutput::default();
             }
             return self
                 .exec_sudo_internal(parsed.command.as_deref().unwrap_or(""), &parsed.target_user);
         }
 
         if parsed.non_interactive {
-            return "sudo: a password is required".into();
+            return CmdOutput::error(1, "sudo: a password is required");
         }
 
         let mut rendered_prompt = parsed.prompt;
@@ -613,14 +1037,15 @@ impl System {
             target_user: parsed.target_user,
             validate_only: parsed.validate_only,
             list_privileges: parsed.list_privileges,
+            switch_user: None,
         });
         self.sudo_waiting_password = true;
 
-        if rendered_prompt.is_empty() {
+        CmdOutput::ok(if rendered_prompt.is_empty() {
             format!("[sudo] password for {}:", current_user)
         } else {
             rendered_prompt
-        }
+        })
     }
 
     fn sudo_list_privileges(&self) -> String {
@@ -631,7 +1056,7 @@ impl System {
         )
     }
 
-    fn exec_sudo_internal(&mut self, cmd: &str, target_user: &str) -> String {
+    fn exec_sudo_internal(&mut self, cmd: &str, target_user: &str) -> CmdOutput {
         let old_user = self
             .shell
             .env
@@ -654,7 +1079,13 @@ impl System {
         let _ = self.kernel.fs.create_dir(&target_home);
         self.kernel.fs.set_default_owner(target_user, target_user);
 
-        let out = self.exec(cmd);
+        let mark = self.session_mark();
+        let out = self.exec_nested(cmd);
+        // `sudo su` stays in the root shell, which `exit` leaves for the
+        // user sudo ran as
+        if self.hand_su_back(mark, &old_user, &old_home, &old_owner, &old_group) {
+            return out;
+        }
 
         // revert
         self.shell.env.insert("USER".into(), old_user);
@@ -670,26 +1101,27 @@ impl System {
         target_user: &str,
         validate_only: bool,
         list_privileges: bool,
-    ) -> String {
+    ) -> CmdOutput {
         match &self.user_password {
             Some(saved) if saved == pw => {
                 let now = js_sys::Date::now();
                 self.sudo_authenticated_until = Some(now + SUDO_TIMEOUT_MS);
                 if list_privileges {
-                    self.sudo_list_privileges()
+                    CmdOutput::ok(self.sudo_list_privileges())
                 } else if validate_only {
-                    String::new()
+                    CmdOutput::default()
                 } else {
                     self.exec_sudo_internal(cmd.unwrap_or(""), target_user)
                 }
             }
-            _ => "sudo: 1 incorrect password attempt".into(),
+            _ => CmdOutput::error(1, "sudo: 1 incorrect password attempt"),
         }
     }
 
     #[wasm_bindgen]
     pub fn exec_sudo(&mut self, cmd: &str, pw: &str) -> String {
         self.exec_sudo_with_context(Some(cmd), pw, "root", false, false)
+            .flatten()
     }
 
     #[wasm_bindgen]
@@ -697,6 +1129,23 @@ impl System {
         self.sudo_waiting_password
     }
 
+    /// A command like `rm -i` asked a question, or `lynx` has a page up,
+    /// and the next line answers it
+    #[wasm_bindgen]
+    pub fn is_waiting_for_answer(&self) -> bool {
+        self.rm_prompt.is_some() || self.browser.is_some()
+    }
+
+    /// Text of /boot/grub/grub.cfg for the boot menu, empty if it's gone
+    #[wasm_bindgen]
+    pub fn grub_config(&self) -> String {
+        self.kernel
+            .fs
+            .resolve("/boot/grub/grub.cfg")
+            .map(|node| node.data.clone())
+            .unwrap_or_default()
+    }
+
     #[wasm_bindgen]
     pub fn has_grub(&self) -> bool {
         // Ensure filesystem initialized before checking
@@ -711,80 +1160,7 @@ impl System {
         self.kernel.fs.resolve("/boot/grub/grub.cfg").is_some()
     }
 
-    fn cmd_ls(&self, args: &[&str]) -> String {
-        let mut show_all = false;
-        let mut show_long = false;
-        let mut path = ".";
-
-        for arg in args {
-            if *arg == "-l" {
-                show_long = true;
-            } else if *arg == "-a" {
-                show_all = true;
-            } else if *arg == "-la" || *arg == "-al" {
-                show_long = true;
-                show_all = true;
-            } else if !arg.starts_with('-') {
-                path = arg;
-            }
-        }
-
-        match self.kernel.fs.resolve(path) {
-            Some(node) if node.is_dir => {
-                let mut entries: Vec<_> = node.children.iter().collect();
-                entries.sort_by(|a, b| a.0.cmp(b.0));
-
-                if show_long {
-                    let mut out = String::new();
-                    if show_all {
-                        out.push_str("drwxr-xr-x   2 user     user         4096 Nov 29 12:00 \x1b[COLOR:blue].\x1b[COLOR:reset]\n");
-                        out.push_str("drwxr-xr-x   2 root     root         4096 Nov 29 12:00 \x1b[COLOR:blue]..\x1b[COLOR:reset]\n");
-                    }
-                    for (name, child) in &entries {
-                        if !show_all && name.starts_with('.') {
-                            continue;
-                        }
-                        let name_display = if child.is_dir {
-                            format!("\x1b[COLOR:blue]{}\x1b[COLOR:reset]", name)
-                        } else if child.is_executable {
-                            format!("\x1b[COLOR:green]{}\x1b[COLOR:reset]", name)
-                        } else {
-                            name.to_string()
-                        };
-                        out.push_str(&format!(
-                            "{} {:>3} {:>8} {:>8} {:>8} {} {}\n",
-                            child.permissions,
-                            1,
-                            child.owner,
-                            child.group,
-                            child.size,
-                            "Nov 29 12:00",
-                            name_display
-                        ));
-                    }
-                    out.trim_end().to_string()
-                } else {
-                    let names: Vec<String> = entries
-                        .iter()
-                        .filter(|(name, _)| show_all || !name.starts_with('.'))
-                        .map(|(name, child)| {
-                            if child.is_dir {
-                                format!("\x1b[COLOR:blue]{}\x1b[COLOR:reset]", name)
-                            } else if child.is_executable {
-                                format!("\x1b[COLOR:green]{}\x1b[COLOR:reset]", name)
-                            } else {
-                                name.to_string()
-                            }
-                        })
-                        .collect();
-                    names.join("  ")
-                }
-            }
-            Some(node) => node.name.clone(),
-            None => format!("ls: cannot access '{}': No such file or directory", path),
-        }
-    }
-    fn cmd_cd(&mut self, args: &[&str]) -> String {
+    fn cmd_cd(&mut self, args: &[&str]) -> CmdOutput {
         let default_home = self
             .shell
             .env
@@ -796,30 +1172,57 @@ impl System {
         } else {
             args[0]
         };
+        if self.kernel.fs.resolve(target).is_some_and(|n| n.is_dir) && !self.has_access(target, 1) {
+            return CmdOutput::error(1, format!("cd: {}: Permission denied", target));
+        }
         match self.kernel.fs.cd(target) {
-            Ok(()) => String::new(),
-            Err(e) => format!("cd: {}: {}", target, e),
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) => CmdOutput::error(1, format!("cd: {}: {}", target, e)),
         }
     }
-    fn cmd_cat(&self, args: &[&str]) -> String {
+    fn cmd_cat(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "cat: missing operand".into();
+            return CmdOutput::error(1, "cat: missing operand");
         }
 
         let path = args[0];
+        if self.has_access(path, 4) {
+            self.kernel.fs.mark_accessed(path);
+        }
         match self.kernel.fs.resolve(path) {
-            Some(n) if n.is_dir => format!("cat: {}: Is a directory", path),
+            Some(n) if n.is_dir => CmdOutput::error(1, format!("cat: {}: Is a directory", path)),
+            Some(_) if !self.has_access(path, 4) => {
+                CmdOutput::error(1, format!("cat: {}: Permission denied", path))
+            }
             Some(n) if n.permissions.starts_with('l') => {
                 let target = n.data.trim();
                 match self.kernel.fs.resolve(target) {
-                    Some(t) if t.is_dir => format!("cat: {}: Is a directory", target),
-                    Some(t) => t.data.clone(),
-                    None => format!("cat: {}: No such file or directory", target),
+                    Some(t) if t.is_dir => {
+                        CmdOutput::error(1, format!("cat: {}: Is a directory", target))
+                    }
+                    Some(t) => CmdOutput::ok(t.data.clone()),
+                    None => {
+                        CmdOutput::error(1, format!("cat: {}: No such file or directory", target))
+                    }
                 }
             }
-            Some(n) => n.data.clone(),
-            None => format!("cat: {}: No such file or directory", path),
+            Some(n) => CmdOutput::ok(n.data.clone()),
+            None => CmdOutput::error(1, format!("cat: {}: No such file or directory", path)),
+        }
+    }
+
+    /// The message of the day for a login that is just starting, written
+    /// before the login is recorded so it shows the previous one
+    #[wasm_bindgen]
+    pub fn login_motd(&mut self) -> String {
+        let user = self.current_user();
+        if self.last_login(&user).is_none() {
+            self.deliver_welcome_mail(&user);
         }
+        self.refresh_motd();
+        self.record_login(&user);
+        self.console_login(&user);
+        self.cmd_motd(&[]).flatten()
     }
 
     #[wasm_bindgen]
@@ -832,174 +1235,195 @@ impl System {
         self.shell.env.insert("USER".into(), uname.into());
         let home = format!("/home/{}", uname);
         self.shell.env.insert("HOME".into(), home.clone());
-        // Ensure home directory exists
+        self.ensure_user_entry(uname);
+        // Ensure home directory exists and belongs to the new user
         let _ = self.kernel.fs.create_dir(&home);
+        if let Some(node) = self.kernel.fs.resolve_mut(&home) {
+            node.owner = uname.into();
+            node.group = uname.into();
+        }
         // Update default owner for new files/directories
         self.kernel.fs.set_default_owner(uname, uname);
-    }
-    fn cmd_touch(&mut self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "touch: missing file operand".into();
-        }
-        match self.kernel.fs.create_file(args[0], "") {
-            Ok(()) => String::new(),
-            Err(e) => format!("touch: cannot touch '{}': {}", args[0], e),
+        self.load_history();
+        self.apply_screensaver_config();
+        self.apply_idle_config();
+        self.apply_audio_config();
+    }
+    fn cmd_touch(&mut self, args: &[&str]) -> CmdOutput {
+        let now = clock::now_secs();
+        let mut time = now;
+        let (mut access, mut modify, mut create) = (false, false, true);
+        let mut files = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match *arg {
+                "-t" | "-d" => {
+                    let Some(first) = iter.next() else {
+                        return CmdOutput::error(
+                            1,
+                            format!("touch: option requires an argument -- '{}'", &arg[1..]),
+                        );
+                    };
+                    // The line is split on spaces, so put `-d "DATE TIME"` back together
+                    let mut value = first.to_string();
+                    if let Some(q) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+                        while value.len() < 2 || !value.ends_with(q) {
+                            match iter.next() {
+                                Some(more) => value = format!("{} {}", value, more),
+                                None => break,
+                            }
+                        }
+                    }
+                    let value = unquote(&value);
+                    let parsed = if *arg == "-t" {
+                        clock::parse_touch_stamp(value, now)
+                    } else {
+                        clock::parse_date(value, now)
+                    };
+                    match parsed {
+                        Some(t) => time = t,
+                        None if *arg == "-t" => {
+                            return CmdOutput::error(
+                                1,
+                                format!("touch: invalid date format '{}'", value),
+                            )
+                        }
+                        None => {
+                            return CmdOutput::error(1, format!("touch: invalid date '{}'", value))
+                        }
+                    }
+                }
+                flags if flags.starts_with('-') && flags.len() > 1 => {
+                    for c in flags[1..].chars() {
+                        match c {
+                            'a' => access = true,
+                            'm' => modify = true,
+                            'c' => create = false,
+                            _ => {
+                                return CmdOutput::error(
+                                    1,
+                                    format!("touch: invalid option -- '{}'", c),
+                                )
+                            }
+                        }
+                    }
+                }
+                file => files.push(file),
+            }
         }
-    }
-    fn cmd_mkdir(&mut self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "mkdir: missing operand".into();
+        if files.is_empty() {
+            return CmdOutput::error(1, "touch: missing file operand");
         }
-        match self.kernel.fs.create_dir(args[0]) {
-            Ok(()) => String::new(),
-            Err(e) => format!("mkdir: cannot create directory '{}': {}", args[0], e),
+        // Neither -a nor -m means both
+        if !access && !modify {
+            access = true;
+            modify = true;
         }
-    }
 
-    fn cmd_rmdir(&mut self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "rmdir: missing operand".into();
-        }
-        for dir in args {
-            match self.kernel.fs.resolve(dir) {
-                Some(node) if !node.is_dir => {
-                    return format!("rmdir: failed to remove '{}': Not a directory", dir);
-                }
-                Some(_) => match self.kernel.fs.remove(dir) {
-                    Ok(()) => {}
-                    Err(e) => return format!("rmdir: failed to remove '{}': {}", dir, e),
-                },
-                None => {
-                    return format!(
-                        "rmdir: failed to remove '{}': No such file or directory",
-                        dir
-                    )
+        let mut errors = Vec::new();
+        for file in files {
+            if self.kernel.fs.resolve(file).is_none() {
+                if !create {
+                    continue;
+                }
+                if !self.can_write_path(file) {
+                    errors.push(format!("touch: cannot touch '{}': Permission denied", file));
+                    continue;
+                }
+                if let Err(e) = self.kernel.fs.create_file(file, "") {
+                    errors.push(format!("touch: cannot touch '{}': {}", file, e));
+                    continue;
                 }
+            } else if !self.can_write_path(file) {
+                errors.push(format!("touch: cannot touch '{}': Permission denied", file));
+                continue;
+            }
+            let result =
+                self.kernel
+                    .fs
+                    .set_times(file, access.then_some(time), modify.then_some(time));
+            if let Err(e) = result {
+                errors.push(format!("touch: setting times of '{}': {}", file, e));
             }
         }
-        String::new()
+        CmdOutput::collected(Vec::new(), errors)
     }
-    fn cmd_rm(&mut self, args: &[&str]) -> String {
+    fn cmd_mkdir(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "rm: missing operand".into();
-        }
-
-        let mut force = false;
-        let mut recursive = false;
-        let mut files = Vec::new();
-
-        for arg in args {
-            match *arg {
-                "-f" | "--force" => force = true,
-                "-r" | "-rf" | "-fr" => {
-                    recursive = true;
-                    force = true;
-                }
-                other => files.push(other),
-            }
+            return CmdOutput::error(1, "mkdir: missing operand");
         }
-
-        if files.is_empty() {
-            return "rm: missing operand".into();
+        if self.kernel.fs.resolve(args[0]).is_none() && !self.can_write_path(args[0]) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "mkdir: cannot create directory '{}': Permission denied",
+                    args[0]
+                ),
+            );
         }
-
-        for file in files {
-            if recursive {
-                self.kernel.fs.set_ignore_critical_deletes(true);
-            }
-            let res = if recursive {
-                self.kernel.fs.remove_recursive(file)
-            } else {
-                self.kernel.fs.remove(file)
-            };
-            if recursive {
-                self.kernel.fs.set_ignore_critical_deletes(false);
-            }
-            match res {
-                Ok(()) => {}
-                Err(e) => {
-                    if self.kernel.fs.kernel_panic {
-                        return format!("\x1b[KERNEL_PANIC]{}", self.kernel.fs.panic_reason);
-                    }
-                    if self.kernel.memory_panic {
-                        return format!("\x1b[KERNEL_PANIC]{}", self.kernel.memory_panic_reason);
-                    }
-                    if !force {
-                        return format!("rm: cannot remove '{}': {}", file, e);
-                    }
-                }
-            }
+        match self.kernel.fs.create_dir(args[0]) {
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) => CmdOutput::error(
+                1,
+                format!("mkdir: cannot create directory '{}': {}", args[0], e),
+            ),
         }
-        String::new()
     }
 
-    fn cmd_grep(&self, args: &[&str]) -> String {
-        if args.len() < 2 {
-            return "usage: grep [pattern] [file]".into();
+    fn cmd_rmdir(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            return CmdOutput::error(1, "rmdir: missing operand");
         }
-        let pattern = args[0];
-        let file_path = args[1];
-        match self.kernel.fs.resolve(file_path) {
-            Some(node) if !node.is_dir => {
-                let lines: Vec<&str> = node.data.lines().collect();
-                let matches: Vec<String> = lines
-                    .iter()
-                    .filter(|line| line.contains(pattern))
-                    .map(|s| s.to_string())
-                    .collect();
-                if matches.is_empty() {
-                    String::new()
-                } else {
-                    matches.join("\n")
+        for dir in args {
+            match self.kernel.fs.resolve(dir) {
+                Some(node) if !node.is_dir => {
+                    return CmdOutput::error(
+                        1,
+                        format!("rmdir: failed to remove '{}': Not a directory", dir),
+                    );
                 }
-            }
-            Some(_) => format!("grep: {}: Is a directory", file_path),
-            None => format!("grep: {}: No such file or directory", file_path),
-        }
-    }
-
-    fn cmd_find(&self, args: &[&str]) -> String {
-        let path = if args.is_empty() { "." } else { args[0] };
-        let mut results = Vec::new();
-        self.find_recursive(&self.kernel.fs.normalize(path), &mut results);
-        results.join("\n")
-    }
-
-    fn find_recursive(&self, path: &str, results: &mut Vec<String>) {
-        if let Some(node) = self.kernel.fs.resolve(path) {
-            results.push(path.to_string());
-            if node.is_dir {
-                for name in node.children.keys() {
-                    let child_path = if path == "/" {
-                        format!("/{}", name)
-                    } else {
-                        format!("{}/{}", path, name)
-                    };
-                    self.find_recursive(&child_path, results);
+                Some(_) => match self.kernel.fs.remove(dir) {
+                    Ok(()) => {}
+                    Err(e) => {
+                        return CmdOutput::error(
+                            1,
+                            format!("rmdir: failed to remove '{}': {}", dir, e),
+                        )
+                    }
+                },
+                None => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "rmdir: failed to remove '{}': No such file or directory",
+                            dir
+                        ),
+                    )
                 }
             }
         }
+        CmdOutput::ok(String::new())
     }
 
-    fn cmd_wc(&self, args: &[&str]) -> String {
+    fn cmd_wc(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: wc [file]".into();
+            return CmdOutput::usage("usage: wc [file]");
         }
         match self.kernel.fs.resolve(args[0]) {
             Some(node) if !node.is_dir => {
                 let lines = node.data.lines().count();
                 let words = node.data.split_whitespace().count();
                 let chars = node.data.len();
-                format!("{:7} {:7} {:7} {}", lines, words, chars, args[0])
+                CmdOutput::ok(format!("{:7} {:7} {:7} {}", lines, words, chars, args[0]))
             }
-            Some(_) => format!("wc: {}: Is a directory", args[0]),
-            None => format!("wc: {}: No such file or directory", args[0]),
+            Some(_) => CmdOutput::error(1, format!("wc: {}: Is a directory", args[0])),
+            None => CmdOutput::error(1, format!("wc: {}: No such file or directory", args[0])),
         }
     }
 
-    fn cmd_cksum(&self, args: &[&str]) -> String {
+    fn cmd_cksum(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: cksum [-a crc32|adler32] FILE...".into();
+            return CmdOutput::usage("usage: cksum [-a crc32|adler32] FILE...");
         }
 
         let mut idx = 0;
@@ -1009,12 +1433,15 @@ impl System {
             idx = 2;
         }
         if idx >= args.len() {
-            return "usage: cksum [-a crc32|adler32] FILE...".into();
+            return CmdOutput::usage("usage: cksum [-a crc32|adler32] FILE...");
         }
         if algo != "crc32" && algo != "adler32" {
-            return format!(
-                "cksum: unsupported algorithm '{}': expected crc32 or adler32",
-                algo
+            return CmdOutput::error(
+                1,
+                format!(
+                    "cksum: unsupported algorithm '{}': expected crc32 or adler32",
+                    algo
+                ),
             );
         }
 
@@ -1022,7 +1449,7 @@ impl System {
         for path in &args[idx..] {
             let data = match self.read_file_bytes(path) {
                 Ok(data) => data,
-                Err(e) => return format!("cksum: {}", e),
+                Err(e) => return CmdOutput::error(1, format!("cksum: {}", e)),
             };
             let sum = if algo == "adler32" {
                 crate::cpp_accel::adler32(&data)
@@ -1031,86 +1458,119 @@ impl System {
             };
             lines.push(format!("{} {} {}", sum, data.len(), path));
         }
-        lines.join("\n")
+        CmdOutput::ok(lines.join("\n"))
     }
 
-    fn cmd_head(&self, args: &[&str]) -> String {
-        let (n, file) = if args.len() >= 2 && args[0] == "-n" {
-            (args[1].parse().unwrap_or(10), args.get(2).copied())
-        } else {
-            (10, args.first().copied())
-        };
-
-        if file.is_none() {
-            return "usage: head [-n lines] [file]".into();
-        }
+    fn cmd_head(&self, args: &[&str]) -> CmdOutput {
+        self.head_tail("head", args)
+    }
 
-        match self.kernel.fs.resolve(file.unwrap()) {
-            Some(node) if !node.is_dir => node.data.lines().take(n).collect::<Vec<_>>().join("\n"),
-            Some(_) => format!("head: {}: Is a directory", file.unwrap()),
-            None => format!("head: {}: No such file or directory", file.unwrap()),
-        }
+    fn cmd_tail(&self, args: &[&str]) -> CmdOutput {
+        self.head_tail("tail", args)
     }
 
-    fn cmd_tail(&self, args: &[&str]) -> String {
-        let (n, file) = if args.len() >= 2 && args[0] == "-n" {
-            (args[1].parse().unwrap_or(10), args.get(2).copied())
-        } else {
-            (10, args.first().copied())
+    /// `head` and `tail`: the first or last 10 lines, `-n NUM` lines or
+    /// `-c NUM` bytes. For `tail`, `+NUM` counts from the start instead
+    fn head_tail(&self, prog: &str, args: &[&str]) -> CmdOutput {
+        let usage = format!("usage: {} [-n NUM | -c NUM] FILE", prog);
+        let mut count = "10";
+        let mut bytes = false;
+        let mut file = None;
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            let (flag, value) = match *arg {
+                "-n" | "-c" => (&arg[1..], rest.next().copied()),
+                a if a.starts_with("--lines=") => ("n", a.strip_prefix("--lines=")),
+                a if a.starts_with("--bytes=") => ("c", a.strip_prefix("--bytes=")),
+                a if a.starts_with("-n") || a.starts_with("-c") => (&a[1..2], Some(&a[2..])),
+                a if a
+                    .strip_prefix('-')
+                    .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) =>
+                {
+                    ("n", a.strip_prefix('-'))
+                }
+                a if a.starts_with('-') && a.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!("{}: invalid option -- '{}'\n{}", prog, &a[1..], usage),
+                    )
+                }
+                a => {
+                    file = Some(a);
+                    continue;
+                }
+            };
+            let Some(value) = value else {
+                return CmdOutput::error(
+                    1,
+                    format!(
+                        "{}: option requires an argument -- '{}'\n{}",
+                        prog, flag, usage
+                    ),
+                );
+            };
+            count = value;
+            bytes = flag == "c";
+        }
+        let Some(file) = file else {
+            return CmdOutput::usage(usage);
         };
-
-        if file.is_none() {
-            return "usage: tail [-n lines] [file]".into();
+        let from_start = prog == "tail" && count.starts_with('+');
+        let Ok(n) = count.trim_start_matches('+').parse::<usize>() else {
+            let what = if bytes { "bytes" } else { "lines" };
+            return CmdOutput::error(
+                1,
+                format!("{}: invalid number of {}: '{}'", prog, what, count),
+            );
+        };
+        if self.kernel.fs.resolve(file).is_some() && !self.has_access(file, 4) {
+            return CmdOutput::error(1, format!("{}: {}: Permission denied", prog, file));
         }
-
-        match self.kernel.fs.resolve(file.unwrap()) {
+        if bytes {
+            let data = match self.read_file_bytes(file) {
+                Ok(data) => data,
+                Err(e) => return CmdOutput::error(1, format!("{}: {}", prog, e)),
+            };
+            let range = match (prog, from_start) {
+                ("head", _) => 0..n.min(data.len()),
+                (_, true) => n.saturating_sub(1).min(data.len())..data.len(),
+                _ => data.len().saturating_sub(n)..data.len(),
+            };
+            return CmdOutput::ok(bytes_to_data(data[range].to_vec()));
+        }
+        match self.kernel.fs.resolve(file) {
             Some(node) if !node.is_dir => {
                 let lines: Vec<&str> = node.data.lines().collect();
-                let start = if lines.len() > n { lines.len() - n } else { 0 };
-                lines[start..].join("\n")
-            }
-            Some(_) => format!("tail: {}: Is a directory", file.unwrap()),
-            None => format!("tail: {}: No such file or directory", file.unwrap()),
-        }
-    }
-
-    fn cmd_diff(&self, args: &[&str]) -> String {
-        if args.len() < 2 {
-            return "usage: diff [file1] [file2]".into();
-        }
-        let file1 = self.kernel.fs.resolve(args[0]);
-        let file2 = self.kernel.fs.resolve(args[1]);
-
-        match (file1, file2) {
-            (Some(f1), Some(f2)) if !f1.is_dir && !f2.is_dir => {
-                if f1.data == f2.data {
-                    String::new()
-                } else {
-                    format!("Files {} and {} differ", args[0], args[1])
-                }
+                let range = match (prog, from_start) {
+                    ("head", _) => 0..n.min(lines.len()),
+                    (_, true) => n.saturating_sub(1).min(lines.len())..lines.len(),
+                    _ => lines.len().saturating_sub(n)..lines.len(),
+                };
+                CmdOutput::ok(lines[range].join("\n"))
             }
-            _ => "diff: invalid files".into(),
+            Some(_) => CmdOutput::error(1, format!("{}: {}: Is a directory", prog, file)),
+            None => CmdOutput::error(1, format!("{}: {}: No such file or directory", prog, file)),
         }
     }
 
-    fn cmd_sort(&self, args: &[&str]) -> String {
+    fn cmd_sort(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: sort [file]".into();
+            return CmdOutput::usage("usage: sort [file]");
         }
         match self.kernel.fs.resolve(args[0]) {
             Some(node) if !node.is_dir => {
                 let mut lines: Vec<&str> = node.data.lines().collect();
                 lines.sort();
-                lines.join("\n")
+                CmdOutput::ok(lines.join("\n"))
             }
-            Some(_) => format!("sort: {}: Is a directory", args[0]),
-            None => format!("sort: {}: No such file or directory", args[0]),
+            Some(_) => CmdOutput::error(1, format!("sort: {}: Is a directory", args[0])),
+            None => CmdOutput::error(1, format!("sort: {}: No such file or directory", args[0])),
         }
     }
 
-    fn cmd_uniq(&self, args: &[&str]) -> String {
+    fn cmd_uniq(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: uniq [file]".into();
+            return CmdOutput::usage("usage: uniq [file]");
         }
         match self.kernel.fs.resolve(args[0]) {
             Some(node) if !node.is_dir => {
@@ -1123,10 +1583,10 @@ impl System {
                         last = line;
                     }
                 }
-                result.join("\n")
+                CmdOutput::ok(result.join("\n"))
             }
-            Some(_) => format!("uniq: {}: Is a directory", args[0]),
-            None => format!("uniq: {}: No such file or directory", args[0]),
+            Some(_) => CmdOutput::error(1, format!("uniq: {}: Is a directory", args[0])),
+            None => CmdOutput::error(1, format!("uniq: {}: No such file or directory", args[0])),
         }
     }
 
@@ -1198,9 +1658,9 @@ impl System {
         out
     }
 
-    fn cmd_cut(&self, args: &[&str]) -> String {
+    fn cmd_cut(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: cut (-f LIST [-d DELIM] | -c LIST) FILE".into();
+            return CmdOutput::usage("usage: cut (-f LIST [-d DELIM] | -c LIST) FILE");
         }
 
         let mut delim = '\t';
@@ -1213,14 +1673,14 @@ impl System {
             match args[i] {
                 "-d" => {
                     if i + 1 >= args.len() {
-                        return "cut: option requires an argument -- 'd'".into();
+                        return CmdOutput::error(1, "cut: option requires an argument -- 'd'");
                     }
                     delim = args[i + 1].chars().next().unwrap_or('\t');
                     i += 2;
                 }
                 "-f" => {
                     if i + 1 >= args.len() {
-                        return "cut: option requires an argument -- 'f'".into();
+                        return CmdOutput::error(1, "cut: option requires an argument -- 'f'");
                     }
                     mode_chars = false;
                     list_spec = Some(args[i + 1].to_string());
@@ -1228,14 +1688,14 @@ impl System {
                 }
                 "-c" => {
                     if i + 1 >= args.len() {
-                        return "cut: option requires an argument -- 'c'".into();
+                        return CmdOutput::error(1, "cut: option requires an argument -- 'c'");
                     }
                     mode_chars = true;
                     list_spec = Some(args[i + 1].to_string());
                     i += 2;
                 }
                 value if value.starts_with('-') => {
-                    return format!("cut: invalid option -- '{}'", value);
+                    return CmdOutput::error(1, format!("cut: invalid option -- '{}'", value));
                 }
                 value => {
                     file = Some(value);
@@ -1246,22 +1706,26 @@ impl System {
 
         let file = match file {
             Some(f) => f,
-            None => return "cut: missing file operand".into(),
+            None => return CmdOutput::error(1, "cut: missing file operand"),
         };
         let list_spec = match list_spec {
             Some(s) => s,
-            None => return "cut: one of -f or -c must be specified".into(),
+            None => return CmdOutput::error(1, "cut: one of -f or -c must be specified"),
         };
 
         let list = match Self::parse_list_spec(&list_spec) {
             Ok(v) => v,
-            Err(e) => return format!("cut: invalid list value: {}", e),
+            Err(e) => return CmdOutput::error(1, format!("cut: invalid list value: {}", e)),
         };
 
         let node = match self.kernel.fs.resolve(file) {
-            Some(n) if n.is_dir => return format!("cut: {}: Is a directory", file),
+            Some(n) if n.is_dir => {
+                return CmdOutput::error(1, format!("cut: {}: Is a directory", file))
+            }
             Some(n) => n,
-            None => return format!("cut: {}: No such file or directory", file),
+            None => {
+                return CmdOutput::error(1, format!("cut: {}: No such file or directory", file))
+            }
         };
 
         let mut out = Vec::new();
@@ -1284,12 +1748,14 @@ impl System {
                 out.push(selected.join(&delim.to_string()));
             }
         }
-        out.join("\n")
+        CmdOutput::ok(out.join("\n"))
     }
 
-    fn cmd_tr(&self, args: &[&str]) -> String {
+    fn cmd_tr(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: tr [-d] SET1 [SET2] <text> | tr [-d] SET1 [SET2] -f FILE".into();
+            return CmdOutput::usage(
+                "usage: tr [-d] SET1 [SET2] <text> | tr [-d] SET1 [SET2] -f FILE",
+            );
         }
 
         let mut delete_mode = false;
@@ -1300,7 +1766,7 @@ impl System {
         }
 
         if idx >= args.len() {
-            return "tr: missing SET1 operand".into();
+            return CmdOutput::error(1, "tr: missing SET1 operand");
         }
         let set1 = Self::expand_tr_set(args[idx]);
         idx += 1;
@@ -1309,29 +1775,33 @@ impl System {
             Vec::new()
         } else {
             if idx >= args.len() {
-                return "tr: missing SET2 operand".into();
+                return CmdOutput::error(1, "tr: missing SET2 operand");
             }
             let s = Self::expand_tr_set(args[idx]);
             idx += 1;
             if s.is_empty() {
-                return "tr: SET2 cannot be empty".into();
+                return CmdOutput::error(1, "tr: SET2 cannot be empty");
             }
             s
         };
 
         let input = if idx < args.len() && args[idx] == "-f" {
             if idx + 1 >= args.len() {
-                return "tr: -f requires a file path".into();
+                return CmdOutput::error(1, "tr: -f requires a file path");
             }
             let path = args[idx + 1];
             match self.kernel.fs.resolve(path) {
-                Some(n) if n.is_dir => return format!("tr: {}: Is a directory", path),
+                Some(n) if n.is_dir => {
+                    return CmdOutput::error(1, format!("tr: {}: Is a directory", path))
+                }
                 Some(n) => n.data.clone(),
-                None => return format!("tr: {}: No such file or directory", path),
+                None => {
+                    return CmdOutput::error(1, format!("tr: {}: No such file or directory", path))
+                }
             }
         } else {
             if idx >= args.len() {
-                return "tr: missing input text (or use -f FILE)".into();
+                return CmdOutput::error(1, "tr: missing input text (or use -f FILE)");
             }
             args[idx..].join(" ")
         };
@@ -1348,12 +1818,14 @@ impl System {
                 out.push(ch);
             }
         }
-        out
+        CmdOutput::ok(out)
     }
 
-    fn cmd_tee(&mut self, args: &[&str]) -> String {
+    fn cmd_tee(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: tee [-a] FILE [TEXT ...] | tee [-a] FILE -f INPUT_FILE".into();
+            return CmdOutput::usage(
+                "usage: tee [-a] FILE [TEXT ...] | tee [-a] FILE -f INPUT_FILE",
+            );
         }
 
         let mut append = false;
@@ -1363,20 +1835,27 @@ impl System {
             idx += 1;
         }
         if idx >= args.len() {
-            return "tee: missing FILE operand".into();
+            return CmdOutput::error(1, "tee: missing FILE operand");
         }
         let out_path = args[idx];
         idx += 1;
 
         let text = if idx < args.len() && args[idx] == "-f" {
             if idx + 1 >= args.len() {
-                return "tee: -f requires INPUT_FILE".into();
+                return CmdOutput::error(1, "tee: -f requires INPUT_FILE");
             }
             let input_path = args[idx + 1];
             match self.kernel.fs.resolve(input_path) {
-                Some(n) if n.is_dir => return format!("tee: {}: Is a directory", input_path),
+                Some(n) if n.is_dir => {
+                    return CmdOutput::error(1, format!("tee: {}: Is a directory", input_path))
+                }
                 Some(n) => n.data.clone(),
-                None => return format!("tee: {}: No such file or directory", input_path),
+                None => {
+                    return CmdOutput::error(
+                        1,
+                        format!("tee: {}: No such file or directory", input_path),
+                    )
+                }
             }
         } else {
             args[idx..].join(" ")
@@ -1384,7 +1863,9 @@ impl System {
 
         let final_data = if append {
             match self.kernel.fs.resolve(out_path) {
-                Some(n) if n.is_dir => return format!("tee: {}: Is a directory", out_path),
+                Some(n) if n.is_dir => {
+                    return CmdOutput::error(1, format!("tee: {}: Is a directory", out_path))
+                }
                 Some(n) => {
                     if n.data.is_empty() {
                         text.clone()
@@ -1407,59 +1888,77 @@ impl System {
         };
 
         match write_result {
-            Ok(()) => text,
-            Err(e) => format!("tee: {}: {}", out_path, e),
+            Ok(()) => CmdOutput::ok(text),
+            Err(e) => CmdOutput::error(1, format!("tee: {}: {}", out_path, e)),
         }
     }
 
-    fn cmd_which(&self, args: &[&str]) -> String {
+    fn cmd_which(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: which [command]".into();
+            return CmdOutput::usage("usage: which [command]");
         }
         let cmd = args[0];
         if let Some(alias) = self.shell.aliases.get(cmd) {
-            return format!("{}: aliased to {}", cmd, alias);
+            return CmdOutput::ok(format!("{}: aliased to {}", cmd, alias));
         }
-        if self.shell.registry.has(cmd) || self.is_builtin(cmd) {
-            format!("/usr/bin/{}", cmd)
+        if let Some(path) = self.package_command_path(cmd) {
+            CmdOutput::ok(path)
+        } else if self.shell.registry.has(cmd) {
+            CmdOutput::ok(format!("/usr/bin/{}", cmd))
         } else {
-            format!("which: no {} in (/usr/bin:/bin:/usr/sbin:/sbin)", cmd)
+            CmdOutput::error(
+                1,
+                format!("which: no {} in (/usr/bin:/bin:/usr/sbin:/sbin)", cmd),
+            )
         }
     }
 
-    fn cmd_whereis(&self, args: &[&str]) -> String {
+    fn cmd_whereis(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: whereis [command]".into();
+            return CmdOutput::usage("usage: whereis [command]");
         }
         let cmd = args[0];
-        if self.shell.registry.has(cmd) || self.is_builtin(cmd) {
-            format!("{}: /usr/bin/{} /usr/share/man/man1/{}.1.gz", cmd, cmd, cmd)
+        if let Some(path) = self.package_command_path(cmd) {
+            CmdOutput::ok(format!(
+                "{}: {} /usr/share/man/man6/{}.6.gz",
+                cmd, path, cmd
+            ))
+        } else if self.shell.registry.has(cmd) {
+            CmdOutput::ok(format!(
+                "{}: /usr/bin/{} /usr/share/man/man1/{}.1.gz",
+                cmd, cmd, cmd
+            ))
         } else {
-            format!("{}: not found", cmd)
+            CmdOutput::error(1, format!("{}: not found", cmd))
         }
     }
 
-    fn cmd_file(&self, args: &[&str]) -> String {
+    fn cmd_file(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: file [file]".into();
+            return CmdOutput::usage("usage: file [file]");
         }
         match self.kernel.fs.resolve(args[0]) {
-            Some(node) if node.is_dir => format!("{}: directory", args[0]),
+            Some(node) if node.is_dir => CmdOutput::ok(format!("{}: directory", args[0])),
             Some(node) if node.is_executable => {
-                format!("{}: ELF 64-bit LSB executable, x86-64", args[0])
+                CmdOutput::ok(format!("{}: ELF 64-bit LSB executable, x86-64", args[0]))
             }
             Some(node) if node.permissions.starts_with('l') => {
-                format!("{}: symbolic link to {}", args[0], node.data)
+                CmdOutput::ok(format!("{}: symbolic link to {}", args[0], node.data))
+            }
+            Some(node) if node.data.starts_with('#') => {
+                CmdOutput::ok(format!("{}: ASCII text", args[0]))
             }
-            Some(node) if node.data.starts_with('#') => format!("{}: ASCII text", args[0]),
-            Some(_) => format!("{}: data", args[0]),
-            None => format!("{}: cannot open (No such file or directory)", args[0]),
+            Some(_) => CmdOutput::ok(format!("{}: data", args[0])),
+            None => CmdOutput::error(
+                1,
+                format!("{}: cannot open (No such file or directory)", args[0]),
+            ),
         }
     }
 
-    fn cmd_ln(&mut self, args: &[&str]) -> String {
+    fn cmd_ln(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: ln [-s] TARGET LINK_NAME".into();
+            return CmdOutput::usage("usage: ln [-s] TARGET LINK_NAME");
         }
 
         let mut symbolic = false;
@@ -1469,14 +1968,17 @@ impl System {
             idx += 1;
         }
         if args.len() - idx < 2 {
-            return "ln: missing file operand\nusage: ln [-s] TARGET LINK_NAME".into();
+            return CmdOutput::usage("ln: missing file operand\nusage: ln [-s] TARGET LINK_NAME");
         }
 
         let target = args[idx];
         let link_name = args[idx + 1];
 
         if self.kernel.fs.resolve(link_name).is_some() {
-            return format!("ln: failed to create link '{}': File exists", link_name);
+            return CmdOutput::error(
+                1,
+                format!("ln: failed to create link '{}': File exists", link_name),
+            );
         }
 
         if symbolic {
@@ -1487,20 +1989,29 @@ impl System {
                         node.size = target.len();
                         node.is_executable = false;
                     }
-                    String::new()
+                    CmdOutput::ok(String::new())
                 }
-                Err(e) => format!("ln: failed to create symbolic link '{}': {}", link_name, e),
+                Err(e) => CmdOutput::error(
+                    1,
+                    format!("ln: failed to create symbolic link '{}': {}", link_name, e),
+                ),
             }
         } else {
             let source = match self.kernel.fs.resolve(target) {
                 Some(n) if n.is_dir => {
-                    return format!("ln: hard link not allowed for directory '{}'", target);
+                    return CmdOutput::error(
+                        1,
+                        format!("ln: hard link not allowed for directory '{}'", target),
+                    );
                 }
                 Some(n) => n.clone(),
                 None => {
-                    return format!(
-                        "ln: failed to access '{}': No such file or directory",
-                        target
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "ln: failed to access '{}': No such file or directory",
+                            target
+                        ),
                     )
                 }
             };
@@ -1514,90 +2025,22 @@ impl System {
                         node.is_executable = source.is_executable;
                         node.size = source.size;
                     }
-                    String::new()
+                    CmdOutput::ok(String::new())
                 }
-                Err(e) => format!("ln: failed to create link '{}': {}", link_name, e),
+                Err(e) => CmdOutput::error(
+                    1,
+                    format!("ln: failed to create link '{}': {}", link_name, e),
+                ),
             }
         }
     }
 
-    fn cmd_date(&self) -> String {
+    fn cmd_date(&self) -> CmdOutput {
         let d = js_sys::Date::new_0();
-        d.to_string().into()
-    }
-
-    fn cmd_cp(&mut self, args: &[&str]) -> String {
-        if args.len() < 2 {
-            return "usage: cp [source] [dest]".into();
-        }
-        let data = match self.kernel.fs.resolve(args[0]) {
-            Some(node) if !node.is_dir => node.data.clone(),
-            Some(_) => return "cp: omitting directory (use -r for recursive)".into(),
-            None => return format!("cp: cannot stat '{}': No such file or directory", args[0]),
-        };
-
-        match self.kernel.fs.create_file(args[1], &data) {
-            Ok(()) => String::new(),
-            Err(e) => format!("cp: cannot create '{}': {}", args[1], e),
-        }
-    }
-
-    fn cmd_mv(&mut self, args: &[&str]) -> String {
-        if args.len() < 2 {
-            return "usage: mv [source] [dest]".into();
-        }
-        match self.kernel.fs.resolve(args[0]) {
-            Some(node) if !node.is_dir => {
-                let data = node.data.clone();
-                match self.kernel.fs.create_file(args[1], &data) {
-                    Ok(()) => {
-                        let _ = self.kernel.fs.remove(args[0]);
-                        String::new()
-                    }
-                    Err(e) => format!("mv: cannot move to '{}': {}", args[1], e),
-                }
-            }
-            Some(_) => "mv: cannot move directory".into(),
-            None => format!("mv: cannot stat '{}': No such file or directory", args[0]),
-        }
-    }
-
-    fn cmd_df(&self, _args: &[&str]) -> String {
-        let (used, total) = self.kernel.mem.usage();
-        format!(
-            "Filesystem     1K-blocks    Used Available Use% Mounted on\n\
-             /dev/sda1      {}  {}   {}  {}% /",
-            total / 1024,
-            used / 1024,
-            (total - used) / 1024,
-            (used * 100) / total
-        )
-    }
-
-    fn cmd_du(&self, args: &[&str]) -> String {
-        let path = if args.is_empty() { "." } else { args[0] };
-        match self.kernel.fs.resolve(path) {
-            Some(node) if node.is_dir => {
-                let size = Self::calc_dir_size(node);
-                format!("{}\t{}", size / 1024, path)
-            }
-            Some(node) => format!("{}\t{}", node.size / 1024, path),
-            None => format!("du: cannot access '{}': No such file or directory", path),
-        }
-    }
-
-    fn calc_dir_size(node: &crate::vfs::Inode) -> usize {
-        let mut total = 4096; // directory itself
-        for child in node.children.values() {
-            if child.is_dir {
-                total += Self::calc_dir_size(child);
-            } else {
-                total += child.size;
-            }
-        }
-        total
+        CmdOutput::ok(String::from(d.to_string()))
     }
 
+    /// The contents of `path` as the current user may read them
     fn read_file_bytes(&self, path: &str) -> Result<Vec<u8>, String> {
         let node = self
             .kernel
@@ -1607,6 +2050,9 @@ impl System {
         if node.is_dir {
             return Err(format!("{}: Is a directory", path));
         }
+        if !self.has_access(path, 4) {
+            return Err(format!("{}: Permission denied", path));
+        }
         if let Some(encoded) = node.data.strip_prefix(BINARY_PREFIX) {
             return B64
                 .decode(encoded)
@@ -1616,10 +2062,7 @@ impl System {
     }
 
     fn write_file_bytes(&mut self, path: &str, bytes: &[u8]) -> Result<(), String> {
-        let content = match String::from_utf8(bytes.to_vec()) {
-            Ok(text) => text,
-            Err(_) => format!("{}{}", BINARY_PREFIX, B64.encode(bytes)),
-        };
+        let content = bytes_to_data(bytes.to_vec());
 
         if self.kernel.fs.resolve(path).is_some() {
             self.kernel
@@ -1678,9 +2121,9 @@ impl System {
         }
     }
 
-    fn cmd_tar(&mut self, args: &[&str]) -> String {
+    fn cmd_tar(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: tar -cf ARCHIVE.tar PATH... | tar -tf ARCHIVE.tar | tar -xf ARCHIVE.tar [-C DIR]".into();
+            return CmdOutput::usage("usage: tar -cf ARCHIVE.tar PATH... | tar -tf ARCHIVE.tar | tar -xf ARCHIVE.tar [-C DIR]");
         }
 
         let mut mode = "";
@@ -1694,7 +2137,7 @@ impl System {
                 "-cf" => {
                     mode = "create";
                     if i + 1 >= args.len() {
-                        return "tar: option '-cf' requires ARCHIVE argument".into();
+                        return CmdOutput::error(1, "tar: option '-cf' requires ARCHIVE argument");
                     }
                     archive = args[i + 1];
                     i += 2;
@@ -1702,7 +2145,7 @@ impl System {
                 "-tf" => {
                     mode = "list";
                     if i + 1 >= args.len() {
-                        return "tar: option '-tf' requires ARCHIVE argument".into();
+                        return CmdOutput::error(1, "tar: option '-tf' requires ARCHIVE argument");
                     }
                     archive = args[i + 1];
                     i += 2;
@@ -1710,20 +2153,20 @@ impl System {
                 "-xf" => {
                     mode = "extract";
                     if i + 1 >= args.len() {
-                        return "tar: option '-xf' requires ARCHIVE argument".into();
+                        return CmdOutput::error(1, "tar: option '-xf' requires ARCHIVE argument");
                     }
                     archive = args[i + 1];
                     i += 2;
                 }
                 "-C" => {
                     if i + 1 >= args.len() {
-                        return "tar: option '-C' requires DIR argument".into();
+                        return CmdOutput::error(1, "tar: option '-C' requires DIR argument");
                     }
                     dest_dir = args[i + 1];
                     i += 2;
                 }
                 value if value.starts_with('-') => {
-                    return format!("tar: unsupported option '{}'", value);
+                    return CmdOutput::error(1, format!("tar: unsupported option '{}'", value));
                 }
                 value => {
                     paths.push(value);
@@ -1733,18 +2176,24 @@ impl System {
         }
 
         if archive.is_empty() || mode.is_empty() {
-            return "tar: missing operation mode (-cf/-tf/-xf) or archive path".into();
+            return CmdOutput::error(
+                1,
+                "tar: missing operation mode (-cf/-tf/-xf) or archive path",
+            );
         }
 
         if mode == "create" {
             if paths.is_empty() {
-                return "tar: Cowardly refusing to create an empty archive".into();
+                return CmdOutput::error(1, "tar: Cowardly refusing to create an empty archive");
             }
 
             let mut entries = Vec::new();
             for input in paths {
                 if self.kernel.fs.resolve(input).is_none() {
-                    return format!("tar: {}: Cannot stat: No such file or directory", input);
+                    return CmdOutput::error(
+                        1,
+                        format!("tar: {}: Cannot stat: No such file or directory", input),
+                    );
                 }
                 self.collect_tree_paths(input, &mut entries);
             }
@@ -1756,7 +2205,7 @@ impl System {
                 } else {
                     let data = match self.read_file_bytes(&path) {
                         Ok(bytes) => bytes,
-                        Err(e) => return format!("tar: {}", e),
+                        Err(e) => return CmdOutput::error(1, format!("tar: {}", e)),
                     };
                     lines.push(format!(
                         "F\t{}\t{}",
@@ -1773,25 +2222,28 @@ impl System {
                 self.kernel.fs.create_file(archive, &payload)
             };
             return match res {
-                Ok(()) => format!("tar: created {}", archive),
-                Err(e) => format!("tar: {}", e),
+                Ok(()) => CmdOutput::ok(format!("tar: created {}", archive)),
+                Err(e) => CmdOutput::error(1, format!("tar: {}", e)),
             };
         }
 
         let Some(node) = self.kernel.fs.resolve(archive) else {
-            return format!("tar: {}: Cannot open: No such file or directory", archive);
+            return CmdOutput::error(
+                1,
+                format!("tar: {}: Cannot open: No such file or directory", archive),
+            );
         };
         if node.is_dir {
-            return format!("tar: {}: Is a directory", archive);
+            return CmdOutput::error(1, format!("tar: {}: Is a directory", archive));
         }
         let archive_data = node.data.clone();
 
         let mut lines = archive_data.lines();
         let Some(magic) = lines.next() else {
-            return format!("tar: {}: Empty archive", archive);
+            return CmdOutput::error(1, format!("tar: {}: Empty archive", archive));
         };
         if magic != "KP_TAR1" {
-            return format!("tar: {}: Unrecognized archive format", archive);
+            return CmdOutput::error(1, format!("tar: {}: Unrecognized archive format", archive));
         }
 
         if mode == "list" {
@@ -1805,13 +2257,16 @@ impl System {
                     }
                 }
             }
-            return out.join("\n");
+            return CmdOutput::ok(out.join("\n"));
         }
 
         if let Err(e) = self.ensure_dir_all(dest_dir) {
-            return format!(
-                "tar: cannot create extraction directory '{}': {}",
-                dest_dir, e
+            return CmdOutput::error(
+                1,
+                format!(
+                    "tar: cannot create extraction directory '{}': {}",
+                    dest_dir, e
+                ),
             );
         }
 
@@ -1822,40 +2277,40 @@ impl System {
                 let rel = path.trim_start_matches('/');
                 let out_path = Self::join_virtual_path(&base, rel);
                 if let Err(e) = self.ensure_dir_all(&out_path) {
-                    return format!("tar: {}", e);
+                    return CmdOutput::error(1, format!("tar: {}", e));
                 }
                 extracted.push(out_path);
             } else if let Some(rest) = line.strip_prefix("F\t") {
                 let Some((path, b64)) = rest.split_once('\t') else {
-                    return "tar: malformed file entry".into();
+                    return CmdOutput::error(1, "tar: malformed file entry");
                 };
                 let rel = path.trim_start_matches('/');
                 let out_path = Self::join_virtual_path(&base, rel);
                 if let Some((parent, _)) = out_path.rsplit_once('/') {
                     let pd = if parent.is_empty() { "/" } else { parent };
                     if let Err(e) = self.ensure_dir_all(pd) {
-                        return format!("tar: {}", e);
+                        return CmdOutput::error(1, format!("tar: {}", e));
                     }
                 }
                 let data = match crate::cpp_accel::b64_decode(b64) {
                     Ok(v) => v,
-                    Err(_) => return "tar: malformed base64 payload".into(),
+                    Err(_) => return CmdOutput::error(1, "tar: malformed base64 payload"),
                 };
                 if let Err(e) = self.write_file_bytes(&out_path, &data) {
-                    return format!("tar: {}", e);
+                    return CmdOutput::error(1, format!("tar: {}", e));
                 }
                 extracted.push(out_path);
             }
         }
 
         if extracted.is_empty() {
-            format!("tar: extracted no entries from {}", archive)
+            CmdOutput::error(1, format!("tar: extracted no entries from {}", archive))
         } else {
-            extracted.join("\n")
+            CmdOutput::ok(extracted.join("\n"))
         }
     }
 
-    fn cmd_gzip(&mut self, args: &[&str], cmd: &str) -> String {
+    fn cmd_gzip(&mut self, args: &[&str], cmd: &str) -> CmdOutput {
         let mut keep_input = false;
         let mut files: Vec<&str> = Vec::new();
 
@@ -1863,7 +2318,7 @@ impl System {
             match *arg {
                 "-k" | "--keep" => keep_input = true,
                 other if other.starts_with('-') => {
-                    return format!("{}: unsupported option '{}'", cmd, other);
+                    return CmdOutput::error(1, format!("{}: unsupported option '{}'", cmd, other));
                 }
                 other => files.push(other),
             }
@@ -1871,9 +2326,9 @@ impl System {
 
         if files.is_empty() {
             return if cmd == "gzip" {
-                "usage: gzip [-k] FILE...".into()
+                CmdOutput::usage("usage: gzip [-k] FILE...")
             } else {
-                "usage: gunzip [-k] FILE...".into()
+                CmdOutput::usage("usage: gunzip [-k] FILE...")
             };
         }
 
@@ -1883,19 +2338,21 @@ impl System {
             if cmd == "gzip" {
                 let input = match self.read_file_bytes(path) {
                     Ok(data) => data,
-                    Err(e) => return format!("gzip: {}", e),
+                    Err(e) => return CmdOutput::error(1, format!("gzip: {}", e)),
                 };
                 let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
                 if encoder.write_all(&input).is_err() {
-                    return format!("gzip: {}: write failure", path);
+                    return CmdOutput::error(1, format!("gzip: {}: write failure", path));
                 }
                 let compressed = match encoder.finish() {
                     Ok(data) => data,
-                    Err(_) => return format!("gzip: {}: compression failure", path),
+                    Err(_) => {
+                        return CmdOutput::error(1, format!("gzip: {}: compression failure", path))
+                    }
                 };
                 let out_path = format!("{}.gz", path);
                 if let Err(e) = self.write_file_bytes(&out_path, &compressed) {
-                    return format!("gzip: {}: {}", out_path, e);
+                    return CmdOutput::error(1, format!("gzip: {}: {}", out_path, e));
                 }
                 if !keep_input {
                     let _ = self.kernel.fs.remove(path);
@@ -1904,12 +2361,12 @@ impl System {
             } else {
                 let input = match self.read_file_bytes(path) {
                     Ok(data) => data,
-                    Err(e) => return format!("gunzip: {}", e),
+                    Err(e) => return CmdOutput::error(1, format!("gunzip: {}", e)),
                 };
                 let mut decoder = GzDecoder::new(Cursor::new(input));
                 let mut decompressed = Vec::new();
                 if decoder.read_to_end(&mut decompressed).is_err() {
-                    return format!("gunzip: {}: invalid gzip stream", path);
+                    return CmdOutput::error(1, format!("gunzip: {}: invalid gzip stream", path));
                 }
                 let out_path = if path.ends_with(".gz") {
                     path.trim_end_matches(".gz").to_string()
@@ -1917,7 +2374,7 @@ impl System {
                     format!("{}.out", path)
                 };
                 if let Err(e) = self.write_file_bytes(&out_path, &decompressed) {
-                    return format!("gunzip: {}: {}", out_path, e);
+                    return CmdOutput::error(1, format!("gunzip: {}: {}", out_path, e));
                 }
                 if !keep_input {
                     let _ = self.kernel.fs.remove(path);
@@ -1926,10 +2383,67 @@ impl System {
             }
         }
 
-        out_lines.join("\n")
+        CmdOutput::ok(out_lines.join("\n"))
+    }
+
+    /// Zip `sources` (normalized paths) with entry names relative to
+    /// `base`; directories need `recursive`
+    fn zip_paths(
+        &self,
+        sources: &[String],
+        recursive: bool,
+        base: &str,
+    ) -> Result<Vec<u8>, String> {
+        let mut sink = Cursor::new(Vec::<u8>::new());
+        let mut writer = ZipWriter::new(&mut sink);
+        let opts = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(0o644);
+        let entry_name = |path: &str| {
+            path.strip_prefix(base)
+                .unwrap_or(path)
+                .trim_start_matches('/')
+                .to_string()
+        };
+
+        let mut queue = sources.to_vec();
+        while let Some(path) = queue.pop() {
+            let node = self
+                .kernel
+                .fs
+                .resolve(&path)
+                .ok_or_else(|| format!("name not matched: {}", path))?;
+            if node.is_dir {
+                if !recursive {
+                    return Err(format!("{} is a directory (try -r)", path));
+                }
+                let name = entry_name(&path);
+                if !name.is_empty()
+                    && writer
+                        .add_directory(format!("{}/", name.trim_end_matches('/')), opts)
+                        .is_err()
+                {
+                    return Err(format!("failed to add directory {}", path));
+                }
+                for child in node.children.keys() {
+                    queue.push(Self::join_virtual_path(&path, child));
+                }
+            } else {
+                writer
+                    .start_file(entry_name(&path), opts)
+                    .map_err(|_| format!("failed to add {}", path))?;
+                let data = self.read_file_bytes(&path)?;
+                writer
+                    .write_all(&data)
+                    .map_err(|_| format!("failed writing {}", path))?;
+            }
+        }
+
+        writer.finish().map_err(|_| "finalize failed".to_string())?;
+        Ok(sink.into_inner())
     }
 
-    fn cmd_zip(&mut self, args: &[&str], cmd: &str) -> String {
+    fn cmd_zip(&mut self, args: &[&str], cmd: &str) -> CmdOutput {
         if cmd == "zip" {
             let mut recursive = false;
             let mut archive_path = None;
@@ -1939,7 +2453,7 @@ impl System {
                 match *arg {
                     "-r" => recursive = true,
                     other if other.starts_with('-') => {
-                        return format!("zip: unsupported option '{}'", other)
+                        return CmdOutput::error(1, format!("zip: unsupported option '{}'", other))
                     }
                     other => {
                         if archive_path.is_none() {
@@ -1953,72 +2467,27 @@ impl System {
 
             let archive_path = match archive_path {
                 Some(v) => v,
-                None => return "usage: zip [-r] ARCHIVE.zip FILE...".into(),
+                None => return CmdOutput::usage("usage: zip [-r] ARCHIVE.zip FILE..."),
             };
             if sources.is_empty() {
-                return "zip: nothing to do".into();
-            }
-
-            let mut sink = Cursor::new(Vec::<u8>::new());
-            {
-                let mut writer = ZipWriter::new(&mut sink);
-                let opts = SimpleFileOptions::default()
-                    .compression_method(CompressionMethod::Deflated)
-                    .unix_permissions(0o644);
-
-                let mut queue: Vec<String> = sources
-                    .iter()
-                    .map(|s| self.kernel.fs.normalize(s))
-                    .collect();
-                while let Some(path) = queue.pop() {
-                    let node = match self.kernel.fs.resolve(&path) {
-                        Some(n) => n,
-                        None => return format!("zip: name not matched: {}", path),
-                    };
-                    if node.is_dir {
-                        if !recursive {
-                            return format!("zip: {} is a directory (try -r)", path);
-                        }
-                        let entry_name = path
-                            .trim_start_matches('/')
-                            .trim_end_matches('/')
-                            .to_string()
-                            + "/";
-                        if !entry_name.is_empty() && writer.add_directory(entry_name, opts).is_err()
-                        {
-                            return format!("zip: failed to add directory {}", path);
-                        }
-                        for child in node.children.keys() {
-                            queue.push(Self::join_virtual_path(&path, child));
-                        }
-                    } else {
-                        let entry_name = path.trim_start_matches('/');
-                        if writer.start_file(entry_name, opts).is_err() {
-                            return format!("zip: failed to add {}", path);
-                        }
-                        let data = match self.read_file_bytes(&path) {
-                            Ok(d) => d,
-                            Err(e) => return format!("zip: {}", e),
-                        };
-                        if writer.write_all(&data).is_err() {
-                            return format!("zip: failed writing {}", path);
-                        }
-                    }
-                }
-
-                if writer.finish().is_err() {
-                    return "zip: finalize failed".into();
-                }
+                return CmdOutput::error(1, "zip: nothing to do");
             }
 
-            let bytes = sink.into_inner();
+            let sources: Vec<String> = sources
+                .iter()
+                .map(|s| self.kernel.fs.normalize(s))
+                .collect();
+            let bytes = match self.zip_paths(&sources, recursive, "/") {
+                Ok(bytes) => bytes,
+                Err(e) => return CmdOutput::error(1, format!("zip: {}", e)),
+            };
             match self.write_file_bytes(archive_path, &bytes) {
-                Ok(()) => format!("created {}", archive_path),
-                Err(e) => format!("zip: {}", e),
+                Ok(()) => CmdOutput::ok(format!("created {}", archive_path)),
+                Err(e) => CmdOutput::error(1, format!("zip: {}", e)),
             }
         } else {
             if args.is_empty() {
-                return "usage: unzip ARCHIVE.zip [-d DIR]".into();
+                return CmdOutput::usage("usage: unzip ARCHIVE.zip [-d DIR]");
             }
 
             let mut archive_path = None;
@@ -2028,13 +2497,19 @@ impl System {
                 match args[i] {
                     "-d" => {
                         if i + 1 >= args.len() {
-                            return "unzip: option requires an argument -- 'd'".into();
+                            return CmdOutput::error(
+                                1,
+                                "unzip: option requires an argument -- 'd'",
+                            );
                         }
                         out_dir = args[i + 1].to_string();
                         i += 2;
                     }
                     value if value.starts_with('-') => {
-                        return format!("unzip: unsupported option '{}'", value)
+                        return CmdOutput::error(
+                            1,
+                            format!("unzip: unsupported option '{}'", value),
+                        )
                     }
                     value => {
                         archive_path = Some(value.to_string());
@@ -2045,25 +2520,30 @@ impl System {
 
             let archive_path = match archive_path {
                 Some(v) => v,
-                None => return "usage: unzip ARCHIVE.zip [-d DIR]".into(),
+                None => return CmdOutput::usage("usage: unzip ARCHIVE.zip [-d DIR]"),
             };
 
             let archive_bytes = match self.read_file_bytes(&archive_path) {
                 Ok(d) => d,
-                Err(e) => return format!("unzip: {}", e),
+                Err(e) => return CmdOutput::error(1, format!("unzip: {}", e)),
             };
 
             let cursor = Cursor::new(archive_bytes);
             let mut archive = match ZipArchive::new(cursor) {
                 Ok(a) => a,
-                Err(_) => return format!("unzip: {}: invalid zip archive", archive_path),
+                Err(_) => {
+                    return CmdOutput::error(
+                        1,
+                        format!("unzip: {}: invalid zip archive", archive_path),
+                    )
+                }
             };
 
             let mut created = Vec::new();
             for idx in 0..archive.len() {
                 let mut entry = match archive.by_index(idx) {
                     Ok(e) => e,
-                    Err(_) => return "unzip: failed reading archive entry".into(),
+                    Err(_) => return CmdOutput::error(1, "unzip: failed reading archive entry"),
                 };
                 let name = entry.name().to_string();
                 if name.starts_with('/') || name.contains("..") {
@@ -2072,7 +2552,7 @@ impl System {
                 let dest = Self::join_virtual_path(&self.kernel.fs.normalize(&out_dir), &name);
                 if entry.is_dir() {
                     if let Err(e) = self.ensure_dir_all(&dest) {
-                        return format!("unzip: {}", e);
+                        return CmdOutput::error(1, format!("unzip: {}", e));
                     }
                     continue;
                 }
@@ -2080,273 +2560,79 @@ impl System {
                 if let Some((parent, _)) = dest.rsplit_once('/') {
                     let parent_dir = if parent.is_empty() { "/" } else { parent };
                     if let Err(e) = self.ensure_dir_all(parent_dir) {
-                        return format!("unzip: {}", e);
+                        return CmdOutput::error(1, format!("unzip: {}", e));
                     }
                 }
 
                 let mut data = Vec::new();
                 if entry.read_to_end(&mut data).is_err() {
-                    return format!("unzip: failed extracting {}", name);
+                    return CmdOutput::error(1, format!("unzip: failed extracting {}", name));
                 }
                 if let Err(e) = self.write_file_bytes(&dest, &data) {
-                    return format!("unzip: {}", e);
+                    return CmdOutput::error(1, format!("unzip: {}", e));
                 }
                 created.push(dest);
             }
 
             if created.is_empty() {
-                "Archive processed: no entries extracted".into()
+                CmdOutput::ok("Archive processed: no entries extracted")
             } else {
-                created.join("\n")
+                CmdOutput::ok(created.join("\n"))
             }
         }
     }
 
-    fn apt_catalog() -> [(&'static str, &'static str, u32, &'static str); 10] {
-        [
-            (
-                "nano",
-                "7.2-1",
-                1632,
-                "small, friendly text editor inspired by Pico",
-            ),
-            ("vim", "9.0-3", 8420, "Vi IMproved - enhanced vi editor"),
-            ("htop", "3.3.0-1", 512, "interactive process viewer"),
-            ("cmatrix", "2.0-4", 296, "simulates the Matrix digital rain"),
-            (
-                "curl",
-                "8.7.1-1",
-                2304,
-                "command line tool for transferring data with URL syntax",
-            ),
-            ("wget", "1.21.4-2", 1480, "retrieves files from the web"),
-            (
-                "git",
-                "2.46.0-1",
-                12640,
-                "fast, scalable, distributed revision control system",
-            ),
-            (
-                "openssh-server",
-                "9.8p1-1",
-                2630,
-                "secure shell (SSH) server",
-            ),
-            ("net-tools", "2.10-1", 720, "NET-3 networking toolkit"),
-            (
-                "python3",
-                "3.12.2-1",
-                11240,
-                "interactive high-level object-oriented language",
-            ),
-        ]
+    fn format_uptime_hms(total_seconds: u64) -> String {
+        let h = total_seconds / 3600;
+        let m = (total_seconds % 3600) / 60;
+        let s = total_seconds % 60;
+        format!("{:02}:{:02}:{:02}", h, m, s)
     }
 
-    fn apt_read_installed(&self) -> BTreeMap<String, String> {
-        let mut installed = BTreeMap::new();
-        if let Some(node) = self.kernel.fs.resolve("/var/lib/apt/installed.db") {
-            for line in node.data.lines() {
-                if let Some((name, ver)) = line.split_once(' ') {
-                    if !name.trim().is_empty() && !ver.trim().is_empty() {
-                        installed.insert(name.trim().to_string(), ver.trim().to_string());
-                    }
-                }
-            }
+    fn proc_state_char(state: ProcState) -> char {
+        match state {
+            ProcState::Run => 'R',
+            ProcState::Sleep => 'S',
+            ProcState::Stop => 'T',
+            ProcState::Zombie => 'Z',
         }
-        installed
     }
 
-    fn apt_write_installed(&mut self, installed: &BTreeMap<String, String>) -> Result<(), String> {
-        self.ensure_dir_all("/var/lib/apt")?;
-        let data = installed
-            .iter()
-            .map(|(name, ver)| format!("{} {}", name, ver))
-            .collect::<Vec<_>>()
-            .join("\n");
+    /// The process's share of the last scheduler tick, weighted by nice
+    fn proc_cpu(p: &Process) -> f64 {
+        p.cpu_percent.min(99.9)
+    }
+
+    /// Scheduler CPU time as `M:SS.hh`, the way top prints TIME+
+    fn format_cpu_time(us: u64) -> String {
+        let hundredths = us / 10_000;
+        format!(
+            "{}:{:02}.{:02}",
+            hundredths / 6000,
+            hundredths / 100 % 60,
+            hundredths % 100
+        )
+    }
 
-        if self
+    /// Load averages over 1, 5 and 15 minutes, from how many processes are
+    /// runnable now
+    fn load_average(&self) -> (f64, f64, f64) {
+        let running = self
             .kernel
-            .fs
-            .resolve("/var/lib/apt/installed.db")
-            .is_some()
-        {
-            self.kernel
-                .fs
-                .write_file("/var/lib/apt/installed.db", &data)
-                .map_err(|e| e.to_string())
-        } else {
-            self.kernel
-                .fs
-                .create_file("/var/lib/apt/installed.db", &data)
-                .map_err(|e| e.to_string())
-        }
+            .proc
+            .list()
+            .iter()
+            .filter(|p| p.state == ProcState::Run)
+            .count()
+            .max(1) as f64;
+        (
+            (running * 0.56).min(9.99),
+            (running * 0.34).min(9.99),
+            (running * 0.21).min(9.99),
+        )
     }
 
-    fn cmd_apt(&mut self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "usage: apt [install|remove|update|upgrade|search] [package]".into();
-        }
-
-        let catalog = Self::apt_catalog();
-        let mut installed = self.apt_read_installed();
-
-        match args[0] {
-            "update" => {
-                if let Err(e) = self.ensure_dir_all("/var/lib/apt/lists") {
-                    return format!("E: failed to prepare package lists: {}", e);
-                }
-                let stamp = format!("{}", js_sys::Date::new_0().to_string());
-                let _ = self
-                    .kernel
-                    .fs
-                    .write_file("/var/lib/apt/lists/last_update", &stamp)
-                    .or_else(|_| {
-                        self.kernel
-                            .fs
-                            .create_file("/var/lib/apt/lists/last_update", &stamp)
-                    });
-                "Hit:1 https://archive.kpawnd.local stable InRelease\nReading package lists... Done\nBuilding dependency tree... Done\nAll packages are up to date.".into()
-            }
-            "upgrade" => {
-                let mut upgraded = 0usize;
-                for (name, _installed_ver) in installed.clone() {
-                    if let Some((_, latest_ver, _, _)) =
-                        catalog.iter().find(|(pkg, _, _, _)| *pkg == name)
-                    {
-                        if installed.get(&name) != Some(&latest_ver.to_string()) {
-                            installed.insert(name.clone(), (*latest_ver).to_string());
-                            upgraded += 1;
-                        }
-                    }
-                }
-                if upgraded > 0 {
-                    if let Err(e) = self.apt_write_installed(&installed) {
-                        return format!("E: failed to write package database: {}", e);
-                    }
-                }
-                format!(
-                    "Reading package lists... Done\nBuilding dependency tree... Done\n{} upgraded, 0 newly installed, 0 to remove and 0 not upgraded.",
-                    upgraded
-                )
-            }
-            "install" => {
-                if args.len() < 2 {
-                    return "usage: apt install [package]".into();
-                }
-                let package = args[1];
-                let Some((_, version, size_kb, _desc)) =
-                    catalog.iter().find(|(name, _, _, _)| *name == package)
-                else {
-                    return format!("E: Unable to locate package {}", package);
-                };
-
-                if let Some(installed_ver) = installed.get(package) {
-                    return format!(
-                        "Reading package lists... Done\nBuilding dependency tree... Done\n{} is already the newest version ({}).\n0 upgraded, 0 newly installed, 0 to remove.",
-                        package, installed_ver
-                    );
-                }
-
-                installed.insert(package.to_string(), (*version).to_string());
-                if let Err(e) = self.apt_write_installed(&installed) {
-                    return format!("E: failed to write package database: {}", e);
-                }
-
-                format!(
-                    "Reading package lists... Done\nBuilding dependency tree... Done\nThe following NEW packages will be installed:\n  {}\n0 upgraded, 1 newly installed, 0 to remove.\nNeed to get {} kB of archives.\nAfter this operation, {} kB of additional disk space will be used.\nGet:1 https://archive.kpawnd.local stable/main amd64 {} {} [{} kB]\nFetched {} kB in 1s\nSelecting previously unselected package {}.\nPreparing to unpack .../{}_{}_amd64.deb ...\nUnpacking {} ({}) ...\nSetting up {} ({}) ...",
-                    package,
-                    size_kb,
-                    size_kb * 3,
-                    package,
-                    version,
-                    size_kb,
-                    size_kb,
-                    package,
-                    package,
-                    version,
-                    package,
-                    version,
-                    package,
-                    version
-                )
-            }
-            "remove" => {
-                if args.len() < 2 {
-                    return "usage: apt remove [package]".into();
-                }
-                let package = args[1];
-                let Some(installed_ver) = installed.remove(package) else {
-                    return format!(
-                        "Reading package lists... Done\nBuilding dependency tree... Done\nPackage '{}' is not installed, so not removed.",
-                        package
-                    );
-                };
-                if let Err(e) = self.apt_write_installed(&installed) {
-                    return format!("E: failed to write package database: {}", e);
-                }
-                format!(
-                    "Reading package lists... Done\nBuilding dependency tree... Done\nThe following packages will be REMOVED:\n  {}\n0 upgraded, 0 newly installed, 1 to remove.\nAfter this operation, 0 kB disk space will be freed.\nRemoving {} ({}) ...",
-                    package, package, installed_ver
-                )
-            }
-            "search" => {
-                if args.len() < 2 {
-                    return "usage: apt search [query]".into();
-                }
-                let query = args[1].to_lowercase();
-                let mut matches = Vec::new();
-                for (name, ver, _size, desc) in &catalog {
-                    if name.contains(&query) || desc.to_lowercase().contains(&query) {
-                        matches.push(format!("{}/stable {} amd64\n  {}", name, ver, desc));
-                    }
-                }
-                if matches.is_empty() {
-                    return format!(
-                        "Sorting... Done\nFull Text Search... Done\nNo packages found matching {}",
-                        args[1]
-                    );
-                }
-                format!(
-                    "Sorting... Done\nFull Text Search... Done\n{}",
-                    matches.join("\n\n")
-                )
-            }
-            _ => format!("E: Invalid operation {}", args[0]),
-        }
-    }
-
-    fn format_uptime_hms(total_seconds: u64) -> String {
-        let h = total_seconds / 3600;
-        let m = (total_seconds % 3600) / 60;
-        let s = total_seconds % 60;
-        format!("{:02}:{:02}:{:02}", h, m, s)
-    }
-
-    fn proc_state_char(state: ProcState) -> char {
-        match state {
-            ProcState::Run => 'R',
-            ProcState::Sleep => 'S',
-            ProcState::Stop => 'T',
-            ProcState::Zombie => 'Z',
-        }
-    }
-
-    fn synthetic_proc_cpu(&self, p: &Process) -> f64 {
-        let base = match p.priority {
-            Priority::High => 4.0,
-            Priority::Normal => 1.8,
-            Priority::Low => 0.7,
-        };
-        let running_bonus = if self.kernel.scheduler.current() == Some(p.pid) {
-            6.0
-        } else {
-            0.0
-        };
-        let jitter = ((self.kernel.ticks / 97 + p.pid as u64) % 11) as f64 * 0.13;
-        (base + running_bonus + jitter).min(99.9)
-    }
-
-    fn cmd_top(&self, _args: &[&str]) -> String {
+    fn cmd_top(&self, _args: &[&str]) -> CmdOutput {
         let total_mem = self.kernel.mem.total;
         let free_mem = self.kernel.mem.free;
         let used_mem = total_mem - free_mem;
@@ -2371,11 +2657,7 @@ impl System {
             .count();
 
         let avg_cpu = if tasks_total > 0 {
-            proc_list
-                .iter()
-                .map(|p| self.synthetic_proc_cpu(p))
-                .sum::<f64>()
-                / tasks_total as f64
+            proc_list.iter().map(|p| Self::proc_cpu(p)).sum::<f64>() / tasks_total as f64
         } else {
             0.0
         };
@@ -2384,18 +2666,19 @@ impl System {
         let idle_cpu = (100.0 - user_cpu - sys_cpu).max(0.0);
 
         let uptime = Self::format_uptime_hms(self.kernel.uptime_ms() / 1000);
-        let load1 = (tasks_running as f64 * 0.56).min(9.99);
-        let load5 = (tasks_running as f64 * 0.34).min(9.99);
-        let load15 = (tasks_running as f64 * 0.21).min(9.99);
+        let (load1, load5, load15) = self.load_average();
+        let users = self.user_count();
 
         let mut out = format!(
-            "top - {} up {}, 1 user, load average: {:.2}, {:.2}, {:.2}\n\
+            "top - {} up {}, {} user{}, load average: {:.2}, {:.2}, {:.2}\n\
 Tasks: {} total, {} running, {} sleeping, {} stopped, {} zombie\n\
 %Cpu(s): {:>4.1} us, {:>4.1} sy,  0.0 ni, {:>4.1} id,  0.0 wa,  0.0 hi,  0.0 si,  0.0 st\n\
 MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
  PID USER      PR  NI    VIRT    RES    SHR S  %CPU  %MEM     TIME+ COMMAND\n",
             js_sys::Date::new_0().to_locale_time_string("en-GB"),
             uptime,
+            users,
+            if users == 1 { "" } else { "s" },
             load1,
             load5,
             load15,
@@ -2416,198 +2699,68 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         let mut rows: Vec<_> = proc_list
             .iter()
             .map(|p| {
-                let cpu = self.synthetic_proc_cpu(p);
+                let cpu = Self::proc_cpu(p);
                 let mem_pct = if total_mem > 0 {
                     (p.memory_size as f64 / total_mem as f64) * 100.0
                 } else {
                     0.0
                 };
-                (
-                    p.pid,
-                    p.ppid,
-                    p.name.clone(),
-                    p.state,
-                    p.priority,
-                    p.memory_size,
-                    cpu,
-                    mem_pct,
-                )
-            })
-            .collect();
-        rows.sort_by(|a, b| b.6.partial_cmp(&a.6).unwrap_or(std::cmp::Ordering::Equal));
-
-        for (pid, _ppid, name, state, prio, mem_size, cpu, mem_pct) in rows.into_iter().take(14) {
-            let pr = match prio {
-                Priority::High => 15,
-                Priority::Normal => 20,
-                Priority::Low => 25,
-            };
-            out.push_str(&format!(
-                "{:>4} {:<8} {:>2}  {:>2} {:>7} {:>6} {:>6} {} {:>5.1} {:>5.1} {:>8} {}\n",
-                pid,
-                "user",
-                pr,
-                0,
-                format!("{}K", mem_size / 1024 * 4),
-                format!("{}K", mem_size / 1024),
-                format!("{}K", mem_size / 4096),
-                Self::proc_state_char(state),
-                cpu,
-                mem_pct,
-                format!("00:{:02}.{:02}", (pid * 3) % 60, (pid * 7) % 100),
-                name
-            ));
-        }
-        out
-    }
-
-    fn cmd_htop(&self, _args: &[&str]) -> String {
-        let total_mem = self.kernel.mem.total;
-        let free_mem = self.kernel.mem.free;
-        let used_mem = total_mem - free_mem;
-        let proc_list = self.kernel.proc.list();
-        let task_total = proc_list.len();
-        let task_running = proc_list
-            .iter()
-            .filter(|p| p.state == ProcState::Run)
-            .count()
-            .max(1);
-        let task_sleep = proc_list
-            .iter()
-            .filter(|p| p.state == ProcState::Sleep)
-            .count();
-        let task_stop = proc_list
-            .iter()
-            .filter(|p| p.state == ProcState::Stop)
-            .count();
-        let uptime_s = self.kernel.uptime_ms() / 1000;
-
-        let mem_pct = if total_mem > 0 {
-            (used_mem as f64 / total_mem as f64) * 100.0
-        } else {
-            0.0
-        };
-
-        let cpu_avg = if task_total > 0 {
-            proc_list
-                .iter()
-                .map(|p| self.synthetic_proc_cpu(p))
-                .sum::<f64>()
-                / task_total as f64
-        } else {
-            0.0
-        };
-
-        let bar = |pct: f64| {
-            let width = 24usize;
-            let fill = ((pct / 100.0) * width as f64).round() as usize;
-            format!(
-                "{}{}",
-                "|".repeat(fill.min(width)),
-                " ".repeat(width.saturating_sub(fill.min(width)))
-            )
-        };
-
-        let mut out = String::new();
-        out.push_str(&format!(
-            "htop - kpawnd Linux  |  uptime {}  |  load average {:.2} {:.2} {:.2}\n",
-            Self::format_uptime_hms(uptime_s),
-            (task_running as f64 * 0.55).min(9.99),
-            (task_running as f64 * 0.35).min(9.99),
-            (task_running as f64 * 0.24).min(9.99)
-        ));
-        out.push_str(&format!(
-            "Tasks: {} total, {} running, {} sleeping, {} stopped\n",
-            task_total, task_running, task_sleep, task_stop
-        ));
-        out.push_str(&format!("CPU [ {} ] {:>5.1}%\n", bar(cpu_avg), cpu_avg));
-        out.push_str(&format!(
-            "MEM [ {} ] {:>5.1}%   {}/{} MiB\n",
-            bar(mem_pct),
-            mem_pct,
-            (used_mem / 1024 / 1024),
-            (total_mem / 1024 / 1024)
-        ));
-        out.push_str("SWP [                        ]   0.0%   0/0 MiB\n\n");
-        out.push_str(" PID USER      PRI  NI   VIRT   RES   SHR S CPU% MEM%   TIME+  Command\n");
-
-        let mut rows: Vec<_> = proc_list
-            .iter()
-            .map(|p| {
-                let cpu = self.synthetic_proc_cpu(p);
-                let mem = if total_mem > 0 {
-                    (p.memory_size as f64 / total_mem as f64) * 100.0
-                } else {
-                    0.0
-                };
-                (p, cpu, mem)
+                (*p, cpu, mem_pct)
             })
             .collect();
         rows.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        for (p, cpu_pct, mem_pct_proc) in rows {
-            let pri = match p.priority {
-                Priority::High => 10,
-                Priority::Normal => 20,
-                Priority::Low => 30,
-            };
+        for (p, cpu, mem_pct) in rows.into_iter().take(14) {
             out.push_str(&format!(
-                "{:>4} {:<8} {:>3} {:>3} {:>6} {:>5} {:>5} {} {:>4.1} {:>4.1} {:>7}  {}\n",
+                "{:>4} {:<8} {:>2} {:>3} {:>7} {:>6} {:>6} {} {:>5.1} {:>5.1} {:>9} {}\n",
                 p.pid,
                 "user",
-                pri,
-                0,
-                format!("{}K", (p.memory_size / 1024) * 4),
+                20 + p.nice as i32,
+                p.nice,
+                format!("{}K", p.memory_size / 1024 * 4),
                 format!("{}K", p.memory_size / 1024),
                 format!("{}K", p.memory_size / 4096),
                 Self::proc_state_char(p.state),
-                cpu_pct,
-                mem_pct_proc,
-                format!(
-                    "{}:{:02}.{:02}",
-                    (p.pid / 7) % 99,
-                    (p.pid * 5) % 60,
-                    (p.pid * 3) % 100
-                ),
+                cpu,
+                mem_pct,
+                Self::format_cpu_time(p.cpu_time_us),
                 p.name
             ));
         }
-
-        out.push_str("\nF1Help F2Setup F3Search F4Filter F5Tree F6SortBy F9Kill F10Quit");
-        out
+        CmdOutput::ok(out)
     }
 
-    fn cmd_help(&self) -> String {
-        "kpawnd terminal help\n\nCore filesystem:\n  ls cd pwd cat cp mv rm rmdir mkdir touch ln file find stat\n  chmod chown mount umount\n\nText processing:\n  grep awk sed sort uniq wc cksum head tail cut tr tee diff\n\nSystem and process:\n  ps top htop kill jobs bg fg disown nohup free df du\n  uname hostname id groups who whoami uptime date env export history clear\n\nNetwork:\n  ip ifconfig route arp ss netstat ping traceroute host dig\n  nslookup curl wget nc myip\n\nTooling and shell:\n  man which whereis alias unalias source sudo python nano vi service\n\nBoot and extras:\n  grub hasgrub reboot screensaver cmatrix doom doommap\n\nQoL:\n  Tab autocomplete, ArrowUp/ArrowDown history, Ctrl+L clear line, Ctrl+C cancel line\n  man -k <term> to search docs\n\nUse `man <command>` for details.".into()
+    fn cmd_help(&self) -> CmdOutput {
+        CmdOutput::ok("kpawnd terminal help\n\nCore filesystem:\n  ls cd pwd cat cp mv rsync rm undo-rm rmdir mkdir touch ln file find stat\n  chmod chown chgrp mount umount losetup dd mkfs.ext4 fsck snapshot download\n\nText processing:\n  grep awk sed sort uniq wc cksum head tail cut tr tee diff\n  less more xxd hexdump strings base64 md5sum sha256sum seq yes shuf xargs\n\nSystem and process:\n  ps pgrep pkill lsof perf sysbench top htop kill nice renice ulimit jobs bg fg disown nohup free df du\n  uname hostname neofetch motd mail dmesg id groups groupadd usermod su who w last whoami uptime date env export history clear reset\n\nNetwork:\n  ip ifconfig route arp ss netstat ping traceroute host dig\n  nslookup curl wget lynx rss weather iss gh nc myip httpd tcpdump wscat downloads\n\nTooling and shell:\n  man which whereis alias unalias source true false sudo python lua sqlite3 git nano vi tmux service\n  systemctl journalctl apt dpkg dpkg-deb\n\nBoot and extras:\n  grub hasgrub reboot shutdown halt poweroff init runlevel memtest screensaver cmatrix idle xset volume beep doom doommap snake pong renderer view\n\nQoL:\n  Tab autocomplete, ArrowUp/ArrowDown history, Ctrl+R search history, Ctrl+Shift+F search scrollback, !! repeat, Ctrl+L clear line, Ctrl+C interrupt\n  cmd1 && cmd2, cmd1 || cmd2, cmd1; cmd2, echo $? for the last exit status\n  man -k <term> to search docs\n\nUse `man <command>` for details.")
     }
 
-    fn cmd_awk(&self, args: &[&str]) -> String {
+    fn cmd_awk(&self, args: &[&str]) -> CmdOutput {
         if args.len() < 2 {
-            return "usage: awk [-F DELIM] '{print ...}' FILE".into();
+            return CmdOutput::usage("usage: awk [-F DELIM] '{print ...}' FILE");
         }
 
         let mut idx = 0;
         let mut delim: Option<char> = None;
         if args.get(idx) == Some(&"-F") {
             let Some(v) = args.get(idx + 1) else {
-                return "awk: option -F requires an argument".into();
+                return CmdOutput::error(1, "awk: option -F requires an argument");
             };
             delim = v.chars().next();
             idx += 2;
         }
 
         let Some(program) = args.get(idx) else {
-            return "usage: awk [-F DELIM] '{print ...}' FILE".into();
+            return CmdOutput::usage("usage: awk [-F DELIM] '{print ...}' FILE");
         };
         let Some(file) = args.get(idx + 1) else {
-            return "awk: missing FILE operand".into();
+            return CmdOutput::error(1, "awk: missing FILE operand");
         };
 
         let Some(node) = self.kernel.fs.resolve(file) else {
-            return format!("awk: {}: No such file or directory", file);
+            return CmdOutput::error(1, format!("awk: {}: No such file or directory", file));
         };
         if node.is_dir {
-            return format!("awk: {}: Is a directory", file);
+            return CmdOutput::error(1, format!("awk: {}: Is a directory", file));
         }
 
         let prog = program.trim();
@@ -2618,7 +2771,7 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         expr = expr.trim();
 
         if !expr.starts_with("print") {
-            return "awk: only print program is supported (for now)".into();
+            return CmdOutput::error(1, "awk: only print program is supported (for now)");
         }
 
         let print_expr = expr.strip_prefix("print").unwrap_or("").trim();
@@ -2656,12 +2809,12 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
             out.push(rendered.join(" "));
         }
 
-        out.join("\n")
+        CmdOutput::ok(out.join("\n"))
     }
 
-    fn cmd_sed(&mut self, args: &[&str]) -> String {
+    fn cmd_sed(&mut self, args: &[&str]) -> CmdOutput {
         if args.len() < 2 {
-            return "usage: sed [-i] 's/old/new/[g]' FILE".into();
+            return CmdOutput::usage("usage: sed [-i] 's/old/new/[g]' FILE");
         }
 
         let mut idx = 0;
@@ -2672,21 +2825,21 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         }
 
         let Some(script) = args.get(idx) else {
-            return "sed: missing script".into();
+            return CmdOutput::error(1, "sed: missing script");
         };
         let Some(file) = args.get(idx + 1) else {
-            return "sed: missing FILE operand".into();
+            return CmdOutput::error(1, "sed: missing FILE operand");
         };
 
         let Some((pattern, replacement, global)) = Self::parse_sed_subst(script) else {
-            return "sed: supported form is s/old/new/[g]".into();
+            return CmdOutput::error(1, "sed: supported form is s/old/new/[g]");
         };
 
         let Some(node) = self.kernel.fs.resolve(file) else {
-            return format!("sed: {}: No such file or directory", file);
+            return CmdOutput::error(1, format!("sed: {}: No such file or directory", file));
         };
         if node.is_dir {
-            return format!("sed: {}: Is a directory", file);
+            return CmdOutput::error(1, format!("sed: {}: Is a directory", file));
         }
 
         let transformed = node
@@ -2704,23 +2857,25 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
 
         if inplace {
             match self.kernel.fs.write_file(file, &transformed) {
-                Ok(()) => String::new(),
-                Err(e) => format!("sed: {}: {}", file, e),
+                Ok(()) => CmdOutput::ok(String::new()),
+                Err(e) => CmdOutput::error(1, format!("sed: {}: {}", file, e)),
             }
         } else {
-            transformed
+            CmdOutput::ok(transformed)
         }
     }
 
-    fn cmd_alias(&mut self, args: &[&str]) -> String {
+    fn cmd_alias(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
             let mut items: Vec<(&String, &String)> = self.shell.aliases.iter().collect();
             items.sort_by(|a, b| a.0.cmp(b.0));
-            return items
-                .into_iter()
-                .map(|(k, v)| format!("alias {}='{}'", k, v))
-                .collect::<Vec<_>>()
-                .join("\n");
+            return CmdOutput::ok(
+                items
+                    .into_iter()
+                    .map(|(k, v)| format!("alias {}='{}'", k, v))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
         }
 
         let mut errors = Vec::new();
@@ -2739,22 +2894,18 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
                 }
                 self.shell.aliases.insert(key.to_string(), val);
             } else if let Some(v) = self.shell.aliases.get(*arg) {
-                return format!("alias {}='{}'", arg, v);
+                return CmdOutput::ok(format!("alias {}='{}'", arg, v));
             } else {
                 errors.push(format!("alias: {}: not found", arg));
             }
         }
 
-        if errors.is_empty() {
-            String::new()
-        } else {
-            errors.join("\n")
-        }
+        CmdOutput::collected(Vec::new(), errors)
     }
 
-    fn cmd_unalias(&mut self, args: &[&str]) -> String {
+    fn cmd_unalias(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: unalias NAME...".into();
+            return CmdOutput::usage("usage: unalias NAME...");
         }
         let mut errors = Vec::new();
         for name in args {
@@ -2762,39 +2913,24 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
                 errors.push(format!("unalias: {}: not found", name));
             }
         }
-        if errors.is_empty() {
-            String::new()
-        } else {
-            errors.join("\n")
-        }
+        CmdOutput::collected(Vec::new(), errors)
     }
 
-    fn cmd_source(&mut self, args: &[&str]) -> String {
+    fn cmd_source(&mut self, args: &[&str]) -> CmdOutput {
         if args.len() != 1 {
-            return "usage: source FILE".into();
+            return CmdOutput::usage("usage: source FILE");
         }
 
         let path = args[0];
         let Some(node) = self.kernel.fs.resolve(path) else {
-            return format!("source: {}: No such file or directory", path);
+            return CmdOutput::error(1, format!("source: {}: No such file or directory", path));
         };
         if node.is_dir {
-            return format!("source: {}: Is a directory", path);
+            return CmdOutput::error(1, format!("source: {}: Is a directory", path));
         }
 
         let script = node.data.clone();
-        let mut outputs = Vec::new();
-        for line in script.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
-            let out = self.exec(trimmed);
-            if !out.trim().is_empty() {
-                outputs.push(out);
-            }
-        }
-        outputs.join("\n")
+        self.exec_script(&script, str::to_string)
     }
 
     fn parse_sed_subst(script: &str) -> Option<(String, String, bool)> {
@@ -2863,19 +2999,25 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         }
     }
 
-    fn exec_pipeline(&mut self, line: &str) -> String {
+    /// Each stage's output feeds the next; errors from any stage go
+    /// straight to the terminal, and the last stage sets the status
+    fn exec_pipeline(&mut self, line: &str) -> CmdOutput {
         let mut stdin_buf = String::new();
+        let mut errors = Vec::new();
+        let mut status = 0;
         let mut has_input = false;
+        let captured = self.output_captured;
+        let segments: Vec<&str> = line.split('|').collect();
 
-        for segment in line.split('|') {
+        for (i, segment) in segments.iter().enumerate() {
             let seg = segment.trim();
             if seg.is_empty() {
-                return "sh: invalid null command in pipeline".into();
+                return CmdOutput::error(2, "sh: invalid null command in pipeline");
             }
 
             let mut rewritten = seg.to_string();
             if has_input {
-                let tmp_path = "/tmp/.pipe.stdin";
+                let tmp_path = PIPE_STDIN;
                 let _ = if self.kernel.fs.resolve(tmp_path).is_some() {
                     self.kernel.fs.write_file(tmp_path, &stdin_buf)
                 } else {
@@ -2885,138 +3027,79 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
                 let first = seg.split_whitespace().next().unwrap_or("");
                 match first {
                     "cat" | "grep" | "sort" | "uniq" | "wc" | "head" | "tail" | "awk" | "sed"
+                    | "less" | "more"
                         if seg.split_whitespace().count() <= 1 =>
                     {
                         rewritten = format!("{} {}", seg, tmp_path);
                     }
-                    "grep" if seg.split_whitespace().count() == 2 => {
+                    // grep reads the pipe unless it was given a file after the pattern
+                    "grep"
+                        if seg
+                            .split_whitespace()
+                            .skip(1)
+                            .filter(|w| !w.starts_with('-'))
+                            .count()
+                            == 1 =>
+                    {
+                        rewritten = format!("{} {}", seg, tmp_path);
+                    }
+                    // These read the pipe when given only options
+                    "head" | "tail" if only_options(seg, &["-n", "-c"]) => {
+                        rewritten = format!("{} {}", seg, tmp_path);
+                    }
+                    "shuf" if only_options(seg, &["-n"]) => {
                         rewritten = format!("{} {}", seg, tmp_path);
                     }
-                    "head" | "tail"
-                        if seg.contains("-n") && seg.split_whitespace().count() <= 3 =>
+                    "xxd" | "hexdump" | "hd" | "strings" | "base64"
+                        if only_options(seg, &["-c", "-g", "-l", "-s", "-n", "-w"]) =>
                     {
                         rewritten = format!("{} {}", seg, tmp_path);
                     }
+                    "md5sum" | "sha256sum" if only_options(seg, &[]) => {
+                        rewritten = format!("{} {}", seg, tmp_path);
+                    }
+                    "dd" if !seg.split_whitespace().any(|w| w.starts_with("if=")) => {
+                        rewritten = format!("{} if={}", seg, tmp_path);
+                    }
+                    // xargs reads the items for its command from a file
+                    "xargs" => {
+                        rewritten =
+                            format!("xargs -a {} {}", tmp_path, seg["xargs".len()..].trim());
+                    }
+                    // These take their message as arguments rather than a file
+                    "cowsay" | "cowthink" | "figlet" => {
+                        rewritten =
+                            format!("{} {}", seg, stdin_buf.trim_end().replace('\n', "\\n"));
+                    }
                     _ => {}
                 }
             }
 
-            stdin_buf = self.exec(&rewritten);
+            // Only the last stage writes to the terminal
+            self.output_captured = captured || i + 1 < segments.len();
+            let output = self.exec_command(&rewritten);
+            if !output.stderr.is_empty() {
+                errors.push(output.stderr);
+            }
+            stdin_buf = output.stdout;
+            status = output.status;
             has_input = true;
         }
+        self.output_captured = captured;
 
-        stdin_buf
-    }
-
-    fn is_builtin(&self, cmd: &str) -> bool {
-        matches!(
-            cmd,
-            "alias"
-                | "apt"
-                | "apt-get"
-                | "arp"
-                | "awk"
-                | "cat"
-                | "cmatrix"
-                | "cd"
-                | "cksum"
-                | "chmod"
-                | "chown"
-                | "clear"
-                | "cp"
-                | "curl"
-                | "cut"
-                | "date"
-                | "df"
-                | "diff"
-                | "dig"
-                | "doom"
-                | "doommap"
-                | "du"
-                | "echo"
-                | "env"
-                | "exit"
-                | "export"
-                | "file"
-                | "find"
-                | "free"
-                | "grub"
-                | "grep"
-                | "gunzip"
-                | "gzip"
-                | "hasgrub"
-                | "head"
-                | "help"
-                | "history"
-                | "host"
-                | "hostname"
-                | "htop"
-                | "id"
-                | "groups"
-                | "who"
-                | "ifconfig"
-                | "ip"
-                | "kill"
-                | "jobs"
-                | "bg"
-                | "fg"
-                | "disown"
-                | "nohup"
-                | "ln"
-                | "ls"
-                | "man"
-                | "mount"
-                | "mkdir"
-                | "mv"
-                | "myip"
-                | "nano"
-                | "nc"
-                | "netcat"
-                | "netstat"
-                | "nslookup"
-                | "ping"
-                | "ps"
-                | "pwd"
-                | "python"
-                | "reboot"
-                | "rm"
-                | "rmdir"
-                | "route"
-                | "screensaver"
-                | "sed"
-                | "service"
-                | "socket"
-                | "sort"
-                | "ss"
-                | "stat"
-                | "sudo"
-                | "tail"
-                | "tar"
-                | "tee"
-                | "top"
-                | "touch"
-                | "tr"
-                | "traceroute"
-                | "tracert"
-                | "unalias"
-                | "uname"
-                | "uniq"
-                | "umount"
-                | "unzip"
-                | "uptime"
-                | "vi"
-                | "vim"
-                | "wc"
-                | "wget"
-                | "whereis"
-                | "which"
-                | "whoami"
-                | "source"
-                | "zip"
-        )
+        CmdOutput {
+            stdout: stdin_buf,
+            stderr: errors.join("\n"),
+            status,
+        }
     }
 
-    fn cmd_ps(&self) -> String {
+    fn cmd_ps(&self, args: &[&str]) -> CmdOutput {
+        match args {
+            ["aux"] | ["-aux"] | ["u"] => return CmdOutput::ok(self.ps_aux()),
+            ["-ef"] | ["-e"] | ["-A"] => return CmdOutput::ok(self.ps_ef()),
+            _ => {}
+        }
         let mut out = String::from("  PID  PPID STAT CMD\n");
         for p in self.kernel.proc.list() {
             let st = match p.state {
@@ -3027,19 +3110,29 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
             };
             out.push_str(&format!("{:5} {:5} {}    {}\n", p.pid, p.ppid, st, p.name));
         }
-        out
+        CmdOutput::ok(out)
     }
 
-    fn spawn_background_job(&mut self, cmdline: &str, detached: bool) -> String {
+    fn spawn_background_job(&mut self, cmdline: &str, detached: bool) -> CmdOutput {
         let expanded = self.expand_alias_line(cmdline);
         let mut parts = expanded.split_whitespace();
         let Some(name) = parts.next() else {
-            return "sh: empty job command".into();
+            return CmdOutput::error(1, "sh: empty job command");
         };
 
+        if let Some(error) = self.fork_denied() {
+            return CmdOutput::error(1, error);
+        }
         let Some(pid) = self.kernel.proc.spawn(name, 1, &mut self.kernel.mem) else {
-            return "Failed to spawn background process: out of memory".into();
+            return CmdOutput::error(1, "Failed to spawn background process: out of memory");
         };
+        if let Some(error) = self.exec_denied(pid, name) {
+            return CmdOutput::error(1, error);
+        }
+        let user = self.current_user();
+        self.kernel
+            .proc
+            .set_session(pid, &user, Some(TERMINAL_TTY), &expanded);
 
         self.kernel.scheduler.add(pid, Priority::Low);
 
@@ -3053,9 +3146,9 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         });
 
         if detached {
-            format!("nohup: job {} started with pid {}", id, pid)
+            CmdOutput::ok(format!("nohup: job {} started with pid {}", id, pid))
         } else {
-            format!("[{}] {}", id, pid)
+            CmdOutput::ok(format!("[{}] {}", id, pid))
         }
     }
 
@@ -3088,65 +3181,66 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         }
     }
 
-    fn cmd_jobs(&self, _args: &[&str]) -> String {
+    fn cmd_jobs(&self, _args: &[&str]) -> CmdOutput {
         if self.jobs.is_empty() {
-            return String::new();
+            return CmdOutput::ok(String::new());
         }
-        self.jobs
-            .iter()
-            .map(|j| {
-                format!(
-                    "[{}] {:<7} {:>5} {}",
-                    j.id,
-                    Self::job_label(j.state),
-                    j.pid,
-                    j.command
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
+        CmdOutput::ok(
+            self.jobs
+                .iter()
+                .map(|j| {
+                    format!(
+                        "[{}] {:<7} {:>5} {}",
+                        j.id,
+                        Self::job_label(j.state),
+                        j.pid,
+                        j.command
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
     }
 
-    fn cmd_bg(&mut self, args: &[&str]) -> String {
+    fn cmd_bg(&mut self, args: &[&str]) -> CmdOutput {
         let idx = match self.find_job_index_by_spec(args.first().copied()) {
             Some(i) => i,
-            None => return "bg: no such job".into(),
+            None => return CmdOutput::error(1, "bg: no such job"),
         };
         let job = &mut self.jobs[idx];
         job.state = JobState::Running;
-        format!("[{}] {} &", job.id, job.command)
+        CmdOutput::ok(format!("[{}] {} &", job.id, job.command))
     }
 
-    fn cmd_fg(&mut self, args: &[&str]) -> String {
+    fn cmd_fg(&mut self, args: &[&str]) -> CmdOutput {
         let idx = match self.find_job_index_by_spec(args.first().copied()) {
             Some(i) => i,
-            None => return "fg: no such job".into(),
+            None => return CmdOutput::error(1, "fg: no such job"),
         };
         let job = self.jobs.remove(idx);
-        let _ = self.kernel.proc.kill(job.pid, &mut self.kernel.mem);
-        self.kernel.scheduler.remove(job.pid);
-        job.command
+        self.foreground = Some(job.pid);
+        CmdOutput::ok(job.command)
     }
 
-    fn cmd_disown(&mut self, args: &[&str]) -> String {
+    fn cmd_disown(&mut self, args: &[&str]) -> CmdOutput {
         let idx = match self.find_job_index_by_spec(args.first().copied()) {
             Some(i) => i,
-            None => return "disown: no such job".into(),
+            None => return CmdOutput::error(1, "disown: no such job"),
         };
         self.jobs.remove(idx);
-        String::new()
+        CmdOutput::ok(String::new())
     }
 
-    fn cmd_nohup(&mut self, args: &[&str]) -> String {
+    fn cmd_nohup(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: nohup COMMAND [ARG]...".into();
+            return CmdOutput::usage("usage: nohup COMMAND [ARG]...");
         }
         self.spawn_background_job(&args.join(" "), true)
     }
 
-    fn cmd_kill(&mut self, args: &[&str]) -> String {
+    fn cmd_kill(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: kill [-SIGNAL] <pid|%job>...".into();
+            return CmdOutput::usage("usage: kill [-SIGNAL] <pid|%job>...");
         }
 
         let mut idx = 0;
@@ -3161,7 +3255,7 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
         }
 
         if idx >= args.len() {
-            return "kill: missing pid or %job".into();
+            return CmdOutput::error(1, "kill: missing pid or %job");
         }
 
         let mut errors = Vec::new();
@@ -3180,6 +3274,9 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
             let killed = self.kernel.proc.kill(pid, &mut self.kernel.mem);
             if killed {
                 self.kernel.scheduler.remove(pid);
+                self.stop_http_server(pid);
+                self.kernel.fs.close_owned(pid);
+                self.network.release_owned(pid);
                 if signal == "STOP" || signal == "19" {
                     if let Some(j) = self.jobs.iter_mut().find(|j| j.pid == pid) {
                         j.state = JobState::Stopped;
@@ -3192,79 +3289,199 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
             }
         }
 
-        if errors.is_empty() {
-            String::new()
-        } else {
-            errors.join("\n")
-        }
+        CmdOutput::collected(Vec::new(), errors)
     }
-    fn cmd_uname(&self, args: &[&str]) -> String {
+    fn cmd_uname(&self, args: &[&str]) -> CmdOutput {
         let kernel_ver = crate::kernel::KERNEL_VERSION;
         let version = crate::kernel::VERSION;
 
         if args.contains(&"-a") {
-            format!(
+            CmdOutput::ok(format!(
                 "Linux kpawnd {} #1 SMP PREEMPT_DYNAMIC {} wasm32 GNU/Linux",
                 kernel_ver, version
-            )
+            ))
         } else if args.contains(&"-r") {
-            kernel_ver.into()
+            CmdOutput::ok(kernel_ver)
         } else if args.contains(&"-s") {
-            "Linux".into()
+            CmdOutput::ok("Linux")
         } else if args.contains(&"-n") {
-            "kpawnd".into()
+            CmdOutput::ok("kpawnd")
         } else if args.contains(&"-m") {
-            "wasm32".into()
+            CmdOutput::ok("wasm32")
         } else if args.contains(&"-o") {
-            "GNU/Linux".into()
+            CmdOutput::ok("GNU/Linux")
         } else {
-            "Linux".into()
+            CmdOutput::ok("Linux")
         }
     }
-    fn cmd_hostname(&self) -> String {
-        self.kernel
-            .fs
-            .resolve("/etc/hostname")
-            .map(|n| n.data.clone())
-            .unwrap_or_else(|| "localhost".into())
+    fn cmd_hostname(&self) -> CmdOutput {
+        CmdOutput::ok(
+            self.kernel
+                .fs
+                .resolve("/etc/hostname")
+                .map(|n| n.data.clone())
+                .unwrap_or_else(|| "localhost".into()),
+        )
     }
-    fn cmd_free(&self) -> String {
+    fn cmd_free(&self) -> CmdOutput {
         let (used, total) = self.kernel.mem.usage();
-        format!(
+        CmdOutput::ok(format!(
             "total: {}K\nused:  {}K\nfree:  {}K",
             total / 1024,
             used / 1024,
             (total - used) / 1024
-        )
-    }
-    fn cmd_history(&self) -> String {
-        self.shell
-            .history
-            .iter()
-            .enumerate()
-            .map(|(i, c)| format!("{:4}  {}", i + 1, c))
-            .collect::<Vec<_>>()
-            .join("\n")
+        ))
     }
-
-    fn cmd_man(&self, args: &[&str]) -> String {
+    fn cmd_man(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "man - Linux manual pager (kpawnd)\n\nUsage:\n  man <command>\n  man -k <keyword>\n\nExamples:\n  man ls\n  man htop\n  man -k network\n\nTip: run `help` to list all available commands.".into();
+            return CmdOutput::ok("man - Linux manual pager (kpawnd)\n\nUsage:\n  man <command>\n  man -k <keyword>\n\nExamples:\n  man ls\n  man htop\n  man -k network\n\nTip: run `help` to list all available commands.");
         }
 
         if args[0] == "-k" {
             if args.len() < 2 {
-                return "man: what keyword?\nusage: man -k <keyword>".into();
+                return CmdOutput::usage("man: what keyword?\nusage: man -k <keyword>");
             }
             let needle = args[1].to_lowercase();
             let pages = [
-                "alias", "apt", "cat", "cmatrix", "cd", "cksum", "chmod", "chown", "clear", "cp",
-                "curl", "cut", "date", "df", "diff", "du", "echo", "find", "free", "grep", "head",
-                "help", "history", "host", "hostname", "htop", "id", "groups", "who", "kill",
-                "jobs", "bg", "fg", "disown", "nohup", "ln", "ls", "man", "mkdir", "mount",
-                "umount", "mv", "ping", "ps", "pwd", "python", "rm", "sort", "source", "stat",
-                "sudo", "tail", "tee", "top", "touch", "tr", "unalias", "uname", "uniq", "uptime",
-                "wc", "whereis", "which", "whoami", "grub", "doom", "doommap",
+                "alias",
+                "apt",
+                "cat",
+                "cmatrix",
+                "cd",
+                "cksum",
+                "chgrp",
+                "chmod",
+                "chown",
+                "clear",
+                "cp",
+                "curl",
+                "cut",
+                "date",
+                "dd",
+                "df",
+                "diff",
+                "dmesg",
+                "dpkg",
+                "dpkg-deb",
+                "du",
+                "echo",
+                "false",
+                "find",
+                "free",
+                "fsck",
+                "git",
+                "grep",
+                "head",
+                "help",
+                "history",
+                "host",
+                "hostname",
+                "htop",
+                "httpd",
+                "id",
+                "groupadd",
+                "groups",
+                "who",
+                "w",
+                "last",
+                "su",
+                "kill",
+                "jobs",
+                "bg",
+                "fg",
+                "disown",
+                "nohup",
+                "less",
+                "ln",
+                "ls",
+                "lua",
+                "man",
+                "mkdir",
+                "mkfs.ext4",
+                "more",
+                "mount",
+                "umount",
+                "losetup",
+                "mv",
+                "ping",
+                "ps",
+                "pgrep",
+                "pkill",
+                "lsof",
+                "perf",
+                "sysbench",
+                "nice",
+                "renice",
+                "ulimit",
+                "pwd",
+                "python",
+                "rm",
+                "sort",
+                "source",
+                "sqlite3",
+                "stat",
+                "sudo",
+                "tail",
+                "tcpdump",
+                "tee",
+                "top",
+                "touch",
+                "tr",
+                "unalias",
+                "uname",
+                "uniq",
+                "undo-rm",
+                "usermod",
+                "uptime",
+                "wc",
+                "whereis",
+                "which",
+                "whoami",
+                "traceroute",
+                "wscat",
+                "lynx",
+                "rss",
+                "weather",
+                "iss",
+                "gh",
+                "downloads",
+                "download",
+                "mail",
+                "grub",
+                "reboot",
+                "shutdown",
+                "init",
+                "doom",
+                "doommap",
+                "renderer",
+                "view",
+                "snake",
+                "snapshot",
+                "tmux",
+                "reset",
+                "xxd",
+                "hexdump",
+                "strings",
+                "base64",
+                "md5sum",
+                "sha256sum",
+                "seq",
+                "yes",
+                "shuf",
+                "xargs",
+                "neofetch",
+                "motd",
+                "pong",
+                "screensaver",
+                "memtest",
+                "idle",
+                "volume",
+                "beep",
+                "systemctl",
+                "journalctl",
+                "cowsay",
+                "figlet",
+                "fortune",
             ];
             let matches: Vec<&str> = pages
                 .iter()
@@ -3272,17 +3489,19 @@ MiB Mem : {:>7.1} total, {:>7.1} free, {:>7.1} used, {:>7.1} buff/cache\n\n\
                 .filter(|name| name.contains(&needle))
                 .collect();
             if matches.is_empty() {
-                return format!("man: nothing appropriate for '{}'", args[1]);
+                return CmdOutput::error(1, format!("man: nothing appropriate for '{}'", args[1]));
             }
-            return matches
-                .iter()
-                .map(|name| format!("{} (1) - manual entry", name))
-                .collect::<Vec<_>>()
-                .join("\n");
+            return CmdOutput::ok(
+                matches
+                    .iter()
+                    .map(|name| format!("{} (1) - manual entry", name))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
         }
 
         let cmd = args[0];
-        match cmd {
+        CmdOutput::ok(match cmd {
             "ls" => {
                 r#"LS(1)                            User Commands                           LS(1)
 
@@ -3294,16 +3513,42 @@ SYNOPSIS
 
 DESCRIPTION
        List information about the FILEs (the current directory by default).
+       Names are laid out in columns to fit the terminal width ($COLUMNS),
+       or one per line when piped.
 
        -a, --all
               do not ignore entries starting with .
 
+       -A, --almost-all
+              like -a, but leave out . and ..
+
        -l     use a long listing format
 
+       -h, --human-readable
+              with -l, print sizes like 1.5K and 234M
+
+       -i, --inode
+              print the inode number of each file
+
+       -R, --recursive
+              list subdirectories recursively
+
+       -r, --reverse
+              reverse the sort order
+
+       -S     sort by file size, largest first
+
+       -t     sort by modification time, newest first
+
+       -1     list one file per line
+
 EXAMPLES
        ls -la /bin
               List all files in /bin with details
 
+       ls -lhS ~
+              Largest files in your home directory first
+
 SEE ALSO
        dir(1), find(1)
 "#
@@ -3383,13 +3628,30 @@ DESCRIPTION
        rm removes each specified file. By default, it does not remove directories.
 
        -f, --force
-              ignore nonexistent files and arguments
+              ignore nonexistent files and arguments, never prompt
+
+       -i     prompt before every removal; answer y to remove
 
        -r, -R, --recursive
               remove directories and their contents recursively
 
+       -v, --verbose
+              explain what is being done
+
+       --trash
+              move files into ~/.Trash instead of deleting them
+
+       --no-trash
+              delete even when the trash is on by default
+
+       Put trash=on in ~/.config/rm to make --trash the default. Files
+       already in ~/.Trash are always deleted for good. undo-rm(1) puts
+       back whatever the last rm removed.
+
 WARNING
        Removing critical system files (like /bin/sh) will cause a kernel panic!
+       The next boot stops at an (initramfs) shell; run fsck -y there to
+       restore them, and reinstall-grub if /boot/grub is gone too.
 "#
                 .into()
             }
@@ -3416,11 +3678,23 @@ NAME
        touch - change file timestamps
 
 SYNOPSIS
-       touch [FILE]...
+       touch [-acm] [-t STAMP | -d DATE] FILE...
 
 DESCRIPTION
        Update the access and modification times of each FILE to the current time.
        A FILE argument that does not exist is created empty.
+
+OPTIONS
+       -a     change only the access time
+       -m     change only the modification time
+       -c     do not create files that do not exist
+       -t STAMP
+              use [[CC]YY]MMDDhhmm[.ss] instead of the current time
+       -d DATE
+              use DATE instead of the current time: YYYY-MM-DD [HH:MM[:SS]],
+              @SECONDS or now
+
+       Times are UTC.
 "#
                 .into()
             }
@@ -3454,77 +3728,300 @@ NAME
        ps - report a snapshot of the current processes
 
 SYNOPSIS
-       ps
+       ps [aux | -ef]
 
 DESCRIPTION
        ps displays information about a selection of the active processes.
+       Use pgrep(1) to look processes up by name.
 
 OUTPUT
        PID    Process ID
        PPID   Parent process ID
        STAT   Process state (R=running, S=sleeping, T=stopped, Z=zombie)
        CMD    Command name
+
+       ps aux adds the scheduler's view of each process:
+
+       %CPU   Share of the CPU in the last scheduler tick, by nice weight
+       %MEM   Share of memory
+       VSZ    Virtual memory size in KiB
+       RSS    Resident memory in KiB
+       TTY    Controlling terminal (? for daemons)
+       NI     Niceness (see nice(1))
+       START  Time the process was started
+       TIME   CPU time used so far
+       COMMAND
+              Full command line
+
+       ps -ef shows every process in the System V format: UID, PID, PPID,
+       C (CPU share), STIME (start time), TTY, TIME and CMD.
 "#
                 .into()
             }
 
-            "kill" => {
-                r#"KILL(1)                          User Commands                         KILL(1)
+            "nice" | "renice" => {
+                r#"NICE(1)                          User Commands                         NICE(1)
 
 NAME
-       kill - send a signal to a process
+       nice, renice - run a program or change a process with modified priority
 
 SYNOPSIS
-            kill [-SIGNAL] <pid|%job>...
+       nice [-n N] COMMAND [ARG]...
+       renice [-n] N [-p] PID...
 
 DESCRIPTION
-            Send a signal to process IDs or shell job references.
-            Common signals: -TERM (default), -KILL, -STOP.
+       Niceness runs from -20 (most favourable) to 19 (least). The scheduler
+       shares CPU time by weight, and each step of niceness is worth about
+       25% CPU against a process one step away, so changes show up in the
+       %CPU column of ps aux, top and htop.
+
+       nice runs COMMAND with niceness N (10 if -n is left out). With no
+       arguments it prints the current niceness.
+
+       renice sets the niceness of running processes.
+
+       Only root can use negative values or lower a process's niceness.
+
+EXAMPLES
+       nice -n 15 python
+       sudo renice -5 -p 12
 "#
                 .into()
             }
 
-            "jobs" => {
-                r#"JOBS(1)                          User Commands                         JOBS(1)
+            "ulimit" => {
+                r#"ULIMIT(1)                        User Commands                       ULIMIT(1)
 
-        NAME
-            jobs - list active jobs
+NAME
+       ulimit - show or set resource limits for the shell
 
-        SYNOPSIS
-            jobs
+SYNOPSIS
+       ulimit [-SH] [-a | -n | -u | -v] [LIMIT]
 
-        DESCRIPTION
-            Display shell-managed background jobs with status and pid.
-        "#
-                .into()
-            }
+DESCRIPTION
+       Each shell, and each tmux pane, has its own limits:
 
-            "bg" => {
-                r#"BG(1)                            User Commands                           BG(1)
+       -n     open files. Opening a file past it fails with EMFILE (Too
+              many open files); stdin, stdout and stderr count too.
+       -u     processes the user may run. Starting a command past it fails
+              with EAGAIN (fork: Resource temporarily unavailable). Root is
+              exempt.
+       -v     virtual memory in KiB. A command that maps more is killed
+              with ENOMEM (Cannot allocate memory).
 
-        NAME
-            bg - resume jobs in the background
+       -a lists every limit. LIMIT is a number or unlimited; -S sets only
+       the soft limit, -H only the hard one, and neither sets both. Only
+       root can raise a hard limit, and the soft limit cannot go above it.
 
-        SYNOPSIS
-            bg [%JOB]
+       The active shell's limits are also in /proc/self/limits.
 
-        DESCRIPTION
-            Mark selected job as running in background.
-        "#
+EXAMPLES
+       ulimit -a
+       ulimit -Sn 64
+       ulimit -u 20
+"#
                 .into()
             }
 
-            "fg" => {
-                r#"FG(1)                            User Commands                           FG(1)
+            "pgrep" | "pkill" => {
+                r#"PGREP(1)                         User Commands                        PGREP(1)
 
-        NAME
-            fg - move job to foreground
+NAME
+       pgrep, pkill - look up or signal processes by name
+
+SYNOPSIS
+       pgrep [-l | -a] [-f] [-x] [-u USER] PATTERN
+       pkill [-SIGNAL] [-f] [-x] [-u USER] PATTERN
+
+DESCRIPTION
+       pgrep prints the PID of every process whose name matches PATTERN.
+       pkill sends those processes a signal (TERM by default), as kill(1)
+       would. PID 1 is never signalled.
+
+       PATTERN matches anywhere in the name. Anchor it with ^ at the start
+       or $ at the end.
+
+OPTIONS
+       -l     List the process name next to the PID (pgrep)
+       -a     List the full command line next to the PID (pgrep)
+       -f     Match PATTERN against the full command line
+       -x     Only match processes whose name is exactly PATTERN
+       -u USER
+              Only match processes owned by USER
+
+EXAMPLES
+       pgrep -l ^http
+       pkill -STOP -u guest python
+"#
+                .into()
+            }
+
+            "lsof" => {
+                r#"LSOF(8)                     System Manager's Manual                    LSOF(8)
+
+NAME
+       lsof - list open files
+
+SYNOPSIS
+       lsof [-i [:PORT]] [-p PID[,PID]] [-u USER] [-c NAME] [FILE]...
+
+DESCRIPTION
+       Lists what every process holds open: its working directory (cwd),
+       its standard streams on the controlling terminal (daemons have
+       /dev/null), the files it opened and its sockets. FD is the
+       descriptor with its mode (r, w or u for both).
+
+       Handles still open after their process is gone are listed with
+       COMMAND ?, so leaks are easy to spot.
+
+       The same descriptors are links in /proc/PID/fd; /proc/self/fd is
+       the shell's.
+
+OPTIONS
+       -i [:PORT]
+              Only sockets, optionally only those on PORT
+       -p PID Only the given processes
+       -u USER
+              Only processes owned by USER
+       -c NAME
+              Only processes whose name starts with NAME
+       FILE   Only processes holding FILE open
+
+       Exits with status 1 when nothing matches.
+
+EXAMPLES
+       lsof -i :8080
+       lsof -p 2
+       ls -l /proc/self/fd
+"#
+                .into()
+            }
+
+            "perf" => {
+                r#"PERF(1)                          User Commands                         PERF(1)
+
+NAME
+       perf - show measured performance of the running system
+
+SYNOPSIS
+       perf [reset]
+
+DESCRIPTION
+       Every number is measured in the browser, not simulated:
+
+       Heap    Size of the WebAssembly linear memory
+       CPU     Share of each of the last 60 seconds the main thread spent
+               running commands and drawing frames
+       Frames  Frame times of the last renderer (doom or a screensaver)
+               over its last 300 frames, with an FPS histogram
+       Exec    Latency percentiles of the last 200 commands
+       Jank    How late a 100 ms timer fires; a stuck main thread makes
+               it late
+
+       Sparklines show the most recent 60 samples, oldest on the left.
+
+       /proc/stat carries the same busy time as user time in 1/100 s.
+
+       reset  Forget every sample
+
+EXAMPLES
+       doom, play a while, then perf
+"#
+                .into()
+            }
+
+            "sysbench" => {
+                r#"SYSBENCH(1)                      User Commands                     SYSBENCH(1)
+
+NAME
+       sysbench - benchmark this device from inside the terminal
+
+SYNOPSIS
+       sysbench [--time=SECONDS] [cpu|memory|fileio|raycast|all]...
+
+DESCRIPTION
+       Runs real micro-benchmarks in the WebAssembly module, each for the
+       given time (0.5 s by default), and prints how fast it went:
+
+       cpu      Primes up to 10000 by trial division (integer) and a
+                64x64 Mandelbrot grid (float), in events per second
+       memory   Writing and reading back an 8 MiB buffer, in MiB/s
+       fileio   Creating, reading and deleting 4 KiB files in the VFS,
+                in files per second for each
+       raycast  Frames per second of the doom renderer drawing 320x200
+                offscreen
+
+       With no test named, all of them run. The score scales each result
+       so a mid-range laptop browser gets 1000, then takes the geometric
+       mean; run the same command on two devices to compare them.
+
+       The page does not respond while a test runs.
+
+EXAMPLES
+       sysbench
+       sysbench --time=2 cpu raycast
+"#
+                .into()
+            }
+
+            "kill" => {
+                r#"KILL(1)                          User Commands                         KILL(1)
+
+NAME
+       kill - send a signal to a process
+
+SYNOPSIS
+            kill [-SIGNAL] <pid|%job>...
+
+DESCRIPTION
+            Send a signal to process IDs or shell job references.
+            Common signals: -TERM (default), -KILL, -STOP.
+"#
+                .into()
+            }
+
+            "jobs" => {
+                r#"JOBS(1)                          User Commands                         JOBS(1)
+
+        NAME
+            jobs - list active jobs
+
+        SYNOPSIS
+            jobs
+
+        DESCRIPTION
+            Display shell-managed background jobs with status and pid.
+        "#
+                .into()
+            }
+
+            "bg" => {
+                r#"BG(1)                            User Commands                           BG(1)
+
+        NAME
+            bg - resume jobs in the background
+
+        SYNOPSIS
+            bg [%JOB]
+
+        DESCRIPTION
+            Mark selected job as running in background.
+        "#
+                .into()
+            }
+
+            "fg" => {
+                r#"FG(1)                            User Commands                           FG(1)
+
+        NAME
+            fg - move job to foreground
 
         SYNOPSIS
             fg [%JOB]
 
         DESCRIPTION
-            Bring a selected job to foreground and remove job tracking.
+            Bring a selected job to the foreground and stop tracking it as a
+            job. Ctrl+C interrupts it.
         "#
                 .into()
             }
@@ -3609,6 +4106,165 @@ NOTE
                 .into()
             }
 
+            "lynx" | "w3m" => {
+                r#"LYNX(1)                          User Commands                         LYNX(1)
+
+NAME
+       lynx, w3m - text-mode web browser
+
+SYNOPSIS
+       lynx [-dump] URL
+
+DESCRIPTION
+       Fetches URL like curl and shows the page as text: scripts and styles
+       are dropped, paragraphs and lists are wrapped to the terminal width
+       and each link is numbered, as in [3]Downloads. Pages served by httpd
+       on this machine load without the network.
+
+       While a page is up, the line typed is a browser command instead of
+       a shell command:
+
+       N       follow link number N
+       b       go back to the previous page
+       g URL   open URL
+       Enter   show the current page again
+       q       quit (Ctrl+C also works)
+
+OPTIONS
+       -dump  print the page followed by a numbered list of its links,
+              then exit
+
+EXAMPLES
+       lynx example.com
+       lynx -dump https://example.com | less
+       lynx localhost:8080
+
+SEE ALSO
+       curl, wget, httpd
+"#
+                .into()
+            }
+
+            "rss" => {
+                r#"RSS(1)                           User Commands                          RSS(1)
+
+NAME
+       rss - read RSS and Atom feeds
+
+SYNOPSIS
+       rss URL
+       rss [list]
+       rss read N
+
+DESCRIPTION
+       rss URL fetches a feed, keeps a copy under ~/.cache/rss/ and lists
+       its entries, numbered, with the date each was published. The feed
+       becomes the current one for the other commands, which work from the
+       cached copy without the network.
+
+       list    list the entries of the current feed again
+       read N  show entry N, its text laid out as lynx would, in the pager
+
+       When a feed can't be fetched, the cached copy is listed instead.
+
+EXAMPLES
+       rss https://hnrss.org/frontpage
+       rss read 3
+
+SEE ALSO
+       lynx, curl
+"#
+                .into()
+            }
+
+            "gh" => {
+                r#"GH(1)                            User Commands                           GH(1)
+
+NAME
+       gh - a GitHub profile or repository at a glance
+
+SYNOPSIS
+       gh USER
+       gh USER/REPO
+
+DESCRIPTION
+       Asks the GitHub REST API about USER or USER/REPO.
+
+       For a user: name, bio, location, repository, follower and
+       following counts and the join date, then the six most starred
+       repositories of their own as cards, the way a profile pins them,
+       and the ones pushed to most recently.
+
+       For a repository: description, stars, forks, watchers, open
+       issues, language, license, default branch and topics, then the
+       latest commits.
+
+       Answers are kept in ~/.cache/gh and reused for ten minutes.
+       Without a token GitHub allows 60 requests an hour; past that, or
+       when GitHub cannot be reached, the cached copy is shown with a
+       note saying how old it is.
+
+EXAMPLES
+       gh kpawnd
+       gh kpawnd/kpawnd.github.io
+
+SEE ALSO
+       git, curl, lynx
+"#
+                .into()
+            }
+
+            "weather" => {
+                r#"WEATHER(1)                       User Commands                      WEATHER(1)
+
+NAME
+       weather - current weather and a short forecast from wttr.in
+
+SYNOPSIS
+       weather [-u] [CITY...]
+
+DESCRIPTION
+       Asks wttr.in for the weather in CITY, or where the browser is when
+       no city is given, and draws it the way wttr.in does: a picture of
+       the sky beside the conditions, temperature (feels-like in
+       brackets), wind, visibility and rain, then the low, high and midday
+       sky for the next days.
+
+       -u     US units: °F, mph, miles and inches
+
+EXAMPLES
+       weather
+       weather New York
+       weather -u Chicago
+
+SEE ALSO
+       iss, curl
+"#
+                .into()
+            }
+
+            "iss" => {
+                r#"ISS(1)                           User Commands                          ISS(1)
+
+NAME
+       iss - where the International Space Station is now
+
+SYNOPSIS
+       iss
+
+DESCRIPTION
+       Asks open-notify.org (or wheretheiss.at when that can't be reached)
+       for the station's position and marks it with @ on a map of the
+       world, with its latitude and longitude and whether it is over land
+       or the ocean. It goes round the Earth about every 90 minutes, so
+       running it again a few minutes later shows it moved.
+
+SEE ALSO
+       weather, curl
+"#
+                .into()
+            }
+
             "curl" => {
                 r#"CURL(1)                          User Commands                         CURL(1)
 
@@ -3625,11 +4281,24 @@ OPTIONS
        -I, --head
               Show response headers only
 
+       -o FILE
+              Write the response body to FILE instead of the terminal
+
+       -O, --remote-name
+              Write to a file named like the remote file
+
+       -C -   Continue a partial download where the file ends
+
+       -s, --silent
+              Hide the progress meter
+
        -v     Verbose mode
 
 EXAMPLES
        curl https://api.github.com
        curl -I https://example.com
+       curl -o page.html https://example.com
+       curl -C - -O https://example.com/big.iso
 "#
                 .into()
             }
@@ -3641,14 +4310,39 @@ NAME
        grep - print lines matching a pattern
 
 SYNOPSIS
-       grep PATTERN FILE
+       grep [-rinvclF] [--color[=WHEN]] PATTERN [FILE]...
 
 DESCRIPTION
        grep searches for PATTERN in each FILE and prints each line that matches.
+       With more than one FILE, or with -r, each line starts with its file name.
+       In a pipeline grep reads the output of the previous command.
+
+OPTIONS
+       -r, -R   Search directories recursively (the current one if no FILE)
+       -i       Ignore case
+       -n       Prefix each line with its line number
+       -v       Print the lines that do not match
+       -c       Print only a count of matching lines per file
+       -l       Print only the names of files with a match
+       -F       Treat PATTERN as a fixed string
+       -E       Accepted for compatibility; patterns are always extended
+       --color[=WHEN]
+                Highlight matches, file names and line numbers; WHEN is
+                always, never or auto (only when not piped)
+
+PATTERNS
+       .        Any character
+       [abc]    One of a, b or c; [a-z] for a range, [^...] to negate
+       \d \w \s Digit, word character, whitespace
+       *  +  ?  Zero or more, one or more, zero or one of the previous item
+       ^  $     Start and end of line
+       \.       A literal dot (and likewise for the other special characters)
 
 EXAMPLES
        grep "error" /var/log/syslog
               Search for lines containing "error" in syslog
+       grep -rn '^root' /etc
+       ps | grep -c '[0-9]+'
 "#
                 .into()
             }
@@ -3672,6 +4366,37 @@ DESCRIPTION
                 .into()
             }
 
+            "true" | "false" => {
+                r#"TRUE(1)                          User Commands                         TRUE(1)
+
+NAME
+       true, false - do nothing, successfully or unsuccessfully
+
+SYNOPSIS
+       true
+       false
+
+DESCRIPTION
+       true exits with status 0 and false with status 1. They are handy for
+       trying out command lists.
+
+COMMAND LISTS
+       cmd1 ; cmd2     Run cmd1, then cmd2
+       cmd1 && cmd2    Run cmd2 only if cmd1 succeeded
+       cmd1 || cmd2    Run cmd2 only if cmd1 failed
+
+       $? expands to the exit status of the last command: 0 for success,
+       1 for an error, 2 for a usage mistake and 127 for an unknown command.
+       grep and pgrep fail when nothing matches.
+
+EXAMPLES
+       mkdir build && cd build
+       cat notes.txt || echo "no notes yet"
+       false; echo $?
+"#
+                .into()
+            }
+
             "unalias" => {
                 r#"UNALIAS(1)                       User Commands                      UNALIAS(1)
 
@@ -3744,17 +4469,34 @@ NAME
        find - search for files in a directory hierarchy
 
 SYNOPSIS
-       find [PATH]
+       find [PATH...] [EXPRESSION]
 
 DESCRIPTION
-       find recursively lists all files and directories under PATH.
-       If PATH is omitted, the current directory is used.
+       find recursively lists the files and directories under each PATH that
+       match every test in EXPRESSION. If PATH is omitted, the current
+       directory is used. Paths are printed starting with PATH as given.
+
+TESTS
+       -name GLOB       Base name matches GLOB (* and ?); -iname ignores case
+       -type f|d        Regular file or directory
+       -size [+-]N[ckMG]
+                        Size in 512-byte blocks, or bytes (c), KiB (k), MiB
+                        (M) or GiB (G), rounded up; +N is more, -N is less
+       -maxdepth N      Descend at most N levels below PATH
+       -mindepth N      Skip matches less than N levels below PATH
+
+ACTIONS
+       -print           Print the path (the default)
+       -exec CMD {} \;  Run CMD through the shell for each match, with {}
+                        replaced by its path
 
 EXAMPLES
        find /etc
               List all files under /etc
-       find .
-              List all files in current directory recursively
+       find . -name "*.txt" -type f
+              Text files below the current directory
+       find /var -size +10k -exec ls -l {} \;
+              Long listing of everything over 10 KiB under /var
 "#
                 .into()
             }
@@ -3784,11 +4526,15 @@ NAME
        head - output the first part of files
 
 SYNOPSIS
-       head [-n NUM] FILE
+       head [-n NUM | -c NUM] FILE
 
 DESCRIPTION
-       Print the first 10 lines of FILE to standard output.
-       With -n NUM, print the first NUM lines instead.
+       Print the first 10 lines of FILE to standard output. In a pipeline
+       head reads the output of the previous command.
+
+OPTIONS
+       -n NUM, -NUM   Print the first NUM lines instead
+       -c NUM         Print the first NUM bytes
 
 EXAMPLES
        head -n 5 /etc/passwd
@@ -3804,11 +4550,16 @@ NAME
        tail - output the last part of files
 
 SYNOPSIS
-       tail [-n NUM] FILE
+       tail [-n NUM | -c NUM] FILE
 
 DESCRIPTION
-       Print the last 10 lines of FILE to standard output.
-       With -n NUM, print the last NUM lines instead.
+       Print the last 10 lines of FILE to standard output. In a pipeline
+       tail reads the output of the previous command.
+
+OPTIONS
+       -n NUM, -NUM   Print the last NUM lines instead; -n +NUM starts at
+                      line NUM
+       -c NUM         Print the last NUM bytes; -c +NUM starts at byte NUM
 
 EXAMPLES
        tail -n 20 /var/log/syslog
@@ -3824,87 +4575,329 @@ NAME
        diff - compare files line by line
 
 SYNOPSIS
-       diff FILE1 FILE2
+       diff [-u] [-q] [--color[=WHEN]] FILE1 FILE2
 
 DESCRIPTION
-       Compare FILE1 and FILE2 line by line.
+       Compare FILE1 and FILE2 line by line. By default each change is shown
+       as a line like 2c2, 5a6,7 or 9d8 followed by the lines removed (<) and
+       added (>).
+
+OPTIONS
+       -u, --unified   Show changes in unified format with three lines of context
+       -q, --brief     Report only whether the files differ
+       --color[=WHEN]  Colour the output; WHEN is always, never or auto
+
+EXIT STATUS
+       0 if the files are the same, 1 if they differ, 2 on trouble.
 "#
                 .into()
             }
 
-            "sort" => {
-                r#"SORT(1)                          User Commands                         SORT(1)
+            "xxd" => {
+                r#"XXD(1)                           User Commands                          XXD(1)
 
 NAME
-       sort - sort lines of text files
+       xxd - make a hex dump or do the reverse
 
 SYNOPSIS
-       sort FILE
+       xxd [-p] [-u] [-c COLS] [-g BYTES] [-l LEN] [-s SEEK] [INFILE]
+       xxd -r [-p] [INFILE [OUTFILE]]
 
 DESCRIPTION
-       Write sorted concatenation of FILE to standard output.
+       Print INFILE as lines of an offset, the bytes in hex and the bytes as
+       text, with . for anything unprintable. Reads the pipe when no INFILE
+       is given. The programs under /bin show up as the ELF images they
+       stand in for.
+
+OPTIONS
+       -c COLS   Bytes per line (default 16, 30 with -p)
+       -g BYTES  Bytes per group of hex (default 2, 0 for no gaps)
+       -l LEN    Stop after LEN bytes
+       -s SEEK   Start at byte SEEK
+       -p        Plain hex with no offsets or text
+       -u        Upper-case hex digits
+       -r        Turn a dump back into bytes, written to OUTFILE if given
+
+EXAMPLES
+       xxd -l 64 /bin/ls
+       xxd notes.txt > notes.hex; xxd -r notes.hex notes.txt
 "#
                 .into()
             }
 
-            "uniq" => {
-                r#"UNIQ(1)                          User Commands                         UNIQ(1)
+            "hexdump" | "hd" => {
+                r#"HEXDUMP(1)                       User Commands                      HEXDUMP(1)
 
 NAME
-       uniq - report or omit repeated lines
+       hexdump, hd - display file contents in hexadecimal
 
 SYNOPSIS
-       uniq FILE
+       hexdump [-C] [-n LEN] [-s SKIP] [FILE]...
+       hd [-n LEN] [-s SKIP] [FILE]...
 
 DESCRIPTION
-       Filter adjacent matching lines from FILE.
+       Print the files one after another as 16-bit words in hex. A run of
+       identical lines is shown once, followed by a line with *. hd is
+       hexdump -C.
+
+OPTIONS
+       -C        Canonical hex+ASCII display: bytes in hex and the text
+                 between | characters
+       -n LEN    Dump only LEN bytes
+       -s SKIP   Skip SKIP bytes from the start
 "#
                 .into()
             }
 
-            "which" => {
-                r#"WHICH(1)                         User Commands                        WHICH(1)
+            "strings" => {
+                r#"STRINGS(1)                       User Commands                      STRINGS(1)
 
 NAME
-       which - locate a command
+       strings - print the printable text in files
 
 SYNOPSIS
-       which COMMAND
+       strings [-a] [-n MIN] FILE...
 
 DESCRIPTION
-       which returns the pathnames of the files that would be executed in the
-       current environment if COMMAND was run.
+       Print each run of at least MIN (default 4) printable characters,
+       one per line. Useful on binaries and downloaded files.
+
+OPTIONS
+       -a        Scan the whole file (always the case here)
+       -n MIN    Shortest run to print
 "#
                 .into()
             }
 
-            "whereis" => {
-                r#"WHEREIS(1)                       User Commands                      WHEREIS(1)
+            "base64" => {
+                r#"BASE64(1)                        User Commands                       BASE64(1)
 
 NAME
-       whereis - locate the binary, source, and manual page files for a command
+       base64 - base64 encode/decode data
 
 SYNOPSIS
-       whereis COMMAND
+       base64 [-d] [-i] [-w COLS] [FILE]
 
 DESCRIPTION
-       whereis locates the binary, source and manual files for the specified
-       command names.
+       Encode FILE, or the pipe, as base64 text wrapped at 76 columns.
+
+OPTIONS
+       -d, --decode          Decode base64 text back into the original bytes
+       -i, --ignore-garbage  When decoding, skip characters outside the alphabet
+       -w, --wrap COLS       Wrap encoded lines after COLS characters; 0 for none
+
+EXAMPLES
+       base64 photo.png > photo.b64
+       base64 -d photo.b64 > photo.png
 "#
                 .into()
             }
 
-            "file" => {
-                r#"FILE(1)                          User Commands                         FILE(1)
+            "md5sum" | "sha256sum" => {
+                r#"SHA256SUM(1)                     User Commands                    SHA256SUM(1)
 
 NAME
-       file - determine file type
+       md5sum, sha256sum - compute and check message digests
 
 SYNOPSIS
-       file FILE
+       sha256sum [-c] FILE...
+       md5sum [-c] FILE...
 
 DESCRIPTION
-       file tests each argument in an attempt to classify it by examining
+       Print the SHA-256 (or MD5) sum of each FILE followed by its name. With
+       no FILE the pipe is read and shown as -.
+
+OPTIONS
+       -c, --check   Read sums from the FILEs and check them, printing OK or
+                     FAILED for each listed file
+
+EXIT STATUS
+       0 if every sum was computed or matched, 1 otherwise.
+
+EXAMPLES
+       sha256sum release.tar.gz > SHA256SUMS
+       sha256sum -c SHA256SUMS
+"#
+                .into()
+            }
+
+            "seq" => {
+                r#"SEQ(1)                           User Commands                          SEQ(1)
+
+NAME
+       seq - print a sequence of numbers
+
+SYNOPSIS
+       seq [-w] [-s SEP] [FIRST [INCREMENT]] LAST
+
+DESCRIPTION
+       Print the numbers from FIRST (default 1) to LAST, INCREMENT (default 1)
+       apart. Decimals are shown to the places FIRST and INCREMENT are given
+       with. At most 100000 numbers are printed.
+
+OPTIONS
+       -s SEP    Separate the numbers with SEP instead of newlines
+       -w        Pad with leading zeros to equal width
+
+EXAMPLES
+       seq 10 -2 0
+       for i in $(seq 3); do echo $i; done
+"#
+                .into()
+            }
+
+            "yes" => {
+                r#"YES(1)                           User Commands                          YES(1)
+
+NAME
+       yes - output a string repeatedly
+
+SYNOPSIS
+       yes [STRING]...
+
+DESCRIPTION
+       Print y, or the STRINGs joined by spaces, once per line. Stops after
+       10000 lines so the terminal stays responsive.
+
+EXAMPLES
+       yes | head -n 3
+"#
+                .into()
+            }
+
+            "shuf" => {
+                r#"SHUF(1)                          User Commands                         SHUF(1)
+
+NAME
+       shuf - generate random permutations
+
+SYNOPSIS
+       shuf [-r] [-n COUNT] [FILE]
+       shuf -i LO-HI [-n COUNT]
+       shuf -e ARG...
+
+DESCRIPTION
+       Print the lines of FILE, or of the pipe, in random order.
+
+OPTIONS
+       -e ARG...    Shuffle the arguments instead of lines
+       -i LO-HI     Shuffle the numbers LO to HI
+       -n COUNT     Print at most COUNT lines
+       -r           Pick lines with repetition (COUNT of them, at most 10000)
+
+EXAMPLES
+       shuf -i 1-6 -n 1
+"#
+                .into()
+            }
+
+            "xargs" => {
+                r#"XARGS(1)                         User Commands                        XARGS(1)
+
+NAME
+       xargs - build and run commands from piped input
+
+SYNOPSIS
+       COMMAND | xargs [-0] [-r] [-t] [-n MAX] [-I REPLACE] [COMMAND [ARG]...]
+
+DESCRIPTION
+       Read blank-separated items from the pipe and run COMMAND (echo if none
+       is given) with the ARGs followed by the items. The command is looked
+       up directly, so items are never treated as shell syntax. At most 1000
+       commands are run.
+
+OPTIONS
+       -n MAX       Use at most MAX items per command
+       -I REPLACE   Run COMMAND once per input line, with REPLACE in the ARGs
+                    standing for the line
+       -0           Items are separated by NUL characters instead of blanks
+       -r           Do not run COMMAND at all if there are no items
+       -t           Print each command before running it
+       -a FILE      Read the items from FILE instead of the pipe
+
+EXIT STATUS
+       0 on success, 123 if any command failed, 127 if COMMAND was not found.
+
+EXAMPLES
+       find . -name *.txt | xargs grep foo
+       ls | xargs -I {} cp {} /tmp/backup
+"#
+                .into()
+            }
+
+            "sort" => {
+                r#"SORT(1)                          User Commands                         SORT(1)
+
+NAME
+       sort - sort lines of text files
+
+SYNOPSIS
+       sort FILE
+
+DESCRIPTION
+       Write sorted concatenation of FILE to standard output.
+"#
+                .into()
+            }
+
+            "uniq" => {
+                r#"UNIQ(1)                          User Commands                         UNIQ(1)
+
+NAME
+       uniq - report or omit repeated lines
+
+SYNOPSIS
+       uniq FILE
+
+DESCRIPTION
+       Filter adjacent matching lines from FILE.
+"#
+                .into()
+            }
+
+            "which" => {
+                r#"WHICH(1)                         User Commands                        WHICH(1)
+
+NAME
+       which - locate a command
+
+SYNOPSIS
+       which COMMAND
+
+DESCRIPTION
+       which returns the pathnames of the files that would be executed in the
+       current environment if COMMAND was run.
+"#
+                .into()
+            }
+
+            "whereis" => {
+                r#"WHEREIS(1)                       User Commands                      WHEREIS(1)
+
+NAME
+       whereis - locate the binary, source, and manual page files for a command
+
+SYNOPSIS
+       whereis COMMAND
+
+DESCRIPTION
+       whereis locates the binary, source and manual files for the specified
+       command names.
+"#
+                .into()
+            }
+
+            "file" => {
+                r#"FILE(1)                          User Commands                         FILE(1)
+
+NAME
+       file - determine file type
+
+SYNOPSIS
+       file FILE
+
+DESCRIPTION
+       file tests each argument in an attempt to classify it by examining
        file type, permissions, and contents.
 "#
                 .into()
@@ -3917,13 +4910,20 @@ NAME
        cp - copy files and directories
 
 SYNOPSIS
-       cp SOURCE DEST
+       cp [-rpav] SOURCE... DEST
 
 DESCRIPTION
-       Copy SOURCE to DEST.
+       Copy SOURCE to DEST, or each SOURCE into the directory DEST.
+       Copies belong to the user making them.
 
-NOTE
-       Directory copying (-r) not yet implemented.
+OPTIONS
+       -r, -R   Copy directories recursively
+       -p       Keep the owner and group of the originals
+       -a       Archive: same as -rp
+       -v       Print each copy as it is made
+
+SEE ALSO
+       mv(1), rsync(1)
 "#
                 .into()
             }
@@ -3935,10 +4935,43 @@ NAME
        mv - move (rename) files
 
 SYNOPSIS
-       mv SOURCE DEST
+       mv [-v] SOURCE... DEST
+
+DESCRIPTION
+       Rename SOURCE to DEST, or move each SOURCE into the directory DEST.
+       Directories move with everything in them. A directory cannot be moved
+       into itself.
+
+OPTIONS
+       -v       Print each move
+"#
+                .into()
+            }
+
+            "rsync" => {
+                r#"RSYNC(1)                         User Commands                        RSYNC(1)
+
+NAME
+       rsync - fast file copying tool (local only)
+
+SYNOPSIS
+       rsync [-arv] SOURCE[/] DEST
 
 DESCRIPTION
-       Rename SOURCE to DEST, or move SOURCE to DEST.
+       rsync copies SOURCE to DEST, skipping files whose size and content
+       already match, so running it again only sends what changed.
+
+       A trailing slash on SOURCE copies the contents of the directory into
+       DEST; without one the directory itself is created inside DEST.
+
+OPTIONS
+       -r       Recurse into directories
+       -a       Archive: recurse and keep owners (root only; other
+                users own their copies)
+       -v       List the files sent and a transfer summary
+
+EXAMPLES
+       rsync -av ~/project/ /tmp/backup
 "#
                 .into()
             }
@@ -3980,6 +5013,63 @@ DESCRIPTION
                 .into()
             }
 
+            "chgrp" => {
+                r#"CHGRP(1)                         User Commands                        CHGRP(1)
+
+NAME
+       chgrp - change group ownership
+
+SYNOPSIS
+       chgrp [-R] GROUP FILE...
+
+DESCRIPTION
+       chgrp sets the group of each FILE to GROUP. Unprivileged users may
+       only change files they own, and only to a group they belong to.
+
+OPTIONS
+       -R     operate on files and directories recursively
+"#
+                .into()
+            }
+
+            "groupadd" => {
+                r#"GROUPADD(8)                  System Management Commands                GROUPADD(8)
+
+NAME
+       groupadd - create a new group
+
+SYNOPSIS
+       groupadd [-g GID] GROUP
+
+DESCRIPTION
+       groupadd appends a new entry to /etc/group. Requires root.
+
+OPTIONS
+       -g GID  use GID instead of the next free ID (1000+)
+"#
+                .into()
+            }
+
+            "usermod" => {
+                r#"USERMOD(8)                   System Management Commands                 USERMOD(8)
+
+NAME
+       usermod - modify a user account
+
+SYNOPSIS
+       usermod [-a] -G GROUP[,GROUP...] USER
+
+DESCRIPTION
+       usermod -G sets the supplementary groups of USER in /etc/group.
+       Group membership is checked against the group permission bits of
+       files and directories. Requires root.
+
+OPTIONS
+       -a, -aG  add USER to the listed groups without removing others
+"#
+                .into()
+            }
+
             "id" => {
                 r#"ID(1)                            User Commands                           ID(1)
 
@@ -4017,348 +5107,1765 @@ NAME
     who - show who is logged on
 
 SYNOPSIS
-    who
+    who [-b|-H|-q|-m|am i]
 
 DESCRIPTION
-    Display current interactive login information for this shell session.
+    One line per login session: user, terminal line and login time. The
+    console login is on tty1, `su` shells share the line they were started
+    on, and every tmux pane is a pts line of its own.
+
+OPTIONS
+    -b     Time of the last system boot
+    -H     Print a heading
+    -q     Only the login names and how many there are
+    -m, am i
+           Only the session typed into
 "#
                 .into()
             }
 
-            "stat" => {
-                r#"STAT(1)                          User Commands                         STAT(1)
+            "w" => {
+                r#"W(1)                             User Commands                            W(1)
 
 NAME
-    stat - display file or file system status
+    w - show who is logged on and what they are doing
 
 SYNOPSIS
-    stat FILE
+    w [-h] [USER]
 
 DESCRIPTION
-    Display metadata including size, mode, owner, and group.
+    The `uptime` line, then per session: user, terminal, login time, how
+    long since anything was typed into it and what it is running. A shell
+    waiting at its prompt shows as -bash.
+
+OPTIONS
+    -h     Leave out the header
+    USER   Only the sessions of USER
+
+SEE ALSO
+    who(1), uptime(1)
 "#
                 .into()
             }
 
-            "mount" => {
-                r#"MOUNT(8)                     System Administration                    MOUNT(8)
+            "uptime" => {
+                r#"UPTIME(1)                        User Commands                       UPTIME(1)
 
 NAME
-    mount - mount a filesystem
+    uptime - tell how long the system has been running
 
 SYNOPSIS
-    mount
-    mount [-t TYPE] SOURCE TARGET
+    uptime [-p|-s]
 
 DESCRIPTION
-    Without arguments, show mounted filesystems from /proc/mounts.
-    With arguments, add a mount entry for SOURCE on TARGET.
+    The current time, how long the system has been up, how many users are
+    logged in and the load averages for the past 1, 5 and 15 minutes.
+
+OPTIONS
+    -p     Only the uptime, in words
+    -s     When the system came up, as YYYY-MM-DD HH:MM:SS
 "#
                 .into()
             }
 
-            "umount" => {
-                r#"UMOUNT(8)                    System Administration                   UMOUNT(8)
+            "last" => {
+                r#"LAST(1)                          User Commands                         LAST(1)
 
 NAME
-    umount - unmount filesystems
+    last - show a listing of last logged in users
 
 SYNOPSIS
-    umount TARGET
+    last [-n N] [USER|TTY]...
 
 DESCRIPTION
-    Remove TARGET from the active /proc/mounts table.
+    Reads /var/log/wtmp and lists logins, newest first, with when they
+    ended and how long they lasted. Sessions still open are "still logged
+    in"; those the system went down under end in "crash". Boots show as
+    user reboot on the pseudo line "system boot".
+
+OPTIONS
+    -n N, -N
+           Show only the last N entries
+    USER|TTY
+           Only entries for these users or terminal lines
+
+FILES
+    /var/log/wtmp
 "#
                 .into()
             }
 
-            "df" => {
-                r#"DF(1)                            User Commands                           DF(1)
+            "su" => {
+                r#"SU(1)                            User Commands                           SU(1)
 
 NAME
-       df - report file system disk space usage
+    su - run a shell with another user ID
+
+SYNOPSIS
+    su [-] [USER]
+
+DESCRIPTION
+    Starts a shell as USER, root by default, on the same terminal. Anyone
+    but root is asked for their password first. `exit` returns to the shell
+    su was typed in. The new shell is a session of its own for who, w and
+    last.
+
+OPTIONS
+    -, -l, --login
+           Start in the user's home directory
+
+EXAMPLES
+    su -
+    sudo su
+"#
+                .into()
+            }
+
+            "stat" => {
+                r#"STAT(1)                          User Commands                         STAT(1)
+
+NAME
+    stat - display file or file system status
 
 SYNOPSIS
-       df
+    stat FILE
 
 DESCRIPTION
-       df displays the amount of disk space available on the file system.
+    Display metadata including size, mode, owner, group, and the access,
+    modify, change, and birth times (UTC).
 "#
                 .into()
             }
 
-            "du" => {
-                r#"DU(1)                            User Commands                           DU(1)
+            "mount" => {
+                r#"MOUNT(8)                     System Administration                    MOUNT(8)
 
 NAME
-       du - estimate file space usage
+    mount - mount a filesystem
 
 SYNOPSIS
-       du [PATH]
+    mount
+    mount [-t TYPE] [-o OPTIONS] SOURCE TARGET
 
 DESCRIPTION
-       Summarize disk usage of PATH (or current directory).
+    Without arguments, list the mount table (also in /proc/mounts).
+    With arguments, mount SOURCE over the directory TARGET, hiding its
+    previous contents until it is unmounted. Only root may mount.
+
+    -t tmpfs
+           Mount an empty in-memory filesystem. Its contents are never
+           persisted. Use -o size=64M to set the size reported by df.
+
+    -o loop
+           Mount a disk image formatted by mkfs.ext4 through a free loop
+           device, which umount detaches again, or a tar or zip archive
+           read-only. Images and archives are detected automatically when
+           no -t is given. A loop device set up by losetup mounts as
+           SOURCE too. What is written to a mounted image reaches the
+           image file when it is unmounted.
+
+    -o ro, -r
+           Mount read-only.
+
+EXAMPLES
+    mount -t tmpfs tmpfs /mnt
+    mount -o loop backup.tar /media
+    mount -o loop disk.img /mnt
 "#
                 .into()
             }
 
-            "apt" | "apt-get" => {
-                r#"APT(8)                      Package Management                         APT(8)
+            "fsck" | "e2fsck" | "fsck.ext4" => {
+                r#"FSCK(8)                      System Administration                     FSCK(8)
 
 NAME
-       apt - command-line interface for package management
+    fsck - check and repair the root filesystem
 
 SYNOPSIS
-       apt [install|remove|update|upgrade|search] [PACKAGE]
+    fsck [-n|-y|-p] [-f] [DEVICE]
 
 DESCRIPTION
-       apt provides a high-level interface for package management.
+    Check /dev/sda1, the only DEVICE there is, in e2fsck's five passes:
+    file sizes, directory entries for the system binaries, connectivity,
+    reference counts and group summaries. Needs root. Without -f a
+    filesystem with nothing wrong is only summed up.
+
+    Files deleted while open are cleared from the orphan list. Files
+    whose directory entry was lost are connected to /lost+found under
+    their inode number, as #INODE.
+
+    -n     report problems without fixing them (the default)
+    -y, -p fix everything found
+    -f     run every pass even when the filesystem looks clean
+
+POWER LOSS
+    Every save marks the filesystem dirty until the image is written.
+    Closing the tab before that leaves it dirty, and the next boot runs
+    the check itself: files written last may come back with stale sizes,
+    now and then one ends up in /lost+found, and files that were deleted
+    while open leave orphaned inodes behind.
+
+EXIT STATUS
+    0 clean, 1 errors corrected, 4 errors left uncorrected, 8 operational
+    error, 16 usage error.
+
+EXAMPLES
+    sudo fsck -f /dev/sda1
+    sudo fsck -y
+"#
+                .into()
+            }
+
+            "dd" => {
+                r#"DD(1)                            User Commands                           DD(1)
+
+NAME
+       dd - convert and copy a file
+
+SYNOPSIS
+       dd if=FILE [of=FILE] [bs=BYTES] [count=N] [skip=N] [status=none]
+
+DESCRIPTION
+       Copy COUNT blocks of BS bytes (512 by default) from if= to of=, or
+       to standard output, skipping SKIP blocks of the input first. With
+       no if= in a pipeline, dd reads the pipe. The transfer summary goes
+       to standard error unless status=none.
+
+       BYTES and N take a suffix: c (1), w (2), b (512), K, M, G (powers
+       of 1024) or kB, MB, GB (powers of 1000).
+
+       /dev/zero writes a disk image: a file of that size that stores
+       nothing until mkfs.ext4 formats it. /dev/urandom copies up to
+       1 MiB of random bytes. Copying a whole image copies its
+       filesystem; copying part of one gives a blank image.
+
+EXAMPLES
+       dd if=/dev/zero of=disk.img bs=1M count=8
+       dd if=disk.img of=backup.img bs=4M
+       dd if=/dev/urandom of=key.bin bs=32 count=1
+"#
+                .into()
+            }
+
+            "mkfs" | "mkfs.ext4" | "mkfs.ext3" | "mkfs.ext2" | "mke2fs" => {
+                r#"MKFS.EXT4(8)                 System Administration                MKFS.EXT4(8)
+
+NAME
+       mkfs.ext4 - create an ext2/ext3/ext4 filesystem
+
+SYNOPSIS
+       mkfs.ext4 [-F] [-q] [-L LABEL] DEVICE
+       mkfs [-t ext2|ext3|ext4] [-F] [-q] [-L LABEL] DEVICE
+
+DESCRIPTION
+       Write an empty filesystem, with only lost+found in it, to DEVICE:
+       an image file made by dd or a loop device attached by losetup. The
+       root directory belongs to whoever runs mkfs. DEVICE has to be at
+       least 64 KiB and must not be mounted.
+
+       -F     overwrite a filesystem or other data already on DEVICE
+       -q     print nothing
+       -L LABEL
+              set the volume label
+       -t TYPE
+              ext2 (no journal), ext3 or ext4, for mkfs
+
+EXAMPLES
+       dd if=/dev/zero of=disk.img bs=1M count=8
+       mkfs.ext4 -L scratch disk.img
+       mount -o loop disk.img /mnt
+"#
+                .into()
+            }
+
+            "losetup" => {
+                r#"LOSETUP(8)                   System Administration                  LOSETUP(8)
+
+NAME
+       losetup - set up and control loop devices
+
+SYNOPSIS
+       losetup [-a|-l]
+       losetup -f [--show] [FILE]
+       losetup DEVICE FILE
+       losetup -d DEVICE...
+       losetup -D
+
+DESCRIPTION
+       A loop device, /dev/loop0 to /dev/loop7, makes a file look like a
+       disk, so mkfs.ext4 and mount can use it.
+
+       -l, --list   list attached devices as a table (also with no options)
+       -a, --all    list attached devices, one line each
+       -f, --find   print the first unused device, or attach FILE to it
+       --show       print the device FILE was attached to
+       -d, --detach DEVICE...
+                    detach devices; a mounted one is busy
+       -D, --detach-all
+                    detach every device that is not mounted
+
+       Devices that mount -o loop attached are marked AUTOCLEAR and go
+       away when the filesystem is unmounted.
+
+EXAMPLES
+       losetup -f --show disk.img
+       mount /dev/loop0 /mnt
+"#
+                .into()
+            }
+
+            "umount" => {
+                r#"UMOUNT(8)                    System Administration                   UMOUNT(8)
+
+NAME
+    umount - unmount filesystems
+
+SYNOPSIS
+    umount TARGET
+
+DESCRIPTION
+    Detach the filesystem mounted on TARGET (or mounted from the source
+    TARGET) and restore the directory it covered. Fails with "target is
+    busy" while the working directory is inside the mount. Only root may
+    unmount.
+"#
+                .into()
+            }
+
+            "df" => {
+                r#"DF(1)                            User Commands                           DF(1)
+
+NAME
+       df - report file system disk space usage
+
+SYNOPSIS
+       df [-h]
+
+DESCRIPTION
+       df displays the amount of disk space available on the file system
+       and on each mounted filesystem.
+
+       -h, --human-readable
+              print sizes like 1.5K and 234M instead of 1K blocks
+"#
+                .into()
+            }
+
+            "du" => {
+                r#"DU(1)                            User Commands                           DU(1)
+
+NAME
+       du - estimate file space usage
+
+SYNOPSIS
+       du [-ahs] [-d N | --max-depth=N] [PATH]...
+
+DESCRIPTION
+       Summarize disk usage of each PATH (or the current directory),
+       recursively for directories. Every subdirectory gets its own line,
+       printed before the directory that holds it. Sizes are in 1K blocks.
+
+       -a, --all
+              list files as well as directories
+       -h, --human-readable
+              print sizes like 1.5K and 234M
+       -s, --summarize
+              print only a total for each PATH
+       -d N, --max-depth=N
+              print a line only for directories at most N levels deep
+
+EXAMPLES
+       du -sh ~
+       du -h --max-depth=1 /var
+"#
+                .into()
+            }
+
+            "apt" | "apt-get" => {
+                r#"APT(8)                      Package Management                         APT(8)
+
+NAME
+       apt - command-line interface for package management
+
+SYNOPSIS
+       apt COMMAND [PACKAGE...]
+
+DESCRIPTION
+       apt provides a high-level interface for package management.
+       Installed packages are recorded in /var/lib/dpkg/status and the
+       files each one unpacked in /var/lib/dpkg/info/PACKAGE.list.
+       Executables a package unpacks become shell commands until the
+       package is removed.
+
+COMMANDS
+       update               Update package list
+       upgrade              Upgrade all packages
+       install PACKAGE...   Install packages and their dependencies
+       remove PACKAGE...    Remove packages and anything depending on them
+       purge PACKAGE...     Same as remove
+       search QUERY         Search names and descriptions
+       list [--installed|--upgradable]
+                            List packages
+       show PACKAGE         Show package details
+
+NOTE
+    install, remove, update and upgrade require root (use sudo).
+"#
+                .into()
+            }
+
+            "dpkg" => {
+                r#"DPKG(1)                        dpkg suite                         DPKG(1)
+
+NAME
+       dpkg - package manager for Debian-style archives
+
+SYNOPSIS
+       dpkg ACTION [ARGUMENT...]
+
+ACTIONS
+       -i, --install ARCHIVE.deb...   Unpack and configure packages (root)
+       -r, --remove PACKAGE...        Remove packages (root)
+       -P, --purge PACKAGE...         Same as --remove
+       -l, --list [PATTERN]           List installed packages
+       -L, --listfiles PACKAGE        Files installed by PACKAGE
+       -s, --status PACKAGE           Status stanza of PACKAGE
+       -S, --search PATTERN           Which package owns a file
+
+DESCRIPTION
+       Files unpacked into /bin, /usr/bin, /usr/sbin or /usr/games become
+       commands. Text files are run as shell scripts with $1..$9, $@ and
+       $# bound to the arguments.
+
+SEE ALSO
+       dpkg-deb(1), apt(8)
+"#
+                .into()
+            }
+
+            "dpkg-deb" => {
+                r#"DPKG-DEB(1)                    dpkg suite                     DPKG-DEB(1)
+
+NAME
+       dpkg-deb - build and inspect .deb archives
+
+SYNOPSIS
+       dpkg-deb --build DIR [ARCHIVE.deb]
+       dpkg-deb --info ARCHIVE.deb
+       dpkg-deb --contents ARCHIVE.deb
+
+DESCRIPTION
+       --build packs DIR into an archive. DIR/DEBIAN/control must contain
+       at least Package and Version fields; Description, Section and
+       Depends are optional. Every other file under DIR is installed at
+       the same path relative to /.
+
+EXAMPLE
+       mkdir hello
+       mkdir hello/DEBIAN
+       mkdir hello/usr
+       mkdir hello/usr/bin
+       echo Package: hello > hello/DEBIAN/control
+       echo Version: 1.0 >> hello/DEBIAN/control
+       echo echo Hello, $1! > hello/usr/bin/hello
+       chmod 755 hello/usr/bin/hello
+       dpkg-deb --build hello
+       sudo dpkg -i hello.deb
+"#
+                .into()
+            }
+
+            "cowsay" | "cowthink" => {
+                r#"COWSAY(6)                        Games Manual                       COWSAY(6)
+
+NAME
+       cowsay, cowthink - configurable speaking/thinking cow
+
+SYNOPSIS
+       cowsay [-bdgpstwy] [-e EYES] [-T TONGUE] [-f COW] [-W WIDTH] [-l] MESSAGE
+
+DESCRIPTION
+       Prints MESSAGE in a speech balloon above a cow. Reads the message
+       from a pipe when used as `fortune | cowsay`. Provided by the
+       cowsay package (sudo apt install cowsay).
+
+OPTIONS
+       -f COW     use another cow (see -l)
+       -e EYES    two-character eye string
+       -T TONGUE  two-character tongue string
+       -W WIDTH   wrap the balloon at WIDTH columns (default 40)
+       -b -d -g -p -s -t -w -y   borg, dead, greedy, paranoid, stoned,
+                  tired, wired and youthful modes
+"#
+                .into()
+            }
+
+            "figlet" => {
+                r#"FIGLET(6)                        Games Manual                       FIGLET(6)
+
+NAME
+       figlet - display large characters made up of ordinary screen characters
+
+SYNOPSIS
+       figlet [-c] [-f FONT] [-w WIDTH] [-l] MESSAGE
+
+DESCRIPTION
+       Renders MESSAGE in the 5x7 bitmap font shared with the doom HUD.
+       Provided by the figlet package (sudo apt install figlet).
+
+OPTIONS
+       -f FONT    standard, block, shade or dots
+       -w WIDTH   output width in columns (default 80)
+       -c         center each line
+       -l         list fonts
+"#
+                .into()
+            }
+
+            "fortune" => {
+                r#"FORTUNE(6)                       Games Manual                      FORTUNE(6)
+
+NAME
+       fortune - print a random, hopefully interesting, adage
+
+SYNOPSIS
+       fortune [-cfls] [-n LENGTH] [-m PATTERN] [FILE...]
+
+DESCRIPTION
+       Picks a cookie from the files in /usr/share/games/fortunes. Each
+       file holds cookies separated by lines containing a single %. Add
+       your own files there to extend the database. Provided by the
+       fortune-mod package (sudo apt install fortune-mod).
+
+OPTIONS
+       -s, -l     only short / long cookies (split at -n LENGTH, default 160)
+       -m PATTERN print every cookie containing PATTERN
+       -c         show the cookie file the fortune came from
+       -f         list cookie files and their share of cookies
+"#
+                .into()
+            }
+
+            "top" => {
+                r#"TOP(1)                           User Commands                          TOP(1)
+
+NAME
+       top - display Linux processes
+
+SYNOPSIS
+       top
+
+DESCRIPTION
+       The top program provides a dynamic real-time view of a running system.
+       It displays system summary information and a list of processes.
+
+NOTE
+    Press q or Ctrl+C to exit.
+"#
+                .into()
+            }
+
+            "sudo" => {
+                r#"SUDO(8)                     System Administration                     SUDO(8)
+
+NAME
+       sudo - execute a command as another user
+
+SYNOPSIS
+    sudo [-h|-K|-k|-V] [-nS] [-u user] [-p prompt] [-l|-v] [--] command
+
+DESCRIPTION
+       sudo allows permitted users to run commands as the superuser or another user.
+       Password authentication is required. The session is cached for 5 minutes.
+
+OPTIONS
+    -u USER
+        run command as USER (default: root)
+
+    -n
+        non-interactive mode; fail instead of prompting for a password
+
+    -k, -K
+        invalidate cached credentials (timestamp)
+
+    -v
+        validate credentials and refresh sudo timestamp
+
+    -l
+        list effective sudo privileges
+
+    -p PROMPT
+        set custom password prompt (supports %u and %U)
+
+EXAMPLES
+       sudo ls /root
+              List files in root's home directory
+    sudo -u user ls /home/user
+        Run command as a non-root target user
+    sudo -l
+        Show sudo privileges for current user
+       sudo rm -rf /boot/grub
+              DANGER: Delete GRUB bootloader (will break boot!)
+"#
+                .into()
+            }
+
+            "echo" => {
+                r#"ECHO(1)                          User Commands                         ECHO(1)
+
+NAME
+       echo - display a line of text
+
+SYNOPSIS
+       echo [-e] [STRING]...
+
+DESCRIPTION
+       Echo the STRING(s) to standard output.
+
+       -e     interpret \a (bell), \n, \t and \\ in the STRINGs
+
+SPECIAL
+       echo github
+              Opens the kpawnd GitHub page in a new tab
+"#
+                .into()
+            }
+
+            "clear" => {
+                r#"CLEAR(1)                         User Commands                        CLEAR(1)
+
+NAME
+       clear - clear the terminal screen
+
+SYNOPSIS
+       clear [-x]
+
+DESCRIPTION
+       clear clears your screen and the scrollback behind it. Inside tmux
+       only the active pane is cleared.
+
+OPTIONS
+       -x     do not clear the scrollback; what was on screen scrolls up
+              into it instead
+
+       Ctrl+Shift+F searches the scrollback.
+"#
+                .into()
+            }
+
+            "reset" => {
+                r#"RESET(1)                         User Commands                        RESET(1)
+
+NAME
+       reset - terminal initialization
+
+SYNOPSIS
+       reset
+
+DESCRIPTION
+       Puts the terminal back the way it was when first opened: the main
+       screen rather than an alternate one left by a full-screen program,
+       the cursor home and visible, and no scrollback.
+"#
+                .into()
+            }
+
+            "history" => {
+                r#"HISTORY(1)                       User Commands                      HISTORY(1)
+
+NAME
+       history - display command history
+
+SYNOPSIS
+       history [-c] [N]
+
+DESCRIPTION
+       Display the history list with line numbers, or only the last N
+       entries. Use arrow keys to navigate through previous commands and
+       Ctrl+R to search them.
+
+       -c     Clear the history
+
+       History is saved to ~/.bash_history after every command and is kept
+       to the last $HISTSIZE entries (1000 by default).
+
+HISTORY EXPANSION
+       !!         The previous command
+       !N         Command number N
+       !-N        The command N lines back
+       !PREFIX    The most recent command starting with PREFIX
+
+       Expansion does not happen inside single quotes or after a backslash.
+       The expanded line is printed before it runs.
+"#
+                .into()
+            }
+
+            "less" | "more" => {
+                r#"LESS(1)                          User Commands                         LESS(1)
+
+NAME
+            less, more - page through text one screenful at a time
+
+SYNOPSIS
+            less [-F] [+/PATTERN] FILE
+            more FILE
+            COMMAND | less
+
+DESCRIPTION
+            less shows a file, or the output of a pipeline, one screen at a
+            time. The status line shows the file name and line range, or
+            (END) once the last page is reached. more behaves the same but
+            returns to the shell when scrolled past the end, and prints
+            text that fits on one screen without paging.
+
+            man pages longer than a screen open in the pager automatically.
+            When output is redirected or piped onwards, less and more copy
+            the text through unchanged.
+
+OPTIONS
+            -F             print the text directly if it fits on one screen
+            +/PATTERN      start at the first line containing PATTERN
+
+KEYS
+            Space, f, PgDn forward one page
+            b, PgUp        back one page
+            d, u           forward/back half a page
+            Enter, j, Down forward one line
+            k, Up          back one line
+            g, Home        first line
+            G, End         last page
+            /PATTERN       search forward; matches are highlighted
+            n, N           next/previous match
+            q, Esc         quit
+"#
+                .into()
+            }
+
+            "dmesg" => {
+                r#"DMESG(1)                         User Commands                        DMESG(1)
+
+NAME
+            dmesg - print the kernel ring buffer
+
+SYNOPSIS
+            dmesg [-H|--human]
+
+DESCRIPTION
+            Prints the timestamped messages the kernel logged while booting.
+            Firmware and init output shown on the console is not included.
+
+            -H, --human    colour the timestamps
+
+            The log is long; use `dmesg | less` to scroll through it.
+"#
+                .into()
+            }
+
+            "htop" => {
+                r#"HTOP(1)                          User Commands                         HTOP(1)
+
+NAME
+            htop - interactive process viewer
+
+SYNOPSIS
+            htop [-t|--tree|--no-tree] [-b|--batch]
+
+DESCRIPTION
+            htop shows an htop-style process and resource view with:
+            CPU/memory/swap bars, task counters, and a process tree built
+            from each process's parent PID, with a memory bar per process.
+
+            This implementation updates metrics from the simulated kernel and
+            process scheduler on every redraw.
+
+KEYS
+            Up/Down, j/k   move the selection
+            F5, t          toggle tree view and CPU-sorted list
+            F9             send a signal to the selected process
+                           (Enter/15 SIGTERM, 9 SIGKILL, Esc cancel)
+            F10, q, Esc    quit
+
+            -b, --batch    print a single frame and return to the shell
+"#
+                .into()
+            }
+
+            "help" => {
+                r#"HELP(1)                          User Commands                         HELP(1)
+
+        NAME
+            help - show command groups and shell quality-of-life features
+
+        SYNOPSIS
+            help
+
+        DESCRIPTION
+            Displays grouped commands (filesystem, text, process, network, tooling)
+            and built-in shortcuts such as Tab completion and history navigation.
+
+        SEE ALSO
+            man(1), which(1), whereis(1)
+        "#
+                .into()
+            }
+
+            "python" => {
+                r#"PYTHON(1)                        User Commands                       PYTHON(1)
+
+NAME
+       python - interactive Python interpreter
+
+SYNOPSIS
+       python [-c command | -m module | script]
+
+DESCRIPTION
+       Start an interactive Python REPL (Read-Eval-Print Loop).
+       This is a sandboxed Rust-backed Python interpreter.
+
+       Supports def functions and lambdas, if/elif/else, for and
+       while loops, lists, tuples, dicts, slicing, list and dict
+       comprehensions, f-strings and the common builtins.
+
+       Blocks opened with ':' continue at the ... prompt and run
+       after a blank line. Type exit() to exit the interpreter.
+
+OPTIONS
+       -c command
+              Run the given program text and exit.
+
+       -m http.server [port] [-d dir]
+              Serve the current directory over HTTP (default port
+              8000) as a background job. curl and wget requests to
+              localhost are answered by it; kill the job to stop it.
+
+MODULES
+       math      sqrt, pi, e, floor, ceil, log, sin/cos/tan and friends
+       random    random, randint, choice, shuffle, sample, seed
+       json      loads, dumps (indent=, sort_keys=)
+       os        getcwd, listdir, mkdir, remove, path.exists/join/...
+
+       open() reads and writes files in the terminal's filesystem with
+       your permissions, e.g. `with open("notes.txt", "w") as f:`.
+"#
+                .into()
+            }
+
+            "lua" => {
+                r#"LUA(1)                           User Commands                          LUA(1)
+
+NAME
+       lua - Lua interpreter
+
+SYNOPSIS
+       lua [-v] [-e stat | script]
+
+DESCRIPTION
+       Start an interactive Lua REPL, or run a script from the
+       filesystem. This is a sandboxed Rust-backed interpreter for
+       the core of Lua 5.4.
+
+       Supports local and global variables, functions and closures,
+       varargs, tables with array and hash parts, if/while/repeat,
+       numeric and generic for loops, and pcall/error.
+
+       At the > prompt expressions print their values; unfinished
+       statements continue at the >> prompt. Call os.exit() to leave.
+
+OPTIONS
+       -e stat
+              Run the given statements and exit.
+       -v
+              Print version information.
+
+LIBRARIES
+       string    len, sub, upper, lower, rep, reverse, byte, char,
+                 format, find and gsub (plain text patterns)
+       table     insert, remove, concat, sort, unpack
+       math      floor, ceil, sqrt, abs, max, min, random, pi, huge
+       os        time, clock, exit
+"#
+                .into()
+            }
+
+            "sqlite3" => {
+                r#"SQLITE3(1)                       User Commands                      SQLITE3(1)
+
+NAME
+       sqlite3 - A command line interface for SQLite version 3
+
+SYNOPSIS
+       sqlite3 [-version] [database] [SQL]
+
+DESCRIPTION
+       Open a database file and read SQL statements at the sqlite>
+       prompt. Statements run once they end with ';'. Without a file
+       name the database lives in memory and is lost on exit.
+
+       Databases are stored as files in the terminal's filesystem and
+       saved after every change, so they persist across reloads.
+
+       Supported SQL: CREATE TABLE, DROP TABLE, INSERT, UPDATE,
+       DELETE and SELECT with WHERE, GROUP BY, HAVING, ORDER BY and
+       LIMIT, plus count/sum/avg/min/max and common scalar functions.
+
+       If SQL is given on the command line it is run and sqlite3
+       exits.
+
+META-COMMANDS
+       .tables          List names of tables
+       .schema ?TABLE?  Show CREATE statements
+       .headers on|off  Turn display of headers on or off
+       .mode MODE       Output as list, csv or column
+       .dump ?TABLE?    Render database content as SQL
+       .quit            Exit
+"#
+                .into()
+            }
+
+            "git" => {
+                r#"GIT(1)                            Git Manual                            GIT(1)
+
+NAME
+       git - the stupid content tracker
+
+SYNOPSIS
+       git [--version] [--help] <command> [<args>]
+
+DESCRIPTION
+       A version control system for files in the terminal's
+       filesystem. Repositories keep their objects, refs and index
+       under .git/ in the work tree, addressed by SHA-1 like real git.
+
+COMMANDS
+       init [dir]             Create an empty repository
+       clone <url> [dir]      Download a GitHub repository (text files
+                              only) and check out its default branch
+       status                 Show staged, unstaged and untracked files
+       add <path>... | -A     Stage files (and deletions)
+       commit [-a] -m <msg>   Record the staged snapshot
+       log [--oneline] [-n N] Show commit history
+       diff [--cached] [rev [rev]]
+                              Show changes as a unified diff
+       checkout <rev|branch>  Switch commits or branches
+       checkout -b <name>     Create and switch to a new branch
+       checkout -- <path>     Discard unstaged changes to files
+       branch [-d] [name]     List, create or delete branches
+       config <key> [value]   Get or set user.name, user.email, ...
+
+       Revisions may be HEAD, HEAD~N, a branch name or an abbreviated
+       commit hash. Paths listed in .gitignore are skipped.
+"#
+                .into()
+            }
+
+            "httpd" => {
+                r#"HTTPD(8)                  System Administration                 HTTPD(8)
+
+NAME
+       httpd - busybox HTTP server
+
+SYNOPSIS
+       httpd [-f] [-v] [-p [IP:]PORT] [-h HOME]
+
+DESCRIPTION
+       Listen for HTTP requests and serve files from HOME (default
+       the current directory), answering directories with their
+       index.html. The server daemonizes and shows up in ps, netstat
+       and ss; stop it with kill.
+
+       Requests from curl or wget to localhost, 127.0.0.1 or this
+       host's name are served locally instead of over the network.
+       Every request is logged to /var/log/httpd.log.
+
+OPTIONS
+       -p [IP:]PORT   Bind to PORT (default 80, which needs root)
+       -h HOME        Document root
+       -f             Don't daemonize (accepted for compatibility)
+"#
+                .into()
+            }
+
+            "tcpdump" => {
+                r#"TCPDUMP(8)                System Administration               TCPDUMP(8)
+
+NAME
+       tcpdump - dump traffic on a network
+
+SYNOPSIS
+       tcpdump [-Dn] [-c count] [-i interface] [-w file] [expression]
+
+DESCRIPTION
+       Print the packets recorded on an interface since boot: pings,
+       curl/wget/fetch requests, sockets and requests to the local
+       HTTP servers (on lo). Browser traffic is reconstructed from
+       the fetch bridge, so remote addresses are only approximate.
+       Capturing requires root.
+
+OPTIONS
+       -i IFACE   eth0 (default), lo or any
+       -c COUNT   Stop after COUNT packets
+       -n         Don't convert addresses or ports to names
+       -w FILE    Write the packets to FILE in pcapng format
+       -D         List the interfaces available for capture
+
+EXPRESSION
+       Terms are combined with 'and' and may be negated with 'not':
+       host H, src H, dst H, port P, src port P, dst port P,
+       tcp, udp, icmp.
+
+EXAMPLES
+       sudo tcpdump -n icmp
+       sudo tcpdump -i lo port 8000
+       sudo tcpdump -w capture.pcapng host example.com
+"#
+                .into()
+            }
+
+            "traceroute" => {
+                r#"TRACEROUTE(8)                    User Commands                   TRACEROUTE(8)
+
+NAME
+       traceroute - print the route packets trace to network host
+
+SYNOPSIS
+       traceroute [-n] [-m max_ttl] [-q nqueries] host
+
+OPTIONS
+       -n     Print hop addresses numerically
+
+       -m max_ttl
+              Give up after max_ttl hops (default 30)
+
+       -q nqueries
+              Probes per hop (default 3)
+
+NOTE
+    Raw UDP and ICMP sockets are not available in the browser. The routers
+    along the path are simulated, but each hop is timed from a real HTTP
+    request to the host, so latencies follow actual network conditions.
+"#
+                .into()
+            }
+
+            "download" => {
+                r#"DOWNLOAD(1)                      User Commands                     DOWNLOAD(1)
+
+NAME
+       download - save a file or directory on your real computer
+
+SYNOPSIS
+       download [-o NAME] PATH
+
+DESCRIPTION
+       Hands PATH to the browser as a download, so scripts written in nano
+       or python can leave the terminal. A file is saved as it is; a
+       directory is saved as NAME.zip with the directory as its top folder.
+
+       -o NAME   save under NAME instead
+
+       Files dragged onto the terminal go the other way, into ~/Downloads.
+
+EXAMPLES
+       download notes.txt
+       download -o project.zip ~/project
+
+SEE ALSO
+       zip, unzip
+"#
+                .into()
+            }
+
+            "downloads" => {
+                r#"DOWNLOADS(1)                     User Commands                    DOWNLOADS(1)
+
+NAME
+       downloads - list and manage wget/curl transfers
+
+SYNOPSIS
+       downloads [list]
+       downloads resume ID
+       downloads cancel ID
+
+DESCRIPTION
+       Files saved by wget and curl -o/-O are fetched in 1 MiB ranged
+       requests and written as each one arrives. Transfers that stop early,
+       through a network error or a page reload, stay listed here with the
+       bytes received so far and can be continued later.
+
+COMMANDS
+       list   Show each transfer with its state, progress and size
+
+       resume ID
+              Continue a paused transfer from the end of its file
+
+       cancel ID
+              Forget a transfer and delete its partial file
+
+SEE ALSO
+       wget -c, curl -C -
+"#
+                .into()
+            }
+
+            "wscat" => {
+                r#"WSCAT(1)                         User Commands                        WSCAT(1)
+
+NAME
+       wscat - WebSocket client
+
+SYNOPSIS
+       wscat [-x MESSAGE] -c URL
+
+DESCRIPTION
+       Connects to a WebSocket server and opens an interactive session: each
+       line typed is sent as a text message and each message received is
+       printed prefixed with "<". A URL without a scheme gets ws://.
+
+OPTIONS
+       -c, --connect URL
+              Server to connect to (ws:// or wss://)
+
+       -x, --execute MESSAGE
+              Send MESSAGE once connected, print the first reply and exit
+
+COMMANDS
+       /close [CODE [REASON]]
+              Close the connection with status CODE (1000 or 3000-4999)
+
+       CTRL+C Leave immediately without waiting for the server
+
+NOTE
+    The browser answers ping frames and never shows them to the page, so
+    /ping and /pong are not available. Traffic appears in tcpdump.
+"#
+                .into()
+            }
+
+            "doom" => {
+                r#"DOOM(1)                          User Commands                         DOOM(1)
+
+        NAME
+            doom - play a game
+
+        SYNOPSIS
+            doom [easy|normal|hard|ai [easy|normal|hard]]
+            doom record <file> [easy|normal|hard]
+            doom play <file>
+            doom --renderer=gl|soft ...
+
+        DESCRIPTION
+            Launch a simple game rendered onto a canvas.
+            Optional difficulty adjusts monster count, damage, player HP.
+            The AI mode lets the game play itself with an internal bot.
+            Press ESC to exit.
+
+            --renderer=gl draws through WebGL, --renderer=soft through a 2d
+            canvas; the choice sticks for later games (see renderer(1)). If
+            WebGL can't start, the game falls back to soft.
+
+        DIFFICULTY
+            easy    Fewer monsters, lower damage, higher player health
+            normal  Balanced baseline (default)
+            hard    More monsters, higher damage, lower player health
+
+        WEAPONS
+            1  pistol           free, never runs dry
+            2  shotgun          7 pellets in a spread, 2 ammo
+            3  chaingun         spins up while fire is held, 1 ammo
+            4  rocket launcher  splash damage (yours too), 5 ammo
+
+            The chaingun and rocket launcher lie in the corner rooms.
+            Switch with the number keys or the mouse wheel.
+
+        TOUCH
+            On a touch screen, drag on the left half of the game to move and
+            on the right half to turn; tap the right half to fire (or to
+            continue after a level). The ESC button in the corner exits.
+
+        LEVELS
+            Walk into the striped exit switch to finish a level. Two authored
+            maps come first, then procedural ones; score, ammo, weapons and
+            health carry over. A summary is printed when the game closes.
+
+        AI MODE
+            doom ai
+            doom ai easy
+            doom ai normal
+            doom ai hard
+
+            Starts the internal AI controller at the selected difficulty.
+
+        DEMOS
+            doom record <file> plays a normal game and saves every frame's
+            input, along with the difficulty and random seed, to <file> when
+            the game closes. doom play <file> replays it frame for frame;
+            ESC stops the replay early.
+        "#
+                .into()
+            }
+
+            "doommap" => {
+                r#"DOOMMAP(1)                       User Commands                      DOOMMAP(1)
+
+        NAME
+            doommap - control procedural map generation for doom
+
+        SYNOPSIS
+            doommap proc
+            doommap restore
+
+        DESCRIPTION
+            Enables or restores the original static map layout used by the Doom game.
+            'proc' will generate a new procedural layout (rooms/corridors) without
+            permanently destroying the original; 'restore' returns to the original map.
+
+        "#
+                .into()
+            }
+
+            "renderer" => {
+                r#"RENDERER(1)                      User Commands                     RENDERER(1)
+
+        NAME
+            renderer - choose how games draw to the screen
+
+        SYNOPSIS
+            renderer [gl|soft]
+
+        DESCRIPTION
+            With no argument, shows the current renderer. 'gl' uploads each
+            frame to a WebGL2 texture; 'soft' copies it to a 2d canvas, which
+            works everywhere. The change applies when the next game starts.
+            If WebGL isn't available, games fall back to soft and this
+            command says so.
+
+        "#
+                .into()
+            }
+
+            "beep" => {
+                r#"BEEP(1)                          User Commands                         BEEP(1)
+
+        NAME
+            beep - play a tone through the speaker
+
+        SYNOPSIS
+            beep [FREQ] [MS]
+
+        DESCRIPTION
+            Plays a square wave of FREQ Hz (default 440) for MS milliseconds
+            (default 200, at most 5000) on the system channel of the mixer.
+
+            A BEL character (echo -e \a) in command output rings the same
+            tone, as does nano when a search finds nothing. 'volume mute'
+            silences both, along with game sounds and music.
+
+        "#
+                .into()
+            }
+
+            "volume" => {
+                r#"VOLUME(1)                        User Commands                       VOLUME(1)
+
+        NAME
+            volume - show or set the sound volume
+
+        SYNOPSIS
+            volume [N|+N|-N]
+            volume CHANNEL N|+N|-N
+            volume mute [on|off]
+            volume unmute
+
+        DESCRIPTION
+            Sound goes through a mixer with three channels under a master
+            volume: effects (game sounds), music (doom's soundtrack) and
+            system. Levels run from 0 to 100.
+
+            Without arguments, prints every level. A single number sets the
+            master volume; +N and -N nudge it. With a channel name, sets that
+            channel instead. 'volume mute' flips the mute switch, which
+            silences everything, doom included, without losing the levels.
+
+        FILES
+            ~/.config/audio   master=, effects=, music= and system= levels,
+                              mute=on|off
+
+        "#
+                .into()
+            }
+
+            "idle" | "xset" => {
+                r#"IDLE(1)                          User Commands                         IDLE(1)
+
+        NAME
+            idle, xset - configure the idle timer, warning and lock screen
+
+        SYNOPSIS
+            idle [timeout SECS|off] [warn SECS|off] [lock on|off] [now]
+            xset s SECS|off|on|activate|reset
+            xset q
+
+        DESCRIPTION
+            After SECS without a key press, click or touch the screensaver
+            starts (see screensaver(1)). WARN seconds earlier the terminal
+            prints a warning. With lock on, dismissing a screensaver the timer
+            started shows a lock screen that wants your login password before
+            the shell comes back.
+
+            Without arguments idle prints the current settings. 'idle now' and
+            'xset s activate' blank the screen straight away; 'xset s reset'
+            counts as activity. xset only knows the screen saver settings.
+
+        FILES
+            ~/.config/idle   timeout=SECS, warn=SECS and lock=on|off
+
+        "#
+                .into()
+            }
+
+            "memtest" => {
+                r#"MEMTEST(1)                       User Commands                      MEMTEST(1)
+
+NAME
+       memtest - run Memtest86+ over the system's memory
+
+SYNOPSIS
+       memtest
+
+DESCRIPTION
+       Runs the nine Memtest86+ pattern tests over the simulated memory map,
+       printing progress as each test works through memory. Cells damaged by
+       out-of-memory panics or doom sessions show up as errors with their
+       address. The same test is on the GRUB menu as "Memory test".
+
+       Press Esc at any time to stop and return to the shell.
+"#
+                .into()
+            }
+
+            "screensaver" => {
+                r#"SCREENSAVER(1)                   User Commands                  SCREENSAVER(1)
+
+        NAME
+            screensaver - run a screensaver now or pick which ones idle runs
+
+        SYNOPSIS
+            screensaver [matrix|starfield|pipes|life]
+            screensaver matrix [-C COLOR] [-d PERCENT] [-s SPEED]
+            screensaver list
+            screensaver rotate NAME...
+            screensaver style [-C COLOR] [-d PERCENT] [-s SPEED]
+
+        DESCRIPTION
+            With a name, runs that screensaver until ESC. Without one, runs a
+            random screensaver from the rotation, moving on to another every
+            two minutes; this is also what starts after the terminal sits
+            idle. cmatrix is the same as 'screensaver matrix' and takes the
+            same options.
+
+            matrix      digital rain of katakana and digits
+            starfield   flying through stars
+            pipes       3D pipes growing through space
+            life        Conway's Game of Life
+
+        OPTIONS
+            list        show every screensaver; * marks the rotation
+            rotate      set the rotation, saved in ~/.config/screensaver
+            style       show the matrix style, or change and save it
+            -C COLOR    matrix rain color: green, amber or blue
+            -d PERCENT  share of columns raining at once, 5 to 100
+            -s SPEED    fall speed multiplier, 0.25 to 4
+
+            Options after 'matrix' last for that run only.
+
+        FILES
+            ~/.config/screensaver   'savers=NAME ...' picks the rotation;
+                                    matrix_color, matrix_density and
+                                    matrix_speed set the matrix style
+
+        EXAMPLES
+            cmatrix -C amber -s 2
+            screensaver style -C blue -d 40
+
+        "#
+                .into()
+            }
+
+            "pong" => {
+                r#"PONG(1)                          User Commands                         PONG(1)
+
+        NAME
+            pong - two-player table tennis
+
+        SYNOPSIS
+            pong [cpu]
+
+        DESCRIPTION
+            W and S move the left paddle, the up and down arrows the right
+            one. With 'cpu' the computer plays the right paddle. Where the
+            ball meets a paddle sets its angle, and every return is a
+            little faster. First to 11 wins.
+
+            SPACE serves, ESC returns to the terminal.
+
+        "#
+                .into()
+            }
+
+            "snake" => {
+                r#"SNAKE(1)                         User Commands                        SNAKE(1)
+
+        NAME
+            snake - the classic snake game
+
+        SYNOPSIS
+            snake
+            snake scores
+
+        DESCRIPTION
+            Steer the snake with the arrow keys or WASD and eat the red
+            food; each one is worth 10 points. The snake speeds up the longer
+            a round lasts. Hitting a wall or yourself ends the round: SPACE
+            plays again, ESC returns to the terminal.
+
+            Scores are kept in ~/.snake_scores, best ten first;
+            'snake scores' prints the table.
+
+        "#
+                .into()
+            }
+
+            "reboot" | "halt" | "poweroff" => {
+                r#"REBOOT(8)                    System Administration                   REBOOT(8)
+
+NAME
+       reboot, halt, poweroff - restart or stop the machine
+
+SYNOPSIS
+       reboot
+       halt [-p]
+       poweroff
+
+DESCRIPTION
+       Stop every running service (dependents first), end all processes,
+       save the filesystem and bring the kernel down. reboot then starts
+       again from the BIOS; halt stops the machine and poweroff switches it
+       off, and either comes back up on the next key press. Only root may
+       run them.
+
+SEE ALSO
+       shutdown(8), init(8)
+"#
+                .into()
+            }
+
+            "shutdown" => {
+                r#"SHUTDOWN(8)                  System Administration                 SHUTDOWN(8)
+
+NAME
+       shutdown - halt, power off or reboot the machine
+
+SYNOPSIS
+       shutdown [OPTION]... [now|+0] [MESSAGE]
+
+DESCRIPTION
+       -h, -P, --poweroff
+              power the machine off (the default)
+
+       -H, --halt
+              halt the machine
 
-COMMANDS
-       update     Update package list
-       upgrade    Upgrade all packages
-       install    Install package
-       remove     Remove package
-       search     Search for packages
+       -r, --reboot
+              reboot the machine
 
-NOTE
-    Package output is sourced from the built-in package database.
+       Only immediate shutdowns are supported; any other time is refused.
+       Only root may shut the machine down.
+
+SEE ALSO
+       reboot(8), init(8)
 "#
                 .into()
             }
 
-            "top" => {
-                r#"TOP(1)                           User Commands                          TOP(1)
+            "init" | "telinit" | "runlevel" => {
+                r#"INIT(8)                      System Administration                     INIT(8)
 
 NAME
-       top - display Linux processes
+       init, telinit - change runlevel; runlevel - show it
 
 SYNOPSIS
-       top
+       init RUNLEVEL
+       runlevel
 
 DESCRIPTION
-       The top program provides a dynamic real-time view of a running system.
-       It displays system summary information and a list of processes.
-
-NOTE
-    Press q or Ctrl+C to exit.
+       0      power off
+       1, S   single-user rescue mode: every service is stopped
+       2-5    multi-user: services enabled at boot are started again
+       6      reboot
+
+       runlevel prints the previous runlevel (N if there was none) and the
+       current one. A normal boot is runlevel 3; booting with single on the
+       kernel command line is runlevel 1. Only root may change runlevel.
 "#
                 .into()
             }
 
-            "sudo" => {
-                r#"SUDO(8)                     System Administration                     SUDO(8)
+            "mail" => {
+                r#"MAIL(1)                          User Commands                         MAIL(1)
 
 NAME
-       sudo - execute a command as another user
+       mail, mailx - read local mail
 
 SYNOPSIS
-    sudo [-h|-K|-k|-V] [-nS] [-u user] [-p prompt] [-l|-v] [--] command
+       mail [-u USER] [list]
+       mail [-u USER] read N
+       mail [-u USER] delete N...
 
 DESCRIPTION
-       sudo allows permitted users to run commands as the superuser or another user.
-       Password authentication is required. The session is cached for 5 minutes.
-
-OPTIONS
-    -u USER
-        run command as USER (default: root)
-
-    -n
-        non-interactive mode; fail instead of prompting for a password
+       The system leaves messages in /var/mail/USER, an mbox file only its
+       owner can read: a welcome note on your first login, the kernel's
+       report after a panic (to root, read after the reboot), and fsck's
+       findings when a boot follows an unclean shutdown (also to root).
 
-    -k, -K
-        invalidate cached credentials (timestamp)
+       With no command, mail lists the messages, N marking the unread
+       ones. read prints message N and marks it read; delete removes the
+       messages given. The message of the day says "You have new mail."
+       while anything is unread.
 
-    -v
-        validate credentials and refresh sudo timestamp
+       -u USER
+              read USER's mailbox instead; only root may read another's
 
-    -l
-        list effective sudo privileges
+EXIT STATUS
+       1 when there is no mail or a message number is wrong.
 
-    -p PROMPT
-        set custom password prompt (supports %u and %U)
+FILES
+       /var/mail/USER
 
-EXAMPLES
-       sudo ls /root
-              List files in root's home directory
-    sudo -u user ls /home/user
-        Run command as a non-root target user
-    sudo -l
-        Show sudo privileges for current user
-       sudo rm -rf /boot/grub
-              DANGER: Delete GRUB bootloader (will break boot!)
+SEE ALSO
+       motd, fsck
 "#
                 .into()
             }
 
-            "echo" => {
-                r#"ECHO(1)                          User Commands                         ECHO(1)
+            "motd" => {
+                r#"MOTD(1)                          User Commands                         MOTD(1)
 
 NAME
-       echo - display a line of text
+       motd - show the message of the day
 
 SYNOPSIS
-       echo [STRING]...
+       motd [-r]
 
 DESCRIPTION
-       Echo the STRING(s) to standard output.
-
-SPECIAL
-       echo github
-              Opens the kpawnd GitHub page in a new tab
+       /etc/motd is written afresh at every boot and login with the longest
+       uptime so far, a tip about something worth trying, whether there is
+       unread mail, and when you last logged in. motd prints it again; -r
+       writes a new one first.
+
+FILES
+       /etc/motd
+       /var/log/lastlog        last login per user
+       /var/log/uptime.record  longest uptime, in seconds
 "#
                 .into()
             }
 
-            "clear" => {
-                r#"CLEAR(1)                         User Commands                        CLEAR(1)
+            "neofetch" => {
+                r#"NEOFETCH(1)                      User Commands                     NEOFETCH(1)
 
 NAME
-       clear - clear the terminal screen
+       neofetch - show system information beside a logo
 
 SYNOPSIS
-       clear
+       neofetch [--ascii arch|debian|kpawnd] [--off] [--stdout]
 
 DESCRIPTION
-       clear clears your screen if this is possible.
+       Prints the user and host, OS, kernel, uptime, installed packages,
+       shell, window resolution, terminal, CPU and memory in use, read from
+       the running system.
+
+       --ascii NAME  draw the arch, debian or kpawnd logo
+       --off         no logo
+       --stdout      no logo and no colours, as when piped
+
+FILES
+       ~/.config/neofetch/config.conf
+              ascii_distro=NAME picks the logo; hide=uptime,memory,...
+              leaves fields out
 "#
                 .into()
             }
 
-            "history" => {
-                r#"HISTORY(1)                       User Commands                      HISTORY(1)
+            "snapshot" => {
+                r#"SNAPSHOT(8)                  System Administration                 SNAPSHOT(8)
 
 NAME
-       history - display command history
+       snapshot - save and roll back copies of the whole filesystem
 
 SYNOPSIS
-       history
+       snapshot create NAME
+       snapshot list
+       snapshot restore NAME
+       snapshot delete NAME
 
 DESCRIPTION
-       Display the history list with line numbers. Use arrow keys to navigate
-       through previous commands.
+       create  store a compressed copy of every file and directory, with
+               permissions and times, under NAME
+       list    show the snapshots with their age, file count and size
+       restore put the filesystem back exactly as it was in NAME; files
+               made since are gone
+       delete  forget NAME (rm works too)
+
+       Only root may create, restore or delete snapshots.
+       Snapshots are kept with the saved filesystem and survive reloads.
+       After a kernel panic the (initramfs) shell offers snapshot list and
+       snapshot restore as well as fsck.
 "#
                 .into()
             }
 
-            "htop" => {
-                r#"HTOP(1)                          User Commands                         HTOP(1)
+            "tmux" => {
+                r#"TMUX(1)                          User Commands                         TMUX(1)
 
 NAME
-            htop - interactive process viewer
+       tmux - terminal multiplexer
 
 SYNOPSIS
-            htop
+       tmux [new [-d] [-s NAME]]
+       tmux attach [-t NAME] | detach | ls
+       tmux kill-session [-t NAME] | kill-server
+       tmux new-window | split-window [-h|-v] | select-pane | kill-pane
+       tmux select-window -t N | next-window | previous-window
 
 DESCRIPTION
-            htop shows an htop-style process and resource view with:
-            CPU/memory/swap bars, task counters, and sorted process table.
-
-            This implementation updates metrics from the simulated kernel and
-            process scheduler each time htop is invoked.
+       Runs several shells inside the one terminal. A session holds
+       windows, a window is split into panes, and each pane has its own
+       working directory, environment and scrollback (2000 lines).
+
+       new starts a session (named 0, 1, ... unless -s) and attaches to it;
+       -d leaves it running in the background. detach returns to the
+       shell tmux was started from, and attach picks any session back up.
+       exit in the last pane of a session ends it.
+
+KEYS
+       Press Ctrl-b, then:
+       c          new window
+       %          split the pane left and right
+       "          split the pane top and bottom
+       o          next pane
+       n, p       next and previous window
+       0-9        window by number
+       x          close the pane
+       d          detach
 "#
                 .into()
             }
 
-            "help" => {
-                r#"HELP(1)                          User Commands                         HELP(1)
+            "view" => {
+                r#"VIEW(1)                          User Commands                         VIEW(1)
 
         NAME
-            help - show command groups and shell quality-of-life features
+            view - display an image
 
         SYNOPSIS
-            help
+            view FILE
 
         DESCRIPTION
-            Displays grouped commands (filesystem, text, process, network, tooling)
-            and built-in shortcuts such as Tab completion and history navigation.
+            Shows a PNG, BMP or PPM/PGM image from the filesystem on the
+            game canvas, scaled down to fit if it is larger than the screen.
+            Images fetched with wget or written by programs work too.
+
+        KEYS
+            Arrows, WASD, drag    pan
+            + - or wheel          zoom
+            0                     fit to screen
+            1                     actual size
+            ESC                   back to the terminal
 
-        SEE ALSO
-            man(1), which(1), whereis(1)
         "#
                 .into()
             }
 
-            "python" => {
-                r#"PYTHON(1)                        User Commands                       PYTHON(1)
+            "man" => {
+                r#"MAN(1)                           User Commands                          MAN(1)
 
 NAME
-       python - interactive Python interpreter
+       man - an interface to the system reference manuals
 
 SYNOPSIS
-       python
+       man [COMMAND]
 
 DESCRIPTION
-       Start an interactive Python REPL (Read-Eval-Print Loop).
-       This is a sandboxed Rust-backed Python interpreter.
-
-       Type exit() to exit the interpreter.
+       man is the system's manual pager. Each page argument given to man is
+       normally the name of a program, utility or function.
 "#
                 .into()
             }
 
-            "doom" => {
-                r#"DOOM(1)                          User Commands                         DOOM(1)
+            "systemctl" => {
+                r#"SYSTEMCTL(1)                     systemctl                    SYSTEMCTL(1)
 
-        NAME
-            doom - play a game
+NAME
+       systemctl - control the service manager
 
-        SYNOPSIS
-            doom [easy|normal|hard|ai [easy|normal|hard]]
+SYNOPSIS
+       systemctl [--all] COMMAND [UNIT...]
 
-        DESCRIPTION
-            Launch a simple game rendered onto a canvas.
-            Optional difficulty adjusts monster count, damage, player HP.
-            The AI mode lets the game play itself with an internal bot.
-            Press ESC to exit.
+DESCRIPTION
+       Inspect and control the kpawnd service manager. Unit files live in
+       /etc/systemd/system; enabled units are linked from
+       /etc/systemd/system/multi-user.target.wants. Only root may start,
+       stop, restart, enable or disable units or reload the daemon.
 
-        DIFFICULTY
-            easy    Fewer monsters, lower damage, higher player health
-            normal  Balanced baseline (default)
-            hard    More monsters, higher damage, lower player health
+       start, stop, restart UNIT...
+              Starting a unit starts its dependencies first; stopping a unit
+              stops any running units that depend on it.
 
-        AI MODE
-            doom ai
-            doom ai easy
-            doom ai normal
-            doom ai hard
+       status UNIT...
+              Show state, main PID and the most recent journal lines.
 
-            Starts the internal AI controller at the selected difficulty.
-        "#
-                .into()
-            }
+       enable, disable UNIT...
+              Toggle whether the unit starts automatically at boot.
 
-            "doommap" => {
-                r#"DOOMMAP(1)                       User Commands                      DOOMMAP(1)
+       is-active, is-enabled UNIT...
+              Print the unit's state in a script-friendly form.
 
-        NAME
-            doommap - control procedural map generation for doom
+       list-units [--all], list-unit-files
+              List loaded units or installed unit files.
 
-        SYNOPSIS
-            doommap proc
-            doommap restore
+       daemon-reload
+              Re-read unit files, registering any new services.
 
-        DESCRIPTION
-            Enables or restores the original static map layout used by the Doom game.
-            'proc' will generate a new procedural layout (rooms/corridors) without
-            permanently destroying the original; 'restore' returns to the original map.
+EXIT STATUS
+       status exits 4 if any named unit does not exist.
 
-        "#
+SEE ALSO
+       journalctl(1), service(8)
+"#
                 .into()
             }
 
-            "man" => {
-                r#"MAN(1)                           User Commands                          MAN(1)
+            "journalctl" => {
+                r#"JOURNALCTL(1)                   journalctl                   JOURNALCTL(1)
 
 NAME
-       man - an interface to the system reference manuals
+       journalctl - print log entries from the service journal
 
 SYNOPSIS
-       man [COMMAND]
+       journalctl [-n LINES] [-u UNIT | UNIT]
 
 DESCRIPTION
-       man is the system's manual pager. Each page argument given to man is
-       normally the name of a program, utility or function.
+       Without a unit, print the journal of every service in start order.
+
+       -u, --unit UNIT
+              Show only entries for UNIT.
+
+       -n, --lines N
+              Show the most recent N entries.
+
+SEE ALSO
+       systemctl(1)
 "#
                 .into()
             }
@@ -4370,7 +6877,7 @@ NAME
             grub - manage bootloaders and simulate boot sequences
 
 SYNOPSIS
-       grub <switch|status|boot>
+       grub <switch|status|fastboot|boot>
 
 DESCRIPTION
             Manage the system's bootloader configuration and simulate boot processes.
@@ -4385,11 +6892,29 @@ DESCRIPTION
        status
               Display current bootloader and list available bootloaders
 
+       fastboot [on|off]
+              Show or set fast boot (kept in /etc/boot.conf). With it on the
+              boot log is printed at once instead of line by line
+
        boot
               Simulate the boot sequence with visual animation
 
     The boot menu mirrors a classic GRUB layout with a timeout, submenu
     navigation, edit mode, and a command-line prompt.
+    Pressing any key while a boot from the menu is printing skips to the end.
+
+CONFIGURATION
+       The menu is read from /boot/grub/grub.cfg. `set default=N` picks the
+       highlighted entry, `set timeout=N` the countdown in seconds, and each
+       menuentry with a `linux /boot/vmlinuz-VERSION PARAMS` line becomes a
+       boot entry. Edit the file with nano to add entries.
+
+KERNEL PARAMETERS
+       single, s, 1, systemd.unit=rescue.target
+              Single-user mode: services are stopped and not started
+       quiet  Print a short boot log
+       mem=SIZE
+              Limit memory to SIZE (K, M or G suffix), at most 32M
 
 EXAMPLES
        grub status
@@ -4407,36 +6932,48 @@ SEE ALSO
                 .into()
             }
 
-            _ => format!(
-                "No manual entry for {}\n\nTry 'help' to see available commands.",
-                cmd
-            ),
-        }
+            _ => {
+                return CmdOutput::error(
+                    1,
+                    format!(
+                        "No manual entry for {}\n\nTry 'help' to see available commands.",
+                        cmd
+                    ),
+                )
+            }
+        })
     }
 
-    fn cmd_nano(&mut self, args: &[&str]) -> String {
+    fn cmd_nano(&mut self, args: &[&str]) -> CmdOutput {
         let filename = if args.is_empty() { "" } else { args[0] };
         let content = if !filename.is_empty() {
             match self.kernel.fs.resolve(filename) {
                 Some(node) if !node.is_dir => node.data.clone(),
-                Some(_) => return format!("nano: {}: Is a directory", filename),
+                Some(_) => {
+                    return CmdOutput::error(1, format!("nano: {}: Is a directory", filename))
+                }
                 None => String::new(), // New file
             }
         } else {
             String::new()
         };
-        format!("\x1b[NANO:{}:{}]", filename, content.replace('\n', "\\n"))
+        CmdOutput::ok(self.emit(SystemEvent::Nano {
+            path: filename.to_string(),
+            content,
+        }))
     }
 
-    fn cmd_env(&self) -> String {
+    fn cmd_env(&self) -> CmdOutput {
         let mut vars: Vec<_> = self.shell.env.iter().collect();
         vars.sort_by_key(|(k, _)| *k);
-        vars.iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect::<Vec<_>>()
-            .join("\n")
+        CmdOutput::ok(
+            vars.iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
     }
-    fn cmd_export(&mut self, args: &[&str]) -> String {
+    fn cmd_export(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
             return self.cmd_env();
         }
@@ -4445,38 +6982,118 @@ SEE ALSO
                 self.shell.env.insert(k.into(), v.into());
             }
         }
-        String::new()
+        CmdOutput::ok(String::new())
     }
 
-    fn cmd_wget(&self, args: &[&str]) -> String {
+    fn cmd_wget(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: wget [options] <url>\n  -O <file>  write to file\n  -q         quiet mode".to_string();
+            return CmdOutput::usage("usage: wget [options] <url>\n  -O <file>  write to file\n  -c         continue a partial download\n  -q         quiet mode".to_string());
+        }
+        let mut url = "";
+        let mut output = None;
+        let mut quiet = false;
+        let mut resume = false;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-O" | "--output-document" if i + 1 < args.len() => {
+                    output = Some(args[i + 1]);
+                    i += 1;
+                }
+                "-q" | "--quiet" => quiet = true,
+                "-c" | "--continue" => resume = true,
+                s if !s.starts_with('-') => url = s,
+                _ => {}
+            }
+            i += 1;
+        }
+        if url.is_empty() {
+            return CmdOutput::error(1, "wget: missing URL".to_string());
+        }
+        let (host, port) = network::parse_remote_endpoint(url);
+        match self.host_unreachable(&host) {
+            Some(Unreachable::Resolve) => {
+                return CmdOutput::error(
+                    1,
+                    format!("wget: unable to resolve host address '{}'", host),
+                )
+            }
+            Some(Unreachable::Route) => {
+                return CmdOutput::error(
+                    1,
+                    format!(
+                        "Connecting to {}:{}... failed: Network is unreachable.",
+                        host, port
+                    ),
+                )
+            }
+            None => {}
+        }
+        match output {
+            Some("-") => match self.local_http_target(url) {
+                Some((port, path)) => self.local_fetch("wget", port, &path, false),
+                None => CmdOutput::ok(self.emit(SystemEvent::Fetch { url: url.into() })),
+            },
+            Some(file) => self.download_target("wget", quiet, file, url, resume),
+            // With -c the existing file is the one to continue
+            None if resume => {
+                self.download_target("wget", quiet, &Self::remote_file_name(url), url, true)
+            }
+            None => {
+                // Like wget, never clobber: `file`, then `file.1`, `file.2`, ...
+                let name = Self::remote_file_name(url);
+                let free = (0..)
+                    .map(|n| match n {
+                        0 => name.clone(),
+                        n => format!("{}.{}", name, n),
+                    })
+                    .find(|candidate| self.kernel.fs.resolve(candidate).is_none())
+                    .unwrap_or(name);
+                self.download_target("wget", quiet, &free, url, false)
+            }
         }
-        format!("\x1b[FETCH:{}]", args.last().unwrap_or(&""))
     }
 
-    fn cmd_curl(&self, args: &[&str]) -> String {
+    fn cmd_curl(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "curl: try 'curl --help' for more information".to_string();
+            return CmdOutput::usage("curl: try 'curl --help' for more information".to_string());
         }
         let mut url = "";
         let mut method = "GET";
         let mut show_headers = false;
+        let mut output = None;
+        let mut remote_name = false;
+        let mut silent = false;
+        let mut resume = false;
         let mut i = 0;
         while i < args.len() {
             match args[i] {
                 "-I" | "--head" => show_headers = true,
-                "-X" => {
-                    if i + 1 < args.len() {
-                        method = args[i + 1];
-                        i += 1;
-                    }
+                "-X" if i + 1 < args.len() => {
+                    method = args[i + 1];
+                    i += 1;
                 }
                 "-H" | "--header" => i += 1, // Skip header value
                 "-d" | "--data" => i += 1,   // Skip data value
-                "-o" | "-O" => i += 1,       // Skip output file
+                "-o" | "--output" if i + 1 < args.len() => {
+                    output = Some(args[i + 1]);
+                    i += 1;
+                }
+                "-O" | "--remote-name" => remote_name = true,
+                "-s" | "--silent" => silent = true,
+                // Only the automatic offset is supported
+                "-C" | "--continue-at" if i + 1 < args.len() => {
+                    if args[i + 1] != "-" {
+                        return CmdOutput::error(
+                            1,
+                            "curl: (33) only '-C -' is supported".to_string(),
+                        );
+                    }
+                    resume = true;
+                    i += 1;
+                }
                 "--help" => {
-                    return "Usage: curl [options] <url>\n  -I, --head     Show headers only\n  -X <method>    HTTP method\n  -H <header>    Add header\n  -d <data>      POST data\n  -o <file>      Output to file".to_string();
+                    return CmdOutput::usage("Usage: curl [options] <url>\n  -I, --head     Show headers only\n  -X <method>    HTTP method\n  -H <header>    Add header\n  -d <data>      POST data\n  -o <file>      Write output to file\n  -O             Write output to a file named like the remote file\n  -C -           Resume a partial download\n  -s, --silent   Hide the progress meter".to_string());
                 }
                 s if !s.starts_with('-') => url = s,
                 _ => {}
@@ -4484,13 +7101,277 @@ SEE ALSO
             i += 1;
         }
         if url.is_empty() {
-            return "curl: no URL specified".to_string();
+            return CmdOutput::error(1, "curl: no URL specified".to_string());
+        }
+        let (host, port) = network::parse_remote_endpoint(url);
+        match self.host_unreachable(&host) {
+            Some(Unreachable::Resolve) => {
+                return CmdOutput::error(1, format!("curl: (6) Could not resolve host: {}", host))
+            }
+            Some(Unreachable::Route) => {
+                return CmdOutput::error(
+                    1,
+                    format!(
+                "curl: (7) Failed to connect to {} port {} after 0 ms: Couldn't connect to server",
+                host, port
+            ),
+                )
+            }
+            None => {}
+        }
+        if method == "GET" && !show_headers {
+            match output {
+                Some("-") => {}
+                Some(file) => return self.download_target("curl", silent, file, url, resume),
+                None if remote_name => {
+                    let file = Self::remote_file_name(url);
+                    return self.download_target("curl", silent, &file, url, resume);
+                }
+                None => {}
+            }
+        }
+        if let Some((port, path)) = self.local_http_target(url) {
+            return self.local_fetch("curl", port, &path, show_headers);
+        }
+        CmdOutput::ok(self.emit(SystemEvent::Curl {
+            method: method.to_string(),
+            headers: show_headers,
+            url: url.to_string(),
+        }))
+    }
+
+    /// The file name `wget` and `curl -O` save a URL under
+    fn remote_file_name(url: &str) -> String {
+        let path = url.split(['?', '#']).next().unwrap_or("");
+        let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+        match path.split_once('/') {
+            Some((_, rest)) if !rest.is_empty() && !rest.ends_with('/') => {
+                rest.rsplit('/').next().unwrap_or("index.html").to_string()
+            }
+            _ => "index.html".to_string(),
+        }
+    }
+
+    fn screensaver_config_path(&self) -> String {
+        let home = Self::default_home_for_user(&self.current_user());
+        format!("{}/.config/screensaver", home)
+    }
+
+    /// Hand the rotation in ~/.config/screensaver to the idle screensaver
+    fn apply_screensaver_config(&self) {
+        let text = self
+            .kernel
+            .fs
+            .resolve(&self.screensaver_config_path())
+            .map(|n| n.data.clone())
+            .unwrap_or_default();
+        crate::screensaver::set_rotation(crate::screensaver::parse_config(&text));
+        crate::screensaver::set_matrix_style(crate::screensaver::parse_matrix_style(&text));
+    }
+
+    /// Write the rotation and matrix style to ~/.config/screensaver and
+    /// put them into effect
+    fn save_screensaver_config(
+        &mut self,
+        kinds: &[crate::screensaver::ScreensaverKind],
+        style: &crate::graphics::MatrixStyle,
+    ) -> Result<(), String> {
+        let path = self.screensaver_config_path();
+        let dir = path.rsplit_once('/').map_or("/", |(d, _)| d).to_string();
+        self.ensure_dir_all(&dir)?;
+        let config = crate::screensaver::format_config(kinds, style);
+        self.write_file_bytes(&path, config.as_bytes())
+            .map_err(|e| format!("{}: {}", path, e))?;
+        self.apply_screensaver_config();
+        Ok(())
+    }
+
+    /// `screensaver [name]`, `screensaver matrix [-C COLOR] [-d PERCENT]
+    /// [-s SPEED]`, `screensaver list`, `screensaver rotate <names>`,
+    /// `screensaver style [flags]`
+    fn cmd_screensaver(&mut self, args: &[&str]) -> CmdOutput {
+        use crate::screensaver::{self, ScreensaverKind};
+        match args {
+            [] => CmdOutput::ok(self.emit(SystemEvent::LaunchScreensaver { kind: None })),
+            ["list"] => {
+                let rotation = crate::screensaver::rotation();
+                CmdOutput::ok(ScreensaverKind::ALL
+                    .iter()
+                    .map(|k| {
+                        let mark = if rotation.contains(k) { '*' } else { ' ' };
+                        format!("{} {}", mark, k.name())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            ["rotate", names @ ..] if !names.is_empty() => {
+                let mut kinds = Vec::new();
+                for name in names {
+                    match ScreensaverKind::parse(name) {
+                        Some(kind) if !kinds.contains(&kind) => kinds.push(kind),
+                        Some(_) => {}
+                        None => return CmdOutput::error(1, format!("screensaver: unknown screensaver '{}'", name)),
+                    }
+                }
+                match self.save_screensaver_config(&kinds, &screensaver::matrix_style()) {
+                    Ok(()) => CmdOutput::ok(String::new()),
+                    Err(e) => CmdOutput::error(1, format!("screensaver: {}", e)),
+                }
+            }
+            ["style"] => {
+                let style = screensaver::matrix_style();
+                CmdOutput::ok(format!(
+                    "matrix: color {}, density {}%, speed {}",
+                    style.color.name(),
+                    style.density,
+                    style.speed
+                ))
+            }
+            ["style", flags @ ..] => {
+                match screensaver::parse_matrix_flags(screensaver::matrix_style(), flags) {
+                    Ok(style) => match self.save_screensaver_config(&screensaver::rotation(), &style)
+                    {
+                        Ok(()) => CmdOutput::ok(String::new()),
+                        Err(e) => CmdOutput::error(1, format!("screensaver: {}", e)),
+                    },
+                    Err(e) => CmdOutput::error(1, format!("screensaver: {}", e)),
+                }
+            }
+            [name, flags @ ..]
+                if !flags.is_empty() && ScreensaverKind::parse(name) == Some(ScreensaverKind::Matrix) =>
+            {
+                match screensaver::parse_matrix_flags(screensaver::matrix_style(), flags) {
+                    Ok(style) => {
+                        screensaver::set_matrix_style_once(style);
+                        CmdOutput::ok(self.emit(SystemEvent::LaunchScreensaver {
+                            kind: Some("matrix".into()),
+                        }))
+                    }
+                    Err(e) => CmdOutput::error(1, format!("screensaver: {}", e)),
+                }
+            }
+            [name] => match ScreensaverKind::parse(name) {
+                Some(kind) => CmdOutput::ok(self.emit(SystemEvent::LaunchScreensaver {
+                    kind: Some(kind.name().into()),
+                })),
+                None => CmdOutput::error(1, format!(
+                    "screensaver: unknown screensaver '{}' (matrix, starfield, pipes, life)",
+                    name
+                )),
+            },
+            _ => CmdOutput::usage("usage: screensaver [name | matrix [-C COLOR] [-d PERCENT] [-s SPEED] | list | rotate <name>... | style [flags]]".to_string()),
+        }
+    }
+
+    /// Show or pick the renderer doom uses, without rebuilding
+    fn cmd_renderer(&self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] => CmdOutput::ok(format!("renderer: {}", crate::doom::renderer_status())),
+            [name] => match crate::doom::RendererKind::parse(name) {
+                Some(kind) => {
+                    crate::doom::set_renderer(kind);
+                    CmdOutput::ok(format!(
+                        "renderer: {} (takes effect the next time a game starts)",
+                        kind.name()
+                    ))
+                }
+                None => CmdOutput::error(
+                    1,
+                    format!("renderer: unknown renderer '{}' (gl or soft)", name),
+                ),
+            },
+            _ => CmdOutput::usage("usage: renderer [gl|soft]".to_string()),
+        }
+    }
+
+    /// `view <file>`: check the file is an image, then let the frontend
+    /// decode and show it through `start_viewer`
+    fn cmd_view(&mut self, args: &[&str]) -> CmdOutput {
+        let [file] = args else {
+            return CmdOutput::usage("usage: view <image>".to_string());
+        };
+        let path = self.kernel.fs.normalize(file);
+        match self.read_file_bytes(&path) {
+            Ok(bytes) if crate::image::format_name(&bytes).is_some() => {
+                CmdOutput::ok(self.emit(SystemEvent::ViewImage { path }))
+            }
+            Ok(_) => CmdOutput::error(1, format!("view: {}: not a PNG, BMP or PPM image", file)),
+            Err(e) => CmdOutput::error(1, format!("view: {}", e)),
+        }
+    }
+
+    /// `doom record <file> [difficulty]` / `doom play <file>`; the frontend
+    /// starts the game and hands the demo back through `save_doom_demo`
+    fn doom_demo_command(&mut self, action: &str, args: &[&str]) -> CmdOutput {
+        let Some(file) = args.first() else {
+            return CmdOutput::usage(format!("usage: doom {} <file>", action));
+        };
+        let path = self.kernel.fs.normalize(file);
+        if action == "play" {
+            return match self.read_file_bytes(&path) {
+                Ok(bytes) if bytes.starts_with(b"KPDM") => {
+                    CmdOutput::ok(self.emit(SystemEvent::PlayDoomDemo { path }))
+                }
+                Ok(_) => CmdOutput::error(1, format!("doom: {}: not a doom demo", file)),
+                Err(e) => CmdOutput::error(1, format!("doom: {}", e)),
+            };
+        }
+        let diff = match args.get(1).map(|d| d.to_lowercase()).as_deref() {
+            None | Some("normal") | Some("1") => 1u8,
+            Some("easy") | Some("0") => 0,
+            Some("hard") | Some("2") => 2,
+            Some(_) => {
+                return CmdOutput::usage("usage: doom record <file> [easy|normal|hard]".to_string())
+            }
+        };
+        if self.kernel.fs.resolve(&path).is_some_and(|n| n.is_dir) {
+            return CmdOutput::error(1, format!("doom: {}: Is a directory", file));
+        }
+        if !self.can_write_path(&path) {
+            return CmdOutput::error(1, format!("doom: {}: Permission denied", file));
+        }
+        CmdOutput::ok(self.emit(SystemEvent::RecordDoomDemo {
+            difficulty: diff,
+            path,
+        }))
+    }
+
+    /// Check that `file` can be written, then register a transfer the
+    /// frontend fetches in chunks through `download_chunk`
+    fn download_target(
+        &mut self,
+        tool: &str,
+        quiet: bool,
+        file: &str,
+        url: &str,
+        resume: bool,
+    ) -> CmdOutput {
+        let path = self.kernel.fs.normalize(file);
+        if self.kernel.fs.resolve(&path).is_some_and(|n| n.is_dir) {
+            return match tool {
+                "wget" => CmdOutput::error(1, format!("{}: Is a directory", file)),
+                _ => CmdOutput::error(
+                    1,
+                    format!("curl: (23) Failed writing body: {}: Is a directory", file),
+                ),
+            };
+        }
+        if !self.can_write_path(&path) {
+            return match tool {
+                "wget" => CmdOutput::error(1, format!("{}: Permission denied", file)),
+                _ => CmdOutput::error(
+                    1,
+                    format!("curl: (23) Failure writing output to destination: {}", file),
+                ),
+            };
         }
-        // Return escape sequence for real curl request
-        format!("\x1b[CURL:{}:{}:{}]", method, show_headers, url)
+        if let Some((port, route)) = self.local_http_target(url) {
+            return self.local_download(tool, quiet, file, &path, port, &route);
+        }
+        self.start_download(tool, quiet, file, &path, url, resume)
     }
 
-    fn cmd_netstat(&self, args: &[&str]) -> String {
+    fn cmd_netstat(&self, args: &[&str]) -> CmdOutput {
         let show_all = args.contains(&"-a");
         let show_listening = args.contains(&"-l");
         let show_tcp = args.contains(&"-t") || args.is_empty();
@@ -4514,10 +7395,10 @@ SEE ALSO
         }
 
         let _ = (show_numeric, show_tcp, show_udp); // Silence unused warnings
-        out
+        CmdOutput::ok(out)
     }
 
-    fn cmd_ss(&self, args: &[&str]) -> String {
+    fn cmd_ss(&self, args: &[&str]) -> CmdOutput {
         let show_numeric = args.contains(&"-n");
 
         let mut out = String::from(
@@ -4530,186 +7411,71 @@ SEE ALSO
             out.push('\n');
         }
 
-        let _ = show_numeric;
-        out
-    }
-
-    fn cmd_ping(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "usage: ping <host>".to_string();
-        }
-
-        // Get the host (last non-flag argument)
-        let host = args.iter().rfind(|a| !a.starts_with('-')).unwrap_or(&"");
-
-        if host.is_empty() {
-            return "ping: missing host operand".to_string();
-        }
-
-        // Return escape sequence for real ping
-        format!("\x1b[PING:{}]", host)
+        let _ = show_numeric;
+        CmdOutput::ok(out)
     }
 
-    fn cmd_host(&self, args: &[&str]) -> String {
+    fn cmd_ping(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "Usage: host <hostname>".to_string();
+            return CmdOutput::usage("usage: ping <host>".to_string());
         }
 
-        let hostname = args.last().unwrap_or(&"");
+        // Get the host (last non-flag argument)
+        let host = *args.iter().rfind(|a| !a.starts_with('-')).unwrap_or(&"");
 
-        // Return escape sequence for real DNS lookup
-        format!("\x1b[DNS:{}]", hostname)
-    }
+        if host.is_empty() {
+            return CmdOutput::error(1, "ping: missing host operand".to_string());
+        }
+        match self.host_unreachable(host) {
+            Some(Unreachable::Resolve) => {
+                return CmdOutput::error(
+                    1,
+                    format!("ping: {}: Temporary failure in name resolution", host),
+                )
+            }
+            Some(Unreachable::Route) => {
+                return CmdOutput::error(1, "ping: connect: Network is unreachable".to_string())
+            }
+            None => {}
+        }
 
-    fn cmd_myip(&self) -> String {
-        "\x1b[MYIP]".to_string()
+        CmdOutput::ok(self.emit(SystemEvent::Ping { host: host.into() }))
     }
 
-    fn cmd_traceroute(&self, args: &[&str]) -> String {
+    fn cmd_host(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: traceroute <host>".to_string();
-        }
-        let host = args.last().unwrap_or(&"");
-        format!(
-            "traceroute: {}: unsupported in browser sandbox (raw UDP/ICMP sockets unavailable)",
-            host
-        )
-    }
-
-    fn cmd_ifconfig(&self, args: &[&str]) -> String {
-        let interfaces = self.network.get_interfaces();
-        let filter = args.first().copied();
-
-        if interfaces.is_empty() {
-            return "ifconfig: unsupported in browser sandbox (network interface details unavailable)"
-                .to_string();
+            return CmdOutput::usage("Usage: host <hostname>".to_string());
         }
 
-        let mut out = String::new();
-        for iface in &interfaces {
-            if let Some(name) = filter {
-                if iface.name != name {
-                    continue;
-                }
-            }
-
-            let flags = if iface.is_up { "UP" } else { "DOWN" };
-            let loopback = if iface.is_loopback { ",LOOPBACK" } else { "" };
-
-            out.push_str(&format!(
-                "{}: flags=4163<{}{},BROADCAST,RUNNING,MULTICAST>  mtu {}\n",
-                iface.name, flags, loopback, iface.mtu
-            ));
-            out.push_str(&format!(
-                "        inet {}  netmask 255.255.255.0  broadcast 192.168.1.255\n",
-                iface.ipv4
-            ));
-            out.push_str(&format!(
-                "        inet6 {}  prefixlen 64  scopeid 0x20<link>\n",
-                iface.ipv6
-            ));
-            out.push_str(&format!(
-                "        ether {}  txqueuelen 1000  (Ethernet)\n",
-                iface.mac
-            ));
-            out.push_str(&format!(
-                "        RX packets {}  bytes {} ({:.1} KB)\n",
-                iface.rx_packets,
-                iface.rx_bytes,
-                iface.rx_bytes as f64 / 1024.0
-            ));
-            out.push_str(&format!(
-                "        TX packets {}  bytes {} ({:.1} KB)\n\n",
-                iface.tx_packets,
-                iface.tx_bytes,
-                iface.tx_bytes as f64 / 1024.0
-            ));
+        let hostname = *args.last().unwrap_or(&"");
+        if self.host_unreachable(hostname).is_some() {
+            return CmdOutput::error(
+                1,
+                ";; connection timed out; no servers could be reached".to_string(),
+            );
         }
 
-        if out.is_empty() {
-            match filter {
-                Some(name) => format!(
-                    "{}: error fetching interface information: Device not found",
-                    name
-                ),
-                None => out,
-            }
-        } else {
-            out
-        }
+        CmdOutput::ok(self.emit(SystemEvent::Dns {
+            host: hostname.into(),
+        }))
     }
 
-    fn cmd_ip(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: ip [ OPTIONS ] OBJECT { COMMAND }\n       ip addr\n       ip link\n       ip route\n       ip neigh".to_string();
-        }
-
-        match args[0] {
-            "addr" | "a" | "address" => {
-                let interfaces = self.network.get_interfaces();
-                if interfaces.is_empty() {
-                    return "ip: unsupported in browser sandbox (address details unavailable)"
-                        .to_string();
-                }
-                let mut out = String::new();
-                for (i, iface) in interfaces.iter().enumerate() {
-                    let state = if iface.is_up { "UP" } else { "DOWN" };
-                    out.push_str(&format!(
-                        "{}: {}: <BROADCAST,MULTICAST,{}> mtu {} state {}\n",
-                        i + 1,
-                        iface.name,
-                        state,
-                        iface.mtu,
-                        state
-                    ));
-                    out.push_str(&format!(
-                        "    link/ether {} brd ff:ff:ff:ff:ff:ff\n",
-                        iface.mac
-                    ));
-                    out.push_str(&format!(
-                        "    inet {}/24 brd 192.168.1.255 scope global {}\n",
-                        iface.ipv4, iface.name
-                    ));
-                    out.push_str(&format!("    inet6 {}/64 scope link\n", iface.ipv6));
-                }
-                out
-            }
-            "link" | "l" => {
-                let interfaces = self.network.get_interfaces();
-                if interfaces.is_empty() {
-                    return "ip: unsupported in browser sandbox (link details unavailable)"
-                        .to_string();
-                }
-                let mut out = String::new();
-                for (i, iface) in interfaces.iter().enumerate() {
-                    let state = if iface.is_up { "UP" } else { "DOWN" };
-                    out.push_str(&format!(
-                        "{}: {}: <BROADCAST,MULTICAST,{}> mtu {} state {}\n",
-                        i + 1,
-                        iface.name,
-                        state,
-                        iface.mtu,
-                        state
-                    ));
-                    out.push_str(&format!(
-                        "    link/ether {} brd ff:ff:ff:ff:ff:ff\n",
-                        iface.mac
-                    ));
-                }
-                out
-            }
-            "route" | "r" => self.cmd_route(&[]),
-            "neigh" | "neighbor" => self.cmd_arp(&[]),
-            _ => format!("ip: unknown command '{}'", args[0]),
+    fn cmd_myip(&mut self) -> CmdOutput {
+        if self.host_unreachable("api.ipify.org").is_some() {
+            return CmdOutput::error(1, "myip: Network is unreachable".to_string());
         }
+        CmdOutput::ok(self.emit(SystemEvent::MyIp))
     }
 
-    fn cmd_route(&self, args: &[&str]) -> String {
+    fn cmd_route(&self, args: &[&str]) -> CmdOutput {
         if args.first() == Some(&"-n") || args.is_empty() {
             let routes = self.network.get_routes();
             if routes.is_empty() {
-                return "route: unsupported in browser sandbox (kernel routing table unavailable)"
-                    .to_string();
+                return CmdOutput::error(
+                    1,
+                    "route: unsupported in browser sandbox (kernel routing table unavailable)"
+                        .to_string(),
+                );
             }
             let mut out = String::from("Kernel IP routing table\n");
             out.push_str(
@@ -4728,18 +7494,21 @@ SEE ALSO
                     route.iface
                 ));
             }
-            out
+            CmdOutput::ok(out)
         } else {
-            "route: unknown option".to_string()
+            CmdOutput::error(1, "route: unknown option".to_string())
         }
     }
 
-    fn cmd_arp(&self, args: &[&str]) -> String {
+    fn cmd_arp(&self, args: &[&str]) -> CmdOutput {
         let show_numeric = args.contains(&"-n");
         let arp_entries = self.network.arp_table();
 
         if arp_entries.is_empty() {
-            return "arp: unsupported in browser sandbox (ARP cache unavailable)".to_string();
+            return CmdOutput::error(
+                1,
+                "arp: unsupported in browser sandbox (ARP cache unavailable)".to_string(),
+            );
         }
 
         let mut out = String::from(
@@ -4753,12 +7522,12 @@ SEE ALSO
         }
 
         let _ = show_numeric;
-        out
+        CmdOutput::ok(out)
     }
 
-    fn cmd_nc(&self, args: &[&str]) -> String {
+    fn cmd_nc(&self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: nc [-lvnz] hostname port".to_string();
+            return CmdOutput::usage("usage: nc [-lvnz] hostname port".to_string());
         }
 
         let listen_mode = args.contains(&"-l");
@@ -4774,29 +7543,53 @@ SEE ALSO
 
         if listen_mode {
             let port = positional.first().unwrap_or(&"0");
-            format!("Listening on 0.0.0.0 {}", port)
+            CmdOutput::ok(format!("Listening on 0.0.0.0 {}", port))
         } else if positional.len() < 2 {
-            "nc: missing hostname and port".to_string()
+            CmdOutput::error(1, "nc: missing hostname and port".to_string())
         } else {
             let host = positional[0];
             let port = positional[1];
 
+            match self.host_unreachable(host) {
+                Some(Unreachable::Resolve) => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                    "nc: getaddrinfo for host \"{}\" port {}: Temporary failure in name resolution",
+                    host, port
+                ),
+                    )
+                }
+                Some(Unreachable::Route) => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "nc: connect to {} port {} (tcp) failed: Network is unreachable",
+                            host, port
+                        ),
+                    )
+                }
+                None => {}
+            }
             if scan_mode {
-                format!("Connection to {} {} port [tcp/*] succeeded!", host, port)
+                CmdOutput::ok(format!(
+                    "Connection to {} {} port [tcp/*] succeeded!",
+                    host, port
+                ))
             } else if verbose {
-                format!(
+                CmdOutput::ok(format!(
                     "Connection to {} {} port [tcp/*] succeeded!\n[Connected - type to send data]",
                     host, port
-                )
+                ))
             } else {
-                format!("[Connected to {}:{}]", host, port)
+                CmdOutput::ok(format!("[Connected to {}:{}]", host, port))
             }
         }
     }
 
-    fn cmd_socket(&mut self, args: &[&str]) -> String {
+    fn cmd_socket(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: socket <ws|http> <action> [args...]".to_string();
+            return CmdOutput::usage("usage: socket <ws|http> <action> [args...]".to_string());
         }
 
         let protocol = match args[0].to_lowercase().as_str() {
@@ -4804,201 +7597,565 @@ SEE ALSO
             "http" => Protocol::Http,
             "tcp" => Protocol::Tcp,
             "udp" => Protocol::Udp,
-            _ => return format!("socket: unknown protocol '{}'", args[0]),
+            _ => return CmdOutput::error(1, format!("socket: unknown protocol '{}'", args[0])),
         };
 
         if args.len() < 2 {
-            return "usage: socket <proto> <action> [args...]".to_string();
+            return CmdOutput::usage("usage: socket <proto> <action> [args...]".to_string());
         }
 
         match args[1] {
             "create" => {
                 let id = self.network.socket(protocol);
-                format!("Created socket {}", id)
+                self.network.set_owner(id, self.shell_pid());
+                CmdOutput::ok(format!("Created socket {}", id))
             }
             "connect" => {
                 if args.len() < 3 {
-                    return "usage: socket <proto> connect <url>".to_string();
+                    return CmdOutput::usage("usage: socket <proto> connect <url>".to_string());
                 }
                 let id = self.network.socket(protocol);
+                self.network.set_owner(id, self.shell_pid());
                 let url = args[2];
                 match self.network.connect_ws(id, url) {
-                    Ok(()) => format!("Connecting socket {} to {}", id, url),
-                    Err(e) => format!("Error: {}", e),
+                    Ok(()) => CmdOutput::ok(format!("Connecting socket {} to {}", id, url)),
+                    Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                 }
             }
             "send" => {
                 if args.len() < 4 {
-                    return "usage: socket <proto> send <socket_id> <data>".to_string();
+                    return CmdOutput::usage(
+                        "usage: socket <proto> send <socket_id> <data>".to_string(),
+                    );
                 }
                 let id: u32 = args[2].parse().unwrap_or(0);
                 let data = args[3..].join(" ");
                 match self.network.send(id, &data) {
-                    Ok(()) => format!("Sent {} bytes", data.len()),
-                    Err(e) => format!("Error: {}", e),
+                    Ok(()) => CmdOutput::ok(format!("Sent {} bytes", data.len())),
+                    Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                 }
             }
             "close" => {
                 if args.len() < 3 {
-                    return "usage: socket <proto> close <socket_id>".to_string();
+                    return CmdOutput::usage("usage: socket <proto> close <socket_id>".to_string());
                 }
                 let id: u32 = args[2].parse().unwrap_or(0);
                 match self.network.close(id) {
-                    Ok(()) => format!("Closed socket {}", id),
-                    Err(e) => format!("Error: {}", e),
+                    Ok(()) => CmdOutput::ok(format!("Closed socket {}", id)),
+                    Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                 }
             }
-            _ => format!("socket: unknown action '{}'", args[1]),
+            _ => CmdOutput::error(1, format!("socket: unknown action '{}'", args[1])),
         }
     }
 
-    fn cmd_service(&mut self, args: &[&str]) -> String {
+    fn cmd_service(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return self.services.list().join("\n");
+            return CmdOutput::ok(self.services.list().join("\n"));
         }
 
         match args[0] {
-            "list" => self.services.list().join("\n"),
+            "list" => CmdOutput::ok(self.services.list().join("\n")),
             "start" => {
                 if args.len() < 2 {
-                    return "usage: service start <name>".to_string();
+                    return CmdOutput::usage("usage: service start <name>".to_string());
                 }
                 let name = args[1];
                 match self.kernel.proc.spawn(name, 1, &mut self.kernel.mem) {
                     Some(pid) => match self.services.start(name, pid) {
-                        Ok(()) => format!("Started service '{}'", name),
-                        Err(e) => format!("Error: {}", e),
+                        Ok(()) => CmdOutput::ok(format!("Started service '{}'", name)),
+                        Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                     },
-                    None => "Failed to start service: out of memory".to_string(),
+                    None => {
+                        CmdOutput::error(1, "Failed to start service: out of memory".to_string())
+                    }
                 }
             }
             "stop" => {
                 if args.len() < 2 {
-                    return "usage: service stop <name>".to_string();
+                    return CmdOutput::usage("usage: service stop <name>".to_string());
                 }
                 let name = args[1];
                 match self.services.stop(name) {
-                    Ok(()) => format!("Stopped service '{}'", name),
-                    Err(e) => format!("Error: {}", e),
+                    Ok(()) => CmdOutput::ok(format!("Stopped service '{}'", name)),
+                    Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                 }
             }
             "restart" => {
                 if args.len() < 2 {
-                    return "usage: service restart <name>".to_string();
+                    return CmdOutput::usage("usage: service restart <name>".to_string());
                 }
                 let name = args[1];
                 match self.kernel.proc.spawn(name, 1, &mut self.kernel.mem) {
                     Some(pid) => match self.services.restart(name, pid) {
-                        Ok(()) => format!("Restarted service '{}'", name),
-                        Err(e) => format!("Error: {}", e),
+                        Ok(()) => CmdOutput::ok(format!("Restarted service '{}'", name)),
+                        Err(e) => CmdOutput::error(1, format!("Error: {}", e)),
                     },
-                    None => "Failed to restart service: out of memory".to_string(),
+                    None => {
+                        CmdOutput::error(1, "Failed to restart service: out of memory".to_string())
+                    }
                 }
             }
             "status" => {
                 if args.len() < 2 {
-                    return "usage: service status <name>".to_string();
+                    return CmdOutput::usage("usage: service status <name>".to_string());
                 }
                 let name = args[1];
                 match self.services.get_state(name) {
-                    Some(state) => format!("{}: {:?}", name, state),
-                    None => format!("Service '{}' not found", name),
+                    Some(state) => CmdOutput::ok(format!("{}: {:?}", name, state)),
+                    None => CmdOutput::error(1, format!("Service '{}' not found", name)),
                 }
             }
-            _ => format!("service: unknown action '{}'", args[0]),
+            _ => CmdOutput::error(1, format!("service: unknown action '{}'", args[0])),
         }
     }
 
     fn start_python_repl(&mut self) -> String {
         self.python_interp = Some(PythonInterpreter::new());
         self.in_python_repl = true;
-        "\x1b[PYTHON_REPL]".into()
+        self.emit(SystemEvent::PythonRepl)
     }
 
-    fn cmd_python(&mut self, args: &[&str]) -> String {
+    fn cmd_python(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return self.start_python_repl();
+            return CmdOutput::ok(self.start_python_repl());
+        }
+
+        if args[0] == "-m" {
+            return match args.get(1) {
+                Some(&"http.server") => self.cmd_http_server(&args[2..]),
+                Some(module) => {
+                    CmdOutput::error(1, format!("/usr/bin/python: No module named {}", module))
+                }
+                None => CmdOutput::usage("Argument expected for the -m option"),
+            };
         }
 
         if args[0] == "-c" {
             if args.len() < 2 {
-                return "python: option -c requires an argument".into();
+                return CmdOutput::usage("python: option -c requires an argument");
             }
             let code = args[1..].join(" ");
-            let mut interp = PythonInterpreter::new();
-            return match interp.eval(&code) {
-                Ok(v) => v,
-                Err(e) => format!("Error: {}", e),
-            };
+            let code = code
+                .strip_prefix('"')
+                .and_then(|c| c.strip_suffix('"'))
+                .or_else(|| code.strip_prefix('\'').and_then(|c| c.strip_suffix('\'')))
+                .unwrap_or(&code);
+            let code = code.to_string();
+            return self.run_python(|py| py.run_script(&code, "<string>"));
         }
 
         let script_path = args[0];
         let Some(node) = self.kernel.fs.resolve(script_path) else {
-            return format!(
-                "python: can't open file '{}': [Errno 2] No such file or directory",
-                script_path
+            return CmdOutput::error(
+                1,
+                format!(
+                    "python: can't open file '{}': [Errno 2] No such file or directory",
+                    script_path
+                ),
             );
         };
         if node.is_dir {
-            return format!("python: {}: Is a directory", script_path);
+            return CmdOutput::error(1, format!("python: {}: Is a directory", script_path));
         }
+        let source = node.data.clone();
+
+        self.run_python(|py| py.run_script(&source, script_path))
+    }
 
+    /// Run a one-off program with the filesystem lent to the interpreter
+    fn run_python(
+        &mut self,
+        run: impl FnOnce(&mut PythonInterpreter) -> Result<String, String>,
+    ) -> CmdOutput {
+        let user = self.current_user();
+        let groups = self.current_group_names();
         let mut interp = PythonInterpreter::new();
-        let mut out = Vec::new();
-        for (line_no, line) in node.data.lines().enumerate() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
-            match interp.eval(trimmed) {
-                Ok(v) => {
-                    if !v.is_empty() {
-                        out.push(v);
-                    }
+        CmdOutput::from_result(interp.with_fs(&mut self.kernel.fs, &user, groups, run))
+    }
+
+    /// Feed one line typed at the REPL. Returns nothing while a block or
+    /// bracket is still open; `python_prompt` then reads `... `.
+    #[wasm_bindgen]
+    pub fn exec_python(&mut self, code: &str) -> String {
+        let user = self.current_user();
+        let groups = self.current_group_names();
+        let Some(interp) = self.python_interp.as_mut() else {
+            return "Error: Python interpreter not initialized".to_string();
+        };
+        if interp.prompt() == ">>> " && matches!(code.trim(), "exit()" | "quit()") {
+            self.in_python_repl = false;
+            self.python_interp = None;
+            return "\x1b[EXIT_PYTHON]".to_string();
+        }
+        match interp.with_fs(&mut self.kernel.fs, &user, groups, |py| py.push_line(code)) {
+            Some(Ok(out)) | Some(Err(out)) => out,
+            None => String::new(),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn python_prompt(&self) -> String {
+        self.python_interp
+            .as_ref()
+            .map_or(">>> ", |p| p.prompt())
+            .to_string()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_in_python_repl(&self) -> bool {
+        self.in_python_repl
+    }
+
+    fn cmd_lua(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            self.lua_interp = Some(LuaInterpreter::new());
+            self.in_lua_repl = true;
+            return CmdOutput::ok(self.emit(SystemEvent::LuaRepl));
+        }
+
+        match args[0] {
+            "-v" => CmdOutput::ok("Lua 5.4.6  Copyright (C) 1994-2023 Lua.org, PUC-Rio"),
+            "-e" => {
+                if args.len() < 2 {
+                    return CmdOutput::error(1, "lua: '-e' needs argument");
                 }
-                Err(e) => {
-                    return format!(
-                        "Traceback (most recent call last):\n  File \"{}\", line {}\nError: {}",
-                        script_path,
-                        line_no + 1,
-                        e
+                let code = args[1..].join(" ");
+                let code = code
+                    .strip_prefix('"')
+                    .and_then(|c| c.strip_suffix('"'))
+                    .or_else(|| code.strip_prefix('\'').and_then(|c| c.strip_suffix('\'')))
+                    .unwrap_or(&code);
+                CmdOutput::from_result(LuaInterpreter::new().run_script(code, "(command line)"))
+            }
+            script_path => {
+                let Some(node) = self.kernel.fs.resolve(script_path) else {
+                    return CmdOutput::error(1, format!("lua: cannot open {}", script_path));
+                };
+                if node.is_dir {
+                    return CmdOutput::error(
+                        1,
+                        format!("lua: cannot read {}: Is a directory", script_path),
                     );
                 }
+                let source = node.data.clone();
+                CmdOutput::from_result(LuaInterpreter::new().run_script(&source, script_path))
             }
         }
-
-        out.join("\n")
     }
 
+    /// Feed one line typed at the Lua REPL; `os.exit()` leaves it
     #[wasm_bindgen]
-    pub fn exec_python(&mut self, code: &str) -> String {
-        if code.trim() == "exit()" {
-            self.in_python_repl = false;
-            self.python_interp = None;
-            return "\x1b[EXIT_PYTHON]".to_string();
+    pub fn exec_lua(&mut self, code: &str) -> String {
+        let Some(interp) = self.lua_interp.as_mut() else {
+            return "Error: Lua interpreter not initialized".to_string();
+        };
+        let out = match interp.push_line(code) {
+            Some(Ok(out)) | Some(Err(out)) => out,
+            None => String::new(),
+        };
+        if interp.exited() {
+            self.in_lua_repl = false;
+            self.lua_interp = None;
+            return "\x1b[EXIT_LUA]".to_string();
         }
+        out
+    }
+
+    #[wasm_bindgen]
+    pub fn lua_prompt(&self) -> String {
+        self.lua_interp
+            .as_ref()
+            .map_or("> ", |l| l.prompt())
+            .to_string()
+    }
 
-        if let Some(ref mut interp) = self.python_interp {
-            match interp.eval(code) {
-                Ok(result) => result,
-                Err(e) => format!("Error: {}", e),
+    #[wasm_bindgen]
+    pub fn is_in_lua_repl(&self) -> bool {
+        self.in_lua_repl
+    }
+
+    fn cmd_sqlite3(&mut self, args: &[&str]) -> CmdOutput {
+        if args.first() == Some(&"-version") {
+            return CmdOutput::ok(sqlite::VERSION);
+        }
+        let path = args
+            .first()
+            .filter(|p| **p != ":memory:")
+            .map(|p| p.to_string());
+        let sql = args.get(1..).unwrap_or_default().join(" ");
+
+        let mut readonly = false;
+        let db = match &path {
+            None => Database::default(),
+            Some(p) => {
+                let cant_open = format!(
+                    "Error: unable to open database \"{}\": unable to open database file",
+                    p
+                );
+                match self.kernel.fs.resolve(p) {
+                    Some(node) if node.is_dir || !self.has_access(p, 4) => {
+                        return CmdOutput::error(1, cant_open)
+                    }
+                    Some(node) => {
+                        readonly = !self.has_access(p, 2);
+                        match Database::load(&node.data) {
+                            Ok(db) => db,
+                            Err(e) => return CmdOutput::error(1, format!("Error: {}", e)),
+                        }
+                    }
+                    None if !self.can_write_path(p) => return CmdOutput::error(1, cant_open),
+                    None => Database::default(),
+                }
             }
+        };
+        let mut shell = SqliteShell::new(db, readonly);
+
+        if !sql.trim().is_empty() {
+            let sql = sql
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .or_else(|| sql.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+                .unwrap_or(&sql);
+            let (out, error) = shell.execute(sql);
+            let save = self.save_sqlite(&mut shell, path.as_deref());
+            let errors = error
+                .into_iter()
+                .chain(Some(save).filter(|s| !s.is_empty()));
+            return CmdOutput::collected(vec![out], errors.collect());
+        }
+
+        let mut banner = format!(
+            "SQLite version {}\nEnter \".help\" for usage hints.",
+            sqlite::VERSION
+        );
+        if path.is_none() {
+            banner.push_str("\nConnected to a transient in-memory database.");
+        }
+        self.sqlite = Some((shell, path));
+        self.in_sqlite_repl = true;
+        CmdOutput::ok(self.emit(SystemEvent::SqliteRepl) + &banner)
+    }
+
+    /// Write the database back to its file if the last statements changed it
+    fn save_sqlite(&mut self, shell: &mut SqliteShell, path: Option<&str>) -> String {
+        let Some(path) = path else {
+            return String::new();
+        };
+        if !shell.take_dirty() {
+            return String::new();
+        }
+        match self.write_file_bytes(path, shell.db.dump().as_bytes()) {
+            Ok(()) => String::new(),
+            Err(e) => format!("Error: disk I/O error: {}", e),
+        }
+    }
+
+    /// Feed one line typed at the `sqlite3` prompt; `.quit` leaves it
+    #[wasm_bindgen]
+    pub fn exec_sqlite(&mut self, line: &str) -> String {
+        let Some((mut shell, path)) = self.sqlite.take() else {
+            return "Error: no database is open".to_string();
+        };
+        let out = shell.push_line(line);
+        let save = self.save_sqlite(&mut shell, path.as_deref());
+        if shell.is_closed() {
+            self.in_sqlite_repl = false;
+            return "\x1b[EXIT_SQLITE]".to_string();
+        }
+        self.sqlite = Some((shell, path));
+        if save.is_empty() {
+            out
         } else {
-            "Error: Python interpreter not initialized".to_string()
+            save
         }
     }
 
+    /// A Memtest86+ run over the current memory map
     #[wasm_bindgen]
-    pub fn is_in_python_repl(&self) -> bool {
-        self.in_python_repl
+    pub fn memtest(&self) -> crate::grub::Memtest {
+        crate::grub::Memtest::for_memory(&self.kernel.mem)
+    }
+
+    /// True when grub.cfg or a critical binary is gone and boot should stop
+    /// at the `(initramfs)` shell
+    #[wasm_bindgen]
+    pub fn needs_recovery(&self) -> bool {
+        !self.recovery_problems().is_empty()
+    }
+
+    #[wasm_bindgen]
+    pub fn initramfs_banner(&self) -> String {
+        self.initramfs_motd()
+    }
+
+    #[wasm_bindgen]
+    pub fn exec_initramfs(&mut self, line: &str) -> String {
+        self.initramfs_exec(line)
+    }
+
+    #[wasm_bindgen]
+    pub fn initramfs_prompt(&self) -> String {
+        "(initramfs) ".to_string()
+    }
+
+    #[wasm_bindgen]
+    pub fn sqlite_prompt(&self) -> String {
+        self.sqlite
+            .as_ref()
+            .map_or("sqlite> ", |(shell, _)| shell.prompt())
+            .to_string()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_in_sqlite_repl(&self) -> bool {
+        self.in_sqlite_repl
+    }
+
+    /// Next `traceroute` hop line for a probe that took `rtt_ms` (negative
+    /// if it failed); empty when the trace is complete
+    #[wasm_bindgen]
+    pub fn traceroute_step(&mut self, rtt_ms: f64) -> String {
+        self.traceroute_next(rtt_ms)
+    }
+
+    /// Feed one line typed at the `wscat` prompt
+    #[wasm_bindgen]
+    pub fn exec_wscat(&mut self, line: &str) -> String {
+        self.wscat_input(line)
+    }
+
+    #[wasm_bindgen]
+    pub fn is_in_wscat(&self) -> bool {
+        self.wscat_active()
+    }
+
+    /// CTRL+C at the `wscat` prompt
+    #[wasm_bindgen]
+    pub fn wscat_interrupt(&mut self) {
+        self.wscat_quit();
+    }
+
+    /// Register the callback WebSocket events are delivered to, as
+    /// `(socket_id, kind, data)`; it should pass them to `socket_event`
+    #[wasm_bindgen]
+    pub fn set_socket_listener(&mut self, listener: js_sys::Function) {
+        self.network.set_listener(listener);
+    }
+
+    /// Apply a WebSocket event; returns a line for the terminal, if any
+    #[wasm_bindgen]
+    pub fn socket_event(&mut self, socket_id: u32, kind: &str, data: &str) -> String {
+        self.wscat_event(socket_id, kind, data)
+    }
+
+    /// Store one chunk of a `wget`/`curl -o` transfer at byte `start`;
+    /// `total` is the full size, or negative when the server didn't say.
+    /// Returns an error message or an empty string.
+    #[wasm_bindgen]
+    pub fn download_chunk(&mut self, id: u32, start: f64, data: &[u8], total: f64) -> String {
+        let total = (total >= 0.0).then_some(total as u64);
+        match self.store_download_chunk(id, start as u64, data, total) {
+            Ok(()) => String::new(),
+            Err(e) => e,
+        }
+    }
+
+    /// The frontend stopped fetching a transfer; incomplete ones stay
+    /// listed by `downloads` so they can be resumed
+    #[wasm_bindgen]
+    pub fn download_finish(&mut self, id: u32, complete: bool) {
+        self.end_download(id, complete);
+    }
+
+    /// Save a demo returned by `doom_stop_recording`; returns an error
+    /// message or an empty string
+    #[wasm_bindgen]
+    pub fn save_doom_demo(&mut self, path: &str, demo: &[u8]) -> String {
+        match self.write_file_bytes(path, demo) {
+            Ok(()) => String::new(),
+            Err(e) => format!("doom: {}: {}", path, e),
+        }
+    }
+
+    /// Demo bytes for `doom play`, empty if the file can't be read
+    #[wasm_bindgen]
+    pub fn read_doom_demo(&self, path: &str) -> Vec<u8> {
+        self.read_file_bytes(path).unwrap_or_default()
+    }
+
+    /// Best score on the `snake` high-score table, 0 if there is none
+    #[wasm_bindgen]
+    pub fn snake_best(&self) -> u32 {
+        self.top_snake_score()
+    }
+
+    /// Record a finished `snake` game; returns a line to print
+    #[wasm_bindgen]
+    pub fn record_snake_score(&mut self, score: u32) -> String {
+        self.store_snake_score(score)
+    }
+
+    /// Raw bytes of `path` for frontend programs such as `view`, empty if it
+    /// can't be read
+    #[wasm_bindgen]
+    pub fn read_binary(&self, path: &str) -> Vec<u8> {
+        self.read_file_bytes(path).unwrap_or_default()
+    }
+
+    /// Check the password typed at the idle lock screen; unlocks on a match
+    #[wasm_bindgen]
+    pub fn unlock_screen(&self, pw: &str) -> bool {
+        self.try_unlock(pw)
+    }
+
+    /// Log a TCP conversation the frontend made through the fetch bridge so
+    /// `tcpdump` can show it
+    #[wasm_bindgen]
+    pub fn network_record_tcp(&mut self, host: &str, port: u16, sent: u32, received: u32) {
+        self.network.record_tcp_exchange(
+            js_sys::Date::now(),
+            host,
+            port,
+            sent as usize,
+            received as usize,
+        );
+    }
+
+    /// Log one `ping` probe; a negative `rtt_ms` means no reply came back
+    #[wasm_bindgen]
+    pub fn network_record_ping(&mut self, host: &str, seq: u16, rtt_ms: f64) {
+        let now = js_sys::Date::now();
+        let rtt = (rtt_ms >= 0.0).then_some(rtt_ms);
+        self.network
+            .record_ping(now - rtt.unwrap_or(0.0), host, seq, rtt);
+    }
+
+    /// Write a repository fetched for `git clone` into `dir`
+    #[wasm_bindgen]
+    pub fn git_clone_finish(
+        &mut self,
+        dir: &str,
+        url: &str,
+        branch: &str,
+        files: &str,
+        head: &str,
+    ) -> String {
+        self.git_materialize_clone(dir, url, branch, files, head)
     }
 
     // Syscalls
+    /// A handle, -1 if `path` cannot be opened, or -EMFILE once `ulimit -n`
+    /// files are open
     #[wasm_bindgen]
     pub fn sys_open(&mut self, path: &str, write: bool) -> i32 {
+        if self.open_denied() {
+            return -ulimit::EMFILE;
+        }
+        let pid = self.shell_pid();
         self.kernel
             .fs
-            .open(path, write)
+            .open(path, write, pid)
             .map(|h| h as i32)
             .unwrap_or(-1)
     }
@@ -5082,161 +8239,21 @@ SEE ALSO
             self.kernel.fs.remove(path).is_ok()
         }
     }
+    /// Tab completion candidates for the last word of `line` (the input up
+    /// to the cursor); each one replaces that word whole
     #[wasm_bindgen]
-    pub fn complete(&self, partial: &str) -> Vec<JsValue> {
-        let mut matches = Vec::new();
-        let cmds = [
-            "alias",
-            "apt",
-            "apt-get",
-            "arp",
-            "awk",
-            "cat",
-            "cmatrix",
-            "cd",
-            "cksum",
-            "chmod",
-            "chown",
-            "clear",
-            "cp",
-            "curl",
-            "cut",
-            "date",
-            "df",
-            "diff",
-            "dig",
-            "doom",
-            "doommap",
-            "du",
-            "echo",
-            "env",
-            "exit",
-            "export",
-            "file",
-            "find",
-            "free",
-            "grep",
-            "grub",
-            "gunzip",
-            "gzip",
-            "hasgrub",
-            "source",
-            "head",
-            "help",
-            "history",
-            "host",
-            "hostname",
-            "htop",
-            "id",
-            "groups",
-            "who",
-            "ifconfig",
-            "ip",
-            "unalias",
-            "kill",
-            "ln",
-            "ls",
-            "man",
-            "mount",
-            "mkdir",
-            "mv",
-            "myip",
-            "nano",
-            "nc",
-            "netcat",
-            "netstat",
-            "nslookup",
-            "ping",
-            "ps",
-            "pwd",
-            "python",
-            "reboot",
-            "rm",
-            "rmdir",
-            "route",
-            "screensaver",
-            "sed",
-            "service",
-            "socket",
-            "sort",
-            "ss",
-            "stat",
-            "sudo",
-            "tail",
-            "tar",
-            "tee",
-            "top",
-            "touch",
-            "tr",
-            "traceroute",
-            "tracert",
-            "uname",
-            "uniq",
-            "umount",
-            "unzip",
-            "uptime",
-            "vi",
-            "vim",
-            "wc",
-            "cksum",
-            "wget",
-            "whereis",
-            "which",
-            "whoami",
-            "zip",
-        ];
-        for c in cmds {
-            if c.starts_with(partial) {
-                matches.push(JsValue::from_str(c));
-            }
-        }
-        if let Some(dir) = self.kernel.fs.resolve(&self.kernel.fs.cwd) {
-            for name in dir.children.keys() {
-                if name.starts_with(partial) {
-                    matches.push(JsValue::from_str(name));
-                }
-            }
-        }
-        matches
+    pub fn complete(&self, line: &str) -> Vec<JsValue> {
+        self.completions(line)
+            .iter()
+            .map(|c| JsValue::from_str(c))
+            .collect()
     }
 
+    /// What every candidate for `line` starts with, so the frontend can
+    /// extend the word as far as it is unambiguous
     #[wasm_bindgen]
-    pub fn complete_path(&self, partial: &str) -> Vec<JsValue> {
-        let mut out = Vec::new();
-        let normalized = self.kernel.fs.normalize(partial);
-
-        let (parent_path, needle) = match normalized.rfind('/') {
-            Some(0) => ("/".to_string(), normalized[1..].to_string()),
-            Some(idx) => (
-                normalized[..idx].to_string(),
-                normalized[idx + 1..].to_string(),
-            ),
-            None => (self.kernel.fs.cwd.clone(), normalized),
-        };
-
-        if let Some(parent) = self.kernel.fs.resolve(&parent_path) {
-            if parent.is_dir {
-                for (name, inode) in &parent.children {
-                    if name.starts_with(&needle) {
-                        let suggestion = if parent_path == "/" {
-                            format!("/{}{}", name, if inode.is_dir { "/" } else { "" })
-                        } else if partial.contains('/') {
-                            format!(
-                                "{}/{}{}",
-                                parent_path,
-                                name,
-                                if inode.is_dir { "/" } else { "" }
-                            )
-                        } else {
-                            format!("{}{}", name, if inode.is_dir { "/" } else { "" })
-                        };
-                        out.push(JsValue::from_str(&suggestion));
-                    }
-                }
-            }
-        }
-
-        out
+    pub fn complete_hint(&self, line: &str) -> String {
+        complete::common_prefix(&self.completions(line))
     }
 
     #[wasm_bindgen]
@@ -5258,16 +8275,26 @@ SEE ALSO
         }
     }
 
-    /// Export user files as JSON for localStorage persistence
+    /// Export user files as JSON, the format localStorage used to hold
     #[wasm_bindgen]
     pub fn export_user_files(&self) -> String {
         self.kernel.fs.export_user_files()
     }
 
-    /// Import user files from JSON (called on startup)
+    /// Import user files from the old localStorage JSON, once, on the first
+    /// load after filesystem images replaced it
     #[wasm_bindgen]
     pub fn import_user_files(&mut self, json: &str) {
         self.kernel.fs.import_user_files(json);
+        self.apply_user_config();
+    }
+
+    /// Pick up history and settings from the user's dotfiles
+    fn apply_user_config(&mut self) {
+        self.load_history();
+        self.apply_screensaver_config();
+        self.apply_idle_config();
+        self.apply_audio_config();
     }
 
     #[wasm_bindgen]
@@ -5289,6 +8316,17 @@ SEE ALSO
         }
     }
 
+    /// Draw the kernel panic on the graphics canvas. A key press (or ten
+    /// seconds) saves the broken filesystem, with the report in root's
+    /// mailbox, and reboots into it
+    #[wasm_bindgen]
+    pub fn show_kernel_panic(&mut self) -> Result<(), JsValue> {
+        let reason = self.get_panic_message();
+        self.deliver_panic_report(&reason);
+        let lines = crate::panic_screen::panic_lines(&reason, self.kernel.uptime_ms());
+        crate::panic_screen::show(lines, self.kernel.save())
+    }
+
     // Boot manager methods for JavaScript
     #[wasm_bindgen]
     pub fn boot_get_current_bootloader(&self) -> String {
@@ -5314,7 +8352,7 @@ SEE ALSO
     #[wasm_bindgen]
     pub fn boot_simulate_sequence(&mut self) -> js_sys::Array {
         let arr = js_sys::Array::new();
-        for message in self.boot.simulate_boot_sequence(&mut self.kernel.mem) {
+        for message in self.boot_with_params() {
             arr.push(&JsValue::from_str(&message));
         }
         arr
@@ -5340,15 +8378,28 @@ SEE ALSO
         self.boot.get_kernel_version()
     }
 
-    /// Initialize system with persistence loading
+    /// Initialize system with persistence loading. Returns `false` when
+    /// there was no full filesystem image, so the frontend should migrate
+    /// the user files it kept in localStorage
     #[wasm_bindgen]
-    pub async fn init(&mut self) {
-        self.kernel.init().await;
+    pub async fn init(&mut self) -> bool {
+        let restored = self.kernel.init().await;
+        self.install_unit_files();
+        self.sync_package_commands();
+        self.apply_user_config();
+        restored
     }
 
-    /// Save system state to persistence
+    /// Save the whole filesystem to IndexedDB. Returns a promise; the system
+    /// can keep running commands while the write finishes
     #[wasm_bindgen]
-    pub async fn save(&self) {
-        self.kernel.save().await;
+    pub fn save(&mut self) -> js_sys::Promise {
+        self.record_uptime();
+        let save = self.kernel.save();
+        wasm_bindgen_futures::future_to_promise(async move {
+            save.await
+                .map(|()| JsValue::UNDEFINED)
+                .map_err(|e| JsValue::from_str(&e))
+        })
     }
 }
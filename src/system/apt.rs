@@ -0,0 +1,521 @@
+use super::{System, SystemEvent};
+use crate::clock;
+use crate::pkg::{self, CatalogEntry, InstalledPackage, PackageDb, INFO_DIR, STATUS_PATH};
+use crate::shell::CmdOutput;
+use crate::shell::ProgramKind;
+use crate::vfs::Inode;
+
+/// Where `apt update` fetches the release file from
+const MIRROR: &str = "https://pawnd.me";
+const LOCK_ERROR: &str = "E: Could not open lock file /var/lib/dpkg/lock-frontend - open (13: Permission denied)\nE: Unable to acquire the dpkg frontend lock (/var/lib/dpkg/lock-frontend), are you root?";
+
+impl System {
+    pub(super) fn package_db(&self) -> PackageDb {
+        self.kernel
+            .fs
+            .resolve(STATUS_PATH)
+            .map(|n| PackageDb::parse(&n.data))
+            .unwrap_or_default()
+    }
+
+    pub(super) fn save_package_db(&mut self, db: &PackageDb) -> Result<(), String> {
+        self.ensure_dir_all(INFO_DIR)?;
+        self.write_package_file(STATUS_PATH, &db.to_status())
+    }
+
+    fn write_package_file(&mut self, path: &str, data: &str) -> Result<(), String> {
+        let res = if self.kernel.fs.resolve(path).is_some() {
+            self.kernel.fs.write_file(path, data)
+        } else {
+            self.kernel.fs.create_file(path, data)
+        };
+        res.map_err(|e| e.to_string())
+    }
+
+    /// Files unpacked by `name`, from its dpkg `.list` file
+    pub(super) fn package_files(&self, name: &str) -> Vec<String> {
+        self.kernel
+            .fs
+            .resolve(&format!("{}/{}.list", INFO_DIR, name))
+            .map(|n| {
+                n.data
+                    .lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(|l| l.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Rebuild the package-provided part of the program registry from the
+    /// dpkg database, so installed commands survive reloads.
+    pub(super) fn sync_package_commands(&mut self) {
+        self.shell.registry.clear_packages();
+        let db = self.package_db();
+        for p in db.iter() {
+            for file in self.package_files(&p.name) {
+                if self.kernel.fs.resolve(&file).is_none() {
+                    continue;
+                }
+                if let Some(cmd) = pkg::command_name(&file) {
+                    self.shell
+                        .registry
+                        .register(cmd, ProgramKind::Package(p.name.clone()));
+                }
+            }
+        }
+    }
+
+    /// Path of the executable behind a package-provided command
+    pub(super) fn package_command_path(&self, cmd: &str) -> Option<String> {
+        let Some(ProgramKind::Package(name)) = self.shell.registry.get(cmd) else {
+            return None;
+        };
+        self.package_files(name)
+            .into_iter()
+            .find(|f| pkg::command_name(f) == Some(cmd))
+    }
+
+    fn unpack_catalog_package(&mut self, entry: &CatalogEntry) -> Result<(), String> {
+        for file in entry.files {
+            let Some((dir, name)) = file.rsplit_once('/') else {
+                continue;
+            };
+            self.ensure_dir_all(dir)?;
+            if let Some(parent) = self.kernel.fs.resolve_mut(dir) {
+                parent
+                    .children
+                    .insert(name.into(), Inode::binary(name, entry.description, false));
+            }
+        }
+        for (path, contents) in entry.data {
+            if let Some((dir, _)) = path.rsplit_once('/') {
+                self.ensure_dir_all(dir)?;
+            }
+            self.write_file_bytes(path, contents.as_bytes())?;
+        }
+        let list: String = entry
+            .files
+            .iter()
+            .chain(entry.data.iter().map(|(p, _)| p))
+            .map(|f| format!("{}\n", f))
+            .collect();
+        self.ensure_dir_all(INFO_DIR)?;
+        self.write_package_file(&format!("{}/{}.list", INFO_DIR, entry.name), &list)
+    }
+
+    pub(super) fn purge_package_files(&mut self, name: &str) {
+        for file in self.package_files(name) {
+            let _ = self.kernel.fs.remove(&file);
+        }
+        let _ = self
+            .kernel
+            .fs
+            .remove(&format!("{}/{}.list", INFO_DIR, name));
+    }
+
+    /// Catalog entries to install for `names`, dependencies first
+    fn install_plan(db: &PackageDb, names: &[&str]) -> Result<Vec<&'static CatalogEntry>, String> {
+        fn visit(
+            name: &str,
+            db: &PackageDb,
+            plan: &mut Vec<&'static CatalogEntry>,
+        ) -> Result<(), String> {
+            if db.contains(name) || plan.iter().any(|p| p.name == name) {
+                return Ok(());
+            }
+            let entry =
+                pkg::find(name).ok_or_else(|| format!("E: Unable to locate package {}", name))?;
+            for dep in entry.depends {
+                visit(dep, db, plan)?;
+            }
+            plan.push(entry);
+            Ok(())
+        }
+
+        let mut plan = Vec::new();
+        for name in names {
+            visit(name, db, &mut plan)?;
+        }
+        Ok(plan)
+    }
+
+    /// The rest of `apt update` once the release file was fetched: its size,
+    /// or why it could not be
+    pub(super) fn apt_update_finish(
+        &mut self,
+        mirror: &str,
+        fetched: Result<usize, String>,
+    ) -> String {
+        let mut out = Vec::new();
+        match &fetched {
+            Ok(bytes) => {
+                let kb = bytes.div_ceil(1024);
+                out.push(format!("Get:1 {} stable InRelease [{} kB]", mirror, kb));
+                out.push(format!("Fetched {} kB", kb));
+                let stamp = clock::ctime(clock::now_secs());
+                let _ = self.write_package_file("/var/lib/apt/lists/last_update", &stamp);
+            }
+            Err(e) => {
+                out.push(format!("Err:1 {} stable InRelease", mirror));
+                out.push(format!("  {}", e));
+            }
+        }
+        out.push("Reading package lists... Done".into());
+        out.push("Building dependency tree... Done".into());
+        match fetched {
+            Ok(_) => out.push(format!(
+                "{} packages available. All packages are up to date.",
+                pkg::CATALOG.len()
+            )),
+            Err(e) => {
+                out.push(format!(
+                    "W: Failed to fetch {}/dists/stable/InRelease  {}",
+                    mirror, e
+                ));
+                out.push("W: Some index files failed to download. They have been ignored, or old ones used instead.".into());
+            }
+        }
+        out.join("\n")
+    }
+
+    pub(super) fn cmd_apt(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            return CmdOutput::usage(
+                "usage: apt [install|remove|purge|update|upgrade|search|list|show] [package]",
+            );
+        }
+
+        let is_root = self.current_user() == "root";
+        let mut db = self.package_db();
+        let names: Vec<&str> = args[1..]
+            .iter()
+            .copied()
+            .filter(|a| !a.starts_with('-'))
+            .collect();
+
+        match args[0] {
+            "update" => {
+                if !is_root {
+                    return CmdOutput::error(1, LOCK_ERROR);
+                }
+                if let Err(e) = self.ensure_dir_all("/var/lib/apt/lists") {
+                    return CmdOutput::error(
+                        1,
+                        format!("E: failed to prepare package lists: {}", e),
+                    );
+                }
+                CmdOutput::ok(self.emit(SystemEvent::AptUpdate {
+                    mirror: MIRROR.into(),
+                }))
+            }
+            "upgrade" => {
+                if !is_root {
+                    return CmdOutput::error(1, LOCK_ERROR);
+                }
+                let stale: Vec<&'static CatalogEntry> = db
+                    .iter()
+                    .filter_map(|p| pkg::find(&p.name).filter(|e| e.version != p.version))
+                    .collect();
+                for entry in &stale {
+                    db.insert(InstalledPackage::from_catalog(entry));
+                }
+                if !stale.is_empty() {
+                    if let Err(e) = self.save_package_db(&db) {
+                        return CmdOutput::error(
+                            1,
+                            format!("E: failed to write package database: {}", e),
+                        );
+                    }
+                }
+                CmdOutput::ok(format!(
+                    "Reading package lists... Done\nBuilding dependency tree... Done\n{} upgraded, 0 newly installed, 0 to remove and 0 not upgraded.",
+                    stale.len()
+                ))
+            }
+            "install" => {
+                if names.is_empty() {
+                    return CmdOutput::usage("usage: apt install PACKAGE...");
+                }
+                if !is_root {
+                    return CmdOutput::error(1, LOCK_ERROR);
+                }
+                let plan = match Self::install_plan(&db, &names) {
+                    Ok(plan) => plan,
+                    Err(e) => {
+                        return CmdOutput::error(1, format!("Reading package lists... Done\n{}", e))
+                    }
+                };
+                let mut out = vec![
+                    "Reading package lists... Done".to_string(),
+                    "Building dependency tree... Done".to_string(),
+                ];
+                for name in &names {
+                    if let Some(p) = db.get(name) {
+                        out.push(format!(
+                            "{} is already the newest version ({}).",
+                            name, p.version
+                        ));
+                    }
+                }
+                if plan.is_empty() {
+                    out.push(
+                        "0 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.".into(),
+                    );
+                    return CmdOutput::ok(out.join("\n"));
+                }
+
+                let total_kb: u32 = plan.iter().map(|p| p.size_kb).sum();
+                out.push("The following NEW packages will be installed:".into());
+                out.push(format!(
+                    "  {}",
+                    plan.iter().map(|p| p.name).collect::<Vec<_>>().join(" ")
+                ));
+                out.push(format!(
+                    "0 upgraded, {} newly installed, 0 to remove and 0 not upgraded.",
+                    plan.len()
+                ));
+                out.push(format!("Need to get {} kB of archives.", total_kb / 3 + 1));
+                out.push(format!(
+                    "After this operation, {} kB of additional disk space will be used.",
+                    total_kb
+                ));
+                for (i, entry) in plan.iter().enumerate() {
+                    out.push(format!(
+                        "Get:{} https://archive.kpawnd.local stable/main amd64 {} {} [{} kB]",
+                        i + 1,
+                        entry.name,
+                        entry.version,
+                        entry.size_kb / 3 + 1
+                    ));
+                }
+                for entry in &plan {
+                    if let Err(e) = self.unpack_catalog_package(entry) {
+                        return CmdOutput::error(
+                            1,
+                            format!("E: failed to unpack {}: {}", entry.name, e),
+                        );
+                    }
+                    db.insert(InstalledPackage::from_catalog(entry));
+                    out.push(format!(
+                        "Selecting previously unselected package {}.\nPreparing to unpack .../{}_{}_amd64.deb ...\nUnpacking {} ({}) ...",
+                        entry.name,
+                        entry.name,
+                        entry.version.rsplit(':').next().unwrap_or(entry.version),
+                        entry.name,
+                        entry.version
+                    ));
+                }
+                if let Err(e) = self.save_package_db(&db) {
+                    return CmdOutput::error(
+                        1,
+                        format!("E: failed to write package database: {}", e),
+                    );
+                }
+                for entry in &plan {
+                    out.push(format!("Setting up {} ({}) ...", entry.name, entry.version));
+                }
+                self.sync_package_commands();
+                CmdOutput::ok(out.join("\n"))
+            }
+            "remove" | "purge" => {
+                if names.is_empty() {
+                    return CmdOutput::usage(format!("usage: apt {} PACKAGE...", args[0]));
+                }
+                if !is_root {
+                    return CmdOutput::error(1, LOCK_ERROR);
+                }
+                let mut out = vec![
+                    "Reading package lists... Done".to_string(),
+                    "Building dependency tree... Done".to_string(),
+                ];
+                let mut doomed: Vec<String> = Vec::new();
+                let mut queue: Vec<String> = Vec::new();
+                for name in &names {
+                    if db.contains(name) {
+                        queue.push(name.to_string());
+                    } else {
+                        out.push(format!(
+                            "Package '{}' is not installed, so not removed",
+                            name
+                        ));
+                    }
+                }
+                // Anything that depends on a removed package goes with it
+                while let Some(name) = queue.pop() {
+                    if doomed.contains(&name) {
+                        continue;
+                    }
+                    queue.extend(db.reverse_depends(&name));
+                    doomed.push(name);
+                }
+                if doomed.is_empty() {
+                    out.push(
+                        "0 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.".into(),
+                    );
+                    return CmdOutput::ok(out.join("\n"));
+                }
+                doomed.sort();
+
+                let freed: u32 = doomed
+                    .iter()
+                    .filter_map(|n| db.get(n))
+                    .map(|p| p.size_kb)
+                    .sum();
+                out.push("The following packages will be REMOVED:".into());
+                out.push(format!("  {}", doomed.join(" ")));
+                out.push(format!(
+                    "0 upgraded, 0 newly installed, {} to remove and 0 not upgraded.",
+                    doomed.len()
+                ));
+                out.push(format!(
+                    "After this operation, {} kB disk space will be freed.",
+                    freed
+                ));
+                for name in &doomed {
+                    if let Some(p) = db.remove(name) {
+                        out.push(format!("Removing {} ({}) ...", p.name, p.version));
+                    }
+                    self.purge_package_files(name);
+                }
+                if let Err(e) = self.save_package_db(&db) {
+                    return CmdOutput::error(
+                        1,
+                        format!("E: failed to write package database: {}", e),
+                    );
+                }
+                self.sync_package_commands();
+                CmdOutput::ok(out.join("\n"))
+            }
+            "search" => {
+                let Some(query) = names.first() else {
+                    return CmdOutput::usage("usage: apt search QUERY");
+                };
+                let query = query.to_lowercase();
+                let matches: Vec<String> = pkg::CATALOG
+                    .iter()
+                    .filter(|p| {
+                        p.name.contains(&query) || p.description.to_lowercase().contains(&query)
+                    })
+                    .map(|p| {
+                        let tag = if db.contains(p.name) {
+                            " [installed]"
+                        } else {
+                            ""
+                        };
+                        format!(
+                            "{}/stable {} amd64{}\n  {}",
+                            p.name, p.version, tag, p.description
+                        )
+                    })
+                    .collect();
+                if matches.is_empty() {
+                    return CmdOutput::ok(format!(
+                        "Sorting... Done\nFull Text Search... Done\nNo packages found matching {}",
+                        query
+                    ));
+                }
+                CmdOutput::ok(format!(
+                    "Sorting... Done\nFull Text Search... Done\n{}",
+                    matches.join("\n\n")
+                ))
+            }
+            "list" => {
+                let installed_only = args.contains(&"--installed");
+                let upgradable_only = args.contains(&"--upgradable");
+                let mut lines = vec!["Listing... Done".to_string()];
+                if installed_only {
+                    for p in db.iter() {
+                        let origin = if pkg::find(&p.name).is_some() {
+                            "stable"
+                        } else {
+                            "now"
+                        };
+                        lines.push(format!(
+                            "{}/{} {} amd64 [installed]",
+                            p.name, origin, p.version
+                        ));
+                    }
+                } else {
+                    for entry in pkg::CATALOG {
+                        let installed = db.get(entry.name);
+                        if upgradable_only && installed.is_none_or(|p| p.version == entry.version) {
+                            continue;
+                        }
+                        let tag = match installed {
+                            Some(p) if p.version != entry.version => {
+                                format!(" [upgradable from: {}]", p.version)
+                            }
+                            Some(_) => " [installed]".into(),
+                            None => String::new(),
+                        };
+                        lines.push(format!(
+                            "{}/stable {} amd64{}",
+                            entry.name, entry.version, tag
+                        ));
+                    }
+                }
+                CmdOutput::ok(lines.join("\n"))
+            }
+            "show" => {
+                let Some(name) = names.first() else {
+                    return CmdOutput::usage("usage: apt show PACKAGE");
+                };
+                let (version, size_kb, section, description, depends) =
+                    match (pkg::find(name), db.get(name)) {
+                        (Some(e), _) => (
+                            e.version.to_string(),
+                            e.size_kb,
+                            e.section.to_string(),
+                            e.description.to_string(),
+                            e.depends.join(", "),
+                        ),
+                        (None, Some(p)) => (
+                            p.version.clone(),
+                            p.size_kb,
+                            p.section.clone(),
+                            p.description.clone(),
+                            p.depends.join(", "),
+                        ),
+                        (None, None) => {
+                            return CmdOutput::error(
+                                1,
+                                format!(
+                                    "N: Unable to locate package {}\nE: No packages found",
+                                    name
+                                ),
+                            )
+                        }
+                    };
+                let mut out = format!(
+                    "Package: {}\nVersion: {}\nPriority: optional\nSection: {}\nMaintainer: kpawnd Developers <devel@kpawnd.local>\nInstalled-Size: {} kB\n",
+                    name, version, section, size_kb
+                );
+                if !depends.is_empty() {
+                    out.push_str(&format!("Depends: {}\n", depends));
+                }
+                out.push_str(&format!(
+                    "APT-Manual-Installed: {}\nDescription: {}",
+                    if db.contains(name) { "yes" } else { "no" },
+                    description
+                ));
+                CmdOutput::ok(out)
+            }
+            _ => CmdOutput::error(1, format!("E: Invalid operation {}", args[0])),
+        }
+    }
+
+    /// Run a command that an installed package registered
+    pub(super) fn exec_package_command(&mut self, cmd: &str, args: &[&str]) -> CmdOutput {
+        match cmd {
+            "sl" => Self::cmd_sl(args),
+            "cowsay" | "cowthink" => self.cmd_cowsay(cmd, args),
+            "figlet" => Self::cmd_figlet(args),
+            "fortune" => self.cmd_fortune(args),
+            _ => match self.package_command_path(cmd) {
+                Some(path) => self.run_package_script(&path, args),
+                None => CmdOutput::error(127, format!("sh: {}: command not found", cmd)),
+            },
+        }
+    }
+}
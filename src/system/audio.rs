@@ -0,0 +1,189 @@
+use super::System;
+use crate::audio::{Channel, Levels};
+use crate::shell::CmdOutput;
+
+const MAX_BEEP_MS: u32 = 5000;
+
+/// `~/.config/audio`: `master=`, `effects=`, `music=`, `system=` (0-100) and
+/// `mute=on|off`
+fn parse_levels(text: &str) -> Levels {
+    let mut levels = Levels::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() == "mute" {
+            levels.muted = value.trim() == "on";
+            continue;
+        }
+        let Ok(percent) = value.trim().parse::<u8>() else {
+            continue;
+        };
+        match key.trim() {
+            "master" => levels.master = percent.min(100),
+            name => {
+                if let Some(channel) = Channel::parse(name) {
+                    levels.set_channel(channel, percent);
+                }
+            }
+        }
+    }
+    levels
+}
+
+fn format_levels(levels: &Levels) -> String {
+    let mut out = format!("master={}\n", levels.master);
+    for channel in Channel::ALL {
+        out.push_str(&format!("{}={}\n", channel.name(), levels.channel(channel)));
+    }
+    out.push_str(if levels.muted {
+        "mute=on\n"
+    } else {
+        "mute=off\n"
+    });
+    out
+}
+
+// "N", "+N" or "-N" against the current level
+fn adjust(current: u8, arg: &str) -> Option<u8> {
+    let value = if let Some(up) = arg.strip_prefix('+') {
+        current as i32 + up.parse::<i32>().ok()?
+    } else if let Some(down) = arg.strip_prefix('-') {
+        current as i32 - down.parse::<i32>().ok()?
+    } else {
+        arg.trim_end_matches('%').parse().ok()?
+    };
+    Some(value.clamp(0, 100) as u8)
+}
+
+impl System {
+    fn audio_config_path(&self) -> String {
+        let home = Self::default_home_for_user(&self.current_user());
+        format!("{}/.config/audio", home)
+    }
+
+    /// Hand the volumes in ~/.config/audio to the mixer
+    pub(super) fn apply_audio_config(&self) {
+        let levels = self
+            .kernel
+            .fs
+            .resolve(&self.audio_config_path())
+            .map(|node| parse_levels(&node.data))
+            .unwrap_or_default();
+        crate::audio::set_levels(levels);
+    }
+
+    /// `volume [N|+N|-N]`, `volume CHANNEL N`, `volume mute [on|off]`
+    pub(super) fn cmd_volume(&mut self, args: &[&str]) -> CmdOutput {
+        let mut levels = crate::audio::levels();
+        match args {
+            [] => {
+                let muted = if levels.muted { " (muted)" } else { "" };
+                let mut out = format!("master: {}%{}", levels.master, muted);
+                for channel in Channel::ALL {
+                    out.push_str(&format!(
+                        "\n{:<7} {}%",
+                        format!("{}:", channel.name()),
+                        levels.channel(channel)
+                    ));
+                }
+                return CmdOutput::ok(out);
+            }
+            ["mute"] => levels.muted = !levels.muted,
+            ["mute", "on"] => levels.muted = true,
+            ["mute", "off"] | ["unmute"] => levels.muted = false,
+            [value] => match adjust(levels.master, value) {
+                Some(percent) => levels.master = percent,
+                None => return CmdOutput::error(1, format!("volume: invalid level '{}'", value)),
+            },
+            [name, value] => {
+                let Some(channel) = Channel::parse(name) else {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "volume: unknown channel '{}' (effects, music, system)",
+                            name
+                        ),
+                    );
+                };
+                match adjust(levels.channel(channel), value) {
+                    Some(percent) => levels.set_channel(channel, percent),
+                    None => {
+                        return CmdOutput::error(1, format!("volume: invalid level '{}'", value))
+                    }
+                }
+            }
+            _ => {
+                return CmdOutput::usage(
+                    "usage: volume [CHANNEL] [N|+N|-N] | volume mute [on|off]".to_string(),
+                )
+            }
+        }
+        crate::audio::set_levels(levels);
+        let path = self.audio_config_path();
+        let dir = path.rsplit_once('/').map_or("/", |(d, _)| d).to_string();
+        if let Err(e) = self.ensure_dir_all(&dir) {
+            return CmdOutput::error(1, format!("volume: {}", e));
+        }
+        match self.write_file_bytes(&path, format_levels(&levels).as_bytes()) {
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) => CmdOutput::error(1, format!("volume: {}: {}", path, e)),
+        }
+    }
+
+    /// `beep [FREQ] [MS]` on the system channel
+    pub(super) fn cmd_beep(args: &[&str]) -> CmdOutput {
+        if args.len() > 2 {
+            return CmdOutput::usage("usage: beep [FREQ] [MS]".to_string());
+        }
+        let freq = match args.first() {
+            None => crate::audio::BEEP_FREQ,
+            Some(f) => match f.parse::<f64>() {
+                Ok(hz) if (20.0..=20000.0).contains(&hz) => hz,
+                _ => {
+                    return CmdOutput::error(
+                        1,
+                        format!("beep: invalid frequency '{}' (20-20000 Hz)", f),
+                    )
+                }
+            },
+        };
+        let ms = match args.get(1) {
+            None => crate::audio::BEEP_MS,
+            Some(l) => match l.parse::<u32>() {
+                Ok(ms) if ms <= MAX_BEEP_MS => ms,
+                _ => {
+                    return CmdOutput::error(
+                        1,
+                        format!("beep: invalid length '{}' (0-{} ms)", l, MAX_BEEP_MS),
+                    )
+                }
+            },
+        };
+        crate::audio::beep(freq, ms);
+        CmdOutput::ok(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_levels_round_trip() {
+        let levels = parse_levels("master=40\nmusic=250\neffects=x\nbogus=3\nmute=on\n");
+        assert_eq!(levels.master, 40);
+        assert!(levels.muted);
+        assert_eq!(levels.channel(Channel::Music), 100);
+        assert_eq!(
+            levels.channel(Channel::Effects),
+            Levels::default().channel(Channel::Effects)
+        );
+        assert_eq!(parse_levels(&format_levels(&levels)), levels);
+
+        assert_eq!(adjust(50, "+60"), Some(100));
+        assert_eq!(adjust(50, "-10"), Some(40));
+        assert_eq!(adjust(50, "75%"), Some(75));
+        assert_eq!(adjust(50, "loud"), None);
+    }
+}
@@ -0,0 +1,125 @@
+use super::System;
+use crate::boot::BootParams;
+use crate::kernel::TOTAL_MEM;
+use crate::shell::CmdOutput;
+
+// System-wide, since boot happens before anyone logs in
+const BOOT_CONF_PATH: &str = "/etc/boot.conf";
+
+// systemd lines for units that rescue mode never gets to
+const MULTI_USER_LINES: [&str; 4] = [
+    "Network Manager",
+    "Getty on tty1",
+    "Reached target Network",
+    "Reached target Multi-User System",
+];
+
+impl System {
+    /// Boot with the current kernel command line: `mem=` resizes memory,
+    /// `single` leaves services stopped and a normal boot brings the
+    /// auto-start ones back. After an unclean shutdown fsck runs first
+    pub(super) fn boot_with_params(&mut self) -> Vec<String> {
+        let params = BootParams::parse(&self.boot.get_cmdline());
+
+        let limit = params.mem_limit.unwrap_or(TOTAL_MEM).min(TOTAL_MEM);
+        let resized = self.kernel.mem.resize(limit);
+        let mut lines = self.boot.simulate_boot_sequence(&mut self.kernel.mem);
+        if std::mem::take(&mut self.kernel.unclean_shutdown) {
+            self.insert_boot_fsck(&mut lines);
+        }
+        if !resized {
+            lines.push(format!(
+                "[    0.000000] mem={}K ignored: memory in use",
+                limit / 1024
+            ));
+        }
+
+        self.previous_runlevel = None;
+        self.runlevel = if params.single_user { 1 } else { 3 };
+        if params.single_user {
+            for name in self.services.start_order().into_iter().rev() {
+                let _ = self.systemctl_stop(&name);
+            }
+            lines.retain(|line| !MULTI_USER_LINES.iter().any(|unit| line.contains(unit)));
+            let at = lines
+                .iter()
+                .rposition(|line| line.starts_with('['))
+                .map_or(lines.len(), |i| i + 1);
+            lines.insert(
+                at,
+                "[    0.120000] systemd[1]: Reached target Rescue Mode (single-user, services not started)."
+                    .to_string(),
+            );
+        } else {
+            self.services.auto_start_services(&mut |name| {
+                self.kernel.proc.spawn(name, 1, &mut self.kernel.mem)
+            });
+        }
+        self.refresh_motd();
+        lines
+    }
+
+    /// `fast_boot=on` in /etc/boot.conf
+    pub(super) fn fast_boot_enabled(&self) -> bool {
+        self.kernel.fs.resolve(BOOT_CONF_PATH).is_some_and(|node| {
+            node.data
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .any(|(key, value)| key.trim() == "fast_boot" && value.trim() == "on")
+        })
+    }
+
+    /// `grub fastboot [on|off]`
+    pub(super) fn cmd_grub_fastboot(&mut self, args: &[&str]) -> CmdOutput {
+        let on = match args {
+            [] => {
+                let state = if self.fast_boot_enabled() {
+                    "on"
+                } else {
+                    "off"
+                };
+                return CmdOutput::ok(format!("fast boot: {}", state));
+            }
+            ["on"] => true,
+            ["off"] => false,
+            _ => return CmdOutput::usage("usage: grub fastboot [on|off]".to_string()),
+        };
+        let data = format!("fast_boot={}\n", if on { "on" } else { "off" });
+        let written = if self.kernel.fs.resolve(BOOT_CONF_PATH).is_some() {
+            self.kernel.fs.write_file(BOOT_CONF_PATH, &data)
+        } else {
+            self.kernel.fs.create_file(BOOT_CONF_PATH, &data)
+        };
+        match written {
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) => CmdOutput::error(1, format!("grub: {}: {}", BOOT_CONF_PATH, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_boot_setting_round_trip() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        assert!(!sys.fast_boot_enabled());
+        assert_eq!(sys.cmd_grub_fastboot(&["on"]).flatten(), "");
+        assert!(sys.fast_boot_enabled());
+        assert_eq!(sys.cmd_grub_fastboot(&[]).flatten(), "fast boot: on");
+        sys.cmd_grub_fastboot(&["off"]);
+        assert!(!sys.fast_boot_enabled());
+        assert!(sys
+            .cmd_grub_fastboot(&["maybe"])
+            .flatten()
+            .starts_with("usage"));
+
+        let first = sys.kernel.next_boot_line().unwrap();
+        let rest = sys.kernel.drain_boot_log();
+        assert!(!rest.contains(&first));
+        assert!(rest.last().is_some_and(|l| l.contains("BOOT_COMPLETE")));
+        assert!(sys.kernel.drain_boot_log().is_empty());
+    }
+}
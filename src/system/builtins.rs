@@ -0,0 +1,207 @@
+//! Every command the shell runs itself, as registered into
+//! `shell.registry` when the system starts. `exec` finds a command here by
+//! name or alias; anything else is a package command or not found.
+use super::System;
+use crate::shell::{Builtin, CmdOutput};
+
+pub(super) static BUILTINS: &[Builtin] = &[
+    Builtin::new("reboot", |sys, args| sys.cmd_power("reboot", args)),
+    Builtin::new("halt", |sys, args| sys.cmd_power("halt", args)),
+    Builtin::new("poweroff", |sys, args| sys.cmd_power("poweroff", args)),
+    Builtin::new("shutdown", |sys, args| sys.cmd_shutdown(args)),
+    Builtin::new("init", |sys, args| sys.cmd_init(args)).with_aliases(&["telinit"]),
+    Builtin::new("runlevel", |sys, _| sys.cmd_runlevel()),
+    Builtin::new("neofetch", |sys, args| sys.cmd_neofetch(args)),
+    Builtin::new("motd", |sys, args| sys.cmd_motd(args)),
+    Builtin::new("echo", |sys, args| sys.cmd_echo(args)).spawning(),
+    Builtin::new("sudo", |sys, args| sys.handle_sudo(args)),
+    Builtin::new("help", |sys, _| sys.cmd_help()),
+    Builtin::new("man", |sys, args| sys.cmd_man_paged(args)),
+    Builtin::new("less", |sys, args| sys.cmd_pager("less", args)),
+    Builtin::new("more", |sys, args| sys.cmd_pager("more", args)),
+    Builtin::new("dmesg", |sys, args| sys.cmd_dmesg(args)),
+    Builtin::new("nano", |sys, args| sys.cmd_nano(args)).with_aliases(&["vi", "vim"]),
+    Builtin::new("python", |sys, args| sys.cmd_python(args)),
+    Builtin::new("lua", |sys, args| sys.cmd_lua(args)),
+    Builtin::new("sqlite3", |sys, args| sys.cmd_sqlite3(args)),
+    Builtin::new("git", |sys, args| sys.cmd_git(args)),
+    Builtin::new("httpd", |sys, args| sys.cmd_httpd(args)),
+    Builtin::new("tcpdump", |sys, args| sys.cmd_tcpdump(args)),
+    Builtin::new("wscat", |sys, args| sys.cmd_wscat(args)),
+    Builtin::new("downloads", |sys, args| sys.cmd_downloads(args)),
+    Builtin::new("download", |sys, args| sys.cmd_download(args)),
+    Builtin::new("doom", |sys, args| sys.cmd_doom(args)),
+    Builtin::new("doommap", |sys, args| sys.cmd_doommap(args)),
+    Builtin::new("renderer", |sys, args| sys.cmd_renderer(args)),
+    Builtin::new("view", |sys, args| sys.cmd_view(args)),
+    Builtin::new("snake", |sys, args| sys.cmd_snake(args)),
+    Builtin::new("snapshot", |sys, args| sys.cmd_snapshot(args)),
+    Builtin::new("tmux", |sys, args| sys.cmd_tmux(args)),
+    Builtin::new("idle", |sys, args| sys.cmd_idle(args)),
+    Builtin::new("xset", |sys, args| sys.cmd_xset(args)),
+    Builtin::new("volume", |sys, args| sys.cmd_volume(args)),
+    Builtin::new("beep", |_, args| System::cmd_beep(args)),
+    Builtin::new("pong", |sys, args| sys.cmd_pong(args)),
+    Builtin::new("screensaver", |sys, args| sys.cmd_screensaver(args)),
+    Builtin::new("memtest", |sys, args| sys.cmd_memtest(args)),
+    Builtin::new("cmatrix", |sys, args| {
+        let args: Vec<&str> = std::iter::once("matrix")
+            .chain(args.iter().copied())
+            .collect();
+        sys.cmd_screensaver(&args)
+    }),
+    Builtin::new("wget", |sys, args| sys.cmd_wget(args)),
+    Builtin::new("curl", |sys, args| sys.cmd_curl(args)),
+    Builtin::new("lynx", |sys, args| sys.cmd_lynx(args)).with_aliases(&["w3m"]),
+    Builtin::new("rss", |sys, args| sys.cmd_rss(args)),
+    Builtin::new("weather", |sys, args| sys.cmd_weather(args)),
+    Builtin::new("iss", |sys, args| sys.cmd_iss(args)),
+    Builtin::new("gh", |sys, args| sys.cmd_gh(args)),
+    Builtin::new("myip", |sys, _| sys.cmd_myip()),
+    Builtin::new("ls", |sys, args| sys.cmd_ls(args)).spawning(),
+    Builtin::new("cd", |sys, args| sys.cmd_cd(args)),
+    Builtin::new("pwd", |sys, _| CmdOutput::ok(sys.kernel.fs.cwd.clone())).spawning(),
+    Builtin::new("cat", |sys, args| sys.cmd_cat(args)).spawning(),
+    Builtin::new("grep", |sys, args| sys.cmd_grep(args)),
+    Builtin::new("find", |sys, args| sys.cmd_find(args)),
+    Builtin::new("wc", |sys, args| sys.cmd_wc(args)),
+    Builtin::new("cksum", |sys, args| sys.cmd_cksum(args)),
+    Builtin::new("head", |sys, args| sys.cmd_head(args)),
+    Builtin::new("tail", |sys, args| sys.cmd_tail(args)),
+    Builtin::new("diff", |sys, args| sys.cmd_diff(args)),
+    Builtin::new("xxd", |sys, args| sys.cmd_xxd(args)),
+    Builtin::new("hexdump", |sys, args| sys.cmd_hexdump(args)),
+    Builtin::new("hd", |sys, args| {
+        let args: Vec<&str> = std::iter::once("-C").chain(args.iter().copied()).collect();
+        sys.cmd_hexdump(&args)
+    }),
+    Builtin::new("strings", |sys, args| sys.cmd_strings(args)),
+    Builtin::new("base64", |sys, args| sys.cmd_base64(args)),
+    Builtin::new("seq", |sys, args| sys.cmd_seq(args)),
+    Builtin::new("yes", |sys, args| sys.cmd_yes(args)),
+    Builtin::new("shuf", |sys, args| sys.cmd_shuf(args)),
+    Builtin::new("xargs", |sys, args| sys.cmd_xargs(args)),
+    Builtin::new("md5sum", |sys, args| sys.cmd_digest("md5sum", args)),
+    Builtin::new("sha256sum", |sys, args| sys.cmd_digest("sha256sum", args)),
+    Builtin::new("sort", |sys, args| sys.cmd_sort(args)),
+    Builtin::new("uniq", |sys, args| sys.cmd_uniq(args)),
+    Builtin::new("cut", |sys, args| sys.cmd_cut(args)),
+    Builtin::new("tr", |sys, args| sys.cmd_tr(args)),
+    Builtin::new("tee", |sys, args| sys.cmd_tee(args)),
+    Builtin::new("which", |sys, args| sys.cmd_which(args)),
+    Builtin::new("whereis", |sys, args| sys.cmd_whereis(args)),
+    Builtin::new("file", |sys, args| sys.cmd_file(args)),
+    Builtin::new("ln", |sys, args| sys.cmd_ln(args)),
+    Builtin::new("cp", |sys, args| sys.cmd_cp(args)),
+    Builtin::new("mv", |sys, args| sys.cmd_mv(args)),
+    Builtin::new("rsync", |sys, args| sys.cmd_rsync(args)),
+    Builtin::new("chmod", |sys, args| sys.cmd_chmod(args)),
+    Builtin::new("chown", |sys, args| sys.cmd_chown(args)),
+    Builtin::new("chgrp", |sys, args| sys.cmd_chgrp(args)),
+    Builtin::new("groupadd", |sys, args| sys.cmd_groupadd(args)),
+    Builtin::new("usermod", |sys, args| sys.cmd_usermod(args)),
+    Builtin::new("df", |sys, args| sys.cmd_df(args)),
+    Builtin::new("du", |sys, args| sys.cmd_du(args)),
+    Builtin::new("tar", |sys, args| sys.cmd_tar(args)),
+    Builtin::new("gzip", |sys, args| sys.cmd_gzip(args, "gzip")),
+    Builtin::new("gunzip", |sys, args| sys.cmd_gzip(args, "gunzip")),
+    Builtin::new("zip", |sys, args| sys.cmd_zip(args, "zip")),
+    Builtin::new("unzip", |sys, args| sys.cmd_zip(args, "unzip")),
+    Builtin::new("apt", |sys, args| sys.cmd_apt(args)).with_aliases(&["apt-get"]),
+    Builtin::new("dpkg", |sys, args| sys.cmd_dpkg(args)),
+    Builtin::new("dpkg-deb", |sys, args| sys.cmd_dpkg_deb(args)),
+    Builtin::new("top", |sys, args| sys.cmd_top(args)),
+    Builtin::new("htop", |sys, args| sys.cmd_htop(args)),
+    Builtin::new("awk", |sys, args| sys.cmd_awk(args)),
+    Builtin::new("sed", |sys, args| sys.cmd_sed(args)),
+    Builtin::new("alias", |sys, args| sys.cmd_alias(args)),
+    Builtin::new("unalias", |sys, args| sys.cmd_unalias(args)),
+    Builtin::new("source", |sys, args| sys.cmd_source(args)).with_aliases(&["."]),
+    Builtin::new("touch", |sys, args| sys.cmd_touch(args)),
+    Builtin::new("mkdir", |sys, args| sys.cmd_mkdir(args)),
+    Builtin::new("rmdir", |sys, args| sys.cmd_rmdir(args)),
+    Builtin::new("rm", |sys, args| sys.cmd_rm(args)),
+    Builtin::new("undo-rm", |sys, _| sys.cmd_undo_rm()),
+    Builtin::new("clear", |sys, args| sys.cmd_clear(args)),
+    Builtin::new("reset", |sys, _| sys.cmd_reset()),
+    Builtin::new("exit", |sys, _| sys.cmd_exit()),
+    Builtin::new("ps", |sys, args| sys.cmd_ps(args)),
+    Builtin::new("nice", |sys, args| sys.cmd_nice(args)),
+    Builtin::new("renice", |sys, args| sys.cmd_renice(args)),
+    Builtin::new("ulimit", |sys, args| sys.cmd_ulimit(args)),
+    Builtin::new("kill", |sys, args| sys.cmd_kill(args)),
+    Builtin::new("pgrep", |sys, args| sys.cmd_pgrep(args)),
+    Builtin::new("pkill", |sys, args| sys.cmd_pkill(args)),
+    Builtin::new("lsof", |sys, args| sys.cmd_lsof(args)),
+    Builtin::new("perf", |sys, args| sys.cmd_perf(args)),
+    Builtin::new("sysbench", |sys, args| sys.cmd_sysbench(args)),
+    Builtin::new("jobs", |sys, args| sys.cmd_jobs(args)),
+    Builtin::new("bg", |sys, args| sys.cmd_bg(args)),
+    Builtin::new("fg", |sys, args| sys.cmd_fg(args)),
+    Builtin::new("disown", |sys, args| sys.cmd_disown(args)),
+    Builtin::new("nohup", |sys, args| sys.cmd_nohup(args)),
+    Builtin::new("uname", |sys, args| sys.cmd_uname(args)).spawning(),
+    Builtin::new("hostname", |sys, _| sys.cmd_hostname()).spawning(),
+    Builtin::new("id", |sys, args| sys.cmd_id(args)).spawning(),
+    Builtin::new("groups", |sys, args| sys.cmd_groups(args)),
+    Builtin::new("who", |sys, args| sys.cmd_who(args)),
+    Builtin::new("w", |sys, args| sys.cmd_w(args)),
+    Builtin::new("last", |sys, args| sys.cmd_last(args)),
+    Builtin::new("mail", |sys, args| sys.cmd_mail(args)).with_aliases(&["mailx"]),
+    Builtin::new("su", |sys, args| sys.cmd_su(args)),
+    Builtin::new("whoami", |sys, _| CmdOutput::ok(sys.current_user())).spawning(),
+    Builtin::new("stat", |sys, args| sys.cmd_stat(args)),
+    Builtin::new("mount", |sys, args| sys.cmd_mount(args)),
+    Builtin::new("umount", |sys, args| sys.cmd_umount(args)),
+    Builtin::new("dd", |sys, args| sys.cmd_dd(args)),
+    Builtin::new("mkfs", |sys, args| sys.cmd_mkfs("mkfs", args)),
+    Builtin::new("mkfs.ext2", |sys, args| sys.cmd_mkfs("mkfs.ext2", args)),
+    Builtin::new("mkfs.ext3", |sys, args| sys.cmd_mkfs("mkfs.ext3", args)),
+    Builtin::new("mkfs.ext4", |sys, args| sys.cmd_mkfs("mkfs.ext4", args))
+        .with_aliases(&["mke2fs"]),
+    Builtin::new("losetup", |sys, args| sys.cmd_losetup(args)),
+    Builtin::new("fsck", |sys, args| sys.cmd_fsck(args)).with_aliases(&["e2fsck", "fsck.ext4"]),
+    Builtin::new("uptime", |sys, args| sys.cmd_uptime(args)),
+    Builtin::new("date", |sys, _| sys.cmd_date()),
+    Builtin::new("free", |sys, _| sys.cmd_free()).spawning(),
+    Builtin::new("history", |sys, args| sys.cmd_history(args)),
+    Builtin::new("env", |sys, _| sys.cmd_env()),
+    Builtin::new("export", |sys, args| sys.cmd_export(args)),
+    Builtin::new("true", |_, _| CmdOutput::default()).with_aliases(&[":"]),
+    Builtin::new("false", |_, _| CmdOutput::error(1, "")),
+    Builtin::new("netstat", |sys, args| sys.cmd_netstat(args)),
+    Builtin::new("ss", |sys, args| sys.cmd_ss(args)),
+    Builtin::new("socket", |sys, args| sys.cmd_socket(args)),
+    Builtin::new("service", |sys, args| sys.cmd_service(args)),
+    Builtin::new("systemctl", |sys, args| sys.cmd_systemctl(args)),
+    Builtin::new("journalctl", |sys, args| sys.cmd_journalctl(args)),
+    Builtin::new("ping", |sys, args| sys.cmd_ping(args)),
+    Builtin::new("traceroute", |sys, args| sys.cmd_traceroute(args)).with_aliases(&["tracert"]),
+    Builtin::new("ifconfig", |sys, args| sys.cmd_ifconfig(args)),
+    Builtin::new("ip", |sys, args| sys.cmd_ip(args)),
+    Builtin::new("route", |sys, args| sys.cmd_route(args)),
+    Builtin::new("arp", |sys, args| sys.cmd_arp(args)),
+    Builtin::new("host", |sys, args| sys.cmd_host(args)).with_aliases(&["nslookup", "dig"]),
+    Builtin::new("nc", |sys, args| sys.cmd_nc(args)).with_aliases(&["netcat"]),
+    Builtin::new("hasgrub", |sys, _| sys.cmd_hasgrub()),
+    Builtin::new("grub", |sys, args| sys.cmd_grub(args)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins_register_under_every_name() {
+        let sys = System::new();
+        let registry = &sys.shell.registry;
+        for builtin in BUILTINS {
+            assert!(registry.is_builtin(builtin.name));
+        }
+        assert!(registry.is_builtin("vim"));
+        assert!(registry.is_builtin("apt-get"));
+        assert!(registry.spawns("ls"));
+        assert!(!registry.spawns("cd"));
+        assert!(!registry.has("nosuchcmd"));
+    }
+}
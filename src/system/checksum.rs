@@ -0,0 +1,226 @@
+//! `base64`, `md5sum` and `sha256sum` over file contents
+use super::{bytes_to_data, System, PIPE_STDIN};
+use crate::cpp_accel::{b64_decode, b64_encode};
+use crate::digest;
+use crate::shell::CmdOutput;
+
+const BASE64_USAGE: &str = "usage: base64 [-d] [-i] [-w COLS] [FILE]";
+
+impl System {
+    /// `base64 [-d] [-w COLS] [FILE]`: encode, wrapping at 76 columns, or
+    /// decode with `-d`
+    pub(super) fn cmd_base64(&mut self, args: &[&str]) -> CmdOutput {
+        let (mut decode, mut ignore_garbage, mut wrap) = (false, false, 76);
+        let mut files = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-d" | "--decode" => decode = true,
+                "-i" | "--ignore-garbage" => ignore_garbage = true,
+                "-w" | "--wrap" => match rest.next().and_then(|n| n.parse().ok()) {
+                    Some(cols) => wrap = cols,
+                    None => return CmdOutput::error(1, "base64: invalid wrap size"),
+                },
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "base64: invalid option -- '{}'\n{}",
+                            &flag[1..],
+                            BASE64_USAGE
+                        ),
+                    )
+                }
+                file => files.push(file),
+            }
+        }
+        let [path] = files[..] else {
+            return CmdOutput::error(1, BASE64_USAGE);
+        };
+        let data = match self.read_file_bytes(path) {
+            Ok(data) => data,
+            Err(e) => return CmdOutput::error(1, format!("base64: {}", e)),
+        };
+        if decode {
+            let text: String = String::from_utf8_lossy(&data)
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .filter(|c| !ignore_garbage || c.is_ascii_alphanumeric() || "+/=".contains(*c))
+                .collect();
+            return match b64_decode(&text) {
+                Ok(bytes) => CmdOutput::ok(bytes_to_data(bytes)),
+                Err(_) => CmdOutput::error(1, "base64: invalid input"),
+            };
+        }
+        let encoded = b64_encode(&data);
+        if wrap == 0 {
+            return CmdOutput::ok(encoded);
+        }
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(wrap)
+            .map(|line| std::str::from_utf8(line).unwrap_or_default())
+            .collect();
+        CmdOutput::ok(lines.join("\n"))
+    }
+
+    /// `md5sum` and `sha256sum`: print sums, or with `-c` check the files
+    /// listed in a file of sums
+    pub(super) fn cmd_digest(&mut self, prog: &str, args: &[&str]) -> CmdOutput {
+        let hash = |data: &[u8]| match prog {
+            "md5sum" => digest::hex(&digest::md5(data)),
+            _ => digest::hex(&digest::sha256(data)),
+        };
+        let (check, files): (Vec<&str>, Vec<&str>) = args
+            .iter()
+            .partition(|arg| matches!(**arg, "-c" | "--check"));
+        if let Some(flag) = files.iter().find(|a| a.starts_with('-') && a.len() > 1) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "{}: unrecognized option '{}'\nusage: {} [-c] FILE...",
+                    prog, flag, prog
+                ),
+            );
+        }
+        if files.is_empty() {
+            return CmdOutput::error(1, format!("usage: {} [-c] FILE...", prog));
+        }
+        // What a file is called in the output: `-` for the pipe
+        let shown = |path: &str| match path {
+            PIPE_STDIN => "-".to_string(),
+            path => path.to_string(),
+        };
+
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        if check.is_empty() {
+            for path in files {
+                match self.read_file_bytes(path) {
+                    Ok(data) => out.push(format!("{}  {}", hash(&data), shown(path))),
+                    Err(e) => errors.push(format!("{}: {}", prog, e)),
+                }
+            }
+        } else {
+            let (mut failed, mut unreadable, mut malformed) = (0, 0, 0);
+            for list in files {
+                let sums = match self.read_file_bytes(list) {
+                    Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+                    Err(e) => {
+                        errors.push(format!("{}: {}", prog, e));
+                        continue;
+                    }
+                };
+                for line in sums.lines().filter(|l| !l.trim().is_empty()) {
+                    // `SUM  FILE`, or `SUM *FILE` for binary mode
+                    let Some((sum, path)) = line
+                        .split_once("  ")
+                        .or_else(|| line.split_once(" *"))
+                        .filter(|(sum, _)| sum.chars().all(|c| c.is_ascii_hexdigit()))
+                    else {
+                        malformed += 1;
+                        continue;
+                    };
+                    match self.read_file_bytes(path) {
+                        Ok(data) if hash(&data).eq_ignore_ascii_case(sum) => {
+                            out.push(format!("{}: OK", path))
+                        }
+                        Ok(_) => {
+                            failed += 1;
+                            out.push(format!("{}: FAILED", path));
+                        }
+                        Err(e) => {
+                            unreadable += 1;
+                            errors.push(format!("{}: {}", prog, e));
+                            out.push(format!("{}: FAILED open or read", path));
+                        }
+                    }
+                }
+            }
+            let plural = |n: usize| if n == 1 { "" } else { "s" };
+            if malformed > 0 {
+                errors.push(format!(
+                    "{}: WARNING: {} line{} improperly formatted",
+                    prog,
+                    malformed,
+                    plural(malformed)
+                ));
+            }
+            if unreadable > 0 {
+                errors.push(format!(
+                    "{}: WARNING: {} listed file{} could not be read",
+                    prog,
+                    unreadable,
+                    plural(unreadable)
+                ));
+            }
+            if failed > 0 {
+                errors.push(format!(
+                    "{}: WARNING: {} computed checksum{} did NOT match",
+                    prog,
+                    failed,
+                    plural(failed)
+                ));
+            }
+        }
+        CmdOutput {
+            stdout: out.join("\n"),
+            status: i32::from(!errors.is_empty()),
+            stderr: errors.join("\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip_and_checked_sums() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_file("/tmp/a", "abc").unwrap();
+        assert_eq!(sys.cmd_base64(&["/tmp/a"]).stdout, "YWJj");
+        sys.kernel.fs.create_file("/tmp/a.b64", "YW\nJj\n").unwrap();
+        assert_eq!(sys.cmd_base64(&["-d", "/tmp/a.b64"]).stdout, "abc");
+        assert_eq!(sys.cmd_base64(&["-d", "/tmp/a"]).status, 1);
+
+        let sum = sys.cmd_digest("sha256sum", &["/tmp/a"]).stdout;
+        assert_eq!(
+            sum,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  /tmp/a"
+        );
+        assert_eq!(
+            sys.cmd_digest("md5sum", &["/tmp/a"]).stdout,
+            "900150983cd24fb0d6963f7d28e17f72  /tmp/a"
+        );
+        sys.kernel.fs.create_file("/tmp/SUMS", &sum).unwrap();
+        assert_eq!(
+            sys.cmd_digest("sha256sum", &["-c", "/tmp/SUMS"]).stdout,
+            "/tmp/a: OK"
+        );
+        sys.kernel.fs.write_file("/tmp/a", "abd").unwrap();
+        let checked = sys.cmd_digest("sha256sum", &["-c", "/tmp/SUMS"]);
+        assert_eq!(checked.stdout, "/tmp/a: FAILED");
+        assert_eq!(
+            checked.stderr,
+            "sha256sum: WARNING: 1 computed checksum did NOT match"
+        );
+        assert_eq!(checked.status, 1);
+
+        // Files the user can't read are neither printed nor hashed
+        let denied = sys.cmd_base64(&["/etc/shadow"]);
+        assert_eq!(denied.stderr, "base64: /etc/shadow: Permission denied");
+        assert_eq!(denied.status, 1);
+        let denied = sys.cmd_digest("md5sum", &["/etc/shadow"]);
+        assert_eq!(denied.stdout, "");
+        assert_eq!(denied.stderr, "md5sum: /etc/shadow: Permission denied");
+        sys.kernel
+            .fs
+            .create_file("/tmp/SHADOW", &format!("{}  /etc/shadow", "0".repeat(64)))
+            .unwrap();
+        let checked = sys.cmd_digest("sha256sum", &["-c", "/tmp/SHADOW"]);
+        assert_eq!(checked.stdout, "/etc/shadow: FAILED open or read");
+        assert_eq!(checked.status, 1);
+    }
+}
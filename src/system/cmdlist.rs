@@ -0,0 +1,252 @@
+use super::System;
+use crate::shell::CmdOutput;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Connector {
+    /// First command, or after `;`
+    Always,
+    /// After `&&`: only if the last command succeeded
+    And,
+    /// After `||`: only if it failed
+    Or,
+}
+
+/// Split a line at `;`, `&&` and `||` outside quotes (and not escaped, so
+/// `find -exec ... \;` survives). Each command comes with the operator in
+/// front of it
+fn split_list(line: &str) -> Result<Vec<(Connector, &str)>, String> {
+    let mut out = Vec::new();
+    let mut connector = Connector::Always;
+    let mut start = 0;
+    let mut quote = None;
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == b'\'' || c == b'"' => quote = Some(c),
+            None if c == b'\\' => i += 1,
+            None => {
+                let op = match (c, bytes.get(i + 1)) {
+                    (b'&', Some(b'&')) => Some((Connector::And, "&&")),
+                    (b'|', Some(b'|')) => Some((Connector::Or, "||")),
+                    (b';', _) => Some((Connector::Always, ";")),
+                    _ => None,
+                };
+                if let Some((next, token)) = op {
+                    let command = line[start..i].trim();
+                    if command.is_empty() {
+                        return Err(format!(
+                            "sh: syntax error near unexpected token `{}'",
+                            token
+                        ));
+                    }
+                    out.push((connector, command));
+                    connector = next;
+                    i += token.len();
+                    start = i;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    let command = line[start..].trim();
+    if command.is_empty() {
+        // A trailing `;` is fine, a dangling `&&` or `||` is not
+        if connector != Connector::Always {
+            return Err("sh: syntax error: unexpected end of line".to_string());
+        }
+    } else {
+        out.push((connector, command));
+    }
+    Ok(out)
+}
+
+/// Replace `$?` outside single quotes
+fn expand_status(command: &str, status: i32) -> String {
+    let mut out = String::with_capacity(command.len());
+    let mut in_quote = false;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            in_quote = !in_quote;
+        } else if c == '$' && !in_quote && chars.peek() == Some(&'?') {
+            chars.next();
+            out.push_str(&status.to_string());
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Several commands' output as one, ending with `status`
+fn joined(outputs: Vec<CmdOutput>, status: i32) -> CmdOutput {
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    for output in outputs {
+        stdout.extend(Some(output.stdout).filter(|s| !s.is_empty()));
+        stderr.extend(Some(output.stderr).filter(|s| !s.is_empty()));
+    }
+    CmdOutput {
+        stdout: stdout.join("\n"),
+        stderr: stderr.join("\n"),
+        status,
+    }
+}
+
+impl System {
+    /// Run a typed line: commands joined by `;`, `&&` and `||`, each of
+    /// which sets `$?` for the next
+    pub(super) fn exec_line(&mut self, line: &str) -> String {
+        if self.sudo_waiting_password {
+            let output = self.exec_command(line);
+            self.last_status = output.status;
+            return output.flatten();
+        }
+        match self.run_list(line) {
+            Ok(outputs) => outputs
+                .into_iter()
+                .map(CmdOutput::flatten)
+                .filter(|output| !output.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => {
+                self.last_status = 2;
+                e
+            }
+        }
+    }
+
+    /// Run a line from inside another command, such as `sudo`, `nice` or
+    /// `source`: what it printed, ending with the status of its last command
+    pub(super) fn exec_nested(&mut self, line: &str) -> CmdOutput {
+        let in_exec = std::mem::replace(&mut self.in_exec, true);
+        let outputs = self.run_list(line);
+        self.in_exec = in_exec;
+        self.deliver_events();
+        match outputs {
+            Ok(outputs) => joined(outputs, self.last_status),
+            Err(e) => CmdOutput::error(2, e),
+        }
+    }
+
+    /// Run a script line by line with [`System::exec_nested`], skipping
+    /// blank lines and comments; `expand` rewrites each line first
+    pub(super) fn exec_script(
+        &mut self,
+        script: &str,
+        expand: impl Fn(&str) -> String,
+    ) -> CmdOutput {
+        let mut outputs = Vec::new();
+        for line in script.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            outputs.push(self.exec_nested(&expand(line)));
+        }
+        let status = outputs.last().map_or(0, |output| output.status);
+        joined(outputs, status)
+    }
+
+    /// The commands of `line` that ran, in order
+    fn run_list(&mut self, line: &str) -> Result<Vec<CmdOutput>, String> {
+        let mut outputs = Vec::new();
+        for (connector, command) in split_list(line)? {
+            let run = match connector {
+                Connector::Always => true,
+                Connector::And => self.last_status == 0,
+                Connector::Or => self.last_status != 0,
+            };
+            if !run {
+                continue;
+            }
+            let command = expand_status(command, self.last_status);
+            let output = self.exec_command(&command);
+            self.last_status = output.status;
+            outputs.push(output);
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_lists_follow_exit_status() {
+        assert_eq!(
+            split_list("a && b || c; d;").unwrap(),
+            [
+                (Connector::Always, "a"),
+                (Connector::And, "b"),
+                (Connector::Or, "c"),
+                (Connector::Always, "d")
+            ]
+        );
+        assert_eq!(
+            split_list("echo 'x && y' | wc").unwrap(),
+            [(Connector::Always, "echo 'x && y' | wc")]
+        );
+        assert_eq!(split_list("find -exec rm {} \\;").unwrap().len(), 1);
+        assert!(split_list("&& ls").is_err());
+        assert!(split_list("ls ||").is_err());
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.exec("cd /tmp");
+        assert_eq!(sys.exec("mkdir foo && cd foo && pwd"), "/tmp/foo");
+        let out = sys.exec("cd /nope && echo yes || echo no");
+        assert!(out.starts_with("cd: /nope: ") && out.ends_with("\nno"));
+        assert_eq!(sys.kernel.fs.cwd, "/tmp/foo");
+        assert_eq!(sys.exec("false; echo $?"), "1");
+        assert_eq!(sys.exec("true && echo '$?' $?"), "'$?' 0");
+        assert!(sys.exec("nosuchcmd; echo $?").ends_with("\n127"));
+        assert_eq!(
+            sys.exec("cat /nope || echo $?"),
+            "cat: /nope: No such file or directory\n1"
+        );
+
+        // Errors skip redirections and pipes and go to the terminal
+        assert_eq!(
+            sys.exec("cat /nope > /tmp/out"),
+            "cat: /nope: No such file or directory"
+        );
+        assert_eq!(sys.last_exit_code(), 1);
+        assert_eq!(sys.kernel.fs.resolve("/tmp/out").unwrap().data, "");
+        assert_eq!(
+            sys.exec("cat /nope | sort"),
+            "cat: /nope: No such file or directory"
+        );
+        assert_eq!(sys.last_exit_code(), 0);
+
+        // Only the status a command reports counts, not what it printed
+        sys.exec("echo Error: disk full > /tmp/e.txt");
+        assert_eq!(sys.last_exit_code(), 0);
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/e.txt").unwrap().data,
+            "Error: disk full"
+        );
+        sys.kernel
+            .fs
+            .create_file("/tmp/f.txt", "Failed to start\nok")
+            .unwrap();
+        assert_eq!(sys.exec("cat /tmp/f.txt | sort"), "Failed to start\nok");
+        assert_eq!(sys.last_exit_code(), 0);
+        sys.kernel
+            .fs
+            .create_file("/tmp/u.txt", "usage: see README")
+            .unwrap();
+        assert_eq!(
+            sys.exec("cat /tmp/u.txt && echo yes || echo no"),
+            "usage: see README\nyes"
+        );
+        assert_eq!(
+            sys.exec("cp /tmp/u.txt > /tmp/o; echo $?"),
+            "usage: cp [-rpav] SOURCE... DEST\n2"
+        );
+    }
+}
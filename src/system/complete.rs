@@ -0,0 +1,225 @@
+use super::System;
+
+// Flags offered when the word being completed starts with `-`
+const COMMAND_FLAGS: &[(&str, &[&str])] = &[
+    ("base64", &["-d", "-i", "-w"]),
+    ("cat", &["-n"]),
+    ("chmod", &["-R"]),
+    ("chown", &["-R"]),
+    ("clear", &["-x"]),
+    ("cmatrix", &["-C", "-d", "-s"]),
+    ("cp", &["-r", "-R", "-p", "-a", "-i", "-v"]),
+    ("curl", &["-I", "-L", "-o", "-s", "-v"]),
+    ("lynx", &["-dump"]),
+    ("weather", &["-u"]),
+    ("mail", &["-u"]),
+    ("df", &["-h"]),
+    ("diff", &["-u", "-q", "--color"]),
+    ("du", &["-a", "-h", "-s", "-d", "--max-depth="]),
+    (
+        "find",
+        &[
+            "-name",
+            "-iname",
+            "-type",
+            "-size",
+            "-maxdepth",
+            "-mindepth",
+            "-exec",
+        ],
+    ),
+    ("free", &["-h", "-m"]),
+    ("fsck", &["-n", "-y", "-p", "-f"]),
+    (
+        "grep",
+        &["-i", "-n", "-r", "-v", "-c", "-l", "-F", "--color"],
+    ),
+    ("head", &["-n", "-c"]),
+    ("hexdump", &["-C", "-n", "-s"]),
+    ("history", &["-c"]),
+    ("kill", &["-TERM", "-KILL", "-STOP", "-CONT", "-l"]),
+    ("ln", &["-s", "-f"]),
+    ("losetup", &["-a", "-l", "-f", "--show", "-d", "-D"]),
+    ("download", &["-o"]),
+    (
+        "ls",
+        &[
+            "-a", "-A", "-l", "-la", "-h", "-i", "-R", "-S", "-t", "-r", "-1",
+        ],
+    ),
+    ("lsof", &["-c", "-i", "-p", "-u"]),
+    ("md5sum", &["-c"]),
+    ("mkdir", &["-p", "-v"]),
+    ("mkfs.ext4", &["-F", "-q", "-L"]),
+    ("motd", &["-r"]),
+    ("mv", &["-i", "-v", "-f"]),
+    ("neofetch", &["--ascii", "--off", "--stdout"]),
+    ("nice", &["-n"]),
+    ("pgrep", &["-a", "-f", "-l", "-u", "-x"]),
+    ("ping", &["-c"]),
+    ("pkill", &["-f", "-u", "-x", "-KILL", "-STOP", "-CONT"]),
+    ("ps", &["-e", "-ef", "-A"]),
+    ("renice", &["-n", "-p"]),
+    (
+        "rm",
+        &["-r", "-R", "-rf", "-f", "-i", "-v", "--trash", "--no-trash"],
+    ),
+    ("rsync", &["-a", "-r", "-v"]),
+    ("screensaver", &["-C", "-d", "-s"]),
+    ("seq", &["-s", "-w"]),
+    ("sha256sum", &["-c"]),
+    ("shuf", &["-e", "-i", "-n", "-r"]),
+    ("shutdown", &["-h", "-H", "-P", "-r", "-c"]),
+    ("sort", &["-n", "-r", "-u"]),
+    ("strings", &["-a", "-n"]),
+    ("tail", &["-n", "-c"]),
+    ("tmux", &["-d", "-h", "-s", "-t", "-v"]),
+    ("touch", &["-a", "-m", "-c", "-t", "-d"]),
+    ("su", &["-", "-l", "--login"]),
+    ("sysbench", &["--time="]),
+    ("uname", &["-a", "-r", "-s", "-m", "-n"]),
+    ("ulimit", &["-a", "-H", "-n", "-S", "-u", "-v"]),
+    ("uniq", &["-c", "-d", "-u"]),
+    ("uptime", &["-p", "-s"]),
+    ("w", &["-h"]),
+    ("wc", &["-l", "-w", "-c"]),
+    ("who", &["-b", "-H", "-m", "-q"]),
+    ("xargs", &["-0", "-I", "-n", "-r", "-t"]),
+    ("xxd", &["-c", "-g", "-l", "-p", "-r", "-s", "-u"]),
+];
+
+// Commands whose first argument is another command
+const COMMAND_PREFIXES: &[&str] = &["sudo", "man", "which", "whereis", "nice", "nohup", "xargs"];
+
+/// Longest prefix shared by every candidate
+pub(super) fn common_prefix(items: &[String]) -> String {
+    let Some(first) = items.first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for item in &items[1..] {
+        len = first
+            .char_indices()
+            .zip(item.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+impl System {
+    /// Candidates for the last word of `line`, which is everything before
+    /// the cursor. The first word completes from commands and aliases,
+    /// `-` words from the command's flags and anything else from the VFS
+    pub(super) fn completions(&self, line: &str) -> Vec<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let current = if line.ends_with(char::is_whitespace) {
+            ""
+        } else {
+            words.last().copied().unwrap_or("")
+        };
+        let previous = &words[..words.len() - usize::from(!current.is_empty())];
+
+        let mut out = match previous {
+            [] => self.complete_command(current),
+            [.., prefix] if COMMAND_PREFIXES.contains(prefix) => self.complete_command(current),
+            [cmd, ..] if current.starts_with('-') => COMMAND_FLAGS
+                .iter()
+                .find(|(name, _)| name == cmd)
+                .map(|(_, flags)| {
+                    flags
+                        .iter()
+                        .filter(|f| f.starts_with(current))
+                        .map(|f| f.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ["cd", ..] | ["rmdir", ..] => self.complete_vfs_path(current, true),
+            _ => self.complete_vfs_path(current, false),
+        };
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    fn complete_command(&self, partial: &str) -> Vec<String> {
+        self.shell
+            .registry
+            .names()
+            .map(str::to_string)
+            .chain(self.shell.aliases.keys().cloned())
+            .filter(|c| c.starts_with(partial))
+            .collect()
+    }
+
+    /// Children of the directory part of `partial` (the cwd if it has none),
+    /// keeping that part as typed so relative paths stay relative
+    fn complete_vfs_path(&self, partial: &str, dirs_only: bool) -> Vec<String> {
+        let (dir, needle) = match partial.rfind('/') {
+            Some(idx) => partial.split_at(idx + 1),
+            None => ("", partial),
+        };
+        let lookup = if dir.is_empty() {
+            self.kernel.fs.cwd.clone()
+        } else {
+            self.kernel.fs.normalize(dir)
+        };
+        let Some(parent) = self.kernel.fs.resolve(&lookup).filter(|n| n.is_dir) else {
+            return Vec::new();
+        };
+        parent
+            .children
+            .iter()
+            // Dotfiles only when asked for, like bash
+            .filter(|(name, _)| {
+                name.starts_with(needle) && (needle.starts_with('.') || !name.starts_with('.'))
+            })
+            .filter(|(_, inode)| inode.is_dir || !dirs_only)
+            .map(|(name, inode)| format!("{}{}{}", dir, name, if inode.is_dir { "/" } else { "" }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion_depends_on_position() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_dir("/tmp/box").unwrap();
+        sys.kernel.fs.create_file("/tmp/box/hosts.txt", "").unwrap();
+        sys.kernel.fs.create_file("/tmp/box/host.conf", "").unwrap();
+        sys.kernel.fs.create_file("/tmp/box/.hidden", "").unwrap();
+        sys.kernel.fs.create_dir("/tmp/box/hostdir").unwrap();
+
+        assert!(sys.completions("mkd").contains(&"mkdir".to_string()));
+        assert!(sys.completions("l").contains(&"ll".to_string()));
+
+        let abs = sys.completions("cat /tmp/box/ho");
+        assert_eq!(
+            abs,
+            [
+                "/tmp/box/host.conf",
+                "/tmp/box/hostdir/",
+                "/tmp/box/hosts.txt"
+            ]
+        );
+        assert_eq!(common_prefix(&abs), "/tmp/box/host");
+        assert_eq!(sys.completions("cd /tmp/box/ho"), ["/tmp/box/hostdir/"]);
+
+        sys.kernel.fs.cwd = "/tmp".into();
+        assert_eq!(sys.completions("ls box/hosts"), ["box/hosts.txt"]);
+        assert_eq!(sys.completions("ls box/"), sys.completions("ls box/h"));
+        assert_eq!(sys.completions("ls box/.h"), ["box/.hidden"]);
+
+        assert_eq!(sys.completions("rm -r"), ["-r", "-rf"]);
+        assert!(sys
+            .completions("sudo sys")
+            .contains(&"systemctl".to_string()));
+        assert!(sys.completions("ls ").contains(&"box/".to_string()));
+    }
+}
@@ -0,0 +1,532 @@
+use super::System;
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+
+#[derive(Default)]
+struct CopyFlags {
+    recursive: bool,
+    preserve: bool,
+    verbose: bool,
+}
+
+impl CopyFlags {
+    /// Split `-r`, `-p`, `-a` and `-v` (in any combination) from operands
+    fn parse<'a>(
+        cmd: &str,
+        args: &[&'a str],
+        allowed: &str,
+    ) -> Result<(Self, Vec<&'a str>), String> {
+        let mut flags = CopyFlags::default();
+        let mut operands = Vec::new();
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(letters) if !letters.is_empty() => {
+                    for c in letters.chars() {
+                        if !allowed.contains(c) {
+                            return Err(format!("{}: invalid option -- '{}'", cmd, c));
+                        }
+                        match c {
+                            'r' | 'R' => flags.recursive = true,
+                            'p' => flags.preserve = true,
+                            'a' => {
+                                flags.recursive = true;
+                                flags.preserve = true;
+                            }
+                            'v' => flags.verbose = true,
+                            // -f and -i: nothing here ever prompts
+                            _ => {}
+                        }
+                    }
+                }
+                _ => operands.push(*arg),
+            }
+        }
+        Ok((flags, operands))
+    }
+}
+
+fn basename(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    trimmed.rsplit('/').next().unwrap_or(trimmed)
+}
+
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+// Every file and directory under `node`, relative to it, parents first
+fn walk_tree<'a>(node: &'a Inode, prefix: &str, out: &mut Vec<(String, &'a Inode)>) {
+    let mut names: Vec<&String> = node.children.keys().collect();
+    names.sort();
+    for name in names {
+        let child = &node.children[name];
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        out.push((path.clone(), child));
+        if child.is_dir {
+            walk_tree(child, &path, out);
+        }
+    }
+}
+
+impl System {
+    /// Copies belong to whoever makes them unless `-p` keeps the original
+    /// owner. Never critical, so they can be deleted again, and new files
+    /// with inode numbers of their own
+    fn stamp_copy(&self, node: &mut Inode, preserve: bool) {
+        node.is_critical = false;
+        node.ino = 0;
+        if !preserve {
+            node.owner = self.kernel.fs.get_default_owner();
+            node.group = self.kernel.fs.get_default_group();
+        }
+        for child in node.children.values_mut() {
+            self.stamp_copy(child, preserve);
+        }
+    }
+
+    /// Where SOURCE lands: inside DEST if that is a directory
+    fn copy_target(&self, source: &str, dest: &str) -> String {
+        match self.kernel.fs.resolve(dest) {
+            Some(node) if node.is_dir => join(dest, basename(source)),
+            _ => dest.to_string(),
+        }
+    }
+
+    /// `cp [-rpav] SOURCE... DEST`
+    pub(super) fn cmd_cp(&mut self, args: &[&str]) -> CmdOutput {
+        let (flags, operands) = match CopyFlags::parse("cp", args, "rRpavfi") {
+            Ok(parsed) => parsed,
+            Err(e) => return CmdOutput::error(1, e),
+        };
+        let Some((dest, sources)) = operands.split_last().filter(|(_, s)| !s.is_empty()) else {
+            return CmdOutput::usage("usage: cp [-rpav] SOURCE... DEST");
+        };
+        if sources.len() > 1 && !self.kernel.fs.resolve(dest).is_some_and(|n| n.is_dir) {
+            return CmdOutput::error(1, format!("cp: target '{}' is not a directory", dest));
+        }
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for source in sources {
+            let target = self.copy_target(source, dest);
+            match self.copy_one(source, &target, &flags) {
+                Ok(()) if flags.verbose => out.push(format!("'{}' -> '{}'", source, target)),
+                Ok(()) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+        CmdOutput::collected(out, errors)
+    }
+
+    fn copy_one(&mut self, source: &str, target: &str, flags: &CopyFlags) -> Result<(), String> {
+        let Some(node) = self.kernel.fs.resolve(source) else {
+            return Err(format!(
+                "cp: cannot stat '{}': No such file or directory",
+                source
+            ));
+        };
+        if node.is_dir && !flags.recursive {
+            return Err(format!(
+                "cp: -r not specified; omitting directory '{}'",
+                source
+            ));
+        }
+        if !self.has_access(source, 4) {
+            return Err(format!(
+                "cp: cannot open '{}' for reading: Permission denied",
+                source
+            ));
+        }
+        let from = self.kernel.fs.normalize(source);
+        let to = self.kernel.fs.normalize(target);
+        if node.is_dir && (to == from || to.starts_with(&format!("{}/", from))) {
+            return Err(format!(
+                "cp: cannot copy a directory, '{}', into itself, '{}'",
+                source, target
+            ));
+        }
+        if !self.can_write_path(target) {
+            return Err(format!("cp: cannot create '{}': Permission denied", target));
+        }
+        let mut copy = node.clone();
+        self.stamp_copy(&mut copy, flags.preserve);
+        self.kernel
+            .fs
+            .insert_node(target, copy)
+            .map_err(|e| format!("cp: cannot create '{}': {}", target, e))
+    }
+
+    /// `mv [-fiv] SOURCE... DEST`; directories move whole
+    pub(super) fn cmd_mv(&mut self, args: &[&str]) -> CmdOutput {
+        let (flags, operands) = match CopyFlags::parse("mv", args, "fiv") {
+            Ok(parsed) => parsed,
+            Err(e) => return CmdOutput::error(1, e),
+        };
+        let Some((dest, sources)) = operands.split_last().filter(|(_, s)| !s.is_empty()) else {
+            return CmdOutput::usage("usage: mv [-fiv] SOURCE... DEST");
+        };
+        if sources.len() > 1 && !self.kernel.fs.resolve(dest).is_some_and(|n| n.is_dir) {
+            return CmdOutput::error(1, format!("mv: target '{}' is not a directory", dest));
+        }
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for source in sources {
+            let target = self.copy_target(source, dest);
+            if self.kernel.fs.resolve(source).is_none() {
+                errors.push(format!(
+                    "mv: cannot stat '{}': No such file or directory",
+                    source
+                ));
+                continue;
+            }
+            if !self.can_write_path(source) || !self.can_write_path(&target) {
+                errors.push(format!(
+                    "mv: cannot move '{}' to '{}': Permission denied",
+                    source, target
+                ));
+                continue;
+            }
+            // Moving a system binary away is as fatal as deleting it
+            if self.kernel.fs.is_critical(source) {
+                let data = self.kernel.fs.resolve(source).map(|n| n.data.clone());
+                let _ = self
+                    .kernel
+                    .fs
+                    .create_file(&target, &data.unwrap_or_default());
+                let _ = self.kernel.fs.remove(source);
+                continue;
+            }
+            match self.kernel.fs.rename(source, &target) {
+                Ok(()) if flags.verbose => {
+                    out.push(format!("renamed '{}' -> '{}'", source, target))
+                }
+                Ok(()) => {}
+                Err(e) => errors.push(format!(
+                    "mv: cannot move '{}' to '{}': {}",
+                    source, target, e
+                )),
+            }
+        }
+        CmdOutput::collected(out, errors)
+    }
+
+    /// `rsync [-arv] SOURCE DEST`: copy only files whose size or content
+    /// differ. A trailing `/` on SOURCE copies its contents rather than the
+    /// directory itself
+    pub(super) fn cmd_rsync(&mut self, args: &[&str]) -> CmdOutput {
+        let (flags, operands) = match CopyFlags::parse("rsync", args, "arvp") {
+            Ok(parsed) => parsed,
+            Err(e) => return CmdOutput::error(1, e),
+        };
+        let [source, dest] = operands[..] else {
+            return CmdOutput::usage("usage: rsync [-arv] SOURCE[/] DEST");
+        };
+        let Some(node) = self.kernel.fs.resolve(source) else {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "rsync: link_stat \"{}\" failed: No such file or directory (2)",
+                    source
+                ),
+            );
+        };
+        if !self.has_access(source, 4) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "rsync: opendir \"{}\" failed: Permission denied (13)",
+                    source
+                ),
+            );
+        }
+
+        // (path relative to the destination root, source node)
+        let mut entries: Vec<(String, Inode)> = Vec::new();
+        let root = if node.is_dir {
+            if !flags.recursive {
+                return CmdOutput::ok(format!("skipping directory {}", source));
+            }
+            let mut tree = Vec::new();
+            walk_tree(node, "", &mut tree);
+            entries.extend(tree.into_iter().map(|(p, n)| (p, n.clone())));
+            if source.ends_with('/') {
+                dest.trim_end_matches('/').to_string()
+            } else {
+                join(dest, basename(source))
+            }
+        } else {
+            entries.push((String::new(), node.clone()));
+            self.copy_target(source, dest)
+        };
+        if !self.can_write_path(&root) {
+            return CmdOutput::error(
+                1,
+                format!("rsync: mkdir \"{}\" failed: Permission denied (13)", root),
+            );
+        }
+        if node.is_dir {
+            if let Err(e) = self.ensure_dir_all(&root) {
+                return CmdOutput::error(1, format!("rsync: mkdir \"{}\" failed: {}", root, e));
+            }
+        }
+
+        // Ownership survives -a only for root; anyone else gets copies of
+        // their own
+        let preserve = flags.preserve && self.current_user() == "root";
+        let mut sent = Vec::new();
+        let mut bytes = 0;
+        let mut total = 0;
+        let mut errors = Vec::new();
+        let mut unreadable: Vec<String> = Vec::new();
+        for (rel, entry) in entries {
+            if unreadable
+                .iter()
+                .any(|d| rel.starts_with(&format!("{}/", d)))
+            {
+                continue;
+            }
+            let from = if rel.is_empty() {
+                source.to_string()
+            } else {
+                join(source, &rel)
+            };
+            if !self.has_access(&from, 4) {
+                if entry.is_dir {
+                    errors.push(format!(
+                        "rsync: opendir \"{}\" failed: Permission denied (13)",
+                        from
+                    ));
+                    unreadable.push(rel);
+                } else {
+                    errors.push(format!(
+                        "rsync: send_files failed to open \"{}\": Permission denied (13)",
+                        from
+                    ));
+                }
+                continue;
+            }
+            let target = if rel.is_empty() {
+                root.clone()
+            } else {
+                join(&root, &rel)
+            };
+            if entry.is_dir {
+                if self.kernel.fs.resolve(&target).is_none() {
+                    if let Err(e) = self.kernel.fs.create_dir(&target) {
+                        errors.push(format!("rsync: mkdir \"{}\" failed: {}", target, e));
+                    }
+                }
+                continue;
+            }
+            total += entry.size;
+            let unchanged =
+                self.kernel.fs.resolve(&target).is_some_and(|old| {
+                    !old.is_dir && old.size == entry.size && old.data == entry.data
+                });
+            if unchanged {
+                continue;
+            }
+            let mut copy = entry.clone();
+            copy.children.clear();
+            self.stamp_copy(&mut copy, preserve);
+            match self.kernel.fs.insert_node(&target, copy) {
+                Ok(()) => {
+                    bytes += entry.size;
+                    sent.push(if rel.is_empty() {
+                        basename(source).to_string()
+                    } else {
+                        rel
+                    });
+                }
+                Err(e) => errors.push(format!("rsync: {}: {}", target, e)),
+            }
+        }
+
+        let mut out = Vec::new();
+        if flags.verbose {
+            out.push("sending incremental file list".to_string());
+            out.extend(sent);
+            out.push(String::new());
+            out.push(format!("sent {} bytes  received 0 bytes", bytes));
+            out.push(format!("total size is {}", total));
+        }
+        CmdOutput::collected(out, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_move_and_sync_trees() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let fs = &mut sys.kernel.fs;
+        fs.create_dir("/tmp/src").unwrap();
+        fs.create_dir("/tmp/src/sub").unwrap();
+        fs.create_file("/tmp/src/a.txt", "alpha").unwrap();
+        fs.create_file("/tmp/src/sub/b.txt", "beta").unwrap();
+        fs.resolve_mut("/tmp/src/a.txt").unwrap().owner = "alice".into();
+
+        assert!(sys
+            .cmd_cp(&["/tmp/src", "/tmp/x"])
+            .flatten()
+            .contains("-r not specified"));
+        assert_eq!(sys.cmd_cp(&["-r", "/tmp/src", "/tmp/copy"]).flatten(), "");
+        let copied = sys.kernel.fs.resolve("/tmp/copy/sub/b.txt").unwrap();
+        assert_eq!(copied.data, "beta");
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/copy/a.txt").unwrap().owner,
+            "user"
+        );
+        sys.cmd_cp(&["-a", "/tmp/src", "/tmp/copy"]);
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/copy/src/a.txt").unwrap().owner,
+            "alice"
+        );
+        assert!(sys
+            .cmd_cp(&["-r", "/tmp/src", "/tmp/src/sub"])
+            .flatten()
+            .contains("into itself"));
+
+        sys.kernel.fs.cwd = "/tmp/copy/sub".into();
+        assert_eq!(sys.cmd_mv(&["/tmp/copy", "/tmp/moved"]).flatten(), "");
+        assert!(sys.kernel.fs.resolve("/tmp/copy").is_none());
+        assert_eq!(sys.kernel.fs.cwd, "/tmp/moved/sub");
+        assert_eq!(
+            sys.cmd_mv(&["/tmp/moved/a.txt", "/tmp/moved/sub"])
+                .flatten(),
+            ""
+        );
+        assert!(sys.kernel.fs.resolve("/tmp/moved/sub/a.txt").is_some());
+        assert!(sys
+            .cmd_mv(&["/tmp/moved", "/tmp/moved/sub"])
+            .flatten()
+            .contains("into itself"));
+
+        let first = sys
+            .cmd_rsync(&["-av", "/tmp/src/", "/tmp/mirror"])
+            .flatten();
+        assert!(first.contains("a.txt\nsub/b.txt\n"));
+        sys.kernel
+            .fs
+            .write_file("/tmp/src/a.txt", "changed")
+            .unwrap();
+        let second = sys
+            .cmd_rsync(&["-av", "/tmp/src/", "/tmp/mirror"])
+            .flatten();
+        assert!(second.contains("list\na.txt\n\nsent 7 bytes"));
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/mirror/a.txt").unwrap().data,
+            "changed"
+        );
+        assert_eq!(
+            sys.cmd_rsync(&["-a", "/tmp/src", "/tmp/mirror"]).flatten(),
+            ""
+        );
+        assert!(sys.kernel.fs.resolve("/tmp/mirror/src/sub/b.txt").is_some());
+    }
+
+    #[test]
+    fn failed_copies_fail_the_command() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_file("/tmp/a.txt", "alpha").unwrap();
+        sys.kernel.fs.create_dir("/tmp/into").unwrap();
+
+        let out = sys.cmd_cp(&["-v", "/tmp/a.txt", "/tmp/gone", "/tmp/into"]);
+        assert_eq!(out.stdout, "'/tmp/a.txt' -> '/tmp/into/a.txt'");
+        assert_eq!(
+            out.stderr,
+            "cp: cannot stat '/tmp/gone': No such file or directory"
+        );
+        assert_eq!(out.status, 1);
+        let out = sys.cmd_cp(&["-v", "/tmp/a.txt", "/tmp/b.txt"]);
+        assert_eq!(out.stdout, "'/tmp/a.txt' -> '/tmp/b.txt'");
+        assert_eq!(out.status, 0);
+        assert!(sys
+            .exec("cp /tmp/gone /tmp/c && echo yes || echo no")
+            .ends_with("no"));
+        assert!(sys
+            .exec("cp /tmp/a.txt /tmp/c && echo yes || echo no")
+            .ends_with("yes"));
+
+        let out = sys.cmd_mv(&["/tmp/gone", "/tmp/d"]);
+        assert_eq!(
+            out.stderr,
+            "mv: cannot stat '/tmp/gone': No such file or directory"
+        );
+        assert_eq!(out.status, 1);
+        assert!(sys
+            .exec("mv /tmp/gone /tmp/d && echo yes || echo no")
+            .ends_with("no"));
+        assert!(sys
+            .exec("mv /tmp/c /tmp/d && echo yes || echo no")
+            .ends_with("yes"));
+
+        sys.kernel.fs.create_dir("/tmp/src").unwrap();
+        sys.kernel.fs.create_file("/tmp/src/f", "x").unwrap();
+        sys.kernel.fs.create_file("/tmp/dst", "").unwrap();
+        let out = sys.cmd_rsync(&["-r", "/tmp/src/", "/tmp/dst"]);
+        assert_eq!(out.status, 1);
+        assert!(sys
+            .exec("rsync -r /tmp/src/ /tmp/dst && echo yes || echo no")
+            .ends_with("no"));
+        assert!(sys
+            .exec("rsync -r /tmp/src/ /tmp/out && echo yes || echo no")
+            .ends_with("yes"));
+    }
+
+    #[test]
+    fn rsync_checks_each_source_and_keeps_owners_only_for_root() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let fs = &mut sys.kernel.fs;
+        fs.create_dir("/tmp/src").unwrap();
+        fs.create_dir("/tmp/src/locked").unwrap();
+        fs.create_file("/tmp/src/open.txt", "open").unwrap();
+        fs.create_file("/tmp/src/secret.txt", "secret").unwrap();
+        fs.create_file("/tmp/src/locked/inner.txt", "inner")
+            .unwrap();
+        for path in ["/tmp/src/secret.txt", "/tmp/src/locked"] {
+            let node = fs.resolve_mut(path).unwrap();
+            node.owner = "root".into();
+            node.permissions = if node.is_dir {
+                "drwx------"
+            } else {
+                "-rw-------"
+            }
+            .into();
+        }
+        fs.resolve_mut("/tmp/src/open.txt").unwrap().owner = "alice".into();
+
+        let out = sys.cmd_rsync(&["-a", "/tmp/src/", "/tmp/dst"]);
+        assert_eq!(
+            out.stderr,
+            "rsync: opendir \"/tmp/src/locked\" failed: Permission denied (13)\n\
+             rsync: send_files failed to open \"/tmp/src/secret.txt\": Permission denied (13)"
+        );
+        assert_eq!(out.status, 1);
+        assert!(sys.kernel.fs.resolve("/tmp/dst/secret.txt").is_none());
+        assert!(sys.kernel.fs.resolve("/tmp/dst/locked/inner.txt").is_none());
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/dst/open.txt").unwrap().owner,
+            "user"
+        );
+
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert_eq!(sys.cmd_rsync(&["-a", "/tmp/src/", "/tmp/root"]).status, 0);
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/root/open.txt").unwrap().owner,
+            "alice"
+        );
+        assert_eq!(
+            sys.kernel.fs.resolve("/tmp/root/secret.txt").unwrap().data,
+            "secret"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use super::git::{line_ops, unified_diff};
+use super::{System, BINARY_PREFIX};
+use crate::ansi;
+use crate::shell::CmdOutput;
+
+const USAGE: &str = "usage: diff [-u] [-q] [--color[=WHEN]] FILE1 FILE2";
+
+impl System {
+    /// `diff [-u] [-q] [--color[=WHEN]] FILE1 FILE2`. Exits 0 when the files
+    /// match, 1 when they differ and 2 on trouble
+    pub(super) fn cmd_diff(&self, args: &[&str]) -> CmdOutput {
+        let mut unified = false;
+        let mut brief = false;
+        let mut color = false;
+        let mut files = Vec::new();
+        for arg in args {
+            match *arg {
+                "-u" | "--unified" => unified = true,
+                "-q" | "--brief" => brief = true,
+                "--color" | "--color=always" => color = true,
+                "--color=never" => color = false,
+                "--color=auto" => color = !self.output_captured,
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        2,
+                        format!("diff: unrecognized option '{}'\n{}", flag, USAGE),
+                    )
+                }
+                file => files.push(file),
+            }
+        }
+        let [a, b] = files[..] else {
+            return CmdOutput::error(2, USAGE);
+        };
+        let read = |path: &str| match self.kernel.fs.resolve(path) {
+            Some(node) if node.is_dir => Err(format!("diff: {}: Is a directory", path)),
+            Some(_) if !self.has_access(path, 4) => {
+                Err(format!("diff: {}: Permission denied", path))
+            }
+            Some(node) => Ok(node.data.clone()),
+            None => Err(format!("diff: {}: No such file or directory", path)),
+        };
+        let (a_text, b_text) = match (read(a), read(b)) {
+            (Ok(a_text), Ok(b_text)) => (a_text, b_text),
+            (Err(e), _) | (_, Err(e)) => return CmdOutput::error(2, e),
+        };
+        if a_text == b_text {
+            return CmdOutput::default();
+        }
+        let differ = |stdout: String| CmdOutput {
+            stdout,
+            status: 1,
+            ..CmdOutput::default()
+        };
+        if brief {
+            return differ(format!("Files {} and {} differ", a, b));
+        }
+        if a_text.starts_with(BINARY_PREFIX) || b_text.starts_with(BINARY_PREFIX) {
+            return differ(format!("Binary files {} and {} differ", a, b));
+        }
+        let text = if unified {
+            format!("--- {}\n+++ {}\n{}", a, b, unified_diff(&a_text, &b_text))
+        } else {
+            normal_diff(&a_text, &b_text)
+        };
+        differ(if color { paint(&text, unified) } else { text })
+    }
+}
+
+/// diff's default format: `2c2`, `5a6,7`, `9d8` and the lines involved
+fn normal_diff(a: &str, b: &str) -> String {
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    let ops = line_ops(&a, &b);
+    let range = |start: usize, len: usize| match len {
+        1 => start.to_string(),
+        _ => format!("{},{}", start, start + len - 1),
+    };
+    let mut out = Vec::new();
+    // Lines of each file behind the current position
+    let (mut old, mut new) = (0, 0);
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i].0 == ' ' {
+            (old, new, i) = (old + 1, new + 1, i + 1);
+            continue;
+        }
+        let end = (i..ops.len())
+            .find(|&j| ops[j].0 == ' ')
+            .unwrap_or(ops.len());
+        let side = |want: char| -> Vec<&str> {
+            ops[i..end]
+                .iter()
+                .filter(|(op, _)| *op == want)
+                .map(|(_, line)| *line)
+                .collect()
+        };
+        let (removed, added) = (side('-'), side('+'));
+        out.push(match (removed.len(), added.len()) {
+            (r, 0) => format!("{}d{}", range(old + 1, r), new),
+            (0, n) => format!("{}a{}", old, range(new + 1, n)),
+            (r, n) => format!("{}c{}", range(old + 1, r), range(new + 1, n)),
+        });
+        out.extend(removed.iter().map(|line| format!("< {}", line)));
+        if !removed.is_empty() && !added.is_empty() {
+            out.push("---".into());
+        }
+        out.extend(added.iter().map(|line| format!("> {}", line)));
+        (old, new, i) = (old + removed.len(), new + added.len(), end);
+    }
+    out.join("\n")
+}
+
+/// GNU diff's colours: file headers bold, hunk headers cyan, removed lines
+/// red and added ones green
+fn paint(text: &str, unified: bool) -> String {
+    text.lines()
+        .map(|line| {
+            let sgr = match line.chars().next() {
+                _ if unified && (line.starts_with("--- ") || line.starts_with("+++ ")) => {
+                    ansi::BOLD
+                }
+                _ if !unified && line == "---" => return line.to_string(),
+                Some('@') if unified => ansi::CYAN,
+                Some('0'..='9') if !unified => ansi::CYAN,
+                Some('-' | '<') => ansi::RED,
+                Some('+' | '>') => ansi::GREEN,
+                _ => return line.to_string(),
+            };
+            ansi::paint(line, sgr)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_unified_and_coloured_output() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel
+            .fs
+            .create_file("/tmp/a", "one\ntwo\nthree\nfour\n")
+            .unwrap();
+        sys.kernel
+            .fs
+            .create_file("/tmp/b", "one\nTWO\nthree\nfour\nfive\nsix\n")
+            .unwrap();
+
+        let out = sys.cmd_diff(&["/tmp/a", "/tmp/b"]);
+        assert_eq!(out.status, 1);
+        assert_eq!(out.stdout, "2c2\n< two\n---\n> TWO\n4a5,6\n> five\n> six");
+        assert_eq!(normal_diff("a\nb\nc\n", "a\n"), "2,3d1\n< b\n< c");
+        assert!(sys
+            .cmd_diff(&["-u", "/tmp/a", "/tmp/b"])
+            .stdout
+            .starts_with("--- /tmp/a\n+++ /tmp/b\n@@ -1,4 +1,6 @@\n one\n-two\n+TWO"));
+        assert_eq!(
+            sys.cmd_diff(&["--color", "/tmp/a", "/tmp/b"])
+                .stdout
+                .lines()
+                .take(3)
+                .collect::<Vec<_>>(),
+            ["\x1b[36m2c2\x1b[0m", "\x1b[31m< two\x1b[0m", "---"]
+        );
+        assert_eq!(
+            sys.cmd_diff(&["-q", "/tmp/a", "/tmp/b"]).stdout,
+            "Files /tmp/a and /tmp/b differ"
+        );
+        assert_eq!(sys.cmd_diff(&["/tmp/a", "/tmp/a"]), CmdOutput::default());
+        assert_eq!(sys.cmd_diff(&["/tmp/a", "/nope"]).status, 2);
+    }
+}
@@ -0,0 +1,289 @@
+use super::{human_size, System};
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+
+struct DuOptions {
+    human: bool,
+    all: bool,
+    max_depth: Option<usize>,
+}
+
+impl DuOptions {
+    fn shows(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    /// Kilobytes, rounded up so a non-empty file never shows as 0
+    fn format(&self, bytes: usize) -> String {
+        if self.human {
+            human_size(bytes as u64)
+        } else {
+            bytes.div_ceil(1024).to_string()
+        }
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+impl System {
+    pub(super) fn cmd_df(&self, args: &[&str]) -> CmdOutput {
+        let mut human = false;
+        for arg in args {
+            match *arg {
+                "-h" | "--human-readable" => human = true,
+                other => {
+                    return CmdOutput::error(
+                        1,
+                        format!("df: invalid option -- '{}'", other.trim_start_matches('-')),
+                    )
+                }
+            }
+        }
+        let (used, total) = self.kernel.mem.usage();
+        // (source, size, used, target), in kilobytes
+        let mut rows = vec![(
+            "/dev/sda1".to_string(),
+            total as usize / 1024,
+            used as usize / 1024,
+            "/".to_string(),
+        )];
+        for mount in self.kernel.fs.mounts() {
+            let used = self
+                .kernel
+                .fs
+                .resolve(&mount.target)
+                .map(|n| Self::calc_dir_size(n) / 1024)
+                .unwrap_or(0);
+            let size = mount
+                .options
+                .split(',')
+                .find_map(|o| o.strip_prefix("size="))
+                .and_then(Self::parse_size_kb)
+                .unwrap_or(if mount.read_only { used } else { 16384 })
+                .max(used);
+            rows.push((mount.source.clone(), size, used, mount.target.clone()));
+        }
+
+        let mut out = vec![if human {
+            "Filesystem      Size  Used Avail Use% Mounted on".to_string()
+        } else {
+            "Filesystem     1K-blocks    Used Available Use% Mounted on".to_string()
+        }];
+        for (source, size, used, target) in rows {
+            let pct = (used * 100).checked_div(size).unwrap_or(0);
+            out.push(if human {
+                let h = |kb: usize| human_size(kb as u64 * 1024);
+                format!(
+                    "{:<14} {:>5} {:>5} {:>5} {:>3}% {}",
+                    source,
+                    h(size),
+                    h(used),
+                    h(size - used),
+                    pct,
+                    target
+                )
+            } else {
+                format!(
+                    "{:<14} {:>9} {:>7} {:>9} {:>3}% {}",
+                    source,
+                    size,
+                    used,
+                    size - used,
+                    pct,
+                    target
+                )
+            });
+        }
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// Parse sizes like `64M`, `512k` or `1G` into kilobytes
+    fn parse_size_kb(raw: &str) -> Option<usize> {
+        let raw = raw.trim();
+        let (num, mult) = match raw.chars().last()? {
+            'k' | 'K' => (&raw[..raw.len() - 1], 1),
+            'm' | 'M' => (&raw[..raw.len() - 1], 1024),
+            'g' | 'G' => (&raw[..raw.len() - 1], 1024 * 1024),
+            _ => (raw, 0),
+        };
+        let value: usize = num.parse().ok()?;
+        Some(if mult == 0 {
+            value / 1024
+        } else {
+            value * mult
+        })
+    }
+
+    /// `du [-ahs] [-d N | --max-depth=N] [PATH]...`: one line per directory,
+    /// deepest first, like the real tool
+    pub(super) fn cmd_du(&self, args: &[&str]) -> CmdOutput {
+        let mut opts = DuOptions {
+            human: false,
+            all: false,
+            max_depth: None,
+        };
+        let mut summarize = false;
+        let mut paths = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let depth = match *arg {
+                "-d" => match iter.next() {
+                    Some(n) => Some(*n),
+                    None => return CmdOutput::error(1, "du: option requires an argument -- 'd'"),
+                },
+                a => a.strip_prefix("--max-depth="),
+            };
+            if let Some(n) = depth {
+                match n.parse() {
+                    Ok(n) => opts.max_depth = Some(n),
+                    Err(_) => {
+                        return CmdOutput::error(1, format!("du: invalid maximum depth '{}'", n))
+                    }
+                }
+                continue;
+            }
+            match *arg {
+                "--human-readable" => opts.human = true,
+                "--summarize" => summarize = true,
+                "--all" => opts.all = true,
+                flags if flags.starts_with('-') && flags.len() > 1 => {
+                    for c in flags[1..].chars() {
+                        match c {
+                            'h' => opts.human = true,
+                            's' => summarize = true,
+                            'a' => opts.all = true,
+                            _ => {
+                                return CmdOutput::error(
+                                    1,
+                                    format!("du: invalid option -- '{}'", c),
+                                )
+                            }
+                        }
+                    }
+                }
+                path => paths.push(path),
+            }
+        }
+        if summarize {
+            if opts.all {
+                return CmdOutput::error(1, "du: cannot both summarize and show all entries");
+            }
+            opts.max_depth = Some(0);
+        }
+        if paths.is_empty() {
+            paths.push(".");
+        }
+
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for path in paths {
+            match self.kernel.fs.resolve(path) {
+                Some(node) if node.is_dir => {
+                    self.du_walk(node, path, 0, &opts, &mut out, &mut errors);
+                }
+                Some(node) => out.push(format!("{}\t{}", opts.format(node.size), path)),
+                None => errors.push(format!(
+                    "du: cannot access '{}': No such file or directory",
+                    path
+                )),
+            }
+        }
+        CmdOutput::collected(out, errors)
+    }
+
+    /// Total bytes under the directory `node`, pushing a line for it (and
+    /// everything below within the depth limit) after its contents
+    fn du_walk(
+        &self,
+        node: &Inode,
+        path: &str,
+        depth: usize,
+        opts: &DuOptions,
+        out: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) -> usize {
+        let mut total = 4096; // directory itself
+        if !self.has_access(path, 4) {
+            errors.push(format!(
+                "du: cannot read directory '{}': Permission denied",
+                path
+            ));
+        } else {
+            let mut names: Vec<&String> = node.children.keys().collect();
+            names.sort();
+            for name in names {
+                let child = &node.children[name];
+                let child_path = join(path, name);
+                if child.is_dir {
+                    total += self.du_walk(child, &child_path, depth + 1, opts, out, errors);
+                } else {
+                    total += child.size;
+                    if opts.all && opts.shows(depth + 1) {
+                        out.push(format!("{}\t{}", opts.format(child.size), child_path));
+                    }
+                }
+            }
+        }
+        if opts.shows(depth) {
+            out.push(format!("{}\t{}", opts.format(total), path));
+        }
+        total
+    }
+
+    fn calc_dir_size(node: &Inode) -> usize {
+        let mut total = 4096; // directory itself
+        for child in node.children.values() {
+            if child.is_dir {
+                total += Self::calc_dir_size(child);
+            } else {
+                total += child.size;
+            }
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_sizes_and_du_tree() {
+        assert_eq!(human_size(0), "0");
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(4096), "4.0K");
+        assert_eq!(human_size(4097), "4.1K");
+        assert_eq!(human_size(12 * 1024), "12K");
+        assert_eq!(human_size(1536 * 1024), "1.5M");
+        assert_eq!(human_size(3 << 30), "3.0G");
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let fs = &mut sys.kernel.fs;
+        fs.create_dir("/tmp/d").unwrap();
+        fs.create_dir("/tmp/d/sub").unwrap();
+        fs.create_file("/tmp/d/a", &"x".repeat(2000)).unwrap();
+        fs.create_file("/tmp/d/sub/b", "hi").unwrap();
+
+        assert_eq!(
+            sys.cmd_du(&["/tmp/d"]).flatten(),
+            "5\t/tmp/d/sub\n10\t/tmp/d"
+        );
+        assert_eq!(sys.cmd_du(&["-sh", "/tmp/d/"]).flatten(), "10K\t/tmp/d/");
+        assert_eq!(
+            sys.cmd_du(&["--max-depth=0", "/tmp/d"]).flatten(),
+            "10\t/tmp/d"
+        );
+        assert_eq!(
+            sys.cmd_du(&["-a", "/tmp/d"]).flatten(),
+            "2\t/tmp/d/a\n1\t/tmp/d/sub/b\n5\t/tmp/d/sub\n10\t/tmp/d"
+        );
+        assert_eq!(sys.cmd_du(&["-h", "/tmp/d/a"]).flatten(), "2.0K\t/tmp/d/a");
+        assert!(sys
+            .cmd_df(&["-h"])
+            .flatten()
+            .starts_with("Filesystem      Size  Used Avail"));
+    }
+}
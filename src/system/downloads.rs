@@ -0,0 +1,219 @@
+use super::{human_size, System, SystemEvent};
+use crate::shell::CmdOutput;
+
+/// Unfinished transfers, one `id<TAB>tool<TAB>total<TAB>path<TAB>url` line
+/// each. It lives in the VFS so interrupted downloads survive a reload.
+const REGISTRY: &str = "/var/spool/downloads/transfers";
+
+/// A `wget`/`curl -o` transfer the frontend fetches in ranged chunks
+struct Transfer {
+    id: u32,
+    tool: String,
+    total: Option<u64>,
+    path: String,
+    url: String,
+}
+
+impl System {
+    fn load_transfers(&self) -> Vec<Transfer> {
+        let Some(node) = self.kernel.fs.resolve(REGISTRY) else {
+            return Vec::new();
+        };
+        node.data
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(5, '\t');
+                Some(Transfer {
+                    id: fields.next()?.parse().ok()?,
+                    tool: fields.next()?.to_string(),
+                    total: fields.next()?.parse().ok(),
+                    path: fields.next()?.to_string(),
+                    url: fields.next()?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn save_transfers(&mut self, transfers: &[Transfer]) {
+        let data: String = transfers
+            .iter()
+            .map(|t| {
+                let total = t.total.map_or("-".to_string(), |n| n.to_string());
+                format!("{}\t{}\t{}\t{}\t{}\n", t.id, t.tool, total, t.path, t.url)
+            })
+            .collect();
+        if self.ensure_dir_all("/var/spool/downloads").is_ok() {
+            let _ = self.write_file_bytes(REGISTRY, data.as_bytes());
+        }
+    }
+
+    fn received_bytes(&self, path: &str) -> u64 {
+        self.read_file_bytes(path).map_or(0, |b| b.len() as u64)
+    }
+
+    /// Register a transfer into `path` and hand it to the frontend. With
+    /// `resume`, whatever is already in the file is kept and the fetch
+    /// starts after it.
+    pub(super) fn start_download(
+        &mut self,
+        tool: &str,
+        quiet: bool,
+        shown: &str,
+        path: &str,
+        url: &str,
+        resume: bool,
+    ) -> CmdOutput {
+        let offset = if resume {
+            self.received_bytes(path)
+        } else {
+            if let Err(e) = self.write_file_bytes(path, &[]) {
+                return match tool {
+                    "wget" => CmdOutput::error(1, format!("{}: {}", shown, e)),
+                    _ => CmdOutput::error(
+                        1,
+                        format!("curl: (23) Failure writing output to destination: {}", e),
+                    ),
+                };
+            }
+            0
+        };
+        let mut transfers = self.load_transfers();
+        let id = match transfers.iter_mut().find(|t| t.path == path) {
+            Some(existing) => {
+                existing.url = url.to_string();
+                existing.tool = tool.to_string();
+                existing.id
+            }
+            None => {
+                let id = transfers.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                transfers.push(Transfer {
+                    id,
+                    tool: tool.to_string(),
+                    total: None,
+                    path: path.to_string(),
+                    url: url.to_string(),
+                });
+                id
+            }
+        };
+        self.save_transfers(&transfers);
+        if !self.active_downloads.contains(&id) {
+            self.active_downloads.push(id);
+        }
+        CmdOutput::ok(self.emit(SystemEvent::Download {
+            id,
+            tool: tool.into(),
+            quiet,
+            shown: shown.into(),
+            offset,
+            url: url.into(),
+        }))
+    }
+
+    /// Write `data` at byte `start` of the transfer's file, dropping
+    /// anything after it
+    pub(super) fn store_download_chunk(
+        &mut self,
+        id: u32,
+        start: u64,
+        data: &[u8],
+        total: Option<u64>,
+    ) -> Result<(), String> {
+        let mut transfers = self.load_transfers();
+        let Some(transfer) = transfers.iter_mut().find(|t| t.id == id) else {
+            return Err("transfer cancelled".to_string());
+        };
+        let path = transfer.path.clone();
+        if total.is_some() {
+            transfer.total = total;
+        }
+        let mut bytes = self.read_file_bytes(&path).unwrap_or_default();
+        bytes.truncate(start as usize);
+        bytes.extend_from_slice(data);
+        self.write_file_bytes(&path, &bytes)?;
+        self.save_transfers(&transfers);
+        Ok(())
+    }
+
+    /// The frontend stopped fetching; a `complete` transfer is forgotten,
+    /// anything else stays listed for `downloads resume`
+    pub(super) fn end_download(&mut self, id: u32, complete: bool) {
+        self.active_downloads.retain(|&active| active != id);
+        if complete {
+            let mut transfers = self.load_transfers();
+            transfers.retain(|t| t.id != id);
+            self.save_transfers(&transfers);
+        }
+    }
+
+    pub(super) fn cmd_downloads(&mut self, args: &[&str]) -> CmdOutput {
+        let transfers = self.load_transfers();
+        let find = |arg: Option<&&str>| {
+            let id: u32 = arg.and_then(|a| a.parse().ok())?;
+            transfers.iter().find(|t| t.id == id)
+        };
+        match args.first().copied() {
+            None | Some("list" | "ls") => {
+                if transfers.is_empty() {
+                    return CmdOutput::ok("No downloads in progress".to_string());
+                }
+                let mut out = vec![format!(
+                    "{:>3}  {:<7}  {:>8}  {:>13}  FILE",
+                    "ID", "STATE", "PROGRESS", "SIZE"
+                )];
+                for t in &transfers {
+                    let received = self.received_bytes(&t.path);
+                    let state = if self.active_downloads.contains(&t.id) {
+                        "active"
+                    } else {
+                        "paused"
+                    };
+                    let (progress, size) = match t.total {
+                        Some(total) if total > 0 => (
+                            format!("{}%", (received * 100 / total).min(100)),
+                            format!("{}/{}", human_size(received), human_size(total)),
+                        ),
+                        _ => ("?".to_string(), format!("{}/?", human_size(received))),
+                    };
+                    out.push(format!(
+                        "{:>3}  {:<7}  {:>8}  {:>13}  {}",
+                        t.id, state, progress, size, t.path
+                    ));
+                }
+                CmdOutput::ok(out.join("\n"))
+            }
+            Some("resume") => {
+                let Some(t) = find(args.get(1)) else {
+                    return CmdOutput::usage("usage: downloads resume <id>".to_string());
+                };
+                if self.active_downloads.contains(&t.id) {
+                    return CmdOutput::error(1, format!("downloads: transfer {} is already running", t.id));
+                }
+                let (tool, path, url) = (t.tool.clone(), t.path.clone(), t.url.clone());
+                if !self.can_write_path(&path) {
+                    return CmdOutput::error(1, format!("downloads: {}: Permission denied", path));
+                }
+                self.start_download(&tool, false, &path, &path, &url, true)
+            }
+            Some("cancel" | "rm") => {
+                let Some(t) = find(args.get(1)) else {
+                    return CmdOutput::usage("usage: downloads cancel <id>".to_string());
+                };
+                let (id, path) = (t.id, t.path.clone());
+                if !self.can_write_path(&path) {
+                    return CmdOutput::error(1, format!("downloads: {}: Permission denied", path));
+                }
+                let _ = self.kernel.fs.remove(&path);
+                let mut transfers = self.load_transfers();
+                transfers.retain(|t| t.id != id);
+                self.save_transfers(&transfers);
+                self.active_downloads.retain(|&active| active != id);
+                CmdOutput::ok(format!("Cancelled transfer {} and removed {}", id, path))
+            }
+            Some(other) => CmdOutput::error(1, format!(
+                "downloads: unknown command '{}'\nusage: downloads [list | resume <id> | cancel <id>]",
+                other
+            )),
+        }
+    }
+}
@@ -0,0 +1,523 @@
+use super::System;
+use crate::pkg::{self, InstalledPackage, PackageDb, INFO_DIR};
+use crate::shell::CmdOutput;
+
+const DEB_MAGIC: &str = "KP_DEB1";
+
+/// A parsed `.deb` archive: control fields plus the files it unpacks
+struct DebArchive {
+    control: String,
+    dirs: Vec<String>,
+    files: Vec<(String, String, Vec<u8>)>,
+}
+
+impl DebArchive {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(DEB_MAGIC) {
+            return Err("not a Debian format archive".into());
+        }
+        let mut deb = DebArchive {
+            control: String::new(),
+            dirs: Vec::new(),
+            files: Vec::new(),
+        };
+        for line in lines {
+            if let Some(b64) = line.strip_prefix("C\t") {
+                let raw = crate::cpp_accel::b64_decode(b64)
+                    .map_err(|_| "corrupted control member".to_string())?;
+                deb.control = String::from_utf8_lossy(&raw).into_owned();
+            } else if let Some(path) = line.strip_prefix("D\t") {
+                deb.dirs.push(path.to_string());
+            } else if let Some(rest) = line.strip_prefix("F\t") {
+                let mut parts = rest.splitn(3, '\t');
+                let (Some(path), Some(mode), Some(b64)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err("malformed data member".into());
+                };
+                let data = crate::cpp_accel::b64_decode(b64)
+                    .map_err(|_| format!("corrupted data for {}", path))?;
+                deb.files.push((path.to_string(), mode.to_string(), data));
+            }
+        }
+        if deb.control.is_empty() {
+            return Err("archive has no control member".into());
+        }
+        Ok(deb)
+    }
+
+    fn field(&self, key: &str) -> Option<&str> {
+        self.control.lines().find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            (k.trim().eq_ignore_ascii_case(key)).then(|| v.trim())
+        })
+    }
+
+    fn package(&self) -> Result<InstalledPackage, String> {
+        let name = self
+            .field("Package")
+            .filter(|n| !n.is_empty())
+            .ok_or("missing 'Package' field in control file")?;
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+        {
+            return Err(format!("invalid package name '{}'", name));
+        }
+        let version = self
+            .field("Version")
+            .filter(|v| !v.is_empty())
+            .ok_or("missing 'Version' field in control file")?;
+        let size: usize = self.files.iter().map(|(_, _, d)| d.len()).sum();
+        Ok(InstalledPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            size_kb: (size / 1024) as u32 + 1,
+            section: self.field("Section").unwrap_or("misc").to_string(),
+            description: self.field("Description").unwrap_or("").to_string(),
+            depends: self
+                .field("Depends")
+                .map(|d| {
+                    d.split(',')
+                        .map(|p| p.trim().to_string())
+                        .filter(|p| !p.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl System {
+    fn load_deb(&self, path: &str) -> Result<DebArchive, String> {
+        let node = self.kernel.fs.resolve(path).ok_or_else(|| {
+            format!(
+                "cannot access archive '{}': No such file or directory",
+                path
+            )
+        })?;
+        if node.is_dir {
+            return Err(format!("'{}' is a directory", path));
+        }
+        DebArchive::parse(&node.data).map_err(|e| format!("'{}' {}", path, e))
+    }
+
+    pub(super) fn cmd_dpkg_deb(&mut self, args: &[&str]) -> CmdOutput {
+        let usage = "usage: dpkg-deb --build DIR [ARCHIVE.deb] | --info ARCHIVE.deb | --contents ARCHIVE.deb";
+        let Some((op, rest)) = args.split_first() else {
+            return CmdOutput::usage(usage);
+        };
+        match *op {
+            "-b" | "--build" => {
+                let Some(dir) = rest.first() else {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg-deb: error: --build needs a <directory> argument",
+                    );
+                };
+                let root = self.kernel.fs.normalize(dir);
+                match self.kernel.fs.resolve(&root) {
+                    Some(node) if node.is_dir => {}
+                    _ => return CmdOutput::error(1, format!("dpkg-deb: error: failed to open package info file '{}/DEBIAN/control' for reading: No such file or directory", root)),
+                }
+                let control_path = format!("{}/DEBIAN/control", root.trim_end_matches('/'));
+                let control = match self.kernel.fs.resolve(&control_path) {
+                    Some(node) if !node.is_dir => node.data.clone(),
+                    _ => {
+                        return CmdOutput::error(1, format!(
+                            "dpkg-deb: error: failed to open package info file '{}' for reading: No such file or directory",
+                            control_path
+                        ))
+                    }
+                };
+
+                let mut entries = Vec::new();
+                self.collect_tree_paths(&root, &mut entries);
+                let prefix = root.trim_end_matches('/');
+                let mut deb = DebArchive {
+                    control,
+                    dirs: Vec::new(),
+                    files: Vec::new(),
+                };
+                for (path, is_dir) in entries {
+                    let rel = path.strip_prefix(prefix).unwrap_or(&path).to_string();
+                    if rel.is_empty() || rel == "/DEBIAN" || rel.starts_with("/DEBIAN/") {
+                        continue;
+                    }
+                    if is_dir {
+                        deb.dirs.push(rel);
+                        continue;
+                    }
+                    let mode = self
+                        .kernel
+                        .fs
+                        .resolve(&path)
+                        .map(|n| n.permissions.clone())
+                        .unwrap_or_else(|| "-rw-r--r--".into());
+                    match self.read_file_bytes(&path) {
+                        Ok(data) => deb.files.push((rel, mode, data)),
+                        Err(e) => return CmdOutput::error(1, format!("dpkg-deb: error: {}", e)),
+                    }
+                }
+                let pkg = match deb.package() {
+                    Ok(pkg) => pkg,
+                    Err(e) => {
+                        return CmdOutput::error(
+                            1,
+                            format!("dpkg-deb: error: parsing file '{}': {}", control_path, e),
+                        )
+                    }
+                };
+
+                let out_path = rest
+                    .get(1)
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| format!("{}.deb", prefix));
+                let mut lines = vec![
+                    DEB_MAGIC.to_string(),
+                    format!(
+                        "C\t{}",
+                        crate::cpp_accel::b64_encode(deb.control.as_bytes())
+                    ),
+                ];
+                lines.extend(deb.dirs.iter().map(|d| format!("D\t{}", d)));
+                lines.extend(deb.files.iter().map(|(path, mode, data)| {
+                    format!(
+                        "F\t{}\t{}\t{}",
+                        path,
+                        mode,
+                        crate::cpp_accel::b64_encode(data)
+                    )
+                }));
+                match self.write_file_bytes(&out_path, lines.join("\n").as_bytes()) {
+                    Ok(()) => CmdOutput::ok(format!(
+                        "dpkg-deb: building package '{}' in '{}'.",
+                        pkg.name, out_path
+                    )),
+                    Err(e) => CmdOutput::error(1, format!("dpkg-deb: error: {}", e)),
+                }
+            }
+            "-I" | "--info" | "-c" | "--contents" => {
+                let Some(path) = rest.first() else {
+                    return CmdOutput::error(
+                        1,
+                        format!("dpkg-deb: error: {} needs a .deb filename argument", op),
+                    );
+                };
+                let deb = match self.load_deb(path) {
+                    Ok(deb) => deb,
+                    Err(e) => return CmdOutput::error(1, format!("dpkg-deb: error: {}", e)),
+                };
+                if matches!(*op, "-I" | "--info") {
+                    let size: usize = deb.files.iter().map(|(_, _, d)| d.len()).sum();
+                    let control: String =
+                        deb.control.lines().map(|l| format!(" {}\n", l)).collect();
+                    return CmdOutput::ok(format!(
+                        " new Debian package, version 2.0.\n size {} bytes: control archive={} bytes.\n{}",
+                        size,
+                        deb.control.len(),
+                        control.trim_end()
+                    ));
+                }
+                let mut out: Vec<String> = deb
+                    .dirs
+                    .iter()
+                    .map(|d| format!("drwxr-xr-x root/root 0 .{}/", d))
+                    .collect();
+                out.extend(
+                    deb.files
+                        .iter()
+                        .map(|(p, mode, d)| format!("{} root/root {} .{}", mode, d.len(), p)),
+                );
+                CmdOutput::ok(out.join("\n"))
+            }
+            _ => CmdOutput::usage(usage),
+        }
+    }
+
+    pub(super) fn cmd_dpkg(&mut self, args: &[&str]) -> CmdOutput {
+        let usage = "usage: dpkg -i ARCHIVE.deb | -r PACKAGE | -l [PATTERN] | -L PACKAGE | -s PACKAGE | -S FILE";
+        let Some((op, rest)) = args.split_first() else {
+            return CmdOutput::usage(usage);
+        };
+        let is_root = self.current_user() == "root";
+        let mut db = self.package_db();
+        match *op {
+            "-i" | "--install" => {
+                if rest.is_empty() {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg: error: --install needs at least one package archive file argument",
+                    );
+                }
+                if !is_root {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg: error: requested operation requires superuser privilege",
+                    );
+                }
+                let mut out = Vec::new();
+                let mut errors = Vec::new();
+                for path in rest {
+                    match self.dpkg_install(path, &mut db) {
+                        Ok(lines) => out.extend(lines),
+                        Err(e) => {
+                            errors.push(format!(
+                                "dpkg: error processing archive {} (--install):\n {}",
+                                path, e
+                            ));
+                        }
+                    }
+                }
+                if let Err(e) = self.save_package_db(&db) {
+                    errors.push(format!(
+                        "dpkg: error: failed to write status database: {}",
+                        e
+                    ));
+                }
+                self.sync_package_commands();
+                CmdOutput::collected(out, errors)
+            }
+            "-r" | "--remove" | "-P" | "--purge" => {
+                if rest.is_empty() {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "dpkg: error: {} needs at least one package name argument",
+                            op
+                        ),
+                    );
+                }
+                if !is_root {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg: error: requested operation requires superuser privilege",
+                    );
+                }
+                let mut out = Vec::new();
+                let mut errors = Vec::new();
+                let mut failed = false;
+                for name in rest {
+                    let Some(p) = db.get(name).cloned() else {
+                        errors.push(format!(
+                            "dpkg: warning: ignoring request to remove {} which isn't installed",
+                            name
+                        ));
+                        continue;
+                    };
+                    let dependents = db.reverse_depends(name);
+                    if let Some(dep) = dependents.first() {
+                        failed = true;
+                        errors.push(format!(
+                            "dpkg: dependency problems prevent removal of {}:\n {} depends on {}.\n\ndpkg: error processing package {} (--remove):\n dependency problems - not removing",
+                            name, dep, name, name
+                        ));
+                        continue;
+                    }
+                    self.purge_package_files(name);
+                    db.remove(name);
+                    out.push(format!("Removing {} ({}) ...", p.name, p.version));
+                }
+                if let Err(e) = self.save_package_db(&db) {
+                    failed = true;
+                    errors.push(format!(
+                        "dpkg: error: failed to write status database: {}",
+                        e
+                    ));
+                }
+                self.sync_package_commands();
+                // Removing what isn't installed is only a warning
+                CmdOutput {
+                    status: i32::from(failed),
+                    ..CmdOutput::collected(out, errors)
+                }
+            }
+            "-l" | "--list" => {
+                let pattern = rest.first().copied();
+                let mut out = vec![
+                    "Desired=Unknown/Install/Remove/Purge/Hold".to_string(),
+                    "| Status=Not/Inst/Conf-files/Unpacked/halF-conf/Half-inst/trig-aWait/Trig-pend".to_string(),
+                    "||/ Name            Version         Architecture Description".to_string(),
+                    "+++-===============-===============-============-================================".to_string(),
+                ];
+                for p in db.iter() {
+                    if pattern.is_some_and(|pat| !p.name.contains(pat.trim_matches('*'))) {
+                        continue;
+                    }
+                    out.push(format!(
+                        "ii  {:<15} {:<15} {:<12} {}",
+                        p.name, p.version, "amd64", p.description
+                    ));
+                }
+                if out.len() == 4 {
+                    if let Some(pat) = pattern {
+                        return CmdOutput::error(
+                            1,
+                            format!("dpkg-query: no packages found matching {}", pat),
+                        );
+                    }
+                }
+                CmdOutput::ok(out.join("\n"))
+            }
+            "-L" | "--listfiles" => {
+                let Some(name) = rest.first() else {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg-query: error: --listfiles needs a valid package name",
+                    );
+                };
+                if !db.contains(name) {
+                    return CmdOutput::error(
+                        1,
+                        format!("dpkg-query: package '{}' is not installed", name),
+                    );
+                }
+                let files = self.package_files(name);
+                if files.is_empty() {
+                    return CmdOutput::ok(format!(
+                        "Package '{}' does not contain any files (!)",
+                        name
+                    ));
+                }
+                CmdOutput::ok(files.join("\n"))
+            }
+            "-s" | "--status" => {
+                let Some(name) = rest.first() else {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg-query: error: --status needs a valid package name",
+                    );
+                };
+                let Some(p) = db.get(name) else {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                        "dpkg-query: package '{}' is not installed and no information is available",
+                        name
+                    ),
+                    );
+                };
+                let mut single = PackageDb::default();
+                single.insert(p.clone());
+                CmdOutput::ok(single.to_status().trim_end().to_string())
+            }
+            "-S" | "--search" => {
+                let Some(query) = rest.first() else {
+                    return CmdOutput::error(
+                        1,
+                        "dpkg-query: error: --search needs at least one file name pattern argument",
+                    );
+                };
+                let names: Vec<String> = db.iter().map(|p| p.name.clone()).collect();
+                let hits: Vec<String> = names
+                    .iter()
+                    .flat_map(|name| {
+                        self.package_files(name)
+                            .into_iter()
+                            .filter(|f| f.contains(query))
+                            .map(move |f| format!("{}: {}", name, f))
+                    })
+                    .collect();
+                if hits.is_empty() {
+                    CmdOutput::error(
+                        1,
+                        format!("dpkg-query: no path found matching pattern *{}*", query),
+                    )
+                } else {
+                    CmdOutput::ok(hits.join("\n"))
+                }
+            }
+            _ => CmdOutput::usage(usage),
+        }
+    }
+
+    fn dpkg_install(&mut self, path: &str, db: &mut PackageDb) -> Result<Vec<String>, String> {
+        let deb = self.load_deb(path)?;
+        let pkg = deb.package()?;
+        for dep in &pkg.depends {
+            if !db.contains(dep) {
+                return Err(format!(
+                    "dependency problems - {} depends on {}; however:\n  Package {} is not installed.",
+                    pkg.name, dep, dep
+                ));
+            }
+        }
+        // Another package owning one of our files is a conflict
+        for (file, _, _) in &deb.files {
+            for other in db.iter().filter(|p| p.name != pkg.name) {
+                if self.package_files(&other.name).contains(file) {
+                    return Err(format!(
+                        "trying to overwrite '{}', which is also in package {} {}",
+                        file, other.name, other.version
+                    ));
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        match db.get(&pkg.name) {
+            Some(old) => out.push(format!(
+                "Preparing to unpack {} ...\nUnpacking {} ({}) over ({}) ...",
+                path, pkg.name, pkg.version, old.version
+            )),
+            None => out.push(format!(
+                "Selecting previously unselected package {}.\nPreparing to unpack {} ...\nUnpacking {} ({}) ...",
+                pkg.name, path, pkg.name, pkg.version
+            )),
+        }
+        self.purge_package_files(&pkg.name);
+
+        for dir in &deb.dirs {
+            self.ensure_dir_all(dir)?;
+        }
+        let mut list = Vec::new();
+        for (file, mode, data) in &deb.files {
+            if let Some((parent, _)) = file.rsplit_once('/') {
+                self.ensure_dir_all(if parent.is_empty() { "/" } else { parent })?;
+            }
+            self.write_file_bytes(file, data)?;
+            if let Some(node) = self.kernel.fs.resolve_mut(file) {
+                node.permissions = mode.clone();
+                node.is_executable = mode.contains('x');
+                node.owner = "root".into();
+                node.group = "root".into();
+            }
+            list.push(file.clone());
+        }
+        self.ensure_dir_all(INFO_DIR)?;
+        let list_text: String = list.iter().map(|f| format!("{}\n", f)).collect();
+        self.write_file_bytes(
+            &format!("{}/{}.list", INFO_DIR, pkg.name),
+            list_text.as_bytes(),
+        )?;
+
+        out.push(format!("Setting up {} ({}) ...", pkg.name, pkg.version));
+        let commands: Vec<&str> = list.iter().filter_map(|f| pkg::command_name(f)).collect();
+        if !commands.is_empty() {
+            out.push(format!("Registered commands: {}", commands.join(" ")));
+        }
+        db.insert(pkg);
+        Ok(out)
+    }
+
+    /// Run an executable unpacked from a user-built package as a shell
+    /// script, with `$1`..`$9`, `$@` and `$#` bound to the arguments.
+    pub(super) fn run_package_script(&mut self, path: &str, args: &[&str]) -> CmdOutput {
+        let Some(node) = self.kernel.fs.resolve(path) else {
+            return CmdOutput::error(127, format!("sh: {}: No such file or directory", path));
+        };
+        let script = node.data.clone();
+        let joined = args.join(" ");
+        self.exec_script(&script, |line| {
+            let mut expanded = line
+                .replace("$@", &joined)
+                .replace("$*", &joined)
+                .replace("$#", &args.len().to_string());
+            for n in (1..=9).rev() {
+                expanded = expanded.replace(&format!("${}", n), args.get(n - 1).unwrap_or(&""));
+            }
+            expanded
+        })
+    }
+}
@@ -0,0 +1,133 @@
+//! Files dragged onto the terminal from the real machine. They land in
+//! ~/Downloads under a name that is free there; text is stored as it is,
+//! anything else as binary data
+use super::{human_size, System, B64, BINARY_PREFIX};
+use base64::Engine as _;
+use wasm_bindgen::prelude::*;
+
+/// Largest file a drop brings in, since the whole filesystem is saved as
+/// one image
+const MAX_DROP: usize = 4 << 20;
+/// How much of a file is searched for NUL bytes
+const SNIFF_BYTES: usize = 8192;
+
+/// Text when the start has no NUL bytes and the whole is UTF-8
+fn is_text(bytes: &[u8]) -> bool {
+    !bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0) && std::str::from_utf8(bytes).is_ok()
+}
+
+/// `ls -h` sizes, except that small files read `6 bytes` rather than `6`
+fn size_label(len: usize) -> String {
+    match len {
+        0..1024 => format!("{} bytes", len),
+        _ => human_size(len as u64),
+    }
+}
+
+/// The last component of `name` without control characters, so a drop
+/// never writes outside ~/Downloads
+fn clean_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base.chars().filter(|c| !c.is_control()).collect();
+    match cleaned.trim() {
+        "" | "." | ".." => "dropped-file".into(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+impl System {
+    /// `notes.txt`, or `notes (1).txt` and so on when that is taken, the
+    /// way browsers name repeated downloads
+    fn free_drop_path(&self, dir: &str, name: &str) -> String {
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+            _ => (name, String::new()),
+        };
+        (0..)
+            .map(|n| match n {
+                0 => format!("{}/{}", dir, name),
+                n => format!("{}/{} ({}){}", dir, stem, n, ext),
+            })
+            .find(|path| self.kernel.fs.resolve(path).is_none())
+            .unwrap_or_default()
+    }
+}
+
+#[wasm_bindgen]
+impl System {
+    /// Largest file `import_real_file` takes, so the frontend can refuse
+    /// bigger ones before reading them
+    #[wasm_bindgen]
+    pub fn import_limit(&self) -> u32 {
+        MAX_DROP as u32
+    }
+
+    /// Save a file dropped on the terminal into ~/Downloads. Returns the
+    /// line to print, or the reason it was refused
+    #[wasm_bindgen]
+    pub fn import_real_file(&mut self, name: &str, bytes: &[u8]) -> Result<String, String> {
+        if bytes.len() > MAX_DROP {
+            return Err(format!(
+                "{}: {} is over the {} import limit",
+                name,
+                human_size(bytes.len() as u64),
+                human_size(MAX_DROP as u64)
+            ));
+        }
+        let home = Self::default_home_for_user(&self.current_user());
+        let dir = format!("{}/Downloads", home);
+        self.ensure_dir_all(&dir)
+            .map_err(|e| format!("{}: {}", dir, e))?;
+        let path = self.free_drop_path(&dir, &clean_name(name));
+        let text = is_text(bytes);
+        let data = match text {
+            true => String::from_utf8_lossy(bytes).into_owned(),
+            false => format!("{}{}", BINARY_PREFIX, B64.encode(bytes)),
+        };
+        self.kernel
+            .fs
+            .create_file(&path, &data)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(format!(
+            "Imported {} ({}, {}) to {}",
+            name,
+            size_label(bytes.len()),
+            if text { "text" } else { "binary data" },
+            path.replacen(&home, "~", 1)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_files_land_in_downloads() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let out = sys.import_real_file("notes.txt", b"hello\n").unwrap();
+        assert_eq!(
+            out,
+            "Imported notes.txt (6 bytes, text) to ~/Downloads/notes.txt"
+        );
+        let again = sys.import_real_file("notes.txt", b"again").unwrap();
+        assert!(again.ends_with("~/Downloads/notes (1).txt"));
+        assert_eq!(sys.exec("cat /home/user/Downloads/notes.txt"), "hello\n");
+
+        let png = [0x89, b'P', b'N', b'G', 0, 0, 0, 13];
+        assert!(sys
+            .import_real_file("../../etc/logo.png", &png)
+            .unwrap()
+            .contains("binary data) to ~/Downloads/logo.png"));
+        assert_eq!(
+            sys.read_file_bytes("/home/user/Downloads/logo.png")
+                .unwrap(),
+            png
+        );
+        assert!(sys
+            .import_real_file("big.iso", &vec![0; MAX_DROP + 1])
+            .is_err());
+        assert_eq!(clean_name(".."), "dropped-file");
+    }
+}
@@ -0,0 +1,308 @@
+use super::System;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Something the frontend has to do after a command: start a game or a
+/// REPL, fetch from the network, take the machine down. Serialized as
+/// `{ type: "snake_case_name", ...fields }`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SystemEvent {
+    Clear,
+    Logout,
+    OpenUrl {
+        url: String,
+    },
+    PythonRepl,
+    LuaRepl,
+    SqliteRepl,
+    Wscat,
+    Nano {
+        path: String,
+        content: String,
+    },
+    /// 0-2 for a player, 3-5 for the AI bot; `None` keeps the last one
+    LaunchDoom {
+        difficulty: Option<u8>,
+    },
+    RecordDoomDemo {
+        difficulty: u8,
+        path: String,
+    },
+    PlayDoomDemo {
+        path: String,
+    },
+    DoomMap {
+        procedural: bool,
+    },
+    LaunchPong {
+        cpu: bool,
+    },
+    LaunchSnake,
+    LaunchScreensaver {
+        kind: Option<String>,
+    },
+    BlankScreen,
+    Memtest,
+    LaunchGrub,
+    BootSequence {
+        messages: Vec<String>,
+    },
+    ViewImage {
+        path: String,
+    },
+    Fetch {
+        url: String,
+    },
+    /// A page for `lynx`, printed with its references and no session
+    /// when `dump` is set
+    Browse {
+        url: String,
+        dump: bool,
+    },
+    /// An RSS or Atom feed for `rss`
+    Feed {
+        url: String,
+    },
+    /// wttr.in's report for `location`, empty for wherever the browser is
+    Weather {
+        location: String,
+        imperial: bool,
+    },
+    /// Where the space station is, for `iss`
+    Iss,
+    /// A GitHub user or USER/REPO for `gh`
+    Github {
+        target: String,
+    },
+    Curl {
+        method: String,
+        headers: bool,
+        url: String,
+    },
+    Download {
+        id: u32,
+        tool: String,
+        quiet: bool,
+        shown: String,
+        offset: u64,
+        url: String,
+    },
+    GitClone {
+        repo: String,
+        dir: String,
+    },
+    Ping {
+        host: String,
+    },
+    Traceroute {
+        host: String,
+        header: String,
+    },
+    Dns {
+        host: String,
+    },
+    MyIp,
+    /// `apt update` contacting the archive at `mirror`
+    AptUpdate {
+        mirror: String,
+    },
+    KernelPanic {
+        reason: String,
+    },
+    Reboot,
+    Halt,
+    PowerOff,
+}
+
+impl SystemEvent {
+    /// The in-band escape older frontends parse out of the output
+    pub fn legacy_marker(&self) -> String {
+        use SystemEvent::*;
+        match self {
+            Clear => "\x1b[CLEAR]".into(),
+            Logout => "\x1b[EXIT]".into(),
+            OpenUrl { url } => format!("\x1b[OPEN:{}]", url),
+            PythonRepl => "\x1b[PYTHON_REPL]".into(),
+            LuaRepl => "\x1b[LUA_REPL]".into(),
+            SqliteRepl => "\x1b[SQLITE_REPL]".into(),
+            Wscat => "\x1b[WSCAT]".into(),
+            Nano { path, content } => {
+                format!("\x1b[NANO:{}:{}]", path, content.replace('\n', "\\n"))
+            }
+            LaunchDoom { difficulty: None } => "\x1b[LAUNCH_DOOM]".into(),
+            LaunchDoom {
+                difficulty: Some(d),
+            } => format!("\x1b[LAUNCH_DOOM:{}]", d),
+            RecordDoomDemo { difficulty, path } => {
+                format!("\x1b[LAUNCH_DOOM_RECORD:{}:{}]", difficulty, path)
+            }
+            PlayDoomDemo { path } => format!("\x1b[PLAY_DOOM_DEMO:{}]", path),
+            DoomMap { procedural: true } => "\x1b[DOOM_ENABLE_PROC]".into(),
+            DoomMap { procedural: false } => "\x1b[DOOM_RESTORE]".into(),
+            LaunchPong { cpu: false } => "\x1b[LAUNCH_PONG]".into(),
+            LaunchPong { cpu: true } => "\x1b[LAUNCH_PONG:cpu]".into(),
+            LaunchSnake => "\x1b[LAUNCH_SNAKE]".into(),
+            LaunchScreensaver { kind: None } => "\x1b[LAUNCH_SCREENSAVER]".into(),
+            LaunchScreensaver { kind: Some(kind) } => {
+                format!("\x1b[LAUNCH_SCREENSAVER:{}]", kind)
+            }
+            BlankScreen => "\x1b[IDLE_BLANK]".into(),
+            Memtest => "\x1b[MEMTEST]".into(),
+            LaunchGrub => "\x1b[LAUNCH_GRUB]".into(),
+            BootSequence { messages } => format!("\x1b[BOOT_SEQUENCE:{}]", messages.join("|")),
+            ViewImage { path } => format!("\x1b[VIEW_IMAGE:{}]", path),
+            Fetch { url } => format!("\x1b[FETCH:{}]", url),
+            Browse { url, dump } => format!("\x1b[BROWSE:{}:{}]", dump, url),
+            Feed { url } => format!("\x1b[FEED:{}]", url),
+            Weather { location, imperial } => format!("\x1b[WEATHER:{}:{}]", imperial, location),
+            Iss => "\x1b[ISS]".into(),
+            Github { target } => format!("\x1b[GITHUB:{}]", target),
+            Curl {
+                method,
+                headers,
+                url,
+            } => format!("\x1b[CURL:{}:{}:{}]", method, headers, url),
+            Download {
+                id,
+                tool,
+                quiet,
+                shown,
+                offset,
+                url,
+            } => format!(
+                "\x1b[DOWNLOAD:{}\t{}\t{}\t{}\t{}\t{}]",
+                id, tool, quiet, shown, offset, url
+            ),
+            GitClone { repo, dir } => format!("\x1b[GIT_CLONE:{}:{}]", repo, dir),
+            Ping { host } => format!("\x1b[PING:{}]", host),
+            Traceroute { host, header } => format!("\x1b[TRACEROUTE:{}\t{}]", host, header),
+            Dns { host } => format!("\x1b[DNS:{}]", host),
+            MyIp => "\x1b[MYIP]".into(),
+            AptUpdate { mirror } => format!("\x1b[APT_UPDATE:{}]", mirror),
+            KernelPanic { reason } => format!("\x1b[KERNEL_PANIC]{}", reason),
+            Reboot => "\x1b[REBOOT]".into(),
+            Halt => "\x1b[HALT]".into(),
+            PowerOff => "\x1b[POWEROFF]".into(),
+        }
+    }
+}
+
+impl System {
+    /// Queue `event` for the frontend. Returns what goes into the output in
+    /// its place: nothing, or the old escape with legacy markers on
+    pub(super) fn emit(&mut self, event: SystemEvent) -> String {
+        if self.legacy_markers {
+            return event.legacy_marker();
+        }
+        self.events.push(event);
+        String::new()
+    }
+
+    /// Everything emitted since the last call, oldest first
+    pub(super) fn take_events(&mut self) -> Vec<SystemEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Hand queued events to the registered callback, if any. It runs once
+    /// the current call into the crate has returned, so it may call back in
+    pub(super) fn deliver_events(&mut self) {
+        let Some(callback) = self.event_callback.clone() else {
+            return;
+        };
+        if self.events.is_empty() {
+            return;
+        }
+        let events = self.take_events();
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(async move {
+            for event in events {
+                if let Ok(value) = serde_wasm_bindgen::to_value(&event) {
+                    let _ = callback.call1(&JsValue::NULL, &value);
+                }
+            }
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        drop((callback, events));
+    }
+}
+
+#[wasm_bindgen]
+impl System {
+    /// The events queued since the last poll, as an array of
+    /// `{ type, ...fields }` objects
+    #[wasm_bindgen]
+    pub fn poll_events(&mut self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.take_events()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Receive each event through `callback` instead of `poll_events`;
+    /// `null` goes back to polling
+    #[wasm_bindgen]
+    pub fn set_event_callback(&mut self, callback: Option<js_sys::Function>) {
+        self.event_callback = callback;
+    }
+
+    /// Compatibility with frontends that parse `\x1b[NAME]` escapes out of
+    /// the output: with this on, commands print those instead of queueing
+    /// events
+    #[wasm_bindgen]
+    pub fn set_legacy_markers(&mut self, on: bool) {
+        self.legacy_markers = on;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_queue_events_or_print_legacy_markers() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+
+        assert_eq!(sys.exec("snake"), "");
+        assert_eq!(sys.exec("doom hard"), "");
+        assert_eq!(sys.exec("pong cpu"), "");
+        assert_eq!(
+            sys.take_events(),
+            vec![
+                SystemEvent::LaunchSnake,
+                SystemEvent::LaunchDoom {
+                    difficulty: Some(2)
+                },
+                SystemEvent::LaunchPong { cpu: true },
+            ]
+        );
+        assert!(sys.take_events().is_empty());
+        assert_eq!(sys.exec("pong x"), "usage: pong [cpu]");
+        assert!(sys.take_events().is_empty());
+
+        sys.exec("nano /etc/hostname");
+        let [SystemEvent::Nano { path, content }] = &sys.take_events()[..] else {
+            panic!("expected a nano event");
+        };
+        assert_eq!(path, "/etc/hostname");
+        assert!(!content.is_empty());
+
+        sys.set_legacy_markers(true);
+        assert_eq!(sys.exec("clear"), "\x1b[CLEAR]");
+        assert_eq!(
+            sys.exec("screensaver pipes"),
+            "\x1b[LAUNCH_SCREENSAVER:pipes]"
+        );
+        assert!(sys.take_events().is_empty());
+        assert_eq!(
+            SystemEvent::Download {
+                id: 3,
+                tool: "wget".into(),
+                quiet: false,
+                shown: "f".into(),
+                offset: 0,
+                url: "http://x".into(),
+            }
+            .legacy_marker(),
+            "\x1b[DOWNLOAD:3\twget\tfalse\tf\t0\thttp://x]"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Files leaving for the real machine: `download` hands a file, or a
+//! directory zipped up, to the browser as a download so work is not stuck
+//! in the VFS
+use super::{human_size, System};
+use crate::shell::CmdOutput;
+use wasm_bindgen::prelude::*;
+
+const DOWNLOAD_USAGE: &str = "usage: download [-o NAME] PATH";
+
+/// What the browser is handed
+struct Export {
+    name: String,
+    mime: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// Type of a file download by its extension, so the browser can open it
+fn mime_for(name: &str) -> &'static str {
+    let ext = name.rsplit_once('.').map_or("", |(_, ext)| ext);
+    match ext.to_ascii_lowercase().as_str() {
+        "txt" | "md" | "log" | "conf" | "cfg" | "ini" | "sh" | "py" | "lua" | "rs" | "c" | "h" => {
+            "text/plain"
+        }
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Have the browser save `export` through a Blob URL on a throwaway link
+#[cfg(target_arch = "wasm32")]
+fn save_to_machine(export: &Export) -> Result<(), String> {
+    let fail = |_| "the browser refused the download".to_string();
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(export.bytes.as_slice()));
+    let props = web_sys::BlobPropertyBag::new();
+    props.set_type(export.mime);
+    let blob =
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &props).map_err(fail)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(fail)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("no document to download from")?;
+    let link: web_sys::HtmlAnchorElement =
+        document.create_element("a").map_err(fail)?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(&export.name);
+    link.click();
+    // The click has queued the download with its own reference to the blob
+    let _ = web_sys::Url::revoke_object_url(&url);
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_to_machine(_export: &Export) -> Result<(), String> {
+    Ok(())
+}
+
+impl System {
+    /// A file as it is, or a directory as NAME.zip with its own name as the
+    /// top folder
+    fn export(&self, path: &str) -> Result<Export, String> {
+        let full = self.kernel.fs.normalize(path);
+        let node = self
+            .kernel
+            .fs
+            .resolve(&full)
+            .ok_or_else(|| format!("{}: No such file or directory", path))?;
+        if !self.has_access(&full, 4) {
+            return Err(format!("{}: Permission denied", path));
+        }
+        let base = full.rsplit_once('/').map_or("", |(parent, _)| parent);
+        let name = match full.rsplit('/').next() {
+            Some("") | None => "root".to_string(),
+            Some(name) => name.to_string(),
+        };
+        if !node.is_dir {
+            return Ok(Export {
+                mime: mime_for(&name),
+                bytes: self.read_file_bytes(&full)?,
+                name,
+            });
+        }
+        let bytes = self.zip_paths(std::slice::from_ref(&full), true, base)?;
+        Ok(Export {
+            name: format!("{}.zip", name),
+            mime: "application/zip",
+            bytes,
+        })
+    }
+
+    /// `download`: save a VFS file or directory on the real machine
+    pub(super) fn cmd_download(&mut self, args: &[&str]) -> CmdOutput {
+        let (rename, path) = match args {
+            [path] if !path.starts_with('-') => (None, *path),
+            ["-o", name, path] | [path, "-o", name] => (Some(*name), *path),
+            _ => return CmdOutput::error(2, DOWNLOAD_USAGE),
+        };
+        let mut export = match self.export(path) {
+            Ok(export) => export,
+            Err(e) => return CmdOutput::error(1, format!("download: {}", e)),
+        };
+        if let Some(name) = rename {
+            export.mime = mime_for(name);
+            export.name = name.to_string();
+        }
+        match save_to_machine(&export) {
+            Ok(()) => CmdOutput::ok(format!(
+                "Saving {} ({}) to your computer",
+                export.name,
+                human_size(export.bytes.len() as u64)
+            )),
+            Err(e) => CmdOutput::error(1, format!("download: {}", e)),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl System {
+    /// The bytes `download PATH` would save: the file itself, or a zip of
+    /// a directory
+    #[wasm_bindgen]
+    pub fn fs_export(&self, path: &str) -> Result<Vec<u8>, String> {
+        self.export(path).map(|export| export.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use zip::ZipArchive;
+
+    #[test]
+    fn downloads_files_and_zipped_directories() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.ensure_dir_all("/home/user/proj/src").unwrap();
+        sys.kernel
+            .fs
+            .create_file("/home/user/proj/src/main.py", "print(1)\n")
+            .unwrap();
+
+        assert_eq!(
+            sys.fs_export("/home/user/proj/src/main.py").unwrap(),
+            b"print(1)\n"
+        );
+        let out = sys.cmd_download(&["/home/user/proj"]);
+        assert_eq!(out.status, 0);
+        assert!(out.stdout.starts_with("Saving proj.zip ("));
+
+        let zipped = sys.fs_export("/home/user/proj").unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(zipped)).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, ["proj/", "proj/src/", "proj/src/main.py"]);
+        assert!(archive.by_name("proj/src/main.py").is_ok());
+
+        assert_eq!(sys.cmd_download(&["/nope"]).status, 1);
+        assert_eq!(sys.cmd_download(&[]).status, 2);
+    }
+}
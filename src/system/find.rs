@@ -0,0 +1,294 @@
+use super::git::glob_match;
+use super::{unquote, System};
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+
+const USAGE: &str =
+    "usage: find [PATH...] [-name GLOB] [-type f|d] [-size [+-]N[ckMG]] [-maxdepth N] [-exec CMD {} ;]";
+
+#[derive(Clone, Copy)]
+enum SizeCmp {
+    Over,
+    Under,
+    Exactly,
+}
+
+/// `-size`: a count of `unit`-byte blocks
+#[derive(Clone, Copy)]
+struct SizeTest {
+    cmp: SizeCmp,
+    count: usize,
+    unit: usize,
+}
+
+#[derive(Default)]
+struct FindQuery {
+    name: Option<(String, bool)>,
+    kind: Option<bool>,
+    size: Option<SizeTest>,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    exec: Option<Vec<String>>,
+}
+
+/// `[+-]N[ckMG]`, in 512-byte blocks when no unit is given
+fn parse_size(arg: &str) -> Option<SizeTest> {
+    let (cmp, num) = match arg.as_bytes().first()? {
+        b'+' => (SizeCmp::Over, &arg[1..]),
+        b'-' => (SizeCmp::Under, &arg[1..]),
+        _ => (SizeCmp::Exactly, arg),
+    };
+    let (digits, unit) = match num.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&num[..i], c),
+        _ => (num, 'b'),
+    };
+    let unit = match unit {
+        'c' => 1,
+        'b' => 512,
+        'k' => 1024,
+        'M' => 1024 * 1024,
+        'G' => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some(SizeTest {
+        cmp,
+        count: digits.parse().ok()?,
+        unit,
+    })
+}
+
+impl FindQuery {
+    fn parse(args: &[&str]) -> Result<FindQuery, String> {
+        let mut query = FindQuery::default();
+        let mut iter = args.iter();
+        while let Some(&arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .map(|v| unquote(v))
+                    .ok_or_else(|| format!("find: missing argument to `{}'", arg))
+            };
+            match arg {
+                "-name" | "-iname" => {
+                    query.name = Some((value()?.to_string(), arg == "-iname"));
+                }
+                "-type" => {
+                    query.kind = match value()? {
+                        "f" => Some(false),
+                        "d" => Some(true),
+                        other => return Err(format!("find: Unknown argument to -type: {}", other)),
+                    };
+                }
+                "-size" => {
+                    let raw = value()?;
+                    query.size = Some(
+                        parse_size(raw)
+                            .ok_or_else(|| format!("find: invalid -size argument `{}'", raw))?,
+                    );
+                }
+                "-maxdepth" | "-mindepth" => {
+                    let raw = value()?;
+                    let depth = raw
+                        .parse()
+                        .map_err(|_| format!("find: {}: `{}' is not a valid depth", arg, raw))?;
+                    if arg == "-maxdepth" {
+                        query.max_depth = Some(depth);
+                    } else {
+                        query.min_depth = depth;
+                    }
+                }
+                "-exec" => {
+                    let mut command = Vec::new();
+                    loop {
+                        match iter.next() {
+                            Some(&"\\;") | Some(&";") | Some(&"';'") => break,
+                            Some(word) => command.push(word.to_string()),
+                            None => return Err("find: missing argument to `-exec'".to_string()),
+                        }
+                    }
+                    if command.is_empty() {
+                        return Err("find: missing argument to `-exec'".to_string());
+                    }
+                    query.exec = Some(command);
+                }
+                "-print" => {}
+                other => return Err(format!("find: unknown predicate `{}'\n{}", other, USAGE)),
+            }
+        }
+        Ok(query)
+    }
+
+    fn matches(&self, name: &str, node: &Inode, depth: usize) -> bool {
+        if depth < self.min_depth {
+            return false;
+        }
+        if let Some((pattern, fold)) = &self.name {
+            let hit = if *fold {
+                glob_match(&pattern.to_lowercase(), &name.to_lowercase())
+            } else {
+                glob_match(pattern, name)
+            };
+            if !hit {
+                return false;
+            }
+        }
+        if self.kind.is_some_and(|dir| dir != node.is_dir) {
+            return false;
+        }
+        let Some(test) = self.size else {
+            return true;
+        };
+        // Sizes round up to whole units like GNU find, so -1k is only
+        // ever an empty file
+        let units = node.size.div_ceil(test.unit);
+        match test.cmp {
+            SizeCmp::Over => units > test.count,
+            SizeCmp::Under => units < test.count,
+            SizeCmp::Exactly => units == test.count,
+        }
+    }
+}
+
+// Paths keep the start as the user typed it, like GNU find
+fn find_walk(
+    path: &str,
+    name: &str,
+    node: &Inode,
+    depth: usize,
+    query: &FindQuery,
+    found: &mut Vec<String>,
+) {
+    if query.matches(name, node, depth) {
+        found.push(path.to_string());
+    }
+    if !node.is_dir || query.max_depth.is_some_and(|max| depth >= max) {
+        return;
+    }
+    let mut names: Vec<&String> = node.children.keys().collect();
+    names.sort();
+    for child in names {
+        let child_path = format!("{}/{}", path.trim_end_matches('/'), child);
+        find_walk(
+            &child_path,
+            child,
+            &node.children[child],
+            depth + 1,
+            query,
+            found,
+        );
+    }
+}
+
+impl System {
+    /// `find [PATH...] [EXPRESSION]`
+    pub(super) fn cmd_find(&mut self, args: &[&str]) -> CmdOutput {
+        let split = args
+            .iter()
+            .position(|a| a.starts_with('-'))
+            .unwrap_or(args.len());
+        let (paths, expr) = args.split_at(split);
+        let query = match FindQuery::parse(expr) {
+            Ok(query) => query,
+            Err(e) => return CmdOutput::error(1, e),
+        };
+
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        let mut found = Vec::new();
+        for &start in if paths.is_empty() { &["."][..] } else { paths } {
+            match self.kernel.fs.resolve(start) {
+                Some(node) => {
+                    let name = start
+                        .trim_end_matches('/')
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or(start);
+                    find_walk(start, name, node, 0, &query, &mut found);
+                }
+                None => errors.push(format!("find: '{}': No such file or directory", start)),
+            }
+        }
+
+        let failed = !errors.is_empty();
+        match &query.exec {
+            None => out.extend(found),
+            Some(command) => {
+                for path in found {
+                    let line = command
+                        .iter()
+                        .map(|word| word.replace("{}", &path))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let result = self.exec_nested(&line);
+                    out.extend(Some(result.stdout).filter(|s| !s.is_empty()));
+                    errors.extend(Some(result.stderr).filter(|s| !s.is_empty()));
+                }
+            }
+        }
+        // Commands run by -exec may complain without failing find itself
+        CmdOutput {
+            status: i32::from(failed),
+            ..CmdOutput::collected(out, errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_filters_and_exec() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_dir("/tmp/proj").unwrap();
+        sys.kernel.fs.create_dir("/tmp/proj/src").unwrap();
+        sys.kernel
+            .fs
+            .create_file("/tmp/proj/src/main.rs", &"x".repeat(2000))
+            .unwrap();
+        sys.kernel
+            .fs
+            .create_file("/tmp/proj/README.md", "hi")
+            .unwrap();
+        sys.kernel.fs.cwd = "/tmp/proj".into();
+
+        assert_eq!(
+            sys.cmd_find(&[]).flatten(),
+            ".\n./README.md\n./src\n./src/main.rs"
+        );
+        assert_eq!(
+            sys.cmd_find(&["-name", "\"*.rs\""]).flatten(),
+            "./src/main.rs"
+        );
+        assert_eq!(
+            sys.cmd_find(&["/tmp/proj", "-iname", "readme*"]).flatten(),
+            "/tmp/proj/README.md"
+        );
+        assert_eq!(sys.cmd_find(&["-type", "d"]).flatten(), ".\n./src");
+        assert_eq!(
+            sys.cmd_find(&["-type", "f", "-size", "+1k"]).flatten(),
+            "./src/main.rs"
+        );
+        assert_eq!(
+            sys.cmd_find(&["-type", "f", "-size", "-2k"]).flatten(),
+            "./README.md"
+        );
+        assert_eq!(
+            sys.cmd_find(&["-maxdepth", "1", "-type", "f"]).flatten(),
+            "./README.md"
+        );
+        assert_eq!(
+            sys.cmd_find(&["-name", "*.md", "-exec", "cat", "{}", "\\;"])
+                .flatten(),
+            "hi"
+        );
+        assert!(sys
+            .cmd_find(&["-bogus"])
+            .flatten()
+            .starts_with("find: unknown predicate"));
+        assert_eq!(
+            sys.cmd_find(&["nope"]).flatten(),
+            "find: 'nope': No such file or directory"
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! `fsck`: e2fsck's five passes over the root filesystem, shared by the
+//! command, the `(initramfs)` shell and the check boot forces after an
+//! unclean shutdown. Also the damage that shutdown leaves: a save cut off
+//! by closing the tab never finishes writing what was pending
+use super::loopdev::is_image;
+use super::System;
+use crate::clock;
+use crate::shell::CmdOutput;
+use crate::vfs::{Inode, Vfs, CRITICAL_FILES};
+
+pub(super) const ROOT_DEVICE: &str = "/dev/sda1";
+const LOST_FOUND: &str = "/lost+found";
+const USAGE: &str = "usage: fsck [-n|-y|-p] [-f] [DEVICE]";
+/// Trees the kernel fills in on the fly; their sizes mean nothing on disk
+const VIRTUAL_DIRS: [&str; 3] = ["/proc", "/sys", "/dev"];
+/// Where the writes a power loss can catch live
+const WRITABLE_DIRS: [&str; 3] = ["/home", "/root", "/tmp"];
+/// Files modified this close to the last write may still have been pending
+const PENDING_SECS: i64 = 600;
+/// Inodes and 4 KiB blocks on the root filesystem, for the summary line
+const FS_INODES: usize = 65_536;
+const FS_BLOCKS: usize = 262_144;
+const BLOCK: usize = 4096;
+/// pid of systemd-fsck in the boot log
+const FSCK_PID: u32 = 98;
+
+/// What the passes found
+pub(super) struct Check {
+    pub lines: Vec<String>,
+    /// Problems found, fixed or not
+    pub problems: usize,
+    /// Critical binaries put back
+    pub restored: usize,
+}
+
+/// xorshift32, so a seeded power loss always does the same damage
+struct Rng(u32);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x as usize % n.max(1)
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// Trees fsck leaves alone: the kernel's own and anything mounted
+fn skipped_dirs(fs: &Vfs) -> Vec<String> {
+    VIRTUAL_DIRS
+        .iter()
+        .map(|d| d.to_string())
+        .chain(fs.mounts().iter().map(|m| m.target.clone()))
+        .collect()
+}
+
+/// Regular files under `node` whose size should match their contents.
+/// Binaries carry made-up sizes and disk images store less than their
+/// size, so they are skipped
+fn disk_files(node: &Inode, path: &str, skip: &[String], out: &mut Vec<String>) {
+    for (name, child) in &node.children {
+        let child_path = join(path, name);
+        if skip.contains(&child_path) {
+            continue;
+        }
+        if child.is_dir {
+            disk_files(child, &child_path, skip, out);
+        } else if !child.is_executable
+            && !child.permissions.starts_with('l')
+            && !is_image(&child.data)
+        {
+            out.push(child_path);
+        }
+    }
+}
+
+/// Inodes in use and 4 KiB blocks they take
+fn usage(node: &Inode) -> (usize, usize) {
+    node.children
+        .values()
+        .map(usage)
+        .fold((1, node.size.div_ceil(BLOCK)), |(files, blocks), (f, b)| {
+            (files + f, blocks + b)
+        })
+}
+
+/// What a save cut off halfway leaves behind in the files written last:
+/// stale sizes, now and then a file whose directory entry never landed,
+/// and a few files that were deleted while still open
+pub(super) fn power_loss(fs: &mut Vfs, seed: u32) {
+    let mut rng = Rng(seed | 1);
+    fs.number_inodes();
+    let skip = skipped_dirs(fs);
+    let mut files = Vec::new();
+    for dir in WRITABLE_DIRS {
+        if let Some(node) = fs.resolve(dir) {
+            disk_files(node, dir, &skip, &mut files);
+        }
+    }
+    files.sort();
+    let last = files
+        .iter()
+        .filter_map(|path| fs.resolve(path))
+        .map(|node| node.modified)
+        .max()
+        .unwrap_or(0);
+    for path in files {
+        let Some(node) = fs.resolve_mut(&path) else {
+            continue;
+        };
+        if node.is_critical || node.modified < last - PENDING_SECS {
+            continue;
+        }
+        match rng.below(16) {
+            0 => {
+                let (dir, name) = path.rsplit_once('/').unwrap_or(("", &path));
+                let dir = if dir.is_empty() { "/" } else { dir };
+                if let Some(detached) = fs
+                    .resolve_mut(dir)
+                    .and_then(|parent| parent.children.remove(name))
+                {
+                    fs.unattached.push(detached);
+                }
+            }
+            1..=3 => node.size = (node.size / BLOCK + 1) * BLOCK,
+            _ => {}
+        }
+    }
+    for _ in 0..rng.below(4) {
+        let mut node = Inode::file("", "");
+        node.ino = fs.fresh_ino();
+        node.owner = "user".into();
+        node.group = "user".into();
+        node.permissions = "-rw-------".into();
+        node.size = rng.below(3) * BLOCK;
+        fs.orphans.push(node);
+    }
+}
+
+impl System {
+    /// `uid=1000, gid=1000, mode=0100644, size=42`, as e2fsck describes an
+    /// inode it clears
+    fn describe_inode(&self, node: &Inode) -> String {
+        let (uid, gid) = self.numeric_ids(&node.owner, &node.group);
+        format!(
+            "uid={}, gid={}, mode=0{}{}, size={}",
+            uid,
+            gid,
+            if node.is_dir { "40" } else { "100" },
+            Self::mode_to_octal(&node.permissions),
+            node.size
+        )
+    }
+
+    /// Run the five passes over the root filesystem, fixing what they find
+    /// when `repair` is set
+    pub(super) fn fsck_check(&mut self, repair: bool) -> Check {
+        let answer = if repair { "yes" } else { "no" };
+        let mut lines = Vec::new();
+        let mut problems = 0;
+        self.kernel.fs.number_inodes();
+
+        // The orphan list is replayed before any pass runs
+        let orphans = match repair {
+            true => std::mem::take(&mut self.kernel.fs.orphans),
+            false => self.kernel.fs.orphans.clone(),
+        };
+        for orphan in &orphans {
+            problems += 1;
+            lines.push(match repair {
+                true => format!(
+                    "Clearing orphaned inode {} ({})",
+                    orphan.ino,
+                    self.describe_inode(orphan)
+                ),
+                false => format!(
+                    "Inode {} was part of the orphaned inode list.  IGNORED.",
+                    orphan.ino
+                ),
+            });
+        }
+
+        lines.push("Pass 1: Checking inodes, blocks, and sizes".to_string());
+        let skip = skipped_dirs(&self.kernel.fs);
+        let mut files = Vec::new();
+        if let Some(root) = self.kernel.fs.resolve("/") {
+            disk_files(root, "/", &skip, &mut files);
+        }
+        files.sort();
+        for path in files {
+            let Some(node) = self.kernel.fs.resolve_mut(&path) else {
+                continue;
+            };
+            if node.size == node.data.len() {
+                continue;
+            }
+            problems += 1;
+            lines.push(format!(
+                "Inode {}, i_size is {}, should be {}.  Fix? {}",
+                node.ino,
+                node.size,
+                node.data.len(),
+                answer
+            ));
+            if repair {
+                node.size = node.data.len();
+            }
+        }
+
+        lines.push("Pass 2: Checking directory structure".to_string());
+        let missing = self.kernel.fs.missing_critical();
+        for path in &missing {
+            problems += 1;
+            let (dir, name) = path.rsplit_once('/').unwrap_or(("/", path));
+            lines.push(format!(
+                "Entry '{}' in {} has deleted/unused inode.  Restore? {}",
+                name, dir, answer
+            ));
+            if repair {
+                self.kernel.fs.restore_critical(path);
+            }
+        }
+        if repair && !missing.is_empty() {
+            self.kernel.fs.kernel_panic = false;
+            self.kernel.fs.panic_reason.clear();
+        }
+
+        lines.push("Pass 3: Checking directory connectivity".to_string());
+        let unattached = match repair {
+            true => std::mem::take(&mut self.kernel.fs.unattached),
+            false => self.kernel.fs.unattached.clone(),
+        };
+        if !unattached.is_empty() && self.kernel.fs.resolve(LOST_FOUND).is_none() {
+            problems += 1;
+            lines.push(format!("{} not found.  Create? {}", LOST_FOUND, answer));
+            if repair {
+                let mut dir = Inode::dir("lost+found");
+                dir.permissions = "drwx------".into();
+                dir.ino = self.kernel.fs.fresh_ino();
+                if let Some(root) = self.kernel.fs.resolve_mut("/") {
+                    root.children.insert(dir.name.clone(), dir);
+                }
+            }
+        }
+
+        lines.push("Pass 4: Checking reference counts".to_string());
+        for mut node in unattached {
+            problems += 1;
+            lines.push(format!("Unattached inode {}", node.ino));
+            lines.push(format!("Connect to {}? {}", LOST_FOUND, answer));
+            if repair {
+                node.name = format!("#{}", node.ino);
+                if let Some(dir) = self.kernel.fs.resolve_mut(LOST_FOUND) {
+                    dir.children.insert(node.name.clone(), node);
+                }
+            }
+        }
+        lines.push("Pass 5: Checking group summary information".to_string());
+
+        Check {
+            lines,
+            problems,
+            restored: if repair { missing.len() } else { 0 },
+        }
+    }
+
+    /// `/dev/sda1: 412/65536 files, 2231/262144 blocks`
+    fn fsck_usage_line(&self, device: &str) -> String {
+        let (files, blocks) = self.kernel.fs.resolve("/").map_or((0, 0), usage);
+        format!(
+            "{}: {}/{} files, {}/{} blocks",
+            device, files, FS_INODES, blocks, FS_BLOCKS
+        )
+    }
+
+    /// The lines that close a run: clean, modified, or still broken
+    pub(super) fn fsck_summary(&self, device: &str, check: &Check, repair: bool) -> Vec<String> {
+        if check.problems == 0 {
+            return vec![self.fsck_usage_line(device).replacen(": ", ": clean, ", 1)];
+        }
+        if !repair {
+            return vec![
+                String::new(),
+                format!(
+                    "{}: ********** WARNING: Filesystem still has errors **********",
+                    device
+                ),
+                "Run fsck -y to repair.".to_string(),
+            ];
+        }
+        let mut out = vec![
+            String::new(),
+            format!("{}: ***** FILE SYSTEM WAS MODIFIED *****", device),
+        ];
+        if check.restored > 0 {
+            out.push(format!(
+                "{}: restored {} of {} system binaries",
+                device,
+                check.restored,
+                CRITICAL_FILES.len()
+            ));
+        }
+        out.push(self.fsck_usage_line(device));
+        out
+    }
+
+    /// `fsck [-n|-y|-p] [-f] [DEVICE]` on the mounted root: without -f a
+    /// filesystem with nothing wrong is only summed up
+    pub(super) fn cmd_fsck(&mut self, args: &[&str]) -> CmdOutput {
+        let mut repair = None;
+        let mut force = false;
+        let mut device = ROOT_DEVICE;
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'n' => repair = Some(false),
+                            'y' | 'p' | 'a' => repair = Some(true),
+                            'f' => force = true,
+                            _ => return CmdOutput::error(16, USAGE),
+                        }
+                    }
+                }
+                _ => device = arg,
+            }
+        }
+        if device != ROOT_DEVICE {
+            return CmdOutput::error(
+                8,
+                format!(
+                    "fsck.ext4: No such file or directory while trying to open {}",
+                    device
+                ),
+            );
+        }
+        if self.current_user() != "root" {
+            return CmdOutput::error(
+                8,
+                format!(
+                    "fsck.ext4: Permission denied while trying to open {}\n\
+                     You must have r/w access to the filesystem or be root",
+                    device
+                ),
+            );
+        }
+
+        let mut out = vec![
+            "fsck from util-linux 2.38.1".to_string(),
+            "e2fsck 1.47.0 (5-Feb-2023)".to_string(),
+            format!("Warning!  {} is mounted.", device),
+        ];
+        let repair = repair.unwrap_or(false);
+        let found = self.fsck_check(false);
+        if found.problems == 0 && !force {
+            out.extend(self.fsck_summary(device, &found, repair));
+            return CmdOutput::ok(out.join("\n"));
+        }
+        let check = match repair {
+            true => self.fsck_check(true),
+            false => found,
+        };
+        let summary = self.fsck_summary(device, &check, repair);
+        out.extend(check.lines);
+        out.extend(summary);
+        let status = match (check.problems, repair) {
+            (0, _) => 0,
+            (_, true) => 1,
+            (_, false) => 4,
+        };
+        let text = out.join("\n");
+        match status {
+            0 => CmdOutput::ok(text),
+            // Like e2fsck, a repaired filesystem is still a nonzero exit
+            _ => CmdOutput {
+                status,
+                ..CmdOutput::ok(text)
+            },
+        }
+    }
+
+    /// After an unclean shutdown: apply what the lost save left behind,
+    /// then check and fix it, as boot log lines following the root mount
+    pub(super) fn insert_boot_fsck(&mut self, lines: &mut Vec<String>) {
+        let Some(at) = lines
+            .iter()
+            .position(|line| line.contains("EXT4-fs (sda1): mounted filesystem"))
+        else {
+            return;
+        };
+        let stamp = lines[at]
+            .split_once("] ")
+            .map_or(String::new(), |(ts, _)| format!("{}] ", ts));
+        let seed = (clock::now_ms() as u32) ^ (clock::now_secs() as u32);
+        power_loss(&mut self.kernel.fs, seed);
+
+        let check = self.fsck_check(true);
+        let summary = self.fsck_summary(ROOT_DEVICE, &check, true);
+        let mut messages = vec![format!(
+            "{} was not cleanly unmounted, check forced.",
+            ROOT_DEVICE
+        )];
+        messages.extend(check.lines);
+        messages.extend(summary.into_iter().filter(|line| !line.is_empty()));
+        // The boot log scrolls away; root gets the report to read later
+        let subject = format!("fsck of {} after an unclean shutdown", ROOT_DEVICE);
+        self.deliver_mail("root", "systemd-fsck", &subject, &messages.join("\n"));
+        let fsck_lines = messages
+            .into_iter()
+            .map(|msg| format!("{}systemd-fsck[{}]: {}", stamp, FSCK_PID, msg));
+        lines.splice(at + 1..at + 1, fsck_lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fsck_clears_orphans_and_fills_lost_found() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        assert!(sys.exec("fsck").contains("Permission denied"));
+        sys.shell.env.insert("USER".into(), "root".into());
+        let clean = sys.cmd_fsck(&[ROOT_DEVICE]);
+        assert_eq!(clean.status, 0);
+        assert!(clean.stdout.contains("/dev/sda1: clean, "));
+        assert!(!clean.stdout.contains("Pass 1"));
+        assert!(sys.cmd_fsck(&["-f"]).stdout.contains("Pass 5"));
+
+        let fs = &mut sys.kernel.fs;
+        fs.create_file("/root/notes.txt", "half written").unwrap();
+        fs.resolve_mut("/root/notes.txt").unwrap().size = 4096;
+        fs.create_file("/root/draft.txt", "pending write").unwrap();
+        fs.number_inodes();
+        let root_home = fs.resolve_mut("/root").unwrap();
+        let detached = root_home.children.remove("draft.txt").unwrap();
+        let ino = detached.ino;
+        fs.unattached.push(detached);
+        fs.orphans.push(Inode::file("", ""));
+
+        let check = sys.cmd_fsck(&["-n"]);
+        assert_eq!(check.status, 4);
+        assert!(check.stdout.contains("orphaned inode list.  IGNORED."));
+        assert!(check.stdout.contains("Filesystem still has errors"));
+        assert!(sys.kernel.fs.resolve(LOST_FOUND).is_none());
+
+        let fixed = sys.cmd_fsck(&["-y", ROOT_DEVICE]);
+        assert_eq!(fixed.status, 1);
+        assert!(fixed.stdout.contains("Clearing orphaned inode"));
+        assert!(fixed
+            .stdout
+            .contains("i_size is 4096, should be 12.  Fix? yes"));
+        assert!(fixed.stdout.contains("/lost+found not found.  Create? yes"));
+        assert!(fixed.stdout.contains(&format!("Unattached inode {}", ino)));
+        assert!(fixed.stdout.contains("FILE SYSTEM WAS MODIFIED"));
+        let saved = sys.kernel.fs.resolve(&format!("/lost+found/#{}", ino));
+        assert_eq!(saved.unwrap().data, "pending write");
+        assert!(sys.kernel.fs.orphans.is_empty());
+        assert_eq!(sys.cmd_fsck(&[]).status, 0);
+        assert_eq!(sys.cmd_fsck(&["/dev/sdb1"]).status, 8);
+
+        sys.kernel.unclean_shutdown = true;
+        let boot = sys.boot_with_params();
+        let mount = boot
+            .iter()
+            .position(|l| l.contains("EXT4-fs (sda1): mounted"))
+            .unwrap();
+        assert!(boot[mount + 1].contains("was not cleanly unmounted, check forced."));
+        assert!(boot.iter().any(|l| l.contains("systemd-fsck[98]: Pass 5")));
+        assert!(!sys.kernel.unclean_shutdown);
+        assert_eq!(sys.unread_mail("root"), 1);
+        assert_eq!(sys.cmd_fsck(&[]).status, 0);
+        assert!(!sys.boot_with_params().iter().any(|l| l.contains("fsck")));
+    }
+}
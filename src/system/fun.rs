@@ -0,0 +1,437 @@
+use super::System;
+use crate::font;
+use crate::shell::CmdOutput;
+
+const FORTUNE_DIR: &str = "/usr/share/games/fortunes";
+
+const COWS: &[(&str, &str)] = &[
+    (
+        "default",
+        r"        $thoughts   ^__^
+         $thoughts  ($eyes)\_______
+            (__)\       )\/\
+             $tongue ||----w |
+                ||     ||",
+    ),
+    (
+        "tux",
+        r"   $thoughts
+    $thoughts
+        .--.
+       |o_o |
+       |:_/ |
+      //   \ \
+     (|     | )
+    /'\_   _/`\
+    \___)=(___/",
+    ),
+    (
+        "small",
+        r"       $thoughts   ,__,
+        $thoughts  ($eyes)____
+           (__)    )\
+            $tongue||--|| *",
+    ),
+    (
+        "moose",
+        r"  $thoughts
+   $thoughts   \_\_    _/_/
+    $thoughts      \__/
+           ($eyes)\_______
+           (__)\       )\/\
+            $tongue ||----w |
+               ||     ||",
+    ),
+];
+
+// Eye presets selected by the single-letter cowsay modes
+const COW_MODES: &[(&str, &str, &str)] = &[
+    ("-b", "==", "  "),
+    ("-d", "XX", "U "),
+    ("-g", "$$", "  "),
+    ("-p", "@@", "  "),
+    ("-s", "**", "U "),
+    ("-t", "--", "  "),
+    ("-w", "OO", "  "),
+    ("-y", "..", "  "),
+];
+
+const SL_FRAME: &str = r#"                        (@@) (  ) (@)  ( )  @@    ()    @     O     @
+                   (   )
+               (@@@@)
+            (    )
+
+          (@@@)
+      ====        ________                ___________
+  _D _|  |_______/        \__I_I_____===__|_________|
+   |(_)---  |   H\________/ |   |        =|___ ___|      _________________
+   /     |  |   H  |  |     |   |         ||_| |_||     _|                \_____A
+  |      |  |   H  |__--------------------| [___] |   =|                        |
+  | ________|___H__/__|_____/[][]~\_______|       |   -|                        |
+  |/ |   |-----------I_____I [][] []  D   |=======|____|________________________|_
+__/ =| o |=-~~\  /~~\  /~~\  /~~\ ____Y___________|__|__________________________|_
+ |/-=|___|=    ||    ||    ||    |_____/~\___/          |_D__D__D_|  |_D__D__D_|
+  \_/      \O=====O=====O=====O_/      \_/               \_/   \_/    \_/   \_/"#;
+
+const SL_LITTLE: &[&str] = &[
+    r"     ++      +------ ____",
+    r"     ||      |+-+ |  |   \@@@@@@@@@@@",
+    r"   /---------|| | |  |    \@@@@@@@@@@@@@_",
+    r"  + ========  +-+ |  |       ____",
+    r" _|--O========O~\-+  |______|    |",
+    r"//// \_/      \_/       \_/   \_/",
+];
+
+/// Greedy word wrap to `width` columns, breaking words longer than a line
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for para in text.lines() {
+        let mut line = String::new();
+        for word in para.split_whitespace() {
+            let mut word = word.to_string();
+            while word.chars().count() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let head: String = word.chars().take(width).collect();
+                word = word.chars().skip(width).collect();
+                lines.push(head);
+            }
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) && lines.len() > 1 {
+        lines.pop();
+    }
+    lines
+}
+
+fn balloon(lines: &[String], think: bool) -> String {
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let mut out = format!(" {}\n", "_".repeat(width + 2));
+    for (i, line) in lines.iter().enumerate() {
+        let (left, right) = if think {
+            ('(', ')')
+        } else if lines.len() == 1 {
+            ('<', '>')
+        } else if i == 0 {
+            ('/', '\\')
+        } else if i + 1 == lines.len() {
+            ('\\', '/')
+        } else {
+            ('|', '|')
+        };
+        out.push_str(&format!(
+            "{} {}{} {}\n",
+            left,
+            line,
+            " ".repeat(width - line.chars().count()),
+            right
+        ));
+    }
+    out.push_str(&format!(" {}\n", "-".repeat(width + 2)));
+    out
+}
+
+impl System {
+    pub(super) fn cmd_sl(args: &[&str]) -> CmdOutput {
+        CmdOutput::ok(
+            if args.iter().any(|a| a.starts_with('-') && a.contains('l')) {
+                SL_LITTLE.join("\n")
+            } else {
+                SL_FRAME.into()
+            },
+        )
+    }
+
+    pub(super) fn cmd_cowsay(&self, cmd: &str, args: &[&str]) -> CmdOutput {
+        let think = cmd == "cowthink";
+        let mut cow = "default".to_string();
+        let mut eyes = "oo".to_string();
+        let mut tongue = "  ".to_string();
+        let mut width = 40usize;
+        let mut words: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            let value = args.get(i + 1).copied();
+            match args[i] {
+                "-l" => {
+                    let names: Vec<&str> = COWS.iter().map(|(n, _)| *n).collect();
+                    return CmdOutput::ok(format!(
+                        "Cow files in /usr/share/cowsay/cows:\n{}",
+                        names.join(" ")
+                    ));
+                }
+                "-f" | "-e" | "-T" | "-W" => {
+                    let Some(value) = value else {
+                        return CmdOutput::usage(format!(
+                            "{}: option requires an argument -- '{}'",
+                            cmd,
+                            &args[i][1..]
+                        ));
+                    };
+                    match args[i] {
+                        "-f" => cow = value.trim_end_matches(".cow").to_string(),
+                        "-e" => eyes = format!("{:<2}", value.chars().take(2).collect::<String>()),
+                        "-T" => {
+                            tongue = format!("{:<2}", value.chars().take(2).collect::<String>())
+                        }
+                        _ => match value.parse::<usize>() {
+                            Ok(w) if w > 0 => width = w,
+                            _ => {
+                                return CmdOutput::usage(format!(
+                                    "{}: invalid width '{}'",
+                                    cmd, value
+                                ))
+                            }
+                        },
+                    }
+                    i += 1;
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    let Some((_, e, t)) = COW_MODES.iter().find(|(f, _, _)| *f == flag) else {
+                        return CmdOutput::usage(format!("{}: unknown option '{}'", cmd, flag));
+                    };
+                    eyes = e.to_string();
+                    tongue = t.to_string();
+                }
+                word => words.push(word),
+            }
+            i += 1;
+        }
+
+        let Some((_, art)) = COWS.iter().find(|(name, _)| *name == cow) else {
+            return CmdOutput::error(1, format!("{}: Could not find {} cowfile!", cmd, cow));
+        };
+        let message = if words.is_empty() {
+            String::new()
+        } else {
+            words.join(" ").replace("\\n", "\n")
+        };
+        let thoughts = if think { "o" } else { "\\" };
+        let body = art
+            .replace("$thoughts", thoughts)
+            .replace("$eyes", &eyes)
+            .replace("$tongue", &tongue);
+        CmdOutput::ok(format!(
+            "{}{}",
+            balloon(&wrap(&message, width), think),
+            body
+        ))
+    }
+
+    pub(super) fn cmd_figlet(args: &[&str]) -> CmdOutput {
+        let mut pixel = "#";
+        let mut width = 80usize;
+        let mut center = false;
+        let mut words: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-f" => {
+                    pixel = match args.get(i + 1).copied() {
+                        Some("standard") => "#",
+                        Some("block") => "\u{2588}",
+                        Some("shade") => "\u{2593}",
+                        Some("dots") => "o",
+                        Some(other) => {
+                            return CmdOutput::error(
+                                1,
+                                format!("figlet: {}: Unable to open font file", other),
+                            )
+                        }
+                        None => {
+                            return CmdOutput::usage("figlet: option requires an argument -- 'f'")
+                        }
+                    };
+                    i += 1;
+                }
+                "-w" => {
+                    match args.get(i + 1).and_then(|w| w.parse::<usize>().ok()) {
+                        Some(w) if w >= 6 => width = w,
+                        _ => return CmdOutput::usage("figlet: invalid width"),
+                    }
+                    i += 1;
+                }
+                "-c" => center = true,
+                "-I2" => return CmdOutput::ok("/usr/share/figlet"),
+                "-l" | "--list" => return CmdOutput::ok("standard\nblock\nshade\ndots"),
+                word => words.push(word),
+            }
+            i += 1;
+        }
+        let text = words.join(" ");
+        if text.trim().is_empty() {
+            return CmdOutput::default();
+        }
+
+        let cell = (font::GLYPH_W + 1) as usize;
+        let per_line = (width / cell).max(1);
+        let mut out = Vec::new();
+        for line in wrap(&text, per_line) {
+            let chars: Vec<char> = line
+                .chars()
+                .map(|c| if font::glyph(c).is_some() { c } else { '?' })
+                .collect();
+            let used = chars.len() * cell;
+            let pad = if center {
+                " ".repeat(width.saturating_sub(used) / 2)
+            } else {
+                String::new()
+            };
+            for y in 0..font::GLYPH_H {
+                let mut row = pad.clone();
+                for &c in &chars {
+                    for x in 0..font::GLYPH_W {
+                        row.push_str(if font::pixel(c, x, y) { pixel } else { " " });
+                    }
+                    row.push(' ');
+                }
+                out.push(row.trim_end().to_string());
+            }
+            out.push(String::new());
+        }
+        CmdOutput::ok(out.join("\n").trim_end())
+    }
+
+    /// Cookie files under the fortune directory, or the ones named on the command line
+    fn fortune_files(&self, named: &[&str]) -> Vec<(String, String)> {
+        if !named.is_empty() {
+            return named
+                .iter()
+                .filter_map(|n| {
+                    let path = if n.contains('/') {
+                        n.to_string()
+                    } else {
+                        format!("{}/{}", FORTUNE_DIR, n)
+                    };
+                    let node = self.kernel.fs.resolve(&path).filter(|n| !n.is_dir)?;
+                    Some((path, node.data.clone()))
+                })
+                .collect();
+        }
+        let Some(dir) = self.kernel.fs.resolve(FORTUNE_DIR) else {
+            return Vec::new();
+        };
+        let mut files: Vec<(String, String)> = dir
+            .children
+            .values()
+            .filter(|n| !n.is_dir)
+            .map(|n| (format!("{}/{}", FORTUNE_DIR, n.name), n.data.clone()))
+            .collect();
+        files.sort();
+        files
+    }
+
+    pub(super) fn cmd_fortune(&self, args: &[&str]) -> CmdOutput {
+        let mut short_only = false;
+        let mut long_only = false;
+        let mut limit = 160usize;
+        let mut list_files = false;
+        let mut show_file = false;
+        let mut pattern: Option<String> = None;
+        let mut named = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-s" => short_only = true,
+                "-l" => long_only = true,
+                "-f" => list_files = true,
+                "-c" => show_file = true,
+                "-n" => {
+                    match args.get(i + 1).and_then(|n| n.parse().ok()) {
+                        Some(n) => limit = n,
+                        None => return CmdOutput::usage("fortune: -n requires a number"),
+                    }
+                    i += 1;
+                }
+                "-m" => {
+                    let Some(p) = args.get(i + 1) else {
+                        return CmdOutput::usage("fortune: -m requires a pattern");
+                    };
+                    pattern = Some(p.to_lowercase());
+                    i += 1;
+                }
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::usage(format!(
+                        "fortune: invalid option -- '{}'",
+                        flag.trim_start_matches('-')
+                    ))
+                }
+                file => named.push(file),
+            }
+            i += 1;
+        }
+
+        let files = self.fortune_files(&named);
+        if files.is_empty() {
+            return CmdOutput::error(1, "No fortunes found");
+        }
+        let cookies: Vec<(&str, &str)> = files
+            .iter()
+            .flat_map(|(path, data)| {
+                data.split("\n%\n")
+                    .map(|c| c.trim_end_matches("\n%").trim_matches('\n'))
+                    .filter(|c| !c.trim().is_empty())
+                    .map(move |c| (path.as_str(), c))
+            })
+            .collect();
+
+        if list_files {
+            let total = cookies.len().max(1) as f64;
+            return CmdOutput::ok(
+                files
+                    .iter()
+                    .map(|(path, _)| {
+                        let n = cookies.iter().filter(|(p, _)| p == path).count();
+                        format!(
+                            "{:>6.2}% {}",
+                            n as f64 * 100.0 / total,
+                            path.trim_start_matches(&format!("{}/", FORTUNE_DIR))
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+        if let Some(pat) = pattern {
+            return CmdOutput::ok(
+                cookies
+                    .iter()
+                    .filter(|(_, c)| c.to_lowercase().contains(&pat))
+                    .map(|(p, c)| format!("({})\n%\n{}", p.rsplit('/').next().unwrap_or(p), c))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+
+        let pool: Vec<&(&str, &str)> = cookies
+            .iter()
+            .filter(|(_, c)| {
+                let len = c.chars().count();
+                (!short_only || len <= limit) && (!long_only || len > limit)
+            })
+            .collect();
+        if pool.is_empty() {
+            return CmdOutput::error(1, "No fortunes found");
+        }
+        let idx = ((js_sys::Math::random() * pool.len() as f64) as usize).min(pool.len() - 1);
+        let (path, cookie) = pool[idx];
+        CmdOutput::ok(if show_file {
+            format!(
+                "({})\n%\n{}",
+                path.rsplit('/').next().unwrap_or(path),
+                cookie
+            )
+        } else {
+            cookie.to_string()
+        })
+    }
+}
@@ -0,0 +1,266 @@
+//! Small generators for one-liners: `seq`, `yes` and `shuf`
+use super::System;
+use crate::shell::CmdOutput;
+
+/// Most lines `yes` writes: the shell has no way to stop a command that
+/// never ends, so it stops itself
+const YES_LINES: usize = 10_000;
+/// Most numbers `seq` prints before giving up
+const SEQ_LIMIT: usize = 100_000;
+
+const SEQ_USAGE: &str = "usage: seq [-w] [-s SEP] [FIRST [INCREMENT]] LAST";
+const SHUF_USAGE: &str =
+    "usage: shuf [-r] [-n COUNT] [FILE]\n       shuf -i LO-HI [-n COUNT]\n       shuf -e ARG...";
+
+/// Digits after the point in `arg`, so `seq 1 0.25 2` prints `1.00`
+fn decimals(arg: &str) -> usize {
+    arg.split_once('.').map_or(0, |(_, frac)| frac.len())
+}
+
+/// xorshift32 seeded from the browser's RNG (or the clock in tests)
+struct Rng(u32);
+
+impl Rng {
+    fn new() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let seed = (js_sys::Math::random() * f64::from(u32::MAX)) as u32;
+        #[cfg(not(target_arch = "wasm32"))]
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(1, |d| d.subsec_nanos());
+        Rng(seed | 1)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x as usize % n.max(1)
+    }
+}
+
+impl System {
+    pub(super) fn cmd_seq(&self, args: &[&str]) -> CmdOutput {
+        let mut separator = "\n";
+        let mut equal_width = false;
+        let mut numbers = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-w" | "--equal-width" => equal_width = true,
+                "-s" | "--separator" => match rest.next() {
+                    Some(sep) => separator = sep,
+                    None => return CmdOutput::error(1, "seq: option requires an argument -- 's'"),
+                },
+                // A negative number is an operand, not an option
+                arg if arg.starts_with('-') && arg.parse::<f64>().is_err() => {
+                    return CmdOutput::error(
+                        1,
+                        format!("seq: invalid option -- '{}'\n{}", &arg[1..], SEQ_USAGE),
+                    )
+                }
+                arg => numbers.push(arg),
+            }
+        }
+        let parsed: Result<Vec<f64>, CmdOutput> = numbers
+            .iter()
+            .map(|n| {
+                n.parse::<f64>().map_err(|_| {
+                    CmdOutput::error(1, format!("seq: invalid floating point argument: '{}'", n))
+                })
+            })
+            .collect();
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => return e,
+        };
+        let (first, step, last) = match parsed[..] {
+            [last] => (1.0, 1.0, last),
+            [first, last] => (first, 1.0, last),
+            [first, step, last] => (first, step, last),
+            _ => return CmdOutput::error(1, SEQ_USAGE),
+        };
+        if step == 0.0 {
+            return CmdOutput::error(
+                1,
+                format!("seq: invalid Zero increment value: '{}'", numbers[1]),
+            );
+        }
+        // Allow for rounding, so `seq 0 0.1 1` still reaches 1
+        let count = ((last - first) / step + 1e-9).floor();
+        if count >= SEQ_LIMIT as f64 {
+            return CmdOutput::error(1, format!("seq: too many numbers (at most {})", SEQ_LIMIT));
+        }
+        let places = numbers[..numbers.len() - 1]
+            .iter()
+            .map(|n| decimals(n))
+            .max()
+            .unwrap_or(0);
+        let values: Vec<String> = (0..=count.max(-1.0) as i64)
+            .map(|i| format!("{:.*}", places, first + step * i as f64))
+            .collect();
+        let width = match equal_width {
+            true => values.iter().map(String::len).max().unwrap_or(0),
+            false => 0,
+        };
+        let values: Vec<String> = values
+            .into_iter()
+            .map(|v| match v.strip_prefix('-') {
+                Some(digits) => format!("-{:0>w$}", digits, w = width.saturating_sub(1)),
+                None => format!("{:0>width$}", v),
+            })
+            .collect();
+        CmdOutput::ok(values.join(separator))
+    }
+
+    /// `yes [STRING]`: `y`, or the arguments, over and over
+    pub(super) fn cmd_yes(&self, args: &[&str]) -> CmdOutput {
+        let line = match args {
+            [] => "y".to_string(),
+            args => args.join(" "),
+        };
+        CmdOutput::ok(vec![line; YES_LINES].join("\n"))
+    }
+
+    /// `shuf`: lines of a file, numbers from `-i LO-HI` or the arguments of
+    /// `-e`, in random order
+    pub(super) fn cmd_shuf(&self, args: &[&str]) -> CmdOutput {
+        let mut count = None;
+        let mut repeat = false;
+        let mut range = None;
+        let mut echo = false;
+        let mut operands = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-r" | "--repeat" => repeat = true,
+                "-e" | "--echo" => echo = true,
+                "-n" | "--head-count" => match rest.next().and_then(|n| n.parse::<usize>().ok()) {
+                    Some(n) => count = Some(n),
+                    None => return CmdOutput::error(1, "shuf: invalid line count"),
+                },
+                "-i" | "--input-range" => {
+                    let spec = rest.next().copied().unwrap_or_default();
+                    let bounds = spec
+                        .split_once('-')
+                        .and_then(|(lo, hi)| {
+                            Some((lo.parse::<u64>().ok()?, hi.parse::<u64>().ok()?))
+                        })
+                        .filter(|&(lo, hi)| lo <= hi.saturating_add(1));
+                    match bounds {
+                        Some(bounds) => range = Some(bounds),
+                        None => {
+                            return CmdOutput::error(
+                                1,
+                                format!("shuf: invalid input range: '{}'", spec),
+                            )
+                        }
+                    }
+                }
+                arg if arg.starts_with('-') && arg.len() > 1 && !echo => {
+                    return CmdOutput::error(
+                        1,
+                        format!("shuf: invalid option -- '{}'\n{}", &arg[1..], SHUF_USAGE),
+                    )
+                }
+                arg => operands.push(arg),
+            }
+        }
+
+        let mut lines: Vec<String> = match (range, echo, &operands[..]) {
+            (Some((lo, hi)), false, []) => {
+                if hi.saturating_sub(lo) >= SEQ_LIMIT as u64 {
+                    return CmdOutput::error(
+                        1,
+                        format!("shuf: range too large (at most {})", SEQ_LIMIT),
+                    );
+                }
+                (lo..=hi).map(|n| n.to_string()).collect()
+            }
+            (None, true, words) => words.iter().map(|w| w.to_string()).collect(),
+            (None, false, [path]) => match self.read_file_bytes(path) {
+                Ok(data) => String::from_utf8_lossy(&data)
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                Err(e) => return CmdOutput::error(1, format!("shuf: {}", e)),
+            },
+            _ => return CmdOutput::error(1, SHUF_USAGE),
+        };
+
+        let mut rng = Rng::new();
+        if repeat {
+            if lines.is_empty() {
+                return CmdOutput::error(1, "shuf: no lines to repeat");
+            }
+            let picks = count.unwrap_or(YES_LINES).min(YES_LINES);
+            let picked: Vec<&str> = (0..picks)
+                .map(|_| lines[rng.below(lines.len())].as_str())
+                .collect();
+            return CmdOutput::ok(picked.join("\n"));
+        }
+        // Fisher-Yates
+        for i in (1..lines.len()).rev() {
+            lines.swap(i, rng.below(i + 1));
+        }
+        lines.truncate(count.unwrap_or(usize::MAX));
+        CmdOutput::ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seq_yes_shuf_and_byte_counts() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        assert_eq!(sys.cmd_seq(&["3"]).stdout, "1\n2\n3");
+        assert_eq!(
+            sys.cmd_seq(&["-s", ",", "10", "-3", "1"]).stdout,
+            "10,7,4,1"
+        );
+        assert_eq!(sys.cmd_seq(&["0", "0.5", "1"]).stdout, "0.0\n0.5\n1.0");
+        assert_eq!(sys.cmd_seq(&["-w", "8", "10"]).stdout, "08\n09\n10");
+        assert_eq!(sys.cmd_seq(&["5", "1"]).stdout, "");
+        assert_eq!(sys.cmd_seq(&["1", "0", "2"]).status, 1);
+
+        assert_eq!(sys.exec("yes | head -n 2"), "y\ny");
+        assert_eq!(sys.cmd_yes(&["ok"]).flatten().lines().count(), YES_LINES);
+
+        let mut shuffled: Vec<u32> = sys
+            .cmd_shuf(&["-i", "1-20"])
+            .stdout
+            .lines()
+            .map(|n| n.parse().unwrap())
+            .collect();
+        shuffled.sort();
+        assert_eq!(shuffled, (1..=20).collect::<Vec<_>>());
+        assert_eq!(
+            sys.cmd_shuf(&["-n", "2", "-e", "a", "b", "c"])
+                .stdout
+                .lines()
+                .count(),
+            2
+        );
+        assert_eq!(sys.exec("seq 5 | shuf -n 3").lines().count(), 3);
+        let denied = sys.cmd_shuf(&["/etc/shadow"]);
+        assert_eq!(denied.stdout, "");
+        assert_eq!(denied.stderr, "shuf: /etc/shadow: Permission denied");
+        assert_eq!(denied.status, 1);
+
+        sys.kernel
+            .fs
+            .create_file("/tmp/t", "one\ntwo\nthree")
+            .unwrap();
+        assert_eq!(sys.exec("head -c 5 /tmp/t"), "one\nt");
+        assert_eq!(sys.exec("tail -c3 /tmp/t"), "ree");
+        assert_eq!(sys.exec("tail -n +2 /tmp/t"), "two\nthree");
+        assert_eq!(sys.exec("seq 10 | tail -2"), "9\n10");
+        assert_eq!(sys.exec("head é"), "head: é: No such file or directory");
+        assert_eq!(sys.exec("tail ééé"), "tail: ééé: No such file or directory");
+    }
+}
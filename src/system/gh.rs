@@ -0,0 +1,425 @@
+//! `gh`: a GitHub profile or repository at a glance, from the REST API
+//! through `net_perform`. Answers are kept under ~/.cache/gh/ and reused
+//! for a while, since without a token GitHub allows 60 requests an hour;
+//! when the limit is hit the last copy is shown instead
+use super::{System, SystemEvent};
+use crate::clock;
+use crate::shell::CmdOutput;
+use serde_json::Value;
+
+pub(super) const API: &str = "https://api.github.com";
+const GH_USAGE: &str = "usage: gh USER | gh USER/REPO";
+/// How long a cached answer is used without asking again
+const FRESH_SECS: i64 = 600;
+/// Repositories shown as cards on a profile, as GitHub pins them
+const PINNED: usize = 6;
+const RECENT: usize = 5;
+
+/// What `gh` was asked to show
+enum Target<'a> {
+    User(&'a str),
+    Repo(&'a str, &'a str),
+}
+
+fn parse_target(arg: &str) -> Option<Target<'_>> {
+    let valid = |s: &str, extra: &str| {
+        !s.is_empty()
+            && s.len() <= 100
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || extra.contains(c))
+    };
+    match arg.trim_end_matches('/').split_once('/') {
+        None if valid(arg, "") => Some(Target::User(arg)),
+        Some((owner, repo)) if valid(owner, "") && valid(repo, "_.") => {
+            Some(Target::Repo(owner, repo))
+        }
+        _ => None,
+    }
+}
+
+/// The API paths `target` needs, in the order they are rendered from
+pub(super) fn api_paths(target: &str) -> Vec<String> {
+    match parse_target(target) {
+        Some(Target::User(user)) => vec![
+            format!("/users/{}", user),
+            format!("/users/{}/repos?per_page=100&sort=pushed", user),
+        ],
+        Some(Target::Repo(owner, repo)) => vec![
+            format!("/repos/{}/{}", owner, repo),
+            format!("/repos/{}/{}/commits?per_page={}", owner, repo, RECENT),
+        ],
+        None => Vec::new(),
+    }
+}
+
+/// Whether an answer is GitHub turning the request down for the hour
+pub(super) fn rate_limited(body: &str) -> bool {
+    body.contains("API rate limit exceeded")
+}
+
+/// The `message` of an error answer such as `{"message": "Not Found"}`
+fn api_error(v: &Value) -> Option<&str> {
+    let fields = v.as_object()?;
+    let has_data = fields.contains_key("id") || fields.contains_key("login");
+    fields
+        .get("message")
+        .and_then(Value::as_str)
+        .filter(|_| !has_data)
+}
+
+fn cache_name(path: &str) -> String {
+    let name: String = path
+        .trim_start_matches('/')
+        .split('?')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}.json", name)
+}
+
+fn text<'a>(v: &'a Value, key: &str) -> &'a str {
+    v[key].as_str().unwrap_or("").trim()
+}
+
+fn count(v: &Value, key: &str) -> u64 {
+    v[key].as_u64().unwrap_or(0)
+}
+
+/// `2026-10-15T12:00:00Z` as `2026-10-15`
+fn day(stamp: &str) -> &str {
+    stamp.get(..10).unwrap_or(stamp)
+}
+
+/// `text` cut to `width` characters, with `…` when it was longer
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Repositories as GitHub's pinned cards, two to a row
+fn cards(repos: &[&Value], width: usize) -> Vec<String> {
+    let inner = (width.saturating_sub(5) / 2).clamp(20, 40);
+    let mut out = Vec::new();
+    for row in repos.chunks(2) {
+        let boxes: Vec<[String; 5]> = row
+            .iter()
+            .map(|repo| {
+                let mut facts = vec![match text(repo, "language") {
+                    "" => String::new(),
+                    lang => format!("● {}", lang),
+                }];
+                facts.push(format!("★ {}", count(repo, "stargazers_count")));
+                if count(repo, "forks_count") > 0 {
+                    facts.push(format!("⑂ {}", count(repo, "forks_count")));
+                }
+                let line = |s: &str| format!("│ {:<inner$} │", fit(s, inner));
+                [
+                    format!("┌{}┐", "─".repeat(inner + 2)),
+                    line(text(repo, "name")),
+                    line(text(repo, "description")),
+                    line(facts.join("  ").trim()),
+                    format!("└{}┘", "─".repeat(inner + 2)),
+                ]
+            })
+            .collect();
+        for n in 0..5 {
+            let parts: Vec<&str> = boxes.iter().map(|b| b[n].as_str()).collect();
+            out.push(format!("  {}", parts.join(" ")));
+        }
+    }
+    out
+}
+
+fn render_user(user: &Value, repos: &Value, width: usize) -> String {
+    let login = text(user, "login");
+    let mut out = vec![match text(user, "name") {
+        "" => login.to_string(),
+        name => format!("{} ({})", name, login),
+    }];
+    if !text(user, "bio").is_empty() {
+        out.push(format!("  {}", text(user, "bio")));
+    }
+    let about: Vec<String> = [
+        ("Location", "location"),
+        ("Company", "company"),
+        ("Blog", "blog"),
+    ]
+    .iter()
+    .filter(|(_, key)| !text(user, key).is_empty())
+    .map(|(label, key)| format!("{}: {}", label, text(user, key)))
+    .collect();
+    if !about.is_empty() {
+        out.push(format!("  {}", about.join("   ")));
+    }
+    out.push(format!(
+        "  Repositories {}   Followers {}   Following {}   Joined {}",
+        count(user, "public_repos"),
+        count(user, "followers"),
+        count(user, "following"),
+        day(text(user, "created_at"))
+    ));
+    out.push(format!("  {}", text(user, "html_url")));
+
+    let mut own: Vec<&Value> = repos
+        .as_array()
+        .map(|list| list.iter().filter(|r| r["fork"] != true).collect())
+        .unwrap_or_default();
+    if own.is_empty() {
+        return out.join("\n");
+    }
+    // The API lists them by push time; the cards go by stars
+    let recent: Vec<&Value> = own.iter().take(RECENT).copied().collect();
+    own.sort_by_key(|r| std::cmp::Reverse(count(r, "stargazers_count")));
+    out.push(String::new());
+    out.push("Popular repositories".into());
+    out.extend(cards(&own[..own.len().min(PINNED)], width));
+    out.push(String::new());
+    out.push("Recently pushed".into());
+    for repo in recent {
+        out.push(format!(
+            "  {}  {}",
+            day(text(repo, "pushed_at")),
+            text(repo, "name")
+        ));
+    }
+    out.join("\n")
+}
+
+fn render_repo(repo: &Value, commits: &Value, width: usize) -> String {
+    let mut out = vec![text(repo, "full_name").to_string()];
+    if !text(repo, "description").is_empty() {
+        out.push(format!("  {}", text(repo, "description")));
+    }
+    out.push(format!(
+        "  ★ {} stars   ⑂ {} forks   {} watching   {} open issues",
+        count(repo, "stargazers_count"),
+        count(repo, "forks_count"),
+        count(repo, "subscribers_count"),
+        count(repo, "open_issues_count")
+    ));
+    let license = repo["license"]["spdx_id"].as_str().unwrap_or("");
+    let facts: Vec<String> = [
+        text(repo, "language").to_string(),
+        license.to_string(),
+        format!("default branch {}", text(repo, "default_branch")),
+        format!("pushed {}", day(text(repo, "pushed_at"))),
+    ]
+    .into_iter()
+    .filter(|f| !f.is_empty() && f != "NOASSERTION")
+    .collect();
+    out.push(format!("  {}", facts.join(" · ")));
+    if let Some(topics) = repo["topics"].as_array().filter(|t| !t.is_empty()) {
+        let topics: Vec<&str> = topics.iter().filter_map(Value::as_str).collect();
+        out.push(format!("  topics: {}", topics.join(", ")));
+    }
+    out.push(format!("  {}", text(repo, "html_url")));
+
+    if let Some(commits) = commits.as_array().filter(|c| !c.is_empty()) {
+        out.push(String::new());
+        out.push("Recent commits".into());
+        for commit in commits.iter().take(RECENT) {
+            let sha = text(commit, "sha");
+            let detail = &commit["commit"];
+            let subject = text(detail, "message").lines().next().unwrap_or("");
+            let line = format!(
+                "  {}  {}  {:<12}  {}",
+                sha.get(..7).unwrap_or(sha),
+                day(text(&detail["author"], "date")),
+                fit(text(&detail["author"], "name"), 12),
+                subject
+            );
+            out.push(fit(&line, width));
+        }
+    }
+    out.join("\n")
+}
+
+impl System {
+    fn gh_cache(&self, path: &str) -> String {
+        let home = Self::default_home_for_user(&self.current_user());
+        format!("{}/.cache/gh/{}", home, cache_name(path))
+    }
+
+    /// The cached answer for `path` and how many seconds old it is
+    fn gh_cached(&self, path: &str) -> Option<(String, i64)> {
+        let node = self.kernel.fs.resolve(&self.gh_cache(path))?;
+        Some((node.data.clone(), clock::now_secs() - node.modified))
+    }
+
+    /// Draw `target` from one answer per API path
+    fn gh_render(&self, target: &str, bodies: &[String]) -> Result<String, String> {
+        let mut values = Vec::new();
+        for body in bodies {
+            let v: Value = serde_json::from_str(body)
+                .map_err(|_| format!("gh: {}: unexpected answer from GitHub", target))?;
+            if let Some(message) = api_error(&v) {
+                return Err(format!("gh: {}: {}", target, message));
+            }
+            values.push(v);
+        }
+        let width = self.lynx_width();
+        match (parse_target(target), values.as_slice()) {
+            (Some(Target::User(_)), [user, repos]) => Ok(render_user(user, repos, width)),
+            (Some(Target::Repo(..)), [repo, commits]) => Ok(render_repo(repo, commits, width)),
+            _ => Err(format!("gh: {}: unexpected answer from GitHub", target)),
+        }
+    }
+
+    /// The rest of `gh` once GitHub answered. Good answers are cached; for
+    /// the rest the cached copy stands in, whatever its age
+    pub(super) fn gh_finish(
+        &mut self,
+        target: &str,
+        fetched: Vec<Result<String, String>>,
+    ) -> CmdOutput {
+        let paths = api_paths(target);
+        let mut bodies = Vec::new();
+        let mut problem = None;
+        for (n, path) in paths.iter().enumerate() {
+            let answer = fetched.get(n).cloned().unwrap_or(Err(String::new()));
+            let usable = answer.as_ref().is_ok_and(|body| {
+                serde_json::from_str::<Value>(body).is_ok_and(|v| api_error(&v).is_none())
+            });
+            match answer {
+                Ok(body) if usable => {
+                    let cache = self.gh_cache(path);
+                    let dir = cache
+                        .rsplit_once('/')
+                        .map_or("/", |(dir, _)| dir)
+                        .to_string();
+                    let _ = self
+                        .ensure_dir_all(&dir)
+                        .and_then(|()| self.write_file_bytes(&cache, body.as_bytes()));
+                    bodies.push(body);
+                }
+                // GitHub's own answer, such as Not Found, is reported below
+                Ok(body) if !rate_limited(&body) => bodies.push(body),
+                answer => {
+                    let why = match answer {
+                        Ok(_) => "GitHub API rate limit exceeded",
+                        Err(_) => "GitHub is unreachable",
+                    };
+                    let Some((body, age)) = self.gh_cached(path) else {
+                        return CmdOutput::error(
+                            1,
+                            if rate_limited(why) {
+                                format!("gh: {} (60 requests an hour); try again later", why)
+                            } else {
+                                format!("gh: {}", why)
+                            },
+                        );
+                    };
+                    problem = Some(format!(
+                        "gh: {}, showing the copy from {} minutes ago",
+                        why,
+                        age / 60
+                    ));
+                    bodies.push(body);
+                }
+            }
+        }
+        let view = self.gh_render(target, &bodies);
+        CmdOutput::from_result(match problem {
+            Some(note) => view.map(|view| format!("{}\n{}", note, view)),
+            None => view,
+        })
+    }
+
+    pub(super) fn cmd_gh(&mut self, args: &[&str]) -> CmdOutput {
+        let [target] = args else {
+            return CmdOutput::usage(GH_USAGE);
+        };
+        let target = target.trim_start_matches('@');
+        if parse_target(target).is_none() {
+            return CmdOutput::error(1, format!("gh: {}: not a GitHub user or USER/REPO", target));
+        }
+        let cached: Option<Vec<(String, i64)>> = api_paths(target)
+            .iter()
+            .map(|path| self.gh_cached(path))
+            .collect();
+        if let Some(cached) = &cached {
+            if cached.iter().all(|(_, age)| *age < FRESH_SECS) {
+                let bodies: Vec<String> = cached.iter().map(|(body, _)| body.clone()).collect();
+                return CmdOutput::from_result(self.gh_render(target, &bodies));
+            }
+        }
+        if self.host_unreachable("api.github.com").is_some() {
+            let offline = api_paths(target)
+                .iter()
+                .map(|_| Err(String::new()))
+                .collect();
+            return self.gh_finish(target, offline);
+        }
+        CmdOutput::ok(self.emit(SystemEvent::Github {
+            target: target.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = r#"{"login": "octo", "name": "Octo Cat", "bio": "Tentacles",
+        "location": "Sea", "public_repos": 3, "followers": 10, "following": 1,
+        "created_at": "2011-01-25T18:44:36Z", "html_url": "https://github.com/octo"}"#;
+    const REPOS: &str = r#"[
+        {"name": "fresh", "stargazers_count": 1, "pushed_at": "2026-10-15T00:00:00Z"},
+        {"name": "forked", "fork": true, "stargazers_count": 99},
+        {"name": "famous", "description": "Loved by many", "language": "Rust",
+         "stargazers_count": 42, "forks_count": 3, "pushed_at": "2026-01-02T00:00:00Z"}
+    ]"#;
+
+    #[test]
+    fn profiles_show_cards_by_stars() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let view = sys
+            .gh_finish("octo", vec![Ok(USER.into()), Ok(REPOS.into())])
+            .stdout;
+        assert!(view.starts_with("Octo Cat (octo)\n  Tentacles\n  Location: Sea"));
+        assert!(view.contains("Repositories 3   Followers 10   Following 1   Joined 2011-01-25"));
+        let famous = view.find("│ famous").unwrap();
+        assert!(famous < view.find("│ fresh").unwrap());
+        assert!(view.contains("● Rust  ★ 42  ⑂ 3"));
+        assert!(!view.contains("forked"));
+        assert!(view.ends_with("Recently pushed\n  2026-10-15  fresh\n  2026-01-02  famous"));
+
+        // Cached now, so a rate-limited answer falls back to the copy
+        let limited = r#"{"message": "API rate limit exceeded for 1.2.3.4."}"#;
+        let stale = sys
+            .gh_finish("octo", vec![Ok(limited.into()), Ok(limited.into())])
+            .stdout;
+        assert!(stale.starts_with("gh: GitHub API rate limit exceeded, showing the copy"));
+        assert!(sys.exec("gh octo").starts_with("Octo Cat (octo)"));
+    }
+
+    #[test]
+    fn repositories_list_recent_commits() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let repo = r#"{"id": 1, "full_name": "octo/famous", "stargazers_count": 42,
+            "forks_count": 3, "subscribers_count": 5, "open_issues_count": 2,
+            "language": "Rust", "license": {"spdx_id": "MIT"}, "default_branch": "main",
+            "pushed_at": "2026-10-15T00:00:00Z", "topics": ["wasm"]}"#;
+        let commits = r#"[{"sha": "a1b2c3d4e5", "commit": {"message": "Fix it\n\nbody",
+            "author": {"name": "Octo", "date": "2026-10-15T09:00:00Z"}}}]"#;
+        let view = sys
+            .gh_finish("octo/famous", vec![Ok(repo.into()), Ok(commits.into())])
+            .stdout;
+        assert!(view.contains("  Rust · MIT · default branch main · pushed 2026-10-15"));
+        assert!(view.ends_with("Recent commits\n  a1b2c3d  2026-10-15  Octo          Fix it"));
+
+        let missing = r#"{"message": "Not Found"}"#;
+        let out = sys.gh_finish("octo/nope", vec![Ok(missing.into()), Ok(missing.into())]);
+        assert_eq!(out.status, 1);
+        assert_eq!(out.stderr, "gh: octo/nope: Not Found");
+        assert!(sys.exec("gh a/b/c").starts_with("gh: a/b/c: not a GitHub"));
+    }
+}
@@ -0,0 +1,1766 @@
+use super::{System, SystemEvent, BINARY_PREFIX};
+use crate::clock;
+use crate::shell::CmdOutput;
+use std::collections::{BTreeMap, BTreeSet};
+
+const VERSION: &str = "git version 2.43.0";
+const DEFAULT_BRANCH: &str = "master";
+const CONTEXT: usize = 3;
+
+/// Tracked paths, relative to the work tree, mapped to their blob hashes
+type FileMap = BTreeMap<String, String>;
+
+const USAGE: &str = "usage: git [--version] [--help] <command> [<args>]
+
+These are common Git commands used in various situations:
+
+start a working area
+   clone      Clone a GitHub repository into a new directory
+   init       Create an empty Git repository or reinitialize an existing one
+
+work on the current change
+   add        Add file contents to the index
+   status     Show the working tree status
+   diff       Show changes between commits, commit and working tree, etc
+
+grow, mark and tweak your common history
+   commit     Record changes to the repository
+   log        Show commit logs
+   branch     List or create branches
+   checkout   Switch branches or restore working tree files
+   config     Get and set repository options";
+
+/// A work tree with its `.git` directory
+struct Repo {
+    root: String,
+}
+
+impl Repo {
+    fn path(&self, rel: &str) -> String {
+        if self.root == "/" {
+            format!("/{}", rel)
+        } else {
+            format!("{}/{}", self.root, rel)
+        }
+    }
+
+    fn git(&self, rel: &str) -> String {
+        self.path(&format!(".git/{}", rel))
+    }
+}
+
+enum Head {
+    Branch(String),
+    Detached(String),
+}
+
+struct Commit {
+    tree: String,
+    parent: Option<String>,
+    author: String,
+    time: i64,
+    message: String,
+}
+
+impl Commit {
+    fn parse(body: &str) -> Option<Self> {
+        let (headers, message) = body.split_once("\n\n").unwrap_or((body, ""));
+        let mut commit = Commit {
+            tree: String::new(),
+            parent: None,
+            author: String::new(),
+            time: 0,
+            message: message.trim_end().to_string(),
+        };
+        for line in headers.lines() {
+            let (key, value) = line.split_once(' ')?;
+            match key {
+                "tree" => commit.tree = value.to_string(),
+                "parent" if commit.parent.is_none() => commit.parent = Some(value.to_string()),
+                "author" => {
+                    // `Name <email> 1700000000 +0000`
+                    let mut parts = value.rsplitn(3, ' ');
+                    let _zone = parts.next();
+                    commit.time = parts.next()?.parse().ok()?;
+                    commit.author = parts.next()?.to_string();
+                }
+                _ => {}
+            }
+        }
+        Some(commit)
+    }
+
+    fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or("")
+    }
+}
+
+impl System {
+    pub(super) fn cmd_git(&mut self, args: &[&str]) -> CmdOutput {
+        let Some((&sub, rest)) = args.split_first() else {
+            return CmdOutput::usage(USAGE);
+        };
+        match sub {
+            "--version" | "version" => return CmdOutput::ok(VERSION),
+            "--help" | "help" => return CmdOutput::ok(USAGE),
+            "init" => return CmdOutput::from_result(self.git_init(rest)),
+            "clone" => return CmdOutput::from_result(self.git_clone(rest)),
+            _ => {}
+        }
+        let repo = match self.git_repo() {
+            Some(repo) => repo,
+            None if is_git_command(sub) => {
+                return CmdOutput::error(
+                    128,
+                    "fatal: not a git repository (or any of the parent directories): .git",
+                )
+            }
+            None => return CmdOutput::error(1, not_a_command(sub)),
+        };
+        let result = match sub {
+            "status" => self.git_status(&repo),
+            "add" => self.git_add(&repo, rest),
+            "commit" => self.git_commit(&repo, rest),
+            "log" => self.git_log(&repo, rest),
+            "diff" => self.git_diff(&repo, rest),
+            "checkout" | "switch" => self.git_checkout(&repo, rest),
+            "branch" => self.git_branch(&repo, rest),
+            "config" => self.git_config(&repo, rest),
+            _ => Err(not_a_command(sub)),
+        };
+        CmdOutput::from_result(result)
+    }
+
+    // ---- repository storage ----
+
+    /// The repository containing the current directory, if any
+    fn git_repo(&self) -> Option<Repo> {
+        let mut dir = self.kernel.fs.normalize(".");
+        loop {
+            let repo = Repo { root: dir.clone() };
+            if self
+                .kernel
+                .fs
+                .resolve(&repo.path(".git"))
+                .is_some_and(|n| n.is_dir)
+            {
+                return Some(repo);
+            }
+            if dir == "/" {
+                return None;
+            }
+            dir = match dir.rfind('/') {
+                Some(0) | None => "/".into(),
+                Some(idx) => dir[..idx].to_string(),
+            };
+        }
+    }
+
+    fn git_read(&self, path: &str) -> Option<String> {
+        self.kernel
+            .fs
+            .resolve(path)
+            .filter(|n| !n.is_dir)
+            .map(|n| n.data.clone())
+    }
+
+    fn git_write(&mut self, path: &str, data: &str) -> Result<(), String> {
+        if let Some(idx) = path.rfind('/') {
+            self.ensure_dir_all(&path[..idx.max(1)])?;
+        }
+        self.write_file_bytes(path, data.as_bytes())
+            .map_err(|e| format!("fatal: could not write '{}': {}", path, e))
+    }
+
+    /// Store an object under `.git/objects` and return its hash
+    fn git_store(&mut self, repo: &Repo, kind: &str, body: &str) -> Result<String, String> {
+        let raw = format!("{} {}\0{}", kind, body.len(), body);
+        let hash = sha1_hex(raw.as_bytes());
+        let path = repo.git(&format!("objects/{}/{}", &hash[..2], &hash[2..]));
+        if self.kernel.fs.resolve(&path).is_none() {
+            self.git_write(&path, &raw)?;
+        }
+        Ok(hash)
+    }
+
+    fn git_load(&self, repo: &Repo, hash: &str) -> Option<(String, String)> {
+        if hash.len() < 3 {
+            return None;
+        }
+        let raw = self.git_read(&repo.git(&format!("objects/{}/{}", &hash[..2], &hash[2..])))?;
+        let (header, body) = raw.split_once('\0')?;
+        let kind = header.split(' ').next()?.to_string();
+        Some((kind, body.to_string()))
+    }
+
+    fn git_head(&self, repo: &Repo) -> Head {
+        let head = self.git_read(&repo.git("HEAD")).unwrap_or_default();
+        match head.trim().strip_prefix("ref: refs/heads/") {
+            Some(branch) => Head::Branch(branch.to_string()),
+            None => Head::Detached(head.trim().to_string()),
+        }
+    }
+
+    fn git_branch_tip(&self, repo: &Repo, branch: &str) -> Option<String> {
+        self.git_read(&repo.git(&format!("refs/heads/{}", branch)))
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty())
+    }
+
+    fn git_head_commit(&self, repo: &Repo) -> Option<String> {
+        match self.git_head(repo) {
+            Head::Branch(branch) => self.git_branch_tip(repo, &branch),
+            Head::Detached(hash) => Some(hash),
+        }
+    }
+
+    fn git_load_commit(&self, repo: &Repo, hash: &str) -> Option<Commit> {
+        match self.git_load(repo, hash)? {
+            (kind, body) if kind == "commit" => Commit::parse(&body),
+            _ => None,
+        }
+    }
+
+    /// Files recorded in a commit; trees here are flat listings of every path
+    fn git_commit_files(&self, repo: &Repo, hash: Option<&str>) -> FileMap {
+        let Some(commit) = hash.and_then(|h| self.git_load_commit(repo, h)) else {
+            return FileMap::new();
+        };
+        let Some((_, tree)) = self.git_load(repo, &commit.tree) else {
+            return FileMap::new();
+        };
+        tree.lines()
+            .filter_map(|line| {
+                let (meta, path) = line.split_once('\t')?;
+                let hash = meta.rsplit(' ').next()?;
+                Some((path.to_string(), hash.to_string()))
+            })
+            .collect()
+    }
+
+    fn git_index(&self, repo: &Repo) -> FileMap {
+        self.git_read(&repo.git("index"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (meta, path) = line.split_once('\t')?;
+                let hash = meta.split(' ').nth(1)?;
+                Some((path.to_string(), hash.to_string()))
+            })
+            .collect()
+    }
+
+    fn git_write_index(&mut self, repo: &Repo, index: &FileMap) -> Result<(), String> {
+        let text: String = index
+            .iter()
+            .map(|(path, hash)| format!("100644 {} 0\t{}\n", hash, path))
+            .collect();
+        self.git_write(&repo.git("index"), &text)
+    }
+
+    /// Every file in the work tree that `.gitignore` does not exclude
+    fn git_worktree(&self, repo: &Repo) -> BTreeMap<String, String> {
+        let ignore: Vec<String> = self
+            .git_read(&repo.path(".gitignore"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(String::from)
+            .collect();
+        let mut files = BTreeMap::new();
+        if let Some(root) = self.kernel.fs.resolve(&repo.root) {
+            collect_files(root, "", &ignore, &mut files);
+        }
+        files
+    }
+
+    fn git_is_ignored(&self, repo: &Repo, rel: &str) -> bool {
+        let ignore: Vec<String> = self
+            .git_read(&repo.path(".gitignore"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(String::from)
+            .collect();
+        let mut prefix = String::new();
+        rel.split('/').any(|part| {
+            prefix = if prefix.is_empty() {
+                part.to_string()
+            } else {
+                format!("{}/{}", prefix, part)
+            };
+            is_ignored(&ignore, &prefix, part, prefix.len() < rel.len())
+        })
+    }
+
+    /// Turn `HEAD`, `HEAD~2`, a branch name or an abbreviated hash into a commit
+    fn git_resolve(&self, repo: &Repo, rev: &str) -> Option<String> {
+        let (base, back) = match rev.split_once('~') {
+            Some((base, "")) => (base, 1),
+            Some((base, n)) => (base, n.parse().ok()?),
+            None => match rev.strip_suffix('^') {
+                Some(base) => (base, 1),
+                None => (rev, 0),
+            },
+        };
+        let mut hash = if base == "HEAD" || base == "@" {
+            self.git_head_commit(repo)?
+        } else if let Some(tip) = self.git_branch_tip(repo, base) {
+            tip
+        } else if base.len() >= 4 && base.chars().all(|c| c.is_ascii_hexdigit()) {
+            let base = base.to_ascii_lowercase();
+            let dir = self
+                .kernel
+                .fs
+                .resolve(&repo.git(&format!("objects/{}", &base[..2])))?;
+            let matches: Vec<String> = dir
+                .children
+                .keys()
+                .map(|rest| format!("{}{}", &base[..2], rest))
+                .filter(|h| h.starts_with(&base))
+                .filter(|h| self.git_load_commit(repo, h).is_some())
+                .collect();
+            match matches.as_slice() {
+                [one] => one.clone(),
+                _ => return None,
+            }
+        } else {
+            return None;
+        };
+        self.git_load_commit(repo, &hash)?;
+        for _ in 0..back {
+            hash = self.git_load_commit(repo, &hash)?.parent?;
+        }
+        Some(hash)
+    }
+
+    /// A repo-relative path as git prints it from the current directory
+    fn git_display_path(&self, repo: &Repo, rel: &str) -> String {
+        let cwd = self.kernel.fs.normalize(".");
+        let here = cwd
+            .strip_prefix(repo.root.as_str())
+            .unwrap_or("")
+            .trim_start_matches('/');
+        if here.is_empty() {
+            return rel.to_string();
+        }
+        if let Some(inside) = rel.strip_prefix(&format!("{}/", here)) {
+            return inside.to_string();
+        }
+        let mut up = String::new();
+        let mut dir = here;
+        loop {
+            up.push_str("../");
+            match dir.rfind('/') {
+                Some(idx) => {
+                    dir = &dir[..idx];
+                    if let Some(inside) = rel.strip_prefix(&format!("{}/", dir)) {
+                        return format!("{}{}", up, inside);
+                    }
+                }
+                None => return format!("{}{}", up, rel),
+            }
+        }
+    }
+
+    /// A path argument as a repo-relative path, or `None` outside the work tree
+    fn git_rel(&self, repo: &Repo, arg: &str) -> Option<String> {
+        let abs = self.kernel.fs.normalize(arg);
+        if abs == repo.root {
+            return Some(String::new());
+        }
+        let rel = if repo.root == "/" {
+            abs.strip_prefix('/')
+        } else {
+            abs.strip_prefix(repo.root.as_str())
+                .and_then(|r| r.strip_prefix('/'))
+        }?;
+        Some(rel.to_string())
+    }
+
+    fn git_identity(&self, repo: &Repo) -> String {
+        let config = self.git_read(&repo.git("config")).unwrap_or_default();
+        let user = self.current_user();
+        let name = config_value(&config, "user", "name").unwrap_or_else(|| user.clone());
+        let email = config_value(&config, "user", "email")
+            .unwrap_or_else(|| format!("{}@{}", user, self.cmd_hostname().stdout.trim()));
+        format!("{} <{}>", name, email)
+    }
+
+    // ---- commands ----
+
+    fn git_init(&mut self, args: &[&str]) -> Result<String, String> {
+        let target = self
+            .kernel
+            .fs
+            .normalize(args.iter().find(|a| !a.starts_with('-')).unwrap_or(&"."));
+        let repo = Repo { root: target };
+        let git_dir = repo.path(".git");
+        if self.kernel.fs.resolve(&git_dir).is_some() {
+            return Ok(format!(
+                "Reinitialized existing Git repository in {}/",
+                git_dir
+            ));
+        }
+        if !self.can_write_path(&repo.root)
+            || (self.kernel.fs.resolve(&repo.root).is_some() && !self.can_write_path(&git_dir))
+        {
+            return Err(format!(
+                "fatal: cannot mkdir {}: Permission denied",
+                git_dir
+            ));
+        }
+        let files = [
+            ("HEAD", format!("ref: refs/heads/{}\n", DEFAULT_BRANCH)),
+            (
+                "config",
+                "[core]\n\trepositoryformatversion = 0\n\tfilemode = true\n\tbare = false\n"
+                    .to_string(),
+            ),
+            (
+                "description",
+                "Unnamed repository; edit this file 'description' to name the repository.\n"
+                    .to_string(),
+            ),
+        ];
+        for dir in ["objects", "refs/heads", "refs/tags"] {
+            self.ensure_dir_all(&repo.git(dir))
+                .map_err(|e| format!("fatal: cannot mkdir {}: {}", git_dir, e))?;
+        }
+        for (name, data) in files {
+            self.git_write(&repo.git(name), &data)?;
+        }
+        Ok(format!("Initialized empty Git repository in {}/", git_dir))
+    }
+
+    fn git_status(&self, repo: &Repo) -> Result<String, String> {
+        let head = self.git_head_commit(repo);
+        let committed = self.git_commit_files(repo, head.as_deref());
+        let index = self.git_index(repo);
+        let work = self.git_worktree(repo);
+
+        let mut out = vec![match self.git_head(repo) {
+            Head::Branch(b) => format!("On branch {}", b),
+            Head::Detached(h) => format!("HEAD detached at {}", short(&h)),
+        }];
+        if head.is_none() {
+            out.push(String::new());
+            out.push("No commits yet".into());
+        }
+
+        let mut staged = Vec::new();
+        for path in committed
+            .keys()
+            .chain(index.keys())
+            .collect::<BTreeSet<_>>()
+        {
+            let label = match (committed.get(path), index.get(path)) {
+                (None, Some(_)) => "new file",
+                (Some(_), None) => "deleted",
+                (Some(a), Some(b)) if a != b => "modified",
+                _ => continue,
+            };
+            staged.push(format!(
+                "\t{}:   {}",
+                label,
+                self.git_display_path(repo, path)
+            ));
+        }
+        let mut unstaged = Vec::new();
+        for (path, hash) in &index {
+            let label = match work.get(path) {
+                None => "deleted",
+                Some(data) if blob_id(data) != *hash => "modified",
+                _ => continue,
+            };
+            unstaged.push(format!(
+                "\t{}:   {}",
+                label,
+                self.git_display_path(repo, path)
+            ));
+        }
+        // Untracked directories are shown once, as `dir/`
+        let mut untracked = BTreeSet::new();
+        for path in work.keys().filter(|p| !index.contains_key(*p)) {
+            let mut shown = path.clone();
+            let mut dir = path.as_str();
+            while let Some(idx) = dir.rfind('/') {
+                dir = &dir[..idx];
+                let prefix = format!("{}/", dir);
+                if index.keys().any(|p| p.starts_with(&prefix)) {
+                    break;
+                }
+                shown = prefix;
+            }
+            untracked.insert(self.git_display_path(repo, &shown));
+        }
+
+        if !staged.is_empty() {
+            out.push(String::new());
+            out.push("Changes to be committed:".into());
+            out.extend(staged.iter().cloned());
+        }
+        if !unstaged.is_empty() {
+            out.push(String::new());
+            out.push("Changes not staged for commit:".into());
+            out.push("  (use \"git add <file>...\" to update what will be committed)".into());
+            out.extend(unstaged.iter().cloned());
+        }
+        if !untracked.is_empty() {
+            out.push(String::new());
+            out.push("Untracked files:".into());
+            out.push("  (use \"git add <file>...\" to include in what will be committed)".into());
+            out.extend(untracked.iter().map(|p| format!("\t{}", p)));
+        }
+        out.push(String::new());
+        if staged.is_empty() {
+            out.push(
+                if !unstaged.is_empty() {
+                    "no changes added to commit (use \"git add\" and/or \"git commit -a\")"
+                } else if !untracked.is_empty() {
+                    "nothing added to commit but untracked files present (use \"git add\" to track)"
+                } else if head.is_none() {
+                    "nothing to commit (create/copy files and use \"git add\" to track)"
+                } else {
+                    "nothing to commit, working tree clean"
+                }
+                .into(),
+            );
+        } else {
+            out.pop();
+        }
+        Ok(out.join("\n"))
+    }
+
+    fn git_add(&mut self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let all = args.iter().any(|a| matches!(*a, "-A" | "--all"));
+        let specs: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|a| !a.starts_with('-'))
+            .collect();
+        if specs.is_empty() && !all {
+            return Ok("Nothing specified, nothing added.\n\
+                       hint: Maybe you wanted to say 'git add .'?"
+                .into());
+        }
+        let specs = if specs.is_empty() { vec!["."] } else { specs };
+        let mut index = self.git_index(repo);
+        let work = self.git_worktree(repo);
+        let mut ignored = Vec::new();
+
+        for spec in specs {
+            let Some(rel) = self.git_rel(repo, spec) else {
+                return Err(format!(
+                    "fatal: {}: '{}' is outside repository at '{}'",
+                    spec, spec, repo.root
+                ));
+            };
+            let prefix = if rel.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", rel)
+            };
+            let under = |p: &str| rel.is_empty() || p == rel || p.starts_with(&prefix);
+            let matched: Vec<&String> = work.keys().filter(|p| under(p)).collect();
+            let gone: Vec<String> = index
+                .keys()
+                .filter(|p| under(p) && !work.contains_key(*p))
+                .cloned()
+                .collect();
+            if matched.is_empty() && gone.is_empty() {
+                if self.kernel.fs.resolve(&repo.path(&rel)).is_some()
+                    && self.git_is_ignored(repo, &rel)
+                {
+                    ignored.push(spec.to_string());
+                    continue;
+                }
+                if self
+                    .kernel
+                    .fs
+                    .resolve(&repo.path(&rel))
+                    .is_some_and(|n| n.is_dir)
+                {
+                    continue;
+                }
+                return Err(format!(
+                    "fatal: pathspec '{}' did not match any files",
+                    spec
+                ));
+            }
+            for path in matched {
+                if !self.has_access(&repo.path(path), 4) {
+                    return Err(format!(
+                        "error: open(\"{}\"): Permission denied\n\
+                         error: unable to index file '{}'\n\
+                         fatal: adding files failed",
+                        path, path
+                    ));
+                }
+                let hash = self.git_store(repo, "blob", &work[path])?;
+                index.insert(path.clone(), hash);
+            }
+            for path in gone {
+                index.remove(&path);
+            }
+        }
+        self.git_write_index(repo, &index)?;
+        if ignored.is_empty() {
+            Ok(String::new())
+        } else {
+            Ok(format!(
+                "The following paths are ignored by one of your .gitignore files:\n{}\n\
+                 hint: Use -f if you really want to add them.",
+                ignored.join("\n")
+            ))
+        }
+    }
+
+    fn git_commit(&mut self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let mut message = None;
+        let mut stage_tracked = false;
+        let mut i = 0;
+        while i < args.len() {
+            let arg = args[i];
+            if arg == "--all"
+                || (arg.starts_with('-') && !arg.starts_with("--") && arg.contains('a'))
+            {
+                stage_tracked = true;
+            }
+            if arg == "-m"
+                || arg == "--message"
+                || (arg.starts_with('-') && arg.ends_with('m') && !arg.starts_with("--"))
+            {
+                // The shell splits on spaces, so the message is the rest of the line
+                let text = args[i + 1..].join(" ");
+                if text.is_empty() {
+                    return Err("error: switch `m' requires a value".into());
+                }
+                message = Some(strip_quotes(&text).to_string());
+                break;
+            }
+            i += 1;
+        }
+
+        let mut index = self.git_index(repo);
+        if stage_tracked {
+            let work = self.git_worktree(repo);
+            let tracked: Vec<String> = index.keys().cloned().collect();
+            for path in tracked {
+                match work.get(&path) {
+                    Some(data) => {
+                        let hash = self.git_store(repo, "blob", data)?;
+                        index.insert(path, hash);
+                    }
+                    None => {
+                        index.remove(&path);
+                    }
+                }
+            }
+            self.git_write_index(repo, &index)?;
+        }
+
+        let parent = self.git_head_commit(repo);
+        let before = self.git_commit_files(repo, parent.as_deref());
+        if before == index {
+            let status = self.git_status(repo)?;
+            return Err(status);
+        }
+        let Some(message) = message.filter(|m| !m.trim().is_empty()) else {
+            return Err("Aborting commit due to empty commit message.".into());
+        };
+
+        let identity = self.git_identity(repo);
+        let stamp = format!("{} {} +0000", identity, clock::now_secs());
+        let hash = self.git_record(repo, &index, parent.as_deref(), &message, &stamp)?;
+
+        let head = self.git_head(repo);
+        let label = match &head {
+            Head::Branch(b) => {
+                self.git_write(
+                    &repo.git(&format!("refs/heads/{}", b)),
+                    &format!("{}\n", hash),
+                )?;
+                b.clone()
+            }
+            Head::Detached(_) => {
+                self.git_write(&repo.git("HEAD"), &format!("{}\n", hash))?;
+                "detached HEAD".into()
+            }
+        };
+
+        let mut out = vec![format!(
+            "[{}{} {}] {}",
+            label,
+            if parent.is_none() {
+                " (root-commit)"
+            } else {
+                ""
+            },
+            short(&hash),
+            message.lines().next().unwrap_or("")
+        )];
+        let (mut files, mut insertions, mut deletions) = (0, 0, 0);
+        let mut modes = Vec::new();
+        for path in before.keys().chain(index.keys()).collect::<BTreeSet<_>>() {
+            let (old, new) = (before.get(path), index.get(path));
+            if old == new {
+                continue;
+            }
+            files += 1;
+            let old_text = old.map(|h| self.git_blob(repo, h)).unwrap_or_default();
+            let new_text = new.map(|h| self.git_blob(repo, h)).unwrap_or_default();
+            let (ins, del) = count_changes(&old_text, &new_text);
+            insertions += ins;
+            deletions += del;
+            match (old, new) {
+                (None, _) => modes.push(format!(" create mode 100644 {}", path)),
+                (_, None) => modes.push(format!(" delete mode 100644 {}", path)),
+                _ => {}
+            }
+        }
+        out.push(diffstat_summary(files, insertions, deletions));
+        out.extend(modes);
+        Ok(out.join("\n"))
+    }
+
+    /// Store the index as a tree plus a commit object on top of `parent`
+    fn git_record(
+        &mut self,
+        repo: &Repo,
+        index: &FileMap,
+        parent: Option<&str>,
+        message: &str,
+        stamp: &str,
+    ) -> Result<String, String> {
+        let tree: String = index
+            .iter()
+            .map(|(path, hash)| format!("100644 blob {}\t{}\n", hash, path))
+            .collect();
+        let tree = self.git_store(repo, "tree", &tree)?;
+        let mut body = format!("tree {}\n", tree);
+        if let Some(p) = parent {
+            body.push_str(&format!("parent {}\n", p));
+        }
+        body.push_str(&format!(
+            "author {}\ncommitter {}\n\n{}\n",
+            stamp, stamp, message
+        ));
+        self.git_store(repo, "commit", &body)
+    }
+
+    fn git_blob(&self, repo: &Repo, hash: &str) -> String {
+        self.git_load(repo, hash)
+            .map(|(_, body)| body)
+            .unwrap_or_default()
+    }
+
+    fn git_log(&self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let oneline = args.contains(&"--oneline");
+        let mut limit = usize::MAX;
+        let mut start = None;
+        let mut i = 0;
+        while i < args.len() {
+            let arg = args[i];
+            if arg == "-n" {
+                limit = args
+                    .get(i + 1)
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(limit);
+                i += 1;
+            } else if let Some(n) = arg.strip_prefix('-').and_then(|n| n.parse().ok()) {
+                limit = n;
+            } else if !arg.starts_with('-') {
+                start = Some(self.git_resolve(repo, arg).ok_or_else(|| {
+                    format!(
+                        "fatal: ambiguous argument '{}': unknown revision or path not in the working tree.",
+                        arg
+                    )
+                })?);
+            }
+            i += 1;
+        }
+        let head = self.git_head(repo);
+        let Some(mut hash) = start.or_else(|| self.git_head_commit(repo)) else {
+            let branch = match head {
+                Head::Branch(b) => b,
+                Head::Detached(_) => "HEAD".into(),
+            };
+            return Err(format!(
+                "fatal: your current branch '{}' does not have any commits yet",
+                branch
+            ));
+        };
+
+        let head_hash = self.git_head_commit(repo);
+        let branches = self.git_branches(repo);
+        let mut out = Vec::new();
+        for _ in 0..limit {
+            let Some(commit) = self.git_load_commit(repo, &hash) else {
+                break;
+            };
+            // `(HEAD -> master, feature)`
+            let mut refs = Vec::new();
+            if head_hash.as_deref() == Some(&hash) {
+                refs.push(match &head {
+                    Head::Branch(b) => format!("HEAD -> {}", b),
+                    Head::Detached(_) => "HEAD".into(),
+                });
+            }
+            for (name, tip) in &branches {
+                let current = matches!(&head, Head::Branch(b) if b == name);
+                if *tip == hash && !(current && head_hash.as_deref() == Some(&hash)) {
+                    refs.push(name.clone());
+                }
+            }
+            let decoration = if refs.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", refs.join(", "))
+            };
+            if oneline {
+                out.push(format!(
+                    "{}{} {}",
+                    short(&hash),
+                    decoration,
+                    commit.subject()
+                ));
+            } else {
+                if !out.is_empty() {
+                    out.push(String::new());
+                }
+                out.push(format!("commit {}{}", hash, decoration));
+                out.push(format!("Author: {}", commit.author));
+                out.push(format!("Date:   {}", format_date(commit.time)));
+                out.push(String::new());
+                out.extend(commit.message.lines().map(|l| format!("    {}", l)));
+            }
+            match commit.parent {
+                Some(p) => hash = p,
+                None => break,
+            }
+        }
+        Ok(out.join("\n"))
+    }
+
+    fn git_branches(&self, repo: &Repo) -> Vec<(String, String)> {
+        let Some(dir) = self.kernel.fs.resolve(&repo.git("refs/heads")) else {
+            return Vec::new();
+        };
+        let mut names: Vec<&String> = dir.children.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .filter_map(|name| Some((name.clone(), self.git_branch_tip(repo, name)?)))
+            .collect()
+    }
+
+    fn git_diff(&mut self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let cached = args.iter().any(|a| matches!(*a, "--cached" | "--staged"));
+        let stat = args.contains(&"--stat");
+        let revs: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|a| !a.starts_with('-'))
+            .collect();
+        let resolve = |this: &Self, rev: &str| {
+            this.git_resolve(repo, rev).ok_or_else(|| {
+                format!(
+                    "fatal: ambiguous argument '{}': unknown revision or path not in the working tree.",
+                    rev
+                )
+            })
+        };
+
+        // Both sides as path -> text; the index side is read back from blobs
+        let index = self.git_index(repo);
+        let blobs = |this: &Self, files: &FileMap| -> BTreeMap<String, String> {
+            files
+                .iter()
+                .map(|(p, h)| (p.clone(), this.git_blob(repo, h)))
+                .collect()
+        };
+        let work = || -> BTreeMap<String, String> {
+            let all = self.git_worktree(repo);
+            all.into_iter()
+                .filter(|(p, _)| index.contains_key(p))
+                .collect()
+        };
+        let (old, new) = match (revs.as_slice(), cached) {
+            ([], false) => (blobs(self, &index), work()),
+            ([], true) => {
+                let head = self.git_head_commit(repo);
+                (
+                    blobs(self, &self.git_commit_files(repo, head.as_deref())),
+                    blobs(self, &index),
+                )
+            }
+            ([rev], false) => {
+                let commit = resolve(self, rev)?;
+                (
+                    blobs(self, &self.git_commit_files(repo, Some(&commit))),
+                    work(),
+                )
+            }
+            ([rev], true) => {
+                let commit = resolve(self, rev)?;
+                (
+                    blobs(self, &self.git_commit_files(repo, Some(&commit))),
+                    blobs(self, &index),
+                )
+            }
+            ([a, b, ..], _) => {
+                let (a, b) = (resolve(self, a)?, resolve(self, b)?);
+                (
+                    blobs(self, &self.git_commit_files(repo, Some(&a))),
+                    blobs(self, &self.git_commit_files(repo, Some(&b))),
+                )
+            }
+        };
+
+        let mut out = Vec::new();
+        let mut totals = (0, 0, 0);
+        for path in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+            let (a, b) = (old.get(path), new.get(path));
+            if a == b {
+                continue;
+            }
+            let (a_text, b_text) = (
+                a.map(String::as_str).unwrap_or(""),
+                b.map(String::as_str).unwrap_or(""),
+            );
+            if stat {
+                let (ins, del) = count_changes(a_text, b_text);
+                totals = (totals.0 + 1, totals.1 + ins, totals.2 + del);
+                out.push(format!(
+                    " {} | {} {}{}",
+                    path,
+                    ins + del,
+                    "+".repeat(ins.min(40)),
+                    "-".repeat(del.min(40))
+                ));
+                continue;
+            }
+            let id = |text: Option<&String>| {
+                text.map_or("0000000".to_string(), |t| short(&blob_id(t)).to_string())
+            };
+            out.push(format!("diff --git a/{} b/{}", path, path));
+            match (a, b) {
+                (None, _) => out.push("new file mode 100644".into()),
+                (_, None) => out.push("deleted file mode 100644".into()),
+                _ => {}
+            }
+            out.push(format!(
+                "index {}..{}{}",
+                id(a),
+                id(b),
+                if a.is_some() && b.is_some() {
+                    " 100644"
+                } else {
+                    ""
+                }
+            ));
+            if a_text.starts_with(BINARY_PREFIX) || b_text.starts_with(BINARY_PREFIX) {
+                out.push(format!(
+                    "Binary files {} and {} differ",
+                    a.map_or("/dev/null".into(), |_| format!("a/{}", path)),
+                    b.map_or("/dev/null".into(), |_| format!("b/{}", path))
+                ));
+                continue;
+            }
+            out.push(a.map_or("--- /dev/null".into(), |_| format!("--- a/{}", path)));
+            out.push(b.map_or("+++ /dev/null".into(), |_| format!("+++ b/{}", path)));
+            out.push(unified_diff(a_text, b_text));
+        }
+        if stat && totals.0 > 0 {
+            out.push(diffstat_summary(totals.0, totals.1, totals.2));
+        }
+        Ok(out.join("\n"))
+    }
+
+    fn git_branch(&mut self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let head = self.git_head(repo);
+        match args {
+            [] => {
+                let mut lines: Vec<String> = self
+                    .git_branches(repo)
+                    .into_iter()
+                    .map(|(name, _)| {
+                        let current = matches!(&head, Head::Branch(b) if *b == name);
+                        format!("{} {}", if current { "*" } else { " " }, name)
+                    })
+                    .collect();
+                if let Head::Detached(hash) = &head {
+                    lines.insert(0, format!("* (HEAD detached at {})", short(hash)));
+                }
+                Ok(lines.join("\n"))
+            }
+            ["-d" | "-D", name] => {
+                if matches!(&head, Head::Branch(b) if b == name) {
+                    return Err(format!(
+                        "error: cannot delete branch '{}' used by worktree at '{}'",
+                        name, repo.root
+                    ));
+                }
+                let Some(tip) = self.git_branch_tip(repo, name) else {
+                    return Err(format!("error: branch '{}' not found", name));
+                };
+                self.kernel
+                    .fs
+                    .remove(&repo.git(&format!("refs/heads/{}", name)))
+                    .map_err(|e| format!("error: {}", e))?;
+                Ok(format!("Deleted branch {} (was {}).", name, short(&tip)))
+            }
+            [name, rest @ ..] if !name.starts_with('-') => {
+                self.git_create_branch(repo, name, rest.first().copied())?;
+                Ok(String::new())
+            }
+            _ => Err("usage: git branch [-d] [<branchname> [<start-point>]]".into()),
+        }
+    }
+
+    fn git_create_branch(
+        &mut self,
+        repo: &Repo,
+        name: &str,
+        start: Option<&str>,
+    ) -> Result<String, String> {
+        if name.is_empty()
+            || name.starts_with('-')
+            || name.contains(['~', '^', ':', ' ', '\\'])
+            || name.contains("..")
+        {
+            return Err(format!("fatal: '{}' is not a valid branch name", name));
+        }
+        if self.git_branch_tip(repo, name).is_some() {
+            return Err(format!("fatal: a branch named '{}' already exists", name));
+        }
+        let tip = match start {
+            Some(rev) => self
+                .git_resolve(repo, rev)
+                .ok_or_else(|| format!("fatal: not a valid object name: '{}'", rev))?,
+            None => self.git_head_commit(repo).ok_or_else(|| {
+                format!(
+                    "fatal: not a valid object name: '{}'",
+                    match self.git_head(repo) {
+                        Head::Branch(b) => b,
+                        Head::Detached(h) => h,
+                    }
+                )
+            })?,
+        };
+        self.git_write(
+            &repo.git(&format!("refs/heads/{}", name)),
+            &format!("{}\n", tip),
+        )?;
+        Ok(tip)
+    }
+
+    fn git_checkout(&mut self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let (target, new_branch) = match args {
+            ["-b" | "-c", name, rest @ ..] => {
+                // A new branch starting here keeps the work tree as it is
+                if rest.is_empty() {
+                    self.git_create_branch(repo, name, None)?;
+                    self.git_write(&repo.git("HEAD"), &format!("ref: refs/heads/{}\n", name))?;
+                    return Ok(format!("Switched to a new branch '{}'", name));
+                }
+                (rest[0], Some(*name))
+            }
+            ["--", paths @ ..] => return self.git_restore_paths(repo, paths),
+            [target] => (*target, None),
+            [] => return Ok(String::new()),
+            _ => return self.git_restore_paths(repo, args),
+        };
+
+        let is_branch = new_branch.is_none() && self.git_branch_tip(repo, target).is_some();
+        let Some(commit) = self.git_resolve(repo, target) else {
+            // `git checkout file` discards changes to a tracked file
+            let index = self.git_index(repo);
+            if self.git_rel(repo, target).is_some_and(|rel| {
+                index
+                    .keys()
+                    .any(|p| *p == rel || p.starts_with(&format!("{}/", rel)))
+            }) {
+                return self.git_restore_paths(repo, args);
+            }
+            return Err(format!(
+                "error: pathspec '{}' did not match any file(s) known to git",
+                target
+            ));
+        };
+
+        let head = self.git_head(repo);
+        if is_branch && matches!(&head, Head::Branch(b) if b == target) {
+            return Ok(format!("Already on '{}'", target));
+        }
+        let current = self.git_head_commit(repo);
+        let previous = match (&head, &current) {
+            (Head::Detached(_), Some(hash)) => self.git_load_commit(repo, hash).map(|c| {
+                format!(
+                    "Previous HEAD position was {} {}\n",
+                    short(hash),
+                    c.subject()
+                )
+            }),
+            _ => None,
+        };
+        self.git_switch_tree(repo, current.as_deref(), &commit)?;
+
+        let out = if let Some(name) = new_branch {
+            self.git_create_branch(repo, name, Some(&commit))?;
+            self.git_write(&repo.git("HEAD"), &format!("ref: refs/heads/{}\n", name))?;
+            format!("Switched to a new branch '{}'", name)
+        } else if is_branch {
+            self.git_write(&repo.git("HEAD"), &format!("ref: refs/heads/{}\n", target))?;
+            format!("Switched to branch '{}'", target)
+        } else {
+            self.git_write(&repo.git("HEAD"), &format!("{}\n", commit))?;
+            let subject = self
+                .git_load_commit(repo, &commit)
+                .map(|c| c.subject().to_string())
+                .unwrap_or_default();
+            let mut text = String::new();
+            if matches!(head, Head::Branch(_)) {
+                text.push_str(&format!(
+                    "Note: switching to '{}'.\n\n\
+                     You are in 'detached HEAD' state. You can look around, make experimental\n\
+                     changes and commit them, and you can discard any commits you make in this\n\
+                     state without impacting any branches by switching back to a branch.\n\n",
+                    target
+                ));
+            }
+            text.push_str(&format!("HEAD is now at {} {}", short(&commit), subject));
+            text
+        };
+        Ok(format!("{}{}", previous.unwrap_or_default(), out))
+    }
+
+    /// Move the work tree and index from `from` to `to`, keeping local edits
+    /// to files the switch does not touch
+    fn git_switch_tree(&mut self, repo: &Repo, from: Option<&str>, to: &str) -> Result<(), String> {
+        let old = self.git_commit_files(repo, from);
+        let new = self.git_commit_files(repo, Some(to));
+        let mut index = self.git_index(repo);
+        let work = self.git_worktree(repo);
+
+        let changing: Vec<&String> = old
+            .keys()
+            .chain(new.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|p| old.get(*p) != new.get(*p))
+            .collect();
+        let conflicts: Vec<&String> = changing
+            .iter()
+            .copied()
+            .filter(|p| {
+                let staged = index.get(*p) != old.get(*p);
+                let edited = match (index.get(*p), work.get(*p)) {
+                    (Some(h), Some(data)) => blob_id(data) != *h,
+                    (Some(_), None) => true,
+                    // An untracked file the target would overwrite
+                    (None, Some(_)) => new.contains_key(*p),
+                    (None, None) => false,
+                };
+                staged || edited
+            })
+            .collect();
+        if !conflicts.is_empty() {
+            return Err(format!(
+                "error: Your local changes to the following files would be overwritten by checkout:\n{}\n\
+                 Please commit your changes or stash them before you switch branches.\n\
+                 Aborting",
+                conflicts
+                    .iter()
+                    .map(|p| format!("\t{}", p))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+        for path in changing {
+            let abs = repo.path(path);
+            match new.get(path) {
+                Some(hash) => {
+                    let data = self.git_blob(repo, hash);
+                    self.git_write(&abs, &data)?;
+                    index.insert(path.clone(), hash.clone());
+                }
+                None => {
+                    let _ = self.kernel.fs.remove(&abs);
+                    index.remove(path);
+                }
+            }
+        }
+        self.git_write_index(repo, &index)
+    }
+
+    /// `git checkout -- <paths>`: put tracked files back as the index has them
+    fn git_restore_paths(&mut self, repo: &Repo, paths: &[&str]) -> Result<String, String> {
+        let index = self.git_index(repo);
+        let mut restored = 0;
+        for spec in paths {
+            let rel = self.git_rel(repo, spec).unwrap_or_default();
+            let prefix = format!("{}/", rel);
+            let matched: Vec<(&String, &String)> = index
+                .iter()
+                .filter(|(p, _)| rel.is_empty() || **p == rel || p.starts_with(&prefix))
+                .collect();
+            if matched.is_empty() {
+                return Err(format!(
+                    "error: pathspec '{}' did not match any file(s) known to git",
+                    spec
+                ));
+            }
+            for (path, hash) in matched {
+                let data = self.git_blob(repo, hash);
+                self.git_write(&repo.path(path), &data)?;
+                restored += 1;
+            }
+        }
+        Ok(format!(
+            "Updated {} path{} from the index",
+            restored,
+            if restored == 1 { "" } else { "s" }
+        ))
+    }
+
+    /// `git clone <github-url> [dir]`: checks the destination, then hands the
+    /// download to the browser, which calls back into `git_clone_finish`
+    fn git_clone(&mut self, args: &[&str]) -> Result<String, String> {
+        let operands: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|a| !a.starts_with('-'))
+            .collect();
+        let Some(url) = operands.first() else {
+            return Err("usage: git clone <repository> [<directory>]".into());
+        };
+        let Some((owner, name)) = parse_github_url(strip_quotes(url)) else {
+            return Err(format!(
+                "fatal: unable to access '{}': only github.com repositories can be cloned",
+                url
+            ));
+        };
+        let dir = self
+            .kernel
+            .fs
+            .normalize(operands.get(1).copied().unwrap_or(name.as_str()));
+        let shown = operands.get(1).copied().unwrap_or(name.as_str());
+        if let Some(node) = self.kernel.fs.resolve(&dir) {
+            if !node.is_dir || !node.children.is_empty() {
+                return Err(format!(
+                    "fatal: destination path '{}' already exists and is not an empty directory.",
+                    shown
+                ));
+            }
+        }
+        if !self.can_write_path(&dir) {
+            return Err(format!(
+                "fatal: could not create work tree dir '{}': Permission denied",
+                shown
+            ));
+        }
+        if self.host_unreachable("github.com").is_some() {
+            return Err(format!(
+                "Cloning into '{}'...\nfatal: unable to access 'https://github.com/{}/{}/': Could not resolve host: github.com",
+                shown, owner, name
+            ));
+        }
+        Ok(self.emit(SystemEvent::GitClone {
+            repo: format!("{}/{}", owner, name),
+            dir,
+        }))
+    }
+
+    /// Materialize a fetched repository: `files` is a JSON list of
+    /// `[path, contents]` and `head` describes the upstream tip commit
+    pub(super) fn git_materialize_clone(
+        &mut self,
+        dir: &str,
+        url: &str,
+        branch: &str,
+        files: &str,
+        head: &str,
+    ) -> String {
+        let files: Vec<(String, String)> = match serde_json::from_str(files) {
+            Ok(files) => files,
+            Err(e) => return format!("fatal: bad clone payload: {}", e),
+        };
+        let head: serde_json::Value = serde_json::from_str(head).unwrap_or_default();
+        let text = |key: &str| head[key].as_str().unwrap_or_default().to_string();
+
+        if let Err(e) = self.ensure_dir_all(dir) {
+            return format!("fatal: could not create work tree dir '{}': {}", dir, e);
+        }
+        if let Err(e) = self.git_init(&[dir]) {
+            return e;
+        }
+        let repo = Repo {
+            root: self.kernel.fs.normalize(dir),
+        };
+        let result = (|| -> Result<usize, String> {
+            let mut index = FileMap::new();
+            for (path, data) in &files {
+                // Never let a hostile listing escape the work tree
+                if path
+                    .split('/')
+                    .any(|p| p.is_empty() || p == "." || p == "..")
+                    || path == ".git"
+                    || path.starts_with(".git/")
+                {
+                    continue;
+                }
+                self.git_write(&repo.path(path), data)?;
+                index.insert(path.clone(), self.git_store(&repo, "blob", data)?);
+            }
+            self.git_write_index(&repo, &index)?;
+            let stamp = format!(
+                "{} <{}> {} +0000",
+                text("author"),
+                text("email"),
+                head["time"].as_i64().unwrap_or(0)
+            );
+            let message = match text("message") {
+                m if m.is_empty() => "Initial commit".to_string(),
+                m => m,
+            };
+            let hash = self.git_record(&repo, &index, None, &message, &stamp)?;
+            for reference in [
+                format!("refs/heads/{}", branch),
+                format!("refs/remotes/origin/{}", branch),
+            ] {
+                self.git_write(&repo.git(&reference), &format!("{}\n", hash))?;
+            }
+            self.git_write(&repo.git("HEAD"), &format!("ref: refs/heads/{}\n", branch))?;
+            let config = self.git_read(&repo.git("config")).unwrap_or_default();
+            let config = set_config_value(&config, "remote \"origin\"", "url", url);
+            let config = set_config_value(
+                &config,
+                "remote \"origin\"",
+                "fetch",
+                "+refs/heads/*:refs/remotes/origin/*",
+            );
+            let config = set_config_value(
+                &config,
+                &format!("branch \"{}\"", branch),
+                "remote",
+                "origin",
+            );
+            let config = set_config_value(
+                &config,
+                &format!("branch \"{}\"", branch),
+                "merge",
+                &format!("refs/heads/{}", branch),
+            );
+            self.git_write(&repo.git("config"), &config)?;
+            Ok(index.len())
+        })();
+        match result {
+            Ok(count) => format!(
+                "Receiving objects: 100% ({0}/{0}), done.\nChecking out files: 100% ({0}/{0}), done.",
+                count
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn git_config(&mut self, repo: &Repo, args: &[&str]) -> Result<String, String> {
+        let args: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|a| !matches!(*a, "--local" | "--global"))
+            .collect();
+        let path = repo.git("config");
+        let config = self.git_read(&path).unwrap_or_default();
+        if args.first() == Some(&"--list") || args.first() == Some(&"-l") {
+            let mut section = String::new();
+            let mut out = Vec::new();
+            for line in config.lines() {
+                let line = line.trim();
+                if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    // `[remote "origin"]` lists as `remote.origin`
+                    section = name.replace(" \"", ".").replace('"', "");
+                } else if let Some((k, v)) = line.split_once('=') {
+                    out.push(format!("{}.{}={}", section, k.trim(), v.trim()));
+                }
+            }
+            return Ok(out.join("\n"));
+        }
+        let Some((section, key)) = args.first().and_then(|k| k.rsplit_once('.')) else {
+            return Err("usage: git config [--list] <name> [<value>]".into());
+        };
+        let section = match section.split_once('.') {
+            Some((name, sub)) => format!("{} \"{}\"", name, sub),
+            None => section.to_string(),
+        };
+        if args.len() == 1 {
+            // A missing key prints nothing
+            return Ok(config_value(&config, &section, key).unwrap_or_default());
+        }
+        let value = strip_quotes(&args[1..].join(" ")).to_string();
+        let updated = set_config_value(&config, &section, key, &value);
+        self.git_write(&path, &updated)?;
+        Ok(String::new())
+    }
+}
+
+fn is_git_command(sub: &str) -> bool {
+    matches!(
+        sub,
+        "status" | "add" | "commit" | "log" | "diff" | "checkout" | "switch" | "branch" | "config"
+    )
+}
+
+fn not_a_command(sub: &str) -> String {
+    format!("git: '{}' is not a git command. See 'git --help'.", sub)
+}
+
+/// `owner` and `name` from `https://github.com/owner/name(.git)`,
+/// `github.com/owner/name` or `git@github.com:owner/name.git`
+fn parse_github_url(url: &str) -> Option<(String, String)> {
+    let rest = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| {
+            let url = url
+                .strip_prefix("https://")
+                .or_else(|| url.strip_prefix("http://"))
+                .unwrap_or(url);
+            url.strip_prefix("www.github.com/")
+                .or_else(|| url.strip_prefix("github.com/"))
+        })?
+        .trim_end_matches('/');
+    let rest = rest.strip_suffix(".git").unwrap_or(rest);
+    let (owner, name) = rest.split_once('/')?;
+    let valid = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (valid(owner) && valid(name)).then(|| (owner.to_string(), name.to_string()))
+}
+
+fn strip_quotes(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .or_else(|| text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')))
+        .unwrap_or(text)
+}
+
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(7)]
+}
+
+fn config_value(config: &str, section: &str, key: &str) -> Option<String> {
+    let mut current = String::new();
+    for line in config.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.to_string();
+        } else if let Some((k, v)) = line.split_once('=') {
+            if current.eq_ignore_ascii_case(section) && k.trim().eq_ignore_ascii_case(key) {
+                return Some(v.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+fn set_config_value(config: &str, section: &str, key: &str, value: &str) -> String {
+    let mut lines: Vec<String> = config.lines().map(String::from).collect();
+    let mut current = String::new();
+    let mut section_end = None;
+    for (i, line) in lines.iter_mut().enumerate() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.to_string();
+            continue;
+        }
+        if current.eq_ignore_ascii_case(section) {
+            section_end = Some(i + 1);
+            if trimmed
+                .split_once('=')
+                .is_some_and(|(k, _)| k.trim().eq_ignore_ascii_case(key))
+            {
+                *line = format!("\t{} = {}", key, value);
+                return lines.join("\n") + "\n";
+            }
+        }
+    }
+    match section_end.or_else(|| {
+        lines
+            .iter()
+            .position(|l| l.trim() == format!("[{}]", section))
+            .map(|i| i + 1)
+    }) {
+        Some(at) => lines.insert(at, format!("\t{} = {}", key, value)),
+        None => {
+            lines.push(format!("[{}]", section));
+            lines.push(format!("\t{} = {}", key, value));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn collect_files(
+    node: &crate::vfs::Inode,
+    prefix: &str,
+    ignore: &[String],
+    out: &mut BTreeMap<String, String>,
+) {
+    for (name, child) in &node.children {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if (prefix.is_empty() && name == ".git") || is_ignored(ignore, &path, name, child.is_dir) {
+            continue;
+        }
+        if child.is_dir {
+            collect_files(child, &path, ignore, out);
+        } else if !child.permissions.starts_with('l') {
+            out.insert(path, child.data.clone());
+        }
+    }
+}
+
+/// `.gitignore` matching: patterns with a `/` match the whole path, others
+/// any single name; a trailing `/` only matches directories
+fn is_ignored(patterns: &[String], path: &str, name: &str, is_dir: bool) -> bool {
+    patterns.iter().any(|pattern| {
+        let (pattern, dir_only) = match pattern.strip_suffix('/') {
+            Some(p) => (p, true),
+            None => (pattern.as_str(), false),
+        };
+        if dir_only && !is_dir {
+            return false;
+        }
+        match pattern.strip_prefix('/') {
+            Some(anchored) => glob_match(anchored, path),
+            None if pattern.contains('/') => glob_match(pattern, path),
+            None => glob_match(pattern, name),
+        }
+    })
+}
+
+pub(super) fn glob_match(pattern: &str, text: &str) -> bool {
+    fn go(p: &[char], t: &[char]) -> bool {
+        match p.split_first() {
+            None => t.is_empty(),
+            Some(('*', rest)) => (0..=t.len()).any(|i| go(rest, &t[i..])),
+            Some(('?', rest)) => !t.is_empty() && go(rest, &t[1..]),
+            Some((c, rest)) => t.first() == Some(c) && go(rest, &t[1..]),
+        }
+    }
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    go(&p, &t)
+}
+
+/// `Thu Oct 16 10:00:00 2026 +0000`
+fn format_date(secs: i64) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    let c = clock::civil(secs);
+    format!(
+        "{} {} {} {:02}:{:02}:{:02} {} +0000",
+        DAYS[c.weekday as usize],
+        c.month_name(),
+        c.day,
+        c.hour,
+        c.minute,
+        c.second,
+        c.year
+    )
+}
+
+/// ` 2 files changed, 3 insertions(+), 1 deletion(-)`
+fn diffstat_summary(files: usize, insertions: usize, deletions: usize) -> String {
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let mut summary = format!(" {} changed", plural(files, "file"));
+    if insertions > 0 || deletions == 0 {
+        summary.push_str(&format!(", {}(+)", plural(insertions, "insertion")));
+    }
+    if deletions > 0 || insertions == 0 {
+        summary.push_str(&format!(", {}(-)", plural(deletions, "deletion")));
+    }
+    summary
+}
+
+fn blob_id(data: &str) -> String {
+    sha1_hex(format!("blob {}\0{}", data.len(), data).as_bytes())
+}
+
+fn sha1_hex(data: &[u8]) -> String {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut msg = data.to_vec();
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&bit_len.to_be_bytes());
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (slot, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *slot = slot.wrapping_add(v);
+        }
+    }
+    h.iter().map(|v| format!("{:08x}", v)).collect()
+}
+
+/// Line edits turning `a` into `b`: `' '` keep, `'-'` remove, `'+'` add
+pub(super) fn line_ops<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(char, &'a str)> {
+    // Longest common subsequence; very large inputs fall back to a full rewrite
+    if a.len().saturating_mul(b.len()) > 4_000_000 {
+        let mut ops: Vec<(char, &str)> = a.iter().map(|l| ('-', *l)).collect();
+        ops.extend(b.iter().map(|l| ('+', *l)));
+        return ops;
+    }
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::new();
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            ops.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', a[i]));
+            i += 1;
+        } else {
+            ops.push(('+', b[j]));
+            j += 1;
+        }
+    }
+    ops
+}
+
+fn count_changes(a: &str, b: &str) -> (usize, usize) {
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    let ops = line_ops(&a, &b);
+    (
+        ops.iter().filter(|(op, _)| *op == '+').count(),
+        ops.iter().filter(|(op, _)| *op == '-').count(),
+    )
+}
+
+/// Hunks of a unified diff with three lines of context
+pub(super) fn unified_diff(a: &str, b: &str) -> String {
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    let ops = line_ops(&a, &b);
+    let changes: Vec<usize> = (0..ops.len()).filter(|&i| ops[i].0 != ' ').collect();
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &c in &changes {
+        match groups.last_mut() {
+            Some((_, last)) if c - *last <= 2 * CONTEXT => *last = c,
+            _ => groups.push((c, c)),
+        }
+    }
+    let range = |start: usize, len: usize| match len {
+        0 => format!("{},0", start.saturating_sub(1)),
+        1 => start.to_string(),
+        _ => format!("{},{}", start, len),
+    };
+    let mut out = Vec::new();
+    for (first, last) in groups {
+        let lo = first.saturating_sub(CONTEXT);
+        let hi = (last + CONTEXT).min(ops.len() - 1);
+        let old_start = ops[..lo].iter().filter(|(op, _)| *op != '+').count() + 1;
+        let new_start = ops[..lo].iter().filter(|(op, _)| *op != '-').count() + 1;
+        let span = &ops[lo..=hi];
+        let old_len = span.iter().filter(|(op, _)| *op != '+').count();
+        let new_len = span.iter().filter(|(op, _)| *op != '-').count();
+        out.push(format!(
+            "@@ -{} +{} @@",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        out.extend(span.iter().map(|(op, line)| format!("{}{}", op, line)));
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_date, glob_match, parse_github_url, sha1_hex, unified_diff};
+
+    #[test]
+    fn object_ids_match_git() {
+        // `git hash-object` of "hello\n"
+        assert_eq!(
+            sha1_hex(b"blob 6\0hello\n"),
+            "ce013625030ba8dba906f756967f9e9ca394464a"
+        );
+        assert_eq!(format_date(0), "Thu Jan 1 00:00:00 1970 +0000");
+        assert_eq!(format_date(1_700_000_000), "Tue Nov 14 22:13:20 2023 +0000");
+        assert!(glob_match("*.log", "build.log") && !glob_match("*.log", "log.txt"));
+    }
+
+    #[test]
+    fn clone_urls_name_a_github_repo() {
+        let repo = Some(("rust-lang".to_string(), "rust".to_string()));
+        assert_eq!(
+            parse_github_url("https://github.com/rust-lang/rust.git"),
+            repo
+        );
+        assert_eq!(parse_github_url("github.com/rust-lang/rust/"), repo);
+        assert_eq!(parse_github_url("git@github.com:rust-lang/rust.git"), repo);
+        assert_eq!(parse_github_url("https://gitlab.com/rust-lang/rust"), None);
+        assert_eq!(parse_github_url("https://github.com/rust-lang"), None);
+    }
+
+    #[test]
+    fn unified_diff_groups_hunks_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        assert_eq!(
+            unified_diff(old, new),
+            "@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n@@ -10,3 +10,4 @@\n j\n k\n l\n+m"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use super::{unquote, System, BINARY_PREFIX};
+use crate::ansi;
+use crate::regex::Regex;
+use crate::shell::CmdOutput;
+
+const USAGE: &str = "usage: grep [-rinvclF] [--color[=WHEN]] PATTERN [FILE]...";
+
+#[derive(Default)]
+struct GrepOptions {
+    recursive: bool,
+    ignore_case: bool,
+    line_numbers: bool,
+    invert: bool,
+    count: bool,
+    files_only: bool,
+    fixed: bool,
+    /// Highlight matches, names and line numbers the way GNU grep does
+    color: bool,
+}
+
+impl System {
+    /// `grep [-rinvclF] [--color[=WHEN]] PATTERN [FILE]...`
+    pub(super) fn cmd_grep(&self, args: &[&str]) -> CmdOutput {
+        let mut opts = GrepOptions::default();
+        let mut operands = Vec::new();
+        for arg in args {
+            if let Some(when) = arg
+                .strip_prefix("--color")
+                .or_else(|| arg.strip_prefix("--colour"))
+            {
+                opts.color = match when {
+                    "" | "=always" => true,
+                    "=auto" => !self.output_captured,
+                    "=never" => false,
+                    _ => {
+                        return CmdOutput::error(2, format!(
+                            "grep: invalid argument '{}' for '--color'\nValid arguments are: 'always', 'never', 'auto'",
+                            when.trim_start_matches('=')
+                        ))
+                    }
+                };
+                continue;
+            }
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() && operands.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'r' | 'R' => opts.recursive = true,
+                            'i' => opts.ignore_case = true,
+                            'n' => opts.line_numbers = true,
+                            'v' => opts.invert = true,
+                            'c' => opts.count = true,
+                            'l' => opts.files_only = true,
+                            'F' => opts.fixed = true,
+                            'E' => {}
+                            _ => {
+                                return CmdOutput::error(
+                                    2,
+                                    format!("grep: invalid option -- '{}'\n{}", flag, USAGE),
+                                )
+                            }
+                        }
+                    }
+                }
+                _ => operands.push(*arg),
+            }
+        }
+        let Some((pattern, files)) = operands.split_first() else {
+            return CmdOutput::usage(USAGE.to_string());
+        };
+        let pattern = unquote(pattern);
+        let regex = if opts.fixed {
+            Regex::literal(pattern)
+        } else {
+            match Regex::new(pattern) {
+                Ok(re) => re,
+                Err(e) => return CmdOutput::error(2, format!("grep: {}", e)),
+            }
+        }
+        .ignore_case(opts.ignore_case);
+
+        let files: Vec<&str> = match files {
+            [] if opts.recursive => vec!["."],
+            [] => return CmdOutput::usage(USAGE.to_string()),
+            files => files.to_vec(),
+        };
+        let mut targets = Vec::new();
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for file in &files {
+            match self.kernel.fs.resolve(file) {
+                Some(node) if node.is_dir && opts.recursive => {
+                    let dir = match file.trim_end_matches('/') {
+                        "" => "/",
+                        dir => dir,
+                    };
+                    self.grep_walk(dir, &mut targets)
+                }
+                Some(node) if node.is_dir => errors.push(format!("grep: {}: Is a directory", file)),
+                Some(_) => targets.push(file.to_string()),
+                None => errors.push(format!("grep: {}: No such file or directory", file)),
+            }
+        }
+
+        let show_names = opts.recursive || files.len() > 1;
+        let mut matched = false;
+        for path in &targets {
+            if !self.has_access(path, 4) {
+                errors.push(format!("grep: {}: Permission denied", path));
+                continue;
+            }
+            let Some(node) = self.kernel.fs.resolve(path) else {
+                continue;
+            };
+            if node.data.starts_with(BINARY_PREFIX) {
+                continue;
+            }
+            let separator = |s: &str| match opts.color {
+                true => ansi::paint(s, ansi::CYAN),
+                false => s.to_string(),
+            };
+            let prefix = match (show_names, opts.color) {
+                (false, _) => String::new(),
+                (true, false) => format!("{}:", path),
+                (true, true) => ansi::paint(path, ansi::MAGENTA) + &separator(":"),
+            };
+            let matches: Vec<(usize, &str)> = node
+                .data
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| regex.is_match(line) != opts.invert)
+                .collect();
+            matched |= !matches.is_empty();
+            if opts.files_only {
+                if !matches.is_empty() && opts.color {
+                    out.push(ansi::paint(path, ansi::MAGENTA));
+                } else if !matches.is_empty() {
+                    out.push(path.clone());
+                }
+            } else if opts.count {
+                out.push(format!("{}{}", prefix, matches.len()));
+            } else {
+                for (n, line) in matches {
+                    let line = match opts.color && !opts.invert {
+                        true => highlight(&regex, line),
+                        false => line.to_string(),
+                    };
+                    if opts.line_numbers && opts.color {
+                        let number = ansi::paint(&(n + 1).to_string(), ansi::GREEN);
+                        out.push(format!("{}{}{}{}", prefix, number, separator(":"), line));
+                    } else if opts.line_numbers {
+                        out.push(format!("{}{}:{}", prefix, n + 1, line));
+                    } else {
+                        out.push(format!("{}{}", prefix, line));
+                    }
+                }
+            }
+        }
+        // 0 when a line matched, 1 when none did, 2 when a file could not
+        // be read
+        CmdOutput {
+            stdout: out.join("\n"),
+            status: if errors.is_empty() {
+                i32::from(!matched)
+            } else {
+                2
+            },
+            stderr: errors.join("\n"),
+        }
+    }
+
+    // Every file under `dir`, in name order
+    fn grep_walk(&self, dir: &str, files: &mut Vec<String>) {
+        let Some(node) = self.kernel.fs.resolve(dir) else {
+            return;
+        };
+        let mut names: Vec<&String> = node.children.keys().collect();
+        names.sort();
+        for name in names {
+            let path = if dir == "/" {
+                format!("/{}", name)
+            } else {
+                format!("{}/{}", dir, name)
+            };
+            if node.children[name].is_dir {
+                self.grep_walk(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+}
+
+/// `line` with every match of `regex` painted
+fn highlight(regex: &Regex, line: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for (start, end) in regex.find_all(line) {
+        out.push_str(&line[last..start]);
+        out.push_str(&ansi::paint(&line[start..end], ansi::MATCH));
+        last = end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grep_flags_and_regex() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_dir("/tmp/logs").unwrap();
+        sys.kernel
+            .fs
+            .create_file("/tmp/logs/a.log", "ok\nERROR disk 42\nerror net\n")
+            .unwrap();
+        sys.kernel
+            .fs
+            .create_file("/tmp/logs/b.log", "port 8080\nfine\n")
+            .unwrap();
+
+        assert_eq!(
+            sys.cmd_grep(&["error", "/tmp/logs/a.log"]).flatten(),
+            "error net"
+        );
+        assert_eq!(
+            sys.cmd_grep(&["-in", "\"error\"", "/tmp/logs/a.log"])
+                .flatten(),
+            "2:ERROR disk 42\n3:error net"
+        );
+        assert_eq!(
+            sys.cmd_grep(&["-vc", "error", "/tmp/logs/a.log"]).flatten(),
+            "2"
+        );
+        assert_eq!(
+            sys.cmd_grep(&["[0-9]+$", "/tmp/logs/a.log", "/tmp/logs/b.log"])
+                .flatten(),
+            "/tmp/logs/a.log:ERROR disk 42\n/tmp/logs/b.log:port 8080"
+        );
+        assert_eq!(
+            sys.cmd_grep(&["-r", "^fine", "/tmp/logs"]).flatten(),
+            "/tmp/logs/b.log:fine"
+        );
+        assert_eq!(
+            sys.cmd_grep(&["-rl", "o", "/tmp/logs/"]).flatten(),
+            "/tmp/logs/a.log\n/tmp/logs/b.log"
+        );
+        assert_eq!(
+            sys.cmd_grep(&["x", "/tmp/logs"]).flatten(),
+            "grep: /tmp/logs: Is a directory"
+        );
+        assert!(sys
+            .cmd_grep(&["-q", "x"])
+            .flatten()
+            .starts_with("grep: invalid option"));
+        assert_eq!(
+            sys.cmd_grep(&["--color=always", "-n", "rr", "/tmp/logs/a.log"])
+                .flatten(),
+            "\x1b[32m3\x1b[0m\x1b[36m:\x1b[0me\x1b[01;31mrr\x1b[0mor net"
+        );
+        assert!(sys
+            .cmd_grep(&["--color=sometimes", "x"])
+            .flatten()
+            .starts_with("grep: invalid argument 'sometimes'"));
+    }
+}
@@ -0,0 +1,423 @@
+//! Looking at files byte by byte: `xxd`, `hexdump` and `strings`. The
+//! stand-in programs under /bin are shown as the small ELF image they
+//! pretend to be rather than the script text they hold.
+use super::{bytes_to_data, System};
+use crate::shell::CmdOutput;
+
+const XXD_USAGE: &str = "usage: xxd [-p] [-u] [-c COLS] [-g BYTES] [-l LEN] [-s SEEK] [INFILE]\n       xxd -r [-p] [INFILE [OUTFILE]]";
+const HEXDUMP_USAGE: &str = "usage: hexdump [-C] [-n LEN] [-s SKIP] [FILE]...";
+const STRINGS_USAGE: &str = "usage: strings [-a] [-n MIN] FILE...";
+
+/// What a stand-in binary looks like on disk: an ELF64 header followed by
+/// the strings a real build of it would carry
+fn fake_elf(name: &str, desc: &str) -> Vec<u8> {
+    let mut image = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    image.extend_from_slice(&3u16.to_le_bytes()); // ET_DYN
+    image.extend_from_slice(&0x3eu16.to_le_bytes()); // x86-64
+    image.extend_from_slice(&1u32.to_le_bytes());
+    image.extend_from_slice(&0x1040u64.to_le_bytes()); // entry
+    image.extend_from_slice(&64u64.to_le_bytes()); // program headers
+    let strings = [
+        "/lib64/ld-linux-x86-64.so.2",
+        "libc.so.6",
+        "__libc_start_main",
+        "GLIBC_2.34",
+        "GLIBC_2.2.5",
+        name,
+        desc,
+        &format!("Usage: {} [OPTION]...", name),
+        "GCC: (GNU) 12.2.0",
+        ".shstrtab",
+        ".interp",
+        ".dynsym",
+        ".dynstr",
+        ".text",
+        ".rodata",
+        ".data",
+        ".bss",
+    ];
+    let table: Vec<u8> = strings
+        .iter()
+        .flat_map(|s| [s.as_bytes(), b"\0"])
+        .flatten()
+        .copied()
+        .collect();
+    image.extend_from_slice(&(128 + table.len() as u64).to_le_bytes()); // section headers
+    image.extend_from_slice(&0u32.to_le_bytes()); // flags
+    for field in [64u16, 56, 13, 64, 31, 30] {
+        image.extend_from_slice(&field.to_le_bytes());
+    }
+    image.resize(128, 0);
+    image.extend(table);
+    image
+}
+
+/// `# name - desc` from a stand-in binary's script, if that is what it is
+fn stand_in(data: &str) -> Option<(&str, &str)> {
+    let mut lines = data.lines();
+    if lines.next()? != "#!/bin/sh" {
+        return None;
+    }
+    let about = lines.next()?.strip_prefix("# ")?;
+    lines.next()?.starts_with("# ELF 64-bit").then_some(())?;
+    about.split_once(" - ")
+}
+
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Parse a count the way the dump tools do: decimal, or hex after `0x`
+fn count(prog: &str, value: Option<&&str>) -> Result<usize, CmdOutput> {
+    let value = value
+        .ok_or_else(|| CmdOutput::error(1, format!("{}: option requires an argument", prog)))?;
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    };
+    parsed.ok_or_else(|| CmdOutput::error(1, format!("{}: invalid number '{}'", prog, value)))
+}
+
+impl System {
+    /// The bytes `prog` reads from `path`
+    fn dump_input(&self, prog: &str, path: &str) -> Result<Vec<u8>, String> {
+        if self.kernel.fs.resolve(path).is_some() && !self.has_access(path, 4) {
+            return Err(format!("{}: {}: Permission denied", prog, path));
+        }
+        match self.kernel.fs.resolve(path) {
+            Some(node) if node.is_executable => {
+                if let Some((name, desc)) = stand_in(&node.data) {
+                    return Ok(fake_elf(name, desc));
+                }
+            }
+            _ => {}
+        }
+        self.read_file_bytes(path)
+            .map_err(|e| format!("{}: {}", prog, e))
+    }
+
+    pub(super) fn cmd_xxd(&mut self, args: &[&str]) -> CmdOutput {
+        let (mut plain, mut upper, mut reverse) = (false, false, false);
+        let (mut cols, mut group, mut len, mut seek) = (None, 2, None, 0);
+        let mut files = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-p" | "-ps" | "-plain" => plain = true,
+                "-u" => upper = true,
+                "-r" | "-revert" => reverse = true,
+                "-c" | "-cols" => match count("xxd", rest.next()) {
+                    Ok(n) => cols = Some(n.clamp(1, 256)),
+                    Err(e) => return e,
+                },
+                "-g" | "-groupsize" => match count("xxd", rest.next()) {
+                    Ok(n) => group = n,
+                    Err(e) => return e,
+                },
+                "-l" | "-len" => match count("xxd", rest.next()) {
+                    Ok(n) => len = Some(n),
+                    Err(e) => return e,
+                },
+                "-s" | "-seek" => match count("xxd", rest.next()) {
+                    Ok(n) => seek = n,
+                    Err(e) => return e,
+                },
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!("xxd: unknown option {}\n{}", flag, XXD_USAGE),
+                    )
+                }
+                file => files.push(file),
+            }
+        }
+        if reverse {
+            return self.xxd_reverse(&files, plain);
+        }
+        let [path] = files[..] else {
+            return CmdOutput::error(1, XXD_USAGE);
+        };
+        let bytes = match self.dump_input("xxd", path) {
+            Ok(bytes) => bytes,
+            Err(e) => return CmdOutput::error(2, e),
+        };
+        let end = len.map_or(bytes.len(), |len| (seek + len).min(bytes.len()));
+        let data = bytes.get(seek..end).unwrap_or_default();
+        let hex = |b: &u8| match upper {
+            true => format!("{:02X}", b),
+            false => format!("{:02x}", b),
+        };
+        if plain {
+            let lines = data
+                .chunks(cols.unwrap_or(30))
+                .map(|line| line.iter().map(hex).collect::<String>());
+            return CmdOutput::ok(lines.collect::<Vec<_>>().join("\n"));
+        }
+        let cols = cols.unwrap_or(16);
+        let width = match group {
+            0 => cols * 2,
+            g => cols * 2 + cols.div_ceil(g) - 1,
+        };
+        let lines = data.chunks(cols).enumerate().map(|(i, line)| {
+            let mut field = String::new();
+            for (j, byte) in line.iter().enumerate() {
+                if group > 0 && j > 0 && j % group == 0 {
+                    field.push(' ');
+                }
+                field.push_str(&hex(byte));
+            }
+            let text: String = line.iter().copied().map(printable).collect();
+            format!("{:08x}: {:<width$}  {}", seek + i * cols, field, text)
+        });
+        CmdOutput::ok(lines.collect::<Vec<_>>().join("\n"))
+    }
+
+    /// `xxd -r`: turn a dump back into bytes, written to OUTFILE if given
+    fn xxd_reverse(&mut self, files: &[&str], plain: bool) -> CmdOutput {
+        let (input, output) = match files {
+            [input] => (*input, None),
+            [input, output] => (*input, Some(*output)),
+            _ => return CmdOutput::error(1, XXD_USAGE),
+        };
+        let dump = match self.dump_input("xxd", input) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => return CmdOutput::error(2, e),
+        };
+        let digits = |field: &str| -> Vec<u8> {
+            let hex: Vec<u8> = field
+                .chars()
+                .filter_map(|c| c.to_digit(16))
+                .map(|d| d as u8)
+                .collect();
+            hex.chunks_exact(2)
+                .map(|pair| pair[0] << 4 | pair[1])
+                .collect()
+        };
+        let mut bytes = Vec::new();
+        if plain {
+            bytes = digits(&dump);
+        } else {
+            for line in dump.lines() {
+                let Some((offset, rest)) = line.split_once(':') else {
+                    continue;
+                };
+                let Ok(offset) = usize::from_str_radix(offset.trim(), 16) else {
+                    continue;
+                };
+                // The hex ends at the two spaces before the text column
+                let field = rest.trim_start_matches(' ');
+                let field = field.find("  ").map_or(field, |end| &field[..end]);
+                let line_bytes = digits(field);
+                if bytes.len() < offset {
+                    bytes.resize(offset, 0);
+                }
+                bytes.truncate(offset);
+                bytes.extend(line_bytes);
+            }
+        }
+        if let Some(path) = output {
+            if !self.can_write_path(path) {
+                return CmdOutput::error(1, format!("xxd: {}: Permission denied", path));
+            }
+            return match self.write_file_bytes(path, &bytes) {
+                Ok(()) => CmdOutput::default(),
+                Err(e) => CmdOutput::error(1, format!("xxd: {}: {}", path, e)),
+            };
+        }
+        CmdOutput::ok(bytes_to_data(bytes))
+    }
+
+    /// `hexdump [-C]`: 16-bit words by default, bytes and text with `-C`.
+    /// Runs of identical lines are shown once, then `*`
+    pub(super) fn cmd_hexdump(&mut self, args: &[&str]) -> CmdOutput {
+        let mut canonical = false;
+        let (mut len, mut skip) = (None, 0);
+        let mut files = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-C" => canonical = true,
+                "-n" => match count("hexdump", rest.next()) {
+                    Ok(n) => len = Some(n),
+                    Err(e) => return e,
+                },
+                "-s" => match count("hexdump", rest.next()) {
+                    Ok(n) => skip = n,
+                    Err(e) => return e,
+                },
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "hexdump: invalid option -- '{}'\n{}",
+                            &flag[1..],
+                            HEXDUMP_USAGE
+                        ),
+                    )
+                }
+                file => files.push(file),
+            }
+        }
+        if files.is_empty() {
+            return CmdOutput::error(1, HEXDUMP_USAGE);
+        }
+        let mut bytes = Vec::new();
+        for path in files {
+            match self.dump_input("hexdump", path) {
+                Ok(data) => bytes.extend(data),
+                Err(e) => return CmdOutput::error(1, e),
+            }
+        }
+        let end = len.map_or(bytes.len(), |len| (skip + len).min(bytes.len()));
+        let data = bytes.get(skip..end).unwrap_or_default();
+
+        let mut out = Vec::new();
+        let mut previous: Option<&[u8]> = None;
+        let mut squeezing = false;
+        for (i, line) in data.chunks(16).enumerate() {
+            let offset = skip + i * 16;
+            if line.len() == 16 && previous == Some(line) {
+                if !squeezing {
+                    out.push("*".to_string());
+                    squeezing = true;
+                }
+                continue;
+            }
+            previous = Some(line);
+            squeezing = false;
+            out.push(if canonical {
+                let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+                let (low, high) = hex.split_at(hex.len().min(8));
+                let text: String = line.iter().copied().map(printable).collect();
+                format!(
+                    "{:08x}  {:<23}  {:<23}  |{}|",
+                    offset,
+                    low.join(" "),
+                    high.join(" "),
+                    text
+                )
+            } else {
+                let words: Vec<String> = line
+                    .chunks(2)
+                    .map(|w| {
+                        format!(
+                            "{:04x}",
+                            u16::from_le_bytes([w[0], *w.get(1).unwrap_or(&0)])
+                        )
+                    })
+                    .collect();
+                format!("{:07x} {}", offset, words.join(" "))
+            });
+        }
+        if !data.is_empty() {
+            out.push(match canonical {
+                true => format!("{:08x}", skip + data.len()),
+                false => format!("{:07x}", skip + data.len()),
+            });
+        }
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// `strings [-n MIN]`: runs of at least MIN (default 4) printable
+    /// characters
+    pub(super) fn cmd_strings(&mut self, args: &[&str]) -> CmdOutput {
+        let mut min = 4;
+        let mut files = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-a" | "--all" => {}
+                "-n" | "--bytes" => match count("strings", rest.next()) {
+                    Ok(n) => min = n.max(1),
+                    Err(e) => return e,
+                },
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "strings: invalid option -- '{}'\n{}",
+                            &flag[1..],
+                            STRINGS_USAGE
+                        ),
+                    )
+                }
+                file => files.push(file),
+            }
+        }
+        if files.is_empty() {
+            return CmdOutput::error(1, STRINGS_USAGE);
+        }
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for path in files {
+            let bytes = match self.dump_input("strings", path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+            let runs = bytes.split(|&b| !(b.is_ascii_graphic() || b == b' ' || b == b'\t'));
+            out.extend(
+                runs.filter(|run| run.len() >= min)
+                    .map(|run| String::from_utf8_lossy(run).into_owned()),
+            );
+        }
+        CmdOutput {
+            stdout: out.join("\n"),
+            status: i32::from(!errors.is_empty()),
+            stderr: errors.join("\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_reverse_and_strings() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel
+            .fs
+            .create_file("/tmp/hi", "Hello, world!\n\n\n")
+            .unwrap();
+
+        assert_eq!(
+            sys.cmd_xxd(&["/tmp/hi"]).stdout,
+            "00000000: 4865 6c6c 6f2c 2077 6f72 6c64 210a 0a0a  Hello, world!..."
+        );
+        assert_eq!(
+            sys.cmd_hexdump(&["-C", "/tmp/hi"]).stdout,
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 0a 0a  |Hello, world!...|\n00000010"
+        );
+        assert_eq!(
+            sys.cmd_hexdump(&["-n", "4", "/tmp/hi"]).stdout,
+            "0000000 6548 6c6c\n0000004"
+        );
+        assert_eq!(sys.cmd_xxd(&["-p", "-l", "2", "/tmp/hi"]).stdout, "4865");
+
+        let dump = sys.cmd_xxd(&["-c", "5", "/tmp/hi"]).stdout;
+        sys.kernel.fs.create_file("/tmp/dump", &dump).unwrap();
+        assert_eq!(
+            sys.cmd_xxd(&["-r", "/tmp/dump"]).stdout,
+            "Hello, world!\n\n\n"
+        );
+        let hosts = sys.kernel.fs.resolve("/etc/hosts").unwrap().data.clone();
+        let denied = sys.cmd_xxd(&["-r", "/tmp/dump", "/etc/hosts"]);
+        assert_eq!(denied.stderr, "xxd: /etc/hosts: Permission denied");
+        assert_eq!(denied.status, 1);
+        assert_eq!(sys.kernel.fs.resolve("/etc/hosts").unwrap().data, hosts);
+
+        let elf = sys.cmd_xxd(&["-l", "16", "/bin/ls"]).stdout;
+        assert!(elf.starts_with("00000000: 7f45 4c46 0201 0100"));
+        let strings = sys.cmd_strings(&["/bin/ls"]).stdout;
+        assert!(strings.lines().any(|l| l == "GLIBC_2.34"));
+        assert_eq!(sys.cmd_strings(&["/nope"]).status, 1);
+    }
+}
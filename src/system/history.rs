@@ -0,0 +1,219 @@
+use super::System;
+use crate::shell::CmdOutput;
+
+const DEFAULT_HISTSIZE: usize = 1000;
+
+// Characters that end a `!prefix` event designator
+fn ends_event(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '&' | '|' | '<' | '>' | '(' | ')' | '"' | '\'')
+}
+
+fn find_event<'a>(history: &'a [String], event: &str) -> Option<&'a str> {
+    let entry = if event == "!" {
+        history.last()
+    } else if let Some(back) = event.strip_prefix('-') {
+        let n: usize = back.parse().ok()?;
+        history.len().checked_sub(n).and_then(|i| history.get(i))
+    } else if let Ok(n) = event.parse::<usize>() {
+        n.checked_sub(1).and_then(|i| history.get(i))
+    } else {
+        history.iter().rev().find(|cmd| cmd.starts_with(event))
+    };
+    entry.map(String::as_str)
+}
+
+/// Expand `!!`, `!n`, `!-n` and `!prefix` against `history`. `None` when
+/// the line has nothing to expand; single quotes and `\!` keep it literal
+fn expand_history(history: &[String], line: &str) -> Result<Option<String>, String> {
+    let mut out = String::with_capacity(line.len());
+    let mut expanded = false;
+    let mut in_quote = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => in_quote = !in_quote,
+            '\\' if !in_quote && chars.peek() == Some(&'!') => continue,
+            '!' if !in_quote => {
+                let event = match chars.peek() {
+                    Some('!') => {
+                        chars.next();
+                        "!".to_string()
+                    }
+                    Some(&next) if !ends_event(next) && next != '=' => {
+                        let mut event = String::new();
+                        while let Some(&n) = chars.peek() {
+                            if ends_event(n) {
+                                break;
+                            }
+                            event.push(n);
+                            chars.next();
+                        }
+                        event
+                    }
+                    _ => {
+                        out.push(c);
+                        continue;
+                    }
+                };
+                let Some(cmd) = find_event(history, &event) else {
+                    return Err(format!("sh: !{}: event not found", event));
+                };
+                out.push_str(cmd);
+                expanded = true;
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    Ok(expanded.then_some(out))
+}
+
+impl System {
+    fn history_path(&self) -> String {
+        format!(
+            "{}/.bash_history",
+            Self::default_home_for_user(&self.current_user())
+        )
+    }
+
+    // HISTSIZE from the environment, like bash
+    fn histsize(&self) -> usize {
+        self.shell
+            .env
+            .get("HISTSIZE")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_HISTSIZE)
+    }
+
+    pub(super) fn expand_history_line(&self, line: &str) -> Result<Option<String>, String> {
+        expand_history(&self.shell.history, line)
+    }
+
+    /// Append a typed line, trim to HISTSIZE and write ~/.bash_history
+    pub(super) fn record_history(&mut self, line: &str) {
+        if line.is_empty() {
+            return;
+        }
+        self.shell.history.push(line.to_string());
+        self.trim_history();
+        self.save_history();
+    }
+
+    fn trim_history(&mut self) {
+        let excess = self.shell.history.len().saturating_sub(self.histsize());
+        self.shell.history.drain(..excess);
+    }
+
+    fn save_history(&mut self) {
+        let path = self.history_path();
+        let mut data = self.shell.history.join("\n");
+        if !data.is_empty() {
+            data.push('\n');
+        }
+        // A read-only or missing home just means history stays in memory
+        let _ = self.write_file_bytes(&path, data.as_bytes());
+    }
+
+    /// Replace the in-memory history with the current user's ~/.bash_history
+    pub(super) fn load_history(&mut self) {
+        self.shell.history = self
+            .kernel
+            .fs
+            .resolve(&self.history_path())
+            .map(|node| {
+                node.data
+                    .lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.trim_history();
+    }
+
+    /// Newest entry containing `query`, passing over the `skip` newer ones
+    /// (each extra Ctrl+R goes one further back)
+    pub(super) fn find_in_history(&self, query: &str, skip: usize) -> Option<String> {
+        self.shell
+            .history
+            .iter()
+            .rev()
+            .filter(|cmd| cmd.contains(query))
+            .nth(skip)
+            .cloned()
+    }
+
+    /// `history [-c] [N]`
+    pub(super) fn cmd_history(&mut self, args: &[&str]) -> CmdOutput {
+        let count = match args {
+            [] => self.shell.history.len(),
+            ["-c"] => {
+                self.shell.history.clear();
+                self.save_history();
+                return CmdOutput::ok(String::new());
+            }
+            [n] => match n.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => {
+                    return CmdOutput::error(
+                        1,
+                        format!("history: {}: numeric argument required", n),
+                    )
+                }
+            },
+            _ => return CmdOutput::usage("usage: history [-c] [N]".to_string()),
+        };
+        let start = self.shell.history.len().saturating_sub(count);
+        CmdOutput::ok(
+            self.shell.history[start..]
+                .iter()
+                .enumerate()
+                .map(|(i, c)| format!("{:4}  {}", start + i + 1, c))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_expansion_and_persistence() {
+        let history: Vec<String> = ["ls -la", "echo hi", "cat notes"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let expand = |line| expand_history(&history, line);
+        assert_eq!(expand("!!").unwrap().as_deref(), Some("cat notes"));
+        assert_eq!(
+            expand("sudo !!").unwrap().as_deref(),
+            Some("sudo cat notes")
+        );
+        assert_eq!(expand("!1").unwrap().as_deref(), Some("ls -la"));
+        assert_eq!(expand("!-2").unwrap().as_deref(), Some("echo hi"));
+        assert_eq!(
+            expand("!ec; !c").unwrap().as_deref(),
+            Some("echo hi; cat notes")
+        );
+        assert_eq!(expand("echo 'hi!!' a != b").unwrap(), None);
+        assert_eq!(expand("!vim").unwrap_err(), "sh: !vim: event not found");
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.shell.env.insert("HISTSIZE".into(), "2".into());
+        for line in ["pwd", "ls", "whoami"] {
+            sys.record_history(line);
+        }
+        assert_eq!(sys.shell.history, ["ls", "whoami"]);
+        assert_eq!(sys.find_in_history("s", 0).as_deref(), Some("ls"));
+        assert_eq!(sys.find_in_history("s", 1), None);
+
+        sys.shell.history.clear();
+        sys.load_history();
+        assert_eq!(sys.shell.history, ["ls", "whoami"]);
+        assert_eq!(sys.cmd_history(&["1"]).flatten(), "   2  whoami");
+    }
+}
@@ -0,0 +1,283 @@
+use super::System;
+use crate::process::{ProcState, Process};
+use crate::shell::CmdOutput;
+use std::collections::BTreeMap;
+
+/// Interactive state for a running `htop` session
+pub(super) struct HtopState {
+    selected: usize,
+    tree: bool,
+    kill_prompt: bool,
+    status: String,
+}
+
+impl HtopState {
+    pub(super) fn new(tree: bool) -> Self {
+        HtopState {
+            selected: 0,
+            tree,
+            kill_prompt: false,
+            status: String::new(),
+        }
+    }
+}
+
+fn bar(pct: f64, width: usize) -> String {
+    let fill = (((pct / 100.0) * width as f64).round() as usize).min(width);
+    format!("{}{}", "|".repeat(fill), " ".repeat(width - fill))
+}
+
+/// Depth-first walk of the process table by PPID, yielding (process, tree prefix)
+fn tree_rows<'a>(procs: &[&'a Process]) -> Vec<(&'a Process, String)> {
+    let mut children: BTreeMap<u32, Vec<&'a Process>> = BTreeMap::new();
+    let mut roots = Vec::new();
+    for p in procs {
+        let has_parent = p.ppid != p.pid && procs.iter().any(|q| q.pid == p.ppid);
+        if has_parent {
+            children.entry(p.ppid).or_default().push(p);
+        } else {
+            roots.push(*p);
+        }
+    }
+    roots.sort_by_key(|p| p.pid);
+    for list in children.values_mut() {
+        list.sort_by_key(|p| p.pid);
+    }
+
+    fn walk<'a>(
+        p: &'a Process,
+        indent: &str,
+        connector: &str,
+        children: &BTreeMap<u32, Vec<&'a Process>>,
+        out: &mut Vec<(&'a Process, String)>,
+    ) {
+        out.push((p, format!("{}{}", indent, connector)));
+        let Some(kids) = children.get(&p.pid) else {
+            return;
+        };
+        let child_indent = match connector {
+            "├─ " => format!("{}│  ", indent),
+            "└─ " => format!("{}   ", indent),
+            _ => indent.to_string(),
+        };
+        for (i, kid) in kids.iter().enumerate() {
+            let last = i + 1 == kids.len();
+            let conn = if last { "└─ " } else { "├─ " };
+            walk(kid, &child_indent, conn, children, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    for root in roots {
+        walk(root, "", "", &children, &mut out);
+    }
+    out
+}
+
+impl System {
+    /// Process rows in display order along with their tree prefix
+    fn htop_rows(&self, tree: bool) -> Vec<(&Process, String)> {
+        let procs = self.kernel.proc.list();
+        if tree {
+            return tree_rows(&procs);
+        }
+        let mut rows: Vec<(&Process, f64)> =
+            procs.into_iter().map(|p| (p, Self::proc_cpu(p))).collect();
+        rows.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        rows.into_iter().map(|(p, _)| (p, String::new())).collect()
+    }
+
+    fn render_htop(&self, state: Option<&HtopState>) -> String {
+        let tree = state.map(|s| s.tree).unwrap_or(true);
+        let total_mem = self.kernel.mem.total;
+        let used_mem = total_mem - self.kernel.mem.free;
+        let proc_list = self.kernel.proc.list();
+        let task_total = proc_list.len();
+        let count = |st: ProcState| proc_list.iter().filter(|p| p.state == st).count();
+        let task_running = count(ProcState::Run).max(1);
+        let task_sleep = count(ProcState::Sleep);
+        let task_stop = count(ProcState::Stop);
+        let uptime_s = self.kernel.uptime_ms() / 1000;
+
+        let mem_pct = if total_mem > 0 {
+            (used_mem as f64 / total_mem as f64) * 100.0
+        } else {
+            0.0
+        };
+        let cpu_avg = if task_total > 0 {
+            proc_list.iter().map(|p| Self::proc_cpu(p)).sum::<f64>() / task_total as f64
+        } else {
+            0.0
+        };
+
+        let mut out = String::new();
+        out.push_str(&format!(
+            "htop - kpawnd Linux  |  uptime {}  |  load average {:.2} {:.2} {:.2}\n",
+            Self::format_uptime_hms(uptime_s),
+            (task_running as f64 * 0.55).min(9.99),
+            (task_running as f64 * 0.35).min(9.99),
+            (task_running as f64 * 0.24).min(9.99)
+        ));
+        out.push_str(&format!(
+            "Tasks: {} total, {} running, {} sleeping, {} stopped\n",
+            task_total, task_running, task_sleep, task_stop
+        ));
+        out.push_str(&format!("CPU [ {} ] {:>5.1}%\n", bar(cpu_avg, 24), cpu_avg));
+        out.push_str(&format!(
+            "MEM [ {} ] {:>5.1}%   {}/{} MiB\n",
+            bar(mem_pct, 24),
+            mem_pct,
+            (used_mem / 1024 / 1024),
+            (total_mem / 1024 / 1024)
+        ));
+        out.push_str("SWP [                        ]   0.0%   0/0 MiB\n\n");
+        out.push_str(" PID USER      PRI  NI   RES S CPU% MEM% MEMORY       TIME+  Command\n");
+
+        let rows = self.htop_rows(tree);
+        // Per-process bars show each process's share of allocated memory
+        let alloc_total: u32 = rows.iter().map(|(p, _)| p.memory_size).sum::<u32>().max(1);
+        let selected = state.map(|s| s.selected.min(rows.len().saturating_sub(1)));
+        for (idx, (p, prefix)) in rows.iter().enumerate() {
+            let mem_proc = if total_mem > 0 {
+                (p.memory_size as f64 / total_mem as f64) * 100.0
+            } else {
+                0.0
+            };
+            let share = p.memory_size as f64 / alloc_total as f64 * 100.0;
+            let line = format!(
+                "{:>4} {:<8} {:>3} {:>3} {:>5} {} {:>4.1} {:>4.1} [{}] {:>7}  {}{}",
+                p.pid,
+                p.user,
+                20 + p.nice as i32,
+                p.nice,
+                format!("{}K", p.memory_size / 1024),
+                Self::proc_state_char(p.state),
+                Self::proc_cpu(p),
+                mem_proc,
+                bar(share, 10),
+                Self::format_cpu_time(p.cpu_time_us),
+                prefix,
+                p.name
+            );
+            if selected == Some(idx) {
+                out.push_str(&format!("\x1b[COLOR:cyan]{}\x1b[COLOR:reset]\n", line));
+            } else {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+
+        if let Some(st) = state {
+            if st.kill_prompt {
+                let target = rows
+                    .get(st.selected)
+                    .map(|(p, _)| format!("{} ({})", p.pid, p.name))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "\n\x1b[COLOR:yellow]Send signal to {}:  Enter/15 SIGTERM   9 SIGKILL   Esc cancel\x1b[COLOR:reset]",
+                    target
+                ));
+            } else if !st.status.is_empty() {
+                out.push_str(&format!("\n{}", st.status));
+            }
+        }
+        out.push_str("\nF1Help F2Setup F3Search F4Filter F5Tree F6SortBy F9Kill F10Quit");
+        out
+    }
+
+    pub(super) fn cmd_htop(&mut self, args: &[&str]) -> CmdOutput {
+        let mut tree = true;
+        let mut batch = false;
+        for arg in args {
+            match *arg {
+                "-t" | "--tree" => tree = true,
+                "--no-tree" => tree = false,
+                "-b" | "--batch" => batch = true,
+                other => {
+                    return CmdOutput::error(1, format!("htop: unrecognized option '{}'", other))
+                }
+            }
+        }
+        if batch {
+            return CmdOutput::ok(self.render_htop(None));
+        }
+        let state = HtopState::new(tree);
+        let frame = self.render_htop(Some(&state));
+        self.htop = Some(state);
+        CmdOutput::ok(format!("\x1b[HTOP]{}", frame))
+    }
+
+    /// Feed a key from the frontend into the interactive htop session.
+    /// Returns the redrawn screen, or `\x1b[HTOP_EXIT]` when the session ends.
+    pub(super) fn htop_key(&mut self, key: &str) -> String {
+        let Some(mut state) = self.htop.take() else {
+            return "\x1b[HTOP_EXIT]".into();
+        };
+        let rows: Vec<u32> = self
+            .htop_rows(state.tree)
+            .iter()
+            .map(|(p, _)| p.pid)
+            .collect();
+        state.selected = state.selected.min(rows.len().saturating_sub(1));
+        state.status.clear();
+
+        if state.kill_prompt {
+            state.kill_prompt = false;
+            let signal = match key {
+                "Enter" | "15" => Some("-TERM"),
+                "9" => Some("-KILL"),
+                _ => None,
+            };
+            if let (Some(sig), Some(pid)) = (signal, rows.get(state.selected)) {
+                let pid = pid.to_string();
+                let res = self.cmd_kill(&[sig, &pid]);
+                state.status = if res.status == 0 {
+                    format!("Sent SIG{} to {}", &sig[1..], pid)
+                } else {
+                    res.flatten()
+                };
+            }
+        } else {
+            match key {
+                "F10" | "q" | "Escape" => return "\x1b[HTOP_EXIT]".into(),
+                "ArrowUp" | "k" => state.selected = state.selected.saturating_sub(1),
+                "ArrowDown" | "j" => {
+                    state.selected = (state.selected + 1).min(rows.len().saturating_sub(1))
+                }
+                "Home" => state.selected = 0,
+                "End" => state.selected = rows.len().saturating_sub(1),
+                "F5" | "t" => state.tree = !state.tree,
+                "F9" => state.kill_prompt = true,
+                _ => {}
+            }
+        }
+
+        let frame = self.render_htop(Some(&state));
+        self.htop = Some(state);
+        format!("\x1b[HTOP]{}", frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_column_shows_the_owner() {
+        let mut sys = System::new();
+        let pid = sys
+            .kernel
+            .proc
+            .spawn("httpd", 1, &mut sys.kernel.mem)
+            .unwrap();
+        sys.kernel
+            .proc
+            .set_session(pid, "alice", Some("tty1"), "httpd");
+        let screen = sys.render_htop(None);
+        let row = screen
+            .lines()
+            .find(|l| l.trim_start().starts_with(&format!("{} ", pid)))
+            .unwrap();
+        assert!(row.contains(" alice "));
+    }
+}
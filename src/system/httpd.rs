@@ -0,0 +1,569 @@
+use super::System;
+use crate::process::Priority;
+use crate::shell::CmdOutput;
+
+const ACCESS_LOG: &str = "/var/log/httpd.log";
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum ServerKind {
+    /// `python -m http.server`
+    Python,
+    /// busybox `httpd`
+    Busybox,
+}
+
+/// A simulated web server bound to a port and serving a VFS directory
+pub(super) struct HttpServer {
+    kind: ServerKind,
+    pid: u32,
+    socket: u32,
+    port: u16,
+    root: String,
+}
+
+pub(super) struct HttpResponse {
+    pub(super) status: u16,
+    pub(super) content_type: &'static str,
+    pub(super) location: Option<String>,
+    pub(super) body: Vec<u8>,
+    pub(super) server: &'static str,
+}
+
+impl HttpResponse {
+    pub(super) fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            301 => "Moved Permanently",
+            403 => "Forbidden",
+            404 => "Not Found",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// Status line and headers as `curl -I` prints them
+    pub(super) fn head(&self) -> String {
+        let mut out = format!(
+            "HTTP/1.0 {} {}\nServer: {}\nDate: {}\n",
+            self.status,
+            self.reason(),
+            self.server,
+            http_date()
+        );
+        if let Some(location) = &self.location {
+            out.push_str(&format!("Location: {}\n", location));
+        }
+        out.push_str(&format!(
+            "Content-type: {}\nContent-Length: {}",
+            self.content_type,
+            self.body.len()
+        ));
+        out
+    }
+}
+
+impl System {
+    /// `python -m http.server [port] [-d dir] [-b addr]`
+    pub(super) fn cmd_http_server(&mut self, args: &[&str]) -> CmdOutput {
+        let mut port = 8000;
+        let mut root = ".".to_string();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-d" | "--directory" if i + 1 < args.len() => {
+                    root = args[i + 1].to_string();
+                    i += 1;
+                }
+                "-b" | "--bind" => i += 1,
+                arg if !arg.starts_with('-') => match arg.parse() {
+                    Ok(p) => port = p,
+                    Err(_) => {
+                        return CmdOutput::usage(format!(
+                            "usage: python -m http.server [-b ADDRESS] [-d DIRECTORY] [port]\n\
+                             http.server: error: argument port: invalid int value: '{}'",
+                            arg
+                        ))
+                    }
+                },
+                _ => {}
+            }
+            i += 1;
+        }
+        match self.start_http_server(ServerKind::Python, port, &root) {
+            Ok(pid) => CmdOutput::ok(format!(
+                "[{}] {}\nServing HTTP on 0.0.0.0 port {} (http://0.0.0.0:{}/) ...",
+                self.next_job_id - 1,
+                pid,
+                port,
+                port
+            )),
+            Err(e) if e.contains("in use") => {
+                CmdOutput::error(1, "OSError: [Errno 98] Address already in use".to_string())
+            }
+            Err(e) if e.contains("denied") => CmdOutput::error(
+                1,
+                "PermissionError: [Errno 13] Permission denied".to_string(),
+            ),
+            Err(e) => CmdOutput::error(1, e),
+        }
+    }
+
+    /// busybox `httpd -p PORT [-h HOME] [-f]`
+    pub(super) fn cmd_httpd(&mut self, args: &[&str]) -> CmdOutput {
+        let mut port = 80;
+        let mut root = ".".to_string();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-p" if i + 1 < args.len() => {
+                    // `-p [IP:]PORT`
+                    let value = args[i + 1].rsplit(':').next().unwrap_or("");
+                    match value.parse() {
+                        Ok(p) => port = p,
+                        Err(_) => {
+                            return CmdOutput::error(
+                                1,
+                                format!("httpd: invalid number '{}'", args[i + 1]),
+                            )
+                        }
+                    }
+                    i += 1;
+                }
+                "-h" if i + 1 < args.len() => {
+                    root = args[i + 1].to_string();
+                    i += 1;
+                }
+                "-f" | "-v" | "-vv" => {}
+                "--help" => {
+                    return CmdOutput::usage(
+                        "Usage: httpd [-f] [-v] [-p [IP:]PORT] [-h HOME]\n\n\
+                            Listen for incoming HTTP requests\n\n\
+                            \t-f\t\tDon't daemonize\n\
+                            \t-v[v]\t\tVerbose\n\
+                            \t-p [IP:]PORT\tBind to IP:PORT (default *:80)\n\
+                            \t-h HOME\t\tHome directory (default .)"
+                            .to_string(),
+                    )
+                }
+                arg => return CmdOutput::error(1, format!("httpd: unrecognized option '{}'", arg)),
+            }
+            i += 1;
+        }
+        match self.start_http_server(ServerKind::Busybox, port, &root) {
+            Ok(_) => CmdOutput::ok(String::new()),
+            Err(e) if e.contains("in use") => {
+                CmdOutput::error(1, "httpd: bind: Address in use".to_string())
+            }
+            Err(e) => CmdOutput::error(1, format!("httpd: {}", e)),
+        }
+    }
+
+    /// Bind `port` and run the server as a background job
+    fn start_http_server(
+        &mut self,
+        kind: ServerKind,
+        port: u16,
+        root: &str,
+    ) -> Result<u32, String> {
+        let root = self.kernel.fs.normalize(root);
+        match self.kernel.fs.resolve(&root) {
+            Some(node) if node.is_dir => {}
+            _ => return Err(format!("can't change directory to '{}'", root)),
+        }
+        if port < 1024 && self.current_user() != "root" {
+            return Err("bind: Permission denied".to_string());
+        }
+        let socket = self.network.listen(port)?;
+        let command = match kind {
+            ServerKind::Python => format!("python3 -m http.server {}", port),
+            ServerKind::Busybox => format!("httpd -p {}", port),
+        };
+        let name = command.split_whitespace().next().unwrap_or("httpd");
+        let Some(pid) = self.kernel.proc.spawn(name, 1, &mut self.kernel.mem) else {
+            self.network.release(socket);
+            return Err("out of memory".to_string());
+        };
+        self.kernel.scheduler.add(pid, Priority::Low);
+        self.network.set_owner(socket, pid);
+        if kind == ServerKind::Python {
+            // busybox httpd daemonizes; the python server stays a shell job
+            let id = self.next_job_id;
+            self.next_job_id += 1;
+            self.jobs.push(super::ShellJob {
+                id,
+                pid,
+                command,
+                state: super::JobState::Running,
+            });
+        }
+        self.http_servers.push(HttpServer {
+            kind,
+            pid,
+            socket,
+            port,
+            root,
+        });
+        Ok(pid)
+    }
+
+    /// Close the listening socket of a server whose process was killed
+    pub(super) fn stop_http_server(&mut self, pid: u32) {
+        let network = &mut self.network;
+        self.http_servers.retain(|server| {
+            if server.pid == pid {
+                network.release(server.socket);
+            }
+            server.pid != pid
+        });
+    }
+
+    /// The port and path of `url` when it names this machine; requests to
+    /// it are answered by the simulated servers instead of the real network
+    pub(super) fn local_http_target(&self, url: &str) -> Option<(u16, String)> {
+        let rest = url
+            .strip_prefix("http://")
+            .or_else(|| url.strip_prefix("https://"))
+            .unwrap_or(url);
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (authority, 80),
+        };
+        let hostname = self.cmd_hostname().stdout;
+        let local = matches!(host, "localhost" | "127.0.0.1" | "0.0.0.0" | "[::1]")
+            || host == hostname.trim();
+        local.then(|| (port, path.to_string()))
+    }
+
+    /// Answer a GET for `path` on `port`; `Err` means nothing is listening
+    pub(super) fn serve_local_http(
+        &mut self,
+        port: u16,
+        path: &str,
+    ) -> Result<HttpResponse, String> {
+        let Some(server) = self.http_servers.iter().find(|s| s.port == port) else {
+            return Err(format!(
+                "Failed to connect to localhost port {} after 0 ms: Couldn't connect to server",
+                port
+            ));
+        };
+        let (kind, root) = (server.kind, server.root.clone());
+        let server_name = match kind {
+            ServerKind::Python => "SimpleHTTP/0.6 Python/3.12.3",
+            ServerKind::Busybox => "busybox httpd/1.36.1",
+        };
+        let request_path = path.split(['?', '#']).next().unwrap_or("/").to_string();
+        let fs_path = format!(
+            "{}/{}",
+            root.trim_end_matches('/'),
+            percent_decode(&request_path).trim_start_matches('/')
+        );
+        let fs_path = self.kernel.fs.normalize(&fs_path);
+
+        // Never serve anything outside the document root
+        let inside = root == "/" || fs_path == root || fs_path.starts_with(&format!("{}/", root));
+        let node = self.kernel.fs.resolve(&fs_path).filter(|_| inside);
+        let mut response = match node {
+            Some(node) if node.is_dir && !request_path.ends_with('/') => HttpResponse {
+                status: 301,
+                content_type: "text/html",
+                location: Some(format!("{}/", request_path)),
+                body: Vec::new(),
+                server: server_name,
+            },
+            Some(node) if node.is_dir => {
+                let index = format!("{}/index.html", fs_path.trim_end_matches('/'));
+                if self.kernel.fs.resolve(&index).is_some_and(|n| !n.is_dir) {
+                    self.http_file(&index, kind, server_name)
+                } else if kind == ServerKind::Python && self.has_access(&fs_path, 4) {
+                    let mut names: Vec<String> = node
+                        .children
+                        .values()
+                        .map(|c| {
+                            if c.is_dir {
+                                format!("{}/", c.name)
+                            } else {
+                                c.name.clone()
+                            }
+                        })
+                        .collect();
+                    names.sort();
+                    HttpResponse {
+                        status: 200,
+                        content_type: "text/html; charset=utf-8",
+                        location: None,
+                        body: directory_listing(&request_path, &names).into_bytes(),
+                        server: server_name,
+                    }
+                } else {
+                    error_response(kind, 404, server_name)
+                }
+            }
+            Some(_) => self.http_file(&fs_path, kind, server_name),
+            None => error_response(kind, 404, server_name),
+        };
+        if response.status == 404 && kind == ServerKind::Busybox && node.is_some() {
+            response = error_response(kind, 403, server_name);
+        }
+
+        let request_len =
+            format!("GET {} HTTP/1.1\r\nHost: localhost:{}\r\n\r\n", path, port).len();
+        self.network.record_tcp_exchange(
+            js_sys::Date::now(),
+            "127.0.0.1",
+            port,
+            request_len,
+            response.head().len() + response.body.len(),
+        );
+
+        let line = format!(
+            "127.0.0.1 - - [{}] \"GET {} HTTP/1.1\" {} {}\n",
+            log_date(),
+            path,
+            response.status,
+            if response.status == 200 {
+                response.body.len().to_string()
+            } else {
+                "-".to_string()
+            }
+        );
+        match self.kernel.fs.resolve(ACCESS_LOG).map(|n| n.data.clone()) {
+            Some(log) => {
+                let _ = self
+                    .kernel
+                    .fs
+                    .write_file(ACCESS_LOG, &format!("{}{}", log, line));
+            }
+            None => {
+                let _ = self.kernel.fs.create_file(ACCESS_LOG, &line);
+            }
+        }
+        Ok(response)
+    }
+
+    /// `curl URL` or `wget -O - URL` against a local server
+    pub(super) fn local_fetch(
+        &mut self,
+        tool: &str,
+        port: u16,
+        path: &str,
+        head: bool,
+    ) -> CmdOutput {
+        match self.serve_local_http(port, path) {
+            Ok(response) if head => CmdOutput::ok(response.head()),
+            Ok(response) if tool == "wget" && response.status >= 400 => CmdOutput::error(
+                1,
+                format!("ERROR {}: {}.", response.status, response.reason()),
+            ),
+            Ok(response) => CmdOutput::ok(String::from_utf8_lossy(&response.body).into_owned()),
+            Err(_) if tool == "wget" => CmdOutput::error(
+                1,
+                format!(
+                    "Connecting to localhost:{}... failed: Connection refused.",
+                    port
+                ),
+            ),
+            Err(e) => CmdOutput::error(1, format!("curl: (7) {}", e)),
+        }
+    }
+
+    /// `wget URL` or `curl -o FILE URL` against a local server, saving to `path`
+    pub(super) fn local_download(
+        &mut self,
+        tool: &str,
+        quiet: bool,
+        shown: &str,
+        path: &str,
+        port: u16,
+        route: &str,
+    ) -> CmdOutput {
+        let response = match self.serve_local_http(port, route) {
+            Ok(response) => response,
+            Err(_) if tool == "wget" => {
+                return CmdOutput::error(
+                    1,
+                    format!(
+                        "Connecting to localhost:{}... failed: Connection refused.",
+                        port
+                    ),
+                )
+            }
+            Err(e) => return CmdOutput::error(1, format!("curl: (7) {}", e)),
+        };
+        let mut out = Vec::new();
+        if tool == "wget" {
+            if !quiet {
+                out.push(format!("Connecting to localhost:{}... connected.", port));
+                out.push(format!(
+                    "HTTP request sent, awaiting response... {} {}",
+                    response.status,
+                    response.reason()
+                ));
+            }
+            if response.status >= 400 {
+                out.push(format!("ERROR {}: {}.", response.status, response.reason()));
+                return CmdOutput::error(1, out.join("\n"));
+            }
+        }
+        if let Err(e) = self.write_file_bytes(path, &response.body) {
+            out.push(match tool {
+                "wget" => format!("{}: {}", shown, e),
+                _ => format!("curl: (23) Failure writing output to destination: {}", e),
+            });
+            return CmdOutput::error(1, out.join("\n"));
+        }
+        let size = response.body.len();
+        if quiet {
+            return CmdOutput::error(1, out.join("\n"));
+        }
+        if tool == "wget" {
+            out.push(format!("Length: {} [{}]", size, response.content_type));
+            out.push(format!("Saving to: '{}'", shown));
+            out.push(String::new());
+            out.push(format!("'{}' saved [{}/{}]", shown, size, size));
+        } else {
+            out.push(
+                "  % Total    % Received % Xferd  Average Speed   Time    Time     Time  Current"
+                    .to_string(),
+            );
+            out.push(
+                "                                 Dload  Upload   Total   Spent    Left  Speed"
+                    .to_string(),
+            );
+            out.push(format!(
+                "100 {:>5}  100 {:>5}    0     0   {:>4}k      0 --:--:-- --:--:-- --:--:--  {:>4}k",
+                size,
+                size,
+                size / 1024,
+                size / 1024
+            ));
+        }
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    fn http_file(&self, path: &str, kind: ServerKind, server: &'static str) -> HttpResponse {
+        match self.read_file_bytes(path) {
+            Ok(body) if self.has_access(path, 4) => HttpResponse {
+                status: 200,
+                content_type: content_type(path),
+                location: None,
+                body,
+                server,
+            },
+            _ => error_response(kind, 404, server),
+        }
+    }
+}
+
+fn error_response(kind: ServerKind, status: u16, server: &'static str) -> HttpResponse {
+    let body = match (kind, status) {
+        (ServerKind::Python, _) => "<!DOCTYPE HTML>\n<html lang=\"en\">\n    <head>\n        \
+             <meta charset=\"utf-8\">\n        <title>Error response</title>\n    </head>\n    \
+             <body>\n        <h1>Error response</h1>\n        <p>Error code: 404</p>\n        \
+             <p>Message: File not found.</p>\n        \
+             <p>Error code explanation: 404 - Nothing matches the given URI.</p>\n    \
+             </body>\n</html>\n"
+            .to_string(),
+        (ServerKind::Busybox, 403) => "<HTML><HEAD><TITLE>403 Forbidden</TITLE></HEAD>\n\
+             <BODY><H1>403 Forbidden</H1>\nThe requested URL is forbidden\n</BODY></HTML>\n"
+            .to_string(),
+        (ServerKind::Busybox, _) => "<HTML><HEAD><TITLE>404 Not Found</TITLE></HEAD>\n\
+             <BODY><H1>404 Not Found</H1>\nThe requested URL was not found\n</BODY></HTML>\n"
+            .to_string(),
+    };
+    HttpResponse {
+        status,
+        content_type: "text/html;charset=utf-8",
+        location: None,
+        body: body.into_bytes(),
+        server,
+    }
+}
+
+/// The page `python -m http.server` renders for a directory
+fn directory_listing(path: &str, names: &[String]) -> String {
+    let title = format!("Directory listing for {}", html_escape(path));
+    let items: String = names
+        .iter()
+        .map(|n| format!("<li><a href=\"{}\">{}</a></li>\n", n, html_escape(n)))
+        .collect();
+    format!(
+        "<!DOCTYPE HTML>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n<hr>\n<ul>\n{1}</ul>\n<hr>\n\
+         </body>\n</html>\n",
+        title, items
+    )
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn content_type(path: &str) -> &'static str {
+    let ext = path.rsplit_once('.').map_or("", |(_, e)| e);
+    match ext.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "py" => "text/x-python",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// `Thu, 16 Oct 2026 10:00:00 GMT`
+fn http_date() -> String {
+    js_sys::Date::new_0().to_utc_string().into()
+}
+
+/// `16/Oct/2026 10:00:00`, the timestamp in access log lines
+fn log_date() -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let d = js_sys::Date::new_0();
+    format!(
+        "{:02}/{}/{} {:02}:{:02}:{:02}",
+        d.get_date(),
+        MONTHS[d.get_month() as usize % 12],
+        d.get_full_year(),
+        d.get_hours(),
+        d.get_minutes(),
+        d.get_seconds()
+    )
+}
@@ -0,0 +1,227 @@
+use super::{System, SystemEvent};
+use crate::idle::{DEFAULT_TIMEOUT_MS, DEFAULT_WARN_MS};
+use crate::shell::CmdOutput;
+
+/// `~/.config/idle`: `timeout=`, `warn=` (seconds, or `off`) and `lock=on|off`
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct IdleConfig {
+    timeout_secs: u32,
+    warn_secs: u32,
+    lock: bool,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        IdleConfig {
+            timeout_secs: DEFAULT_TIMEOUT_MS / 1000,
+            warn_secs: DEFAULT_WARN_MS / 1000,
+            lock: false,
+        }
+    }
+}
+
+fn parse_secs(value: &str) -> Option<u32> {
+    match value.trim() {
+        "off" | "0" => Some(0),
+        v => v.parse().ok(),
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.trim() {
+        "on" | "yes" | "true" | "1" => Some(true),
+        "off" | "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_config(text: &str) -> IdleConfig {
+    let mut config = IdleConfig::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "timeout" => config.timeout_secs = parse_secs(value).unwrap_or(config.timeout_secs),
+            "warn" => config.warn_secs = parse_secs(value).unwrap_or(config.warn_secs),
+            "lock" => config.lock = parse_switch(value).unwrap_or(config.lock),
+            _ => {}
+        }
+    }
+    config
+}
+
+fn format_config(config: &IdleConfig) -> String {
+    let secs = |s: u32| {
+        if s == 0 {
+            "off".to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    format!(
+        "timeout={}\nwarn={}\nlock={}\n",
+        secs(config.timeout_secs),
+        secs(config.warn_secs),
+        if config.lock { "on" } else { "off" }
+    )
+}
+
+impl System {
+    fn idle_config_path(&self) -> String {
+        let home = Self::default_home_for_user(&self.current_user());
+        format!("{}/.config/idle", home)
+    }
+
+    fn idle_config(&self) -> IdleConfig {
+        self.kernel
+            .fs
+            .resolve(&self.idle_config_path())
+            .map(|node| parse_config(&node.data))
+            .unwrap_or_default()
+    }
+
+    /// Hand ~/.config/idle to the idle timer
+    pub(super) fn apply_idle_config(&self) {
+        let config = self.idle_config();
+        crate::idle::configure(
+            config.timeout_secs.saturating_mul(1000),
+            config.warn_secs.saturating_mul(1000),
+            config.lock,
+        );
+    }
+
+    fn save_idle_config(&mut self, config: &IdleConfig) -> String {
+        let path = self.idle_config_path();
+        let dir = path.rsplit_once('/').map_or("/", |(d, _)| d).to_string();
+        if let Err(e) = self.ensure_dir_all(&dir) {
+            return e;
+        }
+        if let Err(e) = self.write_file_bytes(&path, format_config(config).as_bytes()) {
+            return format!("{}: {}", path, e);
+        }
+        self.apply_idle_config();
+        String::new()
+    }
+
+    fn idle_status(&self) -> String {
+        let config = self.idle_config();
+        let secs = |s: u32| {
+            if s == 0 {
+                "off".to_string()
+            } else {
+                format!("{}s", s)
+            }
+        };
+        format!(
+            "timeout: {}\nwarning: {}\nlock:    {}",
+            secs(config.timeout_secs),
+            secs(config.warn_secs),
+            if config.lock { "on" } else { "off" }
+        )
+    }
+
+    /// `idle [timeout|warn SECS|off] [lock on|off] [now]`
+    pub(super) fn cmd_idle(&mut self, args: &[&str]) -> CmdOutput {
+        let mut config = self.idle_config();
+        match args {
+            [] => return CmdOutput::ok(self.idle_status()),
+            ["now"] => return CmdOutput::ok(self.emit(SystemEvent::BlankScreen)),
+            ["timeout", value] => match parse_secs(value) {
+                Some(secs) => config.timeout_secs = secs,
+                None => return CmdOutput::error(1, format!("idle: invalid timeout '{}'", value)),
+            },
+            ["warn", value] => match parse_secs(value) {
+                Some(secs) => config.warn_secs = secs,
+                None => {
+                    return CmdOutput::error(1, format!("idle: invalid warning time '{}'", value))
+                }
+            },
+            ["lock", value] => match parse_switch(value) {
+                Some(lock) => config.lock = lock,
+                None => return CmdOutput::usage("usage: idle lock on|off".to_string()),
+            },
+            _ => {
+                return CmdOutput::usage(
+                    "usage: idle [timeout SECS|off] [warn SECS|off] [lock on|off] [now]"
+                        .to_string(),
+                )
+            }
+        }
+        match self.save_idle_config(&config) {
+            e if e.is_empty() => CmdOutput::ok(String::new()),
+            e => CmdOutput::error(1, format!("idle: {}", e)),
+        }
+    }
+
+    /// The screen saver half of X's `xset`: `xset s SECS|off|on|activate|reset`, `xset q`
+    pub(super) fn cmd_xset(&mut self, args: &[&str]) -> CmdOutput {
+        let mut config = self.idle_config();
+        match args {
+            ["q"] => return CmdOutput::ok(self.idle_status()),
+            ["s", "activate"] => return CmdOutput::ok(self.emit(SystemEvent::BlankScreen)),
+            ["s", "reset"] => {
+                crate::idle::mark_activity();
+                return CmdOutput::ok(String::new());
+            }
+            ["s", "on"] | ["s", "default"] => {
+                config.timeout_secs = IdleConfig::default().timeout_secs
+            }
+            ["s", value] => match parse_secs(value) {
+                Some(secs) => config.timeout_secs = secs,
+                None => {
+                    return CmdOutput::error(
+                        1,
+                        format!("xset: bad screen saver timeout '{}'", value),
+                    )
+                }
+            },
+            _ => {
+                return CmdOutput::usage(
+                    "usage: xset s SECS|off|on|activate|reset\n       xset q".to_string(),
+                )
+            }
+        }
+        match self.save_idle_config(&config) {
+            e if e.is_empty() => CmdOutput::ok(String::new()),
+            e => CmdOutput::error(1, format!("xset: {}", e)),
+        }
+    }
+
+    /// Check the password typed at the lock screen
+    pub(super) fn try_unlock(&self, pw: &str) -> bool {
+        // Without a login password there is nothing to check against
+        let ok = self
+            .user_password
+            .as_deref()
+            .is_none_or(|saved| saved == pw);
+        if ok {
+            crate::idle::unlock();
+        }
+        ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_config_round_trip() {
+        let config = parse_config("timeout=300\nwarn=off\nlock=on\nbogus\n");
+        assert_eq!(
+            config,
+            IdleConfig {
+                timeout_secs: 300,
+                warn_secs: 0,
+                lock: true,
+            }
+        );
+        assert_eq!(parse_config(&format_config(&config)), config);
+        // Bad values keep the defaults
+        assert_eq!(
+            parse_config("timeout=soon\nlock=maybe"),
+            IdleConfig::default()
+        );
+    }
+}
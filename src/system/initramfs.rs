@@ -0,0 +1,177 @@
+use super::fsck::ROOT_DEVICE;
+use super::System;
+use crate::vfs::{DEFAULT_GRUB_CFG, GRUB_CFG_PATH};
+
+const HELP: &str = "Built-in commands:
+    cat ls echo mount clear help
+    fsck [-y] [DEVICE]     check the root filesystem, -y to repair
+    reinstall-grub         write a fresh /boot/grub/grub.cfg
+    snapshot list          show saved copies of the filesystem
+    snapshot restore NAME  roll the filesystem back to one
+    exit                   continue booting
+    reboot                 restart the machine";
+
+impl System {
+    /// What stops a normal boot: a missing grub.cfg or critical binary
+    pub(super) fn recovery_problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .kernel
+            .fs
+            .missing_critical()
+            .into_iter()
+            .map(|path| format!("ALERT! {} does not exist.", path))
+            .collect();
+        if self.kernel.fs.resolve(GRUB_CFG_PATH).is_none() {
+            problems.push(format!("error: file '{}' not found.", GRUB_CFG_PATH));
+        }
+        problems
+    }
+
+    pub(super) fn initramfs_motd(&self) -> String {
+        let mut out = self.recovery_problems().join("\n");
+        if !self.kernel.snapshots.is_empty() {
+            let names: Vec<&str> = self
+                .kernel
+                .snapshots
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect();
+            out.push_str(&format!(
+                "\nSnapshots available: {} (snapshot restore NAME)",
+                names.join(", ")
+            ));
+        }
+        out.push_str(
+            "\nDropping to a shell!\n\n\
+             BusyBox v1.36.1 (kpawnd) built-in shell (ash)\n\
+             Enter 'help' for a list of built-in commands.\n",
+        );
+        out
+    }
+
+    /// One line typed at the `(initramfs)` prompt
+    pub(super) fn initramfs_exec(&mut self, line: &str) -> String {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let Some((&cmd, args)) = parts.split_first() else {
+            return String::new();
+        };
+        match cmd {
+            "help" => HELP.to_string(),
+            "ls" => self.cmd_ls(args).flatten(),
+            "cat" => self.cmd_cat(args).flatten(),
+            "echo" => args.join(" "),
+            "mount" => self.cmd_mount(&[]).flatten(),
+            "clear" => "\x1b[CLEAR]".to_string(),
+            "fsck" | "e2fsck" | "fsck.ext4" => self.initramfs_fsck(args),
+            "reinstall-grub" | "grub-install" => self.reinstall_grub(),
+            // The initramfs shell runs as root whoever is logged in
+            "snapshot" => match args {
+                ["list"] => self.snapshot_list(),
+                ["restore", name] => self.snapshot_restore(name).flatten(),
+                _ => "usage: snapshot list | restore NAME".to_string(),
+            },
+            "reboot" => "\x1b[REBOOT]".to_string(),
+            "exit" => {
+                if self.recovery_problems().is_empty() {
+                    "\x1b[EXIT_INITRAMFS]".to_string()
+                } else {
+                    "Cannot continue boot: the system is still broken (try fsck -y, reinstall-grub)"
+                        .to_string()
+                }
+            }
+            _ => format!("sh: {}: not found", cmd),
+        }
+    }
+
+    fn initramfs_fsck(&mut self, args: &[&str]) -> String {
+        let repair = args.iter().any(|a| matches!(*a, "-y" | "-p" | "-a"));
+        let device = args
+            .iter()
+            .find(|a| !a.starts_with('-'))
+            .copied()
+            .unwrap_or(ROOT_DEVICE);
+        if device != ROOT_DEVICE {
+            return format!("fsck: {}: No such device", device);
+        }
+
+        let mut out = vec![
+            "fsck from util-linux 2.38.1".to_string(),
+            format!("{}: recovering journal", device),
+        ];
+        let check = self.fsck_check(repair);
+        let summary = self.fsck_summary(device, &check, repair);
+        out.extend(check.lines);
+        out.extend(summary);
+        out.join("\n")
+    }
+
+    fn reinstall_grub(&mut self) -> String {
+        for dir in ["/boot", "/boot/grub"] {
+            if self.kernel.fs.resolve(dir).is_none() {
+                if let Err(e) = self.kernel.fs.create_dir(dir) {
+                    return format!("grub-install: error: {}: {}", dir, e);
+                }
+            }
+        }
+        let written = if self.kernel.fs.resolve(GRUB_CFG_PATH).is_some() {
+            self.kernel.fs.write_file(GRUB_CFG_PATH, DEFAULT_GRUB_CFG)
+        } else {
+            self.kernel.fs.create_file(GRUB_CFG_PATH, DEFAULT_GRUB_CFG)
+        };
+        match written {
+            Ok(()) => format!(
+                "Installing for i386-pc platform.\n\
+                 Installation finished. No error reported.\n\
+                 Generating grub configuration file ...\n\
+                 Wrote {}",
+                GRUB_CFG_PATH
+            ),
+            Err(e) => format!("grub-install: error: {}: {}", GRUB_CFG_PATH, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initramfs_repairs_deleted_system() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.set_ignore_critical_deletes(true);
+        sys.kernel.fs.remove_recursive("/sbin").unwrap();
+        sys.kernel.fs.remove("/bin/sh").unwrap();
+        sys.kernel.fs.remove_recursive("/boot/grub").unwrap();
+        sys.kernel.fs.set_ignore_critical_deletes(false);
+        assert_eq!(sys.recovery_problems().len(), 4);
+
+        assert!(sys.initramfs_exec("exit").starts_with("Cannot continue"));
+        assert!(sys.initramfs_exec("fsck").contains("still has errors"));
+        assert_eq!(sys.kernel.fs.missing_critical().len(), 3);
+        sys.initramfs_exec("fsck -y /dev/sda1");
+        assert!(sys.kernel.fs.missing_critical().is_empty());
+        assert!(sys.kernel.fs.is_critical("/sbin/init"));
+
+        sys.initramfs_exec("reinstall-grub");
+        assert!(sys.recovery_problems().is_empty());
+        assert_eq!(sys.initramfs_exec("exit"), "\x1b[EXIT_INITRAMFS]");
+        assert_eq!(sys.initramfs_exec("vim"), "sh: vim: not found");
+    }
+
+    #[test]
+    fn initramfs_restores_snapshots_as_root() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.shell.env.insert("USER".into(), "root".into());
+        sys.cmd_snapshot(&["create", "good"]);
+        sys.shell.env.insert("USER".into(), "user".into());
+        sys.kernel.fs.create_file("/tmp/junk", "").unwrap();
+
+        assert!(sys.initramfs_exec("snapshot list").contains("\ngood "));
+        assert!(sys
+            .initramfs_exec("snapshot restore good")
+            .starts_with("Rolled the filesystem back to 'good'"));
+        assert!(sys.kernel.fs.resolve("/tmp/junk").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Ctrl+C. Each subsystem that keeps running past the command that started
+//! it has a hook here that stops it; `interrupt` asks every one of them.
+use super::System;
+use wasm_bindgen::prelude::*;
+
+/// Stops something if it is running; returns whether it was
+type Hook = fn(&mut System) -> bool;
+
+static HOOKS: &[Hook] = &[
+    // sudo's password prompt and `rm -i`'s question
+    |sys| {
+        let waiting = sys.sudo_waiting_password;
+        sys.sudo_waiting_password = false;
+        sys.sudo_pending_request = None;
+        waiting
+    },
+    |sys| sys.rm_prompt.take().is_some(),
+    |sys| sys.browser.take().is_some(),
+    |sys| {
+        let open = sys.pager.is_some();
+        if open {
+            sys.pager_exit();
+        }
+        open
+    },
+    |sys| sys.htop.take().is_some(),
+    |sys| {
+        let open = sys.in_python_repl;
+        sys.in_python_repl = false;
+        sys.python_interp = None;
+        open
+    },
+    |sys| {
+        let open = sys.in_lua_repl;
+        sys.in_lua_repl = false;
+        sys.lua_interp = None;
+        open
+    },
+    // Changes are saved after each statement, so nothing is lost
+    |sys| {
+        let open = sys.in_sqlite_repl;
+        sys.in_sqlite_repl = false;
+        sys.sqlite = None;
+        open
+    },
+    |sys| {
+        let open = sys.wscat_active();
+        sys.wscat_quit();
+        open
+    },
+    |sys| sys.traceroute.take().is_some(),
+    // Network requests a typed line is still waiting on
+    |sys| sys.net_interrupt(),
+    // SIGINT to the job `fg` brought forward: it ends, as nothing here
+    // catches the signal
+    |sys| {
+        let Some(pid) = sys.foreground.take() else {
+            return false;
+        };
+        sys.kernel.proc.kill(pid, &mut sys.kernel.mem);
+        sys.kernel.scheduler.remove(pid);
+        sys.stop_http_server(pid);
+        true
+    },
+];
+
+#[wasm_bindgen]
+impl System {
+    /// Ctrl+C: cancel prompts, leave the pager, htop and the REPLs, stop
+    /// the foreground job and drop pending network requests. Returns the `^C` to print; `$?` becomes 130 if
+    /// anything was stopped
+    #[wasm_bindgen]
+    pub fn interrupt(&mut self) -> String {
+        let mut stopped = false;
+        for hook in HOOKS {
+            stopped |= hook(self);
+        }
+        if stopped {
+            self.last_status = 130;
+        }
+        let pane = self.mux.active_pane();
+        self.pane_record_output(pane, "^C");
+        "^C".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupt_stops_the_pager_prompts_and_foreground_job() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        assert_eq!(sys.interrupt(), "^C");
+        assert_eq!(sys.last_status, 0);
+
+        sys.kernel
+            .fs
+            .create_file("/tmp/long", &"line\n".repeat(100))
+            .unwrap();
+        sys.exec("less /tmp/long");
+        assert!(sys.is_pager_active());
+        sys.exec("httpd &");
+        let pid = sys.jobs[0].pid;
+        sys.exec("fg %1");
+        assert!(sys.kernel.proc.get(pid).is_some());
+        sys.sudo_waiting_password = true;
+
+        sys.interrupt();
+        assert!(!sys.is_pager_active());
+        assert!(!sys.term.in_alternate());
+        assert!(!sys.is_waiting_for_sudo());
+        assert!(sys.kernel.proc.get(pid).is_none());
+        assert_eq!(sys.last_status, 130);
+
+        // A ping still waiting on its first probe
+        sys.last_status = 0;
+        sys.exec_start("ping example.com".into()).unwrap();
+        assert!(sys.net_request().is_some());
+        sys.interrupt();
+        assert!(sys.net_request().is_none());
+        assert_eq!(sys.last_status, 130);
+        assert_eq!(sys.exec_finish(), "");
+    }
+}
@@ -0,0 +1,148 @@
+//! `iss`: where the International Space Station is right now, asked of a
+//! public tracking API through `net_perform` and marked on a world map
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+use serde_json::Value;
+
+/// Asked in turn: open-notify is plain http, which a page served over
+/// https may not be allowed to fetch
+pub(super) const ISS_SOURCES: [&str; 2] = [
+    "http://api.open-notify.org/iss-now.json",
+    "https://api.wheretheiss.at/v1/satellites/25544",
+];
+
+/// The world from 90°N to 90°S in 10° rows and from 180°W in 5° columns
+const WORLD: [&str; 18] = [
+    "                      ...........                                       ",
+    "           ............ ........      ...      .................        ",
+    "   ..............   .... ..... ..    ...................................",
+    "         ........   .....         .. ...........................   ..   ",
+    "           ............           ....... ...  ...............  .       ",
+    "            .........             ....  ....................  ..        ",
+    "             ....                ...............  .... .....            ",
+    "               ....              ............      .   ...  .           ",
+    "                    ......       ............           . ..            ",
+    "                    .........         ......            ..........      ",
+    "                     .......          ...... .               ....       ",
+    "                      ......           ....  .             ........     ",
+    "                      ...               ..                 .......    ..",
+    "                     ..                                          .   .. ",
+    "                     ..                                                 ",
+    "                       ..       ......................................  ",
+    "........................................................................",
+    "........................................................................",
+];
+
+/// Where the station is, with its altitude and speed when the source
+/// says
+struct Position {
+    lat: f64,
+    lon: f64,
+    altitude: Option<f64>,
+    velocity: Option<f64>,
+}
+
+/// A number that may come quoted, as open-notify sends them
+fn number(v: &Value) -> Option<f64> {
+    v.as_f64().or_else(|| v.as_str()?.parse().ok())
+}
+
+/// open-notify's `{"iss_position": {...}}` or wheretheiss.at's flat object
+fn parse_position(json: &str) -> Option<Position> {
+    let v: Value = serde_json::from_str(json).ok()?;
+    let at = v.get("iss_position").unwrap_or(&v);
+    Some(Position {
+        lat: number(&at["latitude"])?,
+        lon: number(&at["longitude"])?,
+        altitude: number(&v["altitude"]),
+        velocity: number(&v["velocity"]),
+    })
+}
+
+/// The map cell a position falls in
+fn cell(lat: f64, lon: f64) -> (usize, usize) {
+    let row = ((90.0 - lat) / 10.0).floor().clamp(0.0, 17.0) as usize;
+    let col = ((lon + 180.0) / 5.0).floor().clamp(0.0, 71.0) as usize;
+    (row, col)
+}
+
+fn render_position(pos: &Position) -> String {
+    let (row, col) = cell(pos.lat, pos.lon);
+    let below = if WORLD[row].as_bytes()[col] == b'.' {
+        "land"
+    } else {
+        "the ocean"
+    };
+    let mut out = vec![
+        "International Space Station".to_string(),
+        format!(
+            "  {:.2}°{}  {:.2}°{}  over {}",
+            pos.lat.abs(),
+            if pos.lat < 0.0 { 'S' } else { 'N' },
+            pos.lon.abs(),
+            if pos.lon < 0.0 { 'W' } else { 'E' },
+            below
+        ),
+    ];
+    if let (Some(alt), Some(speed)) = (pos.altitude, pos.velocity) {
+        out.push(format!("  {:.0} km up, {:.0} km/h", alt, speed));
+    }
+    let border = format!("+{}+", "-".repeat(72));
+    out.push(border.clone());
+    for (n, line) in WORLD.iter().enumerate() {
+        let line = if n == row {
+            format!(
+                "{}\x1b[COLOR:red]@\x1b[COLOR:reset]{}",
+                &line[..col],
+                &line[col + 1..]
+            )
+        } else {
+            line.to_string()
+        };
+        out.push(format!("|{}|", line));
+    }
+    out.push(border);
+    out.join("\n")
+}
+
+impl System {
+    /// The rest of `iss` once a tracking API answered, or none did
+    pub(super) fn iss_finish(&mut self, fetched: Result<String, String>) -> CmdOutput {
+        match fetched.ok().as_deref().and_then(parse_position) {
+            Some(pos) => CmdOutput::ok(render_position(&pos)),
+            None => CmdOutput::error(1, "iss: the station's position is unavailable"),
+        }
+    }
+
+    pub(super) fn cmd_iss(&mut self, args: &[&str]) -> CmdOutput {
+        if !args.is_empty() {
+            return CmdOutput::usage("usage: iss");
+        }
+        if self.host_unreachable("api.open-notify.org").is_some() {
+            return CmdOutput::error(1, "iss: Could not resolve host: api.open-notify.org");
+        }
+        CmdOutput::ok(self.emit(SystemEvent::Iss))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_station_is_marked_on_the_map() {
+        let open_notify = r#"{"iss_position": {"latitude": "51.5", "longitude": "-0.1"},
+            "timestamp": 1760000000, "message": "success"}"#;
+        let pos = parse_position(open_notify).unwrap();
+        let map = render_position(&pos);
+        assert!(map.contains("  51.50°N  0.10°W  over land"));
+        let row = map.lines().find(|l| l.contains('@')).unwrap();
+        assert!(row.starts_with("|         ........   .....         .\x1b[COLOR:red]@"));
+
+        let flat = r#"{"latitude": -30.0, "longitude": -150.0, "altitude": 420.1,
+            "velocity": 27600.4}"#;
+        let map = render_position(&parse_position(flat).unwrap());
+        assert!(map.contains("30.00°S  150.00°W  over the ocean\n  420 km up, 27600 km/h"));
+        assert!(parse_position("<html>").is_none());
+    }
+}
@@ -1,4 +1,6 @@
 use super::System;
+use crate::clock;
+use crate::shell::CmdOutput;
 
 #[derive(Clone)]
 struct UserEntry {
@@ -87,6 +89,17 @@ impl System {
         groups.iter().find(|g| g.gid == gid)
     }
 
+    /// Numeric uid and gid for an owner and group, 0 when unknown
+    pub(super) fn numeric_ids(&self, owner: &str, group: &str) -> (u32, u32) {
+        let uid = self
+            .lookup_user(&self.parse_users(), owner)
+            .map_or(0, |u| u.uid);
+        let gid = self
+            .lookup_group_by_name(&self.parse_groups(), group)
+            .map_or(0, |g| g.gid);
+        (uid, gid)
+    }
+
     fn lookup_group_by_name<'a>(
         &self,
         groups: &'a [GroupEntry],
@@ -149,7 +162,7 @@ impl System {
         bits
     }
 
-    fn mode_to_octal(mode: &str) -> String {
+    pub(super) fn mode_to_octal(mode: &str) -> String {
         let chars: Vec<char> = mode.chars().collect();
         if chars.len() < 10 {
             return "000".into();
@@ -224,7 +237,7 @@ impl System {
         Some(chars.into_iter().collect())
     }
 
-    pub(super) fn cmd_id(&self, args: &[&str]) -> String {
+    pub(super) fn cmd_id(&self, args: &[&str]) -> CmdOutput {
         let users = self.parse_users();
         let groups = self.parse_groups();
 
@@ -240,7 +253,7 @@ impl System {
         }
 
         let Some(user) = self.lookup_user(&users, &username) else {
-            return format!("id: '{}' no such user", username);
+            return CmdOutput::error(1, format!("id: '{}' no such user", username));
         };
 
         let primary_group = self
@@ -250,26 +263,26 @@ impl System {
         let group_list = self.groups_for_user(user, &groups);
 
         match flag {
-            "-u" => format!("{}", user.uid),
-            "-g" => format!("{}", user.gid),
-            "-un" => user.name.clone(),
-            "-gn" => primary_group,
+            "-u" => CmdOutput::ok(format!("{}", user.uid)),
+            "-g" => CmdOutput::ok(format!("{}", user.gid)),
+            "-un" => CmdOutput::ok(user.name.clone()),
+            "-gn" => CmdOutput::ok(primary_group),
             "" => {
                 let rendered = group_list
                     .iter()
                     .map(|g| format!("{}({})", g.gid, g.name))
                     .collect::<Vec<_>>()
                     .join(",");
-                format!(
+                CmdOutput::ok(format!(
                     "uid={}({}) gid={}({}) groups={}",
                     user.uid, user.name, user.gid, primary_group, rendered
-                )
+                ))
             }
-            _ => "usage: id [-u|-g|-un|-gn] [USER]".into(),
+            _ => CmdOutput::usage("usage: id [-u|-g|-un|-gn] [USER]"),
         }
     }
 
-    pub(super) fn cmd_groups(&self, args: &[&str]) -> String {
+    pub(super) fn cmd_groups(&self, args: &[&str]) -> CmdOutput {
         let users = self.parse_users();
         let groups = self.parse_groups();
         let username = if args.is_empty() {
@@ -279,7 +292,7 @@ impl System {
         };
 
         let Some(user) = self.lookup_user(&users, &username) else {
-            return format!("groups: '{}' no such user", username);
+            return CmdOutput::error(1, format!("groups: '{}' no such user", username));
         };
 
         let names = self
@@ -289,35 +302,58 @@ impl System {
             .collect::<Vec<_>>()
             .join(" ");
 
-        format!("{} : {}", user.name, names)
+        CmdOutput::ok(format!("{} : {}", user.name, names))
     }
 
-    pub(super) fn cmd_who(&self, _args: &[&str]) -> String {
-        let d = js_sys::Date::new_0();
-        let user = self.current_user();
-        let host = self
-            .kernel
-            .fs
-            .resolve("/etc/hostname")
-            .map(|n| n.data.trim().to_string())
-            .unwrap_or_else(|| "localhost".into());
-
-        format!(
-            "{} tty1 {} ({})",
-            user,
-            d.to_locale_string("en-GB", &wasm_bindgen::JsValue::NULL),
-            host
+    pub(super) fn user_exists(&self, name: &str) -> bool {
+        let users = self.parse_users();
+        self.lookup_user(&users, name).is_some()
+    }
+
+    pub(super) fn cmd_dmesg(&self, args: &[&str]) -> CmdOutput {
+        let mut human = false;
+        for arg in args {
+            match *arg {
+                "-H" | "--human" => human = true,
+                other => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "dmesg: invalid option -- '{}'",
+                            other.trim_start_matches('-')
+                        ),
+                    )
+                }
+            }
+        }
+        let lines: Vec<&str> = self.kernel.dmesg().collect();
+        if !human {
+            return CmdOutput::ok(lines.join("\n"));
+        }
+        CmdOutput::ok(
+            lines
+                .iter()
+                .map(|l| match l.split_once("] ") {
+                    Some((ts, msg)) => format!("\x1b[COLOR:green]{}]\x1b[COLOR:reset] {}", ts, msg),
+                    None => l.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
         )
     }
 
-    pub(super) fn cmd_stat(&self, args: &[&str]) -> String {
+    pub(super) fn cmd_stat(&mut self, args: &[&str]) -> CmdOutput {
         if args.is_empty() {
-            return "usage: stat FILE".into();
+            return CmdOutput::usage("usage: stat FILE");
         }
 
         let path = args[0];
+        self.kernel.fs.number_inodes();
         let Some(node) = self.kernel.fs.resolve(path) else {
-            return format!("stat: cannot stat '{}': No such file or directory", path);
+            return CmdOutput::error(
+                1,
+                format!("stat: cannot stat '{}': No such file or directory", path),
+            );
         };
 
         let file_type = if node.permissions.starts_with('l') {
@@ -328,134 +364,30 @@ impl System {
             "regular file"
         };
         let blocks = node.size.div_ceil(512);
-        let inode_like = (node.name.len() as u64) * 131 + (node.size as u64);
 
-        format!(
-            "  File: {}\n  Size: {}\tBlocks: {}\tIO Block: 4096\t{}\nDevice: 00:00\tInode: {}\tLinks: 1\nAccess: ({}/{})\tUid: ({}/{})\tGid: ({}/{})",
+        CmdOutput::ok(format!(
+            "  File: {}\n  Size: {}\tBlocks: {}\tIO Block: 4096\t{}\nDevice: 00:00\tInode: {}\tLinks: 1\nAccess: ({}/{})\tUid: ({}/{})\tGid: ({}/{})\nAccess: {}\nModify: {}\nChange: {}\n Birth: {}",
             path,
             node.size,
             blocks,
             file_type,
-            inode_like,
+            node.ino,
             Self::mode_to_octal(&node.permissions),
             node.permissions,
             node.owner,
             node.owner,
             node.group,
-            node.group
-        )
-    }
-
-    pub(super) fn cmd_mount(&mut self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return self
-                .kernel
-                .fs
-                .resolve("/proc/mounts")
-                .map(|n| n.data.clone())
-                .unwrap_or_else(|| "/dev/sda1 / ext4 rw,relatime 0 0".into());
-        }
-
-        let mut fs_type = "ext4".to_string();
-        let mut values: Vec<&str> = Vec::new();
-        let mut i = 0;
-        while i < args.len() {
-            match args[i] {
-                "-t" => {
-                    if i + 1 >= args.len() {
-                        return "mount: option requires an argument -- 't'".into();
-                    }
-                    fs_type = args[i + 1].to_string();
-                    i += 2;
-                }
-                flag if flag.starts_with('-') => {
-                    return format!("mount: unsupported option '{}'", flag);
-                }
-                val => {
-                    values.push(val);
-                    i += 1;
-                }
-            }
-        }
-
-        if values.len() != 2 {
-            return "usage: mount [-t TYPE] SOURCE TARGET".into();
-        }
-
-        let source = values[0];
-        let target = values[1];
-
-        match self.kernel.fs.resolve(target) {
-            Some(n) if !n.is_dir => {
-                return format!("mount: {}: mount point is not a directory", target)
-            }
-            Some(_) => {}
-            None => return format!("mount: {}: mount point does not exist", target),
-        }
-
-        let existing = self
-            .kernel
-            .fs
-            .resolve("/proc/mounts")
-            .map(|n| n.data.clone())
-            .unwrap_or_default();
-
-        if existing.lines().any(|line| {
-            let cols: Vec<&str> = line.split_whitespace().collect();
-            cols.len() >= 2 && cols[1] == target
-        }) {
-            return format!("mount: {}: already mounted", target);
-        }
-
-        let mut next = existing;
-        if !next.is_empty() && !next.ends_with('\n') {
-            next.push('\n');
-        }
-        next.push_str(&format!(
-            "{} {} {} rw,relatime 0 0\n",
-            source, target, fs_type
-        ));
-
-        let _ = self.kernel.fs.write_file("/proc/mounts", &next);
-        format!("mounted {} on {} type {}", source, target, fs_type)
-    }
-
-    pub(super) fn cmd_umount(&mut self, args: &[&str]) -> String {
-        if args.len() != 1 {
-            return "usage: umount TARGET".into();
-        }
-
-        let target = args[0];
-        let current = self
-            .kernel
-            .fs
-            .resolve("/proc/mounts")
-            .map(|n| n.data.clone())
-            .unwrap_or_default();
-
-        let kept: Vec<&str> = current
-            .lines()
-            .filter(|line| {
-                let cols: Vec<&str> = line.split_whitespace().collect();
-                !(cols.len() >= 2 && cols[1] == target)
-            })
-            .collect();
-
-        if kept.len() == current.lines().count() {
-            return format!("umount: {}: not mounted", target);
-        }
-
-        let mut next = kept.join("\n");
-        if !next.is_empty() {
-            next.push('\n');
-        }
-        let _ = self.kernel.fs.write_file("/proc/mounts", &next);
-        format!("unmounted {}", target)
+            node.group,
+            clock::stat_time(node.accessed),
+            clock::stat_time(node.modified),
+            clock::stat_time(node.modified),
+            clock::stat_time(node.created)
+        ))
     }
 
-    pub(super) fn cmd_chmod(&mut self, args: &[&str]) -> String {
+    pub(super) fn cmd_chmod(&mut self, args: &[&str]) -> CmdOutput {
         if args.len() < 2 {
-            return "usage: chmod MODE FILE...".into();
+            return CmdOutput::usage("usage: chmod MODE FILE...");
         }
 
         let mode = args[0];
@@ -497,7 +429,7 @@ impl System {
             } else if let Some(updated) = Self::apply_symbolic_mode(mode, &next_perm) {
                 next_perm = updated;
             } else {
-                return format!("chmod: invalid mode: '{}'", mode);
+                return CmdOutput::error(1, format!("chmod: invalid mode: '{}'", mode));
             }
 
             if let Some(node_mut) = self.kernel.fs.resolve_mut(path) {
@@ -510,16 +442,12 @@ impl System {
             }
         }
 
-        if errors.is_empty() {
-            String::new()
-        } else {
-            errors.join("\n")
-        }
+        CmdOutput::collected(Vec::new(), errors)
     }
 
-    pub(super) fn cmd_chown(&mut self, args: &[&str]) -> String {
+    pub(super) fn cmd_chown(&mut self, args: &[&str]) -> CmdOutput {
         if args.len() < 2 {
-            return "usage: chown OWNER[:GROUP] FILE...".into();
+            return CmdOutput::usage("usage: chown OWNER[:GROUP] FILE...");
         }
 
         let spec = args[0];
@@ -538,7 +466,7 @@ impl System {
         } else if self.lookup_user(&users, owner_spec).is_some() {
             Some(owner_spec.to_string())
         } else {
-            return format!("chown: invalid user: '{}'", owner_spec);
+            return CmdOutput::error(1, format!("chown: invalid user: '{}'", owner_spec));
         };
 
         let resolved_group = if let Some(g) = group_spec {
@@ -547,7 +475,7 @@ impl System {
             } else if self.lookup_group_by_name(&groups, g).is_some() {
                 Some(g.to_string())
             } else {
-                return format!("chown: invalid group: '{}'", g);
+                return CmdOutput::error(1, format!("chown: invalid group: '{}'", g));
             }
         } else {
             None
@@ -585,10 +513,311 @@ impl System {
             }
         }
 
-        if errors.is_empty() {
-            String::new()
+        CmdOutput::collected(Vec::new(), errors)
+    }
+
+    /// Names of every group the current user belongs to
+    pub(super) fn current_group_names(&self) -> Vec<String> {
+        let user = self.current_user();
+        let users = self.parse_users();
+        let groups = self.parse_groups();
+        match self.lookup_user(&users, &user) {
+            Some(entry) => self
+                .groups_for_user(entry, &groups)
+                .into_iter()
+                .map(|g| g.name)
+                .collect(),
+            None => groups
+                .into_iter()
+                .filter(|g| g.name == user || g.members.iter().any(|m| m == &user))
+                .map(|g| g.name)
+                .collect(),
+        }
+    }
+
+    /// Check `want` (4 = read, 2 = write, 1 = execute/search) against the
+    /// owner, group, or other permission triplet that applies to the current user.
+    pub(super) fn has_access(&self, path: &str, want: u8) -> bool {
+        let user = self.current_user();
+        let Some(node) = self.kernel.fs.resolve(path) else {
+            return true;
+        };
+        let groups = if node.owner == user {
+            Vec::new()
+        } else {
+            self.current_group_names()
+        };
+        node.allows(&user, &groups, want)
+    }
+
+    /// Write access to `path` itself when it exists, otherwise to its parent directory
+    pub(super) fn can_write_path(&self, path: &str) -> bool {
+        if self.kernel.fs.resolve(path).is_some() {
+            return self.has_access(path, 2);
+        }
+        let norm = self.kernel.fs.normalize(path);
+        let parent = match norm.rfind('/') {
+            Some(0) | None => "/".to_string(),
+            Some(idx) => norm[..idx].to_string(),
+        };
+        self.has_access(&parent, 3)
+    }
+
+    /// Add a passwd/group entry for a login name that does not have one yet
+    pub(super) fn ensure_user_entry(&mut self, name: &str) {
+        let users = self.parse_users();
+        if self.lookup_user(&users, name).is_some() {
+            return;
+        }
+        let groups = self.parse_groups();
+        let uid = users
+            .iter()
+            .map(|u| u.uid)
+            .filter(|uid| (1000..65534).contains(uid))
+            .max()
+            .map(|u| u + 1)
+            .unwrap_or(1000);
+        let gid = match self.lookup_group_by_name(&groups, name) {
+            Some(g) => g.gid,
+            None => {
+                let gid = groups
+                    .iter()
+                    .map(|g| g.gid)
+                    .filter(|gid| (1000..65534).contains(gid))
+                    .max()
+                    .map(|g| g + 1)
+                    .unwrap_or(1000)
+                    .max(uid);
+                self.append_line("/etc/group", &format!("{}:x:{}:{}", name, gid, name));
+                gid
+            }
+        };
+        self.append_line(
+            "/etc/passwd",
+            &format!(
+                "{}:x:{}:{}:{}:{}:/bin/bash",
+                name,
+                uid,
+                gid,
+                name,
+                Self::default_home_for_user(name)
+            ),
+        );
+    }
+
+    fn append_line(&mut self, path: &str, line: &str) {
+        let mut data = self
+            .kernel
+            .fs
+            .resolve(path)
+            .map(|n| n.data.clone())
+            .unwrap_or_default();
+        if !data.is_empty() && !data.ends_with('\n') {
+            data.push('\n');
+        }
+        data.push_str(line);
+        data.push('\n');
+        if self.kernel.fs.resolve(path).is_some() {
+            let _ = self.kernel.fs.write_file(path, &data);
         } else {
-            errors.join("\n")
+            let _ = self.kernel.fs.create_file(path, &data);
+        }
+    }
+
+    fn write_groups(&mut self, groups: &[GroupEntry]) {
+        let text: String = groups
+            .iter()
+            .map(|g| format!("{}:x:{}:{}\n", g.name, g.gid, g.members.join(",")))
+            .collect();
+        let _ = self.kernel.fs.write_file("/etc/group", &text);
+    }
+
+    pub(super) fn cmd_groupadd(&mut self, args: &[&str]) -> CmdOutput {
+        let mut gid: Option<u32> = None;
+        let mut name: Option<&str> = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-g" | "--gid" => {
+                    let Some(raw) = args.get(i + 1) else {
+                        return CmdOutput::error(1, "groupadd: option requires an argument -- 'g'");
+                    };
+                    match raw.parse::<u32>() {
+                        Ok(v) => gid = Some(v),
+                        Err(_) => {
+                            return CmdOutput::error(
+                                1,
+                                format!("groupadd: invalid group ID '{}'", raw),
+                            )
+                        }
+                    }
+                    i += 1;
+                }
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(
+                        1,
+                        format!("groupadd: unrecognized option '{}'", flag),
+                    );
+                }
+                value => name = Some(value),
+            }
+            i += 1;
+        }
+        let Some(name) = name else {
+            return CmdOutput::usage("usage: groupadd [-g GID] GROUP");
+        };
+        if self.current_user() != "root" {
+            return CmdOutput::error(
+                1,
+                "groupadd: Permission denied.\ngroupadd: cannot lock /etc/group; try again later.",
+            );
+        }
+        if name.is_empty() || name.contains([':', ',', ' ', '/']) {
+            return CmdOutput::error(1, format!("groupadd: '{}' is not a valid group name", name));
+        }
+
+        let mut groups = self.parse_groups();
+        if self.lookup_group_by_name(&groups, name).is_some() {
+            return CmdOutput::error(1, format!("groupadd: group '{}' already exists", name));
+        }
+        let gid = match gid {
+            Some(g) if groups.iter().any(|e| e.gid == g) => {
+                return CmdOutput::error(1, format!("groupadd: GID '{}' already exists", g));
+            }
+            Some(g) => g,
+            None => groups
+                .iter()
+                .map(|g| g.gid)
+                .filter(|g| (1000..65534).contains(g))
+                .max()
+                .map(|g| g + 1)
+                .unwrap_or(1000),
+        };
+        groups.push(GroupEntry {
+            name: name.to_string(),
+            gid,
+            members: Vec::new(),
+        });
+        self.write_groups(&groups);
+        CmdOutput::ok(String::new())
+    }
+
+    pub(super) fn cmd_usermod(&mut self, args: &[&str]) -> CmdOutput {
+        let usage = "usage: usermod [-a] -G GROUP[,GROUP...] USER";
+        let mut append = false;
+        let mut list: Option<&str> = None;
+        let mut user: Option<&str> = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-a" | "--append" => append = true,
+                "-aG" | "-Ga" => {
+                    append = true;
+                    list = args.get(i + 1).copied();
+                    i += 1;
+                }
+                "-G" | "--groups" => {
+                    list = args.get(i + 1).copied();
+                    i += 1;
+                }
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(
+                        1,
+                        format!("usermod: unrecognized option '{}'\n{}", flag, usage),
+                    );
+                }
+                value => user = Some(value),
+            }
+            i += 1;
+        }
+        let (Some(list), Some(user)) = (list, user) else {
+            return CmdOutput::usage(usage);
+        };
+        if self.current_user() != "root" {
+            return CmdOutput::error(
+                1,
+                "usermod: Permission denied.\nusermod: cannot lock /etc/passwd; try again later.",
+            );
+        }
+        let users = self.parse_users();
+        if self.lookup_user(&users, user).is_none() {
+            return CmdOutput::error(1, format!("usermod: user '{}' does not exist", user));
+        }
+
+        let mut groups = self.parse_groups();
+        let wanted: Vec<&str> = list.split(',').filter(|g| !g.is_empty()).collect();
+        for g in &wanted {
+            if self.lookup_group_by_name(&groups, g).is_none() {
+                return CmdOutput::error(1, format!("usermod: group '{}' does not exist", g));
+            }
+        }
+        for group in groups.iter_mut() {
+            let listed = wanted.contains(&group.name.as_str());
+            let member = group.members.iter().any(|m| m == user);
+            if listed && !member {
+                group.members.push(user.to_string());
+            } else if !listed && member && !append {
+                group.members.retain(|m| m != user);
+            }
+        }
+        self.write_groups(&groups);
+        CmdOutput::ok(String::new())
+    }
+
+    pub(super) fn cmd_chgrp(&mut self, args: &[&str]) -> CmdOutput {
+        let mut recursive = false;
+        let mut rest: Vec<&str> = Vec::new();
+        for arg in args {
+            match *arg {
+                "-R" | "--recursive" => recursive = true,
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(1, format!("chgrp: unrecognized option '{}'", flag));
+                }
+                value => rest.push(value),
+            }
+        }
+        if rest.len() < 2 {
+            return CmdOutput::usage("usage: chgrp [-R] GROUP FILE...");
+        }
+        let group = rest[0];
+        let groups = self.parse_groups();
+        if self.lookup_group_by_name(&groups, group).is_none() {
+            return CmdOutput::error(1, format!("chgrp: invalid group: '{}'", group));
+        }
+
+        let user = self.current_user();
+        let is_root = user == "root";
+        let member = is_root || self.current_group_names().iter().any(|g| g == group);
+
+        let mut errors = Vec::new();
+        for path in &rest[1..] {
+            let Some(node) = self.kernel.fs.resolve(path) else {
+                errors.push(format!(
+                    "chgrp: cannot access '{}': No such file or directory",
+                    path
+                ));
+                continue;
+            };
+            if !is_root && (node.owner != user || !member) {
+                errors.push(format!(
+                    "chgrp: changing group of '{}': Operation not permitted",
+                    path
+                ));
+                continue;
+            }
+            let mut targets = Vec::new();
+            if recursive {
+                let norm = self.kernel.fs.normalize(path);
+                self.collect_tree_paths(&norm, &mut targets);
+            } else {
+                targets.push((path.to_string(), false));
+            }
+            for (target, _) in targets {
+                if let Some(node_mut) = self.kernel.fs.resolve_mut(&target) {
+                    node_mut.group = group.to_string();
+                }
+            }
         }
+        CmdOutput::collected(Vec::new(), errors)
     }
 }
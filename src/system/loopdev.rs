@@ -0,0 +1,913 @@
+//! Disk images and loop devices: `dd` writes sized files, `mkfs.ext4`
+//! formats them and `losetup` or `mount -o loop` attaches them so their
+//! filesystem mounts like a disk. An image of zeros stores only its size;
+//! a formatted one stores its filesystem tree, written back on `umount`
+use super::{bytes_to_data, System};
+use crate::clock;
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+
+const IMAGE_MAGIC: &str = "KP_IMG1 ";
+/// /dev/loop0 to /dev/loop7
+const LOOP_DEVICES: usize = 8;
+/// Largest image `dd` writes, the size of the root filesystem
+const MAX_IMAGE: usize = 1 << 30;
+/// Most bytes `dd` copies that are not zeros; those are stored as they are
+const MAX_DENSE: usize = 1 << 20;
+/// Smallest image `mkfs` formats
+const MIN_FS: usize = 64 * 1024;
+const FS_KINDS: [&str; 3] = ["ext2", "ext3", "ext4"];
+
+const DD_USAGE: &str = "usage: dd if=FILE [of=FILE] [bs=BYTES] [count=N] [skip=N] [status=none]";
+const MKFS_USAGE: &str = "usage: mkfs.ext4 [-F] [-q] [-L LABEL] DEVICE";
+const LOSETUP_USAGE: &str =
+    "usage: losetup [-a|-l] | -f [--show] [FILE] | DEVICE FILE | -d DEVICE... | -D";
+
+/// Whether file data is a disk image, whose size is its header's rather
+/// than the length of what is stored
+pub(super) fn is_image(data: &str) -> bool {
+    data.starts_with(IMAGE_MAGIC)
+}
+
+/// `KP_IMG1 SIZE` on the first line; a formatted image follows it with
+/// `TYPE UUID LABEL` and the filesystem tree as JSON
+struct DiskImage {
+    size: usize,
+    fs: Option<Formatted>,
+}
+
+struct Formatted {
+    kind: String,
+    uuid: String,
+    label: String,
+    tree: Inode,
+}
+
+impl DiskImage {
+    fn parse(data: &str) -> Option<Self> {
+        let mut parts = data.strip_prefix(IMAGE_MAGIC)?.splitn(3, '\n');
+        let size = parts.next()?.trim().parse().ok()?;
+        let fs = match (parts.next(), parts.next()) {
+            (Some(header), Some(json)) => {
+                let mut fields = header.splitn(3, ' ');
+                match (fields.next(), fields.next()) {
+                    (Some(kind), Some(uuid)) => {
+                        serde_json::from_str(json).ok().map(|tree| Formatted {
+                            kind: kind.to_string(),
+                            uuid: uuid.to_string(),
+                            label: fields.next().unwrap_or("").to_string(),
+                            tree,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        Some(DiskImage { size, fs })
+    }
+
+    fn encode(&self) -> String {
+        match &self.fs {
+            None => format!("{}{}\n", IMAGE_MAGIC, self.size),
+            Some(fs) => format!(
+                "{}{}\n{} {} {}\n{}",
+                IMAGE_MAGIC,
+                self.size,
+                fs.kind,
+                fs.uuid,
+                fs.label,
+                serde_json::to_string(&fs.tree).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// A loop device and the file behind it
+pub(super) struct LoopDevice {
+    name: String,
+    file: String,
+    /// Attached by `mount -o loop`, so `umount` detaches it
+    autoclear: bool,
+}
+
+/// xorshift32 seeded from the browser's RNG (or the clock in tests)
+struct Rng(u32);
+
+impl Rng {
+    fn new() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let seed = (js_sys::Math::random() * f64::from(u32::MAX)) as u32;
+        #[cfg(not(target_arch = "wasm32"))]
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(1, |d| d.subsec_nanos());
+        Rng(seed | 1)
+    }
+
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
+/// What `dd` read
+enum Data {
+    Zeros(usize),
+    Bytes(Vec<u8>),
+    /// A whole image copied as it is stored
+    Image(String, usize),
+}
+
+impl Data {
+    fn len(&self) -> usize {
+        match self {
+            Data::Zeros(n) | Data::Image(_, n) => *n,
+            Data::Bytes(bytes) => bytes.len(),
+        }
+    }
+}
+
+/// A `bs=` or `count=` number with an optional c, w, b, K, M or G suffix
+/// (powers of 1024) or kB, MB or GB (powers of 1000)
+fn parse_size(text: &str) -> Option<usize> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let unit = match unit {
+        "" | "c" => 1,
+        "w" => 2,
+        "b" => 512,
+        "K" | "k" | "KiB" => 1 << 10,
+        "M" | "MiB" => 1 << 20,
+        "G" | "GiB" => 1 << 30,
+        "kB" | "KB" => 1000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// `8.4 MB` or `942 MB`, the way dd rounds
+fn scaled(value: f64, step: f64, units: &[&str]) -> String {
+    let mut value = value;
+    let mut unit = 0;
+    while value >= step && unit + 1 < units.len() {
+        value /= step;
+        unit += 1;
+    }
+    match value < 10.0 {
+        true => format!("{:.1} {}", value, units[unit]),
+        false => format!("{:.0} {}", value, units[unit]),
+    }
+}
+
+const DECIMAL: [&str; 4] = ["B", "kB", "MB", "GB"];
+const BINARY: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+/// The records and transfer lines dd ends with
+fn dd_report(bytes: usize, bs: usize, ms: f64) -> String {
+    let records = format!("{}+{}", bytes / bs, usize::from(!bytes.is_multiple_of(bs)));
+    let copied = match bytes {
+        0..1000 => format!("{} bytes copied", bytes),
+        1000..1024 => format!(
+            "{} bytes ({}) copied",
+            bytes,
+            scaled(bytes as f64, 1000.0, &DECIMAL)
+        ),
+        _ => format!(
+            "{} bytes ({}, {}) copied",
+            bytes,
+            scaled(bytes as f64, 1000.0, &DECIMAL),
+            scaled(bytes as f64, 1024.0, &BINARY)
+        ),
+    };
+    let secs = (ms / 1000.0).max(0.000_001);
+    format!(
+        "{} records in\n{} records out\n{}, {:.6} s, {}/s",
+        records,
+        records,
+        copied,
+        secs,
+        scaled(bytes as f64 / secs, 1000.0, &DECIMAL)
+    )
+}
+
+impl System {
+    /// The file behind `path`: a loop device's backing file, or `path`
+    fn backing_file(&self, path: &str) -> String {
+        let norm = self.kernel.fs.normalize(path);
+        self.loops
+            .iter()
+            .find(|l| l.name == norm)
+            .map_or(norm, |l| l.file.clone())
+    }
+
+    fn read_image(&self, path: &str) -> Option<DiskImage> {
+        let node = self.kernel.fs.resolve(path)?;
+        DiskImage::parse(&node.data)
+    }
+
+    fn write_image(&mut self, path: &str, image: &DiskImage) -> Result<(), String> {
+        let data = image.encode();
+        let written = match self.kernel.fs.resolve(path) {
+            Some(_) => self.kernel.fs.write_file(path, &data),
+            None => self.kernel.fs.create_file(path, &data),
+        };
+        written.map_err(|e| e.to_string())?;
+        if let Some(node) = self.kernel.fs.resolve_mut(path) {
+            node.size = image.size;
+        }
+        Ok(())
+    }
+
+    /// Attach `file` to `device`, or the first free loop device, and return
+    /// the device's name
+    fn loop_attach(
+        &mut self,
+        file: &str,
+        device: Option<&str>,
+        autoclear: bool,
+    ) -> Result<String, String> {
+        let file = self.kernel.fs.normalize(file);
+        match self.kernel.fs.resolve(&file) {
+            Some(node) if !node.is_dir => {}
+            Some(_) => return Err(format!("{}: Is a directory", file)),
+            None => return Err(format!("{}: No such file or directory", file)),
+        }
+        let name = match device {
+            Some(device) => device.to_string(),
+            None => self
+                .free_loop()
+                .ok_or("could not find any free loop device")?,
+        };
+        if let Some(dev) = self.kernel.fs.resolve_mut("/dev") {
+            let node_name = name.trim_start_matches("/dev/");
+            let mut node = Inode::file(node_name, "");
+            node.permissions = "brw-rw----".into();
+            node.group = "disk".into();
+            dev.children.insert(node_name.to_string(), node);
+        }
+        self.loops.push(LoopDevice {
+            name: name.clone(),
+            file,
+            autoclear,
+        });
+        Ok(name)
+    }
+
+    fn free_loop(&self) -> Option<String> {
+        (0..LOOP_DEVICES)
+            .map(|n| format!("/dev/loop{}", n))
+            .find(|name| !self.loops.iter().any(|l| &l.name == name))
+    }
+
+    fn loop_detach(&mut self, name: &str) -> Result<(), String> {
+        let idx = self
+            .loops
+            .iter()
+            .position(|l| l.name == name)
+            .ok_or("No such device or address")?;
+        if self.kernel.fs.mounts().iter().any(|m| m.source == name) {
+            return Err("Device or resource busy".into());
+        }
+        self.loops.remove(idx);
+        if let Some(dev) = self.kernel.fs.resolve_mut("/dev") {
+            dev.children.remove(name.trim_start_matches("/dev/"));
+        }
+        Ok(())
+    }
+
+    /// `mount` of a loop device or an image file; `None` when `source` is
+    /// neither, so the other kinds of mount get their turn
+    pub(super) fn mount_image(
+        &mut self,
+        source: &str,
+        target: &str,
+        fs_type: Option<&str>,
+        options: &[String],
+    ) -> Option<CmdOutput> {
+        let norm = self.kernel.fs.normalize(source);
+        let (device, attached) = match self.loops.iter().find(|l| l.name == norm) {
+            Some(dev) => (dev.name.clone(), false),
+            None if norm.starts_with("/dev/loop") => {
+                return Some(CmdOutput::error(
+                    1,
+                    format!(
+                        "mount: {}: special device {} does not exist.",
+                        target, source
+                    ),
+                ))
+            }
+            None => {
+                let node = self.kernel.fs.resolve(&norm)?;
+                if node.is_dir || !is_image(&node.data) {
+                    return None;
+                }
+                match self.loop_attach(&norm, None, true) {
+                    Ok(device) => (device, true),
+                    Err(e) => {
+                        return Some(CmdOutput::error(
+                            1,
+                            format!(
+                                "mount: {}: failed to setup loop device for {}: {}",
+                                target, source, e
+                            ),
+                        ))
+                    }
+                }
+            }
+        };
+        let file = self.backing_file(&device);
+        let formatted = self
+            .read_image(&file)
+            .and_then(|image| image.fs)
+            .filter(|fs| fs_type.is_none_or(|t| t == "auto" || t == fs.kind));
+        let Some(fs) = formatted else {
+            if attached {
+                let _ = self.loop_detach(&device);
+            }
+            return Some(CmdOutput::error(
+                1,
+                format!(
+                    "mount: {}: wrong fs type, bad option, bad superblock on {}, missing codepage or helper program, or other error.",
+                    target, device
+                ),
+            ));
+        };
+        let mut options: Vec<String> = options.iter().filter(|o| *o != "loop").cloned().collect();
+        if !options.iter().any(|o| o == "ro" || o == "rw") {
+            options.insert(0, "rw".into());
+        }
+        if !options.iter().any(|o| o == "relatime") {
+            options.push("relatime".into());
+        }
+        match self
+            .kernel
+            .fs
+            .mount(&device, target, &fs.kind, &options.join(","), fs.tree)
+        {
+            Ok(()) => {
+                self.refresh_proc_mounts();
+                Some(CmdOutput::ok(format!(
+                    "mounted {} on {} type {}",
+                    device, target, fs.kind
+                )))
+            }
+            Err(e) => {
+                if attached {
+                    let _ = self.loop_detach(&device);
+                }
+                Some(CmdOutput::error(1, format!("mount: {}: {}", target, e)))
+            }
+        }
+    }
+
+    /// After `umount` of a loop device: write what was mounted back into
+    /// the image, and detach the device if `mount -o loop` attached it
+    pub(super) fn release_loop(&mut self, device: &str, mut tree: Inode) {
+        let Some(dev) = self.loops.iter().find(|l| l.name == device) else {
+            return;
+        };
+        let (file, autoclear) = (dev.file.clone(), dev.autoclear);
+        if let Some(mut image) = self.read_image(&file) {
+            if let Some(fs) = image.fs.as_mut() {
+                tree.name = "/".into();
+                fs.tree = tree;
+                let _ = self.write_image(&file, &image);
+            }
+        }
+        if autoclear {
+            let _ = self.loop_detach(device);
+        }
+    }
+
+    /// `dd if=FILE [of=FILE] [bs=BYTES] [count=N] [skip=N] [status=none]`.
+    /// Zeros become a sparse image; /dev/urandom gives real random bytes
+    pub(super) fn cmd_dd(&mut self, args: &[&str]) -> CmdOutput {
+        let mut input = None;
+        let mut output = None;
+        let mut bs = 512;
+        let mut count = None;
+        let mut skip = 0;
+        let mut quiet = false;
+        for arg in args {
+            let Some((key, value)) = arg.split_once('=') else {
+                return CmdOutput::error(1, format!("dd: unrecognized operand '{}'", arg));
+            };
+            let number =
+                || parse_size(value).ok_or_else(|| format!("dd: invalid number: '{}'", value));
+            let parsed = match key {
+                "if" => {
+                    input = Some(value);
+                    Ok(())
+                }
+                "of" => {
+                    output = Some(value);
+                    Ok(())
+                }
+                "bs" => number().map(|n| bs = n.max(1)),
+                "count" => number().map(|n| count = Some(n)),
+                "skip" => number().map(|n| skip = n),
+                "status" => match value {
+                    "none" => {
+                        quiet = true;
+                        Ok(())
+                    }
+                    "progress" | "noxfer" => Ok(()),
+                    _ => Err(format!("dd: invalid status level: '{}'", value)),
+                },
+                _ => Err(format!("dd: unrecognized operand '{}'", arg)),
+            };
+            if let Err(e) = parsed {
+                return CmdOutput::error(1, e);
+            }
+        }
+        let Some(input) = input else {
+            return CmdOutput::error(1, DD_USAGE);
+        };
+
+        let start = clock::now_ms();
+        let limit = count.map(|c| c.saturating_mul(bs));
+        let offset = skip.saturating_mul(bs);
+        let source = self.backing_file(input);
+        let data = match source.as_str() {
+            "/dev/zero" | "/dev/urandom" | "/dev/random" => {
+                let Some(limit) = limit else {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "dd: {}: count= is needed to read a device that never ends",
+                            input
+                        ),
+                    );
+                };
+                if source == "/dev/zero" {
+                    Data::Zeros(limit)
+                } else if limit > MAX_DENSE {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "dd: error writing '{}': File too large",
+                            output.unwrap_or("standard output")
+                        ),
+                    );
+                } else {
+                    let mut rng = Rng::new();
+                    Data::Bytes((0..limit).map(|_| rng.next() as u8).collect())
+                }
+            }
+            "/dev/null" => Data::Bytes(Vec::new()),
+            path if !self.has_access(path, 4) => {
+                return CmdOutput::error(
+                    1,
+                    format!("dd: failed to open '{}': Permission denied", input),
+                )
+            }
+            path => match self.kernel.fs.resolve(path) {
+                Some(node) if is_image(&node.data) => {
+                    let rest = node.size.saturating_sub(offset);
+                    let len = limit.map_or(rest, |l| l.min(rest));
+                    // Anything short of the whole image loses the filesystem
+                    match offset == 0 && len == node.size {
+                        true => Data::Image(node.data.clone(), node.size),
+                        false => Data::Zeros(len),
+                    }
+                }
+                _ => match self.read_file_bytes(path) {
+                    Ok(bytes) => {
+                        let rest = bytes.get(offset..).unwrap_or_default();
+                        let len = limit.map_or(rest.len(), |l| l.min(rest.len()));
+                        Data::Bytes(rest[..len].to_vec())
+                    }
+                    Err(_) => {
+                        return CmdOutput::error(
+                            1,
+                            format!("dd: failed to open '{}': No such file or directory", input),
+                        )
+                    }
+                },
+            },
+        };
+
+        let copied = data.len();
+        let mut stdout = String::new();
+        match output.map(|o| self.backing_file(o)) {
+            None => {
+                stdout = match data {
+                    Data::Zeros(n) | Data::Image(_, n) => "\0".repeat(n.min(MAX_DENSE)),
+                    Data::Bytes(bytes) => bytes_to_data(bytes),
+                }
+            }
+            Some(path) if path == "/dev/null" => {}
+            Some(path) if !self.can_write_path(&path) => {
+                return CmdOutput::error(
+                    1,
+                    format!("dd: failed to open '{}': Permission denied", path),
+                )
+            }
+            Some(path) => {
+                let written = match data {
+                    Data::Zeros(n) if n > MAX_IMAGE => Err("No space left on device".to_string()),
+                    Data::Zeros(size) => self.write_image(&path, &DiskImage { size, fs: None }),
+                    Data::Image(raw, size) => {
+                        let image = DiskImage::parse(&raw).unwrap_or(DiskImage { size, fs: None });
+                        self.write_image(&path, &image)
+                    }
+                    Data::Bytes(bytes) => self.write_file_bytes(&path, &bytes),
+                };
+                if let Err(e) = written {
+                    return CmdOutput::error(1, format!("dd: failed to open '{}': {}", path, e));
+                }
+            }
+        }
+        CmdOutput {
+            stdout,
+            stderr: match quiet {
+                true => String::new(),
+                false => dd_report(copied, bs, clock::now_ms() - start),
+            },
+            status: 0,
+        }
+    }
+
+    /// `mkfs.ext4 [-F] [-q] [-L LABEL] DEVICE`, and `mkfs -t TYPE` for
+    /// ext2 and ext3 as well. DEVICE is an image file or a loop device
+    pub(super) fn cmd_mkfs(&mut self, cmd: &str, args: &[&str]) -> CmdOutput {
+        let mut kind = cmd.strip_prefix("mkfs.").unwrap_or("ext4").to_string();
+        let mut force = false;
+        let mut quiet = false;
+        let mut label = String::new();
+        let mut device = None;
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-F" => force = true,
+                "-q" => quiet = true,
+                "-L" | "-t" => {
+                    let Some(value) = rest.next() else {
+                        return CmdOutput::error(1, MKFS_USAGE);
+                    };
+                    match *arg {
+                        "-L" => label = value.to_string(),
+                        _ => kind = value.to_string(),
+                    }
+                }
+                flag if flag.starts_with('-') => return CmdOutput::error(1, MKFS_USAGE),
+                path => device = Some(path),
+            }
+        }
+        let Some(device) = device else {
+            return CmdOutput::error(1, MKFS_USAGE);
+        };
+        if !FS_KINDS.contains(&kind.as_str()) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "mkfs: failed to execute mkfs.{}: No such file or directory",
+                    kind
+                ),
+            );
+        }
+        let name = format!("mkfs.{}", kind);
+        let norm = self.kernel.fs.normalize(device);
+        if norm.starts_with("/dev/loop") && !self.loops.iter().any(|l| l.name == norm) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "{}: The file {} does not exist and no size was specified.",
+                    name, device
+                ),
+            );
+        }
+        if self.kernel.fs.mounts().iter().any(|m| m.source == norm) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "{}: {} is mounted; will not make a filesystem here!",
+                    name, device
+                ),
+            );
+        }
+        let file = self.backing_file(device);
+        let Some(node) = self.kernel.fs.resolve(&file).filter(|n| !n.is_dir) else {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "{}: The file {} does not exist and no size was specified.",
+                    name, device
+                ),
+            );
+        };
+        let size = node.size;
+        let existing = match DiskImage::parse(&node.data) {
+            Some(image) => image.fs.map(|fs| format!("a {} file system", fs.kind)),
+            None => Some("data".to_string()),
+        };
+        if let (Some(what), false) = (existing, force) {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "{}: {} contains {}; use -F to overwrite it",
+                    name, device, what
+                ),
+            );
+        }
+        if size < MIN_FS {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "{}: {}: Filesystem too small, at least {} KiB is needed",
+                    name,
+                    device,
+                    MIN_FS / 1024
+                ),
+            );
+        }
+
+        let mut rng = Rng::new();
+        let hex: Vec<String> = (0..4).map(|_| format!("{:08x}", rng.next())).collect();
+        let uuid = format!(
+            "{}-{}-4{}-a{}-{}{}",
+            hex[0],
+            &hex[1][..4],
+            &hex[1][5..],
+            &hex[2][..3],
+            &hex[2][3..7],
+            hex[3]
+        );
+        let mut tree = Inode::dir("/");
+        tree.owner = self.current_user();
+        tree.group = tree.owner.clone();
+        let mut lost = Inode::dir("lost+found");
+        lost.permissions = "drwx------".into();
+        tree.children.insert("lost+found".into(), lost);
+        let image = DiskImage {
+            size,
+            fs: Some(Formatted {
+                kind: kind.clone(),
+                uuid: uuid.clone(),
+                label: label.clone(),
+                tree,
+            }),
+        };
+        if let Err(e) = self.write_image(&file, &image) {
+            return CmdOutput::error(1, format!("{}: {}: {}", name, device, e));
+        }
+        if quiet {
+            return CmdOutput::default();
+        }
+
+        let block = if size < 512 << 20 { 1024 } else { 4096 };
+        let blocks = size / block;
+        let mut out = vec![
+            "mke2fs 1.47.0 (5-Feb-2023)".to_string(),
+            "Discarding device blocks: done".to_string(),
+            format!(
+                "Creating filesystem with {} {}k blocks and {} inodes",
+                blocks,
+                block / 1024,
+                (size / 4096).max(16)
+            ),
+            format!("Filesystem UUID: {}", uuid),
+        ];
+        if !label.is_empty() {
+            out.push(format!("Filesystem volume name: {}", label));
+        }
+        out.push(String::new());
+        out.push("Allocating group tables: done".to_string());
+        out.push("Writing inode tables: done".to_string());
+        if kind != "ext2" {
+            out.push(format!(
+                "Creating journal ({} blocks): done",
+                (blocks / 32).clamp(1024, 32768).min(blocks / 4)
+            ));
+        }
+        out.push("Writing superblocks and filesystem accounting information: done".to_string());
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// `losetup`: list, attach and detach loop devices
+    pub(super) fn cmd_losetup(&mut self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] | ["-l"] | ["--list"] => {
+                if self.loops.is_empty() {
+                    return CmdOutput::default();
+                }
+                let mut out =
+                    vec!["NAME       SIZELIMIT OFFSET AUTOCLEAR RO BACK-FILE".to_string()];
+                for dev in &self.loops {
+                    out.push(format!(
+                        "{:<10} {:>9} {:>6} {:>9} {:>2} {}",
+                        dev.name,
+                        0,
+                        0,
+                        u8::from(dev.autoclear),
+                        0,
+                        dev.file
+                    ));
+                }
+                CmdOutput::ok(out.join("\n"))
+            }
+            ["-a"] | ["--all"] => CmdOutput::ok(
+                self.loops
+                    .iter()
+                    .map(|dev| format!("{}: []: ({})", dev.name, dev.file))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ["-f"] | ["--find"] => match self.free_loop() {
+                Some(name) => CmdOutput::ok(name),
+                None => CmdOutput::error(1, "losetup: cannot find an unused loop device"),
+            },
+            ["-D"] | ["--detach-all"] => {
+                let names: Vec<String> = self.loops.iter().map(|l| l.name.clone()).collect();
+                let errors: Vec<String> = names
+                    .iter()
+                    .filter_map(|name| {
+                        self.loop_detach(name)
+                            .err()
+                            .map(|e| format!("losetup: {}: detach failed: {}", name, e))
+                    })
+                    .collect();
+                match errors.is_empty() {
+                    true => CmdOutput::default(),
+                    false => CmdOutput::error(1, errors.join("\n")),
+                }
+            }
+            ["-d" | "--detach", devices @ ..] if !devices.is_empty() => {
+                let errors: Vec<String> = devices
+                    .iter()
+                    .filter_map(|name| {
+                        let norm = self.kernel.fs.normalize(name);
+                        self.loop_detach(&norm)
+                            .err()
+                            .map(|e| format!("losetup: {}: detach failed: {}", name, e))
+                    })
+                    .collect();
+                match errors.is_empty() {
+                    true => CmdOutput::default(),
+                    false => CmdOutput::error(1, errors.join("\n")),
+                }
+            }
+            _ => {
+                let show = args.contains(&"--show");
+                let operands: Vec<&str> = args
+                    .iter()
+                    .copied()
+                    .filter(|a| !matches!(*a, "--show" | "-f" | "--find"))
+                    .collect();
+                let find = args.iter().any(|a| matches!(*a, "-f" | "--find"));
+                let (wanted, file) = match (find, operands.as_slice()) {
+                    (true, [file]) => (None, *file),
+                    (false, [device, file]) if device.starts_with("/dev/loop") => {
+                        (Some(*device), *file)
+                    }
+                    _ => return CmdOutput::error(1, LOSETUP_USAGE),
+                };
+                if let Some(device) = wanted {
+                    let valid = device
+                        .strip_prefix("/dev/loop")
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .is_some_and(|n| n < LOOP_DEVICES);
+                    if !valid {
+                        return CmdOutput::error(
+                            1,
+                            format!(
+                                "losetup: {}: failed to set up loop device: No such device",
+                                device
+                            ),
+                        );
+                    }
+                    if self.loops.iter().any(|l| l.name == device) {
+                        return CmdOutput::error(
+                            1,
+                            format!("losetup: {}: failed to set up loop device: Device or resource busy", file),
+                        );
+                    }
+                }
+                match self.loop_attach(file, wanted, false) {
+                    Ok(name) if show => CmdOutput::ok(name),
+                    Ok(_) => CmdOutput::default(),
+                    Err(e) => CmdOutput::error(
+                        1,
+                        format!("losetup: {}: failed to set up loop device: {}", file, e),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dd_mkfs_and_loop_mount_round_trip() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_dir("/home/user/disk").unwrap();
+        sys.kernel.fs.cwd = "/home/user".into();
+
+        let dd = sys.cmd_dd(&["if=/dev/zero", "of=disk.img", "bs=1M", "count=8"]);
+        assert!(dd.stderr.starts_with(
+            "8+0 records in\n8+0 records out\n8388608 bytes (8.4 MB, 8.0 MiB) copied"
+        ));
+        assert_eq!(sys.kernel.fs.resolve("disk.img").unwrap().size, 8 << 20);
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert!(sys
+            .exec("mount -o loop disk.img disk")
+            .contains("wrong fs type"));
+        assert!(sys.loops.is_empty());
+
+        let mkfs = sys.cmd_mkfs("mkfs.ext4", &["-L", "scratch", "disk.img"]);
+        assert!(mkfs
+            .stdout
+            .contains("Creating filesystem with 8192 1k blocks and 2048 inodes"));
+        assert!(sys
+            .cmd_mkfs("mkfs.ext4", &["disk.img"])
+            .stderr
+            .contains("use -F"));
+
+        assert!(sys
+            .exec("mount -o loop disk.img disk")
+            .contains("/dev/loop0"));
+        assert!(sys
+            .kernel
+            .fs
+            .resolve("/home/user/disk/lost+found")
+            .is_some());
+        assert!(sys
+            .exec("losetup -a")
+            .contains("/dev/loop0: []: (/home/user/disk.img)"));
+        sys.kernel
+            .fs
+            .create_file("/home/user/disk/hello.txt", "kept in the image")
+            .unwrap();
+        assert!(sys
+            .cmd_losetup(&["-d", "/dev/loop0"])
+            .stderr
+            .contains("busy"));
+        sys.exec("umount disk");
+        assert!(sys.loops.is_empty());
+        assert!(sys.kernel.fs.resolve("/home/user/disk/hello.txt").is_none());
+
+        assert_eq!(
+            sys.cmd_losetup(&["-f", "--show", "disk.img"]).stdout,
+            "/dev/loop0"
+        );
+        assert!(sys.exec("mount /dev/loop0 disk").contains("type ext4"));
+        let kept = sys.kernel.fs.resolve("/home/user/disk/hello.txt");
+        assert_eq!(kept.unwrap().data, "kept in the image");
+        sys.exec("umount disk");
+        assert_eq!(sys.loops.len(), 1);
+        assert_eq!(sys.cmd_losetup(&["-D"]).status, 0);
+        assert!(sys.kernel.fs.resolve("/dev/loop0").is_none());
+
+        sys.cmd_dd(&["if=disk.img", "of=copy.img", "bs=4M"]);
+        assert!(sys
+            .exec("mount -o loop copy.img disk")
+            .contains("type ext4"));
+        sys.exec("umount disk");
+
+        let random = sys.cmd_dd(&[
+            "if=/dev/urandom",
+            "of=noise",
+            "bs=100",
+            "count=3",
+            "status=none",
+        ]);
+        assert!(random.stderr.is_empty());
+        assert_eq!(sys.read_file_bytes("noise").unwrap().len(), 300);
+        assert_eq!(parse_size("2K"), Some(2048));
+        assert_eq!(parse_size("1kB"), Some(1000));
+        assert_eq!(parse_size("3x"), None);
+    }
+
+    #[test]
+    fn dd_respects_file_permissions() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let read = sys.exec("dd if=/etc/shadow of=/tmp/x");
+        assert_eq!(read, "dd: failed to open '/etc/shadow': Permission denied");
+        assert_eq!(sys.last_exit_code(), 1);
+        assert!(sys.kernel.fs.resolve("/tmp/x").is_none());
+
+        let passwd = sys.kernel.fs.resolve("/etc/passwd").unwrap().data.clone();
+        let write = sys.exec("dd if=/dev/zero of=/etc/passwd bs=1 count=3");
+        assert_eq!(write, "dd: failed to open '/etc/passwd': Permission denied");
+        assert_eq!(sys.last_exit_code(), 1);
+        assert_eq!(sys.kernel.fs.resolve("/etc/passwd").unwrap().data, passwd);
+        assert!(sys
+            .exec("dd if=/dev/zero of=/etc/new.img bs=1 count=3")
+            .ends_with("Permission denied"));
+        assert!(sys.kernel.fs.resolve("/etc/new.img").is_none());
+    }
+}
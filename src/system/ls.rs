@@ -0,0 +1,403 @@
+use super::{human_size, System};
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+use crate::{ansi, clock};
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Name,
+    Time,
+    Size,
+}
+
+struct LsOptions {
+    all: bool,
+    almost_all: bool,
+    long: bool,
+    human: bool,
+    inode: bool,
+    recursive: bool,
+    reverse: bool,
+    one_per_line: bool,
+    sort: SortKey,
+}
+
+impl LsOptions {
+    fn parse<'a>(args: &[&'a str]) -> Result<(Self, Vec<&'a str>), String> {
+        let mut opts = LsOptions {
+            all: false,
+            almost_all: false,
+            long: false,
+            human: false,
+            inode: false,
+            recursive: false,
+            reverse: false,
+            one_per_line: false,
+            sort: SortKey::Name,
+        };
+        let mut paths = Vec::new();
+        for arg in args {
+            match *arg {
+                "--all" => opts.all = true,
+                "--almost-all" => opts.almost_all = true,
+                "--human-readable" => opts.human = true,
+                "--inode" => opts.inode = true,
+                "--recursive" => opts.recursive = true,
+                "--reverse" => opts.reverse = true,
+                // --color=auto and friends from aliases
+                long if long.starts_with("--") => {}
+                flags if flags.starts_with('-') && flags.len() > 1 => {
+                    for c in flags[1..].chars() {
+                        match c {
+                            'a' => opts.all = true,
+                            'A' => opts.almost_all = true,
+                            'l' => opts.long = true,
+                            'h' => opts.human = true,
+                            'i' => opts.inode = true,
+                            'R' => opts.recursive = true,
+                            'r' => opts.reverse = true,
+                            't' => opts.sort = SortKey::Time,
+                            'S' => opts.sort = SortKey::Size,
+                            '1' => opts.one_per_line = true,
+                            _ => return Err(format!("ls: invalid option -- '{}'", c)),
+                        }
+                    }
+                }
+                path => paths.push(path),
+            }
+        }
+        Ok((opts, paths))
+    }
+
+    fn shows(&self, name: &str) -> bool {
+        self.all || self.almost_all || !name.starts_with('.')
+    }
+
+    fn sort(&self, entries: &mut [(String, &Inode)]) {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        match self.sort {
+            SortKey::Name => {}
+            SortKey::Time => entries.sort_by_key(|e| std::cmp::Reverse(e.1.modified)),
+            SortKey::Size => entries.sort_by_key(|e| std::cmp::Reverse(e.1.size)),
+        }
+        if self.reverse {
+            entries.reverse();
+        }
+    }
+}
+
+fn colored(name: &str, node: &Inode, color: bool) -> String {
+    if !color {
+        name.to_string()
+    } else if node.is_dir {
+        ansi::paint(name, ansi::DIR)
+    } else if node.is_executable {
+        ansi::paint(name, ansi::EXEC)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Lay `items` (text, visible width) out in as few rows as fit in `width`
+/// columns, filling each column top to bottom like ls does
+fn columns(items: &[(String, usize)], width: usize) -> String {
+    let n = items.len();
+    let mut rows = 1;
+    let widths = loop {
+        let cols = n.div_ceil(rows);
+        let widths: Vec<usize> = (0..cols)
+            .map(|c| {
+                items[c * rows..((c + 1) * rows).min(n)]
+                    .iter()
+                    .map(|(_, w)| *w)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        if rows >= n || widths.iter().sum::<usize>() + 2 * (cols - 1) <= width {
+            break widths;
+        }
+        rows += 1;
+    };
+    let mut lines = Vec::new();
+    for r in 0..rows {
+        let mut line = String::new();
+        for (c, col_width) in widths.iter().enumerate() {
+            let Some((text, w)) = items.get(c * rows + r) else {
+                break;
+            };
+            line.push_str(text);
+            if (c + 1) * rows + r < n {
+                line.push_str(&" ".repeat(col_width - w + 2));
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+impl System {
+    /// `ls [-aAlhiRrtS1] [PATH]...`. Short listings fill as many columns as
+    /// `$COLUMNS` allows; piped output is one name per line without colour
+    pub(super) fn cmd_ls(&mut self, args: &[&str]) -> CmdOutput {
+        let (opts, mut paths) = match LsOptions::parse(args) {
+            Ok(parsed) => parsed,
+            Err(e) => return CmdOutput::error(1, e),
+        };
+        if opts.inode {
+            self.kernel.fs.number_inodes();
+        }
+        if paths.is_empty() {
+            paths.push(".");
+        }
+
+        let mut errors = Vec::new();
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        for path in &paths {
+            match self.kernel.fs.resolve(path) {
+                Some(node) if node.is_dir => dirs.push(*path),
+                Some(node) => files.push((path.to_string(), node)),
+                None => errors.push(format!(
+                    "ls: cannot access '{}': No such file or directory",
+                    path
+                )),
+            }
+        }
+        opts.sort(&mut files);
+
+        let mut blocks = Vec::new();
+        if !files.is_empty() {
+            blocks.push(self.ls_format(&files, &opts));
+        }
+        let headers = paths.len() > 1 || opts.recursive;
+        for dir in dirs {
+            self.ls_dir(dir, headers, &opts, &mut blocks, &mut errors);
+        }
+
+        let out = if blocks.is_empty() {
+            Vec::new()
+        } else {
+            vec![blocks.join("\n\n")]
+        };
+        CmdOutput::collected(out, errors)
+    }
+
+    fn ls_dir(
+        &self,
+        path: &str,
+        header: bool,
+        opts: &LsOptions,
+        blocks: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) {
+        let Some(node) = self.kernel.fs.resolve(path) else {
+            return;
+        };
+        if !self.has_access(path, 4) {
+            errors.push(format!(
+                "ls: cannot open directory '{}': Permission denied",
+                path
+            ));
+            return;
+        }
+        let mut entries: Vec<(String, &Inode)> = node
+            .children
+            .iter()
+            .filter(|(name, _)| opts.shows(name))
+            .map(|(name, child)| (name.clone(), child))
+            .collect();
+        if opts.all {
+            let parent = self
+                .kernel
+                .fs
+                .resolve(&format!("{}/..", path))
+                .unwrap_or(node);
+            entries.push((".".to_string(), node));
+            entries.push(("..".to_string(), parent));
+        }
+        opts.sort(&mut entries);
+
+        let mut body = self.ls_format(&entries, opts);
+        if opts.long {
+            let kb: usize = entries.iter().map(|(_, n)| n.size.div_ceil(1024)).sum();
+            let total = if opts.human {
+                human_size(kb as u64 * 1024)
+            } else {
+                kb.to_string()
+            };
+            body = if body.is_empty() {
+                format!("total {}", total)
+            } else {
+                format!("total {}\n{}", total, body)
+            };
+        }
+        blocks.push(match (header, body.is_empty()) {
+            (false, _) => body,
+            (true, true) => format!("{}:", path),
+            (true, false) => format!("{}:\n{}", path, body),
+        });
+
+        if opts.recursive {
+            for (name, child) in &entries {
+                if child.is_dir && name != "." && name != ".." {
+                    self.ls_dir(&join(path, name), true, opts, blocks, errors);
+                }
+            }
+        }
+    }
+
+    fn ls_format(&self, entries: &[(String, &Inode)], opts: &LsOptions) -> String {
+        let ino = |node: &Inode| {
+            if opts.inode {
+                format!("{} ", node.ino)
+            } else {
+                String::new()
+            }
+        };
+        if opts.long {
+            let now = clock::now_secs();
+            return entries
+                .iter()
+                .map(|(name, node)| {
+                    let mut line = format!(
+                        "{}{} {:>3} {:>8} {:>8} {:>8} {} {}",
+                        if opts.inode {
+                            format!("{:>7} ", node.ino)
+                        } else {
+                            String::new()
+                        },
+                        node.permissions,
+                        1,
+                        node.owner,
+                        node.group,
+                        if opts.human {
+                            human_size(node.size as u64)
+                        } else {
+                            node.size.to_string()
+                        },
+                        clock::ls_time(node.modified, now),
+                        colored(name, node, true)
+                    );
+                    if node.permissions.starts_with('l') {
+                        line.push_str(&format!(" -> {}", node.data.trim()));
+                    }
+                    line
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        // Names go one per line into pipes and files, without colour
+        let piped = self.output_captured;
+        let items: Vec<(String, usize)> = entries
+            .iter()
+            .map(|(name, node)| {
+                let prefix = ino(node);
+                let width = prefix.len() + name.chars().count();
+                (format!("{}{}", prefix, colored(name, node, !piped)), width)
+            })
+            .collect();
+        if piped || opts.one_per_line {
+            return items
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        if items.is_empty() {
+            return String::new();
+        }
+        let width = self
+            .shell
+            .env
+            .get("COLUMNS")
+            .and_then(|c| c.parse().ok())
+            .filter(|w| *w > 0)
+            .unwrap_or(80);
+        columns(&items, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_sorting_recursion_and_inodes() {
+        let items: Vec<(String, usize)> = ["a", "bb", "ccc", "d", "eeeee"]
+            .iter()
+            .map(|s| (s.to_string(), s.len()))
+            .collect();
+        assert_eq!(columns(&items, 80), "a  bb  ccc  d  eeeee");
+        assert_eq!(columns(&items, 12), "a    d\nbb   eeeee\nccc");
+        assert_eq!(columns(&items, 1), "a\nbb\nccc\nd\neeeee");
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let fs = &mut sys.kernel.fs;
+        fs.create_dir("/tmp/l").unwrap();
+        fs.create_dir("/tmp/l/sub").unwrap();
+        fs.create_file("/tmp/l/big", &"x".repeat(3000)).unwrap();
+        fs.create_file("/tmp/l/small", "x").unwrap();
+        fs.create_file("/tmp/l/sub/inner", "").unwrap();
+        fs.set_times("/tmp/l/small", None, Some(2_000_000_000))
+            .unwrap();
+
+        assert_eq!(
+            sys.cmd_ls(&["-R", "/tmp/l"]).flatten(),
+            "/tmp/l:\nbig  small  \x1b[01;34msub\x1b[0m\n\n/tmp/l/sub:\ninner"
+        );
+        assert_eq!(
+            sys.cmd_ls(&["-1S", "/tmp/l"]).flatten().lines().next(),
+            Some("\x1b[01;34msub\x1b[0m")
+        );
+        assert_eq!(
+            sys.cmd_ls(&["-1t", "/tmp/l"]).flatten().lines().next(),
+            Some("small")
+        );
+        assert_eq!(
+            sys.cmd_ls(&["-1tr", "/tmp/l"]).flatten().lines().last(),
+            Some("small")
+        );
+        assert!(sys
+            .cmd_ls(&["-x", "/tmp/l"])
+            .flatten()
+            .contains("invalid option"));
+
+        let ino = sys.kernel.fs.resolve("/tmp/l/big").unwrap().ino;
+        assert!(ino > 2);
+        assert!(sys
+            .cmd_ls(&["-i", "/tmp/l"])
+            .flatten()
+            .starts_with(&format!("{} big", ino)));
+        sys.kernel.fs.rename("/tmp/l/big", "/tmp/l/huge").unwrap();
+        assert_eq!(sys.kernel.fs.resolve("/tmp/l/huge").unwrap().ino, ino);
+        sys.cmd_cp(&["/tmp/l/huge", "/tmp/l/copy"]);
+        assert_ne!(sys.kernel.fs.resolve("/tmp/l/copy").unwrap().ino, ino);
+    }
+
+    #[test]
+    fn missing_paths_fail_the_command() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_file("/tmp/here", "").unwrap();
+        let out = sys.cmd_ls(&["/tmp/here", "/tmp/gone"]);
+        assert_eq!(out.stdout, "/tmp/here");
+        assert_eq!(
+            out.stderr,
+            "ls: cannot access '/tmp/gone': No such file or directory"
+        );
+        assert_eq!(out.status, 1);
+        assert!(sys
+            .exec("ls /tmp/gone && echo yes || echo no")
+            .ends_with("no"));
+        assert!(sys
+            .exec("ls /tmp/here && echo yes || echo no")
+            .ends_with("yes"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Open files per process: `lsof`, and the `/proc/<pid>/fd` links that
+//! show the same descriptors
+use super::{System, TERMINAL_TTY};
+use crate::network::{self, Protocol, Socket, SocketState};
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+
+const USAGE: &str = "usage: lsof [-i [:PORT]] [-p PID[,PID]] [-u USER] [-c NAME] [FILE]...";
+
+/// One open descriptor of a process
+struct Descriptor {
+    /// `cwd`, or the number with its access mode, like `3r`
+    fd: String,
+    kind: &'static str,
+    size: Option<usize>,
+    /// Where `/proc/<pid>/fd` points
+    target: String,
+    /// What `lsof` prints; the target unless it is a socket
+    name: String,
+    socket: bool,
+}
+
+struct OpenFile {
+    command: String,
+    pid: u32,
+    user: String,
+    descriptor: Descriptor,
+}
+
+fn socket_name(socket: &Socket) -> String {
+    let proto = match socket.protocol {
+        Protocol::Udp => "UDP",
+        _ => "TCP",
+    };
+    let state = match socket.state {
+        SocketState::Listen => "LISTEN",
+        SocketState::Connecting => "SYN_SENT",
+        SocketState::Open | SocketState::Established => "ESTABLISHED",
+        SocketState::Closing => "FIN_WAIT1",
+        SocketState::TimeWait => "TIME_WAIT",
+        SocketState::Closed => "CLOSED",
+    };
+    if socket.state == SocketState::Listen {
+        return format!("{} *:{} ({})", proto, socket.local_port, state);
+    }
+    let (host, port) = match &socket.url {
+        Some(url) => network::parse_remote_endpoint(url),
+        None => (socket.remote_addr.clone(), socket.remote_port),
+    };
+    format!(
+        "{} {}:{}->{}:{} ({})",
+        proto,
+        network::HOST_IPV4,
+        socket.local_port,
+        host,
+        port,
+        state
+    )
+}
+
+impl System {
+    pub(super) fn shell_pid(&self) -> u32 {
+        self.kernel.shell_pid.unwrap_or(1)
+    }
+
+    /// The descriptors `pid` holds: its working directory, the standard
+    /// streams on its terminal (or `/dev/null` for a daemon), then the VFS
+    /// handles and sockets it opened
+    fn descriptors(&self, pid: u32, tty: Option<&str>) -> Vec<Descriptor> {
+        // The shell started at boot has the terminal, whatever its entry says
+        let shell = pid == self.shell_pid();
+        let cwd = match shell {
+            true => self.kernel.fs.cwd.clone(),
+            false => "/".to_string(),
+        };
+        let stdio = match tty.or(shell.then_some(TERMINAL_TTY)) {
+            Some(tty) => format!("/dev/{}", tty),
+            None => "/dev/null".to_string(),
+        };
+        let mut out = vec![Descriptor {
+            fd: "cwd".into(),
+            kind: "DIR",
+            size: Some(4096),
+            target: cwd.clone(),
+            name: cwd,
+            socket: false,
+        }];
+        for fd in 0..3 {
+            out.push(Descriptor {
+                fd: format!("{}u", fd),
+                kind: "CHR",
+                size: None,
+                target: stdio.clone(),
+                name: stdio.clone(),
+                socket: false,
+            });
+        }
+        let mut next = 3;
+        for (_, handle) in self.kernel.fs.handles() {
+            if handle.pid != pid {
+                continue;
+            }
+            let mode = if handle.writable { 'w' } else { 'r' };
+            out.push(Descriptor {
+                fd: format!("{}{}", next, mode),
+                kind: "REG",
+                size: self.kernel.fs.resolve(&handle.path).map(|n| n.data.len()),
+                target: handle.path.clone(),
+                name: handle.path.clone(),
+                socket: false,
+            });
+            next += 1;
+        }
+        for socket in self.network.sockets() {
+            if socket.pid != Some(pid) {
+                continue;
+            }
+            out.push(Descriptor {
+                fd: format!("{}u", next),
+                kind: "IPv4",
+                size: None,
+                target: format!("socket:[{}]", socket.id),
+                name: socket_name(socket),
+                socket: true,
+            });
+            next += 1;
+        }
+        out
+    }
+
+    /// Every open descriptor, by process. Handles whose process is gone
+    /// are leaks and show up under `?`
+    fn open_files(&self) -> Vec<OpenFile> {
+        let mut procs = self.kernel.proc.list();
+        procs.sort_by_key(|p| p.pid);
+        let mut out = Vec::new();
+        for p in &procs {
+            for descriptor in self.descriptors(p.pid, p.tty.as_deref()) {
+                out.push(OpenFile {
+                    command: p.name.clone(),
+                    pid: p.pid,
+                    user: p.user.clone(),
+                    descriptor,
+                });
+            }
+        }
+        for (handle, open) in self.kernel.fs.handles() {
+            if self.kernel.proc.get(open.pid).is_none() {
+                out.push(OpenFile {
+                    command: "?".into(),
+                    pid: open.pid,
+                    user: "?".into(),
+                    descriptor: Descriptor {
+                        fd: format!("h{}", handle),
+                        kind: "REG",
+                        size: None,
+                        target: open.path.clone(),
+                        name: open.path.clone(),
+                        socket: false,
+                    },
+                });
+            }
+        }
+        out
+    }
+
+    /// `lsof`: open files, sockets and terminals of every process, or of
+    /// those picked by `-p`, `-u` and `-c`; `-i` keeps only sockets and
+    /// FILE operands only the processes holding those files
+    pub(super) fn cmd_lsof(&self, args: &[&str]) -> CmdOutput {
+        let mut pids: Vec<u32> = Vec::new();
+        let mut user = None;
+        let mut command = None;
+        let mut inet = false;
+        let mut port = None;
+        let mut files = Vec::new();
+        let mut rest = args.iter().peekable();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-i" => {
+                    inet = true;
+                    if let Some(p) = rest.peek().and_then(|a| a.strip_prefix(':')) {
+                        port = Some(p.to_string());
+                        rest.next();
+                    }
+                }
+                "-p" | "-u" | "-c" => {
+                    let Some(value) = rest.next() else {
+                        return CmdOutput::error(
+                            1,
+                            format!("lsof: missing value for {}\n{}", arg, USAGE),
+                        );
+                    };
+                    match *arg {
+                        "-p" => {
+                            for pid in value.split(',') {
+                                match pid.parse() {
+                                    Ok(pid) => pids.push(pid),
+                                    Err(_) => {
+                                        return CmdOutput::error(
+                                            1,
+                                            format!("lsof: illegal process ID: {}", pid),
+                                        )
+                                    }
+                                }
+                            }
+                        }
+                        "-u" => user = Some(*value),
+                        _ => command = Some(*value),
+                    }
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!("lsof: unknown option: {}\n{}", flag, USAGE),
+                    )
+                }
+                path => files.push(self.kernel.fs.normalize(path)),
+            }
+        }
+
+        let rows: Vec<OpenFile> = self
+            .open_files()
+            .into_iter()
+            .filter(|f| pids.is_empty() || pids.contains(&f.pid))
+            .filter(|f| user.is_none_or(|u| f.user == u))
+            .filter(|f| command.is_none_or(|c| f.command.starts_with(c)))
+            .filter(|f| !inet || f.descriptor.socket)
+            .filter(|f| {
+                port.as_ref()
+                    .is_none_or(|p| f.descriptor.name.contains(&format!(":{} ", p)))
+            })
+            .filter(|f| {
+                files.is_empty() || (!f.descriptor.socket && files.contains(&f.descriptor.target))
+            })
+            .collect();
+        // lsof fails when it finds nothing to list
+        if rows.is_empty() {
+            return CmdOutput {
+                status: 1,
+                ..CmdOutput::default()
+            };
+        }
+        let mut out = vec![format!(
+            "{:<9} {:>5} {:<8} {:>4} {:<5} {:>8} NAME",
+            "COMMAND", "PID", "USER", "FD", "TYPE", "SIZE/OFF"
+        )];
+        for f in rows {
+            let d = &f.descriptor;
+            out.push(format!(
+                "{:<9} {:>5} {:<8} {:>4} {:<5} {:>8} {}",
+                f.command.chars().take(9).collect::<String>(),
+                f.pid,
+                f.user,
+                d.fd,
+                d.kind,
+                d.size.map_or_else(|| "0t0".to_string(), |s| s.to_string()),
+                d.name
+            ));
+        }
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// Rebuild `/proc/<pid>` for every process, each with `cmdline` and an
+    /// `fd` directory of links to what it holds open; `/proc/self/fd` is
+    /// the shell's
+    pub(super) fn refresh_proc_pids(&mut self) {
+        let mut entries = Vec::new();
+        for p in self.kernel.proc.list() {
+            let mut fd = Inode::dir("fd");
+            for d in self.descriptors(p.pid, p.tty.as_deref()) {
+                let num = d.fd.trim_end_matches(['r', 'w', 'u']);
+                if num.parse::<u32>().is_ok() {
+                    fd.children
+                        .insert(num.to_string(), Inode::symlink(num, &d.target));
+                }
+            }
+            let mut dir = Inode::dir(&p.pid.to_string());
+            let cmdline = format!(
+                "{}\0",
+                p.cmdline.split_whitespace().collect::<Vec<_>>().join("\0")
+            );
+            dir.children
+                .insert("cmdline".into(), Inode::file("cmdline", &cmdline));
+            dir.children.insert("fd".into(), fd);
+            entries.push((p.pid, dir));
+        }
+        let shell_fds = entries
+            .iter()
+            .find(|(pid, _)| *pid == self.shell_pid())
+            .and_then(|(_, dir)| dir.children.get("fd").cloned());
+        let Some(proc_dir) = self.kernel.fs.resolve_mut("/proc") else {
+            return;
+        };
+        proc_dir
+            .children
+            .retain(|name, _| name.parse::<u32>().is_err());
+        for (pid, dir) in entries {
+            proc_dir.children.insert(pid.to_string(), dir);
+        }
+        if let (Some(fd), Some(self_dir)) = (shell_fds, proc_dir.children.get_mut("self")) {
+            self_dir.children.insert("fd".into(), fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_handles_sockets_and_proc_fd_links() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.start_boot();
+        sys.kernel.fs.create_file("/tmp/log", "hello").unwrap();
+        let shell = sys.shell_pid();
+
+        let handle = sys.sys_open("/tmp/log", false);
+        assert!(handle > 0);
+        let out = sys.exec(&format!("lsof -p {}", shell));
+        assert!(out.starts_with("COMMAND"));
+        assert!(out.contains("   3r REG          5 /tmp/log"));
+        assert!(out.contains("   0u CHR"));
+        assert_eq!(sys.exec("lsof /tmp/log").lines().count(), 2);
+
+        let fds = sys.exec(&format!("ls /proc/{}/fd", shell));
+        assert!(fds.contains('3'));
+        let links = sys.exec("ls -l /proc/self/fd");
+        assert!(links.contains("0 -> /dev/tty1"));
+        assert!(links.contains("3 -> /tmp/log"));
+
+        sys.sys_close(handle as u32);
+        sys.exec("lsof /tmp/log");
+        assert_eq!(sys.last_status, 1);
+    }
+}
@@ -0,0 +1,618 @@
+//! `lynx`, a text-mode web browser. Pages come through the same `fetch`
+//! bridge as `curl` (or straight from `httpd` for local URLs) and are laid
+//! out as plain text with each link numbered. While a page is up the next
+//! line typed is a browser command: a link number, `b` for back, `q` to quit
+use super::netexec::normalize_url;
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+
+const LYNX_USAGE: &str = "usage: lynx [-dump] URL";
+const COMMANDS: &str =
+    "Commands: link number to follow it, 'b' to go back, 'g URL' to open a page, 'q' to quit";
+/// Redirects a local page may chain before giving up
+const MAX_REDIRECTS: usize = 5;
+/// Elements whose content is never shown
+const HIDDEN: [&str; 5] = ["script", "style", "template", "svg", "title"];
+/// Elements that start and end a paragraph
+const BLOCKS: [&str; 21] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "nav",
+    "main",
+    "aside",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "table",
+    "blockquote",
+    "pre",
+    "form",
+];
+
+/// A page already laid out, kept so `b` does not fetch it again
+struct Page {
+    url: String,
+    title: String,
+    lines: Vec<String>,
+    links: Vec<String>,
+}
+
+impl Page {
+    /// What the screen shows: the title top right, the text and the
+    /// command line
+    fn screen(&self, width: usize) -> String {
+        let title = match self.title.as_str() {
+            "" => self.url.as_str(),
+            title => title,
+        };
+        let mut out = vec![format!("{:>width$}", title), String::new()];
+        out.extend(self.lines.iter().cloned());
+        out.push(String::new());
+        out.push(format!(
+            "\x1b[COLOR:gray]{} ({} links)\x1b[COLOR:reset]",
+            COMMANDS,
+            self.links.len()
+        ));
+        out.join("\n")
+    }
+}
+
+/// The running `lynx` session: the page on screen is the last one
+pub(super) struct Browser {
+    history: Vec<Page>,
+}
+
+/// `&amp;`, `&#169;` and friends back to text
+pub(super) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = rest[1..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+            .map_or(rest.len(), |n| n + 1);
+        let name = &rest[1..end];
+        let decoded = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            _ => name
+                .strip_prefix("#x")
+                .or_else(|| name.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| name.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = rest[end..].strip_prefix(';').unwrap_or(&rest[end..]);
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Value of attribute `name` inside a tag such as `a href="/x" class=y`
+pub(super) fn attr(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag.split_once(char::is_whitespace)?.1;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..end];
+        rest = rest[end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            None => "",
+            Some(v) => {
+                let v = v.trim_start();
+                let (value, after) = match v.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let body = &v[1..];
+                        let close = body.find(q).unwrap_or(body.len());
+                        (&body[..close], body.get(close + 1..).unwrap_or(""))
+                    }
+                    _ => v.split_at(v.find(char::is_whitespace).unwrap_or(v.len())),
+                };
+                rest = after;
+                value
+            }
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(decode_entities(value));
+        }
+    }
+}
+
+/// Where `href` on the page at `base` points, or `None` for anchors on the
+/// same page and schemes a browser in a terminal can't follow
+fn resolve_href(base: &str, href: &str) -> Option<String> {
+    let href = href.trim().split('#').next().unwrap_or("");
+    if href.is_empty() {
+        return None;
+    }
+    let lower = href.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        return Some(href.to_string());
+    }
+    let has_scheme = href.find(':').is_some_and(|i| {
+        i > 0
+            && href[..i]
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    if has_scheme {
+        return None;
+    }
+    let (scheme, rest) = base.split_once("://")?;
+    if let Some(net) = href.strip_prefix("//") {
+        return Some(format!("{}://{}", scheme, net));
+    }
+    let (authority, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let path = match path.split(['?', '#']).next() {
+        Some(p) if p.starts_with('/') => p,
+        _ => "/",
+    };
+    let joined = if href.starts_with('/') {
+        href.to_string()
+    } else if href.starts_with('?') {
+        format!("{}{}", path, href)
+    } else {
+        format!("{}{}", &path[..path.rfind('/').map_or(0, |i| i + 1)], href)
+    };
+    let (path, query) = joined.split_at(joined.find('?').unwrap_or(joined.len()));
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    let mut parts: Vec<&str> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match *segment {
+            "." => {}
+            ".." => {
+                parts.pop();
+            }
+            s => parts.push(s),
+        }
+        if i + 1 == segments.len() && matches!(*segment, "." | "..") {
+            parts.push("");
+        }
+    }
+    Some(format!(
+        "{}://{}/{}{}",
+        scheme,
+        authority,
+        parts.join("/"),
+        query
+    ))
+}
+
+/// Words flowed into lines no wider than `width`
+struct Layout {
+    width: usize,
+    lines: Vec<String>,
+    line: String,
+    space: bool,
+    pre: bool,
+}
+
+impl Layout {
+    fn new(width: usize) -> Self {
+        Layout {
+            width,
+            lines: Vec::new(),
+            line: String::new(),
+            space: false,
+            pre: false,
+        }
+    }
+
+    fn break_line(&mut self) {
+        if !self.line.trim().is_empty() {
+            self.lines.push(std::mem::take(&mut self.line));
+        }
+        self.line.clear();
+        self.space = false;
+    }
+
+    fn paragraph(&mut self) {
+        self.break_line();
+        if self.lines.last().is_some_and(|l| !l.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    fn word(&mut self, word: &str) {
+        let len = self.line.chars().count();
+        let gap = usize::from(self.space && len > 0);
+        if len > 0 && len + gap + word.chars().count() > self.width {
+            self.break_line();
+        } else if gap == 1 {
+            self.line.push(' ');
+        }
+        self.line.push_str(word);
+        self.space = false;
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre {
+            let mut lines = text.split('\n');
+            if let Some(first) = lines.next() {
+                self.line.push_str(first);
+            }
+            for line in lines {
+                self.lines.push(std::mem::take(&mut self.line));
+                self.line.push_str(line);
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        for word in text.split_whitespace() {
+            self.word(word);
+            self.space = true;
+        }
+        if !text.ends_with(char::is_whitespace) {
+            self.space = false;
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.break_line();
+        while self.lines.last().is_some_and(|l| l.is_empty()) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+/// Lay out `html` from `url` as text `width` columns wide. Links become
+/// `[N]text`; returns the title, the lines and the link targets
+pub(super) fn render(html: &str, url: &str, width: usize) -> (String, Vec<String>, Vec<String>) {
+    let lower = html.to_ascii_lowercase();
+    let title = lower
+        .find("<title")
+        .and_then(|at| Some(at + lower[at..].find('>')? + 1))
+        .and_then(|start| Some(&html[start..start + lower[start..].find("</title")?]))
+        .map(|raw| {
+            decode_entities(raw)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    let mut layout = Layout::new(width);
+    let mut links = Vec::new();
+    // Numbering of each open list, `None` for bullets
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut i = 0;
+    while i < html.len() {
+        let Some(open) = html[i..].find('<').map(|n| i + n) else {
+            layout.text(&decode_entities(&html[i..]));
+            break;
+        };
+        layout.text(&decode_entities(&html[i..open]));
+        if lower[open..].starts_with("<!--") {
+            i = lower[open..]
+                .find("-->")
+                .map_or(html.len(), |n| open + n + 3);
+            continue;
+        }
+        let Some(close) = html[open..].find('>').map(|n| open + n) else {
+            break;
+        };
+        i = close + 1;
+        let tag = &html[open + 1..close];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if !closing && HIDDEN.contains(&name.as_str()) {
+            let end = lower[i..]
+                .find(&format!("</{}", name))
+                .map_or(html.len(), |n| i + n);
+            i = lower[end..].find('>').map_or(html.len(), |n| end + n + 1);
+            continue;
+        }
+        match name.as_str() {
+            "br" => layout.break_line(),
+            "pre" => {
+                layout.paragraph();
+                layout.pre = !closing;
+            }
+            "ul" | "ol" if !closing => {
+                layout.paragraph();
+                lists.push((name == "ol").then_some(0));
+            }
+            "ul" | "ol" => {
+                lists.pop();
+                layout.paragraph();
+            }
+            "li" if !closing => {
+                layout.break_line();
+                let indent = "  ".repeat(lists.len().max(1));
+                let marker = match lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}{}.", indent, n)
+                    }
+                    _ => format!("{}*", indent),
+                };
+                layout.word(&marker);
+                layout.space = true;
+            }
+            "tr" | "dt" | "dd" => layout.break_line(),
+            "td" | "th" if !closing => layout.space = true,
+            "hr" => {
+                layout.paragraph();
+                layout.word(&"_".repeat(width.min(60)));
+                layout.paragraph();
+            }
+            "img" => {
+                if let Some(alt) = attr(tag, "alt").filter(|a| !a.trim().is_empty()) {
+                    layout.word(&format!("[{}]", alt.trim()));
+                }
+            }
+            "a" if !closing => {
+                if let Some(target) = attr(tag, "href").and_then(|h| resolve_href(url, &h)) {
+                    links.push(target);
+                    layout.word(&format!("[{}]", links.len()));
+                }
+            }
+            block if BLOCKS.contains(&block) => layout.paragraph(),
+            _ => {}
+        }
+    }
+    (title, layout.finish(), links)
+}
+
+impl System {
+    /// Columns pages are laid out in, from `$COLUMNS`
+    pub(super) fn lynx_width(&self) -> usize {
+        self.shell
+            .env
+            .get("COLUMNS")
+            .and_then(|c| c.parse::<usize>().ok())
+            .unwrap_or(80)
+            .clamp(40, 100)
+            - 1
+    }
+
+    /// Load `url`: a local page at once, anything else through the
+    /// network once the command returns
+    fn lynx_visit(&mut self, url: &str, dump: bool) -> Result<String, String> {
+        let mut url = normalize_url(url, true);
+        let host = url
+            .split_once("://")
+            .map_or(url.as_str(), |(_, rest)| rest)
+            .split(['/', '?', '#', ':'])
+            .next()
+            .unwrap_or("")
+            .to_string();
+        if self.host_unreachable(&host).is_some() {
+            return Err(self.lynx_failed(&url, "Unable to connect to remote host."));
+        }
+        for _ in 0..MAX_REDIRECTS {
+            let Some((port, path)) = self.local_http_target(&url) else {
+                return Ok(self.emit(SystemEvent::Browse { url, dump }));
+            };
+            let response = match self.serve_local_http(port, &path) {
+                Ok(response) => response,
+                Err(_) => return Err(self.lynx_failed(&url, "Unable to connect to remote host.")),
+            };
+            match response.location {
+                Some(location) if (300..400).contains(&response.status) => {
+                    match resolve_href(&url, &location) {
+                        Some(next) => url = next,
+                        None => break,
+                    }
+                }
+                _ => {
+                    let body = String::from_utf8_lossy(&response.body).into_owned();
+                    return Ok(self.lynx_show(&url, &body, dump));
+                }
+            }
+        }
+        Err(self.lynx_failed(&url, "Too many redirections."))
+    }
+
+    /// A page that could not be loaded: the session stays on the page it
+    /// was on, and a start page that fails ends `lynx`
+    pub(super) fn lynx_failed(&mut self, url: &str, why: &str) -> String {
+        match &self.browser {
+            Some(browser) if !browser.history.is_empty() => {
+                format!("Alert!: {}\n{}", why, COMMANDS)
+            }
+            _ => {
+                self.browser = None;
+                format!("lynx: Can't access startfile {}", url)
+            }
+        }
+    }
+
+    /// Lay out a fetched page. With `dump` it is printed with its list of
+    /// references and `lynx` exits; otherwise it becomes the current page
+    pub(super) fn lynx_show(&mut self, url: &str, body: &str, dump: bool) -> String {
+        let width = self.lynx_width();
+        let (title, lines, links) = if body.contains('<') {
+            render(body, url, width)
+        } else {
+            let lines = body.lines().map(str::to_string).collect();
+            (String::new(), lines, Vec::new())
+        };
+        if dump {
+            let mut out = lines;
+            if !links.is_empty() {
+                out.extend(["".into(), "References".into(), "".into()]);
+                out.extend(
+                    links
+                        .iter()
+                        .enumerate()
+                        .map(|(n, link)| format!("{:>4}. {}", n + 1, link)),
+                );
+            }
+            return out.join("\n");
+        }
+        let page = Page {
+            url: url.to_string(),
+            title,
+            lines,
+            links,
+        };
+        let out = page.screen(width);
+        self.browser
+            .get_or_insert_with(|| Browser {
+                history: Vec::new(),
+            })
+            .history
+            .push(page);
+        out
+    }
+
+    /// A line typed while a page is up
+    pub(super) fn lynx_input(&mut self, line: &str) -> String {
+        let width = self.lynx_width();
+        let Some(browser) = &mut self.browser else {
+            return String::new();
+        };
+        let line = line.trim();
+        match line {
+            "q" | "Q" | "quit" => {
+                self.browser = None;
+                String::new()
+            }
+            "b" | "B" | "back" => {
+                if browser.history.len() < 2 {
+                    return format!("Alert!: Already at the first document\n{}", COMMANDS);
+                }
+                browser.history.pop();
+                let out = browser.history.last().map(|page| page.screen(width));
+                out.unwrap_or_default()
+            }
+            "" => {
+                let out = browser.history.last().map(|page| page.screen(width));
+                out.unwrap_or_default()
+            }
+            _ => {
+                if let Some(url) = line.strip_prefix("g ") {
+                    let (Ok(out) | Err(out)) = self.lynx_visit(url.trim(), false);
+                    return out;
+                }
+                let target = line
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| browser.history.last()?.links.get(n).cloned());
+                match target {
+                    Some(url) => {
+                        let (Ok(out) | Err(out)) = self.lynx_visit(&url, false);
+                        out
+                    }
+                    None if line.parse::<usize>().is_ok() => {
+                        format!("Alert!: Link number {} does not exist\n{}", line, COMMANDS)
+                    }
+                    None => COMMANDS.into(),
+                }
+            }
+        }
+    }
+
+    /// `lynx URL` browses from URL; `lynx -dump URL` prints it and exits
+    pub(super) fn cmd_lynx(&mut self, args: &[&str]) -> CmdOutput {
+        let (dump, url) = match args {
+            [url] if !url.starts_with('-') => (false, *url),
+            ["-dump", url] | [url, "-dump"] => (true, *url),
+            _ => return CmdOutput::error(1, LYNX_USAGE),
+        };
+        self.browser = None;
+        match self.lynx_visit(url, dump) {
+            Ok(page) => CmdOutput::ok(page),
+            Err(e) => CmdOutput::error(1, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: &str = r##"<html><head><title>Home &amp; Garden</title>
+<script>document.write("hidden")</script><style>p { color: red }</style></head>
+<body><h1>Welcome</h1><p>Read the <a href="/docs/intro.html">intro</a> or
+<a href="../about?x=1#team">about us</a>.</p><ul><li>one</li><li>two</li></ul>
+<a href="#top">top</a> <a href="mailto:me@example.com">mail</a></body></html>"##;
+
+    #[test]
+    fn pages_render_as_text_with_numbered_links() {
+        let (title, lines, links) = render(HOME, "https://example.com/a/b/index.html", 79);
+        assert_eq!(title, "Home & Garden");
+        assert_eq!(
+            links,
+            [
+                "https://example.com/docs/intro.html",
+                "https://example.com/a/about?x=1"
+            ]
+        );
+        let text = lines.join("\n");
+        assert!(text.contains("Read the [1]intro or [2]about us."));
+        assert!(text.contains("  * one\n  * two"));
+        assert!(!text.contains("hidden") && !text.contains("color"));
+    }
+
+    #[test]
+    fn lines_typed_while_browsing_follow_links_and_go_back() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.lynx_show("https://example.com/", HOME, false);
+        sys.lynx_show("https://example.com/docs/intro.html", "<p>Intro</p>", false);
+
+        let back = sys.exec("b");
+        assert!(back.contains("Home & Garden"));
+        assert!(sys
+            .exec("7")
+            .starts_with("Alert!: Link number 7 does not exist"));
+
+        sys.exec("1");
+        assert_eq!(
+            sys.take_events(),
+            [SystemEvent::Browse {
+                url: "https://example.com/docs/intro.html".into(),
+                dump: false
+            }]
+        );
+        assert_eq!(sys.exec("q"), "");
+        assert!(sys.browser.is_none());
+        assert_eq!(sys.cmd_lynx(&[]).stderr, LYNX_USAGE);
+    }
+}
@@ -0,0 +1,304 @@
+//! Local mail: the system writes to `/var/mail/USER` in mbox format (a
+//! welcome note on first login, kernel panic reports, the boot fsck's
+//! findings) and `mail` lists, reads and deletes what is there
+use super::System;
+use crate::clock;
+use crate::shell::CmdOutput;
+
+const SPOOL: &str = "/var/mail";
+const MAIL_USAGE: &str = "usage: mail [-u USER] [list | read N | delete N...]";
+const HOST: &str = "kpawnd";
+
+/// One message of an mbox
+struct Message {
+    from: String,
+    subject: String,
+    date: i64,
+    read: bool,
+    body: String,
+}
+
+impl Message {
+    fn to_mbox(&self, to: &str) -> String {
+        // A body line that looks like a separator would split the message
+        let body: Vec<String> = self
+            .body
+            .lines()
+            .map(|l| {
+                if l.starts_with("From ") {
+                    format!(">{}", l)
+                } else {
+                    l.to_string()
+                }
+            })
+            .collect();
+        format!(
+            "From {from} {date}\nFrom: {from}\nTo: {to}@{host}\nSubject: {subject}\n\
+             Date: {date}\nX-Date: {secs}\nStatus: {status}\n\n{body}\n\n",
+            from = self.from,
+            date = clock::ctime(self.date),
+            to = to,
+            host = HOST,
+            subject = self.subject,
+            secs = self.date,
+            status = if self.read { "RO" } else { "O" },
+            body = body.join("\n"),
+        )
+    }
+}
+
+fn parse_mbox(data: &str) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut lines = data.lines().peekable();
+    while let Some(line) = lines.next() {
+        if !line.starts_with("From ") {
+            continue;
+        }
+        let mut msg = Message {
+            from: line[5..].split_whitespace().next().unwrap_or("").into(),
+            subject: String::new(),
+            date: 0,
+            read: false,
+            body: String::new(),
+        };
+        for header in lines.by_ref() {
+            let Some((key, value)) = header.split_once(": ") else {
+                break;
+            };
+            match key {
+                "Subject" => msg.subject = value.into(),
+                "X-Date" => msg.date = value.parse().unwrap_or(0),
+                "Status" => msg.read = value.contains('R'),
+                _ => {}
+            }
+        }
+        let mut body = Vec::new();
+        while let Some(line) = lines.next_if(|l| !l.starts_with("From ")) {
+            body.push(
+                line.strip_prefix(">From ")
+                    .map_or(line.to_string(), |rest| format!("From {}", rest)),
+            );
+        }
+        while body.last().is_some_and(|l| l.is_empty()) {
+            body.pop();
+        }
+        msg.body = body.join("\n");
+        messages.push(msg);
+    }
+    messages
+}
+
+impl System {
+    fn mailbox_path(user: &str) -> String {
+        format!("{}/{}", SPOOL, user)
+    }
+
+    fn mailbox(&self, user: &str) -> Vec<Message> {
+        self.kernel
+            .fs
+            .resolve(&Self::mailbox_path(user))
+            .map(|node| parse_mbox(&node.data))
+            .unwrap_or_default()
+    }
+
+    /// Rewrite `user`'s mailbox, readable by them alone
+    fn save_mailbox(&mut self, user: &str, messages: &[Message]) {
+        let data: String = messages.iter().map(|m| m.to_mbox(user)).collect();
+        let path = Self::mailbox_path(user);
+        let _ = self.ensure_dir_all(SPOOL);
+        self.write_system_file(&path, &data);
+        if let Some(node) = self.kernel.fs.resolve_mut(&path) {
+            node.owner = user.into();
+            node.group = user.into();
+            node.permissions = "-rw-------".into();
+        }
+    }
+
+    /// Append a message to `user`'s mailbox, as the system's own mailers do
+    pub(super) fn deliver_mail(&mut self, user: &str, from: &str, subject: &str, body: &str) {
+        let mut messages = self.mailbox(user);
+        messages.push(Message {
+            from: format!("{}@{}", from, HOST),
+            subject: subject.into(),
+            date: clock::now_secs(),
+            read: false,
+            body: body.trim_end().into(),
+        });
+        self.save_mailbox(user, &messages);
+    }
+
+    /// Messages in `user`'s mailbox not read yet
+    pub(super) fn unread_mail(&self, user: &str) -> usize {
+        self.mailbox(user).iter().filter(|m| !m.read).count()
+    }
+
+    /// The note for someone's first login
+    pub(super) fn deliver_welcome_mail(&mut self, user: &str) {
+        let body = format!(
+            "Hi {},\n\n\
+             Welcome to kpawnd. This is a whole (small) Linux running in your\n\
+             browser tab: the filesystem is saved between visits, so what you\n\
+             make here is still here next time.\n\n\
+             A few places to start:\n  \
+             help          every command, by topic\n  \
+             man -k WORD   search the manual pages\n  \
+             neofetch      what this machine thinks it is\n  \
+             gh kpawnd     the author on GitHub\n\n\
+             Mail from the system lands here too; `mail` lists it.\n\n\
+             -- root",
+            user
+        );
+        self.deliver_mail(user, "root", "Welcome to kpawnd", &body);
+    }
+
+    /// Mail root what the kernel said before it stopped, so it is still
+    /// there after the reboot
+    pub(super) fn deliver_panic_report(&mut self, reason: &str) {
+        let uptime = self.kernel.uptime_ms() / 1000;
+        let body = format!(
+            "The kernel panicked after {}s of uptime.\n\n{}\n\n\
+             If system files were removed, boot and run `fsck -y /dev/sda1`\n\
+             as root, or `snapshot restore NAME` if you kept one.",
+            uptime,
+            reason.trim_end()
+        );
+        let subject = reason.lines().next().unwrap_or("Kernel panic").trim();
+        self.deliver_mail("root", "kernel", subject, &body);
+    }
+
+    fn mail_list(&self, user: &str, messages: &[Message]) -> String {
+        let unread = messages.iter().filter(|m| !m.read).count();
+        let mut out = vec![format!(
+            "\"{}\": {} message{} {} new",
+            Self::mailbox_path(user),
+            messages.len(),
+            if messages.len() == 1 { "" } else { "s" },
+            unread
+        )];
+        for (n, msg) in messages.iter().enumerate() {
+            let date = clock::ctime(msg.date);
+            out.push(format!(
+                "{} {:>3} {:<18} {:.16}  {}",
+                if msg.read { ' ' } else { 'N' },
+                n + 1,
+                msg.from,
+                date,
+                msg.subject
+            ));
+        }
+        out.join("\n")
+    }
+
+    pub(super) fn cmd_mail(&mut self, args: &[&str]) -> CmdOutput {
+        let mut user = self.current_user();
+        let mut args = args;
+        if let ["-u", name, rest @ ..] = args {
+            if *name != user && user != "root" {
+                return CmdOutput::error(
+                    1,
+                    format!("mail: {}: Permission denied", Self::mailbox_path(name)),
+                );
+            }
+            user = name.to_string();
+            args = rest;
+        }
+        let mut messages = self.mailbox(&user);
+        if messages.is_empty() {
+            return CmdOutput::error(1, format!("No mail for {}", user));
+        }
+        let pick = |n: &str, count: usize| {
+            n.parse::<usize>()
+                .ok()
+                .filter(|n| (1..=count).contains(n))
+                .ok_or_else(|| format!("mail: {}: Invalid message number", n))
+        };
+        match args {
+            [] | ["list"] => CmdOutput::ok(self.mail_list(&user, &messages)),
+            ["read", n] | ["p", n] => {
+                let n = match pick(n, messages.len()) {
+                    Ok(n) => n,
+                    Err(e) => return CmdOutput::error(1, e),
+                };
+                let msg = &mut messages[n - 1];
+                msg.read = true;
+                let text = format!(
+                    "Message {}:\nFrom: {}\nTo: {}@{}\nSubject: {}\nDate: {}\n\n{}",
+                    n,
+                    msg.from,
+                    user,
+                    HOST,
+                    msg.subject,
+                    clock::ctime(msg.date),
+                    msg.body
+                );
+                let title = msg.subject.clone();
+                self.save_mailbox(&user, &messages);
+                CmdOutput::ok(self.pager_start(&title, &text, false, true))
+            }
+            ["delete", numbers @ ..] | ["d", numbers @ ..] if !numbers.is_empty() => {
+                let mut doomed = Vec::new();
+                for n in numbers {
+                    match pick(n, messages.len()) {
+                        Ok(n) => doomed.push(n - 1),
+                        Err(e) => return CmdOutput::error(1, e),
+                    }
+                }
+                let mut index = 0;
+                messages.retain(|_| {
+                    index += 1;
+                    !doomed.contains(&(index - 1))
+                });
+                self.save_mailbox(&user, &messages);
+                CmdOutput::ok(String::new())
+            }
+            _ => CmdOutput::error(2, MAIL_USAGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mail_is_delivered_listed_read_and_deleted() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        assert_eq!(sys.exec("mail"), "No mail for user");
+
+        sys.login_motd();
+        sys.deliver_mail(
+            "user",
+            "cron",
+            "From the daily job",
+            "From here on\nall is well",
+        );
+        assert_eq!(sys.unread_mail("user"), 2);
+        assert!(sys
+            .cmd_motd(&["-r"])
+            .flatten()
+            .contains("\nYou have new mail.\n"));
+        let list = sys.exec("mail");
+        assert!(list.starts_with("\"/var/mail/user\": 2 messages 2 new\nN   1 root@kpawnd"));
+        assert!(list.ends_with("  From the daily job"));
+
+        let read = sys.exec("mail read 2");
+        assert!(read.starts_with("Message 2:\nFrom: cron@kpawnd\n"));
+        assert!(read.ends_with("\n\nFrom here on\nall is well"));
+        assert_eq!(sys.unread_mail("user"), 1);
+        sys.exec("mail read 1");
+        assert!(!sys.cmd_motd(&["-r"]).flatten().contains("new mail"));
+
+        assert_eq!(sys.exec("mail read 3"), "mail: 3: Invalid message number");
+        sys.exec("mail delete 1");
+        assert!(sys
+            .exec("mail")
+            .contains("1 message 0 new\n    1 cron@kpawnd"));
+        assert!(sys.exec("mail -u root").contains("Permission denied"));
+        let node = sys.kernel.fs.resolve("/var/mail/user").unwrap();
+        assert_eq!(
+            (node.owner.as_str(), node.permissions.as_str()),
+            ("user", "-rw-------")
+        );
+    }
+}
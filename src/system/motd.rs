@@ -0,0 +1,180 @@
+use super::neofetch::format_uptime;
+use super::{System, TERMINAL_TTY};
+use crate::clock;
+use crate::shell::CmdOutput;
+
+const MOTD: &str = "/etc/motd";
+/// One `USER TTY SECONDS` line per user who has logged in
+pub(super) const LASTLOG: &str = "/var/log/lastlog";
+/// The longest uptime seen, in seconds
+const UPTIME_RECORD: &str = "/var/log/uptime.record";
+
+const TIPS: &[&str] = &[
+    "`doom` runs the real thing on a canvas; `doommap` draws the level.",
+    "`python` and `lua` open a REPL; both can also run a script file.",
+    "`snapshot create NAME` before experimenting, `snapshot restore NAME` after.",
+    "`rm --trash` moves files to ~/.Trash and `undo-rm` brings them back.",
+    "`sudo rm -rf /` panics the kernel; the rescue shell can bring it back.",
+    "`grub` edits the kernel command line; try `single` for rescue mode.",
+    "`cmatrix` and `screensaver` are better in a dark room.",
+    "`apt install fortune-mod cowsay` and pipe one into the other.",
+    "`snake` and `pong` are one keypress away.",
+    "`neofetch --ascii arch` for when you miss your other machine.",
+    "Ctrl+R searches history, and `!!` repeats the last command.",
+];
+
+impl System {
+    /// Write `/etc/motd` afresh: the uptime record, a tip, and the current
+    /// user's previous login
+    pub(super) fn refresh_motd(&mut self) {
+        let record = self.record_uptime();
+        let release = self
+            .kernel
+            .fs
+            .resolve("/etc/os-release")
+            .and_then(|n| {
+                n.data.lines().find_map(|l| {
+                    l.strip_prefix("PRETTY_NAME=")
+                        .map(|v| v.trim_matches('"').to_string())
+                })
+            })
+            .unwrap_or_else(|| "kpawnd GNU/Linux".into());
+        // Seconds since the epoch mixed with the uptime, good enough for a tip
+        let seed = (clock::now_secs() as u64 ^ self.kernel.uptime_ms())
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let tip = TIPS[(seed >> 32) as usize % TIPS.len()];
+
+        let mut text = format!(
+            "Welcome to {} ({} wasm32)\n\n\
+             Type 'help' for available commands.\n\
+             Type 'echo github' to visit the project page.\n\n\
+             Uptime record: {}\n\
+             Tip: {}\n",
+            release,
+            crate::kernel::KERNEL_VERSION,
+            format_uptime(record),
+            tip
+        );
+        let user = self.current_user();
+        if self.unread_mail(&user) > 0 {
+            text.push_str("\nYou have new mail.\n");
+        }
+        if let Some((tty, secs)) = self.last_login(&user) {
+            text.push_str(&format!(
+                "\nLast login: {} on {}\n",
+                clock::ctime(secs),
+                tty
+            ));
+        }
+        self.write_system_file(MOTD, &text);
+    }
+
+    /// Keep the longest uptime in `/var/log/uptime.record`; returns it
+    pub(super) fn record_uptime(&mut self) -> u64 {
+        let now = self.kernel.uptime_ms() / 1000;
+        let best = self
+            .kernel
+            .fs
+            .resolve(UPTIME_RECORD)
+            .and_then(|n| n.data.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        if now > best || best == 0 {
+            self.write_system_file(UPTIME_RECORD, &format!("{}\n", now));
+        }
+        now.max(best)
+    }
+
+    /// The `(tty, time)` of `user`'s last login from `/var/log/lastlog`
+    pub(super) fn last_login(&self, user: &str) -> Option<(String, i64)> {
+        let log = self.kernel.fs.resolve(LASTLOG)?;
+        log.data.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            if fields.next()? != user {
+                return None;
+            }
+            let tty = fields.next()?.to_string();
+            Some((tty, fields.next()?.parse().ok()?))
+        })
+    }
+
+    /// Replace `user`'s lastlog entry with a login now on the terminal
+    pub(super) fn record_login(&mut self, user: &str) {
+        let mut lines: Vec<String> = self
+            .kernel
+            .fs
+            .resolve(LASTLOG)
+            .map(|n| n.data.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        lines.retain(|l| l.split_whitespace().next() != Some(user));
+        lines.push(format!("{} {} {}", user, TERMINAL_TTY, clock::now_secs()));
+        self.write_system_file(LASTLOG, &(lines.join("\n") + "\n"));
+    }
+
+    /// A root-owned file the system keeps up to date itself
+    pub(super) fn write_system_file(&mut self, path: &str, data: &str) {
+        let fs = &mut self.kernel.fs;
+        if fs.resolve(path).is_some() {
+            let _ = fs.write_file(path, data);
+        } else if fs.create_file(path, data).is_ok() {
+            if let Some(node) = fs.resolve_mut(path) {
+                node.owner = "root".into();
+                node.group = "root".into();
+            }
+        }
+    }
+
+    /// `motd [-r]`: show the message of the day again, or with `-r` write a
+    /// new one first
+    pub(super) fn cmd_motd(&mut self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] => {}
+            ["-r"] | ["--refresh"] => self.refresh_motd(),
+            _ => return CmdOutput::usage("usage: motd [-r]"),
+        }
+        if self.kernel.fs.resolve(MOTD).is_none() {
+            self.refresh_motd();
+        }
+        CmdOutput::ok(
+            self.kernel
+                .fs
+                .resolve(MOTD)
+                .map(|n| n.data.trim_end().to_string())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn motd_tracks_logins_and_uptime_record() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.boot_with_params();
+
+        let motd = sys.cmd_motd(&[]).flatten();
+        assert!(motd.starts_with("Welcome to kpawnd GNU/Linux"));
+        assert!(motd.contains("\nTip: "));
+        assert!(!motd.contains("Last login"));
+
+        sys.record_login("user");
+        sys.record_login("root");
+        sys.record_login("user");
+        let log = sys.kernel.fs.resolve(LASTLOG).unwrap().data.clone();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.lines().last().unwrap().starts_with("user tty1 "));
+        let motd = sys.cmd_motd(&["-r"]).flatten();
+        assert!(motd.contains("\nLast login: "));
+        assert!(motd.ends_with(" on tty1"));
+
+        sys.write_system_file(UPTIME_RECORD, "7200\n");
+        assert_eq!(sys.record_uptime(), 7200);
+        assert!(sys
+            .cmd_motd(&["-r"])
+            .flatten()
+            .contains("Uptime record: 2 hours\n"));
+        assert_eq!(sys.cmd_motd(&["-x"]).flatten(), "usage: motd [-r]");
+    }
+}
@@ -0,0 +1,335 @@
+use super::{System, B64, BINARY_PREFIX};
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+use base64::Engine as _;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+// Filesystems that are always present and cannot be unmounted
+const BASE_MOUNTS: &[(&str, &str, &str, &str)] = &[
+    ("/dev/sda1", "/", "ext4", "rw,relatime"),
+    ("proc", "/proc", "proc", "rw,nosuid,nodev,noexec"),
+    ("sysfs", "/sys", "sysfs", "rw,nosuid,nodev,noexec"),
+];
+
+enum ArchiveKind {
+    Tar,
+    Zip,
+}
+
+fn archive_kind(bytes: &[u8]) -> Option<ArchiveKind> {
+    if bytes.starts_with(b"KP_TAR1") {
+        Some(ArchiveKind::Tar)
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        Some(ArchiveKind::Zip)
+    } else {
+        None
+    }
+}
+
+fn encode_data(bytes: &[u8]) -> String {
+    match String::from_utf8(bytes.to_vec()) {
+        Ok(text) => text,
+        Err(_) => format!("{}{}", BINARY_PREFIX, B64.encode(bytes)),
+    }
+}
+
+/// Insert a file or directory into an in-memory tree, creating parents as needed
+fn insert_entry(root: &mut Inode, rel: &str, is_dir: bool, data: &str) {
+    let parts: Vec<&str> = rel
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != "." && *p != "..")
+        .collect();
+    let Some((last, parents)) = parts.split_last() else {
+        return;
+    };
+    let mut node = root;
+    for part in parents {
+        node = node
+            .children
+            .entry(part.to_string())
+            .or_insert_with(|| Inode::dir(part));
+    }
+    if is_dir {
+        node.children
+            .entry(last.to_string())
+            .or_insert_with(|| Inode::dir(last));
+    } else {
+        let mut file = Inode::file(last, data);
+        file.permissions = "-r--r--r--".into();
+        node.children.insert(last.to_string(), file);
+    }
+}
+
+fn archive_tree(bytes: &[u8], kind: ArchiveKind) -> Result<Inode, String> {
+    let mut root = Inode::dir("/");
+    match kind {
+        ArchiveKind::Tar => {
+            let text = String::from_utf8_lossy(bytes);
+            for line in text.lines().skip(1) {
+                if let Some(path) = line.strip_prefix("D\t") {
+                    insert_entry(&mut root, path, true, "");
+                } else if let Some(rest) = line.strip_prefix("F\t") {
+                    let (path, b64) = rest.split_once('\t').ok_or("malformed file entry")?;
+                    let data = crate::cpp_accel::b64_decode(b64)
+                        .map_err(|_| "malformed base64 payload".to_string())?;
+                    insert_entry(&mut root, path, false, &encode_data(&data));
+                }
+            }
+        }
+        ArchiveKind::Zip => {
+            let mut archive =
+                ZipArchive::new(Cursor::new(bytes)).map_err(|_| "invalid zip archive")?;
+            for idx in 0..archive.len() {
+                let mut entry = archive
+                    .by_index(idx)
+                    .map_err(|_| "failed reading archive entry")?;
+                let name = entry.name().to_string();
+                if entry.is_dir() {
+                    insert_entry(&mut root, &name, true, "");
+                    continue;
+                }
+                let mut data = Vec::new();
+                entry
+                    .read_to_end(&mut data)
+                    .map_err(|_| format!("failed extracting {}", name))?;
+                insert_entry(&mut root, &name, false, &encode_data(&data));
+            }
+        }
+    }
+    for child in root.children.values_mut() {
+        make_read_only(child);
+    }
+    Ok(root)
+}
+
+fn make_read_only(node: &mut Inode) {
+    if node.is_dir {
+        node.permissions = "dr-xr-xr-x".into();
+        for child in node.children.values_mut() {
+            make_read_only(child);
+        }
+    }
+}
+
+impl System {
+    /// Every mounted filesystem as (source, target, type, options)
+    pub(super) fn mount_table(&self) -> Vec<(String, String, String, String)> {
+        let mut out: Vec<(String, String, String, String)> = BASE_MOUNTS
+            .iter()
+            .map(|(s, t, f, o)| (s.to_string(), t.to_string(), f.to_string(), o.to_string()))
+            .collect();
+        for m in self.kernel.fs.mounts() {
+            out.push((
+                m.source.clone(),
+                m.target.clone(),
+                m.fs_type.clone(),
+                m.options.clone(),
+            ));
+        }
+        out
+    }
+
+    pub(super) fn refresh_proc_mounts(&mut self) {
+        let text: String = self
+            .mount_table()
+            .iter()
+            .map(|(s, t, f, o)| format!("{} {} {} {} 0 0\n", s, t, f, o))
+            .collect();
+        if self.kernel.fs.resolve("/proc/mounts").is_some() {
+            let _ = self.kernel.fs.write_file("/proc/mounts", &text);
+        } else {
+            let _ = self.kernel.fs.create_file("/proc/mounts", &text);
+        }
+    }
+
+    pub(super) fn cmd_mount(&mut self, args: &[&str]) -> CmdOutput {
+        if args.is_empty() {
+            return CmdOutput::ok(
+                self.mount_table()
+                    .iter()
+                    .map(|(s, t, f, o)| format!("{} on {} type {} ({})", s, t, f, o))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "mount: only root can do that");
+        }
+
+        let mut fs_type: Option<String> = None;
+        let mut options: Vec<String> = Vec::new();
+        let mut values: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-t" => {
+                    if i + 1 >= args.len() {
+                        return CmdOutput::usage("mount: option requires an argument -- 't'");
+                    }
+                    fs_type = Some(args[i + 1].to_string());
+                    i += 2;
+                }
+                "-o" => {
+                    if i + 1 >= args.len() {
+                        return CmdOutput::usage("mount: option requires an argument -- 'o'");
+                    }
+                    options.extend(args[i + 1].split(',').map(|o| o.to_string()));
+                    i += 2;
+                }
+                "-r" => {
+                    options.push("ro".into());
+                    i += 1;
+                }
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(1, format!("mount: unsupported option '{}'", flag));
+                }
+                val => {
+                    values.push(val);
+                    i += 1;
+                }
+            }
+        }
+
+        if values.len() != 2 {
+            return CmdOutput::usage("usage: mount [-t TYPE] [-o OPTIONS] SOURCE TARGET");
+        }
+        let source = values[0];
+        let target = values[1];
+        if let Some(out) = self.mount_image(source, target, fs_type.as_deref(), &options) {
+            return out;
+        }
+
+        let is_loop = options.iter().any(|o| o == "loop");
+        let archive = match self.kernel.fs.resolve(source) {
+            Some(node) if !node.is_dir && (is_loop || fs_type.is_none()) => {
+                match self.read_file_bytes(source) {
+                    Ok(bytes) => archive_kind(&bytes).map(|kind| (bytes, kind)),
+                    Err(e) => return CmdOutput::error(1, format!("mount: {}", e)),
+                }
+            }
+            _ => None,
+        };
+
+        let (contents, fs_type) = if let Some((bytes, kind)) = archive {
+            let kind_name = match kind {
+                ArchiveKind::Tar => "tar",
+                ArchiveKind::Zip => "zip",
+            };
+            let tree = match archive_tree(&bytes, kind) {
+                Ok(tree) => tree,
+                Err(e) => return CmdOutput::error(1, format!("mount: {}: {}", source, e)),
+            };
+            options.retain(|o| o != "rw" && o != "ro");
+            options.insert(0, "ro".into());
+            (tree, fs_type.unwrap_or_else(|| kind_name.into()))
+        } else if is_loop {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "mount: {}: failed to setup loop device: unrecognized archive format",
+                    source
+                ),
+            );
+        } else {
+            let fs_type = fs_type.unwrap_or_else(|| {
+                if source == "tmpfs" {
+                    "tmpfs".into()
+                } else {
+                    "ext4".into()
+                }
+            });
+            let mut dir = Inode::dir("/");
+            if fs_type == "tmpfs" {
+                dir.permissions = "drwxrwxrwt".into();
+            }
+            (dir, fs_type)
+        };
+
+        if !options.iter().any(|o| o == "ro" || o == "rw") {
+            options.insert(0, "rw".into());
+        }
+        if fs_type != "tmpfs" && !options.iter().any(|o| o == "relatime") {
+            options.push("relatime".into());
+        }
+        let opts = options.join(",");
+
+        match self
+            .kernel
+            .fs
+            .mount(source, target, &fs_type, &opts, contents)
+        {
+            Ok(()) => {
+                self.refresh_proc_mounts();
+                CmdOutput::ok(format!("mounted {} on {} type {}", source, target, fs_type))
+            }
+            Err(e) => CmdOutput::error(1, format!("mount: {}: {}", target, e)),
+        }
+    }
+
+    pub(super) fn cmd_umount(&mut self, args: &[&str]) -> CmdOutput {
+        if args.len() != 1 {
+            return CmdOutput::usage("usage: umount TARGET");
+        }
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "umount: only root can do that");
+        }
+
+        let target = args[0];
+        let norm = self.kernel.fs.normalize(target);
+        if BASE_MOUNTS.iter().any(|(_, t, _, _)| *t == norm) {
+            return CmdOutput::error(1, format!("umount: {}: target is busy.", target));
+        }
+        // A loop mount's contents go back into its image
+        let loop_tree = self
+            .kernel
+            .fs
+            .mounts()
+            .iter()
+            .rev()
+            .find(|m| m.target == norm || m.source == target)
+            .filter(|m| m.source.starts_with("/dev/loop"))
+            .and_then(|m| self.kernel.fs.resolve(&m.target).cloned());
+        match self.kernel.fs.umount(target) {
+            Ok(mount) => {
+                if let Some(tree) = loop_tree {
+                    self.release_loop(&mount.source, tree);
+                }
+                self.refresh_proc_mounts();
+                CmdOutput::ok(format!("unmounted {}", mount.target))
+            }
+            Err(e) => CmdOutput::error(1, format!("umount: {}: {}", target, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_root_mounts_and_unmounts() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_dir("/tmp/m").unwrap();
+
+        let out = sys.cmd_mount(&["-t", "tmpfs", "tmpfs", "/tmp/m"]);
+        assert_eq!(out.stderr, "mount: only root can do that");
+        assert_eq!(out.status, 1);
+        assert!(!sys.cmd_mount(&[]).stdout.contains("/tmp/m"));
+
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert_eq!(sys.cmd_mount(&["-t", "tmpfs", "tmpfs", "/tmp/m"]).status, 0);
+        assert!(sys
+            .cmd_mount(&[])
+            .stdout
+            .contains("tmpfs on /tmp/m type tmpfs"));
+
+        sys.shell.env.insert("USER".into(), "user".into());
+        let out = sys.cmd_umount(&["/tmp/m"]);
+        assert_eq!(out.stderr, "umount: only root can do that");
+        assert_eq!(out.status, 1);
+        assert!(sys.exec("umount /tmp/m || echo denied").ends_with("denied"));
+        assert!(sys.cmd_mount(&[]).stdout.contains("/tmp/m"));
+    }
+}
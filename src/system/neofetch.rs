@@ -0,0 +1,326 @@
+use super::System;
+use crate::shell::CmdOutput;
+
+const CONFIG: &str = ".config/neofetch/config.conf";
+
+/// (name, colour, art)
+const LOGOS: &[(&str, &str, &str)] = &[
+    (
+        "kpawnd",
+        "cyan",
+        r"    __                                  __
+   / /______  ____ __      ______  ____/ /
+  / //_/ __ \/ __ `/ | /| / / __ \/ __  /
+ / ,< / /_/ / /_/ /| |/ |/ / / / / /_/ /
+/_/|_/ .___/\__,_/ |__/|__/_/ /_/\__,_/
+    /_/
+         .--.
+        |o_o |
+        |:_/ |
+       //   \ \
+      (|     | )
+     /'\_   _/`\
+     \___)=(___/",
+    ),
+    (
+        "debian",
+        "red",
+        r"       _,met$$$$$gg.
+    ,g$$$$$$$$$$$$$$$P.
+  ,g$$P$$       $$$Y$$.
+ ,$$P'              `$$$.
+',$$P       ,ggs.     `$$b:
+`d$$'     ,$P'   .    $$$
+ $$P      d$'     ,    $$P
+ $$:      $$.   -    ,d$$'
+ $$;      Y$b._   _,d$P'
+ Y$$.    `.`'Y$$$$P'
+ `$$b      '-.__
+  `Y$$b
+   `Y$$.
+     `$$b.
+       `Y$$b.
+         `'Y$b._
+             `'''",
+    ),
+    (
+        "arch",
+        "blue",
+        r"                   -`
+                  .o+`
+                 `ooo/
+                `+oooo:
+               `+oooooo:
+               -+oooooo+:
+             `/:-:++oooo+:
+            `/++++/+++++++:
+           `/++++++++++++++:
+          `/+++ooooooooooooo/`
+         ./ooosssso++osssssso+`
+        .oossssso-````/ossssss+`
+       -osssssso.      :ssssssso.
+      :osssssss/        osssso+++.
+     /ossssssss/        +ssssooo/-
+   `/ossssso+/:-        -:/+osssso+-
+  `+sso+:-`                 `.-/+oso:
+ `++:.                           `-/+/
+ .`                                 `/",
+    ),
+];
+
+const FIELDS: &[&str] = &[
+    "os",
+    "host",
+    "kernel",
+    "uptime",
+    "packages",
+    "shell",
+    "resolution",
+    "terminal",
+    "cpu",
+    "memory",
+];
+
+fn logo(name: &str) -> Option<&'static (&'static str, &'static str, &'static str)> {
+    LOGOS.iter().find(|(n, _, _)| *n == name)
+}
+
+/// The browser window in device pixels. Native builds (the tests) have no
+/// window
+fn resolution() -> Option<(u32, u32)> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let w = web_sys::window()?;
+        let ratio = w.device_pixel_ratio();
+        let width = w.inner_width().ok()?.as_f64()? * ratio;
+        let height = w.inner_height().ok()?.as_f64()? * ratio;
+        Some((width.round() as u32, height.round() as u32))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// `1 hour, 5 mins` the way neofetch spells uptime
+pub(super) fn format_uptime(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    let unit = |n: u64, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let mut parts = Vec::new();
+    if days > 0 {
+        parts.push(unit(days, "day", "days"));
+    }
+    if hours > 0 {
+        parts.push(unit(hours, "hour", "hours"));
+    }
+    if mins > 0 {
+        parts.push(unit(mins, "min", "mins"));
+    }
+    if parts.is_empty() {
+        parts.push(unit(secs, "sec", "secs"));
+    }
+    parts.join(", ")
+}
+
+impl System {
+    /// `neofetch [--ascii NAME] [--off] [--stdout]`: system facts beside a
+    /// logo. `~/.config/neofetch/config.conf` may set `ascii_distro=` and
+    /// `hide=` (a comma list of fields)
+    pub(super) fn cmd_neofetch(&self, args: &[&str]) -> CmdOutput {
+        let home = Self::default_home_for_user(&self.current_user());
+        let config = self
+            .kernel
+            .fs
+            .resolve(&format!("{}/{}", home, CONFIG))
+            .map(|n| n.data.clone())
+            .unwrap_or_default();
+        let setting = |key: &str| {
+            config.lines().find_map(|line| {
+                let (k, v) = line.split_once('=')?;
+                (k.trim() == key).then(|| v.trim().trim_matches('"').to_string())
+            })
+        };
+        let mut ascii = setting("ascii_distro")
+            .filter(|name| name != "auto")
+            .unwrap_or_else(|| "kpawnd".into());
+        let hidden: Vec<String> = setting("hide")
+            .map(|list| list.split(',').map(|f| f.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        let mut show_logo = true;
+        let mut color = !self.output_captured;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match *arg {
+                "--ascii" | "--ascii_distro" | "-a" => match iter.next() {
+                    Some(name) => ascii = name.to_lowercase(),
+                    None => {
+                        return CmdOutput::error(
+                            1,
+                            format!("neofetch: option '{}' needs a name", arg),
+                        )
+                    }
+                },
+                "--off" => show_logo = false,
+                "--stdout" => {
+                    show_logo = false;
+                    color = false;
+                }
+                other => {
+                    return CmdOutput::error(1, format!("neofetch: unknown option '{}'", other))
+                }
+            }
+        }
+        let Some(&(_, logo_color, art)) = logo(&ascii) else {
+            let names: Vec<&str> = LOGOS.iter().map(|(n, _, _)| *n).collect();
+            return CmdOutput::error(
+                1,
+                format!(
+                    "neofetch: unknown ascii '{}' (try {})",
+                    ascii,
+                    names.join(", ")
+                ),
+            );
+        };
+
+        let paint = |text: &str, c: &str| {
+            if color {
+                format!("\x1b[COLOR:{}]{}\x1b[COLOR:reset]", c, text)
+            } else {
+                text.to_string()
+            }
+        };
+        let user = self.current_user();
+        let host = self.cmd_hostname().stdout.trim().to_string();
+        let title = format!("{}@{}", user, host);
+        let mut info = vec![
+            format!("{}@{}", paint(&user, logo_color), paint(&host, logo_color)),
+            "-".repeat(title.len()),
+        ];
+        for field in FIELDS {
+            if hidden.iter().any(|h| h == field) {
+                continue;
+            }
+            let Some(value) = self.neofetch_field(field) else {
+                continue;
+            };
+            let mut label = field.to_string();
+            label[..1].make_ascii_uppercase();
+            if *field == "os" || *field == "cpu" {
+                label.make_ascii_uppercase();
+            }
+            info.push(format!("{}: {}", paint(&label, logo_color), value));
+        }
+        if color {
+            info.push(String::new());
+            info.push(
+                [
+                    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+                ]
+                .iter()
+                .map(|c| paint("███", c))
+                .collect(),
+            );
+        }
+        if !show_logo {
+            return CmdOutput::ok(info.join("\n"));
+        }
+
+        let art: Vec<&str> = art.lines().collect();
+        let width = art.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let rows = art.len().max(info.len());
+        CmdOutput::ok(
+            (0..rows)
+                .map(|i| {
+                    let left = art.get(i).copied().unwrap_or("");
+                    let pad = " ".repeat(width - left.chars().count() + 3);
+                    let right = info.get(i).map_or("", String::as_str);
+                    format!("{}{}{}", paint(left, logo_color), pad, right)
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    fn neofetch_field(&self, field: &str) -> Option<String> {
+        Some(match field {
+            "os" => {
+                let release = self.kernel.fs.resolve("/etc/os-release")?;
+                let pretty = release.data.lines().find_map(|l| {
+                    l.strip_prefix("PRETTY_NAME=")
+                        .map(|v| v.trim_matches('"').to_string())
+                })?;
+                format!("{} wasm32", pretty)
+            }
+            "host" => "WASM Virtual Machine".into(),
+            "kernel" => crate::kernel::KERNEL_VERSION.into(),
+            "uptime" => format_uptime(self.kernel.uptime_ms() / 1000),
+            "packages" => format!("{} (dpkg)", self.package_db().iter().count()),
+            "shell" => {
+                let shell = self
+                    .shell
+                    .env
+                    .get("SHELL")
+                    .map_or("/bin/sh", String::as_str);
+                shell.rsplit('/').next().unwrap_or(shell).to_string()
+            }
+            "resolution" => {
+                let (w, h) = resolution()?;
+                format!("{}x{}", w, h)
+            }
+            "terminal" => "kpawnd-term".into(),
+            "cpu" => "WebAssembly vCPU (1)".into(),
+            "memory" => {
+                let (used, total) = self.kernel.mem.usage();
+                format!("{}MiB / {}MiB", used / (1024 * 1024), total / (1024 * 1024))
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neofetch_logos_fields_and_config() {
+        assert_eq!(format_uptime(42), "42 secs");
+        assert_eq!(format_uptime(3900), "1 hour, 5 mins");
+        assert_eq!(format_uptime(2 * 86_400 + 60), "2 days, 1 min");
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let out = sys.cmd_neofetch(&["--stdout"]).flatten();
+        assert!(out.contains("\nOS: kpawnd GNU/Linux"));
+        assert!(out.contains(&format!("\nKernel: {}", crate::kernel::KERNEL_VERSION)));
+        assert!(out.contains("\nShell: bash"));
+        assert!(out.contains("MiB / 32MiB"));
+        assert!(!out.contains("\x1b["));
+
+        let arch = sys.cmd_neofetch(&["--ascii", "arch"]).flatten();
+        assert!(arch.starts_with("\x1b[COLOR:blue]"));
+        assert!(arch.contains("`ooo/"));
+        assert!(sys
+            .cmd_neofetch(&["--ascii", "gentoo"])
+            .flatten()
+            .starts_with("neofetch: unknown ascii 'gentoo'"));
+
+        let home = System::default_home_for_user(&sys.current_user());
+        sys.ensure_dir_all(&format!("{}/.config/neofetch", home))
+            .unwrap();
+        sys.kernel
+            .fs
+            .create_file(
+                &format!("{}/{}", home, CONFIG),
+                "ascii_distro=\"debian\"\nhide=memory, packages\n",
+            )
+            .unwrap();
+        let out = sys.cmd_neofetch(&[]).flatten();
+        assert!(out.contains("$$$$$gg."));
+        assert!(!out.contains("Memory:"));
+        assert!(!out.contains("Packages:"));
+    }
+}
@@ -0,0 +1,893 @@
+//! `exec_start`: a line runs as it does under `exec`, then the network
+//! requests its commands queued (`curl`, `wget`, `ping`, `apt update`, `lynx`,
+//! `rss`, `weather`, `iss`, `gh`) are carried out one at a time. The
+//! frontend takes each from `net_request`, awaits `net_perform` for the
+//! `fetch` and hands the answer to `net_reply`, so the system is never
+//! borrowed while a request is in flight. Output comes back from
+//! `exec_finish` with the rest, and downloads land in the VFS chunk by chunk.
+//!
+//! The calls go in that order, one line at a time; `execAsync` in
+//! `js/network.js` is the one place that makes them:
+//!
+//! 1. `exec_start(line)`, refused while an earlier line is unfinished
+//! 2. while `net_request()` returns a request: `net_reply(await
+//!    net_perform(request))`. A reply to anything but the request last
+//!    handed out is ignored
+//! 3. `exec_finish()`, which drops requests still outstanding as Ctrl+C
+//!    would and returns the output
+use super::{gh, human_size, iss, weather, System, SystemEvent};
+use crate::clock;
+use crate::network;
+use crate::shell::CmdOutput;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+/// Large files arrive in ranged requests of this size, each stored as it lands
+const CHUNK_SIZE: u64 = 1024 * 1024;
+const PING_COUNT: u16 = 4;
+const PING_INTERVAL_MS: i32 = 200;
+
+/// `example.com` becomes `https://example.com`; IPv4 addresses and
+/// `prefer_https = false` get plain http
+pub(super) fn normalize_url(raw: &str, prefer_https: bool) -> String {
+    let target = raw.trim().trim_matches(['\'', '"', '`']);
+    let lower = target.to_ascii_lowercase();
+    if target.is_empty() || lower.starts_with("http://") || lower.starts_with("https://") {
+        return target.to_string();
+    }
+    let ipv4 = target.parse::<std::net::Ipv4Addr>().is_ok();
+    let scheme = if prefer_https && !ipv4 {
+        "https"
+    } else {
+        "http"
+    };
+    format!("{}://{}", scheme, target)
+}
+
+/// Host and port a URL connects to
+fn url_endpoint(url: &str) -> (&str, u16) {
+    let (https, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (scheme.eq_ignore_ascii_case("https"), rest),
+        None => (false, url),
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    match authority.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => (host, port.parse().unwrap()),
+        _ => (authority, if https { 443 } else { 80 }),
+    }
+}
+
+/// `2026-10-16 10:05:09`, the stamp wget puts on its lines
+fn wget_stamp() -> String {
+    let c = clock::civil(clock::now_secs());
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        c.year, c.month, c.day, c.hour, c.minute, c.second
+    )
+}
+
+/// wget's finished progress bar
+fn wget_bar(shown: &str, done: u64, total: Option<u64>, rate: &str) -> String {
+    let pct = total.filter(|&t| t > 0).map(|t| (done * 100 / t).min(100));
+    let filled = pct.unwrap_or(0) as usize / 5;
+    let bar = if filled >= 20 {
+        "=".repeat(20)
+    } else {
+        format!("{}>{}", "=".repeat(filled), " ".repeat(19 - filled))
+    };
+    let percent = pct.map_or("    ".to_string(), |p| format!("{:>3}%", p));
+    format!(
+        "{:<20}{}[{}] {:>7}  {:>9}",
+        shown,
+        percent,
+        bar,
+        human_size(done),
+        rate
+    )
+}
+
+/// curl's progress meter, header and the line for a finished transfer
+fn curl_meter(done: u64, total: Option<u64>, rate: &str) -> String {
+    let pct = total
+        .filter(|&t| t > 0)
+        .map_or(0, |t| (done * 100 / t).min(100));
+    let size = human_size(total.filter(|&t| t > 0).unwrap_or(done));
+    format!(
+        "  % Total    % Received % Xferd  Average Speed   Time    Time     Time  Current\n\
+         \x20                                Dload  Upload   Total   Spent    Left  Speed\n\
+         {:>3} {:>5}  {:>3} {:>5}    0     0  {:>5}      0 --:--:-- --:--:-- --:--:-- {:>5}",
+        pct,
+        size,
+        pct,
+        human_size(done),
+        rate,
+        rate
+    )
+}
+
+/// What `ping` prints for the round trips to `host`, `None` for a lost probe
+fn ping_report(host: &str, rtts: &[Option<f64>]) -> String {
+    let mut out = vec![format!("PING {}", host)];
+    for (seq, rtt) in rtts.iter().enumerate() {
+        out.push(match rtt {
+            Some(ms) => format!("seq={}: time={:.1}ms", seq + 1, ms),
+            None => format!("seq={}: timeout", seq + 1),
+        });
+    }
+    let received: Vec<f64> = rtts.iter().flatten().copied().collect();
+    out.push(String::new());
+    out.push(format!("--- {} ping statistics ---", host));
+    out.push(format!(
+        "{} packets transmitted, {} received, {:.0}% packet loss",
+        rtts.len(),
+        received.len(),
+        (rtts.len() - received.len()) as f64 * 100.0 / rtts.len().max(1) as f64
+    ));
+    let (min, avg, max) = if received.is_empty() {
+        (0.0, 0.0, 0.0)
+    } else {
+        (
+            received.iter().copied().fold(f64::INFINITY, f64::min),
+            received.iter().sum::<f64>() / received.len() as f64,
+            received.iter().copied().fold(0.0, f64::max),
+        )
+    };
+    out.push(format!(
+        "rtt min/avg/max = {:.1}/{:.1}/{:.1} ms",
+        min, avg, max
+    ));
+    out.join("\n")
+}
+
+/// One network operation, handed out by `System::net_request` for
+/// `net_perform`, numbered so its reply can be matched to it
+#[wasm_bindgen]
+pub struct NetRequest(u64, Request);
+
+enum Request {
+    Get(String),
+    Curl {
+        url: String,
+        method: String,
+        show_headers: bool,
+    },
+    /// The next chunk of a download, from byte `from`
+    Range {
+        url: String,
+        from: u64,
+    },
+    /// A ping probe; `pause` waits out the interval since the last one first
+    Ping {
+        url: String,
+        pause: bool,
+    },
+}
+
+/// What `net_perform` got back, for `System::net_reply`, under the
+/// number of the request it answers
+#[wasm_bindgen]
+pub struct NetReply(u64, Reply);
+
+enum Reply {
+    Body(Result<String, String>),
+    Range(Result<network::RangeResponse, String>),
+    Ping(Option<f64>),
+}
+
+/// Carry out `request`. Nothing in the system is touched, so other calls
+/// can go into it while this is awaited
+#[wasm_bindgen]
+pub async fn net_perform(request: NetRequest) -> NetReply {
+    let NetRequest(serial, request) = request;
+    let reply = match request {
+        Request::Get(url) => Reply::Body(network::NetworkStack::http_get(&url).await),
+        Request::Curl {
+            url,
+            method,
+            show_headers,
+        } => Reply::Body(network::curl_text(&url, &method, show_headers).await),
+        Request::Range { url, from } => {
+            Reply::Range(network::fetch_range(&url, Some(from), from + CHUNK_SIZE - 1).await)
+        }
+        Request::Ping { url, pause } => {
+            if pause {
+                network::sleep_ms(PING_INTERVAL_MS).await;
+            }
+            Reply::Ping(network::ping_rtt(&url).await.ok())
+        }
+    };
+    NetReply(serial, reply)
+}
+
+/// A `wget`/`curl -o` transfer, fetched in ranged chunks and written into
+/// the file as each arrives
+struct Download {
+    id: u32,
+    wget: bool,
+    quiet: bool,
+    shown: String,
+    url: String,
+    host: String,
+    started: f64,
+    out: Vec<String>,
+    offset: u64,
+    total: Option<u64>,
+    fetched: u64,
+    responded: bool,
+}
+
+/// A network command waiting on its requests
+enum Job {
+    /// `wget -O - URL`: the body on stdout
+    Fetch(String),
+    Curl {
+        url: String,
+        method: String,
+        show_headers: bool,
+    },
+    /// A page for `lynx`, laid out once it arrives
+    Browse {
+        url: String,
+        dump: bool,
+    },
+    Feed(String),
+    Weather {
+        location: String,
+        imperial: bool,
+        url: String,
+    },
+    /// The tracking APIs in turn, until one answers
+    Iss {
+        next: usize,
+    },
+    Github {
+        target: String,
+        urls: Vec<String>,
+        fetched: Vec<Result<String, String>>,
+    },
+    Ping {
+        target: String,
+        url: String,
+        rtts: Vec<Option<f64>>,
+    },
+    AptUpdate {
+        mirror: String,
+    },
+    Download(Box<Download>),
+}
+
+/// After a reply: the job's output, or the job again with more to fetch
+enum Step {
+    Done(String),
+    Next(Job),
+}
+
+impl Job {
+    /// The work behind a queued event; anything else is handed back
+    fn for_event(event: SystemEvent) -> Result<Job, SystemEvent> {
+        Ok(match event {
+            SystemEvent::Fetch { url } => Job::Fetch(normalize_url(&url, true)),
+            SystemEvent::Curl {
+                method,
+                headers,
+                url,
+            } => Job::Curl {
+                url: normalize_url(&url, false),
+                method,
+                show_headers: headers,
+            },
+            SystemEvent::Download {
+                id,
+                tool,
+                quiet,
+                shown,
+                offset,
+                url,
+            } => Job::Download(Box::new(Download::new(
+                id, &tool, quiet, shown, offset, &url,
+            ))),
+            SystemEvent::Browse { url, dump } => Job::Browse { url, dump },
+            SystemEvent::Feed { url } => Job::Feed(url),
+            SystemEvent::Weather { location, imperial } => Job::Weather {
+                url: weather::wttr_url(&location),
+                location,
+                imperial,
+            },
+            SystemEvent::Iss => Job::Iss { next: 0 },
+            SystemEvent::Github { target } => Job::Github {
+                urls: gh::api_paths(&target)
+                    .iter()
+                    .map(|path| format!("{}{}", gh::API, path))
+                    .collect(),
+                target,
+                fetched: Vec::new(),
+            },
+            SystemEvent::Ping { host } => {
+                let target = host.trim().trim_matches(['\'', '"', '`']).to_string();
+                Job::Ping {
+                    url: normalize_url(&target, true),
+                    target,
+                    rtts: Vec::new(),
+                }
+            }
+            SystemEvent::AptUpdate { mirror } => Job::AptUpdate { mirror },
+            other => return Err(other),
+        })
+    }
+
+    fn request(&self) -> Request {
+        match self {
+            Job::Fetch(url) | Job::Browse { url, .. } | Job::Feed(url) => Request::Get(url.clone()),
+            Job::Weather { url, .. } => Request::Get(url.clone()),
+            Job::Curl {
+                url,
+                method,
+                show_headers,
+            } => Request::Curl {
+                url: url.clone(),
+                method: method.clone(),
+                show_headers: *show_headers,
+            },
+            Job::Iss { next } => Request::Get(iss::ISS_SOURCES[*next].to_string()),
+            Job::Github { urls, fetched, .. } => {
+                Request::Get(urls.get(fetched.len()).cloned().unwrap_or_default())
+            }
+            Job::Ping { url, rtts, .. } => Request::Ping {
+                url: url.clone(),
+                pause: !rtts.is_empty(),
+            },
+            Job::AptUpdate { mirror } => Request::Get(format!("{}/", mirror)),
+            Job::Download(d) => Request::Range {
+                url: d.url.clone(),
+                from: d.offset,
+            },
+        }
+    }
+}
+
+impl Download {
+    fn new(id: u32, tool: &str, quiet: bool, shown: String, offset: u64, url: &str) -> Download {
+        let url = normalize_url(url, true);
+        let host = url_endpoint(&url).0.to_string();
+        let wget = tool == "wget";
+        let mut out = Vec::new();
+        if wget && !quiet {
+            out.push(format!("--{}--  {}", wget_stamp(), url));
+            out.push(format!(
+                "Resolving {0}... Connecting to {0}... connected.",
+                host
+            ));
+        }
+        Download {
+            id,
+            wget,
+            quiet,
+            shown,
+            url,
+            host,
+            started: js_sys::Date::now(),
+            out,
+            offset,
+            total: None,
+            fetched: 0,
+            responded: false,
+        }
+    }
+}
+
+/// The line `exec_start` ran, with the network work it left, until
+/// `exec_finish`
+pub(super) struct NetRun {
+    pane: Option<u32>,
+    output: String,
+    jobs: VecDeque<Job>,
+}
+
+impl NetRun {
+    fn push_output(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.output.push_str(text);
+    }
+}
+
+#[wasm_bindgen]
+impl System {
+    /// `exec` for commands that may reach the network. Their requests are
+    /// left for `net_request`; `exec_finish` has the output once the last
+    /// one is answered
+    #[wasm_bindgen]
+    pub fn exec_start(&mut self, line: String) -> Result<(), String> {
+        if self.net_run.is_some() {
+            return Err("exec_start: the previous line has not finished".into());
+        }
+        let pane = self.pane_record_input(&line);
+        let output = self.exec_typed(&line);
+        let mut jobs = VecDeque::new();
+        for event in self.take_events() {
+            match Job::for_event(event) {
+                Ok(job) => jobs.push_back(job),
+                Err(other) => self.events.push(other),
+            }
+        }
+        self.net_run = Some(NetRun { pane, output, jobs });
+        self.net_serial += 1;
+        Ok(())
+    }
+
+    /// The next request the running line is waiting on, if any
+    #[wasm_bindgen]
+    pub fn net_request(&self) -> Option<NetRequest> {
+        let job = self.net_run.as_ref()?.jobs.front()?;
+        Some(NetRequest(self.net_serial, job.request()))
+    }
+
+    /// The answer to the last `net_request`. Returns whether it was taken:
+    /// a reply to an earlier request, or with none waiting, is dropped
+    #[wasm_bindgen]
+    pub fn net_reply(&mut self, reply: NetReply) -> bool {
+        let NetReply(serial, reply) = reply;
+        if serial != self.net_serial {
+            return false;
+        }
+        let Some(job) = self.net_run.as_mut().and_then(|run| run.jobs.pop_front()) else {
+            return false;
+        };
+        self.net_serial += 1;
+        let text = match self.net_step(job, reply) {
+            Step::Next(job) => {
+                if let Some(run) = self.net_run.as_mut() {
+                    run.jobs.push_front(job);
+                }
+                return true;
+            }
+            Step::Done(text) => text,
+        };
+        if let Some(run) = self.net_run.as_mut() {
+            run.push_output(&text);
+        }
+        true
+    }
+
+    /// What the line `exec_start` ran printed, network output included.
+    /// Requests never answered are dropped as Ctrl+C drops them
+    #[wasm_bindgen]
+    pub fn exec_finish(&mut self) -> String {
+        self.net_interrupt();
+        let Some(run) = self.net_run.take() else {
+            return String::new();
+        };
+        self.pane_record_output(run.pane, &run.output);
+        self.deliver_events();
+        run.output
+    }
+}
+
+impl System {
+    /// Put a request to `url` on the packet bus so `tcpdump` can show it
+    fn record_fetch(&mut self, url: &str, received: usize) {
+        let (host, port) = url_endpoint(url);
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let path = rest.find('/').map_or("/", |i| &rest[i..]);
+        let sent = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, host).len();
+        self.network
+            .record_tcp_exchange(js_sys::Date::now(), host, port, sent, received);
+    }
+
+    fn record_body(&mut self, url: &str, fetched: &Result<String, String>) {
+        if let Ok(body) = fetched {
+            self.record_fetch(url, body.len());
+        }
+    }
+
+    /// Ctrl+C while requests are out: drop them. A ping still prints what
+    /// it has so far and downloads keep their partial files
+    pub(super) fn net_interrupt(&mut self) -> bool {
+        let Some(run) = self.net_run.as_mut() else {
+            return false;
+        };
+        let jobs = std::mem::take(&mut run.jobs);
+        let stopped = !jobs.is_empty();
+        for job in jobs {
+            let text = match job {
+                Job::Ping { target, rtts, .. } if !rtts.is_empty() => ping_report(&target, &rtts),
+                Job::Download(d) => {
+                    self.end_download(d.id, false);
+                    let mut out = d.out;
+                    if d.offset > 0 {
+                        out.push(format!(
+                            "Partial file kept; run 'downloads resume {}' to continue.",
+                            d.id
+                        ));
+                    }
+                    out.join("\n")
+                }
+                _ => String::new(),
+            };
+            if let Some(run) = self.net_run.as_mut() {
+                run.push_output(&text);
+            }
+        }
+        stopped
+    }
+
+    /// Hand `reply` to the job that asked for it
+    fn net_step(&mut self, job: Job, reply: Reply) -> Step {
+        let fetched = match reply {
+            Reply::Body(fetched) => fetched,
+            Reply::Range(resp) => {
+                let Job::Download(mut d) = job else {
+                    return Step::Done(String::new());
+                };
+                if !self.download_step(&mut d, resp) {
+                    return Step::Next(Job::Download(d));
+                }
+                return Step::Done(d.out.join("\n"));
+            }
+            Reply::Ping(rtt) => {
+                let Job::Ping {
+                    target,
+                    url,
+                    mut rtts,
+                } = job
+                else {
+                    return Step::Done(String::new());
+                };
+                let seq = rtts.len() as u16 + 1;
+                self.network.record_ping(
+                    js_sys::Date::now() - rtt.unwrap_or(0.0),
+                    &target,
+                    seq,
+                    rtt,
+                );
+                rtts.push(rtt);
+                if seq < PING_COUNT {
+                    return Step::Next(Job::Ping { target, url, rtts });
+                }
+                if rtts.iter().all(Option::is_none) {
+                    self.last_status = 1;
+                }
+                return Step::Done(ping_report(&target, &rtts));
+            }
+        };
+        Step::Done(match job {
+            Job::Fetch(url) => match fetched {
+                Ok(body) => {
+                    self.record_fetch(&url, body.len());
+                    body.trim_end_matches('\n').to_string()
+                }
+                Err(_) => {
+                    self.last_status = 4;
+                    format!(
+                        "wget: unable to resolve host address '{}'",
+                        url_endpoint(&url).0
+                    )
+                }
+            },
+            Job::Curl { url, .. } => match fetched {
+                Ok(body) => {
+                    self.record_fetch(&url, body.len());
+                    body.trim_end_matches('\n').to_string()
+                }
+                Err(e) => {
+                    self.last_status = 7;
+                    e
+                }
+            },
+            Job::Browse { url, dump } => match fetched {
+                Ok(body) => {
+                    self.record_fetch(&url, body.len());
+                    self.lynx_show(&url, &body, dump)
+                }
+                Err(_) => {
+                    let why = self.lynx_failed(&url, "Unable to connect to remote host.");
+                    self.settle(CmdOutput::error(1, why))
+                }
+            },
+            Job::Feed(url) => {
+                self.record_body(&url, &fetched);
+                let out = self.rss_loaded(&url, fetched);
+                self.settle(out)
+            }
+            Job::Weather {
+                location,
+                imperial,
+                url,
+            } => {
+                self.record_body(&url, &fetched);
+                let out = self.weather_finish(&location, imperial, fetched);
+                self.settle(out)
+            }
+            Job::Iss { next } => {
+                self.record_body(iss::ISS_SOURCES[next], &fetched);
+                if fetched.is_err() && next + 1 < iss::ISS_SOURCES.len() {
+                    return Step::Next(Job::Iss { next: next + 1 });
+                }
+                let out = self.iss_finish(fetched);
+                self.settle(out)
+            }
+            Job::Github {
+                target,
+                urls,
+                fetched: mut answers,
+            } => {
+                if let Some(url) = urls.get(answers.len()) {
+                    self.record_body(url, &fetched);
+                }
+                // Once over the limit every request is refused
+                let limited = fetched.as_ref().is_ok_and(|b| gh::rate_limited(b));
+                answers.push(fetched);
+                if !limited && answers.len() < urls.len() {
+                    return Step::Next(Job::Github {
+                        target,
+                        urls,
+                        fetched: answers,
+                    });
+                }
+                let out = self.gh_finish(&target, answers);
+                self.settle(out)
+            }
+            Job::AptUpdate { mirror } => {
+                self.record_body(&mirror, &fetched);
+                let (host, _) = url_endpoint(&mirror);
+                let fetched = fetched
+                    .map(|body| body.len())
+                    .map_err(|_| format!("Could not resolve '{}'", host));
+                self.apt_update_finish(&mirror, fetched)
+            }
+            Job::Ping { .. } | Job::Download(_) => String::new(),
+        })
+    }
+
+    /// A finished command's status, kept for the rest of the line, and
+    /// everything it printed
+    fn settle(&mut self, out: CmdOutput) -> String {
+        self.last_status = out.status;
+        out.flatten()
+    }
+
+    /// Store one chunk of `d`; returns whether the transfer is over
+    fn download_step(
+        &mut self,
+        d: &mut Download,
+        resp: Result<network::RangeResponse, String>,
+    ) -> bool {
+        let resp = match resp {
+            Ok(resp) => resp,
+            Err(_) => {
+                self.end_download(d.id, false);
+                d.out.push(match (d.responded, d.wget) {
+                    (false, true) => format!("wget: unable to resolve host address '{}'", d.host),
+                    (false, false) => format!("curl: (6) Could not resolve host: {}", d.host),
+                    (true, true) => format!(
+                        "{} ({}) - Connection closed at byte {}.",
+                        wget_stamp(),
+                        human_size(d.offset),
+                        d.offset
+                    ),
+                    (true, false) => format!(
+                        "curl: (18) transfer closed with {} bytes remaining to read",
+                        d.total.map_or("unknown".to_string(), |t| {
+                            t.saturating_sub(d.offset).to_string()
+                        })
+                    ),
+                });
+                if d.offset > 0 {
+                    d.out.push(format!(
+                        "Partial file kept; run 'downloads resume {}' to continue.",
+                        d.id
+                    ));
+                }
+                self.last_status = match (d.responded, d.wget) {
+                    (_, true) => 4,
+                    (false, false) => 6,
+                    (true, false) => 18,
+                };
+                return true;
+            }
+        };
+        self.record_fetch(&d.url, resp.bytes.len());
+
+        if !d.responded {
+            d.responded = true;
+            // Asking past the end of the resource: the file is already whole
+            if resp.status == 416 && d.offset > 0 {
+                self.end_download(d.id, true);
+                if d.wget {
+                    d.out
+                        .push("The file is already fully retrieved; nothing to do.".into());
+                }
+                return true;
+            }
+            if d.wget {
+                if !d.quiet {
+                    d.out.push(format!(
+                        "HTTP request sent, awaiting response... {} {}",
+                        resp.status, resp.status_text
+                    ));
+                }
+                if resp.status >= 400 {
+                    self.end_download(d.id, true);
+                    d.out.push(format!(
+                        "{} ERROR {}: {}.",
+                        wget_stamp(),
+                        resp.status,
+                        resp.status_text
+                    ));
+                    self.last_status = 8;
+                    return true;
+                }
+            }
+            // A server that ignores Range sends everything from the start
+            if resp.status != 206 {
+                d.offset = 0;
+            }
+            d.total = resp.total;
+            if d.wget && !d.quiet {
+                let kind = resp.content_type.split(';').next().unwrap_or("").trim();
+                let length = d.total.map_or("unspecified".to_string(), |t| {
+                    format!("{} ({})", t, human_size(t))
+                });
+                let remaining = match d.total {
+                    Some(t) if d.offset > 0 => format!(
+                        ", {} ({}) remaining",
+                        t - d.offset,
+                        human_size(t - d.offset)
+                    ),
+                    _ => String::new(),
+                };
+                let kind = if kind.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [{}]", kind)
+                };
+                d.out
+                    .push(format!("Length: {}{}{}", length, remaining, kind));
+                d.out.push(format!("Saving to: '{}'", d.shown));
+                d.out.push(String::new());
+            }
+        }
+
+        let len = resp.bytes.len() as u64;
+        if let Err(e) = self.store_download_chunk(d.id, d.offset, &resp.bytes, d.total) {
+            self.end_download(d.id, false);
+            d.out.push(if d.wget {
+                format!("{}: {}", d.shown, e)
+            } else {
+                format!("curl: (23) Failure writing output to destination: {}", e)
+            });
+            self.last_status = if d.wget { 3 } else { 23 };
+            return true;
+        }
+        d.offset += len;
+        d.fetched += len;
+        let more = resp.status == 206 && len >= CHUNK_SIZE && d.total.is_none_or(|t| d.offset < t);
+        if more {
+            return false;
+        }
+
+        self.end_download(d.id, true);
+        if !d.quiet {
+            let secs = ((js_sys::Date::now() - d.started) / 1000.0).max(0.001);
+            let rate = format!("{}B/s", human_size((d.fetched as f64 / secs) as u64));
+            if d.wget {
+                d.out.push(format!(
+                    "{}    in {:.1}s",
+                    wget_bar(&d.shown, d.offset, Some(d.offset), &rate),
+                    secs
+                ));
+                d.out.push(String::new());
+                d.out.push(format!(
+                    "{} ({}) - '{}' saved [{}/{}]",
+                    wget_stamp(),
+                    rate,
+                    d.shown,
+                    d.offset,
+                    d.offset
+                ));
+            } else {
+                d.out.push(curl_meter(d.offset, d.total, &rate));
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_reports_and_apt_update_output() {
+        assert_eq!(normalize_url("example.com", true), "https://example.com");
+        assert_eq!(normalize_url("'10.0.0.1'", true), "http://10.0.0.1");
+        assert_eq!(normalize_url("example.com", false), "http://example.com");
+        assert_eq!(normalize_url("HTTPS://x.org/a", false), "HTTPS://x.org/a");
+        assert_eq!(url_endpoint("https://x.org/a?b"), ("x.org", 443));
+        assert_eq!(url_endpoint("http://localhost:8080"), ("localhost", 8080));
+
+        let report = ping_report("x.org", &[Some(10.0), None, Some(30.0), None]);
+        assert!(report.starts_with("PING x.org\nseq=1: time=10.0ms\nseq=2: timeout\n"));
+        assert!(report.contains("4 packets transmitted, 2 received, 50% packet loss"));
+        assert!(report.ends_with("rtt min/avg/max = 10.0/20.0/30.0 ms"));
+        assert!(wget_bar("f", 5, Some(10), "1B/s").contains(" 50%[==========>         ]"));
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert_eq!(sys.exec("apt update"), "");
+        let [SystemEvent::AptUpdate { mirror }] = &sys.take_events()[..] else {
+            panic!("expected an apt update event");
+        };
+        let out = sys.apt_update_finish(mirror, Ok(3000));
+        assert!(out.starts_with(&format!("Get:1 {} stable InRelease [3 kB]", mirror)));
+        assert!(out.ends_with("All packages are up to date."));
+        assert!(sys
+            .kernel
+            .fs
+            .resolve("/var/lib/apt/lists/last_update")
+            .is_some());
+
+        // The same through the steps the frontend drives, with the request
+        // failing
+        sys.exec_start("apt update".into()).unwrap();
+        let Some(NetRequest(serial, Request::Get(url))) = sys.net_request() else {
+            panic!("expected a request for the mirror");
+        };
+        assert_eq!(url, format!("{}/", mirror));
+        assert!(sys.net_reply(NetReply(serial, Reply::Body(Err("offline".into())))));
+        assert!(sys.net_request().is_none());
+        let out = sys.exec_finish();
+        assert!(out.starts_with(&format!("Err:1 {} stable InRelease\n", mirror)));
+        assert!(out.ends_with("old ones used instead."));
+        assert_eq!(sys.exec_finish(), "");
+    }
+
+    #[test]
+    fn network_calls_out_of_order() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.shell.env.insert("USER".into(), "root".into());
+        let offline = |serial| NetReply(serial, Reply::Body(Err("offline".into())));
+
+        // Nothing running: no requests, replies are dropped, nothing to finish
+        assert!(sys.net_request().is_none());
+        assert!(!sys.net_reply(offline(0)));
+        assert_eq!(sys.exec_finish(), "");
+
+        sys.exec_start("apt update; apt update".into()).unwrap();
+        assert!(sys.exec_start("echo hi".into()).is_err());
+        let Some(NetRequest(first, _)) = sys.net_request() else {
+            panic!("expected a request for the mirror");
+        };
+        // Asking again hands out the same request
+        assert_eq!(sys.net_request().map(|r| r.0), Some(first));
+        assert!(!sys.net_reply(offline(first + 1)));
+        assert!(sys.net_reply(offline(first)));
+        // A second answer to the first request is not taken for the second
+        assert!(!sys.net_reply(offline(first)));
+        let Some(NetRequest(second, _)) = sys.net_request() else {
+            panic!("expected the second apt update's request");
+        };
+        assert_ne!(second, first);
+
+        // Finishing early drops the request still out, and its late answer
+        let out = sys.exec_finish();
+        assert_eq!(out.matches("Err:1").count(), 1);
+        assert!(sys.net_request().is_none());
+        assert!(!sys.net_reply(offline(second)));
+
+        // A new line's requests are numbered apart from the last line's
+        sys.exec_start("apt update".into()).unwrap();
+        assert!(!sys.net_reply(offline(second)));
+        let Some(NetRequest(third, _)) = sys.net_request() else {
+            panic!("expected a request for the mirror");
+        };
+        assert!(sys.net_reply(offline(third)));
+        assert!(sys.exec_finish().contains("Err:1"));
+    }
+}
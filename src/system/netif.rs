@@ -0,0 +1,524 @@
+use super::System;
+use crate::network::{
+    broadcast_address, format_ipv4, network_address, parse_ipv4, prefix_mask, NetworkInterface,
+};
+use crate::shell::CmdOutput;
+
+/// Nameserver handed out on `eth0`'s network
+const NAMESERVER: &str = "10.0.2.3";
+
+/// Why a network tool can't reach a host with the interfaces as they are
+pub(super) enum Unreachable {
+    /// No route to a nameserver, so the name never resolved
+    Resolve,
+    /// No interface or route to the address itself
+    Route,
+}
+
+impl System {
+    /// Whether `host` can be reached with the interfaces and routes as
+    /// they are now, and if not, where it falls over
+    pub(super) fn host_unreachable(&self, host: &str) -> Option<Unreachable> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let host = if host == self.cmd_hostname().stdout.trim() {
+            "localhost"
+        } else {
+            host
+        };
+        if self.network.route_for(host).is_ok() {
+            return None;
+        }
+        let literal = host == "localhost" || host.contains(':') || parse_ipv4(host).is_some();
+        if !literal && self.network.route_for(NAMESERVER).is_err() {
+            Some(Unreachable::Resolve)
+        } else {
+            Some(Unreachable::Route)
+        }
+    }
+
+    pub(super) fn cmd_ifconfig(&mut self, args: &[&str]) -> CmdOutput {
+        let show_all = args.first() == Some(&"-a");
+        let args = if show_all { &args[1..] } else { args };
+        match args {
+            [] => CmdOutput::ok(
+                self.network
+                    .get_interfaces()
+                    .iter()
+                    .filter(|iface| show_all || iface.is_up)
+                    .map(ifconfig_block)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            [name] => match self.network.interface(name) {
+                Some(iface) => CmdOutput::ok(ifconfig_block(iface)),
+                None => CmdOutput::error(
+                    1,
+                    format!(
+                        "{}: error fetching interface information: Device not found",
+                        name
+                    ),
+                ),
+            },
+            [name, settings @ ..] => self.ifconfig_set(name, settings),
+        }
+    }
+
+    /// `ifconfig IFACE [ADDR[/PREFIX]] [netmask MASK] [mtu N] [up|down]`
+    fn ifconfig_set(&mut self, name: &str, settings: &[&str]) -> CmdOutput {
+        if self.network.interface(name).is_none() {
+            return CmdOutput::error(1, format!("SIOCSIFFLAGS: No such device\n{}: ERROR while getting interface flags: No such device", name));
+        }
+        let mut address = None;
+        let mut netmask = None;
+        let mut mtu = None;
+        let mut link = None;
+        let mut i = 0;
+        while i < settings.len() {
+            match settings[i] {
+                "up" => link = Some(true),
+                "down" => link = Some(false),
+                "netmask" if i + 1 < settings.len() => {
+                    netmask = match netmask_prefix(settings[i + 1]) {
+                        Some(prefix) => Some(prefix),
+                        None => {
+                            return CmdOutput::error(
+                                1,
+                                "SIOCSIFNETMASK: Invalid argument".to_string(),
+                            )
+                        }
+                    };
+                    i += 1;
+                }
+                "mtu" if i + 1 < settings.len() => {
+                    mtu = match settings[i + 1].parse::<u32>() {
+                        Ok(n) => Some(n),
+                        Err(_) => {
+                            return CmdOutput::error(1, "SIOCSIFMTU: Invalid argument".to_string())
+                        }
+                    };
+                    i += 1;
+                }
+                arg => match parse_cidr(arg, None) {
+                    Some(cidr) => address = Some(cidr),
+                    None => {
+                        return CmdOutput::error(
+                            1,
+                            format!(
+                                "{}: Unknown host\nifconfig: `--help' gives usage information.",
+                                arg
+                            ),
+                        )
+                    }
+                },
+            }
+            i += 1;
+        }
+        if self.current_user() != "root" {
+            let call = match (address, netmask, mtu) {
+                (Some(_), _, _) => "SIOCSIFADDR",
+                (_, Some(_), _) => "SIOCSIFNETMASK",
+                (_, _, Some(_)) => "SIOCSIFMTU",
+                _ => "SIOCSIFFLAGS",
+            };
+            return CmdOutput::error(1, format!("{}: Operation not permitted", call));
+        }
+
+        let current = self
+            .network
+            .interface(name)
+            .and_then(|iface| iface.ipv4.first().cloned());
+        let result = match (address, netmask, current) {
+            (Some((addr, prefix)), mask, _) => {
+                // Without a netmask ifconfig falls back to the classful one
+                let prefix = mask.or(prefix).unwrap_or_else(|| classful_prefix(&addr));
+                self.network.set_address(name, Some((&addr, prefix)))
+            }
+            (None, Some(prefix), Some((addr, _))) => {
+                self.network.set_address(name, Some((&addr, prefix)))
+            }
+            (None, Some(_), None) => {
+                Err("SIOCSIFNETMASK: Cannot assign requested address".to_string())
+            }
+            (None, None, _) => Ok(()),
+        };
+        let result = result
+            .and_then(|_| mtu.map_or(Ok(()), |n| self.network.set_mtu(name, n)))
+            .and_then(|_| link.map_or(Ok(()), |up| self.network.set_link(name, up)));
+        match result {
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) if e.starts_with("SIOC") => CmdOutput::error(1, e),
+            Err(e) => CmdOutput::error(1, format!("SIOCSIFMTU: {}", e)),
+        }
+    }
+
+    pub(super) fn cmd_ip(&mut self, args: &[&str]) -> CmdOutput {
+        // Family and formatting options (-4, -6, -c, ...) don't change anything here
+        let start = args.iter().take_while(|a| a.starts_with('-')).count();
+        let args = &args[start..];
+        if args.is_empty() {
+            return CmdOutput::usage("Usage: ip [ OPTIONS ] OBJECT { COMMAND | help }\nwhere  OBJECT := { address | link | route | neigh }".to_string());
+        }
+
+        let rest = &args[1..];
+        match args[0] {
+            "addr" | "a" | "address" => match rest.first().copied() {
+                None | Some("show" | "list" | "s" | "ls") => {
+                    self.ip_show(rest.iter().skip(1).copied(), true)
+                }
+                Some(op @ ("add" | "del" | "delete" | "flush")) => {
+                    self.ip_addr_change(op, &rest[1..])
+                }
+                Some(other) => CmdOutput::error(
+                    1,
+                    format!("Command \"{}\" is unknown, try \"ip address help\".", other),
+                ),
+            },
+            "link" | "l" => match rest.first().copied() {
+                None | Some("show" | "list" | "s" | "ls") => {
+                    self.ip_show(rest.iter().skip(1).copied(), false)
+                }
+                Some("set") => self.ip_link_set(&rest[1..]),
+                Some(other) => CmdOutput::error(
+                    1,
+                    format!("Command \"{}\" is unknown, try \"ip link help\".", other),
+                ),
+            },
+            "route" | "r" | "ro" => {
+                let mut out = String::new();
+                if let Some(gateway) = self.network.default_gateway() {
+                    out.push_str(&format!(
+                        "default via {} dev eth0 proto dhcp metric 100\n",
+                        gateway
+                    ));
+                }
+                let interfaces = self.network.get_interfaces();
+                for iface in interfaces.iter().filter(|i| i.is_up && !i.is_loopback) {
+                    for (addr, prefix) in &iface.ipv4 {
+                        out.push_str(&format!(
+                            "{}/{} dev {} proto kernel scope link src {}\n",
+                            network_address(addr, *prefix),
+                            prefix,
+                            iface.name,
+                            addr
+                        ));
+                    }
+                }
+                CmdOutput::ok(out)
+            }
+            "neigh" | "neighbor" | "n" => CmdOutput::ok(
+                self.network
+                    .arp_table()
+                    .into_iter()
+                    .map(|(ip, mac, iface)| {
+                        format!("{} dev {} lladdr {} REACHABLE\n", ip, iface, mac)
+                    })
+                    .collect::<String>(),
+            ),
+            _ => CmdOutput::error(
+                1,
+                format!("Object \"{}\" is unknown, try \"ip help\".", args[0]),
+            ),
+        }
+    }
+
+    /// `ip link` and `ip addr` listings, optionally for one device
+    fn ip_show<'a>(&self, mut args: impl Iterator<Item = &'a str>, addresses: bool) -> CmdOutput {
+        let mut filter = args.next();
+        if filter == Some("dev") {
+            filter = args.next();
+        }
+        let interfaces = self.network.get_interfaces();
+        if let Some(name) = filter {
+            if !interfaces.iter().any(|iface| iface.name == name) {
+                return CmdOutput::error(1, format!("Device \"{}\" does not exist.", name));
+            }
+        }
+        let mut out = String::new();
+        for (i, iface) in interfaces.iter().enumerate() {
+            if filter.is_some_and(|name| name != iface.name) {
+                continue;
+            }
+            let flags = match (iface.is_loopback, iface.is_up) {
+                (true, true) => "LOOPBACK,UP,LOWER_UP",
+                (true, false) => "LOOPBACK",
+                (false, true) => "BROADCAST,MULTICAST,UP,LOWER_UP",
+                (false, false) => "BROADCAST,MULTICAST",
+            };
+            let (qdisc, state) = match (iface.is_loopback, iface.is_up) {
+                (_, false) if iface.is_loopback => ("noqueue", "DOWN"),
+                (_, false) => ("fq_codel", "DOWN"),
+                (true, true) => ("noqueue", "UNKNOWN"),
+                (false, true) => ("fq_codel", "UP"),
+            };
+            out.push_str(&format!(
+                "{}: {}: <{}> mtu {} qdisc {} state {} {}group default qlen 1000\n",
+                i + 1,
+                iface.name,
+                flags,
+                iface.mtu,
+                qdisc,
+                state,
+                if addresses { "" } else { "mode DEFAULT " }
+            ));
+            if iface.is_loopback {
+                out.push_str("    link/loopback 00:00:00:00:00:00 brd 00:00:00:00:00:00\n");
+            } else {
+                out.push_str(&format!(
+                    "    link/ether {} brd ff:ff:ff:ff:ff:ff\n",
+                    iface.mac
+                ));
+            }
+            if !addresses {
+                continue;
+            }
+            for (n, (addr, prefix)) in iface.ipv4.iter().enumerate() {
+                let scope = if iface.is_loopback {
+                    "scope host".to_string()
+                } else {
+                    format!(
+                        "brd {} scope global{}",
+                        broadcast_address(addr, *prefix),
+                        if n > 0 { " secondary" } else { "" }
+                    )
+                };
+                out.push_str(&format!(
+                    "    inet {}/{} {} {}\n       valid_lft forever preferred_lft forever\n",
+                    addr, prefix, scope, iface.name
+                ));
+            }
+            if iface.is_up {
+                let (prefix, scope) = if iface.is_loopback {
+                    (128, "host")
+                } else {
+                    (64, "link")
+                };
+                out.push_str(&format!(
+                    "    inet6 {}/{} scope {}\n       valid_lft forever preferred_lft forever\n",
+                    iface.ipv6, prefix, scope
+                ));
+            }
+        }
+        CmdOutput::ok(out)
+    }
+
+    /// `ip addr add|del ADDR[/PREFIX] dev IFACE` and `ip addr flush dev IFACE`
+    fn ip_addr_change(&mut self, op: &str, args: &[&str]) -> CmdOutput {
+        let mut device = None;
+        let mut address = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "dev" if i + 1 < args.len() => {
+                    device = Some(args[i + 1]);
+                    i += 1;
+                }
+                arg if address.is_none() && op != "flush" => address = Some(arg),
+                arg => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "Error: either \"local\" is duplicate, or \"{}\" is a garbage.",
+                            arg
+                        ),
+                    )
+                }
+            }
+            i += 1;
+        }
+        let Some(device) = device else {
+            return CmdOutput::error(
+                1,
+                "Not enough information: \"dev\" argument is required.".to_string(),
+            );
+        };
+        if self.network.interface(device).is_none() {
+            return CmdOutput::error(1, format!("Cannot find device \"{}\"", device));
+        }
+        let cidr = match (op, address) {
+            ("flush", _) => None,
+            (_, Some(text)) => match parse_cidr(text, Some(32)) {
+                Some((addr, prefix)) => Some((addr, prefix.unwrap_or(32))),
+                None => {
+                    return CmdOutput::error(
+                        1,
+                        format!("Error: inet prefix is expected rather than \"{}\".", text),
+                    )
+                }
+            },
+            (_, None) => {
+                return CmdOutput::error(
+                    1,
+                    "Not enough information: \"local\" argument is required.".to_string(),
+                )
+            }
+        };
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "RTNETLINK answers: Operation not permitted".to_string());
+        }
+        let result = match (op, cidr) {
+            ("add", Some((addr, prefix))) => self.network.add_address(device, &addr, prefix),
+            (_, Some((addr, _))) => self.network.del_address(device, &addr),
+            _ => self.network.set_address(device, None),
+        };
+        match result {
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) => CmdOutput::error(1, e),
+        }
+    }
+
+    /// `ip link set [dev] IFACE up|down|mtu N`
+    fn ip_link_set(&mut self, args: &[&str]) -> CmdOutput {
+        let args = match args.first() {
+            Some(&"dev") => &args[1..],
+            _ => args,
+        };
+        let Some((&device, settings)) = args.split_first() else {
+            return CmdOutput::error(
+                1,
+                "Not enough information: \"dev\" argument is required.".to_string(),
+            );
+        };
+        if self.network.interface(device).is_none() {
+            return CmdOutput::error(1, format!("Cannot find device \"{}\"", device));
+        }
+        let mut link = None;
+        let mut mtu = None;
+        let mut i = 0;
+        while i < settings.len() {
+            match settings[i] {
+                "up" => link = Some(true),
+                "down" => link = Some(false),
+                "mtu" if i + 1 < settings.len() => {
+                    mtu = match settings[i + 1].parse::<u32>() {
+                        Ok(n) => Some(n),
+                        Err(_) => {
+                            return CmdOutput::error(
+                                1,
+                                format!(
+                                    "Error: argument \"{}\" is wrong: Invalid \"mtu\" value",
+                                    settings[i + 1]
+                                ),
+                            )
+                        }
+                    };
+                    i += 1;
+                }
+                other => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "Error: either \"dev\" is duplicate, or \"{}\" is a garbage.",
+                            other
+                        ),
+                    )
+                }
+            }
+            i += 1;
+        }
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "RTNETLINK answers: Operation not permitted");
+        }
+        let result = mtu
+            .map_or(Ok(()), |n| self.network.set_mtu(device, n))
+            .and_then(|_| link.map_or(Ok(()), |up| self.network.set_link(device, up)));
+        match result {
+            Ok(()) => CmdOutput::ok(String::new()),
+            Err(e) => CmdOutput::error(1, format!("Error: {}.", e)),
+        }
+    }
+}
+
+fn ifconfig_block(iface: &NetworkInterface) -> String {
+    let flags = match (iface.is_loopback, iface.is_up) {
+        (true, true) => "73<UP,LOOPBACK,RUNNING>",
+        (true, false) => "8<LOOPBACK>",
+        (false, true) => "4163<UP,BROADCAST,RUNNING,MULTICAST>",
+        (false, false) => "4098<BROADCAST,MULTICAST>",
+    };
+    let mut out = format!("{}: flags={}  mtu {}\n", iface.name, flags, iface.mtu);
+    if let Some((addr, prefix)) = iface.ipv4.first() {
+        let netmask = format_ipv4(prefix_mask(*prefix));
+        if iface.is_loopback {
+            out.push_str(&format!("        inet {}  netmask {}\n", addr, netmask));
+        } else {
+            out.push_str(&format!(
+                "        inet {}  netmask {}  broadcast {}\n",
+                addr,
+                netmask,
+                broadcast_address(addr, *prefix)
+            ));
+        }
+    }
+    if iface.is_up {
+        if iface.is_loopback {
+            out.push_str(&format!(
+                "        inet6 {}  prefixlen 128  scopeid 0x10<host>\n",
+                iface.ipv6
+            ));
+        } else {
+            out.push_str(&format!(
+                "        inet6 {}  prefixlen 64  scopeid 0x20<link>\n",
+                iface.ipv6
+            ));
+        }
+    }
+    if iface.is_loopback {
+        out.push_str("        loop  txqueuelen 1000  (Local Loopback)\n");
+    } else {
+        out.push_str(&format!(
+            "        ether {}  txqueuelen 1000  (Ethernet)\n",
+            iface.mac
+        ));
+    }
+    out.push_str(&format!(
+        "        RX packets {}  bytes {} ({})\n        RX errors 0  dropped 0  overruns 0  frame 0\n",
+        iface.rx_packets,
+        iface.rx_bytes,
+        human_bytes(iface.rx_bytes)
+    ));
+    out.push_str(&format!(
+        "        TX packets {}  bytes {} ({})\n        TX errors 0  dropped 0 overruns 0  carrier 0  collisions 0\n",
+        iface.tx_packets,
+        iface.tx_bytes,
+        human_bytes(iface.tx_bytes)
+    ));
+    out
+}
+
+/// Byte counts the way net-tools prints them: `0.0 B`, `1.2 KB`, `3.4 MB`
+fn human_bytes(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    for unit in ["B", "KB", "MB", "GB"] {
+        if value < 1024.0 || unit == "GB" {
+            return format!("{:.1} {}", value, unit);
+        }
+        value /= 1024.0;
+    }
+    unreachable!()
+}
+
+/// Prefix length of a dotted netmask, if its bits are contiguous
+fn netmask_prefix(mask: &str) -> Option<u8> {
+    let bits = u32::from_be_bytes(parse_ipv4(mask)?);
+    let prefix = bits.leading_ones();
+    (bits.checked_shl(prefix).unwrap_or(0) == 0).then_some(prefix as u8)
+}
+
+/// Split `ADDR[/PREFIX]`, falling back to `default` when there's no prefix
+fn parse_cidr(text: &str, default: Option<u8>) -> Option<(String, Option<u8>)> {
+    let (addr, prefix) = match text.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok().filter(|p| *p <= 32)?)),
+        None => (text, default),
+    };
+    parse_ipv4(addr)?;
+    Some((addr.to_string(), prefix))
+}
+
+/// The pre-CIDR netmask for an address's class
+fn classful_prefix(addr: &str) -> u8 {
+    match parse_ipv4(addr).map_or(0, |ip| ip[0]) {
+        0..=127 => 8,
+        128..=191 => 16,
+        _ => 24,
+    }
+}
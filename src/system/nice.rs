@@ -0,0 +1,106 @@
+use super::System;
+use crate::process::{Priority, NICE_MAX, NICE_MIN};
+use crate::shell::CmdOutput;
+
+const NICE_DEFAULT_INCREMENT: i8 = 10;
+
+fn parse_nice(value: &str) -> Option<i8> {
+    let n: i32 = value.parse().ok()?;
+    Some(n.clamp(NICE_MIN as i32, NICE_MAX as i32) as i8)
+}
+
+impl System {
+    /// `nice [-n N | -N] COMMAND...`: run COMMAND at niceness N
+    pub(super) fn cmd_nice(&mut self, args: &[&str]) -> CmdOutput {
+        let (nice, rest) = match args {
+            [] => return CmdOutput::ok("0".to_string()),
+            ["-n", value, rest @ ..] => match parse_nice(value) {
+                Some(n) => (n, rest),
+                None => {
+                    return CmdOutput::error(1, format!("nice: invalid adjustment '{}'", value))
+                }
+            },
+            [flag, rest @ ..] if flag.starts_with('-') && flag.len() > 1 => {
+                match parse_nice(&flag[1..]) {
+                    Some(n) => (n, rest),
+                    None => {
+                        return CmdOutput::error(
+                            1,
+                            format!("nice: invalid option -- '{}'", &flag[1..]),
+                        )
+                    }
+                }
+            }
+            _ => (NICE_DEFAULT_INCREMENT, args),
+        };
+        if rest.is_empty() {
+            return CmdOutput::usage("usage: nice [-n N] COMMAND [ARG]...".to_string());
+        }
+        if nice < 0 && self.current_user() != "root" {
+            return CmdOutput::error(
+                1,
+                "nice: cannot set niceness: Permission denied".to_string(),
+            );
+        }
+        self.pending_nice = Some(nice);
+        let out = self.exec_nested(&rest.join(" "));
+        self.pending_nice = None;
+        out
+    }
+
+    /// `renice [-n] N [-p] PID...`
+    pub(super) fn cmd_renice(&mut self, args: &[&str]) -> CmdOutput {
+        let usage = "usage: renice [-n] PRIORITY [-p] PID...";
+        let args = match args {
+            ["-n", rest @ ..] => rest,
+            _ => args,
+        };
+        let Some((value, pids)) = args.split_first() else {
+            return CmdOutput::usage(usage.to_string());
+        };
+        let Some(nice) = parse_nice(value) else {
+            return CmdOutput::error(1, format!("renice: invalid priority '{}'", value));
+        };
+        let pids = match pids {
+            ["-p", rest @ ..] => rest,
+            _ => pids,
+        };
+        if pids.is_empty() {
+            return CmdOutput::usage(usage.to_string());
+        }
+
+        let is_root = self.current_user() == "root";
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for target in pids {
+            let Some(pid) = target.parse::<u32>().ok() else {
+                errors.push(format!("renice: invalid process id '{}'", target));
+                continue;
+            };
+            let Some(old) = self.kernel.proc.get(pid).map(|p| p.nice) else {
+                errors.push(format!(
+                    "renice: failed to get priority for {} (process ID): No such process",
+                    pid
+                ));
+                continue;
+            };
+            // Only root may raise a process's priority
+            if nice < old && !is_root {
+                errors.push(format!(
+                    "renice: failed to set priority for {} (process ID): Permission denied",
+                    pid
+                ));
+                continue;
+            }
+            self.kernel.proc.set_nice(pid, nice);
+            self.kernel
+                .scheduler
+                .reprioritize(pid, Priority::from_nice(nice));
+            out.push(format!(
+                "{} (process ID) old priority {}, new priority {}",
+                pid, old, nice
+            ));
+        }
+        CmdOutput::collected(out, errors)
+    }
+}
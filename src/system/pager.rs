@@ -0,0 +1,238 @@
+use super::{System, PIPE_STDIN};
+use crate::shell::CmdOutput;
+
+/// Text rows shown per page; the status line takes the row below them
+pub(super) const PAGE_LINES: usize = 22;
+
+/// Interactive state for a running `less`/`more` session
+pub(super) struct PagerState {
+    name: String,
+    lines: Vec<String>,
+    top: usize,
+    pattern: Option<String>,
+    status: String,
+    more: bool,
+}
+
+impl PagerState {
+    fn last_top(&self) -> usize {
+        self.lines.len().saturating_sub(PAGE_LINES)
+    }
+
+    fn at_end(&self) -> bool {
+        self.top >= self.last_top()
+    }
+
+    fn scroll(&mut self, delta: i32) {
+        let top = self.top as i64 + delta as i64;
+        self.top = top.clamp(0, self.last_top() as i64) as usize;
+    }
+
+    /// Move to the next line matching the current pattern, in either direction
+    fn find(&mut self, forward: bool) {
+        let Some(pattern) = self.pattern.clone() else {
+            self.status = "No previous regular expression".into();
+            return;
+        };
+        let hit = if forward {
+            (self.top + 1..self.lines.len()).find(|&i| self.lines[i].contains(&pattern))
+        } else {
+            (0..self.top)
+                .rev()
+                .find(|&i| self.lines[i].contains(&pattern))
+        };
+        match hit {
+            Some(i) => self.top = i.min(self.last_top()),
+            None => self.status = "Pattern not found".into(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for line in self.lines.iter().skip(self.top).take(PAGE_LINES) {
+            match &self.pattern {
+                // Lines that already carry colour codes are left as-is
+                Some(p) if !p.is_empty() && !line.contains('\x1b') => out.push_str(&line.replace(
+                    p.as_str(),
+                    &format!("\x1b[COLOR:yellow]{}\x1b[COLOR:reset]", p),
+                )),
+                _ => out.push_str(line),
+            }
+            out.push('\n');
+        }
+        for _ in self.lines.len().saturating_sub(self.top)..PAGE_LINES {
+            out.push_str("~\n");
+        }
+        let shown = (self.top + PAGE_LINES).min(self.lines.len());
+        let status = if !self.status.is_empty() {
+            self.status.clone()
+        } else if self.more {
+            format!("--More--({}%)", shown * 100 / self.lines.len().max(1))
+        } else if self.at_end() {
+            "(END)".into()
+        } else if self.top == 0 {
+            format!("{} (lines 1-{}/{})", self.name, shown, self.lines.len())
+        } else {
+            ":".into()
+        };
+        out.push_str(&format!("\x1b[COLOR:gray]{}\x1b[COLOR:reset]", status));
+        out
+    }
+}
+
+impl System {
+    /// Open `text` in the pager. With `quit_if_one_screen` short text is
+    /// printed directly instead, like `less -F`.
+    pub(super) fn pager_start(
+        &mut self,
+        name: &str,
+        text: &str,
+        more: bool,
+        quit_if_one_screen: bool,
+    ) -> String {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        if self.output_captured || (quit_if_one_screen && lines.len() <= PAGE_LINES) {
+            return text.to_string();
+        }
+        let state = PagerState {
+            name: name.to_string(),
+            lines,
+            top: 0,
+            pattern: None,
+            status: String::new(),
+            more,
+        };
+        self.pager_show(state)
+    }
+
+    /// Keep `state` as the running pager and draw its page on the alternate
+    /// screen, leaving the shell's output underneath untouched
+    fn pager_show(&mut self, state: PagerState) -> String {
+        let frame = state.render();
+        self.pager = Some(state);
+        self.term.enter_alternate();
+        self.term.clear();
+        self.term.write(&frame, "output");
+        format!("\x1b[PAGER]{}", frame)
+    }
+
+    pub(super) fn pager_exit(&mut self) -> String {
+        self.pager = None;
+        self.term.leave_alternate();
+        "\x1b[PAGER_EXIT]".into()
+    }
+
+    /// Open a file in the pager, reporting errors the way `less` does
+    pub(super) fn pager_open_path(&mut self, path: &str, more: bool, quit: bool) -> CmdOutput {
+        let prog = if more { "more" } else { "less" };
+        if !self.has_access(path, 4) {
+            return CmdOutput::error(1, format!("{}: {}: Permission denied", prog, path));
+        }
+        match self.kernel.fs.resolve(path) {
+            Some(node) if node.is_dir => {
+                CmdOutput::error(1, format!("{}: {} is a directory", prog, path))
+            }
+            Some(node) => {
+                let text = node.data.clone();
+                let name = if path == PIPE_STDIN {
+                    "(standard input)"
+                } else {
+                    path
+                };
+                CmdOutput::ok(self.pager_start(name, &text, more, quit))
+            }
+            None => CmdOutput::error(1, format!("{}: {}: No such file or directory", prog, path)),
+        }
+    }
+
+    pub(super) fn cmd_pager(&mut self, cmd: &str, args: &[&str]) -> CmdOutput {
+        let more = cmd == "more";
+        let mut quit = more;
+        let mut pattern = None;
+        let mut files = Vec::new();
+        for arg in args {
+            if let Some(p) = arg.strip_prefix("+/").or_else(|| arg.strip_prefix("-p")) {
+                pattern = Some(p.to_string());
+            } else if matches!(*arg, "-F" | "--quit-if-one-screen") {
+                quit = true;
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                return CmdOutput::error(
+                    1,
+                    format!(
+                        "{}: unknown option -- '{}'",
+                        cmd,
+                        arg.trim_start_matches('-')
+                    ),
+                );
+            } else {
+                files.push(*arg);
+            }
+        }
+        let Some(path) = files.first() else {
+            return CmdOutput::error(1, format!("Missing filename (\"{} --help\" for help)", cmd));
+        };
+        let out = self.pager_open_path(path, more, quit);
+        match pattern {
+            Some(p) if self.pager.is_some() => CmdOutput::ok(self.pager_find_text(&p)),
+            _ => out,
+        }
+    }
+
+    pub(super) fn pager_scroll_by(&mut self, lines: i32) -> String {
+        let Some(mut state) = self.pager.take() else {
+            return self.pager_exit();
+        };
+        state.status.clear();
+        if state.more && lines > 0 && state.at_end() {
+            return self.pager_exit();
+        }
+        state.scroll(lines);
+        self.pager_show(state)
+    }
+
+    pub(super) fn pager_find_text(&mut self, pattern: &str) -> String {
+        let Some(mut state) = self.pager.take() else {
+            return self.pager_exit();
+        };
+        state.status.clear();
+        if !pattern.is_empty() {
+            state.pattern = Some(pattern.to_string());
+        }
+        // Stay put if the top line already matches, otherwise search forward
+        let top = state.top;
+        if state
+            .lines
+            .get(top)
+            .zip(state.pattern.as_ref())
+            .is_none_or(|(line, p)| !line.contains(p.as_str()))
+        {
+            state.find(true);
+        }
+        self.pager_show(state)
+    }
+
+    /// Feed a key from the frontend into the pager. Returns the redrawn page,
+    /// or `\x1b[PAGER_EXIT]` when the session ends.
+    pub(super) fn pager_key(&mut self, key: &str) -> String {
+        match key {
+            "q" | "Q" | "Escape" => self.pager_exit(),
+            " " | "f" | "PageDown" => self.pager_scroll_by(PAGE_LINES as i32),
+            "b" | "PageUp" => self.pager_scroll_by(-(PAGE_LINES as i32)),
+            "d" => self.pager_scroll_by(PAGE_LINES as i32 / 2),
+            "u" => self.pager_scroll_by(-(PAGE_LINES as i32) / 2),
+            "j" | "e" | "Enter" | "ArrowDown" => self.pager_scroll_by(1),
+            "k" | "y" | "ArrowUp" => self.pager_scroll_by(-1),
+            "g" | "<" | "Home" => self.pager_scroll_by(i32::MIN / 2),
+            "G" | ">" | "End" => self.pager_scroll_by(i32::MAX / 2),
+            "n" | "N" => {
+                let Some(mut state) = self.pager.take() else {
+                    return self.pager_exit();
+                };
+                state.status.clear();
+                state.find(key == "n");
+                self.pager_show(state)
+            }
+            _ => self.pager_scroll_by(0),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! `perf`: the samples `crate::perf` measures, summed up with sparklines,
+//! and `/proc/stat` built from the same counters
+use super::System;
+use crate::clock;
+use crate::perf;
+use crate::process::ProcState;
+use crate::shell::CmdOutput;
+
+const USAGE: &str = "usage: perf [reset]";
+/// Samples drawn per sparkline
+const SPARK_WIDTH: usize = 60;
+/// Width of the longest bar in the FPS histogram
+const BAR_WIDTH: usize = 30;
+/// Lower bound and label of each FPS histogram bucket
+const FPS_BUCKETS: [(f64, &str); 5] = [
+    (0.0, "<15"),
+    (15.0, "15-30"),
+    (30.0, "30-45"),
+    (45.0, "45-60"),
+    (60.0, "60+"),
+];
+/// Timers this late count as a stall
+const STALL_MS: f64 = 50.0;
+const WASM_PAGE: u64 = 65_536;
+
+/// The newest `SPARK_WIDTH` samples
+fn recent(values: &[f64]) -> &[f64] {
+    &values[values.len().saturating_sub(SPARK_WIDTH)..]
+}
+
+fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut values = values.to_vec();
+    values.sort_by(f64::total_cmp);
+    values
+}
+
+fn max_of(values: &[f64]) -> f64 {
+    values.iter().copied().fold(0.0, f64::max)
+}
+
+fn frame_report(renderer: &str, intervals: &[f64]) -> Vec<String> {
+    let mut out = vec![format!(
+        "Frames:  {}, last {} frames",
+        renderer,
+        intervals.len()
+    )];
+    if intervals.is_empty() {
+        return out;
+    }
+    let by_time = sorted(intervals);
+    let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+    let worst = by_time[by_time.len() - 1];
+    out.push(format!(
+        "         fps   avg {:.1}  min {:.1}  1% low {:.1}  max {:.1}",
+        1000.0 / mean,
+        1000.0 / worst,
+        1000.0 / perf::percentile(&by_time, 99.0),
+        1000.0 / by_time[0]
+    ));
+    let shown = recent(intervals);
+    out.push(format!(
+        "         {}  {:.1} ms avg, {:.1} ms worst",
+        perf::sparkline(shown, max_of(shown)),
+        mean,
+        worst
+    ));
+    let mut counts = [0usize; FPS_BUCKETS.len()];
+    for interval in intervals {
+        let fps = 1000.0 / interval;
+        let bucket = FPS_BUCKETS.iter().rposition(|(low, _)| fps >= *low);
+        counts[bucket.unwrap_or(0)] += 1;
+    }
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    for ((_, label), count) in FPS_BUCKETS.iter().zip(counts) {
+        let bar = "█".repeat((count * BAR_WIDTH).div_ceil(most));
+        out.push(format!(
+            "         {:>5} |{:<width$} {}",
+            label,
+            bar,
+            count,
+            width = BAR_WIDTH
+        ));
+    }
+    out
+}
+
+fn exec_report(times: &[f64], count: u64) -> Vec<String> {
+    if times.is_empty() {
+        return vec!["Exec:    no commands timed yet".into()];
+    }
+    let by_time = sorted(times);
+    let shown = recent(times);
+    vec![
+        format!(
+            "Exec:    {} commands  p50 {:.2} ms  p90 {:.2} ms  p99 {:.2} ms  max {:.2} ms",
+            count,
+            perf::percentile(&by_time, 50.0),
+            perf::percentile(&by_time, 90.0),
+            perf::percentile(&by_time, 99.0),
+            by_time[by_time.len() - 1]
+        ),
+        format!("         {}", perf::sparkline(shown, max_of(shown))),
+    ]
+}
+
+fn jank_report(late: &[f64]) -> Vec<String> {
+    if late.is_empty() {
+        return vec!["Jank:    no samples yet".into()];
+    }
+    let by_time = sorted(late);
+    let stalls = late.iter().filter(|ms| **ms >= STALL_MS).count();
+    let shown = recent(late);
+    vec![
+        format!(
+            "Jank:    timers late p50 {:.1} ms  p99 {:.1} ms  worst {:.1} ms, {} stall{} over {} ms",
+            perf::percentile(&by_time, 50.0),
+            perf::percentile(&by_time, 99.0),
+            by_time[by_time.len() - 1],
+            stalls,
+            if stalls == 1 { "" } else { "s" },
+            STALL_MS
+        ),
+        format!("         {}", perf::sparkline(shown, max_of(shown))),
+    ]
+}
+
+impl System {
+    /// `perf [reset]`: heap size, CPU busy time, frame times of the last
+    /// renderer, `exec` latency and event-loop jank, all measured
+    pub(super) fn cmd_perf(&mut self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] => {}
+            ["reset"] => {
+                perf::reset();
+                return CmdOutput::default();
+            }
+            _ => return CmdOutput::error(1, USAGE),
+        }
+        let mut out = Vec::new();
+        out.push(match perf::heap_bytes() {
+            Some(bytes) => format!(
+                "Heap:    {:.1} MiB wasm linear memory ({} pages)",
+                bytes as f64 / (1024.0 * 1024.0),
+                bytes / WASM_PAGE
+            ),
+            None => "Heap:    n/a outside the browser".into(),
+        });
+        let cpu = perf::cpu_history();
+        let now = cpu.last().copied().unwrap_or(0.0);
+        let average = cpu.iter().sum::<f64>() / cpu.len().max(1) as f64;
+        out.push(format!(
+            "CPU:     {}  {:.1}% busy now, {:.1}% over {} s",
+            perf::sparkline(&cpu, 100.0),
+            now,
+            average,
+            perf::CPU_WINDOW
+        ));
+        out.extend(match perf::frames() {
+            (Some(renderer), intervals) => frame_report(renderer, &intervals),
+            (None, _) => vec!["Frames:  nothing drawn yet (try doom or screensaver)".into()],
+        });
+        let (times, count) = perf::exec_times();
+        out.extend(exec_report(&times, count));
+        out.extend(jank_report(&perf::jank()));
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// `/proc/stat` in clock ticks of 1/100 s: busy time measured on the
+    /// main thread as user time, the rest idle
+    pub(super) fn refresh_proc_stat(&mut self) {
+        let busy = (perf::busy_total_ms() / 10.0) as u64;
+        let idle = ((clock::now_ms() - perf::busy_total_ms()).max(0.0) / 10.0) as u64;
+        let procs = self.kernel.proc.list();
+        let running = procs.iter().filter(|p| p.state == ProcState::Run).count();
+        let cpu = format!("{} 0 0 {} 0 0 0 0 0 0", busy, idle);
+        let stat = format!(
+            "cpu  {}\ncpu0 {}\nctxt {}\nbtime {}\nprocesses {}\nprocs_running {}\nprocs_blocked 0\n",
+            cpu,
+            cpu,
+            self.kernel.proc.ticks(),
+            clock::now_secs() - (self.kernel.uptime_ms() / 1000) as i64,
+            self.kernel.proc.forks(),
+            running
+        );
+        let Some(proc_dir) = self.kernel.fs.resolve_mut("/proc") else {
+            return;
+        };
+        match proc_dir.children.get_mut("stat") {
+            Some(node) => node.data = stat,
+            None => {
+                proc_dir
+                    .children
+                    .insert("stat".into(), crate::vfs::Inode::file("stat", &stat));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_frames_exec_and_proc_stat() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        perf::reset();
+        let report = sys.cmd_perf(&[]).stdout;
+        assert!(report.contains("Frames:  nothing drawn yet"));
+        assert!(report.contains("Jank:    no samples yet"));
+
+        for frame in 0..10 {
+            perf::record_frame("doom", frame as f64 * 20.0, 1.0);
+        }
+        sys.exec("echo hi");
+        sys.exec("cat /proc/stat");
+        let report = sys.cmd_perf(&[]).stdout;
+        assert!(report.contains("Frames:  doom, last 9 frames"));
+        assert!(report.contains("fps   avg 50.0  min 50.0"));
+        assert!(report.contains("45-60 |██████████████████████████████ 9"));
+        assert!(report.contains("Exec:    2 commands  p50 "));
+
+        let stat = sys.exec("cat /proc/stat");
+        assert!(stat.starts_with("cpu  "));
+        assert!(stat.contains("\nprocs_running "));
+        assert_eq!(sys.cmd_perf(&["bogus"]).stderr, USAGE);
+    }
+}
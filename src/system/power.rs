@@ -0,0 +1,267 @@
+use super::{System, SystemEvent};
+use crate::process::{ProcessTable, Scheduler};
+use crate::services::ServiceState;
+use crate::shell::CmdOutput;
+
+/// How the machine comes down, and what the frontend does afterwards
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum PowerAction {
+    Halt,
+    PowerOff,
+    Reboot,
+}
+
+impl PowerAction {
+    fn target(self) -> &'static str {
+        match self {
+            PowerAction::Halt => "System Halt",
+            PowerAction::PowerOff => "System Power Off",
+            PowerAction::Reboot => "System Reboot",
+        }
+    }
+
+    fn kernel_line(self) -> &'static str {
+        match self {
+            PowerAction::Halt => "reboot: System halted",
+            PowerAction::PowerOff => "reboot: Power down",
+            PowerAction::Reboot => "reboot: Restarting system",
+        }
+    }
+
+    fn event(self) -> SystemEvent {
+        match self {
+            PowerAction::Halt => SystemEvent::Halt,
+            PowerAction::PowerOff => SystemEvent::PowerOff,
+            PowerAction::Reboot => SystemEvent::Reboot,
+        }
+    }
+}
+
+impl System {
+    /// `reboot`, `halt` and `poweroff`, which take no arguments worth
+    /// honouring beyond `-p` on halt
+    pub(super) fn cmd_power(&mut self, cmd: &str, args: &[&str]) -> CmdOutput {
+        let action = match (cmd, args) {
+            ("reboot", _) => PowerAction::Reboot,
+            ("halt", [.., "-p"]) | ("poweroff", _) => PowerAction::PowerOff,
+            _ => PowerAction::Halt,
+        };
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "Must be root.");
+        }
+        CmdOutput::ok(self.go_down(action))
+    }
+
+    /// `shutdown [-h|-H|-P|-r|-c] [now|+0] [MESSAGE]`. Delayed shutdowns are
+    /// not scheduled; only `now` and `+0` are accepted
+    pub(super) fn cmd_shutdown(&mut self, args: &[&str]) -> CmdOutput {
+        let mut action = PowerAction::PowerOff;
+        let mut when = None;
+        for arg in args {
+            match *arg {
+                "-h" | "-P" | "--poweroff" => action = PowerAction::PowerOff,
+                "-H" | "--halt" => action = PowerAction::Halt,
+                "-r" | "--reboot" => action = PowerAction::Reboot,
+                "-c" => return CmdOutput::error(1, "shutdown: no scheduled shutdown to cancel"),
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(
+                        1,
+                        format!("shutdown: invalid option -- '{}'", &flag[1..]),
+                    )
+                }
+                time if when.is_none() => when = Some(time),
+                _ => {} // wall message; nobody else is logged in
+            }
+        }
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "Must be root.");
+        }
+        match when {
+            None | Some("now") | Some("+0") => CmdOutput::ok(self.go_down(action)),
+            Some(time) => CmdOutput::error(
+                1,
+                format!(
+                    "shutdown: cannot schedule '{}': only 'now' is supported",
+                    time
+                ),
+            ),
+        }
+    }
+
+    /// `init N` / `telinit N`: 0 halts, 1 drops to single-user, 2-5 come
+    /// back to multi-user, 6 reboots
+    pub(super) fn cmd_init(&mut self, args: &[&str]) -> CmdOutput {
+        let level = match args {
+            [level] => match level.parse::<u8>() {
+                Ok(n) if n <= 6 => n,
+                _ if level.eq_ignore_ascii_case("s") => 1,
+                _ => return CmdOutput::error(1, format!("init: invalid runlevel '{}'", level)),
+            },
+            _ => return CmdOutput::usage("usage: init RUNLEVEL (0-6)"),
+        };
+        if self.current_user() != "root" {
+            return CmdOutput::error(1, "Must be root.");
+        }
+        match level {
+            0 => CmdOutput::ok(self.go_down(PowerAction::PowerOff)),
+            6 => CmdOutput::ok(self.go_down(PowerAction::Reboot)),
+            1 => {
+                let mut out = self.stop_all_services();
+                out.push("[  OK  ] Reached target Rescue Mode.".into());
+                self.set_runlevel(1);
+                CmdOutput::ok(out.join("\n"))
+            }
+            n => {
+                self.services.auto_start_services(&mut |name| {
+                    self.kernel.proc.spawn(name, 1, &mut self.kernel.mem)
+                });
+                self.set_runlevel(n);
+                CmdOutput::ok("[  OK  ] Reached target Multi-User System.")
+            }
+        }
+    }
+
+    /// `runlevel`: the previous and current runlevel, `N` for none
+    pub(super) fn cmd_runlevel(&self) -> CmdOutput {
+        let previous = self
+            .previous_runlevel
+            .map_or("N".to_string(), |n| n.to_string());
+        CmdOutput::ok(format!("{} {}", previous, self.runlevel))
+    }
+
+    pub(super) fn set_runlevel(&mut self, level: u8) {
+        if level != self.runlevel {
+            self.previous_runlevel = Some(self.runlevel);
+            self.runlevel = level;
+        }
+    }
+
+    /// Stop running services, dependents before what they depend on
+    fn stop_all_services(&mut self) -> Vec<String> {
+        let mut out = Vec::new();
+        for name in self.services.start_order().into_iter().rev() {
+            if self.services.get_state(&name) != Some(ServiceState::Running) {
+                continue;
+            }
+            let description = self
+                .services
+                .get(&name)
+                .map_or(name.clone(), |s| s.description.clone());
+            out.push(format!("         Stopping {}...", description));
+            if self.systemctl_stop(&name).is_ok() {
+                out.push(format!("[  OK  ] Stopped {}.", description));
+            }
+        }
+        out
+    }
+
+    /// Take the whole system down: services in reverse order, every process,
+    /// then the kernel. The filesystem is saved by the frontend on the
+    /// event this emits, and the next boot starts from scratch
+    fn go_down(&mut self, action: PowerAction) -> String {
+        let mut out = self.stop_all_services();
+        out.push("[  OK  ] Stopped target Multi-User System.".into());
+
+        let pids: Vec<u32> = self.kernel.proc.list().iter().map(|p| p.pid).collect();
+        for pid in pids {
+            self.kernel.scheduler.remove(pid);
+            self.kernel.proc.kill(pid, &mut self.kernel.mem);
+        }
+        self.kernel.proc = ProcessTable::new();
+        self.kernel.scheduler = Scheduler::new();
+        self.jobs.clear();
+        self.next_job_id = 1;
+        self.http_servers.clear();
+        self.wscat = None;
+        self.traceroute = None;
+        self.htop = None;
+        self.pager = None;
+        self.term.leave_alternate();
+        self.rm_prompt = None;
+        self.sudo_authenticated_until = None;
+        out.push("[  OK  ] Finished Save filesystem to disk.".into());
+        out.push(format!("[  OK  ] Reached target {}.", action.target()));
+
+        self.record_uptime();
+        let secs = self.kernel.uptime_ms() as f64 / 1000.0;
+        out.push(format!("[{:12.6}] {}", secs, action.kernel_line()));
+        self.kernel.power_off();
+        self.booted = false;
+        self.cleared_after_boot = false;
+        self.set_runlevel(if action == PowerAction::Reboot { 6 } else { 0 });
+
+        let marker = self.emit(action.event());
+        if !marker.is_empty() {
+            out.push(marker);
+        }
+        out.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runlevels_and_reboot_reset_the_system() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let running = |sys: &System| {
+            sys.services
+                .names()
+                .iter()
+                .filter(|n| sys.services.get_state(n) == Some(ServiceState::Running))
+                .count()
+        };
+        assert!(running(&sys) > 0);
+        assert_eq!(sys.cmd_runlevel().flatten(), "N 3");
+
+        let denied = [
+            sys.cmd_init(&["1"]),
+            sys.cmd_shutdown(&["-r", "now"]),
+            sys.cmd_power("reboot", &[]),
+            sys.cmd_power("halt", &[]),
+        ];
+        for out in denied {
+            assert_eq!((out.stderr.as_str(), out.status), ("Must be root.", 1));
+        }
+        assert!(running(&sys) > 0);
+        assert_eq!(sys.cmd_runlevel().flatten(), "N 3");
+        assert!(sys.take_events().is_empty());
+
+        sys.shell.env.insert("USER".into(), "root".into());
+        let out = sys.cmd_init(&["1"]).flatten();
+        assert!(out.contains("[  OK  ] Stopped "));
+        assert!(out.ends_with("Reached target Rescue Mode."));
+        assert_eq!(running(&sys), 0);
+        assert_eq!(sys.cmd_runlevel().flatten(), "3 1");
+        sys.cmd_init(&["5"]);
+        assert!(running(&sys) > 0);
+        assert!(sys
+            .cmd_init(&["9"])
+            .flatten()
+            .starts_with("init: invalid runlevel"));
+
+        assert!(sys
+            .cmd_shutdown(&["-r", "+5"])
+            .flatten()
+            .contains("only 'now'"));
+        let out = sys.cmd_shutdown(&["-r", "now"]).flatten();
+        assert!(out.contains("Reached target System Reboot."));
+        assert!(out.ends_with("reboot: Restarting system"));
+        assert_eq!(sys.take_events(), vec![SystemEvent::Reboot]);
+        assert_eq!(running(&sys), 0);
+        assert!(sys.kernel.proc.list().is_empty());
+        assert!(!sys.is_booted());
+
+        sys.boot_with_params();
+        assert!(running(&sys) > 0);
+        assert_eq!(sys.cmd_runlevel().flatten(), "N 3");
+        sys.set_legacy_markers(true);
+        assert!(sys
+            .cmd_power("poweroff", &[])
+            .flatten()
+            .ends_with("\x1b[POWEROFF]"));
+        assert!(sys.cmd_power("halt", &[]).flatten().ends_with("\x1b[HALT]"));
+    }
+}
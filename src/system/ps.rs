@@ -0,0 +1,279 @@
+use super::System;
+use crate::process::{Process, TICK_US};
+use crate::shell::CmdOutput;
+
+// pgrep patterns: `^` and `$` anchor, anything else matches as a substring
+fn pattern_matches(pattern: &str, text: &str) -> bool {
+    match (pattern.strip_prefix('^'), pattern.strip_suffix('$')) {
+        (Some(rest), _) if rest.ends_with('$') => text == &rest[..rest.len() - 1],
+        (Some(rest), _) => text.starts_with(rest),
+        (None, Some(rest)) => text.ends_with(rest),
+        (None, None) => text.contains(pattern),
+    }
+}
+
+#[derive(Default)]
+struct Selection<'a> {
+    pattern: Option<&'a str>,
+    full: bool,
+    exact: bool,
+    user: Option<&'a str>,
+}
+
+impl Selection<'_> {
+    fn matches(&self, p: &Process) -> bool {
+        // Never pick ourselves
+        if p.name == "pgrep" || p.name == "pkill" {
+            return false;
+        }
+        if self.user.is_some_and(|u| u != p.user) {
+            return false;
+        }
+        let text = if self.full { &p.cmdline } else { &p.name };
+        match self.pattern {
+            Some(pattern) if self.exact => text == pattern,
+            Some(pattern) => pattern_matches(pattern, text),
+            None => true,
+        }
+    }
+}
+
+impl System {
+    fn ps_tty(p: &Process) -> &str {
+        p.tty.as_deref().unwrap_or("?")
+    }
+
+    /// Scheduler CPU time the way ps prints TIME
+    fn ps_time(p: &Process) -> String {
+        let secs = p.cpu_time_us / 1_000_000;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+
+    // Wall-clock start, worked back from how many scheduler ticks ago it was
+    fn ps_start(&self, p: &Process) -> String {
+        let ago_ms = (self.kernel.proc.ticks() - p.start_tick) as f64 * TICK_US / 1000.0;
+        let start = js_sys::Date::new(&(js_sys::Date::now() - ago_ms).into());
+        format!("{:02}:{:02}", start.get_hours(), start.get_minutes())
+    }
+
+    pub(super) fn ps_aux(&self) -> String {
+        let total_mem = self.kernel.mem.total.max(1) as f64;
+        let mut out = String::from(
+            "USER       PID %CPU %MEM    VSZ   RSS TTY       NI STAT START   TIME COMMAND\n",
+        );
+        for p in self.kernel.proc.list() {
+            out.push_str(&format!(
+                "{:<8} {:>5} {:>4.1} {:>4.1} {:>6} {:>5} {:<8} {:>3} {:<4} {:>5} {:>6} {}\n",
+                p.user,
+                p.pid,
+                Self::proc_cpu(p),
+                p.memory_size as f64 / total_mem * 100.0,
+                p.vsz_kb(),
+                p.rss_kb(),
+                Self::ps_tty(p),
+                p.nice,
+                Self::proc_state_char(p.state),
+                self.ps_start(p),
+                Self::ps_time(p),
+                p.cmdline
+            ));
+        }
+        out
+    }
+
+    pub(super) fn ps_ef(&self) -> String {
+        let mut out = String::from("UID          PID    PPID  C STIME TTY          TIME CMD\n");
+        for p in self.kernel.proc.list() {
+            out.push_str(&format!(
+                "{:<8} {:>7} {:>7} {:>2} {:>5} {:<8} {:>8} {}\n",
+                p.user,
+                p.pid,
+                p.ppid,
+                Self::proc_cpu(p) as u32,
+                self.ps_start(p),
+                Self::ps_tty(p),
+                Self::ps_time(p),
+                p.cmdline
+            ));
+        }
+        out
+    }
+
+    // Shared option parsing for pgrep and pkill; returns the selection and
+    // the signal (pkill only)
+    fn parse_selection<'a>(
+        cmd: &str,
+        args: &[&'a str],
+        list: &mut (bool, bool),
+    ) -> Result<(Selection<'a>, Option<&'a str>), String> {
+        let mut sel = Selection::default();
+        let mut signal = None;
+        let mut iter = args.iter();
+        while let Some(&arg) = iter.next() {
+            match arg {
+                "-f" => sel.full = true,
+                "-x" => sel.exact = true,
+                "-l" if cmd == "pgrep" => list.0 = true,
+                "-a" if cmd == "pgrep" => list.1 = true,
+                "-u" => match iter.next() {
+                    Some(&user) => sel.user = Some(user),
+                    None => return Err(format!("{}: option requires an argument -- 'u'", cmd)),
+                },
+                flag if cmd == "pkill" && flag.len() > 1 && flag.starts_with('-') => {
+                    signal = Some(flag)
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("{}: invalid option -- '{}'", cmd, &flag[1..]))
+                }
+                pattern if sel.pattern.is_none() => sel.pattern = Some(pattern),
+                _ => return Err(format!("{}: only one pattern can be provided", cmd)),
+            }
+        }
+        if sel.pattern.is_none() && sel.user.is_none() {
+            return Err(format!(
+                "usage: {} [-f] [-x] [-u USER] {}PATTERN",
+                cmd,
+                if cmd == "pgrep" {
+                    "[-l|-a] "
+                } else {
+                    "[-SIGNAL] "
+                }
+            ));
+        }
+        Ok((sel, signal))
+    }
+
+    /// `pgrep [-l|-a] [-f] [-x] [-u USER] PATTERN`
+    pub(super) fn cmd_pgrep(&self, args: &[&str]) -> CmdOutput {
+        let mut list = (false, false);
+        let (sel, _) = match Self::parse_selection("pgrep", args, &mut list) {
+            Ok(parsed) => parsed,
+            Err(e) => return CmdOutput::usage(e),
+        };
+        let mut found: Vec<&Process> = self
+            .kernel
+            .proc
+            .list()
+            .into_iter()
+            .filter(|p| sel.matches(p))
+            .collect();
+        found.sort_by_key(|p| p.pid);
+        let lines: Vec<String> = found
+            .iter()
+            .map(|p| match list {
+                (_, true) => format!("{} {}", p.pid, p.cmdline),
+                (true, _) => format!("{} {}", p.pid, p.name),
+                _ => p.pid.to_string(),
+            })
+            .collect();
+        // Like grep, finding nothing is a failure
+        CmdOutput {
+            status: i32::from(lines.is_empty()),
+            ..CmdOutput::ok(lines.join("\n"))
+        }
+    }
+
+    /// `pkill [-SIGNAL] [-f] [-x] [-u USER] PATTERN`, sent through `kill`
+    pub(super) fn cmd_pkill(&mut self, args: &[&str]) -> CmdOutput {
+        let (sel, signal) = match Self::parse_selection("pkill", args, &mut (false, false)) {
+            Ok(parsed) => parsed,
+            Err(e) => return CmdOutput::usage(e),
+        };
+        let user = self.current_user();
+        let mut found: Vec<&Process> = self
+            .kernel
+            .proc
+            .list()
+            .into_iter()
+            .filter(|p| p.pid > 1 && sel.matches(p))
+            .collect();
+        if found.is_empty() {
+            return CmdOutput::error(1, String::new());
+        }
+        found.sort_by_key(|p| p.pid);
+        // Only root may signal another user's processes
+        let (pids, denied): (Vec<&Process>, Vec<&Process>) = found
+            .into_iter()
+            .partition(|p| user == "root" || p.user == user);
+        let pids: Vec<String> = pids.iter().map(|p| p.pid.to_string()).collect();
+        let mut errors: Vec<String> = denied
+            .iter()
+            .map(|p| {
+                format!(
+                    "pkill: killing pid {} failed: Operation not permitted",
+                    p.pid
+                )
+            })
+            .collect();
+        if !pids.is_empty() {
+            let mut kill_args: Vec<&str> = signal.into_iter().collect();
+            kill_args.extend(pids.iter().map(String::as_str));
+            let killed = self.cmd_kill(&kill_args);
+            if !killed.stderr.is_empty() {
+                errors.push(killed.stderr);
+            }
+        }
+        CmdOutput::collected(Vec::new(), errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pgrep_and_pkill_by_pattern() {
+        assert!(pattern_matches("ng", "nginx"));
+        assert!(pattern_matches("^ngi", "nginx"));
+        assert!(pattern_matches("inx$", "nginx"));
+        assert!(pattern_matches("^nginx$", "nginx"));
+        assert!(!pattern_matches("^gin", "nginx"));
+
+        let mut sys = System::new();
+        let mem = &mut sys.kernel.mem;
+        let a = sys.kernel.proc.spawn("httpd", 1, mem).unwrap();
+        let b = sys.kernel.proc.spawn("httpd", 1, mem).unwrap();
+        sys.kernel
+            .proc
+            .set_session(b, "alice", Some("tty1"), "httpd -p 8080");
+
+        let both = sys.cmd_pgrep(&["^httpd$"]).flatten();
+        assert!(both.contains(&a.to_string()) && both.contains(&b.to_string()));
+        assert_eq!(
+            sys.cmd_pgrep(&["-u", "alice", "httpd"]).flatten(),
+            b.to_string()
+        );
+        assert_eq!(
+            sys.cmd_pgrep(&["-a", "-f", "8080"]).flatten(),
+            format!("{} httpd -p 8080", b)
+        );
+        assert_eq!(sys.cmd_pgrep(&["-x", "http"]).flatten(), "");
+        assert!(sys.cmd_pgrep(&[]).flatten().starts_with("usage"));
+
+        let out = sys.cmd_pkill(&["-u", "alice", "httpd"]);
+        assert_eq!(
+            out.stderr,
+            format!("pkill: killing pid {} failed: Operation not permitted", b)
+        );
+        assert_eq!(out.status, 1);
+        assert!(sys.kernel.proc.get(b).is_some());
+
+        let c = sys
+            .kernel
+            .proc
+            .spawn("httpd", 1, &mut sys.kernel.mem)
+            .unwrap();
+        sys.kernel
+            .proc
+            .set_session(c, "user", Some("tty1"), "httpd");
+        let out = sys.cmd_pkill(&["httpd"]);
+        assert_eq!(out.status, 1);
+        assert!(sys.kernel.proc.get(c).is_none());
+        assert!(sys.kernel.proc.get(b).is_some());
+
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert_eq!(sys.cmd_pkill(&["-u", "alice", "httpd"]).status, 0);
+        assert!(sys.kernel.proc.get(b).is_none());
+        assert!(sys.kernel.proc.get(a).is_some());
+    }
+}
@@ -0,0 +1,355 @@
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+use crate::vfs::Inode;
+use std::collections::VecDeque;
+
+/// Something the last `rm` took away, so `undo-rm` can put it back
+pub(super) enum Removed {
+    Trashed { from: String, to: String },
+    Deleted { from: String, node: Inode },
+}
+
+#[derive(Clone, Copy, Default)]
+struct RmFlags {
+    force: bool,
+    recursive: bool,
+    interactive: bool,
+    verbose: bool,
+    trash: bool,
+}
+
+/// `rm -i` waiting for a y/n answer about the first of `files`
+pub(super) struct RmPrompt {
+    files: VecDeque<String>,
+    flags: RmFlags,
+}
+
+fn basename(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    trimmed.rsplit('/').next().unwrap_or(trimmed)
+}
+
+impl System {
+    fn trash_dir(&self) -> String {
+        format!(
+            "{}/.Trash",
+            Self::default_home_for_user(&self.current_user())
+        )
+    }
+
+    /// `~/.config/rm`: `trash=on` makes plain `rm` move files to ~/.Trash
+    fn trash_by_default(&self) -> bool {
+        let home = Self::default_home_for_user(&self.current_user());
+        self.kernel
+            .fs
+            .resolve(&format!("{}/.config/rm", home))
+            .is_some_and(|node| {
+                node.data.lines().any(|line| {
+                    line.split_once('=').is_some_and(|(k, v)| {
+                        k.trim() == "trash" && matches!(v.trim(), "on" | "yes" | "true" | "1")
+                    })
+                })
+            })
+    }
+
+    pub(super) fn cmd_rm(&mut self, args: &[&str]) -> CmdOutput {
+        let mut flags = RmFlags {
+            trash: self.trash_by_default(),
+            ..RmFlags::default()
+        };
+        let mut files = Vec::new();
+        for arg in args {
+            match *arg {
+                "--force" => {
+                    flags.force = true;
+                    flags.interactive = false;
+                }
+                "--recursive" => flags.recursive = true,
+                "--interactive" => flags.interactive = true,
+                "--verbose" => flags.verbose = true,
+                "--trash" => flags.trash = true,
+                "--no-trash" => flags.trash = false,
+                opts if opts.starts_with('-') && opts.len() > 1 => {
+                    // Whichever of -f and -i comes last wins
+                    for c in opts[1..].chars() {
+                        match c {
+                            'f' => {
+                                flags.force = true;
+                                flags.interactive = false;
+                            }
+                            'i' => {
+                                flags.interactive = true;
+                                flags.force = false;
+                            }
+                            'r' | 'R' => flags.recursive = true,
+                            'v' => flags.verbose = true,
+                            _ => {
+                                return CmdOutput::error(
+                                    1,
+                                    format!("rm: invalid option -- '{}'", c),
+                                )
+                            }
+                        }
+                    }
+                }
+                file => files.push(file.to_string()),
+            }
+        }
+        if files.is_empty() {
+            return CmdOutput::error(1, "rm: missing operand");
+        }
+
+        self.rm_undo.clear();
+        let files: VecDeque<String> = files.into();
+        if flags.interactive {
+            return CmdOutput::ok(self.ask_next_rm(RmPrompt { files, flags }, Vec::new()));
+        }
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for file in files {
+            match self.rm_one(&file, flags) {
+                Ok(Some(line)) => out.push(line),
+                Ok(None) => {}
+                Err(e) if self.check_kernel_panic() => return CmdOutput::error(1, e),
+                Err(e) => errors.push(e),
+            }
+        }
+        CmdOutput::collected(out, errors)
+    }
+
+    /// Ask about the next file still to answer for, or finish the command
+    fn ask_next_rm(&mut self, mut prompt: RmPrompt, mut out: Vec<String>) -> String {
+        while let Some(file) = prompt.files.front() {
+            let question = match self.kernel.fs.resolve(file) {
+                None if prompt.flags.force => None,
+                None => Some(Err(format!(
+                    "rm: cannot remove '{}': No such file or directory",
+                    file
+                ))),
+                Some(node) if node.is_dir && !prompt.flags.recursive => {
+                    Some(Err(format!("rm: cannot remove '{}': Is a directory", file)))
+                }
+                Some(node) if node.is_dir => Some(Ok(format!("rm: remove directory '{}'?", file))),
+                Some(node) if node.data.is_empty() => {
+                    Some(Ok(format!("rm: remove regular empty file '{}'?", file)))
+                }
+                Some(_) => Some(Ok(format!("rm: remove regular file '{}'?", file))),
+            };
+            match question {
+                Some(Ok(question)) => {
+                    out.push(question);
+                    self.rm_prompt = Some(prompt);
+                    return out.join("\n");
+                }
+                Some(Err(e)) => out.push(e),
+                None => {}
+            }
+            prompt.files.pop_front();
+        }
+        out.join("\n")
+    }
+
+    /// The line typed in answer to an `rm -i` question
+    pub(super) fn answer_rm_prompt(&mut self, answer: &str) -> String {
+        let Some(mut prompt) = self.rm_prompt.take() else {
+            return String::new();
+        };
+        let Some(file) = prompt.files.pop_front() else {
+            return String::new();
+        };
+        let mut out = Vec::new();
+        if answer.trim_start().starts_with(['y', 'Y']) {
+            match self.rm_one(&file, prompt.flags) {
+                Ok(Some(line)) => out.push(line),
+                Ok(None) => {}
+                Err(e) if self.check_kernel_panic() => return e,
+                Err(e) => out.push(e),
+            }
+        }
+        self.ask_next_rm(prompt, out)
+    }
+
+    /// Remove (or trash) one operand. `Ok` carries the `-v` line
+    fn rm_one(&mut self, file: &str, flags: RmFlags) -> Result<Option<String>, String> {
+        let Some(is_dir) = self.kernel.fs.resolve(file).map(|node| node.is_dir) else {
+            if flags.force {
+                return Ok(None);
+            }
+            return Err(format!(
+                "rm: cannot remove '{}': No such file or directory",
+                file
+            ));
+        };
+        if is_dir && !flags.recursive {
+            return Err(format!("rm: cannot remove '{}': Is a directory", file));
+        }
+        let from = self.kernel.fs.normalize(file);
+        let verbose = |what: &str| flags.verbose.then(|| format!("{} '{}'", what, file));
+
+        let trash = self.trash_dir();
+        let in_trash = from == trash || from.starts_with(&format!("{}/", trash));
+        if flags.trash && !in_trash {
+            match self.move_to_trash(&from, &trash) {
+                Ok(to) => {
+                    self.rm_undo.push(Removed::Trashed {
+                        from,
+                        to: to.clone(),
+                    });
+                    return Ok(flags
+                        .verbose
+                        .then(|| format!("trashed '{}' -> '{}'", file, to)));
+                }
+                // System binaries are never spared; removing them panics below
+                Err("device or resource busy") => {}
+                Err(e) => return Err(format!("rm: cannot move '{}' to the trash: {}", file, e)),
+            }
+        }
+
+        let saved = self.kernel.fs.resolve(file).cloned();
+        if flags.recursive {
+            self.kernel.fs.set_ignore_critical_deletes(true);
+        }
+        let res = if flags.recursive {
+            self.kernel.fs.remove_recursive(file)
+        } else {
+            self.kernel.fs.remove(file)
+        };
+        if flags.recursive {
+            self.kernel.fs.set_ignore_critical_deletes(false);
+        }
+        match res {
+            Ok(()) => {
+                if let Some(node) = saved {
+                    self.rm_undo.push(Removed::Deleted { from, node });
+                }
+                Ok(verbose(if is_dir {
+                    "removed directory"
+                } else {
+                    "removed"
+                }))
+            }
+            Err(e) => {
+                if self.check_kernel_panic() {
+                    let reason = self.get_panic_message();
+                    return Err(self.emit(SystemEvent::KernelPanic { reason }));
+                }
+                if flags.force {
+                    return Ok(None);
+                }
+                Err(format!("rm: cannot remove '{}': {}", file, e))
+            }
+        }
+    }
+
+    /// Move `from` into the trash under a name not taken yet: `notes.txt`,
+    /// then `notes.txt.1`, `notes.txt.2`, ...
+    fn move_to_trash(&mut self, from: &str, trash: &str) -> Result<String, &'static str> {
+        if self.kernel.fs.resolve(trash).is_none() {
+            self.ensure_dir_all(trash)
+                .map_err(|_| "cannot create the trash directory")?;
+        }
+        let name = basename(from);
+        let mut to = format!("{}/{}", trash, name);
+        let mut n = 1;
+        while self.kernel.fs.resolve(&to).is_some() {
+            to = format!("{}/{}.{}", trash, name, n);
+            n += 1;
+        }
+        self.kernel.fs.rename(from, &to)?;
+        Ok(to)
+    }
+
+    /// `undo-rm`: put back everything the last `rm` removed or trashed
+    pub(super) fn cmd_undo_rm(&mut self) -> CmdOutput {
+        if self.rm_undo.is_empty() {
+            return CmdOutput::error(1, "undo-rm: nothing to undo");
+        }
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        for removed in std::mem::take(&mut self.rm_undo).into_iter().rev() {
+            let from = match &removed {
+                Removed::Trashed { from, .. } | Removed::Deleted { from, .. } => from.clone(),
+            };
+            if self.kernel.fs.resolve(&from).is_some() {
+                errors.push(format!("undo-rm: '{}' exists; not restoring it", from));
+                continue;
+            }
+            let parent = from.rsplit_once('/').map_or("/", |(p, _)| p);
+            let _ = self.ensure_dir_all(parent);
+            let result = match removed {
+                Removed::Trashed { to, .. } => self.kernel.fs.rename(&to, &from),
+                Removed::Deleted { node, .. } => self.kernel.fs.insert_node(&from, node),
+            };
+            match result {
+                Ok(()) => out.push(format!("restored '{}'", from)),
+                Err(e) => errors.push(format!("undo-rm: cannot restore '{}': {}", from, e)),
+            }
+        }
+        CmdOutput::collected(out, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interactive_trash_and_undo() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let fs = &mut sys.kernel.fs;
+        fs.create_file("/tmp/a", "alpha").unwrap();
+        fs.create_file("/tmp/b", "").unwrap();
+        fs.create_dir("/tmp/d").unwrap();
+        fs.create_file("/tmp/d/c", "gamma").unwrap();
+
+        assert_eq!(
+            sys.cmd_rm(&["/tmp/d"]).flatten(),
+            "rm: cannot remove '/tmp/d': Is a directory"
+        );
+        assert_eq!(
+            sys.exec("rm -i /tmp/a /tmp/nope /tmp/b"),
+            "rm: remove regular file '/tmp/a'?"
+        );
+        assert_eq!(
+            sys.exec("n"),
+            "rm: cannot remove '/tmp/nope': No such file or directory\n\
+             rm: remove regular empty file '/tmp/b'?"
+        );
+        assert_eq!(sys.exec("y"), "");
+        assert!(sys.kernel.fs.resolve("/tmp/a").is_some());
+        assert!(sys.kernel.fs.resolve("/tmp/b").is_none());
+
+        assert_eq!(sys.cmd_rm(&["-r", "/tmp/d"]).flatten(), "");
+        assert!(sys.kernel.fs.resolve("/tmp/d").is_none());
+        assert_eq!(sys.cmd_undo_rm().flatten(), "restored '/tmp/d'");
+        assert_eq!(sys.kernel.fs.resolve("/tmp/d/c").unwrap().data, "gamma");
+        assert_eq!(sys.cmd_undo_rm().flatten(), "undo-rm: nothing to undo");
+
+        let trash = sys.trash_dir();
+        assert_eq!(sys.cmd_rm(&["--trash", "/tmp/a"]).flatten(), "");
+        sys.kernel.fs.create_file("/tmp/a", "again").unwrap();
+        assert_eq!(
+            sys.cmd_rm(&["--trash", "-v", "/tmp/a"]).flatten(),
+            format!("trashed '/tmp/a' -> '{}/a.1'", trash)
+        );
+        assert_eq!(
+            sys.kernel.fs.resolve(&format!("{}/a", trash)).unwrap().data,
+            "alpha"
+        );
+        assert_eq!(sys.cmd_undo_rm().flatten(), "restored '/tmp/a'");
+        assert_eq!(sys.kernel.fs.resolve("/tmp/a").unwrap().data, "again");
+
+        let config = format!(
+            "{}/.config/rm",
+            System::default_home_for_user(&sys.current_user())
+        );
+        sys.ensure_dir_all(config.rsplit_once('/').unwrap().0)
+            .unwrap();
+        sys.kernel.fs.create_file(&config, "trash=on\n").unwrap();
+        sys.cmd_rm(&["/tmp/a"]);
+        assert!(sys.kernel.fs.resolve(&format!("{}/a.1", trash)).is_some());
+        sys.cmd_rm(&[&format!("{}/a.1", trash)]);
+        assert!(sys.kernel.fs.resolve(&format!("{}/a.1", trash)).is_none());
+    }
+}
@@ -0,0 +1,410 @@
+//! `rss`, a feed reader. A feed is fetched like a `lynx` page, parsed with
+//! the small XML reader here (RSS 2.0, RSS 1.0 and Atom all come down to a
+//! list of entries) and kept under ~/.cache/rss/, so `rss read N` and
+//! `rss list` work from the copy without fetching again
+use super::lynx::{attr, decode_entities, render};
+use super::netexec::normalize_url;
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+
+const RSS_USAGE: &str = "usage: rss URL | rss list | rss read N";
+/// The URL of the feed `rss list` and `rss read` use
+const CURRENT: &str = "current";
+
+/// An XML element with its attributes' source text and the text directly
+/// inside it
+struct Element {
+    name: String,
+    tag: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn new(name: &str, tag: &str) -> Self {
+        Element {
+            name: name.to_string(),
+            tag: tag.to_string(),
+            text: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Text of the first child named any of `names`
+    fn text_of(&self, names: &[&str]) -> String {
+        names
+            .iter()
+            .find_map(|name| self.child(name))
+            .map_or(String::new(), |c| c.text.trim().to_string())
+    }
+
+    /// Every `item` and `entry` below this element
+    fn entries(&self) -> Vec<&Element> {
+        let mut out = Vec::new();
+        for child in &self.children {
+            if matches!(child.name.as_str(), "item" | "entry") {
+                out.push(child);
+            } else {
+                out.extend(child.entries());
+            }
+        }
+        out
+    }
+}
+
+/// Parse `src` into its root element. Comments, the prolog and doctypes
+/// are skipped; CDATA is taken as it is
+fn parse_xml(src: &str) -> Result<Element, String> {
+    let mut stack = vec![Element::new("", "")];
+    let mut rest = src;
+    while let Some(open) = rest.find('<') {
+        let text = &rest[..open];
+        stack
+            .last_mut()
+            .unwrap()
+            .text
+            .push_str(&decode_entities(text));
+        rest = &rest[open..];
+        let skip = |rest: &str, end: &str| rest.find(end).map(|n| n + end.len());
+        if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body.find("]]>").ok_or("unterminated CDATA section")?;
+            stack.last_mut().unwrap().text.push_str(&body[..end]);
+            rest = &body[end + 3..];
+            continue;
+        }
+        let consumed = if rest.starts_with("<!--") {
+            skip(rest, "-->").ok_or("unterminated comment")?
+        } else if rest.starts_with("<?") {
+            skip(rest, "?>").ok_or("unterminated processing instruction")?
+        } else if rest.starts_with("<!") {
+            skip(rest, ">").ok_or("unterminated declaration")?
+        } else {
+            let close = rest.find('>').ok_or("unterminated tag")?;
+            let tag = &rest[1..close];
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or("");
+            if tag.starts_with('/') {
+                if stack.len() < 2 || stack.last().unwrap().name != name {
+                    return Err(format!("mismatched </{}>", name));
+                }
+                let done = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(done);
+            } else if tag.ends_with('/') {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(Element::new(name, tag));
+            } else {
+                stack.push(Element::new(name, tag));
+            }
+            close + 1
+        };
+        rest = &rest[consumed..];
+    }
+    if stack.len() != 1 {
+        return Err(format!("<{}> is never closed", stack.last().unwrap().name));
+    }
+    stack
+        .pop()
+        .and_then(|doc| doc.children.into_iter().next())
+        .ok_or_else(|| "no root element".to_string())
+}
+
+/// One story in a feed
+struct Entry {
+    title: String,
+    link: String,
+    date: String,
+    body: String,
+}
+
+/// A feed's title and entries, newest first as published
+struct Feed {
+    title: String,
+    entries: Vec<Entry>,
+}
+
+/// `Tue, 10 Jun 2003 04:00:00 GMT` or `2003-12-13T18:30:02Z` as
+/// `2003-06-10`; anything else as it is
+fn short_date(raw: &str) -> String {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let raw = raw.trim();
+    if let Some(day) = raw
+        .get(..10)
+        .filter(|d| d.as_bytes()[4] == b'-' && d.as_bytes()[7] == b'-')
+    {
+        return day.to_string();
+    }
+    let words: Vec<&str> = raw.split([' ', ',']).filter(|w| !w.is_empty()).collect();
+    let parsed = words.windows(3).find_map(|w| {
+        let day: u32 = w[0].parse().ok()?;
+        let month = MONTHS
+            .iter()
+            .position(|m| w[1].to_ascii_lowercase().starts_with(m))?;
+        let year: u32 = w[2].parse().ok()?;
+        Some(format!("{:04}-{:02}-{:02}", year, month + 1, day))
+    });
+    parsed.unwrap_or_else(|| raw.to_string())
+}
+
+/// The entries of an RSS or Atom document
+fn parse_feed(xml: &str) -> Result<Feed, String> {
+    let root = parse_xml(xml)?;
+    if !matches!(root.name.as_str(), "rss" | "feed" | "rdf:RDF") {
+        return Err("not an RSS or Atom feed".into());
+    }
+    let channel = root.child("channel").unwrap_or(&root);
+    let entries = root
+        .entries()
+        .into_iter()
+        .map(|item| {
+            // Atom links are attributes; the page itself is the alternate one
+            let link = match item.text_of(&["link"]) {
+                link if !link.is_empty() => link,
+                _ => item
+                    .children
+                    .iter()
+                    .filter(|c| c.name == "link")
+                    .find(|c| attr(&c.tag, "rel").is_none_or(|rel| rel == "alternate"))
+                    .and_then(|c| attr(&c.tag, "href"))
+                    .unwrap_or_default(),
+            };
+            Entry {
+                title: item.text_of(&["title"]),
+                link,
+                date: short_date(&item.text_of(&["pubDate", "published", "updated", "dc:date"])),
+                body: item.text_of(&["content:encoded", "content", "description", "summary"]),
+            }
+        })
+        .collect();
+    Ok(Feed {
+        title: channel.text_of(&["title"]),
+        entries,
+    })
+}
+
+/// File under ~/.cache/rss/ a feed is kept in
+fn cache_name(url: &str) -> String {
+    let bare = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let name: String = bare
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = name.trim_matches('_');
+    format!("{}.xml", &name[..name.len().min(60)])
+}
+
+impl System {
+    fn rss_dir(&self) -> String {
+        let home = Self::default_home_for_user(&self.current_user());
+        format!("{}/.cache/rss", home)
+    }
+
+    /// The feed `rss list` and `rss read` use, from the cache
+    fn rss_current(&self) -> Result<(String, Feed), String> {
+        let dir = self.rss_dir();
+        let url = self
+            .kernel
+            .fs
+            .resolve(&format!("{}/{}", dir, CURRENT))
+            .map(|node| node.data.trim().to_string())
+            .ok_or("rss: no feed yet; try rss URL")?;
+        let xml = self
+            .kernel
+            .fs
+            .resolve(&format!("{}/{}", dir, cache_name(&url)))
+            .map(|node| node.data.clone())
+            .ok_or_else(|| format!("rss: {}: not in the cache", url))?;
+        let feed = parse_feed(&xml).map_err(|e| format!("rss: {}: {}", url, e))?;
+        Ok((url, feed))
+    }
+
+    /// The numbered entries of `feed`, one line each
+    fn rss_listing(&self, url: &str, feed: &Feed) -> String {
+        let width = self.lynx_width();
+        let title = match feed.title.as_str() {
+            "" => url,
+            title => title,
+        };
+        let mut out = vec![format!("{} ({} entries)", title, feed.entries.len())];
+        for (n, entry) in feed.entries.iter().enumerate() {
+            let line = format!("{:>3}  {:<10}  {}", n + 1, entry.date, entry.title);
+            out.push(line.chars().take(width).collect());
+        }
+        out.join("\n")
+    }
+
+    /// A feed that arrived: cached and listed. If it could not be fetched
+    /// the cached copy is listed instead, when there is one
+    pub(super) fn rss_loaded(&mut self, url: &str, fetched: Result<String, String>) -> CmdOutput {
+        let dir = self.rss_dir();
+        let cache = format!("{}/{}", dir, cache_name(url));
+        let (xml, stale) = match fetched {
+            Ok(xml) => (xml, false),
+            Err(e) => match self.kernel.fs.resolve(&cache) {
+                Some(node) => (node.data.clone(), true),
+                None => {
+                    return CmdOutput::error(1, format!("rss: {}: {}", url, e));
+                }
+            },
+        };
+        let feed = match parse_feed(&xml) {
+            Ok(feed) => feed,
+            Err(e) => {
+                return CmdOutput::error(1, format!("rss: {}: {}", url, e));
+            }
+        };
+        let saved = self.ensure_dir_all(&dir).and_then(|()| {
+            self.write_file_bytes(&cache, xml.as_bytes())?;
+            self.write_file_bytes(&format!("{}/{}", dir, CURRENT), url.as_bytes())
+        });
+        if let Err(e) = saved {
+            return CmdOutput::error(1, format!("rss: {}: {}", dir, e));
+        }
+        let listing = self.rss_listing(url, &feed);
+        CmdOutput::ok(if stale {
+            format!(
+                "rss: {}: offline, showing the cached copy\n{}",
+                url, listing
+            )
+        } else {
+            listing
+        })
+    }
+
+    /// `rss read N`: entry N of the current feed in the pager
+    fn rss_read(&mut self, n: &str) -> CmdOutput {
+        let (url, feed) = match self.rss_current() {
+            Ok(current) => current,
+            Err(e) => return CmdOutput::error(1, e),
+        };
+        let Some(entry) = n
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|n| feed.entries.get(n))
+        else {
+            return CmdOutput::error(
+                1,
+                format!("rss: no entry {} (the feed has {})", n, feed.entries.len()),
+            );
+        };
+        let width = self.lynx_width();
+        let base = if entry.link.is_empty() {
+            &url
+        } else {
+            &entry.link
+        };
+        let (_, body, _) = render(&entry.body, base, width);
+        let mut text = vec![entry.title.clone()];
+        text.extend(
+            [entry.date.clone(), entry.link.clone()]
+                .into_iter()
+                .filter(|l| !l.is_empty()),
+        );
+        text.push(String::new());
+        text.extend(body);
+        CmdOutput::ok(self.pager_start(&entry.title, &text.join("\n"), false, true))
+    }
+
+    pub(super) fn cmd_rss(&mut self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] | ["list"] => match self.rss_current() {
+                Ok((url, feed)) => CmdOutput::ok(self.rss_listing(&url, &feed)),
+                Err(e) => CmdOutput::error(1, e),
+            },
+            ["read", n] => self.rss_read(n),
+            [url] if !url.starts_with('-') => {
+                let url = normalize_url(url, true);
+                if let Some((port, path)) = self.local_http_target(&url) {
+                    let fetched = self
+                        .serve_local_http(port, &path)
+                        .map(|response| String::from_utf8_lossy(&response.body).into_owned());
+                    return self.rss_loaded(&url, fetched);
+                }
+                let host = url
+                    .split_once("://")
+                    .map_or(url.as_str(), |(_, rest)| rest)
+                    .split(['/', '?', '#', ':'])
+                    .next()
+                    .unwrap_or("")
+                    .to_string();
+                if self.host_unreachable(&host).is_some() {
+                    return self.rss_loaded(&url, Err(format!("Could not resolve host: {}", host)));
+                }
+                CmdOutput::ok(self.emit(SystemEvent::Feed { url }))
+            }
+            _ => CmdOutput::usage(RSS_USAGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Kernel &amp; Friends</title>
+<item><title>Release 6.1</title><link>https://example.com/6.1</link>
+<pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate>
+<description><![CDATA[<p>The <b>new</b> kernel is out.</p>]]></description></item>
+<item><title>Patch notes</title><pubDate>Mon, 9 Jun 2003 09:00:00 GMT</pubDate></item>
+</channel></rss>"#;
+
+    const ATOM: &str = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+<entry><title>Hello</title><link rel="self" href="/self"/>
+<link href="https://blog.example/hello"/><updated>2003-12-13T18:30:02Z</updated>
+<summary>First post</summary></entry></feed>"#;
+
+    #[test]
+    fn rss_and_atom_feeds_parse_into_entries() {
+        let feed = parse_feed(RSS).unwrap();
+        assert_eq!(feed.title, "Kernel & Friends");
+        assert_eq!(feed.entries.len(), 2);
+        assert_eq!(feed.entries[0].date, "2003-06-10");
+        assert_eq!(feed.entries[0].body, "<p>The <b>new</b> kernel is out.</p>");
+
+        let atom = parse_feed(ATOM).unwrap();
+        assert_eq!(atom.entries[0].link, "https://blog.example/hello");
+        assert_eq!(atom.entries[0].date, "2003-12-13");
+        assert!(parse_feed("<html><p>hi</p></html>").is_err());
+        assert!(parse_xml("<rss><channel></rss>").is_err());
+    }
+
+    #[test]
+    fn feeds_are_cached_and_read_back() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let url = "https://example.com/feed.xml";
+        let listing = sys.rss_loaded(url, Ok(RSS.into())).stdout;
+        assert!(listing.starts_with("Kernel & Friends (2 entries)"));
+        assert!(listing.contains("  1  2003-06-10  Release 6.1"));
+        assert!(sys
+            .kernel
+            .fs
+            .resolve("/home/user/.cache/rss/example_com_feed_xml.xml")
+            .is_some());
+
+        let offline = sys
+            .rss_loaded(url, Err("Could not resolve host".into()))
+            .stdout;
+        assert!(offline.contains("showing the cached copy"));
+        assert!(sys
+            .exec("rss list")
+            .contains("  2  2003-06-09  Patch notes"));
+        let entry = sys.exec("rss read 1");
+        assert!(entry.starts_with("Release 6.1\n2003-06-10\nhttps://example.com/6.1"));
+        assert!(entry.contains("The new kernel is out."));
+        assert!(sys.exec("rss read 9").starts_with("rss: no entry 9"));
+    }
+}
@@ -0,0 +1,137 @@
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+use wasm_bindgen::prelude::*;
+
+impl System {
+    /// `clear [-x]`: blank the screen and drop the scrollback, or with `-x`
+    /// keep it. Inside tmux only the active pane is cleared
+    pub(super) fn cmd_clear(&mut self, args: &[&str]) -> CmdOutput {
+        let keep_scrollback = match args {
+            [] => false,
+            ["-x"] => true,
+            _ => return CmdOutput::usage("usage: clear [-x]"),
+        };
+        if let Some(pane) = self.mux.active_pane().and_then(|id| self.mux.pane_mut(id)) {
+            pane.clear();
+            return CmdOutput::ok(String::new());
+        }
+        if keep_scrollback {
+            self.term.clear_screen();
+        } else {
+            self.term.clear();
+        }
+        CmdOutput::ok(self.emit(SystemEvent::Clear))
+    }
+
+    /// `reset`: back to a fresh terminal, out of any alternate screen a
+    /// program left behind and with the scrollback gone
+    pub(super) fn cmd_reset(&mut self) -> CmdOutput {
+        self.pager = None;
+        self.htop = None;
+        self.term.reset();
+        CmdOutput::ok(self.emit(SystemEvent::Clear))
+    }
+}
+
+#[wasm_bindgen]
+impl System {
+    /// Record a line the frontend printed; returns the id of its first line
+    #[wasm_bindgen]
+    pub fn term_write(&mut self, text: &str, class: &str) -> u32 {
+        self.term.write(text, class)
+    }
+
+    /// Blank the screen and scrollback from the frontend's side (boot,
+    /// lock screen, Ctrl+L)
+    #[wasm_bindgen]
+    pub fn term_clear(&mut self) {
+        self.term.clear();
+    }
+
+    /// Every line kept, oldest first, as `{ id, text, class }`
+    #[wasm_bindgen]
+    pub fn term_lines(&self) -> JsValue {
+        let lines: Vec<_> = self.term.lines().collect();
+        serde_wasm_bindgen::to_value(&lines).unwrap_or(JsValue::NULL)
+    }
+
+    /// The lines on screen, as `term_lines`
+    #[wasm_bindgen]
+    pub fn term_visible(&self) -> JsValue {
+        let lines: Vec<_> = self.term.visible().collect();
+        serde_wasm_bindgen::to_value(&lines).unwrap_or(JsValue::NULL)
+    }
+
+    /// Id of the oldest line still kept; anything drawn before it can go
+    #[wasm_bindgen]
+    pub fn term_first_line(&self) -> u32 {
+        self.term.first_id()
+    }
+
+    /// Matches of `query` in the scrollback, newest first, as `{ line,
+    /// start, end }` for Ctrl+Shift+F
+    #[wasm_bindgen]
+    pub fn term_search(&self, query: &str) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.term.search(query)).unwrap_or(JsValue::NULL)
+    }
+
+    /// `{ row, col, visible }` of the cursor on the screen
+    #[wasm_bindgen]
+    pub fn term_cursor(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.term.cursor()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Most lines of scrollback to keep; never fewer than fit on screen
+    #[wasm_bindgen]
+    pub fn set_scrollback_limit(&mut self, lines: u32) {
+        self.term.set_limit(lines as usize);
+    }
+
+    /// Height of the terminal in rows, measured by the frontend. Exported
+    /// as `$LINES`
+    #[wasm_bindgen]
+    pub fn set_terminal_height(&mut self, rows: u32) {
+        if rows > 0 {
+            self.shell.env.insert("LINES".into(), rows.to_string());
+            self.term.resize(rows as usize, self.term.cols());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_reset_and_the_pager_share_the_screen() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.term_write("user@kpawnd:~$ ls\nDocuments", "output");
+
+        let long: String = (0..60).map(|i| format!("line {}\n", i)).collect();
+        sys.kernel.fs.create_file("/tmp/long.txt", &long).unwrap();
+        assert!(sys
+            .exec("less /tmp/long.txt")
+            .starts_with("\x1b[PAGER]line 0\n"));
+        assert!(sys.term.in_alternate());
+        assert_eq!(sys.term.lines().next().unwrap().text, "line 0");
+        sys.pager_key("q");
+        assert!(!sys.term.in_alternate());
+        assert_eq!(sys.term.lines().count(), 2);
+
+        assert_eq!(sys.exec("clear -x"), "");
+        assert_eq!(sys.term.search("documents").len(), 1);
+        assert_eq!(sys.exec("clear"), "");
+        assert_eq!(sys.term.lines().count(), 0);
+        assert_eq!(sys.exec("clear -z"), "usage: clear [-x]");
+
+        sys.exec("less /tmp/long.txt");
+        sys.exec("reset");
+        assert!(!sys.term.in_alternate());
+        assert!(sys.pager.is_none());
+        assert_eq!(
+            sys.take_events(),
+            vec![SystemEvent::Clear, SystemEvent::Clear, SystemEvent::Clear]
+        );
+    }
+}
@@ -0,0 +1,696 @@
+//! Login sessions: the console login, `su` shells and tmux panes, each on
+//! a terminal line of its own. `who` and `w` list the open ones, and every
+//! login and logout is kept in `/var/log/wtmp` for `last`
+use super::{System, TERMINAL_TTY};
+use crate::clock;
+use crate::kernel::KERNEL_VERSION;
+use crate::shell::CmdOutput;
+
+/// One `USER LINE LOGIN LOGOUT` record per session, oldest first. LOGOUT is
+/// `-` while the session is open and `crash` if the system went down under
+/// it; boots are recorded as user `reboot` on line `~`
+pub(super) const WTMP: &str = "/var/log/wtmp";
+
+/// What `w` shows for a shell sitting at its prompt
+const IDLE_SHELL: &str = "-bash";
+
+/// The shell a `su` was typed in, to go back to on `exit`
+struct Caller {
+    user: String,
+    home: String,
+    cwd: String,
+    owner: String,
+    group: String,
+}
+
+struct LoginSession {
+    id: u32,
+    user: String,
+    tty: String,
+    login: i64,
+    /// The tmux pane the session runs in
+    pane: Option<u32>,
+    /// Set for a `su` shell
+    caller: Option<Caller>,
+    /// The last line typed into the session
+    what: Option<String>,
+    last_input: i64,
+}
+
+#[derive(Default)]
+pub(super) struct Sessions {
+    open: Vec<LoginSession>,
+    next_id: u32,
+}
+
+/// `up` as `uptime` prints it: `5 min`, `1:05`, `2 days,  1:05`
+fn uptime_text(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    let clock = match hours {
+        0 => format!("{} min", mins),
+        _ => format!("{:>2}:{:02}", hours, mins),
+    };
+    match days {
+        0 => clock,
+        1 => format!("1 day, {}", clock),
+        _ => format!("{} days, {}", days, clock),
+    }
+}
+
+/// `w`'s IDLE column: `4.00s`, `3:05`, `1:02m`, `2days`
+fn idle_text(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{}.00s", s),
+        s if s < 3600 => format!("{}:{:02}", s / 60, s % 60),
+        s if s < 86_400 => format!("{}:{:02}m", s / 3600, s / 60 % 60),
+        s => format!("{}days", s / 86_400),
+    }
+}
+
+/// `Fri Oct 16 08:30`, the time column of `last`
+fn last_time(secs: i64) -> String {
+    let c = clock::civil(secs);
+    format!(
+        "{} {} {:>2} {:02}:{:02}",
+        c.weekday_name(),
+        c.month_name(),
+        c.day,
+        c.hour,
+        c.minute
+    )
+}
+
+fn hh_mm(secs: i64) -> String {
+    let c = clock::civil(secs);
+    format!("{:02}:{:02}", c.hour, c.minute)
+}
+
+/// How long a session lasted, `(00:15)` or `(1+02:30)`
+fn duration_text(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (days, hours, mins) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    match days {
+        0 => format!("({:02}:{:02})", hours, mins),
+        _ => format!("({}+{:02}:{:02})", days, hours, mins),
+    }
+}
+
+impl System {
+    fn wtmp_lines(&self) -> Vec<String> {
+        self.kernel
+            .fs
+            .resolve(WTMP)
+            .map(|n| n.data.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    fn write_wtmp(&mut self, lines: &[String]) {
+        self.write_system_file(WTMP, &(lines.join("\n") + "\n"));
+    }
+
+    /// Add a boot record. Sessions an earlier boot left open crashed with
+    /// it, and the earlier boot ends now
+    pub(super) fn record_boot(&mut self) {
+        let now = clock::now_secs();
+        let mut lines = self.wtmp_lines();
+        for line in &mut lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let ended = match fields[..] {
+                [user, tty, login, "-"] => {
+                    let end = match user {
+                        "reboot" => now.to_string(),
+                        _ => "crash".to_string(),
+                    };
+                    Some(format!("{} {} {} {}", user, tty, login, end))
+                }
+                _ => None,
+            };
+            if let Some(ended) = ended {
+                *line = ended;
+            }
+        }
+        lines.push(format!("reboot ~ {} -", now));
+        self.write_wtmp(&lines);
+    }
+
+    fn open_session(&mut self, user: &str, tty: &str, pane: Option<u32>, caller: Option<Caller>) {
+        let now = clock::now_secs();
+        let id = self.sessions.next_id;
+        self.sessions.next_id += 1;
+        self.sessions.open.push(LoginSession {
+            id,
+            user: user.to_string(),
+            tty: tty.to_string(),
+            login: now,
+            pane,
+            caller,
+            what: None,
+            last_input: now,
+        });
+        let mut lines = self.wtmp_lines();
+        lines.push(format!("{} {} {} -", user, tty, now));
+        self.write_wtmp(&lines);
+    }
+
+    fn close_session(&mut self, id: u32) -> Option<LoginSession> {
+        let at = self.sessions.open.iter().position(|s| s.id == id)?;
+        let session = self.sessions.open.remove(at);
+        let open = format!("{} {} {} -", session.user, session.tty, session.login);
+        let mut lines = self.wtmp_lines();
+        if let Some(line) = lines.iter_mut().rev().find(|l| **l == open) {
+            *line = format!(
+                "{} {} {} {}",
+                session.user,
+                session.tty,
+                session.login,
+                clock::now_secs()
+            );
+        }
+        self.write_wtmp(&lines);
+        Some(session)
+    }
+
+    /// The session typed lines go to: the newest one in the active pane,
+    /// or on the console outside tmux
+    fn current_session(&self) -> Option<&LoginSession> {
+        let pane = self.mux.active_pane();
+        self.sessions.open.iter().rev().find(|s| s.pane == pane)
+    }
+
+    /// The terminal line commands typed now run on
+    pub(super) fn current_tty(&self) -> String {
+        self.current_session()
+            .map_or(TERMINAL_TTY.to_string(), |s| s.tty.clone())
+    }
+
+    /// A login on the console, replacing whoever was logged in there
+    pub(super) fn console_login(&mut self, user: &str) {
+        self.console_logout();
+        self.open_session(user, TERMINAL_TTY, None, None);
+    }
+
+    pub(super) fn console_logout(&mut self) {
+        let console: Vec<u32> = self
+            .sessions
+            .open
+            .iter()
+            .filter(|s| s.pane.is_none())
+            .map(|s| s.id)
+            .collect();
+        for id in console {
+            self.close_session(id);
+        }
+    }
+
+    /// Note a typed line against the session it went to, for `w`. Answers
+    /// to a prompt leave the command that asked in place
+    pub(super) fn session_input(&mut self, line: &str) {
+        if self.in_exec {
+            return;
+        }
+        let answering = self.sudo_waiting_password || self.rm_prompt.is_some();
+        let pane = self.mux.active_pane();
+        let Some(session) = self.sessions.open.iter_mut().rev().find(|s| s.pane == pane) else {
+            return;
+        };
+        session.last_input = clock::now_secs();
+        if !answering && !line.trim().is_empty() {
+            session.what = Some(line.trim().to_string());
+        }
+    }
+
+    /// Open a session for every tmux pane that has none yet and close the
+    /// sessions of panes that are gone
+    pub(super) fn sync_pane_sessions(&mut self) {
+        let panes = self.mux.pane_ids();
+        let gone: Vec<u32> = self
+            .sessions
+            .open
+            .iter()
+            .filter(|s| s.pane.is_some_and(|p| !panes.contains(&p)))
+            .map(|s| s.id)
+            .collect();
+        for id in gone {
+            self.close_session(id);
+        }
+        for pane in panes {
+            if self.sessions.open.iter().any(|s| s.pane == Some(pane)) {
+                continue;
+            }
+            let user = self
+                .mux
+                .pane(pane)
+                .and_then(|p| p.shell.env.get("USER").cloned())
+                .unwrap_or_else(|| "user".into());
+            self.open_session(&user, &format!("pts/{}", pane), Some(pane), None);
+        }
+    }
+
+    /// `exit` from a `su` shell: close it and go back to the caller.
+    /// Returns whether there was one
+    pub(super) fn exit_su(&mut self) -> bool {
+        let Some(id) = self
+            .current_session()
+            .filter(|s| s.caller.is_some())
+            .map(|s| s.id)
+        else {
+            return false;
+        };
+        let Some(Caller {
+            user,
+            home,
+            cwd,
+            owner,
+            group,
+        }) = self.close_session(id).and_then(|s| s.caller)
+        else {
+            return false;
+        };
+        self.shell.env.insert("USER".into(), user);
+        self.shell.env.insert("HOME".into(), home);
+        self.kernel.fs.cwd = cwd;
+        self.kernel.fs.set_default_owner(&owner, &group);
+        true
+    }
+
+    /// The id the next session will get, to find the ones opened after
+    pub(super) fn session_mark(&self) -> u32 {
+        self.sessions.next_id
+    }
+
+    /// After `sudo su`: the shell `su` opened since `mark` goes back to the
+    /// user sudo was run by, not to root. Returns whether there was one
+    pub(super) fn hand_su_back(
+        &mut self,
+        mark: u32,
+        user: &str,
+        home: &str,
+        owner: &str,
+        group: &str,
+    ) -> bool {
+        let Some(caller) = self
+            .sessions
+            .open
+            .iter_mut()
+            .rev()
+            .find(|s| s.id >= mark)
+            .and_then(|s| s.caller.as_mut())
+        else {
+            return false;
+        };
+        caller.user = user.to_string();
+        caller.home = home.to_string();
+        caller.owner = owner.to_string();
+        caller.group = group.to_string();
+        true
+    }
+
+    /// `su [-] [USER]`: a shell as USER (root by default) on the same
+    /// terminal until `exit`. Anyone but root is asked for the password
+    pub(super) fn cmd_su(&mut self, args: &[&str]) -> CmdOutput {
+        let mut login = false;
+        let mut target = None;
+        for arg in args {
+            match *arg {
+                "-" | "-l" | "--login" => login = true,
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(
+                        1,
+                        format!(
+                            "su: invalid option -- '{}'\nusage: su [-] [USER]",
+                            flag.trim_start_matches('-')
+                        ),
+                    )
+                }
+                user if target.is_none() => target = Some(user.to_string()),
+                _ => return CmdOutput::error(1, "usage: su [-] [USER]"),
+            }
+        }
+        let target = target.unwrap_or_else(|| "root".into());
+        if !self.user_exists(&target) {
+            return CmdOutput::error(1, format!("su: user {} does not exist", target));
+        }
+        if self.current_user() == "root" {
+            self.su_switch(&target, login);
+            return CmdOutput::default();
+        }
+        self.sudo_pending_request = Some(super::SudoPendingRequest {
+            command: None,
+            target_user: target,
+            validate_only: false,
+            list_privileges: false,
+            switch_user: Some(login),
+        });
+        self.sudo_waiting_password = true;
+        CmdOutput::ok("Password:")
+    }
+
+    /// The password typed after `su` asked for it
+    pub(super) fn su_authenticate(&mut self, target: &str, login: bool, pw: &str) -> CmdOutput {
+        match &self.user_password {
+            Some(saved) if saved == pw => {
+                self.su_switch(target, login);
+                CmdOutput::default()
+            }
+            _ => CmdOutput::error(1, "su: Authentication failure"),
+        }
+    }
+
+    fn su_switch(&mut self, target: &str, login: bool) {
+        let caller = Caller {
+            user: self.current_user(),
+            home: self
+                .shell
+                .env
+                .get("HOME")
+                .cloned()
+                .unwrap_or_else(|| "/home/user".into()),
+            cwd: self.kernel.fs.cwd.clone(),
+            owner: self.kernel.fs.get_default_owner(),
+            group: self.kernel.fs.get_default_group(),
+        };
+        let home = Self::default_home_for_user(target);
+        self.shell.env.insert("USER".into(), target.into());
+        self.shell.env.insert("HOME".into(), home.clone());
+        let _ = self.kernel.fs.create_dir(&home);
+        self.kernel.fs.set_default_owner(target, target);
+        if login {
+            self.kernel.fs.cwd = home;
+        }
+        let tty = self.current_tty();
+        let pane = self.mux.active_pane();
+        self.open_session(target, &tty, pane, Some(caller));
+    }
+
+    /// The first line of `uptime` and `w`: time, uptime, users and load
+    fn uptime_summary(&self) -> String {
+        let now = clock::civil(clock::now_secs());
+        let users = self.sessions.open.len();
+        let (load1, load5, load15) = self.load_average();
+        format!(
+            " {:02}:{:02}:{:02} up {},  {} user{},  load average: {:.2}, {:.2}, {:.2}",
+            now.hour,
+            now.minute,
+            now.second,
+            uptime_text(self.kernel.uptime_ms() / 1000),
+            users,
+            if users == 1 { "" } else { "s" },
+            load1,
+            load5,
+            load15
+        )
+    }
+
+    /// Users logged in, for `top`'s summary line
+    pub(super) fn user_count(&self) -> usize {
+        self.sessions.open.len()
+    }
+
+    /// `uptime [-p|-s]`
+    pub(super) fn cmd_uptime(&self, args: &[&str]) -> CmdOutput {
+        let secs = self.kernel.uptime_ms() / 1000;
+        match args {
+            [] => CmdOutput::ok(self.uptime_summary()),
+            ["-p" | "--pretty"] => {
+                CmdOutput::ok(format!("up {}", super::neofetch::format_uptime(secs)))
+            }
+            ["-s" | "--since"] => {
+                let c = clock::civil(clock::now_secs() - secs as i64);
+                CmdOutput::ok(format!(
+                    "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    c.year, c.month, c.day, c.hour, c.minute, c.second
+                ))
+            }
+            _ => CmdOutput::error(1, "usage: uptime [-p|-s]"),
+        }
+    }
+
+    /// `who [-b|-H|-q|-m|am i]`: the open sessions, one per line
+    pub(super) fn cmd_who(&self, args: &[&str]) -> CmdOutput {
+        let (mut boot, mut heading, mut count, mut mine) = (false, false, false, false);
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "-b" | "--boot" => boot = true,
+                "-H" | "--heading" => heading = true,
+                "-q" | "--count" => count = true,
+                "-m" => mine = true,
+                "am" if rest.next() == Some(&"i") => mine = true,
+                _ => return CmdOutput::error(1, "usage: who [-b|-H|-q|-m|am i]"),
+            }
+        }
+        let stamp = |secs: i64| {
+            let c = clock::civil(secs);
+            format!(
+                "{}-{:02}-{:02} {:02}:{:02}",
+                c.year, c.month, c.day, c.hour, c.minute
+            )
+        };
+        if boot {
+            let booted = clock::now_secs() - (self.kernel.uptime_ms() / 1000) as i64;
+            return CmdOutput::ok(format!("         system boot  {}", stamp(booted)));
+        }
+        let current = self.current_session().map(|s| s.id);
+        let sessions: Vec<&LoginSession> = self
+            .sessions
+            .open
+            .iter()
+            .filter(|s| !mine || Some(s.id) == current)
+            .collect();
+        if count {
+            let names: Vec<&str> = sessions.iter().map(|s| s.user.as_str()).collect();
+            return CmdOutput::ok(format!("{}\n# users={}", names.join(" "), names.len()));
+        }
+        let mut out = Vec::new();
+        if heading {
+            out.push("NAME     LINE         TIME             COMMENT".to_string());
+        }
+        for s in sessions {
+            let comment = match s.pane {
+                Some(pane) => format!(" (tmux.%{})", pane),
+                None => String::new(),
+            };
+            out.push(format!(
+                "{:<8} {:<12} {}{}",
+                s.user,
+                s.tty,
+                stamp(s.login),
+                comment
+            ));
+        }
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// What `w` shows a session running: the line typed into it if it is
+    /// the one running `w` or a `su` or tmux sits on top of it, otherwise
+    /// the shell waiting at its prompt
+    fn session_what(&self, session: &LoginSession, current: Option<u32>) -> String {
+        let covered = self.sessions.open.iter().any(|other| {
+            other.id > session.id
+                && ((other.pane == session.pane && other.caller.is_some())
+                    || (session.pane.is_none() && other.pane.is_some()))
+        });
+        match &session.what {
+            Some(what) if covered || Some(session.id) == current => what.clone(),
+            _ => IDLE_SHELL.to_string(),
+        }
+    }
+
+    /// `w [-h] [USER]`: the `uptime` line, then who is on which terminal,
+    /// since when, idle how long and running what
+    pub(super) fn cmd_w(&self, args: &[&str]) -> CmdOutput {
+        let mut header = true;
+        let mut user = None;
+        for arg in args {
+            match *arg {
+                "-h" | "--no-header" => header = false,
+                flag if flag.starts_with('-') => {
+                    return CmdOutput::error(1, "usage: w [-h] [USER]")
+                }
+                name => user = Some(name),
+            }
+        }
+        let now = clock::now_secs();
+        let today = clock::civil(now);
+        let current = self.current_session().map(|s| s.id);
+        let mut out = Vec::new();
+        if header {
+            out.push(self.uptime_summary());
+            out.push("USER     TTY      LOGIN@   IDLE  WHAT".to_string());
+        }
+        for s in &self.sessions.open {
+            if user.is_some_and(|u| u != s.user) {
+                continue;
+            }
+            let login = clock::civil(s.login);
+            let login_at =
+                if (login.year, login.month, login.day) == (today.year, today.month, today.day) {
+                    hh_mm(s.login)
+                } else {
+                    format!("{:02}{}", login.day, login.month_name())
+                };
+            let idle = match Some(s.id) == current {
+                true => "0.00s".to_string(),
+                false => idle_text(now - s.last_input),
+            };
+            out.push(format!(
+                "{:<8} {:<8} {:<8} {:<5} {}",
+                s.user,
+                s.tty,
+                login_at,
+                idle,
+                self.session_what(s, current)
+            ));
+        }
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// `last [-n N] [USER|TTY]...`: logins and boots from `/var/log/wtmp`,
+    /// newest first
+    pub(super) fn cmd_last(&self, args: &[&str]) -> CmdOutput {
+        let mut limit = None;
+        let mut names = Vec::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            let count = match *arg {
+                "-n" => match rest.next() {
+                    Some(n) => *n,
+                    None => return CmdOutput::error(1, "last: option requires an argument -- 'n'"),
+                },
+                flag if flag.starts_with('-') => &flag[1..],
+                name => {
+                    names.push(name);
+                    continue;
+                }
+            };
+            match count.parse::<usize>() {
+                Ok(n) => limit = Some(n),
+                Err(_) => {
+                    return CmdOutput::error(
+                        1,
+                        format!("last: invalid number of lines: '{}'", count),
+                    )
+                }
+            }
+        }
+        let lines = self.wtmp_lines();
+        let records: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>())
+            .filter(|f| f.len() == 4)
+            .collect();
+        let mut out = Vec::new();
+        for fields in records.iter().rev() {
+            let [user, tty, login, logout] = fields[..] else {
+                continue;
+            };
+            let boot = user == "reboot";
+            let line = if boot { "system boot" } else { tty };
+            if !names.is_empty() && !names.iter().any(|n| *n == user || *n == line) {
+                continue;
+            }
+            if limit.is_some_and(|n| out.len() >= n) {
+                break;
+            }
+            let Ok(login) = login.parse::<i64>() else {
+                continue;
+            };
+            let host = if boot { KERNEL_VERSION } else { "" };
+            let end = match logout {
+                "-" if boot => "  still running".to_string(),
+                "-" => "  still logged in".to_string(),
+                "crash" => " - crash".to_string(),
+                secs => match secs.parse::<i64>() {
+                    Ok(secs) => format!(" - {}  {}", hh_mm(secs), duration_text(secs - login)),
+                    Err(_) => String::new(),
+                },
+            };
+            out.push(format!(
+                "{:<8} {:<12} {:<16} {}{}",
+                user,
+                line,
+                host,
+                last_time(login),
+                end
+            ));
+        }
+        let begins = records
+            .first()
+            .and_then(|f| f[2].parse::<i64>().ok())
+            .unwrap_or_else(clock::now_secs);
+        out.push(String::new());
+        out.push(format!("wtmp begins {}", clock::ctime(begins)));
+        CmdOutput::ok(out.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_follow_logins_su_and_panes() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.record_boot();
+        sys.console_login("user");
+        sys.set_user_password("hunter2");
+        assert_eq!(sys.user_count(), 1);
+        assert!(sys
+            .cmd_who(&[])
+            .stdout
+            .starts_with("user     tty1         "));
+
+        // su asks for the password, then stacks a root shell on tty1
+        let cwd = sys.kernel.fs.cwd.clone();
+        assert_eq!(sys.exec("su -"), "Password:");
+        assert_eq!(sys.exec("hunter2"), "");
+        assert_eq!(sys.current_user(), "root");
+        assert_eq!(sys.kernel.fs.cwd, "/root");
+        let w = sys.cmd_w(&[]).stdout;
+        assert!(w.contains("  2 users,  load average: "));
+        assert!(w.contains("\nuser     tty1     "));
+        assert!(w.ends_with(" su -") || w.contains(" su -\n"));
+        sys.exec("exit");
+        assert_eq!(sys.current_user(), "user");
+        assert_eq!(sys.kernel.fs.cwd, cwd);
+        assert_eq!(sys.exec("su ghost"), "su: user ghost does not exist");
+
+        // Each tmux pane is a pts line of its own
+        sys.exec("tmux new");
+        sys.exec("tmux split-window");
+        let who = sys.cmd_who(&["-q"]).stdout;
+        assert!(who.ends_with("# users=3"));
+        assert_eq!(sys.current_tty(), "pts/1");
+        sys.exec("exit");
+        sys.exec("exit");
+        assert_eq!(sys.user_count(), 1);
+
+        let last = sys.cmd_last(&[]).stdout;
+        let lines: Vec<&str> = last.lines().collect();
+        assert!(lines[0].starts_with("user     pts/1 "));
+        assert!(lines[1].starts_with("user     pts/0 "));
+        assert!(lines[2].starts_with("root     tty1 "));
+        assert!(lines[3].starts_with("user     tty1 ") && lines[3].ends_with("still logged in"));
+        assert!(last.contains("reboot   system boot  6."));
+        assert!(last.contains("\n\nwtmp begins "));
+        assert_eq!(sys.cmd_last(&["-n", "1"]).stdout.lines().count(), 3);
+        assert!(sys.cmd_last(&["root"]).stdout.starts_with("root "));
+
+        sys.record_boot();
+        let last = sys.cmd_last(&["tty1"]).stdout;
+        assert!(last.lines().nth(1).unwrap().ends_with(" - crash"));
+        assert!(!last.contains("still logged in"));
+    }
+
+    #[test]
+    fn uptime_and_idle_formats() {
+        assert_eq!(uptime_text(300), "5 min");
+        assert_eq!(uptime_text(3900), " 1:05");
+        assert_eq!(uptime_text(2 * 86_400 + 3900), "2 days,  1:05");
+        assert_eq!(idle_text(4), "4.00s");
+        assert_eq!(idle_text(185), "3:05");
+        assert_eq!(duration_text(900), "(00:15)");
+        assert_eq!(duration_text(86_400 + 9000), "(1+02:30)");
+    }
+}
@@ -0,0 +1,163 @@
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+
+const SCORES_FILE: &str = ".snake_scores";
+const MAX_SCORES: usize = 10;
+
+/// One line of `~/.snake_scores`: `score<TAB>user<TAB>date`, best first
+#[derive(Debug, PartialEq)]
+struct ScoreEntry {
+    score: u32,
+    user: String,
+    date: String,
+}
+
+fn parse_scores(text: &str) -> Vec<ScoreEntry> {
+    let mut entries: Vec<ScoreEntry> = text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let score = fields.next()?.trim().parse().ok()?;
+            Some(ScoreEntry {
+                score,
+                user: fields.next().unwrap_or("?").to_string(),
+                date: fields.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect();
+    // Hand-edited files may be out of order
+    entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+    entries.truncate(MAX_SCORES);
+    entries
+}
+
+fn format_scores(entries: &[ScoreEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| format!("{}\t{}\t{}\n", e.score, e.user, e.date))
+        .collect()
+}
+
+/// Add `entry` below any equal scores; returns its 1-based rank if it made
+/// the table
+fn insert_score(entries: &mut Vec<ScoreEntry>, entry: ScoreEntry) -> Option<usize> {
+    let pos = entries
+        .iter()
+        .position(|e| e.score < entry.score)
+        .unwrap_or(entries.len());
+    if pos >= MAX_SCORES {
+        return None;
+    }
+    entries.insert(pos, entry);
+    entries.truncate(MAX_SCORES);
+    Some(pos + 1)
+}
+
+impl System {
+    fn snake_scores_path(&self) -> String {
+        let home = Self::default_home_for_user(&self.current_user());
+        format!("{}/{}", home, SCORES_FILE)
+    }
+
+    fn snake_scores(&self) -> Vec<ScoreEntry> {
+        self.kernel
+            .fs
+            .resolve(&self.snake_scores_path())
+            .map(|node| parse_scores(&node.data))
+            .unwrap_or_default()
+    }
+
+    /// `snake` starts a game, `snake scores` prints the high-score table
+    pub(super) fn cmd_snake(&mut self, args: &[&str]) -> CmdOutput {
+        match args {
+            [] => CmdOutput::ok(self.emit(SystemEvent::LaunchSnake)),
+            ["scores"] => {
+                let entries = self.snake_scores();
+                if entries.is_empty() {
+                    return CmdOutput::ok("snake: no high scores yet".to_string());
+                }
+                let mut out = String::from(" #  SCORE  USER          DATE\n");
+                for (i, e) in entries.iter().enumerate() {
+                    out.push_str(&format!(
+                        "{:>2}  {:>5}  {:<12}  {}\n",
+                        i + 1,
+                        e.score,
+                        e.user,
+                        e.date
+                    ));
+                }
+                CmdOutput::ok(out.trim_end().to_string())
+            }
+            _ => CmdOutput::usage("usage: snake [scores]".to_string()),
+        }
+    }
+
+    pub(super) fn top_snake_score(&self) -> u32 {
+        self.snake_scores().first().map_or(0, |e| e.score)
+    }
+
+    /// Put a finished game in the table; returns a line for the terminal
+    pub(super) fn store_snake_score(&mut self, score: u32) -> String {
+        let mut entries = self.snake_scores();
+        let d = js_sys::Date::new_0();
+        let entry = ScoreEntry {
+            score,
+            user: self.current_user(),
+            date: format!(
+                "{}-{:02}-{:02}",
+                d.get_full_year(),
+                d.get_month() + 1,
+                d.get_date()
+            ),
+        };
+        let Some(rank) = insert_score(&mut entries, entry) else {
+            return format!("snake: scored {}", score);
+        };
+        let path = self.snake_scores_path();
+        if let Err(e) = self.write_file_bytes(&path, format_scores(&entries).as_bytes()) {
+            return format!("snake: {}: {}", path, e);
+        }
+        if rank == 1 {
+            format!("snake: scored {} - new high score!", score)
+        } else {
+            format!(
+                "snake: scored {} - #{} on the high-score table",
+                score, rank
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, user: &str) -> ScoreEntry {
+        ScoreEntry {
+            score,
+            user: user.to_string(),
+            date: "2026-01-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn scores_stay_sorted_and_capped() {
+        let mut entries = parse_scores("30\tbob\t2026-01-01\nnot a score\n90\tann\t2026-01-02\n");
+        assert_eq!(
+            entries.iter().map(|e| e.score).collect::<Vec<_>>(),
+            [90, 30]
+        );
+
+        assert_eq!(insert_score(&mut entries, entry(50, "cy")), Some(2));
+        // Ties go below the existing score
+        assert_eq!(insert_score(&mut entries, entry(90, "dee")), Some(2));
+        for _ in 0..MAX_SCORES {
+            insert_score(&mut entries, entry(100, "ed"));
+        }
+        assert_eq!(entries.len(), MAX_SCORES);
+        assert_eq!(insert_score(&mut entries, entry(10, "fi")), None);
+
+        let round_trip = parse_scores(&format_scores(&entries));
+        assert_eq!(round_trip, entries);
+    }
+}
@@ -0,0 +1,186 @@
+use super::{human_size, System};
+use crate::clock;
+use crate::shell::CmdOutput;
+use crate::vfs_persist::Snapshot;
+
+const USAGE: &str = "usage: snapshot create NAME | list | restore NAME | delete NAME";
+
+impl System {
+    /// `snapshot create|list|restore|delete`: whole-filesystem copies to
+    /// roll back to after an unlucky `rm -rf`. Anyone may list them; only
+    /// root may change them or roll back
+    pub(super) fn cmd_snapshot(&mut self, args: &[&str]) -> CmdOutput {
+        let changes = matches!(args, ["create" | "restore" | "delete" | "rm", _]);
+        if changes && self.current_user() != "root" {
+            return CmdOutput::error(1, "snapshot: only root can do that");
+        }
+        match args {
+            ["list"] | ["ls"] => CmdOutput::ok(self.snapshot_list()),
+            ["create", name] => self.snapshot_create(name),
+            ["restore", name] => self.snapshot_restore(name),
+            ["delete", name] | ["rm", name] => {
+                let before = self.kernel.snapshots.len();
+                self.kernel.snapshots.retain(|s| s.name != *name);
+                if self.kernel.snapshots.len() == before {
+                    CmdOutput::error(1, format!("snapshot: '{}': no such snapshot", name))
+                } else {
+                    CmdOutput::ok(format!("Deleted snapshot '{}'", name))
+                }
+            }
+            _ => CmdOutput::usage(USAGE),
+        }
+    }
+
+    fn snapshot_create(&mut self, name: &str) -> CmdOutput {
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "snapshot: '{}': names may only use letters, digits, '.', '_' and '-'",
+                    name
+                ),
+            );
+        }
+        if self.kernel.snapshots.iter().any(|s| s.name == name) {
+            return CmdOutput::error(1, format!("snapshot: '{}' already exists", name));
+        }
+        match Snapshot::take(name, &self.kernel.fs.root_clone(), clock::now_secs()) {
+            Ok(snapshot) => {
+                let line = format!(
+                    "Created snapshot '{}': {} files, {} ({} compressed)",
+                    name,
+                    snapshot.files,
+                    human_size(snapshot.size as u64),
+                    human_size(snapshot.stored_size() as u64)
+                );
+                self.kernel.snapshots.push(snapshot);
+                CmdOutput::ok(line)
+            }
+            Err(e) => CmdOutput::error(1, format!("snapshot: cannot create '{}': {}", name, e)),
+        }
+    }
+
+    pub(super) fn snapshot_list(&self) -> String {
+        if self.kernel.snapshots.is_empty() {
+            return "No snapshots (create one with: snapshot create NAME)".into();
+        }
+        let now = clock::now_secs();
+        let width = self
+            .kernel
+            .snapshots
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        let mut out = vec![format!(
+            "{:<width$}  {:<12}  {:>6}  {:>5}",
+            "NAME", "CREATED", "FILES", "SIZE"
+        )];
+        for s in &self.kernel.snapshots {
+            out.push(format!(
+                "{:<width$}  {:<12}  {:>6}  {:>5}",
+                s.name,
+                clock::ls_time(s.created, now),
+                s.files,
+                human_size(s.stored_size() as u64)
+            ));
+        }
+        out.join("\n")
+    }
+
+    /// Put the whole tree back as it was, clearing a panic if that brings
+    /// the system binaries back
+    pub(super) fn snapshot_restore(&mut self, name: &str) -> CmdOutput {
+        let Some(snapshot) = self.kernel.snapshots.iter().find(|s| s.name == name) else {
+            return CmdOutput::error(1, format!("snapshot: '{}': no such snapshot", name));
+        };
+        let root = match snapshot.tree() {
+            Ok(root) => root,
+            Err(e) => {
+                return CmdOutput::error(1, format!("snapshot: cannot restore '{}': {}", name, e))
+            }
+        };
+        let created = snapshot.created;
+        let cwd = self.kernel.fs.cwd.clone();
+        self.kernel.fs.set_root(root);
+        if self.kernel.fs.resolve(&cwd).is_none() {
+            self.kernel.fs.cwd = "/".into();
+        }
+        if self.kernel.fs.missing_critical().is_empty() {
+            self.kernel.fs.kernel_panic = false;
+            self.kernel.fs.panic_reason.clear();
+        }
+        CmdOutput::ok(format!(
+            "Rolled the filesystem back to '{}' ({})",
+            name,
+            clock::stat_time(created)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_roll_back_the_tree() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_file("/tmp/keep", "precious").unwrap();
+
+        for args in [["create", "x"], ["restore", "x"], ["delete", "x"]] {
+            let out = sys.cmd_snapshot(&args);
+            assert_eq!(out.stderr, "snapshot: only root can do that");
+            assert_eq!(out.status, 1);
+        }
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert!(sys
+            .cmd_snapshot(&["list"])
+            .flatten()
+            .starts_with("No snapshots"));
+        assert!(sys
+            .cmd_snapshot(&["create", "before"])
+            .flatten()
+            .starts_with("Created snapshot 'before'"));
+        assert_eq!(
+            sys.cmd_snapshot(&["create", "before"]).flatten(),
+            "snapshot: 'before' already exists"
+        );
+        assert!(sys
+            .cmd_snapshot(&["create", "a/b"])
+            .flatten()
+            .contains("names may only"));
+        assert!(sys.cmd_snapshot(&["list"]).flatten().contains("\nbefore "));
+
+        sys.kernel.fs.create_dir("/tmp/new").unwrap();
+        sys.kernel.fs.cwd = "/tmp/new".into();
+        sys.kernel.fs.remove("/tmp/keep").unwrap();
+        sys.kernel.fs.set_ignore_critical_deletes(true);
+        sys.kernel.fs.remove_recursive("/bin").unwrap();
+        sys.kernel.fs.set_ignore_critical_deletes(false);
+
+        assert!(sys
+            .cmd_snapshot(&["restore", "before"])
+            .flatten()
+            .starts_with("Rolled the filesystem back to 'before'"));
+        assert_eq!(sys.kernel.fs.resolve("/tmp/keep").unwrap().data, "precious");
+        assert!(sys.kernel.fs.resolve("/tmp/new").is_none());
+        assert!(sys.kernel.fs.resolve("/bin/sh").is_some());
+        assert_eq!(sys.kernel.fs.cwd, "/");
+        assert!(!sys.kernel.fs.kernel_panic);
+
+        assert_eq!(
+            sys.cmd_snapshot(&["rm", "before"]).flatten(),
+            "Deleted snapshot 'before'"
+        );
+        assert_eq!(
+            sys.cmd_snapshot(&["restore", "before"]).flatten(),
+            "snapshot: 'before': no such snapshot"
+        );
+        assert_eq!(sys.cmd_snapshot(&[]).flatten(), USAGE);
+    }
+}
@@ -0,0 +1,129 @@
+use super::System;
+use crate::pkg;
+
+// Commands that ship in a catalog package under a different name
+const COMMAND_PACKAGES: &[(&str, &str)] = &[
+    ("ssh", "openssh-server"),
+    ("sshd", "openssh-server"),
+    ("pip3", "python3"),
+];
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions,
+/// so `gerp` is one edit away from `grep`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+impl System {
+    /// Closest known commands to `cmd`, best match first. A transposed typo
+    /// (`sl`, `gerp`) wins outright over other candidates at the same distance.
+    fn similar_commands(&self, cmd: &str) -> Vec<String> {
+        let max = if cmd.chars().count() <= 3 { 1 } else { 2 };
+        let mut sorted_cmd: Vec<char> = cmd.chars().collect();
+        sorted_cmd.sort_unstable();
+        let mut scored: Vec<(usize, bool, usize, String)> = self
+            .shell
+            .registry
+            .names()
+            .map(str::to_string)
+            .chain(self.shell.aliases.keys().cloned())
+            .filter(|c| c != cmd)
+            .filter_map(|c| {
+                let dist = edit_distance(cmd, &c);
+                if dist > max {
+                    return None;
+                }
+                let mut sorted: Vec<char> = c.chars().collect();
+                sorted.sort_unstable();
+                let len_diff = c.chars().count().abs_diff(cmd.chars().count());
+                Some((dist, sorted != sorted_cmd, len_diff, c))
+            })
+            .collect();
+        scored.sort();
+        scored.dedup_by(|a, b| a.3 == b.3);
+        let Some(best) = scored.first().map(|s| s.0) else {
+            return Vec::new();
+        };
+        if scored.first().is_some_and(|s| !s.1) {
+            return vec![scored.remove(0).3];
+        }
+        scored
+            .into_iter()
+            .filter(|s| s.0 == best)
+            .take(3)
+            .map(|s| s.3)
+            .collect()
+    }
+
+    /// Catalog package that provides `cmd`, if it is not installed yet
+    fn package_for_command(&self, cmd: &str) -> Option<String> {
+        let package = COMMAND_PACKAGES
+            .iter()
+            .find(|(c, _)| *c == cmd)
+            .map(|(_, p)| *p)
+            .or_else(|| pkg::provider_of(cmd).map(|p| p.name))
+            .or_else(|| pkg::find(cmd).map(|p| p.name))?;
+        if self.package_db().contains(package) {
+            return None;
+        }
+        Some(package.to_string())
+    }
+
+    pub(super) fn command_not_found(&self, cmd: &str) -> String {
+        let mut out = format!("sh: {}: command not found", cmd);
+        let similar = self.similar_commands(cmd);
+        match similar.as_slice() {
+            [] => {}
+            [only] => out.push_str(&format!(", did you mean '{}'?", only)),
+            many => {
+                let quoted: Vec<String> = many.iter().map(|c| format!("'{}'", c)).collect();
+                out.push_str(&format!(", did you mean one of {}?", quoted.join(", ")));
+            }
+        }
+        if let Some(package) = self.package_for_command(cmd) {
+            out.push_str(&format!(
+                "\nCommand '{}' can be installed with:\n  sudo apt install {}",
+                cmd, package
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::edit_distance;
+
+    #[test]
+    fn transposition_counts_as_one_edit() {
+        assert_eq!(edit_distance("sl", "ls"), 1);
+        assert_eq!(edit_distance("gerp", "grep"), 1);
+    }
+
+    #[test]
+    fn insertions_and_substitutions() {
+        assert_eq!(edit_distance("", "cat"), 3);
+        assert_eq!(edit_distance("pyton", "python"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
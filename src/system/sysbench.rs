@@ -0,0 +1,344 @@
+//! `sysbench`: real micro-benchmarks run inside the module, each for a
+//! fixed slice of wall-clock time, scored against a reference machine so
+//! two browsers or devices can be compared by one number
+use super::System;
+use crate::clock;
+use crate::shell::CmdOutput;
+use std::hint::black_box;
+
+const USAGE: &str = "usage: sysbench [--time=SECONDS] [cpu|memory|fileio|raycast|all]...";
+/// Seconds each test runs for unless `--time` says otherwise
+const DEFAULT_TIME: f64 = 0.5;
+/// Longest `--time` accepted; every test blocks the page while it runs
+const MAX_TIME: f64 = 10.0;
+
+/// Primes counted by trial division per integer event, as `sysbench cpu`
+const MAX_PRIME: u32 = 10_000;
+/// Side of the Mandelbrot grid drawn per float event, and its iterations
+const MANDEL_SIDE: u32 = 64;
+const MANDEL_ITERS: u32 = 64;
+/// Size of the buffer each memory event writes and reads back
+const MEMORY_MIB: usize = 8;
+const FILE_SIZE: usize = 4096;
+/// Files created, read and deleted per file I/O event
+const FILE_BATCH: usize = 64;
+const SCRATCH_DIR: &str = "/tmp/.sysbench";
+const RAYCAST_WIDTH: u32 = 320;
+const RAYCAST_HEIGHT: u32 = 200;
+/// Frames drawn between clock reads
+const RAYCAST_BATCH: u32 = 5;
+
+/// What a mid-range laptop browser scores per test, which counts as 1000
+const REFERENCE_INT: f64 = 1500.0;
+const REFERENCE_FLOAT: f64 = 1800.0;
+const REFERENCE_MEMORY: f64 = 3000.0;
+const REFERENCE_FILEIO: f64 = 350_000.0;
+const REFERENCE_RAYCAST: f64 = 2500.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Test {
+    Cpu,
+    Memory,
+    FileIo,
+    Raycast,
+}
+
+impl Test {
+    const ALL: [Test; 4] = [Test::Cpu, Test::Memory, Test::FileIo, Test::Raycast];
+
+    fn parse(name: &str) -> Option<Test> {
+        match name {
+            "cpu" => Some(Test::Cpu),
+            "memory" => Some(Test::Memory),
+            "fileio" => Some(Test::FileIo),
+            "raycast" => Some(Test::Raycast),
+            _ => None,
+        }
+    }
+}
+
+/// Run `event` until `budget_ms` has passed, at least once; returns how
+/// many events ran and the milliseconds they took
+fn timed(budget_ms: f64, mut event: impl FnMut()) -> (u64, f64) {
+    let start = clock::now_ms();
+    let mut events = 0;
+    loop {
+        event();
+        events += 1;
+        let elapsed = clock::now_ms() - start;
+        if elapsed >= budget_ms {
+            return (events, elapsed);
+        }
+    }
+}
+
+/// Events per second, never dividing by a zero-length run
+fn rate(events: f64, ms: f64) -> f64 {
+    events * 1000.0 / ms.max(0.001)
+}
+
+fn count_primes(limit: u32) -> u32 {
+    let mut count = 0;
+    for n in 2..=limit {
+        let mut d = 2;
+        while d * d <= n && n % d != 0 {
+            d += 1;
+        }
+        if d * d > n {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Iterations spent over a Mandelbrot grid covering the whole set
+fn mandelbrot(side: u32, max_iter: u32) -> u32 {
+    let mut total = 0;
+    for py in 0..side {
+        for px in 0..side {
+            let cx = -2.0 + 2.5 * f64::from(px) / f64::from(side);
+            let cy = -1.25 + 2.5 * f64::from(py) / f64::from(side);
+            let (mut x, mut y) = (0.0f64, 0.0f64);
+            let mut i = 0;
+            while i < max_iter && x * x + y * y <= 4.0 {
+                let xt = x * x - y * y + cx;
+                y = 2.0 * x * y + cy;
+                x = xt;
+                i += 1;
+            }
+            total += i;
+        }
+    }
+    total
+}
+
+/// One pass writing every word of `buffer`, one reading it back
+fn memory_pass(buffer: &mut [u64], seed: u64) -> u64 {
+    for (i, word) in buffer.iter_mut().enumerate() {
+        *word = seed ^ i as u64;
+    }
+    buffer.iter().fold(0, |sum, word| sum.wrapping_add(*word))
+}
+
+/// An indented result line with the numbers lined up
+fn row(label: &str, value: f64, unit: &str) -> String {
+    format!("    {:<30}{:>12.1} {}", label, value, unit)
+}
+
+fn score(rate: f64, reference: f64) -> f64 {
+    rate / reference * 1000.0
+}
+
+impl System {
+    /// `sysbench [--time=SECONDS] [TEST]...`: run each test for the given
+    /// time and print its rate, then a score where 1000 is the reference
+    pub(super) fn cmd_sysbench(&mut self, args: &[&str]) -> CmdOutput {
+        let mut seconds = DEFAULT_TIME;
+        let mut tests = Vec::new();
+        for arg in args {
+            if let Some(value) = arg.strip_prefix("--time=") {
+                match value.parse::<f64>() {
+                    Ok(s) if s > 0.0 && s <= MAX_TIME => seconds = s,
+                    _ => {
+                        return CmdOutput::error(
+                            1,
+                            format!("sysbench: --time must be above 0 and at most {}", MAX_TIME),
+                        )
+                    }
+                }
+            } else if *arg == "all" {
+                tests.extend(Test::ALL);
+            } else if let Some(test) = Test::parse(arg) {
+                tests.push(test);
+            } else {
+                return CmdOutput::error(1, USAGE);
+            }
+        }
+        if tests.is_empty() {
+            tests.extend(Test::ALL);
+        }
+        let budget = seconds * 1000.0;
+
+        let mut out = vec![format!("sysbench: {} s per test", seconds), String::new()];
+        let mut scores = Vec::new();
+        for test in Test::ALL.iter().filter(|t| tests.contains(t)) {
+            match test {
+                Test::Cpu => {
+                    let (events, ms) = timed(budget, || {
+                        black_box(count_primes(black_box(MAX_PRIME)));
+                    });
+                    let int = rate(events as f64, ms);
+                    let (events, ms) = timed(budget, || {
+                        black_box(mandelbrot(black_box(MANDEL_SIDE), MANDEL_ITERS));
+                    });
+                    let float = rate(events as f64, ms);
+                    out.push("CPU".into());
+                    out.push(row(
+                        &format!("integer  primes up to {}", MAX_PRIME),
+                        int,
+                        "events/s",
+                    ));
+                    out.push(row(
+                        &format!("float    {}x{} Mandelbrot", MANDEL_SIDE, MANDEL_SIDE),
+                        float,
+                        "events/s",
+                    ));
+                    scores.push(("integer", score(int, REFERENCE_INT)));
+                    scores.push(("float", score(float, REFERENCE_FLOAT)));
+                }
+                Test::Memory => {
+                    let mut buffer = vec![0u64; MEMORY_MIB * 1024 * 1024 / 8];
+                    let mut seed = 0;
+                    let (events, ms) = timed(budget, || {
+                        seed += 1;
+                        black_box(memory_pass(black_box(&mut buffer), seed));
+                    });
+                    // Each event writes the buffer once and reads it once
+                    let mib = (events as usize * MEMORY_MIB * 2) as f64;
+                    let bandwidth = rate(mib, ms);
+                    out.push("Memory".into());
+                    out.push(row(
+                        &format!("{} MiB write + read", MEMORY_MIB),
+                        bandwidth,
+                        &format!("MiB/s  ({:.0} MiB moved)", mib),
+                    ));
+                    scores.push(("memory", score(bandwidth, REFERENCE_MEMORY)));
+                }
+                Test::FileIo => match self.bench_fileio(budget) {
+                    Ok([create, read, delete]) => {
+                        // Three operations per file, in the time all three took
+                        let ops = 3.0 / (1.0 / create + 1.0 / read + 1.0 / delete);
+                        out.push(format!("File I/O (VFS, {} KiB files)", FILE_SIZE / 1024));
+                        out.push(format!(
+                            "    create {:.0}/s  read {:.0}/s  delete {:.0}/s  ({:.0} ops/s)",
+                            create, read, delete, ops
+                        ));
+                        scores.push(("fileio", score(ops, REFERENCE_FILEIO)));
+                    }
+                    Err(e) => return CmdOutput::error(1, format!("sysbench: fileio: {}", e)),
+                },
+                Test::Raycast => {
+                    // Only drawing counts; setting up each level does not
+                    let mut drawing = 0.0;
+                    let (batches, _) = timed(budget, || {
+                        drawing += crate::doom::benchmark_render(
+                            RAYCAST_WIDTH,
+                            RAYCAST_HEIGHT,
+                            RAYCAST_BATCH,
+                        );
+                    });
+                    let frames = batches * u64::from(RAYCAST_BATCH);
+                    let fps = rate(frames as f64, drawing);
+                    out.push(format!(
+                        "Raycast (doom, {}x{} offscreen)",
+                        RAYCAST_WIDTH, RAYCAST_HEIGHT
+                    ));
+                    out.push(row(
+                        "frames drawn",
+                        fps,
+                        &format!("fps  ({} frames)", frames),
+                    ));
+                    scores.push(("raycast", score(fps, REFERENCE_RAYCAST)));
+                }
+            }
+        }
+
+        // Geometric mean, so no one test outweighs the rest
+        let total =
+            (scores.iter().map(|(_, s)| s.max(1.0).ln()).sum::<f64>() / scores.len() as f64).exp();
+        let parts: Vec<String> = scores
+            .iter()
+            .map(|(name, s)| format!("{} {:.0}", name, s))
+            .collect();
+        out.push(String::new());
+        out.push(format!("Score: {:.0}  ({})", total, parts.join(", ")));
+        out.push("       1000 is a mid-range laptop browser; higher is faster".into());
+        CmdOutput::ok(out.join("\n"))
+    }
+
+    /// Create, read back and delete batches of files in a scratch
+    /// directory until `budget_ms` has passed; returns files per second
+    /// for each of the three
+    fn bench_fileio(&mut self, budget_ms: f64) -> Result<[f64; 3], String> {
+        if self.kernel.fs.resolve(SCRATCH_DIR).is_some() {
+            return Err(format!("{} already exists", SCRATCH_DIR));
+        }
+        self.kernel.fs.create_dir(SCRATCH_DIR)?;
+        let data = "x".repeat(FILE_SIZE);
+        let paths: Vec<String> = (0..FILE_BATCH)
+            .map(|i| format!("{}/file.{}", SCRATCH_DIR, i))
+            .collect();
+        let mut spent = [0.0f64; 3];
+        let mut files = 0u64;
+        let mut result = Ok(());
+        let start = clock::now_ms();
+        while result.is_ok() && (files == 0 || clock::now_ms() - start < budget_ms) {
+            let t0 = clock::now_ms();
+            for path in &paths {
+                if let Err(e) = self.kernel.fs.create_file(path, &data) {
+                    result = Err(e.to_string());
+                }
+            }
+            let t1 = clock::now_ms();
+            for path in &paths {
+                let len = self.kernel.fs.resolve(path).map_or(0, |f| f.data.len());
+                if len != FILE_SIZE {
+                    result = Err(format!("{}: short read", path));
+                }
+                black_box(len);
+            }
+            let t2 = clock::now_ms();
+            for path in &paths {
+                if let Err(e) = self.kernel.fs.remove(path) {
+                    result = Err(e);
+                }
+            }
+            let t3 = clock::now_ms();
+            spent[0] += t1 - t0;
+            spent[1] += t2 - t1;
+            spent[2] += t3 - t2;
+            files += FILE_BATCH as u64;
+        }
+        let _ = self.kernel.fs.remove_recursive(SCRATCH_DIR);
+        result.map(|()| spent.map(|ms| rate(files as f64, ms)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_each_test_and_cleans_up() {
+        assert_eq!(count_primes(100), 25);
+        assert!(mandelbrot(8, 16) > 0);
+
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let out = sys.cmd_sysbench(&["--time=0.01", "cpu", "memory", "fileio", "raycast"]);
+        assert_eq!(out.status, 0, "{}", out.stderr);
+        for line in [
+            "integer  primes up to 10000",
+            "float    64x64 Mandelbrot",
+            "8 MiB write + read",
+            "File I/O (VFS, 4 KiB files)",
+            "Raycast (doom, 320x200 offscreen)",
+            "Score: ",
+            "(integer ",
+            ", raycast ",
+        ] {
+            assert!(
+                out.stdout.contains(line),
+                "missing {:?} in\n{}",
+                line,
+                out.stdout
+            );
+        }
+        assert!(sys.kernel.fs.resolve(SCRATCH_DIR).is_none());
+
+        let one = sys.cmd_sysbench(&["--time=0.01", "fileio"]).stdout;
+        assert!(one.contains("Score: ") && !one.contains("integer"));
+        assert_eq!(sys.cmd_sysbench(&["disk"]).stderr, USAGE);
+        assert_eq!(sys.cmd_sysbench(&["--time=0"]).status, 1);
+    }
+}
@@ -0,0 +1,529 @@
+use super::System;
+use crate::services::ServiceState;
+use crate::shell::CmdOutput;
+
+const UNIT_DIR: &str = "/etc/systemd/system";
+const WANTS_DIR: &str = "/etc/systemd/system/multi-user.target.wants";
+
+fn unit_name(arg: &str) -> &str {
+    arg.strip_suffix(".service").unwrap_or(arg)
+}
+
+fn state_words(state: ServiceState) -> (&'static str, &'static str) {
+    match state {
+        ServiceState::Stopped => ("inactive", "dead"),
+        ServiceState::Starting => ("activating", "start"),
+        ServiceState::Running => ("active", "running"),
+        ServiceState::Stopping => ("deactivating", "stop"),
+        ServiceState::Failed => ("failed", "failed"),
+    }
+}
+
+struct UnitFile {
+    description: Option<String>,
+    dependencies: Vec<String>,
+}
+
+fn parse_unit_file(data: &str) -> UnitFile {
+    let mut unit = UnitFile {
+        description: None,
+        dependencies: Vec::new(),
+    };
+    for line in data.lines() {
+        let line = line.trim();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "Description" => unit.description = Some(value.trim().to_string()),
+            "Requires" | "After" | "Wants" => {
+                for dep in value.split_whitespace() {
+                    let dep = unit_name(dep);
+                    if dep.ends_with(".target") {
+                        continue;
+                    }
+                    if !unit.dependencies.iter().any(|d| d == dep) {
+                        unit.dependencies.push(dep.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    unit
+}
+
+impl System {
+    /// Write a unit file for every registered service that does not have one yet
+    pub(super) fn install_unit_files(&mut self) {
+        let _ = self.ensure_dir_all(WANTS_DIR);
+        for name in self.services.names() {
+            let path = format!("{}/{}.service", UNIT_DIR, name);
+            if self.kernel.fs.resolve(&path).is_none() {
+                let Some(service) = self.services.get(&name) else {
+                    continue;
+                };
+                let deps: Vec<String> = service
+                    .dependencies
+                    .iter()
+                    .map(|d| format!("{}.service", d))
+                    .collect();
+                let mut text = format!("[Unit]\nDescription={}\n", service.description);
+                if !deps.is_empty() {
+                    text.push_str(&format!(
+                        "Requires={}\nAfter={}\n",
+                        deps.join(" "),
+                        deps.join(" ")
+                    ));
+                }
+                text.push_str(&format!(
+                    "\n[Service]\nType=simple\nExecStart=/sbin/{}\nRestart=on-failure\n\n[Install]\nWantedBy=multi-user.target\n",
+                    name
+                ));
+                let _ = self.kernel.fs.create_file(&path, &text);
+            }
+            self.sync_wants_link(&name);
+        }
+    }
+
+    fn sync_wants_link(&mut self, name: &str) {
+        let link = format!("{}/{}.service", WANTS_DIR, name);
+        let exists = self.kernel.fs.resolve(&link).is_some();
+        if self.services.is_enabled(name) && !exists {
+            let target = format!("{}/{}.service", UNIT_DIR, name);
+            if self.kernel.fs.create_file(&link, &target).is_ok() {
+                if let Some(node) = self.kernel.fs.resolve_mut(&link) {
+                    node.permissions = "lrwxrwxrwx".into();
+                    node.owner = "root".into();
+                    node.group = "root".into();
+                }
+            }
+        } else if !self.services.is_enabled(name) && exists {
+            let _ = self.kernel.fs.remove(&link);
+        }
+    }
+
+    fn systemctl_start(&mut self, name: &str) -> Result<(), String> {
+        let kernel = &mut self.kernel;
+        self.services
+            .start_with_dependencies(name, &mut |n| kernel.proc.spawn(n, 1, &mut kernel.mem))
+    }
+
+    pub(super) fn systemctl_stop(&mut self, name: &str) -> Result<(), String> {
+        if self.services.get_state(name) != Some(ServiceState::Running) {
+            return if self.services.contains(name) {
+                Ok(())
+            } else {
+                Err(format!("Unit {}.service not loaded.", name))
+            };
+        }
+        let mut pids = Vec::new();
+        for dep in self.services.dependents(name) {
+            if let Some(pid) = self.services.get(&dep).and_then(|s| s.pid) {
+                pids.push(pid);
+            }
+        }
+        if let Some(pid) = self.services.get(name).and_then(|s| s.pid) {
+            pids.push(pid);
+        }
+        self.services.stop_with_dependents(name)?;
+        for pid in pids {
+            self.kernel.scheduler.remove(pid);
+            self.kernel.proc.kill(pid, &mut self.kernel.mem);
+        }
+        Ok(())
+    }
+
+    fn systemctl_status(&self, name: &str) -> Option<String> {
+        let service = self.services.get(name)?;
+        let (active, sub) = state_words(service.state);
+        let dot = match service.state {
+            ServiceState::Running => "\x1b[COLOR:green]●\x1b[COLOR:reset]",
+            ServiceState::Failed => "\x1b[COLOR:red]×\x1b[COLOR:reset]",
+            _ => "○",
+        };
+        let enabled = if service.auto_start {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        let mut out = vec![
+            format!("{} {}.service - {}", dot, name, service.description),
+            format!(
+                "     Loaded: loaded ({}/{}.service; {}; preset: enabled)",
+                UNIT_DIR, name, enabled
+            ),
+            format!("     Active: {} ({})", active, sub),
+        ];
+        if let Some(pid) = service.pid {
+            out.push(format!("   Main PID: {} ({})", pid, name));
+        }
+        if !service.dependencies.is_empty() {
+            let deps: Vec<String> = service
+                .dependencies
+                .iter()
+                .map(|d| format!("{}.service", d))
+                .collect();
+            out.push(format!("   Requires: {}", deps.join(" ")));
+        }
+        let tail: Vec<&String> = service.journal.iter().rev().take(5).collect();
+        if !tail.is_empty() {
+            out.push(String::new());
+            for line in tail.into_iter().rev() {
+                out.push(format!("kpawnd systemd[1]: {}", line));
+            }
+        }
+        Some(out.join("\n"))
+    }
+
+    fn systemctl_list_units(&self, all: bool) -> String {
+        let mut out = vec![format!(
+            "{:<24} {:<7} {:<9} {:<8} {}",
+            "UNIT", "LOAD", "ACTIVE", "SUB", "DESCRIPTION"
+        )];
+        let mut count = 0;
+        for name in self.services.names() {
+            let Some(service) = self.services.get(&name) else {
+                continue;
+            };
+            if !all && service.state == ServiceState::Stopped {
+                continue;
+            }
+            let (active, sub) = state_words(service.state);
+            out.push(format!(
+                "{:<24} {:<7} {:<9} {:<8} {}",
+                format!("{}.service", name),
+                "loaded",
+                active,
+                sub,
+                service.description
+            ));
+            count += 1;
+        }
+        out.push(String::new());
+        out.push(format!("{} loaded units listed.", count));
+        out.join("\n")
+    }
+
+    fn systemctl_list_unit_files(&self) -> String {
+        let mut out = vec![format!("{:<24} {}", "UNIT FILE", "STATE")];
+        let names = self.services.names();
+        for name in &names {
+            let state = if self.services.is_enabled(name) {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            out.push(format!("{:<24} {}", format!("{}.service", name), state));
+        }
+        out.push(String::new());
+        out.push(format!("{} unit files listed.", names.len()));
+        out.join("\n")
+    }
+
+    /// Re-read unit files from /etc/systemd/system, registering new services
+    fn systemctl_daemon_reload(&mut self) {
+        let Some(dir) = self.kernel.fs.resolve(UNIT_DIR) else {
+            return;
+        };
+        let mut units: Vec<(String, String)> = dir
+            .children
+            .values()
+            .filter(|n| !n.is_dir && n.name.ends_with(".service"))
+            .map(|n| (unit_name(&n.name).to_string(), n.data.clone()))
+            .collect();
+        units.sort();
+        let wanted: Vec<String> = self
+            .kernel
+            .fs
+            .resolve(WANTS_DIR)
+            .map(|d| {
+                d.children
+                    .keys()
+                    .map(|k| unit_name(k).to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (name, data) in units {
+            let unit = parse_unit_file(&data);
+            let enabled = wanted.iter().any(|w| w == &name);
+            if !self.services.contains(&name) {
+                self.services.register(&name, enabled, unit.dependencies);
+            } else {
+                let _ = self.services.set_enabled(&name, enabled);
+            }
+            if let Some(desc) = unit.description {
+                self.services.set_description(&name, &desc);
+            }
+        }
+    }
+
+    pub(super) fn cmd_systemctl(&mut self, args: &[&str]) -> CmdOutput {
+        let all = args.iter().any(|a| *a == "-a" || *a == "--all");
+        let args: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|a| !a.starts_with('-'))
+            .collect();
+        let Some(&action) = args.first() else {
+            return CmdOutput::ok(self.systemctl_list_units(all));
+        };
+        let units: Vec<&str> = args[1..].iter().map(|a| unit_name(a)).collect();
+        let needs_unit = matches!(
+            action,
+            "start"
+                | "stop"
+                | "restart"
+                | "status"
+                | "enable"
+                | "disable"
+                | "is-active"
+                | "is-enabled"
+        );
+        if needs_unit && units.is_empty() {
+            return CmdOutput::error(
+                1,
+                format!("Too few arguments.\nusage: systemctl {} UNIT...", action),
+            );
+        }
+
+        // Changing what runs is root's job; querying is open to everyone
+        let changes_state = matches!(
+            action,
+            "start" | "stop" | "restart" | "enable" | "disable" | "daemon-reload"
+        );
+        if changes_state && self.current_user() != "root" {
+            let what = match action {
+                "daemon-reload" => "reload daemon".to_string(),
+                "enable" | "disable" => format!("{} unit", action),
+                _ => format!("{} {}.service", action, units.join(".service ")),
+            };
+            return CmdOutput::error(1, format!("Failed to {}: Access denied", what));
+        }
+
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        match action {
+            "list-units" => return CmdOutput::ok(self.systemctl_list_units(all)),
+            "list-unit-files" => return CmdOutput::ok(self.systemctl_list_unit_files()),
+            "daemon-reload" => {
+                self.systemctl_daemon_reload();
+                return CmdOutput::ok(String::new());
+            }
+            "start" => {
+                for name in units {
+                    if !self.services.contains(name) {
+                        errors.push(format!(
+                            "Failed to start {}.service: Unit {}.service not found.",
+                            name, name
+                        ));
+                        continue;
+                    }
+                    if let Err(e) = self.systemctl_start(name) {
+                        errors.push(format!(
+                            "Job for {}.service failed: {}\nSee \"journalctl -u {}\" for details.",
+                            name, e, name
+                        ));
+                    }
+                }
+            }
+            "stop" => {
+                for name in units {
+                    if let Err(e) = self.systemctl_stop(name) {
+                        errors.push(format!("Failed to stop {}.service: {}", name, e));
+                    }
+                }
+            }
+            "restart" => {
+                for name in units {
+                    let res = self
+                        .systemctl_stop(name)
+                        .and_then(|_| self.systemctl_start(name));
+                    if let Err(e) = res {
+                        errors.push(format!("Failed to restart {}.service: {}", name, e));
+                    }
+                }
+            }
+            "status" => {
+                let mut pages = Vec::new();
+                for name in units {
+                    match self.systemctl_status(name) {
+                        Some(page) => pages.push(page),
+                        None => errors.push(format!("Unit {}.service could not be found.", name)),
+                    }
+                }
+                // systemd's "no such unit" status
+                let status = if errors.is_empty() { 0 } else { 4 };
+                return CmdOutput {
+                    status,
+                    ..CmdOutput::collected(vec![pages.join("\n\n")], errors)
+                };
+            }
+            "enable" | "disable" => {
+                let enable = action == "enable";
+                for name in units {
+                    if let Err(e) = self.services.set_enabled(name, enable) {
+                        errors.push(format!("Failed to {} unit: {}", action, e));
+                        continue;
+                    }
+                    self.sync_wants_link(name);
+                    let link = format!("{}/{}.service", WANTS_DIR, name);
+                    if enable {
+                        out.push(format!(
+                            "Created symlink {} → {}/{}.service.",
+                            link, UNIT_DIR, name
+                        ));
+                    } else {
+                        out.push(format!("Removed \"{}\".", link));
+                    }
+                }
+            }
+            "is-active" => {
+                for name in units {
+                    let state = self
+                        .services
+                        .get_state(name)
+                        .map(|s| state_words(s).0)
+                        .unwrap_or("inactive");
+                    out.push(state.to_string());
+                }
+                // Like systemd, any unit not active fails the check
+                let status = if out.iter().all(|s| s == "active") {
+                    0
+                } else {
+                    3
+                };
+                return CmdOutput {
+                    status,
+                    ..CmdOutput::ok(out.join("\n"))
+                };
+            }
+            "is-enabled" => {
+                for name in units {
+                    let state = if !self.services.contains(name) {
+                        "not-found"
+                    } else if self.services.is_enabled(name) {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    };
+                    out.push(state.to_string());
+                }
+                let status = i32::from(out.iter().any(|s| s != "enabled"));
+                return CmdOutput {
+                    status,
+                    ..CmdOutput::ok(out.join("\n"))
+                };
+            }
+            _ => return CmdOutput::error(1, format!("Unknown command verb '{}'.", action)),
+        }
+        CmdOutput::collected(out, errors)
+    }
+
+    pub(super) fn cmd_journalctl(&self, args: &[&str]) -> CmdOutput {
+        let mut unit: Option<&str> = None;
+        let mut limit: Option<usize> = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-u" | "--unit" if i + 1 < args.len() => {
+                    unit = Some(unit_name(args[i + 1]));
+                    i += 1;
+                }
+                "-n" | "--lines" if i + 1 < args.len() => {
+                    match args[i + 1].parse::<usize>() {
+                        Ok(n) => limit = Some(n),
+                        Err(_) => {
+                            return CmdOutput::error(
+                                1,
+                                format!("journalctl: invalid number: {}", args[i + 1]),
+                            )
+                        }
+                    }
+                    i += 1;
+                }
+                "-u" | "-n" => {
+                    return CmdOutput::error(
+                        1,
+                        format!("journalctl: option '{}' requires an argument", args[i]),
+                    )
+                }
+                arg if !arg.starts_with('-') => unit = Some(unit_name(arg)),
+                _ => {}
+            }
+            i += 1;
+        }
+
+        let names = match unit {
+            Some(name) if !self.services.contains(name) => {
+                return CmdOutput::ok("-- No entries --");
+            }
+            Some(name) => vec![name.to_string()],
+            None => self.services.start_order(),
+        };
+
+        let mut lines = Vec::new();
+        for name in &names {
+            if let Some(journal) = self.services.journal(name) {
+                for entry in journal {
+                    lines.push(format!("kpawnd systemd[1]: {}", entry));
+                }
+            }
+        }
+        if let Some(n) = limit {
+            let skip = lines.len().saturating_sub(n);
+            lines.drain(..skip);
+        }
+        if lines.is_empty() {
+            return CmdOutput::ok("-- No entries --");
+        }
+        CmdOutput::ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_of_missing_units_and_root_only_changes() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+
+        let out = sys.cmd_systemctl(&["status", "nope"]);
+        assert_eq!(out.stdout, "");
+        assert_eq!(out.stderr, "Unit nope.service could not be found.");
+        assert_eq!(out.status, 4);
+        let out = sys.cmd_systemctl(&["status", "logger"]);
+        assert!(out.stdout.contains("logger.service"));
+        assert_eq!(out.status, 0);
+
+        for (args, msg) in [
+            (
+                &["stop", "logger"][..],
+                "Failed to stop logger.service: Access denied",
+            ),
+            (
+                &["start", "logger"][..],
+                "Failed to start logger.service: Access denied",
+            ),
+            (
+                &["restart", "logger"][..],
+                "Failed to restart logger.service: Access denied",
+            ),
+            (
+                &["daemon-reload"][..],
+                "Failed to reload daemon: Access denied",
+            ),
+        ] {
+            let out = sys.cmd_systemctl(args);
+            assert_eq!((out.stderr.as_str(), out.status), (msg, 1));
+        }
+        assert_eq!(sys.cmd_systemctl(&["is-active", "logger"]).stdout, "active");
+
+        sys.shell.env.insert("USER".into(), "root".into());
+        assert_eq!(sys.cmd_systemctl(&["stop", "logger"]).status, 0);
+        assert_eq!(sys.cmd_systemctl(&["is-active", "logger"]).status, 3);
+    }
+}
@@ -0,0 +1,458 @@
+use super::System;
+use crate::network::{ipv4_for, Packet, PacketKind, Protocol, HOST_IPV4};
+use crate::shell::CmdOutput;
+
+const SNAPLEN: u32 = 262_144;
+const HOST_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const GATEWAY_MAC: [u8; 6] = [0x52, 0x55, 0x0a, 0x00, 0x02, 0x02];
+
+/// One term of a capture filter; terms are ANDed together
+enum Filter {
+    Host(String),
+    Src(String),
+    Dst(String),
+    Port(u16),
+    SrcPort(u16),
+    DstPort(u16),
+    Proto(Protocol),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    fn matches(&self, p: &Packet) -> bool {
+        match self {
+            Filter::Host(h) => host_matches(&p.src, h) || host_matches(&p.dst, h),
+            Filter::Src(h) => host_matches(&p.src, h),
+            Filter::Dst(h) => host_matches(&p.dst, h),
+            Filter::Port(n) => {
+                p.protocol() != Protocol::Icmp && (p.src_port == *n || p.dst_port == *n)
+            }
+            Filter::SrcPort(n) => p.protocol() != Protocol::Icmp && p.src_port == *n,
+            Filter::DstPort(n) => p.protocol() != Protocol::Icmp && p.dst_port == *n,
+            Filter::Proto(proto) => p.protocol() == *proto,
+            Filter::Not(inner) => !inner.matches(p),
+        }
+    }
+}
+
+fn host_matches(addr: &str, wanted: &str) -> bool {
+    addr.eq_ignore_ascii_case(wanted) || ipv4_for(addr) == ipv4_for(wanted)
+}
+
+/// Parse a filter expression such as `host example.com and port 443`
+fn parse_filter(tokens: &[&str]) -> Result<Vec<Filter>, String> {
+    let mut filters = Vec::new();
+    let mut negate = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let arg = tokens.get(i + 1).copied();
+        let port = |value: Option<&str>| -> Result<u16, String> {
+            let value = value.ok_or("syntax error")?;
+            service_port(value).ok_or_else(|| {
+                format!(
+                    "syntax error in filter expression: unknown port '{}'",
+                    value
+                )
+            })
+        };
+        let (filter, used) = match (token, arg) {
+            ("and" | "&&", _) => {
+                i += 1;
+                continue;
+            }
+            ("not" | "!", _) => {
+                negate = !negate;
+                i += 1;
+                continue;
+            }
+            ("tcp", _) => (Filter::Proto(Protocol::Tcp), 1),
+            ("udp", _) => (Filter::Proto(Protocol::Udp), 1),
+            ("icmp", _) => (Filter::Proto(Protocol::Icmp), 1),
+            ("host", Some(h)) => (Filter::Host(h.to_string()), 2),
+            ("port", _) => (Filter::Port(port(arg)?), 2),
+            ("src" | "dst", Some("port")) => {
+                let n = port(tokens.get(i + 2).copied())?;
+                let f = if token == "src" {
+                    Filter::SrcPort(n)
+                } else {
+                    Filter::DstPort(n)
+                };
+                (f, 3)
+            }
+            ("src" | "dst", Some("host")) => {
+                let h = tokens.get(i + 2).ok_or("syntax error")?.to_string();
+                let f = if token == "src" {
+                    Filter::Src(h)
+                } else {
+                    Filter::Dst(h)
+                };
+                (f, 3)
+            }
+            ("src", Some(h)) => (Filter::Src(h.to_string()), 2),
+            ("dst", Some(h)) => (Filter::Dst(h.to_string()), 2),
+            ("or" | "||", _) => return Err("'or' is not supported in this build".into()),
+            _ => return Err("syntax error".into()),
+        };
+        filters.push(if negate {
+            Filter::Not(Box::new(filter))
+        } else {
+            filter
+        });
+        negate = false;
+        i += used;
+    }
+    Ok(filters)
+}
+
+fn service_port(name: &str) -> Option<u16> {
+    match name {
+        "ssh" => Some(22),
+        "domain" => Some(53),
+        "http" => Some(80),
+        "https" => Some(443),
+        _ => name.parse().ok(),
+    }
+}
+
+fn service_name(port: u16) -> Option<&'static str> {
+    match port {
+        22 => Some("ssh"),
+        53 => Some("domain"),
+        80 => Some("http"),
+        443 => Some("https"),
+        8000 => Some("irdmi"),
+        8080 => Some("http-alt"),
+        _ => None,
+    }
+}
+
+fn endpoint(host: &str, port: Option<u16>, numeric: bool) -> String {
+    let host = match host {
+        _ if numeric => {
+            let [a, b, c, d] = ipv4_for(host);
+            format!("{}.{}.{}.{}", a, b, c, d)
+        }
+        "127.0.0.1" => "localhost".to_string(),
+        HOST_IPV4 => "kpawnd".to_string(),
+        other => other.to_string(),
+    };
+    match port {
+        Some(port) => match service_name(port).filter(|_| !numeric) {
+            Some(name) => format!("{}.{}", host, name),
+            None => format!("{}.{}", host, port),
+        },
+        None => host,
+    }
+}
+
+/// `10:00:00.123456`, the time of day tcpdump prefixes each line with
+fn clock(time_ms: f64) -> String {
+    let micros = (time_ms * 1000.0) as u64;
+    let secs = micros / 1_000_000 % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:06}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        micros % 1_000_000
+    )
+}
+
+fn describe(p: &Packet, numeric: bool) -> String {
+    match p.kind {
+        PacketKind::Tcp { flags, seq, ack } => {
+            let mut line = format!(
+                "IP {} > {}: Flags [{}], ",
+                endpoint(&p.src, Some(p.src_port), numeric),
+                endpoint(&p.dst, Some(p.dst_port), numeric),
+                flags
+            );
+            if p.length > 0 {
+                line.push_str(&format!("seq {}:{}, ", seq, seq as usize + p.length));
+            } else if flags != "." {
+                line.push_str(&format!("seq {}, ", seq));
+            }
+            if flags != "S" {
+                line.push_str(&format!("ack {}, ", ack));
+            }
+            line.push_str(&format!(
+                "win {}, length {}",
+                if flags.starts_with('S') { 64240 } else { 502 },
+                p.length
+            ));
+            line
+        }
+        PacketKind::Udp => format!(
+            "IP {} > {}: UDP, length {}",
+            endpoint(&p.src, Some(p.src_port), numeric),
+            endpoint(&p.dst, Some(p.dst_port), numeric),
+            p.length
+        ),
+        PacketKind::IcmpEcho { reply, id, seq } => format!(
+            "IP {} > {}: ICMP echo {}, id {}, seq {}, length {}",
+            endpoint(&p.src, None, numeric),
+            endpoint(&p.dst, None, numeric),
+            if reply { "reply" } else { "request" },
+            id,
+            seq,
+            p.length
+        ),
+    }
+}
+
+impl System {
+    pub(super) fn cmd_tcpdump(&mut self, args: &[&str]) -> CmdOutput {
+        let mut iface = "eth0".to_string();
+        let mut numeric = false;
+        let mut count = usize::MAX;
+        let mut write = None;
+        let mut expr = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-i" | "--interface" if i + 1 < args.len() => {
+                    iface = args[i + 1].to_string();
+                    i += 1;
+                }
+                "-c" if i + 1 < args.len() => {
+                    count = match args[i + 1].parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => return CmdOutput::error(1, format!("tcpdump: invalid packet count {}", args[i + 1])),
+                    };
+                    i += 1;
+                }
+                "-w" if i + 1 < args.len() => {
+                    write = Some(args[i + 1].to_string());
+                    i += 1;
+                }
+                "-D" | "--list-interfaces" => {
+                    let up = |name| self.network.interface(name).is_some_and(|i| i.is_up);
+                    return CmdOutput::ok(format!(
+                        "1.eth0 [{}]\n\
+                         2.any (Pseudo-device that captures on all interfaces) [Up, Running]\n\
+                         3.lo [{}]",
+                        if up("eth0") { "Up, Running, Connected" } else { "Disconnected" },
+                        if up("lo") { "Up, Running, Loopback" } else { "Loopback" },
+                    ));
+                }
+                "-n" | "-nn" => numeric = true,
+                "-v" | "-vv" | "-vvv" | "-q" | "-l" | "-e" => {}
+                "-h" | "--help" | "--version" => {
+                    return CmdOutput::ok("tcpdump version 4.99.4\nlibpcap version 1.10.4 (with TPACKET_V3)\n\
+                            Usage: tcpdump [-Dhnv] [-c count] [-i interface] [-w file] [expression]")
+                }
+                arg if arg.starts_with('-') => {
+                    return CmdOutput::error(1, format!("tcpdump: invalid option -- '{}'", arg.trim_start_matches('-')))
+                }
+                arg => expr.push(arg),
+            }
+            i += 1;
+        }
+        if !matches!(iface.as_str(), "eth0" | "lo" | "any") {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "tcpdump: {}: No such device exists\n(SIOCGIFHWADDR: No such device)",
+                    iface
+                ),
+            );
+        }
+        if self.current_user() != "root" {
+            return CmdOutput::error(
+                1,
+                format!(
+                "tcpdump: {}: You don't have permission to perform this capture on that device\n\
+                 (socket: Operation not permitted)",
+                iface
+            ),
+            );
+        }
+        if iface != "any" && !self.network.interface(&iface).is_some_and(|i| i.is_up) {
+            return CmdOutput::error(1, format!("tcpdump: {}: That device is not up", iface));
+        }
+        let filters = match parse_filter(&expr) {
+            Ok(f) => f,
+            Err(e) => return CmdOutput::error(1, format!("tcpdump: {}", e)),
+        };
+
+        let captured: Vec<Packet> = self
+            .network
+            .packets()
+            .filter(|p| iface == "any" || p.iface == iface)
+            .filter(|p| filters.iter().all(|f| f.matches(p)))
+            .take(count)
+            .cloned()
+            .collect();
+
+        let link = if iface == "any" {
+            "LINUX_SLL2 (Linux cooked v2)"
+        } else {
+            "EN10MB (Ethernet)"
+        };
+        let mut out = Vec::new();
+        match &write {
+            Some(file) => {
+                let path = self.kernel.fs.normalize(file);
+                if !self.can_write_path(&path) {
+                    return CmdOutput::error(1, format!("tcpdump: {}: Permission denied", file));
+                }
+                if let Err(e) = self.write_file_bytes(&path, &pcapng(&captured)) {
+                    return CmdOutput::error(1, format!("tcpdump: {}: {}", file, e));
+                }
+                out.push(format!(
+                    "tcpdump: listening on {}, link-type EN10MB (Ethernet), snapshot length {} bytes",
+                    iface, SNAPLEN
+                ));
+            }
+            None => {
+                out.push(
+                    "tcpdump: verbose output suppressed, use -v[v]... for full protocol decode"
+                        .into(),
+                );
+                out.push(format!(
+                    "listening on {}, link-type {}, snapshot length {} bytes",
+                    iface, link, SNAPLEN
+                ));
+                for p in &captured {
+                    let prefix = if iface == "any" {
+                        let outbound = p.outbound || p.iface == "lo";
+                        format!("{} {} ", p.iface, if outbound { "Out" } else { "In" })
+                    } else {
+                        String::new()
+                    };
+                    out.push(format!(
+                        "{} {}{}",
+                        clock(p.time_ms),
+                        prefix,
+                        describe(p, numeric)
+                    ));
+                }
+            }
+        }
+        let n = captured.len();
+        out.push(String::new());
+        out.push(format!(
+            "{} packet{} captured",
+            n,
+            if n == 1 { "" } else { "s" }
+        ));
+        out.push(format!(
+            "{} packet{} received by filter",
+            n,
+            if n == 1 { "" } else { "s" }
+        ));
+        out.push("0 packets dropped by kernel".into());
+        CmdOutput::ok(out.join("\n"))
+    }
+}
+
+/// Serialize packets as a pcapng capture with one Ethernet interface
+fn pcapng(packets: &[Packet]) -> Vec<u8> {
+    let mut out = Vec::new();
+    // Section Header Block
+    block(&mut out, 0x0A0D_0D0A, |b| {
+        b.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+        b.extend_from_slice(&1u16.to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes());
+        b.extend_from_slice(&(-1i64).to_le_bytes());
+    });
+    // Interface Description Block: LINKTYPE_ETHERNET
+    block(&mut out, 1, |b| {
+        b.extend_from_slice(&1u16.to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes());
+        b.extend_from_slice(&SNAPLEN.to_le_bytes());
+    });
+    for p in packets {
+        let frame = frame(p);
+        let micros = (p.time_ms * 1000.0) as u64;
+        // Enhanced Packet Block
+        block(&mut out, 6, |b| {
+            b.extend_from_slice(&0u32.to_le_bytes());
+            b.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+            b.extend_from_slice(&(micros as u32).to_le_bytes());
+            b.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            b.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            b.extend_from_slice(&frame);
+            while b.len() % 4 != 0 {
+                b.push(0);
+            }
+        });
+    }
+    out
+}
+
+fn block(out: &mut Vec<u8>, kind: u32, body: impl FnOnce(&mut Vec<u8>)) {
+    let mut content = Vec::new();
+    body(&mut content);
+    let len = (content.len() + 12) as u32;
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(&content);
+    out.extend_from_slice(&len.to_le_bytes());
+}
+
+/// Ethernet + IPv4 + transport headers, with a zeroed payload
+fn frame(p: &Packet) -> Vec<u8> {
+    let payload = p.length.min(1400);
+    let mut transport = Vec::new();
+    let proto = match p.kind {
+        PacketKind::Tcp { flags, seq, ack } => {
+            let mut bits = 0u8;
+            for (c, bit) in [('F', 0x01), ('S', 0x02), ('P', 0x08), ('.', 0x10)] {
+                if flags.contains(c) {
+                    bits |= bit;
+                }
+            }
+            transport.extend_from_slice(&p.src_port.to_be_bytes());
+            transport.extend_from_slice(&p.dst_port.to_be_bytes());
+            transport.extend_from_slice(&seq.to_be_bytes());
+            transport.extend_from_slice(&ack.to_be_bytes());
+            transport.extend_from_slice(&[0x50, bits]);
+            transport.extend_from_slice(&64240u16.to_be_bytes());
+            transport.extend_from_slice(&[0, 0, 0, 0]);
+            6
+        }
+        PacketKind::Udp => {
+            transport.extend_from_slice(&p.src_port.to_be_bytes());
+            transport.extend_from_slice(&p.dst_port.to_be_bytes());
+            transport.extend_from_slice(&((8 + payload) as u16).to_be_bytes());
+            transport.extend_from_slice(&[0, 0]);
+            17
+        }
+        PacketKind::IcmpEcho { reply, id, seq } => {
+            transport.extend_from_slice(&[if reply { 0 } else { 8 }, 0, 0, 0]);
+            transport.extend_from_slice(&id.to_be_bytes());
+            transport.extend_from_slice(&seq.to_be_bytes());
+            1
+        }
+    };
+    transport.resize(transport.len() + payload, 0);
+
+    let mut ip = vec![0x45, 0];
+    ip.extend_from_slice(&((20 + transport.len()) as u16).to_be_bytes());
+    ip.extend_from_slice(&[0, 0, 0x40, 0, 64, proto, 0, 0]);
+    ip.extend_from_slice(&ipv4_for(&p.src));
+    ip.extend_from_slice(&ipv4_for(&p.dst));
+    let checksum = !ip
+        .chunks(2)
+        .map(|w| u32::from(u16::from_be_bytes([w[0], w[1]])))
+        .fold(0u32, |sum, w| {
+            let sum = sum + w;
+            (sum & 0xffff) + (sum >> 16)
+        }) as u16;
+    ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    let (dst_mac, src_mac) = match p.iface {
+        "lo" => ([0; 6], [0; 6]),
+        _ if p.outbound => (GATEWAY_MAC, HOST_MAC),
+        _ => (HOST_MAC, GATEWAY_MAC),
+    };
+    let mut frame = Vec::with_capacity(14 + ip.len() + transport.len());
+    frame.extend_from_slice(&dst_mac);
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&0x0800u16.to_be_bytes());
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(&transport);
+    frame
+}
@@ -0,0 +1,287 @@
+use super::{System, SystemEvent};
+use crate::mux::{Direction, Mux, ShellState};
+use crate::shell::CmdOutput;
+use wasm_bindgen::prelude::*;
+
+const USAGE: &str = "usage: tmux [new [-d] [-s NAME] | attach [-t NAME] | detach | ls | \
+                     kill-session [-t NAME] | kill-server | new-window | split-window [-h|-v] | \
+                     select-pane | select-window -t N | next-window | previous-window | kill-pane]";
+
+/// The server socket, as tmux names it in `$TMUX` and its errors
+const SOCKET: &str = "/tmp/tmux-1000/default";
+
+/// A new pane's shell: a copy of `shell`, marked as running under tmux
+fn pane_shell(mut shell: ShellState) -> ShellState {
+    shell.env.insert("TMUX".into(), format!("{},1,0", SOCKET));
+    shell
+}
+
+impl System {
+    /// `tmux`: sessions of windows of panes, each pane with its own working
+    /// directory, environment and scrollback. `Ctrl-b` keys reach the same
+    /// operations through `tmux_key`
+    pub(super) fn cmd_tmux(&mut self, args: &[&str]) -> CmdOutput {
+        let result = match args {
+            [] => self.tmux_new(&[]),
+            ["new" | "new-session", flags @ ..] => self.tmux_new(flags),
+            ["attach" | "attach-session" | "a"] => self.tmux_attach(None),
+            ["attach" | "attach-session" | "a", "-t", name] => self.tmux_attach(Some(name)),
+            ["detach" | "detach-client"] => self.tmux_detach(),
+            ["ls" | "list-sessions" | "kill-server"] if !self.mux.has_sessions() => {
+                Err(format!("no server running on {}", SOCKET))
+            }
+            ["ls" | "list-sessions"] => Ok(self.mux.list().join("\n")),
+            ["kill-session"] => self
+                .mux_switch(|mux, _| mux.kill_session(None))
+                .map(|_| String::new()),
+            ["kill-session", "-t", name] => self
+                .mux_switch(|mux, _| mux.kill_session(Some(name)))
+                .map(|_| String::new()),
+            ["kill-server"] => self
+                .mux_switch(|mux, _| Ok(mux.kill_server()))
+                .map(|attached| match attached {
+                    true => "[server exited]".into(),
+                    false => String::new(),
+                }),
+            ["new-window" | "neww"] => self.tmux_key_op("c"),
+            ["split-window" | "splitw"] | ["split-window" | "splitw", "-v"] => {
+                self.tmux_key_op("\"")
+            }
+            ["split-window" | "splitw", "-h"] => self.tmux_key_op("%"),
+            ["select-pane" | "selectp"] => self.tmux_key_op("o"),
+            ["select-window" | "selectw", "-t", index] => match index.parse::<usize>() {
+                Ok(index) => self
+                    .mux_switch(|mux, _| mux.select_window(index))
+                    .map(|_| String::new()),
+                Err(_) => Err(format!("can't find window: {}", index)),
+            },
+            ["next-window" | "next"] => self.tmux_key_op("n"),
+            ["previous-window" | "prev"] => self.tmux_key_op("p"),
+            ["kill-pane" | "killp"] => self.tmux_key_op("x"),
+            _ => Ok(USAGE.into()),
+        };
+        CmdOutput::ok(result.unwrap_or_else(|e| format!("tmux: {}", e)))
+    }
+
+    /// `new-session [-d] [-s NAME]`: start a session and attach to it
+    /// unless `-d`
+    fn tmux_new(&mut self, flags: &[&str]) -> Result<String, String> {
+        let mut name = None;
+        let mut detached = false;
+        let mut rest = flags.iter();
+        while let Some(flag) = rest.next() {
+            match *flag {
+                "-d" => detached = true,
+                "-s" => name = Some(*rest.next().ok_or("option requires an argument -- s")?),
+                _ => return Ok(USAGE.into()),
+            }
+        }
+        if !detached && self.shell.env.contains_key("TMUX") {
+            return Err("sessions should be nested with care, unset $TMUX to force".into());
+        }
+        let name = self.mux.new_session(name, pane_shell(self.shell_state()))?;
+        if detached {
+            return Ok(String::new());
+        }
+        self.tmux_attach(Some(&name))
+    }
+
+    fn tmux_attach(&mut self, target: Option<&str>) -> Result<String, String> {
+        if self.shell.env.contains_key("TMUX") {
+            return Err("sessions should be nested with care, unset $TMUX to force".into());
+        }
+        self.mux_switch(|mux, _| mux.attach(target))?;
+        Ok(String::new())
+    }
+
+    fn tmux_detach(&mut self) -> Result<String, String> {
+        let name = self
+            .mux_switch(|mux, _| Ok(mux.detach()))?
+            .ok_or("no current client")?;
+        Ok(format!("[detached (from session {})]", name))
+    }
+
+    /// What a `Ctrl-b` key does to the attached session
+    fn tmux_key_op(&mut self, key: &str) -> Result<String, String> {
+        match key {
+            "c" => self.mux_switch(|mux, shell| mux.new_window(shell).map(drop)),
+            "%" => self.mux_switch(|mux, shell| mux.split(Direction::Row, shell).map(drop)),
+            "\"" => self.mux_switch(|mux, shell| mux.split(Direction::Column, shell).map(drop)),
+            "o" => self.mux_switch(|mux, _| mux.next_pane()),
+            "n" => self.mux_switch(|mux, _| mux.cycle_window(1)),
+            "p" => self.mux_switch(|mux, _| mux.cycle_window(-1)),
+            "x" => {
+                let ended = self.mux_switch(|mux, _| mux.kill_pane())?;
+                return Ok(ended.map_or(String::new(), |_| "[exited]".into()));
+            }
+            "d" => return self.tmux_detach(),
+            digit => match digit.parse::<usize>() {
+                Ok(index) => self.mux_switch(|mux, _| mux.select_window(index)),
+                Err(_) => Ok(()),
+            },
+        }
+        .map(|_| String::new())
+    }
+
+    fn shell_state(&self) -> ShellState {
+        ShellState {
+            cwd: self.kernel.fs.cwd.clone(),
+            env: self.shell.env.clone(),
+            limits: self.shell.limits.clone(),
+        }
+    }
+
+    /// Run `op` on the multiplexer with the shell new panes should start
+    /// from, then swap in the shell of whichever pane is active afterwards.
+    /// The pane left keeps the shell as it was; the shell from before
+    /// attaching comes back once no pane is active
+    fn mux_switch<T>(
+        &mut self,
+        op: impl FnOnce(&mut Mux, ShellState) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let current = self.shell_state();
+        match self.mux.active_pane() {
+            Some(id) => {
+                if let Some(pane) = self.mux.pane_mut(id) {
+                    pane.shell = current.clone();
+                }
+            }
+            None => self.mux_outer = Some(current.clone()),
+        }
+        let result = op(&mut self.mux, pane_shell(current));
+        let next = match self.mux.active_pane().and_then(|id| self.mux.pane_mut(id)) {
+            Some(pane) => Some(pane.shell.clone()),
+            None => self.mux_outer.take(),
+        };
+        if let Some(ShellState { cwd, env, limits }) = next {
+            self.kernel.fs.cwd = cwd;
+            self.shell.env = env;
+            self.shell.limits = limits;
+            self.refresh_proc_limits();
+        }
+        self.sync_pane_sessions();
+        result
+    }
+
+    /// Put a typed line into the active pane after its prompt. Returns the
+    /// pane, so the output lands there even if the line switches away
+    pub(super) fn pane_record_input(&mut self, line: &str) -> Option<u32> {
+        if self.in_exec {
+            return None;
+        }
+        let id = self.mux.active_pane()?;
+        // Answers to the sudo password prompt stay off the screen
+        if !self.sudo_waiting_password {
+            let text = format!("{}{}", self.prompt(), line);
+            self.mux.pane_mut(id)?.push(&text);
+        }
+        Some(id)
+    }
+
+    pub(super) fn pane_record_output(&mut self, pane: Option<u32>, output: &str) {
+        if output.is_empty() {
+            return;
+        }
+        if let Some(pane) = pane.and_then(|id| self.mux.pane_mut(id)) {
+            pane.push(output);
+        }
+    }
+
+    /// `exit`: leaves a `su` shell, closes the pane inside tmux, logs out
+    /// otherwise
+    pub(super) fn cmd_exit(&mut self) -> CmdOutput {
+        if self.exit_su() {
+            return CmdOutput::ok(String::new());
+        }
+        if self.mux.is_attached() {
+            return CmdOutput::ok(self.tmux_key_op("x").unwrap_or_default());
+        }
+        self.console_logout();
+        CmdOutput::ok(self.emit(SystemEvent::Logout))
+    }
+}
+
+#[wasm_bindgen]
+impl System {
+    /// The key pressed after the `Ctrl-b` prefix: `c` new window, `%` and
+    /// `"` split, `o` next pane, `n`/`p`/`0`-`9` switch window, `x` close
+    /// the pane, `d` detach. Returns what to print once detached
+    #[wasm_bindgen]
+    pub fn tmux_key(&mut self, key: &str) -> String {
+        self.tmux_key_op(key).unwrap_or_default()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_tmux_attached(&self) -> bool {
+        self.mux.is_attached()
+    }
+
+    /// The attached session as `{ session, windows, layout, active_pane,
+    /// panes }`, or `null` outside tmux. `layout` nests `{ type: "split",
+    /// direction: "row" | "column", children }` down to `{ type: "pane", id }`
+    #[wasm_bindgen]
+    pub fn tmux_view(&self) -> JsValue {
+        self.mux
+            .view()
+            .and_then(|view| serde_wasm_bindgen::to_value(&view).ok())
+            .unwrap_or(JsValue::NULL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane_text(sys: &mut System, id: u32) -> String {
+        let pane = sys.mux.pane_mut(id).unwrap();
+        pane.lines().collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn panes_keep_their_own_shell_and_scrollback() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        let home = sys.kernel.fs.cwd.clone();
+        assert_eq!(
+            sys.exec("tmux ls"),
+            "tmux: no server running on /tmp/tmux-1000/default"
+        );
+
+        assert_eq!(sys.exec("tmux new -s work"), "");
+        assert!(sys.shell.env.contains_key("TMUX"));
+        assert_eq!(
+            sys.exec("tmux new"),
+            "tmux: sessions should be nested with care, unset $TMUX to force"
+        );
+        sys.exec("cd /tmp");
+        sys.exec("echo left");
+        assert!(pane_text(&mut sys, 0).ends_with("echo left\nleft"));
+
+        sys.tmux_key("%");
+        assert_eq!(sys.mux.active_pane(), Some(1));
+        assert_eq!(sys.kernel.fs.cwd, "/tmp");
+        sys.exec("cd /");
+        sys.tmux_key("o");
+        assert_eq!(sys.kernel.fs.cwd, "/tmp");
+        sys.exec("clear");
+        assert_eq!(pane_text(&mut sys, 0), "");
+        assert!(sys.take_events().is_empty());
+
+        assert_eq!(sys.exec("tmux detach"), "[detached (from session work)]");
+        assert!(!sys.shell.env.contains_key("TMUX"));
+        assert_eq!(sys.kernel.fs.cwd, home);
+        assert!(sys.exec("tmux ls").starts_with("work: 1 windows (created "));
+        assert_eq!(
+            sys.exec("tmux attach -t nope"),
+            "tmux: can't find session: nope"
+        );
+
+        sys.exec("tmux a");
+        assert_eq!(sys.kernel.fs.cwd, "/tmp");
+        assert_eq!(sys.exec("exit"), "");
+        assert_eq!(sys.kernel.fs.cwd, "/");
+        assert_eq!(sys.exec("exit"), "[exited]");
+        assert_eq!(sys.kernel.fs.cwd, home);
+        assert!(sys.take_events().is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use super::netif::Unreachable;
+use super::{System, SystemEvent};
+use crate::network::{ipv4_for, name_hash, GATEWAY_IPV4};
+use crate::shell::CmdOutput;
+
+const CITIES: [&str; 8] = ["fra", "ams", "lon", "nyc", "chi", "sjc", "sea", "dal"];
+const BACKBONES: [&str; 6] = [
+    "telia.net",
+    "cogentco.com",
+    "level3.net",
+    "he.net",
+    "gtt.net",
+    "zayo.com",
+];
+
+/// A router on the simulated path
+struct Hop {
+    name: String,
+    addr: String,
+}
+
+/// A `traceroute` in progress. The frontend times one real HTTP probe to
+/// the target per hop and hands it to `traceroute_step`, which scales it
+/// along the simulated path.
+pub(super) struct TracerouteRun {
+    host: String,
+    /// `None` for routers that never answer
+    hops: Vec<Option<Hop>>,
+    next: usize,
+    queries: usize,
+    numeric: bool,
+}
+
+/// Well-spread bits for hop `n` of the path to `host`
+fn hop_hash(host: &str, n: usize) -> u32 {
+    let h = name_hash(host) ^ (n as u32).wrapping_mul(0x9e37_79b9);
+    let h = (h ^ (h >> 16)).wrapping_mul(0x045d_9f3b);
+    h ^ (h >> 16)
+}
+
+fn addr_text(ip: [u8; 4]) -> String {
+    let [a, b, c, d] = ip;
+    format!("{}.{}.{}.{}", a, b, c, d)
+}
+
+/// Gateway, a couple of access routers, some backbone, then the target.
+/// Everything but the target's measured latency is derived from its name.
+fn path_to(host: &str) -> Vec<Option<Hop>> {
+    let seed = name_hash(host);
+    let city = CITIES[seed as usize % CITIES.len()];
+    let backbone = BACKBONES[(seed >> 8) as usize % BACKBONES.len()];
+    let mut hops = vec![Some(Hop {
+        name: "_gateway".to_string(),
+        addr: GATEWAY_IPV4.to_string(),
+    })];
+    for i in 0..4 + seed % 6 {
+        let [a, b, c, d] = hop_hash(host, i as usize).to_be_bytes();
+        hops.push(match i {
+            0 => {
+                let addr = format!("100.{}.{}.1", 64 + a % 64, b);
+                Some(Hop {
+                    name: addr.clone(),
+                    addr,
+                })
+            }
+            1 => Some(Hop {
+                name: format!("lo0.bras{}.{}.isp.net", 1 + c % 4, city),
+                addr: format!("172.{}.{}.{}", 16 + a % 16, b, d.max(1)),
+            }),
+            // Routers that rate-limit or drop probes show up as * * *
+            _ if d % 7 == 0 => None,
+            _ => Some(Hop {
+                name: format!("ae{}.cr{}.{}.{}", a % 32, 1 + b % 9, city, backbone),
+                addr: format!("{}.{}.{}.{}", 62 + c % 150, a, b, d.max(1)),
+            }),
+        });
+    }
+    hops.push(Some(Hop {
+        name: host.to_string(),
+        addr: addr_text(ipv4_for(host)),
+    }));
+    hops
+}
+
+/// Three-decimal latencies the way traceroute prints them
+fn probe_times(times: impl Iterator<Item = f64>) -> String {
+    times
+        .map(|t| format!("{:.3} ms", t))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+impl System {
+    pub(super) fn cmd_traceroute(&mut self, args: &[&str]) -> CmdOutput {
+        let mut host = None;
+        let mut numeric = false;
+        let mut max_ttl = 30;
+        let mut queries = 3;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-n" => numeric = true,
+                "-m" | "-q" if i + 1 < args.len() => {
+                    let limit = if args[i] == "-m" { 255 } else { 10 };
+                    let value = match args[i + 1].parse::<usize>() {
+                        Ok(n) if (1..=limit).contains(&n) => n,
+                        _ => {
+                            return CmdOutput::error(
+                                1,
+                                format!(
+                                    "Cannot handle `{}' option with arg `{}' (argc {})",
+                                    args[i],
+                                    args[i + 1],
+                                    i + 2
+                                ),
+                            )
+                        }
+                    };
+                    if args[i] == "-m" {
+                        max_ttl = value;
+                    } else {
+                        queries = value;
+                    }
+                    i += 1;
+                }
+                arg if arg.starts_with('-') => {
+                    return CmdOutput::error(1, format!("Bad option `{}' (argc {})", arg, i + 1))
+                }
+                arg => host = Some(arg),
+            }
+            i += 1;
+        }
+        let Some(host) = host else {
+            return CmdOutput::usage(
+                "Usage:\n  traceroute [ -n ] [ -m max_ttl ] [ -q nqueries ] host".to_string(),
+            );
+        };
+        match self.host_unreachable(host) {
+            Some(Unreachable::Resolve) => {
+                return CmdOutput::error(1, format!(
+                    "{}: Temporary failure in name resolution\nCannot handle \"host\" cmdline arg `{}' on position 1 (argc {})",
+                    host, host, args.len()
+                ))
+            }
+            Some(Unreachable::Route) => return CmdOutput::error(1, "connect: Network is unreachable".to_string()),
+            None => {}
+        }
+
+        let hostname = self.cmd_hostname().stdout;
+        let local = host == "localhost" || host == hostname.trim() || host.starts_with("127.");
+        let addr = if local && !host.starts_with("127.") {
+            "127.0.0.1".to_string()
+        } else {
+            addr_text(ipv4_for(host))
+        };
+        let header = format!(
+            "traceroute to {} ({}), {} hops max, 60 byte packets",
+            host, addr, max_ttl
+        );
+
+        // Nothing to time over the bridge for this machine or its own LAN
+        if local || self.network.on_link(host) {
+            let (name, addr, times, suffix) = if local {
+                (host.to_string(), addr, [0.045, 0.012, 0.010], "")
+            } else if host == GATEWAY_IPV4 {
+                ("_gateway".to_string(), addr, [0.312, 0.287, 0.254], "")
+            } else {
+                // Nobody answers ARP for the address: our own stack reports
+                // the host unreachable
+                let (_, source) = self.network.route_for(host).unwrap_or_default();
+                let name = hostname.trim().to_string();
+                (name, source, [3071.321, 3071.301, 3071.290], " !H")
+            };
+            let shown = if numeric {
+                addr
+            } else {
+                format!("{} ({})", name, addr)
+            };
+            let times: Vec<String> = times
+                .iter()
+                .cycle()
+                .take(queries)
+                .map(|t| format!("{:.3} ms{}", t, suffix))
+                .collect();
+            return CmdOutput::ok(format!("{}\n 1  {}  {}", header, shown, times.join("  ")));
+        }
+
+        let mut hops = path_to(host);
+        hops.truncate(max_ttl);
+        self.traceroute = Some(TracerouteRun {
+            host: host.to_string(),
+            hops,
+            next: 0,
+            queries,
+            numeric,
+        });
+        CmdOutput::ok(self.emit(SystemEvent::Traceroute {
+            host: host.to_string(),
+            header,
+        }))
+    }
+
+    /// Print the next hop given one measured round trip to the target
+    /// (negative if the probe failed); empty once the trace is done
+    pub(super) fn traceroute_next(&mut self, rtt_ms: f64) -> String {
+        let Some(run) = self.traceroute.as_mut() else {
+            return String::new();
+        };
+        let Some(hop) = run.hops.get(run.next) else {
+            self.traceroute = None;
+            return String::new();
+        };
+        run.next += 1;
+        let ttl = run.next;
+        let total = run.hops.len();
+        let line = match hop {
+            Some(hop) if rtt_ms >= 0.0 => {
+                let times = (0..run.queries).map(|q| {
+                    let jitter = f64::from(hop_hash(&run.host, ttl * 16 + q) % 1000) / 1000.0;
+                    if ttl == 1 {
+                        0.2 + jitter * 0.3
+                    } else {
+                        // Latency grows along the path and ends at the measured RTT
+                        let share = (ttl as f64 / total as f64).powf(1.4);
+                        (rtt_ms * share).max(0.5) * (0.95 + jitter * 0.1)
+                    }
+                });
+                let shown = if run.numeric {
+                    hop.addr.clone()
+                } else {
+                    format!("{} ({})", hop.name, hop.addr)
+                };
+                format!("{:>2}  {}  {}", ttl, shown, probe_times(times))
+            }
+            _ => format!("{:>2}  {}", ttl, vec!["*"; run.queries].join(" ")),
+        };
+        let host = run.host.clone();
+        let queries = run.queries;
+        let now = js_sys::Date::now();
+        for q in 0..queries {
+            let port = 33434 + ((ttl - 1) * queries + q) as u16;
+            self.network.record_udp(now, &host, port, 32);
+        }
+        line
+    }
+}
@@ -0,0 +1,272 @@
+//! `ulimit` and the checks behind it: open VFS handles when a file is
+//! opened, processes per user and address space when one is spawned
+use super::System;
+use crate::process::Rlimit;
+use crate::shell::CmdOutput;
+
+/// Descriptors every process holds before it opens anything: stdin,
+/// stdout and stderr
+const STD_FDS: u64 = 3;
+/// "Too many open files", which `sys_open` hands back negated
+pub(super) const EMFILE: i32 = 24;
+
+const USAGE: &str = "usage: ulimit [-SHa] [-n|-u|-v [LIMIT]]";
+
+#[derive(Clone, Copy)]
+enum Resource {
+    Files,
+    Processes,
+    Memory,
+}
+
+impl Resource {
+    const ALL: [Resource; 3] = [Resource::Files, Resource::Processes, Resource::Memory];
+
+    fn from_flag(flag: char) -> Option<Resource> {
+        match flag {
+            'n' => Some(Resource::Files),
+            'u' => Some(Resource::Processes),
+            'v' => Some(Resource::Memory),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Resource::Files => "open files",
+            Resource::Processes => "max user processes",
+            Resource::Memory => "virtual memory",
+        }
+    }
+
+    /// How `ulimit -a` labels the flag: `(-n)`, or `(kbytes, -v)`
+    fn label(self) -> &'static str {
+        match self {
+            Resource::Files => "(-n)",
+            Resource::Processes => "(-u)",
+            Resource::Memory => "(kbytes, -v)",
+        }
+    }
+}
+
+fn show(value: Option<u64>) -> String {
+    value.map_or_else(|| "unlimited".to_string(), |v| v.to_string())
+}
+
+impl System {
+    fn limit_mut(&mut self, resource: Resource) -> &mut Rlimit {
+        let limits = &mut self.shell.limits;
+        match resource {
+            Resource::Files => &mut limits.nofile,
+            Resource::Processes => &mut limits.nproc,
+            Resource::Memory => &mut limits.vmem,
+        }
+    }
+
+    /// `ulimit [-SH] [-a | -n | -u | -v] [LIMIT]`: show or set this shell's
+    /// soft (`-S`) and hard (`-H`) limits; setting without either sets both
+    pub(super) fn cmd_ulimit(&mut self, args: &[&str]) -> CmdOutput {
+        let mut soft = false;
+        let mut hard = false;
+        let mut all = false;
+        let mut resource = None;
+        let mut value = None;
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'S' => soft = true,
+                            'H' => hard = true,
+                            'a' => all = true,
+                            flag => match Resource::from_flag(flag) {
+                                Some(r) => resource = Some(r),
+                                None => {
+                                    return CmdOutput::error(
+                                        2,
+                                        format!("ulimit: -{}: invalid option\n{}", flag, USAGE),
+                                    )
+                                }
+                            },
+                        }
+                    }
+                }
+                _ if value.is_some() => return CmdOutput::error(2, USAGE),
+                _ => value = Some(*arg),
+            }
+        }
+
+        let pick = |limit: Rlimit| match hard && !soft {
+            true => limit.hard,
+            false => limit.soft,
+        };
+        if all {
+            let lines: Vec<String> = Resource::ALL
+                .iter()
+                .map(|&r| {
+                    let label = r.label();
+                    format!(
+                        "{:<w$}{} {}",
+                        r.name(),
+                        label,
+                        show(pick(*self.limit_mut(r))),
+                        w = 36 - label.len()
+                    )
+                })
+                .collect();
+            return CmdOutput::ok(lines.join("\n"));
+        }
+        // Plain `ulimit` is the file size limit, which is never set here
+        let Some(resource) = resource else {
+            return match value {
+                None => CmdOutput::ok("unlimited"),
+                Some(_) => CmdOutput::error(
+                    1,
+                    "ulimit: file size: cannot modify limit: Operation not permitted",
+                ),
+            };
+        };
+        let Some(value) = value else {
+            return CmdOutput::ok(show(pick(*self.limit_mut(resource))));
+        };
+        let new = match value {
+            "unlimited" => None,
+            n => match n.parse::<u64>() {
+                Ok(n) => Some(n),
+                Err(_) => return CmdOutput::error(1, format!("ulimit: {}: invalid number", value)),
+            },
+        };
+
+        let (soft, hard) = match (soft, hard) {
+            (false, false) => (true, true),
+            flags => flags,
+        };
+        let is_root = self.current_user() == "root";
+        let current = *self.limit_mut(resource);
+        let mut next = current;
+        if soft {
+            next.soft = new;
+        }
+        if hard {
+            next.hard = new;
+        }
+        // `None` is unlimited, so it sorts above every number
+        let above = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(a), Some(b)) => a > b,
+        };
+        // Only root may raise a hard limit, and the soft one stays under it
+        let error = if above(next.hard, current.hard) && !is_root {
+            Some("Operation not permitted")
+        } else if above(next.soft, next.hard) {
+            Some("Invalid argument")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return CmdOutput::error(
+                1,
+                format!(
+                    "ulimit: {}: cannot modify limit: {}",
+                    resource.name(),
+                    error
+                ),
+            );
+        }
+        *self.limit_mut(resource) = next;
+        self.refresh_proc_limits();
+        CmdOutput::default()
+    }
+
+    /// Keep `/proc/self/limits` in step with the active shell
+    pub(super) fn refresh_proc_limits(&mut self) {
+        let text = self.shell.limits.proc_text();
+        let _ = self.kernel.fs.write_file("/proc/self/limits", &text);
+    }
+
+    /// Why the shell cannot fork another process, as bash words EAGAIN:
+    /// the user already runs as many as `ulimit -u` allows. Root is exempt
+    pub(super) fn fork_denied(&self) -> Option<String> {
+        let user = self.current_user();
+        if user == "root" {
+            return None;
+        }
+        let running = self
+            .kernel
+            .proc
+            .list()
+            .iter()
+            .filter(|p| p.user == user)
+            .count();
+        self.shell
+            .limits
+            .nproc
+            .exceeded(running as u64)
+            .then(|| "bash: fork: retry: Resource temporarily unavailable".to_string())
+    }
+
+    /// Whether the freshly forked `pid` maps more than `ulimit -v` allows,
+    /// in which case it is killed before it runs `cmd`
+    pub(super) fn exec_denied(&mut self, pid: u32, cmd: &str) -> Option<String> {
+        let vsz = self.kernel.proc.get(pid)?.vsz_kb();
+        if !self.shell.limits.vmem.exceeded(u64::from(vsz) + 1) {
+            return None;
+        }
+        self.kernel.proc.kill(pid, &mut self.kernel.mem);
+        Some(format!("bash: {}: Cannot allocate memory", cmd))
+    }
+
+    /// Refuse `sys_open` once the handles plus the standard descriptors
+    /// reach `ulimit -n`
+    pub(super) fn open_denied(&self) -> bool {
+        let open = self.kernel.fs.open_handles() as u64 + STD_FDS;
+        self.shell.limits.nofile.exceeded(open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_are_set_shown_and_enforced() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_file("/tmp/f", "data").unwrap();
+
+        assert_eq!(sys.exec("ulimit -n"), "1024");
+        assert_eq!(sys.exec("ulimit -Hn"), "4096");
+        assert!(sys
+            .exec("ulimit -a")
+            .contains("virtual memory          (kbytes, -v) unlimited"));
+
+        sys.exec("ulimit -n 5");
+        assert_eq!(sys.exec("ulimit -Hn"), "5");
+        assert!(sys
+            .exec("cat /proc/self/limits")
+            .contains("Max open files            5                    5"));
+        assert!(sys.exec("ulimit -n 6").contains("Operation not permitted"));
+        let handles: Vec<i32> = (0..3).map(|_| sys.sys_open("/tmp/f", false)).collect();
+        assert_eq!(handles[2], -EMFILE);
+        sys.sys_close(handles[0] as u32);
+        assert!(sys.sys_open("/tmp/f", false) > 0);
+
+        sys.exec("ulimit -Sv 100");
+        assert_eq!(sys.exec("ls /tmp"), "bash: ls: Cannot allocate memory");
+        assert_eq!(sys.exec("ulimit -Sv unlimited"), "");
+
+        let running = sys
+            .kernel
+            .proc
+            .list()
+            .iter()
+            .filter(|p| p.user == sys.current_user())
+            .count();
+        sys.exec(&format!("ulimit -u {}", running));
+        assert_eq!(
+            sys.fork_denied().as_deref(),
+            Some("bash: fork: retry: Resource temporarily unavailable")
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! `weather`: a wttr.in report, fetched as JSON through `net_perform` and
+//! drawn the way wttr.in draws one in a terminal, sky glyph and all
+use super::{System, SystemEvent};
+use crate::shell::CmdOutput;
+use serde_json::Value;
+
+const WTTR: &str = "https://wttr.in";
+const WEATHER_USAGE: &str = "usage: weather [-u] [CITY...]";
+/// Wind arrows by the direction the wind comes from, N first, as wttr.in
+const ARROWS: [&str; 8] = ["↓", "↙", "←", "↖", "↑", "↗", "→", "↘"];
+
+/// What the sky looks like, by wttr.in's weather code
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Sky {
+    Sunny,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Thunder,
+}
+
+impl Sky {
+    fn from_code(code: u32) -> Sky {
+        match code {
+            113 => Sky::Sunny,
+            116 => Sky::PartlyCloudy,
+            143 | 248 | 260 => Sky::Fog,
+            176 | 185 | 263 | 266 | 281 | 284 | 293..=314 | 353..=359 => Sky::Rain,
+            179 | 182 | 227 | 230 | 317..=338 | 350 | 362..=377 => Sky::Snow,
+            200 | 386..=395 => Sky::Thunder,
+            _ => Sky::Cloudy,
+        }
+    }
+
+    fn glyph(self) -> [&'static str; 5] {
+        match self {
+            Sky::Sunny => [
+                "    \\   /    ",
+                "     .-.     ",
+                "  ― (   ) ―  ",
+                "     `-’     ",
+                "    /   \\    ",
+            ],
+            Sky::PartlyCloudy => [
+                "   \\  /      ",
+                " _ /\"\".-.    ",
+                "   \\_(   ).  ",
+                "   /(___(__) ",
+                "             ",
+            ],
+            Sky::Cloudy => [
+                "             ",
+                "     .--.    ",
+                "  .-(    ).  ",
+                " (___.__)__) ",
+                "             ",
+            ],
+            Sky::Fog => [
+                "             ",
+                " _ - _ - _ - ",
+                "  _ - _ - _  ",
+                " _ - _ - _ - ",
+                "             ",
+            ],
+            Sky::Rain => [
+                "     .-.     ",
+                "    (   ).   ",
+                "   (___(__)  ",
+                "    ‘ ‘ ‘ ‘  ",
+                "   ‘ ‘ ‘ ‘   ",
+            ],
+            Sky::Snow => [
+                "     .-.     ",
+                "    (   ).   ",
+                "   (___(__)  ",
+                "    *  *  *  ",
+                "   *  *  *   ",
+            ],
+            Sky::Thunder => [
+                "     .-.     ",
+                "    (   ).   ",
+                "   (___(__)  ",
+                "    ⚡‘ ‘⚡‘  ",
+                "    ‘ ‘ ‘ ‘  ",
+            ],
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Sky::Sunny => "yellow",
+            Sky::Rain | Sky::Thunder => "cyan",
+            Sky::Snow => "white",
+            _ => "gray",
+        }
+    }
+}
+
+/// wttr.in's JSON for `location`, or for wherever the request comes from
+pub(super) fn wttr_url(location: &str) -> String {
+    let encoded: String = location
+        .trim()
+        .bytes()
+        .map(|b| match b {
+            b' ' => "+".to_string(),
+            b if b.is_ascii_alphanumeric() || b"-_.~,".contains(&b) => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("{}/{}?format=j1", WTTR, encoded)
+}
+
+/// A string field of a wttr.in object
+fn field<'a>(v: &'a Value, key: &str) -> &'a str {
+    v[key].as_str().unwrap_or("")
+}
+
+/// `[{"value": "..."}]`, the way wttr.in wraps names and descriptions
+fn value_of<'a>(v: &'a Value, key: &str) -> &'a str {
+    v[key][0]["value"].as_str().unwrap_or("").trim()
+}
+
+/// `+18(17) °C`, with the feels-like temperature when it differs
+fn temperature(actual: &str, feels: &str, unit: &str) -> String {
+    let signed = |t: &str| match t.parse::<i32>() {
+        Ok(t) if t > 0 => format!("+{}", t),
+        _ => t.to_string(),
+    };
+    if feels.is_empty() || feels == actual {
+        format!("{} {}", signed(actual), unit)
+    } else {
+        format!("{}({}) {}", signed(actual), feels, unit)
+    }
+}
+
+/// The report for wttr.in's `format=j1` answer
+fn render_weather(json: &str, imperial: bool) -> Option<String> {
+    let v: Value = serde_json::from_str(json).ok()?;
+    let now = v["current_condition"].get(0)?;
+    // wttr.in sends both; the key and the label for the units in use
+    let unit = |metric: &'static str, us: &'static str| if imperial { us } else { metric };
+    let sky = Sky::from_code(field(now, "weatherCode").parse().unwrap_or(0));
+    let arrow = field(now, "winddirDegree")
+        .parse::<u32>()
+        .map_or("", |d| ARROWS[((d * 2 + 45) / 90 % 8) as usize]);
+    let facts = [
+        value_of(now, "weatherDesc").to_string(),
+        temperature(
+            field(now, unit("temp_C", "temp_F")),
+            field(now, unit("FeelsLikeC", "FeelsLikeF")),
+            unit("°C", "°F"),
+        ),
+        format!(
+            "{} {} {}",
+            arrow,
+            field(now, unit("windspeedKmph", "windspeedMiles")),
+            unit("km/h", "mph")
+        ),
+        format!(
+            "{} {}",
+            field(now, unit("visibility", "visibilityMiles")),
+            unit("km", "mi")
+        ),
+        format!(
+            "{} {}",
+            field(now, unit("precipMM", "precipInches")),
+            unit("mm", "in")
+        ),
+    ];
+
+    let area = &v["nearest_area"][0];
+    let place = [value_of(area, "areaName"), value_of(area, "country")]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut out = vec![format!("Weather report: {}", place), String::new()];
+    for (art, fact) in sky.glyph().iter().zip(facts) {
+        out.push(format!(
+            "\x1b[COLOR:{}]{}\x1b[COLOR:reset] {}",
+            sky.color(),
+            art,
+            fact
+        ));
+    }
+    let days = v["weather"].as_array().filter(|d| !d.is_empty());
+    if let Some(days) = days {
+        out.push(String::new());
+        out.push(format!(
+            "  {:<10}  {:>4}  {:>4}  Midday",
+            "Date", "Low", "High"
+        ));
+        for day in days {
+            // Eight readings a day; the fifth is noon
+            let hourly = day["hourly"].as_array();
+            let noon = hourly.and_then(|h| h.get(4).or(h.last()));
+            out.push(format!(
+                "  {:<10}  {:>4}  {:>4}  {}",
+                field(day, "date"),
+                field(day, unit("mintempC", "mintempF")),
+                field(day, unit("maxtempC", "maxtempF")),
+                noon.map_or("", |h| value_of(h, "weatherDesc"))
+            ));
+        }
+    }
+    Some(out.join("\n"))
+}
+
+impl System {
+    /// The rest of `weather` once wttr.in answered, or didn't
+    pub(super) fn weather_finish(
+        &mut self,
+        location: &str,
+        imperial: bool,
+        fetched: Result<String, String>,
+    ) -> CmdOutput {
+        let report = fetched
+            .map_err(|_| "weather: wttr.in is unreachable".to_string())
+            .and_then(|json| {
+                render_weather(&json, imperial).ok_or_else(|| match location {
+                    "" => "weather: wttr.in sent no report".to_string(),
+                    place => format!("weather: no report for '{}'", place),
+                })
+            });
+        CmdOutput::from_result(report)
+    }
+
+    pub(super) fn cmd_weather(&mut self, args: &[&str]) -> CmdOutput {
+        let imperial = args.contains(&"-u");
+        let mut words = Vec::new();
+        for arg in args.iter().filter(|a| **a != "-u") {
+            if arg.starts_with('-') {
+                return CmdOutput::usage(WEATHER_USAGE);
+            }
+            words.push(*arg);
+        }
+        if self.host_unreachable("wttr.in").is_some() {
+            return CmdOutput::error(1, "weather: Could not resolve host: wttr.in");
+        }
+        CmdOutput::ok(self.emit(SystemEvent::Weather {
+            location: words.join(" "),
+            imperial,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"{
+        "current_condition": [{"temp_C": "18", "FeelsLikeC": "17", "weatherCode": "113",
+            "weatherDesc": [{"value": "Sunny"}], "winddirDegree": "225",
+            "windspeedKmph": "11", "visibility": "10", "precipMM": "0.0"}],
+        "nearest_area": [{"areaName": [{"value": "London"}],
+            "country": [{"value": "United Kingdom"}]}],
+        "weather": [{"date": "2026-10-16", "mintempC": "12", "maxtempC": "19",
+            "hourly": [{}, {}, {}, {}, {"weatherDesc": [{"value": "Partly cloudy "}]}]}]
+    }"#;
+
+    #[test]
+    fn wttr_reports_render_with_a_sky_glyph() {
+        let report = render_weather(REPORT, false).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "Weather report: London, United Kingdom");
+        assert!(lines[2].contains("\\   /") && lines[2].ends_with(" Sunny"));
+        assert!(lines[3].ends_with(" +18(17) °C"));
+        assert!(lines[4].ends_with(" ↗ 11 km/h"));
+        assert!(report.ends_with("  2026-10-16    12    19  Partly cloudy"));
+        assert_eq!(Sky::from_code(302), Sky::Rain);
+        assert_eq!(Sky::from_code(389), Sky::Thunder);
+        assert_eq!(wttr_url("New York"), "https://wttr.in/New+York?format=j1");
+        assert!(render_weather("Unknown location", false).is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use super::netif::Unreachable;
+use super::{System, SystemEvent};
+use crate::network::{self, Protocol, SocketState};
+use crate::shell::CmdOutput;
+
+const USAGE: &str = "Usage: wscat [options] (--connect <url>)\n\n\
+Options:\n  \
+-c, --connect <url>     connect to a WebSocket server\n  \
+-x, --execute <command> send a message, print the first reply and exit\n  \
+-h, --help              display help for command\n\n\
+While connected, lines are sent as text messages. /close [code [reason]]\n\
+ends the session, as does CTRL+C. Ping and pong frames are handled by the\n\
+browser and never reach the page, so /ping and /pong are unavailable.";
+
+/// An interactive `wscat` connection
+pub(super) struct WscatSession {
+    socket: u32,
+    /// Message for `-x`; sent once the connection opens
+    execute: Option<String>,
+    /// The server closed the connection or `/close` was acknowledged
+    done: bool,
+}
+
+impl System {
+    pub(super) fn cmd_wscat(&mut self, args: &[&str]) -> CmdOutput {
+        let mut url = None;
+        let mut execute = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i] {
+                "-c" | "--connect" if i + 1 < args.len() => {
+                    url = Some(args[i + 1]);
+                    i += 1;
+                }
+                "-x" | "--execute" if i + 1 < args.len() => {
+                    execute = Some(args[i + 1].to_string());
+                    i += 1;
+                }
+                "-h" | "--help" => return CmdOutput::usage(USAGE.to_string()),
+                arg if arg.starts_with('-') => {
+                    return CmdOutput::error(1, format!("error: unknown option '{}'", arg))
+                }
+                arg => url = Some(arg),
+            }
+            i += 1;
+        }
+        let Some(url) = url else {
+            return CmdOutput::usage(USAGE.to_string());
+        };
+        let lower = url.to_lowercase();
+        let url = if lower.starts_with("ws://") || lower.starts_with("wss://") {
+            url.to_string()
+        } else if lower.contains("://") {
+            return CmdOutput::error(1, format!("error: Invalid URL: {}", url));
+        } else {
+            format!("ws://{}", url)
+        };
+
+        let (host, port) = network::parse_remote_endpoint(&url);
+        match self.host_unreachable(&host) {
+            Some(Unreachable::Resolve) => {
+                return CmdOutput::error(1, format!("error: getaddrinfo EAI_AGAIN {}", host))
+            }
+            Some(Unreachable::Route) => {
+                return CmdOutput::error(1, format!("error: connect ENETUNREACH {}:{}", host, port))
+            }
+            None => {}
+        }
+
+        // A session the server already ended is only dropped here, outside
+        // of the event callbacks that are still running when it closes
+        if let Some(old) = self.wscat.take() {
+            self.network.release(old.socket);
+        }
+        let socket = self.network.socket(Protocol::WebSocket);
+        self.network.set_owner(socket, self.shell_pid());
+        if let Err(e) = self.network.connect_ws(socket, &url) {
+            self.network.release(socket);
+            return CmdOutput::error(1, format!("error: {}", e));
+        }
+        self.wscat = Some(WscatSession {
+            socket,
+            execute,
+            done: false,
+        });
+        CmdOutput::ok(self.emit(SystemEvent::Wscat))
+    }
+
+    pub(super) fn wscat_active(&self) -> bool {
+        self.wscat.as_ref().is_some_and(|s| !s.done)
+    }
+
+    /// One line typed at the `wscat` prompt
+    pub(super) fn wscat_input(&mut self, line: &str) -> String {
+        let Some(session) = self.wscat.as_ref().filter(|s| !s.done) else {
+            return String::new();
+        };
+        let socket = session.socket;
+        if let Some(command) = line.strip_prefix('/') {
+            let mut parts = command.splitn(3, ' ');
+            match parts.next().unwrap_or("") {
+                "close" => {
+                    let code = match parts.next().map(str::parse::<u16>) {
+                        None => 1000,
+                        Some(Ok(code)) if code == 1000 || (3000..=4999).contains(&code) => code,
+                        Some(_) => {
+                            return "error: close code must be 1000 or between 3000 and 4999"
+                                .to_string()
+                        }
+                    };
+                    let reason = parts.next().unwrap_or("");
+                    return match self.network.close_with(socket, code, reason) {
+                        Ok(()) => String::new(),
+                        Err(e) => format!("error: {}", e),
+                    };
+                }
+                "ping" | "pong" => {
+                    return "error: ping and pong frames are handled by the browser".to_string()
+                }
+                _ => {}
+            }
+        }
+        if self.network.socket_state(socket) != Some(SocketState::Established) {
+            return "error: not opened".to_string();
+        }
+        match self.network.send(socket, line) {
+            Ok(()) => String::new(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    /// A WebSocket event forwarded by the frontend; returns what `wscat`
+    /// prints for it, if the socket is the session's
+    pub(super) fn wscat_event(&mut self, socket: u32, kind: &str, data: &str) -> String {
+        self.network
+            .socket_event(socket, js_sys::Date::now(), kind, data.len());
+        let Some(session) = self
+            .wscat
+            .as_mut()
+            .filter(|s| s.socket == socket && !s.done)
+        else {
+            return String::new();
+        };
+        match kind {
+            "open" => match session.execute.clone() {
+                Some(message) => match self.network.send(socket, &message) {
+                    Ok(()) => String::new(),
+                    Err(e) => format!("error: {}", e),
+                },
+                None => "Connected (press CTRL+C to quit)".to_string(),
+            },
+            "message" => {
+                if session.execute.is_some() {
+                    let _ = self.network.close(socket);
+                }
+                format!("< {}", data)
+            }
+            "error" => {
+                session.done = true;
+                "error: Unexpected server response".to_string()
+            }
+            "close" => {
+                session.done = true;
+                if session.execute.is_some() {
+                    return String::new();
+                }
+                let (code, reason) = data.split_once('\t').unwrap_or((data, ""));
+                format!("Disconnected (code: {}, reason: \"{}\")", code, reason)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// CTRL+C: drop the connection without waiting for the close handshake
+    pub(super) fn wscat_quit(&mut self) {
+        if let Some(session) = self.wscat.take() {
+            let _ = self.network.close(session.socket);
+            self.network.release(session.socket);
+        }
+    }
+}
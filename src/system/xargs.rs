@@ -0,0 +1,200 @@
+use super::System;
+use crate::shell::CmdOutput;
+
+/// Most commands one `xargs` runs, so a huge input cannot hang the tab
+const MAX_INVOCATIONS: usize = 1000;
+
+const USAGE: &str =
+    "usage: xargs [-0] [-r] [-t] [-n MAX] [-I REPLACE] [-a FILE] [COMMAND [ARG]...]";
+
+impl System {
+    /// `xargs`: run COMMAND (default `echo`) with the items read from the
+    /// pipe, or from `-a FILE`, appended as arguments. The pipeline passes
+    /// its input along as `-a`
+    pub(super) fn cmd_xargs(&mut self, args: &[&str]) -> CmdOutput {
+        let mut nul = false;
+        let mut no_empty = false;
+        let mut trace = false;
+        let mut max_args = None;
+        let mut replace = None;
+        let mut input = None;
+        let mut rest = args.iter();
+        let command: Vec<&str> = loop {
+            let Some(arg) = rest.next() else {
+                break Vec::new();
+            };
+            match *arg {
+                "-0" | "--null" => nul = true,
+                "-r" | "--no-run-if-empty" => no_empty = true,
+                "-t" | "--verbose" => trace = true,
+                "-n" | "--max-args" => match rest.next().and_then(|n| n.parse::<usize>().ok()) {
+                    Some(n) if n > 0 => max_args = Some(n),
+                    _ => return CmdOutput::error(1, "xargs: value for -n must be at least 1"),
+                },
+                "-I" => match rest.next() {
+                    Some(r) => replace = Some(*r),
+                    None => {
+                        return CmdOutput::error(1, "xargs: option requires an argument -- 'I'")
+                    }
+                },
+                "-a" | "--arg-file" => match rest.next() {
+                    Some(path) => input = Some(*path),
+                    None => {
+                        return CmdOutput::error(1, "xargs: option requires an argument -- 'a'")
+                    }
+                },
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return CmdOutput::error(
+                        1,
+                        format!("xargs: invalid option -- '{}'\n{}", &flag[1..], USAGE),
+                    )
+                }
+                first => break std::iter::once(first).chain(rest.copied()).collect(),
+            }
+        };
+        let (program, fixed) = match command.split_first() {
+            Some((program, fixed)) => (*program, fixed.to_vec()),
+            None => ("echo", Vec::new()),
+        };
+
+        let text = match input {
+            Some(path) => match self.read_file_bytes(path) {
+                Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+                Err(e) => return CmdOutput::error(1, format!("xargs: {}", e)),
+            },
+            None => String::new(),
+        };
+        // -I takes whole lines, one command each; otherwise blank-separated
+        // words, or NUL-separated items with -0
+        let items: Vec<&str> = if nul {
+            text.split('\0').filter(|i| !i.is_empty()).collect()
+        } else if replace.is_some() {
+            text.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .collect()
+        } else {
+            text.split_whitespace().collect()
+        };
+        if items.is_empty() && no_empty {
+            return CmdOutput::default();
+        }
+
+        let batches: Vec<Vec<&str>> = match (replace, max_args) {
+            (Some(_), _) => items.iter().map(|item| vec![*item]).collect(),
+            (None, Some(n)) => items.chunks(n).map(<[&str]>::to_vec).collect(),
+            (None, None) => vec![items],
+        };
+        let mut out = Vec::new();
+        let mut errors = Vec::new();
+        let mut status = 0;
+        for (run, batch) in batches.into_iter().enumerate() {
+            if run == MAX_INVOCATIONS {
+                errors.push(format!("xargs: stopped after {} commands", MAX_INVOCATIONS));
+                status = 1;
+                break;
+            }
+            let argv: Vec<String> = match replace {
+                Some(marker) => fixed.iter().map(|a| a.replace(marker, batch[0])).collect(),
+                None => fixed.iter().chain(&batch).map(|a| a.to_string()).collect(),
+            };
+            let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+            let cmdline = std::iter::once(program)
+                .chain(argv.iter().copied())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if trace {
+                errors.push(cmdline.clone());
+            }
+            // xargs waits for each command, so its process is gone before
+            // the next batch forks; a refused fork ends the run
+            let child = match self.spawn_program(program, &cmdline) {
+                Ok(child) => child,
+                Err(e) => {
+                    errors.push(e.stderr);
+                    status = e.status;
+                    break;
+                }
+            };
+            let result = self.dispatch_program(program, &argv);
+            if let Some(pid) = child {
+                self.kernel.proc.kill(pid, &mut self.kernel.mem);
+                self.kernel.scheduler.remove(pid);
+            }
+            if !result.stdout.is_empty() {
+                out.push(result.stdout);
+            }
+            if !result.stderr.is_empty() {
+                errors.push(result.stderr);
+            }
+            // As GNU xargs reports it: 127 if the command is missing, 123
+            // if any run failed
+            match result.status {
+                0 => {}
+                127 => {
+                    status = 127;
+                    break;
+                }
+                _ => status = 123,
+            }
+        }
+        CmdOutput {
+            stdout: out.join("\n"),
+            stderr: errors.join("\n"),
+            status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_the_command_over_piped_items() {
+        let mut sys = System::new();
+        sys.kernel.fs.init();
+        sys.kernel.fs.create_file("/tmp/a.txt", "foo one").unwrap();
+        sys.kernel.fs.create_file("/tmp/b.txt", "bar").unwrap();
+        sys.kernel.fs.create_file("/tmp/c.txt", "foo two").unwrap();
+
+        assert_eq!(sys.exec("seq 5 | xargs"), "1 2 3 4 5");
+        assert_eq!(sys.exec("seq 5 | xargs -n 2"), "1 2\n3 4\n5");
+        assert_eq!(
+            sys.exec("find /tmp -name *.txt | xargs grep foo"),
+            "/tmp/a.txt:foo one\n/tmp/c.txt:foo two"
+        );
+        assert_eq!(
+            sys.exec("seq 2 | xargs -I {} echo item-{}"),
+            "item-1\nitem-2"
+        );
+        assert_eq!(sys.exec("echo | xargs -r echo hi"), "");
+        sys.exec("seq 3 | xargs nosuchcmd");
+        assert_eq!(sys.last_status, 127);
+
+        // Every batch's process is reaped, so long runs don't use up memory
+        let before = sys.kernel.proc.list().len();
+        let out = sys.exec("seq 600 | xargs -n 1 echo");
+        assert_eq!(out.lines().count(), 600);
+        assert_eq!(sys.last_status, 0);
+        assert_eq!(sys.kernel.proc.list().len(), before);
+        assert_eq!(sys.exec("echo still"), "still");
+        let out = sys.exec("seq 1200 | xargs -n 1 echo");
+        assert!(out.ends_with("xargs: stopped after 1000 commands"));
+        assert_eq!(sys.last_status, 1);
+
+        // A refused fork stops xargs at once
+        sys.exec("ulimit -u 1");
+        let out = sys.exec("seq 5 | xargs -n 1 echo");
+        assert_eq!(out, "bash: fork: retry: Resource temporarily unavailable");
+        assert_eq!(sys.last_status, 1);
+        sys.exec("ulimit -u unlimited");
+
+        sys.exec("chmod 600 /tmp/b.txt");
+        sys.kernel.fs.resolve_mut("/tmp/b.txt").unwrap().owner = "root".into();
+        assert_eq!(
+            sys.exec("xargs -a /tmp/b.txt echo"),
+            "xargs: /tmp/b.txt: Permission denied"
+        );
+    }
+}
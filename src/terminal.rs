@@ -0,0 +1,278 @@
+//! The terminal screen as the crate sees it: every line printed, kept as
+//! scrollback up to a limit, the rows on screen and the cursor. The
+//! frontend draws from here, so `clear`, `reset` and the pager change what
+//! is shown the same way whichever frontend is attached.
+use crate::ansi;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Lines kept before the oldest are dropped
+pub const DEFAULT_SCROLLBACK: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Line {
+    /// Stays the same while the line is kept, however many drop off before it
+    pub id: u32,
+    pub text: String,
+    /// How the frontend styles it: `command`, `output`, `boot`, ...
+    pub class: String,
+}
+
+/// Where the next output goes, in screen rows and columns from the top left
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Cursor {
+    pub row: usize,
+    pub col: usize,
+    pub visible: bool,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor {
+            row: 0,
+            col: 0,
+            visible: true,
+        }
+    }
+}
+
+/// A match of a scrollback search: the line and the byte range within it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub line: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Default)]
+struct Buffer {
+    lines: VecDeque<Line>,
+    cursor: Cursor,
+}
+
+pub struct Terminal {
+    buffer: Buffer,
+    /// The main buffer, put aside while a full-screen program has the
+    /// alternate one
+    primary: Option<Buffer>,
+    next_id: u32,
+    limit: usize,
+    rows: usize,
+    cols: usize,
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Terminal {
+    pub fn new() -> Self {
+        Terminal {
+            buffer: Buffer::default(),
+            primary: None,
+            next_id: 0,
+            limit: DEFAULT_SCROLLBACK,
+            rows: 24,
+            cols: 80,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.buffer.cursor
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        self.rows = rows.max(1);
+        self.cols = cols.max(1);
+        self.place_cursor();
+    }
+
+    /// Keep at most `limit` lines, dropping the oldest now if over
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(self.rows);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.buffer.lines.len() > self.limit {
+            self.buffer.lines.pop_front();
+        }
+    }
+
+    /// The cursor sits on the row after the last line, or the bottom row
+    /// once the screen has filled
+    fn place_cursor(&mut self) {
+        let cursor = &mut self.buffer.cursor;
+        cursor.row = self.buffer.lines.len().min(self.rows - 1);
+        cursor.col = 0;
+    }
+
+    /// Append `text`, one line per `\n`; returns the id of the first
+    pub fn write(&mut self, text: &str, class: &str) -> u32 {
+        let first = self.next_id;
+        for line in text.split('\n') {
+            self.buffer.lines.push_back(Line {
+                id: self.next_id,
+                text: line.to_string(),
+                class: class.to_string(),
+            });
+            self.next_id += 1;
+        }
+        self.trim();
+        self.place_cursor();
+        first
+    }
+
+    /// Blank the screen and forget the scrollback, like `clear`
+    pub fn clear(&mut self) {
+        self.buffer.lines.clear();
+        self.buffer.cursor = Cursor {
+            visible: self.buffer.cursor.visible,
+            ..Cursor::default()
+        };
+    }
+
+    /// Blank the screen but scroll what was on it into the scrollback,
+    /// like `clear -x`
+    pub fn clear_screen(&mut self) {
+        for _ in 0..self.rows {
+            self.write("", "output");
+        }
+        self.buffer.cursor.row = 0;
+    }
+
+    /// Back to a freshly opened terminal: main buffer, nothing in it, the
+    /// cursor home and shown
+    pub fn reset(&mut self) {
+        self.primary = None;
+        self.buffer = Buffer::default();
+    }
+
+    pub fn in_alternate(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    /// Switch to an empty alternate buffer; the main one is kept as it is
+    pub fn enter_alternate(&mut self) {
+        if self.primary.is_none() {
+            self.primary = Some(std::mem::take(&mut self.buffer));
+        }
+    }
+
+    /// Back to the main buffer, dropping whatever the alternate one held
+    pub fn leave_alternate(&mut self) {
+        if let Some(primary) = self.primary.take() {
+            self.buffer = primary;
+        }
+    }
+
+    /// Every line kept, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.buffer.lines.iter()
+    }
+
+    /// Id of the oldest line kept; lines before it were dropped
+    pub fn first_id(&self) -> u32 {
+        self.buffer.lines.front().map_or(self.next_id, |l| l.id)
+    }
+
+    /// The lines on screen: the last `rows` of them
+    pub fn visible(&self) -> impl Iterator<Item = &Line> {
+        let skip = self.buffer.lines.len().saturating_sub(self.rows);
+        self.buffer.lines.iter().skip(skip)
+    }
+
+    /// Every occurrence of `query`, newest line first. Ignores case unless
+    /// `query` has capitals, like `less -i`
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let fold = !query.chars().any(char::is_uppercase);
+        let needle = if fold {
+            query.to_ascii_lowercase()
+        } else {
+            query.to_string()
+        };
+        let mut hits = Vec::new();
+        for line in self.buffer.lines.iter().rev() {
+            // Offsets are into the text as shown, escapes taken out; ASCII
+            // folding keeps them valid
+            let shown = ansi::strip(&line.text);
+            let haystack = if fold {
+                shown.to_ascii_lowercase()
+            } else {
+                shown
+            };
+            let mut from = 0;
+            while let Some(at) = haystack[from..].find(&needle) {
+                let start = from + at;
+                hits.push(SearchHit {
+                    line: line.id,
+                    start,
+                    end: start + needle.len(),
+                });
+                from = start + needle.len().max(1);
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrollback_screen_and_alternate_buffer() {
+        let mut term = Terminal::new();
+        term.resize(3, 40);
+        assert_eq!(term.write("a\nb", "output"), 0);
+        assert_eq!(term.cursor().row, 2);
+        term.write("Needle c\nd \x1b[1mneedle\x1b[0m", "output");
+        let shown: Vec<&str> = term.visible().map(|l| l.text.as_str()).collect();
+        assert_eq!(shown, ["b", "Needle c", "d \x1b[1mneedle\x1b[0m"]);
+        assert_eq!(term.cursor().row, 2);
+
+        let hits = term.search("needle");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(
+            hits[0],
+            SearchHit {
+                line: 3,
+                start: 2,
+                end: 8
+            }
+        );
+        assert_eq!(term.search("Needle").len(), 1);
+
+        term.set_limit(3);
+        assert_eq!(term.first_id(), 1);
+        term.enter_alternate();
+        term.write("frame", "output");
+        assert_eq!(term.lines().count(), 1);
+        term.leave_alternate();
+        assert_eq!(term.lines().count(), 3);
+
+        term.clear_screen();
+        assert_eq!(term.visible().filter(|l| !l.text.is_empty()).count(), 0);
+        assert_eq!(term.cursor().row, 0);
+        term.clear();
+        assert_eq!(term.lines().count(), 0);
+        assert_eq!(term.first_id(), 8);
+    }
+}
@@ -1,14 +1,28 @@
 impl Vfs {
-    /// Get a clone of the root inode (for persistence)
+    /// Get a clone of the root inode (for persistence).
+    /// Mounted filesystems are swapped back out so only the on-disk tree is saved.
     pub fn root_clone(&self) -> Inode {
-        self.root.clone()
+        let mut root = self.root.clone();
+        for mount in self.mounts.iter().rev() {
+            if let Some(node) = Self::walk_mut(&mut root, &mount.target) {
+                *node = mount.covered.clone();
+            }
+        }
+        root
     }
 
     /// Replace the root inode (for persistence load)
     pub fn set_root(&mut self, root: Inode) {
         self.root = root;
+        self.mounts.clear();
+        self.next_ino = 0;
+        self.orphans.clear();
+        self.unattached.clear();
     }
 }
+use crate::process::ResourceLimits;
+use crate::vfs_persist::{self, Restored};
+use crate::{ansi, clock};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -16,6 +30,17 @@ use std::collections::HashMap;
 pub const CRITICAL_BINARIES: &[&str] = &["sh", "bash", "init", "login", "getty"];
 pub const IMPORTANT_BINARIES: &[&str] =
     &["ls", "cat", "cd", "pwd", "rm", "mkdir", "touch", "cp", "mv"];
+// Where the critical binaries live, with their descriptions, so fsck can put
+// them back
+pub const CRITICAL_FILES: &[(&str, &str)] = &[
+    ("/bin/sh", "Bourne shell"),
+    ("/bin/bash", "Bourne Again SHell"),
+    ("/bin/login", "begin session"),
+    ("/sbin/init", "process control initialization"),
+    ("/sbin/getty", "set terminal mode"),
+];
+pub const GRUB_CFG_PATH: &str = "/boot/grub/grub.cfg";
+pub const DEFAULT_GRUB_CFG: &str = "# GRUB configuration file\nset default=0\nset timeout=5\n\nmenuentry 'kpawnd GNU/Linux' {\n    linux /boot/vmlinuz-6.1.0-kpawnd root=/dev/sda1 ro quiet\n    initrd /boot/initrd.img-6.1.0-kpawnd\n}\n";
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Inode {
@@ -29,10 +54,53 @@ pub struct Inode {
     pub size: usize,
     pub is_executable: bool,
     pub is_critical: bool,
+    /// Birth, modification and access times, in seconds since the epoch.
+    /// Trees saved before timestamps existed load them as 0
+    #[serde(default)]
+    pub created: i64,
+    #[serde(default)]
+    pub modified: i64,
+    #[serde(default)]
+    pub accessed: i64,
+    /// Inode number for `ls -i` and `stat`; 0 until the VFS hands one out
+    #[serde(default)]
+    pub ino: u64,
 }
 
 impl Inode {
+    /// Whether `user`, belonging to `groups`, has the `want` bits
+    /// (4 read, 2 write, 1 execute/search) on this node
+    pub fn allows(&self, user: &str, groups: &[String], want: u8) -> bool {
+        if user == "root" {
+            return true;
+        }
+        let chars: Vec<char> = self.permissions.chars().collect();
+        if chars.len() < 10 {
+            return true;
+        }
+        let triplet = if self.owner == user {
+            &chars[1..4]
+        } else if groups.contains(&self.group) {
+            &chars[4..7]
+        } else {
+            &chars[7..10]
+        };
+        let mut bits = 0;
+        if triplet[0] == 'r' {
+            bits |= 4;
+        }
+        if triplet[1] == 'w' {
+            bits |= 2;
+        }
+        // setuid/setgid/sticky letters in the execute slot still grant search
+        if matches!(triplet[2], 'x' | 's' | 't') {
+            bits |= 1;
+        }
+        bits & want == want
+    }
+
     pub fn dir(name: &str) -> Self {
+        let now = clock::now_secs();
         Inode {
             name: name.into(),
             is_dir: true,
@@ -44,9 +112,14 @@ impl Inode {
             size: 4096,
             is_executable: false,
             is_critical: false,
+            created: now,
+            modified: now,
+            accessed: now,
+            ino: 0,
         }
     }
     pub fn file(name: &str, data: &str) -> Self {
+        let now = clock::now_secs();
         Inode {
             name: name.into(),
             is_dir: false,
@@ -58,9 +131,14 @@ impl Inode {
             size: data.len(),
             is_executable: false,
             is_critical: false,
+            created: now,
+            modified: now,
+            accessed: now,
+            ino: 0,
         }
     }
     pub fn binary(name: &str, desc: &str, critical: bool) -> Self {
+        let now = clock::now_secs();
         Inode {
             name: name.into(),
             is_dir: false,
@@ -75,9 +153,14 @@ impl Inode {
             size: 35000 + (name.len() * 1000), // Fake realistic size
             is_executable: true,
             is_critical: critical,
+            created: now,
+            modified: now,
+            accessed: now,
+            ino: 0,
         }
     }
     pub fn symlink(name: &str, target: &str) -> Self {
+        let now = clock::now_secs();
         Inode {
             name: name.into(),
             is_dir: false,
@@ -89,15 +172,47 @@ impl Inode {
             size: target.len(),
             is_executable: false,
             is_critical: false,
+            created: now,
+            modified: now,
+            accessed: now,
+            ino: 0,
         }
     }
 }
 
+/// One file in the `export_user_files` JSON. Saves from before timestamps
+/// were kept are bare content strings
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SavedFile {
+    Plain(String),
+    Stamped {
+        data: String,
+        created: i64,
+        modified: i64,
+        accessed: i64,
+    },
+}
+
+/// An entry in the mount table. `covered` holds the directory that was
+/// hidden by the mount so it can be restored on umount.
+#[derive(Clone)]
+pub struct Mount {
+    pub source: String,
+    pub target: String,
+    pub fs_type: String,
+    pub options: String,
+    pub read_only: bool,
+    covered: Inode,
+}
+
 #[derive(Clone)]
 pub struct VfsHandle {
     pub path: String,
     pub offset: usize,
     pub writable: bool,
+    /// Process that opened it
+    pub pid: u32,
 }
 
 pub struct Vfs {
@@ -105,11 +220,19 @@ pub struct Vfs {
     pub cwd: String,
     handles: HashMap<u32, VfsHandle>,
     next_handle: u32,
+    // Next inode number to hand out; 0 until the tree has been numbered
+    next_ino: u64,
     pub kernel_panic: bool,
     pub panic_reason: String,
     default_owner: String,
     default_group: String,
     ignore_critical_deletes: bool,
+    mounts: Vec<Mount>,
+    /// Files deleted while still open when the power went, for fsck to clear
+    pub orphans: Vec<Inode>,
+    /// Inodes whose directory entry never reached the disk, for fsck to
+    /// connect to /lost+found
+    pub unattached: Vec<Inode>,
 }
 
 impl Default for Vfs {
@@ -125,15 +248,20 @@ impl Vfs {
             cwd: "/".into(),
             handles: HashMap::new(),
             next_handle: 1,
+            next_ino: 0,
             kernel_panic: false,
             panic_reason: String::new(),
             default_owner: "user".into(),
             default_group: "user".into(),
             ignore_critical_deletes: false,
+            mounts: Vec::new(),
+            orphans: Vec::new(),
+            unattached: Vec::new(),
         }
     }
 
     pub fn init(&mut self) {
+        self.mounts.clear();
         // Create main directories
         for d in [
             "bin", "sbin", "dev", "etc", "home", "lib", "lib64", "proc", "sys", "tmp", "usr",
@@ -143,6 +271,9 @@ impl Vfs {
         {
             self.root.children.insert((*d).into(), Inode::dir(d));
         }
+        if let Some(tmp) = self.root.children.get_mut("tmp") {
+            tmp.permissions = "drwxrwxrwt".into();
+        }
 
         // Populate /bin with real binaries
         if let Some(bin) = self.root.children.get_mut("bin") {
@@ -536,16 +667,15 @@ impl Vfs {
                 Inode::file("resolv.conf", "nameserver 8.8.8.8\nnameserver 8.8.4.4\n"),
             );
             etc.children.insert("passwd".into(), Inode::file("passwd", "root:x:0:0:root:/root:/bin/bash\nuser:x:1000:1000:User:/home/user:/bin/bash\nnobody:x:65534:65534:Nobody:/:/usr/sbin/nologin\n"));
-            etc.children.insert(
-                "shadow".into(),
-                Inode::file(
-                    "shadow",
-                    "root:!:19000:0:99999:7:::\nuser:!:19000:0:99999:7:::\n",
-                ),
+            let mut shadow = Inode::file(
+                "shadow",
+                "root:!:19000:0:99999:7:::\nuser:!:19000:0:99999:7:::\n",
             );
+            shadow.permissions = "-rw-r-----".into();
+            etc.children.insert("shadow".into(), shadow);
             etc.children.insert(
                 "group".into(),
-                Inode::file("group", "root:x:0:\nuser:x:1000:user\nnogroup:x:65534:\n"),
+                Inode::file("group", "root:x:0:\nsudo:x:27:user\nusers:x:100:user\nuser:x:1000:user\nnogroup:x:65534:\n"),
             );
             etc.children.insert("fstab".into(), Inode::file("fstab", "# /etc/fstab: static file system information.\n/dev/sda1\t/\text4\tdefaults\t0\t1\n"));
             etc.children.insert("motd".into(), Inode::file("motd", "Welcome to kpawnd GNU/Linux!\n\nType 'help' for available commands.\nType 'echo github' to visit the project page.\n"));
@@ -600,6 +730,10 @@ impl Vfs {
                     "Name:\tbash\nPid:\t1\nUid:\t1000\t1000\t1000\t1000\n",
                 ),
             );
+            self_dir.children.insert(
+                "limits".into(),
+                Inode::file("limits", &ResourceLimits::default().proc_text()),
+            );
             proc_dir.children.insert("self".into(), self_dir);
         }
 
@@ -633,7 +767,9 @@ impl Vfs {
         // Populate /var
         if let Some(var) = self.root.children.get_mut("var") {
             var.children.insert("log".into(), Inode::dir("log"));
-            var.children.insert("tmp".into(), Inode::dir("tmp"));
+            let mut var_tmp = Inode::dir("tmp");
+            var_tmp.permissions = "drwxrwxrwt".into();
+            var.children.insert("tmp".into(), var_tmp);
             var.children.insert("run".into(), Inode::dir("run"));
             var.children.insert("cache".into(), Inode::dir("cache"));
 
@@ -674,7 +810,8 @@ impl Vfs {
             boot.children.insert("grub".into(), Inode::dir("grub"));
 
             if let Some(grub) = boot.children.get_mut("grub") {
-                grub.children.insert("grub.cfg".into(), Inode::file("grub.cfg", "# GRUB configuration file\nset default=0\nset timeout=5\n\nmenuentry 'kpawnd GNU/Linux' {\n    linux /boot/vmlinuz-6.1.0-kpawnd root=/dev/sda1 ro quiet\n    initrd /boot/initrd.img-6.1.0-kpawnd\n}\n"));
+                grub.children
+                    .insert("grub.cfg".into(), Inode::file("grub.cfg", DEFAULT_GRUB_CFG));
             }
         }
 
@@ -709,6 +846,43 @@ impl Vfs {
                 Inode::file("README", "Welcome to root's home directory.\n\nBe careful with administrative commands.\nAlways double-check before running destructive operations.\n"),
             );
         }
+        self.number_inodes();
+    }
+
+    /// Give every node without an inode number the next free one. Numbers
+    /// stick to a node through renames and are saved with the tree
+    pub fn number_inodes(&mut self) {
+        if self.next_ino == 0 {
+            fn highest(node: &Inode) -> u64 {
+                node.children.values().map(highest).fold(node.ino, u64::max)
+            }
+            // The root directory is inode 2, as on ext4
+            self.next_ino = (highest(&self.root) + 1).max(2);
+        }
+        Self::assign_inos(&mut self.root, &mut self.next_ino);
+    }
+
+    fn assign_inos(node: &mut Inode, next: &mut u64) {
+        if node.ino == 0 {
+            node.ino = *next;
+            *next += 1;
+        }
+        let mut names: Vec<String> = node.children.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            if let Some(child) = node.children.get_mut(&name) {
+                Self::assign_inos(child, next);
+            }
+        }
+    }
+
+    pub fn fresh_ino(&mut self) -> u64 {
+        if self.next_ino == 0 {
+            self.number_inodes();
+        }
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        ino
     }
     pub fn normalize(&self, path: &str) -> String {
         let raw = if path.starts_with('/') {
@@ -744,7 +918,10 @@ impl Vfs {
     }
     pub fn resolve_mut(&mut self, path: &str) -> Option<&mut Inode> {
         let norm = self.normalize(path);
-        let mut node = &mut self.root;
+        Self::walk_mut(&mut self.root, &norm)
+    }
+    fn walk_mut<'a>(root: &'a mut Inode, norm: &str) -> Option<&'a mut Inode> {
+        let mut node = root;
         for part in norm.split('/').filter(|s| !s.is_empty()) {
             node = node.children.get_mut(part)?;
         }
@@ -775,7 +952,7 @@ impl Vfs {
             None => Err("no such directory"),
         }
     }
-    pub fn open(&mut self, path: &str, write: bool) -> Result<u32, &'static str> {
+    pub fn open(&mut self, path: &str, write: bool, pid: u32) -> Result<u32, &'static str> {
         if let Some(node) = self.resolve(path) {
             if node.is_dir {
                 return Err("is directory");
@@ -788,6 +965,7 @@ impl Vfs {
                     path: self.normalize(path),
                     offset: 0,
                     writable: write,
+                    pid,
                 },
             );
             Ok(h)
@@ -800,7 +978,8 @@ impl Vfs {
             let h = self.handles.get(&handle).ok_or("bad handle")?;
             (h.path.clone(), h.offset)
         };
-        let inode = self.resolve(&path).ok_or("gone")?;
+        let inode = self.resolve_mut(&path).ok_or("gone")?;
+        inode.accessed = clock::now_secs();
         let start = offset;
         let end = (start + size).min(inode.data.len());
         let out = inode.data[start..end].to_string();
@@ -817,8 +996,13 @@ impl Vfs {
         if !writable {
             return Err("not writable");
         }
+        if self.is_read_only(&path) {
+            return Err("read-only file system");
+        }
         let new_len = if let Some(inode) = self.resolve_mut(&path) {
             inode.data.push_str(data);
+            inode.size = inode.data.len();
+            inode.modified = clock::now_secs();
             inode.data.len()
         } else {
             return Err("gone");
@@ -831,6 +1015,20 @@ impl Vfs {
     pub fn close(&mut self, handle: u32) {
         self.handles.remove(&handle);
     }
+    /// Handles opened and not yet closed
+    pub fn open_handles(&self) -> usize {
+        self.handles.len()
+    }
+    /// Open handles with their numbers, lowest first
+    pub fn handles(&self) -> Vec<(u32, &VfsHandle)> {
+        let mut handles: Vec<_> = self.handles.iter().map(|(&h, v)| (h, v)).collect();
+        handles.sort_by_key(|&(h, _)| h);
+        handles
+    }
+    /// Close whatever `pid` left open, as the kernel does when it exits
+    pub fn close_owned(&mut self, pid: u32) {
+        self.handles.retain(|_, h| h.pid != pid);
+    }
 
     /// Check if a file is critical (deleting it should cause a panic)
     pub fn is_critical(&self, path: &str) -> bool {
@@ -840,11 +1038,53 @@ impl Vfs {
         false
     }
 
-    /// Remove a file or directory, returns error if critical
+    /// Critical binaries that are no longer on disk
+    pub fn missing_critical(&self) -> Vec<&'static str> {
+        CRITICAL_FILES
+            .iter()
+            .map(|(path, _)| *path)
+            .filter(|path| self.resolve(path).is_none())
+            .collect()
+    }
+
+    /// Put a critical binary back the way `init` created it, recreating its
+    /// top-level directory if that went too
+    pub fn restore_critical(&mut self, path: &str) -> bool {
+        let Some(&(_, desc)) = CRITICAL_FILES.iter().find(|(p, _)| *p == path) else {
+            return false;
+        };
+        let Some((dir, name)) = path.rsplit_once('/') else {
+            return false;
+        };
+        let dir_name = dir.trim_start_matches('/');
+        self.root
+            .children
+            .entry(dir_name.to_string())
+            .or_insert_with(|| Inode::dir(dir_name));
+        match self.resolve_mut(dir) {
+            Some(parent) if parent.is_dir => {
+                parent
+                    .children
+                    .insert(name.to_string(), Inode::binary(name, desc, true));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove a file or directory. Removing a critical binary still happens
+    /// but panics the kernel and returns an error
     pub fn remove(&mut self, path: &str) -> Result<(), String> {
         let norm = self.normalize(path);
+        if self.is_read_only(&norm) {
+            return Err("read-only file system".into());
+        }
+        if self.mounts.iter().any(|m| m.target == norm) {
+            return Err("device or resource busy".into());
+        }
 
         // Check if it's a critical file
+        let mut panicked = None;
         if self.is_critical(&norm) && !self.ignore_critical_deletes {
             let filename = norm.split('/').next_back().unwrap_or("unknown");
             self.kernel_panic = true;
@@ -865,7 +1105,7 @@ impl Vfs {
                  ---[ end Kernel panic - not syncing: {} ]---",
                 filename, filename
             );
-            return Err(format!(
+            panicked = Some(format!(
                 "KERNEL PANIC: Cannot remove critical system file '{}'",
                 filename
             ));
@@ -900,7 +1140,7 @@ impl Vfs {
                 }
             }
             parent.children.remove(&filename);
-            Ok(())
+            panicked.map_or(Ok(()), Err)
         } else {
             Err("parent directory not found".into())
         }
@@ -939,6 +1179,9 @@ impl Vfs {
     /// Create a new file
     pub fn create_file(&mut self, path: &str, data: &str) -> Result<(), &'static str> {
         let norm = self.normalize(path);
+        if self.is_read_only(&norm) {
+            return Err("read-only file system");
+        }
         let parts: Vec<&str> = norm.split('/').filter(|s| !s.is_empty()).collect();
         if parts.is_empty() {
             return Err("invalid path");
@@ -953,6 +1196,7 @@ impl Vfs {
 
         let owner = self.default_owner.clone();
         let group = self.default_group.clone();
+        let ino = self.fresh_ino();
         if let Some(parent) = self.resolve_mut(&parent_path) {
             if !parent.is_dir {
                 return Err("parent is not a directory");
@@ -960,6 +1204,7 @@ impl Vfs {
             let mut new_file = Inode::file(&filename, data);
             new_file.owner = owner;
             new_file.group = group;
+            new_file.ino = ino;
             parent.children.insert(filename, new_file);
             Ok(())
         } else {
@@ -970,6 +1215,9 @@ impl Vfs {
     /// Create a directory
     pub fn create_dir(&mut self, path: &str) -> Result<(), &'static str> {
         let norm = self.normalize(path);
+        if self.is_read_only(&norm) {
+            return Err("read-only file system");
+        }
         let parts: Vec<&str> = norm.split('/').filter(|s| !s.is_empty()).collect();
         if parts.is_empty() {
             return Err("invalid path");
@@ -984,6 +1232,7 @@ impl Vfs {
 
         let owner = self.default_owner.clone();
         let group = self.default_group.clone();
+        let ino = self.fresh_ino();
         if let Some(parent) = self.resolve_mut(&parent_path) {
             if !parent.is_dir {
                 return Err("parent is not a directory");
@@ -994,6 +1243,7 @@ impl Vfs {
             let mut new_dir = Inode::dir(&dirname);
             new_dir.owner = owner;
             new_dir.group = group;
+            new_dir.ino = ino;
             parent.children.insert(dirname, new_dir);
             Ok(())
         } else {
@@ -1001,20 +1251,146 @@ impl Vfs {
         }
     }
 
+    /// Put `node` at `path` under its new name, replacing a file already
+    /// there. Used by `cp` to drop in a copied tree, and by `rename`
+    pub fn insert_node(&mut self, path: &str, mut node: Inode) -> Result<(), &'static str> {
+        let norm = self.normalize(path);
+        if self.is_read_only(&norm) {
+            return Err("read-only file system");
+        }
+        let Some((parent_path, name)) = norm.rsplit_once('/') else {
+            return Err("invalid path");
+        };
+        if name.is_empty() {
+            return Err("invalid path");
+        }
+        let parent_path = if parent_path.is_empty() {
+            "/"
+        } else {
+            parent_path
+        };
+        // Copies arrive with their numbers cleared; moved trees keep theirs
+        if self.next_ino == 0 {
+            self.number_inodes();
+        }
+        Self::assign_inos(&mut node, &mut self.next_ino);
+        let Some(parent) = self.resolve_mut(parent_path) else {
+            return Err("parent directory not found");
+        };
+        if !parent.is_dir {
+            return Err("parent is not a directory");
+        }
+        if parent.children.get(name).is_some_and(|old| old.is_dir) {
+            return Err("is a directory");
+        }
+        node.name = name.to_string();
+        parent.children.insert(name.to_string(), node);
+        Ok(())
+    }
+
+    /// Move a file or a whole directory tree to `to` by re-parenting it.
+    /// `to` must not exist yet, except as a file a file can replace
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), &'static str> {
+        let from = self.normalize(from);
+        let to = self.normalize(to);
+        if from == "/" || to == "/" {
+            return Err("invalid path");
+        }
+        if from == to {
+            return Ok(());
+        }
+        if to.starts_with(&format!("{}/", from)) {
+            return Err("cannot move a directory into itself");
+        }
+        if self.is_read_only(&from) || self.is_read_only(&to) {
+            return Err("read-only file system");
+        }
+        let Some(node) = self.resolve(&from) else {
+            return Err("no such file or directory");
+        };
+        if Self::contains_critical(node) {
+            return Err("device or resource busy");
+        }
+        match self.resolve(&to) {
+            Some(existing) if existing.is_dir || node.is_dir => return Err("file exists"),
+            _ => {}
+        }
+        let (parent_path, name) = from.rsplit_once('/').unwrap_or(("", &from));
+        let parent_path = if parent_path.is_empty() {
+            "/"
+        } else {
+            parent_path
+        };
+        let name = name.to_string();
+        let node = self
+            .resolve_mut(parent_path)
+            .and_then(|parent| parent.children.remove(&name))
+            .ok_or("no such file or directory")?;
+        if let Err(e) = self.insert_node(&to, node.clone()) {
+            // Put it back where it was
+            if let Some(parent) = self.resolve_mut(parent_path) {
+                parent.children.insert(name, node);
+            }
+            return Err(e);
+        }
+        // Keep the shell inside a directory that moved
+        if self.cwd == from || self.cwd.starts_with(&format!("{}/", from)) {
+            self.cwd = format!("{}{}", to, &self.cwd[from.len()..]);
+        }
+        Ok(())
+    }
+
+    fn contains_critical(node: &Inode) -> bool {
+        node.is_critical || node.children.values().any(Self::contains_critical)
+    }
+
     /// Update file contents
     pub fn write_file(&mut self, path: &str, data: &str) -> Result<(), &'static str> {
+        if self.is_read_only(path) {
+            return Err("read-only file system");
+        }
         if let Some(node) = self.resolve_mut(path) {
             if node.is_dir {
                 return Err("is a directory");
             }
             node.data = data.into();
             node.size = data.len();
+            node.modified = clock::now_secs();
             Ok(())
         } else {
             Err("no such file")
         }
     }
 
+    /// Set the access and/or modification time of `path`, as `touch` does
+    pub fn set_times(
+        &mut self,
+        path: &str,
+        accessed: Option<i64>,
+        modified: Option<i64>,
+    ) -> Result<(), &'static str> {
+        if self.is_read_only(path) {
+            return Err("read-only file system");
+        }
+        let node = self.resolve_mut(path).ok_or("no such file or directory")?;
+        if let Some(t) = accessed {
+            node.accessed = t;
+        }
+        if let Some(t) = modified {
+            node.modified = t;
+        }
+        Ok(())
+    }
+
+    /// Note that `path` was just read. Read-only mounts keep their times
+    pub fn mark_accessed(&mut self, path: &str) {
+        if !self.is_read_only(path) {
+            if let Some(node) = self.resolve_mut(path) {
+                node.accessed = clock::now_secs();
+            }
+        }
+    }
+
     /// List directory contents with details
     pub fn list_detailed(&self, path: &str) -> Result<Vec<String>, &'static str> {
         if let Some(node) = self.resolve(path) {
@@ -1024,14 +1400,15 @@ impl Vfs {
 
             let mut entries: Vec<_> = node.children.iter().collect();
             entries.sort_by(|a, b| a.0.cmp(b.0));
+            let now = clock::now_secs();
 
             let output: Vec<String> = entries
                 .iter()
                 .map(|(name, child)| {
                     let name_display = if child.is_dir {
-                        format!("\x1b[COLOR:blue]{}\x1b[COLOR:reset]", name)
+                        ansi::paint(name, ansi::DIR)
                     } else if child.is_executable {
-                        format!("\x1b[COLOR:green]{}\x1b[COLOR:reset]", name)
+                        ansi::paint(name, ansi::EXEC)
                     } else {
                         name.to_string()
                     };
@@ -1043,7 +1420,7 @@ impl Vfs {
                         child.owner,
                         child.group,
                         child.size,
-                        "Nov 29 12:00",
+                        clock::ls_time(child.modified, now),
                         name_display
                     )
                 })
@@ -1072,10 +1449,89 @@ impl Vfs {
         self.ignore_critical_deletes = val;
     }
 
+    /// Active mounts in the order they were made
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    /// The innermost mount containing `path`, if any
+    pub fn mount_for(&self, path: &str) -> Option<&Mount> {
+        let norm = self.normalize(path);
+        self.mounts
+            .iter()
+            .filter(|m| norm == m.target || norm.starts_with(&format!("{}/", m.target)))
+            .max_by_key(|m| m.target.len())
+    }
+
+    pub fn is_read_only(&self, path: &str) -> bool {
+        self.mount_for(path).map(|m| m.read_only).unwrap_or(false)
+    }
+
+    /// Mount `contents` over the directory at `target`, hiding what was there
+    pub fn mount(
+        &mut self,
+        source: &str,
+        target: &str,
+        fs_type: &str,
+        options: &str,
+        mut contents: Inode,
+    ) -> Result<(), &'static str> {
+        let norm = self.normalize(target);
+        if self.mounts.iter().any(|m| m.target == norm) {
+            return Err("already mounted");
+        }
+        if self.is_read_only(&norm) {
+            return Err("read-only file system");
+        }
+        let node = self
+            .resolve_mut(&norm)
+            .ok_or("mount point does not exist")?;
+        if !node.is_dir {
+            return Err("mount point is not a directory");
+        }
+        contents.name = node.name.clone();
+        contents.is_dir = true;
+        let covered = std::mem::replace(node, contents);
+        self.mounts.push(Mount {
+            source: source.to_string(),
+            target: norm,
+            fs_type: fs_type.to_string(),
+            options: options.to_string(),
+            read_only: options.split(',').any(|o| o == "ro"),
+            covered,
+        });
+        Ok(())
+    }
+
+    /// Detach the mount at `target` (or whose source is `target`)
+    pub fn umount(&mut self, target: &str) -> Result<Mount, &'static str> {
+        let norm = self.normalize(target);
+        let idx = self
+            .mounts
+            .iter()
+            .rposition(|m| m.target == norm || m.source == target)
+            .ok_or("not mounted")?;
+        let mount_target = self.mounts[idx].target.clone();
+        let nested = self
+            .mounts
+            .iter()
+            .any(|m| m.target.starts_with(&format!("{}/", mount_target)));
+        let cwd_inside =
+            self.cwd == mount_target || self.cwd.starts_with(&format!("{}/", mount_target));
+        if nested || cwd_inside {
+            return Err("target is busy");
+        }
+        let mount = self.mounts.remove(idx);
+        if let Some(node) = self.resolve_mut(&mount.target) {
+            *node = mount.covered.clone();
+        }
+        Ok(mount)
+    }
+
     /// Get all user-created files for persistence
-    /// Returns a JSON string of path -> content mapping
+    /// Returns a JSON string of path -> content and timestamps
     pub fn export_user_files(&self) -> String {
-        let mut files: HashMap<String, String> = HashMap::new();
+        let mut files: HashMap<String, SavedFile> = HashMap::new();
 
         // Collect all non-system files from the entire filesystem
         self.collect_user_files_recursive(&self.root, "", &mut files);
@@ -1088,7 +1544,7 @@ impl Vfs {
         &self,
         node: &Inode,
         path: &str,
-        files: &mut HashMap<String, String>,
+        files: &mut HashMap<String, SavedFile>,
     ) {
         for (name, child) in &node.children {
             let child_path = if path.is_empty() {
@@ -1118,7 +1574,15 @@ impl Vfs {
                 // Save user files (non-executable, non-critical)
                 // Skip system config files
                 if !child_path.starts_with("/etc/") || child_path.starts_with("/etc/user/") {
-                    files.insert(child_path, child.data.clone());
+                    files.insert(
+                        child_path,
+                        SavedFile::Stamped {
+                            data: child.data.clone(),
+                            created: child.created,
+                            modified: child.modified,
+                            accessed: child.accessed,
+                        },
+                    );
                 }
             }
         }
@@ -1126,8 +1590,8 @@ impl Vfs {
 
     /// Import user files from JSON string
     pub fn import_user_files(&mut self, json: &str) {
-        if let Ok(files) = serde_json::from_str::<HashMap<String, String>>(json) {
-            for (path, content) in files {
+        if let Ok(files) = serde_json::from_str::<HashMap<String, SavedFile>>(json) {
+            for (path, saved) in files {
                 // Create parent directories if needed
                 if let Some(parent_end) = path.rfind('/') {
                     let parent = &path[..parent_end];
@@ -1136,11 +1600,28 @@ impl Vfs {
                     }
                 }
 
+                let content = match &saved {
+                    SavedFile::Plain(data) => data,
+                    SavedFile::Stamped { data, .. } => data,
+                };
                 // Create or update the file
                 if self.resolve(&path).is_some() {
-                    let _ = self.write_file(&path, &content);
+                    let _ = self.write_file(&path, content);
                 } else {
-                    let _ = self.create_file(&path, &content);
+                    let _ = self.create_file(&path, content);
+                }
+                if let SavedFile::Stamped {
+                    created,
+                    modified,
+                    accessed,
+                    ..
+                } = saved
+                {
+                    if let Some(node) = self.resolve_mut(&path) {
+                        node.created = created;
+                        node.modified = modified;
+                        node.accessed = accessed;
+                    }
                 }
             }
         }
@@ -1160,18 +1641,28 @@ impl Vfs {
         Ok(())
     }
 
-    /// Load filesystem state from IndexedDB persistence
-    pub async fn load_from_persistence(&mut self) {
-        if let Some(root) = Inode::load_from_indexeddb().await {
-            self.root = root;
-        } else {
-            // If no persisted state, initialize fresh filesystem
-            self.init();
+    /// Load the filesystem saved in IndexedDB. `true` when it was a full
+    /// image; after an older save (or none) the user files kept in
+    /// localStorage still need importing on top
+    pub async fn load_from_persistence(&mut self) -> bool {
+        match vfs_persist::load_image().await {
+            Restored::Image(root) => {
+                self.set_root(root);
+                true
+            }
+            Restored::Legacy(root) => {
+                self.set_root(root);
+                false
+            }
+            Restored::Nothing => {
+                self.init();
+                false
+            }
         }
     }
 
-    /// Save filesystem state to IndexedDB persistence
-    pub async fn save_to_persistence(&self) {
-        self.root.save_to_indexeddb().await;
+    /// The on-disk tree as an IndexedDB image, ready to write
+    pub fn save_image(&self) -> Result<(vfs_persist::Manifest, Vec<String>), String> {
+        vfs_persist::encode_image(&self.root_clone(), vfs_persist::CHUNK_BYTES)
     }
 }
@@ -1,24 +1,252 @@
-use crate::persist::{idb_load_vfs, idb_save_vfs};
+//! The whole filesystem as saved in IndexedDB: the root inode serialized to
+//! JSON and cut into chunks, with a manifest saying how to put them back
+//! together. Older saves kept a single JSON tree under `root`. Snapshots
+//! are whole trees too, kept gzipped beside the image.
+use crate::persist::{
+    idb_load_image, idb_load_snapshots, idb_load_state, idb_load_vfs, idb_mark_dirty,
+    idb_save_image, idb_save_snapshots,
+};
 use crate::vfs::Inode;
-use serde_json;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
 
-impl Inode {
-    pub async fn save_to_indexeddb(&self) {
-        if let Ok(json) = serde_json::to_string(self) {
-            let _ = idb_save_vfs(&json).await;
+/// Bumped whenever the saved tree changes shape; `decode_image` upgrades
+/// anything older
+pub const IMAGE_VERSION: u32 = 1;
+/// Largest record written to IndexedDB
+pub const CHUNK_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub chunks: usize,
+    pub bytes: usize,
+}
+
+/// What `load_image` found in IndexedDB
+pub enum Restored {
+    Image(Inode),
+    /// A tree saved before images; user files in localStorage still belong
+    /// on top of it
+    Legacy(Inode),
+    Nothing,
+}
+
+/// Serialize `root` into chunks of at most `chunk_bytes`, split on
+/// character boundaries
+pub fn encode_image(root: &Inode, chunk_bytes: usize) -> Result<(Manifest, Vec<String>), String> {
+    let json = serde_json::to_string(root).map_err(|e| e.to_string())?;
+    let mut chunks = Vec::new();
+    let mut rest = json.as_str();
+    while !rest.is_empty() {
+        let mut end = chunk_bytes.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, tail) = rest.split_at(end.max(1));
+        chunks.push(chunk.to_string());
+        rest = tail;
+    }
+    let manifest = Manifest {
+        version: IMAGE_VERSION,
+        chunks: chunks.len(),
+        bytes: json.len(),
+    };
+    Ok((manifest, chunks))
+}
+
+pub fn decode_image(manifest: &Manifest, chunks: &[String]) -> Result<Inode, String> {
+    if manifest.version > IMAGE_VERSION {
+        return Err(format!(
+            "image version {} is newer than this kernel ({})",
+            manifest.version, IMAGE_VERSION
+        ));
+    }
+    if chunks.len() != manifest.chunks {
+        return Err(format!(
+            "expected {} chunks, found {}",
+            manifest.chunks,
+            chunks.len()
+        ));
+    }
+    let json = chunks.concat();
+    if json.len() != manifest.bytes {
+        return Err(format!(
+            "expected {} bytes, found {}",
+            manifest.bytes,
+            json.len()
+        ));
+    }
+    // Version 1 is the tree as it stands; later versions migrate from here
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+/// A named copy of the whole tree, gzipped and base64'd so it stores as text
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub created: i64,
+    /// Regular files in the tree
+    pub files: usize,
+    /// Bytes of serialized tree before compression
+    pub size: usize,
+    version: u32,
+    data: String,
+}
+
+impl Snapshot {
+    pub fn take(name: &str, root: &Inode, now: i64) -> Result<Self, String> {
+        fn count_files(node: &Inode) -> usize {
+            node.children
+                .values()
+                .map(|c| if c.is_dir { count_files(c) } else { 1 })
+                .sum()
+        }
+        let json = serde_json::to_string(root).map_err(|e| e.to_string())?;
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
+        let packed = gz.finish().map_err(|e| e.to_string())?;
+        Ok(Snapshot {
+            name: name.to_string(),
+            created: now,
+            files: count_files(root),
+            size: json.len(),
+            version: IMAGE_VERSION,
+            data: B64.encode(packed),
+        })
+    }
+
+    /// Bytes the snapshot takes compressed
+    pub fn stored_size(&self) -> usize {
+        self.data.len() / 4 * 3
+    }
+
+    /// The tree as it was when the snapshot was taken
+    pub fn tree(&self) -> Result<Inode, String> {
+        if self.version > IMAGE_VERSION {
+            return Err(format!(
+                "snapshot version {} is newer than this kernel ({})",
+                self.version, IMAGE_VERSION
+            ));
         }
+        let packed = B64.decode(&self.data).map_err(|e| e.to_string())?;
+        let mut json = String::new();
+        GzDecoder::new(packed.as_slice())
+            .read_to_string(&mut json)
+            .map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
     }
+}
 
-    pub async fn load_from_indexeddb() -> Option<Inode> {
-        match idb_load_vfs().await {
-            Ok(jsval) => {
-                if let Some(json) = jsval.as_string() {
-                    serde_json::from_str(&json).ok()
-                } else {
-                    None
-                }
+/// Read the saved filesystem, falling back to the old single-record tree
+pub async fn load_image() -> Restored {
+    if let Ok(saved) = idb_load_image().await {
+        if !saved.is_null() {
+            let parts: Vec<String> = js_sys::Array::from(&saved)
+                .iter()
+                .filter_map(|part| part.as_string())
+                .collect();
+            let image = parts.split_first().and_then(|(manifest, chunks)| {
+                let manifest = serde_json::from_str(manifest).ok()?;
+                decode_image(&manifest, chunks).ok()
+            });
+            if let Some(root) = image {
+                return Restored::Image(root);
             }
-            Err(_) => None,
         }
     }
+    match idb_load_vfs().await {
+        Ok(json) => json
+            .as_string()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .map_or(Restored::Nothing, Restored::Legacy),
+        Err(_) => Restored::Nothing,
+    }
+}
+
+/// Write an image made by `encode_image`, replacing whatever was saved
+pub async fn save_image(manifest: Manifest, chunks: Vec<String>) -> Result<(), String> {
+    let manifest = serde_json::to_string(&manifest).map_err(|e| e.to_string())?;
+    let chunks: js_sys::Array = chunks
+        .iter()
+        .map(|chunk| wasm_bindgen::JsValue::from_str(chunk))
+        .collect();
+    idb_save_image(&manifest, chunks).await.map_err(|e| {
+        e.as_string()
+            .unwrap_or_else(|| "IndexedDB write failed".into())
+    })
+}
+
+/// Flag the saved image as being rewritten; `save_image` clears the flag in
+/// the same transaction that writes the image
+pub async fn mark_dirty() -> Result<(), String> {
+    idb_mark_dirty().await.map_err(|e| {
+        e.as_string()
+            .unwrap_or_else(|| "IndexedDB write failed".into())
+    })
+}
+
+/// Whether the last save was cut off before the image was written. Saves
+/// from before the marker count as clean
+pub async fn was_unclean() -> bool {
+    idb_load_state()
+        .await
+        .ok()
+        .and_then(|state| state.as_string())
+        .is_some_and(|state| state == "dirty")
+}
+
+pub async fn load_snapshots() -> Vec<Snapshot> {
+    idb_load_snapshots()
+        .await
+        .ok()
+        .and_then(|json| json.as_string())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub async fn save_snapshots(snapshots: &str) -> Result<(), String> {
+    idb_save_snapshots(snapshots).await.map_err(|e| {
+        e.as_string()
+            .unwrap_or_else(|| "IndexedDB write failed".into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_round_trips_in_chunks() {
+        let mut root = Inode::dir("/");
+        let mut bin = Inode::dir("bin");
+        bin.children
+            .insert("sh".into(), Inode::binary("sh", "shell", true));
+        root.children.insert("bin".into(), bin);
+        root.children.insert("empty".into(), Inode::dir("empty"));
+        let mut note = Inode::file("note", "héllo wörld");
+        note.permissions = "-rw-------".into();
+        root.children.insert("note".into(), note);
+
+        let (manifest, chunks) = encode_image(&root, 7).unwrap();
+        assert_eq!(manifest.version, IMAGE_VERSION);
+        assert_eq!(manifest.chunks, chunks.len());
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.len() <= 7));
+
+        let back = decode_image(&manifest, &chunks).unwrap();
+        assert!(back.children["empty"].is_dir);
+        assert!(back.children["bin"].children["sh"].is_critical);
+        assert_eq!(back.children["note"].permissions, "-rw-------");
+        assert_eq!(back.children["note"].data, "héllo wörld");
+
+        assert!(decode_image(&manifest, &chunks[1..]).is_err());
+        let newer = Manifest {
+            version: IMAGE_VERSION + 1,
+            ..manifest
+        };
+        assert!(matches!(decode_image(&newer, &chunks), Err(e) if e.contains("newer")));
+    }
 }
@@ -0,0 +1,289 @@
+//! `view <file>`: shows a decoded image on the game canvas. Arrow keys/WASD or
+//! dragging pan, +/- or the wheel zoom, 0 fits, 1 is actual size, ESC exits.
+use crate::graphics::{Color, Graphics, Sprite};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document};
+
+const MIN_ZOOM: f64 = 0.05;
+const MAX_ZOOM: f64 = 32.0;
+const ZOOM_STEP: f64 = 1.25;
+// Screen pixels per frame while an arrow key is held
+const PAN_SPEED: f64 = 12.0;
+const STATUS_H: u32 = 24;
+
+type LoopClosure = std::cell::RefCell<Option<wasm_bindgen::closure::Closure<dyn FnMut(f64)>>>;
+
+thread_local! {
+    static GFX: std::cell::RefCell<Option<Graphics>> = const { std::cell::RefCell::new(None) };
+    static VIEW: std::cell::RefCell<Option<View>> = const { std::cell::RefCell::new(None) };
+    static LOOP: LoopClosure = const { std::cell::RefCell::new(None) };
+    static KEYS: std::cell::RefCell<[bool; 256]> = const { std::cell::RefCell::new([false;256]) };
+    static LISTENING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+fn document() -> Document {
+    window().unwrap().document().unwrap()
+}
+
+struct View {
+    sprite: Sprite,
+    name: String,
+    zoom: f64,
+    // Image coordinate shown at the middle of the screen
+    center: (f64, f64),
+    redraw: bool,
+}
+
+impl View {
+    fn new(sprite: Sprite, name: String, width: u32, height: u32) -> View {
+        let mut view = View {
+            center: (sprite.width as f64 / 2.0, sprite.height as f64 / 2.0),
+            sprite,
+            name,
+            zoom: 1.0,
+            redraw: true,
+        };
+        // Large images start scaled down to fit; small ones at actual size
+        view.fit(width, height);
+        view.zoom = view.zoom.min(1.0);
+        view
+    }
+
+    fn fit(&mut self, width: u32, height: u32) {
+        let avail_h = height.saturating_sub(STATUS_H).max(1) as f64;
+        let zoom =
+            (width as f64 / self.sprite.width as f64).min(avail_h / self.sprite.height as f64);
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center = (
+            self.sprite.width as f64 / 2.0,
+            self.sprite.height as f64 / 2.0,
+        );
+        self.redraw = true;
+    }
+
+    fn zoom_by(&mut self, factor: f64) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.redraw = true;
+    }
+
+    /// Move the image by a screen-space offset, keeping some of it in view
+    fn pan(&mut self, dx: f64, dy: f64) {
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+        self.center.0 = (self.center.0 - dx / self.zoom).clamp(0.0, self.sprite.width as f64);
+        self.center.1 = (self.center.1 - dy / self.zoom).clamp(0.0, self.sprite.height as f64);
+        self.redraw = true;
+    }
+
+    fn render(&mut self, gfx: &mut Graphics) {
+        let (w, h) = (gfx.width(), gfx.height());
+        let dst_w = (self.sprite.width as f64 * self.zoom).max(1.0);
+        let dst_h = (self.sprite.height as f64 * self.zoom).max(1.0);
+        let x = w as f64 / 2.0 - self.center.0 * self.zoom;
+        let y = (h.saturating_sub(STATUS_H)) as f64 / 2.0 - self.center.1 * self.zoom;
+
+        let fb = gfx.frame_buffer();
+        fb.clear(&Color::rgb(24, 24, 28));
+        fb.blit_scaled(
+            &self.sprite,
+            x as i32,
+            y as i32,
+            dst_w as u32,
+            dst_h as u32,
+            false,
+        );
+
+        let bar_y = h.saturating_sub(STATUS_H);
+        fb.fill_rect(0, bar_y, w, STATUS_H, 0, 0, 0);
+        let status = format!(
+            "{}  {}x{}  {:.0}%   ARROWS PAN  +/- ZOOM  0 FIT  1 ACTUAL  ESC EXIT",
+            self.name,
+            self.sprite.width,
+            self.sprite.height,
+            self.zoom * 100.0
+        );
+        fb.draw_text(&status, 8, bar_y + 5, 2, &Color::rgb(200, 200, 200));
+        self.redraw = false;
+    }
+}
+
+fn with_view(f: impl FnOnce(&mut View)) {
+    VIEW.with(|v| {
+        if let Some(ref mut view) = *v.borrow_mut() {
+            f(view);
+        }
+    });
+}
+
+fn screen_size() -> (u32, u32) {
+    GFX.with(|g| {
+        g.borrow()
+            .as_ref()
+            .map_or((1, 1), |g| (g.width(), g.height()))
+    })
+}
+
+// Listeners stay on the window for the page's lifetime and do nothing while
+// no image is open
+fn install_listeners() {
+    if LISTENING.with(|l| l.replace(true)) {
+        return;
+    }
+    let w = window().unwrap();
+    let keydown = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(
+        |e: web_sys::KeyboardEvent| {
+            if VIEW.with(|v| v.borrow().is_none()) {
+                return;
+            }
+            KEYS.with(|k| k.borrow_mut()[(e.key_code() & 0xff) as usize] = true);
+            let (w, h) = screen_size();
+            match e.key().as_str() {
+                "+" | "=" => with_view(|v| v.zoom_by(ZOOM_STEP)),
+                "-" | "_" => with_view(|v| v.zoom_by(1.0 / ZOOM_STEP)),
+                "0" => with_view(|v| v.fit(w, h)),
+                "1" => with_view(|v| {
+                    v.zoom = 1.0;
+                    v.redraw = true;
+                }),
+                _ => return,
+            }
+            e.prevent_default();
+        },
+    ));
+    let keyup = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(
+        |e: web_sys::KeyboardEvent| {
+            KEYS.with(|k| k.borrow_mut()[(e.key_code() & 0xff) as usize] = false);
+        },
+    ));
+    let wheel =
+        wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(|e: web_sys::WheelEvent| {
+            let factor = if e.delta_y() < 0.0 {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            with_view(|v| v.zoom_by(factor));
+        }));
+    let drag =
+        wasm_bindgen::closure::Closure::<dyn FnMut(_)>::wrap(Box::new(|e: web_sys::MouseEvent| {
+            if e.buttons() & 1 != 0 {
+                with_view(|v| v.pan(e.movement_x() as f64, e.movement_y() as f64));
+            }
+        }));
+    w.add_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())
+        .unwrap();
+    w.add_event_listener_with_callback("keyup", keyup.as_ref().unchecked_ref())
+        .unwrap();
+    w.add_event_listener_with_callback("wheel", wheel.as_ref().unchecked_ref())
+        .unwrap();
+    w.add_event_listener_with_callback("mousemove", drag.as_ref().unchecked_ref())
+        .unwrap();
+    keydown.forget();
+    keyup.forget();
+    wheel.forget();
+    drag.forget();
+}
+
+fn held(code: usize) -> bool {
+    KEYS.with(|k| k.borrow()[code])
+}
+
+fn start_loop() {
+    LOOP.with(|l| {
+        if l.borrow().is_some() {
+            return;
+        }
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |_ts: f64| {
+            if held(27) {
+                stop_viewer();
+                return;
+            }
+
+            // Arrows or WASD
+            let axis = |neg: [usize; 2], pos: [usize; 2]| {
+                let on = |keys: [usize; 2]| keys.iter().any(|&k| held(k)) as i32 as f64;
+                (on(neg) - on(pos)) * PAN_SPEED
+            };
+            let dx = axis([37, 65], [39, 68]);
+            let dy = axis([38, 87], [40, 83]);
+            with_view(|v| v.pan(dx, dy));
+
+            VIEW.with(|v| {
+                if let Some(ref mut view) = *v.borrow_mut() {
+                    GFX.with(|gfx| {
+                        if let Some(ref mut g) = *gfx.borrow_mut() {
+                            if view.redraw {
+                                view.render(g);
+                            }
+                            let _ = g.present();
+                        }
+                    });
+                }
+            });
+
+            LOOP.with(|l2| {
+                if let Some(ref cb) = *l2.borrow() {
+                    let _ = window()
+                        .unwrap()
+                        .request_animation_frame(cb.as_ref().unchecked_ref());
+                }
+            });
+        }) as Box<dyn FnMut(f64)>);
+        let _ = window()
+            .unwrap()
+            .request_animation_frame(closure.as_ref().unchecked_ref());
+        *l.borrow_mut() = Some(closure);
+    });
+}
+
+/// Decode `bytes` and show them full screen; returns an error message, or an
+/// empty string once the viewer is up
+#[wasm_bindgen]
+pub fn start_viewer(bytes: Vec<u8>, name: &str) -> String {
+    let sprite = match crate::image::decode(&bytes) {
+        Ok(sprite) => sprite,
+        Err(e) => return e,
+    };
+    let w = window().unwrap();
+    let width = w.inner_width().unwrap().as_f64().unwrap() as u32;
+    let height = w.inner_height().unwrap().as_f64().unwrap() as u32;
+
+    crate::doom::claim_2d_canvas();
+    let gfx = match Graphics::new("game-canvas", width, height) {
+        Ok(g) => g,
+        Err(e) => return e.as_string().unwrap_or_else(|| "canvas unavailable".into()),
+    };
+    let label = name.rsplit('/').next().unwrap_or(name).to_string();
+    VIEW.with(|v| *v.borrow_mut() = Some(View::new(sprite, label, width, height)));
+    GFX.with(|g| *g.borrow_mut() = Some(gfx));
+    KEYS.with(|k| *k.borrow_mut() = [false; 256]);
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:block;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:none;").ok();
+    }
+    install_listeners();
+    crate::idle::set_game_active(true);
+    start_loop();
+    String::new()
+}
+
+#[wasm_bindgen]
+pub fn stop_viewer() {
+    LOOP.with(|l| *l.borrow_mut() = None);
+    VIEW.with(|v| *v.borrow_mut() = None);
+    GFX.with(|g| *g.borrow_mut() = None);
+    KEYS.with(|k| *k.borrow_mut() = [false; 256]);
+
+    if let Some(g) = document().get_element_by_id("graphics") {
+        g.set_attribute("style", "display:none;").ok();
+    }
+    if let Some(t) = document().get_element_by_id("terminal") {
+        t.set_attribute("style", "display:flex;").ok();
+    }
+    crate::idle::set_game_active(false);
+}